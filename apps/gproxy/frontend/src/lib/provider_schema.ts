@@ -29,7 +29,8 @@ export const providerKinds: ProviderKind[] = [
   "antigravity",
   "nvidia",
   "deepseek",
-  "custom"
+  "custom",
+  "plugin"
 ];
 
 export function kindFromConfig(config: unknown): ProviderKind {
@@ -53,9 +54,18 @@ export function channelSettingsFromConfig(config: unknown): Record<string, unkno
 }
 
 export const configFieldMap: Record<ProviderKind, FieldSpec[]> = {
-  openai: [{ key: "base_url", type: "text" }],
-  claude: [{ key: "base_url", type: "text" }],
-  aistudio: [{ key: "base_url", type: "text" }],
+  openai: [
+    { key: "base_url", type: "text" },
+    { key: "path_prefix", type: "text" }
+  ],
+  claude: [
+    { key: "base_url", type: "text" },
+    { key: "path_prefix", type: "text" }
+  ],
+  aistudio: [
+    { key: "base_url", type: "text" },
+    { key: "path_prefix", type: "text" }
+  ],
   vertexexpress: [{ key: "base_url", type: "text" }],
   vertex: [
     { key: "base_url", type: "text" },
@@ -81,7 +91,8 @@ export const configFieldMap: Record<ProviderKind, FieldSpec[]> = {
           labelKey: "providers.prelude_option_claude_agent_sdk"
         }
       ]
-    }
+    },
+    { key: "prelude_template", type: "textarea" }
   ],
   codex: [{ key: "base_url", type: "text" }],
   antigravity: [{ key: "base_url", type: "text" }],
@@ -97,7 +108,8 @@ export const configFieldMap: Record<ProviderKind, FieldSpec[]> = {
     { key: "proto", type: "text", required: true },
     { key: "base_url", type: "text", required: true },
     { key: "count_tokens", type: "text" }
-  ]
+  ],
+  plugin: [{ key: "implementation", type: "text", required: true }]
 };
 
 const configDefaultFieldMap: Partial<Record<ProviderKind, Record<string, string>>> = {
@@ -156,6 +168,7 @@ export const credentialFieldMap: Record<ProviderKind, FieldSpec[]> = {
   nvidia: apiKeyFields,
   deepseek: apiKeyFields,
   custom: apiKeyFields,
+  plugin: apiKeyFields,
   vertex: [
     { key: "project_id", type: "text", required: true },
     { key: "client_email", type: "text", required: true },
@@ -223,7 +236,8 @@ const credentialTagMap: Record<ProviderKind, string> = {
   antigravity: "Antigravity",
   nvidia: "Nvidia",
   deepseek: "DeepSeek",
-  custom: "Custom"
+  custom: "Custom",
+  plugin: "Plugin"
 };
 
 export function buildProviderConfig(kind: ProviderKind, fields: Record<string, string>): Record<string, unknown> {
@@ -151,7 +151,8 @@ export type ProviderKind =
   | "antigravity"
   | "nvidia"
   | "deepseek"
-  | "custom";
+  | "custom"
+  | "plugin";
 
 export type OAuthStartResponse = {
   mode?: string;
@@ -55,10 +55,33 @@ type CustomConfigDraft = {
   baseUrl: string;
   countTokens: CountTokensMode;
   jsonParamMaskText: string;
+  authHeader: string;
+  authTemplate: string;
+  pathsText: string;
+  allowedModelsText: string;
+  errorRulesText: string;
   dispatchRows: DispatchRowDraft[];
   useModelTable: boolean;
   models: CustomModelDraft[];
+  // Config keys this form does not edit (timeouts, egress, request_signing, ...), kept on save.
+  extraConfig: Record<string, unknown>;
+  extraSettings: Record<string, unknown>;
 };
+const CUSTOM_EDITED_CONFIG_KEYS = new Set(["kind", "channel_settings"]);
+const CUSTOM_EDITED_SETTING_KEYS = new Set([
+  "id",
+  "enabled",
+  "proto",
+  "base_url",
+  "dispatch",
+  "count_tokens",
+  "json_param_mask",
+  "model_table",
+  "auth",
+  "paths",
+  "allowed_models",
+  "error_rules"
+]);
 type OAuthUiDefaults = {
   redirectUri?: string;
   scope?: string;
@@ -514,6 +537,35 @@ function parseJsonParamMaskText(value: unknown): string {
     .join("\n");
 }
 
+function parsePathsText(value: unknown): string {
+  if (!value || typeof value !== "object") {
+    return "";
+  }
+  return Object.entries(value as Record<string, unknown>)
+    .map(([op, path]) => `${op} = ${String(path ?? "")}`)
+    .join("\n");
+}
+
+function parsePathsMap(text: string): Record<string, string> {
+  const out: Record<string, string> = {};
+  for (const line of text.split(/\r?\n/)) {
+    const index = line.indexOf("=");
+    if (index <= 0) {
+      continue;
+    }
+    const op = line.slice(0, index).trim();
+    const path = line.slice(index + 1).trim();
+    if (op && path) {
+      out[op] = path;
+    }
+  }
+  return out;
+}
+
+function omitKeys(source: Record<string, unknown>, keys: Set<string>): Record<string, unknown> {
+  return Object.fromEntries(Object.entries(source).filter(([key]) => !keys.has(key)));
+}
+
 function parseJsonParamMaskList(text: string): string[] {
   const values = text
     .split(/\r?\n/)
@@ -628,6 +680,13 @@ function extractCustomDraft(configJson: unknown, providerEnabled: boolean): Cust
           .filter((item): item is CustomModelDraft => Boolean(item && item.id))
       : [];
 
+  const auth =
+    settings.auth && typeof settings.auth === "object"
+      ? (settings.auth as Record<string, unknown>)
+      : {};
+  const config =
+    configJson && typeof configJson === "object" ? (configJson as Record<string, unknown>) : {};
+
   return {
     id: String(settings.id ?? ""),
     enabled: typeof settings.enabled === "boolean" ? settings.enabled : providerEnabled,
@@ -635,9 +694,18 @@ function extractCustomDraft(configJson: unknown, providerEnabled: boolean): Cust
     baseUrl: String(settings.base_url ?? ""),
     countTokens: parseCountTokensMode(settings.count_tokens),
     jsonParamMaskText: parseJsonParamMaskText(settings.json_param_mask),
+    authHeader: String(auth.header ?? ""),
+    authTemplate: String(auth.template ?? ""),
+    pathsText: parsePathsText(settings.paths),
+    allowedModelsText: parseJsonParamMaskText(settings.allowed_models),
+    errorRulesText: Array.isArray(settings.error_rules)
+      ? JSON.stringify(settings.error_rules, null, 2)
+      : "",
     dispatchRows: parseDispatchRows(settings.dispatch, proto),
     useModelTable: modelTableRaw !== undefined,
-    models: modelRows
+    models: modelRows,
+    extraConfig: omitKeys(config, CUSTOM_EDITED_CONFIG_KEYS),
+    extraSettings: omitKeys(settings, CUSTOM_EDITED_SETTING_KEYS)
   };
 }
 
@@ -664,6 +732,7 @@ function buildCustomConfigJson(draft: CustomConfigDraft): Record<string, unknown
     }))
     .filter((row) => row.id);
   const channelSettings: Record<string, unknown> = {
+    ...draft.extraSettings,
     id: draft.id.trim(),
     enabled: draft.enabled,
     proto: draft.proto,
@@ -677,7 +746,29 @@ function buildCustomConfigJson(draft: CustomConfigDraft): Record<string, unknown
   if (draft.useModelTable) {
     channelSettings.model_table = { models };
   }
+  if (draft.authHeader.trim()) {
+    channelSettings.auth = {
+      header: draft.authHeader.trim(),
+      template: draft.authTemplate.trim() || "{api_key}"
+    };
+  }
+  const paths = parsePathsMap(draft.pathsText);
+  if (Object.keys(paths).length > 0) {
+    channelSettings.paths = paths;
+  }
+  const allowedModels = parseJsonParamMaskList(draft.allowedModelsText);
+  if (allowedModels.length > 0) {
+    channelSettings.allowed_models = allowedModels;
+  }
+  if (draft.errorRulesText.trim()) {
+    const rules: unknown = JSON.parse(draft.errorRulesText);
+    if (!Array.isArray(rules)) {
+      throw new Error("error_rules must be a JSON array");
+    }
+    channelSettings.error_rules = rules;
+  }
   return {
+    ...draft.extraConfig,
     kind: "custom",
     channel_settings: channelSettings
   };
@@ -1383,9 +1474,16 @@ export function ProvidersSection({ adminKey, notify }: Props) {
         baseUrl,
         countTokens: customCountTokens,
         jsonParamMaskText: customJsonParamMaskText,
+        authHeader: "",
+        authTemplate: "",
+        pathsText: "",
+        allowedModelsText: "",
+        errorRulesText: "",
         dispatchRows: customDispatchRows,
         useModelTable: customUseModelTable,
-        models: customModels
+        models: customModels,
+        extraConfig: {},
+        extraSettings: {}
       };
       const configJson = buildCustomConfigJson(draft);
 
@@ -1673,6 +1771,71 @@ export function ProvidersSection({ adminKey, notify }: Props) {
               </div>
               <div className="mt-1 text-xs text-slate-500">{t("providers.json_param_mask_hint")}</div>
             </div>
+            <div>
+              <FieldLabel>{t("providers.custom_auth_header")}</FieldLabel>
+              <div className="mt-2">
+                <TextInput
+                  value={customDraft.authHeader}
+                  onChange={(next) =>
+                    updateSelectedCustomDraft((draft) => ({ ...draft, authHeader: next }))
+                  }
+                  placeholder={t("providers.custom_auth_header_placeholder")}
+                />
+              </div>
+            </div>
+            <div>
+              <FieldLabel>{t("providers.custom_auth_template")}</FieldLabel>
+              <div className="mt-2">
+                <TextInput
+                  value={customDraft.authTemplate}
+                  onChange={(next) =>
+                    updateSelectedCustomDraft((draft) => ({ ...draft, authTemplate: next }))
+                  }
+                  placeholder="{api_key}"
+                />
+              </div>
+            </div>
+            <div className="md:col-span-2">
+              <FieldLabel>{t("providers.custom_paths")}</FieldLabel>
+              <div className="mt-2">
+                <TextArea
+                  value={customDraft.pathsText}
+                  onChange={(next) =>
+                    updateSelectedCustomDraft((draft) => ({ ...draft, pathsText: next }))
+                  }
+                  rows={3}
+                  placeholder={t("providers.custom_paths_placeholder")}
+                />
+              </div>
+              <div className="mt-1 text-xs text-slate-500">{t("providers.custom_paths_hint")}</div>
+            </div>
+            <div className="md:col-span-2">
+              <FieldLabel>{t("providers.custom_allowed_models")}</FieldLabel>
+              <div className="mt-2">
+                <TextArea
+                  value={customDraft.allowedModelsText}
+                  onChange={(next) =>
+                    updateSelectedCustomDraft((draft) => ({ ...draft, allowedModelsText: next }))
+                  }
+                  rows={3}
+                  placeholder={t("providers.custom_allowed_models_placeholder")}
+                />
+              </div>
+            </div>
+            <div className="md:col-span-2">
+              <FieldLabel>{t("providers.custom_error_rules")}</FieldLabel>
+              <div className="mt-2">
+                <TextArea
+                  value={customDraft.errorRulesText}
+                  onChange={(next) =>
+                    updateSelectedCustomDraft((draft) => ({ ...draft, errorRulesText: next }))
+                  }
+                  rows={4}
+                  placeholder={t("providers.custom_error_rules_placeholder")}
+                />
+              </div>
+              <div className="mt-1 text-xs text-slate-500">{t("providers.custom_error_rules_hint")}</div>
+            </div>
           </div>
 
           {renderDispatchEditor(
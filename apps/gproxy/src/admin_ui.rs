@@ -1,22 +1,154 @@
-use axum::extract::Path;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Component, Path as FsPath, PathBuf};
+use std::sync::Arc;
+
+use axum::body::Bytes;
+use axum::extract::{Path, State};
 use axum::http::{HeaderValue, StatusCode, header};
 use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use gproxy_common::{log_info, log_warn};
 use rust_embed::RustEmbed;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
 
 #[derive(RustEmbed)]
 #[folder = "frontend/dist"]
 struct AdminUi;
 
-pub async fn index() -> Response {
-    render("index.html")
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// `manifest.json` of a UI bundle in the data dir; every served file must be listed with
+/// its SHA-256.
+#[derive(Debug, Deserialize)]
+struct UiManifest {
+    version: String,
+    files: BTreeMap<String, String>,
+}
+
+/// The admin UI being served: the bundle embedded at build time, or a verified bundle
+/// loaded from disk at startup.
+pub enum UiAssets {
+    Embedded,
+    Dir {
+        version: String,
+        files: HashMap<String, Bytes>,
+    },
+}
+
+impl UiAssets {
+    /// Loads the bundle in `dir` when present and valid; otherwise keeps the embedded UI.
+    pub fn load(dir: Option<PathBuf>, pinned_version: Option<&str>) -> Self {
+        let Some(dir) = dir else {
+            return UiAssets::Embedded;
+        };
+        if !dir.join(MANIFEST_FILE).is_file() {
+            return UiAssets::Embedded;
+        }
+        match load_bundle(&dir, pinned_version) {
+            Ok((version, files)) => {
+                log_info!("admin_ui", "serving {version} from {}", dir.display());
+                UiAssets::Dir { version, files }
+            }
+            Err(err) => {
+                log_warn!(
+                    "admin_ui",
+                    "ignoring bundle in {}: {err}; serving the embedded ui",
+                    dir.display()
+                );
+                UiAssets::Embedded
+            }
+        }
+    }
+
+    fn get(&self, path: &str) -> Option<Bytes> {
+        match self {
+            UiAssets::Embedded => AdminUi::get(path).map(|file| match file.data {
+                Cow::Borrowed(data) => Bytes::from_static(data),
+                Cow::Owned(data) => Bytes::from(data),
+            }),
+            UiAssets::Dir { files, .. } => files.get(path).cloned(),
+        }
+    }
+
+    /// Served unauthenticated, so it names the source but not where the bundle lives.
+    fn version_json(&self) -> serde_json::Value {
+        match self {
+            UiAssets::Embedded => serde_json::json!({
+                "source": "embedded",
+                "version": env!("CARGO_PKG_VERSION"),
+            }),
+            UiAssets::Dir { version, .. } => serde_json::json!({
+                "source": "data_dir",
+                "version": version,
+            }),
+        }
+    }
+}
+
+fn load_bundle(
+    dir: &FsPath,
+    pinned_version: Option<&str>,
+) -> Result<(String, HashMap<String, Bytes>), String> {
+    let raw = std::fs::read(dir.join(MANIFEST_FILE)).map_err(|err| err.to_string())?;
+    let manifest: UiManifest =
+        serde_json::from_slice(&raw).map_err(|err| format!("invalid manifest: {err}"))?;
+    if let Some(pinned) = pinned_version
+        && manifest.version != pinned
+    {
+        return Err(format!(
+            "version {} does not match the pinned version {pinned}",
+            manifest.version
+        ));
+    }
+    if !manifest.files.contains_key("index.html") {
+        return Err("manifest does not list index.html".to_string());
+    }
+
+    let mut files = HashMap::with_capacity(manifest.files.len());
+    for (name, expected) in manifest.files {
+        let relative = FsPath::new(&name);
+        if !relative
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)))
+        {
+            return Err(format!("invalid file path {name}"));
+        }
+        let data = std::fs::read(dir.join(relative)).map_err(|err| format!("{name}: {err}"))?;
+        let actual = format!("{:x}", Sha256::digest(&data));
+        if !actual.eq_ignore_ascii_case(expected.trim()) {
+            return Err(format!("{name}: sha256 mismatch"));
+        }
+        files.insert(name, Bytes::from(data));
+    }
+    Ok((manifest.version, files))
+}
+
+/// `/`, `/assets/...` and `GET /ui/version`.
+pub fn router(assets: UiAssets) -> Router {
+    Router::new()
+        .route("/", get(index))
+        .route("/assets/{*path}", get(asset))
+        .route("/ui/version", get(version))
+        .with_state(Arc::new(assets))
+}
+
+async fn index(State(assets): State<Arc<UiAssets>>) -> Response {
+    render(&assets, "index.html")
 }
 
-pub async fn asset(Path(path): Path<String>) -> Response {
-    render(&format!("assets/{path}"))
+async fn asset(State(assets): State<Arc<UiAssets>>, Path(path): Path<String>) -> Response {
+    render(&assets, &format!("assets/{path}"))
 }
 
-fn render(path: &str) -> Response {
-    let Some(content) = AdminUi::get(path) else {
+async fn version(State(assets): State<Arc<UiAssets>>) -> Response {
+    Json(assets.version_json()).into_response()
+}
+
+fn render(assets: &UiAssets, path: &str) -> Response {
+    let Some(content) = assets.get(path) else {
         return (StatusCode::NOT_FOUND, "not found").into_response();
     };
 
@@ -24,7 +156,7 @@ fn render(path: &str) -> Response {
         .first_raw()
         .unwrap_or("application/octet-stream");
 
-    let mut response = Response::new(axum::body::Body::from(content.data));
+    let mut response = Response::new(axum::body::Body::from(content));
     response.headers_mut().insert(
         header::CONTENT_TYPE,
         HeaderValue::from_str(mime)
@@ -32,3 +164,108 @@ fn render(path: &str) -> Response {
     );
     response
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::SystemTime;
+
+    use super::*;
+
+    const INDEX: &[u8] = b"<html></html>";
+    const APP: &[u8] = b"console.log(1)";
+
+    fn sha(data: &[u8]) -> String {
+        format!("{:x}", Sha256::digest(data))
+    }
+
+    static NEXT_BUNDLE: AtomicUsize = AtomicUsize::new(0);
+
+    /// A bundle dir holding `index.html` and `assets/app.js`, with `manifest` written as is.
+    fn bundle(manifest: serde_json::Value) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "gproxy-ui-{}-{}-{}",
+            std::process::id(),
+            NEXT_BUNDLE.fetch_add(1, Ordering::Relaxed),
+            SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        std::fs::create_dir_all(dir.join("assets")).unwrap();
+        std::fs::write(dir.join("index.html"), INDEX).unwrap();
+        std::fs::write(dir.join("assets/app.js"), APP).unwrap();
+        std::fs::write(dir.join(MANIFEST_FILE), manifest.to_string()).unwrap();
+        dir
+    }
+
+    fn manifest(version: &str, files: &[(&str, String)]) -> serde_json::Value {
+        let files: BTreeMap<_, _> = files.iter().cloned().collect();
+        serde_json::json!({ "version": version, "files": files })
+    }
+
+    #[test]
+    fn listed_files_are_loaded_and_the_version_is_pinned() {
+        let dir = bundle(manifest(
+            "1.2.0",
+            &[("index.html", sha(INDEX)), ("assets/app.js", sha(APP))],
+        ));
+
+        let (version, files) = load_bundle(&dir, None).unwrap();
+        assert_eq!(version, "1.2.0");
+        assert_eq!(files.len(), 2);
+        assert_eq!(files["assets/app.js"].as_ref(), APP);
+        assert!(load_bundle(&dir, Some("1.2.0")).is_ok());
+        assert!(
+            load_bundle(&dir, Some("1.3.0"))
+                .unwrap_err()
+                .contains("pinned version")
+        );
+
+        let assets = UiAssets::load(Some(dir.clone()), None);
+        assert_eq!(
+            assets.version_json(),
+            serde_json::json!({ "source": "data_dir", "version": "1.2.0" })
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn bad_bundles_are_refused() {
+        let cases = [
+            (serde_json::json!({ "version": "1" }), "invalid manifest"),
+            (
+                manifest("1", &[("assets/app.js", sha(APP))]),
+                "does not list index.html",
+            ),
+            (
+                manifest("1", &[("index.html", sha(INDEX)), ("../secret", sha(APP))]),
+                "invalid file path",
+            ),
+            (
+                manifest(
+                    "1",
+                    &[("index.html", sha(INDEX)), ("/etc/passwd", sha(APP))],
+                ),
+                "invalid file path",
+            ),
+            (
+                manifest(
+                    "1",
+                    &[("index.html", sha(INDEX)), ("assets/app.js", sha(INDEX))],
+                ),
+                "sha256 mismatch",
+            ),
+        ];
+        for (manifest, expected) in cases {
+            let dir = bundle(manifest);
+            let err = load_bundle(&dir, None).unwrap_err();
+            assert!(err.contains(expected), "{err}");
+            assert!(matches!(
+                UiAssets::load(Some(dir.clone()), None),
+                UiAssets::Embedded
+            ));
+            std::fs::remove_dir_all(dir).unwrap();
+        }
+    }
+}
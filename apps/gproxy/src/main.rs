@@ -1,43 +1,59 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+use axum::Router;
 use axum::http::StatusCode;
 use axum::routing::get;
+use gproxy_common::log_info;
+use gproxy_core::bootstrap;
 
 mod admin_ui;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let boot = gproxy_core::bootstrap::bootstrap_from_env().await?;
-    let global = boot.state.global.load();
-    let state_for_proxy = boot.state.clone();
+    let args = bootstrap::cli_args_from_env();
+    gproxy_common::log::init(bootstrap::log_config_from_args(&args)?).context("open log file")?;
+    let ui = (!args.no_ui).then(|| {
+        admin_ui::UiAssets::load(
+            bootstrap::ui_dir_from_args(&args),
+            bootstrap::ui_version_from_args(&args).as_deref(),
+        )
+    });
+    let gproxy = gproxy_router::GproxyBuilder::from_args(args)?
+        .build()
+        .await?;
+    let global = gproxy.state.global.load();
 
-    let upstream_cfg = gproxy_core::upstream_client::UpstreamClientConfig::from_global(&global);
-    let upstream_client: std::sync::Arc<dyn gproxy_core::upstream_client::UpstreamClient> =
-        std::sync::Arc::new(
-            gproxy_core::upstream_client::WreqUpstreamClient::new_with_proxy_resolver(
-                upstream_cfg,
-                move || state_for_proxy.global.load().proxy.clone(),
-            )?,
-        );
-    let engine = std::sync::Arc::new(gproxy_core::proxy_engine::ProxyEngine::new(
-        boot.state.clone(),
-        boot.registry.clone(),
-        upstream_client,
-        boot.storage.clone(),
-    ));
+    let favicon = get(|| async { StatusCode::NO_CONTENT });
+    let proxy_bind = format!("{}:{}", global.host, global.port);
+    let Some((admin_host, admin_port)) = global.admin_bind() else {
+        let mut app = gproxy.router().route("/favicon.ico", favicon);
+        if let Some(ui) = ui {
+            app = app.merge(admin_ui::router(ui));
+        }
+        return serve(&proxy_bind, app).await;
+    };
 
-    let app = axum::Router::new()
-        .merge(gproxy_router::proxy_router(engine))
-        .nest(
-            "/admin",
-            gproxy_router::admin_router(boot.state.clone(), boot.storage.clone()),
-        )
-        .route("/favicon.ico", get(|| async { StatusCode::NO_CONTENT }))
-        .route("/", get(admin_ui::index))
-        .route("/assets/{*path}", get(admin_ui::asset));
+    // The admin API and UI only exist on their own listener; the proxy port serves no `/admin`.
+    let proxy_app = gproxy.proxy_router().route("/favicon.ico", favicon.clone());
+    let mut admin_app = Router::new()
+        .nest("/admin", gproxy.admin_router())
+        .route("/favicon.ico", favicon);
+    if let Some(ui) = ui {
+        admin_app = admin_app.merge(admin_ui::router(ui));
+    }
+    let admin_bind = format!("{admin_host}:{admin_port}");
+    tokio::try_join!(serve(&proxy_bind, proxy_app), serve(&admin_bind, admin_app))?;
+    Ok(())
+}
 
-    let bind = format!("{}:{}", global.host, global.port);
-    let listener = tokio::net::TcpListener::bind(&bind).await?;
-    println!("listening on {bind}");
-    axum::serve(listener, app).await?;
+async fn serve(bind: &str, app: Router) -> Result<()> {
+    let listener = tokio::net::TcpListener::bind(bind)
+        .await
+        .with_context(|| format!("bind {bind}"))?;
+    log_info!("server", "listening on {bind}");
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+    )
+    .await?;
     Ok(())
 }
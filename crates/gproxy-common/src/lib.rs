@@ -1,5 +1,10 @@
+pub mod log;
+
 use serde::{Deserialize, Serialize};
 
+/// Event-log body cap used when none is configured (50 MiB).
+pub const DEFAULT_EVENT_LOG_MAX_BODY_BYTES: u64 = 50 * 1024 * 1024;
+
 #[derive(Debug, thiserror::Error)]
 pub enum GlobalConfigError {
     #[error("missing required global config field: {0}")]
@@ -13,6 +18,10 @@ pub enum GlobalConfigError {
 pub struct GlobalConfig {
     pub host: String,
     pub port: u16,
+    /// Bind host of the admin listener; defaults to `127.0.0.1` when `admin_port` is set.
+    pub admin_host: Option<String>,
+    /// Serve the admin API and UI on this port only, away from the proxy listener.
+    pub admin_port: Option<u16>,
     pub admin_key: String,
     /// Optional outbound proxy (for upstream egress).
     pub proxy: Option<String>,
@@ -20,6 +29,63 @@ pub struct GlobalConfig {
     pub dsn: String,
     /// Whether to redact sensitive fields in emitted events.
     pub event_redact_sensitive: bool,
+    /// Render engine errors in the caller's protocol error shape.
+    pub native_error_format: bool,
+    /// Retry a stream once when it breaks before any output was forwarded.
+    pub stream_retry_on_interrupt: bool,
+    /// Fail a stream over to another credential when no content arrives within this many ms (0 disables).
+    pub stream_first_token_timeout_ms: u64,
+    /// Share one upstream call between identical concurrent non-stream requests from the same key.
+    pub coalesce_inflight_requests: bool,
+    /// Gzip non-stream JSON responses at least this large when the client accepts it (0 disables).
+    pub response_compress_min_bytes: u64,
+    /// Auto-disable a user key used from more than this many client IPs within one minute (0 disables).
+    pub key_abuse_max_ips: u64,
+    /// Only apply the distinct-IP rule once the key made at least this many requests in that minute.
+    pub key_abuse_min_rpm: u64,
+    /// Comma-separated ISO country codes; a user key used from one of them is auto-disabled.
+    pub key_abuse_blocked_countries: Option<String>,
+    /// Receives a JSON POST whenever a user key is auto-disabled.
+    pub alert_webhook_url: Option<String>,
+    /// Path to a MaxMind country database (.mmdb) used to tag downstream requests with the client country.
+    pub geoip_country_db: Option<String>,
+    /// Path to a MaxMind ASN database (.mmdb) used to tag downstream requests with the client ASN.
+    pub geoip_asn_db: Option<String>,
+    /// Abort a forwarded stream when the upstream sends nothing for this long (0 disables the watchdog).
+    pub stream_idle_timeout_ms: u64,
+    /// Upstream address family: auto, ipv4, ipv6 (preferred first) or ipv4_only / ipv6_only.
+    pub egress_ip_family: Option<String>,
+    /// Source IP address bound for upstream connections.
+    pub egress_local_address: Option<String>,
+    /// Network interface bound for upstream connections.
+    pub egress_interface: Option<String>,
+    /// Record the tool calls of generate responses to the `tool_calls` table.
+    pub tool_call_audit: bool,
+    /// Largest non-stream upstream response body accepted, in bytes (0 disables the limit).
+    pub max_response_body_bytes: u64,
+    /// Cut oversize raw passthrough responses to the limit instead of rejecting them.
+    pub truncate_oversize_responses: bool,
+    /// Bytes of each request/response body kept in event logs.
+    pub event_log_max_body_bytes: u64,
+    /// Index logged bodies and error messages for full-text search.
+    pub log_search: bool,
+    /// Comma-separated addresses or CIDRs of reverse proxies whose forwarding headers are believed.
+    pub trusted_proxies: Option<String>,
+    /// Number downstream SSE events and keep the recent ones so a dropped client can resume with `Last-Event-ID`.
+    pub sse_resume: bool,
+}
+
+impl GlobalConfig {
+    /// Address of the dedicated admin listener, when one is configured and differs from
+    /// the proxy listener.
+    pub fn admin_bind(&self) -> Option<(String, u16)> {
+        let port = self.admin_port?;
+        let host = self
+            .admin_host
+            .clone()
+            .unwrap_or_else(|| "127.0.0.1".to_string());
+        (host != self.host || port != self.port).then_some((host, port))
+    }
 }
 
 /// Optional layer used for merging global config.
@@ -27,10 +93,34 @@ pub struct GlobalConfig {
 pub struct GlobalConfigPatch {
     pub host: Option<String>,
     pub port: Option<u16>,
+    pub admin_host: Option<String>,
+    pub admin_port: Option<u16>,
     pub admin_key: Option<String>,
     pub proxy: Option<String>,
     pub dsn: Option<String>,
     pub event_redact_sensitive: Option<bool>,
+    pub native_error_format: Option<bool>,
+    pub stream_retry_on_interrupt: Option<bool>,
+    pub stream_first_token_timeout_ms: Option<u64>,
+    pub coalesce_inflight_requests: Option<bool>,
+    pub response_compress_min_bytes: Option<u64>,
+    pub key_abuse_max_ips: Option<u64>,
+    pub key_abuse_min_rpm: Option<u64>,
+    pub key_abuse_blocked_countries: Option<String>,
+    pub alert_webhook_url: Option<String>,
+    pub geoip_country_db: Option<String>,
+    pub geoip_asn_db: Option<String>,
+    pub stream_idle_timeout_ms: Option<u64>,
+    pub egress_ip_family: Option<String>,
+    pub egress_local_address: Option<String>,
+    pub egress_interface: Option<String>,
+    pub tool_call_audit: Option<bool>,
+    pub max_response_body_bytes: Option<u64>,
+    pub truncate_oversize_responses: Option<bool>,
+    pub event_log_max_body_bytes: Option<u64>,
+    pub log_search: Option<bool>,
+    pub trusted_proxies: Option<String>,
+    pub sse_resume: Option<bool>,
 }
 
 impl GlobalConfigPatch {
@@ -41,6 +131,12 @@ impl GlobalConfigPatch {
         if other.port.is_some() {
             self.port = other.port;
         }
+        if other.admin_host.is_some() {
+            self.admin_host = other.admin_host;
+        }
+        if other.admin_port.is_some() {
+            self.admin_port = other.admin_port;
+        }
         if other.admin_key.is_some() {
             self.admin_key = other.admin_key;
         }
@@ -53,18 +149,110 @@ impl GlobalConfigPatch {
         if other.event_redact_sensitive.is_some() {
             self.event_redact_sensitive = other.event_redact_sensitive;
         }
+        if other.native_error_format.is_some() {
+            self.native_error_format = other.native_error_format;
+        }
+        if other.stream_retry_on_interrupt.is_some() {
+            self.stream_retry_on_interrupt = other.stream_retry_on_interrupt;
+        }
+        if other.stream_first_token_timeout_ms.is_some() {
+            self.stream_first_token_timeout_ms = other.stream_first_token_timeout_ms;
+        }
+        if other.coalesce_inflight_requests.is_some() {
+            self.coalesce_inflight_requests = other.coalesce_inflight_requests;
+        }
+        if other.response_compress_min_bytes.is_some() {
+            self.response_compress_min_bytes = other.response_compress_min_bytes;
+        }
+        if other.key_abuse_max_ips.is_some() {
+            self.key_abuse_max_ips = other.key_abuse_max_ips;
+        }
+        if other.key_abuse_min_rpm.is_some() {
+            self.key_abuse_min_rpm = other.key_abuse_min_rpm;
+        }
+        if other.key_abuse_blocked_countries.is_some() {
+            self.key_abuse_blocked_countries = other.key_abuse_blocked_countries;
+        }
+        if other.alert_webhook_url.is_some() {
+            self.alert_webhook_url = other.alert_webhook_url;
+        }
+        if other.geoip_country_db.is_some() {
+            self.geoip_country_db = other.geoip_country_db;
+        }
+        if other.geoip_asn_db.is_some() {
+            self.geoip_asn_db = other.geoip_asn_db;
+        }
+        if other.stream_idle_timeout_ms.is_some() {
+            self.stream_idle_timeout_ms = other.stream_idle_timeout_ms;
+        }
+        if other.egress_ip_family.is_some() {
+            self.egress_ip_family = other.egress_ip_family;
+        }
+        if other.egress_local_address.is_some() {
+            self.egress_local_address = other.egress_local_address;
+        }
+        if other.egress_interface.is_some() {
+            self.egress_interface = other.egress_interface;
+        }
+        if other.tool_call_audit.is_some() {
+            self.tool_call_audit = other.tool_call_audit;
+        }
+        if other.max_response_body_bytes.is_some() {
+            self.max_response_body_bytes = other.max_response_body_bytes;
+        }
+        if other.truncate_oversize_responses.is_some() {
+            self.truncate_oversize_responses = other.truncate_oversize_responses;
+        }
+        if other.event_log_max_body_bytes.is_some() {
+            self.event_log_max_body_bytes = other.event_log_max_body_bytes;
+        }
+        if other.log_search.is_some() {
+            self.log_search = other.log_search;
+        }
+        if other.trusted_proxies.is_some() {
+            self.trusted_proxies = other.trusted_proxies;
+        }
+        if other.sse_resume.is_some() {
+            self.sse_resume = other.sse_resume;
+        }
     }
 
     pub fn into_config(self) -> Result<GlobalConfig, GlobalConfigError> {
         Ok(GlobalConfig {
             host: self.host.unwrap_or_else(|| "0.0.0.0".to_string()),
             port: self.port.unwrap_or(8787),
+            admin_host: self.admin_host,
+            admin_port: self.admin_port,
             admin_key: self
                 .admin_key
                 .ok_or(GlobalConfigError::MissingField("admin_key"))?,
             proxy: self.proxy,
             dsn: self.dsn.ok_or(GlobalConfigError::MissingField("dsn"))?,
             event_redact_sensitive: self.event_redact_sensitive.unwrap_or(true),
+            native_error_format: self.native_error_format.unwrap_or(false),
+            stream_retry_on_interrupt: self.stream_retry_on_interrupt.unwrap_or(false),
+            stream_first_token_timeout_ms: self.stream_first_token_timeout_ms.unwrap_or(0),
+            coalesce_inflight_requests: self.coalesce_inflight_requests.unwrap_or(false),
+            response_compress_min_bytes: self.response_compress_min_bytes.unwrap_or(0),
+            key_abuse_max_ips: self.key_abuse_max_ips.unwrap_or(0),
+            key_abuse_min_rpm: self.key_abuse_min_rpm.unwrap_or(0),
+            key_abuse_blocked_countries: self.key_abuse_blocked_countries,
+            alert_webhook_url: self.alert_webhook_url,
+            geoip_country_db: self.geoip_country_db,
+            geoip_asn_db: self.geoip_asn_db,
+            stream_idle_timeout_ms: self.stream_idle_timeout_ms.unwrap_or(30000),
+            egress_ip_family: self.egress_ip_family,
+            egress_local_address: self.egress_local_address,
+            egress_interface: self.egress_interface,
+            tool_call_audit: self.tool_call_audit.unwrap_or(false),
+            max_response_body_bytes: self.max_response_body_bytes.unwrap_or(0),
+            truncate_oversize_responses: self.truncate_oversize_responses.unwrap_or(false),
+            event_log_max_body_bytes: self
+                .event_log_max_body_bytes
+                .unwrap_or(DEFAULT_EVENT_LOG_MAX_BODY_BYTES),
+            log_search: self.log_search.unwrap_or(false),
+            trusted_proxies: self.trusted_proxies,
+            sse_resume: self.sse_resume.unwrap_or(false),
         })
     }
 }
@@ -74,10 +262,34 @@ impl From<GlobalConfig> for GlobalConfigPatch {
         Self {
             host: Some(value.host),
             port: Some(value.port),
+            admin_host: value.admin_host,
+            admin_port: value.admin_port,
             admin_key: Some(value.admin_key),
             proxy: value.proxy,
             dsn: Some(value.dsn),
             event_redact_sensitive: Some(value.event_redact_sensitive),
+            native_error_format: Some(value.native_error_format),
+            stream_retry_on_interrupt: Some(value.stream_retry_on_interrupt),
+            stream_first_token_timeout_ms: Some(value.stream_first_token_timeout_ms),
+            coalesce_inflight_requests: Some(value.coalesce_inflight_requests),
+            response_compress_min_bytes: Some(value.response_compress_min_bytes),
+            key_abuse_max_ips: Some(value.key_abuse_max_ips),
+            key_abuse_min_rpm: Some(value.key_abuse_min_rpm),
+            key_abuse_blocked_countries: value.key_abuse_blocked_countries,
+            alert_webhook_url: value.alert_webhook_url,
+            geoip_country_db: value.geoip_country_db,
+            geoip_asn_db: value.geoip_asn_db,
+            stream_idle_timeout_ms: Some(value.stream_idle_timeout_ms),
+            egress_ip_family: value.egress_ip_family,
+            egress_local_address: value.egress_local_address,
+            egress_interface: value.egress_interface,
+            tool_call_audit: Some(value.tool_call_audit),
+            max_response_body_bytes: Some(value.max_response_body_bytes),
+            truncate_oversize_responses: Some(value.truncate_oversize_responses),
+            event_log_max_body_bytes: Some(value.event_log_max_body_bytes),
+            log_search: Some(value.log_search),
+            trusted_proxies: value.trusted_proxies,
+            sse_resume: Some(value.sse_resume),
         }
     }
 }
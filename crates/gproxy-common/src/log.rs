@@ -0,0 +1,480 @@
+//! Process log output: plain text or JSON lines, to the console and/or a rotating file.
+//!
+//! Until [`init`] runs, lines go to stderr as text. Which lines are written is decided by
+//! a [`LogFilter`] that can be replaced at runtime with [`set_filter`].
+
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock, RwLock};
+
+use serde_json::{Value as JsonValue, json};
+use time::format_description::well_known::Rfc3339;
+use time::{Date, OffsetDateTime};
+
+/// Name of the active log file; rotated files get a timestamp suffix.
+pub const LOG_FILE_NAME: &str = "gproxy.log";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+impl LogLevel {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LogLevel::Error => "error",
+            LogLevel::Warn => "warn",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "error" => Some(LogLevel::Error),
+            "warn" | "warning" => Some(LogLevel::Warn),
+            "info" => Some(LogLevel::Info),
+            "debug" | "trace" => Some(LogLevel::Debug),
+            _ => None,
+        }
+    }
+}
+
+/// Most verbose level written per target, e.g. `info,jobs=debug,event=warn`: a bare level
+/// applies to every target without a directive of its own.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogFilter {
+    default: LogLevel,
+    directives: Vec<(String, LogLevel)>,
+}
+
+impl Default for LogFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LogFilter {
+    /// `info` for every target.
+    pub const fn new() -> Self {
+        Self {
+            default: LogLevel::Info,
+            directives: Vec::new(),
+        }
+    }
+
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut filter = Self::new();
+        for part in spec
+            .split(',')
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+        {
+            match part.split_once('=') {
+                Some((target, level)) => {
+                    let target = target.trim();
+                    if target.is_empty() {
+                        return Err(format!("missing target in `{part}`"));
+                    }
+                    let level = LogLevel::parse(level)
+                        .ok_or_else(|| format!("unknown level in `{part}`"))?;
+                    filter.directives.retain(|(existing, _)| existing != target);
+                    filter.directives.push((target.to_string(), level));
+                }
+                None => {
+                    filter.default =
+                        LogLevel::parse(part).ok_or_else(|| format!("unknown level `{part}`"))?;
+                }
+            }
+        }
+        Ok(filter)
+    }
+
+    pub fn enabled(&self, level: LogLevel, target: &str) -> bool {
+        let max = self
+            .directives
+            .iter()
+            .find(|(name, _)| name == target)
+            .map_or(self.default, |(_, level)| *level);
+        level <= max
+    }
+}
+
+impl fmt::Display for LogFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.default.as_str())?;
+        for (target, level) in &self.directives {
+            write!(f, ",{target}={}", level.as_str())?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogOutput {
+    /// stderr only.
+    #[default]
+    Console,
+    File,
+    Both,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogRotation {
+    /// A new file every UTC day.
+    #[default]
+    Daily,
+    /// A new file once the current one reaches `max_bytes`.
+    Size,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogConfig {
+    pub filter: LogFilter,
+    pub format: LogFormat,
+    pub output: LogOutput,
+    /// Directory of the log file; created when missing.
+    pub dir: PathBuf,
+    pub rotation: LogRotation,
+    pub max_bytes: u64,
+    /// Rotated files kept next to the active one.
+    pub retention: usize,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            filter: LogFilter::new(),
+            format: LogFormat::Text,
+            output: LogOutput::Console,
+            dir: PathBuf::from("logs"),
+            rotation: LogRotation::Daily,
+            max_bytes: 100 * 1024 * 1024,
+            retention: 7,
+        }
+    }
+}
+
+impl LogFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "text" | "pretty" => Some(LogFormat::Text),
+            "json" => Some(LogFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+impl LogOutput {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "console" | "stderr" => Some(LogOutput::Console),
+            "file" => Some(LogOutput::File),
+            "both" => Some(LogOutput::Both),
+            _ => None,
+        }
+    }
+}
+
+impl LogRotation {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "daily" | "day" => Some(LogRotation::Daily),
+            "size" => Some(LogRotation::Size),
+            _ => None,
+        }
+    }
+}
+
+struct Logger {
+    format: LogFormat,
+    console: bool,
+    file: Option<Mutex<RotatingFile>>,
+}
+
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+static FILTER: RwLock<LogFilter> = RwLock::new(LogFilter::new());
+
+/// Installs the process logger. Only the first call has an effect.
+pub fn init(config: LogConfig) -> io::Result<()> {
+    let file = match config.output {
+        LogOutput::Console => None,
+        LogOutput::File | LogOutput::Both => Some(Mutex::new(RotatingFile::open(&config)?)),
+    };
+    let installed = LOGGER
+        .set(Logger {
+            format: config.format,
+            console: config.output != LogOutput::File,
+            file,
+        })
+        .is_ok();
+    if installed {
+        set_filter(config.filter);
+    }
+    Ok(())
+}
+
+/// The filter currently applied.
+pub fn filter() -> LogFilter {
+    FILTER.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Replaces the filter; takes effect for the next line written.
+pub fn set_filter(filter: LogFilter) {
+    *FILTER.write().unwrap_or_else(|e| e.into_inner()) = filter;
+}
+
+pub fn enabled(level: LogLevel, target: &str) -> bool {
+    FILTER
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .enabled(level, target)
+}
+
+/// Writes one line when the filter lets it through. `target` names the subsystem
+/// (`bootstrap`, `jobs`, `clickhouse`, ...).
+pub fn write(level: LogLevel, target: &str, message: fmt::Arguments<'_>) {
+    if !enabled(level, target) {
+        return;
+    }
+    let format = LOGGER.get().map_or(LogFormat::Text, |logger| logger.format);
+    let now = OffsetDateTime::now_utc();
+    let line = match format {
+        LogFormat::Text => format!(
+            "{} {:<5} {target}: {message}",
+            timestamp(now),
+            level.as_str().to_ascii_uppercase()
+        ),
+        LogFormat::Json => json!({
+            "ts": timestamp(now),
+            "level": level.as_str(),
+            "target": target,
+            "msg": message.to_string(),
+        })
+        .to_string(),
+    };
+    emit(&line, now);
+}
+
+/// Writes a structured event (request/usage/operational) as one line: the event JSON
+/// itself in text mode, nested under `event` in JSON mode. Filtered as `info` of target
+/// `event`.
+pub fn event(value: &JsonValue) {
+    if !enabled(LogLevel::Info, "event") {
+        return;
+    }
+    let format = LOGGER.get().map_or(LogFormat::Text, |logger| logger.format);
+    let now = OffsetDateTime::now_utc();
+    let line = match format {
+        LogFormat::Text => value.to_string(),
+        LogFormat::Json => json!({
+            "ts": timestamp(now),
+            "level": LogLevel::Info.as_str(),
+            "target": "event",
+            "event": value,
+        })
+        .to_string(),
+    };
+    emit(&line, now);
+}
+
+fn emit(line: &str, now: OffsetDateTime) {
+    let Some(logger) = LOGGER.get() else {
+        eprintln!("{line}");
+        return;
+    };
+    if logger.console {
+        eprintln!("{line}");
+    }
+    if let Some(file) = &logger.file {
+        let mut file = file.lock().unwrap_or_else(|e| e.into_inner());
+        // Logging must never take the process down; a failed write is dropped.
+        let _ = file.write_line(line, now);
+    }
+}
+
+fn timestamp(now: OffsetDateTime) -> String {
+    now.format(&Rfc3339).unwrap_or_default()
+}
+
+#[macro_export]
+macro_rules! log_error {
+    ($target:expr, $($arg:tt)+) => {
+        $crate::log::write($crate::log::LogLevel::Error, $target, format_args!($($arg)+))
+    };
+}
+
+#[macro_export]
+macro_rules! log_warn {
+    ($target:expr, $($arg:tt)+) => {
+        $crate::log::write($crate::log::LogLevel::Warn, $target, format_args!($($arg)+))
+    };
+}
+
+#[macro_export]
+macro_rules! log_info {
+    ($target:expr, $($arg:tt)+) => {
+        $crate::log::write($crate::log::LogLevel::Info, $target, format_args!($($arg)+))
+    };
+}
+
+#[macro_export]
+macro_rules! log_debug {
+    ($target:expr, $($arg:tt)+) => {
+        $crate::log::write($crate::log::LogLevel::Debug, $target, format_args!($($arg)+))
+    };
+}
+
+struct RotatingFile {
+    dir: PathBuf,
+    rotation: LogRotation,
+    max_bytes: u64,
+    retention: usize,
+    file: File,
+    size: u64,
+    opened_on: Date,
+}
+
+impl RotatingFile {
+    fn open(config: &LogConfig) -> io::Result<Self> {
+        fs::create_dir_all(&config.dir)?;
+        let file = open_append(&config.dir)?;
+        let metadata = file.metadata()?;
+        // An existing file belongs to the day it was last written.
+        let opened_on = metadata
+            .modified()
+            .map(|at| OffsetDateTime::from(at).date())
+            .unwrap_or_else(|_| OffsetDateTime::now_utc().date());
+        Ok(Self {
+            dir: config.dir.clone(),
+            rotation: config.rotation,
+            max_bytes: config.max_bytes.max(1),
+            retention: config.retention,
+            file,
+            size: metadata.len(),
+            opened_on,
+        })
+    }
+
+    fn write_line(&mut self, line: &str, now: OffsetDateTime) -> io::Result<()> {
+        let len = line.len() as u64 + 1;
+        let due = match self.rotation {
+            LogRotation::Daily => now.date() != self.opened_on,
+            LogRotation::Size => self.size > 0 && self.size + len > self.max_bytes,
+        };
+        if due {
+            self.rotate(now)?;
+        }
+        self.file.write_all(line.as_bytes())?;
+        self.file.write_all(b"\n")?;
+        self.size += len;
+        Ok(())
+    }
+
+    fn rotate(&mut self, now: OffsetDateTime) -> io::Result<()> {
+        let suffix = match self.rotation {
+            // Named after the day the file covers.
+            LogRotation::Daily => self.opened_on.to_string(),
+            LogRotation::Size => {
+                let (h, m, s) = now.time().as_hms();
+                format!("{}T{h:02}{m:02}{s:02}", now.date())
+            }
+        };
+        let mut target = self.dir.join(format!("{LOG_FILE_NAME}.{suffix}"));
+        let mut n = 1;
+        while target.exists() {
+            target = self.dir.join(format!("{LOG_FILE_NAME}.{suffix}.{n}"));
+            n += 1;
+        }
+        fs::rename(self.dir.join(LOG_FILE_NAME), &target)?;
+        self.file = open_append(&self.dir)?;
+        self.size = 0;
+        self.opened_on = now.date();
+        prune_rotated(&self.dir, self.retention)
+    }
+}
+
+fn open_append(dir: &Path) -> io::Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(LOG_FILE_NAME))
+}
+
+/// Deletes the oldest rotated files beyond `retention`.
+fn prune_rotated(dir: &Path, retention: usize) -> io::Result<()> {
+    let prefix = format!("{LOG_FILE_NAME}.");
+    let mut rotated: Vec<_> = fs::read_dir(dir)?
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with(&prefix))
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .collect();
+    if rotated.len() <= retention {
+        return Ok(());
+    }
+    rotated.sort();
+    for (_, path) in &rotated[..rotated.len() - retention] {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_directives_override_the_default_level() {
+        let filter = LogFilter::parse("warn, jobs=debug,event=error").unwrap();
+        assert!(filter.enabled(LogLevel::Debug, "jobs"));
+        assert!(!filter.enabled(LogLevel::Info, "event"));
+        assert!(filter.enabled(LogLevel::Warn, "clickhouse"));
+        assert!(!filter.enabled(LogLevel::Info, "clickhouse"));
+        assert_eq!(filter.to_string(), "warn,jobs=debug,event=error");
+        assert_eq!(LogFilter::parse("").unwrap(), LogFilter::new());
+        assert!(LogFilter::parse("jobs=loud").is_err());
+        assert!(LogFilter::parse("=debug").is_err());
+    }
+
+    #[test]
+    fn size_rotation_keeps_the_configured_number_of_files() {
+        let dir = std::env::temp_dir().join(format!("gproxy-log-{}", uuid::Uuid::now_v7()));
+        let config = LogConfig {
+            dir: dir.clone(),
+            rotation: LogRotation::Size,
+            max_bytes: 16,
+            retention: 2,
+            ..LogConfig::default()
+        };
+        let mut file = RotatingFile::open(&config).unwrap();
+        let now = OffsetDateTime::now_utc();
+        for i in 0..5 {
+            file.write_line(&format!("line number {i}"), now).unwrap();
+        }
+
+        let mut names: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        assert_eq!(names.len(), 3);
+        assert_eq!(names[0], LOG_FILE_NAME);
+        assert_eq!(
+            fs::read_to_string(dir.join(LOG_FILE_NAME)).unwrap(),
+            "line number 4\n"
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
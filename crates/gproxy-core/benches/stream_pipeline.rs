@@ -0,0 +1,132 @@
+//! Upstream stream hot path: bytes are decoded, transformed to the client's proto and
+//! re-encoded, for every proto pair at a few network chunk sizes.
+//!
+//! `cargo bench -p gproxy-core --bench stream_pipeline`
+
+use std::hint::black_box;
+
+use bytes::Bytes;
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use gproxy_core::proxy_engine::{StreamDecoder, encode_stream_event};
+use gproxy_transform::middleware::{Op, Proto, StreamTransformer, TransformContext, stream_format};
+
+const PROTOS: [Proto; 4] = [
+    Proto::Claude,
+    Proto::OpenAIChat,
+    Proto::OpenAIResponse,
+    Proto::Gemini,
+];
+/// Small TLS records, a typical read, and a burst after a stall.
+const CHUNK_SIZES: [usize; 3] = [64, 1024, 16 * 1024];
+const TEXT_DELTAS: usize = 400;
+
+fn stream_ctx(src: Proto, dst: Proto) -> TransformContext {
+    TransformContext {
+        src,
+        dst,
+        src_op: Op::StreamGenerateContent,
+        dst_op: Op::StreamGenerateContent,
+    }
+}
+
+/// A Claude text answer of `TEXT_DELTAS` deltas, as the upstream sends it.
+fn claude_stream() -> String {
+    let mut out = String::new();
+    let mut event = |name: &str, data: String| {
+        out.push_str(&format!("event: {name}\ndata: {data}\n\n"));
+    };
+    event(
+        "message_start",
+        r#"{"type":"message_start","message":{"id":"msg_bench","type":"message","role":"assistant","model":"claude-sonnet-4-5","content":[],"stop_reason":null,"stop_sequence":null,"usage":{"input_tokens":1200,"cache_creation_input_tokens":0,"cache_read_input_tokens":0,"cache_creation":{"ephemeral_5m_input_tokens":0,"ephemeral_1h_input_tokens":0},"output_tokens":1,"service_tier":"standard"}}}"#.to_string(),
+    );
+    event(
+        "content_block_start",
+        r#"{"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#
+            .to_string(),
+    );
+    for i in 0..TEXT_DELTAS {
+        event(
+            "content_block_delta",
+            format!(
+                r#"{{"type":"content_block_delta","index":0,"delta":{{"type":"text_delta","text":"token {i} of the answer, "}}}}"#
+            ),
+        );
+    }
+    event(
+        "content_block_stop",
+        r#"{"type":"content_block_stop","index":0}"#.to_string(),
+    );
+    event(
+        "message_delta",
+        format!(
+            r#"{{"type":"message_delta","delta":{{"stop_reason":"end_turn","stop_sequence":null}},"usage":{{"output_tokens":{TEXT_DELTAS}}}}}"#
+        ),
+    );
+    event("message_stop", r#"{"type":"message_stop"}"#.to_string());
+    out
+}
+
+/// The same answer in `proto`'s own wire format.
+fn upstream_bytes(proto: Proto) -> Vec<u8> {
+    let claude = claude_stream();
+    if proto == Proto::Claude {
+        return claude.into_bytes();
+    }
+    let mut decoder = StreamDecoder::new(Proto::Claude, stream_format(Proto::Claude).unwrap());
+    let mut events = decoder.push_bytes(&Bytes::from(claude));
+    events.extend(decoder.finish());
+    let mut transformer = StreamTransformer::new(&stream_ctx(Proto::Claude, proto)).unwrap();
+    let mut out = Vec::new();
+    for event in events {
+        for converted in transformer.push(event).unwrap() {
+            if let Some(bytes) = encode_stream_event(proto, &converted) {
+                out.extend_from_slice(&bytes);
+            }
+        }
+    }
+    out
+}
+
+/// Decode, transform and encode one whole stream; returns the bytes written.
+fn run_pipeline(src: Proto, dst: Proto, chunks: &[Bytes]) -> usize {
+    let mut decoder = StreamDecoder::new(src, stream_format(src).unwrap());
+    let mut transformer = StreamTransformer::new(&stream_ctx(src, dst)).unwrap();
+    let mut written = 0;
+    let mut forward = |events: Vec<_>, written: &mut usize| {
+        for event in events {
+            for converted in transformer.push(event).unwrap_or_default() {
+                if let Some(bytes) = encode_stream_event(dst, &converted) {
+                    *written += bytes.len();
+                }
+            }
+        }
+    };
+    for chunk in chunks {
+        forward(decoder.push_bytes(chunk), &mut written);
+    }
+    forward(decoder.finish(), &mut written);
+    written
+}
+
+fn stream_pipeline(c: &mut Criterion) {
+    for src in PROTOS {
+        let upstream = upstream_bytes(src);
+        let mut group = c.benchmark_group(format!("stream_pipeline/{src:?}"));
+        group.throughput(Throughput::Bytes(upstream.len() as u64));
+        for dst in PROTOS {
+            for size in CHUNK_SIZES {
+                let chunks: Vec<Bytes> =
+                    upstream.chunks(size).map(Bytes::copy_from_slice).collect();
+                group.bench_with_input(
+                    BenchmarkId::new(format!("{dst:?}"), size),
+                    &chunks,
+                    |b, chunks| b.iter(|| run_pipeline(src, dst, black_box(chunks))),
+                );
+            }
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, stream_pipeline);
+criterion_main!(benches);
@@ -1,17 +1,27 @@
 use std::collections::HashSet;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 
 use anyhow::Context;
 use clap::Parser;
+use time::OffsetDateTime;
 
+use gproxy_common::log::{LogConfig, LogFilter, LogFormat, LogOutput, LogRotation};
 use gproxy_common::{GlobalConfig, GlobalConfigPatch};
-use gproxy_provider_core::{EventHub, ProviderRegistry, TerminalEventSink};
+use gproxy_provider_core::{
+    EventHub, EventSink, ProviderRegistry, TerminalEventSink, UpstreamProvider,
+};
 use gproxy_provider_impl::builtin_provider_seeds;
 use gproxy_provider_impl::register_builtin_providers;
-use gproxy_storage::{DbEventSink, SeaOrmStorage, Storage};
+use gproxy_storage::memory::is_memory_dsn;
+use gproxy_storage::{
+    DbEventSink, MemoryStorage, SeaOrmStorage, SplitStorage, Storage, USAGE_JOURNAL_FILE,
+    UsageJournal,
+};
 
-use crate::state::AppState;
+use crate::clickhouse::{ClickHouseConfig, ClickHouseSink};
+use crate::state::{AppState, SEMANTIC_CACHE_FILE, TrafficStats};
 
 #[derive(Debug, Clone, Parser)]
 #[command(
@@ -20,10 +30,19 @@ use crate::state::AppState;
     about = "High-performance multi-provider LLM proxy"
 )]
 pub struct CliArgs {
-    /// Database DSN (required to bootstrap the rest of config).
+    /// Database DSN (required to bootstrap the rest of config); `memory://[seed.json]` keeps
+    /// everything in process memory.
     #[arg(long, env = "GPROXY_DSN")]
     pub dsn: Option<String>,
 
+    /// Separate DSN for logs, usage, events and hourly stats; configuration stays on `--dsn`.
+    #[arg(long, env = "GPROXY_TELEMETRY_DSN")]
+    pub telemetry_dsn: Option<String>,
+
+    /// ClickHouse HTTP endpoint receiving request and usage rows in batches.
+    #[arg(long, env = "GPROXY_CLICKHOUSE_URL")]
+    pub clickhouse_url: Option<String>,
+
     /// Bind host.
     #[arg(long, env = "GPROXY_HOST")]
     pub host: Option<String>,
@@ -32,6 +51,14 @@ pub struct CliArgs {
     #[arg(long, env = "GPROXY_PORT")]
     pub port: Option<String>,
 
+    /// Bind host of the separate admin listener (default `127.0.0.1`).
+    #[arg(long, env = "GPROXY_ADMIN_HOST")]
+    pub admin_host: Option<String>,
+
+    /// Serve the admin API and UI on this port instead of the proxy port.
+    #[arg(long, env = "GPROXY_ADMIN_PORT")]
+    pub admin_port: Option<String>,
+
     /// Admin key (plaintext). Stored in DB and memory.
     #[arg(long, env = "GPROXY_ADMIN_KEY")]
     pub admin_key: Option<String>,
@@ -43,10 +70,146 @@ pub struct CliArgs {
     /// Redact sensitive headers/body fields in emitted events.
     #[arg(long, env = "GPROXY_EVENT_REDACT_SENSITIVE")]
     pub event_redact_sensitive: Option<String>,
+
+    /// Render engine errors in the caller's protocol error shape.
+    #[arg(long, env = "GPROXY_NATIVE_ERROR_FORMAT")]
+    pub native_error_format: Option<String>,
+
+    /// Retry a stream once when it breaks before any output was forwarded.
+    #[arg(long, env = "GPROXY_STREAM_RETRY_ON_INTERRUPT")]
+    pub stream_retry_on_interrupt: Option<String>,
+
+    /// Fail a stream over to another credential when no content arrives within this many ms (0 disables).
+    #[arg(long, env = "GPROXY_STREAM_FIRST_TOKEN_TIMEOUT_MS")]
+    pub stream_first_token_timeout_ms: Option<String>,
+
+    /// Share one upstream call between identical concurrent non-stream requests from the same key.
+    #[arg(long, env = "GPROXY_COALESCE_INFLIGHT_REQUESTS")]
+    pub coalesce_inflight_requests: Option<String>,
+
+    /// Gzip non-stream JSON responses at least this large when the client accepts it (0 disables).
+    #[arg(long, env = "GPROXY_RESPONSE_COMPRESS_MIN_BYTES")]
+    pub response_compress_min_bytes: Option<String>,
+
+    /// Auto-disable a user key used from more than this many client IPs within one minute (0 disables).
+    #[arg(long, env = "GPROXY_KEY_ABUSE_MAX_IPS")]
+    pub key_abuse_max_ips: Option<String>,
+
+    /// Only apply the distinct-IP rule once the key made at least this many requests in that minute.
+    #[arg(long, env = "GPROXY_KEY_ABUSE_MIN_RPM")]
+    pub key_abuse_min_rpm: Option<String>,
+
+    /// Comma-separated ISO country codes; a user key used from one of them is auto-disabled.
+    #[arg(long, env = "GPROXY_KEY_ABUSE_BLOCKED_COUNTRIES")]
+    pub key_abuse_blocked_countries: Option<String>,
+
+    /// Receives a JSON POST whenever a user key is auto-disabled.
+    #[arg(long, env = "GPROXY_ALERT_WEBHOOK_URL")]
+    pub alert_webhook_url: Option<String>,
+
+    /// Path to a MaxMind country database (.mmdb) used to tag downstream requests with the client country.
+    #[arg(long, env = "GPROXY_GEOIP_COUNTRY_DB")]
+    pub geoip_country_db: Option<String>,
+
+    /// Path to a MaxMind ASN database (.mmdb) used to tag downstream requests with the client ASN.
+    #[arg(long, env = "GPROXY_GEOIP_ASN_DB")]
+    pub geoip_asn_db: Option<String>,
+
+    /// Abort a forwarded stream when the upstream sends nothing for this long (0 disables the watchdog).
+    #[arg(long, env = "GPROXY_STREAM_IDLE_TIMEOUT_MS")]
+    pub stream_idle_timeout_ms: Option<String>,
+
+    /// Upstream address family: auto, ipv4, ipv6 (preferred first) or ipv4_only / ipv6_only.
+    #[arg(long, env = "GPROXY_EGRESS_IP_FAMILY")]
+    pub egress_ip_family: Option<String>,
+
+    /// Source IP address bound for upstream connections.
+    #[arg(long, env = "GPROXY_EGRESS_LOCAL_ADDRESS")]
+    pub egress_local_address: Option<String>,
+
+    /// Network interface bound for upstream connections.
+    #[arg(long, env = "GPROXY_EGRESS_INTERFACE")]
+    pub egress_interface: Option<String>,
+
+    /// Record the tool calls of generate responses to the `tool_calls` table.
+    #[arg(long, env = "GPROXY_TOOL_CALL_AUDIT")]
+    pub tool_call_audit: Option<String>,
+
+    /// Largest non-stream upstream response body accepted, in bytes (0 disables the limit).
+    #[arg(long, env = "GPROXY_MAX_RESPONSE_BODY_BYTES")]
+    pub max_response_body_bytes: Option<String>,
+
+    /// Cut oversize raw passthrough responses to the limit instead of rejecting them.
+    #[arg(long, env = "GPROXY_TRUNCATE_OVERSIZE_RESPONSES")]
+    pub truncate_oversize_responses: Option<String>,
+
+    /// Bytes of each request/response body kept in event logs (default 50 MiB).
+    #[arg(long, env = "GPROXY_EVENT_LOG_MAX_BODY_BYTES")]
+    pub event_log_max_body_bytes: Option<String>,
+
+    /// Index logged bodies and error messages for `GET /admin/logs/search`.
+    #[arg(long, env = "GPROXY_LOG_SEARCH")]
+    pub log_search: Option<String>,
+
+    /// Let clients resume a dropped stream with `Last-Event-ID` instead of starting it over.
+    #[arg(long, env = "GPROXY_SSE_RESUME")]
+    pub sse_resume: Option<String>,
+
+    /// Comma-separated addresses or CIDRs of reverse proxies allowed to set
+    /// `x-forwarded-for`, `x-real-ip` and edge country headers.
+    #[arg(long, env = "GPROXY_TRUSTED_PROXIES")]
+    pub trusted_proxies: Option<String>,
+
+    /// External authorizer asked (after the stored user keys) with the incoming headers;
+    /// a 2xx answer names the user key in `x-gproxy-user-key-id`.
+    #[arg(long, env = "GPROXY_FORWARD_AUTH_URL")]
+    pub forward_auth_url: Option<String>,
+
+    /// Directory with an admin UI bundle (`manifest.json` plus files) served instead of the
+    /// embedded UI; defaults to `$GPROXY_DATA_DIR/ui`.
+    #[arg(long, env = "GPROXY_UI_DIR")]
+    pub ui_dir: Option<String>,
+
+    /// Only serve a UI bundle whose manifest has this version.
+    #[arg(long, env = "GPROXY_UI_VERSION")]
+    pub ui_version: Option<String>,
+
+    /// Serve no admin UI at all (headless servers).
+    #[arg(long, env = "GPROXY_NO_UI")]
+    pub no_ui: bool,
+
+    /// Initial log filter, e.g. `info,jobs=debug` (default `info`); adjustable at runtime
+    /// through `PUT /admin/system/log_filter`.
+    #[arg(long, env = "GPROXY_LOG_LEVEL")]
+    pub log_level: Option<String>,
+
+    /// Log line format: `text` (default) or `json` (one object per line, for Loki/ELK).
+    #[arg(long, env = "GPROXY_LOG_FORMAT")]
+    pub log_format: Option<String>,
+
+    /// Where logs go: `console` (stderr, default), `file` or `both`.
+    #[arg(long, env = "GPROXY_LOG_OUTPUT")]
+    pub log_output: Option<String>,
+
+    /// Directory of `gproxy.log`; defaults to `$GPROXY_DATA_DIR/logs`, else `./logs`.
+    #[arg(long, env = "GPROXY_LOG_DIR")]
+    pub log_dir: Option<String>,
+
+    /// Log file rotation: `daily` (default) or `size`.
+    #[arg(long, env = "GPROXY_LOG_ROTATION")]
+    pub log_rotation: Option<String>,
+
+    /// Size at which the log file rotates with `--log-rotation size` (default 100 MiB).
+    #[arg(long, env = "GPROXY_LOG_MAX_BYTES")]
+    pub log_max_bytes: Option<String>,
+
+    /// Rotated log files kept (default 7).
+    #[arg(long, env = "GPROXY_LOG_RETENTION")]
+    pub log_retention: Option<String>,
 }
 
 pub struct Bootstrap {
-    pub storage: Arc<SeaOrmStorage>,
+    pub storage: Arc<dyn Storage>,
     pub state: Arc<AppState>,
     pub registry: Arc<ProviderRegistry>,
 }
@@ -57,25 +220,262 @@ pub async fn bootstrap_from_env() -> anyhow::Result<Bootstrap> {
 }
 
 pub async fn bootstrap(args: CliArgs) -> anyhow::Result<Bootstrap> {
+    bootstrap_with_providers(args, Vec::new()).await
+}
+
+/// Like [`bootstrap`], with extra provider implementations from the embedding application.
+/// They are registered after the built-ins, so one with a built-in's name replaces it;
+/// providers select them with `{"kind": "plugin", "channel_settings": {"implementation": ...}}`.
+pub async fn bootstrap_with_providers(
+    args: CliArgs,
+    extra_providers: Vec<Arc<dyn UpstreamProvider>>,
+) -> anyhow::Result<Bootstrap> {
+    let telemetry_dsn = telemetry_dsn_from_args(&args);
+    let clickhouse_url = clickhouse_url_from_args(&args);
+    let patch = global_patch_from_args(args)?;
+    let storage = connect_storage(patch.dsn.as_deref().unwrap_or_default()).await?;
+    let storage = with_telemetry_dsn(storage, telemetry_dsn.as_deref()).await?;
+    let mut extras = BootstrapExtras {
+        providers: extra_providers,
+        ..Default::default()
+    };
+    if let Some(url) = clickhouse_url {
+        extras.event_sinks.push(clickhouse_sink(url)?);
+    }
+    bootstrap_storage(storage, patch, extras).await
+}
+
+/// What an embedding application adds on top of the built-in wiring.
+#[derive(Default)]
+pub struct BootstrapExtras {
+    /// Registered after the built-in providers.
+    pub providers: Vec<Arc<dyn UpstreamProvider>>,
+    /// Added next to the terminal and storage sinks.
+    pub event_sinks: Vec<Arc<dyn EventSink>>,
+}
+
+pub fn global_patch_from_env() -> anyhow::Result<GlobalConfigPatch> {
+    global_patch_from_args(CliArgs::parse())
+}
+
+pub fn cli_args_from_env() -> CliArgs {
+    CliArgs::parse()
+}
+
+pub fn telemetry_dsn_from_args(args: &CliArgs) -> Option<String> {
+    sanitize_optional_env_value(args.telemetry_dsn.clone())
+}
+
+pub fn clickhouse_url_from_args(args: &CliArgs) -> Option<String> {
+    sanitize_optional_env_value(args.clickhouse_url.clone())
+}
+
+pub fn forward_auth_url_from_args(args: &CliArgs) -> Option<String> {
+    sanitize_optional_env_value(args.forward_auth_url.clone())
+}
+
+/// Where an external admin UI bundle is looked up: `--ui-dir`, else `$GPROXY_DATA_DIR/ui`.
+pub fn ui_dir_from_args(args: &CliArgs) -> Option<PathBuf> {
+    if let Some(dir) = sanitize_optional_env_value(args.ui_dir.clone()) {
+        return Some(PathBuf::from(dir));
+    }
+    sanitize_optional_env_value(std::env::var("GPROXY_DATA_DIR").ok())
+        .map(|data_dir| PathBuf::from(data_dir).join("ui"))
+}
+
+pub fn ui_version_from_args(args: &CliArgs) -> Option<String> {
+    sanitize_optional_env_value(args.ui_version.clone())
+}
+
+/// Reads the `--log-*` settings; anything unset keeps the [`LogConfig`] default.
+pub fn log_config_from_args(args: &CliArgs) -> anyhow::Result<LogConfig> {
+    let mut config = LogConfig::default();
+    if let Some(raw) = sanitize_optional_env_value(args.log_level.clone()) {
+        config.filter = LogFilter::parse(&raw)
+            .map_err(|err| anyhow::anyhow!("invalid GPROXY_LOG_LEVEL value: {raw}: {err}"))?;
+    }
+    if let Some(raw) = sanitize_optional_env_value(args.log_format.clone()) {
+        config.format = LogFormat::parse(&raw)
+            .ok_or_else(|| anyhow::anyhow!("invalid GPROXY_LOG_FORMAT value: {raw}"))?;
+    }
+    if let Some(raw) = sanitize_optional_env_value(args.log_output.clone()) {
+        config.output = LogOutput::parse(&raw)
+            .ok_or_else(|| anyhow::anyhow!("invalid GPROXY_LOG_OUTPUT value: {raw}"))?;
+    }
+    if let Some(raw) = sanitize_optional_env_value(args.log_rotation.clone()) {
+        config.rotation = LogRotation::parse(&raw)
+            .ok_or_else(|| anyhow::anyhow!("invalid GPROXY_LOG_ROTATION value: {raw}"))?;
+    }
+    if let Some(dir) = sanitize_optional_env_value(args.log_dir.clone()) {
+        config.dir = PathBuf::from(dir);
+    } else if let Some(data_dir) =
+        sanitize_optional_env_value(std::env::var("GPROXY_DATA_DIR").ok())
+    {
+        config.dir = PathBuf::from(data_dir).join("logs");
+    }
+    if let Some(max_bytes) =
+        parse_u64_env_value(args.log_max_bytes.clone(), "GPROXY_LOG_MAX_BYTES")?
+    {
+        config.max_bytes = max_bytes;
+    }
+    if let Some(retention) =
+        parse_u64_env_value(args.log_retention.clone(), "GPROXY_LOG_RETENTION")?
+    {
+        config.retention = retention as usize;
+    }
+    Ok(config)
+}
+
+/// Starts the ClickHouse writer for `url` with the default batching.
+pub fn clickhouse_sink(url: String) -> anyhow::Result<Arc<dyn EventSink>> {
+    let sink =
+        ClickHouseSink::spawn(ClickHouseConfig::new(url)).context("start clickhouse sink")?;
+    Ok(Arc::new(sink))
+}
+
+/// Reads the CLI/ENV settings into a patch that overlays the stored global config.
+pub fn global_patch_from_args(args: CliArgs) -> anyhow::Result<GlobalConfigPatch> {
     let dsn = sanitize_dsn_value(args.dsn.clone());
     let host = sanitize_optional_env_value(args.host.clone());
     let port = parse_u16_env_value(args.port.clone(), "GPROXY_PORT")?;
+    let admin_host = sanitize_optional_env_value(args.admin_host.clone());
+    let admin_port = parse_u16_env_value(args.admin_port.clone(), "GPROXY_ADMIN_PORT")?;
     let admin_key = sanitize_optional_env_value(args.admin_key.clone());
     let proxy = sanitize_optional_env_value(args.proxy.clone());
     let event_redact_sensitive = parse_bool_env_value(
         args.event_redact_sensitive.clone(),
         "GPROXY_EVENT_REDACT_SENSITIVE",
     )?;
+    let native_error_format = parse_bool_env_value(
+        args.native_error_format.clone(),
+        "GPROXY_NATIVE_ERROR_FORMAT",
+    )?;
+    let stream_retry_on_interrupt = parse_bool_env_value(
+        args.stream_retry_on_interrupt.clone(),
+        "GPROXY_STREAM_RETRY_ON_INTERRUPT",
+    )?;
+    let stream_first_token_timeout_ms = parse_u64_env_value(
+        args.stream_first_token_timeout_ms.clone(),
+        "GPROXY_STREAM_FIRST_TOKEN_TIMEOUT_MS",
+    )?;
+    let coalesce_inflight_requests = parse_bool_env_value(
+        args.coalesce_inflight_requests.clone(),
+        "GPROXY_COALESCE_INFLIGHT_REQUESTS",
+    )?;
+    let response_compress_min_bytes = parse_u64_env_value(
+        args.response_compress_min_bytes.clone(),
+        "GPROXY_RESPONSE_COMPRESS_MIN_BYTES",
+    )?;
+    let key_abuse_max_ips =
+        parse_u64_env_value(args.key_abuse_max_ips.clone(), "GPROXY_KEY_ABUSE_MAX_IPS")?;
+    let key_abuse_min_rpm =
+        parse_u64_env_value(args.key_abuse_min_rpm.clone(), "GPROXY_KEY_ABUSE_MIN_RPM")?;
+    let key_abuse_blocked_countries =
+        sanitize_optional_env_value(args.key_abuse_blocked_countries.clone());
+    let alert_webhook_url = sanitize_optional_env_value(args.alert_webhook_url.clone());
+    let geoip_country_db = sanitize_optional_env_value(args.geoip_country_db.clone());
+    let geoip_asn_db = sanitize_optional_env_value(args.geoip_asn_db.clone());
+    let stream_idle_timeout_ms = parse_u64_env_value(
+        args.stream_idle_timeout_ms.clone(),
+        "GPROXY_STREAM_IDLE_TIMEOUT_MS",
+    )?;
+    let egress_ip_family = sanitize_optional_env_value(args.egress_ip_family.clone());
+    let egress_local_address = sanitize_optional_env_value(args.egress_local_address.clone());
+    let egress_interface = sanitize_optional_env_value(args.egress_interface.clone());
+    let tool_call_audit =
+        parse_bool_env_value(args.tool_call_audit.clone(), "GPROXY_TOOL_CALL_AUDIT")?;
+    let max_response_body_bytes = parse_u64_env_value(
+        args.max_response_body_bytes.clone(),
+        "GPROXY_MAX_RESPONSE_BODY_BYTES",
+    )?;
+    let truncate_oversize_responses = parse_bool_env_value(
+        args.truncate_oversize_responses.clone(),
+        "GPROXY_TRUNCATE_OVERSIZE_RESPONSES",
+    )?;
+    let event_log_max_body_bytes = parse_u64_env_value(
+        args.event_log_max_body_bytes.clone(),
+        "GPROXY_EVENT_LOG_MAX_BODY_BYTES",
+    )?;
+    let log_search = parse_bool_env_value(args.log_search.clone(), "GPROXY_LOG_SEARCH")?;
+    let sse_resume = parse_bool_env_value(args.sse_resume.clone(), "GPROXY_SSE_RESUME")?;
+    let trusted_proxies = sanitize_optional_env_value(args.trusted_proxies.clone());
 
-    ensure_sqlite_parent_dir(&dsn)?;
+    Ok(GlobalConfigPatch {
+        host,
+        port,
+        admin_host,
+        admin_port,
+        admin_key,
+        proxy,
+        dsn: Some(dsn),
+        event_redact_sensitive,
+        native_error_format,
+        stream_retry_on_interrupt,
+        stream_first_token_timeout_ms,
+        coalesce_inflight_requests,
+        response_compress_min_bytes,
+        key_abuse_max_ips,
+        key_abuse_min_rpm,
+        key_abuse_blocked_countries,
+        alert_webhook_url,
+        geoip_country_db,
+        geoip_asn_db,
+        stream_idle_timeout_ms,
+        egress_ip_family,
+        egress_local_address,
+        egress_interface,
+        tool_call_audit,
+        max_response_body_bytes,
+        truncate_oversize_responses,
+        event_log_max_body_bytes,
+        log_search,
+        sse_resume,
+        trusted_proxies,
+    })
+}
 
-    // 1) connect DB from CLI/ENV DSN (required).
-    let storage = Arc::new(
-        SeaOrmStorage::connect(&dsn)
-            .await
-            .context("connect storage")?,
-    );
+/// Opens the storage selected by `dsn`: `memory://[seed.json]` keeps everything in
+/// process memory, anything else goes to SeaORM (creating the SQLite file's directory
+/// if needed).
+pub async fn connect_storage(dsn: &str) -> anyhow::Result<Arc<dyn Storage>> {
+    if is_memory_dsn(dsn) {
+        let storage = MemoryStorage::from_dsn(dsn).context("open memory storage")?;
+        return Ok(Arc::new(storage));
+    }
+    ensure_sqlite_parent_dir(dsn)?;
+    let storage = SeaOrmStorage::connect(dsn)
+        .await
+        .context("connect storage")?;
+    Ok(Arc::new(storage))
+}
+
+/// Routes telemetry to `telemetry_dsn` when set; configuration stays on `storage`.
+pub async fn with_telemetry_dsn(
+    storage: Arc<dyn Storage>,
+    telemetry_dsn: Option<&str>,
+) -> anyhow::Result<Arc<dyn Storage>> {
+    let Some(telemetry_dsn) = telemetry_dsn else {
+        return Ok(storage);
+    };
+    let telemetry = connect_storage(telemetry_dsn)
+        .await
+        .context("connect telemetry storage")?;
+    Ok(Arc::new(SplitStorage::new(storage, telemetry)))
+}
+
+/// Brings up the state on top of an already opened storage: schema sync, global config
+/// merge (`patch` > stored), seeding, snapshot load and event sinks.
+pub async fn bootstrap_storage(
+    storage: Arc<dyn Storage>,
+    mut patch: GlobalConfigPatch,
+    extras: BootstrapExtras,
+) -> anyhow::Result<Bootstrap> {
+    // 1) bring the schema up to date.
     storage.sync().await.context("schema sync")?;
+    storage
+        .sync_telemetry()
+        .await
+        .context("telemetry schema sync")?;
 
     // 2) load DB global config (if any), then merge once: CLI > ENV > DB.
     // clap already applies CLI > ENV precedence for each field; we then overlay on DB.
@@ -91,33 +491,22 @@ pub async fn bootstrap(args: CliArgs) -> anyhow::Result<Bootstrap> {
     // Select admin key source:
     // - CLI/ENV provided key wins and overwrites DB
     // - else, if DB missing admin_key, generate one and persist
-    let mut admin_key_override: Option<String> = None;
-    if let Some(key_plain) = admin_key.as_deref() {
-        admin_key_override = Some(key_plain.to_string());
-    } else if merged.admin_key.is_none() {
-        admin_key_override = Some(generate_admin_key());
+    if patch.admin_key.is_none() && merged.admin_key.is_none() {
+        patch.admin_key = Some(generate_admin_key());
     }
-
-    let cli_patch = GlobalConfigPatch {
-        host,
-        port,
-        admin_key: admin_key_override,
-        proxy,
-        dsn: Some(dsn),
-        event_redact_sensitive,
-    };
-    merged.overlay(cli_patch);
+    merged.overlay(patch);
 
     let global: GlobalConfig = merged
         .into_config()
         .context("finalize merged global config")?;
-    println!("admin key: {}", global.admin_key);
+    gproxy_common::log_info!("bootstrap", "admin key: {}", global.admin_key);
 
     // 3) persist merged global config back to DB.
     storage
         .upsert_global_config(&global)
         .await
         .context("upsert global_config")?;
+    storage.set_log_search(global.log_search);
 
     // 3.1) bootstrap default user/key if needed (user0 + admin key as API key).
     // Bootstrap default user/key if needed (user_id=0, name=user0).
@@ -155,24 +544,207 @@ pub async fn bootstrap(args: CliArgs) -> anyhow::Result<Bootstrap> {
     // 5) build in-memory state (all runtime reads come from here).
     let events = EventHub::new(1024);
     events.add_sink(Arc::new(TerminalEventSink::new())).await;
-    events
-        .add_sink(Arc::new(DbEventSink::new(storage.clone())))
-        .await;
+    let usage_journal = match usage_journal_path() {
+        Some(path) => {
+            let journal = UsageJournal::open(&path)
+                .with_context(|| format!("open usage journal {}", path.display()))?;
+            Some(Arc::new(journal))
+        }
+        None => None,
+    };
+    let mut db_sink = DbEventSink::new(storage.clone());
+    if let Some(journal) = &usage_journal {
+        db_sink = db_sink.with_journal(journal.clone());
+    }
+    events.add_sink(Arc::new(db_sink)).await;
+    for sink in extras.event_sinks {
+        events.add_sink(sink).await;
+    }
     let state = AppState::from_bootstrap(global, snapshot, events.clone())
         .await
         .context("build app state")?;
 
+    // 6) restore persisted hourly stats, then keep flushing the recent hours.
+    let stats_since = OffsetDateTime::now_utc() - TrafficStats::RETENTION;
+    let stats_rows = storage
+        .load_stats_hourly(stats_since)
+        .await
+        .context("load hourly stats")?;
+    state.stats.load_hours(&stats_rows);
+    register_stats_flush(&state, storage.clone());
+    state.debug_captures.start(storage.clone());
+    state.mcp_tool_calls.start(storage.clone());
+    state.tool_calls.start(storage.clone());
+    state.aborted_streams.start(storage.clone());
+    register_debug_capture_purge(&state, storage.clone());
+    if let Some(path) = semantic_cache_path() {
+        match state.semantic_cache.load(&path) {
+            Ok(loaded) => gproxy_common::log_info!(
+                "bootstrap",
+                "semantic cache: {loaded} entries from {}",
+                path.display()
+            ),
+            Err(err) => gproxy_common::log_warn!(
+                "bootstrap",
+                "semantic cache: read {}: {err}",
+                path.display()
+            ),
+        }
+        register_semantic_cache_flush(&state, path);
+    }
+    if let Some(journal) = usage_journal {
+        let pending = journal.pending();
+        if pending > 0 {
+            let replay = journal
+                .replay(storage.as_ref())
+                .await
+                .with_context(|| format!("replay usage journal {}", journal.path().display()))?;
+            gproxy_common::log_info!(
+                "bootstrap",
+                "usage journal: replayed {} of {pending} entries from {}",
+                replay.written,
+                journal.path().display()
+            );
+        }
+        register_usage_journal_replay(&state, journal, storage.clone());
+    }
+    state.jobs.start(storage.clone());
+
     Ok(Bootstrap {
         storage,
         state: Arc::new(state),
         registry: Arc::new({
             let mut r = ProviderRegistry::new();
             register_builtin_providers(&mut r);
+            r.extend(extras.providers);
             r
         }),
     })
 }
 
+const STATS_FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+const STATS_FLUSH_JITTER: Duration = Duration::from_secs(5);
+
+/// Periodically upserts the current and previous hour so a restart loses at most one
+/// flush interval of hourly history.
+fn register_stats_flush(state: &AppState, storage: Arc<dyn Storage>) {
+    let stats = state.stats.clone();
+    state.jobs.register(
+        "stats_flush",
+        STATS_FLUSH_INTERVAL,
+        STATS_FLUSH_JITTER,
+        move || {
+            let stats = stats.clone();
+            let storage = storage.clone();
+            async move {
+                let rows = stats.snapshot_hours(SystemTime::now() - Duration::from_secs(3600));
+                storage
+                    .upsert_stats_hourly(&rows)
+                    .await
+                    .map_err(|err| format!("flush hourly stats: {err}"))?;
+                Ok(Some(format!("{} rows", rows.len())))
+            }
+        },
+    );
+}
+
+const DEBUG_CAPTURE_PURGE_INTERVAL: Duration = Duration::from_secs(10 * 60);
+const DEBUG_CAPTURE_PURGE_JITTER: Duration = Duration::from_secs(30);
+
+/// Deletes debug captures whose retention has passed.
+fn register_debug_capture_purge(state: &AppState, storage: Arc<dyn Storage>) {
+    state.jobs.register(
+        "debug_capture_purge",
+        DEBUG_CAPTURE_PURGE_INTERVAL,
+        DEBUG_CAPTURE_PURGE_JITTER,
+        move || {
+            let storage = storage.clone();
+            async move {
+                let purged = storage
+                    .purge_debug_captures(OffsetDateTime::now_utc())
+                    .await
+                    .map_err(|err| format!("purge debug captures: {err}"))?;
+                Ok(Some(format!("{purged} rows")))
+            }
+        },
+    );
+}
+
+const USAGE_JOURNAL_REPLAY_INTERVAL: Duration = Duration::from_secs(30);
+const USAGE_JOURNAL_REPLAY_JITTER: Duration = Duration::from_secs(5);
+
+/// Where usage events are journaled: `$GPROXY_DATA_DIR/usage_journal.jsonl`. Without a
+/// data dir they go to the database only.
+fn usage_journal_path() -> Option<PathBuf> {
+    sanitize_optional_env_value(std::env::var("GPROXY_DATA_DIR").ok())
+        .map(|data_dir| PathBuf::from(data_dir).join(USAGE_JOURNAL_FILE))
+}
+
+/// Writes journaled usage events whose database write failed, e.g. during an outage.
+fn register_usage_journal_replay(
+    state: &AppState,
+    journal: Arc<UsageJournal>,
+    storage: Arc<dyn Storage>,
+) {
+    state.jobs.register(
+        "usage_journal_replay",
+        USAGE_JOURNAL_REPLAY_INTERVAL,
+        USAGE_JOURNAL_REPLAY_JITTER,
+        move || {
+            let journal = journal.clone();
+            let storage = storage.clone();
+            async move {
+                let replay = journal
+                    .replay(storage.as_ref())
+                    .await
+                    .map_err(|err| format!("replay usage journal: {err}"))?;
+                if replay.failed > 0 {
+                    return Err(format!(
+                        "replay usage journal: {} written, {} still failing",
+                        replay.written, replay.failed
+                    ));
+                }
+                Ok((replay.written > 0).then(|| format!("{} entries", replay.written)))
+            }
+        },
+    );
+}
+
+const SEMANTIC_CACHE_FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+const SEMANTIC_CACHE_FLUSH_JITTER: Duration = Duration::from_secs(5);
+
+/// Where the semantic cache is persisted: `$GPROXY_DATA_DIR/semantic_cache.jsonl`.
+/// Without a data dir the cache lives in memory only.
+fn semantic_cache_path() -> Option<PathBuf> {
+    sanitize_optional_env_value(std::env::var("GPROXY_DATA_DIR").ok())
+        .map(|data_dir| PathBuf::from(data_dir).join(SEMANTIC_CACHE_FILE))
+}
+
+/// Drops expired semantic cache entries and rewrites the file when the cache changed.
+fn register_semantic_cache_flush(state: &AppState, path: PathBuf) {
+    let cache = state.semantic_cache.clone();
+    state.jobs.register(
+        "semantic_cache_flush",
+        SEMANTIC_CACHE_FLUSH_INTERVAL,
+        SEMANTIC_CACHE_FLUSH_JITTER,
+        move || {
+            let cache = cache.clone();
+            let path = path.clone();
+            async move {
+                let saved = tokio::task::spawn_blocking(move || {
+                    cache.purge_expired();
+                    cache
+                        .save(&path)
+                        .map_err(|err| format!("write {}: {err}", path.display()))
+                })
+                .await
+                .map_err(|err| format!("flush semantic cache: {err}"))??;
+                Ok(saved.map(|entries| format!("{entries} entries")))
+            }
+        },
+    );
+}
+
 fn sanitize_optional_env_value(value: Option<String>) -> Option<String> {
     let trimmed = value?.trim().to_string();
     if trimmed.is_empty() {
@@ -190,7 +762,7 @@ fn sanitize_dsn_value(value: Option<String>) -> String {
     sanitize_optional_env_value(value).unwrap_or_else(default_dsn)
 }
 
-fn default_dsn() -> String {
+pub fn default_dsn() -> String {
     if let Some(data_dir) = sanitize_optional_env_value(std::env::var("GPROXY_DATA_DIR").ok()) {
         let dir = data_dir.trim_end_matches('/');
         return format!("sqlite://{dir}/gproxy.db?mode=rwc");
@@ -244,6 +816,16 @@ fn parse_u16_env_value(value: Option<String>, env_name: &str) -> anyhow::Result<
     Ok(Some(parsed))
 }
 
+fn parse_u64_env_value(value: Option<String>, env_name: &str) -> anyhow::Result<Option<u64>> {
+    let Some(raw) = sanitize_optional_env_value(value) else {
+        return Ok(None);
+    };
+    let parsed = raw
+        .parse::<u64>()
+        .with_context(|| format!("invalid {env_name} value: {raw}"))?;
+    Ok(Some(parsed))
+}
+
 fn parse_bool_env_value(value: Option<String>, env_name: &str) -> anyhow::Result<Option<bool>> {
     let Some(raw) = sanitize_optional_env_value(value) else {
         return Ok(None);
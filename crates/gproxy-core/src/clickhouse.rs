@@ -0,0 +1,339 @@
+//! Batched ClickHouse writer for request and usage telemetry.
+//!
+//! Rows go over the ClickHouse HTTP interface as `JSONEachRow` inserts. The sink never
+//! blocks the request path: when the buffer is full, rows are dropped and counted.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime};
+
+use bytes::Bytes;
+use serde_json::json;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+use tokio::sync::mpsc;
+
+use gproxy_provider_core::{
+    DownstreamEvent, Event, EventSink, Headers, HttpMethod, UpstreamEvent, UpstreamHttpRequest,
+};
+
+use crate::upstream_client::{UpstreamClient, UpstreamClientConfig, WreqUpstreamClient};
+
+const DOWNSTREAM_TABLE: &str = "gproxy_downstream_requests";
+const UPSTREAM_TABLE: &str = "gproxy_upstream_requests";
+const USAGE_TABLE: &str = "gproxy_upstream_usages";
+
+const CREATE_TABLES: [&str; 3] = [
+    "CREATE TABLE IF NOT EXISTS gproxy_downstream_requests (
+        at DateTime64(3, 'UTC'),
+        trace_id Nullable(String),
+        user_id Nullable(Int64),
+        user_key_id Nullable(Int64),
+        request_method LowCardinality(String),
+        request_path String,
+        response_status Nullable(UInt16),
+        latency_ms Nullable(UInt64),
+        request_bytes UInt64,
+        response_bytes UInt64,
+        tags Array(String),
+        client_ip Nullable(String),
+        country LowCardinality(Nullable(String)),
+        asn Nullable(UInt32)
+    ) ENGINE = MergeTree ORDER BY at",
+    "CREATE TABLE IF NOT EXISTS gproxy_upstream_requests (
+        at DateTime64(3, 'UTC'),
+        trace_id Nullable(String),
+        user_id Nullable(Int64),
+        user_key_id Nullable(Int64),
+        provider LowCardinality(String),
+        credential_id Nullable(Int64),
+        internal Bool,
+        attempt_no UInt32,
+        operation LowCardinality(String),
+        model Nullable(String),
+        request_method LowCardinality(String),
+        request_path String,
+        response_status Nullable(UInt16),
+        error_kind Nullable(String),
+        error_message Nullable(String),
+        latency_ms Nullable(UInt64),
+        tags Array(String)
+    ) ENGINE = MergeTree ORDER BY (provider, at)",
+    "CREATE TABLE IF NOT EXISTS gproxy_upstream_usages (
+        at DateTime64(3, 'UTC'),
+        trace_id Nullable(String),
+        user_id Nullable(Int64),
+        user_key_id Nullable(Int64),
+        provider LowCardinality(String),
+        credential_id Nullable(Int64),
+        operation LowCardinality(String),
+        model Nullable(String),
+        input_tokens Nullable(UInt32),
+        output_tokens Nullable(UInt32),
+        cache_read_input_tokens Nullable(UInt32),
+        cache_creation_input_tokens Nullable(UInt32),
+        tags Array(String),
+        experiment Nullable(String),
+        experiment_arm Nullable(String)
+    ) ENGINE = MergeTree ORDER BY (provider, at)",
+];
+
+/// Batching knobs for [`ClickHouseSink`].
+#[derive(Debug, Clone)]
+pub struct ClickHouseConfig {
+    /// HTTP endpoint, e.g. `http://localhost:8123/?database=gproxy&user=default&password=...`.
+    pub url: String,
+    /// Rows per table that trigger an immediate insert.
+    pub batch_rows: usize,
+    /// Longest time a row waits in the buffer.
+    pub flush_interval: Duration,
+    /// Rows queued between the event bus and the writer task.
+    pub queue_capacity: usize,
+}
+
+impl ClickHouseConfig {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            batch_rows: 1000,
+            flush_interval: Duration::from_secs(2),
+            queue_capacity: 65536,
+        }
+    }
+}
+
+struct Row {
+    table: &'static str,
+    line: String,
+}
+
+/// Event sink writing downstream requests, upstream attempts and usage rows to ClickHouse.
+pub struct ClickHouseSink {
+    tx: mpsc::Sender<Row>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl ClickHouseSink {
+    /// Creates the tables if needed and starts the writer task. Must run inside a Tokio runtime.
+    pub fn spawn(config: ClickHouseConfig) -> anyhow::Result<Self> {
+        let client: Arc<dyn UpstreamClient> =
+            Arc::new(WreqUpstreamClient::new(UpstreamClientConfig::default())?);
+        Ok(Self::spawn_with_client(config, client))
+    }
+
+    pub fn spawn_with_client(config: ClickHouseConfig, client: Arc<dyn UpstreamClient>) -> Self {
+        let (tx, rx) = mpsc::channel(config.queue_capacity.max(1));
+        tokio::spawn(run_writer(config, client, rx));
+        Self {
+            tx,
+            dropped: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Rows dropped because the writer fell behind.
+    pub fn dropped_rows(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    fn enqueue(&self, table: &'static str, value: serde_json::Value) {
+        let row = Row {
+            table,
+            line: value.to_string(),
+        };
+        if self.tx.try_send(row).is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+impl EventSink for ClickHouseSink {
+    fn write<'a>(&'a self, event: &'a Event) -> Pin<Box<dyn Future<Output = ()> + Send + 'a>> {
+        Box::pin(async move {
+            match event {
+                Event::Downstream(ev) => self.enqueue(DOWNSTREAM_TABLE, downstream_row(ev)),
+                Event::Upstream(ev) => {
+                    self.enqueue(UPSTREAM_TABLE, upstream_row(ev));
+                    if let Some(row) = usage_row(ev) {
+                        self.enqueue(USAGE_TABLE, row);
+                    }
+                }
+                // Availability transitions are low volume and stay in the main storage.
+                Event::Operational(_) => {}
+            }
+        })
+    }
+}
+
+async fn run_writer(
+    config: ClickHouseConfig,
+    client: Arc<dyn UpstreamClient>,
+    mut rx: mpsc::Receiver<Row>,
+) {
+    for statement in CREATE_TABLES {
+        if let Err(err) = execute(&config.url, client.as_ref(), statement, None).await {
+            gproxy_common::log_error!("clickhouse", "create table: {err}");
+        }
+    }
+
+    let batch_rows = config.batch_rows.max(1);
+    let mut buffers: [(&'static str, Vec<String>); 3] = [
+        (DOWNSTREAM_TABLE, Vec::new()),
+        (UPSTREAM_TABLE, Vec::new()),
+        (USAGE_TABLE, Vec::new()),
+    ];
+    let mut ticker = tokio::time::interval(config.flush_interval);
+    loop {
+        tokio::select! {
+            row = rx.recv() => {
+                let Some(row) = row else {
+                    break;
+                };
+                let Some((table, lines)) = buffers.iter_mut().find(|(t, _)| *t == row.table)
+                else {
+                    continue;
+                };
+                lines.push(row.line);
+                if lines.len() >= batch_rows {
+                    flush(&config.url, client.as_ref(), table, lines).await;
+                }
+            }
+            _ = ticker.tick() => {
+                for (table, lines) in buffers.iter_mut() {
+                    flush(&config.url, client.as_ref(), table, lines).await;
+                }
+            }
+        }
+    }
+    for (table, lines) in buffers.iter_mut() {
+        flush(&config.url, client.as_ref(), table, lines).await;
+    }
+}
+
+async fn flush(url: &str, client: &dyn UpstreamClient, table: &str, lines: &mut Vec<String>) {
+    if lines.is_empty() {
+        return;
+    }
+    let body = lines.join("\n");
+    let count = lines.len();
+    lines.clear();
+    let statement = format!("INSERT INTO {table} FORMAT JSONEachRow");
+    if let Err(err) = execute(url, client, &statement, Some(body)).await {
+        gproxy_common::log_error!("clickhouse", "insert into {table} ({count} rows): {err}");
+    }
+}
+
+async fn execute(
+    url: &str,
+    client: &dyn UpstreamClient,
+    statement: &str,
+    body: Option<String>,
+) -> Result<(), String> {
+    let req = UpstreamHttpRequest {
+        method: HttpMethod::Post,
+        url: statement_url(url, statement),
+        headers: Headers::new(),
+        body: Some(Bytes::from(body.unwrap_or_default())),
+        is_stream: false,
+    };
+    let resp = client.send(req).await.map_err(|err| format!("{err:?}"))?;
+    if (200..300).contains(&resp.status) {
+        Ok(())
+    } else {
+        Err(format!("status {}", resp.status))
+    }
+}
+
+/// Appends the statement (and lenient timestamp parsing) to the configured endpoint.
+fn statement_url(url: &str, statement: &str) -> String {
+    let params = serde_urlencoded::to_string([
+        ("query", statement),
+        ("date_time_input_format", "best_effort"),
+    ])
+    .unwrap_or_default();
+    let separator = if url.contains('?') { '&' } else { '?' };
+    format!("{url}{separator}{params}")
+}
+
+fn format_at(at: SystemTime) -> Option<String> {
+    OffsetDateTime::from(at).format(&Rfc3339).ok()
+}
+
+fn downstream_row(ev: &DownstreamEvent) -> serde_json::Value {
+    json!({
+        "at": format_at(ev.at),
+        "trace_id": ev.trace_id,
+        "user_id": ev.user_id,
+        "user_key_id": ev.user_key_id,
+        "request_method": ev.request_method,
+        "request_path": ev.request_path,
+        "response_status": ev.response_status,
+        "latency_ms": ev.latency_ms,
+        "request_bytes": ev.request_body.as_ref().map_or(0, Vec::len),
+        "response_bytes": ev.response_body.as_ref().map_or(0, Vec::len),
+        "tags": ev.tags,
+        "client_ip": ev.client_ip,
+        "country": ev.country,
+        "asn": ev.asn,
+    })
+}
+
+fn upstream_row(ev: &UpstreamEvent) -> serde_json::Value {
+    json!({
+        "at": format_at(ev.at),
+        "trace_id": ev.trace_id,
+        "user_id": ev.user_id,
+        "user_key_id": ev.user_key_id,
+        "provider": ev.provider,
+        "credential_id": ev.credential_id,
+        "internal": ev.internal,
+        "attempt_no": ev.attempt_no,
+        "operation": ev.operation,
+        "model": ev.model,
+        "request_method": ev.request_method,
+        "request_path": ev.request_path,
+        "response_status": ev.response_status,
+        "error_kind": ev.error_kind,
+        "error_message": ev.error_message,
+        "latency_ms": ev.latency_ms,
+        "tags": ev.tags,
+    })
+}
+
+fn usage_row(ev: &UpstreamEvent) -> Option<serde_json::Value> {
+    let usage = ev.usage.as_ref()?;
+    Some(json!({
+        "at": format_at(ev.at),
+        "trace_id": ev.trace_id,
+        "user_id": ev.user_id,
+        "user_key_id": ev.user_key_id,
+        "provider": ev.provider,
+        "credential_id": ev.credential_id,
+        "operation": ev.operation,
+        "model": ev.model,
+        "input_tokens": usage.input_tokens,
+        "output_tokens": usage.output_tokens,
+        "cache_read_input_tokens": usage.cache_read_input_tokens,
+        "cache_creation_input_tokens": usage.cache_creation_input_tokens,
+        "tags": ev.tags,
+        "experiment": ev.experiment,
+        "experiment_arm": ev.experiment_arm,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::statement_url;
+
+    #[test]
+    fn statement_is_appended_to_existing_query() {
+        let url = statement_url("http://ch:8123/?database=gproxy", "SELECT 1");
+        assert_eq!(
+            url,
+            "http://ch:8123/?database=gproxy&query=SELECT+1&date_time_input_format=best_effort"
+        );
+        let url = statement_url("http://ch:8123/", "SELECT 1");
+        assert!(url.starts_with("http://ch:8123/?query=SELECT+1"));
+    }
+}
@@ -0,0 +1,167 @@
+//! The `provider_incidents` job: stores the incidents the proxy engine opens when a
+//! provider runs out of credentials, resolves each once one of its credentials is
+//! available again, and posts both transitions to `alert_webhook_url`.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use bytes::Bytes;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+use gproxy_provider_core::{HttpMethod, UpstreamHttpRequest};
+use gproxy_storage::{IncidentRecord, Storage};
+
+use crate::state::AppState;
+use crate::upstream_client::UpstreamClient;
+
+const INCIDENT_INTERVAL: Duration = Duration::from_secs(10);
+const INCIDENT_JITTER: Duration = Duration::from_secs(2);
+/// Most incidents a previous run may have left open that are closed on the first run.
+const STALE_SCAN_LIMIT: usize = 1_000;
+
+/// Checks open incidents every ten seconds.
+pub fn register_provider_incidents(
+    state: &Arc<AppState>,
+    storage: Arc<dyn Storage>,
+    client: Arc<dyn UpstreamClient>,
+) {
+    let app = state.clone();
+    let first_run = Arc::new(AtomicBool::new(true));
+    state.jobs.register(
+        "provider_incidents",
+        INCIDENT_INTERVAL,
+        INCIDENT_JITTER,
+        move || {
+            let app = app.clone();
+            let storage = storage.clone();
+            let client = client.clone();
+            let first_run = first_run.clone();
+            async move {
+                let now = OffsetDateTime::now_utc();
+                if first_run.swap(false, Ordering::Relaxed) {
+                    // Nothing tracks what a previous run left open any more.
+                    let stale = storage
+                        .list_incidents(None, true, STALE_SCAN_LIMIT)
+                        .await
+                        .map_err(|err| format!("list open incidents: {err}"))?;
+                    for mut record in stale {
+                        record.ended_at = Some(now);
+                        storage
+                            .update_incident(&record)
+                            .await
+                            .map_err(|err| format!("close incident {}: {err}", record.id))?;
+                    }
+                }
+
+                let runtimes = app.providers.load_full();
+                for provider in app.incidents.open_providers() {
+                    let recovered = match runtimes.get(&provider) {
+                        Some(runtime) => {
+                            let availability = runtime.pool.availability(&provider).await;
+                            availability.credentials == 0
+                                || availability.unavailable < availability.credentials
+                        }
+                        None => true,
+                    };
+                    if recovered {
+                        app.incidents.resolve(&provider, now);
+                    }
+                }
+
+                let webhook = app.global.load().alert_webhook_url.clone();
+                let (mut opened, mut resolved) = (0, 0);
+                for mut record in app.incidents.take_changes() {
+                    if record.id == 0 {
+                        record.id = storage
+                            .append_incident(&record)
+                            .await
+                            .map_err(|err| format!("store incident: {err}"))?;
+                        app.incidents
+                            .stored(&record.provider, record.started_at, record.id);
+                        gproxy_common::log_warn!(
+                            "incidents",
+                            "provider {} has no available credential (incident {})",
+                            record.provider,
+                            record.id
+                        );
+                        opened += 1;
+                        notify(
+                            &client,
+                            webhook.as_deref(),
+                            "provider_incident_opened",
+                            &record,
+                        )
+                        .await;
+                    } else {
+                        storage
+                            .update_incident(&record)
+                            .await
+                            .map_err(|err| format!("update incident {}: {err}", record.id))?;
+                    }
+                    if record.ended_at.is_some() {
+                        gproxy_common::log_info!(
+                            "incidents",
+                            "provider {} recovered (incident {})",
+                            record.provider,
+                            record.id
+                        );
+                        resolved += 1;
+                        notify(
+                            &client,
+                            webhook.as_deref(),
+                            "provider_incident_resolved",
+                            &record,
+                        )
+                        .await;
+                    }
+                }
+                Ok(
+                    (opened + resolved > 0)
+                        .then(|| format!("opened {opened}, resolved {resolved}")),
+                )
+            }
+        },
+    );
+}
+
+/// The incident as the admin API and the alert webhook show it.
+pub fn incident_json(record: &IncidentRecord) -> serde_json::Value {
+    serde_json::json!({
+        "id": record.id,
+        "provider": record.provider,
+        "started_at": record.started_at.format(&Rfc3339).ok(),
+        "ended_at": record.ended_at.and_then(|at| at.format(&Rfc3339).ok()),
+        "duration_secs": record
+            .ended_at
+            .map(|at| (at - record.started_at).whole_seconds()),
+        "models": record.models,
+        "error_samples": record.error_samples,
+        "refused_requests": record.refused_requests,
+    })
+}
+
+async fn notify(
+    client: &Arc<dyn UpstreamClient>,
+    webhook: Option<&str>,
+    event: &str,
+    record: &IncidentRecord,
+) {
+    let Some(url) = webhook else {
+        return;
+    };
+    let mut body = incident_json(record);
+    body["event"] = serde_json::json!(event);
+    body["at"] = serde_json::json!(OffsetDateTime::now_utc().format(&Rfc3339).ok());
+    let req = UpstreamHttpRequest {
+        method: HttpMethod::Post,
+        url: url.to_string(),
+        headers: vec![("content-type".to_string(), "application/json".to_string())].into(),
+        body: Some(Bytes::from(body.to_string())),
+        is_stream: false,
+    };
+    if let Err(err) = client.send(req).await {
+        gproxy_common::log_warn!("alert_webhook", "{err:?}");
+    }
+}
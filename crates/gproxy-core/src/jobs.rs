@@ -0,0 +1,297 @@
+//! Periodic background jobs: each job runs on its own interval plus a random jitter,
+//! can be triggered on demand from the admin API, and records every run in storage.
+
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::Duration;
+
+use time::OffsetDateTime;
+
+use gproxy_storage::{JobRunRecord, Storage};
+
+/// Outcome of one run: an optional summary on success, an error message on failure.
+pub type JobResult = Result<Option<String>, String>;
+pub type JobFuture = Pin<Box<dyn Future<Output = JobResult> + Send>>;
+type JobFn = Arc<dyn Fn() -> JobFuture + Send + Sync>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobTrigger {
+    Schedule,
+    Manual,
+}
+
+impl JobTrigger {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            JobTrigger::Schedule => "schedule",
+            JobTrigger::Manual => "manual",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerError {
+    NotFound,
+    AlreadyRunning,
+}
+
+struct LastRun {
+    trigger: JobTrigger,
+    started_at: OffsetDateTime,
+    finished_at: OffsetDateTime,
+    ok: bool,
+    message: Option<String>,
+}
+
+struct JobEntry {
+    name: String,
+    interval: Duration,
+    jitter: Duration,
+    run: JobFn,
+    running: AtomicBool,
+    runs: AtomicU64,
+    failures: AtomicU64,
+    last: Mutex<Option<LastRun>>,
+    next_run_at: Mutex<Option<OffsetDateTime>>,
+}
+
+impl JobEntry {
+    fn next_delay(&self) -> Duration {
+        let jitter_ms = self.jitter.as_millis() as u64;
+        if jitter_ms == 0 {
+            return self.interval;
+        }
+        self.interval + Duration::from_millis(rand::random_range(0..=jitter_ms))
+    }
+
+    fn status_json(&self) -> serde_json::Value {
+        let last = self.last.lock().unwrap_or_else(|e| e.into_inner());
+        let next_run_at = *self.next_run_at.lock().unwrap_or_else(|e| e.into_inner());
+        serde_json::json!({
+            "name": self.name,
+            "interval_secs": self.interval.as_secs(),
+            "jitter_secs": self.jitter.as_secs(),
+            "running": self.running.load(Ordering::Relaxed),
+            "runs": self.runs.load(Ordering::Relaxed),
+            "failures": self.failures.load(Ordering::Relaxed),
+            "next_run_at": next_run_at,
+            "last_run": last.as_ref().map(|run| serde_json::json!({
+                "trigger": run.trigger.as_str(),
+                "started_at": run.started_at,
+                "finished_at": run.finished_at,
+                "ok": run.ok,
+                "message": run.message,
+            })),
+        })
+    }
+}
+
+/// Registry of periodic jobs. Jobs may be registered before or after [`JobScheduler::start`];
+/// run history is only persisted once the scheduler has been started with a storage.
+#[derive(Default)]
+pub struct JobScheduler {
+    jobs: RwLock<BTreeMap<String, Arc<JobEntry>>>,
+    storage: OnceLock<Arc<dyn Storage>>,
+}
+
+impl JobScheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a job running every `interval` plus up to `jitter`. Returns `false` when a job
+    /// with this name already exists.
+    pub fn register<F, Fut>(
+        self: &Arc<Self>,
+        name: &str,
+        interval: Duration,
+        jitter: Duration,
+        run: F,
+    ) -> bool
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = JobResult> + Send + 'static,
+    {
+        let entry = Arc::new(JobEntry {
+            name: name.to_string(),
+            interval,
+            jitter,
+            run: Arc::new(move || Box::pin(run()) as JobFuture),
+            running: AtomicBool::new(false),
+            runs: AtomicU64::new(0),
+            failures: AtomicU64::new(0),
+            last: Mutex::new(None),
+            next_run_at: Mutex::new(None),
+        });
+        {
+            let mut jobs = self.jobs.write().unwrap_or_else(|e| e.into_inner());
+            if jobs.contains_key(name) {
+                return false;
+            }
+            jobs.insert(name.to_string(), entry.clone());
+        }
+        if self.storage.get().is_some() {
+            self.spawn_loop(entry);
+        }
+        true
+    }
+
+    /// Starts the schedule of every registered job; later registrations start immediately.
+    pub fn start(self: &Arc<Self>, storage: Arc<dyn Storage>) {
+        if self.storage.set(storage).is_err() {
+            return;
+        }
+        let jobs: Vec<_> = self
+            .jobs
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .values()
+            .cloned()
+            .collect();
+        for entry in jobs {
+            self.spawn_loop(entry);
+        }
+    }
+
+    /// Runs a job now, outside its schedule; the run continues in the background.
+    pub fn trigger(self: &Arc<Self>, name: &str) -> Result<(), TriggerError> {
+        let entry = self.entry(name).ok_or(TriggerError::NotFound)?;
+        if entry.running.swap(true, Ordering::AcqRel) {
+            return Err(TriggerError::AlreadyRunning);
+        }
+        let scheduler = self.clone();
+        tokio::spawn(async move { scheduler.execute(&entry, JobTrigger::Manual).await });
+        Ok(())
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.entry(name).is_some()
+    }
+
+    pub fn status_json(&self) -> Vec<serde_json::Value> {
+        self.jobs
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .values()
+            .map(|entry| entry.status_json())
+            .collect()
+    }
+
+    fn entry(&self, name: &str) -> Option<Arc<JobEntry>> {
+        self.jobs
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(name)
+            .cloned()
+    }
+
+    fn spawn_loop(self: &Arc<Self>, entry: Arc<JobEntry>) {
+        let scheduler = self.clone();
+        tokio::spawn(async move {
+            loop {
+                let delay = entry.next_delay();
+                *entry.next_run_at.lock().unwrap_or_else(|e| e.into_inner()) =
+                    Some(OffsetDateTime::now_utc() + delay);
+                tokio::time::sleep(delay).await;
+                // A manual run still in flight takes the place of this tick.
+                if entry.running.swap(true, Ordering::AcqRel) {
+                    continue;
+                }
+                scheduler.execute(&entry, JobTrigger::Schedule).await;
+            }
+        });
+    }
+
+    /// Runs the job; the caller has already set `entry.running`.
+    async fn execute(&self, entry: &JobEntry, trigger: JobTrigger) {
+        let started_at = OffsetDateTime::now_utc();
+        let result = (entry.run)().await;
+        let finished_at = OffsetDateTime::now_utc();
+        entry.running.store(false, Ordering::Release);
+        entry.runs.fetch_add(1, Ordering::Relaxed);
+        let (ok, message) = match result {
+            Ok(message) => {
+                gproxy_common::log_debug!(
+                    "jobs",
+                    "job {} ({}) ok: {}",
+                    entry.name,
+                    trigger.as_str(),
+                    message.as_deref().unwrap_or("-")
+                );
+                (true, message)
+            }
+            Err(err) => {
+                entry.failures.fetch_add(1, Ordering::Relaxed);
+                gproxy_common::log_error!("jobs", "job {}: {err}", entry.name);
+                (false, Some(err))
+            }
+        };
+        *entry.last.lock().unwrap_or_else(|e| e.into_inner()) = Some(LastRun {
+            trigger,
+            started_at,
+            finished_at,
+            ok,
+            message: message.clone(),
+        });
+        if let Some(storage) = self.storage.get() {
+            let record = JobRunRecord {
+                id: 0,
+                job: entry.name.clone(),
+                trigger: trigger.as_str().to_string(),
+                started_at,
+                finished_at,
+                ok,
+                message,
+            };
+            if let Err(err) = storage.append_job_run(&record).await {
+                gproxy_common::log_error!("jobs", "record job run {}: {err}", entry.name);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gproxy_storage::MemoryStorage;
+
+    #[tokio::test]
+    async fn manual_trigger_records_run() {
+        let storage: Arc<dyn Storage> = Arc::new(MemoryStorage::new());
+        let scheduler = Arc::new(JobScheduler::new());
+        assert!(
+            scheduler.register("flaky", Duration::from_secs(3600), Duration::ZERO, || {
+                async { Err("boom".to_string()) }
+            })
+        );
+        assert!(
+            !scheduler.register("flaky", Duration::from_secs(1), Duration::ZERO, || {
+                async { Ok(None) }
+            })
+        );
+        scheduler.start(storage.clone());
+
+        assert_eq!(scheduler.trigger("missing"), Err(TriggerError::NotFound));
+        scheduler.trigger("flaky").unwrap();
+        for _ in 0..100 {
+            if !storage
+                .list_job_runs(Some("flaky"), 10)
+                .await
+                .unwrap()
+                .is_empty()
+            {
+                break;
+            }
+            tokio::task::yield_now().await;
+        }
+        let runs = storage.list_job_runs(Some("flaky"), 10).await.unwrap();
+        assert_eq!(runs.len(), 1);
+        assert!(!runs[0].ok);
+        assert_eq!(runs[0].trigger, "manual");
+        assert_eq!(runs[0].message.as_deref(), Some("boom"));
+        assert_eq!(scheduler.status_json()[0]["failures"], 1);
+    }
+}
@@ -1,4 +1,10 @@
 pub mod bootstrap;
+pub mod clickhouse;
+pub mod incidents;
+pub mod jobs;
+pub mod log_views;
+pub mod org_budgets;
 pub mod proxy_engine;
 pub mod state;
+pub mod temporary_keys;
 pub mod upstream_client;
@@ -0,0 +1,259 @@
+//! Saved log views: named `/admin/logs` filters over a trailing window. A view with an
+//! `alert_threshold` is checked periodically and posted to `alert_webhook_url` when the
+//! rows it matches within its window reach the threshold, once per crossing.
+
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+use gproxy_provider_core::{HttpMethod, UpstreamHttpRequest};
+use gproxy_storage::{LogQueryFilter, LogRecordKind, LogViewRow, Storage, StorageSnapshot};
+
+use crate::state::AppState;
+use crate::upstream_client::UpstreamClient;
+
+/// Largest `alert_threshold`; counting a view reads up to this many rows.
+pub const MAX_ALERT_THRESHOLD: i64 = 10_000;
+/// Longest window a view may cover.
+pub const MAX_WINDOW_SECS: i64 = 30 * 24 * 3600;
+
+const ALERT_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+const ALERT_CHECK_JITTER: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogViewKind {
+    All,
+    Upstream,
+    Downstream,
+}
+
+/// The stored filter: the filter parameters of `/admin/logs`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LogViewFilter {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<LogViewKind>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credential_id: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_key_id: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org_id: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operation: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path_contains: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status_min: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status_max: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub country: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asn: Option<i64>,
+}
+
+impl LogViewFilter {
+    pub fn parse(value: &JsonValue) -> Result<Self, String> {
+        let filter: Self = serde_json::from_value(value.clone()).map_err(|err| err.to_string())?;
+        if let (Some(min), Some(max)) = (filter.status_min, filter.status_max)
+            && max < min
+        {
+            return Err("`status_max` must be >= `status_min`".to_string());
+        }
+        Ok(filter)
+    }
+
+    /// The storage query for rows between `from` and `to`. `org_id` (or the caller's
+    /// `scope_org_id`, which wins) limits the rows to the organization's users.
+    pub fn query(
+        &self,
+        snapshot: &StorageSnapshot,
+        scope_org_id: Option<i64>,
+        from: OffsetDateTime,
+        to: OffsetDateTime,
+        limit: usize,
+    ) -> LogQueryFilter {
+        let user_ids = scope_org_id.or(self.org_id).map(|org_id| {
+            snapshot
+                .users
+                .iter()
+                .filter(|u| u.org_id == Some(org_id))
+                .map(|u| u.id)
+                .collect()
+        });
+        LogQueryFilter {
+            from,
+            to,
+            kind: match self.kind {
+                None | Some(LogViewKind::All) => None,
+                Some(LogViewKind::Upstream) => Some(LogRecordKind::Upstream),
+                Some(LogViewKind::Downstream) => Some(LogRecordKind::Downstream),
+            },
+            provider: self.provider.clone(),
+            credential_id: self.credential_id,
+            user_id: self.user_id,
+            user_ids,
+            user_key_id: self.user_key_id,
+            trace_id: None,
+            operation: self.operation.clone(),
+            tag: self.tag.clone(),
+            request_path_contains: self.path_contains.clone(),
+            status_min: self.status_min,
+            status_max: self.status_max,
+            country: self.country.as_ref().map(|code| code.to_ascii_uppercase()),
+            asn: self.asn,
+            limit,
+            cursor: None,
+            include_body: false,
+        }
+    }
+}
+
+/// Views currently at or over their threshold; a view alerts again only after it dropped
+/// below.
+#[derive(Default)]
+pub struct LogViewAlerts {
+    firing: Mutex<HashSet<String>>,
+}
+
+impl LogViewAlerts {
+    /// Records the count of a view; `true` when it just reached the threshold.
+    pub fn observe(&self, view: &str, count: usize, threshold: usize) -> bool {
+        let mut firing = self.firing.lock().unwrap_or_else(|e| e.into_inner());
+        if count >= threshold {
+            firing.insert(view.to_string())
+        } else {
+            firing.remove(view);
+            false
+        }
+    }
+
+    /// Forgets views that no longer exist or no longer alert.
+    fn retain(&self, views: &[LogViewRow]) {
+        self.firing
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|name| {
+                views
+                    .iter()
+                    .any(|v| v.name == *name && v.alert_threshold.is_some())
+            });
+    }
+}
+
+/// Checks every alerting view once a minute.
+pub fn register_log_view_alerts(
+    state: &Arc<AppState>,
+    storage: Arc<dyn Storage>,
+    client: Arc<dyn UpstreamClient>,
+) {
+    let alerts = Arc::new(LogViewAlerts::default());
+    let app = state.clone();
+    state.jobs.register(
+        "log_view_alerts",
+        ALERT_CHECK_INTERVAL,
+        ALERT_CHECK_JITTER,
+        move || {
+            let alerts = alerts.clone();
+            let snapshot = app.snapshot.load_full();
+            let webhook = app.global.load().alert_webhook_url.clone();
+            let storage = storage.clone();
+            let client = client.clone();
+            async move {
+                alerts.retain(&snapshot.log_views);
+                let now = OffsetDateTime::now_utc();
+                let mut fired = Vec::new();
+                for view in &snapshot.log_views {
+                    let Some(threshold) = view.alert_threshold else {
+                        continue;
+                    };
+                    let threshold =
+                        usize::try_from(threshold.clamp(1, MAX_ALERT_THRESHOLD)).unwrap_or(1);
+                    let Ok(filter) = LogViewFilter::parse(&view.filter_json) else {
+                        continue;
+                    };
+                    let from = now - time::Duration::seconds(view.window_secs);
+                    let query = filter.query(&snapshot, None, from, now, threshold);
+                    let count = storage
+                        .query_logs(query)
+                        .await
+                        .map_err(|err| format!("count log view {}: {err}", view.name))?
+                        .rows
+                        .len();
+                    if !alerts.observe(&view.name, count, threshold) {
+                        continue;
+                    }
+                    fired.push(view.name.clone());
+                    let Some(url) = webhook.clone() else {
+                        continue;
+                    };
+                    let body = serde_json::json!({
+                        "event": "log_view_threshold",
+                        "at": now.format(&Rfc3339).ok(),
+                        "view": view.name,
+                        "threshold": threshold,
+                        "window_secs": view.window_secs,
+                        "filter": view.filter_json,
+                    });
+                    let req = UpstreamHttpRequest {
+                        method: HttpMethod::Post,
+                        url,
+                        headers: vec![("content-type".to_string(), "application/json".to_string())]
+                            .into(),
+                        body: Some(Bytes::from(body.to_string())),
+                        is_stream: false,
+                    };
+                    if let Err(err) = client.send(req).await {
+                        gproxy_common::log_warn!("alert_webhook", "{err:?}");
+                    }
+                }
+                Ok((!fired.is_empty()).then(|| format!("fired: {}", fired.join(", "))))
+            }
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filters_reject_unknown_keys_and_bad_ranges() {
+        let filter = LogViewFilter::parse(&serde_json::json!({
+            "kind": "upstream",
+            "provider": "claude",
+            "status_min": 500,
+        }))
+        .unwrap();
+        assert_eq!(filter.kind, Some(LogViewKind::Upstream));
+        assert!(LogViewFilter::parse(&serde_json::json!({ "limit": 10 })).is_err());
+        assert!(
+            LogViewFilter::parse(&serde_json::json!({ "status_min": 500, "status_max": 400 }))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn alerts_fire_once_per_crossing() {
+        let alerts = LogViewAlerts::default();
+        assert!(!alerts.observe("5xx", 2, 3));
+        assert!(alerts.observe("5xx", 3, 3));
+        assert!(!alerts.observe("5xx", 5, 3));
+        assert!(!alerts.observe("5xx", 0, 3));
+        assert!(alerts.observe("5xx", 4, 3));
+    }
+}
@@ -0,0 +1,66 @@
+//! The `org_budgets` job: reads each budgeted organization's token usage for the
+//! current month back from storage, so `monthly_token_budget` holds across restarts and
+//! counts traffic served by other replicas.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use time::OffsetDateTime;
+
+use gproxy_storage::{Storage, UsageAggregateFilter};
+
+use crate::state::{AppState, month_start};
+
+const REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+const REFRESH_JITTER: Duration = Duration::from_secs(5);
+
+/// Refreshes the month's usage of every organization with a budget once a minute.
+pub fn register_org_budgets(state: &Arc<AppState>, storage: Arc<dyn Storage>) {
+    let app = state.clone();
+    state
+        .jobs
+        .register("org_budgets", REFRESH_INTERVAL, REFRESH_JITTER, move || {
+            let app = app.clone();
+            let storage = storage.clone();
+            async move {
+                let now = OffsetDateTime::now_utc();
+                let snapshot = app.snapshot.load_full();
+                let mut refreshed = 0;
+                for org in snapshot
+                    .organizations
+                    .iter()
+                    .filter(|o| o.monthly_token_budget.is_some())
+                {
+                    let user_ids = snapshot
+                        .users
+                        .iter()
+                        .filter(|u| u.org_id == Some(org.id))
+                        .map(|u| u.id)
+                        .collect();
+                    let usage = storage
+                        .aggregate_usage_tokens(UsageAggregateFilter {
+                            from: month_start(now),
+                            to: now,
+                            provider: None,
+                            credential_id: None,
+                            user_ids: Some(user_ids),
+                            model: None,
+                            model_contains: None,
+                            tag: None,
+                            experiment: None,
+                            experiment_arm: None,
+                        })
+                        .await
+                        .map_err(|err| format!("org {} usage: {err}", org.id))?;
+                    let tokens = usage.input_tokens.saturating_add(usage.output_tokens);
+                    app.org_limits.set_month_tokens(
+                        org.id,
+                        now,
+                        u64::try_from(tokens).unwrap_or(0),
+                    );
+                    refreshed += 1;
+                }
+                Ok((refreshed > 0).then(|| format!("refreshed {refreshed}")))
+            }
+        });
+}
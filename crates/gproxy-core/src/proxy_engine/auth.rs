@@ -0,0 +1,737 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use gproxy_provider_core::{
+    CallerInfo, Headers, HttpMethod, UpstreamHttpRequest, UpstreamTimeouts, header_get,
+};
+use gproxy_storage::{StorageSnapshot, UserKeyRow};
+use time::OffsetDateTime;
+
+use crate::state::{AppState, KeyLimits};
+
+use super::key_scope::KeyScope;
+use super::mcp::McpPolicy;
+use super::moderation::ModerationPolicy;
+use super::origins::AllowedOrigins;
+use crate::upstream_client::{SendOptions, UpstreamClient};
+
+use super::ProxyAuth;
+
+pub type AuthFuture<'a> = Pin<Box<dyn Future<Output = AuthDecision> + Send + 'a>>;
+
+/// What a downstream request presented, captured before its auth headers are stripped.
+#[derive(Debug, Clone, Default)]
+pub struct AuthRequest {
+    /// Key from `authorization`, `x-api-key`, `x-goog-api-key` or `?key=`.
+    pub api_key: Option<String>,
+    pub method: String,
+    pub path: String,
+    pub headers: Headers,
+    pub client_ip: Option<String>,
+}
+
+/// Outcome of one provider. `Allow` and `Deny` end a chain; `Pass` hands the request to
+/// the next provider.
+#[derive(Debug)]
+pub enum AuthDecision {
+    Allow(Box<ProxyAuth>),
+    Deny,
+    Pass,
+}
+
+/// Resolves a downstream request to the caller it belongs to. A request no provider
+/// allows is rejected with 401.
+pub trait AuthProvider: Send + Sync {
+    fn authenticate<'a>(&'a self, state: &'a AppState, request: &'a AuthRequest) -> AuthFuture<'a>;
+}
+
+/// Providers asked in order until one allows or denies the request.
+pub struct AuthChain {
+    providers: Vec<Arc<dyn AuthProvider>>,
+}
+
+impl AuthChain {
+    pub fn new(providers: Vec<Arc<dyn AuthProvider>>) -> Self {
+        Self { providers }
+    }
+}
+
+impl AuthProvider for AuthChain {
+    fn authenticate<'a>(&'a self, state: &'a AppState, request: &'a AuthRequest) -> AuthFuture<'a> {
+        Box::pin(async move {
+            for provider in &self.providers {
+                match provider.authenticate(state, request).await {
+                    AuthDecision::Pass => continue,
+                    decision => return decision,
+                }
+            }
+            AuthDecision::Deny
+        })
+    }
+}
+
+/// The default: enabled user keys of enabled users (and organizations) from the snapshot.
+/// Unknown keys pass; known keys that are disabled are denied.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SnapshotAuthProvider;
+
+impl AuthProvider for SnapshotAuthProvider {
+    fn authenticate<'a>(&'a self, state: &'a AppState, request: &'a AuthRequest) -> AuthFuture<'a> {
+        let decision = match request.api_key.as_deref() {
+            Some(api_key) => {
+                let snapshot = state.snapshot.load();
+                match snapshot.user_keys.iter().find(|k| k.api_key == api_key) {
+                    Some(key) => user_key_decision(&snapshot, key, request),
+                    None => AuthDecision::Pass,
+                }
+            }
+            None => AuthDecision::Pass,
+        };
+        Box::pin(std::future::ready(decision))
+    }
+}
+
+/// Response header naming the user key an external authorizer admitted the request as.
+pub const FORWARD_AUTH_USER_KEY_HEADER: &str = "x-gproxy-user-key-id";
+
+const FORWARD_AUTH_TIMEOUT_MS: u64 = 5_000;
+/// How long an authorizer's answer is reused for an identical forwarded request.
+pub const FORWARD_AUTH_CACHE_TTL: Duration = Duration::from_secs(10);
+/// Answers kept at most; expired ones are dropped first, then all of them.
+const FORWARD_AUTH_CACHE_MAX: usize = 4_096;
+
+/// Presented key plus every header sent to the authorizer (names lower-cased, sorted), so
+/// origin, forwarded client headers, method, path and client address all take part.
+type ForwardAuthCacheKey = (String, Vec<(String, String)>);
+
+/// What the authorizer said about a request it did not pass on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ForwardAuthAnswer {
+    /// Admitted as this user key.
+    Allow(i64),
+    Deny,
+}
+
+/// Asks an external HTTP authorizer: the incoming headers are sent with a `GET` to `url`
+/// (plus `x-forwarded-method`, `x-forwarded-uri` and `x-forwarded-for`). A 2xx answer
+/// allows the request as the user key in `x-gproxy-user-key-id`, 401/403 denies it, and
+/// anything else (including transport errors) passes it on.
+///
+/// Allow and deny answers for a request presenting a key are reused for
+/// [`FORWARD_AUTH_CACHE_TTL`] only when the authorizer would be sent exactly the same
+/// headers again, so a busy client does not cost one authorizer round trip per request
+/// while a decision based on the origin or client address is never served to another. The admitted key is still checked against
+/// the current snapshot every time. Requests without a key always ask the authorizer.
+pub struct ForwardAuthProvider {
+    url: String,
+    client: Arc<dyn UpstreamClient>,
+    cache: Mutex<HashMap<ForwardAuthCacheKey, (Instant, ForwardAuthAnswer)>>,
+}
+
+impl ForwardAuthProvider {
+    pub fn new(url: impl Into<String>, client: Arc<dyn UpstreamClient>) -> Self {
+        Self {
+            url: url.into(),
+            client,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn cached(&self, key: &ForwardAuthCacheKey) -> Option<ForwardAuthAnswer> {
+        let cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        let (at, answer) = cache.get(key)?;
+        (at.elapsed() < FORWARD_AUTH_CACHE_TTL).then_some(*answer)
+    }
+
+    fn remember(&self, key: ForwardAuthCacheKey, answer: ForwardAuthAnswer) {
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        if cache.len() >= FORWARD_AUTH_CACHE_MAX {
+            cache.retain(|_, (at, _)| at.elapsed() < FORWARD_AUTH_CACHE_TTL);
+            if cache.len() >= FORWARD_AUTH_CACHE_MAX {
+                cache.clear();
+            }
+        }
+        cache.insert(key, (Instant::now(), answer));
+    }
+
+    /// The authorizer's answer, or `None` when it passes the request on.
+    async fn ask(&self, forwarded: UpstreamHttpRequest) -> Option<ForwardAuthAnswer> {
+        let options = SendOptions {
+            timeouts: UpstreamTimeouts {
+                total_ms: Some(FORWARD_AUTH_TIMEOUT_MS),
+                ..UpstreamTimeouts::default()
+            },
+            ..SendOptions::default()
+        };
+        let resp = match self.client.send_with_options(forwarded, options).await {
+            Ok(resp) => resp,
+            Err(err) => {
+                gproxy_common::log_warn!("forward_auth", "{}: {err:?}", self.url);
+                return None;
+            }
+        };
+        match resp.status {
+            200..=299 => {
+                let Some(key_id) = header_get(&resp.headers, FORWARD_AUTH_USER_KEY_HEADER)
+                    .and_then(|v| v.trim().parse::<i64>().ok())
+                else {
+                    gproxy_common::log_warn!(
+                        "forward_auth",
+                        "{}: allowed without {FORWARD_AUTH_USER_KEY_HEADER}",
+                        self.url
+                    );
+                    return Some(ForwardAuthAnswer::Deny);
+                };
+                Some(ForwardAuthAnswer::Allow(key_id))
+            }
+            401 | 403 => Some(ForwardAuthAnswer::Deny),
+            _ => None,
+        }
+    }
+
+    fn build_request(&self, request: &AuthRequest) -> UpstreamHttpRequest {
+        let mut headers = request.headers.clone();
+        headers.retain(|name, _| !is_forward_auth_skipped_header(name));
+        headers.append("x-forwarded-method", request.method.clone());
+        headers.append("x-forwarded-uri", request.path.clone());
+        if let Some(ip) = &request.client_ip {
+            headers.append("x-forwarded-for", ip.clone());
+        }
+        UpstreamHttpRequest {
+            method: HttpMethod::Get,
+            url: self.url.clone(),
+            headers,
+            body: None,
+            is_stream: false,
+        }
+    }
+}
+
+impl AuthProvider for ForwardAuthProvider {
+    fn authenticate<'a>(&'a self, state: &'a AppState, request: &'a AuthRequest) -> AuthFuture<'a> {
+        Box::pin(async move {
+            let forwarded = self.build_request(request);
+            let cache_key = request
+                .api_key
+                .clone()
+                .map(|key| (key, forwarded_headers_key(&forwarded.headers)));
+            let answer = match cache_key.as_ref().and_then(|key| self.cached(key)) {
+                Some(answer) => answer,
+                None => {
+                    let Some(answer) = self.ask(forwarded).await else {
+                        return AuthDecision::Pass;
+                    };
+                    if let Some(key) = cache_key {
+                        self.remember(key, answer);
+                    }
+                    answer
+                }
+            };
+            let ForwardAuthAnswer::Allow(key_id) = answer else {
+                return AuthDecision::Deny;
+            };
+            let snapshot = state.snapshot.load();
+            match snapshot.user_keys.iter().find(|k| k.id == key_id) {
+                Some(key) => user_key_decision(&snapshot, key, request),
+                None => AuthDecision::Deny,
+            }
+        })
+    }
+}
+
+fn forwarded_headers_key(headers: &Headers) -> Vec<(String, String)> {
+    let mut key: Vec<_> = headers
+        .iter()
+        .map(|(name, value)| (name.to_ascii_lowercase(), value.clone()))
+        .collect();
+    key.sort();
+    key
+}
+
+fn is_forward_auth_skipped_header(name: &str) -> bool {
+    [
+        "host",
+        "content-length",
+        "content-type",
+        "content-encoding",
+        "transfer-encoding",
+        "connection",
+        "keep-alive",
+        "upgrade",
+        "te",
+        "trailer",
+    ]
+    .iter()
+    .any(|skip| name.eq_ignore_ascii_case(skip))
+}
+
+/// Admits a known key when it, its user and the user's organization are enabled, the
+/// key has not expired and the request comes from one of its allowed origins.
+fn user_key_decision(
+    snapshot: &StorageSnapshot,
+    key: &UserKeyRow,
+    request: &AuthRequest,
+) -> AuthDecision {
+    if !key.enabled
+        || key
+            .expires_at
+            .is_some_and(|at| at <= OffsetDateTime::now_utc())
+    {
+        return AuthDecision::Deny;
+    }
+    // A stored list that no longer parses admits no origin.
+    if let Some(allowed) = &key.allowed_origins
+        && !AllowedOrigins::from_json(allowed).is_ok_and(|allowed| allowed.allows(&request.headers))
+    {
+        return AuthDecision::Deny;
+    }
+    let Some(user) = snapshot
+        .users
+        .iter()
+        .find(|u| u.id == key.user_id && u.enabled)
+    else {
+        return AuthDecision::Deny;
+    };
+    let org = match user.org_id {
+        Some(org_id) => match snapshot
+            .organizations
+            .iter()
+            .find(|o| o.id == org_id && o.enabled)
+        {
+            Some(org) => Some(org),
+            None => return AuthDecision::Deny,
+        },
+        None => None,
+    };
+    // Keys without defaults of their own route like their organization.
+    let (default_provider, default_model) = match (&key.default_provider, org) {
+        (None, Some(org)) => (org.default_provider.clone(), org.default_model.clone()),
+        _ => (key.default_provider.clone(), key.default_model.clone()),
+    };
+
+    AuthDecision::Allow(Box::new(ProxyAuth {
+        user_id: user.id,
+        user_key_id: key.id,
+        org_id: user.org_id,
+        user_agent: None,
+        tags: Vec::new(),
+        request_headers: Headers::new(),
+        rate_limits: KeyLimits::new(key.rpm_limit, key.tpm_limit),
+        stream_tps: key
+            .stream_tps_limit
+            .and_then(|v| u32::try_from(v).ok())
+            .filter(|v| *v > 0),
+        max_output_tokens: key
+            .max_output_tokens
+            .and_then(|v| u32::try_from(v).ok())
+            .filter(|v| *v > 0),
+        received_at: Instant::now(),
+        model: None,
+        transform: None,
+        default_provider,
+        default_model,
+        experiment: None,
+        // A stored policy that no longer parses allows no server rather than every one.
+        mcp_policy: key
+            .mcp_policy
+            .as_ref()
+            .map(|policy| Arc::new(McpPolicy::from_json(policy).unwrap_or_default())),
+        // Policies are validated on write; one that no longer parses moderates nothing.
+        moderation_policy: key
+            .moderation_policy
+            .as_ref()
+            .and_then(|policy| ModerationPolicy::from_json(policy).ok())
+            .map(Arc::new),
+        // A stored scope that no longer parses allows nothing rather than everything.
+        key_scope: key.key_scope.as_ref().map(|scope| {
+            Arc::new(KeyScope::from_json(scope).unwrap_or_else(|_| KeyScope {
+                providers: vec![String::new()],
+                models: Vec::new(),
+            }))
+        }),
+        caller: Arc::new(CallerInfo {
+            key_label: key.label.clone(),
+            org_name: org.map(|org| org.name.clone()),
+            prelude_template: key.prelude_template.clone(),
+        }),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use bytes::Bytes;
+    use gproxy_common::GlobalConfigPatch;
+    use gproxy_provider_core::provider::{UpstreamFailure, UpstreamTransportErrorKind};
+    use gproxy_provider_core::{EventHub, UpstreamBody, UpstreamHttpResponse};
+    use gproxy_storage::{OrganizationRow, UserRow};
+
+    use super::*;
+
+    fn key(id: i64, enabled: bool) -> UserKeyRow {
+        let now = OffsetDateTime::now_utc();
+        UserKeyRow {
+            id,
+            user_id: 1,
+            api_key: format!("sk-{id}"),
+            label: None,
+            enabled,
+            rpm_limit: None,
+            tpm_limit: None,
+            stream_tps_limit: None,
+            max_output_tokens: None,
+            omit_bodies: false,
+            default_provider: None,
+            default_model: None,
+            mcp_policy: None,
+            moderation_policy: None,
+            prelude_template: None,
+            key_scope: None,
+            expires_at: None,
+            allowed_origins: None,
+            body_sample_percent: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// Key 1 is enabled, key 2 disabled.
+    fn snapshot() -> StorageSnapshot {
+        let now = OffsetDateTime::now_utc();
+        StorageSnapshot {
+            global_config: None,
+            providers: Vec::new(),
+            credentials: Vec::new(),
+            organizations: Vec::new(),
+            org_grants: Vec::new(),
+            users: vec![UserRow {
+                id: 1,
+                name: "alice".to_string(),
+                enabled: true,
+                org_id: None,
+                created_at: now,
+                updated_at: now,
+            }],
+            user_keys: vec![key(1, true), key(2, false)],
+            model_profiles: Vec::new(),
+            experiments: Vec::new(),
+            secrets: Vec::new(),
+            log_views: Vec::new(),
+        }
+    }
+
+    async fn state() -> AppState {
+        let global = GlobalConfigPatch {
+            admin_key: Some("admin".to_string()),
+            dsn: Some("sqlite::memory:".to_string()),
+            ..GlobalConfigPatch::default()
+        }
+        .into_config()
+        .unwrap();
+        AppState::from_bootstrap(global, snapshot(), EventHub::new(16))
+            .await
+            .unwrap()
+    }
+
+    fn defaults(decision: &AuthDecision) -> (Option<&str>, Option<&str>) {
+        match decision {
+            AuthDecision::Allow(auth) => (
+                auth.default_provider.as_deref(),
+                auth.default_model.as_deref(),
+            ),
+            _ => panic!("key was not admitted"),
+        }
+    }
+
+    #[test]
+    fn keys_without_defaults_route_like_their_organization() {
+        let now = OffsetDateTime::now_utc();
+        let mut snapshot = snapshot();
+        snapshot.organizations.push(OrganizationRow {
+            id: 3,
+            name: "team-a".to_string(),
+            enabled: true,
+            admin_key: None,
+            omit_bodies: false,
+            rpm_limit: None,
+            tpm_limit: None,
+            monthly_token_budget: None,
+            default_provider: Some("openai".to_string()),
+            default_model: Some("gpt-4o".to_string()),
+            created_at: now,
+            updated_at: now,
+        });
+        let request = request(None);
+        let own = UserKeyRow {
+            default_provider: Some("claude".to_string()),
+            ..key(1, true)
+        };
+        assert_eq!(
+            defaults(&user_key_decision(&snapshot, &key(1, true), &request)),
+            (None, None)
+        );
+
+        snapshot.users[0].org_id = Some(3);
+        assert_eq!(
+            defaults(&user_key_decision(&snapshot, &key(1, true), &request)),
+            (Some("openai"), Some("gpt-4o"))
+        );
+        // A key's own provider keeps its own (unset) model too.
+        assert_eq!(
+            defaults(&user_key_decision(&snapshot, &own, &request)),
+            (Some("claude"), None)
+        );
+
+        snapshot.organizations[0].enabled = false;
+        assert!(matches!(
+            user_key_decision(&snapshot, &key(1, true), &request),
+            AuthDecision::Deny
+        ));
+    }
+
+    fn request(api_key: Option<&str>) -> AuthRequest {
+        AuthRequest {
+            api_key: api_key.map(str::to_string),
+            method: "POST".to_string(),
+            path: "/v1/chat/completions".to_string(),
+            headers: Headers::from(vec![
+                ("authorization".to_string(), "Bearer token".to_string()),
+                ("host".to_string(), "gproxy".to_string()),
+            ]),
+            client_ip: Some("10.0.0.1".to_string()),
+        }
+    }
+
+    fn allowed_key(decision: &AuthDecision) -> Option<i64> {
+        match decision {
+            AuthDecision::Allow(auth) => Some(auth.user_key_id),
+            _ => None,
+        }
+    }
+
+    /// A provider that always answers the same and counts how often it was asked.
+    struct Fixed {
+        deny: bool,
+        calls: AtomicUsize,
+    }
+
+    impl Fixed {
+        fn new(deny: bool) -> Arc<Self> {
+            Arc::new(Self {
+                deny,
+                calls: AtomicUsize::new(0),
+            })
+        }
+    }
+
+    impl AuthProvider for Fixed {
+        fn authenticate<'a>(&'a self, _: &'a AppState, _: &'a AuthRequest) -> AuthFuture<'a> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let decision = if self.deny {
+                AuthDecision::Deny
+            } else {
+                AuthDecision::Pass
+            };
+            Box::pin(std::future::ready(decision))
+        }
+    }
+
+    #[tokio::test]
+    async fn chain_asks_in_order_until_a_decision() {
+        let state = state().await;
+        let (pass, deny) = (Fixed::new(false), Fixed::new(true));
+        let chain = AuthChain::new(vec![
+            pass.clone(),
+            Arc::new(SnapshotAuthProvider),
+            deny.clone(),
+        ]);
+        let decision = chain.authenticate(&state, &request(Some("sk-1"))).await;
+        assert_eq!(allowed_key(&decision), Some(1));
+        assert_eq!(pass.calls.load(Ordering::SeqCst), 1);
+        assert_eq!(deny.calls.load(Ordering::SeqCst), 0);
+
+        // Unknown keys pass on to the denying provider.
+        let decision = chain.authenticate(&state, &request(Some("sk-9"))).await;
+        assert!(matches!(decision, AuthDecision::Deny));
+        assert_eq!(deny.calls.load(Ordering::SeqCst), 1);
+
+        // A deny stops the chain before the key lookup.
+        let chain = AuthChain::new(vec![deny.clone(), Arc::new(SnapshotAuthProvider)]);
+        let decision = chain.authenticate(&state, &request(Some("sk-1"))).await;
+        assert!(matches!(decision, AuthDecision::Deny));
+
+        // Nobody allowing means denied.
+        let chain = AuthChain::new(vec![pass.clone()]);
+        let decision = chain.authenticate(&state, &request(Some("sk-1"))).await;
+        assert!(matches!(decision, AuthDecision::Deny));
+    }
+
+    /// An authorizer answering every request with `answer`.
+    struct Authorizer {
+        answer: Result<(u16, Option<&'static str>), UpstreamTransportErrorKind>,
+        calls: AtomicUsize,
+        last: Mutex<Option<UpstreamHttpRequest>>,
+    }
+
+    impl Authorizer {
+        fn new(
+            answer: Result<(u16, Option<&'static str>), UpstreamTransportErrorKind>,
+        ) -> Arc<Self> {
+            Arc::new(Self {
+                answer,
+                calls: AtomicUsize::new(0),
+                last: Mutex::new(None),
+            })
+        }
+    }
+
+    impl UpstreamClient for Authorizer {
+        fn send<'a>(
+            &'a self,
+            req: UpstreamHttpRequest,
+        ) -> Pin<Box<dyn Future<Output = Result<UpstreamHttpResponse, UpstreamFailure>> + Send + 'a>>
+        {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            *self.last.lock().unwrap() = Some(req);
+            let resp = match self.answer {
+                Ok((status, key_id)) => {
+                    let mut headers = Headers::new();
+                    if let Some(key_id) = key_id {
+                        headers.append(FORWARD_AUTH_USER_KEY_HEADER, key_id);
+                    }
+                    Ok(UpstreamHttpResponse {
+                        status,
+                        headers,
+                        body: UpstreamBody::Bytes(Bytes::new()),
+                    })
+                }
+                Err(kind) => Err(UpstreamFailure::Transport {
+                    kind,
+                    message: "unreachable".to_string(),
+                }),
+            };
+            Box::pin(std::future::ready(resp))
+        }
+    }
+
+    async fn forward(
+        answer: Result<(u16, Option<&'static str>), UpstreamTransportErrorKind>,
+    ) -> AuthDecision {
+        let state = state().await;
+        let provider = ForwardAuthProvider::new("http://authz/check", Authorizer::new(answer));
+        provider.authenticate(&state, &request(Some("token"))).await
+    }
+
+    #[tokio::test]
+    async fn forward_auth_admits_the_named_enabled_key() {
+        assert_eq!(allowed_key(&forward(Ok((200, Some("1")))).await), Some(1));
+        assert_eq!(allowed_key(&forward(Ok((204, Some(" 1 ")))).await), Some(1));
+        for key_id in [None, Some("2"), Some("9"), Some("one")] {
+            assert!(
+                matches!(forward(Ok((200, key_id))).await, AuthDecision::Deny),
+                "{key_id:?}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn forward_auth_denies_on_401_403_and_passes_otherwise() {
+        for status in [401, 403] {
+            assert!(matches!(
+                forward(Ok((status, Some("1")))).await,
+                AuthDecision::Deny
+            ));
+        }
+        for status in [302, 404, 500, 503] {
+            assert!(matches!(
+                forward(Ok((status, Some("1")))).await,
+                AuthDecision::Pass
+            ));
+        }
+        for kind in [
+            UpstreamTransportErrorKind::Connect,
+            UpstreamTransportErrorKind::Timeout,
+        ] {
+            assert!(matches!(forward(Err(kind)).await, AuthDecision::Pass));
+        }
+    }
+
+    #[tokio::test]
+    async fn forward_auth_sends_the_request_context() {
+        let state = state().await;
+        let authorizer = Authorizer::new(Ok((200, Some("1"))));
+        let provider = ForwardAuthProvider::new("http://authz/check", authorizer.clone());
+        provider.authenticate(&state, &request(Some("token"))).await;
+        let sent = authorizer.last.lock().unwrap().take().unwrap();
+        assert_eq!(sent.url, "http://authz/check");
+        assert_eq!(
+            header_get(&sent.headers, "authorization"),
+            Some("Bearer token")
+        );
+        assert_eq!(header_get(&sent.headers, "host"), None);
+        assert_eq!(
+            header_get(&sent.headers, "x-forwarded-method"),
+            Some("POST")
+        );
+        assert_eq!(
+            header_get(&sent.headers, "x-forwarded-uri"),
+            Some("/v1/chat/completions")
+        );
+        assert_eq!(
+            header_get(&sent.headers, "x-forwarded-for"),
+            Some("10.0.0.1")
+        );
+    }
+
+    #[tokio::test]
+    async fn forward_auth_reuses_answers_only_for_identical_requests() {
+        let state = state().await;
+        let authorizer = Authorizer::new(Ok((200, Some("1"))));
+        let provider = ForwardAuthProvider::new("http://authz/check", authorizer.clone());
+        for _ in 0..3 {
+            let decision = provider.authenticate(&state, &request(Some("token"))).await;
+            assert_eq!(allowed_key(&decision), Some(1));
+        }
+        assert_eq!(authorizer.calls.load(Ordering::SeqCst), 1);
+
+        let mut other_path = request(Some("token"));
+        other_path.path = "/v1/messages".to_string();
+        provider.authenticate(&state, &other_path).await;
+        provider.authenticate(&state, &request(Some("other"))).await;
+        assert_eq!(authorizer.calls.load(Ordering::SeqCst), 3);
+
+        // Anything else the authorizer is sent is part of the decision too.
+        let mut other_client = request(Some("token"));
+        other_client.client_ip = Some("10.0.0.2".to_string());
+        provider.authenticate(&state, &other_client).await;
+        let mut other_origin = request(Some("token"));
+        other_origin
+            .headers
+            .append("origin", "https://elsewhere.example");
+        provider.authenticate(&state, &other_origin).await;
+        // Header name case and order do not split the cache.
+        let mut reordered = request(Some("token"));
+        reordered.headers = Headers::from(vec![
+            ("Host".to_string(), "gproxy".to_string()),
+            ("Authorization".to_string(), "Bearer token".to_string()),
+        ]);
+        provider.authenticate(&state, &reordered).await;
+        assert_eq!(authorizer.calls.load(Ordering::SeqCst), 5);
+
+        // Without a presented key every request is asked.
+        provider.authenticate(&state, &request(None)).await;
+        provider.authenticate(&state, &request(None)).await;
+        assert_eq!(authorizer.calls.load(Ordering::SeqCst), 7);
+
+        // Passed-on answers are not kept.
+        let authorizer = Authorizer::new(Ok((503, None)));
+        let provider = ForwardAuthProvider::new("http://authz/check", authorizer.clone());
+        provider.authenticate(&state, &request(Some("token"))).await;
+        provider.authenticate(&state, &request(Some("token"))).await;
+        assert_eq!(authorizer.calls.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,195 @@
+//! Candidate fan-out: a non-stream chat `n` or Gemini `candidateCount` request served by
+//! an upstream protocol with one candidate per answer (Claude, Responses) is sent once per
+//! candidate, and the answers are merged into one response with summed usage.
+
+use bytes::Bytes;
+use gproxy_provider_core::{
+    GenerateContentRequest, Proto, Request, UpstreamBody, UpstreamHttpResponse, header_remove,
+};
+use serde_json::{Value as JsonValue, json};
+
+/// Candidates a non-stream generate request asks for, when more than one.
+pub(crate) fn requested(req: &Request) -> Option<u32> {
+    let Request::GenerateContent(inner) = req else {
+        return None;
+    };
+    let count = match inner {
+        GenerateContentRequest::OpenAIChat(r) if r.body.stream != Some(true) => {
+            u32::try_from(r.body.n?).ok()?
+        }
+        GenerateContentRequest::Gemini(r) => r.body.generation_config.as_ref()?.candidate_count?,
+        _ => return None,
+    };
+    (count > 1).then_some(count)
+}
+
+/// Makes the request ask for a single candidate.
+pub(crate) fn single(req: &mut Request) {
+    match req {
+        Request::GenerateContent(GenerateContentRequest::OpenAIChat(r)) => r.body.n = None,
+        Request::GenerateContent(GenerateContentRequest::Gemini(r)) => {
+            if let Some(config) = r.body.generation_config.as_mut() {
+                config.candidate_count = None;
+            }
+        }
+        _ => {}
+    }
+}
+
+/// One response holding every answer's choices (chat) or candidates (Gemini), numbered
+/// in call order, and the sum of their usage; `None` without answers. Answers that are
+/// not JSON are left out.
+pub(crate) fn merge(
+    proto: Proto,
+    mut answers: Vec<UpstreamHttpResponse>,
+) -> Option<UpstreamHttpResponse> {
+    let (list_key, usage_key) = match proto {
+        Proto::Gemini => ("candidates", "usageMetadata"),
+        _ => ("choices", "usage"),
+    };
+    if answers.is_empty() {
+        return None;
+    }
+    let mut resp = answers.remove(0);
+    let Some(mut merged) = json_body(&resp) else {
+        return Some(resp);
+    };
+    for body in answers.iter().filter_map(json_body) {
+        if let Some(items) = body.get(list_key).and_then(JsonValue::as_array)
+            && let Some(list) = merged.get_mut(list_key).and_then(JsonValue::as_array_mut)
+        {
+            list.extend(items.iter().cloned());
+        }
+        if let Some(usage) = body.get(usage_key) {
+            match merged.get_mut(usage_key) {
+                Some(total) if !total.is_null() => add_usage(total, usage),
+                _ => merged[usage_key] = usage.clone(),
+            }
+        }
+    }
+    if let Some(list) = merged.get_mut(list_key).and_then(JsonValue::as_array_mut) {
+        for (index, item) in list.iter_mut().enumerate() {
+            if let Some(item) = item.as_object_mut() {
+                item.insert("index".to_string(), json!(index));
+            }
+        }
+    }
+    header_remove(&mut resp.headers, "content-length");
+    resp.body = UpstreamBody::Bytes(Bytes::from(serde_json::to_vec(&merged).unwrap_or_default()));
+    Some(resp)
+}
+
+fn json_body(resp: &UpstreamHttpResponse) -> Option<JsonValue> {
+    match &resp.body {
+        UpstreamBody::Bytes(body) => serde_json::from_slice(body).ok(),
+        UpstreamBody::Stream(_) => None,
+    }
+}
+
+/// Adds the integer counts of `usage` to `total`, field by field.
+fn add_usage(total: &mut JsonValue, usage: &JsonValue) {
+    match (total, usage) {
+        (JsonValue::Object(total), JsonValue::Object(usage)) => {
+            for (key, value) in usage {
+                match total.get_mut(key) {
+                    Some(existing) => add_usage(existing, value),
+                    None => {
+                        total.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (total @ JsonValue::Number(_), JsonValue::Number(count)) => {
+            if let (Some(a), Some(b)) = (total.as_i64(), count.as_i64()) {
+                *total = json!(a + b);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gproxy_protocol::gemini::generate_content::request::{
+        GenerateContentPath, GenerateContentRequest as GeminiGenerateContentRequest,
+    };
+
+    use super::*;
+
+    fn answer(body: JsonValue) -> UpstreamHttpResponse {
+        UpstreamHttpResponse {
+            status: 200,
+            headers: vec![("content-length".to_string(), "1".to_string())].into(),
+            body: UpstreamBody::Bytes(Bytes::from(body.to_string())),
+        }
+    }
+
+    #[test]
+    fn chat_answers_merge_into_numbered_choices() {
+        let one = json!({
+            "id": "a",
+            "choices": [{ "index": 0, "message": { "content": "x" } }],
+            "usage": { "prompt_tokens": 5, "completion_tokens": 2, "total_tokens": 7 },
+        });
+        let two = json!({
+            "id": "b",
+            "choices": [{ "index": 0, "message": { "content": "y" } }],
+            "usage": {
+                "prompt_tokens": 5,
+                "completion_tokens": 3,
+                "total_tokens": 8,
+                "completion_tokens_details": { "reasoning_tokens": 1 },
+            },
+        });
+        let resp = merge(Proto::OpenAIChat, vec![answer(one), answer(two)]).unwrap();
+        assert!(resp.headers.is_empty());
+        let UpstreamBody::Bytes(body) = resp.body else {
+            unreachable!()
+        };
+        let body: JsonValue = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["id"], "a");
+        assert_eq!(body["choices"][1]["index"], 1);
+        assert_eq!(body["choices"][1]["message"]["content"], "y");
+        assert_eq!(body["usage"]["prompt_tokens"], 10);
+        assert_eq!(body["usage"]["total_tokens"], 15);
+        assert_eq!(
+            body["usage"]["completion_tokens_details"]["reasoning_tokens"],
+            1
+        );
+    }
+
+    #[test]
+    fn gemini_candidate_count_is_read_and_cleared() {
+        let mut req = Request::GenerateContent(GenerateContentRequest::Gemini(
+            GeminiGenerateContentRequest {
+                path: GenerateContentPath {
+                    model: "models/claude".to_string(),
+                },
+                body: serde_json::from_value(json!({
+                    "contents": [],
+                    "generationConfig": { "candidateCount": 3 },
+                }))
+                .unwrap(),
+            },
+        ));
+        assert_eq!(requested(&req), Some(3));
+        single(&mut req);
+        assert_eq!(requested(&req), None);
+
+        let merged = merge(
+            Proto::Gemini,
+            vec![
+                answer(json!({ "candidates": [{ "index": 0 }], "usageMetadata": { "totalTokenCount": 4 } })),
+                answer(json!({ "candidates": [{ "index": 0 }], "usageMetadata": { "totalTokenCount": 6 } })),
+            ],
+        )
+        .unwrap();
+        let UpstreamBody::Bytes(body) = merged.body else {
+            unreachable!()
+        };
+        let body: JsonValue = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["candidates"][1]["index"], 1);
+        assert_eq!(body["usageMetadata"]["totalTokenCount"], 10);
+        assert!(merge(Proto::Gemini, Vec::new()).is_none());
+    }
+}
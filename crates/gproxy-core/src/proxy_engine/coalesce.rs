@@ -0,0 +1,260 @@
+//! Coalescing of identical concurrent non-stream generate and count tokens requests.
+//!
+//! The first request for a key becomes the leader and performs the upstream call; requests
+//! arriving while it is in flight wait for a copy of its response instead of issuing their
+//! own. Streaming bodies cannot be shared, so followers fall back to a normal call. Other
+//! operations (deletes, uploads, conversation writes) are never coalesced.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use bytes::Bytes;
+use serde::Serialize;
+use tokio::sync::oneshot;
+
+use gproxy_provider_core::{
+    CountTokensRequest, GenerateContentRequest, Headers, Op, Proto, Request, UpstreamBody,
+    UpstreamHttpResponse,
+};
+
+use super::types::ProxyCall;
+
+#[derive(Debug, Clone)]
+pub(super) struct SharedResponse {
+    status: u16,
+    headers: Headers,
+    body: Bytes,
+}
+
+impl SharedResponse {
+    pub(super) fn into_response(self) -> UpstreamHttpResponse {
+        UpstreamHttpResponse {
+            status: self.status,
+            headers: self.headers,
+            body: UpstreamBody::Bytes(self.body),
+        }
+    }
+}
+
+type Waiters = Vec<oneshot::Sender<SharedResponse>>;
+
+#[derive(Debug, Default)]
+pub(super) struct InflightRequests {
+    inner: Mutex<HashMap<CoalesceKey, Waiters>>,
+}
+
+pub(super) enum Slot {
+    Leader(LeaderGuard),
+    Follower(oneshot::Receiver<SharedResponse>),
+}
+
+impl InflightRequests {
+    pub(super) fn join(self: &Arc<Self>, key: CoalesceKey) -> Slot {
+        let mut guard = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(waiters) = guard.get_mut(&key) {
+            let (tx, rx) = oneshot::channel();
+            waiters.push(tx);
+            return Slot::Follower(rx);
+        }
+        guard.insert(key.clone(), Vec::new());
+        Slot::Leader(LeaderGuard {
+            inflight: self.clone(),
+            key,
+            finished: false,
+        })
+    }
+
+    fn take(&self, key: &CoalesceKey) -> Waiters {
+        let mut guard = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        guard.remove(key).unwrap_or_default()
+    }
+}
+
+/// Held by the leader; dropping it without [`LeaderGuard::finish`] releases the followers
+/// so they retry on their own.
+pub(super) struct LeaderGuard {
+    inflight: Arc<InflightRequests>,
+    key: CoalesceKey,
+    finished: bool,
+}
+
+impl LeaderGuard {
+    pub(super) fn finish(mut self, resp: &UpstreamHttpResponse) {
+        // A new leader may claim the key right after this; Drop must not evict it.
+        self.finished = true;
+        let waiters = self.inflight.take(&self.key);
+        let UpstreamBody::Bytes(body) = &resp.body else {
+            return;
+        };
+        let shared = SharedResponse {
+            status: resp.status,
+            headers: resp.headers.clone(),
+            body: body.clone(),
+        };
+        for waiter in waiters {
+            let _ = waiter.send(shared.clone());
+        }
+    }
+}
+
+impl Drop for LeaderGuard {
+    fn drop(&mut self) {
+        if !self.finished {
+            drop(self.inflight.take(&self.key));
+        }
+    }
+}
+
+/// Identity of a coalescable call: calls share a response only when every field is equal,
+/// down to the request bytes.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(super) struct CoalesceKey {
+    user_key_id: i64,
+    provider: String,
+    response_model_prefix_provider: Option<String>,
+    user_proto: Proto,
+    user_op: Op,
+    request: Vec<u8>,
+}
+
+/// Key of a non-stream generate or count tokens call; `None` for every other call.
+pub(super) fn coalesce_key(call: &ProxyCall) -> Option<CoalesceKey> {
+    let ProxyCall::Protocol {
+        auth,
+        provider,
+        response_model_prefix_provider,
+        user_proto,
+        user_op,
+        req,
+        ..
+    } = call
+    else {
+        return None;
+    };
+    if !matches!(user_op, Op::GenerateContent | Op::CountTokens) {
+        return None;
+    }
+    Some(CoalesceKey {
+        user_key_id: auth.user_key_id,
+        provider: provider.clone(),
+        response_model_prefix_provider: response_model_prefix_provider.clone(),
+        user_proto: *user_proto,
+        user_op: *user_op,
+        request: canonical_request(req)?,
+    })
+}
+
+/// The request's path or headers and body as JSON with object keys sorted, so equal
+/// requests give equal bytes.
+fn canonical_request(req: &Request) -> Option<Vec<u8>> {
+    fn canonical(parts: impl Serialize) -> Option<Vec<u8>> {
+        serde_json::to_vec(&serde_json::to_value(parts).ok()?).ok()
+    }
+    match req {
+        Request::GenerateContent(req) => match req {
+            GenerateContentRequest::Claude(r) => canonical((&r.headers, &r.body)),
+            GenerateContentRequest::OpenAIChat(r) => canonical(&r.body),
+            GenerateContentRequest::OpenAIResponse(r) => canonical(&r.body),
+            GenerateContentRequest::Gemini(r) => canonical((&r.path, &r.body)),
+            GenerateContentRequest::GeminiStream(_) => None,
+        },
+        Request::CountTokens(req) => match req {
+            CountTokensRequest::Claude(r) => canonical((&r.headers, &r.body)),
+            CountTokensRequest::OpenAI(r) => canonical(&r.body),
+            CountTokensRequest::Gemini(r) => canonical((&r.path, &r.body)),
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gproxy_protocol::openai::create_chat_completions::request::CreateChatCompletionRequest;
+    use serde_json::json;
+
+    fn bytes_response(body: &'static str) -> UpstreamHttpResponse {
+        UpstreamHttpResponse {
+            status: 200,
+            headers: Headers::new(),
+            body: UpstreamBody::Bytes(Bytes::from_static(body.as_bytes())),
+        }
+    }
+
+    fn key(user_key_id: i64, request: &str) -> CoalesceKey {
+        CoalesceKey {
+            user_key_id,
+            provider: "openai".to_string(),
+            response_model_prefix_provider: None,
+            user_proto: Proto::OpenAIChat,
+            user_op: Op::GenerateContent,
+            request: request.as_bytes().to_vec(),
+        }
+    }
+
+    fn chat(body: serde_json::Value) -> Request {
+        Request::GenerateContent(GenerateContentRequest::OpenAIChat(
+            CreateChatCompletionRequest {
+                body: serde_json::from_value(body).unwrap(),
+            },
+        ))
+    }
+
+    #[test]
+    fn follower_receives_leader_response() {
+        let inflight = Arc::new(InflightRequests::default());
+        let Slot::Leader(leader) = inflight.join(key(7, "{}")) else {
+            panic!("first join should lead");
+        };
+        let Slot::Follower(mut rx) = inflight.join(key(7, "{}")) else {
+            panic!("second join should follow");
+        };
+        leader.finish(&bytes_response("ok"));
+        let shared = rx.try_recv().expect("shared response").into_response();
+        assert!(matches!(shared.body, UpstreamBody::Bytes(ref b) if b.as_ref() == b"ok"));
+        assert!(matches!(inflight.join(key(7, "{}")), Slot::Leader(_)));
+    }
+
+    #[test]
+    fn only_equal_keys_share_a_call() {
+        let inflight = Arc::new(InflightRequests::default());
+        let _leader = inflight.join(key(7, "{}"));
+        assert!(matches!(inflight.join(key(8, "{}")), Slot::Leader(_)));
+        assert!(matches!(inflight.join(key(7, "{ }")), Slot::Leader(_)));
+    }
+
+    #[test]
+    fn dropped_leader_releases_followers() {
+        let inflight = Arc::new(InflightRequests::default());
+        let leader = inflight.join(key(1, "{}"));
+        let Slot::Follower(mut rx) = inflight.join(key(1, "{}")) else {
+            panic!("second join should follow");
+        };
+        drop(leader);
+        assert!(matches!(
+            rx.try_recv(),
+            Err(oneshot::error::TryRecvError::Closed)
+        ));
+    }
+
+    #[test]
+    fn canonical_request_ignores_key_order() {
+        let a = chat(json!({
+            "model": "gpt-4o",
+            "messages": [{ "role": "user", "content": "hi" }],
+            "metadata": { "a": "1", "b": "2" },
+        }));
+        let b = chat(json!({
+            "metadata": { "b": "2", "a": "1" },
+            "messages": [{ "role": "user", "content": "hi" }],
+            "model": "gpt-4o",
+        }));
+        let other = chat(json!({
+            "model": "gpt-4o",
+            "messages": [{ "role": "user", "content": "hello" }],
+        }));
+        assert!(canonical_request(&a).is_some());
+        assert_eq!(canonical_request(&a), canonical_request(&b));
+        assert_ne!(canonical_request(&a), canonical_request(&other));
+    }
+}
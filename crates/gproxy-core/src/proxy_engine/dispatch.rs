@@ -26,6 +26,7 @@ pub fn resolve_call_shape(
     dispatch: &DispatchTable,
     user_proto: Proto,
     user_op: Op,
+    model: Option<&str>,
 ) -> Option<ResolvedCall> {
     let is_generate = matches!(user_op, Op::GenerateContent | Op::StreamGenerateContent);
     if matches!(
@@ -36,6 +37,18 @@ pub fn resolve_call_shape(
             | Op::ResponseListInputItems
             | Op::ResponseCompact
             | Op::MemoryTraceSummarize
+            | Op::ResponseList
+            | Op::ConversationCreate
+            | Op::ConversationGet
+            | Op::ConversationUpdate
+            | Op::ConversationDelete
+            | Op::ConversationItemList
+            | Op::ConversationItemCreate
+            | Op::ConversationItemGet
+            | Op::ConversationItemDelete
+            | Op::FileList
+            | Op::FileGet
+            | Op::FileDelete
     ) {
         if user_proto != Proto::OpenAI {
             return None;
@@ -46,6 +59,16 @@ pub fn resolve_call_shape(
             mode: GenerateMode::Same,
         });
     }
+    if matches!(user_op, Op::BatchGenerateContent | Op::BatchGet) {
+        if user_proto != Proto::Gemini {
+            return None;
+        }
+        return Some(ResolvedCall {
+            provider_proto: user_proto,
+            provider_op: user_op,
+            mode: GenerateMode::Same,
+        });
+    }
     if !is_generate {
         let ctx = TransformContext {
             src: user_proto,
@@ -53,7 +76,7 @@ pub fn resolve_call_shape(
             src_op: user_op,
             dst_op: user_op,
         };
-        let rule = dispatch.rule_for_context(&ctx);
+        let rule = dispatch.rule_for_model(&ctx, model);
         let provider_proto = rule_to_proto(user_proto, rule)?;
         return Some(ResolvedCall {
             provider_proto,
@@ -69,7 +92,7 @@ pub fn resolve_call_shape(
         src_op: user_op,
         dst_op: user_op,
     };
-    let same_rule = dispatch.rule_for_context(&same_ctx);
+    let same_rule = dispatch.rule_for_model(&same_ctx, model);
     if let Some(provider_proto) = rule_to_proto(user_proto, same_rule) {
         return Some(ResolvedCall {
             provider_proto,
@@ -78,6 +101,9 @@ pub fn resolve_call_shape(
         });
     }
 
+    if !dispatch.allows_stream_fallback(model) {
+        return None;
+    }
     let want_stream = user_op == Op::StreamGenerateContent;
     if want_stream {
         // Non-stream -> stream fallback: call non-stream upstream and streamify to the user.
@@ -87,7 +113,7 @@ pub fn resolve_call_shape(
             src_op: Op::GenerateContent,
             dst_op: Op::GenerateContent,
         };
-        let rule = dispatch.rule_for_context(&non_ctx);
+        let rule = dispatch.rule_for_model(&non_ctx, model);
         let provider_proto = rule_to_proto(user_proto, rule)?;
         return Some(ResolvedCall {
             provider_proto,
@@ -103,7 +129,7 @@ pub fn resolve_call_shape(
         src_op: Op::StreamGenerateContent,
         dst_op: Op::StreamGenerateContent,
     };
-    let rule = dispatch.rule_for_context(&stream_ctx);
+    let rule = dispatch.rule_for_model(&stream_ctx, model);
     let provider_proto = rule_to_proto(user_proto, rule)?;
     Some(ResolvedCall {
         provider_proto,
@@ -0,0 +1,474 @@
+//! Stable schema for engine-generated downstream errors.
+//!
+//! Every error produced by the engine carries `type`, `code`, `provider`, `retryable`
+//! and `trace_id`. The default body keeps the legacy `{"error": code, "detail": ..}`
+//! keys; with `native_error_format` enabled it is rendered in the caller's protocol
+//! envelope (Claude error, OpenAI error object, Gemini status). Native upstream error
+//! bodies from a provider speaking another protocol are translated the same way.
+
+use bytes::Bytes;
+use serde_json::{Value as JsonValue, json};
+
+use gproxy_provider_core::{
+    Headers, Proto, UpstreamBody, UpstreamHttpResponse, header_remove, header_set,
+};
+
+/// Marks a response body as engine-generated and carries its stable error code.
+pub const ERROR_CODE_HEADER: &str = "x-gproxy-error-code";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorType {
+    InvalidRequest,
+    Authentication,
+    PermissionDenied,
+    NotFound,
+    RequestTooLarge,
+    RateLimit,
+    Unsupported,
+    Upstream,
+    Unavailable,
+    Internal,
+}
+
+impl ErrorType {
+    pub fn from_status(status: u16) -> Self {
+        match status {
+            401 => ErrorType::Authentication,
+            403 => ErrorType::PermissionDenied,
+            404 => ErrorType::NotFound,
+            413 => ErrorType::RequestTooLarge,
+            429 => ErrorType::RateLimit,
+            400..=499 => ErrorType::InvalidRequest,
+            501 => ErrorType::Unsupported,
+            502 | 504 => ErrorType::Upstream,
+            503 => ErrorType::Unavailable,
+            _ => ErrorType::Internal,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorType::InvalidRequest => "invalid_request",
+            ErrorType::Authentication => "authentication",
+            ErrorType::PermissionDenied => "permission_denied",
+            ErrorType::NotFound => "not_found",
+            ErrorType::RequestTooLarge => "request_too_large",
+            ErrorType::RateLimit => "rate_limit",
+            ErrorType::Unsupported => "unsupported",
+            ErrorType::Upstream => "upstream",
+            ErrorType::Unavailable => "unavailable",
+            ErrorType::Internal => "internal",
+        }
+    }
+
+    pub(super) fn claude_type(self) -> &'static str {
+        match self {
+            ErrorType::InvalidRequest | ErrorType::Unsupported => "invalid_request_error",
+            ErrorType::Authentication => "authentication_error",
+            ErrorType::PermissionDenied => "permission_error",
+            ErrorType::NotFound => "not_found_error",
+            ErrorType::RequestTooLarge => "request_too_large",
+            ErrorType::RateLimit => "rate_limit_error",
+            ErrorType::Unavailable => "overloaded_error",
+            ErrorType::Upstream | ErrorType::Internal => "api_error",
+        }
+    }
+
+    pub(super) fn openai_type(self) -> &'static str {
+        match self {
+            ErrorType::InvalidRequest | ErrorType::RequestTooLarge | ErrorType::Unsupported => {
+                "invalid_request_error"
+            }
+            ErrorType::Authentication => "authentication_error",
+            ErrorType::PermissionDenied => "permission_error",
+            ErrorType::NotFound => "not_found_error",
+            ErrorType::RateLimit => "rate_limit_error",
+            ErrorType::Upstream | ErrorType::Unavailable | ErrorType::Internal => "server_error",
+        }
+    }
+
+    pub(super) fn gemini_status(self) -> &'static str {
+        match self {
+            ErrorType::InvalidRequest | ErrorType::RequestTooLarge => "INVALID_ARGUMENT",
+            ErrorType::Authentication => "UNAUTHENTICATED",
+            ErrorType::PermissionDenied => "PERMISSION_DENIED",
+            ErrorType::NotFound => "NOT_FOUND",
+            ErrorType::RateLimit => "RESOURCE_EXHAUSTED",
+            ErrorType::Unsupported => "UNIMPLEMENTED",
+            ErrorType::Unavailable => "UNAVAILABLE",
+            ErrorType::Upstream | ErrorType::Internal => "INTERNAL",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EngineError {
+    pub status: u16,
+    pub code: String,
+    pub detail: JsonValue,
+    pub provider: Option<String>,
+    pub trace_id: Option<String>,
+}
+
+impl EngineError {
+    pub fn new(status: u16, code: impl Into<String>, detail: JsonValue) -> Self {
+        Self {
+            status,
+            code: code.into(),
+            detail,
+            provider: None,
+            trace_id: None,
+        }
+    }
+
+    pub fn error_type(&self) -> ErrorType {
+        ErrorType::from_status(self.status)
+    }
+
+    pub fn retryable(&self) -> bool {
+        matches!(self.status, 408 | 429 | 500 | 502 | 503 | 504)
+    }
+
+    pub fn message(&self) -> String {
+        match &self.detail {
+            JsonValue::Null => self.code.clone(),
+            JsonValue::String(detail) => format!("{}: {detail}", self.code),
+            other => format!("{}: {other}", self.code),
+        }
+    }
+
+    pub fn to_json(&self) -> JsonValue {
+        json!({
+            "error": self.code,
+            "detail": self.detail,
+            "type": self.error_type().as_str(),
+            "code": self.code,
+            "provider": self.provider,
+            "retryable": self.retryable(),
+            "trace_id": self.trace_id,
+        })
+    }
+
+    pub fn to_native_json(&self, proto: Proto) -> JsonValue {
+        let error_type = self.error_type();
+        match proto {
+            Proto::Claude => json!({
+                "type": "error",
+                "error": {
+                    "type": error_type.claude_type(),
+                    "message": self.message(),
+                    "code": self.code,
+                    "provider": self.provider,
+                    "retryable": self.retryable(),
+                    "trace_id": self.trace_id,
+                },
+            }),
+            Proto::OpenAI | Proto::OpenAIChat | Proto::OpenAIResponse => json!({
+                "error": {
+                    "message": self.message(),
+                    "type": error_type.openai_type(),
+                    "param": JsonValue::Null,
+                    "code": self.code,
+                    "provider": self.provider,
+                    "retryable": self.retryable(),
+                    "trace_id": self.trace_id,
+                },
+            }),
+            Proto::Gemini => json!({
+                "error": {
+                    "code": self.status,
+                    "message": self.message(),
+                    "status": error_type.gemini_status(),
+                    "details": [{
+                        "@type": "type.googleapis.com/google.rpc.ErrorInfo",
+                        "reason": self.code,
+                        "domain": "gproxy",
+                        "metadata": {
+                            "provider": self.provider.clone().unwrap_or_default(),
+                            "retryable": self.retryable().to_string(),
+                            "trace_id": self.trace_id.clone().unwrap_or_default(),
+                        },
+                    }],
+                },
+            }),
+        }
+    }
+
+    pub fn into_response(self, native: Option<Proto>) -> UpstreamHttpResponse {
+        let body = match native {
+            Some(proto) => self.to_native_json(proto),
+            None => self.to_json(),
+        };
+        let mut headers = Headers::new();
+        header_set(&mut headers, "content-type", "application/json");
+        header_set(&mut headers, ERROR_CODE_HEADER, &self.code);
+        UpstreamHttpResponse {
+            status: self.status,
+            headers,
+            body: UpstreamBody::Bytes(Bytes::from(serde_json::to_vec(&body).unwrap_or_default())),
+        }
+    }
+
+    /// Recover an engine error from a response built by [`EngineError::into_response`].
+    pub fn from_response(resp: &UpstreamHttpResponse) -> Option<Self> {
+        let code = resp
+            .headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(ERROR_CODE_HEADER))
+            .map(|(_, value)| value.clone())?;
+        let UpstreamBody::Bytes(bytes) = &resp.body else {
+            return None;
+        };
+        let value = serde_json::from_slice::<JsonValue>(bytes).ok()?;
+        Some(Self {
+            status: resp.status,
+            code,
+            detail: value.get("detail").cloned().unwrap_or(JsonValue::Null),
+            provider: value
+                .get("provider")
+                .and_then(JsonValue::as_str)
+                .map(str::to_string),
+            trace_id: value
+                .get("trace_id")
+                .and_then(JsonValue::as_str)
+                .map(str::to_string),
+        })
+    }
+}
+
+/// Attach request context to an engine-generated error and render it in the final shape.
+/// Non-engine responses are returned unchanged.
+pub fn decorate_error_response(
+    resp: UpstreamHttpResponse,
+    provider: &str,
+    trace_id: Option<&str>,
+    native: Option<Proto>,
+) -> UpstreamHttpResponse {
+    let Some(mut err) = EngineError::from_response(&resp) else {
+        return resp;
+    };
+    if err.provider.is_none() {
+        err.provider = Some(provider.to_string());
+    }
+    if err.trace_id.is_none() {
+        err.trace_id = trace_id.map(str::to_string);
+    }
+    err.into_response(native)
+}
+
+fn same_error_family(a: Proto, b: Proto) -> bool {
+    let is_openai = |p| matches!(p, Proto::OpenAI | Proto::OpenAIChat | Proto::OpenAIResponse);
+    a == b || (is_openai(a) && is_openai(b))
+}
+
+/// Message and vendor error code extracted from a native upstream error body.
+struct UpstreamErrorInfo {
+    message: String,
+    code: Option<String>,
+}
+
+fn parse_upstream_error_body(proto: Proto, body: &JsonValue) -> Option<UpstreamErrorInfo> {
+    // Gemini stream endpoints wrap the error object in a one-element array.
+    let body = match body {
+        JsonValue::Array(items) => items.first()?,
+        other => other,
+    };
+    let error = body.get("error")?;
+    let message = error
+        .get("message")
+        .and_then(JsonValue::as_str)?
+        .to_string();
+    let code = match proto {
+        Proto::Claude => error.get("type").and_then(JsonValue::as_str),
+        Proto::OpenAI | Proto::OpenAIChat | Proto::OpenAIResponse => error
+            .get("code")
+            .and_then(JsonValue::as_str)
+            .or_else(|| error.get("type").and_then(JsonValue::as_str)),
+        Proto::Gemini => error.get("status").and_then(JsonValue::as_str),
+    };
+    Some(UpstreamErrorInfo {
+        message,
+        code: code.map(str::to_string),
+    })
+}
+
+/// Rewrite a native upstream error body into the caller's protocol error shape when the
+/// provider speaks a different protocol. Status and message are preserved; bodies that
+/// are not recognisable upstream errors are returned unchanged.
+pub fn translate_upstream_error(
+    resp: UpstreamHttpResponse,
+    upstream: Proto,
+    downstream: Proto,
+) -> UpstreamHttpResponse {
+    if resp.status < 400 || same_error_family(upstream, downstream) {
+        return resp;
+    }
+    if EngineError::from_response(&resp).is_some() {
+        return resp;
+    }
+    let UpstreamBody::Bytes(bytes) = &resp.body else {
+        return resp;
+    };
+    let Some(info) = serde_json::from_slice::<JsonValue>(bytes)
+        .ok()
+        .and_then(|value| parse_upstream_error_body(upstream, &value))
+    else {
+        return resp;
+    };
+
+    let error_type = ErrorType::from_status(resp.status);
+    let body = match downstream {
+        Proto::Claude => json!({
+            "type": "error",
+            "error": {
+                "type": error_type.claude_type(),
+                "message": info.message,
+            },
+        }),
+        Proto::OpenAI | Proto::OpenAIChat | Proto::OpenAIResponse => json!({
+            "error": {
+                "message": info.message,
+                "type": error_type.openai_type(),
+                "param": JsonValue::Null,
+                "code": info.code,
+            },
+        }),
+        Proto::Gemini => json!({
+            "error": {
+                "code": resp.status,
+                "message": info.message,
+                "status": error_type.gemini_status(),
+            },
+        }),
+    };
+
+    let mut headers = resp.headers;
+    header_remove(&mut headers, "content-length");
+    header_remove(&mut headers, "content-encoding");
+    header_set(&mut headers, "content-type", "application/json");
+    UpstreamHttpResponse {
+        status: resp.status,
+        headers,
+        body: UpstreamBody::Bytes(Bytes::from(serde_json::to_vec(&body).unwrap_or_default())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn body_json(resp: &UpstreamHttpResponse) -> JsonValue {
+        match &resp.body {
+            UpstreamBody::Bytes(bytes) => serde_json::from_slice(bytes).unwrap(),
+            UpstreamBody::Stream(_) => panic!("expected bytes body"),
+        }
+    }
+
+    #[test]
+    fn decorate_keeps_legacy_keys_and_adds_context() {
+        let resp =
+            EngineError::new(503, "no_active_credentials", JsonValue::Null).into_response(None);
+        let resp = decorate_error_response(resp, "openai", Some("t1"), None);
+        let body = body_json(&resp);
+        assert_eq!(body["error"], "no_active_credentials");
+        assert_eq!(body["type"], "unavailable");
+        assert_eq!(body["provider"], "openai");
+        assert_eq!(body["retryable"], true);
+        assert_eq!(body["trace_id"], "t1");
+    }
+
+    #[test]
+    fn decorate_renders_native_shapes() {
+        let err =
+            || EngineError::new(404, "provider_not_found", JsonValue::Null).into_response(None);
+
+        let claude = body_json(&decorate_error_response(
+            err(),
+            "p",
+            None,
+            Some(Proto::Claude),
+        ));
+        assert_eq!(claude["type"], "error");
+        assert_eq!(claude["error"]["type"], "not_found_error");
+
+        let openai = body_json(&decorate_error_response(
+            err(),
+            "p",
+            None,
+            Some(Proto::OpenAIChat),
+        ));
+        assert_eq!(openai["error"]["code"], "provider_not_found");
+
+        let gemini = body_json(&decorate_error_response(
+            err(),
+            "p",
+            None,
+            Some(Proto::Gemini),
+        ));
+        assert_eq!(gemini["error"]["status"], "NOT_FOUND");
+        assert_eq!(gemini["error"]["code"], 404);
+    }
+
+    #[test]
+    fn decorate_ignores_non_engine_responses() {
+        let resp = UpstreamHttpResponse {
+            status: 400,
+            headers: Headers::new(),
+            body: UpstreamBody::Bytes(Bytes::from_static(b"{\"error\":{}}")),
+        };
+        let out = decorate_error_response(resp, "p", None, Some(Proto::Claude));
+        assert!(out.headers.is_empty());
+    }
+
+    fn upstream(status: u16, body: &'static [u8]) -> UpstreamHttpResponse {
+        UpstreamHttpResponse {
+            status,
+            headers: vec![("content-type".to_string(), "application/json".to_string())].into(),
+            body: UpstreamBody::Bytes(Bytes::from_static(body)),
+        }
+    }
+
+    #[test]
+    fn translate_gemini_error_for_openai_client() {
+        let resp = upstream(
+            400,
+            br#"{"error":{"code":400,"message":"bad field","status":"INVALID_ARGUMENT"}}"#,
+        );
+        let out = translate_upstream_error(resp, Proto::Gemini, Proto::OpenAIChat);
+        assert_eq!(out.status, 400);
+        let body = body_json(&out);
+        assert_eq!(body["error"]["message"], "bad field");
+        assert_eq!(body["error"]["type"], "invalid_request_error");
+        assert_eq!(body["error"]["code"], "INVALID_ARGUMENT");
+    }
+
+    #[test]
+    fn translate_openai_error_for_claude_client() {
+        let resp = upstream(
+            429,
+            br#"{"error":{"message":"slow down","type":"requests","code":"rate_limit_exceeded"}}"#,
+        );
+        let out = translate_upstream_error(resp, Proto::OpenAIResponse, Proto::Claude);
+        let body = body_json(&out);
+        assert_eq!(body["type"], "error");
+        assert_eq!(body["error"]["type"], "rate_limit_error");
+        assert_eq!(body["error"]["message"], "slow down");
+    }
+
+    #[test]
+    fn translate_keeps_same_family_and_unknown_bodies() {
+        let raw = br#"{"error":{"message":"x","type":"invalid_request_error"}}"#;
+        let out = translate_upstream_error(upstream(400, raw), Proto::OpenAI, Proto::OpenAIChat);
+        assert_eq!(
+            body_json(&out),
+            serde_json::from_slice::<JsonValue>(raw).unwrap()
+        );
+
+        let out = translate_upstream_error(
+            upstream(502, b"<html>bad gateway</html>"),
+            Proto::Claude,
+            Proto::Gemini,
+        );
+        assert!(matches!(out.body, UpstreamBody::Bytes(ref b) if b.starts_with(b"<html>")));
+    }
+}
@@ -0,0 +1,142 @@
+//! A/B experiments: a virtual model whose traffic is split across weighted arms.
+
+use serde::Deserialize;
+
+use gproxy_provider_core::header_get;
+use gproxy_storage::ExperimentRow;
+
+use super::types::{ExperimentAssignment, ProxyAuth};
+
+/// Request header carrying the caller's session id for `session` splits.
+pub const SESSION_HEADER: &str = "x-gproxy-session";
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct ExperimentArm {
+    pub name: String,
+    pub provider: String,
+    /// Upstream model, or a model profile of `provider`.
+    pub model: String,
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SplitMode {
+    Random,
+    User,
+    Session,
+}
+
+impl SplitMode {
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw {
+            "random" => Some(Self::Random),
+            "user" => Some(Self::User),
+            "session" => Some(Self::Session),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Experiment {
+    pub name: String,
+    pub split: SplitMode,
+    pub arms: Vec<ExperimentArm>,
+}
+
+impl Experiment {
+    /// Parses a stored experiment; `None` when it has no arm with a positive weight.
+    pub fn from_row(row: &ExperimentRow) -> Option<Self> {
+        let split = SplitMode::parse(&row.split)?;
+        let arms: Vec<ExperimentArm> = serde_json::from_value(row.arms_json.clone()).ok()?;
+        let arms: Vec<ExperimentArm> = arms.into_iter().filter(|arm| arm.weight > 0).collect();
+        if arms.is_empty() {
+            return None;
+        }
+        Some(Self {
+            name: row.name.clone(),
+            split,
+            arms,
+        })
+    }
+
+    /// Picks an arm for the caller. Hash splits keep a user (or session) on the same arm
+    /// while the arm list is unchanged; a `session` split without the session header
+    /// falls back to the user.
+    pub fn assign(&self, auth: &ProxyAuth) -> (&ExperimentArm, ExperimentAssignment) {
+        let total: u64 = self.arms.iter().map(|arm| u64::from(arm.weight)).sum();
+        let session = header_get(&auth.request_headers, SESSION_HEADER).filter(|s| !s.is_empty());
+        let bucket = match (self.split, session) {
+            (SplitMode::Random, _) => rand::random::<u64>() % total,
+            (SplitMode::Session, Some(session)) => stable_hash(&self.name, session) % total,
+            (SplitMode::User | SplitMode::Session, _) => {
+                stable_hash(&self.name, &auth.user_id.to_string()) % total
+            }
+        };
+        let arm = pick_arm(&self.arms, bucket);
+        let assignment = ExperimentAssignment {
+            experiment: self.name.clone(),
+            arm: arm.name.clone(),
+        };
+        (arm, assignment)
+    }
+}
+
+fn pick_arm(arms: &[ExperimentArm], mut bucket: u64) -> &ExperimentArm {
+    for arm in arms {
+        let weight = u64::from(arm.weight);
+        if bucket < weight {
+            return arm;
+        }
+        bucket -= weight;
+    }
+    &arms[arms.len() - 1]
+}
+
+/// FNV-1a over `experiment` and `key`; stable across restarts and builds.
+fn stable_hash(experiment: &str, key: &str) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in experiment.bytes().chain([0]).chain(key.bytes()) {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn arms(weights: &[u32]) -> Vec<ExperimentArm> {
+        weights
+            .iter()
+            .enumerate()
+            .map(|(i, weight)| ExperimentArm {
+                name: format!("arm-{i}"),
+                provider: "openai".to_string(),
+                model: "gpt-4o".to_string(),
+                weight: *weight,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn buckets_follow_weights() {
+        let arms = arms(&[30, 70]);
+        assert_eq!(pick_arm(&arms, 0).name, "arm-0");
+        assert_eq!(pick_arm(&arms, 29).name, "arm-0");
+        assert_eq!(pick_arm(&arms, 30).name, "arm-1");
+        assert_eq!(pick_arm(&arms, 99).name, "arm-1");
+    }
+
+    #[test]
+    fn hash_is_stable_per_experiment() {
+        assert_eq!(stable_hash("exp", "42"), stable_hash("exp", "42"));
+        assert_ne!(stable_hash("exp", "42"), stable_hash("exp2", "42"));
+    }
+}
@@ -0,0 +1,189 @@
+//! Fan-out ("best of N"): one generate request sent to several providers at once.
+
+use serde::Deserialize;
+use serde_json::{Value as JsonValue, json};
+
+use gproxy_provider_core::{Headers, UpstreamBody, UpstreamHttpResponse, header_get};
+
+/// Request header listing comma-separated `provider/model` targets to fan the request out to.
+pub const FAN_OUT_HEADER: &str = "x-gproxy-fan-out";
+/// Request header picking the mode of a header fan-out: `first` (default) or `all`.
+pub const FAN_OUT_MODE_HEADER: &str = "x-gproxy-fan-out-mode";
+/// Most branches one request fans out to; further targets are ignored.
+pub const MAX_FAN_OUT_BRANCHES: usize = 8;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum FanOutMode {
+    /// The first successful answer is returned and the other branches are cancelled.
+    #[default]
+    First,
+    /// Every answer is returned in one combined payload. Streams fall back to `First`.
+    All,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub(crate) struct FanOutTarget {
+    pub provider: String,
+    /// Upstream model, or a model profile of `provider`.
+    pub model: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub(crate) struct FanOut {
+    #[serde(default)]
+    pub mode: FanOutMode,
+    pub targets: Vec<FanOutTarget>,
+}
+
+impl FanOut {
+    /// `fan_out` of a model profile's settings; `None` when absent or without targets.
+    pub fn from_settings(settings: &JsonValue) -> Option<Self> {
+        let fan_out: Self = serde_json::from_value(settings.get("fan_out")?.clone()).ok()?;
+        fan_out.validated()
+    }
+
+    /// The fan-out a client asked for with [`FAN_OUT_HEADER`].
+    pub fn from_headers(headers: &Headers) -> Option<Self> {
+        let raw = header_get(headers, FAN_OUT_HEADER)?;
+        let targets = raw
+            .split(',')
+            .filter_map(|item| {
+                let (provider, model) = item.trim().split_once('/')?;
+                Some(FanOutTarget {
+                    provider: provider.trim().to_string(),
+                    model: model.trim().to_string(),
+                })
+            })
+            .collect();
+        let mode = match header_get(headers, FAN_OUT_MODE_HEADER).map(str::trim) {
+            Some("all") => FanOutMode::All,
+            _ => FanOutMode::First,
+        };
+        Self { mode, targets }.validated()
+    }
+
+    fn validated(mut self) -> Option<Self> {
+        self.targets
+            .retain(|target| !target.provider.is_empty() && !target.model.is_empty());
+        self.targets.truncate(MAX_FAN_OUT_BRANCHES);
+        (!self.targets.is_empty()).then_some(self)
+    }
+}
+
+/// The answer to hand back when no branch succeeded: the failure of the earliest target.
+pub(crate) fn first_failure(
+    mut failures: Vec<(usize, UpstreamHttpResponse)>,
+) -> Option<UpstreamHttpResponse> {
+    failures.sort_by_key(|(index, _)| *index);
+    failures.into_iter().next().map(|(_, resp)| resp)
+}
+
+/// Every branch's answer in target order, as `{"object":"fan_out","answers":[...]}`.
+/// Each answer carries its `provider`, `model`, `status` and the `response` body.
+pub(crate) fn combine(
+    targets: &[FanOutTarget],
+    mut answers: Vec<(usize, UpstreamHttpResponse)>,
+) -> UpstreamHttpResponse {
+    answers.sort_by_key(|(index, _)| *index);
+    let answers: Vec<JsonValue> = answers
+        .into_iter()
+        .map(|(index, resp)| {
+            let response = match &resp.body {
+                UpstreamBody::Bytes(body) => serde_json::from_slice(body)
+                    .unwrap_or_else(|_| JsonValue::String(String::from_utf8_lossy(body).into())),
+                UpstreamBody::Stream(_) => JsonValue::Null,
+            };
+            let target = &targets[index];
+            json!({
+                "provider": target.provider,
+                "model": target.model,
+                "status": resp.status,
+                "response": response,
+            })
+        })
+        .collect();
+    let body = json!({ "object": "fan_out", "answers": answers });
+    UpstreamHttpResponse {
+        status: 200,
+        headers: vec![("content-type".to_string(), "application/json".to_string())].into(),
+        body: UpstreamBody::Bytes(serde_json::to_vec(&body).unwrap_or_default().into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(status: u16, body: &'static str) -> UpstreamHttpResponse {
+        UpstreamHttpResponse {
+            status,
+            headers: Headers::new(),
+            body: UpstreamBody::Bytes(body.into()),
+        }
+    }
+
+    #[test]
+    fn header_targets_are_parsed_and_capped() {
+        let list = (0..10)
+            .map(|i| format!("p{i}/m{i}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let headers: Headers = vec![
+            (FAN_OUT_HEADER.to_string(), format!("bogus, /x, {list}")),
+            (FAN_OUT_MODE_HEADER.to_string(), "all".to_string()),
+        ]
+        .into();
+        let fan_out = FanOut::from_headers(&headers).unwrap();
+        assert_eq!(fan_out.mode, FanOutMode::All);
+        assert_eq!(fan_out.targets.len(), MAX_FAN_OUT_BRANCHES);
+        assert_eq!(fan_out.targets[0].provider, "p0");
+        assert_eq!(fan_out.targets[0].model, "m0");
+
+        let empty: Headers = vec![(FAN_OUT_HEADER.to_string(), "nothing".to_string())].into();
+        assert!(FanOut::from_headers(&empty).is_none());
+    }
+
+    #[test]
+    fn profile_settings_default_to_first() {
+        let settings = json!({
+            "fan_out": { "targets": [{ "provider": "openai", "model": "gpt-4o" }] }
+        });
+        let fan_out = FanOut::from_settings(&settings).unwrap();
+        assert_eq!(fan_out.mode, FanOutMode::First);
+        assert!(FanOut::from_settings(&json!({ "fan_out": { "targets": [] } })).is_none());
+        assert!(FanOut::from_settings(&json!({})).is_none());
+    }
+
+    #[test]
+    fn answers_are_combined_in_target_order() {
+        let targets = vec![
+            FanOutTarget {
+                provider: "a".to_string(),
+                model: "m1".to_string(),
+            },
+            FanOutTarget {
+                provider: "b".to_string(),
+                model: "m2".to_string(),
+            },
+        ];
+        let resp = combine(
+            &targets,
+            vec![
+                (1, response(502, "bad gateway")),
+                (0, response(200, r#"{"id":"x"}"#)),
+            ],
+        );
+        let UpstreamBody::Bytes(body) = resp.body else {
+            unreachable!()
+        };
+        let body: JsonValue = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["answers"][0]["provider"], "a");
+        assert_eq!(body["answers"][0]["response"]["id"], "x");
+        assert_eq!(body["answers"][1]["status"], 502);
+        assert_eq!(body["answers"][1]["response"], "bad gateway");
+
+        let failure = first_failure(vec![(1, response(500, "")), (0, response(429, ""))]);
+        assert_eq!(failure.unwrap().status, 429);
+    }
+}
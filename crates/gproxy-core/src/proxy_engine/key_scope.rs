@@ -0,0 +1,72 @@
+//! Providers and models a user key may call, for temporary keys handed to demos, CI
+//! pipelines or contractors. A key without a `key_scope` may call anything its
+//! organization's grants allow.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KeyScope {
+    /// Provider names; empty allows every provider.
+    #[serde(default)]
+    pub providers: Vec<String>,
+    /// Model names as sent upstream, a trailing `*` matching any suffix; empty allows
+    /// every model.
+    #[serde(default)]
+    pub models: Vec<String>,
+}
+
+impl KeyScope {
+    pub fn from_json(value: &JsonValue) -> Result<Self, String> {
+        let scope: Self = serde_json::from_value(value.clone()).map_err(|err| err.to_string())?;
+        if let Some(model) = scope
+            .models
+            .iter()
+            .find(|m| m.trim_end_matches('*').contains('*') || m.trim().is_empty())
+        {
+            return Err(format!("model `{model}`: only a trailing `*` is supported"));
+        }
+        Ok(scope)
+    }
+
+    pub fn allows_provider(&self, provider: &str) -> bool {
+        self.providers.is_empty() || self.providers.iter().any(|p| p == provider)
+    }
+
+    pub fn allows_model(&self, model: &str) -> bool {
+        self.models.is_empty()
+            || self
+                .models
+                .iter()
+                .any(|pattern| match pattern.strip_suffix('*') {
+                    Some(prefix) => model.starts_with(prefix),
+                    None => model == pattern,
+                })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scope_matches_providers_and_model_prefixes() {
+        let scope = KeyScope::from_json(&serde_json::json!({
+            "providers": ["claude"],
+            "models": ["claude-sonnet-4*", "claude-3-5-haiku-latest"],
+        }))
+        .unwrap();
+        assert!(scope.allows_provider("claude"));
+        assert!(!scope.allows_provider("openai"));
+        assert!(scope.allows_model("claude-sonnet-4-5"));
+        assert!(scope.allows_model("claude-3-5-haiku-latest"));
+        assert!(!scope.allows_model("claude-opus-4-1"));
+
+        let open = KeyScope::from_json(&serde_json::json!({})).unwrap();
+        assert!(open.allows_provider("openai") && open.allows_model("gpt-5"));
+
+        assert!(KeyScope::from_json(&serde_json::json!({ "models": ["gpt-*-mini"] })).is_err());
+        assert!(KeyScope::from_json(&serde_json::json!({ "model": ["gpt-5"] })).is_err());
+    }
+}
@@ -0,0 +1,346 @@
+//! Remote MCP servers declared in OpenAI Responses requests: the per-key allowlist, auth
+//! injected from stored secrets, and the tool calls upstream reports back.
+//!
+//! A key without an `mcp_policy` passes MCP tools through untouched. A key with one may
+//! only declare servers its policy lists; each listed server can carry an authorization
+//! token and headers, named by secret, that replace whatever the client sent.
+
+use std::collections::BTreeMap;
+
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+use gproxy_protocol::openai::create_response::types::{MCPTool, Tool};
+use gproxy_protocol::sse::SseParser;
+use gproxy_provider_core::provider::ByteStream;
+use gproxy_provider_core::{GenerateContentRequest, Request};
+
+/// Longest argument, output or error text kept per recorded call.
+const MAX_RECORDED_CHARS: usize = 16 * 1024;
+
+/// MCP servers a user key may declare.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct McpPolicy {
+    #[serde(default)]
+    pub servers: Vec<McpServerRule>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct McpServerRule {
+    /// Allowed `server_url` prefix; a match must end at a path or query boundary.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Allowed OpenAI connector, e.g. `connector_gmail`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connector_id: Option<String>,
+    /// Secret sent as the tool's `authorization`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub authorization_secret: Option<String>,
+    /// Header name to the secret sent as its value.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+}
+
+impl McpPolicy {
+    pub fn from_json(value: &JsonValue) -> Result<Self, String> {
+        let policy: Self = serde_json::from_value(value.clone()).map_err(|err| err.to_string())?;
+        for rule in &policy.servers {
+            match (&rule.url, &rule.connector_id) {
+                (Some(url), None) if url.starts_with("https://") || url.starts_with("http://") => {}
+                (Some(url), None) => return Err(format!("url `{url}` must be http(s)")),
+                (None, Some(_)) => {}
+                _ => return Err("each server needs exactly one of url and connector_id".into()),
+            }
+        }
+        Ok(policy)
+    }
+
+    fn rule_for(&self, tool: &MCPTool) -> Option<&McpServerRule> {
+        let connector = tool
+            .connector_id
+            .and_then(|id| serde_json::to_value(id).ok())
+            .and_then(|id| id.as_str().map(str::to_string));
+        self.servers
+            .iter()
+            .find(|rule| match (&rule.url, &rule.connector_id) {
+                (Some(prefix), _) => tool
+                    .server_url
+                    .as_deref()
+                    .is_some_and(|url| url_matches(prefix, url)),
+                (None, Some(id)) => connector.as_deref() == Some(id.as_str()),
+                (None, None) => false,
+            })
+    }
+}
+
+fn url_matches(prefix: &str, url: &str) -> bool {
+    let Some(rest) = url.strip_prefix(prefix) else {
+        return false;
+    };
+    rest.is_empty() || prefix.ends_with('/') || rest.starts_with(['/', '?'])
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum McpPolicyError {
+    /// The server of this label is not in the key's policy.
+    NotAllowed(String),
+    /// The policy names a secret that is not stored.
+    MissingSecret(String),
+}
+
+/// Checks the MCP tools of a Responses request against `policy` and injects their auth.
+/// Other requests are left alone.
+pub(super) fn apply_policy(
+    policy: &McpPolicy,
+    req: &mut Request,
+    secret: impl Fn(&str) -> Option<String>,
+) -> Result<(), McpPolicyError> {
+    let Request::GenerateContent(GenerateContentRequest::OpenAIResponse(r)) = req else {
+        return Ok(());
+    };
+    let Some(tools) = r.body.tools.as_mut() else {
+        return Ok(());
+    };
+    for tool in tools {
+        let Tool::MCP(tool) = tool else {
+            continue;
+        };
+        let Some(rule) = policy.rule_for(tool) else {
+            return Err(McpPolicyError::NotAllowed(tool.server_label.clone()));
+        };
+        let lookup =
+            |name: &String| secret(name).ok_or_else(|| McpPolicyError::MissingSecret(name.clone()));
+        if let Some(name) = &rule.authorization_secret {
+            tool.authorization = Some(lookup(name)?);
+        }
+        for (header, name) in &rule.headers {
+            tool.headers
+                .get_or_insert_with(BTreeMap::new)
+                .insert(header.clone(), lookup(name)?);
+        }
+    }
+    Ok(())
+}
+
+/// A tool call upstream made on an MCP server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct McpCall {
+    pub server_label: String,
+    pub tool_name: String,
+    pub arguments: Option<String>,
+    pub output: Option<String>,
+    pub error: Option<String>,
+}
+
+fn call_from_item(item: &JsonValue) -> Option<McpCall> {
+    if item.get("type")?.as_str()? != "mcp_call" {
+        return None;
+    }
+    let text = |key: &str| {
+        let value = item.get(key)?;
+        let text = match value {
+            JsonValue::Null => return None,
+            JsonValue::String(text) => text.clone(),
+            other => other.to_string(),
+        };
+        Some(text.chars().take(MAX_RECORDED_CHARS).collect())
+    };
+    Some(McpCall {
+        server_label: item.get("server_label")?.as_str()?.to_string(),
+        tool_name: item.get("name")?.as_str()?.to_string(),
+        arguments: text("arguments"),
+        output: text("output"),
+        error: text("error"),
+    })
+}
+
+/// MCP calls among the output items of a non-stream Responses body.
+pub(super) fn calls_in_response(body: &[u8]) -> Vec<McpCall> {
+    let Ok(value) = serde_json::from_slice::<JsonValue>(body) else {
+        return Vec::new();
+    };
+    value
+        .get("output")
+        .and_then(JsonValue::as_array)
+        .map(|items| items.iter().filter_map(call_from_item).collect())
+        .unwrap_or_default()
+}
+
+/// Forwards a Responses SSE stream unchanged, passing each finished MCP call to `on_call`.
+pub(super) fn observe_stream(
+    mut rx: ByteStream,
+    on_call: impl Fn(McpCall) + Send + 'static,
+) -> ByteStream {
+    let (tx, rx_out) = tokio::sync::mpsc::channel::<Bytes>(32);
+    tokio::spawn(async move {
+        let mut parser = SseParser::new();
+        while let Some(chunk) = rx.recv().await {
+            for ev in parser.push_bytes(&chunk) {
+                if let Some(call) = call_from_event(&ev.data) {
+                    on_call(call);
+                }
+            }
+            if tx.send(chunk).await.is_err() {
+                return;
+            }
+        }
+        for ev in parser.finish() {
+            if let Some(call) = call_from_event(&ev.data) {
+                on_call(call);
+            }
+        }
+    });
+    rx_out
+}
+
+fn call_from_event(data: &str) -> Option<McpCall> {
+    let value: JsonValue = serde_json::from_str(data).ok()?;
+    if value.get("type")?.as_str()? != "response.output_item.done" {
+        return None;
+    }
+    call_from_item(value.get("item")?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn request(tools: JsonValue) -> Request {
+        let body = json!({ "model": "gpt-4.1", "input": "hi", "tools": tools });
+        Request::GenerateContent(GenerateContentRequest::OpenAIResponse(
+            gproxy_protocol::openai::create_response::request::CreateResponseRequest {
+                body: serde_json::from_value(body).unwrap(),
+            },
+        ))
+    }
+
+    fn mcp_tools(req: &Request) -> Vec<MCPTool> {
+        let Request::GenerateContent(GenerateContentRequest::OpenAIResponse(r)) = req else {
+            unreachable!()
+        };
+        r.body
+            .tools
+            .iter()
+            .flatten()
+            .filter_map(|tool| match tool {
+                Tool::MCP(tool) => Some(tool.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn policy() -> McpPolicy {
+        McpPolicy::from_json(&json!({
+            "servers": [
+                {
+                    "url": "https://mcp.example.com/docs",
+                    "authorization_secret": "docs-token",
+                    "headers": { "x-tenant": "docs-tenant" },
+                },
+                { "connector_id": "connector_gmail" },
+            ],
+        }))
+        .unwrap()
+    }
+
+    fn secrets(name: &str) -> Option<String> {
+        match name {
+            "docs-token" => Some("tok".to_string()),
+            "docs-tenant" => Some("acme".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn allowed_servers_get_their_secrets_injected() {
+        let mut req = request(json!([
+            {
+                "type": "mcp",
+                "server_label": "docs",
+                "server_url": "https://mcp.example.com/docs/sse",
+                "authorization": "client-token",
+            },
+            { "type": "mcp", "server_label": "mail", "connector_id": "connector_gmail" },
+            { "type": "function", "name": "f", "parameters": {} },
+        ]));
+        apply_policy(&policy(), &mut req, secrets).unwrap();
+        let tools = mcp_tools(&req);
+        assert_eq!(tools[0].authorization.as_deref(), Some("tok"));
+        assert_eq!(
+            tools[0].headers.as_ref().unwrap().get("x-tenant").unwrap(),
+            "acme"
+        );
+        assert_eq!(tools[1].authorization, None);
+    }
+
+    #[test]
+    fn unlisted_servers_and_missing_secrets_are_refused() {
+        for url in [
+            "https://mcp.example.com/docsx",
+            "https://evil.example.com/docs",
+        ] {
+            let mut req = request(json!([
+                { "type": "mcp", "server_label": "docs", "server_url": url },
+            ]));
+            assert_eq!(
+                apply_policy(&policy(), &mut req, secrets),
+                Err(McpPolicyError::NotAllowed("docs".to_string()))
+            );
+        }
+        let mut req = request(json!([
+            { "type": "mcp", "server_label": "docs", "server_url": "https://mcp.example.com/docs" },
+        ]));
+        assert_eq!(
+            apply_policy(&policy(), &mut req, |_| None),
+            Err(McpPolicyError::MissingSecret("docs-token".to_string()))
+        );
+        assert!(McpPolicy::from_json(&json!({ "servers": [{}] })).is_err());
+        assert!(McpPolicy::from_json(&json!({ "servers": [{ "url": "ftp://x" }] })).is_err());
+    }
+
+    #[test]
+    fn mcp_calls_are_read_from_responses_and_stream_events() {
+        let body = json!({
+            "output": [
+                { "type": "message", "content": [] },
+                {
+                    "type": "mcp_call",
+                    "server_label": "docs",
+                    "name": "search",
+                    "arguments": "{\"q\":\"rust\"}",
+                    "output": "3 results",
+                    "error": null,
+                },
+            ],
+        });
+        let calls = calls_in_response(body.to_string().as_bytes());
+        assert_eq!(
+            calls,
+            vec![McpCall {
+                server_label: "docs".to_string(),
+                tool_name: "search".to_string(),
+                arguments: Some("{\"q\":\"rust\"}".to_string()),
+                output: Some("3 results".to_string()),
+                error: None,
+            }]
+        );
+
+        let event = json!({
+            "type": "response.output_item.done",
+            "output_index": 1,
+            "item": {
+                "type": "mcp_call",
+                "server_label": "docs",
+                "name": "fetch",
+                "arguments": "{}",
+                "error": { "message": "timeout" },
+            },
+        });
+        let call = call_from_event(&event.to_string()).unwrap();
+        assert_eq!(call.tool_name, "fetch");
+        assert_eq!(call.error.as_deref(), Some(r#"{"message":"timeout"}"#));
+        assert!(call_from_event(r#"{"type":"response.output_text.delta"}"#).is_none());
+    }
+}
@@ -1,49 +1,103 @@
-use std::collections::VecDeque;
+use std::collections::{HashSet, VecDeque};
+use std::net::IpAddr;
 use std::sync::Arc;
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, Instant, SystemTime};
 
 use bytes::Bytes;
 
 use gproxy_provider_core::AcquireError;
 use gproxy_provider_core::Event;
 use gproxy_provider_core::UnavailableReason;
-use gproxy_provider_core::config::{DispatchRule, OperationKind};
-use gproxy_provider_core::provider::{ByteStream, UpstreamFailure};
+use gproxy_provider_core::config::{DispatchRule, DispatchTable, OperationKind};
+use gproxy_provider_core::provider::{
+    ByteStream, InternalEventUnwrap, UnavailableDecision, UpstreamFailure,
+    UpstreamTransportErrorKind, decide_unavailable_with_failure_rule,
+};
 use gproxy_provider_core::{
     AuthRetryAction, CountTokensFn, CountTokensRequest, CountTokensResponse, Credential,
-    GenerateContentRequest, GenerateContentResponse, Headers, HttpMethod, ModelGetResponse,
-    ModelListResponse, Op, OutputAccumulator, Proto, ProviderConfig, ProviderError,
-    ProviderRegistry, ProviderResult, Request, Response, StreamEvent, TransformContext,
+    DisallowRule, EgressPolicy, FailureAction, FailureMatch, GenerateContentRequest,
+    GenerateContentResponse, HeaderPolicy, Headers, HttpMethod, ModelGetResponse,
+    ModelListResponse, Op, OutputAccumulator, ParsingMode, PostProcessPolicy, Proto,
+    ProviderConfig, ProviderError, ProviderPolicies, ProviderRegistry, ProviderResult,
+    RawPassthroughRequest, Request, Response, SemanticCacheSettings, StreamEvent, TransformContext,
     TransformError, UpstreamBody, UpstreamCtx, UpstreamEvent, UpstreamHttpRequest,
-    UpstreamHttpResponse, UpstreamProvider, UsageAccumulator, UsageSummary,
-    fallback_usage_with_count_tokens, header_set, usage_from_response,
+    UpstreamHttpResponse, UpstreamProvider, UpstreamTimeouts, UsageAccumulator, UsageSummary,
+    fallback_usage_with_count_tokens, header_betas, header_get, header_remove, header_set,
+    usage_from_response,
 };
+use gproxy_provider_core::{OperationalEvent, UserKeyAutoDisabledEvent};
 
-use gproxy_transform::middleware::{
-    NostreamToStream, StreamToNostream, StreamTransformer, stream_format,
-};
+use gproxy_storage::{AbortedStreamRecord, McpToolCallRecord, ToolCallRecord};
+use gproxy_transform::middleware::{NostreamToStream, StreamToNostream, stream_format};
 
-use crate::state::{AppState, CredentialInsertInput, ProviderRuntime};
-use crate::upstream_client::UpstreamClient;
+use crate::state::{
+    AppState, CredentialInsertInput, CredentialScope, GeoInfo, KeyAbuseRules, OrgRejection,
+    ProviderCanary, ProviderRuntime, TrustedProxies, rate_limit_headers,
+};
+use crate::upstream_client::{SendOptions, UpstreamClient};
 
 use gproxy_protocol::claude::count_tokens::types::Model as ClaudeModel;
 use gproxy_protocol::sse::SseParser;
 use serde_json::{self, Value as JsonValue};
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
 
+mod auth;
+mod candidates;
+mod coalesce;
 mod dispatch;
+mod error_body;
+mod experiments;
+mod fan_out;
+mod key_scope;
+mod mcp;
+mod moderation;
+mod origins;
+mod output_cap;
+mod pacing;
+mod post_process;
+mod profiles;
+mod raw_json;
+mod response_limit;
+mod semantic_cache;
+mod stream_prime;
+mod tool_calls;
+mod transcode;
 mod types;
 mod wire;
 
-pub use types::ProxyAuth;
+pub use auth::{
+    AuthChain, AuthDecision, AuthFuture, AuthProvider, AuthRequest, FORWARD_AUTH_USER_KEY_HEADER,
+    ForwardAuthProvider, SnapshotAuthProvider,
+};
+pub use error_body::{ERROR_CODE_HEADER, EngineError, ErrorType};
+pub use key_scope::KeyScope;
+pub use mcp::{McpPolicy, McpServerRule};
+pub use moderation::{MODERATION_HEADER, ModerationAction, ModerationCheck, ModerationPolicy};
+pub use origins::AllowedOrigins;
+pub use response_limit::RESPONSE_TRUNCATED_HEADER;
+pub use semantic_cache::{SEMANTIC_CACHE_HEADER, SEMANTIC_SIMILARITY_HEADER};
 pub use types::ProxyCall;
+pub use types::{ExperimentAssignment, ProxyAuth};
+pub use wire::{StreamDecoder, encode_stream_event};
 
+use coalesce::{CoalesceKey, InflightRequests, Slot, coalesce_key};
 use dispatch::{GenerateMode, ResolvedCall};
-use wire::{StreamDecoder, content_type_for_stream, encode_openai_chat_done, encode_stream_event};
+use error_body::{decorate_error_response, translate_upstream_error};
+use experiments::Experiment;
+use fan_out::{FanOut, FanOutMode};
+use mcp::{McpCall, McpPolicyError};
+use moderation::Verdict;
+use profiles::ModelProfile;
+use stream_prime::{StreamPrime, StreamStall, is_stall_response, prime_stream};
+use tool_calls::ToolCall;
+use transcode::{StreamEnd, StreamTranscode};
+use wire::{content_type_for_stream, encode_openai_chat_done, encode_stream_error};
 
 type ProviderContext = (
     Arc<dyn UpstreamProvider>,
     Arc<ProviderRuntime>,
-    ProviderConfig,
+    Arc<ProviderConfig>,
 );
 
 struct NonGenerateUnavailableInput<'a> {
@@ -80,9 +134,60 @@ struct UpstreamEventInput<'a> {
 struct ProtocolRouteCtx {
     provider: String,
     response_model_prefix_provider: Option<String>,
+    /// Model name responses report instead of the upstream one.
+    alias: Option<String>,
+}
+
+/// Why a request goes upstream verbatim.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RawCall {
+    /// A client request on an untyped path; the provider's `raw_passthrough` policy
+    /// must allow it.
+    Passthrough,
+    /// The prompt embedding of a semantic cache lookup.
+    Embedding,
+    /// A moderation check of a prompt or answer.
+    Moderation,
+}
+
+impl RawCall {
+    fn operation(self) -> &'static str {
+        match self {
+            RawCall::Passthrough => "RawPassthrough",
+            RawCall::Embedding => "Embedding",
+            RawCall::Moderation => "Moderation",
+        }
+    }
+}
+
+/// How model names in responses are rewritten before reaching the client.
+#[derive(Debug, Clone)]
+struct ModelRewrite {
+    /// Provider prepended to model names on aggregate routes.
+    prefix_provider: Option<String>,
+    /// Profile name reported in place of the upstream model.
+    alias: Option<String>,
 }
 
-const MAX_UPSTREAM_LOG_BODY_BYTES: usize = 50 * 1024 * 1024;
+impl ModelRewrite {
+    fn is_none(&self) -> bool {
+        self.prefix_provider.is_none() && self.alias.is_none()
+    }
+
+    /// Model name reported for generated content.
+    fn generated_model(&self, model: &str) -> String {
+        if let Some(alias) = &self.alias {
+            return alias.clone();
+        }
+        match &self.prefix_provider {
+            Some(provider) => prefix_model_string(model, provider),
+            None => model.to_string(),
+        }
+    }
+}
+
+/// Reconnects attempted for one interrupted stream before giving up.
+const MAX_STREAM_RESUMES: u32 = 3;
 
 macro_rules! emit_upstream_event {
     (
@@ -127,6 +232,8 @@ pub struct ProxyEngine {
     registry: Arc<ProviderRegistry>,
     client: Arc<dyn UpstreamClient>,
     storage: Arc<dyn gproxy_storage::Storage>,
+    auth: Arc<dyn AuthProvider>,
+    inflight: Arc<InflightRequests>,
 }
 
 impl ProxyEngine {
@@ -141,85 +248,1183 @@ impl ProxyEngine {
             registry,
             client,
             storage,
+            auth: Arc::new(SnapshotAuthProvider),
+            inflight: Arc::new(InflightRequests::default()),
+        }
+    }
+
+    /// Replaces the default snapshot-backed key lookup; use an [`AuthChain`] to combine
+    /// several providers.
+    pub fn with_auth_provider(mut self, auth: Arc<dyn AuthProvider>) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    pub fn events(&self) -> gproxy_provider_core::EventHub {
+        self.state.events.clone()
+    }
+
+    pub fn event_redact_sensitive(&self) -> bool {
+        self.state.global.load().event_redact_sensitive
+    }
+
+    /// Bytes of a streamed body kept in its event log.
+    pub fn event_log_max_body_bytes(&self) -> usize {
+        usize::try_from(self.state.global.load().event_log_max_body_bytes).unwrap_or(usize::MAX)
+    }
+
+    /// Takes one of the key's pending debug captures for this request; its events then keep
+    /// their bodies (see [`crate::state::DebugCaptures`]).
+    pub fn claim_debug_capture(&self, user_key_id: i64, trace_id: &str) -> bool {
+        self.state
+            .debug_captures
+            .claim(user_key_id, trace_id, self.event_redact_sensitive())
+    }
+
+    /// Whether events of this request leave out bodies: redaction is on and the request is
+    /// not being captured for debugging.
+    fn redact_bodies(&self, trace_id: Option<&str>) -> bool {
+        self.event_redact_sensitive() && !self.state.debug_captures.captures(trace_id)
+    }
+
+    pub fn response_compress_min_bytes(&self) -> u64 {
+        self.state.global.load().response_compress_min_bytes
+    }
+
+    /// The 503 every proxy call gets while maintenance mode is on.
+    pub fn maintenance_response(&self) -> Option<UpstreamHttpResponse> {
+        let message = self.state.system_mode.maintenance_message()?;
+        Some(json_error_with(503, "maintenance", message))
+    }
+
+    pub async fn authenticate(
+        &self,
+        request: &AuthRequest,
+    ) -> Option<crate::proxy_engine::ProxyAuth> {
+        match self.auth.authenticate(&self.state, request).await {
+            AuthDecision::Allow(auth) => Some(*auth),
+            AuthDecision::Deny | AuthDecision::Pass => None,
+        }
+    }
+
+    /// Whether `peer` is a configured reverse proxy whose forwarding headers are believed.
+    pub fn is_trusted_proxy(&self, peer: IpAddr) -> bool {
+        TrustedProxies::from_global(&self.state.global.load()).contains(peer)
+    }
+
+    /// Country and ASN of a client. The local database wins; `edge_country`, which callers
+    /// only take from trusted proxies, fills in a country the database does not know.
+    pub fn client_geo(&self, client_ip: Option<&str>, edge_country: Option<String>) -> GeoInfo {
+        client_ip
+            .map(|ip| self.state.geoip.lookup(ip))
+            .unwrap_or_default()
+            .or_edge_country(edge_country)
+    }
+
+    /// The proxy of the provider's egress pool this attempt goes through, if it has one.
+    fn pick_egress_proxy(&self, egress: &EgressPolicy, credential_id: i64) -> Option<String> {
+        self.state.egress_proxies.pick(
+            &egress.proxies,
+            egress.proxy_rotation.unwrap_or_default(),
+            credential_id,
+        )
+    }
+
+    /// Takes a pool proxy out of rotation when the connection through it failed, and
+    /// back in once a response came through it.
+    fn record_egress_proxy(&self, proxy: Option<&str>, failure: Option<&UpstreamFailure>) {
+        let Some(proxy) = proxy else {
+            return;
+        };
+        match failure {
+            None => self.state.egress_proxies.mark_alive(proxy),
+            Some(UpstreamFailure::Transport {
+                kind:
+                    UpstreamTransportErrorKind::Connect | UpstreamTransportErrorKind::ConnectTimeout,
+                message,
+            }) => self.state.egress_proxies.mark_dead(proxy, message),
+            Some(_) => {}
+        }
+    }
+
+    /// Runs the leaked-key rules for one request. When a rule trips, the key is disabled
+    /// in storage and memory, the evidence is emitted as an operational event and posted
+    /// to the alert webhook if one is configured. Returns whether the key was disabled.
+    pub async fn screen_key_abuse(
+        &self,
+        auth: &crate::proxy_engine::ProxyAuth,
+        client_ip: Option<&str>,
+        country: Option<&str>,
+    ) -> bool {
+        let global = self.state.global.load();
+        let rules = KeyAbuseRules::from_global(&global);
+        if !rules.is_active() {
+            return false;
+        }
+        let Some(verdict) =
+            self.state
+                .key_abuse
+                .observe(auth.user_key_id, client_ip, country, &rules)
+        else {
+            return false;
+        };
+
+        if let Err(err) = self
+            .storage
+            .set_user_key_enabled(auth.user_key_id, false)
+            .await
+        {
+            gproxy_common::log_error!(
+                "key_abuse",
+                "auto-disable user key {}: {err}",
+                auth.user_key_id
+            );
+        }
+        self.state.apply_user_key_enabled(auth.user_key_id, false);
+
+        let event = UserKeyAutoDisabledEvent {
+            at: SystemTime::now(),
+            user_id: auth.user_id,
+            user_key_id: auth.user_key_id,
+            rule: verdict.rule.to_string(),
+            requests: verdict.requests,
+            client_ips: verdict.client_ips,
+            country: verdict.country,
+        };
+        if let Some(url) = global.alert_webhook_url.clone() {
+            let body = serde_json::json!({
+                "event": "user_key_auto_disabled",
+                "at": OffsetDateTime::now_utc().format(&Rfc3339).ok(),
+                "user_id": event.user_id,
+                "user_key_id": event.user_key_id,
+                "rule": event.rule,
+                "requests": event.requests,
+                "client_ips": event.client_ips,
+                "country": event.country,
+            });
+            let req = UpstreamHttpRequest {
+                method: HttpMethod::Post,
+                url,
+                headers: vec![("content-type".to_string(), "application/json".to_string())].into(),
+                body: Some(Bytes::from(body.to_string())),
+                is_stream: false,
+            };
+            let client = self.client.clone();
+            tokio::spawn(async move {
+                if let Err(err) = client.send(req).await {
+                    gproxy_common::log_warn!("alert_webhook", "{err:?}");
+                }
+            });
+        }
+        self.state
+            .events
+            .emit(Event::Operational(OperationalEvent::UserKeyAutoDisabled(
+                event,
+            )))
+            .await;
+        true
+    }
+
+    pub async fn handle(&self, call: ProxyCall) -> UpstreamHttpResponse {
+        let (trace_id, provider, native_proto) = match &call {
+            ProxyCall::Protocol {
+                trace_id,
+                provider,
+                user_proto,
+                ..
+            } => (trace_id.clone(), provider.clone(), Some(*user_proto)),
+            ProxyCall::OAuthStart {
+                trace_id, provider, ..
+            }
+            | ProxyCall::OAuthCallback {
+                trace_id, provider, ..
+            }
+            | ProxyCall::UpstreamUsage {
+                trace_id, provider, ..
+            }
+            | ProxyCall::RawPassthrough {
+                trace_id, provider, ..
+            } => (trace_id.clone(), provider.clone(), None),
+        };
+        let (sse_key, stream_tps, output_cap) = match &call {
+            ProxyCall::Protocol { auth, .. } => (
+                Some(auth.user_key_id).filter(|_| self.state.global.load().sse_resume),
+                auth.stream_tps,
+                auth.max_output_tokens,
+            ),
+            _ => (None, None, None),
+        };
+        // A client reconnecting to a stream it lost gets the rest of that stream instead of
+        // a new generation.
+        if let ProxyCall::Protocol { auth, .. } = &call
+            && let Some(user_key_id) = sse_key
+            && let Some(last_event_id) = header_get(&auth.request_headers, "last-event-id")
+            && let Some(rx) = self.state.sse_replay.resume(last_event_id, user_key_id)
+        {
+            return UpstreamHttpResponse {
+                status: 200,
+                headers: vec![("content-type".to_string(), "text/event-stream".to_string())].into(),
+                body: UpstreamBody::Stream(match stream_tps {
+                    Some(tps) => pacing::pace_stream(rx, tps),
+                    None => rx,
+                }),
+            };
+        }
+        let post_process = match &call {
+            ProxyCall::Protocol {
+                provider, user_op, ..
+            } if is_generate_op(*user_op) => self.post_process_policy(provider),
+            _ => None,
+        };
+        let mcp_calls = match &call {
+            ProxyCall::Protocol {
+                auth,
+                user_proto: Proto::OpenAIResponse,
+                user_op,
+                ..
+            } if is_generate_op(*user_op) => {
+                Some(self.mcp_call_recorder(trace_id.clone(), auth.user_key_id, provider.clone()))
+            }
+            _ => None,
+        };
+        let audited_calls = match &call {
+            ProxyCall::Protocol {
+                auth,
+                user_proto,
+                user_op,
+                req,
+                ..
+            } if is_generate_op(*user_op) && self.state.global.load().tool_call_audit => {
+                self.state
+                    .tool_calls
+                    .results(auth.user_key_id, tool_calls::result_ids(req));
+                Some(self.tool_call_recorder(
+                    trace_id.clone(),
+                    auth.user_key_id,
+                    provider.clone(),
+                    extract_model_from_request(req),
+                    *user_proto,
+                ))
+            }
+            _ => None,
+        };
+        let rate_limit = match &call {
+            ProxyCall::Protocol { auth, .. } | ProxyCall::RawPassthrough { auth, .. }
+                if !auth.rate_limits.is_unlimited() =>
+            {
+                Some(
+                    self.state
+                        .key_rates
+                        .admit(auth.user_key_id, auth.rate_limits),
+                )
+            }
+            _ => None,
+        };
+        // Organization limits are only counted for requests the key's own limits let through.
+        let org_rejection = match &call {
+            ProxyCall::Protocol { auth, .. } | ProxyCall::RawPassthrough { auth, .. }
+                if !matches!(rate_limit, Some(Err(_))) =>
+            {
+                auth.org_id.and_then(|org_id| self.org_rejection(org_id))
+            }
+            _ => None,
+        };
+        let mut resp = if let Some(Err(status)) = rate_limit {
+            let mut resp = json_error(429, "key_rate_limited");
+            header_set(
+                &mut resp.headers,
+                "retry-after",
+                status.retry_after().as_secs().max(1).to_string(),
+            );
+            resp
+        } else if let Some(rejection) = org_rejection {
+            match rejection {
+                OrgRejection::RateLimited(status) => {
+                    let mut resp = json_error(429, "org_rate_limited");
+                    header_set(
+                        &mut resp.headers,
+                        "retry-after",
+                        status.retry_after().as_secs().max(1).to_string(),
+                    );
+                    resp
+                }
+                OrgRejection::BudgetExhausted => json_error(429, "org_budget_exhausted"),
+            }
+        } else {
+            let coalesce = if self.state.global.load().coalesce_inflight_requests {
+                coalesce_key(&call)
+            } else {
+                None
+            };
+            match coalesce {
+                Some(key) => self.dispatch_coalesced(key, call).await,
+                None => self.dispatch_call(call).await,
+            }
+        };
+        if let Some(proto) = native_proto {
+            if let Some(policies) = self.provider_policies(&provider) {
+                policies.header.apply_response(&mut resp.headers);
+            }
+            if let Some(Ok(status) | Err(status)) = rate_limit {
+                rate_limit_headers(proto, &status, &mut resp.headers);
+            }
+        }
+        if resp.status < 400 {
+            let is_sse = header_get(&resp.headers, "content-type")
+                .is_some_and(|v| v.contains("text/event-stream"));
+            if let Some(record) = mcp_calls {
+                resp.body = match resp.body {
+                    UpstreamBody::Stream(rx) if is_sse => {
+                        UpstreamBody::Stream(mcp::observe_stream(rx, record))
+                    }
+                    UpstreamBody::Bytes(body) => {
+                        mcp::calls_in_response(&body).into_iter().for_each(&record);
+                        UpstreamBody::Bytes(body)
+                    }
+                    body => body,
+                };
+            }
+            if let Some(record) = audited_calls
+                && let Some(proto) = native_proto
+            {
+                resp.body = match resp.body {
+                    UpstreamBody::Stream(rx) if is_sse => {
+                        UpstreamBody::Stream(tool_calls::observe_stream(rx, proto, record))
+                    }
+                    UpstreamBody::Bytes(body) => {
+                        tool_calls::calls_in_response(proto, &body)
+                            .into_iter()
+                            .for_each(&record);
+                        UpstreamBody::Bytes(body)
+                    }
+                    body => body,
+                };
+            }
+            if let Some(policy) = post_process
+                && let Some(proto) = native_proto
+            {
+                resp.body = match resp.body {
+                    UpstreamBody::Stream(rx) => UpstreamBody::Stream(post_process::process_stream(
+                        rx, proto, policy, is_sse,
+                    )),
+                    UpstreamBody::Bytes(body) => {
+                        match post_process::process_response(proto, &policy, &body) {
+                            Some(processed) => {
+                                header_remove(&mut resp.headers, "content-length");
+                                UpstreamBody::Bytes(processed)
+                            }
+                            None => UpstreamBody::Bytes(body),
+                        }
+                    }
+                };
+            }
+            if let Some(cap) = output_cap
+                && let Some(proto) = native_proto
+                && is_sse
+            {
+                resp.body = match resp.body {
+                    UpstreamBody::Stream(rx) => {
+                        UpstreamBody::Stream(output_cap::cap_stream(rx, proto, cap))
+                    }
+                    body => body,
+                };
+            }
+            if let Some(user_key_id) = sse_key
+                && is_sse
+            {
+                resp.body = match resp.body {
+                    UpstreamBody::Stream(rx) => {
+                        UpstreamBody::Stream(self.state.sse_replay.track(user_key_id, rx))
+                    }
+                    body => body,
+                };
+            }
+            if let Some(tps) = stream_tps {
+                resp.body = match resp.body {
+                    UpstreamBody::Stream(rx) => UpstreamBody::Stream(pacing::pace_stream(rx, tps)),
+                    body => body,
+                };
+            }
+            return resp;
+        }
+        let native_proto = native_proto.filter(|_| self.state.global.load().native_error_format);
+        decorate_error_response(resp, &provider, trace_id.as_deref(), native_proto)
+    }
+
+    /// The provider's parsed policies, kept until its config changes.
+    fn provider_policies(&self, provider: &str) -> Option<Arc<ProviderPolicies>> {
+        self.state
+            .providers
+            .load()
+            .get(provider)
+            .map(|runtime| runtime.policies())
+    }
+
+    /// The provider's `post_process` rules, when it has any.
+    fn post_process_policy(&self, provider: &str) -> Option<Arc<PostProcessPolicy>> {
+        self.provider_policies(provider)?.post_process.clone()
+    }
+
+    /// Picks the provider that serves a request while `provider` may be in a maintenance
+    /// window. Fallbacks are followed one hop only and must not be in maintenance themselves.
+    fn maintenance_route(&self, provider: String) -> Result<String, UpstreamHttpResponse> {
+        let now = OffsetDateTime::now_utc();
+        let Some(policies) = self.provider_policies(&provider) else {
+            return Ok(provider);
+        };
+        let Some(window) = policies.maintenance.active_at(now) else {
+            return Ok(provider);
+        };
+        if let Some(fallback) = window.fallback_provider.as_ref()
+            && *fallback != provider
+            && self
+                .provider_policies(fallback)
+                .is_none_or(|policies| policies.maintenance.active_at(now).is_none())
+        {
+            return Ok(fallback.clone());
+        }
+        let message = window
+            .message
+            .clone()
+            .unwrap_or_else(|| format!("provider {provider} is under maintenance"));
+        let mut resp = json_error_with(503, "provider_maintenance", message);
+        let retry_after = (window.end - now).whole_seconds().max(1);
+        header_set(&mut resp.headers, "retry-after", retry_after.to_string());
+        Err(resp)
+    }
+
+    async fn dispatch_coalesced(&self, key: CoalesceKey, call: ProxyCall) -> UpstreamHttpResponse {
+        match self.inflight.join(key) {
+            Slot::Leader(guard) => {
+                let resp = self.dispatch_call(call).await;
+                guard.finish(&resp);
+                resp
+            }
+            Slot::Follower(rx) => match rx.await {
+                Ok(shared) => shared.into_response(),
+                // Leader went away or streamed its body; issue our own call.
+                Err(_) => self.dispatch_call(call).await,
+            },
+        }
+    }
+
+    async fn dispatch_call(&self, call: ProxyCall) -> UpstreamHttpResponse {
+        match call {
+            ProxyCall::OAuthStart {
+                trace_id,
+                auth,
+                provider,
+                req,
+            } => self.handle_oauth_start(trace_id, auth, provider, req).await,
+            ProxyCall::OAuthCallback {
+                trace_id,
+                auth,
+                provider,
+                req,
+            } => {
+                self.handle_oauth_callback(trace_id, auth, provider, req)
+                    .await
+            }
+            ProxyCall::UpstreamUsage {
+                trace_id,
+                auth,
+                provider,
+                credential_id,
+            } => {
+                self.handle_upstream_usage(trace_id, auth, provider, credential_id)
+                    .await
+            }
+            ProxyCall::RawPassthrough {
+                trace_id,
+                auth,
+                provider,
+                req,
+            } => {
+                self.handle_raw_passthrough(trace_id, auth, provider, *req)
+                    .await
+            }
+            ProxyCall::Protocol {
+                trace_id,
+                auth,
+                provider,
+                response_model_prefix_provider,
+                user_proto,
+                user_op,
+                mut req,
+            } => {
+                if let Some(policy) = &auth.mcp_policy
+                    && let Err(resp) = self.apply_mcp_policy(policy, &mut req)
+                {
+                    return resp;
+                }
+                let route_ctx = ProtocolRouteCtx {
+                    provider,
+                    response_model_prefix_provider,
+                    alias: None,
+                };
+                if is_generate_op(user_op)
+                    && let Some(policy) = auth.moderation_policy.clone()
+                {
+                    return self
+                        .handle_moderated(
+                            trace_id, auth, route_ctx, user_proto, user_op, *req, &policy,
+                        )
+                        .await;
+                }
+                self.route_protocol_call(trace_id, auth, route_ctx, user_proto, user_op, *req)
+                    .await
+            }
+        }
+    }
+
+    async fn route_protocol_call(
+        &self,
+        trace_id: Option<String>,
+        auth: ProxyAuth,
+        route_ctx: ProtocolRouteCtx,
+        user_proto: Proto,
+        user_op: Op,
+        req: Request,
+    ) -> UpstreamHttpResponse {
+        if let Some((fan_out, profile)) =
+            self.fan_out_for(&auth, &route_ctx.provider, user_op, &req)
+        {
+            return self
+                .handle_fan_out(
+                    trace_id, auth, route_ctx, user_proto, user_op, req, fan_out, profile,
+                )
+                .await;
+        }
+        if let Some(calls) =
+            self.candidate_fan_out_for(&route_ctx.provider, user_proto, user_op, &req)
+        {
+            return self
+                .handle_candidate_fan_out(
+                    trace_id, auth, route_ctx, user_proto, user_op, req, calls,
+                )
+                .await;
+        }
+        if user_op == Op::GenerateContent
+            && let Some(settings) = self.semantic_cache_settings(&route_ctx.provider)
+        {
+            return self
+                .handle_semantic_cached(trace_id, auth, route_ctx, user_proto, req, settings)
+                .await;
+        }
+        self.handle_protocol_call(trace_id, auth, route_ctx, user_proto, user_op, req)
+            .await
+    }
+
+    /// Runs a generate call past the key's moderation policy: the prompt before it goes
+    /// upstream, the answer of a non-stream call before it reaches the client. The
+    /// verdict is returned in [`MODERATION_HEADER`], which the downstream event records.
+    #[allow(clippy::too_many_arguments)]
+    async fn handle_moderated(
+        &self,
+        trace_id: Option<String>,
+        auth: ProxyAuth,
+        route_ctx: ProtocolRouteCtx,
+        user_proto: Proto,
+        user_op: Op,
+        req: Request,
+        policy: &ModerationPolicy,
+    ) -> UpstreamHttpResponse {
+        let mut verdict = Verdict::default();
+        if policy.checks_input()
+            && let Some(text) = moderation::request_text(&req)
+        {
+            verdict.merge(
+                self.moderate(trace_id.clone(), auth.clone(), policy, &text)
+                    .await,
+            );
+            if let Some(refusal) = moderation_refusal(policy, &verdict) {
+                return refusal;
+            }
+        }
+        let mut resp = self
+            .route_protocol_call(
+                trace_id.clone(),
+                auth.clone(),
+                route_ctx,
+                user_proto,
+                user_op,
+                req,
+            )
+            .await;
+        if policy.checks_output()
+            && resp.status < 400
+            && let UpstreamBody::Bytes(body) = &resp.body
+            && let Some(text) = moderation::response_text(body)
+        {
+            verdict.merge(self.moderate(trace_id, auth, policy, &text).await);
+            if let Some(refusal) = moderation_refusal(policy, &verdict) {
+                return refusal;
+            }
+        }
+        header_set(
+            &mut resp.headers,
+            MODERATION_HEADER,
+            verdict.header_value(policy.action),
+        );
+        resp
+    }
+
+    /// Verdict of the policy's moderation endpoint on `text`, billed to the caller's key
+    /// like any other upstream call.
+    async fn moderate(
+        &self,
+        trace_id: Option<String>,
+        auth: ProxyAuth,
+        policy: &ModerationPolicy,
+        text: &str,
+    ) -> Verdict {
+        let req = RawPassthroughRequest {
+            method: HttpMethod::Post,
+            path: policy.path.clone(),
+            query: None,
+            headers: vec![("content-type".to_string(), "application/json".to_string())].into(),
+            body: Some(moderation::moderation_request(policy, text)),
+        };
+        let resp = self
+            .send_raw(
+                trace_id,
+                auth,
+                policy.provider.clone(),
+                req,
+                RawCall::Moderation,
+            )
+            .await;
+        let verdict = match &resp.body {
+            UpstreamBody::Bytes(body) if (200..300).contains(&resp.status) => {
+                moderation::parse_verdict(policy, body)
+            }
+            _ => None,
+        };
+        verdict.unwrap_or_else(|| {
+            gproxy_common::log_warn!(
+                "moderation",
+                "moderation via {} failed with status {}",
+                policy.provider,
+                resp.status
+            );
+            Verdict::failure()
+        })
+    }
+
+    /// Holds the MCP tools of a Responses request to the key's allowlist and injects
+    /// their auth from stored secrets.
+    fn apply_mcp_policy(
+        &self,
+        policy: &McpPolicy,
+        req: &mut Request,
+    ) -> Result<(), UpstreamHttpResponse> {
+        mcp::apply_policy(policy, req, |name| self.state.secret(name)).map_err(|err| match err {
+            McpPolicyError::NotAllowed(label) => {
+                json_error_with(403, "mcp_server_not_allowed", label)
+            }
+            McpPolicyError::MissingSecret(name) => {
+                gproxy_common::log_error!("mcp", "mcp policy names missing secret `{name}`");
+                json_error(500, "mcp_secret_missing")
+            }
+        })
+    }
+
+    /// Records an MCP call of a Responses answer to the `mcp_tool_calls` table.
+    fn mcp_call_recorder(
+        &self,
+        trace_id: Option<String>,
+        user_key_id: i64,
+        provider: String,
+    ) -> impl Fn(McpCall) + Send + 'static {
+        let state = self.state.clone();
+        move |call: McpCall| {
+            state.mcp_tool_calls.record(McpToolCallRecord {
+                id: 0,
+                trace_id: trace_id.clone(),
+                user_key_id,
+                provider: provider.clone(),
+                server_label: call.server_label,
+                tool_name: call.tool_name,
+                arguments: call.arguments,
+                output: call.output,
+                error: call.error,
+                at: OffsetDateTime::now_utc(),
+            });
+        }
+    }
+
+    /// Records a client tool call of a generate answer to the `tool_calls` table.
+    fn tool_call_recorder(
+        &self,
+        trace_id: Option<String>,
+        user_key_id: i64,
+        provider: String,
+        model: Option<String>,
+        proto: Proto,
+    ) -> impl Fn(ToolCall) + Send + 'static {
+        let state = self.state.clone();
+        let proto = serde_json::to_value(proto)
+            .ok()
+            .and_then(|v| v.as_str().map(str::to_string))
+            .unwrap_or_default();
+        move |call: ToolCall| {
+            state.tool_calls.record(ToolCallRecord {
+                id: 0,
+                trace_id: trace_id.clone(),
+                user_key_id,
+                provider: provider.clone(),
+                model: model.clone(),
+                proto: proto.clone(),
+                tool_name: call.name,
+                call_id: call.call_id,
+                arguments_bytes: call.arguments_bytes as i64,
+                at: OffsetDateTime::now_utc(),
+                result_at: None,
+                latency_ms: None,
+            });
+        }
+    }
+
+    async fn handle_protocol_call(
+        &self,
+        trace_id: Option<String>,
+        auth: ProxyAuth,
+        route_ctx: ProtocolRouteCtx,
+        user_proto: Proto,
+        user_op: Op,
+        req: Request,
+    ) -> UpstreamHttpResponse {
+        // A generate stream that stalls before its first token on every credential is
+        // sent once more to the provider's failover target.
+        let failover = is_generate_op(user_op)
+            .then(|| self.stream_failover(&route_ctx.provider))
+            .flatten()
+            .map(|provider| (provider, route_ctx.clone(), req.clone()));
+        let resp = self
+            .handle_protocol_canaried(
+                trace_id.clone(),
+                auth.clone(),
+                route_ctx,
+                user_proto,
+                user_op,
+                req,
+            )
+            .await;
+        let Some((provider, mut route_ctx, req)) = failover.filter(|_| is_stall_response(&resp))
+        else {
+            return resp;
+        };
+        // A `provider/model` route reports the provider that served the call.
+        if route_ctx.response_model_prefix_provider.is_some() {
+            route_ctx.response_model_prefix_provider = Some(provider.clone());
+        }
+        route_ctx.provider = provider;
+        self.handle_protocol_canaried(trace_id, auth, route_ctx, user_proto, user_op, req)
+            .await
+    }
+
+    async fn handle_protocol_canaried(
+        &self,
+        trace_id: Option<String>,
+        auth: ProxyAuth,
+        route_ctx: ProtocolRouteCtx,
+        user_proto: Proto,
+        user_op: Op,
+        req: Request,
+    ) -> UpstreamHttpResponse {
+        let mut canary = None;
+        let resp = self
+            .handle_protocol(
+                trace_id,
+                auth,
+                route_ctx,
+                user_proto,
+                user_op,
+                req,
+                &mut canary,
+            )
+            .await;
+        if let Some((canary_provider, canary)) = canary
+            && canary.record(resp.status >= 500)
+        {
+            gproxy_common::log_warn!(
+                "canary",
+                "provider {canary_provider}: canary config rolled back after error rate exceeded {}",
+                canary.settings.max_error_rate
+            );
+        }
+        resp
+    }
+
+    /// Counts the request against its organization's shared limits; `Some` rejects it.
+    fn org_rejection(&self, org_id: i64) -> Option<OrgRejection> {
+        let snapshot = self.state.snapshot.load();
+        let org = snapshot.organizations.iter().find(|o| o.id == org_id)?;
+        self.state.org_limits.admit(org).err()
+    }
+
+    /// The provider's `stream_failover` target, unless it points back at the provider.
+    fn stream_failover(&self, provider: &str) -> Option<String> {
+        self.provider_policies(provider)?
+            .stream_failover
+            .provider
+            .clone()
+            .filter(|target| target != provider)
+    }
+
+    fn semantic_cache_settings(&self, provider: &str) -> Option<SemanticCacheSettings> {
+        self.provider_policies(provider)?.semantic_cache.clone()
+    }
+
+    /// Answers a non-stream generate call from the semantic cache when an earlier prompt
+    /// is similar enough, else calls upstream and caches a successful answer. A failed
+    /// embedding call only skips the cache.
+    async fn handle_semantic_cached(
+        &self,
+        trace_id: Option<String>,
+        auth: ProxyAuth,
+        route_ctx: ProtocolRouteCtx,
+        user_proto: Proto,
+        req: Request,
+        settings: SemanticCacheSettings,
+    ) -> UpstreamHttpResponse {
+        let model = extract_model_from_request(&req);
+        let key = semantic_cache::request_body_json(&req).and_then(|body| {
+            let scope = semantic_cache::Scope {
+                provider: &route_ctx.provider,
+                proto: user_proto,
+                model: model.as_deref(),
+                embedding_model: &settings.embedding_model,
+                user_key_id: (!settings.shared).then_some(auth.user_key_id),
+            };
+            semantic_cache::cache_key(&scope, body)
+        });
+        let embedding = match key {
+            Some(key) => self
+                .embed_prompt(trace_id.clone(), auth.clone(), &settings, &key.prompt)
+                .await
+                .map(|embedding| (key.partition, embedding)),
+            None => None,
+        };
+        if let Some((partition, embedding)) = &embedding
+            && let Some(hit) =
+                self.state
+                    .semantic_cache
+                    .lookup(*partition, embedding, settings.threshold)
+        {
+            let content_type = hit.content_type.as_deref().unwrap_or("application/json");
+            let mut headers: Headers =
+                vec![("content-type".to_string(), content_type.to_string())].into();
+            header_set(&mut headers, SEMANTIC_CACHE_HEADER, "hit");
+            header_set(
+                &mut headers,
+                SEMANTIC_SIMILARITY_HEADER,
+                format!("{:.4}", hit.similarity),
+            );
+            return UpstreamHttpResponse {
+                status: 200,
+                headers,
+                body: UpstreamBody::Bytes(hit.body),
+            };
+        }
+        let mut resp = self
+            .handle_protocol_call(
+                trace_id,
+                auth,
+                route_ctx,
+                user_proto,
+                Op::GenerateContent,
+                req,
+            )
+            .await;
+        if let Some((partition, embedding)) = embedding
+            && resp.status == 200
+            && let UpstreamBody::Bytes(body) = &resp.body
+        {
+            self.state.semantic_cache.insert(
+                partition,
+                embedding,
+                header_get(&resp.headers, "content-type").map(str::to_string),
+                body,
+                Duration::from_secs(settings.ttl_secs),
+                settings.max_entries,
+            );
+        }
+        header_set(&mut resp.headers, SEMANTIC_CACHE_HEADER, "miss");
+        resp
+    }
+
+    /// Embedding of `prompt` from the cache's embedding provider, billed to the caller's
+    /// key like any other upstream call.
+    async fn embed_prompt(
+        &self,
+        trace_id: Option<String>,
+        auth: ProxyAuth,
+        settings: &SemanticCacheSettings,
+        prompt: &str,
+    ) -> Option<Vec<f32>> {
+        let req = RawPassthroughRequest {
+            method: HttpMethod::Post,
+            path: settings.embedding_path.clone(),
+            query: None,
+            headers: vec![("content-type".to_string(), "application/json".to_string())].into(),
+            body: Some(semantic_cache::embedding_request(
+                &settings.embedding_model,
+                prompt,
+            )),
+        };
+        let resp = self
+            .send_raw(
+                trace_id,
+                auth,
+                settings.embedding_provider.clone(),
+                req,
+                RawCall::Embedding,
+            )
+            .await;
+        let embedding = match &resp.body {
+            UpstreamBody::Bytes(body) if (200..300).contains(&resp.status) => {
+                semantic_cache::parse_embedding(body)
+            }
+            _ => None,
+        };
+        if embedding.is_none() {
+            gproxy_common::log_warn!(
+                "semantic_cache",
+                "embedding via {} failed with status {}; request not cached",
+                settings.embedding_provider,
+                resp.status
+            );
+        }
+        embedding
+    }
+
+    /// Fan-out for a generate call: the `fan_out` setting of the profile the model names,
+    /// else the client's `x-gproxy-fan-out` header. Returns the profile too, whose other
+    /// settings still apply to every branch.
+    fn fan_out_for(
+        &self,
+        auth: &ProxyAuth,
+        provider: &str,
+        user_op: Op,
+        req: &Request,
+    ) -> Option<(FanOut, Option<ModelProfile>)> {
+        if !is_generate_op(user_op) {
+            return None;
+        }
+        let profile = self.model_profile_for(provider, req);
+        if let Some(fan_out) = profile.as_ref().and_then(|profile| profile.fan_out.clone()) {
+            return Some((fan_out, profile));
         }
+        FanOut::from_headers(&auth.request_headers).map(|fan_out| (fan_out, None))
     }
 
-    pub fn events(&self) -> gproxy_provider_core::EventHub {
-        self.state.events.clone()
+    /// Runs one branch per fan-out target concurrently. In `first` mode (and for every
+    /// stream) the first successful answer is returned: streams still running are cut off,
+    /// which records their usage so far, while non-stream calls already sent upstream
+    /// finish in the background so their usage is recorded too. In `all` mode the
+    /// answers of every branch come back in one payload.
+    #[allow(clippy::too_many_arguments)]
+    async fn handle_fan_out(
+        &self,
+        trace_id: Option<String>,
+        auth: ProxyAuth,
+        route_ctx: ProtocolRouteCtx,
+        user_proto: Proto,
+        user_op: Op,
+        mut req: Request,
+        fan_out: FanOut,
+        profile: Option<ModelProfile>,
+    ) -> UpstreamHttpResponse {
+        let alias = profile.map(|profile| {
+            profile.apply(&mut req);
+            profile.name
+        });
+        let mut branches = tokio::task::JoinSet::new();
+        for (index, target) in fan_out.targets.iter().enumerate() {
+            let mut branch_req = req.clone();
+            ModelProfile::retarget(&mut branch_req, &target.model);
+            let branch_ctx = ProtocolRouteCtx {
+                provider: target.provider.clone(),
+                // A `provider/model` route reports each branch's own provider.
+                response_model_prefix_provider: route_ctx
+                    .response_model_prefix_provider
+                    .as_ref()
+                    .map(|_| target.provider.clone()),
+                alias: alias.clone(),
+            };
+            let engine = self.clone();
+            let trace_id = trace_id.clone();
+            let auth = auth.clone();
+            branches.spawn(async move {
+                let resp = engine
+                    .handle_protocol_call(
+                        trace_id, auth, branch_ctx, user_proto, user_op, branch_req,
+                    )
+                    .await;
+                (index, resp)
+            });
+        }
+        gproxy_common::log_debug!(
+            "fan_out",
+            "trace {:?}: {} branches, mode {:?}",
+            trace_id,
+            fan_out.targets.len(),
+            fan_out.mode
+        );
+
+        let combine = fan_out.mode == FanOutMode::All && user_op == Op::GenerateContent;
+        let mut answers = Vec::new();
+        let mut failures = Vec::new();
+        while let Some(joined) = branches.join_next().await {
+            let Ok((index, resp)) = joined else {
+                continue;
+            };
+            if resp.status >= 400 {
+                failures.push((index, resp));
+            } else if combine {
+                answers.push((index, resp));
+            } else {
+                // Dropping the losers' responses cuts their streams off; the set itself is
+                // drained rather than dropped, which would abort calls mid-flight.
+                tokio::spawn(async move { while branches.join_next().await.is_some() {} });
+                return resp;
+            }
+        }
+        if answers.is_empty() {
+            return fan_out::first_failure(failures)
+                .unwrap_or_else(|| json_error(502, "fan_out_failed"));
+        }
+        answers.extend(failures);
+        fan_out::combine(&fan_out.targets, answers)
     }
 
-    pub fn event_redact_sensitive(&self) -> bool {
-        self.state.global.load().event_redact_sensitive
+    /// Calls to fan a multi-candidate request out to: the provider opts in with
+    /// `candidate_fan_out`, and the call is served in a protocol whose answers carry one
+    /// candidate (Claude, Responses).
+    fn candidate_fan_out_for(
+        &self,
+        provider: &str,
+        user_proto: Proto,
+        user_op: Op,
+        req: &Request,
+    ) -> Option<u32> {
+        if user_op != Op::GenerateContent {
+            return None;
+        }
+        let requested = candidates::requested(req)?;
+        let (provider_impl, runtime, config) = self.load_provider(provider).ok()?;
+        let policies = runtime.policies();
+        let calls = policies.candidate_fan_out.calls(requested)?;
+        let dispatch = provider_impl
+            .dispatch_table(&config)
+            .with_overrides(&policies.dispatch)
+            .with_model_rules(policies.model_dispatch.clone());
+        let model = extract_model_from_request(req);
+        let resolved =
+            dispatch::resolve_call_shape(&dispatch, user_proto, user_op, model.as_deref())?;
+        matches!(
+            resolved.provider_proto,
+            Proto::Claude | Proto::OpenAIResponse
+        )
+        .then_some(calls)
     }
 
-    pub fn authenticate_user_key(&self, api_key: &str) -> Option<crate::proxy_engine::ProxyAuth> {
-        let snapshot = self.state.snapshot.load();
+    /// Sends a multi-candidate request `calls` times concurrently, each asking for one
+    /// candidate, and merges the answers into one response. Every call is billed and
+    /// logged on its own; when all of them fail the first failure is returned.
+    #[allow(clippy::too_many_arguments)]
+    async fn handle_candidate_fan_out(
+        &self,
+        trace_id: Option<String>,
+        auth: ProxyAuth,
+        route_ctx: ProtocolRouteCtx,
+        user_proto: Proto,
+        user_op: Op,
+        mut req: Request,
+        calls: u32,
+    ) -> UpstreamHttpResponse {
+        candidates::single(&mut req);
+        let mut branches = tokio::task::JoinSet::new();
+        for index in 0..calls as usize {
+            let engine = self.clone();
+            let trace_id = trace_id.clone();
+            let auth = auth.clone();
+            let route_ctx = route_ctx.clone();
+            let req = req.clone();
+            branches.spawn(async move {
+                let resp = engine
+                    .handle_protocol_call(trace_id, auth, route_ctx, user_proto, user_op, req)
+                    .await;
+                (index, resp)
+            });
+        }
+
+        let mut answers = Vec::new();
+        let mut failures = Vec::new();
+        while let Some(joined) = branches.join_next().await {
+            let Ok((index, resp)) = joined else {
+                continue;
+            };
+            if resp.status >= 400 {
+                failures.push((index, resp));
+            } else {
+                answers.push((index, resp));
+            }
+        }
+        answers.sort_by_key(|(index, _)| *index);
+        let answers = answers.into_iter().map(|(_, resp)| resp).collect();
+        candidates::merge(user_proto, answers).unwrap_or_else(|| {
+            fan_out::first_failure(failures).unwrap_or_else(|| json_error(502, "fan_out_failed"))
+        })
+    }
 
-        let key = snapshot
-            .user_keys
+    /// Provider a model profile routes to, for resolving bare profile names on aggregate routes.
+    pub fn model_profile_provider(&self, name: &str) -> Option<String> {
+        let name = name.strip_prefix("models/").unwrap_or(name);
+        self.state
+            .snapshot
+            .load()
+            .model_profiles
             .iter()
-            .find(|k| k.enabled && k.api_key == api_key)?;
-        let user = snapshot
-            .users
+            .find(|row| row.enabled && row.name == name)
+            .map(|row| row.provider.clone())
+    }
+
+    /// Picks an arm when `name` is an enabled experiment, recording the assignment on
+    /// `auth`. Returns the arm's provider and model.
+    pub fn assign_experiment(&self, auth: &mut ProxyAuth, name: &str) -> Option<(String, String)> {
+        let snapshot = self.state.snapshot.load();
+        let row = snapshot
+            .experiments
             .iter()
-            .find(|u| u.id == key.user_id && u.enabled)?;
+            .find(|row| row.enabled && row.name == name)?;
+        let experiment = Experiment::from_row(row)?;
+        let (arm, assignment) = experiment.assign(auth);
+        let target = (arm.provider.clone(), arm.model.clone());
+        auth.experiment = Some(assignment);
+        Some(target)
+    }
 
-        Some(crate::proxy_engine::ProxyAuth {
-            user_id: user.id,
-            user_key_id: key.id,
-            user_agent: None,
-        })
+    /// Enabled profile named by the request model, when it belongs to `provider`.
+    fn model_profile_for(&self, provider: &str, req: &Request) -> Option<ModelProfile> {
+        let model = extract_model_from_request(req)?;
+        let name = model.strip_prefix("models/").unwrap_or(&model);
+        self.state
+            .snapshot
+            .load()
+            .model_profiles
+            .iter()
+            .find(|row| row.enabled && row.name == name && row.provider == provider)
+            .map(ModelProfile::from_row)
     }
 
-    pub async fn handle(&self, call: ProxyCall) -> UpstreamHttpResponse {
-        match call {
-            ProxyCall::OAuthStart {
-                trace_id,
-                auth,
-                provider,
-                req,
-            } => self.handle_oauth_start(trace_id, auth, provider, req).await,
-            ProxyCall::OAuthCallback {
-                trace_id,
-                auth,
-                provider,
-                req,
-            } => {
-                self.handle_oauth_callback(trace_id, auth, provider, req)
-                    .await
-            }
-            ProxyCall::UpstreamUsage {
-                trace_id,
-                auth,
-                provider,
-                credential_id,
-            } => {
-                self.handle_upstream_usage(trace_id, auth, provider, credential_id)
-                    .await
-            }
-            ProxyCall::Protocol {
-                trace_id,
-                auth,
-                provider,
-                response_model_prefix_provider,
-                user_proto,
-                user_op,
-                req,
-            } => {
-                self.handle_protocol(
-                    trace_id,
-                    auth,
-                    ProtocolRouteCtx {
-                        provider,
-                        response_model_prefix_provider,
-                    },
-                    user_proto,
-                    user_op,
-                    *req,
-                )
-                .await
-            }
-        }
+    /// Built-in dispatch table of the implementation serving `config`, before the
+    /// provider's overrides; `None` when no such implementation is registered.
+    pub fn builtin_dispatch_table(&self, config: &ProviderConfig) -> Option<DispatchTable> {
+        self.registry
+            .get(provider_impl_name_from_config(config))
+            .map(|provider_impl| provider_impl.dispatch_table(config))
     }
 
     pub fn enabled_provider_names(&self) -> Vec<String> {
@@ -247,8 +1452,20 @@ impl ProxyEngine {
             Ok(v) => v,
             Err(resp) => return resp,
         };
+        if !self
+            .state
+            .credential_scope(auth.org_id, &provider)
+            .permits(credential_id)
+        {
+            return json_error(403, "credential_not_allowed");
+        }
+        if !key_scope_allows_provider(&auth, &provider) {
+            return json_error(403, "provider_not_allowed");
+        }
 
-        let dispatch = provider_impl.dispatch_table(&config);
+        let dispatch = provider_impl
+            .dispatch_table(&config)
+            .with_overrides(&runtime.policies().dispatch);
         if matches!(
             dispatch.rule(OperationKind::Usage),
             DispatchRule::Unsupported
@@ -283,6 +1500,7 @@ impl ProxyEngine {
                 op: Op::ModelList,
                 internal: true,
                 attempt_no,
+                caller: auth.caller.clone(),
             };
 
             let mut cred = cred;
@@ -304,13 +1522,17 @@ impl ProxyEngine {
                 Err(err) => return error_response_from_provider_err(&err),
             }
 
-            let upstream_req = match provider_impl
+            let mut upstream_req = match provider_impl
                 .build_upstream_usage(&ctx, &config, &cred)
                 .await
             {
                 Ok(r) => r,
                 Err(err) => return error_response_from_provider_err(&err),
             };
+            runtime
+                .policies()
+                .header
+                .apply_request(&auth.request_headers, &mut upstream_req.headers);
 
             let resp = match self.client.send(upstream_req.clone()).await {
                 Ok(r) => r,
@@ -355,18 +1577,23 @@ impl ProxyEngine {
                                 attempt_no += 1;
                                 continue;
                             }
+                            AuthRetryAction::Failover => {
+                                attempt_no += 1;
+                                continue;
+                            }
                             AuthRetryAction::None => {}
                         }
                     }
-                    if let Some(decision) =
+                    if let Some(decision) = decide_unavailable(&runtime, &failure, || {
                         provider_impl.decide_unavailable(&ctx, &config, &cred, &fake_req, &failure)
-                    {
+                    }) {
                         self.apply_unavailable_decision(
                             runtime.clone(),
                             cred_id,
                             Op::ModelList,
                             None,
                             decision,
+                            &failure,
                         )
                         .await;
                         return failure_to_http(failure);
@@ -431,10 +1658,14 @@ impl ProxyEngine {
                             attempt_no += 1;
                             continue;
                         }
+                        AuthRetryAction::Failover => {
+                            attempt_no += 1;
+                            continue;
+                        }
                         AuthRetryAction::None => {}
                     }
                 }
-                if is_auth_failure(&failure)
+                if is_auth_failure(&runtime, &failure)
                     && auth_retry_used != Some(cred_id)
                     && let Ok(action) = provider_impl
                         .on_auth_failure(&ctx, &config, &cred, &fake_req, &failure)
@@ -458,18 +1689,23 @@ impl ProxyEngine {
                             attempt_no += 1;
                             continue;
                         }
+                        AuthRetryAction::Failover => {
+                            attempt_no += 1;
+                            continue;
+                        }
                         AuthRetryAction::None => {}
                     }
                 }
-                if let Some(decision) =
+                if let Some(decision) = decide_unavailable(&runtime, &failure, || {
                     provider_impl.decide_unavailable(&ctx, &config, &cred, &fake_req, &failure)
-                {
+                }) {
                     self.apply_unavailable_decision(
                         runtime.clone(),
                         cred_id,
                         Op::ModelList,
                         None,
                         decision,
+                        &failure,
                     )
                     .await;
                     return resp;
@@ -506,11 +1742,247 @@ impl ProxyEngine {
                         return resp;
                     }
                 }
-                Ok(None) => {}
-                Err(err) => return error_response_from_provider_err(&err),
+                Ok(None) => {}
+                Err(err) => return error_response_from_provider_err(&err),
+            }
+
+            return resp;
+        }
+    }
+
+    /// Forwards a request on a `/{provider}/...` path without a typed route: no transform,
+    /// the provider only injects the credential's auth. Only for providers whose
+    /// `raw_passthrough` policy allows the path.
+    async fn handle_raw_passthrough(
+        &self,
+        trace_id: Option<String>,
+        auth: crate::proxy_engine::ProxyAuth,
+        provider: String,
+        req: RawPassthroughRequest,
+    ) -> UpstreamHttpResponse {
+        self.send_raw(trace_id, auth, provider, req, RawCall::Passthrough)
+            .await
+    }
+
+    /// Sends a request verbatim through `provider` with a credential's auth injected. A
+    /// credential the provider marks unavailable is swapped for the next one, like on
+    /// protocol calls.
+    async fn send_raw(
+        &self,
+        trace_id: Option<String>,
+        auth: crate::proxy_engine::ProxyAuth,
+        provider: String,
+        req: RawPassthroughRequest,
+        kind: RawCall,
+    ) -> UpstreamHttpResponse {
+        let (provider_impl, runtime, config) = match self.load_provider(&provider) {
+            Ok(v) => v,
+            Err(resp) => return resp,
+        };
+        let policies = runtime.policies();
+        if kind == RawCall::Passthrough && !policies.raw_passthrough.allows(&req.path) {
+            return json_error(404, "route_not_found");
+        }
+        let scope = self.state.credential_scope(auth.org_id, &provider);
+        if scope == CredentialScope::Denied || !key_scope_allows_provider(&auth, &provider) {
+            return json_error(403, "provider_not_allowed");
+        }
+        let (timeout_policy, egress, tls) = (&policies.timeouts, &policies.egress, &policies.tls);
+
+        let mut attempt_no: u32 = 1;
+        // Provider hooks take a protocol request; raw calls have none, like upstream usage.
+        let fake_req = Request::ModelList(gproxy_provider_core::ModelListRequest::OpenAI(
+            gproxy_protocol::openai::list_models::request::ListModelsRequest,
+        ));
+        loop {
+            let (cred_id, cred) = match runtime
+                .pool
+                .acquire_scoped(&provider, None, scope.allowed())
+                .await
+            {
+                Ok(v) => v,
+                Err(AcquireError::ProviderUnknown) => {
+                    return json_error(404, "provider_not_found");
+                }
+                Err(AcquireError::NoActiveCredentials) => {
+                    self.note_provider_outage(&runtime, None, None).await;
+                    return json_error(503, "no_active_credentials");
+                }
+            };
+
+            let ctx = UpstreamCtx {
+                trace_id: trace_id.clone(),
+                user_id: Some(auth.user_id),
+                user_key_id: Some(auth.user_key_id),
+                user_agent: auth.user_agent.clone(),
+                outbound_proxy: self.state.global.load().proxy.clone(),
+                provider: provider.clone(),
+                credential_id: Some(cred_id),
+                // No protocol op; a stable value for logging, like upstream usage.
+                op: Op::ModelList,
+                internal: false,
+                attempt_no,
+                caller: auth.caller.clone(),
+            };
+
+            let mut cred = cred;
+            match provider_impl
+                .upgrade_credential(&ctx, &config, &cred, &fake_req)
+                .await
+            {
+                Ok(Some(new_cred)) => {
+                    if let Err(resp) = self
+                        .persist_credential_update(cred_id, &new_cred, &runtime)
+                        .await
+                    {
+                        return resp;
+                    }
+                    cred = Arc::new(new_cred);
+                }
+                Ok(None) => {}
+                Err(err) => return error_response_from_provider_err(&err),
+            }
+
+            let mut upstream_req = match provider_impl
+                .build_raw_passthrough(&ctx, &config, &cred, &req)
+                .await
+            {
+                Ok(r) => r,
+                Err(err) => return error_response_from_provider_err(&err),
+            };
+            policies
+                .header
+                .apply_request(&auth.request_headers, &mut upstream_req.headers);
+            if let Err(err) = provider_impl
+                .finalize_request(&ctx, &config, &cred, &mut upstream_req)
+                .await
+            {
+                return error_response_from_provider_err(&err);
+            }
+
+            let op = if upstream_req.is_stream {
+                Op::StreamGenerateContent
+            } else {
+                Op::GenerateContent
+            };
+            let (timeouts, _) = with_stream_watchdog(
+                timeout_policy.for_op(op),
+                op,
+                self.state.global.load().stream_idle_timeout_ms,
+            );
+            let proxy = self.pick_egress_proxy(egress, cred_id);
+            let result = self
+                .client
+                .send_with_options(
+                    upstream_req.clone(),
+                    SendOptions {
+                        timeouts,
+                        egress: egress.clone(),
+                        tls: tls.clone(),
+                        proxy: proxy.clone(),
+                        credential_id: Some(cred_id),
+                    },
+                )
+                .await;
+            let failure = match result {
+                Ok(resp) => {
+                    self.record_egress_proxy(proxy.as_deref(), None);
+                    let status = resp.status;
+                    let failure = (!(200..300).contains(&status)).then(|| UpstreamFailure::Http {
+                        status,
+                        headers: resp.headers.clone(),
+                        body: resp_body_bytes(&resp.body).unwrap_or_default(),
+                    });
+                    emit_upstream_event!(
+                        self,
+                        trace_id.clone(),
+                        auth.clone(),
+                        provider.clone(),
+                        Some(cred_id),
+                        false,
+                        attempt_no,
+                        kind.operation(),
+                        &upstream_req,
+                        Some(status),
+                        None,
+                        failure.as_ref().map(|_| "http".to_string()),
+                        failure.as_ref().map(|_| format!("http_status_{status}")),
+                        None,
+                    )
+                    .await;
+                    let Some(failure) = failure else {
+                        let global = self.state.global.load();
+                        return response_limit::limit_raw(
+                            resp,
+                            global.max_response_body_bytes,
+                            global.truncate_oversize_responses,
+                        );
+                    };
+                    let Some(decision) = decide_unavailable(&runtime, &failure, || {
+                        provider_impl.decide_unavailable(&ctx, &config, &cred, &fake_req, &failure)
+                    }) else {
+                        return resp;
+                    };
+                    self.apply_unavailable_decision(
+                        runtime.clone(),
+                        cred_id,
+                        op,
+                        None,
+                        decision,
+                        &failure,
+                    )
+                    .await;
+                    if !self
+                        .has_retry_candidate(&runtime, &provider, None, &scope, &HashSet::new())
+                        .await
+                    {
+                        return resp;
+                    }
+                    attempt_no += 1;
+                    continue;
+                }
+                Err(failure) => failure,
+            };
+
+            self.record_egress_proxy(proxy.as_deref(), Some(&failure));
+            emit_upstream_event!(
+                self,
+                trace_id.clone(),
+                auth.clone(),
+                provider.clone(),
+                Some(cred_id),
+                false,
+                attempt_no,
+                kind.operation(),
+                &upstream_req,
+                None,
+                None,
+                Some("transport".to_string()),
+                Some(failure_message(&failure)),
+                transport_kind_from_failure(&failure),
+            )
+            .await;
+            if let Some(decision) = decide_unavailable(&runtime, &failure, || {
+                provider_impl.decide_unavailable(&ctx, &config, &cred, &fake_req, &failure)
+            }) {
+                self.apply_unavailable_decision(
+                    runtime.clone(),
+                    cred_id,
+                    op,
+                    None,
+                    decision,
+                    &failure,
+                )
+                .await;
+                if self
+                    .has_retry_candidate(&runtime, &provider, None, &scope, &HashSet::new())
+                    .await
+                {
+                    attempt_no += 1;
+                    continue;
+                }
             }
-
-            return resp;
+            return failure_to_http(failure);
         }
     }
 
@@ -567,6 +2039,7 @@ impl ProxyEngine {
             op: Op::ModelList,
             internal: true,
             attempt_no: 0,
+            caller: auth.caller.clone(),
         };
 
         match provider_impl.oauth_start(&ctx, &config, &req) {
@@ -644,6 +2117,7 @@ impl ProxyEngine {
             op: Op::ModelList,
             internal: true,
             attempt_no: 0,
+            caller: auth.caller.clone(),
         };
 
         match provider_impl.oauth_callback(&ctx, &config, &req) {
@@ -903,27 +2377,100 @@ impl ProxyEngine {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn handle_protocol(
         &self,
         trace_id: Option<String>,
-        auth: crate::proxy_engine::ProxyAuth,
+        mut auth: crate::proxy_engine::ProxyAuth,
         route_ctx: ProtocolRouteCtx,
         user_proto: Proto,
         user_op: Op,
-        req_user: Request,
+        mut req_user: Request,
+        canary_slot: &mut Option<(String, Arc<ProviderCanary>)>,
     ) -> UpstreamHttpResponse {
-        let provider = route_ctx.provider;
-        let response_model_prefix_provider = route_ctx.response_model_prefix_provider;
-        let (provider_impl, runtime, config) = match self.load_provider(&provider) {
+        let profile = self.model_profile_for(&route_ctx.provider, &req_user);
+        if let Some(profile) = &profile {
+            profile.apply(&mut req_user);
+        }
+        if let Some(cap) = auth.max_output_tokens {
+            output_cap::cap_request(&mut req_user, cap);
+        }
+        // Experiments report their own name, whichever arm (or profile) served the call.
+        let alias = match &auth.experiment {
+            Some(assignment) => Some(assignment.experiment.clone()),
+            None => route_ctx
+                .alias
+                .or_else(|| profile.map(|profile| profile.name)),
+        };
+        let model_rewrite = ModelRewrite {
+            prefix_provider: route_ctx.response_model_prefix_provider,
+            alias,
+        };
+        let provider = match self.maintenance_route(route_ctx.provider) {
+            Ok(provider) => provider,
+            Err(resp) => return resp,
+        };
+        let (provider_impl, runtime, mut config) = match self.load_provider(&provider) {
             Ok(v) => v,
             Err(resp) => return resp,
         };
+        let scope = self.state.credential_scope(auth.org_id, &provider);
+        if scope == CredentialScope::Denied || !key_scope_allows_provider(&auth, &provider) {
+            return json_error(403, "provider_not_allowed");
+        }
+        let mut policies = runtime.policies();
+        if let Some(canary) = runtime.canary.load_full().filter(|canary| canary.sample())
+            && let Some(canary_config) = canary.config.clone()
+        {
+            config = canary_config;
+            policies = canary.policies.clone();
+            *canary_slot = Some((provider.clone(), canary));
+        }
+        let (header_policy, beta_policy, timeout_policy, egress, tls) = (
+            &policies.header,
+            &policies.anthropic_beta,
+            &policies.timeouts,
+            &policies.egress,
+            &policies.tls,
+        );
+
+        if policies.parsing == ParsingMode::Strict {
+            let unknown = unknown_request_fields(&req_user);
+            if !unknown.is_empty() {
+                return json_error_with(400, "unknown_fields", unknown.join(", "));
+            }
+        }
+
+        let user_model = extract_model_from_request(&req_user);
+        if let Some(rule) = DisallowRule::find(
+            &policies.disallow,
+            user_model.as_deref(),
+            user_op,
+            OffsetDateTime::now_utc(),
+        ) {
+            return json_error_with(
+                403,
+                "disallowed",
+                serde_json::json!({ "provider": provider, "rule": rule }),
+            );
+        }
 
-        let dispatch = provider_impl.dispatch_table(&config);
-        let Some(resolved) = dispatch::resolve_call_shape(&dispatch, user_proto, user_op) else {
+        let dispatch = provider_impl
+            .dispatch_table(&config)
+            .with_overrides(&policies.dispatch)
+            .with_model_rules(policies.model_dispatch.clone());
+        let Some(resolved) =
+            dispatch::resolve_call_shape(&dispatch, user_proto, user_op, user_model.as_deref())
+        else {
             return json_error(501, "unsupported_operation");
         };
 
+        let provider_proto = resolved.provider_proto;
+        let (timeouts, stream_idle) = with_stream_watchdog(
+            timeout_policy.for_op(resolved.provider_op),
+            resolved.provider_op,
+            self.state.global.load().stream_idle_timeout_ms,
+        );
         let to_provider = TransformContext {
             src: user_proto,
             dst: resolved.provider_proto,
@@ -931,6 +2478,7 @@ impl ProxyEngine {
             dst_op: resolved.provider_op,
         };
 
+        let warnings = transform_warnings(&to_provider, &req_user);
         let req_native = match transform_request_maybe(&to_provider, req_user) {
             Ok(r) => r,
             Err(err) => {
@@ -943,30 +2491,63 @@ impl ProxyEngine {
         } else {
             None
         };
+        auth.model = extract_model_from_request(&req_native);
+        auth.transform = Some(format!(
+            "{}->{}",
+            user_proto.as_str(),
+            provider_proto.as_str()
+        ));
+        if let (Some(key_scope), Some(model)) = (&auth.key_scope, &auth.model)
+            && !key_scope.allows_model(model)
+        {
+            return json_error_with(403, "model_not_allowed", model.clone());
+        }
+        // Follow-up calls on a response/conversation/file first try the credential that
+        // created it; other accounts can't see the resource.
+        let owner_scope = referenced_resource_id(&req_native)
+            .and_then(|id| self.state.resource_owners.owner(&provider, id))
+            .filter(|owner| scope.permits(*owner))
+            .map(|owner| HashSet::from([owner]));
 
         let mut attempt_no: u32 = 1;
         let mut auth_retry_used: Option<i64> = None;
         let mut provider_retry_used: Option<i64> = None;
+        // Credentials whose stream stalled before its first token; skipped from then on
+        // whether or not the stall put them on cooldown.
+        let mut stalled: HashSet<i64> = HashSet::new();
         loop {
-            let (cred_id, cred) = match model_for_cooldown.as_deref() {
-                Some(model) => match runtime.pool.acquire_for_model(&provider, model).await {
-                    Ok(v) => v,
-                    Err(AcquireError::ProviderUnknown) => {
-                        return json_error(404, "provider_not_found");
-                    }
-                    Err(AcquireError::NoActiveCredentials) => {
-                        return json_error(503, "no_active_credentials");
-                    }
-                },
-                None => match runtime.pool.acquire(&provider).await {
-                    Ok(v) => v,
-                    Err(AcquireError::ProviderUnknown) => {
-                        return json_error(404, "provider_not_found");
-                    }
-                    Err(AcquireError::NoActiveCredentials) => {
-                        return json_error(503, "no_active_credentials");
-                    }
-                },
+            let owned = match owner_scope.as_ref().filter(|_| attempt_no == 1) {
+                Some(owner) => runtime
+                    .pool
+                    .acquire_scoped(&provider, model_for_cooldown.as_deref(), Some(owner))
+                    .await
+                    .ok(),
+                None => None,
+            };
+            let acquired = match owned {
+                Some(v) => Ok(v),
+                None => {
+                    runtime
+                        .pool
+                        .acquire_excluding(
+                            &provider,
+                            model_for_cooldown.as_deref(),
+                            scope.allowed(),
+                            &stalled,
+                        )
+                        .await
+                }
+            };
+            let (cred_id, cred) = match acquired {
+                Ok(v) => v,
+                Err(AcquireError::ProviderUnknown) => {
+                    return json_error(404, "provider_not_found");
+                }
+                Err(AcquireError::NoActiveCredentials) => {
+                    self.note_provider_outage(&runtime, model_for_cooldown.as_ref(), None)
+                        .await;
+                    return json_error(503, "no_active_credentials");
+                }
             };
 
             let ctx = UpstreamCtx {
@@ -980,6 +2561,7 @@ impl ProxyEngine {
                 op: resolved.provider_op,
                 internal: false,
                 attempt_no,
+                caller: auth.caller.clone(),
             };
 
             let mut cred = cred;
@@ -994,7 +2576,7 @@ impl ProxyEngine {
                     {
                         return resp;
                     }
-                    cred = new_cred;
+                    cred = Arc::new(new_cred);
                 }
                 Ok(None) => {}
                 Err(err) => return error_response_from_provider_err(&err),
@@ -1028,14 +2610,14 @@ impl ProxyEngine {
                         transport_kind: None,
                     })
                     .await;
-                    return local_resp;
+                    return translate_upstream_error(local_resp, provider_proto, user_proto);
                 }
-                return self
+                let resp = self
                     .handle_success(
                         trace_id.clone(),
                         auth,
                         provider.clone(),
-                        response_model_prefix_provider.clone(),
+                        model_rewrite.clone(),
                         provider_impl,
                         runtime,
                         config,
@@ -1051,9 +2633,10 @@ impl ProxyEngine {
                         local_resp,
                     )
                     .await;
+                return with_transform_warnings(resp, &warnings);
             }
 
-            let upstream_req = match build_upstream_request(
+            let mut upstream_req = match build_upstream_request(
                 provider_impl.as_ref(),
                 &ctx,
                 &config,
@@ -1065,10 +2648,38 @@ impl ProxyEngine {
                 Ok(r) => r,
                 Err(err) => return error_response_from_provider_err(&err),
             };
+            header_policy.apply_request(&auth.request_headers, &mut upstream_req.headers);
+            if provider_proto == Proto::Claude {
+                beta_policy.apply(&auth.request_headers, &mut upstream_req.headers);
+            }
+            if let Err(err) = provider_impl
+                .finalize_request(&ctx, &config, &cred, &mut upstream_req)
+                .await
+            {
+                return error_response_from_provider_err(&err);
+            }
 
-            let resp = match self.client.send(upstream_req.clone()).await {
-                Ok(r) => r,
+            let proxy = self.pick_egress_proxy(egress, cred_id);
+            let resp = match self
+                .client
+                .send_with_options(
+                    upstream_req.clone(),
+                    SendOptions {
+                        timeouts,
+                        egress: egress.clone(),
+                        tls: tls.clone(),
+                        proxy: proxy.clone(),
+                        credential_id: Some(cred_id),
+                    },
+                )
+                .await
+            {
+                Ok(r) => {
+                    self.record_egress_proxy(proxy.as_deref(), None);
+                    r
+                }
                 Err(failure) => {
+                    self.record_egress_proxy(proxy.as_deref(), Some(&failure));
                     emit_upstream_event!(
                         self,
                         trace_id.clone(),
@@ -1108,10 +2719,14 @@ impl ProxyEngine {
                                 attempt_no += 1;
                                 continue;
                             }
+                            AuthRetryAction::Failover => {
+                                attempt_no += 1;
+                                continue;
+                            }
                             AuthRetryAction::None => {}
                         }
                     }
-                    if is_auth_failure(&failure)
+                    if is_auth_failure(&runtime, &failure)
                         && auth_retry_used != Some(cred_id)
                         && let Ok(action) = provider_impl
                             .on_auth_failure(&ctx, &config, &cred, &req_native, &failure)
@@ -1134,6 +2749,10 @@ impl ProxyEngine {
                                 attempt_no += 1;
                                 continue;
                             }
+                            AuthRetryAction::Failover => {
+                                attempt_no += 1;
+                                continue;
+                            }
                             AuthRetryAction::None => {}
                         }
                     }
@@ -1155,27 +2774,32 @@ impl ProxyEngine {
                         .await;
                         return failure_to_http(failure);
                     }
-                    if let Some(decision) = provider_impl.decide_unavailable(
-                        &ctx,
-                        &config,
-                        &cred,
-                        &req_native,
-                        &failure,
-                    ) {
+                    if let Some(decision) = decide_unavailable(&runtime, &failure, || {
+                        provider_impl.decide_unavailable(
+                            &ctx,
+                            &config,
+                            &cred,
+                            &req_native,
+                            &failure,
+                        )
+                    }) {
                         self.apply_unavailable_decision(
                             runtime.clone(),
                             cred_id,
                             resolved.provider_op,
                             model_for_cooldown.as_ref(),
                             decision,
+                            &failure,
                         )
                         .await;
-                        if is_retryable_failure(&failure) {
+                        if is_retryable_failure(&runtime, &failure) {
                             if !self
                                 .has_retry_candidate(
                                     &runtime,
                                     &provider,
                                     model_for_cooldown.as_ref(),
+                                    &scope,
+                                    &stalled,
                                 )
                                 .await
                             {
@@ -1246,10 +2870,14 @@ impl ProxyEngine {
                             attempt_no += 1;
                             continue;
                         }
+                        AuthRetryAction::Failover => {
+                            attempt_no += 1;
+                            continue;
+                        }
                         AuthRetryAction::None => {}
                     }
                 }
-                if is_auth_failure(&failure)
+                if is_auth_failure(&runtime, &failure)
                     && auth_retry_used != Some(cred_id)
                     && let Ok(action) = provider_impl
                         .on_auth_failure(&ctx, &config, &cred, &req_native, &failure)
@@ -1272,6 +2900,10 @@ impl ProxyEngine {
                             attempt_no += 1;
                             continue;
                         }
+                        AuthRetryAction::Failover => {
+                            attempt_no += 1;
+                            continue;
+                        }
                         AuthRetryAction::None => {}
                     }
                 }
@@ -1291,35 +2923,146 @@ impl ProxyEngine {
                         },
                     )
                     .await;
-                    return resp;
+                    return translate_upstream_error(resp, provider_proto, user_proto);
                 }
-                if let Some(decision) =
+                if let Some(decision) = decide_unavailable(&runtime, &failure, || {
                     provider_impl.decide_unavailable(&ctx, &config, &cred, &req_native, &failure)
-                {
+                }) {
                     self.apply_unavailable_decision(
                         runtime.clone(),
                         cred_id,
                         resolved.provider_op,
                         model_for_cooldown.as_ref(),
                         decision,
+                        &failure,
                     )
                     .await;
-                    if is_retryable_failure(&failure) {
+                    if is_retryable_failure(&runtime, &failure) {
                         if !self
-                            .has_retry_candidate(&runtime, &provider, model_for_cooldown.as_ref())
+                            .has_retry_candidate(
+                                &runtime,
+                                &provider,
+                                model_for_cooldown.as_ref(),
+                                &scope,
+                                &stalled,
+                            )
                             .await
                         {
-                            return resp;
+                            return translate_upstream_error(resp, provider_proto, user_proto);
                         }
                         backoff_sleep(attempt_no).await;
                         attempt_no += 1;
                         continue;
                     }
-                    return resp;
+                    return translate_upstream_error(resp, provider_proto, user_proto);
                 }
-                return resp;
+                return translate_upstream_error(resp, provider_proto, user_proto);
             }
 
+            // Hold a generate stream back until its first content token so a stalled or
+            // dropped upstream can fail over without the client seeing a broken stream.
+            let first_token_timeout = Some(Duration::from_millis(
+                self.state.global.load().stream_first_token_timeout_ms,
+            ))
+            .filter(|timeout| !timeout.is_zero());
+            let resp = match resp {
+                UpstreamHttpResponse {
+                    status,
+                    headers,
+                    body: UpstreamBody::Stream(rx),
+                } if (first_token_timeout.is_some() || stream_idle.is_some())
+                    && is_generate_op(resolved.provider_op) =>
+                {
+                    match prime_stream(
+                        provider_impl.unwrap_internal_stream(provider_proto),
+                        provider_proto,
+                        rx,
+                        first_token_timeout,
+                        stream_idle,
+                    )
+                    .await
+                    {
+                        StreamPrime::Ready(rx) => UpstreamHttpResponse {
+                            status,
+                            headers,
+                            body: UpstreamBody::Stream(rx),
+                        },
+                        StreamPrime::Stalled { body, stall } => {
+                            let timed_out = stall != StreamStall::Closed;
+                            let message = match stall {
+                                StreamStall::FirstToken => "stream_first_token_timeout",
+                                StreamStall::Idle => STREAM_IDLE_TIMEOUT,
+                                StreamStall::Closed => "stream_closed_before_first_token",
+                            };
+                            self.emit_upstream_event(UpstreamEventInput {
+                                trace_id: trace_id.clone(),
+                                auth: auth.clone(),
+                                provider: provider.clone(),
+                                credential_id: Some(cred_id),
+                                internal: false,
+                                attempt_no,
+                                operation: format!("{:?}", resolved.provider_op),
+                                upstream_req: &upstream_req,
+                                response_status: Some(status),
+                                response_headers: Some(headers),
+                                response_body: Some(body),
+                                usage: None,
+                                error_kind: Some("stream_stalled".to_string()),
+                                error_message: Some(message.to_string()),
+                                transport_kind: Some(
+                                    gproxy_provider_core::provider::UpstreamTransportErrorKind::ReadTimeout,
+                                ),
+                            })
+                            .await;
+                            let failure = UpstreamFailure::Transport {
+                                kind: gproxy_provider_core::provider::UpstreamTransportErrorKind::ReadTimeout,
+                                message: message.to_string(),
+                            };
+                            if let Some(decision) = decide_unavailable(&runtime, &failure, || {
+                                provider_impl.decide_unavailable(
+                                    &ctx,
+                                    &config,
+                                    &cred,
+                                    &req_native,
+                                    &failure,
+                                )
+                            }) {
+                                self.apply_unavailable_decision(
+                                    runtime.clone(),
+                                    cred_id,
+                                    resolved.provider_op,
+                                    model_for_cooldown.as_ref(),
+                                    decision,
+                                    &failure,
+                                )
+                                .await;
+                            }
+                            // Nothing reached the client yet: move on to another credential
+                            // even when the stall put this one on no cooldown.
+                            stalled.insert(cred_id);
+                            if self
+                                .has_retry_candidate(
+                                    &runtime,
+                                    &provider,
+                                    model_for_cooldown.as_ref(),
+                                    &scope,
+                                    &stalled,
+                                )
+                                .await
+                            {
+                                attempt_no += 1;
+                                continue;
+                            }
+                            // Out of credentials; the caller may still fail over to the
+                            // provider's `stream_failover` target.
+                            let status = if timed_out { 504 } else { 502 };
+                            return json_error(status, stall.code());
+                        }
+                    }
+                }
+                other => other,
+            };
+
             // Success path.
             match provider_impl
                 .on_upstream_success(&ctx, &config, &cred, &req_native, &resp)
@@ -1336,12 +3079,12 @@ impl ProxyEngine {
                 Ok(None) => {}
                 Err(err) => return error_response_from_provider_err(&err),
             }
-            return self
+            let resp = self
                 .handle_success(
                     trace_id.clone(),
                     auth,
                     provider.clone(),
-                    response_model_prefix_provider.clone(),
+                    model_rewrite.clone(),
                     provider_impl,
                     runtime,
                     config,
@@ -1357,6 +3100,7 @@ impl ProxyEngine {
                     resp,
                 )
                 .await;
+            return with_transform_warnings(resp, &warnings);
         }
     }
 
@@ -1366,12 +3110,12 @@ impl ProxyEngine {
         trace_id: Option<String>,
         auth: crate::proxy_engine::ProxyAuth,
         provider: String,
-        response_model_prefix_provider: Option<String>,
+        model_rewrite: ModelRewrite,
         provider_impl: Arc<dyn UpstreamProvider>,
         runtime: Arc<ProviderRuntime>,
-        config: ProviderConfig,
+        config: Arc<ProviderConfig>,
         cred_id: i64,
-        cred: Credential,
+        cred: Arc<Credential>,
         attempt_no: u32,
         user_proto: Proto,
         user_op: Op,
@@ -1383,6 +3127,18 @@ impl ProxyEngine {
     ) -> UpstreamHttpResponse {
         let provider_proto = resolved.provider_proto;
         let provider_op = resolved.provider_op;
+        let upstream_resp = match upstream_resp.body {
+            UpstreamBody::Stream(rx) => UpstreamHttpResponse {
+                body: UpstreamBody::Stream(provider_impl.normalize_stream_response(
+                    &config,
+                    provider_proto,
+                    provider_op,
+                    rx,
+                )),
+                ..upstream_resp
+            },
+            _ => upstream_resp,
+        };
 
         match (user_op, resolved.mode) {
             // Non-stream to non-stream (includes non-generate ops and generate non-stream).
@@ -1396,14 +3152,28 @@ impl ProxyEngine {
                 | Op::ResponseCancel
                 | Op::ResponseListInputItems
                 | Op::ResponseCompact
-                | Op::MemoryTraceSummarize,
+                | Op::MemoryTraceSummarize
+                | Op::ResponseList
+                | Op::ConversationCreate
+                | Op::ConversationGet
+                | Op::ConversationUpdate
+                | Op::ConversationDelete
+                | Op::ConversationItemList
+                | Op::ConversationItemCreate
+                | Op::ConversationItemGet
+                | Op::ConversationItemDelete
+                | Op::FileList
+                | Op::FileGet
+                | Op::FileDelete
+                | Op::BatchGenerateContent
+                | Op::BatchGet,
                 GenerateMode::Same,
             ) => {
                 self.handle_nonstream_response(
                     trace_id,
                     auth,
                     provider,
-                    response_model_prefix_provider,
+                    model_rewrite,
                     provider_impl,
                     runtime,
                     config,
@@ -1427,7 +3197,7 @@ impl ProxyEngine {
                     trace_id,
                     auth,
                     provider,
-                    response_model_prefix_provider,
+                    model_rewrite,
                     provider_impl,
                     runtime,
                     config,
@@ -1449,7 +3219,7 @@ impl ProxyEngine {
                     trace_id,
                     auth,
                     provider,
-                    response_model_prefix_provider,
+                    model_rewrite,
                     provider_impl,
                     runtime,
                     config,
@@ -1471,7 +3241,7 @@ impl ProxyEngine {
                     trace_id,
                     auth,
                     provider,
-                    response_model_prefix_provider,
+                    model_rewrite,
                     provider_impl,
                     runtime,
                     config,
@@ -1491,18 +3261,48 @@ impl ProxyEngine {
         }
     }
 
+    /// The client's 500 for a response that could not be encoded; op mismatches are
+    /// counted and logged, as they point at a routing or transform bug.
+    fn encode_failed(&self, trace_id: &Option<String>, err: &EncodeError) -> UpstreamHttpResponse {
+        if let EncodeError::OpMismatch { op } = err {
+            self.state.stats.record_encode_mismatch();
+            gproxy_common::log_error!(
+                "engine",
+                "encode mismatch for op {op:?} (trace {})",
+                trace_id.as_deref().unwrap_or("-")
+            );
+        }
+        let detail = err.to_string();
+        json_error_with(500, err.event_kind(), detail)
+    }
+
+    /// Pins created resources to the credential that made them and drops deleted ones.
+    fn track_resource_owner(&self, provider: &str, cred_id: i64, req: &Request, resp: &Response) {
+        let owners = &self.state.resource_owners;
+        if let Some(id) = created_resource_id(resp) {
+            owners.record(provider, id, cred_id);
+        }
+        if matches!(
+            req,
+            Request::ResponseDelete(_) | Request::ConversationDelete(_) | Request::FileDelete(_)
+        ) && let Some(id) = referenced_resource_id(req)
+        {
+            owners.forget(provider, id);
+        }
+    }
+
     #[allow(clippy::too_many_arguments)]
     async fn handle_nonstream_response(
         &self,
         trace_id: Option<String>,
         auth: crate::proxy_engine::ProxyAuth,
         provider: String,
-        response_model_prefix_provider: Option<String>,
+        model_rewrite: ModelRewrite,
         provider_impl: Arc<dyn UpstreamProvider>,
         _runtime: Arc<ProviderRuntime>,
-        config: ProviderConfig,
+        config: Arc<ProviderConfig>,
         cred_id: i64,
-        cred: Credential,
+        cred: Arc<Credential>,
         attempt_no: u32,
         user_proto: Proto,
         user_op: Op,
@@ -1515,13 +3315,14 @@ impl ProxyEngine {
         let Some(body) = resp_body_bytes(&upstream_resp.body) else {
             return json_error(502, "upstream_body_missing");
         };
-        let body = if needs_internal_unwrap(&provider, provider_proto, provider_op) {
-            match unwrap_internal_json_bytes(&provider, &body) {
-                Ok(bytes) => bytes,
-                Err(err) => return json_error_with(502, "unwrap_internal_failed", err),
-            }
-        } else {
-            body
+        if let Some(resp) =
+            response_limit::too_large(body.len(), self.state.global.load().max_response_body_bytes)
+        {
+            return resp;
+        }
+        let body = match provider_impl.unwrap_internal_response(provider_proto, provider_op, body) {
+            Ok(bytes) => bytes,
+            Err(err) => return json_error_with(502, "unwrap_internal_failed", err.to_string()),
         };
         let ctx = UpstreamCtx {
             trace_id: trace_id.clone(),
@@ -1534,6 +3335,7 @@ impl ProxyEngine {
             op: provider_op,
             internal: false,
             attempt_no,
+            caller: auth.caller.clone(),
         };
         let body = match provider_impl.normalize_nonstream_response(
             &ctx,
@@ -1552,6 +3354,9 @@ impl ProxyEngine {
             Ok(r) => r,
             Err(err) => return json_error_with(502, "decode_response_failed", err.to_string()),
         };
+        if (200..300).contains(&upstream_resp.status) {
+            self.track_resource_owner(&provider, cred_id, _req_native, &resp_native);
+        }
 
         // Generate usage only for generate ops.
         let usage = if matches!(user_op, Op::GenerateContent) {
@@ -1560,9 +3365,60 @@ impl ProxyEngine {
             None
         };
 
+        // A finished inline batch is accounted once, as one usage row per inner request.
+        let batch_usage = match &resp_native {
+            Response::BatchGet(gproxy_provider_core::BatchGetResponse::Gemini(batch))
+                if batch.done == Some(true)
+                    && !batch.inlined_responses().is_empty()
+                    && self
+                        .state
+                        .accounted_batches
+                        .first_completion(&provider, &batch.name) =>
+            {
+                batch_inner_usage(batch)
+            }
+            _ => Vec::new(),
+        };
+
+        // Same protocol: hand back the upstream JSON itself so fields the typed responses
+        // don't model reach the client; only the model names are rewritten in place.
+        let same_proto_bytes = if provider_proto == user_proto && provider_op == user_op {
+            rewrite_models_in_json(&body, user_proto, user_op, &model_rewrite)
+        } else {
+            None
+        };
+        // Failures are returned after the upstream event, which records them.
+        let mut encode_failure = None;
+        let out_bytes = match same_proto_bytes {
+            Some(bytes) => Ok(bytes),
+            None => {
+                let to_user = TransformContext {
+                    src: provider_proto,
+                    dst: user_proto,
+                    src_op: user_op,
+                    dst_op: user_op,
+                };
+                match transform_response_maybe(&to_user, resp_native) {
+                    Ok(resp_user) => {
+                        let resp_user = maybe_prefix_model_in_response(resp_user, &model_rewrite);
+                        encode_response(user_proto, user_op, &resp_user).map_err(|err| {
+                            let resp = self.encode_failed(&trace_id, &err);
+                            encode_failure = Some(err);
+                            resp
+                        })
+                    }
+                    Err(err) => Err(json_error_with(
+                        500,
+                        "transform_response_failed",
+                        format!("{err:?}"),
+                    )),
+                }
+            }
+        };
+
         self.emit_upstream_event(UpstreamEventInput {
             trace_id: trace_id.clone(),
-            auth,
+            auth: auth.clone(),
             provider: provider.clone(),
             credential_id: Some(cred_id),
             internal: false,
@@ -1573,30 +3429,40 @@ impl ProxyEngine {
             response_headers: Some(upstream_resp.headers.clone()),
             response_body: Some(body.to_vec()),
             usage: usage.clone(),
-            error_kind: None,
-            error_message: None,
+            error_kind: encode_failure
+                .as_ref()
+                .map(|err| err.event_kind().to_string()),
+            error_message: encode_failure.as_ref().map(ToString::to_string),
             transport_kind: None,
         })
         .await;
+        for (model, inner_usage) in batch_usage {
+            self.emit_upstream_event(UpstreamEventInput {
+                trace_id: trace_id.clone(),
+                auth: ProxyAuth {
+                    model: model.or_else(|| auth.model.clone()),
+                    ..auth.clone()
+                },
+                provider: provider.clone(),
+                credential_id: Some(cred_id),
+                internal: false,
+                attempt_no,
+                operation: format!("{:?}", Op::BatchGenerateContent),
+                upstream_req: &upstream_req,
+                response_status: Some(upstream_resp.status),
+                response_headers: None,
+                response_body: None,
+                usage: Some(inner_usage),
+                error_kind: None,
+                error_message: None,
+                transport_kind: None,
+            })
+            .await;
+        }
 
-        let to_user = TransformContext {
-            src: provider_proto,
-            dst: user_proto,
-            src_op: user_op,
-            dst_op: user_op,
-        };
-        let resp_user = match transform_response_maybe(&to_user, resp_native) {
-            Ok(r) => r,
-            Err(err) => {
-                return json_error_with(500, "transform_response_failed", format!("{err:?}"));
-            }
-        };
-        let resp_user =
-            maybe_prefix_model_in_response(resp_user, response_model_prefix_provider.as_deref());
-
-        let out_bytes = match encode_response(user_proto, user_op, &resp_user) {
-            Ok(b) => b,
-            Err(err) => return json_error_with(500, "encode_response_failed", err.to_string()),
+        let out_bytes = match out_bytes {
+            Ok(bytes) => bytes,
+            Err(resp) => return resp,
         };
 
         let mut headers = upstream_resp.headers.clone();
@@ -1614,12 +3480,12 @@ impl ProxyEngine {
         trace_id: Option<String>,
         auth: crate::proxy_engine::ProxyAuth,
         provider: String,
-        response_model_prefix_provider: Option<String>,
+        model_rewrite: ModelRewrite,
         provider_impl: Arc<dyn UpstreamProvider>,
-        _runtime: Arc<ProviderRuntime>,
-        config: ProviderConfig,
+        runtime: Arc<ProviderRuntime>,
+        config: Arc<ProviderConfig>,
         cred_id: i64,
-        cred: Credential,
+        cred: Arc<Credential>,
         attempt_no: u32,
         user_proto: Proto,
         provider_proto: Proto,
@@ -1630,15 +3496,24 @@ impl ProxyEngine {
         let UpstreamBody::Stream(rx_in) = upstream_resp.body else {
             return json_error(502, "expected_stream_body");
         };
-        let rx_in = if needs_internal_stream_unwrap(&provider, provider_proto) {
-            map_internal_gemini_stream(&provider, rx_in)
-        } else {
-            rx_in
+        let internal_unwrap = provider_impl.unwrap_internal_stream(provider_proto);
+        let rx_in = match internal_unwrap {
+            Some(unwrap) => map_internal_stream(unwrap, rx_in),
+            None => rx_in,
         };
         let format = match stream_format(provider_proto) {
             Some(f) => f,
             None => return json_error(500, "invalid_stream_proto"),
         };
+        let policies = runtime.policies();
+        let (timeouts, stream_idle) = with_stream_watchdog(
+            policies.timeouts.for_op(Op::StreamGenerateContent),
+            Op::StreamGenerateContent,
+            self.state.global.load().stream_idle_timeout_ms,
+        );
+        // Owned: the resume attempt runs in the spawned stream task.
+        let (egress, tls) = (policies.egress.clone(), policies.tls.clone());
+        let resume_proxy = self.pick_egress_proxy(&egress, cred_id);
 
         // Native Gemini stream passthrough.
         //
@@ -1659,25 +3534,46 @@ impl ProxyEngine {
             let (upstream_path, upstream_query) = split_path_query(&upstream_req.url);
             let upstream_resp_headers = upstream_resp.headers.clone();
             let redact_sensitive = self.state.global.load().event_redact_sensitive;
+            let redact_bodies = self.redact_bodies(trace_id.as_deref());
+            let log_body_cap = self.event_log_max_body_bytes();
             let status = upstream_resp.status;
+            let stream_guard = self.state.stats.stream_started();
 
             tokio::spawn(async move {
+                let _stream_guard = stream_guard;
                 let mut rx_in = rx_in;
                 let mut response_body = Vec::new();
                 let mut error_kind: Option<String> = None;
                 let mut error_message: Option<String> = None;
-                while let Some(chunk) = rx_in.recv().await {
-                    append_capped(
-                        &mut response_body,
-                        chunk.as_ref(),
-                        MAX_UPSTREAM_LOG_BODY_BYTES,
-                    );
+                loop {
+                    let chunk = match recv_watched(&mut rx_in, stream_idle).await {
+                        Ok(Some(chunk)) => chunk,
+                        Ok(None) => break,
+                        Err(_) => {
+                            error_kind = Some(STREAM_IDLE_TIMEOUT.to_string());
+                            error_message = Some("upstream_stream_idle".to_string());
+                            let _ = tx_out
+                                .send(encode_stream_error(
+                                    Proto::Gemini,
+                                    504,
+                                    "upstream_stream_idle_timeout",
+                                    "upstream stream went idle",
+                                    0,
+                                ))
+                                .await;
+                            break;
+                        }
+                    };
+                    append_capped(&mut response_body, chunk.as_ref(), log_body_cap);
                     if tx_out.send(chunk).await.is_err() {
                         error_kind = Some("stream_forward_error".to_string());
                         error_message = Some("downstream_stream_closed".to_string());
                         break;
                     }
                 }
+                // Dropping the receiver aborts the upstream read.
+                drop(rx_in);
+                let idle = error_kind.as_deref() == Some(STREAM_IDLE_TIMEOUT);
                 events
                     .emit(Event::Upstream(UpstreamEvent {
                         trace_id: trace_id2,
@@ -1694,9 +3590,10 @@ impl ProxyEngine {
                             upstream_req2.headers.clone(),
                             redact_sensitive,
                         ),
+                        request_host: url_host(&upstream_req2.url),
                         request_path: upstream_path,
                         request_query: maybe_redact_query(upstream_query, redact_sensitive),
-                        request_body: if redact_sensitive {
+                        request_body: if redact_bodies {
                             None
                         } else {
                             upstream_req2.body.clone().map(|b| b.to_vec())
@@ -1706,7 +3603,7 @@ impl ProxyEngine {
                             upstream_resp_headers.clone(),
                             redact_sensitive,
                         ),
-                        response_body: if redact_sensitive {
+                        response_body: if redact_bodies {
                             None
                         } else {
                             Some(response_body)
@@ -1714,7 +3611,16 @@ impl ProxyEngine {
                         usage: None,
                         error_kind,
                         error_message,
-                        transport_kind: None,
+                        transport_kind: idle.then_some(
+                            gproxy_provider_core::provider::UpstreamTransportErrorKind::ReadTimeout,
+                        ),
+                        tags: auth2.tags.clone(),
+                        anthropic_betas: header_betas(&upstream_req2.headers),
+                        model: auth2.model.clone(),
+                        latency_ms: Some(elapsed_ms(auth2.received_at)),
+                        experiment: auth2.experiment.as_ref().map(|e| e.experiment.clone()),
+                        experiment_arm: auth2.experiment.as_ref().map(|e| e.arm.clone()),
+                        transform: auth2.transform.clone(),
                     }))
                     .await;
             });
@@ -1732,6 +3638,7 @@ impl ProxyEngine {
         let client = self.client.clone();
         let provider_impl2 = provider_impl.clone();
         let config2 = config.clone();
+        let policies2 = policies.clone();
         let cred2 = cred.clone();
         let trace_id2 = trace_id.clone();
         let auth2 = auth;
@@ -1741,33 +3648,21 @@ impl ProxyEngine {
         let (upstream_path, upstream_query) = split_path_query(&upstream_req.url);
         let upstream_resp_headers = upstream_resp.headers.clone();
         let redact_sensitive = self.state.global.load().event_redact_sensitive;
+        let redact_bodies = self.redact_bodies(trace_id.as_deref());
+        let log_body_cap = self.event_log_max_body_bytes();
+        let retry_on_interrupt = self.state.global.load().stream_retry_on_interrupt;
         let status = upstream_resp.status;
-        let prefix_provider = response_model_prefix_provider;
+        let stream_guard = self.state.stats.stream_started();
+        let resource_owners = self.state.resource_owners.clone();
+        let app_state = self.state.clone();
 
         tokio::spawn(async move {
-            let mut decoder = StreamDecoder::new(provider_proto, format);
-            let mut usage_acc = UsageAccumulator::new(provider_proto);
-            let mut out_acc = OutputAccumulator::new(provider_proto);
-            let mut response_body = Vec::new();
-            let mut error_kind: Option<String> = None;
-            let mut error_message: Option<String> = None;
+            let _stream_guard = stream_guard;
             // For same-proto OpenAI streams, prefer raw passthrough to avoid dropping
             // forward-compatible events during decode/re-encode.
             let passthrough_raw = provider_proto == user_proto
                 && user_proto != Proto::Gemini
-                && prefix_provider.is_none();
-
-            let mut transformer = if provider_proto == user_proto {
-                None
-            } else {
-                let ctx = TransformContext {
-                    src: provider_proto,
-                    dst: user_proto,
-                    src_op: Op::StreamGenerateContent,
-                    dst_op: Op::StreamGenerateContent,
-                };
-                StreamTransformer::new(&ctx).ok()
-            };
+                && model_rewrite.is_none();
 
             // Extract provider-native generate request for fallback counting.
             let input_req = match &req_native {
@@ -1790,174 +3685,338 @@ impl ProxyEngine {
             };
 
             let mut rx_in = rx_in;
-            'stream_loop: while let Some(chunk) = rx_in.recv().await {
-                append_capped(
-                    &mut response_body,
-                    chunk.as_ref(),
-                    MAX_UPSTREAM_LOG_BODY_BYTES,
-                );
-                if passthrough_raw {
-                    for ev in decoder.push_bytes(&chunk) {
-                        let _ = usage_acc.push(&ev);
-                        out_acc.push(&ev);
-                    }
-                    if tx_out.send(chunk).await.is_err() {
-                        error_kind = Some("stream_forward_error".to_string());
-                        error_message = Some("downstream_stream_closed".to_string());
-                        break 'stream_loop;
-                    }
-                    continue;
-                }
+            let mut attempt_no = attempt_no;
+            let mut status = status;
+            let mut upstream_resp_headers = upstream_resp_headers;
+            let mut retry_left = retry_on_interrupt;
+            // Set once any byte reached the client; a resumed stream must not replay output.
+            let mut forwarded_any = false;
+
+            loop {
+                let mut tc =
+                    StreamTranscode::new(provider_proto, user_proto, format, model_rewrite.clone());
+                let mut response_body = Vec::new();
+                let mut error_kind: Option<String> = None;
+                let mut error_message: Option<String> = None;
 
-                for ev in decoder.push_bytes(&chunk) {
-                    let _ = usage_acc.push(&ev);
-                    out_acc.push(&ev);
+                let mut resumes: u32 = 0;
+                loop {
+                    'stream_loop: loop {
+                        let chunk = match recv_watched(&mut rx_in, stream_idle).await {
+                            Ok(Some(chunk)) => chunk,
+                            Ok(None) => break,
+                            Err(_) => {
+                                error_kind = Some(STREAM_IDLE_TIMEOUT.to_string());
+                                error_message = Some("upstream_stream_idle".to_string());
+                                break;
+                            }
+                        };
+                        append_capped(&mut response_body, chunk.as_ref(), log_body_cap);
+                        if passthrough_raw {
+                            let Some(events) = tc.passthrough(&chunk) else {
+                                continue;
+                            };
+                            if tx_out.send(events).await.is_err() {
+                                error_kind = Some("stream_forward_error".to_string());
+                                error_message = Some("downstream_stream_closed".to_string());
+                                break 'stream_loop;
+                            }
+                            forwarded_any = true;
+                            continue;
+                        }
 
-                    let mut out_events: Vec<StreamEvent> = Vec::new();
-                    if let Some(t) = transformer.as_mut() {
-                        match t.push(ev) {
-                            Ok(mut v) => out_events.append(&mut v),
-                            Err(err) => {
-                                error_kind = Some("stream_transform_error".to_string());
-                                error_message = Some(format!("{err:?}"));
+                        // Converting between protocols is CPU work; keep it off the runtime.
+                        let (encoded, failure) = if tc.converts() {
+                            let (back, result) = app_state
+                                .transcoder
+                                .run(move || {
+                                    let mut tc = tc;
+                                    let result = tc.transcode(&chunk);
+                                    (tc, result)
+                                })
+                                .await;
+                            tc = back;
+                            result
+                        } else {
+                            tc.transcode(&chunk)
+                        };
+                        for bytes in encoded {
+                            if tx_out.send(bytes).await.is_err() {
+                                error_kind = Some("stream_forward_error".to_string());
+                                error_message = Some("downstream_stream_closed".to_string());
                                 break 'stream_loop;
                             }
+                            forwarded_any = true;
+                        }
+                        if let Some(err) = failure {
+                            error_kind = Some("stream_transform_error".to_string());
+                            error_message = Some(err);
+                            break 'stream_loop;
                         }
-                    } else {
-                        out_events.push(ev);
                     }
 
-                    for out_ev in out_events {
-                        let out_ev =
-                            maybe_prefix_model_in_stream_event(out_ev, prefix_provider.as_deref());
-                        if let Some(bytes) = encode_stream_event(user_proto, &out_ev)
-                            && tx_out.send(bytes).await.is_err()
-                        {
+                    if passthrough_raw
+                        && error_kind.is_none()
+                        && let Some(tail) = tc.passthrough_tail()
+                    {
+                        if tx_out.send(tail).await.is_err() {
                             error_kind = Some("stream_forward_error".to_string());
                             error_message = Some("downstream_stream_closed".to_string());
-                            break 'stream_loop;
+                        } else {
+                            forwarded_any = true;
                         }
                     }
-                }
-            }
-
-            if error_kind.is_none() {
-                for ev in decoder.finish() {
-                    let _ = usage_acc.push(&ev);
-                    out_acc.push(&ev);
-                    if passthrough_raw {
-                        continue;
-                    }
 
-                    let mut out_events: Vec<StreamEvent> = Vec::new();
-                    if let Some(t) = transformer.as_mut() {
-                        match t.push(ev) {
-                            Ok(mut v) => out_events.append(&mut v),
-                            Err(err) => {
-                                error_kind = Some("stream_transform_error".to_string());
-                                error_message = Some(format!("{err:?}"));
-                                break;
-                            }
+                    // Transient drop: replay the rest of the upstream stream from the last
+                    // observed event when the provider exposes a resume cursor.
+                    if error_kind.is_none()
+                        && !tc.terminal_seen
+                        && resumes < MAX_STREAM_RESUMES
+                        && let Some(resume_req) = tc.resume_cursor.resume_request()
+                    {
+                        resumes += 1;
+                        let ctx = UpstreamCtx {
+                            trace_id: trace_id2.clone(),
+                            user_id: Some(auth2.user_id),
+                            user_key_id: Some(auth2.user_key_id),
+                            user_agent: auth2.user_agent.clone(),
+                            outbound_proxy: outbound_proxy2.clone(),
+                            provider: provider2.clone(),
+                            credential_id: Some(cred_id),
+                            op: Op::ResponseGet,
+                            internal: true,
+                            attempt_no,
+                            caller: auth2.caller.clone(),
+                        };
+                        if let Some(stream) = resume_upstream_stream(
+                            client.as_ref(),
+                            provider_impl2.as_ref(),
+                            &ctx,
+                            &config2,
+                            &cred2,
+                            &resume_req,
+                            &policies2.header,
+                            &auth2.request_headers,
+                        )
+                        .await
+                        {
+                            rx_in = stream;
+                            tc.restart_decoder();
+                            continue;
                         }
-                    } else {
-                        out_events.push(ev);
                     }
+                    break;
+                }
 
-                    for out_ev in out_events {
-                        let out_ev =
-                            maybe_prefix_model_in_stream_event(out_ev, prefix_provider.as_deref());
-                        if let Some(bytes) = encode_stream_event(user_proto, &out_ev)
-                            && tx_out.send(bytes).await.is_err()
-                        {
+                if error_kind.is_none() {
+                    let (encoded, failure) = tc.finish(passthrough_raw);
+                    for bytes in encoded {
+                        if tx_out.send(bytes).await.is_err() {
                             error_kind = Some("stream_forward_error".to_string());
                             error_message = Some("downstream_stream_closed".to_string());
                             break;
                         }
+                        forwarded_any = true;
                     }
-                    if error_kind.is_some() {
-                        break;
+                    if error_kind.is_none()
+                        && let Some(err) = failure
+                    {
+                        error_kind = Some("stream_transform_error".to_string());
+                        error_message = Some(err);
                     }
                 }
-            }
 
-            if error_kind.is_none()
-                && !passthrough_raw
-                && user_proto == Proto::OpenAIChat
-                && tx_out.send(encode_openai_chat_done()).await.is_err()
-            {
-                error_kind = Some("stream_forward_error".to_string());
-                error_message = Some("downstream_stream_closed".to_string());
-            }
+                let end = tc.end(error_kind.as_deref(), retry_left, forwarded_any);
+                // The upstream closed (error, reset) before its terminal event.
+                if error_kind.is_none() && end != StreamEnd::Done {
+                    error_kind = Some("stream_interrupted".to_string());
+                    error_message = Some("upstream_stream_ended_before_completion".to_string());
+                }
+                // The watchdog saw no upstream bytes for the idle window.
+                let idle = error_kind.as_deref() == Some(STREAM_IDLE_TIMEOUT);
+                let retry = end == StreamEnd::Retry;
+                if let StreamEnd::Fail {
+                    status: error_status,
+                    code,
+                    message,
+                } = end
+                {
+                    let _ = tx_out
+                        .send(encode_stream_error(
+                            user_proto,
+                            error_status,
+                            code,
+                            message,
+                            tc.next_sequence_number(),
+                        ))
+                        .await;
+                }
 
-            // Finalize usage (provider-native).
-            let mut usage = usage_acc.finalize();
-            if usage.is_none()
-                && error_kind.is_none()
-                && let Some(input_req) = input_req
-            {
-                let count_fn = EngineCountTokensFn {
-                    provider: provider_impl2,
-                    config: config2,
-                    credential: cred2,
-                    trace_id: trace_id2.clone(),
-                    outbound_proxy: outbound_proxy2.clone(),
-                    provider_name: provider2.clone(),
-                    client,
-                };
-                if let Ok(Ok(u)) = tokio::task::spawn_blocking(move || {
-                    fallback_usage_with_count_tokens(
+                if error_kind.is_none()
+                    && !passthrough_raw
+                    && user_proto == Proto::OpenAIChat
+                    && tx_out.send(encode_openai_chat_done()).await.is_err()
+                {
+                    error_kind = Some("stream_forward_error".to_string());
+                    error_message = Some("downstream_stream_closed".to_string());
+                }
+
+                if let Some(response_id) = tc.resume_cursor.response_id() {
+                    resource_owners.record(&provider2, response_id, cred_id);
+                }
+
+                let output_chars = tc.output.as_str().chars().count() as i64;
+                // Finalize usage (provider-native).
+                let mut usage = tc.usage.finalize();
+                if usage.is_none()
+                    && error_kind.is_none()
+                    && let Some(input_req) = input_req.clone()
+                {
+                    let count_fn = EngineCountTokensFn {
+                        provider: provider_impl2.clone(),
+                        config: config2.clone(),
+                        policies: policies2.clone(),
+                        request_headers: auth2.request_headers.clone(),
+                        credential: cred2.clone(),
+                        trace_id: trace_id2.clone(),
+                        outbound_proxy: outbound_proxy2.clone(),
+                        provider_name: provider2.clone(),
+                        client: client.clone(),
+                    };
+                    if let Ok(u) = fallback_usage_with_count_tokens(
                         provider_proto,
                         &input_req,
-                        out_acc.as_str(),
+                        tc.output.as_str(),
                         &count_fn,
                     )
-                })
-                .await
-                {
-                    usage = Some(u)
+                    .await
+                    {
+                        usage = Some(u)
+                    }
                 }
-            }
 
-            // Emit usage event (async, non-blocking for the stream itself).
-            events
-                .emit(Event::Upstream(UpstreamEvent {
-                    trace_id: trace_id2,
-                    at: SystemTime::now(),
-                    user_id: Some(auth2.user_id),
-                    user_key_id: Some(auth2.user_key_id),
-                    provider: provider2,
-                    credential_id: Some(cred_id),
-                    internal: false,
-                    attempt_no,
-                    operation: format!("{:?}", Op::StreamGenerateContent),
-                    request_method: upstream_req2.method.as_str().to_string(),
-                    request_headers: maybe_redact_headers(
-                        upstream_req2.headers.clone(),
-                        redact_sensitive,
-                    ),
-                    request_path: upstream_path,
-                    request_query: maybe_redact_query(upstream_query, redact_sensitive),
-                    request_body: if redact_sensitive {
-                        None
-                    } else {
-                        upstream_req2.body.clone().map(|b| b.to_vec())
-                    },
-                    response_status: Some(status),
-                    response_headers: maybe_redact_headers(
-                        upstream_resp_headers.clone(),
-                        redact_sensitive,
-                    ),
-                    response_body: if redact_sensitive {
-                        None
-                    } else {
-                        Some(response_body)
-                    },
-                    usage,
-                    error_kind,
-                    error_message,
-                    transport_kind: None,
-                }))
-                .await;
+                // A stream that ends here for good keeps what it produced on record.
+                if !retry && let Some(reason) = &error_kind {
+                    app_state.aborted_streams.record(AbortedStreamRecord {
+                        id: 0,
+                        trace_id: trace_id2.clone(),
+                        user_key_id: auth2.user_key_id,
+                        provider: provider2.clone(),
+                        credential_id: Some(cred_id),
+                        model: auth2.model.clone(),
+                        reason: reason.clone(),
+                        output_chars,
+                        input_tokens: usage.as_ref().and_then(|u| u.input_tokens).map(i64::from),
+                        // Four characters per token when upstream did not report a count.
+                        output_tokens: usage
+                            .as_ref()
+                            .and_then(|u| u.output_tokens)
+                            .map_or((output_chars + 3) / 4, i64::from),
+                        duration_ms: i64::try_from(elapsed_ms(auth2.received_at))
+                            .unwrap_or(i64::MAX),
+                        at: OffsetDateTime::now_utc(),
+                    });
+                }
+
+                // Emit usage event (async, non-blocking for the stream itself).
+                events
+                    .emit(Event::Upstream(UpstreamEvent {
+                        trace_id: trace_id2.clone(),
+                        at: SystemTime::now(),
+                        user_id: Some(auth2.user_id),
+                        user_key_id: Some(auth2.user_key_id),
+                        provider: provider2.clone(),
+                        credential_id: Some(cred_id),
+                        internal: false,
+                        attempt_no,
+                        operation: format!("{:?}", Op::StreamGenerateContent),
+                        request_method: upstream_req2.method.as_str().to_string(),
+                        request_headers: maybe_redact_headers(
+                            upstream_req2.headers.clone(),
+                            redact_sensitive,
+                        ),
+                        request_host: url_host(&upstream_req2.url),
+                        request_path: upstream_path.clone(),
+                        request_query: maybe_redact_query(upstream_query.clone(), redact_sensitive),
+                        request_body: if redact_bodies {
+                            None
+                        } else {
+                            upstream_req2.body.clone().map(|b| b.to_vec())
+                        },
+                        response_status: Some(status),
+                        response_headers: maybe_redact_headers(
+                            upstream_resp_headers.clone(),
+                            redact_sensitive,
+                        ),
+                        response_body: if redact_bodies {
+                            None
+                        } else {
+                            Some(response_body)
+                        },
+                        usage,
+                        error_kind,
+                        error_message,
+                        transport_kind: idle.then_some(
+                            gproxy_provider_core::provider::UpstreamTransportErrorKind::ReadTimeout,
+                        ),
+                        tags: auth2.tags.clone(),
+                        anthropic_betas: header_betas(&upstream_req2.headers),
+                        model: auth2.model.clone(),
+                        latency_ms: Some(elapsed_ms(auth2.received_at)),
+                        experiment: auth2.experiment.as_ref().map(|e| e.experiment.clone()),
+                        experiment_arm: auth2.experiment.as_ref().map(|e| e.arm.clone()),
+                        transform: auth2.transform.clone(),
+                    }))
+                    .await;
+
+                if !retry {
+                    break;
+                }
+
+                // Nothing reached the client yet: reissue the same upstream request once.
+                retry_left = false;
+                attempt_no += 1;
+                let resumed = match client
+                    .send_with_options(
+                        upstream_req2.clone(),
+                        SendOptions {
+                            timeouts,
+                            egress: egress.clone(),
+                            tls: tls.clone(),
+                            proxy: resume_proxy.clone(),
+                            credential_id: Some(cred_id),
+                        },
+                    )
+                    .await
+                {
+                    Ok(UpstreamHttpResponse {
+                        status: resumed_status,
+                        headers,
+                        body: UpstreamBody::Stream(stream),
+                    }) if (200..300).contains(&resumed_status) => {
+                        Some((resumed_status, headers, stream))
+                    }
+                    _ => None,
+                };
+                let Some((resumed_status, headers, stream)) = resumed else {
+                    let _ = tx_out
+                        .send(encode_stream_error(
+                            user_proto,
+                            502,
+                            "upstream_stream_interrupted",
+                            "upstream stream ended before completion and could not be resumed",
+                            tc.next_sequence_number(),
+                        ))
+                        .await;
+                    break;
+                };
+                status = resumed_status;
+                upstream_resp_headers = headers;
+                rx_in = match internal_unwrap {
+                    Some(unwrap) => map_internal_stream(unwrap, stream),
+                    None => stream,
+                };
+            }
         });
 
         let mut headers = upstream_resp.headers;
@@ -1979,12 +4038,12 @@ impl ProxyEngine {
         trace_id: Option<String>,
         auth: crate::proxy_engine::ProxyAuth,
         provider: String,
-        response_model_prefix_provider: Option<String>,
+        model_rewrite: ModelRewrite,
         provider_impl: Arc<dyn UpstreamProvider>,
-        _runtime: Arc<ProviderRuntime>,
-        config: ProviderConfig,
+        runtime: Arc<ProviderRuntime>,
+        config: Arc<ProviderConfig>,
         cred_id: i64,
-        cred: Credential,
+        cred: Arc<Credential>,
         attempt_no: u32,
         user_proto: Proto,
         provider_proto: Proto,
@@ -2004,6 +4063,7 @@ impl ProxyEngine {
         let mut usage_acc = UsageAccumulator::new(provider_proto);
         let mut out_acc = OutputAccumulator::new(provider_proto);
         let mut response_body = Vec::new();
+        let log_body_cap = self.event_log_max_body_bytes();
         let mut completed_resp: Option<Response> = None;
 
         let ctx = TransformContext {
@@ -2020,11 +4080,7 @@ impl ProxyEngine {
         };
 
         while let Some(chunk) = rx.recv().await {
-            append_capped(
-                &mut response_body,
-                chunk.as_ref(),
-                MAX_UPSTREAM_LOG_BODY_BYTES,
-            );
+            append_capped(&mut response_body, chunk.as_ref(), log_body_cap);
             for ev in decoder.push_bytes(&chunk) {
                 let _ = usage_acc.push(&ev);
                 out_acc.push(&ev);
@@ -2061,13 +4117,16 @@ impl ProxyEngine {
             Some(r) => r,
             None => return json_error(502, "stream_to_nonstream_failed"),
         };
-        let resp_user =
-            maybe_prefix_model_in_response(resp_user, response_model_prefix_provider.as_deref());
-
-        let out_bytes = match encode_response(user_proto, Op::GenerateContent, &resp_user) {
-            Ok(b) => b,
-            Err(err) => return json_error_with(500, "encode_response_failed", err.to_string()),
-        };
+        let resp_user = maybe_prefix_model_in_response(resp_user, &model_rewrite);
+
+        // Failures are returned after the upstream event, which records them.
+        let mut encode_failure = None;
+        let out_bytes =
+            encode_response(user_proto, Op::GenerateContent, &resp_user).map_err(|err| {
+                let resp = self.encode_failed(&trace_id, &err);
+                encode_failure = Some(err);
+                resp
+            });
 
         // Usage (provider-native).
         let mut usage = usage_acc.finalize();
@@ -2077,20 +4136,20 @@ impl ProxyEngine {
             let count_fn = EngineCountTokensFn {
                 provider: provider_impl.clone(),
                 config: config.clone(),
+                policies: runtime.policies(),
+                request_headers: auth.request_headers.clone(),
                 credential: cred.clone(),
                 trace_id: trace_id.clone(),
                 outbound_proxy: self.state.global.load().proxy.clone(),
                 provider_name: provider.clone(),
                 client: self.client.clone(),
             };
-            if let Ok(Ok(u)) = tokio::task::spawn_blocking(move || {
-                fallback_usage_with_count_tokens(
-                    provider_proto,
-                    &input_req,
-                    out_acc.as_str(),
-                    &count_fn,
-                )
-            })
+            if let Ok(u) = fallback_usage_with_count_tokens(
+                provider_proto,
+                &input_req,
+                out_acc.as_str(),
+                &count_fn,
+            )
             .await
             {
                 usage = Some(u)
@@ -2110,12 +4169,18 @@ impl ProxyEngine {
             response_headers: Some(upstream_resp.headers.clone()),
             response_body: Some(response_body),
             usage: usage.clone(),
-            error_kind: None,
-            error_message: None,
+            error_kind: encode_failure
+                .as_ref()
+                .map(|err| err.event_kind().to_string()),
+            error_message: encode_failure.as_ref().map(ToString::to_string),
             transport_kind: None,
         })
         .await;
 
+        let out_bytes = match out_bytes {
+            Ok(bytes) => bytes,
+            Err(resp) => return resp,
+        };
         let mut headers = upstream_resp.headers;
         header_set(&mut headers, "content-type", "application/json");
         UpstreamHttpResponse {
@@ -2131,12 +4196,12 @@ impl ProxyEngine {
         trace_id: Option<String>,
         auth: crate::proxy_engine::ProxyAuth,
         provider: String,
-        response_model_prefix_provider: Option<String>,
+        model_rewrite: ModelRewrite,
         _provider_impl: Arc<dyn UpstreamProvider>,
         _runtime: Arc<ProviderRuntime>,
-        _config: ProviderConfig,
+        _config: Arc<ProviderConfig>,
         cred_id: i64,
-        _cred: Credential,
+        _cred: Arc<Credential>,
         attempt_no: u32,
         user_proto: Proto,
         provider_proto: Proto,
@@ -2147,6 +4212,11 @@ impl ProxyEngine {
         let Some(body) = resp_body_bytes(&upstream_resp.body) else {
             return json_error(502, "upstream_body_missing");
         };
+        if let Some(resp) =
+            response_limit::too_large(body.len(), self.state.global.load().max_response_body_bytes)
+        {
+            return resp;
+        }
         let resp_native = match decode_response(provider_proto, Op::GenerateContent, &body) {
             Ok(r) => r,
             Err(err) => return json_error_with(502, "decode_response_failed", err.to_string()),
@@ -2194,9 +4264,7 @@ impl ProxyEngine {
         };
         let out_events: Vec<StreamEvent> = out_events
             .into_iter()
-            .map(|ev| {
-                maybe_prefix_model_in_stream_event(ev, response_model_prefix_provider.as_deref())
-            })
+            .map(|ev| maybe_prefix_model_in_stream_event(ev, &model_rewrite))
             .collect();
 
         let (tx, rx) = tokio::sync::mpsc::channel::<Bytes>(32);
@@ -2232,14 +4300,21 @@ impl ProxyEngine {
         cred_id: i64,
         op: Op,
         model: Option<&String>,
-        decision: gproxy_provider_core::provider::UnavailableDecision,
+        decision: UnavailableDecision,
+        failure: &UpstreamFailure,
     ) {
+        // A `retry` failure rule: try again without cooling the credential down.
+        if decision.duration.is_zero() {
+            return;
+        }
         if !is_generate_op(op) {
             if matches!(decision.reason, UnavailableReason::AuthInvalid) {
                 runtime
                     .pool
                     .mark_unavailable(cred_id, decision.duration, decision.reason)
                     .await;
+                self.note_provider_outage(&runtime, model, Some(failure_message(failure)))
+                    .await;
             }
             return;
         }
@@ -2272,6 +4347,26 @@ impl ProxyEngine {
                 .mark_unavailable(cred_id, decision.duration, decision.reason)
                 .await;
         }
+        self.note_provider_outage(&runtime, model, Some(failure_message(failure)))
+            .await;
+    }
+
+    /// Reports the provider to the incident tracker when none of its credentials is
+    /// available. `error` is the failure that cooled the last one down; `None` counts a
+    /// request refused with `no_active_credentials`.
+    async fn note_provider_outage(
+        &self,
+        runtime: &ProviderRuntime,
+        model: Option<&String>,
+        error: Option<String>,
+    ) {
+        let availability = runtime.pool.availability(&runtime.provider_id).await;
+        if availability.credentials == 0 || availability.unavailable < availability.credentials {
+            return;
+        }
+        self.state
+            .incidents
+            .observe(&runtime.provider_id, model.map(String::as_str), error);
     }
 
     async fn persist_credential_update(
@@ -2329,22 +4424,27 @@ impl ProxyEngine {
         runtime: Arc<ProviderRuntime>,
         input: NonGenerateUnavailableInput<'_>,
     ) {
-        if !is_auth_failure(input.failure) {
+        if !is_auth_failure(&runtime, input.failure)
+            && failure_rule(&runtime, input.failure).is_none()
+        {
             return;
         }
-        if let Some(decision) = input.provider_impl.decide_unavailable(
-            input.ctx,
-            input.config,
-            input.cred,
-            input.req_native,
-            input.failure,
-        ) {
+        if let Some(decision) = decide_unavailable(&runtime, input.failure, || {
+            input.provider_impl.decide_unavailable(
+                input.ctx,
+                input.config,
+                input.cred,
+                input.req_native,
+                input.failure,
+            )
+        }) {
             self.apply_unavailable_decision(
                 runtime,
                 input.cred_id,
                 input.op,
                 input.model,
                 decision,
+                input.failure,
             )
             .await;
         }
@@ -2372,8 +4472,8 @@ impl ProxyEngine {
             return Err(json_error(404, "provider_not_found"));
         };
 
-        let cfg_value = runtime.config_json.load().as_ref().clone();
-        let cfg: ProviderConfig = serde_json::from_value(cfg_value)
+        let cfg = runtime
+            .provider_config()
             .map_err(|err| json_error_with(500, "provider_config_invalid", err.to_string()))?;
 
         let provider_impl_name = provider_impl_name_from_config(&cfg);
@@ -2389,19 +4489,24 @@ impl ProxyEngine {
         runtime: &Arc<ProviderRuntime>,
         provider: &str,
         model: Option<&String>,
+        scope: &CredentialScope,
+        excluded: &HashSet<i64>,
     ) -> bool {
-        match model {
-            Some(model) => runtime
-                .pool
-                .acquire_for_model(provider, model)
-                .await
-                .is_ok(),
-            None => runtime.pool.acquire(provider).await.is_ok(),
-        }
+        runtime
+            .pool
+            .acquire_excluding(
+                provider,
+                model.map(String::as_str),
+                scope.allowed(),
+                excluded,
+            )
+            .await
+            .is_ok()
     }
 
     async fn emit_upstream_event(&self, input: UpstreamEventInput<'_>) {
         let redact_sensitive = self.state.global.load().event_redact_sensitive;
+        let redact_bodies = self.redact_bodies(input.trace_id.as_deref());
         let (request_path, request_query) = split_path_query(&input.upstream_req.url);
         self.state
             .events
@@ -2420,9 +4525,10 @@ impl ProxyEngine {
                     input.upstream_req.headers.clone(),
                     redact_sensitive,
                 ),
+                request_host: url_host(&input.upstream_req.url),
                 request_path,
                 request_query: maybe_redact_query(request_query, redact_sensitive),
-                request_body: if redact_sensitive {
+                request_body: if redact_bodies {
                     None
                 } else {
                     input.upstream_req.body.clone().map(|b| b.to_vec())
@@ -2432,7 +4538,7 @@ impl ProxyEngine {
                     input.response_headers.unwrap_or_default(),
                     redact_sensitive,
                 ),
-                response_body: if redact_sensitive {
+                response_body: if redact_bodies {
                     None
                 } else {
                     input.response_body
@@ -2441,11 +4547,29 @@ impl ProxyEngine {
                 error_kind: input.error_kind,
                 error_message: input.error_message,
                 transport_kind: input.transport_kind,
+                tags: input.auth.tags,
+                anthropic_betas: header_betas(&input.upstream_req.headers),
+                latency_ms: Some(elapsed_ms(input.auth.received_at)),
+                experiment: input.auth.experiment.as_ref().map(|e| e.experiment.clone()),
+                experiment_arm: input.auth.experiment.as_ref().map(|e| e.arm.clone()),
+                transform: input.auth.transform,
+                model: input.auth.model,
             }))
             .await;
     }
 }
 
+fn elapsed_ms(since: Instant) -> u64 {
+    u64::try_from(since.elapsed().as_millis()).unwrap_or(u64::MAX)
+}
+
+/// Host (and port) of an absolute URL.
+fn url_host(target: &str) -> Option<String> {
+    let rest = &target[target.find("://")? + 3..];
+    let end = rest.find(['/', '?']).unwrap_or(rest.len());
+    Some(rest[..end].to_string()).filter(|host| !host.is_empty())
+}
+
 fn split_path_query(target: &str) -> (String, Option<String>) {
     if let Some(scheme_idx) = target.find("://") {
         let rest = &target[(scheme_idx + 3)..];
@@ -2476,7 +4600,7 @@ fn maybe_redact_headers(mut headers: Headers, redact: bool) -> Headers {
     if !redact {
         return headers;
     }
-    for (k, v) in &mut headers {
+    for (k, v) in headers.iter_mut() {
         let key = k.to_ascii_lowercase();
         if matches!(
             key.as_str(),
@@ -2514,7 +4638,7 @@ fn maybe_redact_query(query: Option<String>, redact: bool) -> Option<String> {
     serde_urlencoded::to_string(pairs).ok()
 }
 
-fn provider_impl_name_from_config(cfg: &ProviderConfig) -> &'static str {
+fn provider_impl_name_from_config(cfg: &ProviderConfig) -> &str {
     match cfg {
         ProviderConfig::OpenAI(_) => "openai",
         ProviderConfig::Claude(_) => "claude",
@@ -2528,6 +4652,7 @@ fn provider_impl_name_from_config(cfg: &ProviderConfig) -> &'static str {
         ProviderConfig::Nvidia(_) => "nvidia",
         ProviderConfig::DeepSeek(_) => "deepseek",
         ProviderConfig::Custom(_) => "custom",
+        ProviderConfig::Plugin(cfg) => cfg.implementation.as_str(),
     }
 }
 
@@ -2535,8 +4660,11 @@ fn provider_impl_name_from_config(cfg: &ProviderConfig) -> &'static str {
 
 struct EngineCountTokensFn {
     provider: Arc<dyn UpstreamProvider>,
-    config: ProviderConfig,
-    credential: Credential,
+    config: Arc<ProviderConfig>,
+    policies: Arc<ProviderPolicies>,
+    /// Headers of the client request the count is made for, forwarded per the policy.
+    request_headers: Headers,
+    credential: Arc<Credential>,
     trace_id: Option<String>,
     outbound_proxy: Option<String>,
     provider_name: String,
@@ -2546,57 +4674,59 @@ struct EngineCountTokensFn {
 impl CountTokensFn for EngineCountTokensFn {
     type Error = String;
 
-    fn count_tokens(
+    async fn count_tokens(
         &self,
         _proto: Proto,
         req: CountTokensRequest,
     ) -> Result<CountTokensResponse, Self::Error> {
-        tokio::runtime::Handle::current().block_on(async move {
-            let ctx = UpstreamCtx {
-                trace_id: self.trace_id.clone(),
-                user_id: None,
-                user_key_id: None,
-                user_agent: None,
-                outbound_proxy: self.outbound_proxy.clone(),
-                provider: self.provider_name.clone(),
-                credential_id: None,
-                op: Op::CountTokens,
-                internal: true,
-                attempt_no: 0,
-            };
+        let ctx = UpstreamCtx {
+            trace_id: self.trace_id.clone(),
+            user_id: None,
+            user_key_id: None,
+            user_agent: None,
+            outbound_proxy: self.outbound_proxy.clone(),
+            provider: self.provider_name.clone(),
+            credential_id: None,
+            op: Op::CountTokens,
+            internal: true,
+            attempt_no: 0,
+            caller: Default::default(),
+        };
 
-            let upstream_req = match &req {
-                CountTokensRequest::Claude(r) => {
-                    self.provider
-                        .build_claude_count_tokens(&ctx, &self.config, &self.credential, r)
-                        .await
-                }
-                CountTokensRequest::OpenAI(r) => {
-                    self.provider
-                        .build_openai_input_tokens(&ctx, &self.config, &self.credential, r)
-                        .await
-                }
-                CountTokensRequest::Gemini(r) => {
-                    self.provider
-                        .build_gemini_count_tokens(&ctx, &self.config, &self.credential, r)
-                        .await
-                }
+        let mut upstream_req = match &req {
+            CountTokensRequest::Claude(r) => {
+                self.provider
+                    .build_claude_count_tokens(&ctx, &self.config, &self.credential, r)
+                    .await
             }
-            .map_err(|e| format!("{e:?}"))?;
-
-            let resp = self
-                .client
-                .send(upstream_req)
-                .await
-                .map_err(|e| format!("{e:?}"))?;
-            if !(200..300).contains(&resp.status) {
-                return Err(format!("count_tokens upstream status {}", resp.status));
+            CountTokensRequest::OpenAI(r) => {
+                self.provider
+                    .build_openai_input_tokens(&ctx, &self.config, &self.credential, r)
+                    .await
             }
-            let Some(body) = resp_body_bytes(&resp.body) else {
-                return Err("count_tokens empty body".to_string());
-            };
-            decode_count_tokens_response(&req, &body).map_err(|e| e.to_string())
-        })
+            CountTokensRequest::Gemini(r) => {
+                self.provider
+                    .build_gemini_count_tokens(&ctx, &self.config, &self.credential, r)
+                    .await
+            }
+        }
+        .map_err(|e| format!("{e:?}"))?;
+        self.policies
+            .header
+            .apply_request(&self.request_headers, &mut upstream_req.headers);
+
+        let resp = self
+            .client
+            .send(upstream_req)
+            .await
+            .map_err(|e| format!("{e:?}"))?;
+        if !(200..300).contains(&resp.status) {
+            return Err(format!("count_tokens upstream status {}", resp.status));
+        }
+        let Some(body) = resp_body_bytes(&resp.body) else {
+            return Err("count_tokens empty body".to_string());
+        };
+        decode_count_tokens_response(&req, &body).map_err(|e| e.to_string())
     }
 }
 
@@ -2628,6 +4758,31 @@ fn decode_count_tokens_response(
 
 // ---- request/response helpers ----
 
+/// Response header listing, comma-separated, what the protocol translation had to drop
+/// from the request (e.g. `logprobs_unsupported`).
+pub const TRANSFORM_WARNING_HEADER: &str = "x-gproxy-transform-warning";
+
+fn transform_warnings(ctx: &TransformContext, req: &Request) -> Vec<&'static str> {
+    if ctx.src == ctx.dst && ctx.src_op == ctx.dst_op {
+        return Vec::new();
+    }
+    gproxy_transform::middleware::transform_warnings(ctx, req)
+}
+
+fn with_transform_warnings(
+    mut resp: UpstreamHttpResponse,
+    warnings: &[&str],
+) -> UpstreamHttpResponse {
+    if !warnings.is_empty() && resp.status < 400 {
+        header_set(
+            &mut resp.headers,
+            TRANSFORM_WARNING_HEADER,
+            warnings.join(","),
+        );
+    }
+    resp
+}
+
 fn transform_request_maybe(
     ctx: &TransformContext,
     req: Request,
@@ -2721,56 +4876,154 @@ async fn build_upstream_request(
                     .build_openai_responses(ctx, config, credential, r)
                     .await
             }
-            gproxy_provider_core::GenerateContentRequest::Gemini(r) => {
+            gproxy_provider_core::GenerateContentRequest::Gemini(r) => {
+                provider
+                    .build_gemini_generate(ctx, config, credential, r)
+                    .await
+            }
+            gproxy_provider_core::GenerateContentRequest::GeminiStream(r) => {
+                provider
+                    .build_gemini_generate_stream(ctx, config, credential, r)
+                    .await
+            }
+        },
+        Request::BatchGenerateContent(req) => match req {
+            gproxy_provider_core::BatchGenerateContentRequest::Gemini(r) => {
+                provider
+                    .build_gemini_batch_generate(ctx, config, credential, r)
+                    .await
+            }
+        },
+        Request::BatchGet(req) => match req {
+            gproxy_provider_core::BatchGetRequest::Gemini(r) => {
+                provider
+                    .build_gemini_batch_get(ctx, config, credential, r)
+                    .await
+            }
+        },
+        Request::ResponseGet(req) => match req {
+            gproxy_provider_core::ResponseGetRequest::OpenAI(r) => {
+                provider
+                    .build_openai_response_get(ctx, config, credential, r)
+                    .await
+            }
+        },
+        Request::ResponseDelete(req) => match req {
+            gproxy_provider_core::ResponseDeleteRequest::OpenAI(r) => {
+                provider
+                    .build_openai_response_delete(ctx, config, credential, r)
+                    .await
+            }
+        },
+        Request::ResponseCancel(req) => match req {
+            gproxy_provider_core::ResponseCancelRequest::OpenAI(r) => {
+                provider
+                    .build_openai_response_cancel(ctx, config, credential, r)
+                    .await
+            }
+        },
+        Request::ResponseListInputItems(req) => match req {
+            gproxy_provider_core::ResponseListInputItemsRequest::OpenAI(r) => {
+                provider
+                    .build_openai_response_list_input_items(ctx, config, credential, r)
+                    .await
+            }
+        },
+        Request::ResponseCompact(req) => match req {
+            gproxy_provider_core::ResponseCompactRequest::OpenAI(r) => {
+                provider
+                    .build_openai_response_compact(ctx, config, credential, r)
+                    .await
+            }
+        },
+        Request::MemoryTraceSummarize(req) => match req {
+            gproxy_provider_core::MemoryTraceSummarizeRequest::OpenAI(r) => {
+                provider
+                    .build_openai_memory_trace_summarize(ctx, config, credential, r)
+                    .await
+            }
+        },
+        Request::ResponseList(req) => match req {
+            gproxy_provider_core::ResponseListRequest::OpenAI(r) => {
+                provider
+                    .build_openai_response_list(ctx, config, credential, r)
+                    .await
+            }
+        },
+        Request::ConversationCreate(req) => match req {
+            gproxy_provider_core::ConversationCreateRequest::OpenAI(r) => {
+                provider
+                    .build_openai_conversation_create(ctx, config, credential, r)
+                    .await
+            }
+        },
+        Request::ConversationGet(req) => match req {
+            gproxy_provider_core::ConversationGetRequest::OpenAI(r) => {
+                provider
+                    .build_openai_conversation_get(ctx, config, credential, r)
+                    .await
+            }
+        },
+        Request::ConversationUpdate(req) => match req {
+            gproxy_provider_core::ConversationUpdateRequest::OpenAI(r) => {
+                provider
+                    .build_openai_conversation_update(ctx, config, credential, r)
+                    .await
+            }
+        },
+        Request::ConversationDelete(req) => match req {
+            gproxy_provider_core::ConversationDeleteRequest::OpenAI(r) => {
                 provider
-                    .build_gemini_generate(ctx, config, credential, r)
+                    .build_openai_conversation_delete(ctx, config, credential, r)
                     .await
             }
-            gproxy_provider_core::GenerateContentRequest::GeminiStream(r) => {
+        },
+        Request::ConversationItemList(req) => match req {
+            gproxy_provider_core::ConversationItemListRequest::OpenAI(r) => {
                 provider
-                    .build_gemini_generate_stream(ctx, config, credential, r)
+                    .build_openai_conversation_item_list(ctx, config, credential, r)
                     .await
             }
         },
-        Request::ResponseGet(req) => match req {
-            gproxy_provider_core::ResponseGetRequest::OpenAI(r) => {
+        Request::ConversationItemCreate(req) => match req {
+            gproxy_provider_core::ConversationItemCreateRequest::OpenAI(r) => {
                 provider
-                    .build_openai_response_get(ctx, config, credential, r)
+                    .build_openai_conversation_item_create(ctx, config, credential, r)
                     .await
             }
         },
-        Request::ResponseDelete(req) => match req {
-            gproxy_provider_core::ResponseDeleteRequest::OpenAI(r) => {
+        Request::ConversationItemGet(req) => match req {
+            gproxy_provider_core::ConversationItemGetRequest::OpenAI(r) => {
                 provider
-                    .build_openai_response_delete(ctx, config, credential, r)
+                    .build_openai_conversation_item_get(ctx, config, credential, r)
                     .await
             }
         },
-        Request::ResponseCancel(req) => match req {
-            gproxy_provider_core::ResponseCancelRequest::OpenAI(r) => {
+        Request::ConversationItemDelete(req) => match req {
+            gproxy_provider_core::ConversationItemDeleteRequest::OpenAI(r) => {
                 provider
-                    .build_openai_response_cancel(ctx, config, credential, r)
+                    .build_openai_conversation_item_delete(ctx, config, credential, r)
                     .await
             }
         },
-        Request::ResponseListInputItems(req) => match req {
-            gproxy_provider_core::ResponseListInputItemsRequest::OpenAI(r) => {
+        Request::FileList(req) => match req {
+            gproxy_provider_core::FileListRequest::OpenAI(r) => {
                 provider
-                    .build_openai_response_list_input_items(ctx, config, credential, r)
+                    .build_openai_file_list(ctx, config, credential, r)
                     .await
             }
         },
-        Request::ResponseCompact(req) => match req {
-            gproxy_provider_core::ResponseCompactRequest::OpenAI(r) => {
+        Request::FileGet(req) => match req {
+            gproxy_provider_core::FileGetRequest::OpenAI(r) => {
                 provider
-                    .build_openai_response_compact(ctx, config, credential, r)
+                    .build_openai_file_get(ctx, config, credential, r)
                     .await
             }
         },
-        Request::MemoryTraceSummarize(req) => match req {
-            gproxy_provider_core::MemoryTraceSummarizeRequest::OpenAI(r) => {
+        Request::FileDelete(req) => match req {
+            gproxy_provider_core::FileDeleteRequest::OpenAI(r) => {
                 provider
-                    .build_openai_memory_trace_summarize(ctx, config, credential, r)
+                    .build_openai_file_delete(ctx, config, credential, r)
                     .await
             }
         },
@@ -2779,21 +5032,36 @@ async fn build_upstream_request(
 
 fn local_upstream_request(provider: &str, op: Op) -> UpstreamHttpRequest {
     let method = match op {
-        Op::ModelList | Op::ModelGet | Op::ResponseGet | Op::ResponseListInputItems => {
-            HttpMethod::Get
-        }
-        Op::ResponseDelete => HttpMethod::Delete,
+        Op::ModelList
+        | Op::ModelGet
+        | Op::ResponseGet
+        | Op::ResponseListInputItems
+        | Op::ResponseList
+        | Op::ConversationGet
+        | Op::ConversationItemList
+        | Op::ConversationItemGet
+        | Op::FileList
+        | Op::FileGet
+        | Op::BatchGet => HttpMethod::Get,
+        Op::ResponseDelete
+        | Op::ConversationDelete
+        | Op::ConversationItemDelete
+        | Op::FileDelete => HttpMethod::Delete,
         Op::CountTokens
         | Op::GenerateContent
         | Op::StreamGenerateContent
         | Op::ResponseCancel
         | Op::ResponseCompact
-        | Op::MemoryTraceSummarize => HttpMethod::Post,
+        | Op::MemoryTraceSummarize
+        | Op::ConversationCreate
+        | Op::ConversationUpdate
+        | Op::ConversationItemCreate
+        | Op::BatchGenerateContent => HttpMethod::Post,
     };
     UpstreamHttpRequest {
         method,
         url: format!("local://{provider}/{op:?}"),
-        headers: Vec::new(),
+        headers: Headers::new(),
         body: None,
         is_stream: matches!(op, Op::StreamGenerateContent),
     }
@@ -2916,13 +5184,100 @@ fn decode_response(proto: Proto, op: Op, body: &Bytes) -> Result<Response, serde
                 ));
             }
         })),
+        Op::ResponseList => Ok(Response::ResponseList(
+            gproxy_provider_core::ResponseListResponse::OpenAI(serde_json::from_slice(body)?),
+        )),
+        Op::ConversationCreate => Ok(Response::ConversationCreate(
+            gproxy_provider_core::ConversationCreateResponse::OpenAI(serde_json::from_slice(body)?),
+        )),
+        Op::ConversationGet => Ok(Response::ConversationGet(
+            gproxy_provider_core::ConversationGetResponse::OpenAI(serde_json::from_slice(body)?),
+        )),
+        Op::ConversationUpdate => Ok(Response::ConversationUpdate(
+            gproxy_provider_core::ConversationUpdateResponse::OpenAI(serde_json::from_slice(body)?),
+        )),
+        Op::ConversationDelete => Ok(Response::ConversationDelete(
+            gproxy_provider_core::ConversationDeleteResponse::OpenAI(serde_json::from_slice(body)?),
+        )),
+        Op::ConversationItemList => Ok(Response::ConversationItemList(
+            gproxy_provider_core::ConversationItemListResponse::OpenAI(serde_json::from_slice(
+                body,
+            )?),
+        )),
+        Op::ConversationItemCreate => Ok(Response::ConversationItemCreate(
+            gproxy_provider_core::ConversationItemCreateResponse::OpenAI(serde_json::from_slice(
+                body,
+            )?),
+        )),
+        Op::ConversationItemGet => Ok(Response::ConversationItemGet(
+            gproxy_provider_core::ConversationItemGetResponse::OpenAI(serde_json::from_slice(
+                body,
+            )?),
+        )),
+        Op::ConversationItemDelete => Ok(Response::ConversationItemDelete(
+            gproxy_provider_core::ConversationItemDeleteResponse::OpenAI(serde_json::from_slice(
+                body,
+            )?),
+        )),
+        Op::FileList => Ok(Response::FileList(
+            gproxy_provider_core::FileListResponse::OpenAI(serde_json::from_slice(body)?),
+        )),
+        Op::FileGet => Ok(Response::FileGet(
+            gproxy_provider_core::FileGetResponse::OpenAI(serde_json::from_slice(body)?),
+        )),
+        Op::FileDelete => Ok(Response::FileDelete(
+            gproxy_provider_core::FileDeleteResponse::OpenAI(serde_json::from_slice(body)?),
+        )),
+        Op::BatchGenerateContent => Ok(Response::BatchGenerateContent(
+            gproxy_provider_core::BatchGenerateContentResponse::Gemini(serde_json::from_slice(
+                body,
+            )?),
+        )),
+        Op::BatchGet => Ok(Response::BatchGet(
+            gproxy_provider_core::BatchGetResponse::Gemini(serde_json::from_slice(body)?),
+        )),
         Op::StreamGenerateContent => Err(serde_json::Error::io(std::io::Error::other(
             "stream response must be decoded via stream parser",
         ))),
     }
 }
 
-fn encode_response(_proto: Proto, op: Op, resp: &Response) -> Result<Bytes, serde_json::Error> {
+/// Why a response could not be encoded for the client.
+#[derive(Debug)]
+enum EncodeError {
+    /// The response is not of the operation the client called; nothing sensible to send.
+    OpMismatch {
+        op: Op,
+    },
+    Json(serde_json::Error),
+}
+
+impl EncodeError {
+    /// `error_kind` of the upstream event recording the failure.
+    fn event_kind(&self) -> &'static str {
+        match self {
+            EncodeError::OpMismatch { .. } => "encode_mismatch",
+            EncodeError::Json(_) => "encode_response_failed",
+        }
+    }
+}
+
+impl std::fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EncodeError::OpMismatch { op } => write!(f, "response does not match op {op:?}"),
+            EncodeError::Json(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl From<serde_json::Error> for EncodeError {
+    fn from(err: serde_json::Error) -> Self {
+        EncodeError::Json(err)
+    }
+}
+
+fn encode_response(_proto: Proto, op: Op, resp: &Response) -> Result<Bytes, EncodeError> {
     let bytes = match (op, resp) {
         (Op::ModelList, Response::ModelList(r)) => match r {
             ModelListResponse::Claude(v) => serde_json::to_vec(v)?,
@@ -2965,11 +5320,126 @@ fn encode_response(_proto: Proto, op: Op, resp: &Response) -> Result<Bytes, serd
         (Op::MemoryTraceSummarize, Response::MemoryTraceSummarize(r)) => match r {
             gproxy_provider_core::MemoryTraceSummarizeResponse::OpenAI(v) => serde_json::to_vec(v)?,
         },
-        _ => serde_json::to_vec(&serde_json::json!({ "error": "op_mismatch" }))?,
+        (Op::ResponseList, Response::ResponseList(r)) => match r {
+            gproxy_provider_core::ResponseListResponse::OpenAI(v) => serde_json::to_vec(v)?,
+        },
+        (Op::ConversationCreate, Response::ConversationCreate(r)) => match r {
+            gproxy_provider_core::ConversationCreateResponse::OpenAI(v) => serde_json::to_vec(v)?,
+        },
+        (Op::ConversationGet, Response::ConversationGet(r)) => match r {
+            gproxy_provider_core::ConversationGetResponse::OpenAI(v) => serde_json::to_vec(v)?,
+        },
+        (Op::ConversationUpdate, Response::ConversationUpdate(r)) => match r {
+            gproxy_provider_core::ConversationUpdateResponse::OpenAI(v) => serde_json::to_vec(v)?,
+        },
+        (Op::ConversationDelete, Response::ConversationDelete(r)) => match r {
+            gproxy_provider_core::ConversationDeleteResponse::OpenAI(v) => serde_json::to_vec(v)?,
+        },
+        (Op::ConversationItemList, Response::ConversationItemList(r)) => match r {
+            gproxy_provider_core::ConversationItemListResponse::OpenAI(v) => serde_json::to_vec(v)?,
+        },
+        (Op::ConversationItemCreate, Response::ConversationItemCreate(r)) => match r {
+            gproxy_provider_core::ConversationItemCreateResponse::OpenAI(v) => {
+                serde_json::to_vec(v)?
+            }
+        },
+        (Op::ConversationItemGet, Response::ConversationItemGet(r)) => match r {
+            gproxy_provider_core::ConversationItemGetResponse::OpenAI(v) => serde_json::to_vec(v)?,
+        },
+        (Op::ConversationItemDelete, Response::ConversationItemDelete(r)) => match r {
+            gproxy_provider_core::ConversationItemDeleteResponse::OpenAI(v) => {
+                serde_json::to_vec(v)?
+            }
+        },
+        (Op::FileList, Response::FileList(r)) => match r {
+            gproxy_provider_core::FileListResponse::OpenAI(v) => serde_json::to_vec(v)?,
+        },
+        (Op::FileGet, Response::FileGet(r)) => match r {
+            gproxy_provider_core::FileGetResponse::OpenAI(v) => serde_json::to_vec(v)?,
+        },
+        (Op::FileDelete, Response::FileDelete(r)) => match r {
+            gproxy_provider_core::FileDeleteResponse::OpenAI(v) => serde_json::to_vec(v)?,
+        },
+        (Op::BatchGenerateContent, Response::BatchGenerateContent(r)) => match r {
+            gproxy_provider_core::BatchGenerateContentResponse::Gemini(v) => serde_json::to_vec(v)?,
+        },
+        (Op::BatchGet, Response::BatchGet(r)) => match r {
+            gproxy_provider_core::BatchGetResponse::Gemini(v) => serde_json::to_vec(v)?,
+        },
+        _ => return Err(EncodeError::OpMismatch { op }),
     };
     Ok(Bytes::from(bytes))
 }
 
+/// Upstream resource (response, conversation, file or batch id) a request operates on.
+fn referenced_resource_id(req: &Request) -> Option<&str> {
+    use gproxy_protocol::openai::create_response::types::ConversationParam;
+    use gproxy_provider_core::{
+        BatchGetRequest, ConversationDeleteRequest, ConversationGetRequest,
+        ConversationItemCreateRequest, ConversationItemDeleteRequest, ConversationItemGetRequest,
+        ConversationItemListRequest, ConversationUpdateRequest, FileDeleteRequest, FileGetRequest,
+        ResponseCancelRequest, ResponseDeleteRequest, ResponseGetRequest,
+        ResponseListInputItemsRequest,
+    };
+
+    let id = match req {
+        Request::GenerateContent(GenerateContentRequest::OpenAIResponse(r)) => {
+            match (&r.body.previous_response_id, &r.body.conversation) {
+                (Some(id), _) => id,
+                (None, Some(ConversationParam::Id(id))) => id,
+                (None, Some(ConversationParam::Ref(conversation))) => &conversation.id,
+                (None, None) => return None,
+            }
+        }
+        Request::ResponseGet(ResponseGetRequest::OpenAI(r)) => &r.path.response_id,
+        Request::ResponseDelete(ResponseDeleteRequest::OpenAI(r)) => &r.path.response_id,
+        Request::ResponseCancel(ResponseCancelRequest::OpenAI(r)) => &r.path.response_id,
+        Request::ResponseListInputItems(ResponseListInputItemsRequest::OpenAI(r)) => {
+            &r.path.response_id
+        }
+        Request::ConversationGet(ConversationGetRequest::OpenAI(r)) => &r.path.conversation_id,
+        Request::ConversationUpdate(ConversationUpdateRequest::OpenAI(r)) => {
+            &r.path.conversation_id
+        }
+        Request::ConversationDelete(ConversationDeleteRequest::OpenAI(r)) => {
+            &r.path.conversation_id
+        }
+        Request::ConversationItemList(ConversationItemListRequest::OpenAI(r)) => {
+            &r.path.conversation_id
+        }
+        Request::ConversationItemCreate(ConversationItemCreateRequest::OpenAI(r)) => {
+            &r.path.conversation_id
+        }
+        Request::ConversationItemGet(ConversationItemGetRequest::OpenAI(r)) => {
+            &r.path.conversation_id
+        }
+        Request::ConversationItemDelete(ConversationItemDeleteRequest::OpenAI(r)) => {
+            &r.path.conversation_id
+        }
+        Request::FileGet(FileGetRequest::OpenAI(r)) => &r.path.file_id,
+        Request::FileDelete(FileDeleteRequest::OpenAI(r)) => &r.path.file_id,
+        Request::BatchGet(BatchGetRequest::Gemini(r)) => &r.path.name,
+        _ => return None,
+    };
+    Some(id.as_str())
+}
+
+/// Upstream resource a successful response created.
+fn created_resource_id(resp: &Response) -> Option<&str> {
+    match resp {
+        Response::GenerateContent(GenerateContentResponse::OpenAIResponse(v)) => {
+            Some(v.id.as_str())
+        }
+        Response::ConversationCreate(gproxy_provider_core::ConversationCreateResponse::OpenAI(
+            v,
+        )) => Some(v.id.as_str()),
+        Response::BatchGenerateContent(
+            gproxy_provider_core::BatchGenerateContentResponse::Gemini(v),
+        ) => Some(v.name.as_str()),
+        _ => None,
+    }
+}
+
 fn extract_generate_request(req: &Request) -> Option<GenerateContentRequest> {
     match req {
         Request::GenerateContent(GenerateContentRequest::Claude(r)) => {
@@ -2991,6 +5461,31 @@ fn extract_generate_request(req: &Request) -> Option<GenerateContentRequest> {
     }
 }
 
+/// Model and usage of each answered request in a finished inline Gemini batch.
+fn batch_inner_usage(
+    batch: &gproxy_protocol::gemini::batch_generate_content::types::BatchOperation,
+) -> Vec<(Option<String>, UsageSummary)> {
+    batch
+        .inlined_responses()
+        .iter()
+        .filter_map(|inner| inner.response.as_ref())
+        .filter_map(|resp| {
+            let model = resp.model_version.as_deref().map(|version| {
+                if version.starts_with("models/") {
+                    version.to_string()
+                } else {
+                    format!("models/{version}")
+                }
+            });
+            let usage = usage_from_response(
+                Proto::Gemini,
+                &GenerateContentResponse::Gemini(resp.clone()),
+            )?;
+            Some((model, usage))
+        })
+        .collect()
+}
+
 fn resp_native_generate_usage(proto: Proto, resp: &Response) -> Option<UsageSummary> {
     match resp {
         Response::GenerateContent(r) => usage_from_response(proto, r),
@@ -3015,19 +5510,6 @@ fn resp_body_bytes(body: &UpstreamBody) -> Option<Bytes> {
     }
 }
 
-fn needs_internal_unwrap(provider: &str, proto: Proto, op: Op) -> bool {
-    proto == Proto::Gemini
-        && op == Op::GenerateContent
-        && (provider.eq_ignore_ascii_case("geminicli")
-            || provider.eq_ignore_ascii_case("antigravity"))
-}
-
-fn needs_internal_stream_unwrap(provider: &str, proto: Proto) -> bool {
-    proto == Proto::Gemini
-        && (provider.eq_ignore_ascii_case("geminicli")
-            || provider.eq_ignore_ascii_case("antigravity"))
-}
-
 fn should_passthrough_native_gemini_stream(
     req_native: &Request,
     upstream_headers: &Headers,
@@ -3068,21 +5550,83 @@ fn upstream_stream_is_sse(headers: &Headers) -> bool {
         .unwrap_or(false)
 }
 
-fn unwrap_internal_json_bytes(provider: &str, body: &Bytes) -> Result<Bytes, String> {
-    let value: JsonValue =
-        serde_json::from_slice(body).map_err(|err| format!("json_decode_failed: {err}"))?;
-    let mut value = unwrap_internal_value(value);
-    if provider.eq_ignore_ascii_case("antigravity") {
-        normalize_gemini_parts(&mut value);
+/// Reconnect to an interrupted upstream stream using a provider resume request.
+#[allow(clippy::too_many_arguments)]
+async fn resume_upstream_stream(
+    client: &dyn UpstreamClient,
+    provider: &dyn UpstreamProvider,
+    ctx: &UpstreamCtx,
+    config: &ProviderConfig,
+    credential: &Credential,
+    req: &Request,
+    header_policy: &HeaderPolicy,
+    downstream: &Headers,
+) -> Option<ByteStream> {
+    let mut upstream_req = build_upstream_request(provider, ctx, config, credential, req)
+        .await
+        .ok()?;
+    header_policy.apply_request(downstream, &mut upstream_req.headers);
+    upstream_req.is_stream = true;
+    let sep = if upstream_req.url.contains('?') {
+        '&'
+    } else {
+        '?'
+    };
+    upstream_req.url = format!("{}{sep}stream=true", upstream_req.url);
+    header_set(&mut upstream_req.headers, "accept", "text/event-stream");
+    provider
+        .finalize_request(ctx, config, credential, &mut upstream_req)
+        .await
+        .ok()?;
+    match client.send(upstream_req).await {
+        Ok(UpstreamHttpResponse {
+            status,
+            body: UpstreamBody::Stream(stream),
+            ..
+        }) if (200..300).contains(&status) => Some(stream),
+        _ => None,
+    }
+}
+
+/// Error message recorded when the stream watchdog fires.
+const STREAM_IDLE_TIMEOUT: &str = "stream_idle_timeout";
+/// Head start of the stream watchdog over the upstream client's own idle cutoff, so a
+/// stalled stream is reported as such instead of looking like a clean close.
+const STREAM_WATCHDOG_GRACE: Duration = Duration::from_secs(1);
+
+/// Watchdog window for a stream operation: the provider's `idle_ms`, else the global
+/// default. The returned timeouts move the client's idle cutoff just past that window.
+fn with_stream_watchdog(
+    mut timeouts: UpstreamTimeouts,
+    op: Op,
+    default_idle_ms: u64,
+) -> (UpstreamTimeouts, Option<Duration>) {
+    if op != Op::StreamGenerateContent {
+        return (timeouts, None);
+    }
+    let idle = timeouts
+        .idle()
+        .or_else(|| Some(Duration::from_millis(default_idle_ms)).filter(|idle| !idle.is_zero()));
+    if let Some(idle) = idle {
+        let client_idle = (idle + STREAM_WATCHDOG_GRACE).as_millis();
+        timeouts.idle_ms = Some(u64::try_from(client_idle).unwrap_or(u64::MAX));
+    }
+    (timeouts, idle)
+}
+
+/// Next upstream chunk, or `Err` once `idle` passes without one.
+async fn recv_watched(
+    rx: &mut ByteStream,
+    idle: Option<Duration>,
+) -> Result<Option<Bytes>, tokio::time::error::Elapsed> {
+    match idle {
+        Some(idle) => tokio::time::timeout(idle, rx.recv()).await,
+        None => Ok(rx.recv().await),
     }
-    serde_json::to_vec(&value)
-        .map(Bytes::from)
-        .map_err(|err| format!("json_encode_failed: {err}"))
 }
 
-fn map_internal_gemini_stream(provider: &str, mut rx_in: ByteStream) -> ByteStream {
+fn map_internal_stream(unwrap: InternalEventUnwrap, mut rx_in: ByteStream) -> ByteStream {
     let (tx, rx) = tokio::sync::mpsc::channel::<Bytes>(32);
-    let provider = provider.to_string();
     tokio::spawn(async move {
         let mut parser = SseParser::new();
         let mut pending: VecDeque<Bytes> = VecDeque::new();
@@ -3096,14 +5640,14 @@ fn map_internal_gemini_stream(provider: &str, mut rx_in: ByteStream) -> ByteStre
             match rx_in.recv().await {
                 Some(chunk) => {
                     for ev in parser.push_bytes(&chunk) {
-                        for mapped in map_internal_event_data(&provider, &ev.data) {
+                        for mapped in unwrap(&ev.data) {
                             pending.push_back(mapped);
                         }
                     }
                 }
                 None => {
                     for ev in parser.finish() {
-                        for mapped in map_internal_event_data(&provider, &ev.data) {
+                        for mapped in unwrap(&ev.data) {
                             pending.push_back(mapped);
                         }
                     }
@@ -3120,93 +5664,6 @@ fn map_internal_gemini_stream(provider: &str, mut rx_in: ByteStream) -> ByteStre
     rx
 }
 
-fn map_internal_event_data(provider: &str, data: &str) -> Vec<Bytes> {
-    if data == "[DONE]" {
-        return vec![Bytes::from_static(b"data: [DONE]\n\n")];
-    }
-    let value: JsonValue = match serde_json::from_str(data) {
-        Ok(value) => value,
-        Err(_) => {
-            let mut raw = Vec::with_capacity(data.len() + 8);
-            raw.extend_from_slice(b"data: ");
-            raw.extend_from_slice(data.as_bytes());
-            raw.extend_from_slice(b"\n\n");
-            return vec![Bytes::from(raw)];
-        }
-    };
-    let mut out = Vec::new();
-    let mut value = unwrap_internal_value(value);
-    if provider.eq_ignore_ascii_case("antigravity") {
-        normalize_gemini_parts(&mut value);
-    }
-    match value {
-        JsonValue::Array(items) => {
-            for item in items {
-                let mut item = unwrap_internal_value(item);
-                if provider.eq_ignore_ascii_case("antigravity") {
-                    normalize_gemini_parts(&mut item);
-                }
-                if let Some(bytes) = sse_json_bytes(&item) {
-                    out.push(bytes);
-                }
-            }
-        }
-        other => {
-            if let Some(bytes) = sse_json_bytes(&other) {
-                out.push(bytes);
-            }
-        }
-    }
-    out
-}
-
-fn unwrap_internal_value(value: JsonValue) -> JsonValue {
-    match value {
-        JsonValue::Object(mut map) => match map.remove("response") {
-            Some(JsonValue::Object(mut inner)) => match inner.remove("response") {
-                Some(nested) => nested,
-                None => JsonValue::Object(inner),
-            },
-            Some(inner) => inner,
-            None => JsonValue::Object(map),
-        },
-        other => other,
-    }
-}
-
-fn normalize_gemini_parts(value: &mut JsonValue) {
-    match value {
-        JsonValue::Object(map) => {
-            if let Some(JsonValue::Array(candidates)) = map.get_mut("candidates") {
-                for candidate in candidates {
-                    if let JsonValue::Object(candidate) = candidate
-                        && let Some(JsonValue::Object(content)) = candidate.get_mut("content")
-                    {
-                        content
-                            .entry("parts")
-                            .or_insert_with(|| JsonValue::Array(Vec::new()));
-                    }
-                }
-            }
-        }
-        JsonValue::Array(items) => {
-            for item in items {
-                normalize_gemini_parts(item);
-            }
-        }
-        _ => {}
-    }
-}
-
-fn sse_json_bytes(value: &JsonValue) -> Option<Bytes> {
-    let payload = serde_json::to_vec(value).ok()?;
-    let mut data = Vec::with_capacity(payload.len() + 8);
-    data.extend_from_slice(b"data: ");
-    data.extend_from_slice(&payload);
-    data.extend_from_slice(b"\n\n");
-    Some(Bytes::from(data))
-}
-
 fn is_generate_op(op: Op) -> bool {
     matches!(op, Op::GenerateContent | Op::StreamGenerateContent)
 }
@@ -3220,10 +5677,26 @@ fn extract_model_from_request(req: &Request) -> Option<String> {
             GenerateContentRequest::Gemini(req) => Some(req.path.model.clone()),
             GenerateContentRequest::GeminiStream(req) => Some(req.path.model.clone()),
         },
+        Request::BatchGenerateContent(
+            gproxy_provider_core::BatchGenerateContentRequest::Gemini(req),
+        ) => Some(req.path.model.clone()),
         _ => None,
     }
 }
 
+/// Top-level body fields of a generate request that its typed struct doesn't model.
+fn unknown_request_fields(req: &Request) -> Vec<&str> {
+    let extra = match req {
+        Request::GenerateContent(GenerateContentRequest::Claude(req)) => &req.body.extra,
+        Request::GenerateContent(GenerateContentRequest::OpenAIChat(req)) => &req.body.extra,
+        Request::GenerateContent(GenerateContentRequest::OpenAIResponse(req)) => &req.body.extra,
+        Request::GenerateContent(GenerateContentRequest::Gemini(req)) => &req.body.extra,
+        Request::GenerateContent(GenerateContentRequest::GeminiStream(req)) => &req.body.extra,
+        _ => return Vec::new(),
+    };
+    extra.keys().map(String::as_str).collect()
+}
+
 fn claude_model_to_string(model: &ClaudeModel) -> String {
     match model {
         ClaudeModel::Custom(s) => s.clone(),
@@ -3234,52 +5707,60 @@ fn claude_model_to_string(model: &ClaudeModel) -> String {
     }
 }
 
-fn maybe_prefix_model_in_response(
-    mut resp: Response,
-    response_model_prefix_provider: Option<&str>,
-) -> Response {
-    let Some(provider) = response_model_prefix_provider else {
+fn maybe_prefix_model_in_response(mut resp: Response, rewrite: &ModelRewrite) -> Response {
+    if rewrite.is_none() {
         return resp;
-    };
+    }
 
     match &mut resp {
-        Response::ModelList(r) => match r {
-            ModelListResponse::Claude(v) => {
-                for item in &mut v.data {
-                    item.id = prefix_model_string(&item.id, provider);
+        // Model listings only carry the provider prefix; profiles are not listed.
+        Response::ModelList(r) => {
+            let Some(provider) = rewrite.prefix_provider.as_deref() else {
+                return resp;
+            };
+            match r {
+                ModelListResponse::Claude(v) => {
+                    for item in &mut v.data {
+                        item.id = prefix_model_string(&item.id, provider);
+                    }
                 }
-            }
-            ModelListResponse::OpenAI(v) => {
-                for item in &mut v.data {
-                    item.id = prefix_model_string(&item.id, provider);
+                ModelListResponse::OpenAI(v) => {
+                    for item in &mut v.data {
+                        item.id = prefix_model_string(&item.id, provider);
+                    }
                 }
-            }
-            ModelListResponse::Gemini(v) => {
-                for item in &mut v.models {
-                    item.name = prefix_gemini_model_name(&item.name, provider);
+                ModelListResponse::Gemini(v) => {
+                    for item in &mut v.models {
+                        item.name = prefix_gemini_model_name(&item.name, provider);
+                    }
                 }
             }
-        },
-        Response::ModelGet(r) => match r {
-            ModelGetResponse::Claude(v) => {
-                v.id = prefix_model_string(&v.id, provider);
-            }
-            ModelGetResponse::OpenAI(v) => {
-                v.id = prefix_model_string(&v.id, provider);
-            }
-            ModelGetResponse::Gemini(v) => {
-                v.name = prefix_gemini_model_name(&v.name, provider);
+        }
+        Response::ModelGet(r) => {
+            let Some(provider) = rewrite.prefix_provider.as_deref() else {
+                return resp;
+            };
+            match r {
+                ModelGetResponse::Claude(v) => {
+                    v.id = prefix_model_string(&v.id, provider);
+                }
+                ModelGetResponse::OpenAI(v) => {
+                    v.id = prefix_model_string(&v.id, provider);
+                }
+                ModelGetResponse::Gemini(v) => {
+                    v.name = prefix_gemini_model_name(&v.name, provider);
+                }
             }
-        },
+        }
         Response::GenerateContent(r) => match r {
             GenerateContentResponse::Claude(v) => {
-                v.model = maybe_prefix_claude_model(v.model.clone(), provider);
+                v.model = maybe_prefix_claude_model(v.model.clone(), rewrite);
             }
             GenerateContentResponse::OpenAIChat(v) => {
-                v.model = prefix_model_string(&v.model, provider);
+                v.model = rewrite.generated_model(&v.model);
             }
             GenerateContentResponse::OpenAIResponse(v) => {
-                v.model = prefix_model_string(&v.model, provider);
+                v.model = rewrite.generated_model(&v.model);
             }
             GenerateContentResponse::Gemini(_) => {}
         },
@@ -3289,29 +5770,40 @@ fn maybe_prefix_model_in_response(
         | Response::ResponseCancel(_)
         | Response::ResponseListInputItems(_)
         | Response::ResponseCompact(_)
-        | Response::MemoryTraceSummarize(_) => {}
+        | Response::MemoryTraceSummarize(_)
+        | Response::ResponseList(_)
+        | Response::ConversationCreate(_)
+        | Response::ConversationGet(_)
+        | Response::ConversationUpdate(_)
+        | Response::ConversationDelete(_)
+        | Response::ConversationItemList(_)
+        | Response::ConversationItemCreate(_)
+        | Response::ConversationItemGet(_)
+        | Response::ConversationItemDelete(_)
+        | Response::FileList(_)
+        | Response::FileGet(_)
+        | Response::FileDelete(_)
+        | Response::BatchGenerateContent(_)
+        | Response::BatchGet(_) => {}
     }
 
     resp
 }
 
-fn maybe_prefix_model_in_stream_event(
-    mut ev: StreamEvent,
-    response_model_prefix_provider: Option<&str>,
-) -> StreamEvent {
-    let Some(provider) = response_model_prefix_provider else {
+fn maybe_prefix_model_in_stream_event(mut ev: StreamEvent, rewrite: &ModelRewrite) -> StreamEvent {
+    if rewrite.is_none() {
         return ev;
-    };
+    }
 
     match &mut ev {
         StreamEvent::Claude(v) => {
-            *v = maybe_prefix_claude_stream_event(v.clone(), provider);
+            *v = maybe_prefix_claude_stream_event(v.clone(), rewrite);
         }
         StreamEvent::OpenAIChat(v) => {
-            v.model = prefix_model_string(&v.model, provider);
+            v.model = rewrite.generated_model(&v.model);
         }
         StreamEvent::OpenAIResponse(v) => {
-            *v = maybe_prefix_openai_response_stream_event(v.clone(), provider);
+            *v = maybe_prefix_openai_response_stream_event(v.clone(), rewrite);
         }
         StreamEvent::Gemini(_) => {}
     }
@@ -3321,7 +5813,7 @@ fn maybe_prefix_model_in_stream_event(
 
 fn maybe_prefix_claude_stream_event(
     mut ev: gproxy_protocol::claude::create_message::stream::BetaStreamEvent,
-    provider: &str,
+    rewrite: &ModelRewrite,
 ) -> gproxy_protocol::claude::create_message::stream::BetaStreamEvent {
     if let gproxy_protocol::claude::create_message::stream::BetaStreamEvent::Known(
         gproxy_protocol::claude::create_message::stream::BetaStreamEventKnown::MessageStart {
@@ -3329,14 +5821,14 @@ fn maybe_prefix_claude_stream_event(
         },
     ) = &mut ev
     {
-        message.model = maybe_prefix_claude_model(message.model.clone(), provider);
+        message.model = maybe_prefix_claude_model(message.model.clone(), rewrite);
     }
     ev
 }
 
 fn maybe_prefix_openai_response_stream_event(
     ev: gproxy_protocol::openai::create_response::stream::ResponseStreamEvent,
-    provider: &str,
+    rewrite: &ModelRewrite,
 ) -> gproxy_protocol::openai::create_response::stream::ResponseStreamEvent {
     let mut value = match serde_json::to_value(&ev) {
         Ok(v) => v,
@@ -3346,18 +5838,96 @@ fn maybe_prefix_openai_response_stream_event(
         && let Some(JsonValue::Object(response)) = map.get_mut("response")
         && let Some(JsonValue::String(model)) = response.get_mut("model")
     {
-        *model = prefix_model_string(model, provider);
+        *model = rewrite.generated_model(model);
     }
 
     serde_json::from_value(value).unwrap_or(ev)
 }
 
-fn maybe_prefix_claude_model(model: ClaudeModel, provider: &str) -> ClaudeModel {
+/// Applies [`maybe_prefix_model_in_response`]'s rewrite to a raw response body, replacing
+/// only the model values so every other byte stays as the upstream sent it. `None` when
+/// the body is not a JSON object, so the caller falls back to re-encoding.
+fn rewrite_models_in_json(
+    body: &Bytes,
+    proto: Proto,
+    op: Op,
+    rewrite: &ModelRewrite,
+) -> Option<Bytes> {
+    if rewrite.is_none() {
+        return Some(body.clone());
+    }
+    serde_json::from_slice::<serde::de::IgnoredAny>(body).ok()?;
+    let members = raw_json::object_members(body, raw_json::skip_ws(body, 0))?;
+    let model_key = if proto == Proto::Gemini { "name" } else { "id" };
+    let mut edits = Vec::new();
+    let mut rewrite_string = |range: &std::ops::Range<usize>,
+                              map: &dyn Fn(&str) -> Option<String>| {
+        if let Ok(value) = serde_json::from_slice::<String>(&body[range.clone()])
+            && let Some(value) = map(&value).filter(|mapped| *mapped != value)
+        {
+            edits.push((
+                range.clone(),
+                serde_json::to_vec(&value).unwrap_or_default(),
+            ));
+        }
+    };
+    let prefix = |name: &str, provider: &str| {
+        Some(if proto == Proto::Gemini {
+            prefix_gemini_model_name(name, provider)
+        } else {
+            prefix_model_string(name, provider)
+        })
+    };
+    let member = |members: &[(String, std::ops::Range<usize>)], key: &str| {
+        members
+            .iter()
+            .filter(|(name, _)| name == key)
+            .map(|(_, range)| range.clone())
+            .collect::<Vec<_>>()
+    };
+    match op {
+        Op::ModelList => {
+            if let Some(provider) = rewrite.prefix_provider.as_deref() {
+                let list_key = if proto == Proto::Gemini {
+                    "models"
+                } else {
+                    "data"
+                };
+                for list in member(&members, list_key) {
+                    for item in raw_json::array_elements(body, list.start).unwrap_or_default() {
+                        let fields = raw_json::object_members(body, item.start).unwrap_or_default();
+                        for range in member(&fields, model_key) {
+                            rewrite_string(&range, &|name| prefix(name, provider));
+                        }
+                    }
+                }
+            }
+        }
+        Op::ModelGet => {
+            if let Some(provider) = rewrite.prefix_provider.as_deref() {
+                for range in member(&members, model_key) {
+                    rewrite_string(&range, &|name| prefix(name, provider));
+                }
+            }
+        }
+        Op::GenerateContent if proto != Proto::Gemini => {
+            for range in member(&members, "model") {
+                rewrite_string(&range, &|model: &str| {
+                    (!model.is_empty()).then(|| rewrite.generated_model(model))
+                });
+            }
+        }
+        _ => {}
+    }
+    Some(raw_json::splice(body, edits))
+}
+
+fn maybe_prefix_claude_model(model: ClaudeModel, rewrite: &ModelRewrite) -> ClaudeModel {
     let model_name = claude_model_to_string(&model);
     if model_name.is_empty() {
         return model;
     }
-    ClaudeModel::Custom(prefix_model_string(&model_name, provider))
+    ClaudeModel::Custom(rewrite.generated_model(&model_name))
 }
 
 fn prefix_model_string(model: &str, provider: &str) -> String {
@@ -3383,23 +5953,39 @@ fn json_error(status: u16, code: &str) -> UpstreamHttpResponse {
     json_error_with(status, code, serde_json::Value::Null)
 }
 
+/// The response refusing a call whose moderation `verdict` trips `policy`, if it does.
+fn moderation_refusal(
+    policy: &ModerationPolicy,
+    verdict: &Verdict,
+) -> Option<UpstreamHttpResponse> {
+    let mut resp = if verdict.tripped() && policy.action == ModerationAction::Block {
+        json_error_with(400, "content_moderated", verdict.categories.join(","))
+    } else if verdict.failed && policy.fail_closed {
+        json_error(503, "moderation_unavailable")
+    } else {
+        return None;
+    };
+    header_set(
+        &mut resp.headers,
+        MODERATION_HEADER,
+        verdict.header_value(policy.action),
+    );
+    Some(resp)
+}
+
+/// Whether the user key's scope, if it has one, includes `provider`.
+fn key_scope_allows_provider(auth: &ProxyAuth, provider: &str) -> bool {
+    auth.key_scope
+        .as_ref()
+        .is_none_or(|scope| scope.allows_provider(provider))
+}
+
 fn json_error_with(
     status: u16,
     code: &str,
     detail: impl Into<serde_json::Value>,
 ) -> UpstreamHttpResponse {
-    let mut headers: Headers = Vec::new();
-    header_set(&mut headers, "content-type", "application/json");
-    let body = serde_json::json!({
-        "error": code,
-        "detail": detail.into(),
-    });
-    let bytes = Bytes::from(serde_json::to_vec(&body).unwrap_or_default());
-    UpstreamHttpResponse {
-        status,
-        headers,
-        body: UpstreamBody::Bytes(bytes),
-    }
+    EngineError::new(status, code, detail.into()).into_response(None)
 }
 
 fn error_response_from_provider_err(err: &ProviderError) -> UpstreamHttpResponse {
@@ -3414,8 +6000,17 @@ fn error_response_from_provider_err(err: &ProviderError) -> UpstreamHttpResponse
 
 fn failure_to_http(failure: UpstreamFailure) -> UpstreamHttpResponse {
     match failure {
-        UpstreamFailure::Transport { kind: _, message } => {
-            json_error_with(502, "upstream_transport_error", message)
+        UpstreamFailure::Transport { kind, message } => {
+            use gproxy_provider_core::provider::UpstreamTransportErrorKind as Kind;
+            match kind {
+                Kind::Timeout => json_error_with(504, "upstream_timeout", message),
+                Kind::ReadTimeout => json_error_with(504, "upstream_idle_timeout", message),
+                Kind::ConnectTimeout => json_error_with(504, "upstream_connect_timeout", message),
+                Kind::FirstByteTimeout => {
+                    json_error_with(504, "upstream_first_byte_timeout", message)
+                }
+                _ => json_error_with(502, "upstream_transport_error", message),
+            }
         }
         UpstreamFailure::Http {
             status,
@@ -3494,7 +6089,7 @@ fn internal_request(path: String) -> UpstreamHttpRequest {
     UpstreamHttpRequest {
         method: HttpMethod::Get,
         url: path,
-        headers: Vec::new(),
+        headers: Headers::new(),
         body: None,
         is_stream: false,
     }
@@ -3509,19 +6104,44 @@ fn transport_kind_from_failure(
     }
 }
 
-fn is_auth_failure(failure: &UpstreamFailure) -> bool {
+/// The provider's `failure_rules` entry matching an HTTP error response; it replaces the
+/// built-in classification below and the provider's own cooldown decision.
+fn failure_rule(runtime: &ProviderRuntime, failure: &UpstreamFailure) -> Option<FailureMatch> {
+    let UpstreamFailure::Http { status, body, .. } = failure else {
+        return None;
+    };
+    runtime.policies().failure.matching(*status, body)
+}
+
+fn decide_unavailable(
+    runtime: &ProviderRuntime,
+    failure: &UpstreamFailure,
+    provider_decision: impl FnOnce() -> Option<UnavailableDecision>,
+) -> Option<UnavailableDecision> {
+    match failure_rule(runtime, failure) {
+        Some(rule) => decide_unavailable_with_failure_rule(rule, failure),
+        None => provider_decision(),
+    }
+}
+
+fn is_auth_failure(runtime: &ProviderRuntime, failure: &UpstreamFailure) -> bool {
     matches!(
         failure,
         UpstreamFailure::Http { status, .. } if *status == 401 || *status == 403
-    )
+    ) && failure_rule(runtime, failure).is_none()
 }
 
-fn is_retryable_failure(failure: &UpstreamFailure) -> bool {
+fn is_retryable_failure(runtime: &ProviderRuntime, failure: &UpstreamFailure) -> bool {
+    if let Some(rule) = failure_rule(runtime, failure) {
+        return rule.action != FailureAction::PassThrough;
+    }
     match failure {
         UpstreamFailure::Transport { kind, .. } => matches!(
             kind,
             gproxy_provider_core::provider::UpstreamTransportErrorKind::Timeout
                 | gproxy_provider_core::provider::UpstreamTransportErrorKind::ReadTimeout
+                | gproxy_provider_core::provider::UpstreamTransportErrorKind::ConnectTimeout
+                | gproxy_provider_core::provider::UpstreamTransportErrorKind::FirstByteTimeout
                 | gproxy_provider_core::provider::UpstreamTransportErrorKind::Connect
                 | gproxy_provider_core::provider::UpstreamTransportErrorKind::Dns
                 | gproxy_provider_core::provider::UpstreamTransportErrorKind::Tls
@@ -3546,3 +6166,248 @@ async fn backoff_sleep(attempt_no: u32) {
         tokio::time::sleep(delay).await;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use gproxy_common::GlobalConfigPatch;
+    use gproxy_protocol::openai::create_chat_completions::request::CreateChatCompletionRequest;
+    use gproxy_provider_core::EventHub;
+    use gproxy_storage::{
+        CredentialRow, MemoryStorage, ProviderRow, Storage, StorageSnapshot, UserKeyRow, UserRow,
+    };
+
+    use super::*;
+
+    /// Answers the one upstream call with an SSE stream the test feeds.
+    struct StreamUpstream(Mutex<Option<ByteStream>>);
+
+    impl UpstreamClient for StreamUpstream {
+        fn send<'a>(
+            &'a self,
+            _req: UpstreamHttpRequest,
+        ) -> std::pin::Pin<
+            Box<dyn Future<Output = Result<UpstreamHttpResponse, UpstreamFailure>> + Send + 'a>,
+        > {
+            let rx = self
+                .0
+                .lock()
+                .unwrap()
+                .take()
+                .expect("a single upstream call");
+            Box::pin(std::future::ready(Ok(UpstreamHttpResponse {
+                status: 200,
+                headers: vec![("content-type".to_string(), "text/event-stream".to_string())].into(),
+                body: UpstreamBody::Stream(rx),
+            })))
+        }
+    }
+
+    fn snapshot() -> StorageSnapshot {
+        let now = OffsetDateTime::now_utc();
+        StorageSnapshot {
+            global_config: None,
+            providers: vec![ProviderRow {
+                id: 1,
+                name: "openai".to_string(),
+                config_json: serde_json::json!({ "kind": "openai", "channel_settings": {} }),
+                enabled: true,
+                updated_at: now,
+            }],
+            credentials: vec![CredentialRow {
+                id: 1,
+                provider_id: 1,
+                name: None,
+                settings_json: serde_json::json!({}),
+                secret_json: serde_json::json!({ "OpenAI": { "api_key": "sk-upstream" } }),
+                enabled: true,
+                created_at: now,
+                updated_at: now,
+            }],
+            organizations: Vec::new(),
+            org_grants: Vec::new(),
+            users: vec![UserRow {
+                id: 1,
+                name: "alice".to_string(),
+                enabled: true,
+                org_id: None,
+                created_at: now,
+                updated_at: now,
+            }],
+            user_keys: vec![UserKeyRow {
+                id: 1,
+                user_id: 1,
+                api_key: "sk-1".to_string(),
+                label: None,
+                enabled: true,
+                rpm_limit: None,
+                tpm_limit: None,
+                stream_tps_limit: None,
+                max_output_tokens: None,
+                omit_bodies: false,
+                default_provider: None,
+                default_model: None,
+                mcp_policy: None,
+                moderation_policy: None,
+                prelude_template: None,
+                key_scope: None,
+                expires_at: None,
+                allowed_origins: None,
+                body_sample_percent: None,
+                created_at: now,
+                updated_at: now,
+            }],
+            model_profiles: Vec::new(),
+            experiments: Vec::new(),
+            secrets: Vec::new(),
+            log_views: Vec::new(),
+        }
+    }
+
+    fn chunk(text: &str) -> Bytes {
+        let event = serde_json::json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion.chunk",
+            "created": 1,
+            "model": "gpt-4o",
+            "choices": [{ "index": 0, "delta": { "content": text }, "finish_reason": null }],
+        });
+        Bytes::from(format!("data: {event}\n\n"))
+    }
+
+    #[test]
+    fn model_rewrite_touches_only_model_values() {
+        let rewrite = ModelRewrite {
+            prefix_provider: Some("openai".to_string()),
+            alias: None,
+        };
+        let rewritten = |body: &'static [u8], proto, op| {
+            let out = rewrite_models_in_json(&Bytes::from_static(body), proto, op, &rewrite);
+            String::from_utf8(out.unwrap().to_vec()).unwrap()
+        };
+
+        // Unknown fields keep their order, spacing and number formatting.
+        assert_eq!(
+            rewritten(
+                br#"{"zeta":1.50, "model" : "gpt-4o","x_new":{"model":"inner","n":2e3},"id":"c1"}"#,
+                Proto::OpenAIChat,
+                Op::GenerateContent,
+            ),
+            r#"{"zeta":1.50, "model" : "openai/gpt-4o","x_new":{"model":"inner","n":2e3},"id":"c1"}"#
+        );
+        assert_eq!(
+            rewritten(
+                br#"{"object":"list","data":[{"id":"gpt-4o","owned_by":"x","meta":{"id":"keep"}},{"id":"openai/o3"}],"has_more":false}"#,
+                Proto::OpenAIChat,
+                Op::ModelList,
+            ),
+            r#"{"object":"list","data":[{"id":"openai/gpt-4o","owned_by":"x","meta":{"id":"keep"}},{"id":"openai/o3"}],"has_more":false}"#
+        );
+        assert_eq!(
+            rewritten(
+                br#"{"models":[{"name":"models/gemini-2.5-pro","version":"001"}],"nextPageToken":"t"}"#,
+                Proto::Gemini,
+                Op::ModelList,
+            ),
+            r#"{"models":[{"name":"models/openai/gemini-2.5-pro","version":"001"}],"nextPageToken":"t"}"#
+        );
+        assert_eq!(
+            rewritten(
+                b"{\n  \"id\": \"gpt-4o\",\n  \"created\": 1\n}",
+                Proto::OpenAIChat,
+                Op::ModelGet,
+            ),
+            "{\n  \"id\": \"openai/gpt-4o\",\n  \"created\": 1\n}"
+        );
+        assert!(
+            rewrite_models_in_json(
+                &Bytes::from_static(b"[1]"),
+                Proto::OpenAIChat,
+                Op::GenerateContent,
+                &rewrite
+            )
+            .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn client_disconnect_records_an_aborted_stream() {
+        let global = GlobalConfigPatch {
+            admin_key: Some("admin".to_string()),
+            dsn: Some("memory://".to_string()),
+            ..GlobalConfigPatch::default()
+        }
+        .into_config()
+        .unwrap();
+        let state = AppState::from_bootstrap(global, snapshot(), EventHub::new(16))
+            .await
+            .unwrap();
+        let storage: Arc<dyn Storage> = Arc::new(MemoryStorage::new());
+        state.aborted_streams.start(storage.clone());
+        let mut registry = ProviderRegistry::new();
+        gproxy_provider_impl::register_builtin_providers(&mut registry);
+        let (upstream, upstream_rx) = tokio::sync::mpsc::channel(8);
+        let engine = ProxyEngine::new(
+            Arc::new(state),
+            Arc::new(registry),
+            Arc::new(StreamUpstream(Mutex::new(Some(upstream_rx)))),
+            storage.clone(),
+        );
+
+        let request = AuthRequest {
+            api_key: Some("sk-1".to_string()),
+            method: "POST".to_string(),
+            path: "/v1/chat/completions".to_string(),
+            ..AuthRequest::default()
+        };
+        let auth = engine
+            .authenticate(&request)
+            .await
+            .expect("key was not admitted");
+        let body = serde_json::from_value(serde_json::json!({
+            "model": "gpt-4o",
+            "messages": [{ "role": "user", "content": "hi" }],
+            "stream": true,
+        }))
+        .unwrap();
+        // The engine waits for the first event before answering.
+        upstream.send(chunk("hel")).await.unwrap();
+        let resp = engine
+            .handle(ProxyCall::Protocol {
+                trace_id: None,
+                auth,
+                provider: "openai".to_string(),
+                response_model_prefix_provider: None,
+                user_proto: Proto::OpenAIChat,
+                user_op: Op::StreamGenerateContent,
+                req: Box::new(Request::GenerateContent(
+                    GenerateContentRequest::OpenAIChat(CreateChatCompletionRequest { body }),
+                )),
+            })
+            .await;
+        let UpstreamBody::Stream(mut downstream) = resp.body else {
+            panic!("expected a stream, got status {}", resp.status);
+        };
+
+        assert!(downstream.recv().await.is_some());
+        drop(downstream);
+        upstream.send(chunk("lo")).await.unwrap();
+
+        let aborted = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let rows = storage.list_aborted_streams(Some(1), 10).await.unwrap();
+                if !rows.is_empty() {
+                    return rows;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("no aborted stream recorded");
+        assert_eq!(aborted.len(), 1);
+        assert_eq!(aborted[0].reason, "stream_forward_error");
+        assert_eq!(aborted[0].provider, "openai");
+        assert_eq!(aborted[0].credential_id, Some(1));
+    }
+}
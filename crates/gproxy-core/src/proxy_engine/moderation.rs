@@ -0,0 +1,320 @@
+//! Content moderation of generate requests: the per-key policy, the call to an
+//! OpenAI-compatible moderations endpoint, and the verdict read from its answer.
+//!
+//! The endpoint is reached through a provider like any other upstream, so a self-hosted
+//! moderation service is just a custom provider whose base URL points at it.
+
+use std::collections::BTreeMap;
+
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value as JsonValue, json};
+
+use gproxy_provider_core::{GenerateContentRequest, Request};
+
+use super::semantic_cache::take_texts;
+
+/// Response header with the moderation verdict: `pass`, `flagged=<categories>`,
+/// `blocked=<categories>` or `error` when the endpoint could not be asked.
+pub const MODERATION_HEADER: &str = "x-gproxy-moderation";
+/// Most text sent for one check; longer text keeps its end, where the newest turn is.
+const MAX_MODERATED_CHARS: usize = 32 * 1024;
+const DEFAULT_PATH: &str = "/v1/moderations";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModerationCheck {
+    #[default]
+    Input,
+    /// Non-stream answers only; a stream has reached the client before it ends.
+    Output,
+    Both,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModerationAction {
+    /// Refuse the request (or withhold the answer) with `400 content_moderated`.
+    #[default]
+    Block,
+    /// Let it through; the verdict only goes into the response header.
+    Flag,
+}
+
+/// How a user key's generate requests are moderated.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModerationPolicy {
+    /// Provider whose credentials serve the moderation calls.
+    pub provider: String,
+    /// Path of the moderations endpoint below the provider's base URL.
+    #[serde(default = "default_path")]
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub check: ModerationCheck,
+    #[serde(default)]
+    pub action: ModerationAction,
+    /// Category to the lowest score that trips it. Empty uses the endpoint's own
+    /// `flagged` and `categories`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub thresholds: BTreeMap<String, f64>,
+    /// Refuse requests when the moderation call fails; by default they pass.
+    #[serde(default)]
+    pub fail_closed: bool,
+}
+
+fn default_path() -> String {
+    DEFAULT_PATH.to_string()
+}
+
+impl ModerationPolicy {
+    pub fn from_json(value: &JsonValue) -> Result<Self, String> {
+        let policy: Self = serde_json::from_value(value.clone()).map_err(|err| err.to_string())?;
+        if policy.provider.trim().is_empty() {
+            return Err("provider is required".to_string());
+        }
+        if !policy.path.starts_with('/') {
+            return Err("path must start with `/`".to_string());
+        }
+        if let Some((category, _)) = policy
+            .thresholds
+            .iter()
+            .find(|(_, score)| !(0.0..=1.0).contains(*score))
+        {
+            return Err(format!("threshold of `{category}` must be in [0, 1]"));
+        }
+        Ok(policy)
+    }
+
+    pub(super) fn checks_input(&self) -> bool {
+        self.check != ModerationCheck::Output
+    }
+
+    pub(super) fn checks_output(&self) -> bool {
+        self.check != ModerationCheck::Input
+    }
+}
+
+/// What the endpoint said about the text checked for one request.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(super) struct Verdict {
+    /// Categories that tripped, sorted; empty when the text passed.
+    pub categories: Vec<String>,
+    /// A check could not be made.
+    pub failed: bool,
+}
+
+impl Verdict {
+    pub fn failure() -> Self {
+        Self {
+            categories: Vec::new(),
+            failed: true,
+        }
+    }
+
+    pub fn tripped(&self) -> bool {
+        !self.categories.is_empty()
+    }
+
+    pub fn merge(&mut self, other: Verdict) {
+        self.categories.extend(other.categories);
+        self.categories.sort();
+        self.categories.dedup();
+        self.failed |= other.failed;
+    }
+
+    /// Value of [`MODERATION_HEADER`] for this verdict under `action`.
+    pub fn header_value(&self, action: ModerationAction) -> String {
+        if !self.tripped() {
+            return if self.failed { "error" } else { "pass" }.to_string();
+        }
+        let label = match action {
+            ModerationAction::Block => "blocked",
+            ModerationAction::Flag => "flagged",
+        };
+        format!("{label}={}", self.categories.join(","))
+    }
+}
+
+/// Prompt text of a generate request; `None` when it has none.
+pub(super) fn request_text(req: &Request) -> Option<String> {
+    let Request::GenerateContent(req) = req else {
+        return None;
+    };
+    let body = match req {
+        GenerateContentRequest::Claude(r) => serde_json::to_value(&r.body),
+        GenerateContentRequest::OpenAIChat(r) => serde_json::to_value(&r.body),
+        GenerateContentRequest::OpenAIResponse(r) => serde_json::to_value(&r.body),
+        GenerateContentRequest::Gemini(r) => serde_json::to_value(&r.body),
+        GenerateContentRequest::GeminiStream(r) => serde_json::to_value(&r.body),
+    };
+    joined_text(body.ok()?)
+}
+
+/// Answer text of a non-stream generate response body.
+pub(super) fn response_text(body: &[u8]) -> Option<String> {
+    joined_text(serde_json::from_slice(body).ok()?)
+}
+
+fn joined_text(mut value: JsonValue) -> Option<String> {
+    let mut texts = Vec::new();
+    take_texts(&mut value, &mut texts);
+    let text = texts.join("\n");
+    if text.trim().is_empty() {
+        return None;
+    }
+    let skip = text.chars().count().saturating_sub(MAX_MODERATED_CHARS);
+    Some(text.chars().skip(skip).collect())
+}
+
+/// Body of an OpenAI-compatible moderations call for `text`.
+pub(super) fn moderation_request(policy: &ModerationPolicy, text: &str) -> Bytes {
+    let mut body = json!({ "input": text });
+    if let Some(model) = &policy.model {
+        body["model"] = json!(model);
+    }
+    serde_json::to_vec(&body).unwrap_or_default().into()
+}
+
+/// Verdict of an OpenAI-compatible moderations response; `None` when it is not one.
+pub(super) fn parse_verdict(policy: &ModerationPolicy, body: &[u8]) -> Option<Verdict> {
+    let value: JsonValue = serde_json::from_slice(body).ok()?;
+    let mut categories = Vec::new();
+    for result in value.get("results")?.as_array()? {
+        if policy.thresholds.is_empty() {
+            if !result.get("flagged").and_then(JsonValue::as_bool)? {
+                continue;
+            }
+            let flagged = result
+                .get("categories")
+                .and_then(JsonValue::as_object)
+                .into_iter()
+                .flatten()
+                .filter(|(_, hit)| hit.as_bool() == Some(true))
+                .map(|(name, _)| name.clone())
+                .collect::<Vec<_>>();
+            if flagged.is_empty() {
+                categories.push("flagged".to_string());
+            }
+            categories.extend(flagged);
+        } else {
+            let scores = result.get("category_scores")?;
+            categories.extend(
+                policy
+                    .thresholds
+                    .iter()
+                    .filter(|(name, threshold)| {
+                        scores
+                            .get(name.as_str())
+                            .and_then(JsonValue::as_f64)
+                            .is_some_and(|score| score >= **threshold)
+                    })
+                    .map(|(name, _)| name.clone()),
+            );
+        }
+    }
+    categories.sort();
+    categories.dedup();
+    Some(Verdict {
+        categories,
+        failed: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(thresholds: JsonValue) -> ModerationPolicy {
+        ModerationPolicy::from_json(&json!({
+            "provider": "openai",
+            "model": "omni-moderation-latest",
+            "thresholds": thresholds,
+        }))
+        .unwrap()
+    }
+
+    fn response() -> Vec<u8> {
+        json!({
+            "id": "modr-1",
+            "results": [{
+                "flagged": true,
+                "categories": { "violence": true, "hate": false, "self-harm": false },
+                "category_scores": { "violence": 0.62, "hate": 0.31, "self-harm": 0.01 },
+            }],
+        })
+        .to_string()
+        .into_bytes()
+    }
+
+    #[test]
+    fn policies_default_to_blocking_on_input_and_are_validated() {
+        let policy = policy(json!({}));
+        assert_eq!(policy.path, "/v1/moderations");
+        assert!(policy.checks_input() && !policy.checks_output());
+        assert_eq!(policy.action, ModerationAction::Block);
+        assert!(!policy.fail_closed);
+
+        assert!(ModerationPolicy::from_json(&json!({ "provider": "" })).is_err());
+        assert!(ModerationPolicy::from_json(&json!({ "provider": "m", "path": "x" })).is_err());
+        let bad = json!({ "provider": "m", "thresholds": { "hate": 1.5 } });
+        assert!(ModerationPolicy::from_json(&bad).is_err());
+    }
+
+    #[test]
+    fn thresholds_override_the_endpoint_flag() {
+        let verdict = parse_verdict(&policy(json!({})), &response()).unwrap();
+        assert_eq!(verdict.categories, ["violence"]);
+        assert_eq!(
+            verdict.header_value(ModerationAction::Block),
+            "blocked=violence"
+        );
+
+        let strict = policy(json!({ "hate": 0.3, "violence": 0.9 }));
+        let verdict = parse_verdict(&strict, &response()).unwrap();
+        assert_eq!(verdict.categories, ["hate"]);
+        assert_eq!(verdict.header_value(ModerationAction::Flag), "flagged=hate");
+
+        let lax = policy(json!({ "violence": 0.9 }));
+        let verdict = parse_verdict(&lax, &response()).unwrap();
+        assert!(!verdict.tripped());
+        assert_eq!(verdict.header_value(ModerationAction::Block), "pass");
+
+        assert!(parse_verdict(&lax, b"{\"error\":{}}").is_none());
+        let mut merged = verdict;
+        merged.merge(Verdict::failure());
+        assert_eq!(merged.header_value(ModerationAction::Block), "error");
+    }
+
+    #[test]
+    fn request_and_answer_text_is_collected() {
+        let req = Request::GenerateContent(GenerateContentRequest::OpenAIChat(
+            gproxy_protocol::openai::create_chat_completions::request::CreateChatCompletionRequest {
+                body: serde_json::from_value(json!({
+                    "model": "gpt-4.1",
+                    "messages": [
+                        { "role": "system", "content": "Be brief." },
+                        { "role": "user", "content": "hello" },
+                    ],
+                }))
+                .unwrap(),
+            },
+        ));
+        assert_eq!(request_text(&req).as_deref(), Some("Be brief.\nhello"));
+
+        let answer = json!({ "content": [{ "type": "text", "text": "hi there" }] });
+        assert_eq!(
+            response_text(answer.to_string().as_bytes()).as_deref(),
+            Some("hi there")
+        );
+        assert!(response_text(b"{\"content\":[]}").is_none());
+
+        let body = moderation_request(&policy(json!({})), "hello");
+        let body: JsonValue = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body,
+            json!({ "input": "hello", "model": "omni-moderation-latest" })
+        );
+    }
+}
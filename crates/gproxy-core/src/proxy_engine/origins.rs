@@ -0,0 +1,123 @@
+//! Browser origins a user key may be used from, for keys embedded in public pages. A key
+//! with `allowed_origins` is only admitted when the request's `Origin` (or, without one,
+//! the origin of its `Referer`) matches an entry; requests carrying neither are refused.
+//!
+//! Entries are `scheme://host[:port]`, and the host may start with `*.` to match any
+//! subdomain. Browsers set these headers themselves, so the restriction keeps a leaked key
+//! off other sites; it does not stop non-browser clients that forge them.
+
+use serde_json::Value as JsonValue;
+
+use gproxy_provider_core::{Headers, header_get};
+
+/// Origins a user key may be used from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AllowedOrigins {
+    origins: Vec<String>,
+}
+
+impl AllowedOrigins {
+    /// Parses a JSON array of origins, normalizing each to lowercase without a trailing
+    /// slash.
+    pub fn from_json(value: &JsonValue) -> Result<Self, String> {
+        let entries: Vec<String> =
+            serde_json::from_value(value.clone()).map_err(|err| err.to_string())?;
+        let origins = entries
+            .iter()
+            .map(|entry| {
+                let origin = entry.trim().trim_end_matches('/').to_ascii_lowercase();
+                let Some((scheme, host)) = origin.split_once("://") else {
+                    return Err(format!("origin `{entry}` must be http(s)://host[:port]"));
+                };
+                let host = host.strip_prefix("*.").unwrap_or(host);
+                if !matches!(scheme, "http" | "https")
+                    || host.is_empty()
+                    || host.contains(['/', '?', '#', '*', '@'])
+                {
+                    return Err(format!("origin `{entry}` must be http(s)://host[:port]"));
+                }
+                Ok(origin)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { origins })
+    }
+
+    pub fn origins(&self) -> &[String] {
+        &self.origins
+    }
+
+    /// Whether the request's `Origin`, or the origin of its `Referer`, is allowed. An
+    /// empty list allows every request.
+    pub fn allows(&self, headers: &Headers) -> bool {
+        if self.origins.is_empty() {
+            return true;
+        }
+        let origin = header_get(headers, "origin")
+            .map(str::trim)
+            .filter(|origin| !origin.is_empty() && *origin != "null")
+            .map(|origin| origin.trim_end_matches('/').to_ascii_lowercase())
+            .or_else(|| header_get(headers, "referer").and_then(referer_origin));
+        origin.is_some_and(|origin| self.origins.iter().any(|entry| matches(entry, &origin)))
+    }
+}
+
+/// `scheme://authority` of a referer URL.
+fn referer_origin(referer: &str) -> Option<String> {
+    let referer = referer.trim();
+    let (scheme, rest) = referer.split_once("://")?;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    (!authority.is_empty()).then(|| format!("{scheme}://{authority}").to_ascii_lowercase())
+}
+
+fn matches(entry: &str, origin: &str) -> bool {
+    let Some((scheme, host)) = entry.split_once("://") else {
+        return false;
+    };
+    match host.strip_prefix("*.") {
+        Some(suffix) => origin
+            .strip_prefix(scheme)
+            .and_then(|rest| rest.strip_prefix("://"))
+            .and_then(|host| host.strip_suffix(suffix))
+            .is_some_and(|sub| sub.len() > 1 && sub.ends_with('.')),
+        None => entry == origin,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&str, &str)]) -> Headers {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn matches_origin_then_referer() {
+        let allowed = AllowedOrigins::from_json(&serde_json::json!([
+            "https://demo.example.com/",
+            "https://*.example.org",
+            "http://localhost:5173",
+        ]))
+        .unwrap();
+
+        assert!(allowed.allows(&headers(&[("Origin", "https://demo.example.com")])));
+        assert!(allowed.allows(&headers(&[("origin", "https://a.b.example.org")])));
+        assert!(allowed.allows(&headers(&[("referer", "http://localhost:5173/chat?x=1")])));
+        assert!(!allowed.allows(&headers(&[("origin", "https://example.org")])));
+        assert!(!allowed.allows(&headers(&[("origin", "https://evilexample.org")])));
+        assert!(!allowed.allows(&headers(&[("origin", "http://demo.example.com")])));
+        assert!(!allowed.allows(&headers(&[(
+            "referer",
+            "https://demo.example.com.evil.io/"
+        )])));
+        assert!(!allowed.allows(&headers(&[("origin", "null")])));
+        assert!(!allowed.allows(&headers(&[])));
+
+        assert!(AllowedOrigins::default().allows(&headers(&[])));
+        assert!(AllowedOrigins::from_json(&serde_json::json!(["demo.example.com"])).is_err());
+        assert!(AllowedOrigins::from_json(&serde_json::json!(["https://a.com/app"])).is_err());
+    }
+}
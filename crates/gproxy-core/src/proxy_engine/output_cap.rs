@@ -0,0 +1,257 @@
+//! Per-key cap on output tokens. The request's own limit is lowered to the cap, and a
+//! stream that runs past it anyway (an upstream ignoring the limit) is cut off with the
+//! protocol's usual finish events.
+
+use bytes::Bytes;
+use gproxy_provider_core::provider::ByteStream;
+use gproxy_provider_core::{GenerateContentRequest, Proto, Request};
+use serde_json::{Value as JsonValue, json};
+
+use super::pacing::estimate_chunk_tokens;
+use super::profiles::patch_json;
+use super::wire::{encode_openai_chat_done, encode_sse};
+
+/// Streamed output is estimated at four characters per token, which runs high for
+/// English prose; a stream is only cut once the estimate passes the cap by this factor.
+const STREAM_CAP_TOLERANCE: f64 = 1.25;
+
+/// Lowers the output token limit of a generate request to `cap`, setting one when the
+/// request has none.
+pub(crate) fn cap_request(req: &mut Request, cap: u32) {
+    let Request::GenerateContent(inner) = req else {
+        return;
+    };
+    let cap_i64 = i64::from(cap);
+    match inner {
+        GenerateContentRequest::Claude(r) => r.body.max_tokens = r.body.max_tokens.min(cap),
+        GenerateContentRequest::OpenAIChat(r) => {
+            let body = &mut r.body;
+            if body.max_completion_tokens.is_none() && body.max_tokens.is_none() {
+                body.max_completion_tokens = Some(cap_i64);
+            }
+            for limit in [&mut body.max_completion_tokens, &mut body.max_tokens]
+                .into_iter()
+                .flatten()
+            {
+                *limit = (*limit).min(cap_i64);
+            }
+        }
+        GenerateContentRequest::OpenAIResponse(r) => {
+            r.body.max_output_tokens =
+                Some(r.body.max_output_tokens.map_or(cap_i64, |v| v.min(cap_i64)));
+        }
+        GenerateContentRequest::Gemini(r) => patch_json(&mut r.body, |body| cap_gemini(body, cap)),
+        GenerateContentRequest::GeminiStream(r) => {
+            patch_json(&mut r.body, |body| cap_gemini(body, cap))
+        }
+    }
+}
+
+fn cap_gemini(body: &mut JsonValue, cap: u32) {
+    let Some(map) = body.as_object_mut() else {
+        return;
+    };
+    let config = map.entry("generationConfig").or_insert_with(|| json!({}));
+    let Some(config) = config.as_object_mut() else {
+        return;
+    };
+    let limit = config
+        .get("maxOutputTokens")
+        .and_then(JsonValue::as_u64)
+        .map_or(u64::from(cap), |v| v.min(u64::from(cap)));
+    config.insert("maxOutputTokens".to_string(), json!(limit));
+}
+
+/// Forwards the SSE stream `rx` of protocol `proto` until its estimated output passes
+/// `cap`, then ends it with a max-tokens finish instead of the event that went over.
+pub(crate) fn cap_stream(mut rx: ByteStream, proto: Proto, cap: u32) -> ByteStream {
+    let (tx, rx_out) = tokio::sync::mpsc::channel::<Bytes>(32);
+    let limit = (f64::from(cap) * STREAM_CAP_TOLERANCE) as u64;
+    tokio::spawn(async move {
+        let mut pending: Vec<u8> = Vec::new();
+        let mut used: u64 = 0;
+        let mut seen = SeenEvents::default();
+        while let Some(chunk) = rx.recv().await {
+            pending.extend_from_slice(&chunk);
+            let mut out = Vec::new();
+            while let Some(end) = event_end(&pending) {
+                let event: Vec<u8> = pending.drain(..end).collect();
+                used += estimate_chunk_tokens(&event);
+                if used > limit {
+                    out.extend_from_slice(&seen.finish_events(proto, cap));
+                    // Dropping `rx` ends the upstream read.
+                    let _ = tx.send(Bytes::from(out)).await;
+                    return;
+                }
+                seen.observe(&event);
+                out.extend_from_slice(&event);
+            }
+            if !out.is_empty() && tx.send(Bytes::from(out)).await.is_err() {
+                return;
+            }
+        }
+        if !pending.is_empty() {
+            let _ = tx.send(Bytes::from(pending)).await;
+        }
+    });
+    rx_out
+}
+
+/// Length of the first complete event in `pending`, including its blank line.
+fn event_end(pending: &[u8]) -> Option<usize> {
+    let find = |needle: &[u8]| {
+        pending
+            .windows(needle.len())
+            .position(|w| w == needle)
+            .map(|pos| pos + needle.len())
+    };
+    match (find(b"\n\n"), find(b"\r\n\r\n")) {
+        (Some(lf), Some(crlf)) => Some(lf.min(crlf)),
+        (lf, crlf) => lf.or(crlf),
+    }
+}
+
+/// What the finish events need from the events already forwarded.
+#[derive(Default)]
+struct SeenEvents {
+    /// Last JSON payload, for ids and model names.
+    last: Option<JsonValue>,
+    /// Responses API: the response object of the latest lifecycle event.
+    response: Option<JsonValue>,
+    /// Claude: the content block still open.
+    open_block: Option<u64>,
+}
+
+impl SeenEvents {
+    fn observe(&mut self, event: &[u8]) {
+        let Some(data) = event_json(event) else {
+            return;
+        };
+        match data.get("type").and_then(JsonValue::as_str) {
+            Some("content_block_start") => {
+                self.open_block = data.get("index").and_then(JsonValue::as_u64);
+            }
+            Some("content_block_stop") => self.open_block = None,
+            _ => {}
+        }
+        if let Some(response) = data.get("response").filter(|r| r.is_object()) {
+            self.response = Some(response.clone());
+        }
+        self.last = Some(data);
+    }
+
+    fn finish_events(&self, proto: Proto, cap: u32) -> Vec<u8> {
+        let last = self.last.as_ref();
+        let field = |name: &str| {
+            last.and_then(|v| v.get(name))
+                .cloned()
+                .unwrap_or(JsonValue::Null)
+        };
+        let mut out = Vec::new();
+        match proto {
+            Proto::Claude => {
+                if let Some(index) = self.open_block {
+                    let stop = json!({ "type": "content_block_stop", "index": index });
+                    out.extend_from_slice(&encode_sse(
+                        Some("content_block_stop"),
+                        &stop.to_string(),
+                    ));
+                }
+                let delta = json!({
+                    "type": "message_delta",
+                    "delta": { "stop_reason": "max_tokens", "stop_sequence": null },
+                    "usage": { "output_tokens": cap },
+                });
+                out.extend_from_slice(&encode_sse(Some("message_delta"), &delta.to_string()));
+                out.extend_from_slice(&encode_sse(
+                    Some("message_stop"),
+                    &json!({ "type": "message_stop" }).to_string(),
+                ));
+            }
+            Proto::OpenAIChat | Proto::OpenAI => {
+                let chunk = json!({
+                    "id": field("id"),
+                    "object": "chat.completion.chunk",
+                    "created": field("created"),
+                    "model": field("model"),
+                    "choices": [{ "index": 0, "delta": {}, "finish_reason": "length" }],
+                });
+                out.extend_from_slice(&encode_sse(None, &chunk.to_string()));
+                out.extend_from_slice(&encode_openai_chat_done());
+            }
+            Proto::OpenAIResponse => {
+                let mut response = self.response.clone().unwrap_or_else(|| json!({}));
+                response["status"] = json!("incomplete");
+                response["incomplete_details"] = json!({ "reason": "max_output_tokens" });
+                let event = json!({
+                    "type": "response.incomplete",
+                    "response": response,
+                    "sequence_number": field("sequence_number").as_i64().map_or(0, |n| n + 1),
+                });
+                out.extend_from_slice(&encode_sse(Some("response.incomplete"), &event.to_string()));
+            }
+            Proto::Gemini => {
+                let chunk = json!({
+                    "candidates": [{
+                        "content": { "role": "model", "parts": [{ "text": "" }] },
+                        "finishReason": "MAX_TOKENS",
+                        "index": 0,
+                    }],
+                    "modelVersion": field("modelVersion"),
+                    "responseId": field("responseId"),
+                });
+                out.extend_from_slice(&encode_sse(None, &chunk.to_string()));
+            }
+        }
+        out
+    }
+}
+
+/// JSON payload of an SSE event's `data:` lines.
+fn event_json(event: &[u8]) -> Option<JsonValue> {
+    let text = std::str::from_utf8(event).ok()?;
+    let data: Vec<&str> = text
+        .lines()
+        .filter_map(|line| line.strip_prefix("data:"))
+        .map(str::trim_start)
+        .collect();
+    serde_json::from_str(&data.join("\n")).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn collect(mut rx: ByteStream) -> String {
+        let mut out = Vec::new();
+        while let Some(chunk) = rx.recv().await {
+            out.extend_from_slice(&chunk);
+        }
+        String::from_utf8(out).unwrap()
+    }
+
+    #[tokio::test]
+    async fn stream_past_the_cap_ends_with_a_max_tokens_finish() {
+        let (tx, rx) = tokio::sync::mpsc::channel(8);
+        let capped = cap_stream(rx, Proto::Claude, 4);
+        tx.send(Bytes::from_static(
+            b"event: content_block_start\ndata: {\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"text\",\"text\":\"\"}}\n\n",
+        ))
+        .await
+        .unwrap();
+        let delta = Bytes::from_static(
+            b"event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"0123456789ab\"}}\n\n",
+        );
+        for _ in 0..3 {
+            tx.send(delta.clone()).await.unwrap();
+        }
+        drop(tx);
+        let out = collect(capped).await;
+
+        // Three tokens per delta: the second one passes the cap with its tolerance.
+        assert_eq!(out.matches("text_delta").count(), 1);
+        assert!(out.contains("event: content_block_stop\n"));
+        assert!(out.contains(r#""stop_reason":"max_tokens""#));
+        assert!(out.ends_with("event: message_stop\ndata: {\"type\":\"message_stop\"}\n\n"));
+    }
+}
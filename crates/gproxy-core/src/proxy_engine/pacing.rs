@@ -0,0 +1,134 @@
+//! Output pacing for keys with a streamed tokens-per-second limit.
+
+use std::time::Duration;
+
+use bytes::Bytes;
+use gproxy_provider_core::provider::ByteStream;
+use serde_json::Value as JsonValue;
+use tokio::time::Instant;
+
+/// Text-bearing fields of streamed deltas across the supported protocols.
+const TEXT_FIELDS: [&str; 7] = [
+    "text",
+    "thinking",
+    "partial_json",
+    "delta",
+    "content",
+    "reasoning_content",
+    "arguments",
+];
+
+/// Forwards `rx` at no more than `tokens_per_sec`, estimated from the text each chunk
+/// carries. Up to one second's worth passes without delay, so short replies are not
+/// slowed down.
+pub(crate) fn pace_stream(mut rx: ByteStream, tokens_per_sec: u32) -> ByteStream {
+    let (tx, rx_out) = tokio::sync::mpsc::channel::<Bytes>(32);
+    let rate = f64::from(tokens_per_sec.max(1));
+    tokio::spawn(async move {
+        let mut available = rate;
+        let mut refilled_at = Instant::now();
+        while let Some(chunk) = rx.recv().await {
+            let tokens = estimate_chunk_tokens(&chunk) as f64;
+            if tokens > 0.0 {
+                let now = Instant::now();
+                available =
+                    (available + now.duration_since(refilled_at).as_secs_f64() * rate).min(rate);
+                refilled_at = now;
+                let needed = tokens.min(rate);
+                if available < needed {
+                    tokio::time::sleep(Duration::from_secs_f64((needed - available) / rate)).await;
+                    available = needed;
+                    refilled_at = Instant::now();
+                }
+                available -= tokens;
+            }
+            if tx.send(chunk).await.is_err() {
+                break;
+            }
+        }
+    });
+    rx_out
+}
+
+/// Rough output tokens in an SSE or NDJSON chunk: about four characters of delta text
+/// per token. Summary events (a `type` not ending in `delta`) repeat earlier text and
+/// count as zero.
+pub(super) fn estimate_chunk_tokens(chunk: &[u8]) -> u64 {
+    let Ok(text) = std::str::from_utf8(chunk) else {
+        return 0;
+    };
+    let chars: usize = text
+        .lines()
+        .map(|line| {
+            line.strip_prefix("data:")
+                .unwrap_or(line)
+                .trim()
+                .trim_start_matches([',', '['])
+        })
+        .filter(|line| line.starts_with('{'))
+        .filter_map(|line| serde_json::from_str::<JsonValue>(line).ok())
+        .filter(|value| {
+            value
+                .get("type")
+                .and_then(JsonValue::as_str)
+                .is_none_or(|kind| kind.ends_with("delta"))
+        })
+        .map(|value| text_chars(&value, false))
+        .sum();
+    chars.div_ceil(4) as u64
+}
+
+fn text_chars(value: &JsonValue, in_text_field: bool) -> usize {
+    match value {
+        JsonValue::String(s) if in_text_field => s.chars().count(),
+        JsonValue::Array(items) => items
+            .iter()
+            .map(|item| text_chars(item, in_text_field))
+            .sum(),
+        JsonValue::Object(map) => map
+            .iter()
+            .map(|(key, value)| text_chars(value, TEXT_FIELDS.contains(&key.as_str())))
+            .sum(),
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimates_delta_text_and_skips_summary_events() {
+        let claude = br#"event: content_block_delta
+data: {"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hello, world"}}
+
+"#;
+        assert_eq!(estimate_chunk_tokens(claude), 3);
+
+        let chat = br#"data: {"object":"chat.completion.chunk","choices":[{"delta":{"content":"abcdefgh"}}]}"#;
+        assert_eq!(estimate_chunk_tokens(chat), 2);
+
+        let done = br#"data: {"type":"response.output_text.done","text":"a long repeated answer"}"#;
+        assert_eq!(estimate_chunk_tokens(done), 0);
+        assert_eq!(estimate_chunk_tokens(b"data: [DONE]\n\n"), 0);
+    }
+
+    #[tokio::test]
+    async fn paces_output_beyond_the_first_second() {
+        let (tx, rx) = tokio::sync::mpsc::channel(8);
+        let mut paced = pace_stream(rx, 10);
+        // Two tokens each: five pass at once, the other two wait 0.2s apiece.
+        let chunk = Bytes::from_static(br#"data: {"delta":{"text":"01234567"}}"#);
+        let start = Instant::now();
+        for _ in 0..7 {
+            tx.send(chunk.clone()).await.unwrap();
+        }
+        drop(tx);
+        let mut received = 0;
+        while paced.recv().await.is_some() {
+            received += 1;
+        }
+        assert_eq!(received, 7);
+        assert!(start.elapsed() >= Duration::from_millis(350));
+    }
+}
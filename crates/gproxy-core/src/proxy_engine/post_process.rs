@@ -0,0 +1,669 @@
+//! Response post-processing: a provider's `post_process` rules applied to the generated
+//! text of responses and streams on their way to the client.
+//!
+//! Streams are handled per text channel (a Claude content block, a chat choice, a
+//! Responses content part, a Gemini candidate). The latest text delta of a channel is
+//! held back until the next one arrives, and receives whatever processed text became
+//! safe to emit meanwhile; the channel's end flushes the rest into it. Events are never
+//! added or dropped, only their text changes.
+
+use std::sync::Arc;
+
+use bytes::Bytes;
+use gproxy_protocol::sse::SseParser;
+use gproxy_provider_core::provider::ByteStream;
+use gproxy_provider_core::{PostProcessPolicy, Proto, TextWindow};
+use serde_json::Value as JsonValue;
+
+use super::wire::encode_sse;
+
+/// Applies `policy` to the text of a generate response body of `proto`. `None` when the
+/// body is not JSON or no text changed.
+pub(crate) fn process_response(
+    proto: Proto,
+    policy: &PostProcessPolicy,
+    body: &[u8],
+) -> Option<Bytes> {
+    let mut value: JsonValue = serde_json::from_slice(body).ok()?;
+    let mut changed = false;
+    for text in response_texts(proto, &mut value) {
+        let processed = policy.apply(text, true);
+        if processed != *text {
+            *text = processed;
+            changed = true;
+        }
+    }
+    if !changed {
+        return None;
+    }
+    serde_json::to_vec(&value).ok().map(Bytes::from)
+}
+
+/// Applies `policy` to a generate stream of `proto`: SSE when `sse`, otherwise Gemini's
+/// JSON lines. Anything that does not parse is forwarded untouched.
+pub(crate) fn process_stream(
+    mut rx: ByteStream,
+    proto: Proto,
+    policy: Arc<PostProcessPolicy>,
+    sse: bool,
+) -> ByteStream {
+    let (tx, rx_out) = tokio::sync::mpsc::channel::<Bytes>(32);
+    tokio::spawn(async move {
+        let mut texts = TextStream::new(proto, policy);
+        let mut parser = SseParser::new();
+        let mut lines: Vec<u8> = Vec::new();
+        while let Some(chunk) = rx.recv().await {
+            let frames = if sse {
+                parser
+                    .push_bytes(&chunk)
+                    .into_iter()
+                    .map(|ev| Frame::from_sse(ev.event, ev.data))
+                    .collect()
+            } else {
+                lines.extend_from_slice(&chunk);
+                complete_lines(&mut lines)
+            };
+            let out = texts.push_all(frames);
+            if !out.is_empty() && tx.send(out).await.is_err() {
+                return;
+            }
+        }
+        let mut frames: Vec<Frame> = if sse {
+            parser
+                .finish()
+                .into_iter()
+                .map(|ev| Frame::from_sse(ev.event, ev.data))
+                .collect()
+        } else if lines.is_empty() {
+            Vec::new()
+        } else {
+            vec![Frame::from_line(&lines)]
+        };
+        frames.push(Frame::End);
+        let out = texts.push_all(frames);
+        if !out.is_empty() {
+            let _ = tx.send(out).await;
+        }
+    });
+    rx_out
+}
+
+fn complete_lines(buf: &mut Vec<u8>) -> Vec<Frame> {
+    let mut frames = Vec::new();
+    while let Some(pos) = buf.iter().position(|b| *b == b'\n') {
+        let line: Vec<u8> = buf.drain(..=pos).collect();
+        frames.push(Frame::from_line(&line[..pos]));
+    }
+    frames
+}
+
+enum Frame {
+    Sse {
+        event: Option<String>,
+        data: JsonValue,
+    },
+    Line(JsonValue),
+    /// Forwarded as is.
+    Raw(Bytes),
+    /// End of the upstream stream.
+    End,
+}
+
+impl Frame {
+    fn from_sse(event: Option<String>, data: String) -> Self {
+        match serde_json::from_str(&data) {
+            Ok(data) => Frame::Sse { event, data },
+            Err(_) => Frame::Raw(encode_sse(event.as_deref(), &data)),
+        }
+    }
+
+    fn from_line(line: &[u8]) -> Self {
+        match serde_json::from_slice::<JsonValue>(line) {
+            Ok(data) if data.is_object() => Frame::Line(data),
+            _ => {
+                let mut raw = line.to_vec();
+                raw.push(b'\n');
+                Frame::Raw(Bytes::from(raw))
+            }
+        }
+    }
+
+    fn data(&self) -> Option<&JsonValue> {
+        match self {
+            Frame::Sse { data, .. } | Frame::Line(data) => Some(data),
+            Frame::Raw(_) | Frame::End => None,
+        }
+    }
+
+    fn data_mut(&mut self) -> Option<&mut JsonValue> {
+        match self {
+            Frame::Sse { data, .. } | Frame::Line(data) => Some(data),
+            Frame::Raw(_) | Frame::End => None,
+        }
+    }
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Frame::Sse { event, data } => {
+                out.extend_from_slice(&encode_sse(event.as_deref(), &data.to_string()))
+            }
+            Frame::Line(data) => {
+                out.extend_from_slice(data.to_string().as_bytes());
+                out.push(b'\n');
+            }
+            Frame::Raw(raw) => out.extend_from_slice(raw),
+            Frame::End => {}
+        }
+    }
+}
+
+#[derive(Default)]
+struct Channel {
+    window: TextWindow,
+    /// Processed text not yet given to an event.
+    ready: String,
+    /// The latest text delta, waiting for the text after it.
+    held: Option<Frame>,
+    /// Processed text emitted so far.
+    emitted: String,
+}
+
+struct TextStream {
+    proto: Proto,
+    policy: Arc<PostProcessPolicy>,
+    channels: Vec<(String, Channel)>,
+    /// Full processed text of ended channels, for events that repeat it.
+    finished: Vec<(String, String)>,
+}
+
+impl TextStream {
+    fn new(proto: Proto, policy: Arc<PostProcessPolicy>) -> Self {
+        Self {
+            proto,
+            policy,
+            channels: Vec::new(),
+            finished: Vec::new(),
+        }
+    }
+
+    fn push_all(&mut self, frames: Vec<Frame>) -> Bytes {
+        let mut out = Vec::new();
+        for frame in frames {
+            for frame in self.push(frame) {
+                frame.encode(&mut out);
+            }
+        }
+        Bytes::from(out)
+    }
+
+    fn push(&mut self, mut frame: Frame) -> Vec<Frame> {
+        let mut out = Vec::new();
+        let Some(data) = frame.data() else {
+            // `[DONE]`, unparsable data and the end of the stream close every channel.
+            out.extend(self.flush_all());
+            out.push(frame);
+            return out;
+        };
+        let proto = self.proto;
+        if let Some((channel, text)) = delta_text(proto, data) {
+            let ends = end_of_channel(proto, data).is_some();
+            let policy = self.policy.clone();
+            let state = self.channel(&channel);
+            let safe = state.window.push(&policy, &text);
+            state.ready.push_str(&safe);
+            if let Some(mut held) = state.held.take() {
+                let text = std::mem::take(&mut state.ready);
+                state.emitted.push_str(&text);
+                set_delta_text(proto, &mut held, text);
+                out.push(held);
+            }
+            state.held = Some(frame);
+            if ends {
+                out.extend(self.flush(&channel));
+            }
+            return out;
+        }
+        if is_terminal(self.proto, data) {
+            out.extend(self.flush_all());
+        } else if let Some(channel) = end_of_channel(self.proto, data) {
+            out.extend(self.flush(&channel));
+        }
+        if self.proto == Proto::OpenAIResponse
+            && let Some(data) = frame.data_mut()
+        {
+            self.fill_finished_texts(data);
+        }
+        out.push(frame);
+        out
+    }
+
+    fn channel(&mut self, key: &str) -> &mut Channel {
+        let index = match self.channels.iter().position(|(k, _)| k == key) {
+            Some(index) => index,
+            None => {
+                self.channels.push((key.to_string(), Channel::default()));
+                self.channels.len() - 1
+            }
+        };
+        &mut self.channels[index].1
+    }
+
+    fn flush(&mut self, key: &str) -> Option<Frame> {
+        let index = self.channels.iter().position(|(k, _)| k == key)?;
+        let (key, mut state) = self.channels.remove(index);
+        let mut text = std::mem::take(&mut state.ready);
+        text.push_str(&state.window.finish(&self.policy));
+        state.emitted.push_str(&text);
+        self.finished.push((key, state.emitted));
+        let mut held = state.held?;
+        set_delta_text(self.proto, &mut held, text);
+        Some(held)
+    }
+
+    fn flush_all(&mut self) -> Vec<Frame> {
+        let keys: Vec<String> = self.channels.iter().map(|(k, _)| k.clone()).collect();
+        keys.iter().filter_map(|key| self.flush(key)).collect()
+    }
+
+    fn finished_text(&self, key: &str) -> Option<&str> {
+        self.finished
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, text)| text.as_str())
+    }
+
+    /// Responses events repeating a content part's full text get the processed text.
+    fn fill_finished_texts(&self, data: &mut JsonValue) {
+        let output_index = data.get("output_index").and_then(JsonValue::as_u64);
+        let content_index = data.get("content_index").and_then(JsonValue::as_u64);
+        let key = |oi: u64, ci: u64| format!("{oi}:{ci}");
+        match data.get("type").and_then(JsonValue::as_str) {
+            Some("response.output_text.done") => {
+                if let (Some(oi), Some(ci)) = (output_index, content_index)
+                    && let Some(text) = self.finished_text(&key(oi, ci))
+                {
+                    data["text"] = JsonValue::from(text);
+                }
+            }
+            Some("response.content_part.done") => {
+                if let (Some(oi), Some(ci)) = (output_index, content_index)
+                    && let Some(text) = self.finished_text(&key(oi, ci))
+                    && let Some(part) = data.get_mut("part")
+                {
+                    set_output_text(part, text);
+                }
+            }
+            Some("response.output_item.done") => {
+                if let Some(oi) = output_index
+                    && let Some(item) = data.get_mut("item")
+                {
+                    self.fill_item(oi, item);
+                }
+            }
+            Some("response.completed" | "response.incomplete" | "response.failed") => {
+                let outputs = data
+                    .get_mut("response")
+                    .and_then(|r| r.get_mut("output"))
+                    .and_then(JsonValue::as_array_mut);
+                for (oi, item) in outputs.into_iter().flatten().enumerate() {
+                    self.fill_item(oi as u64, item);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn fill_item(&self, output_index: u64, item: &mut JsonValue) {
+        let parts = item.get_mut("content").and_then(JsonValue::as_array_mut);
+        for (ci, part) in parts.into_iter().flatten().enumerate() {
+            if let Some(text) = self.finished_text(&format!("{output_index}:{ci}")) {
+                set_output_text(part, text);
+            }
+        }
+    }
+}
+
+fn set_output_text(part: &mut JsonValue, text: &str) {
+    if part.get("type").and_then(JsonValue::as_str) == Some("output_text") {
+        part["text"] = JsonValue::from(text);
+    }
+}
+
+fn first_choice(data: &JsonValue) -> Option<&JsonValue> {
+    data.get("choices")?.as_array()?.first()
+}
+
+fn first_candidate(data: &JsonValue) -> Option<&JsonValue> {
+    data.get("candidates")?.as_array()?.first()
+}
+
+fn index_key(value: &JsonValue) -> String {
+    value
+        .get("index")
+        .and_then(JsonValue::as_u64)
+        .unwrap_or(0)
+        .to_string()
+}
+
+/// Channel and text of a text delta event.
+fn delta_text(proto: Proto, data: &JsonValue) -> Option<(String, String)> {
+    match proto {
+        Proto::Claude => {
+            if data.get("type")?.as_str()? != "content_block_delta" {
+                return None;
+            }
+            let delta = data.get("delta")?;
+            if delta.get("type")?.as_str()? != "text_delta" {
+                return None;
+            }
+            Some((index_key(data), delta.get("text")?.as_str()?.to_string()))
+        }
+        Proto::OpenAIChat | Proto::OpenAI => {
+            let choice = first_choice(data)?;
+            let text = choice.get("delta")?.get("content")?.as_str()?;
+            Some((index_key(choice), text.to_string()))
+        }
+        Proto::OpenAIResponse => {
+            if data.get("type")?.as_str()? != "response.output_text.delta" {
+                return None;
+            }
+            Some((
+                responses_key(data)?,
+                data.get("delta")?.as_str()?.to_string(),
+            ))
+        }
+        Proto::Gemini => {
+            let candidate = first_candidate(data)?;
+            let parts = candidate.get("content")?.get("parts")?.as_array()?;
+            let mut text = None::<String>;
+            for part in parts.iter().filter(|part| !is_thought(part)) {
+                if let Some(t) = part.get("text").and_then(JsonValue::as_str) {
+                    text.get_or_insert_with(String::new).push_str(t);
+                }
+            }
+            Some((index_key(candidate), text?))
+        }
+    }
+}
+
+fn set_delta_text(proto: Proto, frame: &mut Frame, text: String) {
+    let Some(data) = frame.data_mut() else {
+        return;
+    };
+    match proto {
+        Proto::Claude => data["delta"]["text"] = JsonValue::from(text),
+        Proto::OpenAIChat | Proto::OpenAI => {
+            data["choices"][0]["delta"]["content"] = JsonValue::from(text)
+        }
+        Proto::OpenAIResponse => data["delta"] = JsonValue::from(text),
+        Proto::Gemini => {
+            let parts = data["candidates"][0]["content"]
+                .get_mut("parts")
+                .and_then(JsonValue::as_array_mut);
+            let mut text = Some(text);
+            for part in parts.into_iter().flatten() {
+                if is_thought(part) || part.get("text").is_none() {
+                    continue;
+                }
+                part["text"] = JsonValue::from(text.take().unwrap_or_default());
+            }
+        }
+    }
+}
+
+fn is_thought(part: &JsonValue) -> bool {
+    part.get("thought").and_then(JsonValue::as_bool) == Some(true)
+}
+
+fn responses_key(data: &JsonValue) -> Option<String> {
+    let output_index = data.get("output_index")?.as_u64()?;
+    let content_index = data.get("content_index")?.as_u64()?;
+    Some(format!("{output_index}:{content_index}"))
+}
+
+/// Channel an event ends.
+fn end_of_channel(proto: Proto, data: &JsonValue) -> Option<String> {
+    match proto {
+        Proto::Claude => {
+            (data.get("type")?.as_str()? == "content_block_stop").then(|| index_key(data))
+        }
+        Proto::OpenAIChat | Proto::OpenAI => {
+            let choice = first_choice(data)?;
+            (!choice.get("finish_reason")?.is_null()).then(|| index_key(choice))
+        }
+        Proto::OpenAIResponse => {
+            if data.get("type")?.as_str()? != "response.output_text.done" {
+                return None;
+            }
+            responses_key(data)
+        }
+        Proto::Gemini => {
+            let candidate = first_candidate(data)?;
+            candidate.get("finishReason")?;
+            Some(index_key(candidate))
+        }
+    }
+}
+
+/// Events after which no more text arrives.
+fn is_terminal(proto: Proto, data: &JsonValue) -> bool {
+    let kind = data.get("type").and_then(JsonValue::as_str);
+    match proto {
+        Proto::Claude => matches!(kind, Some("message_delta" | "message_stop")),
+        Proto::OpenAIResponse => matches!(
+            kind,
+            Some("response.completed" | "response.incomplete" | "response.failed")
+        ),
+        Proto::OpenAIChat | Proto::OpenAI | Proto::Gemini => false,
+    }
+}
+
+fn array_mut(value: Option<&mut JsonValue>) -> impl Iterator<Item = &mut JsonValue> {
+    value
+        .and_then(JsonValue::as_array_mut)
+        .into_iter()
+        .flatten()
+}
+
+/// Generated text fields of a non-stream response; thinking is left alone.
+fn response_texts(proto: Proto, value: &mut JsonValue) -> Vec<&mut String> {
+    let mut out = Vec::new();
+    match proto {
+        Proto::Claude => {
+            out.extend(array_mut(value.get_mut("content")).filter_map(|b| text_of(b, "text")));
+        }
+        Proto::OpenAIChat | Proto::OpenAI => {
+            for choice in array_mut(value.get_mut("choices")) {
+                if let Some(JsonValue::String(text)) = choice
+                    .get_mut("message")
+                    .and_then(|message| message.get_mut("content"))
+                {
+                    out.push(text);
+                }
+            }
+        }
+        Proto::OpenAIResponse => {
+            for item in array_mut(value.get_mut("output")) {
+                if item.get("type").and_then(JsonValue::as_str) != Some("message") {
+                    continue;
+                }
+                out.extend(
+                    array_mut(item.get_mut("content")).filter_map(|p| text_of(p, "output_text")),
+                );
+            }
+        }
+        Proto::Gemini => {
+            for candidate in array_mut(value.get_mut("candidates")) {
+                let parts = candidate
+                    .get_mut("content")
+                    .and_then(|content| content.get_mut("parts"));
+                for part in array_mut(parts) {
+                    if is_thought(part) {
+                        continue;
+                    }
+                    if let Some(JsonValue::String(text)) = part.get_mut("text") {
+                        out.push(text);
+                    }
+                }
+            }
+        }
+    }
+    out
+}
+
+fn text_of<'a>(part: &'a mut JsonValue, kind: &str) -> Option<&'a mut String> {
+    if part.get("type").and_then(JsonValue::as_str) != Some(kind) {
+        return None;
+    }
+    match part.get_mut("text") {
+        Some(JsonValue::String(text)) => Some(text),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> Arc<PostProcessPolicy> {
+        Arc::new(PostProcessPolicy::from_config_json(&serde_json::json!({
+            "post_process": {
+                "rules": [
+                    { "type": "replace", "pattern": "secret-\\d+", "replacement": "[redacted]" },
+                    { "type": "trim_trailing_whitespace" },
+                ],
+                "window": 16,
+            },
+        })))
+    }
+
+    async fn run(proto: Proto, sse: bool, chunks: &[&str]) -> String {
+        let (tx, rx) = tokio::sync::mpsc::channel(16);
+        let out = process_stream(rx, proto, policy(), sse);
+        for chunk in chunks {
+            tx.send(Bytes::copy_from_slice(chunk.as_bytes()))
+                .await
+                .unwrap();
+        }
+        drop(tx);
+        let mut out = out;
+        let mut body = Vec::new();
+        while let Some(chunk) = out.recv().await {
+            body.extend_from_slice(&chunk);
+        }
+        String::from_utf8(body).unwrap()
+    }
+
+    fn claude_delta(text: &str) -> String {
+        let data = serde_json::json!({
+            "type": "content_block_delta",
+            "index": 0,
+            "delta": { "type": "text_delta", "text": text },
+        });
+        format!("event: content_block_delta\ndata: {data}\n\n")
+    }
+
+    #[tokio::test]
+    async fn claude_stream_text_is_processed_across_deltas() {
+        let start = "event: content_block_start\ndata: {\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"text\",\"text\":\"\"}}\n\n";
+        let stop =
+            "event: content_block_stop\ndata: {\"type\":\"content_block_stop\",\"index\":0}\n\n";
+        let deltas = [
+            claude_delta("key secr"),
+            claude_delta("et-12"),
+            claude_delta("3 done  "),
+        ];
+        let mut chunks = vec![start];
+        chunks.extend(deltas.iter().map(String::as_str));
+        chunks.push(stop);
+        let out = run(Proto::Claude, true, &chunks).await;
+
+        let texts: Vec<String> = out
+            .split("\n\n")
+            .filter_map(|ev| ev.strip_prefix("event: content_block_delta\ndata: "))
+            .map(|data| {
+                let v: JsonValue = serde_json::from_str(data).unwrap();
+                v["delta"]["text"].as_str().unwrap().to_string()
+            })
+            .collect();
+        assert_eq!(texts.len(), 3);
+        assert_eq!(texts.concat(), "key [redacted] done");
+        assert!(out.ends_with(
+            "event: content_block_stop\ndata: {\"index\":0,\"type\":\"content_block_stop\"}\n\n"
+        ));
+    }
+
+    #[tokio::test]
+    async fn chat_stream_flushes_before_done() {
+        let chunk = |content: &str| {
+            let data = serde_json::json!({
+                "choices": [{ "index": 0, "delta": { "content": content }, "finish_reason": null }],
+            });
+            format!("data: {data}\n\n")
+        };
+        let chunks = [
+            chunk("a secret-1"),
+            chunk("  "),
+            "data: [DONE]\n\n".to_string(),
+        ];
+        let chunks: Vec<&str> = chunks.iter().map(String::as_str).collect();
+        let out = run(Proto::OpenAIChat, true, &chunks).await;
+        assert!(out.contains(r#""content":"a [redacted]""#));
+        assert!(out.contains(r#""content":"""#));
+        assert!(out.ends_with("data: [DONE]\n\n"));
+    }
+
+    #[tokio::test]
+    async fn gemini_json_lines_are_processed() {
+        let line = |text: &str, finish: bool| {
+            let mut candidate = serde_json::json!({
+                "content": { "role": "model", "parts": [{ "text": text }] },
+                "index": 0,
+            });
+            if finish {
+                candidate["finishReason"] = JsonValue::from("STOP");
+            }
+            format!("{}\n", serde_json::json!({ "candidates": [candidate] }))
+        };
+        let chunks = [line("x secret-", false), line("9 \n", true)];
+        let chunks: Vec<&str> = chunks.iter().map(String::as_str).collect();
+        let out = run(Proto::Gemini, false, &chunks).await;
+        let texts: Vec<String> = out
+            .lines()
+            .map(|l| {
+                let v: JsonValue = serde_json::from_str(l).unwrap();
+                v["candidates"][0]["content"]["parts"][0]["text"]
+                    .as_str()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect();
+        assert_eq!(texts.concat(), "x [redacted]");
+        assert!(out.lines().last().unwrap().contains("STOP"));
+    }
+
+    #[test]
+    fn response_bodies_are_processed_per_protocol() {
+        let body = serde_json::json!({
+            "output": [
+                { "type": "reasoning", "summary": [] },
+                { "type": "message", "content": [
+                    { "type": "output_text", "text": "it is secret-7 \n" },
+                ] },
+            ],
+        });
+        let out = process_response(
+            Proto::OpenAIResponse,
+            &policy(),
+            body.to_string().as_bytes(),
+        )
+        .unwrap();
+        let out: JsonValue = serde_json::from_slice(&out).unwrap();
+        assert_eq!(out["output"][1]["content"][0]["text"], "it is [redacted]");
+
+        let untouched = serde_json::json!({ "content": [{ "type": "text", "text": "fine" }] });
+        assert!(
+            process_response(Proto::Claude, &policy(), untouched.to_string().as_bytes()).is_none()
+        );
+    }
+}
@@ -0,0 +1,178 @@
+//! Model profiles: named virtual models bound to a provider, an upstream model and
+//! request settings.
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Value as JsonValue, json};
+
+use gproxy_protocol::claude::count_tokens::types::Model as ClaudeModel;
+use gproxy_provider_core::{GenerateContentRequest, Request};
+use gproxy_storage::ModelProfileRow;
+
+use super::fan_out::FanOut;
+
+#[derive(Debug, Clone)]
+pub(crate) struct ModelProfile {
+    pub name: String,
+    pub model: String,
+    /// Upper bound for an explicit `temperature` sent by the client.
+    pub temperature_max: Option<f64>,
+    /// Prepended to the request's system prompt.
+    pub system_prompt: Option<String>,
+    /// Sends the request to several targets at once instead of `model`.
+    pub fan_out: Option<FanOut>,
+}
+
+impl ModelProfile {
+    pub fn from_row(row: &ModelProfileRow) -> Self {
+        let settings = &row.settings_json;
+        Self {
+            name: row.name.clone(),
+            model: row.model.clone(),
+            temperature_max: settings.get("temperature_max").and_then(JsonValue::as_f64),
+            system_prompt: settings
+                .get("system_prompt")
+                .and_then(JsonValue::as_str)
+                .filter(|s| !s.is_empty())
+                .map(str::to_string),
+            fan_out: FanOut::from_settings(settings),
+        }
+    }
+
+    /// Points a generate request at `model` without applying any profile settings.
+    pub fn retarget(req: &mut Request, model: &str) {
+        let bare = Self {
+            name: String::new(),
+            model: model.to_string(),
+            temperature_max: None,
+            system_prompt: None,
+            fan_out: None,
+        };
+        bare.apply(req);
+    }
+
+    /// Rewrites a generate request addressed to the profile name so it targets the
+    /// profile's upstream model with the profile settings applied.
+    pub fn apply(&self, req: &mut Request) {
+        let Request::GenerateContent(inner) = req else {
+            return;
+        };
+        match inner {
+            GenerateContentRequest::Claude(r) => {
+                r.body.model = ClaudeModel::Custom(self.model.clone());
+                patch_json(&mut r.body, |body| {
+                    self.clamp_temperature(body);
+                    if let Some(prompt) = &self.system_prompt {
+                        prepend_claude_system(body, prompt);
+                    }
+                });
+            }
+            GenerateContentRequest::OpenAIChat(r) => {
+                r.body.model = self.model.clone();
+                patch_json(&mut r.body, |body| {
+                    self.clamp_temperature(body);
+                    if let Some(prompt) = &self.system_prompt
+                        && let Some(JsonValue::Array(messages)) = body.get_mut("messages")
+                    {
+                        messages.insert(0, json!({ "role": "system", "content": prompt }));
+                    }
+                });
+            }
+            GenerateContentRequest::OpenAIResponse(r) => {
+                r.body.model = self.model.clone();
+                patch_json(&mut r.body, |body| self.clamp_temperature(body));
+                if let Some(prompt) = &self.system_prompt {
+                    r.body.instructions = Some(match r.body.instructions.take() {
+                        Some(existing) if !existing.is_empty() => format!("{prompt}\n\n{existing}"),
+                        _ => prompt.clone(),
+                    });
+                }
+            }
+            GenerateContentRequest::Gemini(r) => {
+                r.path.model = self.gemini_path_model(&r.path.model);
+                patch_json(&mut r.body, |body| self.patch_gemini(body));
+            }
+            GenerateContentRequest::GeminiStream(r) => {
+                r.path.model = self.gemini_path_model(&r.path.model);
+                patch_json(&mut r.body, |body| self.patch_gemini(body));
+            }
+        }
+    }
+
+    fn gemini_path_model(&self, current: &str) -> String {
+        if current.starts_with("models/") {
+            format!("models/{}", self.model)
+        } else {
+            self.model.clone()
+        }
+    }
+
+    fn clamp_temperature(&self, body: &mut JsonValue) {
+        let Some(max) = self.temperature_max else {
+            return;
+        };
+        if let Some(temperature) = body.get_mut("temperature")
+            && temperature.as_f64().is_some_and(|t| t > max)
+        {
+            *temperature = json!(max);
+        }
+    }
+
+    fn patch_gemini(&self, body: &mut JsonValue) {
+        if let Some(config) = body.get_mut("generationConfig") {
+            self.clamp_temperature(config);
+        }
+        let Some(prompt) = &self.system_prompt else {
+            return;
+        };
+        let Some(map) = body.as_object_mut() else {
+            return;
+        };
+        let part = json!({ "text": prompt });
+        match map
+            .get_mut("systemInstruction")
+            .and_then(|s| s.get_mut("parts"))
+            .and_then(JsonValue::as_array_mut)
+        {
+            Some(parts) => parts.insert(0, part),
+            None => {
+                map.insert(
+                    "systemInstruction".to_string(),
+                    json!({ "role": "user", "parts": [part] }),
+                );
+            }
+        }
+    }
+}
+
+fn prepend_claude_system(body: &mut JsonValue, prompt: &str) {
+    let Some(map) = body.as_object_mut() else {
+        return;
+    };
+    let system = match map.remove("system") {
+        Some(JsonValue::String(existing)) if !existing.is_empty() => {
+            JsonValue::String(format!("{prompt}\n\n{existing}"))
+        }
+        Some(JsonValue::Array(mut blocks)) if !blocks.is_empty() => {
+            blocks.insert(0, json!({ "type": "text", "text": prompt }));
+            JsonValue::Array(blocks)
+        }
+        _ => JsonValue::String(prompt.to_string()),
+    };
+    map.insert("system".to_string(), system);
+}
+
+/// Edits a typed body through its JSON form; the body is left untouched when the
+/// patched JSON no longer deserializes.
+pub(super) fn patch_json<T: Serialize + DeserializeOwned>(
+    body: &mut T,
+    patch: impl FnOnce(&mut JsonValue),
+) {
+    let Ok(mut value) = serde_json::to_value(&*body) else {
+        return;
+    };
+    patch(&mut value);
+    if let Ok(patched) = serde_json::from_value(value) {
+        *body = patched;
+    }
+}
@@ -0,0 +1,160 @@
+//! Finds values inside a JSON document by byte range, so single values can be replaced
+//! while every other byte stays as the upstream sent it.
+
+use std::ops::Range;
+
+use bytes::Bytes;
+
+pub(super) fn skip_ws(body: &[u8], mut at: usize) -> usize {
+    while body.get(at).is_some_and(u8::is_ascii_whitespace) {
+        at += 1;
+    }
+    at
+}
+
+/// Members of the object starting at `at`, in document order, with the byte range of each
+/// value. `None` when there is no object at `at`. Expects a document already known to be
+/// valid JSON.
+pub(super) fn object_members(body: &[u8], at: usize) -> Option<Vec<(String, Range<usize>)>> {
+    if body.get(at) != Some(&b'{') {
+        return None;
+    }
+    let mut members = Vec::new();
+    let mut at = skip_ws(body, at + 1);
+    if body.get(at) == Some(&b'}') {
+        return Some(members);
+    }
+    loop {
+        let key_end = value_end(body, at)?;
+        let key: String = serde_json::from_slice(&body[at..key_end]).ok()?;
+        at = skip_ws(body, key_end);
+        if body.get(at) != Some(&b':') {
+            return None;
+        }
+        let start = skip_ws(body, at + 1);
+        let end = value_end(body, start)?;
+        members.push((key, start..end));
+        at = skip_ws(body, end);
+        match body.get(at)? {
+            b',' => at = skip_ws(body, at + 1),
+            b'}' => return Some(members),
+            _ => return None,
+        }
+    }
+}
+
+/// Byte ranges of the elements of the array starting at `at`; `None` when there is no
+/// array at `at`.
+pub(super) fn array_elements(body: &[u8], at: usize) -> Option<Vec<Range<usize>>> {
+    if body.get(at) != Some(&b'[') {
+        return None;
+    }
+    let mut elements = Vec::new();
+    let mut at = skip_ws(body, at + 1);
+    if body.get(at) == Some(&b']') {
+        return Some(elements);
+    }
+    loop {
+        let end = value_end(body, at)?;
+        elements.push(at..end);
+        at = skip_ws(body, end);
+        match body.get(at)? {
+            b',' => at = skip_ws(body, at + 1),
+            b']' => return Some(elements),
+            _ => return None,
+        }
+    }
+}
+
+/// Copies `body` with each range replaced; ranges must be sorted and must not overlap.
+pub(super) fn splice(body: &[u8], edits: Vec<(Range<usize>, Vec<u8>)>) -> Bytes {
+    let mut out = Vec::with_capacity(body.len() + 64);
+    let mut copied = 0;
+    for (range, replacement) in edits {
+        out.extend_from_slice(&body[copied..range.start]);
+        out.extend_from_slice(&replacement);
+        copied = range.end;
+    }
+    out.extend_from_slice(&body[copied..]);
+    Bytes::from(out)
+}
+
+/// End (exclusive) of the value starting at `at`.
+fn value_end(body: &[u8], at: usize) -> Option<usize> {
+    match body.get(at)? {
+        b'"' => string_end(body, at),
+        b'{' | b'[' => {
+            let mut depth = 0usize;
+            let mut i = at;
+            while i < body.len() {
+                match body[i] {
+                    b'"' => {
+                        i = string_end(body, i)?;
+                        continue;
+                    }
+                    b'{' | b'[' => depth += 1,
+                    b'}' | b']' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Some(i + 1);
+                        }
+                    }
+                    _ => {}
+                }
+                i += 1;
+            }
+            None
+        }
+        _ => Some(
+            body[at..]
+                .iter()
+                .position(|b| matches!(b, b',' | b'}' | b']') || b.is_ascii_whitespace())
+                .map_or(body.len(), |len| at + len),
+        ),
+    }
+}
+
+fn string_end(body: &[u8], at: usize) -> Option<usize> {
+    let mut i = at + 1;
+    while i < body.len() {
+        match body[i] {
+            b'\\' => i += 2,
+            b'"' => return Some(i + 1),
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn members_and_elements_keep_their_byte_ranges() {
+        let body = br#" { "a" : [1, {"b":"]}"}, "x\"y"] ,"c":null,"d":-1.5e3 } "#;
+        let members = object_members(body, skip_ws(body, 0)).unwrap();
+        let slices: Vec<_> = members
+            .iter()
+            .map(|(key, range)| (key.as_str(), &body[range.clone()]))
+            .collect();
+        assert_eq!(
+            slices,
+            vec![
+                ("a", &br#"[1, {"b":"]}"}, "x\"y"]"#[..]),
+                ("c", b"null"),
+                ("d", b"-1.5e3"),
+            ]
+        );
+        let elements = array_elements(body, members[0].1.start).unwrap();
+        assert_eq!(elements.len(), 3);
+        assert_eq!(&body[elements[2].clone()], br#""x\"y""#);
+
+        assert!(object_members(b"[]", 0).is_none());
+        assert_eq!(object_members(b"{ }", 0), Some(Vec::new()));
+        assert_eq!(
+            &splice(body, vec![(members[1].1.clone(), b"true".to_vec())])[..],
+            &br#" { "a" : [1, {"b":"]}"}, "x\"y"] ,"c":true,"d":-1.5e3 } "#[..]
+        );
+    }
+}
@@ -0,0 +1,82 @@
+//! Size limit on non-stream upstream bodies (`max_response_body_bytes`). A typed answer
+//! over the limit is rejected, as a cut JSON body cannot be decoded; a raw passthrough
+//! answer may instead be cut to the limit when `truncate_oversize_responses` is on.
+
+use serde_json::json;
+
+use gproxy_provider_core::{UpstreamBody, UpstreamHttpResponse, header_remove, header_set};
+
+use super::json_error_with;
+
+/// Response header set on a raw passthrough answer that was cut to the size limit.
+pub const RESPONSE_TRUNCATED_HEADER: &str = "x-gproxy-response-truncated";
+
+/// The client's 502 for a body of `len` bytes when it is over `max` (0 is no limit).
+pub(crate) fn too_large(len: usize, max: u64) -> Option<UpstreamHttpResponse> {
+    (max > 0 && len as u64 > max).then(|| {
+        json_error_with(
+            502,
+            "upstream_response_too_large",
+            json!({ "bytes": len, "limit": max }),
+        )
+    })
+}
+
+/// Applies the limit to a raw passthrough answer; streams are left alone.
+pub(crate) fn limit_raw(
+    mut resp: UpstreamHttpResponse,
+    max: u64,
+    truncate: bool,
+) -> UpstreamHttpResponse {
+    let UpstreamBody::Bytes(body) = &resp.body else {
+        return resp;
+    };
+    let Some(rejected) = too_large(body.len(), max) else {
+        return resp;
+    };
+    if !truncate {
+        return rejected;
+    }
+    let body = body.slice(..usize::try_from(max).unwrap_or(usize::MAX));
+    header_remove(&mut resp.headers, "content-length");
+    header_set(&mut resp.headers, RESPONSE_TRUNCATED_HEADER, "true");
+    resp.body = UpstreamBody::Bytes(body);
+    resp
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use gproxy_provider_core::header_get;
+
+    use super::*;
+
+    fn answer(body: &'static [u8]) -> UpstreamHttpResponse {
+        UpstreamHttpResponse {
+            status: 200,
+            headers: vec![("content-length".to_string(), body.len().to_string())].into(),
+            body: UpstreamBody::Bytes(Bytes::from_static(body)),
+        }
+    }
+
+    #[test]
+    fn oversize_bodies_are_rejected_or_cut() {
+        assert!(too_large(10, 0).is_none());
+        assert!(too_large(10, 10).is_none());
+        assert_eq!(too_large(11, 10).map(|resp| resp.status), Some(502));
+
+        let kept = limit_raw(answer(b"0123456789"), 10, false);
+        assert!(matches!(kept.body, UpstreamBody::Bytes(ref b) if b.len() == 10));
+
+        assert_eq!(limit_raw(answer(b"0123456789"), 4, false).status, 502);
+
+        let cut = limit_raw(answer(b"0123456789"), 4, true);
+        assert_eq!(cut.status, 200);
+        assert_eq!(header_get(&cut.headers, "content-length"), None);
+        assert_eq!(
+            header_get(&cut.headers, RESPONSE_TRUNCATED_HEADER),
+            Some("true")
+        );
+        assert!(matches!(cut.body, UpstreamBody::Bytes(ref b) if b.as_ref() == b"0123"));
+    }
+}
@@ -0,0 +1,193 @@
+//! Semantic caching of non-stream generate requests: which text of a request is embedded,
+//! and which requests may share answers.
+//!
+//! The text of a request (messages, system prompt, instructions) is what gets embedded;
+//! everything else — model, parameters, tools, images — must match exactly, so it goes
+//! into the partition key instead.
+
+use bytes::Bytes;
+use serde_json::{Value as JsonValue, json};
+
+use gproxy_provider_core::{GenerateContentRequest, Proto, Request};
+
+/// Response header telling whether an answer came from the semantic cache (`hit`) or not
+/// (`miss`).
+pub const SEMANTIC_CACHE_HEADER: &str = "x-gproxy-semantic-cache";
+/// Response header with the similarity of a cached answer's prompt.
+pub const SEMANTIC_SIMILARITY_HEADER: &str = "x-gproxy-semantic-similarity";
+/// Requests with more prompt text than this are not cached.
+const MAX_PROMPT_CHARS: usize = 32 * 1024;
+/// Fields whose string values are prompt text.
+const TEXT_KEYS: &[&str] = &[
+    "text",
+    "content",
+    "system",
+    "instructions",
+    "input",
+    "prompt",
+    "thinking",
+];
+/// Top-level fields that identify the caller rather than shape the answer.
+const IGNORED_KEYS: &[&str] = &["metadata", "user"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct CacheKey {
+    pub partition: u64,
+    pub prompt: String,
+}
+
+/// Everything that decides whether two requests may share an answer.
+pub(super) struct Scope<'a> {
+    pub provider: &'a str,
+    pub proto: Proto,
+    pub model: Option<&'a str>,
+    pub embedding_model: &'a str,
+    /// `None` when answers are shared between keys.
+    pub user_key_id: Option<i64>,
+}
+
+/// The prompt of a generate request body and its partition; `None` when there is no text
+/// or too much of it.
+pub(super) fn cache_key(scope: &Scope<'_>, mut body: JsonValue) -> Option<CacheKey> {
+    if let Some(object) = body.as_object_mut() {
+        for key in IGNORED_KEYS {
+            object.remove(*key);
+        }
+    }
+    let mut texts = Vec::new();
+    take_texts(&mut body, &mut texts);
+    let prompt = texts.join("\n");
+    if prompt.trim().is_empty() || prompt.chars().count() > MAX_PROMPT_CHARS {
+        return None;
+    }
+    let shape = json!([
+        scope.provider,
+        scope.proto,
+        scope.model,
+        scope.embedding_model,
+        scope.user_key_id,
+        body,
+    ]);
+    Some(CacheKey {
+        partition: fnv1a(shape.to_string().as_bytes()),
+        prompt,
+    })
+}
+
+/// Moves the prompt strings out of `value`, leaving empty strings in their place.
+pub(super) fn take_texts(value: &mut JsonValue, texts: &mut Vec<String>) {
+    match value {
+        JsonValue::Object(object) => {
+            for (key, value) in object.iter_mut() {
+                match value {
+                    JsonValue::String(text) if TEXT_KEYS.contains(&key.as_str()) => {
+                        texts.push(std::mem::take(text));
+                    }
+                    value => take_texts(value, texts),
+                }
+            }
+        }
+        JsonValue::Array(items) => items.iter_mut().for_each(|item| take_texts(item, texts)),
+        _ => {}
+    }
+}
+
+/// FNV-1a; the partition is persisted, so the hash must not change between builds.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Body of a non-stream generate request as JSON.
+pub(super) fn request_body_json(req: &Request) -> Option<JsonValue> {
+    let Request::GenerateContent(req) = req else {
+        return None;
+    };
+    match req {
+        GenerateContentRequest::Claude(r) => serde_json::to_value(&r.body).ok(),
+        GenerateContentRequest::OpenAIChat(r) => serde_json::to_value(&r.body).ok(),
+        GenerateContentRequest::OpenAIResponse(r) => serde_json::to_value(&r.body).ok(),
+        GenerateContentRequest::Gemini(r) => serde_json::to_value(&r.body).ok(),
+        GenerateContentRequest::GeminiStream(_) => None,
+    }
+}
+
+/// Body of an OpenAI-compatible embeddings call for `prompt`.
+pub(super) fn embedding_request(model: &str, prompt: &str) -> Bytes {
+    let body = json!({ "model": model, "input": prompt });
+    serde_json::to_vec(&body).unwrap_or_default().into()
+}
+
+/// The vector of an OpenAI-compatible embeddings response.
+pub(super) fn parse_embedding(body: &[u8]) -> Option<Vec<f32>> {
+    let value: JsonValue = serde_json::from_slice(body).ok()?;
+    let embedding: Vec<f32> = value
+        .get("data")?
+        .get(0)?
+        .get("embedding")?
+        .as_array()?
+        .iter()
+        .map(|x| x.as_f64().map(|x| x as f32))
+        .collect::<Option<_>>()?;
+    (!embedding.is_empty()).then_some(embedding)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scope(user_key_id: Option<i64>) -> Scope<'static> {
+        Scope {
+            provider: "openai",
+            proto: Proto::OpenAIChat,
+            model: Some("gpt-4o"),
+            embedding_model: "text-embedding-3-small",
+            user_key_id,
+        }
+    }
+
+    fn chat(question: &str, temperature: f64) -> JsonValue {
+        json!({
+            "model": "gpt-4o",
+            "temperature": temperature,
+            "user": "end-user-1",
+            "messages": [
+                { "role": "system", "content": "Be brief." },
+                { "role": "user", "content": [{ "type": "text", "text": question }] },
+            ],
+        })
+    }
+
+    #[test]
+    fn prompt_text_is_embedded_and_the_rest_partitions() {
+        let a = cache_key(&scope(Some(1)), chat("What is Rust?", 0.0)).unwrap();
+        assert_eq!(a.prompt, "Be brief.\nWhat is Rust?");
+
+        let mut other_user = chat("Tell me about Rust", 0.0);
+        other_user["user"] = json!("end-user-2");
+        let b = cache_key(&scope(Some(1)), other_user).unwrap();
+        assert_eq!(a.partition, b.partition);
+
+        let hotter = cache_key(&scope(Some(1)), chat("What is Rust?", 1.0)).unwrap();
+        assert_ne!(a.partition, hotter.partition);
+        let other_key = cache_key(&scope(Some(2)), chat("What is Rust?", 0.0)).unwrap();
+        assert_ne!(a.partition, other_key.partition);
+
+        assert!(cache_key(&scope(None), chat(" ", 0.0)).is_some());
+        let empty = json!({ "model": "gpt-4o", "messages": [] });
+        assert!(cache_key(&scope(None), empty).is_none());
+        let long = chat(&"x".repeat(MAX_PROMPT_CHARS), 0.0);
+        assert!(cache_key(&scope(None), long).is_none());
+    }
+
+    #[test]
+    fn embedding_responses_are_parsed() {
+        let body = br#"{"object":"list","data":[{"index":0,"embedding":[0.5,-1,2e-1]}]}"#;
+        assert_eq!(parse_embedding(body), Some(vec![0.5, -1.0, 0.2]));
+        assert_eq!(parse_embedding(br#"{"data":[]}"#), None);
+        assert_eq!(parse_embedding(br#"{"data":[{"embedding":["x"]}]}"#), None);
+        let request: JsonValue = serde_json::from_slice(&embedding_request("m", "hi")).unwrap();
+        assert_eq!(request, json!({ "model": "m", "input": "hi" }));
+    }
+}
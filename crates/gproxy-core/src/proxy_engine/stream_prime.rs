@@ -0,0 +1,237 @@
+//! Holding a generate stream back until its first content event, so a stream that stalls
+//! or closes before producing anything can still be retried elsewhere.
+
+use std::time::Duration;
+
+use bytes::Bytes;
+
+use gproxy_protocol::sse::SseParser;
+use gproxy_provider_core::provider::{ByteStream, InternalEventUnwrap};
+use gproxy_provider_core::{Proto, UpstreamHttpResponse, header_get};
+use gproxy_transform::middleware::stream_format;
+
+use super::error_body::ERROR_CODE_HEADER;
+use super::wire::{StreamDecoder, is_content_stream_event};
+
+pub(super) enum StreamPrime {
+    /// Content arrived; the stream replays everything read so far.
+    Ready(ByteStream),
+    /// The upstream stalled or closed before any content.
+    Stalled { body: Vec<u8>, stall: StreamStall },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum StreamStall {
+    /// No content before the first-token deadline.
+    FirstToken,
+    /// No bytes at all within the stream watchdog window.
+    Idle,
+    /// The upstream closed the stream.
+    Closed,
+}
+
+impl StreamStall {
+    const ALL: [StreamStall; 3] = [
+        StreamStall::FirstToken,
+        StreamStall::Idle,
+        StreamStall::Closed,
+    ];
+
+    /// Error code of the response to a request whose stream stalled this way.
+    pub fn code(self) -> &'static str {
+        match self {
+            StreamStall::FirstToken => "upstream_first_token_timeout",
+            StreamStall::Idle => "upstream_stream_idle_timeout",
+            StreamStall::Closed => "upstream_stream_interrupted",
+        }
+    }
+}
+
+/// Whether `resp` answers a generate stream that stalled before its first token on every
+/// credential tried.
+pub(super) fn is_stall_response(resp: &UpstreamHttpResponse) -> bool {
+    header_get(&resp.headers, ERROR_CODE_HEADER)
+        .is_some_and(|code| StreamStall::ALL.iter().any(|stall| stall.code() == code))
+}
+
+/// Read an upstream stream until its first content event, buffering the raw chunks.
+pub(super) async fn prime_stream(
+    internal_unwrap: Option<InternalEventUnwrap>,
+    proto: Proto,
+    mut rx_in: ByteStream,
+    first_token_timeout: Option<Duration>,
+    idle_timeout: Option<Duration>,
+) -> StreamPrime {
+    let Some(format) = stream_format(proto) else {
+        return StreamPrime::Ready(rx_in);
+    };
+    let mut decoder = StreamDecoder::new(proto, format);
+    // Internal envelopes are unwrapped for inspection only; the buffered chunks stay raw
+    // so the regular stream path handles them unchanged.
+    let mut internal = internal_unwrap.map(|unwrap| (SseParser::new(), unwrap));
+    let deadline = first_token_timeout.map(|timeout| tokio::time::Instant::now() + timeout);
+    let mut buffered: Vec<Bytes> = Vec::new();
+    loop {
+        let past_deadline =
+            || deadline.is_some_and(|deadline| tokio::time::Instant::now() >= deadline);
+        let idle_deadline = idle_timeout.map(|idle| tokio::time::Instant::now() + idle);
+        let wait_until = match (deadline, idle_deadline) {
+            (Some(deadline), Some(idle_deadline)) => Some(deadline.min(idle_deadline)),
+            (deadline, idle_deadline) => deadline.or(idle_deadline),
+        };
+        let next = match wait_until {
+            Some(at) => tokio::time::timeout_at(at, rx_in.recv()).await,
+            None => Ok(rx_in.recv().await),
+        };
+        let chunk = match next {
+            Ok(Some(chunk)) => chunk,
+            Ok(None) | Err(_) => {
+                let stall = if past_deadline() {
+                    StreamStall::FirstToken
+                } else if next.is_err() {
+                    StreamStall::Idle
+                } else {
+                    StreamStall::Closed
+                };
+                return StreamPrime::Stalled {
+                    body: buffered.concat(),
+                    stall,
+                };
+            }
+        };
+        let events = match internal.as_mut() {
+            Some((parser, unwrap)) => {
+                let unwrap = *unwrap;
+                parser
+                    .push_bytes(&chunk)
+                    .iter()
+                    .flat_map(|ev| unwrap(&ev.data))
+                    .flat_map(|mapped| decoder.push_bytes(&mapped))
+                    .collect::<Vec<_>>()
+            }
+            None => decoder.push_bytes(&chunk),
+        };
+        buffered.push(chunk);
+        if events.iter().any(is_content_stream_event) {
+            break;
+        }
+    }
+
+    let (tx, rx) = tokio::sync::mpsc::channel::<Bytes>(32);
+    tokio::spawn(async move {
+        for chunk in buffered {
+            if tx.send(chunk).await.is_err() {
+                return;
+            }
+        }
+        loop {
+            let chunk = tokio::select! {
+                _ = tx.closed() => return,
+                chunk = rx_in.recv() => chunk,
+            };
+            let Some(chunk) = chunk else {
+                return;
+            };
+            if tx.send(chunk).await.is_err() {
+                return;
+            }
+        }
+    });
+    StreamPrime::Ready(rx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use super::super::error_body::EngineError;
+
+    const CREATED: &str = "event: response.created\ndata: {\"type\":\"response.created\",\"response\":{\"id\":\"resp_1\",\"object\":\"response\",\"created_at\":1,\"model\":\"m\"},\"sequence_number\":0}\n\n";
+    const DELTA: &str = "event: response.output_text.delta\ndata: {\"type\":\"response.output_text.delta\",\"item_id\":\"msg_1\",\"output_index\":0,\"content_index\":0,\"delta\":\"hi\",\"sequence_number\":1}\n\n";
+
+    fn upstream(chunks: &[&'static str]) -> (tokio::sync::mpsc::Sender<Bytes>, ByteStream) {
+        let (tx, rx) = tokio::sync::mpsc::channel(8);
+        for chunk in chunks {
+            tx.try_send(Bytes::from_static(chunk.as_bytes())).unwrap();
+        }
+        (tx, rx)
+    }
+
+    async fn prime(rx: ByteStream, first_token: Option<u64>, idle: Option<u64>) -> StreamPrime {
+        prime_stream(
+            None,
+            Proto::OpenAIResponse,
+            rx,
+            first_token.map(Duration::from_millis),
+            idle.map(Duration::from_millis),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn no_content_before_the_deadline_is_a_first_token_stall() {
+        // Bytes keep arriving, so only the first-token deadline fires.
+        let (tx, rx) = upstream(&[CREATED]);
+        let keep_alive = tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                if tx.send(Bytes::from_static(b": ping\n\n")).await.is_err() {
+                    return;
+                }
+            }
+        });
+        let StreamPrime::Stalled { body, stall } = prime(rx, Some(200), Some(100)).await else {
+            panic!("expected a stall");
+        };
+        keep_alive.abort();
+        assert_eq!(stall, StreamStall::FirstToken);
+        assert!(body.starts_with(CREATED.as_bytes()));
+    }
+
+    #[tokio::test]
+    async fn silent_upstream_is_an_idle_stall() {
+        let (_tx, rx) = upstream(&[CREATED]);
+        let StreamPrime::Stalled { stall, .. } = prime(rx, Some(10_000), Some(50)).await else {
+            panic!("expected a stall");
+        };
+        assert_eq!(stall, StreamStall::Idle);
+    }
+
+    #[tokio::test]
+    async fn close_before_content_is_a_closed_stall() {
+        let (tx, rx) = upstream(&[CREATED]);
+        drop(tx);
+        let StreamPrime::Stalled { body, stall } = prime(rx, Some(10_000), None).await else {
+            panic!("expected a stall");
+        };
+        assert_eq!(stall, StreamStall::Closed);
+        assert_eq!(body, CREATED.as_bytes());
+    }
+
+    #[tokio::test]
+    async fn first_content_replays_the_buffered_stream() {
+        let (tx, rx) = upstream(&[CREATED, DELTA]);
+        let StreamPrime::Ready(mut rx) = prime(rx, Some(10_000), Some(10_000)).await else {
+            panic!("expected a ready stream");
+        };
+        tx.send(Bytes::from_static(b"tail")).await.unwrap();
+        drop(tx);
+        let mut replayed = Vec::new();
+        while let Some(chunk) = rx.recv().await {
+            replayed.extend_from_slice(&chunk);
+        }
+        assert_eq!(replayed, format!("{CREATED}{DELTA}tail").as_bytes());
+    }
+
+    #[test]
+    fn stall_responses_are_recognized_by_code() {
+        for stall in StreamStall::ALL {
+            let resp =
+                EngineError::new(504, stall.code(), serde_json::Value::Null).into_response(None);
+            assert!(is_stall_response(&resp));
+        }
+        let resp =
+            EngineError::new(504, "upstream_timeout", serde_json::Value::Null).into_response(None);
+        assert!(!is_stall_response(&resp));
+    }
+}
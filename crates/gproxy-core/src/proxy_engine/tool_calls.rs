@@ -0,0 +1,439 @@
+//! Client-side tool/function calls in generate responses, and the tool results that later
+//! requests send back for them.
+//!
+//! Calls are read from what the client receives, so `proto` is always the client's
+//! protocol. Only SSE streams are observed; Gemini's JSON-array streams are not.
+
+use std::collections::BTreeMap;
+
+use bytes::Bytes;
+use serde_json::Value as JsonValue;
+
+use gproxy_protocol::sse::SseParser;
+use gproxy_provider_core::provider::ByteStream;
+use gproxy_provider_core::{GenerateContentRequest, Request};
+use gproxy_transform::middleware::Proto;
+
+/// A tool call the model asked the client to run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct ToolCall {
+    pub name: String,
+    /// Id the client sends back with the result; Gemini often leaves it out.
+    pub call_id: Option<String>,
+    /// Length of the arguments as JSON text.
+    pub arguments_bytes: usize,
+}
+
+fn text(value: &JsonValue, key: &str) -> Option<String> {
+    value.get(key)?.as_str().map(str::to_string)
+}
+
+fn items<'a>(value: &'a JsonValue, key: &str) -> impl Iterator<Item = &'a JsonValue> {
+    value
+        .get(key)
+        .and_then(JsonValue::as_array)
+        .into_iter()
+        .flatten()
+}
+
+/// Size of arguments that are either JSON text already or a JSON value.
+fn arguments_len(value: Option<&JsonValue>) -> usize {
+    match value {
+        None | Some(JsonValue::Null) => 0,
+        Some(JsonValue::String(text)) => text.len(),
+        Some(other) => other.to_string().len(),
+    }
+}
+
+fn claude_block(block: &JsonValue) -> Option<ToolCall> {
+    if block.get("type")?.as_str()? != "tool_use" {
+        return None;
+    }
+    Some(ToolCall {
+        name: text(block, "name")?,
+        call_id: text(block, "id"),
+        arguments_bytes: arguments_len(block.get("input")),
+    })
+}
+
+fn chat_call(call: &JsonValue) -> Option<ToolCall> {
+    let (function, arguments) = match call.get("custom") {
+        Some(custom) => (custom, "input"),
+        None => (call.get("function")?, "arguments"),
+    };
+    Some(ToolCall {
+        name: text(function, "name")?,
+        call_id: text(call, "id"),
+        arguments_bytes: arguments_len(function.get(arguments)),
+    })
+}
+
+fn responses_item(item: &JsonValue) -> Option<ToolCall> {
+    let arguments = match item.get("type")?.as_str()? {
+        "function_call" => "arguments",
+        "custom_tool_call" => "input",
+        _ => return None,
+    };
+    Some(ToolCall {
+        name: text(item, "name")?,
+        call_id: text(item, "call_id"),
+        arguments_bytes: arguments_len(item.get(arguments)),
+    })
+}
+
+fn gemini_calls(value: &JsonValue) -> Vec<ToolCall> {
+    items(value, "candidates")
+        .filter_map(|candidate| candidate.get("content"))
+        .flat_map(|content| items(content, "parts"))
+        .filter_map(|part| part.get("functionCall"))
+        .filter_map(|call| {
+            Some(ToolCall {
+                name: text(call, "name")?,
+                call_id: text(call, "id"),
+                arguments_bytes: arguments_len(call.get("args")),
+            })
+        })
+        .collect()
+}
+
+/// Tool calls in a non-stream generate response body.
+pub(super) fn calls_in_response(proto: Proto, body: &[u8]) -> Vec<ToolCall> {
+    let Ok(value) = serde_json::from_slice::<JsonValue>(body) else {
+        return Vec::new();
+    };
+    match proto {
+        Proto::Claude => items(&value, "content").filter_map(claude_block).collect(),
+        Proto::OpenAIChat => items(&value, "choices")
+            .filter_map(|choice| choice.get("message"))
+            .flat_map(|message| items(message, "tool_calls"))
+            .filter_map(chat_call)
+            .collect(),
+        Proto::OpenAIResponse => items(&value, "output").filter_map(responses_item).collect(),
+        Proto::Gemini => gemini_calls(&value),
+        Proto::OpenAI => Vec::new(),
+    }
+}
+
+/// Assembles tool calls from the events of one stream. Claude and Chat Completions send a
+/// call in pieces; the others send each call whole.
+struct StreamCalls {
+    proto: Proto,
+    /// Calls still being streamed, by (choice or block index, tool index).
+    open: BTreeMap<(u64, u64), ToolCall>,
+}
+
+impl StreamCalls {
+    fn new(proto: Proto) -> Self {
+        Self {
+            proto,
+            open: BTreeMap::new(),
+        }
+    }
+
+    /// Calls finished by the event with this `data`.
+    fn push(&mut self, data: &str) -> Vec<ToolCall> {
+        let Ok(value) = serde_json::from_str::<JsonValue>(data) else {
+            return Vec::new();
+        };
+        let index = |value: &JsonValue| value.get("index").and_then(JsonValue::as_u64);
+        match self.proto {
+            Proto::Claude => {
+                let Some(block) = index(&value) else {
+                    return Vec::new();
+                };
+                match value.get("type").and_then(JsonValue::as_str) {
+                    Some("content_block_start") => {
+                        if let Some(call) = value.get("content_block").and_then(claude_block) {
+                            // The input arrives in deltas; the start block carries `{}`.
+                            let call = ToolCall {
+                                arguments_bytes: 0,
+                                ..call
+                            };
+                            self.open.insert((block, 0), call);
+                        }
+                        Vec::new()
+                    }
+                    Some("content_block_delta") => {
+                        if let Some(call) = self.open.get_mut(&(block, 0))
+                            && let Some(partial) = value
+                                .get("delta")
+                                .and_then(|delta| delta.get("partial_json"))
+                                .and_then(JsonValue::as_str)
+                        {
+                            call.arguments_bytes += partial.len();
+                        }
+                        Vec::new()
+                    }
+                    Some("content_block_stop") => {
+                        self.open.remove(&(block, 0)).into_iter().collect()
+                    }
+                    _ => Vec::new(),
+                }
+            }
+            Proto::OpenAIChat => {
+                let mut done = Vec::new();
+                for choice in items(&value, "choices") {
+                    let choice_index = index(choice).unwrap_or(0);
+                    let deltas = choice
+                        .get("delta")
+                        .map(|delta| items(delta, "tool_calls"))
+                        .into_iter()
+                        .flatten();
+                    for delta in deltas {
+                        let key = (choice_index, index(delta).unwrap_or(0));
+                        if let Some(call) = self.open.get_mut(&key) {
+                            let piece = match delta.get("custom") {
+                                Some(custom) => custom.get("input"),
+                                None => delta.get("function").and_then(|f| f.get("arguments")),
+                            };
+                            call.arguments_bytes += arguments_len(piece);
+                        } else if let Some(call) = chat_call(delta) {
+                            self.open.insert(key, call);
+                        }
+                    }
+                    if choice
+                        .get("finish_reason")
+                        .is_some_and(|reason| !reason.is_null())
+                    {
+                        let finished: Vec<_> = self
+                            .open
+                            .keys()
+                            .filter(|(choice, _)| *choice == choice_index)
+                            .copied()
+                            .collect();
+                        done.extend(finished.iter().filter_map(|key| self.open.remove(key)));
+                    }
+                }
+                done
+            }
+            Proto::OpenAIResponse => {
+                if value.get("type").and_then(JsonValue::as_str)
+                    != Some("response.output_item.done")
+                {
+                    return Vec::new();
+                }
+                value
+                    .get("item")
+                    .and_then(responses_item)
+                    .into_iter()
+                    .collect()
+            }
+            Proto::Gemini => gemini_calls(&value),
+            Proto::OpenAI => Vec::new(),
+        }
+    }
+
+    /// Calls a truncated stream left open.
+    fn finish(self) -> Vec<ToolCall> {
+        self.open.into_values().collect()
+    }
+}
+
+/// Forwards an SSE stream unchanged, passing each finished tool call to `on_call`.
+pub(super) fn observe_stream(
+    mut rx: ByteStream,
+    proto: Proto,
+    on_call: impl Fn(ToolCall) + Send + 'static,
+) -> ByteStream {
+    let (tx, rx_out) = tokio::sync::mpsc::channel::<Bytes>(32);
+    tokio::spawn(async move {
+        let mut parser = SseParser::new();
+        let mut calls = StreamCalls::new(proto);
+        while let Some(chunk) = rx.recv().await {
+            for ev in parser.push_bytes(&chunk) {
+                calls.push(&ev.data).into_iter().for_each(&on_call);
+            }
+            if tx.send(chunk).await.is_err() {
+                return;
+            }
+        }
+        for ev in parser.finish() {
+            calls.push(&ev.data).into_iter().for_each(&on_call);
+        }
+        calls.finish().into_iter().for_each(&on_call);
+    });
+    rx_out
+}
+
+/// Ids of the tool calls whose results a generate request sends back.
+pub(super) fn result_ids(req: &Request) -> Vec<String> {
+    let Request::GenerateContent(req) = req else {
+        return Vec::new();
+    };
+    let body = match req {
+        GenerateContentRequest::Claude(r) => serde_json::to_value(&r.body),
+        GenerateContentRequest::OpenAIChat(r) => serde_json::to_value(&r.body),
+        GenerateContentRequest::OpenAIResponse(r) => serde_json::to_value(&r.body),
+        GenerateContentRequest::Gemini(r) => serde_json::to_value(&r.body),
+        GenerateContentRequest::GeminiStream(r) => serde_json::to_value(&r.body),
+    };
+    let Ok(body) = body else {
+        return Vec::new();
+    };
+    match req {
+        GenerateContentRequest::Claude(_) => items(&body, "messages")
+            .flat_map(|message| items(message, "content"))
+            .filter(|block| block.get("type").and_then(JsonValue::as_str) == Some("tool_result"))
+            .filter_map(|block| text(block, "tool_use_id"))
+            .collect(),
+        GenerateContentRequest::OpenAIChat(_) => items(&body, "messages")
+            .filter(|message| message.get("role").and_then(JsonValue::as_str) == Some("tool"))
+            .filter_map(|message| text(message, "tool_call_id"))
+            .collect(),
+        GenerateContentRequest::OpenAIResponse(_) => items(&body, "input")
+            .filter(|item| {
+                matches!(
+                    item.get("type").and_then(JsonValue::as_str),
+                    Some("function_call_output" | "custom_tool_call_output")
+                )
+            })
+            .filter_map(|item| text(item, "call_id"))
+            .collect(),
+        GenerateContentRequest::Gemini(_) | GenerateContentRequest::GeminiStream(_) => {
+            items(&body, "contents")
+                .flat_map(|content| items(content, "parts"))
+                .filter_map(|part| part.get("functionResponse"))
+                .filter_map(|response| text(response, "id"))
+                .collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn call(name: &str, call_id: Option<&str>, arguments_bytes: usize) -> ToolCall {
+        ToolCall {
+            name: name.to_string(),
+            call_id: call_id.map(str::to_string),
+            arguments_bytes,
+        }
+    }
+
+    #[test]
+    fn calls_are_read_from_each_response_shape() {
+        let claude = json!({
+            "content": [
+                { "type": "text", "text": "checking" },
+                { "type": "tool_use", "id": "toolu_1", "name": "get_weather", "input": { "city": "Oslo" } },
+            ],
+        });
+        assert_eq!(
+            calls_in_response(Proto::Claude, claude.to_string().as_bytes()),
+            vec![call("get_weather", Some("toolu_1"), 15)]
+        );
+
+        let chat = json!({
+            "choices": [{
+                "index": 0,
+                "message": {
+                    "role": "assistant",
+                    "tool_calls": [{
+                        "id": "call_1",
+                        "type": "function",
+                        "function": { "name": "search", "arguments": "{\"q\":\"rust\"}" },
+                    }],
+                },
+            }],
+        });
+        assert_eq!(
+            calls_in_response(Proto::OpenAIChat, chat.to_string().as_bytes()),
+            vec![call("search", Some("call_1"), 12)]
+        );
+
+        let responses = json!({
+            "output": [
+                { "type": "function_call", "call_id": "call_2", "name": "search", "arguments": "{}" },
+                { "type": "mcp_call", "name": "remote", "server_label": "docs" },
+            ],
+        });
+        assert_eq!(
+            calls_in_response(Proto::OpenAIResponse, responses.to_string().as_bytes()),
+            vec![call("search", Some("call_2"), 2)]
+        );
+
+        let gemini = json!({
+            "candidates": [{
+                "content": { "parts": [{ "functionCall": { "name": "lookup", "args": {} } }] },
+            }],
+        });
+        assert_eq!(
+            calls_in_response(Proto::Gemini, gemini.to_string().as_bytes()),
+            vec![call("lookup", None, 2)]
+        );
+    }
+
+    #[test]
+    fn streamed_calls_are_assembled_from_their_pieces() {
+        let mut claude = StreamCalls::new(Proto::Claude);
+        let events = [
+            json!({ "type": "content_block_start", "index": 1, "content_block": { "type": "tool_use", "id": "toolu_1", "name": "get_weather", "input": {} } }),
+            json!({ "type": "content_block_delta", "index": 1, "delta": { "type": "input_json_delta", "partial_json": "{\"city\":" } }),
+            json!({ "type": "content_block_delta", "index": 1, "delta": { "type": "input_json_delta", "partial_json": "\"Oslo\"}" } }),
+        ];
+        for event in events {
+            assert!(claude.push(&event.to_string()).is_empty());
+        }
+        assert_eq!(
+            claude.push(r#"{"type":"content_block_stop","index":1}"#),
+            vec![call("get_weather", Some("toolu_1"), 15)]
+        );
+
+        let mut chat = StreamCalls::new(Proto::OpenAIChat);
+        let start = json!({ "choices": [{ "index": 0, "delta": { "tool_calls": [
+            { "index": 0, "id": "call_1", "type": "function", "function": { "name": "search", "arguments": "" } },
+        ] } }] });
+        let delta = json!({ "choices": [{ "index": 0, "delta": { "tool_calls": [
+            { "index": 0, "function": { "arguments": "{\"q\":1}" } },
+        ] } }] });
+        let end =
+            json!({ "choices": [{ "index": 0, "delta": {}, "finish_reason": "tool_calls" }] });
+        assert!(chat.push(&start.to_string()).is_empty());
+        assert!(chat.push(&delta.to_string()).is_empty());
+        assert_eq!(
+            chat.push(&end.to_string()),
+            vec![call("search", Some("call_1"), 7)]
+        );
+
+        let mut cut = StreamCalls::new(Proto::OpenAIChat);
+        cut.push(&start.to_string());
+        assert_eq!(cut.finish(), vec![call("search", Some("call_1"), 0)]);
+    }
+
+    #[test]
+    fn result_ids_are_read_from_follow_up_requests() {
+        let req = Request::GenerateContent(GenerateContentRequest::OpenAIChat(
+            gproxy_protocol::openai::create_chat_completions::request::CreateChatCompletionRequest {
+                body: serde_json::from_value(json!({
+                    "model": "gpt-4.1",
+                    "messages": [
+                        { "role": "user", "content": "weather?" },
+                        { "role": "assistant", "tool_calls": [{
+                            "id": "call_1",
+                            "type": "function",
+                            "function": { "name": "get_weather", "arguments": "{}" },
+                        }] },
+                        { "role": "tool", "tool_call_id": "call_1", "content": "sunny" },
+                    ],
+                }))
+                .unwrap(),
+            },
+        ));
+        assert_eq!(result_ids(&req), vec!["call_1".to_string()]);
+
+        let req = Request::GenerateContent(GenerateContentRequest::OpenAIResponse(
+            gproxy_protocol::openai::create_response::request::CreateResponseRequest {
+                body: serde_json::from_value(json!({
+                    "model": "gpt-4.1",
+                    "input": [
+                        { "type": "function_call_output", "call_id": "call_2", "output": "ok" },
+                    ],
+                }))
+                .unwrap(),
+            },
+        ));
+        assert_eq!(result_ids(&req), vec!["call_2".to_string()]);
+    }
+}
@@ -0,0 +1,437 @@
+//! Per-stream state of a generate stream relayed to the client: the upstream decoder, usage
+//! and output accounting, the resume cursor, the event framing of verbatim streams and, for
+//! cross-protocol streams, the event transformer. Cross-protocol streams move it to the transcoder pool for every chunk.
+
+use bytes::Bytes;
+
+use gproxy_provider_core::{
+    Op, OutputAccumulator, Proto, StreamEvent, StreamFormat, TransformContext, UsageAccumulator,
+};
+use gproxy_transform::middleware::StreamTransformer;
+
+use super::wire::{
+    SseEventFramer, StreamDecoder, StreamResumeCursor, encode_stream_event,
+    is_terminal_stream_event,
+};
+use super::{ModelRewrite, STREAM_IDLE_TIMEOUT, maybe_prefix_model_in_stream_event};
+
+/// What follows once the upstream stream stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum StreamEnd {
+    /// The stream completed, or failed in a way that needs no error event (the client went
+    /// away, an event failed to convert).
+    Done,
+    /// The stream was cut off before anything reached the client: reissue the request.
+    Retry,
+    /// The stream was cut off: close it with this error event.
+    Fail {
+        status: u16,
+        code: &'static str,
+        message: &'static str,
+    },
+}
+
+pub(super) struct StreamTranscode {
+    provider_proto: Proto,
+    user_proto: Proto,
+    format: StreamFormat,
+    decoder: StreamDecoder,
+    framer: SseEventFramer,
+    transformer: Option<StreamTransformer>,
+    model_rewrite: ModelRewrite,
+    pub usage: UsageAccumulator,
+    pub output: OutputAccumulator,
+    pub resume_cursor: StreamResumeCursor,
+    pub terminal_seen: bool,
+    /// `sequence_number` of the last OpenAI Responses event sent to the client.
+    last_sequence_number: Option<i64>,
+}
+
+impl StreamTranscode {
+    pub fn new(
+        provider_proto: Proto,
+        user_proto: Proto,
+        format: StreamFormat,
+        model_rewrite: ModelRewrite,
+    ) -> Self {
+        let transformer = if provider_proto == user_proto {
+            None
+        } else {
+            let ctx = TransformContext {
+                src: provider_proto,
+                dst: user_proto,
+                src_op: Op::StreamGenerateContent,
+                dst_op: Op::StreamGenerateContent,
+            };
+            StreamTransformer::new(&ctx).ok()
+        };
+        Self {
+            provider_proto,
+            user_proto,
+            format,
+            decoder: StreamDecoder::new(provider_proto, format),
+            framer: SseEventFramer::default(),
+            transformer,
+            model_rewrite,
+            usage: UsageAccumulator::new(provider_proto),
+            output: OutputAccumulator::new(provider_proto),
+            resume_cursor: StreamResumeCursor::default(),
+            terminal_seen: false,
+            last_sequence_number: None,
+        }
+    }
+
+    /// Whether events are converted to another protocol; such chunks go to the pool.
+    pub fn converts(&self) -> bool {
+        self.transformer.is_some()
+    }
+
+    /// Starts decoding a resumed upstream stream. A partial event held back from the
+    /// interrupted one is dropped: the resumed stream replays it whole.
+    pub fn restart_decoder(&mut self) {
+        self.decoder = StreamDecoder::new(self.provider_proto, self.format);
+        self.framer.clear();
+    }
+
+    /// Accounts a chunk forwarded verbatim and returns the complete events it finishes;
+    /// the rest is held back until its event ends.
+    pub fn passthrough(&mut self, chunk: &Bytes) -> Option<Bytes> {
+        let complete = self.framer.push(chunk)?;
+        for ev in self.decoder.push_bytes(&complete) {
+            self.resume_cursor.observe(&ev);
+            self.account(&ev);
+            self.note_forwarded(&ev);
+        }
+        Some(complete)
+    }
+
+    /// Bytes held back when a verbatim stream closes. A last event the upstream left
+    /// unterminated is completed and returned; a partial one, cut off mid-event, is
+    /// dropped so whatever follows (a resumed stream, an error event) starts on an event
+    /// boundary.
+    pub fn passthrough_tail(&mut self) -> Option<Bytes> {
+        let rest = self.framer.take_rest();
+        if rest.is_empty() {
+            return None;
+        }
+        let mut events = self.decoder.push_bytes(&rest);
+        events.extend(self.decoder.finish());
+        if events.is_empty() && !self.terminal_seen {
+            return None;
+        }
+        for ev in &events {
+            self.resume_cursor.observe(ev);
+            self.account(ev);
+            self.note_forwarded(ev);
+        }
+        let mut tail = rest.to_vec();
+        tail.extend_from_slice(b"\n\n");
+        Some(Bytes::from(tail))
+    }
+
+    /// Decodes a chunk and encodes its events for the client. On a transform failure the
+    /// bytes encoded before it are returned with the error.
+    pub fn transcode(&mut self, chunk: &Bytes) -> (Vec<Bytes>, Option<String>) {
+        let events = self.decoder.push_bytes(chunk);
+        let mut out = Vec::new();
+        for ev in events {
+            self.resume_cursor.observe(&ev);
+            if let Err(err) = self.relay(ev, &mut out) {
+                return (out, Some(err));
+            }
+        }
+        (out, None)
+    }
+
+    /// Flushes the decoder at the end of the upstream stream. `passthrough` streams only
+    /// account the tail.
+    pub fn finish(&mut self, passthrough: bool) -> (Vec<Bytes>, Option<String>) {
+        let events = self.decoder.finish();
+        let mut out = Vec::new();
+        for ev in events {
+            if passthrough {
+                self.account(&ev);
+                continue;
+            }
+            if let Err(err) = self.relay(ev, &mut out) {
+                return (out, Some(err));
+            }
+        }
+        (out, None)
+    }
+
+    /// `sequence_number` for an event appended after everything sent so far.
+    pub fn next_sequence_number(&self) -> i64 {
+        self.last_sequence_number.map_or(0, |n| n + 1)
+    }
+
+    /// How the stream goes on once the upstream stopped with `error_kind` (`None` for a
+    /// close). A stream cut off before its terminal event or gone idle is retried while
+    /// the client has seen nothing and a retry is left, else closed with an error event.
+    pub fn end(
+        &self,
+        error_kind: Option<&str>,
+        retry_left: bool,
+        forwarded_any: bool,
+    ) -> StreamEnd {
+        let idle = error_kind == Some(STREAM_IDLE_TIMEOUT);
+        let interrupted = error_kind.is_none() && !self.terminal_seen;
+        if !idle && !interrupted {
+            return StreamEnd::Done;
+        }
+        if retry_left && !forwarded_any {
+            return StreamEnd::Retry;
+        }
+        if idle {
+            StreamEnd::Fail {
+                status: 504,
+                code: "upstream_stream_idle_timeout",
+                message: "upstream stream went idle",
+            }
+        } else {
+            StreamEnd::Fail {
+                status: 502,
+                code: "upstream_stream_interrupted",
+                message: "upstream stream ended before completion",
+            }
+        }
+    }
+
+    fn account(&mut self, ev: &StreamEvent) {
+        self.terminal_seen |= is_terminal_stream_event(ev);
+        let _ = self.usage.push(ev);
+        self.output.push(ev);
+    }
+
+    fn relay(&mut self, ev: StreamEvent, out: &mut Vec<Bytes>) -> Result<(), String> {
+        self.account(&ev);
+        let events = match self.transformer.as_mut() {
+            Some(t) => t.push(ev).map_err(|err| format!("{err:?}"))?,
+            None => vec![ev],
+        };
+        for ev in events {
+            let ev = maybe_prefix_model_in_stream_event(ev, &self.model_rewrite);
+            if let Some(bytes) = encode_stream_event(self.user_proto, &ev) {
+                self.note_forwarded(&ev);
+                out.push(bytes);
+            }
+        }
+        Ok(())
+    }
+
+    fn note_forwarded(&mut self, ev: &StreamEvent) {
+        if let StreamEvent::OpenAIResponse(ev) = ev {
+            self.last_sequence_number = Some(ev.sequence_number());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use gproxy_provider_core::{Request, ResponseGetRequest};
+
+    use super::super::wire::{StreamDecoder, encode_stream_error};
+
+    fn event(name: &str, data: &str) -> String {
+        format!("event: {name}\ndata: {{\"type\":\"{name}\",{data}}}\n\n")
+    }
+
+    fn response(name: &str, seq: i64) -> String {
+        event(
+            name,
+            &format!(
+                "\"response\":{{\"id\":\"resp_1\",\"object\":\"response\",\"created_at\":1,\"model\":\"m\",\"background\":true}},\"sequence_number\":{seq}"
+            ),
+        )
+    }
+
+    fn text_delta(seq: i64) -> String {
+        event(
+            "response.output_text.delta",
+            &format!(
+                "\"item_id\":\"msg_1\",\"output_index\":0,\"content_index\":0,\"delta\":\"x\",\"sequence_number\":{seq}"
+            ),
+        )
+    }
+
+    fn passthrough() -> StreamTranscode {
+        StreamTranscode::new(
+            Proto::OpenAIResponse,
+            Proto::OpenAIResponse,
+            StreamFormat::SseNamedEvent,
+            ModelRewrite {
+                prefix_provider: None,
+                alias: None,
+            },
+        )
+    }
+
+    fn sequence_numbers(sse: &[u8]) -> Vec<i64> {
+        let mut decoder = StreamDecoder::new(Proto::OpenAIResponse, StreamFormat::SseNamedEvent);
+        let mut events = decoder.push_bytes(&Bytes::copy_from_slice(sse));
+        events.extend(decoder.finish());
+        assert_eq!(decoder.skipped(), 0);
+        events
+            .iter()
+            .map(|ev| match ev {
+                StreamEvent::OpenAIResponse(ev) => ev.sequence_number(),
+                _ => panic!("unexpected event"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn resumed_passthrough_replays_the_cut_event_whole() {
+        let mut tc = passthrough();
+        let mut client = Vec::new();
+        let first = format!(
+            "{}{}{}",
+            response("response.created", 0),
+            text_delta(1),
+            text_delta(2)
+        );
+        // The upstream drops the connection halfway through the third event.
+        let cut = first.len() - 20;
+        for chunk in [&first[..40], &first[40..cut]] {
+            if let Some(bytes) = tc.passthrough(&Bytes::copy_from_slice(chunk.as_bytes())) {
+                client.extend_from_slice(&bytes);
+            }
+        }
+        assert_eq!(tc.passthrough_tail(), None);
+        assert!(!tc.terminal_seen);
+
+        let Some(Request::ResponseGet(ResponseGetRequest::OpenAI(resume))) =
+            tc.resume_cursor.resume_request()
+        else {
+            panic!("expected a resume request");
+        };
+        assert_eq!(resume.query.starting_after, Some(1));
+
+        tc.restart_decoder();
+        let replay = format!("{}{}", text_delta(2), response("response.completed", 3));
+        if let Some(bytes) = tc.passthrough(&Bytes::from(replay)) {
+            client.extend_from_slice(&bytes);
+        }
+        assert_eq!(tc.passthrough_tail(), None);
+        assert!(tc.terminal_seen);
+        assert_eq!(sequence_numbers(&client), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn passthrough_completes_an_unterminated_last_event() {
+        let mut tc = passthrough();
+        let mut client = Vec::new();
+        let sse = format!(
+            "{}{}",
+            response("response.created", 0),
+            response("response.completed", 1)
+        );
+        let sse = sse.trim_end();
+        if let Some(bytes) = tc.passthrough(&Bytes::copy_from_slice(sse.as_bytes())) {
+            client.extend_from_slice(&bytes);
+        }
+        assert!(!tc.terminal_seen);
+        client.extend_from_slice(&tc.passthrough_tail().expect("last event"));
+        assert!(tc.terminal_seen);
+        assert!(client.ends_with(b"\n\n"));
+        assert_eq!(sequence_numbers(&client), vec![0, 1]);
+    }
+
+    #[test]
+    fn stream_end_retries_only_before_output() {
+        let mut tc = passthrough();
+        assert_eq!(tc.end(None, true, false), StreamEnd::Retry);
+        assert_eq!(
+            tc.end(Some(STREAM_IDLE_TIMEOUT), true, false),
+            StreamEnd::Retry
+        );
+        let interrupted = StreamEnd::Fail {
+            status: 502,
+            code: "upstream_stream_interrupted",
+            message: "upstream stream ended before completion",
+        };
+        assert_eq!(tc.end(None, true, true), interrupted);
+        assert_eq!(tc.end(None, false, false), interrupted);
+        assert!(matches!(
+            tc.end(Some(STREAM_IDLE_TIMEOUT), true, true),
+            StreamEnd::Fail { status: 504, .. }
+        ));
+        // The client already saw why the stream stopped.
+        assert_eq!(
+            tc.end(Some("stream_forward_error"), true, false),
+            StreamEnd::Done
+        );
+
+        tc.terminal_seen = true;
+        assert_eq!(tc.end(None, true, false), StreamEnd::Done);
+        assert_eq!(tc.end(None, false, true), StreamEnd::Done);
+    }
+
+    #[test]
+    fn interrupted_passthrough_ends_with_a_whole_error_event() {
+        let mut tc = passthrough();
+        assert_eq!(tc.next_sequence_number(), 0);
+        let mut client = Vec::new();
+        let sse = format!(
+            "{}{}{}",
+            response("response.created", 0),
+            text_delta(1),
+            text_delta(2)
+        );
+        let cut = &sse[..sse.len() - 10];
+        if let Some(bytes) = tc.passthrough(&Bytes::copy_from_slice(cut.as_bytes())) {
+            client.extend_from_slice(&bytes);
+        }
+        assert_eq!(tc.passthrough_tail(), None);
+        let StreamEnd::Fail {
+            status,
+            code,
+            message,
+        } = tc.end(None, true, true)
+        else {
+            panic!("expected an error event");
+        };
+        client.extend_from_slice(&encode_stream_error(
+            Proto::OpenAIResponse,
+            status,
+            code,
+            message,
+            tc.next_sequence_number(),
+        ));
+        assert_eq!(sequence_numbers(&client), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn converted_streams_number_errors_after_the_last_sent_event() {
+        let mut tc = StreamTranscode::new(
+            Proto::Claude,
+            Proto::OpenAIResponse,
+            StreamFormat::SseNamedEvent,
+            ModelRewrite {
+                prefix_provider: None,
+                alias: None,
+            },
+        );
+        let claude = concat!(
+            "event: message_start\ndata: {\"type\":\"message_start\",\"message\":{\"id\":\"msg_1\",\"type\":\"message\",\"role\":\"assistant\",\"model\":\"m\",\"content\":[],\"stop_reason\":null,\"stop_sequence\":null,\"usage\":{\"input_tokens\":1,\"output_tokens\":0}}}\n\n",
+            "event: content_block_start\ndata: {\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"text\",\"text\":\"\"}}\n\n",
+            "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"hi\"}}\n\n",
+        );
+        let (encoded, failure) = tc.transcode(&Bytes::from_static(claude.as_bytes()));
+        assert_eq!(failure, None);
+        let mut client = encoded.concat();
+        let sent = sequence_numbers(&client);
+        assert!(!sent.is_empty());
+        client.extend_from_slice(&encode_stream_error(
+            Proto::OpenAIResponse,
+            502,
+            "upstream_stream_interrupted",
+            "cut",
+            tc.next_sequence_number(),
+        ));
+        let all = sequence_numbers(&client);
+        assert_eq!(all.last(), Some(&(sent.last().unwrap() + 1)));
+    }
+}
@@ -1,10 +1,60 @@
-use gproxy_provider_core::{OAuthCallbackRequest, OAuthStartRequest, Op, Proto, Request};
+use std::sync::Arc;
+use std::time::Instant;
+
+use gproxy_provider_core::{
+    CallerInfo, Headers, OAuthCallbackRequest, OAuthStartRequest, Op, Proto, RawPassthroughRequest,
+    Request,
+};
+
+use crate::state::KeyLimits;
+
+use super::key_scope::KeyScope;
+use super::mcp::McpPolicy;
+use super::moderation::ModerationPolicy;
 
 #[derive(Debug, Clone)]
 pub struct ProxyAuth {
     pub user_id: i64,
     pub user_key_id: i64,
+    pub org_id: Option<i64>,
     pub user_agent: Option<String>,
+    /// Request tags propagated to upstream events and usage rows.
+    pub tags: Vec<String>,
+    /// Downstream request headers (auth stripped), forwarded per provider header policy.
+    pub request_headers: Headers,
+    /// Per-minute limits configured on the key.
+    pub rate_limits: KeyLimits,
+    /// Output tokens per second the key's streams are paced to.
+    pub stream_tps: Option<u32>,
+    /// Cap on output tokens per request configured on the key.
+    pub max_output_tokens: Option<u32>,
+    /// When the downstream request arrived.
+    pub received_at: Instant,
+    /// Model named by the request; filled in by the engine once the request is parsed.
+    pub model: Option<String>,
+    /// `user_proto->provider_proto` pair the engine converts the request through; filled in
+    /// once the provider's dispatch table is resolved.
+    pub transform: Option<String>,
+    /// Provider for aggregate routes when the model carries no `provider/` prefix.
+    pub default_provider: Option<String>,
+    /// Model for aggregate routes when the request leaves it empty.
+    pub default_model: Option<String>,
+    /// A/B experiment arm picked for this request; recorded on usage rows.
+    pub experiment: Option<ExperimentAssignment>,
+    /// MCP servers the key may declare in Responses requests; `None` allows any.
+    pub mcp_policy: Option<Arc<McpPolicy>>,
+    /// How the key's generate requests are moderated; `None` leaves them alone.
+    pub moderation_policy: Option<Arc<ModerationPolicy>>,
+    /// Providers and models the key may call; `None` allows all.
+    pub key_scope: Option<Arc<KeyScope>>,
+    /// Key label, organization name and prelude override handed to providers.
+    pub caller: Arc<CallerInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExperimentAssignment {
+    pub experiment: String,
+    pub arm: String,
 }
 
 #[derive(Debug, Clone)]
@@ -36,4 +86,11 @@ pub enum ProxyCall {
         provider: String,
         credential_id: i64,
     },
+    /// A `/{provider}/...` path without a typed route, forwarded verbatim.
+    RawPassthrough {
+        trace_id: Option<String>,
+        auth: ProxyAuth,
+        provider: String,
+        req: Box<RawPassthroughRequest>,
+    },
 }
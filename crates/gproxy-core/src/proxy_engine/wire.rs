@@ -1,17 +1,30 @@
 use bytes::Bytes;
 
-use serde_json;
+use serde_json::{self, json};
 
-use gproxy_protocol::sse::{SseEvent, SseParser};
-use gproxy_provider_core::{Proto, StreamEvent, StreamFormat};
+use gproxy_protocol::claude::create_message::stream::{BetaStreamEvent, BetaStreamEventKnown};
+use gproxy_protocol::openai::create_response::stream::ResponseStreamEvent;
+use gproxy_protocol::openai::get_response::request::{
+    GetResponsePath, GetResponseQuery, GetResponseRequest,
+};
+use gproxy_protocol::sse::{MAX_SSE_EVENT_BYTES, SseEvent, SseParser, Utf8Decoder};
+use gproxy_provider_core::{Proto, Request, ResponseGetRequest, StreamEvent, StreamFormat};
 
+use super::error_body::ErrorType;
+
+/// Decodes upstream stream bytes into events. Malformed input never fails the stream:
+/// an SSE event whose payload does not parse, or a line or event over
+/// [`MAX_SSE_EVENT_BYTES`], is skipped and counted in [`StreamDecoder::skipped`], and the
+/// first one of a stream is logged.
 #[derive(Debug)]
 pub struct StreamDecoder {
     proto: Proto,
     format: StreamFormat,
+    utf8: Utf8Decoder,
     sse: SseParser,
     // Best-effort JSON-line decoding for Gemini-style streams.
     json_buf: String,
+    skipped: u64,
 }
 
 impl StreamDecoder {
@@ -19,63 +32,23 @@ impl StreamDecoder {
         Self {
             proto,
             format,
+            utf8: Utf8Decoder::new(),
             sse: SseParser::new(),
             json_buf: String::new(),
+            skipped: 0,
         }
     }
 
     pub fn push_bytes(&mut self, chunk: &Bytes) -> Vec<StreamEvent> {
-        let mut out = Vec::new();
-
-        match self.format {
-            StreamFormat::SseNamedEvent | StreamFormat::SseDataOnly => {
-                for ev in self.sse.push_bytes(chunk) {
-                    if let Some(item) = decode_sse_event(self.proto, &ev) {
-                        out.push(item);
-                    }
-                }
-            }
-            StreamFormat::JsonStream => {
-                // 1) Try SSE framing (some upstreams use SSE even for "JSON object stream").
-                for ev in self.sse.push_bytes(chunk) {
-                    if let Some(item) = decode_sse_event(self.proto, &ev) {
-                        out.push(item);
-                    }
-                }
-                // 2) Try newline-delimited JSON objects as a fallback.
-                if let Ok(s) = std::str::from_utf8(chunk) {
-                    self.json_buf.push_str(s);
-                    while let Some(pos) = self.json_buf.find('\n') {
-                        let mut line = self.json_buf[..pos].to_string();
-                        self.json_buf.drain(..=pos);
-                        if line.ends_with('\r') {
-                            line.pop();
-                        }
-                        let line = line.trim();
-                        if line.is_empty() {
-                            continue;
-                        }
-                        // Ignore SSE "data:"/etc lines if present.
-                        if !(line.starts_with('{') || line.starts_with('[')) {
-                            continue;
-                        }
-                        if let Some(item) = decode_json_line(self.proto, line) {
-                            out.push(item);
-                        }
-                    }
-                }
-            }
-        }
-
-        out
+        let text = self.utf8.push(chunk);
+        self.push_text(&text)
     }
 
     pub fn finish(&mut self) -> Vec<StreamEvent> {
-        let mut out = Vec::new();
+        let tail = self.utf8.finish();
+        let mut out = self.push_text(&tail);
         for ev in self.sse.finish() {
-            if let Some(item) = decode_sse_event(self.proto, &ev) {
-                out.push(item);
-            }
+            self.decode_sse(&ev, &mut out);
         }
         if self.format == StreamFormat::JsonStream {
             let line = self.json_buf.trim();
@@ -89,6 +62,70 @@ impl StreamDecoder {
         }
         out
     }
+
+    /// Events and lines dropped so far as malformed or oversized.
+    pub fn skipped(&self) -> u64 {
+        self.skipped + self.sse.dropped()
+    }
+
+    fn push_text(&mut self, text: &str) -> Vec<StreamEvent> {
+        let mut out = Vec::new();
+        // JSON streams still try SSE framing first: some upstreams use SSE even for a
+        // "JSON object stream".
+        for ev in self.sse.push_str(text) {
+            self.decode_sse(&ev, &mut out);
+        }
+        if self.format != StreamFormat::JsonStream {
+            return out;
+        }
+
+        // Newline-delimited JSON objects as a fallback.
+        self.json_buf.push_str(text);
+        let buf = std::mem::take(&mut self.json_buf);
+        let mut start = 0;
+        while let Some(offset) = buf[start..].find('\n') {
+            let line = buf[start..start + offset].trim();
+            start += offset + 1;
+            // Ignore SSE "data:"/etc lines if present.
+            if !(line.starts_with('{') || line.starts_with('[')) {
+                continue;
+            }
+            if let Some(item) = decode_json_line(self.proto, line) {
+                out.push(item);
+            }
+        }
+        self.json_buf = buf[start..].to_string();
+        if self.json_buf.len() > MAX_SSE_EVENT_BYTES {
+            self.json_buf.clear();
+            self.note_skipped("json line over the size limit");
+        }
+        out
+    }
+
+    fn decode_sse(&mut self, ev: &SseEvent, out: &mut Vec<StreamEvent>) {
+        match decode_sse_event(self.proto, ev) {
+            Ok(Some(item)) => out.push(item),
+            Ok(None) => {}
+            Err(err) => self.note_skipped(&err),
+        }
+    }
+
+    fn note_skipped(&mut self, reason: &str) {
+        self.skipped += 1;
+        if self.skipped == 1 {
+            gproxy_common::log_warn!(
+                "stream",
+                "skipping malformed {:?} upstream stream event: {reason}",
+                self.proto
+            );
+        } else {
+            gproxy_common::log_debug!(
+                "stream",
+                "skipping malformed {:?} upstream stream event: {reason}",
+                self.proto
+            );
+        }
+    }
 }
 
 pub fn encode_stream_event(dst_proto: Proto, event: &StreamEvent) -> Option<Bytes> {
@@ -128,6 +165,221 @@ pub fn encode_openai_chat_done() -> Bytes {
     Bytes::from_static(b"data: [DONE]\n\n")
 }
 
+/// Position reached in a resumable upstream stream.
+///
+/// Only OpenAI Responses streams expose a cursor (`response.id` plus `sequence_number`,
+/// replayed via `GET /responses/{id}?stream=true&starting_after=N`), and only for responses
+/// created with `background: true`, which the upstream keeps to replay; other streams never
+/// become resumable.
+#[derive(Debug, Default)]
+pub struct StreamResumeCursor {
+    response_id: Option<String>,
+    background: bool,
+    sequence_number: Option<i64>,
+}
+
+impl StreamResumeCursor {
+    pub fn observe(&mut self, event: &StreamEvent) {
+        let StreamEvent::OpenAIResponse(ev) = event else {
+            return;
+        };
+        let response = match ev {
+            ResponseStreamEvent::Created(created) => Some(&created.response),
+            ResponseStreamEvent::InProgress(in_progress) => Some(&in_progress.response),
+            _ => None,
+        };
+        if let Some(response) = response {
+            self.response_id = Some(response.id.clone());
+            self.background = response.background == Some(true);
+        }
+        self.sequence_number = Some(ev.sequence_number());
+    }
+
+    /// Id of the response being streamed, once the upstream announced it.
+    pub fn response_id(&self) -> Option<&str> {
+        self.response_id.as_deref()
+    }
+
+    /// Upstream request that replays the stream after the last observed event. The
+    /// caller switches it to streaming mode (`stream=true`).
+    pub fn resume_request(&self) -> Option<Request> {
+        if !self.background {
+            return None;
+        }
+        let response_id = self.response_id.clone()?;
+        let starting_after = self.sequence_number?;
+        Some(Request::ResponseGet(ResponseGetRequest::OpenAI(
+            GetResponseRequest {
+                path: GetResponsePath { response_id },
+                query: GetResponseQuery {
+                    starting_after: Some(starting_after),
+                    ..GetResponseQuery::default()
+                },
+            },
+        )))
+    }
+}
+
+/// Splits SSE bytes forwarded verbatim at event boundaries, so the client only ever gets
+/// whole events: a resumed stream or a closing error event never lands inside one.
+#[derive(Debug, Default)]
+pub struct SseEventFramer {
+    pending: Vec<u8>,
+}
+
+impl SseEventFramer {
+    /// Adds `chunk` and returns the bytes through the last complete event, if any.
+    pub fn push(&mut self, chunk: &[u8]) -> Option<Bytes> {
+        self.pending.extend_from_slice(chunk);
+        let end = last_event_end(&self.pending)?;
+        let rest = self.pending.split_off(end);
+        Some(Bytes::from(std::mem::replace(&mut self.pending, rest)))
+    }
+
+    /// Bytes after the last complete event.
+    pub fn take_rest(&mut self) -> Bytes {
+        Bytes::from(std::mem::take(&mut self.pending))
+    }
+
+    pub fn clear(&mut self) {
+        self.pending.clear();
+    }
+}
+
+/// End of the last blank line (`\n\n`, `\n\r\n` or `\r\r`) in `buf`.
+fn last_event_end(buf: &[u8]) -> Option<usize> {
+    (1..buf.len())
+        .rev()
+        .find(|&i| match buf[i] {
+            b'\n' => buf[i - 1] == b'\n' || (i >= 2 && buf[i - 1] == b'\r' && buf[i - 2] == b'\n'),
+            b'\r' => buf[i - 1] == b'\r',
+            _ => false,
+        })
+        .map(|i| i + 1)
+}
+
+/// Whether an upstream event marks the end of a well-formed stream. A stream that closes
+/// without one was cut off mid-flight.
+pub fn is_terminal_stream_event(event: &StreamEvent) -> bool {
+    match event {
+        StreamEvent::Claude(BetaStreamEvent::Known(ev)) => matches!(
+            ev,
+            BetaStreamEventKnown::MessageStop | BetaStreamEventKnown::Error { .. }
+        ),
+        StreamEvent::Claude(BetaStreamEvent::Unknown(_)) => false,
+        StreamEvent::OpenAIChat(ev) => ev
+            .choices
+            .iter()
+            .any(|choice| choice.finish_reason.is_some()),
+        StreamEvent::OpenAIResponse(ev) => matches!(
+            ev,
+            ResponseStreamEvent::Completed(_)
+                | ResponseStreamEvent::Failed(_)
+                | ResponseStreamEvent::Incomplete(_)
+                | ResponseStreamEvent::Error(_)
+        ),
+        StreamEvent::Gemini(ev) => {
+            ev.candidates
+                .iter()
+                .any(|candidate| candidate.finish_reason.is_some())
+                || ev
+                    .prompt_feedback
+                    .as_ref()
+                    .is_some_and(|feedback| feedback.block_reason.is_some())
+        }
+    }
+}
+
+/// Whether an upstream event carries model output (or ends the stream). Events before the
+/// first such event are preamble that can be discarded when failing over.
+pub fn is_content_stream_event(event: &StreamEvent) -> bool {
+    if is_terminal_stream_event(event) {
+        return true;
+    }
+    match event {
+        StreamEvent::Claude(BetaStreamEvent::Known(ev)) => {
+            matches!(ev, BetaStreamEventKnown::ContentBlockDelta { .. })
+        }
+        StreamEvent::Claude(BetaStreamEvent::Unknown(_)) => false,
+        StreamEvent::OpenAIChat(ev) => ev.choices.iter().any(|choice| {
+            let delta = &choice.delta;
+            delta.content.as_deref().is_some_and(|s| !s.is_empty())
+                || delta
+                    .reasoning_content
+                    .as_deref()
+                    .is_some_and(|s| !s.is_empty())
+                || delta.refusal.as_deref().is_some_and(|s| !s.is_empty())
+                || delta.tool_calls.is_some()
+                || delta.function_call.is_some()
+        }),
+        StreamEvent::OpenAIResponse(ev) => matches!(
+            ev,
+            ResponseStreamEvent::OutputTextDelta(_)
+                | ResponseStreamEvent::RefusalDelta(_)
+                | ResponseStreamEvent::FunctionCallArgumentsDelta(_)
+                | ResponseStreamEvent::ReasoningTextDelta(_)
+                | ResponseStreamEvent::ReasoningSummaryTextDelta(_)
+        ),
+        StreamEvent::Gemini(ev) => ev
+            .candidates
+            .iter()
+            .any(|candidate| !candidate.content.parts.is_empty()),
+    }
+}
+
+/// Terminal error event in the downstream protocol's stream framing.
+pub fn encode_stream_error(
+    dst_proto: Proto,
+    status: u16,
+    code: &str,
+    message: &str,
+    sequence_number: i64,
+) -> Bytes {
+    let error_type = ErrorType::from_status(status);
+    match dst_proto {
+        Proto::Claude => {
+            let data = json!({
+                "type": "error",
+                "error": { "type": error_type.claude_type(), "message": message },
+            });
+            encode_sse(Some("error"), &data.to_string())
+        }
+        Proto::OpenAIResponse => {
+            let data = json!({
+                "type": "error",
+                "code": code,
+                "message": message,
+                "param": null,
+                "sequence_number": sequence_number,
+            });
+            encode_sse(Some("error"), &data.to_string())
+        }
+        Proto::OpenAIChat | Proto::OpenAI => {
+            let data = json!({
+                "error": {
+                    "message": message,
+                    "type": error_type.openai_type(),
+                    "param": null,
+                    "code": code,
+                },
+            });
+            encode_sse(None, &data.to_string())
+        }
+        Proto::Gemini => {
+            let data = json!({
+                "error": {
+                    "code": status,
+                    "message": message,
+                    "status": error_type.gemini_status(),
+                },
+            });
+            let mut out = data.to_string().into_bytes();
+            out.push(b'\n');
+            Bytes::from(out)
+        }
+    }
+}
+
 pub fn content_type_for_stream(proto: Proto) -> &'static str {
     match proto {
         Proto::Gemini => "application/json",
@@ -135,24 +387,23 @@ pub fn content_type_for_stream(proto: Proto) -> &'static str {
     }
 }
 
-fn decode_sse_event(proto: Proto, ev: &SseEvent) -> Option<StreamEvent> {
+fn decode_sse_event(proto: Proto, ev: &SseEvent) -> Result<Option<StreamEvent>, String> {
     let data = ev.data.trim();
     if data.is_empty() {
-        return None;
+        return Ok(None);
     }
     if data == "[DONE]" {
-        return None;
+        return Ok(None);
     }
 
-    match proto {
-        Proto::Claude => serde_json::from_str(data).ok().map(StreamEvent::Claude),
-        Proto::OpenAIChat => serde_json::from_str(data).ok().map(StreamEvent::OpenAIChat),
-        Proto::OpenAIResponse => serde_json::from_str(data)
-            .ok()
-            .map(StreamEvent::OpenAIResponse),
-        Proto::Gemini => serde_json::from_str(data).ok().map(StreamEvent::Gemini),
-        Proto::OpenAI => None,
-    }
+    let decoded = match proto {
+        Proto::Claude => serde_json::from_str(data).map(StreamEvent::Claude),
+        Proto::OpenAIChat => serde_json::from_str(data).map(StreamEvent::OpenAIChat),
+        Proto::OpenAIResponse => serde_json::from_str(data).map(StreamEvent::OpenAIResponse),
+        Proto::Gemini => serde_json::from_str(data).map(StreamEvent::Gemini),
+        Proto::OpenAI => return Ok(None),
+    };
+    decoded.map(Some).map_err(|err| err.to_string())
 }
 
 fn decode_json_line(proto: Proto, line: &str) -> Option<StreamEvent> {
@@ -162,7 +413,7 @@ fn decode_json_line(proto: Proto, line: &str) -> Option<StreamEvent> {
     }
 }
 
-fn encode_sse(event: Option<&str>, data: &str) -> Bytes {
+pub fn encode_sse(event: Option<&str>, data: &str) -> Bytes {
     // Minimal SSE encoding: `event:` is optional. For multi-line data, each line gets `data:`.
     let mut out = String::new();
     if let Some(event) = event {
@@ -178,3 +429,210 @@ fn encode_sse(event: Option<&str>, data: &str) -> Bytes {
     out.push('\n');
     Bytes::from(out)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn malformed_events_are_skipped_and_counted() {
+        let mut decoder = StreamDecoder::new(Proto::OpenAIChat, StreamFormat::SseDataOnly);
+        let chunk = concat!(
+            "data: {\"id\":\"c\",\"object\":\"chat.completion.chunk\",\"created\":1,",
+            "\"model\":\"m\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"h\u{e9}\"}}]}\n\n",
+            "data: {not json\n\n",
+            "data: [DONE]\n\n",
+        )
+        .as_bytes();
+        // Split inside the two-byte `é`.
+        let split = chunk.iter().position(|b| *b == 0xC3).unwrap() + 1;
+        let mut events = decoder.push_bytes(&Bytes::copy_from_slice(&chunk[..split]));
+        events.extend(decoder.push_bytes(&Bytes::copy_from_slice(&chunk[split..])));
+        events.extend(decoder.finish());
+        assert_eq!(events.len(), 1);
+        assert_eq!(decoder.skipped(), 1);
+        let StreamEvent::OpenAIChat(ev) = &events[0] else {
+            panic!("unexpected event");
+        };
+        assert_eq!(ev.choices[0].delta.content.as_deref(), Some("h\u{e9}"));
+    }
+
+    fn response_events(sse: &str) -> Vec<StreamEvent> {
+        let mut decoder = StreamDecoder::new(Proto::OpenAIResponse, StreamFormat::SseNamedEvent);
+        let mut events = decoder.push_bytes(&Bytes::copy_from_slice(sse.as_bytes()));
+        events.extend(decoder.finish());
+        events
+    }
+
+    fn created(background: bool, seq: i64) -> String {
+        format!(
+            "event: response.created\ndata: {{\"type\":\"response.created\",\"response\":{{\"id\":\"resp_1\",\"object\":\"response\",\"created_at\":1,\"model\":\"m\",\"background\":{background}}},\"sequence_number\":{seq}}}\n\n"
+        )
+    }
+
+    fn text_delta(seq: i64) -> String {
+        format!(
+            "event: response.output_text.delta\ndata: {{\"type\":\"response.output_text.delta\",\"item_id\":\"msg_1\",\"output_index\":0,\"content_index\":0,\"delta\":\"x\",\"sequence_number\":{seq}}}\n\n"
+        )
+    }
+
+    #[test]
+    fn resume_cursor_tracks_the_last_sequence_number() {
+        let mut cursor = StreamResumeCursor::default();
+        for ev in response_events(&format!(
+            "{}{}{}",
+            created(true, 0),
+            text_delta(1),
+            text_delta(2)
+        )) {
+            cursor.observe(&ev);
+        }
+        assert_eq!(cursor.response_id(), Some("resp_1"));
+        let Some(Request::ResponseGet(ResponseGetRequest::OpenAI(req))) = cursor.resume_request()
+        else {
+            panic!("expected a resume request");
+        };
+        assert_eq!(req.path.response_id, "resp_1");
+        assert_eq!(req.query.starting_after, Some(2));
+    }
+
+    #[test]
+    fn resume_cursor_requires_a_background_response() {
+        let mut cursor = StreamResumeCursor::default();
+        for ev in response_events(&format!("{}{}", created(false, 0), text_delta(1))) {
+            cursor.observe(&ev);
+        }
+        assert_eq!(cursor.response_id(), Some("resp_1"));
+        assert!(cursor.resume_request().is_none());
+
+        // Events before `response.created` give no id to resume.
+        let mut cursor = StreamResumeCursor::default();
+        for ev in response_events(&text_delta(1)) {
+            cursor.observe(&ev);
+        }
+        assert!(cursor.resume_request().is_none());
+    }
+
+    #[test]
+    fn framer_releases_whole_events_only() {
+        let mut framer = SseEventFramer::default();
+        assert_eq!(framer.push(b"event: a\ndata: 1"), None);
+        assert_eq!(
+            framer.push(b"\n\nevent: b\ndata: 2\r\n\r\nevent: c\n"),
+            Some(Bytes::from_static(
+                b"event: a\ndata: 1\n\nevent: b\ndata: 2\r\n\r\n"
+            ))
+        );
+        assert_eq!(framer.push(b"data: 3\n"), None);
+        assert_eq!(
+            framer.take_rest(),
+            Bytes::from_static(b"event: c\ndata: 3\n")
+        );
+        assert_eq!(
+            framer.push(b"data: 4\r\r"),
+            Some(Bytes::from_static(b"data: 4\r\r"))
+        );
+        assert!(framer.take_rest().is_empty());
+    }
+
+    fn decode(proto: Proto, format: StreamFormat, text: &str) -> Vec<StreamEvent> {
+        let mut decoder = StreamDecoder::new(proto, format);
+        let mut events = decoder.push_bytes(&Bytes::copy_from_slice(text.as_bytes()));
+        events.extend(decoder.finish());
+        assert_eq!(decoder.skipped(), 0, "{text}");
+        events
+    }
+
+    #[test]
+    fn terminal_events_per_protocol() {
+        let cases = [
+            (
+                Proto::Claude,
+                StreamFormat::SseNamedEvent,
+                "event: ping\ndata: {\"type\":\"ping\"}\n\n",
+                "event: message_stop\ndata: {\"type\":\"message_stop\"}\n\n",
+            ),
+            (
+                Proto::OpenAIChat,
+                StreamFormat::SseDataOnly,
+                "data: {\"id\":\"c\",\"object\":\"chat.completion.chunk\",\"created\":1,\"model\":\"m\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"h\"}}]}\n\n",
+                "data: {\"id\":\"c\",\"object\":\"chat.completion.chunk\",\"created\":1,\"model\":\"m\",\"choices\":[{\"index\":0,\"delta\":{},\"finish_reason\":\"stop\"}]}\n\n",
+            ),
+            (
+                Proto::OpenAIResponse,
+                StreamFormat::SseNamedEvent,
+                &created(false, 0),
+                "event: response.completed\ndata: {\"type\":\"response.completed\",\"response\":{\"id\":\"resp_1\",\"object\":\"response\",\"created_at\":1,\"model\":\"m\"},\"sequence_number\":1}\n\n",
+            ),
+            (
+                Proto::Gemini,
+                StreamFormat::JsonStream,
+                "{\"candidates\":[{\"content\":{\"role\":\"model\",\"parts\":[{\"text\":\"h\"}]}}]}\n",
+                "{\"candidates\":[{\"content\":{\"role\":\"model\",\"parts\":[]},\"finishReason\":\"STOP\"}]}\n",
+            ),
+        ];
+        for (proto, format, open, terminal) in cases {
+            let open = decode(proto, format, open);
+            let terminal = decode(proto, format, terminal);
+            assert_eq!(open.len(), 1, "{proto:?}");
+            assert_eq!(terminal.len(), 1, "{proto:?}");
+            assert!(!is_terminal_stream_event(&open[0]), "{proto:?}");
+            assert!(is_terminal_stream_event(&terminal[0]), "{proto:?}");
+        }
+    }
+
+    #[test]
+    fn stream_errors_use_the_downstream_framing() {
+        let claude =
+            encode_stream_error(Proto::Claude, 502, "upstream_stream_interrupted", "cut", 7);
+        let events = decode(
+            Proto::Claude,
+            StreamFormat::SseNamedEvent,
+            std::str::from_utf8(&claude).unwrap(),
+        );
+        assert!(is_terminal_stream_event(&events[0]));
+
+        let response = encode_stream_error(
+            Proto::OpenAIResponse,
+            504,
+            "upstream_stream_idle_timeout",
+            "idle",
+            7,
+        );
+        let events = decode(
+            Proto::OpenAIResponse,
+            StreamFormat::SseNamedEvent,
+            std::str::from_utf8(&response).unwrap(),
+        );
+        let StreamEvent::OpenAIResponse(ev @ ResponseStreamEvent::Error(_)) = &events[0] else {
+            panic!("expected an error event");
+        };
+        assert_eq!(ev.sequence_number(), 7);
+
+        let chat = encode_stream_error(
+            Proto::OpenAIChat,
+            502,
+            "upstream_stream_interrupted",
+            "cut",
+            7,
+        );
+        let data = std::str::from_utf8(&chat)
+            .unwrap()
+            .strip_prefix("data: ")
+            .and_then(|rest| rest.strip_suffix("\n\n"))
+            .unwrap();
+        let value: serde_json::Value = serde_json::from_str(data).unwrap();
+        assert_eq!(value["error"]["code"], "upstream_stream_interrupted");
+
+        let gemini = encode_stream_error(
+            Proto::Gemini,
+            504,
+            "upstream_stream_idle_timeout",
+            "idle",
+            7,
+        );
+        assert!(gemini.ends_with(b"\n"));
+        let value: serde_json::Value = serde_json::from_slice(&gemini).unwrap();
+        assert_eq!(value["error"]["code"], 504);
+    }
+}
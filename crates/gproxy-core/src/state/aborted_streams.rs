@@ -0,0 +1,29 @@
+//! Generate streams that ended early, written to the `aborted_streams` table so the
+//! output they produced before the abort is still attributed.
+
+use std::sync::{Arc, OnceLock};
+
+use gproxy_storage::{AbortedStreamRecord, Storage};
+
+#[derive(Default)]
+pub struct AbortedStreams {
+    storage: OnceLock<Arc<dyn Storage>>,
+}
+
+impl AbortedStreams {
+    /// Sets where aborts are written; until then they are dropped.
+    pub fn start(&self, storage: Arc<dyn Storage>) {
+        let _ = self.storage.set(storage);
+    }
+
+    pub fn record(&self, record: AbortedStreamRecord) {
+        let Some(storage) = self.storage.get().cloned() else {
+            return;
+        };
+        tokio::spawn(async move {
+            if let Err(err) = storage.append_aborted_stream(&record).await {
+                gproxy_common::log_error!("aborted_streams", "store aborted stream: {err}");
+            }
+        });
+    }
+}
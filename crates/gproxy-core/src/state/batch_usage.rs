@@ -0,0 +1,41 @@
+//! Finished Gemini batches whose per-request usage was already recorded, so polling a
+//! done batch again doesn't count its tokens twice.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long an accounted batch is remembered; later polls of the same batch are rare.
+pub const ACCOUNTED_BATCH_TTL: Duration = Duration::from_secs(72 * 60 * 60);
+const MAX_ACCOUNTED_BATCHES: usize = 10_000;
+
+#[derive(Debug, Default)]
+pub struct AccountedBatches {
+    seen: Mutex<HashMap<(String, String), Instant>>,
+}
+
+impl AccountedBatches {
+    /// Marks `batch` of `provider` as accounted; true only the first time.
+    pub fn first_completion(&self, provider: &str, batch: &str) -> bool {
+        let now = Instant::now();
+        let mut seen = self.seen.lock().unwrap_or_else(|e| e.into_inner());
+        if seen.len() >= MAX_ACCOUNTED_BATCHES {
+            seen.retain(|_, at| now.duration_since(*at) < ACCOUNTED_BATCH_TTL);
+        }
+        seen.insert((provider.to_string(), batch.to_string()), now)
+            .is_none_or(|at| now.duration_since(at) >= ACCOUNTED_BATCH_TTL)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn batch_usage_is_accounted_once_per_provider() {
+        let batches = AccountedBatches::default();
+        assert!(batches.first_completion("aistudio", "batches/1"));
+        assert!(!batches.first_completion("aistudio", "batches/1"));
+        assert!(batches.first_completion("aistudio-2", "batches/1"));
+    }
+}
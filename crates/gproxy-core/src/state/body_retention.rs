@@ -0,0 +1,50 @@
+//! Privacy-tier keys: request and response bodies of user keys (or organizations) marked
+//! `omit_bodies` are dropped from events as they are emitted, so no sink, subscriber or
+//! log table ever receives them. Metadata and usage are kept.
+
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+use gproxy_provider_core::{Event, EventFilter};
+use gproxy_storage::StorageSnapshot;
+
+pub struct BodyRetention {
+    snapshot: Arc<ArcSwap<StorageSnapshot>>,
+}
+
+impl BodyRetention {
+    pub fn new(snapshot: Arc<ArcSwap<StorageSnapshot>>) -> Self {
+        Self { snapshot }
+    }
+
+    /// Whether bodies of `user_key_id`'s traffic must not be kept, through the key itself
+    /// or its user's organization.
+    pub fn omits(&self, user_key_id: i64) -> bool {
+        let snapshot = self.snapshot.load();
+        let Some(key) = snapshot.user_keys.iter().find(|k| k.id == user_key_id) else {
+            return false;
+        };
+        key.omit_bodies
+            || snapshot
+                .users
+                .iter()
+                .find(|u| u.id == key.user_id)
+                .and_then(|u| u.org_id)
+                .and_then(|org_id| snapshot.organizations.iter().find(|o| o.id == org_id))
+                .is_some_and(|org| org.omit_bodies)
+    }
+}
+
+impl EventFilter for BodyRetention {
+    fn apply(&self, event: &mut Event) {
+        let (user_key_id, request_body, response_body) = match event {
+            Event::Downstream(ev) => (ev.user_key_id, &mut ev.request_body, &mut ev.response_body),
+            Event::Upstream(ev) => (ev.user_key_id, &mut ev.request_body, &mut ev.response_body),
+            Event::Operational(_) => return,
+        };
+        if user_key_id.is_some_and(|id| self.omits(id)) {
+            *request_body = None;
+            *response_body = None;
+        }
+    }
+}
@@ -0,0 +1,84 @@
+//! Body sampling: only a share of successful requests keep their request and response
+//! bodies in events; the rest lose them as they are emitted. The share comes from the
+//! user key's `body_sample_percent`, else from the provider's `body_sampling`; with
+//! neither, every body is kept. Failed requests always keep their bodies, and usage and
+//! other metadata are never dropped.
+//!
+//! Downstream events carry no provider, so they follow the provider named by the first
+//! segment of a `/{provider}/...` path; on aggregate routes only the key's setting
+//! applies.
+
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+use gproxy_provider_core::{BodySampling, Event, EventFilter};
+use gproxy_storage::StorageSnapshot;
+
+pub struct BodySampler {
+    snapshot: Arc<ArcSwap<StorageSnapshot>>,
+}
+
+impl BodySampler {
+    pub fn new(snapshot: Arc<ArcSwap<StorageSnapshot>>) -> Self {
+        Self { snapshot }
+    }
+
+    /// Sampling for traffic of `user_key_id` through `provider`; `None` keeps all bodies.
+    pub fn sampling(
+        &self,
+        user_key_id: Option<i64>,
+        provider: Option<&str>,
+    ) -> Option<BodySampling> {
+        let snapshot = self.snapshot.load();
+        if let Some(percent) = user_key_id
+            .and_then(|id| snapshot.user_keys.iter().find(|k| k.id == id))
+            .and_then(|key| key.body_sample_percent)
+        {
+            return Some(BodySampling::new(percent.clamp(0, 100) as u8));
+        }
+        let provider = snapshot
+            .providers
+            .iter()
+            .find(|p| Some(p.name.as_str()) == provider)?;
+        BodySampling::from_config_json(&provider.config_json)
+    }
+}
+
+impl EventFilter for BodySampler {
+    fn apply(&self, event: &mut Event) {
+        let (trace_id, user_key_id, provider, failed, request_body, response_body) = match event {
+            Event::Downstream(ev) => (
+                ev.trace_id.as_deref(),
+                ev.user_key_id,
+                ev.request_path.trim_start_matches('/').split('/').next(),
+                !matches!(ev.response_status, Some(200..=399)),
+                &mut ev.request_body,
+                &mut ev.response_body,
+            ),
+            Event::Upstream(ev) => (
+                ev.trace_id.as_deref(),
+                ev.user_key_id,
+                Some(ev.provider.as_str()),
+                ev.error_kind.is_some()
+                    || ev.transport_kind.is_some()
+                    || !matches!(ev.response_status, Some(200..=399)),
+                &mut ev.request_body,
+                &mut ev.response_body,
+            ),
+            Event::Operational(_) => return,
+        };
+        if failed || (request_body.is_none() && response_body.is_none()) {
+            return;
+        }
+        let Some(trace_id) = trace_id else {
+            return;
+        };
+        if self
+            .sampling(user_key_id, provider)
+            .is_some_and(|sampling| !sampling.keeps(trace_id))
+        {
+            *request_body = None;
+            *response_body = None;
+        }
+    }
+}
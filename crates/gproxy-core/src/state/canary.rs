@@ -0,0 +1,144 @@
+//! Canary rollout of provider config edits: a share of traffic runs on the new config
+//! until an admin promotes it, and it is rolled back automatically when its error rate
+//! crosses the threshold inside the evaluation window.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use time::OffsetDateTime;
+
+use gproxy_provider_core::{ProviderConfig, ProviderPolicies};
+
+#[derive(Debug, Clone, Copy)]
+pub struct CanarySettings {
+    /// Share of requests (0-100) served with the canary config.
+    pub percent: u8,
+    /// Error rate (0.0-1.0) that triggers a rollback.
+    pub max_error_rate: f64,
+    /// Canary requests needed before the error rate is evaluated.
+    pub min_requests: u64,
+    /// How long after the start the error rate is watched.
+    pub window: Duration,
+}
+
+pub struct ProviderCanary {
+    pub config_json: Arc<serde_json::Value>,
+    /// `config_json` parsed once when the canary starts; `None` when it is not a valid
+    /// provider config, and such a canary serves no traffic.
+    pub config: Option<Arc<ProviderConfig>>,
+    pub policies: Arc<ProviderPolicies>,
+    pub settings: CanarySettings,
+    pub started_at: OffsetDateTime,
+    started: Instant,
+    requests: AtomicU64,
+    errors: AtomicU64,
+    rolled_back: AtomicBool,
+}
+
+impl ProviderCanary {
+    pub fn new(config_json: serde_json::Value, settings: CanarySettings) -> Self {
+        Self {
+            config: serde_json::from_value::<ProviderConfig>(config_json.clone())
+                .ok()
+                .map(Arc::new),
+            policies: Arc::new(ProviderPolicies::from_config_json(&config_json)),
+            config_json: Arc::new(config_json),
+            settings,
+            started_at: OffsetDateTime::now_utc(),
+            started: Instant::now(),
+            requests: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+            rolled_back: AtomicBool::new(false),
+        }
+    }
+
+    /// Whether this request should use the canary config.
+    pub fn sample(&self) -> bool {
+        !self.is_rolled_back() && rand::random_range(0..100u8) < self.settings.percent
+    }
+
+    pub fn is_rolled_back(&self) -> bool {
+        self.rolled_back.load(Ordering::Relaxed)
+    }
+
+    /// Records one canary request. Returns `true` when this outcome triggered the rollback.
+    pub fn record(&self, failed: bool) -> bool {
+        if self.is_rolled_back() || self.started.elapsed() > self.settings.window {
+            return false;
+        }
+        let requests = self.requests.fetch_add(1, Ordering::Relaxed) + 1;
+        let errors = if failed {
+            self.errors.fetch_add(1, Ordering::Relaxed) + 1
+        } else {
+            self.errors.load(Ordering::Relaxed)
+        };
+        if requests < self.settings.min_requests.max(1) {
+            return false;
+        }
+        let rate = errors as f64 / requests as f64;
+        rate > self.settings.max_error_rate && !self.rolled_back.swap(true, Ordering::Relaxed)
+    }
+
+    pub fn status_json(&self) -> serde_json::Value {
+        let requests = self.requests.load(Ordering::Relaxed);
+        let errors = self.errors.load(Ordering::Relaxed);
+        let state = if self.is_rolled_back() {
+            "rolled_back"
+        } else if self.started.elapsed() > self.settings.window {
+            "passed"
+        } else {
+            "evaluating"
+        };
+        serde_json::json!({
+            "state": state,
+            "percent": self.settings.percent,
+            "max_error_rate": self.settings.max_error_rate,
+            "min_requests": self.settings.min_requests,
+            "window_secs": self.settings.window.as_secs(),
+            "started_at": self.started_at,
+            "requests": requests,
+            "errors": errors,
+            "config_json": self.config_json.as_ref(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn canary(max_error_rate: f64) -> ProviderCanary {
+        ProviderCanary::new(
+            serde_json::json!({}),
+            CanarySettings {
+                percent: 100,
+                max_error_rate,
+                min_requests: 4,
+                window: Duration::from_secs(60),
+            },
+        )
+    }
+
+    #[test]
+    fn rolls_back_once_error_rate_exceeds_threshold() {
+        let canary = canary(0.5);
+        assert!(!canary.record(true));
+        assert!(!canary.record(true));
+        assert!(!canary.record(false));
+        // 3 errors out of 4 requests.
+        assert!(canary.record(true));
+        assert!(canary.is_rolled_back());
+        assert!(!canary.sample());
+        assert!(!canary.record(true));
+    }
+
+    #[test]
+    fn healthy_canary_keeps_serving() {
+        let canary = canary(0.5);
+        for _ in 0..10 {
+            assert!(!canary.record(false));
+        }
+        assert!(canary.sample());
+    }
+}
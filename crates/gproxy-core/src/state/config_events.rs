@@ -0,0 +1,147 @@
+//! Notifications of config changes applied to the running state, for in-process
+//! consumers and the admin event stream.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::Serialize;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+use tokio::sync::broadcast;
+
+/// Events buffered per subscriber; one that falls further behind gets `Lagged`.
+pub const CONFIG_EVENT_BUFFER: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigEntity {
+    GlobalConfig,
+    Provider,
+    ProviderCanary,
+    Credential,
+    Organization,
+    OrgGrant,
+    User,
+    UserKey,
+    ModelProfile,
+    Experiment,
+    Secret,
+    LogView,
+    /// The whole snapshot was reloaded from storage.
+    Snapshot,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigAction {
+    Created,
+    Updated,
+    Enabled,
+    Disabled,
+    Deleted,
+}
+
+impl ConfigAction {
+    pub fn toggled(enabled: bool) -> Self {
+        if enabled {
+            ConfigAction::Enabled
+        } else {
+            ConfigAction::Disabled
+        }
+    }
+}
+
+/// One applied change. Carries ids and names only, never secrets; consumers read the
+/// current state through the admin API.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConfigEvent {
+    /// Increases by one per event since startup.
+    pub seq: u64,
+    pub at: String,
+    pub entity: ConfigEntity,
+    pub action: ConfigAction,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<i64>,
+    /// Provider, profile or experiment name; for credentials, their provider.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+pub struct ConfigEvents {
+    tx: broadcast::Sender<ConfigEvent>,
+    seq: AtomicU64,
+}
+
+impl Default for ConfigEvents {
+    fn default() -> Self {
+        Self {
+            tx: broadcast::channel(CONFIG_EVENT_BUFFER).0,
+            seq: AtomicU64::new(0),
+        }
+    }
+}
+
+impl ConfigEvents {
+    /// Receives every change published after this call.
+    pub fn subscribe(&self) -> broadcast::Receiver<ConfigEvent> {
+        self.tx.subscribe()
+    }
+
+    pub fn publish(
+        &self,
+        entity: ConfigEntity,
+        action: ConfigAction,
+        id: Option<i64>,
+        name: Option<&str>,
+    ) {
+        let event = ConfigEvent {
+            seq: self.seq.fetch_add(1, Ordering::Relaxed) + 1,
+            at: OffsetDateTime::now_utc()
+                .format(&Rfc3339)
+                .unwrap_or_default(),
+            entity,
+            action,
+            id,
+            name: name.map(str::to_string),
+        };
+        gproxy_common::log_debug!(
+            "config",
+            "{:?} {:?} id={:?} name={:?}",
+            event.entity,
+            event.action,
+            event.id,
+            event.name
+        );
+        // No subscribers is fine.
+        let _ = self.tx.send(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn subscribers_receive_numbered_events() {
+        let events = ConfigEvents::default();
+        events.publish(
+            ConfigEntity::Provider,
+            ConfigAction::Created,
+            Some(1),
+            Some("openai"),
+        );
+        let mut rx = events.subscribe();
+        events.publish(
+            ConfigEntity::Credential,
+            ConfigAction::toggled(false),
+            Some(7),
+            Some("openai"),
+        );
+
+        let event = rx.recv().await.unwrap();
+        assert_eq!(event.seq, 2);
+        assert_eq!(event.action, ConfigAction::Disabled);
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["entity"], "credential");
+        assert_eq!(json["id"], 7);
+    }
+}
@@ -0,0 +1,195 @@
+//! Credentials being taken out of service gracefully: they get no new requests while the
+//! ones in flight finish, then the pending disable or delete is applied.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+/// Longest an admin may ask a drain to wait.
+pub const MAX_DRAIN_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+/// Finished drains stay visible for this long.
+pub const DRAIN_STATUS_RETENTION: Duration = Duration::from_secs(60 * 60);
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DrainAction {
+    Disable,
+    Delete,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DrainPhase {
+    /// Waiting for in-flight requests.
+    Draining,
+    /// The change was applied.
+    Applied,
+    /// Applying the change failed; see `error`.
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DrainStatus {
+    pub credential_id: i64,
+    pub action: DrainAction,
+    pub phase: DrainPhase,
+    /// Requests still running at the last check.
+    pub in_flight: u64,
+    pub elapsed_ms: u64,
+    pub timeout_ms: u64,
+    /// The wait ended at the timeout with requests still in flight.
+    pub timed_out: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug)]
+struct Drain {
+    action: DrainAction,
+    phase: DrainPhase,
+    in_flight: u64,
+    started: Instant,
+    finished: Option<Instant>,
+    timeout: Duration,
+    error: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct CredentialDrains {
+    drains: Mutex<HashMap<i64, Drain>>,
+}
+
+impl CredentialDrains {
+    /// Registers a drain of `credential_id`; `false` when one is already running.
+    pub fn begin(&self, credential_id: i64, action: DrainAction, timeout: Duration) -> bool {
+        let now = Instant::now();
+        let mut drains = self.drains.lock().unwrap_or_else(|e| e.into_inner());
+        drains.retain(|_, drain| {
+            drain
+                .finished
+                .is_none_or(|at| now.duration_since(at) < DRAIN_STATUS_RETENTION)
+        });
+        if drains
+            .get(&credential_id)
+            .is_some_and(|drain| drain.phase == DrainPhase::Draining)
+        {
+            return false;
+        }
+        drains.insert(
+            credential_id,
+            Drain {
+                action,
+                phase: DrainPhase::Draining,
+                in_flight: 0,
+                started: now,
+                finished: None,
+                timeout: timeout.min(MAX_DRAIN_TIMEOUT),
+                error: None,
+            },
+        );
+        true
+    }
+
+    pub fn is_draining(&self, credential_id: i64) -> bool {
+        self.drains
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&credential_id)
+            .is_some_and(|drain| drain.phase == DrainPhase::Draining)
+    }
+
+    /// Polls `in_flight` until it reaches zero or the drain's timeout passes, and returns
+    /// the last count.
+    pub async fn wait(&self, credential_id: i64, in_flight: impl Fn() -> u64) -> u64 {
+        loop {
+            let count = in_flight();
+            let timed_out = {
+                let mut drains = self.drains.lock().unwrap_or_else(|e| e.into_inner());
+                let Some(drain) = drains.get_mut(&credential_id) else {
+                    return count;
+                };
+                drain.in_flight = count;
+                drain.started.elapsed() >= drain.timeout
+            };
+            if count == 0 || timed_out {
+                return count;
+            }
+            tokio::time::sleep(DRAIN_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Records the outcome of applying the change after the wait.
+    pub fn finish(&self, credential_id: i64, result: Result<(), String>) {
+        let mut drains = self.drains.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(drain) = drains.get_mut(&credential_id) {
+            drain.finished = Some(Instant::now());
+            match result {
+                Ok(()) => drain.phase = DrainPhase::Applied,
+                Err(err) => {
+                    drain.phase = DrainPhase::Failed;
+                    drain.error = Some(err);
+                }
+            }
+        }
+    }
+
+    pub fn status(&self, credential_id: i64) -> Option<DrainStatus> {
+        let drains = self.drains.lock().unwrap_or_else(|e| e.into_inner());
+        let drain = drains.get(&credential_id)?;
+        let elapsed = drain
+            .finished
+            .unwrap_or_else(Instant::now)
+            .duration_since(drain.started);
+        Some(DrainStatus {
+            credential_id,
+            action: drain.action,
+            phase: drain.phase,
+            in_flight: drain.in_flight,
+            elapsed_ms: elapsed.as_millis() as u64,
+            timeout_ms: drain.timeout.as_millis() as u64,
+            timed_out: drain.phase != DrainPhase::Draining && drain.in_flight > 0,
+            error: drain.error.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    #[tokio::test]
+    async fn waits_for_in_flight_requests_then_reports_the_outcome() {
+        let drains = CredentialDrains::default();
+        assert!(drains.begin(7, DrainAction::Delete, Duration::from_secs(5)));
+        assert!(!drains.begin(7, DrainAction::Disable, Duration::from_secs(5)));
+        assert!(drains.is_draining(7));
+
+        let in_flight = AtomicU64::new(3);
+        let left = drains
+            .wait(7, || in_flight.fetch_sub(1, Ordering::Relaxed) - 1)
+            .await;
+        assert_eq!(left, 0);
+        drains.finish(7, Ok(()));
+
+        let status = drains.status(7).unwrap();
+        assert_eq!(status.phase, DrainPhase::Applied);
+        assert!(!status.timed_out);
+        assert!(!drains.is_draining(7));
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_the_timeout() {
+        let drains = CredentialDrains::default();
+        assert!(drains.begin(8, DrainAction::Disable, Duration::ZERO));
+        assert_eq!(drains.wait(8, || 2).await, 2);
+        drains.finish(8, Err("storage_error".to_string()));
+        let status = drains.status(8).unwrap();
+        assert_eq!(status.phase, DrainPhase::Failed);
+        assert!(status.timed_out);
+        assert_eq!(status.error.as_deref(), Some("storage_error"));
+    }
+}
@@ -0,0 +1,243 @@
+//! Which credentials of a provider a caller may consume, from its organization's grants.
+
+use std::collections::HashSet;
+
+use gproxy_storage::StorageSnapshot;
+
+/// Which credentials of a provider a caller may consume.
+///
+/// Grants reserve what they name: a granted credential, or every credential of a provider
+/// granted as a whole, is only usable by the organizations holding the grant. An
+/// organization with grants reaches only its granted providers and credentials; callers
+/// outside any grant (no organization, or one without grants) reach the credentials no
+/// organization holds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CredentialScope {
+    /// No tenant restriction applies.
+    Unrestricted,
+    /// No credential of this provider is open to the caller.
+    Denied,
+    /// Only these credential ids may be used.
+    Only(HashSet<i64>),
+}
+
+impl CredentialScope {
+    pub fn allowed(&self) -> Option<&HashSet<i64>> {
+        match self {
+            CredentialScope::Only(ids) => Some(ids),
+            _ => None,
+        }
+    }
+
+    pub fn permits(&self, credential_id: i64) -> bool {
+        match self {
+            CredentialScope::Unrestricted => true,
+            CredentialScope::Denied => false,
+            CredentialScope::Only(ids) => ids.contains(&credential_id),
+        }
+    }
+}
+
+pub(super) fn resolve(
+    snap: &StorageSnapshot,
+    org_id: Option<i64>,
+    provider: &str,
+) -> CredentialScope {
+    let provider_id = snap
+        .providers
+        .iter()
+        .find(|p| p.name == provider)
+        .map(|p| p.id);
+    let org_grants = org_id.map_or_else(Vec::new, |org_id| {
+        snap.org_grants
+            .iter()
+            .filter(|g| g.org_id == org_id)
+            .collect()
+    });
+    if !org_grants.is_empty() {
+        // A provider-wide grant (no credential) allows every credential of the provider.
+        let mut ids = HashSet::new();
+        for grant in org_grants
+            .iter()
+            .filter(|g| Some(g.provider_id) == provider_id)
+        {
+            match grant.credential_id {
+                None => return CredentialScope::Unrestricted,
+                Some(id) => {
+                    ids.insert(id);
+                }
+            }
+        }
+        return if ids.is_empty() {
+            CredentialScope::Denied
+        } else {
+            CredentialScope::Only(ids)
+        };
+    }
+
+    let Some(provider_id) = provider_id else {
+        return CredentialScope::Unrestricted;
+    };
+    let mut reserved = HashSet::new();
+    for grant in snap
+        .org_grants
+        .iter()
+        .filter(|g| g.provider_id == provider_id)
+    {
+        match grant.credential_id {
+            None => return CredentialScope::Denied,
+            Some(id) => {
+                reserved.insert(id);
+            }
+        }
+    }
+    if reserved.is_empty() {
+        return CredentialScope::Unrestricted;
+    }
+    let open: HashSet<i64> = snap
+        .credentials
+        .iter()
+        .filter(|c| c.provider_id == provider_id && !reserved.contains(&c.id))
+        .map(|c| c.id)
+        .collect();
+    if open.is_empty() {
+        CredentialScope::Denied
+    } else {
+        CredentialScope::Only(open)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use gproxy_provider_core::{AcquireError, Credential, CredentialPool, EventHub};
+    use gproxy_storage::{CredentialRow, OrgGrantRow, ProviderRow};
+    use time::OffsetDateTime;
+
+    use super::*;
+
+    const ORG_A: i64 = 1;
+    const ORG_B: i64 = 2;
+    const ORG_WITHOUT_GRANTS: i64 = 3;
+
+    fn snapshot(grants: &[(i64, i64, Option<i64>)]) -> StorageSnapshot {
+        let now = OffsetDateTime::UNIX_EPOCH;
+        let provider = |id: i64, name: &str| ProviderRow {
+            id,
+            name: name.to_string(),
+            config_json: serde_json::json!({}),
+            enabled: true,
+            updated_at: now,
+        };
+        let credential = |id: i64, provider_id: i64| CredentialRow {
+            id,
+            provider_id,
+            name: None,
+            settings_json: serde_json::json!({}),
+            secret_json: serde_json::json!({}),
+            enabled: true,
+            created_at: now,
+            updated_at: now,
+        };
+        StorageSnapshot {
+            global_config: None,
+            providers: vec![provider(10, "shared"), provider(20, "other")],
+            credentials: vec![credential(11, 10), credential(12, 10), credential(21, 20)],
+            organizations: Vec::new(),
+            org_grants: grants
+                .iter()
+                .enumerate()
+                .map(|(i, &(org_id, provider_id, credential_id))| OrgGrantRow {
+                    id: i as i64 + 1,
+                    org_id,
+                    provider_id,
+                    credential_id,
+                    created_at: now,
+                })
+                .collect(),
+            users: Vec::new(),
+            user_keys: Vec::new(),
+            model_profiles: Vec::new(),
+            experiments: Vec::new(),
+            secrets: Vec::new(),
+            log_views: Vec::new(),
+        }
+    }
+
+    fn only(ids: &[i64]) -> CredentialScope {
+        CredentialScope::Only(ids.iter().copied().collect())
+    }
+
+    #[test]
+    fn ungranted_providers_are_open_to_everyone() {
+        let snap = snapshot(&[(ORG_A, 10, Some(11))]);
+        for org in [None, Some(ORG_B), Some(ORG_WITHOUT_GRANTS)] {
+            assert_eq!(resolve(&snap, org, "other"), CredentialScope::Unrestricted);
+        }
+        // An org with grants stays limited to them.
+        assert_eq!(
+            resolve(&snap, Some(ORG_A), "other"),
+            CredentialScope::Denied
+        );
+    }
+
+    #[test]
+    fn granted_credentials_are_reserved_for_their_org() {
+        let snap = snapshot(&[(ORG_A, 10, Some(11)), (ORG_B, 20, None)]);
+        assert_eq!(resolve(&snap, Some(ORG_A), "shared"), only(&[11]));
+        for org in [None, Some(ORG_WITHOUT_GRANTS)] {
+            assert_eq!(resolve(&snap, org, "shared"), only(&[12]));
+        }
+        // ORG_B only holds a grant elsewhere.
+        assert_eq!(
+            resolve(&snap, Some(ORG_B), "shared"),
+            CredentialScope::Denied
+        );
+    }
+
+    #[test]
+    fn provider_wide_grants_reserve_the_whole_provider() {
+        let snap = snapshot(&[(ORG_A, 10, None)]);
+        assert_eq!(
+            resolve(&snap, Some(ORG_A), "shared"),
+            CredentialScope::Unrestricted
+        );
+        for org in [None, Some(ORG_B), Some(ORG_WITHOUT_GRANTS)] {
+            assert_eq!(resolve(&snap, org, "shared"), CredentialScope::Denied);
+        }
+
+        let snap = snapshot(&[(ORG_A, 10, Some(11)), (ORG_A, 10, Some(12))]);
+        assert_eq!(resolve(&snap, None, "shared"), CredentialScope::Denied);
+    }
+
+    #[tokio::test]
+    async fn callers_outside_the_org_never_acquire_its_credential() {
+        let snap = snapshot(&[(ORG_A, 10, Some(11))]);
+        let pool = CredentialPool::new(EventHub::new(16));
+        for id in [11, 12] {
+            pool.insert("shared", id, Credential::Plugin(serde_json::json!({})))
+                .await;
+        }
+        let acquire = |org: Option<i64>| {
+            let scope = resolve(&snap, org, "shared");
+            let pool = &pool;
+            async move {
+                pool.acquire_scoped("shared", None, scope.allowed())
+                    .await
+                    .map(|(id, _): (i64, Arc<Credential>)| id)
+            }
+        };
+        assert_eq!(acquire(Some(ORG_A)).await.ok(), Some(11));
+        assert_eq!(acquire(None).await.ok(), Some(12));
+        assert_eq!(acquire(Some(ORG_WITHOUT_GRANTS)).await.ok(), Some(12));
+
+        // Without an open credential left, outsiders get none rather than ORG_A's.
+        pool.set_enabled("shared", 12, false).await;
+        assert!(matches!(
+            acquire(None).await,
+            Err(AcquireError::NoActiveCredentials)
+        ));
+        assert_eq!(acquire(Some(ORG_A)).await.ok(), Some(11));
+    }
+}
@@ -0,0 +1,255 @@
+//! Debug capture: the next requests of an armed user key are recorded in full — the
+//! downstream request and response and every upstream attempt, i.e. the payload before
+//! and after each transform — into the `debug_captures` table, whatever
+//! `event_redact_sensitive` says. Regular sinks and subscribers still get those events
+//! redacted. Credentials in headers and queries stay masked in both.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use gproxy_provider_core::{Event, EventFilter};
+use gproxy_storage::{DebugCaptureRecord, Storage};
+use serde::Serialize;
+use time::OffsetDateTime;
+
+/// Most requests one arming may capture.
+pub const MAX_CAPTURE_REQUESTS: u32 = 100;
+pub const DEFAULT_CAPTURE_RETENTION: Duration = Duration::from_secs(24 * 60 * 60);
+/// Longest captured rows may be kept before the purge job deletes them.
+pub const MAX_CAPTURE_RETENTION: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+/// An armed capture that saw no traffic for this long is dropped.
+const ARM_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+/// Events of a captured request are expected within this long of its arrival.
+const TRACE_WINDOW: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DebugCaptureArm {
+    pub user_key_id: i64,
+    /// Requests still to be captured.
+    pub remaining: u32,
+    pub captured: u32,
+    pub retention_secs: u64,
+}
+
+#[derive(Debug)]
+struct Arm {
+    remaining: u32,
+    captured: u32,
+    retention: Duration,
+    touched: Instant,
+}
+
+#[derive(Debug)]
+struct Trace {
+    user_key_id: i64,
+    /// Whether regular sinks get the events without bodies.
+    redact_bodies: bool,
+    expires_at: OffsetDateTime,
+    started: Instant,
+}
+
+#[derive(Default)]
+pub struct DebugCaptures {
+    arms: Mutex<HashMap<i64, Arm>>,
+    traces: Mutex<HashMap<String, Trace>>,
+    storage: OnceLock<Arc<dyn Storage>>,
+}
+
+impl DebugCaptures {
+    /// Sets where captured events are written; until then they are dropped.
+    pub fn start(&self, storage: Arc<dyn Storage>) {
+        let _ = self.storage.set(storage);
+    }
+
+    /// Captures the next `requests` of `user_key_id`, replacing an earlier arming.
+    pub fn arm(&self, user_key_id: i64, requests: u32, retention: Duration) -> DebugCaptureArm {
+        let arm = Arm {
+            remaining: requests.clamp(1, MAX_CAPTURE_REQUESTS),
+            captured: 0,
+            retention: retention.min(MAX_CAPTURE_RETENTION),
+            touched: Instant::now(),
+        };
+        let view = arm_view(user_key_id, &arm);
+        self.arms
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(user_key_id, arm);
+        view
+    }
+
+    pub fn disarm(&self, user_key_id: i64) -> bool {
+        self.arms
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&user_key_id)
+            .is_some()
+    }
+
+    pub fn arms(&self) -> Vec<DebugCaptureArm> {
+        let mut arms = self.arms.lock().unwrap_or_else(|e| e.into_inner());
+        arms.retain(|_, arm| arm.touched.elapsed() < ARM_TTL);
+        let mut out: Vec<_> = arms.iter().map(|(id, arm)| arm_view(*id, arm)).collect();
+        out.sort_by_key(|arm| arm.user_key_id);
+        out
+    }
+
+    /// Takes one of `user_key_id`'s remaining captures for the request `trace_id`.
+    /// `redact_bodies` is what the regular sinks would get without the capture.
+    pub fn claim(&self, user_key_id: i64, trace_id: &str, redact_bodies: bool) -> bool {
+        let retention = {
+            let mut arms = self.arms.lock().unwrap_or_else(|e| e.into_inner());
+            let Some(arm) = arms.get_mut(&user_key_id) else {
+                return false;
+            };
+            if arm.touched.elapsed() >= ARM_TTL {
+                arms.remove(&user_key_id);
+                return false;
+            }
+            arm.remaining -= 1;
+            arm.captured += 1;
+            arm.touched = Instant::now();
+            let retention = arm.retention;
+            if arm.remaining == 0 {
+                arms.remove(&user_key_id);
+            }
+            retention
+        };
+        let mut traces = self.traces.lock().unwrap_or_else(|e| e.into_inner());
+        traces.retain(|_, trace| trace.started.elapsed() < TRACE_WINDOW);
+        traces.insert(
+            trace_id.to_string(),
+            Trace {
+                user_key_id,
+                redact_bodies,
+                expires_at: OffsetDateTime::now_utc() + retention,
+                started: Instant::now(),
+            },
+        );
+        true
+    }
+
+    /// Whether the request `trace_id` is being captured, so its events keep their bodies.
+    pub fn captures(&self, trace_id: Option<&str>) -> bool {
+        trace_id.is_some_and(|trace_id| {
+            self.traces
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .contains_key(trace_id)
+        })
+    }
+}
+
+fn arm_view(user_key_id: i64, arm: &Arm) -> DebugCaptureArm {
+    DebugCaptureArm {
+        user_key_id,
+        remaining: arm.remaining,
+        captured: arm.captured,
+        retention_secs: arm.retention.as_secs(),
+    }
+}
+
+impl EventFilter for DebugCaptures {
+    fn apply(&self, event: &mut Event) {
+        let (kind, trace_id) = match event {
+            Event::Downstream(ev) => ("downstream", ev.trace_id.clone()),
+            Event::Upstream(ev) => ("upstream", ev.trace_id.clone()),
+            Event::Operational(_) => return,
+        };
+        let Some(trace_id) = trace_id else {
+            return;
+        };
+        let (user_key_id, redact_bodies, expires_at) = {
+            let traces = self.traces.lock().unwrap_or_else(|e| e.into_inner());
+            let Some(trace) = traces.get(&trace_id) else {
+                return;
+            };
+            (trace.user_key_id, trace.redact_bodies, trace.expires_at)
+        };
+        if let Some(storage) = self.storage.get()
+            && let Ok(event_json) = event.to_log_value()
+        {
+            let record = DebugCaptureRecord {
+                id: 0,
+                trace_id,
+                user_key_id,
+                kind: kind.to_string(),
+                event_json,
+                at: OffsetDateTime::now_utc(),
+                expires_at,
+            };
+            let storage = storage.clone();
+            tokio::spawn(async move {
+                if let Err(err) = storage.append_debug_capture(&record).await {
+                    gproxy_common::log_error!("debug_capture", "store capture: {err}");
+                }
+            });
+        }
+        if redact_bodies {
+            let (request_body, response_body) = match event {
+                Event::Downstream(ev) => (&mut ev.request_body, &mut ev.response_body),
+                Event::Upstream(ev) => (&mut ev.request_body, &mut ev.response_body),
+                Event::Operational(_) => return,
+            };
+            *request_body = None;
+            *response_body = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::SystemTime;
+
+    use super::*;
+    use gproxy_provider_core::{DownstreamEvent, Headers};
+
+    fn downstream(trace_id: &str) -> Event {
+        Event::Downstream(DownstreamEvent {
+            trace_id: Some(trace_id.to_string()),
+            at: SystemTime::now(),
+            user_id: Some(1),
+            user_key_id: Some(7),
+            request_method: "POST".to_string(),
+            request_headers: Headers::new(),
+            request_path: "/v1/messages".to_string(),
+            request_query: None,
+            request_body: Some(b"question".to_vec()),
+            response_status: Some(200),
+            response_headers: Headers::new(),
+            response_body: Some(b"answer".to_vec()),
+            tags: Vec::new(),
+            latency_ms: None,
+            client_ip: None,
+            country: None,
+            asn: None,
+        })
+    }
+
+    #[test]
+    fn captures_the_next_requests_and_redacts_them_for_regular_sinks() {
+        let captures = DebugCaptures::default();
+        assert!(!captures.claim(7, "t-0", true));
+        captures.arm(7, 2, DEFAULT_CAPTURE_RETENTION);
+        assert!(captures.claim(7, "t-1", true));
+        assert!(captures.claim(7, "t-2", false));
+        assert!(!captures.claim(7, "t-3", true));
+        assert!(captures.arms().is_empty());
+        assert!(captures.captures(Some("t-1")));
+        assert!(!captures.captures(Some("t-3")));
+
+        let mut redacted = downstream("t-1");
+        captures.apply(&mut redacted);
+        let Event::Downstream(ev) = redacted else {
+            unreachable!()
+        };
+        assert!(ev.request_body.is_none() && ev.response_body.is_none());
+
+        let mut kept = downstream("t-2");
+        captures.apply(&mut kept);
+        let Event::Downstream(ev) = kept else {
+            unreachable!()
+        };
+        assert_eq!(ev.response_body.as_deref(), Some(&b"answer"[..]));
+    }
+}
@@ -0,0 +1,233 @@
+//! Differences between the in-memory snapshot and a fresh read of the database, for
+//! spotting hot-reload edits that did not land in memory (or the other way round).
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use gproxy_storage::{
+    CredentialRow, OrgGrantRow, OrganizationRow, ProviderRow, StorageSnapshot, UserKeyRow, UserRow,
+};
+
+/// Rows of one table that differ, by key. Timestamps are not compared.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SectionDrift<K> {
+    pub missing_in_memory: Vec<K>,
+    pub missing_in_db: Vec<K>,
+    pub changed: Vec<K>,
+}
+
+impl<K> SectionDrift<K> {
+    pub fn is_empty(&self) -> bool {
+        self.missing_in_memory.is_empty()
+            && self.missing_in_db.is_empty()
+            && self.changed.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SnapshotDrift {
+    /// Keyed by provider name.
+    pub providers: SectionDrift<String>,
+    pub credentials: SectionDrift<i64>,
+    pub organizations: SectionDrift<i64>,
+    pub org_grants: SectionDrift<i64>,
+    pub users: SectionDrift<i64>,
+    pub user_keys: SectionDrift<i64>,
+    /// Providers whose runtime is missing, orphaned, or serving another config than the
+    /// in-memory snapshot holds.
+    pub runtimes: Vec<String>,
+}
+
+impl SnapshotDrift {
+    /// Compares `memory` against `db`; `runtimes` is filled in by the caller.
+    pub fn between(memory: &StorageSnapshot, db: &StorageSnapshot) -> Self {
+        Self {
+            providers: diff_rows(
+                &memory.providers,
+                &db.providers,
+                |p| p.name.clone(),
+                same_provider,
+            ),
+            credentials: diff_rows(
+                &memory.credentials,
+                &db.credentials,
+                |c| c.id,
+                same_credential,
+            ),
+            organizations: diff_rows(&memory.organizations, &db.organizations, |o| o.id, same_org),
+            org_grants: diff_rows(&memory.org_grants, &db.org_grants, |g| g.id, same_grant),
+            users: diff_rows(&memory.users, &db.users, |u| u.id, same_user),
+            user_keys: diff_rows(&memory.user_keys, &db.user_keys, |k| k.id, same_user_key),
+            runtimes: Vec::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.providers.is_empty()
+            && self.credentials.is_empty()
+            && self.organizations.is_empty()
+            && self.org_grants.is_empty()
+            && self.users.is_empty()
+            && self.user_keys.is_empty()
+            && self.runtimes.is_empty()
+    }
+}
+
+fn diff_rows<T, K: Ord + Clone>(
+    memory: &[T],
+    db: &[T],
+    key: impl Fn(&T) -> K,
+    same: impl Fn(&T, &T) -> bool,
+) -> SectionDrift<K> {
+    let memory: BTreeMap<K, &T> = memory.iter().map(|row| (key(row), row)).collect();
+    let db: BTreeMap<K, &T> = db.iter().map(|row| (key(row), row)).collect();
+    SectionDrift {
+        missing_in_memory: db
+            .keys()
+            .filter(|k| !memory.contains_key(*k))
+            .cloned()
+            .collect(),
+        missing_in_db: memory
+            .keys()
+            .filter(|k| !db.contains_key(*k))
+            .cloned()
+            .collect(),
+        changed: memory
+            .iter()
+            .filter(|(k, row)| db.get(*k).is_some_and(|other| !same(row, other)))
+            .map(|(k, _)| k.clone())
+            .collect(),
+    }
+}
+
+fn same_provider(a: &ProviderRow, b: &ProviderRow) -> bool {
+    a.id == b.id && a.enabled == b.enabled && a.config_json == b.config_json
+}
+
+fn same_credential(a: &CredentialRow, b: &CredentialRow) -> bool {
+    a.provider_id == b.provider_id
+        && a.name == b.name
+        && a.enabled == b.enabled
+        && a.settings_json == b.settings_json
+        && a.secret_json == b.secret_json
+}
+
+fn same_org(a: &OrganizationRow, b: &OrganizationRow) -> bool {
+    a.name == b.name
+        && a.enabled == b.enabled
+        && a.admin_key == b.admin_key
+        && a.omit_bodies == b.omit_bodies
+        && a.rpm_limit == b.rpm_limit
+        && a.tpm_limit == b.tpm_limit
+        && a.monthly_token_budget == b.monthly_token_budget
+        && a.default_provider == b.default_provider
+        && a.default_model == b.default_model
+}
+
+fn same_grant(a: &OrgGrantRow, b: &OrgGrantRow) -> bool {
+    a.org_id == b.org_id && a.provider_id == b.provider_id && a.credential_id == b.credential_id
+}
+
+fn same_user(a: &UserRow, b: &UserRow) -> bool {
+    a.name == b.name && a.enabled == b.enabled && a.org_id == b.org_id
+}
+
+fn same_user_key(a: &UserKeyRow, b: &UserKeyRow) -> bool {
+    a.user_id == b.user_id
+        && a.api_key == b.api_key
+        && a.label == b.label
+        && a.enabled == b.enabled
+        && a.rpm_limit == b.rpm_limit
+        && a.tpm_limit == b.tpm_limit
+        && a.stream_tps_limit == b.stream_tps_limit
+        && a.max_output_tokens == b.max_output_tokens
+        && a.omit_bodies == b.omit_bodies
+        && a.default_provider == b.default_provider
+        && a.default_model == b.default_model
+        && a.mcp_policy == b.mcp_policy
+        && a.moderation_policy == b.moderation_policy
+        && a.prelude_template == b.prelude_template
+        && a.key_scope == b.key_scope
+        && a.expires_at == b.expires_at
+        && a.allowed_origins == b.allowed_origins
+        && a.body_sample_percent == b.body_sample_percent
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::OffsetDateTime;
+
+    fn snapshot(providers: Vec<ProviderRow>, user_keys: Vec<UserKeyRow>) -> StorageSnapshot {
+        StorageSnapshot {
+            global_config: None,
+            providers,
+            credentials: Vec::new(),
+            organizations: Vec::new(),
+            org_grants: Vec::new(),
+            users: Vec::new(),
+            user_keys,
+            model_profiles: Vec::new(),
+            experiments: Vec::new(),
+            secrets: Vec::new(),
+            log_views: Vec::new(),
+        }
+    }
+
+    fn provider(name: &str, config_json: serde_json::Value) -> ProviderRow {
+        ProviderRow {
+            id: 1,
+            name: name.to_string(),
+            config_json,
+            enabled: true,
+            updated_at: OffsetDateTime::now_utc(),
+        }
+    }
+
+    fn key(id: i64, enabled: bool) -> UserKeyRow {
+        let now = OffsetDateTime::now_utc();
+        UserKeyRow {
+            id,
+            user_id: 1,
+            api_key: format!("sk-{id}"),
+            label: None,
+            enabled,
+            rpm_limit: None,
+            tpm_limit: None,
+            stream_tps_limit: None,
+            max_output_tokens: None,
+            omit_bodies: false,
+            default_provider: None,
+            default_model: None,
+            mcp_policy: None,
+            moderation_policy: None,
+            prelude_template: None,
+            key_scope: None,
+            expires_at: None,
+            allowed_origins: None,
+            body_sample_percent: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    #[test]
+    fn reports_missing_and_changed_rows_but_not_timestamps() {
+        let memory = snapshot(
+            vec![provider("openai", serde_json::json!({ "kind": "openai" }))],
+            vec![key(1, true), key(2, true)],
+        );
+        let db = snapshot(
+            vec![provider("openai", serde_json::json!({ "kind": "openai" }))],
+            vec![key(1, false), key(3, true)],
+        );
+        let drift = SnapshotDrift::between(&memory, &db);
+        assert!(drift.providers.is_empty());
+        assert_eq!(drift.user_keys.changed, vec![1]);
+        assert_eq!(drift.user_keys.missing_in_db, vec![2]);
+        assert_eq!(drift.user_keys.missing_in_memory, vec![3]);
+        assert!(!drift.is_empty());
+        assert!(SnapshotDrift::between(&db, &db).is_empty());
+    }
+}
@@ -0,0 +1,152 @@
+//! Rotation over per-provider egress proxy pools, with proxies that fail to connect
+//! skipped until their cooldown passes.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use gproxy_provider_core::ProxyRotation;
+
+/// How long a proxy that failed to connect is left out of rotation.
+pub const DEAD_PROXY_COOLDOWN: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EgressProxyStatus {
+    pub proxy: String,
+    pub failures: u64,
+    /// Time left before the proxy rejoins rotation; `None` while it is healthy.
+    pub dead_for: Option<Duration>,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct ProxyHealth {
+    failures: u64,
+    dead_until: Option<Instant>,
+    last_error: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct EgressProxyPool {
+    health: Mutex<HashMap<String, ProxyHealth>>,
+    cursor: AtomicU64,
+}
+
+impl EgressProxyPool {
+    /// Picks the proxy for one attempt. Dead proxies are skipped unless every proxy of
+    /// the pool is dead, in which case the whole pool is used again.
+    pub fn pick(
+        &self,
+        proxies: &[String],
+        rotation: ProxyRotation,
+        credential_id: i64,
+    ) -> Option<String> {
+        if proxies.is_empty() {
+            return None;
+        }
+        let now = Instant::now();
+        let alive: Vec<&String> = {
+            let health = self.health.lock().unwrap_or_else(|e| e.into_inner());
+            proxies
+                .iter()
+                .filter(|proxy| {
+                    health
+                        .get(proxy.as_str())
+                        .and_then(|h| h.dead_until)
+                        .is_none_or(|until| until <= now)
+                })
+                .collect()
+        };
+        let candidates: Vec<&String> = if alive.is_empty() {
+            proxies.iter().collect()
+        } else {
+            alive
+        };
+        let index = match rotation {
+            ProxyRotation::Credential => credential_id.unsigned_abs(),
+            ProxyRotation::Request => self.cursor.fetch_add(1, Ordering::Relaxed),
+        } % candidates.len() as u64;
+        Some(candidates[index as usize].clone())
+    }
+
+    pub fn mark_dead(&self, proxy: &str, error: &str) {
+        let mut health = self.health.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = health.entry(proxy.to_string()).or_default();
+        entry.failures += 1;
+        entry.dead_until = Some(Instant::now() + DEAD_PROXY_COOLDOWN);
+        entry.last_error = Some(error.to_string());
+    }
+
+    pub fn mark_alive(&self, proxy: &str) {
+        let mut health = self.health.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(entry) = health.get_mut(proxy) {
+            entry.dead_until = None;
+        }
+    }
+
+    /// Proxies that have failed at least once.
+    pub fn snapshot(&self) -> Vec<EgressProxyStatus> {
+        let now = Instant::now();
+        let health = self.health.lock().unwrap_or_else(|e| e.into_inner());
+        let mut out: Vec<_> = health
+            .iter()
+            .map(|(proxy, h)| EgressProxyStatus {
+                proxy: proxy.clone(),
+                failures: h.failures,
+                dead_for: h
+                    .dead_until
+                    .filter(|until| *until > now)
+                    .map(|until| until - now),
+                last_error: h.last_error.clone(),
+            })
+            .collect();
+        out.sort_by(|a, b| a.proxy.cmp(&b.proxy));
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool() -> Vec<String> {
+        vec!["http://a:1".to_string(), "http://b:1".to_string()]
+    }
+
+    #[test]
+    fn credential_rotation_is_sticky_and_skips_dead_proxies() {
+        let proxies = EgressProxyPool::default();
+        let first = proxies.pick(&pool(), ProxyRotation::Credential, 7).unwrap();
+        assert_eq!(
+            proxies.pick(&pool(), ProxyRotation::Credential, 7),
+            Some(first.clone())
+        );
+
+        proxies.mark_dead(&first, "connect refused");
+        let other = proxies.pick(&pool(), ProxyRotation::Credential, 7).unwrap();
+        assert_ne!(other, first);
+
+        proxies.mark_dead(&other, "connect refused");
+        // Everything is dead: fall back to the whole pool rather than no proxy.
+        assert!(
+            proxies
+                .pick(&pool(), ProxyRotation::Credential, 7)
+                .is_some()
+        );
+
+        proxies.mark_alive(&first);
+        assert_eq!(
+            proxies.pick(&pool(), ProxyRotation::Credential, 7),
+            Some(first)
+        );
+    }
+
+    #[test]
+    fn request_rotation_cycles() {
+        let proxies = EgressProxyPool::default();
+        let a = proxies.pick(&pool(), ProxyRotation::Request, 1);
+        let b = proxies.pick(&pool(), ProxyRotation::Request, 1);
+        assert_ne!(a, b);
+    }
+}
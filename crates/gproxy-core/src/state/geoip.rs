@@ -0,0 +1,138 @@
+//! Optional client IP enrichment from local MaxMind databases (country and ASN).
+
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+use maxminddb::{Reader, geoip2};
+
+use gproxy_common::GlobalConfig;
+
+/// What is known about a client address.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GeoInfo {
+    /// Upper-cased ISO country code.
+    pub country: Option<String>,
+    pub asn: Option<u32>,
+}
+
+impl GeoInfo {
+    /// Takes the country an edge reported when the database had none.
+    pub fn or_edge_country(mut self, edge_country: Option<String>) -> Self {
+        if self.country.is_none() {
+            self.country = edge_country
+                .map(|country| country.trim().to_ascii_uppercase())
+                .filter(|country| !country.is_empty());
+        }
+        self
+    }
+}
+
+type Database = Arc<Reader<Vec<u8>>>;
+
+#[derive(Default)]
+struct Databases {
+    country_path: Option<String>,
+    asn_path: Option<String>,
+    country: Option<Database>,
+    asn: Option<Database>,
+}
+
+#[derive(Default)]
+pub struct GeoIpResolver {
+    databases: ArcSwap<Databases>,
+}
+
+impl std::fmt::Debug for GeoIpResolver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let databases = self.databases.load();
+        f.debug_struct("GeoIpResolver")
+            .field("country_path", &databases.country_path)
+            .field("asn_path", &databases.asn_path)
+            .finish()
+    }
+}
+
+impl GeoIpResolver {
+    /// Opens the databases named by the global config. Paths that did not change keep
+    /// their loaded reader; a database that fails to open is skipped.
+    pub fn configure(&self, global: &GlobalConfig) {
+        let current = self.databases.load();
+        let country_path = global.geoip_country_db.clone();
+        let asn_path = global.geoip_asn_db.clone();
+        if current.country_path == country_path && current.asn_path == asn_path {
+            return;
+        }
+        let reuse =
+            |path: &Option<String>, loaded_path: &Option<String>, loaded: &Option<Database>| {
+                if path == loaded_path {
+                    loaded.clone()
+                } else {
+                    path.as_deref().and_then(open_database)
+                }
+            };
+        let next = Databases {
+            country: reuse(&country_path, &current.country_path, &current.country),
+            asn: reuse(&asn_path, &current.asn_path, &current.asn),
+            country_path,
+            asn_path,
+        };
+        self.databases.store(Arc::new(next));
+    }
+
+    pub fn lookup(&self, ip: &str) -> GeoInfo {
+        let Ok(ip) = ip.trim().parse::<IpAddr>() else {
+            return GeoInfo::default();
+        };
+        let databases = self.databases.load();
+        let country = databases.country.as_ref().and_then(|reader| {
+            let record = reader.lookup::<geoip2::Country>(ip).ok()?;
+            record
+                .country
+                .and_then(|country| country.iso_code)
+                .map(|code| code.to_ascii_uppercase())
+        });
+        let asn = databases.asn.as_ref().and_then(|reader| {
+            reader
+                .lookup::<geoip2::Asn>(ip)
+                .ok()?
+                .autonomous_system_number
+        });
+        GeoInfo { country, asn }
+    }
+}
+
+fn open_database(path: &str) -> Option<Database> {
+    match Reader::open_readfile(path) {
+        Ok(reader) => Some(Arc::new(reader)),
+        Err(err) => {
+            gproxy_common::log_warn!("geoip", "failed to open {path}: {err}");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn database_country_wins_over_the_edge() {
+        let looked_up = GeoInfo {
+            country: Some("DE".to_string()),
+            asn: Some(3320),
+        };
+        assert_eq!(
+            looked_up.clone().or_edge_country(Some("us".to_string())),
+            looked_up
+        );
+        assert_eq!(
+            GeoInfo::default()
+                .or_edge_country(Some("us".to_string()))
+                .country
+                .as_deref(),
+            Some("US")
+        );
+        assert_eq!(GeoInfo::default().or_edge_country(None), GeoInfo::default());
+    }
+}
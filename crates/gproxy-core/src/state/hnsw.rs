@@ -0,0 +1,273 @@
+//! A small in-process HNSW (hierarchical navigable small world) index over vectors,
+//! searched by cosine similarity. Vectors are normalized on the way in, so similarity is
+//! a dot product. Removal leaves a tombstone that still routes searches but is never
+//! returned; owners rebuild the index once tombstones pile up.
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashSet};
+
+/// Links per node on the upper layers; the bottom layer keeps twice as many.
+const LINKS: usize = 12;
+const EF_CONSTRUCTION: usize = 64;
+const EF_SEARCH: usize = 48;
+const MAX_LEVEL: usize = 12;
+
+#[derive(Debug)]
+struct Node {
+    vector: Vec<f32>,
+    /// Neighbors per layer, `links[0]` being the bottom layer.
+    links: Vec<Vec<usize>>,
+    removed: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Scored {
+    distance: f32,
+    id: usize,
+}
+
+impl Eq for Scored {}
+
+impl PartialOrd for Scored {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Scored {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance
+            .total_cmp(&other.distance)
+            .then(self.id.cmp(&other.id))
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Hnsw {
+    nodes: Vec<Node>,
+    entry: Option<usize>,
+    removed: usize,
+}
+
+impl Hnsw {
+    /// Live (not removed) vectors.
+    pub(crate) fn len(&self) -> usize {
+        self.nodes.len() - self.removed
+    }
+
+    /// Removed vectors still held as routing nodes.
+    pub(crate) fn tombstones(&self) -> usize {
+        self.removed
+    }
+
+    /// Adds `vector` and returns its id; ids are dense and never reused.
+    pub(crate) fn insert(&mut self, mut vector: Vec<f32>) -> usize {
+        normalize(&mut vector);
+        let level = random_level();
+        let id = self.nodes.len();
+        self.nodes.push(Node {
+            vector,
+            links: vec![Vec::new(); level + 1],
+            removed: false,
+        });
+        let Some(mut entry) = self.entry else {
+            self.entry = Some(id);
+            return id;
+        };
+        let top = self.nodes[entry].links.len() - 1;
+        let query = self.nodes[id].vector.clone();
+        for layer in (level + 1..=top).rev() {
+            entry = self.search_layer(&query, &[entry], 1, layer)[0].id;
+        }
+        let mut entries = vec![entry];
+        for layer in (0..=level.min(top)).rev() {
+            let found = self.search_layer(&query, &entries, EF_CONSTRUCTION, layer);
+            let neighbors: Vec<usize> = found.iter().take(LINKS).map(|s| s.id).collect();
+            let max_links = if layer == 0 { LINKS * 2 } else { LINKS };
+            for &neighbor in &neighbors {
+                self.nodes[neighbor].links[layer].push(id);
+                if self.nodes[neighbor].links[layer].len() > max_links {
+                    self.prune(neighbor, layer, max_links);
+                }
+            }
+            self.nodes[id].links[layer] = neighbors;
+            entries = found.into_iter().map(|s| s.id).collect();
+        }
+        if level > top {
+            self.entry = Some(id);
+        }
+        id
+    }
+
+    /// Marks `id` removed; it no longer shows up in results.
+    pub(crate) fn remove(&mut self, id: usize) {
+        if let Some(node) = self.nodes.get_mut(id)
+            && !node.removed
+        {
+            node.removed = true;
+            self.removed += 1;
+        }
+    }
+
+    /// Up to `k` live vectors closest to `query`, most similar first, with their cosine
+    /// similarity.
+    pub(crate) fn search(&self, query: &[f32], k: usize) -> Vec<(usize, f32)> {
+        let Some(mut entry) = self.entry else {
+            return Vec::new();
+        };
+        let mut query = query.to_vec();
+        normalize(&mut query);
+        for layer in (1..self.nodes[entry].links.len()).rev() {
+            entry = self.search_layer(&query, &[entry], 1, layer)[0].id;
+        }
+        self.search_layer(&query, &[entry], EF_SEARCH.max(k), 0)
+            .into_iter()
+            .filter(|s| !self.nodes[s.id].removed)
+            .take(k)
+            .map(|s| (s.id, 1.0 - s.distance))
+            .collect()
+    }
+
+    /// Best-first search of one layer from `entries`; the `ef` closest nodes found,
+    /// closest first.
+    fn search_layer(
+        &self,
+        query: &[f32],
+        entries: &[usize],
+        ef: usize,
+        layer: usize,
+    ) -> Vec<Scored> {
+        let mut visited: HashSet<usize> = entries.iter().copied().collect();
+        let mut candidates = BinaryHeap::new();
+        let mut found = BinaryHeap::new();
+        for &id in entries {
+            let scored = self.scored(query, id);
+            candidates.push(Reverse(scored));
+            found.push(scored);
+        }
+        while found.len() > ef {
+            found.pop();
+        }
+        while let Some(Reverse(current)) = candidates.pop() {
+            if found.len() >= ef
+                && found
+                    .peek()
+                    .is_some_and(|worst: &Scored| current.distance > worst.distance)
+            {
+                break;
+            }
+            let Some(links) = self.nodes[current.id].links.get(layer) else {
+                continue;
+            };
+            for &next in links {
+                if !visited.insert(next) {
+                    continue;
+                }
+                let scored = self.scored(query, next);
+                if found.len() < ef
+                    || found
+                        .peek()
+                        .is_some_and(|worst: &Scored| scored.distance < worst.distance)
+                {
+                    candidates.push(Reverse(scored));
+                    found.push(scored);
+                    if found.len() > ef {
+                        found.pop();
+                    }
+                }
+            }
+        }
+        found.into_sorted_vec()
+    }
+
+    /// Keeps the `max_links` closest neighbors of `id` on `layer`.
+    fn prune(&mut self, id: usize, layer: usize, max_links: usize) {
+        let mut links: Vec<Scored> = self.nodes[id].links[layer]
+            .iter()
+            .map(|&other| Scored {
+                distance: distance(&self.nodes[id].vector, &self.nodes[other].vector),
+                id: other,
+            })
+            .collect();
+        links.sort();
+        links.truncate(max_links);
+        self.nodes[id].links[layer] = links.into_iter().map(|s| s.id).collect();
+    }
+
+    fn scored(&self, query: &[f32], id: usize) -> Scored {
+        Scored {
+            distance: distance(query, &self.nodes[id].vector),
+            id,
+        }
+    }
+}
+
+fn distance(a: &[f32], b: &[f32]) -> f32 {
+    1.0 - a.iter().zip(b).map(|(x, y)| x * y).sum::<f32>()
+}
+
+fn normalize(vector: &mut [f32]) {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|x| *x /= norm);
+    }
+}
+
+/// Level of a new node: geometric, so each layer holds about `1 / LINKS` of the one below.
+fn random_level() -> usize {
+    let uniform = rand::random::<f64>().max(f64::MIN_POSITIVE);
+    let level = (-uniform.ln() / (LINKS as f64).ln()).floor() as usize;
+    level.min(MAX_LEVEL)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn random_vector(dims: usize) -> Vec<f32> {
+        (0..dims).map(|_| rand::random::<f32>() - 0.5).collect()
+    }
+
+    #[test]
+    fn nearest_neighbors_match_a_linear_scan() {
+        let mut index = Hnsw::default();
+        let vectors: Vec<Vec<f32>> = (0..500).map(|_| random_vector(16)).collect();
+        for vector in &vectors {
+            index.insert(vector.clone());
+        }
+        let mut exact = 0;
+        for _ in 0..50 {
+            let mut query = random_vector(16);
+            let found = index.search(&query, 1);
+            normalize(&mut query);
+            let best = vectors
+                .iter()
+                .enumerate()
+                .map(|(id, v)| {
+                    let mut v = v.clone();
+                    normalize(&mut v);
+                    (id, 1.0 - distance(&query, &v))
+                })
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+                .unwrap();
+            if found[0].0 == best.0 {
+                exact += 1;
+            }
+        }
+        assert!(exact >= 45, "recall {exact}/50");
+    }
+
+    #[test]
+    fn removed_vectors_are_not_returned() {
+        let mut index = Hnsw::default();
+        let a = index.insert(vec![1.0, 0.0]);
+        let b = index.insert(vec![0.9, 0.1]);
+        index.insert(vec![0.0, 1.0]);
+        let found = index.search(&[2.0, 0.0], 1);
+        assert_eq!(found[0].0, a);
+        assert!((found[0].1 - 1.0).abs() < 1e-6);
+        index.remove(a);
+        assert_eq!(index.search(&[1.0, 0.0], 1)[0].0, b);
+        assert_eq!((index.len(), index.tombstones()), (2, 1));
+    }
+}
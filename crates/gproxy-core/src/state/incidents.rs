@@ -0,0 +1,165 @@
+//! Provider incidents: a provider whose credentials are all unavailable gets one open
+//! incident collecting the models asked for, the first failures and how many requests were
+//! refused, instead of a stream of individual errors. The proxy engine reports outages
+//! here; the `provider_incidents` job stores the incidents, resolves them once a
+//! credential is back and sends the notifications.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use time::OffsetDateTime;
+
+use gproxy_storage::IncidentRecord;
+
+/// Most models remembered per incident.
+const MAX_MODELS: usize = 50;
+/// Most failures kept per incident.
+const MAX_ERROR_SAMPLES: usize = 10;
+/// Longest failure text kept.
+const MAX_ERROR_CHARS: usize = 500;
+
+struct OpenIncident {
+    /// `id` is 0 until the job has stored it.
+    record: IncidentRecord,
+    /// Changed since the job last stored it.
+    dirty: bool,
+}
+
+#[derive(Default)]
+struct Tracked {
+    open: HashMap<String, OpenIncident>,
+    /// Resolved, waiting for the job to store them.
+    resolved: Vec<IncidentRecord>,
+}
+
+#[derive(Default)]
+pub struct ProviderIncidents {
+    tracked: Mutex<Tracked>,
+}
+
+impl ProviderIncidents {
+    /// Notes that every credential of `provider` is unavailable, opening an incident if it
+    /// has none. `error` is the upstream failure that cooled a credential down; `None`
+    /// counts a request refused for want of a credential.
+    pub fn observe(&self, provider: &str, model: Option<&str>, error: Option<String>) {
+        let mut tracked = self.lock();
+        let incident = tracked
+            .open
+            .entry(provider.to_string())
+            .or_insert_with(|| OpenIncident {
+                record: IncidentRecord {
+                    id: 0,
+                    provider: provider.to_string(),
+                    started_at: OffsetDateTime::now_utc(),
+                    ended_at: None,
+                    models: Vec::new(),
+                    error_samples: Vec::new(),
+                    refused_requests: 0,
+                },
+                dirty: true,
+            });
+        let record = &mut incident.record;
+        if let Some(model) = model
+            && record.models.len() < MAX_MODELS
+            && !record.models.iter().any(|m| m == model)
+        {
+            record.models.push(model.to_string());
+            incident.dirty = true;
+        }
+        match error {
+            Some(error) if record.error_samples.len() < MAX_ERROR_SAMPLES => {
+                record
+                    .error_samples
+                    .push(error.chars().take(MAX_ERROR_CHARS).collect());
+                incident.dirty = true;
+            }
+            Some(_) => {}
+            None => {
+                record.refused_requests += 1;
+                incident.dirty = true;
+            }
+        }
+    }
+
+    /// Providers with an open incident.
+    pub fn open_providers(&self) -> Vec<String> {
+        self.lock().open.keys().cloned().collect()
+    }
+
+    /// Closes the provider's incident as of `at`.
+    pub fn resolve(&self, provider: &str, at: OffsetDateTime) {
+        let mut tracked = self.lock();
+        if let Some(mut incident) = tracked.open.remove(provider) {
+            incident.record.ended_at = Some(at);
+            tracked.resolved.push(incident.record);
+        }
+    }
+
+    /// Incidents to store: open ones changed since the last call, then resolved ones.
+    pub fn take_changes(&self) -> Vec<IncidentRecord> {
+        let mut tracked = self.lock();
+        let mut changes: Vec<IncidentRecord> = tracked
+            .open
+            .values_mut()
+            .filter(|incident| incident.dirty)
+            .map(|incident| {
+                incident.dirty = false;
+                incident.record.clone()
+            })
+            .collect();
+        changes.append(&mut tracked.resolved);
+        changes
+    }
+
+    /// Remembers the id storage gave the provider's open incident.
+    pub fn stored(&self, provider: &str, started_at: OffsetDateTime, id: i64) {
+        if let Some(incident) = self
+            .lock()
+            .open
+            .get_mut(provider)
+            .filter(|incident| incident.record.started_at == started_at)
+        {
+            incident.record.id = id;
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Tracked> {
+        self.tracked.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_incident_per_outage() {
+        let incidents = ProviderIncidents::default();
+        incidents.observe(
+            "claude",
+            Some("claude-sonnet-4"),
+            Some("http_status_429".into()),
+        );
+        incidents.observe("claude", Some("claude-sonnet-4"), None);
+        incidents.observe("claude", Some("claude-opus-4"), None);
+
+        let changes = incidents.take_changes();
+        assert_eq!(changes.len(), 1);
+        let opened = &changes[0];
+        assert_eq!(opened.models, ["claude-sonnet-4", "claude-opus-4"]);
+        assert_eq!(opened.error_samples, ["http_status_429"]);
+        assert_eq!(opened.refused_requests, 2);
+        assert!(incidents.take_changes().is_empty());
+
+        incidents.stored("claude", opened.started_at, 7);
+        incidents.observe("claude", None, None);
+        assert_eq!(incidents.take_changes()[0].id, 7);
+
+        let now = OffsetDateTime::now_utc();
+        incidents.resolve("claude", now);
+        assert!(incidents.open_providers().is_empty());
+        let resolved = incidents.take_changes();
+        assert_eq!(resolved[0].ended_at, Some(now));
+        assert_eq!(resolved[0].refused_requests, 3);
+    }
+}
@@ -0,0 +1,183 @@
+//! Leaked-key detection: how many client IPs a user key is used from per minute, and
+//! whether it is used from a blocked country.
+
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use gproxy_common::GlobalConfig;
+
+const WINDOW: Duration = Duration::from_secs(60);
+/// Distinct IPs remembered per key and window; enough to report without growing unbounded.
+const MAX_TRACKED_IPS: usize = 64;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyAbuseRules {
+    /// More distinct client IPs than this within a minute trips the rule (0 disables).
+    pub max_ips: u64,
+    /// The IP rule only applies once the key made this many requests in the minute.
+    pub min_rpm: u64,
+    /// Upper-cased ISO country codes.
+    pub blocked_countries: Vec<String>,
+}
+
+impl KeyAbuseRules {
+    pub fn from_global(global: &GlobalConfig) -> Self {
+        Self {
+            max_ips: global.key_abuse_max_ips,
+            min_rpm: global.key_abuse_min_rpm,
+            blocked_countries: global
+                .key_abuse_blocked_countries
+                .as_deref()
+                .unwrap_or_default()
+                .split(',')
+                .map(|code| code.trim().to_ascii_uppercase())
+                .filter(|code| !code.is_empty())
+                .collect(),
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.max_ips > 0 || !self.blocked_countries.is_empty()
+    }
+}
+
+/// Why a key was judged leaked, with the evidence seen so far.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AbuseVerdict {
+    pub rule: &'static str,
+    pub requests: u64,
+    pub client_ips: Vec<String>,
+    pub country: Option<String>,
+}
+
+#[derive(Debug)]
+struct Window {
+    started: Instant,
+    requests: u64,
+    ips: BTreeSet<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct KeyAbuseGuard {
+    windows: Mutex<HashMap<i64, Window>>,
+}
+
+impl KeyAbuseGuard {
+    /// Records one request for the key and returns a verdict when a rule trips.
+    pub fn observe(
+        &self,
+        user_key_id: i64,
+        client_ip: Option<&str>,
+        country: Option<&str>,
+        rules: &KeyAbuseRules,
+    ) -> Option<AbuseVerdict> {
+        self.observe_at(user_key_id, client_ip, country, rules, Instant::now())
+    }
+
+    fn observe_at(
+        &self,
+        user_key_id: i64,
+        client_ip: Option<&str>,
+        country: Option<&str>,
+        rules: &KeyAbuseRules,
+        now: Instant,
+    ) -> Option<AbuseVerdict> {
+        let mut windows = self.windows.lock().unwrap_or_else(|e| e.into_inner());
+        let fresh = || Window {
+            started: now,
+            requests: 0,
+            ips: BTreeSet::new(),
+        };
+        let window = windows.entry(user_key_id).or_insert_with(fresh);
+        if now.duration_since(window.started) >= WINDOW {
+            *window = fresh();
+        }
+        window.requests += 1;
+        if let Some(ip) = client_ip
+            && window.ips.len() < MAX_TRACKED_IPS
+        {
+            window.ips.insert(ip.to_string());
+        }
+
+        let country = country.map(|code| code.trim().to_ascii_uppercase());
+        let rule = if country
+            .as_ref()
+            .is_some_and(|code| rules.blocked_countries.contains(code))
+        {
+            "blocked_country"
+        } else if rules.max_ips > 0
+            && window.ips.len() as u64 > rules.max_ips
+            && window.requests >= rules.min_rpm
+        {
+            "distinct_ips"
+        } else {
+            return None;
+        };
+        let window = windows.remove(&user_key_id)?;
+        Some(AbuseVerdict {
+            rule,
+            requests: window.requests,
+            client_ips: window.ips.into_iter().collect(),
+            country,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules() -> KeyAbuseRules {
+        KeyAbuseRules {
+            max_ips: 2,
+            min_rpm: 4,
+            blocked_countries: vec!["KP".to_string()],
+        }
+    }
+
+    #[test]
+    fn trips_on_ip_spread_only_past_min_rpm() {
+        let guard = KeyAbuseGuard::default();
+        let start = Instant::now();
+        for ip in ["1.1.1.1", "2.2.2.2", "3.3.3.3"] {
+            assert!(
+                guard
+                    .observe_at(1, Some(ip), None, &rules(), start)
+                    .is_none()
+            );
+        }
+        let verdict = guard
+            .observe_at(1, Some("1.1.1.1"), None, &rules(), start)
+            .unwrap();
+        assert_eq!(verdict.rule, "distinct_ips");
+        assert_eq!(verdict.requests, 4);
+        assert_eq!(verdict.client_ips.len(), 3);
+
+        // A new window starts clean.
+        let later = start + WINDOW;
+        for ip in ["1.1.1.1", "2.2.2.2", "3.3.3.3"] {
+            assert!(
+                guard
+                    .observe_at(1, Some(ip), None, &rules(), later)
+                    .is_none()
+            );
+        }
+    }
+
+    #[test]
+    fn trips_on_blocked_country() {
+        let guard = KeyAbuseGuard::default();
+        let now = Instant::now();
+        assert!(
+            guard
+                .observe_at(2, Some("9.9.9.9"), Some("us"), &rules(), now)
+                .is_none()
+        );
+        let verdict = guard
+            .observe_at(2, Some("9.9.9.9"), Some("kp"), &rules(), now)
+            .unwrap();
+        assert_eq!(verdict.rule, "blocked_country");
+        assert_eq!(verdict.country.as_deref(), Some("KP"));
+    }
+}
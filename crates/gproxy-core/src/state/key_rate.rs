@@ -0,0 +1,280 @@
+//! Per-key request and token counters over fixed one-minute windows.
+//!
+//! Only keys with a configured limit are tracked. Token usage arrives through the event
+//! hub, so the token count lags the request that produced it.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+use gproxy_provider_core::{Event, EventSink, Headers, Proto, header_set};
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyLimits {
+    pub rpm: Option<u64>,
+    pub tpm: Option<u64>,
+}
+
+impl KeyLimits {
+    pub fn new(rpm: Option<i64>, tpm: Option<i64>) -> Self {
+        let positive = |v: Option<i64>| v.and_then(|v| u64::try_from(v).ok()).filter(|v| *v > 0);
+        Self {
+            rpm: positive(rpm),
+            tpm: positive(tpm),
+        }
+    }
+
+    pub fn is_unlimited(&self) -> bool {
+        self.rpm.is_none() && self.tpm.is_none()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quota {
+    pub limit: u64,
+    pub remaining: u64,
+    pub reset_after: Duration,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KeyRateStatus {
+    pub requests: Option<Quota>,
+    pub tokens: Option<Quota>,
+}
+
+impl KeyRateStatus {
+    pub fn exhausted(&self) -> bool {
+        [self.requests, self.tokens]
+            .into_iter()
+            .flatten()
+            .any(|q| q.remaining == 0)
+    }
+
+    /// Time until every exhausted quota resets.
+    pub fn retry_after(&self) -> Duration {
+        [self.requests, self.tokens]
+            .into_iter()
+            .flatten()
+            .filter(|q| q.remaining == 0)
+            .map(|q| q.reset_after)
+            .max()
+            .unwrap_or_default()
+    }
+}
+
+/// Rate-limit headers in the shape the caller's SDK reads for backoff. Gemini has no
+/// standard headers, so it gets none.
+pub fn rate_limit_headers(proto: Proto, status: &KeyRateStatus, headers: &mut Headers) {
+    let quotas = [("requests", status.requests), ("tokens", status.tokens)];
+    for (kind, quota) in quotas {
+        let Some(quota) = quota else {
+            continue;
+        };
+        match proto {
+            Proto::Claude => {
+                let reset = OffsetDateTime::now_utc() + quota.reset_after;
+                let reset = reset
+                    .format(&Rfc3339)
+                    .unwrap_or_else(|_| reset.unix_timestamp().to_string());
+                let prefix = format!("anthropic-ratelimit-{kind}");
+                header_set(headers, format!("{prefix}-limit"), quota.limit.to_string());
+                header_set(
+                    headers,
+                    format!("{prefix}-remaining"),
+                    quota.remaining.to_string(),
+                );
+                header_set(headers, format!("{prefix}-reset"), reset);
+            }
+            Proto::OpenAI | Proto::OpenAIChat | Proto::OpenAIResponse => {
+                let reset = format!("{}s", quota.reset_after.as_secs().max(1));
+                header_set(
+                    headers,
+                    format!("x-ratelimit-limit-{kind}"),
+                    quota.limit.to_string(),
+                );
+                header_set(
+                    headers,
+                    format!("x-ratelimit-remaining-{kind}"),
+                    quota.remaining.to_string(),
+                );
+                header_set(headers, format!("x-ratelimit-reset-{kind}"), reset);
+            }
+            Proto::Gemini => {}
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Window {
+    started: Instant,
+    requests: u64,
+    tokens: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct KeyRateCounters {
+    windows: Mutex<HashMap<i64, Window>>,
+}
+
+impl KeyRateCounters {
+    /// Counts one request for the key unless a limit is already used up. Returns the
+    /// status after counting, or the exhausted status when the request is rejected.
+    pub fn admit(
+        &self,
+        user_key_id: i64,
+        limits: KeyLimits,
+    ) -> Result<KeyRateStatus, KeyRateStatus> {
+        self.admit_at(user_key_id, limits, Instant::now())
+    }
+
+    fn admit_at(
+        &self,
+        user_key_id: i64,
+        limits: KeyLimits,
+        now: Instant,
+    ) -> Result<KeyRateStatus, KeyRateStatus> {
+        let mut windows = self.windows.lock().unwrap_or_else(|e| e.into_inner());
+        let window = windows.entry(user_key_id).or_insert(Window {
+            started: now,
+            requests: 0,
+            tokens: 0,
+        });
+        if now.duration_since(window.started) >= WINDOW {
+            *window = Window {
+                started: now,
+                requests: 0,
+                tokens: 0,
+            };
+        }
+        let before = status(window, limits, now);
+        if before.exhausted() {
+            return Err(before);
+        }
+        window.requests += 1;
+        Ok(status(window, limits, now))
+    }
+
+    pub fn record_tokens(&self, user_key_id: i64, tokens: u64) {
+        let mut windows = self.windows.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(window) = windows.get_mut(&user_key_id)
+            && window.started.elapsed() < WINDOW
+        {
+            window.tokens = window.tokens.saturating_add(tokens);
+        }
+    }
+}
+
+fn status(window: &Window, limits: KeyLimits, now: Instant) -> KeyRateStatus {
+    let reset_after = WINDOW.saturating_sub(now.duration_since(window.started));
+    let quota = |limit: Option<u64>, used: u64| {
+        limit.map(|limit| Quota {
+            limit,
+            remaining: limit.saturating_sub(used),
+            reset_after,
+        })
+    };
+    KeyRateStatus {
+        requests: quota(limits.rpm, window.requests),
+        tokens: quota(limits.tpm, window.tokens),
+    }
+}
+
+impl EventSink for KeyRateCounters {
+    fn write<'a>(&'a self, event: &'a Event) -> Pin<Box<dyn Future<Output = ()> + Send + 'a>> {
+        Box::pin(async move {
+            let Event::Upstream(ev) = event else {
+                return;
+            };
+            let (Some(user_key_id), Some(usage)) = (ev.user_key_id, ev.usage.as_ref()) else {
+                return;
+            };
+            let tokens = u64::from(usage.input_tokens.unwrap_or(0))
+                + u64::from(usage.output_tokens.unwrap_or(0));
+            if tokens > 0 {
+                self.record_tokens(user_key_id, tokens);
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_once_requests_are_used_up_and_resets_with_window() {
+        let counters = KeyRateCounters::default();
+        let limits = KeyLimits::new(Some(2), None);
+        let start = Instant::now();
+        let first = counters.admit_at(1, limits, start).unwrap();
+        assert_eq!(first.requests.unwrap().remaining, 1);
+        assert_eq!(
+            counters
+                .admit_at(1, limits, start)
+                .unwrap()
+                .requests
+                .unwrap()
+                .remaining,
+            0
+        );
+        let rejected = counters.admit_at(1, limits, start).unwrap_err();
+        assert_eq!(rejected.retry_after(), WINDOW);
+        assert!(counters.admit_at(1, limits, start + WINDOW).is_ok());
+    }
+
+    #[test]
+    fn token_usage_counts_against_tpm() {
+        let counters = KeyRateCounters::default();
+        let limits = KeyLimits::new(None, Some(100));
+        counters.admit(3, limits).unwrap();
+        counters.record_tokens(3, 100);
+        assert!(
+            counters
+                .admit(3, limits)
+                .unwrap_err()
+                .tokens
+                .unwrap()
+                .remaining
+                == 0
+        );
+        // Keys without a window are not tracked.
+        counters.record_tokens(4, 10);
+        assert!(!counters.windows.lock().unwrap().contains_key(&4));
+    }
+
+    #[test]
+    fn headers_follow_caller_protocol() {
+        let status = KeyRateStatus {
+            requests: Some(Quota {
+                limit: 10,
+                remaining: 4,
+                reset_after: Duration::from_secs(30),
+            }),
+            tokens: None,
+        };
+        let mut openai = Headers::new();
+        rate_limit_headers(Proto::OpenAIChat, &status, &mut openai);
+        assert_eq!(
+            openai,
+            vec![
+                ("x-ratelimit-limit-requests".to_string(), "10".to_string()),
+                (
+                    "x-ratelimit-remaining-requests".to_string(),
+                    "4".to_string()
+                ),
+                ("x-ratelimit-reset-requests".to_string(), "30s".to_string()),
+            ]
+        );
+        let mut claude = Headers::new();
+        rate_limit_headers(Proto::Claude, &status, &mut claude);
+        assert_eq!(claude.len(), 3);
+        assert_eq!(claude[1].0, "anthropic-ratelimit-requests-remaining");
+    }
+}
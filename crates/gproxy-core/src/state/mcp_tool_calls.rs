@@ -0,0 +1,28 @@
+//! MCP tool calls seen in Responses answers, written to the `mcp_tool_calls` table.
+
+use std::sync::{Arc, OnceLock};
+
+use gproxy_storage::{McpToolCallRecord, Storage};
+
+#[derive(Default)]
+pub struct McpToolCalls {
+    storage: OnceLock<Arc<dyn Storage>>,
+}
+
+impl McpToolCalls {
+    /// Sets where calls are written; until then they are dropped.
+    pub fn start(&self, storage: Arc<dyn Storage>) {
+        let _ = self.storage.set(storage);
+    }
+
+    pub fn record(&self, record: McpToolCallRecord) {
+        let Some(storage) = self.storage.get().cloned() else {
+            return;
+        };
+        tokio::spawn(async move {
+            if let Err(err) = storage.append_mcp_tool_call(&record).await {
+                gproxy_common::log_error!("mcp", "store tool call: {err}");
+            }
+        });
+    }
+}
@@ -1,27 +1,212 @@
+mod aborted_streams;
+mod batch_usage;
+mod body_retention;
+mod body_sampling;
+mod canary;
+mod config_events;
+mod credential_drain;
+mod credential_scope;
+mod debug_capture;
+mod drift;
+mod egress_proxies;
+mod geoip;
+mod hnsw;
+mod incidents;
+mod key_abuse;
+mod key_rate;
+mod mcp_tool_calls;
+mod org_limits;
+mod resource_owners;
+mod semantic_cache;
+mod sse_replay;
+mod stats;
+mod system_mode;
+mod tool_calls;
+mod transcoder;
+mod trusted_proxies;
+mod upstream_pool;
+
 use std::collections::HashMap;
+use std::convert::Infallible;
 use std::sync::Arc;
 
 use anyhow::Context;
-use arc_swap::ArcSwap;
+use arc_swap::{ArcSwap, ArcSwapOption};
 use time::OffsetDateTime;
 
 use gproxy_common::GlobalConfig;
 use gproxy_common::GlobalConfigPatch;
-use gproxy_provider_core::{Credential, CredentialPool, EventHub};
-use gproxy_storage::{CredentialRow, ProviderRow, StorageSnapshot, UserKeyRow, UserRow};
+use gproxy_provider_core::{
+    Credential, CredentialPool, EventHub, ProviderConfig, ProviderPolicies,
+};
+use gproxy_storage::{
+    CredentialRow, ExperimentRow, LogViewRow, ModelProfileRow, OrgGrantRow, OrganizationRow,
+    ProviderRow, SecretRow, StorageSnapshot, UserKeyRow, UserRow,
+};
+
+use crate::jobs::JobScheduler;
+
+pub use aborted_streams::AbortedStreams;
+pub use batch_usage::{ACCOUNTED_BATCH_TTL, AccountedBatches};
+pub use body_retention::BodyRetention;
+pub use body_sampling::BodySampler;
+pub use canary::{CanarySettings, ProviderCanary};
+pub use config_events::{
+    CONFIG_EVENT_BUFFER, ConfigAction, ConfigEntity, ConfigEvent, ConfigEvents,
+};
+pub use credential_drain::{
+    CredentialDrains, DRAIN_STATUS_RETENTION, DrainAction, DrainPhase, DrainStatus,
+    MAX_DRAIN_TIMEOUT,
+};
+pub use credential_scope::CredentialScope;
+pub use debug_capture::{
+    DEFAULT_CAPTURE_RETENTION, DebugCaptureArm, DebugCaptures, MAX_CAPTURE_REQUESTS,
+    MAX_CAPTURE_RETENTION,
+};
+pub use drift::{SectionDrift, SnapshotDrift};
+pub use egress_proxies::{DEAD_PROXY_COOLDOWN, EgressProxyPool, EgressProxyStatus};
+pub use geoip::{GeoInfo, GeoIpResolver};
+pub use incidents::ProviderIncidents;
+pub use key_abuse::{AbuseVerdict, KeyAbuseGuard, KeyAbuseRules};
+pub use key_rate::{KeyLimits, KeyRateCounters, KeyRateStatus, Quota, rate_limit_headers};
+pub use mcp_tool_calls::McpToolCalls;
+pub use org_limits::{OrgLimits, OrgRejection, month_start};
+pub use resource_owners::{MAX_RESOURCE_OWNERS, RESOURCE_OWNER_TTL, ResourceOwners};
+pub use semantic_cache::{SEMANTIC_CACHE_FILE, SemanticCache, SemanticHit};
+pub use sse_replay::{SSE_REPLAY_EVENTS, SSE_REPLAY_RETENTION, SseReplay};
+pub use stats::{
+    ActiveStreamGuard, LatencyHistogram, SeriesStats, StatsDimension, StatsWindow, TrafficStats,
+};
+pub use system_mode::{DEFAULT_MAINTENANCE_MESSAGE, SystemMode, SystemModeStatus};
+pub use tool_calls::ToolCalls;
+pub use transcoder::{TranscoderPool, TranscoderStatus};
+pub use trusted_proxies::TrustedProxies;
+pub use upstream_pool::{
+    ConnectFailure, DNS_CACHE_TTL, DnsCacheEntry, HostPoolStats, InFlightGuard,
+    UpstreamPoolSnapshot, UpstreamPoolStats,
+};
 
 pub struct ProviderRuntime {
     pub provider_id: String,
-    /// Provider config as JSON for now (parsed into typed ProviderConfig later).
+    /// Provider config as JSON; [`ProviderRuntime::provider_config`] and
+    /// [`ProviderRuntime::policies`] give it parsed.
     pub config_json: ArcSwap<serde_json::Value>,
     pub pool: CredentialPool,
+    /// Pending config edit serving a share of traffic; runtime only, dropped on restart.
+    pub canary: ArcSwapOption<ProviderCanary>,
+    /// `config_json` as last parsed, with the value it was parsed from.
+    parsed_config: ArcSwapOption<Parsed<ProviderConfig>>,
+    parsed_policies: ArcSwapOption<Parsed<ProviderPolicies>>,
+}
+
+struct Parsed<T> {
+    source: Arc<serde_json::Value>,
+    value: Arc<T>,
+}
+
+/// The value parsed from `source`, reusing the one in `slot` when it was parsed from the
+/// same `Arc`.
+fn parse_once<T, E>(
+    slot: &ArcSwapOption<Parsed<T>>,
+    source: Arc<serde_json::Value>,
+    parse: impl FnOnce(&serde_json::Value) -> Result<T, E>,
+) -> Result<Arc<T>, E> {
+    if let Some(parsed) = slot.load().as_ref()
+        && Arc::ptr_eq(&parsed.source, &source)
+    {
+        return Ok(parsed.value.clone());
+    }
+    let value = Arc::new(parse(&source)?);
+    slot.store(Some(Arc::new(Parsed {
+        source,
+        value: value.clone(),
+    })));
+    Ok(value)
+}
+
+impl ProviderRuntime {
+    pub fn new(provider_id: String, config_json: serde_json::Value, pool: CredentialPool) -> Self {
+        Self {
+            provider_id,
+            config_json: ArcSwap::from_pointee(config_json),
+            pool,
+            canary: ArcSwapOption::empty(),
+            parsed_config: ArcSwapOption::empty(),
+            parsed_policies: ArcSwapOption::empty(),
+        }
+    }
+
+    /// The typed provider config. It is parsed once per `config_json` value: storing a
+    /// new value makes the next call parse again.
+    pub fn provider_config(&self) -> Result<Arc<ProviderConfig>, serde_json::Error> {
+        parse_once(
+            &self.parsed_config,
+            self.config_json.load_full(),
+            |source| serde_json::from_value::<ProviderConfig>(source.clone()),
+        )
+    }
+
+    /// The policies beside the typed config, parsed once per `config_json` value like
+    /// [`ProviderRuntime::provider_config`].
+    pub fn policies(&self) -> Arc<ProviderPolicies> {
+        parse_once(
+            &self.parsed_policies,
+            self.config_json.load_full(),
+            |source| Ok::<_, Infallible>(ProviderPolicies::from_config_json(source)),
+        )
+        .unwrap_or_else(|never| match never {})
+    }
 }
 
 pub struct AppState {
     pub global: ArcSwap<GlobalConfig>,
     pub providers: ArcSwap<HashMap<String, Arc<ProviderRuntime>>>,
-    pub snapshot: ArcSwap<StorageSnapshot>,
+    /// Shared with the event filter that drops bodies of privacy-tier keys.
+    pub snapshot: Arc<ArcSwap<StorageSnapshot>>,
     pub events: EventHub,
+    /// Per-key rate counters; fed with token usage through the event hub.
+    pub key_rates: Arc<KeyRateCounters>,
+    /// Per-organization rate windows and monthly token budgets; fed like `key_rates`.
+    pub org_limits: Arc<OrgLimits>,
+    /// Per-key client IP tracking for leaked-key detection.
+    pub key_abuse: KeyAbuseGuard,
+    /// Client country/ASN lookup; empty unless MaxMind databases are configured.
+    pub geoip: GeoIpResolver,
+    /// Rolling traffic statistics for the admin overview.
+    pub stats: Arc<TrafficStats>,
+    /// Upstream client connection/DNS bookkeeping, shared with the upstream client.
+    pub upstream_pool: Arc<UpstreamPoolStats>,
+    /// Rotation and health of provider egress proxy pools.
+    pub egress_proxies: EgressProxyPool,
+    /// Credential that created each known response/conversation/file id.
+    pub resource_owners: Arc<ResourceOwners>,
+    /// Finished batches whose per-request usage has been recorded.
+    pub accounted_batches: AccountedBatches,
+    /// Recent events of downstream SSE streams, for `Last-Event-ID` reconnects.
+    pub sse_replay: SseReplay,
+    /// Periodic background jobs; started by bootstrap once storage is connected.
+    pub jobs: Arc<JobScheduler>,
+    /// Changes applied through the `apply_*` methods, for sidecars and the admin stream.
+    pub config_events: ConfigEvents,
+    /// Credentials waiting for their in-flight requests before a disable or delete.
+    pub credential_drains: CredentialDrains,
+    /// User keys whose next requests are recorded unredacted to the debug capture table.
+    pub debug_captures: Arc<DebugCaptures>,
+    /// Answers of earlier generate requests, looked up by prompt embedding.
+    pub semantic_cache: Arc<SemanticCache>,
+    /// MCP tool calls of Responses answers, logged to the `mcp_tool_calls` table.
+    pub mcp_tool_calls: McpToolCalls,
+    /// Client tool calls of generate answers, logged to the `tool_calls` table when
+    /// `tool_call_audit` is on.
+    pub tool_calls: ToolCalls,
+    /// Generate streams that ended early, logged to the `aborted_streams` table.
+    pub aborted_streams: AbortedStreams,
+    /// Maintenance and read-only switches of the admin API.
+    pub system_mode: SystemMode,
+    /// Open provider outages, stored to the `incidents` table by the `provider_incidents` job.
+    pub incidents: ProviderIncidents,
+    /// Worker threads that convert cross-protocol stream chunks off the runtime.
+    pub transcoder: TranscoderPool,
 }
 
 pub struct CredentialInsertInput {
@@ -46,11 +231,11 @@ impl AppState {
         // Create per-provider runtimes first.
         for p in &snapshot.providers {
             provider_id_to_name.insert(p.id, p.name.clone());
-            let runtime = ProviderRuntime {
-                provider_id: p.name.clone(),
-                config_json: ArcSwap::from_pointee(p.config_json.clone()),
-                pool: CredentialPool::new(events.clone()),
-            };
+            let runtime = ProviderRuntime::new(
+                p.name.clone(),
+                p.config_json.clone(),
+                CredentialPool::new(events.clone()),
+            );
             providers.insert(p.name.clone(), Arc::new(runtime));
         }
 
@@ -70,16 +255,64 @@ impl AppState {
             runtime.pool.insert(provider_name.clone(), c.id, cred).await;
         }
 
+        let key_rates = Arc::new(KeyRateCounters::default());
+        events.add_sink(key_rates.clone()).await;
+        let stats = Arc::new(TrafficStats::default());
+        events.add_sink(stats.clone()).await;
+        let geoip = GeoIpResolver::default();
+        geoip.configure(&global);
+        let snapshot = Arc::new(ArcSwap::from_pointee(snapshot));
+        let org_limits = Arc::new(OrgLimits::new(snapshot.clone()));
+        events.add_sink(org_limits.clone()).await;
+        events
+            .add_filter(Arc::new(BodyRetention::new(snapshot.clone())))
+            .await;
+        // After body retention, so privacy-tier bodies are never captured either.
+        let debug_captures = Arc::new(DebugCaptures::default());
+        events.add_filter(debug_captures.clone()).await;
+        // After debug captures, so captured requests are recorded in full.
+        events
+            .add_filter(Arc::new(BodySampler::new(snapshot.clone())))
+            .await;
+
         Ok(Self {
             global: ArcSwap::from_pointee(global),
             providers: ArcSwap::from_pointee(providers),
-            snapshot: ArcSwap::from_pointee(snapshot),
+            snapshot,
             events,
+            key_rates,
+            org_limits,
+            key_abuse: KeyAbuseGuard::default(),
+            geoip,
+            stats,
+            upstream_pool: Arc::new(UpstreamPoolStats::default()),
+            egress_proxies: EgressProxyPool::default(),
+            resource_owners: Arc::new(ResourceOwners::default()),
+            accounted_batches: AccountedBatches::default(),
+            sse_replay: SseReplay::default(),
+            jobs: Arc::new(JobScheduler::new()),
+            config_events: ConfigEvents::default(),
+            credential_drains: CredentialDrains::default(),
+            debug_captures,
+            semantic_cache: Arc::new(SemanticCache::default()),
+            mcp_tool_calls: McpToolCalls::default(),
+            tool_calls: ToolCalls::default(),
+            aborted_streams: AbortedStreams::default(),
+            system_mode: SystemMode::default(),
+            incidents: ProviderIncidents::default(),
+            transcoder: TranscoderPool::default(),
         })
     }
 
     pub fn apply_global_config(&self, config: GlobalConfig) {
+        self.geoip.configure(&config);
         self.global.store(Arc::new(config));
+        self.config_events.publish(
+            ConfigEntity::GlobalConfig,
+            ConfigAction::Updated,
+            None,
+            None,
+        );
     }
 
     pub fn apply_provider_upsert(
@@ -93,6 +326,7 @@ impl AppState {
 
         // 1) Update snapshot (admin/proxy reads only).
         let mut snap = self.snapshot.load().as_ref().clone();
+        let action = upsert_action(snap.providers.iter().any(|p| p.name == name));
         match snap.providers.iter_mut().find(|p| p.name == name) {
             Some(p) => {
                 p.id = id;
@@ -113,19 +347,64 @@ impl AppState {
         // 2) Ensure a runtime exists (used by proxy engine for upstream IO).
         let mut map = self.providers.load().as_ref().clone();
         match map.get(&name) {
-            Some(rt) => rt.config_json.store(Arc::new(config_json)),
+            Some(rt) => {
+                rt.config_json.store(Arc::new(config_json));
+                // A direct edit supersedes any canary in flight.
+                rt.canary.store(None);
+            }
             None => {
                 map.insert(
                     name.clone(),
-                    Arc::new(ProviderRuntime {
-                        provider_id: name.clone(),
-                        config_json: ArcSwap::from_pointee(config_json),
-                        pool: CredentialPool::new(self.events.clone()),
-                    }),
+                    Arc::new(ProviderRuntime::new(
+                        name.clone(),
+                        config_json,
+                        CredentialPool::new(self.events.clone()),
+                    )),
                 );
                 self.providers.store(Arc::new(map));
             }
         }
+        self.config_events
+            .publish(ConfigEntity::Provider, action, Some(id), Some(&name));
+    }
+
+    /// Starts serving `config_json` to a share of the provider's traffic, replacing any
+    /// earlier canary. Returns `false` when the provider has no runtime.
+    pub fn start_provider_canary(
+        &self,
+        name: &str,
+        config_json: serde_json::Value,
+        settings: CanarySettings,
+    ) -> bool {
+        let Some(runtime) = self.providers.load().get(name).cloned() else {
+            return false;
+        };
+        runtime
+            .canary
+            .store(Some(Arc::new(ProviderCanary::new(config_json, settings))));
+        self.config_events.publish(
+            ConfigEntity::ProviderCanary,
+            ConfigAction::Created,
+            None,
+            Some(name),
+        );
+        true
+    }
+
+    pub fn provider_canary(&self, name: &str) -> Option<Arc<ProviderCanary>> {
+        self.providers.load().get(name)?.canary.load_full()
+    }
+
+    /// Drops the provider's canary, returning it.
+    pub fn clear_provider_canary(&self, name: &str) -> Option<Arc<ProviderCanary>> {
+        let canary = self.providers.load().get(name)?.canary.swap(None)?;
+        self.config_events.publish(
+            ConfigEntity::ProviderCanary,
+            ConfigAction::Deleted,
+            None,
+            Some(name),
+        );
+        Some(canary)
     }
 
     pub fn apply_provider_delete(&self, name: &str) {
@@ -135,6 +414,7 @@ impl AppState {
         snap.providers.retain(|p| p.name != name);
         if let Some(pid) = provider_id {
             snap.credentials.retain(|c| c.provider_id != pid);
+            snap.org_grants.retain(|g| g.provider_id != pid);
         }
         self.snapshot.store(Arc::new(snap));
 
@@ -142,17 +422,51 @@ impl AppState {
         let mut map = self.providers.load().as_ref().clone();
         map.remove(name);
         self.providers.store(Arc::new(map));
+        self.config_events.publish(
+            ConfigEntity::Provider,
+            ConfigAction::Deleted,
+            provider_id,
+            Some(name),
+        );
     }
 
     pub fn apply_credential_delete(&self, credential_id: i64) {
         let mut snap = self.snapshot.load().as_ref().clone();
         snap.credentials.retain(|c| c.id != credential_id);
+        snap.org_grants
+            .retain(|g| g.credential_id != Some(credential_id));
         self.snapshot.store(Arc::new(snap));
+        self.config_events.publish(
+            ConfigEntity::Credential,
+            ConfigAction::Deleted,
+            Some(credential_id),
+            None,
+        );
         // Pool removal is handled by disabling (set_enabled=false); for delete we currently
         // just remove from the provider index by best-effort.
         // If needed, we can add a pool.delete(id) later.
     }
 
+    /// Stops handing out the credential for new requests, leaving the snapshot as is.
+    pub async fn detach_credential(&self, credential_id: i64) {
+        let provider_name = {
+            let snap = self.snapshot.load();
+            snap.credentials
+                .iter()
+                .find(|c| c.id == credential_id)
+                .and_then(|row| snap.providers.iter().find(|p| p.id == row.provider_id))
+                .map(|p| p.name.clone())
+        };
+        if let Some(provider_name) = provider_name
+            && let Some(runtime) = self.providers.load().get(&provider_name).cloned()
+        {
+            runtime
+                .pool
+                .set_enabled(&provider_name, credential_id, false)
+                .await;
+        }
+    }
+
     pub async fn apply_credential_update(
         &self,
         credential_id: i64,
@@ -178,6 +492,12 @@ impl AppState {
             .map(|p| p.name.clone());
         let enabled = row.enabled;
         self.snapshot.store(Arc::new(snap));
+        self.config_events.publish(
+            ConfigEntity::Credential,
+            ConfigAction::Updated,
+            Some(credential_id),
+            provider_name.as_deref(),
+        );
 
         // If enabled, ensure pool has the latest credential material.
         if enabled {
@@ -206,7 +526,14 @@ impl AppState {
         let mut merged = GlobalConfigPatch::from(current);
         merged.overlay(patch);
         let next = merged.into_config()?;
+        self.geoip.configure(&next);
         self.global.store(Arc::new(next.clone()));
+        self.config_events.publish(
+            ConfigEntity::GlobalConfig,
+            ConfigAction::Updated,
+            None,
+            None,
+        );
         Ok(next)
     }
 
@@ -238,6 +565,12 @@ impl AppState {
             updated_at: now,
         });
         self.snapshot.store(Arc::new(snap));
+        self.config_events.publish(
+            ConfigEntity::Credential,
+            ConfigAction::Created,
+            Some(id),
+            Some(&provider_name),
+        );
 
         // Update pool (enabled credentials only).
         if enabled {
@@ -276,6 +609,12 @@ impl AppState {
         let secret_json = row.secret_json.clone();
 
         self.snapshot.store(Arc::new(snap));
+        self.config_events.publish(
+            ConfigEntity::Credential,
+            ConfigAction::toggled(enabled),
+            Some(credential_id),
+            provider_name.as_deref(),
+        );
 
         let Some(provider_name) = provider_name else {
             return Ok(());
@@ -307,10 +646,318 @@ impl AppState {
         Ok(())
     }
 
+    /// Compares the in-memory snapshot, and the provider runtimes built from it, against
+    /// `db`, a fresh read of storage.
+    pub fn drift(&self, db: &StorageSnapshot) -> SnapshotDrift {
+        let snap = self.snapshot.load();
+        let mut drift = SnapshotDrift::between(&snap, db);
+        let runtimes = self.providers.load();
+        for p in &snap.providers {
+            let serving = runtimes
+                .get(&p.name)
+                .is_some_and(|rt| *rt.config_json.load_full() == p.config_json);
+            if !serving {
+                drift.runtimes.push(p.name.clone());
+            }
+        }
+        for name in runtimes.keys() {
+            if !snap.providers.iter().any(|p| &p.name == name) {
+                drift.runtimes.push(name.clone());
+            }
+        }
+        drift.runtimes.sort();
+        drift
+    }
+
+    /// Replaces the in-memory snapshot with `db` and brings provider runtimes and
+    /// credential pools in line with it. Credential health is kept; a provider whose
+    /// config changes loses its canary, as with a direct edit.
+    pub async fn resync(&self, db: StorageSnapshot) -> anyhow::Result<()> {
+        let names: HashMap<i64, String> = db
+            .providers
+            .iter()
+            .map(|p| (p.id, p.name.clone()))
+            .collect();
+        // Decode everything before touching state, so a bad row leaves memory as it was.
+        let mut enabled = Vec::new();
+        for c in db.credentials.iter().filter(|c| c.enabled) {
+            let Some(provider_name) = names.get(&c.provider_id) else {
+                continue;
+            };
+            let cred: Credential =
+                serde_json::from_value(c.secret_json.clone()).with_context(|| {
+                    format!(
+                        "decode credential_json for credential_id={} provider={provider_name}",
+                        c.id
+                    )
+                })?;
+            enabled.push((provider_name.clone(), c.id, cred));
+        }
+
+        let mut map = self.providers.load().as_ref().clone();
+        map.retain(|name, _| db.providers.iter().any(|p| &p.name == name));
+        for p in &db.providers {
+            match map.get(&p.name) {
+                Some(rt) => {
+                    if *rt.config_json.load_full() != p.config_json {
+                        rt.config_json.store(Arc::new(p.config_json.clone()));
+                        rt.canary.store(None);
+                    }
+                }
+                None => {
+                    map.insert(
+                        p.name.clone(),
+                        Arc::new(ProviderRuntime::new(
+                            p.name.clone(),
+                            p.config_json.clone(),
+                            CredentialPool::new(self.events.clone()),
+                        )),
+                    );
+                }
+            }
+        }
+        self.providers.store(Arc::new(map.clone()));
+
+        // Take credentials that are gone, disabled or moved out of the pools they were in.
+        let previous = self.snapshot.load_full();
+        for c in &previous.credentials {
+            let Some(old_name) = previous
+                .providers
+                .iter()
+                .find(|p| p.id == c.provider_id)
+                .map(|p| &p.name)
+            else {
+                continue;
+            };
+            let still_there = enabled
+                .iter()
+                .any(|(name, id, _)| *id == c.id && name == old_name);
+            if !still_there && let Some(rt) = map.get(old_name) {
+                rt.pool.set_enabled(old_name, c.id, false).await;
+            }
+        }
+        for c in db.credentials.iter().filter(|c| !c.enabled) {
+            if let Some(name) = names.get(&c.provider_id)
+                && let Some(rt) = map.get(name)
+            {
+                rt.pool.set_enabled(name, c.id, false).await;
+            }
+        }
+        for (provider_name, id, cred) in enabled {
+            if let Some(rt) = map.get(&provider_name) {
+                rt.pool.insert(provider_name.clone(), id, cred).await;
+            }
+        }
+
+        self.snapshot.store(Arc::new(db));
+        self.config_events
+            .publish(ConfigEntity::Snapshot, ConfigAction::Updated, None, None);
+        Ok(())
+    }
+
+    pub fn apply_org_upsert(
+        &self,
+        id: i64,
+        name: String,
+        enabled: bool,
+        admin_key: Option<String>,
+    ) {
+        let now = OffsetDateTime::now_utc();
+
+        let mut snap = self.snapshot.load().as_ref().clone();
+        let action = upsert_action(snap.organizations.iter().any(|o| o.id == id));
+        match snap.organizations.iter_mut().find(|o| o.id == id) {
+            Some(o) => {
+                o.name = name;
+                o.enabled = enabled;
+                o.admin_key = admin_key;
+                o.updated_at = now;
+            }
+            None => snap.organizations.push(OrganizationRow {
+                id,
+                name,
+                enabled,
+                admin_key,
+                omit_bodies: false,
+                rpm_limit: None,
+                tpm_limit: None,
+                monthly_token_budget: None,
+                default_provider: None,
+                default_model: None,
+                created_at: now,
+                updated_at: now,
+            }),
+        }
+        self.snapshot.store(Arc::new(snap));
+        self.config_events
+            .publish(ConfigEntity::Organization, action, Some(id), None);
+    }
+
+    pub fn apply_org_enabled(&self, org_id: i64, enabled: bool) {
+        let now = OffsetDateTime::now_utc();
+
+        let mut snap = self.snapshot.load().as_ref().clone();
+        if let Some(o) = snap.organizations.iter_mut().find(|o| o.id == org_id) {
+            o.enabled = enabled;
+            o.updated_at = now;
+            self.snapshot.store(Arc::new(snap));
+            self.config_events.publish(
+                ConfigEntity::Organization,
+                ConfigAction::toggled(enabled),
+                Some(org_id),
+                None,
+            );
+        }
+    }
+
+    pub fn apply_org_omit_bodies(&self, org_id: i64, omit_bodies: bool) {
+        let now = OffsetDateTime::now_utc();
+
+        let mut snap = self.snapshot.load().as_ref().clone();
+        if let Some(o) = snap.organizations.iter_mut().find(|o| o.id == org_id) {
+            o.omit_bodies = omit_bodies;
+            o.updated_at = now;
+            self.snapshot.store(Arc::new(snap));
+            self.config_events.publish(
+                ConfigEntity::Organization,
+                ConfigAction::Updated,
+                Some(org_id),
+                None,
+            );
+        }
+    }
+
+    pub fn apply_org_limits(
+        &self,
+        org_id: i64,
+        rpm_limit: Option<i64>,
+        tpm_limit: Option<i64>,
+        monthly_token_budget: Option<i64>,
+    ) {
+        let now = OffsetDateTime::now_utc();
+
+        let mut snap = self.snapshot.load().as_ref().clone();
+        if let Some(o) = snap.organizations.iter_mut().find(|o| o.id == org_id) {
+            o.rpm_limit = rpm_limit;
+            o.tpm_limit = tpm_limit;
+            o.monthly_token_budget = monthly_token_budget;
+            o.updated_at = now;
+            self.snapshot.store(Arc::new(snap));
+            self.config_events.publish(
+                ConfigEntity::Organization,
+                ConfigAction::Updated,
+                Some(org_id),
+                None,
+            );
+        }
+    }
+
+    pub fn apply_org_defaults(
+        &self,
+        org_id: i64,
+        default_provider: Option<String>,
+        default_model: Option<String>,
+    ) {
+        let now = OffsetDateTime::now_utc();
+
+        let mut snap = self.snapshot.load().as_ref().clone();
+        if let Some(o) = snap.organizations.iter_mut().find(|o| o.id == org_id) {
+            o.default_provider = default_provider;
+            o.default_model = default_model;
+            o.updated_at = now;
+            self.snapshot.store(Arc::new(snap));
+            self.config_events.publish(
+                ConfigEntity::Organization,
+                ConfigAction::Updated,
+                Some(org_id),
+                None,
+            );
+        }
+    }
+
+    pub fn apply_org_delete(&self, org_id: i64) {
+        let now = OffsetDateTime::now_utc();
+
+        let mut snap = self.snapshot.load().as_ref().clone();
+        snap.organizations.retain(|o| o.id != org_id);
+        snap.org_grants.retain(|g| g.org_id != org_id);
+        for u in snap.users.iter_mut().filter(|u| u.org_id == Some(org_id)) {
+            u.org_id = None;
+            u.updated_at = now;
+        }
+        self.snapshot.store(Arc::new(snap));
+        self.config_events.publish(
+            ConfigEntity::Organization,
+            ConfigAction::Deleted,
+            Some(org_id),
+            None,
+        );
+    }
+
+    pub fn apply_org_grant_insert(
+        &self,
+        id: i64,
+        org_id: i64,
+        provider_id: i64,
+        credential_id: Option<i64>,
+    ) {
+        let mut snap = self.snapshot.load().as_ref().clone();
+        snap.org_grants.push(OrgGrantRow {
+            id,
+            org_id,
+            provider_id,
+            credential_id,
+            created_at: OffsetDateTime::now_utc(),
+        });
+        self.snapshot.store(Arc::new(snap));
+        self.config_events.publish(
+            ConfigEntity::OrgGrant,
+            ConfigAction::Created,
+            Some(id),
+            None,
+        );
+    }
+
+    pub fn apply_org_grant_delete(&self, grant_id: i64) {
+        let mut snap = self.snapshot.load().as_ref().clone();
+        snap.org_grants.retain(|g| g.id != grant_id);
+        self.snapshot.store(Arc::new(snap));
+        self.config_events.publish(
+            ConfigEntity::OrgGrant,
+            ConfigAction::Deleted,
+            Some(grant_id),
+            None,
+        );
+    }
+
+    /// Resolve the credential scope for a caller's organization on a provider; see
+    /// [`CredentialScope`].
+    pub fn credential_scope(&self, org_id: Option<i64>, provider: &str) -> CredentialScope {
+        credential_scope::resolve(&self.snapshot.load(), org_id, provider)
+    }
+
+    pub fn apply_user_org(&self, user_id: i64, org_id: Option<i64>) {
+        let now = OffsetDateTime::now_utc();
+
+        let mut snap = self.snapshot.load().as_ref().clone();
+        if let Some(u) = snap.users.iter_mut().find(|u| u.id == user_id) {
+            u.org_id = org_id;
+            u.updated_at = now;
+            self.snapshot.store(Arc::new(snap));
+            self.config_events.publish(
+                ConfigEntity::User,
+                ConfigAction::Updated,
+                Some(user_id),
+                None,
+            );
+        }
+    }
+
     pub fn apply_user_upsert(&self, id: i64, name: String, enabled: bool) {
         let now = OffsetDateTime::now_utc();
 
         let mut snap = self.snapshot.load().as_ref().clone();
+        let action = upsert_action(snap.users.iter().any(|u| u.id == id));
         match snap.users.iter_mut().find(|u| u.id == id) {
             Some(u) => {
                 u.id = id;
@@ -322,11 +969,14 @@ impl AppState {
                 id,
                 name,
                 enabled,
+                org_id: None,
                 created_at: now,
                 updated_at: now,
             }),
         }
         self.snapshot.store(Arc::new(snap));
+        self.config_events
+            .publish(ConfigEntity::User, action, Some(id), None);
     }
 
     pub fn apply_user_enabled(&self, user_id: i64, enabled: bool) {
@@ -337,6 +987,12 @@ impl AppState {
             u.enabled = enabled;
             u.updated_at = now;
             self.snapshot.store(Arc::new(snap));
+            self.config_events.publish(
+                ConfigEntity::User,
+                ConfigAction::toggled(enabled),
+                Some(user_id),
+                None,
+            );
         }
     }
 
@@ -345,6 +1001,12 @@ impl AppState {
         snap.users.retain(|u| u.id != user_id);
         snap.user_keys.retain(|k| k.user_id != user_id);
         self.snapshot.store(Arc::new(snap));
+        self.config_events.publish(
+            ConfigEntity::User,
+            ConfigAction::Deleted,
+            Some(user_id),
+            None,
+        );
     }
 
     pub fn apply_user_key_insert(
@@ -364,10 +1026,26 @@ impl AppState {
             api_key,
             label,
             enabled,
+            rpm_limit: None,
+            tpm_limit: None,
+            stream_tps_limit: None,
+            max_output_tokens: None,
+            omit_bodies: false,
+            default_provider: None,
+            default_model: None,
+            mcp_policy: None,
+            moderation_policy: None,
+            prelude_template: None,
+            key_scope: None,
+            expires_at: None,
+            allowed_origins: None,
+            body_sample_percent: None,
             created_at: now,
             updated_at: now,
         });
         self.snapshot.store(Arc::new(snap));
+        self.config_events
+            .publish(ConfigEntity::UserKey, ConfigAction::Created, Some(id), None);
     }
 
     pub fn apply_user_key_label(&self, user_key_id: i64, label: Option<String>) {
@@ -378,6 +1056,169 @@ impl AppState {
             k.label = label;
             k.updated_at = now;
             self.snapshot.store(Arc::new(snap));
+            self.config_events.publish(
+                ConfigEntity::UserKey,
+                ConfigAction::Updated,
+                Some(user_key_id),
+                None,
+            );
+        }
+    }
+
+    pub fn apply_user_key_limits(
+        &self,
+        user_key_id: i64,
+        rpm_limit: Option<i64>,
+        tpm_limit: Option<i64>,
+        stream_tps_limit: Option<i64>,
+        max_output_tokens: Option<i64>,
+    ) {
+        let now = OffsetDateTime::now_utc();
+
+        let mut snap = self.snapshot.load().as_ref().clone();
+        if let Some(k) = snap.user_keys.iter_mut().find(|k| k.id == user_key_id) {
+            k.rpm_limit = rpm_limit;
+            k.tpm_limit = tpm_limit;
+            k.stream_tps_limit = stream_tps_limit;
+            k.max_output_tokens = max_output_tokens;
+            k.updated_at = now;
+            self.snapshot.store(Arc::new(snap));
+            self.config_events.publish(
+                ConfigEntity::UserKey,
+                ConfigAction::Updated,
+                Some(user_key_id),
+                None,
+            );
+        }
+    }
+
+    pub fn apply_user_key_defaults(
+        &self,
+        user_key_id: i64,
+        default_provider: Option<String>,
+        default_model: Option<String>,
+    ) {
+        let now = OffsetDateTime::now_utc();
+
+        let mut snap = self.snapshot.load().as_ref().clone();
+        if let Some(k) = snap.user_keys.iter_mut().find(|k| k.id == user_key_id) {
+            k.default_provider = default_provider;
+            k.default_model = default_model;
+            k.updated_at = now;
+            self.snapshot.store(Arc::new(snap));
+            self.config_events.publish(
+                ConfigEntity::UserKey,
+                ConfigAction::Updated,
+                Some(user_key_id),
+                None,
+            );
+        }
+    }
+
+    pub fn apply_user_key_mcp_policy(
+        &self,
+        user_key_id: i64,
+        mcp_policy: Option<serde_json::Value>,
+    ) {
+        let now = OffsetDateTime::now_utc();
+
+        let mut snap = self.snapshot.load().as_ref().clone();
+        if let Some(k) = snap.user_keys.iter_mut().find(|k| k.id == user_key_id) {
+            k.mcp_policy = mcp_policy;
+            k.updated_at = now;
+            self.snapshot.store(Arc::new(snap));
+            self.config_events.publish(
+                ConfigEntity::UserKey,
+                ConfigAction::Updated,
+                Some(user_key_id),
+                None,
+            );
+        }
+    }
+
+    pub fn apply_user_key_moderation_policy(
+        &self,
+        user_key_id: i64,
+        moderation_policy: Option<serde_json::Value>,
+    ) {
+        let now = OffsetDateTime::now_utc();
+
+        let mut snap = self.snapshot.load().as_ref().clone();
+        if let Some(k) = snap.user_keys.iter_mut().find(|k| k.id == user_key_id) {
+            k.moderation_policy = moderation_policy;
+            k.updated_at = now;
+            self.snapshot.store(Arc::new(snap));
+            self.config_events.publish(
+                ConfigEntity::UserKey,
+                ConfigAction::Updated,
+                Some(user_key_id),
+                None,
+            );
+        }
+    }
+
+    pub fn apply_user_key_prelude_template(
+        &self,
+        user_key_id: i64,
+        prelude_template: Option<String>,
+    ) {
+        let now = OffsetDateTime::now_utc();
+
+        let mut snap = self.snapshot.load().as_ref().clone();
+        if let Some(k) = snap.user_keys.iter_mut().find(|k| k.id == user_key_id) {
+            k.prelude_template = prelude_template;
+            k.updated_at = now;
+            self.snapshot.store(Arc::new(snap));
+            self.config_events.publish(
+                ConfigEntity::UserKey,
+                ConfigAction::Updated,
+                Some(user_key_id),
+                None,
+            );
+        }
+    }
+
+    pub fn apply_user_key_scope(
+        &self,
+        user_key_id: i64,
+        key_scope: Option<serde_json::Value>,
+        expires_at: Option<OffsetDateTime>,
+    ) {
+        let now = OffsetDateTime::now_utc();
+
+        let mut snap = self.snapshot.load().as_ref().clone();
+        if let Some(k) = snap.user_keys.iter_mut().find(|k| k.id == user_key_id) {
+            k.key_scope = key_scope;
+            k.expires_at = expires_at;
+            k.updated_at = now;
+            self.snapshot.store(Arc::new(snap));
+            self.config_events.publish(
+                ConfigEntity::UserKey,
+                ConfigAction::Updated,
+                Some(user_key_id),
+                None,
+            );
+        }
+    }
+
+    pub fn apply_user_key_allowed_origins(
+        &self,
+        user_key_id: i64,
+        allowed_origins: Option<serde_json::Value>,
+    ) {
+        let now = OffsetDateTime::now_utc();
+
+        let mut snap = self.snapshot.load().as_ref().clone();
+        if let Some(k) = snap.user_keys.iter_mut().find(|k| k.id == user_key_id) {
+            k.allowed_origins = allowed_origins;
+            k.updated_at = now;
+            self.snapshot.store(Arc::new(snap));
+            self.config_events.publish(
+                ConfigEntity::UserKey,
+                ConfigAction::Updated,
+                Some(user_key_id),
+                None,
+            );
         }
     }
 
@@ -385,6 +1226,214 @@ impl AppState {
         let mut snap = self.snapshot.load().as_ref().clone();
         snap.user_keys.retain(|k| k.id != user_key_id);
         self.snapshot.store(Arc::new(snap));
+        self.config_events.publish(
+            ConfigEntity::UserKey,
+            ConfigAction::Deleted,
+            Some(user_key_id),
+            None,
+        );
+    }
+
+    pub fn apply_model_profile_upsert(
+        &self,
+        id: i64,
+        name: String,
+        provider: String,
+        model: String,
+        settings_json: serde_json::Value,
+        enabled: bool,
+    ) {
+        let now = OffsetDateTime::now_utc();
+
+        let mut snap = self.snapshot.load().as_ref().clone();
+        let action = upsert_action(snap.model_profiles.iter().any(|p| p.name == name));
+        let event_name = name.clone();
+        match snap.model_profiles.iter_mut().find(|p| p.name == name) {
+            Some(p) => {
+                p.id = id;
+                p.provider = provider;
+                p.model = model;
+                p.settings_json = settings_json;
+                p.enabled = enabled;
+                p.updated_at = now;
+            }
+            None => snap.model_profiles.push(ModelProfileRow {
+                id,
+                name,
+                provider,
+                model,
+                settings_json,
+                enabled,
+                created_at: now,
+                updated_at: now,
+            }),
+        }
+        self.snapshot.store(Arc::new(snap));
+        self.config_events.publish(
+            ConfigEntity::ModelProfile,
+            action,
+            Some(id),
+            Some(&event_name),
+        );
+    }
+
+    pub fn apply_model_profile_delete(&self, name: &str) {
+        let mut snap = self.snapshot.load().as_ref().clone();
+        snap.model_profiles.retain(|p| p.name != name);
+        self.snapshot.store(Arc::new(snap));
+        self.config_events.publish(
+            ConfigEntity::ModelProfile,
+            ConfigAction::Deleted,
+            None,
+            Some(name),
+        );
+    }
+
+    pub fn apply_experiment_upsert(
+        &self,
+        id: i64,
+        name: String,
+        arms_json: serde_json::Value,
+        split: String,
+        enabled: bool,
+    ) {
+        let now = OffsetDateTime::now_utc();
+
+        let mut snap = self.snapshot.load().as_ref().clone();
+        let action = upsert_action(snap.experiments.iter().any(|e| e.name == name));
+        let event_name = name.clone();
+        match snap.experiments.iter_mut().find(|e| e.name == name) {
+            Some(e) => {
+                e.id = id;
+                e.arms_json = arms_json;
+                e.split = split;
+                e.enabled = enabled;
+                e.updated_at = now;
+            }
+            None => snap.experiments.push(ExperimentRow {
+                id,
+                name,
+                arms_json,
+                split,
+                enabled,
+                created_at: now,
+                updated_at: now,
+            }),
+        }
+        self.snapshot.store(Arc::new(snap));
+        self.config_events.publish(
+            ConfigEntity::Experiment,
+            action,
+            Some(id),
+            Some(&event_name),
+        );
+    }
+
+    pub fn apply_experiment_delete(&self, name: &str) {
+        let mut snap = self.snapshot.load().as_ref().clone();
+        snap.experiments.retain(|e| e.name != name);
+        self.snapshot.store(Arc::new(snap));
+        self.config_events.publish(
+            ConfigEntity::Experiment,
+            ConfigAction::Deleted,
+            None,
+            Some(name),
+        );
+    }
+
+    pub fn apply_secret_upsert(&self, id: i64, name: String, value: String) {
+        let now = OffsetDateTime::now_utc();
+
+        let mut snap = self.snapshot.load().as_ref().clone();
+        let action = upsert_action(snap.secrets.iter().any(|s| s.name == name));
+        let event_name = name.clone();
+        match snap.secrets.iter_mut().find(|s| s.name == name) {
+            Some(s) => {
+                s.id = id;
+                s.value = value;
+                s.updated_at = now;
+            }
+            None => snap.secrets.push(SecretRow {
+                id,
+                name,
+                value,
+                created_at: now,
+                updated_at: now,
+            }),
+        }
+        self.snapshot.store(Arc::new(snap));
+        self.config_events
+            .publish(ConfigEntity::Secret, action, Some(id), Some(&event_name));
+    }
+
+    pub fn apply_secret_delete(&self, name: &str) {
+        let mut snap = self.snapshot.load().as_ref().clone();
+        snap.secrets.retain(|s| s.name != name);
+        self.snapshot.store(Arc::new(snap));
+        self.config_events.publish(
+            ConfigEntity::Secret,
+            ConfigAction::Deleted,
+            None,
+            Some(name),
+        );
+    }
+
+    pub fn apply_log_view_upsert(
+        &self,
+        id: i64,
+        name: String,
+        filter_json: serde_json::Value,
+        window_secs: i64,
+        alert_threshold: Option<i64>,
+    ) {
+        let now = OffsetDateTime::now_utc();
+
+        let mut snap = self.snapshot.load().as_ref().clone();
+        let action = upsert_action(snap.log_views.iter().any(|v| v.name == name));
+        let event_name = name.clone();
+        match snap.log_views.iter_mut().find(|v| v.name == name) {
+            Some(v) => {
+                v.id = id;
+                v.filter_json = filter_json;
+                v.window_secs = window_secs;
+                v.alert_threshold = alert_threshold;
+                v.updated_at = now;
+            }
+            None => snap.log_views.push(LogViewRow {
+                id,
+                name,
+                filter_json,
+                window_secs,
+                alert_threshold,
+                created_at: now,
+                updated_at: now,
+            }),
+        }
+        self.snapshot.store(Arc::new(snap));
+        self.config_events
+            .publish(ConfigEntity::LogView, action, Some(id), Some(&event_name));
+    }
+
+    pub fn apply_log_view_delete(&self, name: &str) {
+        let mut snap = self.snapshot.load().as_ref().clone();
+        snap.log_views.retain(|v| v.name != name);
+        self.snapshot.store(Arc::new(snap));
+        self.config_events.publish(
+            ConfigEntity::LogView,
+            ConfigAction::Deleted,
+            None,
+            Some(name),
+        );
+    }
+
+    /// Value of the secret `name`, if one is stored.
+    pub fn secret(&self, name: &str) -> Option<String> {
+        self.snapshot
+            .load()
+            .secrets
+            .iter()
+            .find(|s| s.name == name)
+            .map(|s| s.value.clone())
     }
 
     pub fn apply_user_key_enabled(&self, user_key_id: i64, enabled: bool) {
@@ -395,6 +1444,95 @@ impl AppState {
             k.enabled = enabled;
             k.updated_at = now;
             self.snapshot.store(Arc::new(snap));
+            self.config_events.publish(
+                ConfigEntity::UserKey,
+                ConfigAction::toggled(enabled),
+                Some(user_key_id),
+                None,
+            );
+        }
+    }
+
+    pub fn apply_user_key_omit_bodies(&self, user_key_id: i64, omit_bodies: bool) {
+        let now = OffsetDateTime::now_utc();
+
+        let mut snap = self.snapshot.load().as_ref().clone();
+        if let Some(k) = snap.user_keys.iter_mut().find(|k| k.id == user_key_id) {
+            k.omit_bodies = omit_bodies;
+            k.updated_at = now;
+            self.snapshot.store(Arc::new(snap));
+            self.config_events.publish(
+                ConfigEntity::UserKey,
+                ConfigAction::Updated,
+                Some(user_key_id),
+                None,
+            );
         }
     }
+
+    pub fn apply_user_key_body_sample_percent(
+        &self,
+        user_key_id: i64,
+        body_sample_percent: Option<i64>,
+    ) {
+        let now = OffsetDateTime::now_utc();
+
+        let mut snap = self.snapshot.load().as_ref().clone();
+        if let Some(k) = snap.user_keys.iter_mut().find(|k| k.id == user_key_id) {
+            k.body_sample_percent = body_sample_percent;
+            k.updated_at = now;
+            self.snapshot.store(Arc::new(snap));
+            self.config_events.publish(
+                ConfigEntity::UserKey,
+                ConfigAction::Updated,
+                Some(user_key_id),
+                None,
+            );
+        }
+    }
+}
+
+fn upsert_action(exists: bool) -> ConfigAction {
+    if exists {
+        ConfigAction::Updated
+    } else {
+        ConfigAction::Created
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn parsed_config_is_kept_until_the_json_changes() {
+        let runtime = ProviderRuntime::new(
+            "openai".to_string(),
+            serde_json::json!({
+                "kind": "openai",
+                "channel_settings": {},
+                "stream_failover": { "provider": "backup" },
+            }),
+            CredentialPool::new(EventHub::new(16)),
+        );
+        let (config, policies) = (runtime.provider_config().unwrap(), runtime.policies());
+        assert!(Arc::ptr_eq(&config, &runtime.provider_config().unwrap()));
+        assert!(Arc::ptr_eq(&policies, &runtime.policies()));
+        assert_eq!(policies.stream_failover.provider.as_deref(), Some("backup"));
+
+        runtime.config_json.store(Arc::new(serde_json::json!({
+            "kind": "openai",
+            "channel_settings": {},
+        })));
+        let updated = runtime.policies();
+        assert!(!Arc::ptr_eq(&policies, &updated));
+        assert_eq!(updated.stream_failover.provider, None);
+        assert!(!Arc::ptr_eq(&config, &runtime.provider_config().unwrap()));
+
+        runtime
+            .config_json
+            .store(Arc::new(serde_json::json!({ "kind": 1 })));
+        assert!(runtime.provider_config().is_err());
+        assert_eq!(runtime.policies().stream_failover.provider, None);
+    }
 }
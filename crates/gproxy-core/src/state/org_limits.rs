@@ -0,0 +1,245 @@
+//! Limits shared by every key of an organization: per-minute request and token windows
+//! and a token budget per calendar month (UTC).
+//!
+//! Token usage arrives through the event hub and is attributed through the user's
+//! organization. The `org_budgets` job replaces the month's count with the usage read
+//! back from storage, so a budget holds across restarts.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+use arc_swap::ArcSwap;
+use time::{Month, OffsetDateTime, Time};
+
+use gproxy_provider_core::{Event, EventSink};
+use gproxy_storage::{OrganizationRow, StorageSnapshot};
+
+use super::key_rate::{KeyLimits, KeyRateCounters, KeyRateStatus};
+
+/// Why a request of an organization's key was turned away.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrgRejection {
+    /// A per-minute limit of the organization is used up.
+    RateLimited(KeyRateStatus),
+    /// The organization's token budget for the month is used up.
+    BudgetExhausted,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct MonthUsage {
+    month: (i32, Month),
+    tokens: u64,
+}
+
+pub struct OrgLimits {
+    rates: KeyRateCounters,
+    months: Mutex<HashMap<i64, MonthUsage>>,
+    snapshot: Arc<ArcSwap<StorageSnapshot>>,
+}
+
+impl OrgLimits {
+    pub fn new(snapshot: Arc<ArcSwap<StorageSnapshot>>) -> Self {
+        Self {
+            rates: KeyRateCounters::default(),
+            months: Mutex::new(HashMap::new()),
+            snapshot,
+        }
+    }
+
+    /// Checks the month's budget, then counts one request against the organization's
+    /// per-minute limits. `Ok(None)` means the organization has no per-minute limit.
+    pub fn admit(&self, org: &OrganizationRow) -> Result<Option<KeyRateStatus>, OrgRejection> {
+        self.admit_at(org, OffsetDateTime::now_utc())
+    }
+
+    fn admit_at(
+        &self,
+        org: &OrganizationRow,
+        now: OffsetDateTime,
+    ) -> Result<Option<KeyRateStatus>, OrgRejection> {
+        if let Some(budget) = org
+            .monthly_token_budget
+            .and_then(|v| u64::try_from(v).ok())
+            .filter(|v| *v > 0)
+            && self.month_tokens(org.id, now) >= budget
+        {
+            return Err(OrgRejection::BudgetExhausted);
+        }
+        let limits = KeyLimits::new(org.rpm_limit, org.tpm_limit);
+        if limits.is_unlimited() {
+            return Ok(None);
+        }
+        self.rates
+            .admit(org.id, limits)
+            .map(Some)
+            .map_err(OrgRejection::RateLimited)
+    }
+
+    /// Tokens the organization has used in the month of `now`.
+    pub fn month_tokens(&self, org_id: i64, now: OffsetDateTime) -> u64 {
+        let months = self.months.lock().unwrap_or_else(|e| e.into_inner());
+        months
+            .get(&org_id)
+            .filter(|usage| usage.month == month_of(now))
+            .map_or(0, |usage| usage.tokens)
+    }
+
+    /// Replaces the count of the month of `now`, e.g. with the stored usage.
+    pub fn set_month_tokens(&self, org_id: i64, now: OffsetDateTime, tokens: u64) {
+        let mut months = self.months.lock().unwrap_or_else(|e| e.into_inner());
+        months.insert(
+            org_id,
+            MonthUsage {
+                month: month_of(now),
+                tokens,
+            },
+        );
+    }
+
+    fn record_tokens(&self, org_id: i64, tokens: u64, now: OffsetDateTime) {
+        self.rates.record_tokens(org_id, tokens);
+        let mut months = self.months.lock().unwrap_or_else(|e| e.into_inner());
+        let usage = months.entry(org_id).or_insert(MonthUsage {
+            month: month_of(now),
+            tokens: 0,
+        });
+        if usage.month != month_of(now) {
+            *usage = MonthUsage {
+                month: month_of(now),
+                tokens: 0,
+            };
+        }
+        usage.tokens = usage.tokens.saturating_add(tokens);
+    }
+}
+
+/// Midnight UTC on the first day of the month of `now`.
+pub fn month_start(now: OffsetDateTime) -> OffsetDateTime {
+    now.replace_day(1)
+        .unwrap_or(now)
+        .replace_time(Time::MIDNIGHT)
+}
+
+fn month_of(now: OffsetDateTime) -> (i32, Month) {
+    (now.year(), now.month())
+}
+
+impl EventSink for OrgLimits {
+    fn write<'a>(&'a self, event: &'a Event) -> Pin<Box<dyn Future<Output = ()> + Send + 'a>> {
+        Box::pin(async move {
+            let Event::Upstream(ev) = event else {
+                return;
+            };
+            let (Some(user_id), Some(usage)) = (ev.user_id, ev.usage.as_ref()) else {
+                return;
+            };
+            let tokens = u64::from(usage.input_tokens.unwrap_or(0))
+                + u64::from(usage.output_tokens.unwrap_or(0));
+            if tokens == 0 {
+                return;
+            }
+            let org_id = self
+                .snapshot
+                .load()
+                .users
+                .iter()
+                .find(|u| u.id == user_id)
+                .and_then(|u| u.org_id);
+            if let Some(org_id) = org_id {
+                self.record_tokens(org_id, tokens, OffsetDateTime::now_utc());
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use time::Date;
+
+    use super::*;
+
+    fn org(rpm_limit: Option<i64>, monthly_token_budget: Option<i64>) -> OrganizationRow {
+        let now = OffsetDateTime::now_utc();
+        OrganizationRow {
+            id: 7,
+            name: "team-a".to_string(),
+            enabled: true,
+            admin_key: None,
+            omit_bodies: false,
+            rpm_limit,
+            tpm_limit: None,
+            monthly_token_budget,
+            default_provider: None,
+            default_model: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    fn limits() -> OrgLimits {
+        OrgLimits::new(Arc::new(ArcSwap::from_pointee(StorageSnapshot {
+            global_config: None,
+            providers: Vec::new(),
+            credentials: Vec::new(),
+            organizations: Vec::new(),
+            org_grants: Vec::new(),
+            users: Vec::new(),
+            user_keys: Vec::new(),
+            model_profiles: Vec::new(),
+            experiments: Vec::new(),
+            secrets: Vec::new(),
+            log_views: Vec::new(),
+        })))
+    }
+
+    fn at(month: Month, day: u8, hour: u8) -> OffsetDateTime {
+        Date::from_calendar_date(2026, month, day)
+            .unwrap()
+            .with_hms(hour, 0, 0)
+            .unwrap()
+            .assume_utc()
+    }
+
+    #[test]
+    fn requests_are_shared_across_the_organization() {
+        let limits = limits();
+        let limited = org(Some(2), None);
+        let first = limits.admit(&limited).unwrap().unwrap();
+        assert_eq!(first.requests.unwrap().remaining, 1);
+        limits.admit(&limited).unwrap();
+        assert!(matches!(
+            limits.admit(&limited),
+            Err(OrgRejection::RateLimited(status)) if status.exhausted()
+        ));
+        assert_eq!(limits.admit(&org(None, None)), Ok(None));
+    }
+
+    #[test]
+    fn budget_counts_tokens_per_calendar_month() {
+        let limits = limits();
+        let budgeted = org(None, Some(100));
+        let march = at(Month::March, 31, 23);
+        let april = at(Month::April, 1, 0);
+        assert_eq!(limits.admit_at(&budgeted, march), Ok(None));
+        limits.record_tokens(budgeted.id, 60, march);
+        limits.record_tokens(budgeted.id, 40, march);
+        assert_eq!(limits.month_tokens(budgeted.id, march), 100);
+        assert_eq!(
+            limits.admit_at(&budgeted, march),
+            Err(OrgRejection::BudgetExhausted)
+        );
+        assert_eq!(limits.admit_at(&budgeted, april), Ok(None));
+        limits.record_tokens(budgeted.id, 5, april);
+        assert_eq!(limits.month_tokens(budgeted.id, april), 5);
+
+        // The stored total replaces the running count.
+        limits.set_month_tokens(budgeted.id, april, 100);
+        assert_eq!(
+            limits.admit_at(&budgeted, april),
+            Err(OrgRejection::BudgetExhausted)
+        );
+        assert_eq!(month_start(at(Month::April, 20, 13)), april);
+    }
+}
@@ -0,0 +1,86 @@
+//! Which credential created an upstream resource (response, conversation, file), so
+//! follow-up calls on it reach the account that can see it.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long an owner is remembered after the resource was created or last used.
+pub const RESOURCE_OWNER_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+/// Upper bound on remembered resources; the oldest entries go first when full.
+pub const MAX_RESOURCE_OWNERS: usize = 100_000;
+
+#[derive(Debug, Default)]
+pub struct ResourceOwners {
+    owners: Mutex<HashMap<(String, String), (i64, Instant)>>,
+}
+
+impl ResourceOwners {
+    pub fn record(&self, provider: &str, resource_id: &str, credential_id: i64) {
+        if resource_id.is_empty() {
+            return;
+        }
+        let now = Instant::now();
+        let mut owners = self.owners.lock().unwrap_or_else(|e| e.into_inner());
+        if owners.len() >= MAX_RESOURCE_OWNERS {
+            owners.retain(|_, (_, seen)| now.duration_since(*seen) < RESOURCE_OWNER_TTL);
+        }
+        if owners.len() >= MAX_RESOURCE_OWNERS
+            && let Some(oldest) = owners
+                .iter()
+                .min_by_key(|(_, (_, seen))| *seen)
+                .map(|(key, _)| key.clone())
+        {
+            owners.remove(&oldest);
+        }
+        owners.insert(
+            (provider.to_string(), resource_id.to_string()),
+            (credential_id, now),
+        );
+    }
+
+    /// Credential that created `resource_id`; a hit refreshes the entry.
+    pub fn owner(&self, provider: &str, resource_id: &str) -> Option<i64> {
+        let now = Instant::now();
+        let mut owners = self.owners.lock().unwrap_or_else(|e| e.into_inner());
+        let key = (provider.to_string(), resource_id.to_string());
+        let (credential_id, seen) = owners.get_mut(&key)?;
+        if now.duration_since(*seen) >= RESOURCE_OWNER_TTL {
+            owners.remove(&key);
+            return None;
+        }
+        *seen = now;
+        Some(*credential_id)
+    }
+
+    pub fn forget(&self, provider: &str, resource_id: &str) {
+        let mut owners = self.owners.lock().unwrap_or_else(|e| e.into_inner());
+        owners.remove(&(provider.to_string(), resource_id.to_string()));
+    }
+
+    pub fn len(&self) -> usize {
+        self.owners.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn owners_are_scoped_per_provider_and_forgotten_on_delete() {
+        let owners = ResourceOwners::default();
+        owners.record("openai", "conv_1", 7);
+        owners.record("openai", "", 8);
+        assert_eq!(owners.owner("openai", "conv_1"), Some(7));
+        assert_eq!(owners.owner("codex", "conv_1"), None);
+        assert_eq!(owners.len(), 1);
+
+        owners.forget("openai", "conv_1");
+        assert!(owners.is_empty());
+    }
+}
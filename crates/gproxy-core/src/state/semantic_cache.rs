@@ -0,0 +1,321 @@
+//! Answers of earlier generate requests, found again by the embedding of their prompt.
+//!
+//! Entries live in partitions (one per provider, model, request shape and, unless shared,
+//! user key); each partition has its own [`Hnsw`] index. The whole cache is written to a
+//! JSON lines file under the data dir and read back on startup.
+
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use super::hnsw::Hnsw;
+
+/// File of the persisted cache inside `GPROXY_DATA_DIR`.
+pub const SEMANTIC_CACHE_FILE: &str = "semantic_cache.jsonl";
+/// Neighbors checked per lookup; more than one so expired entries don't hide live ones.
+const LOOKUP_NEIGHBORS: usize = 4;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry {
+    partition: u64,
+    embedding: Vec<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    content_type: Option<String>,
+    body: String,
+    /// Unix seconds.
+    expires_at: i64,
+}
+
+#[derive(Debug, Default)]
+struct Partition {
+    index: Hnsw,
+    entries: HashMap<usize, Entry>,
+    /// Index ids, oldest first, for eviction.
+    order: VecDeque<usize>,
+}
+
+impl Partition {
+    fn insert(&mut self, entry: Entry) {
+        let id = self.index.insert(entry.embedding.clone());
+        self.entries.insert(id, entry);
+        self.order.push_back(id);
+    }
+
+    fn remove(&mut self, id: usize) {
+        if self.entries.remove(&id).is_some() {
+            self.index.remove(id);
+        }
+    }
+
+    /// Drops the oldest entries until there is room for one more.
+    fn evict_to(&mut self, max_entries: usize) {
+        while self.entries.len() >= max_entries {
+            let Some(id) = self.order.pop_front() else {
+                break;
+            };
+            self.remove(id);
+        }
+    }
+
+    /// Rebuilds the index once removed entries outnumber live ones.
+    fn compact(&mut self) {
+        if self.index.tombstones() <= self.index.len() {
+            return;
+        }
+        let order = std::mem::take(&mut self.order);
+        let mut entries = std::mem::take(&mut self.entries);
+        self.index = Hnsw::default();
+        for id in order {
+            if let Some(entry) = entries.remove(&id) {
+                self.insert(entry);
+            }
+        }
+    }
+}
+
+/// A cached answer close enough to the prompt.
+#[derive(Debug, Clone)]
+pub struct SemanticHit {
+    pub content_type: Option<String>,
+    pub body: Bytes,
+    pub similarity: f32,
+}
+
+#[derive(Debug, Default)]
+pub struct SemanticCache {
+    partitions: Mutex<HashMap<u64, Partition>>,
+    /// Changed since the last save.
+    dirty: AtomicBool,
+}
+
+impl SemanticCache {
+    /// The most similar unexpired answer in `partition` at or above `threshold`.
+    pub fn lookup(&self, partition: u64, embedding: &[f32], threshold: f32) -> Option<SemanticHit> {
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        let partitions = self.partitions.lock().unwrap_or_else(|e| e.into_inner());
+        let partition = partitions.get(&partition)?;
+        partition
+            .index
+            .search(embedding, LOOKUP_NEIGHBORS)
+            .into_iter()
+            .filter(|(_, similarity)| *similarity >= threshold)
+            .find_map(|(id, similarity)| {
+                let entry = partition.entries.get(&id)?;
+                (entry.expires_at > now && entry.embedding.len() == embedding.len()).then(|| {
+                    SemanticHit {
+                        content_type: entry.content_type.clone(),
+                        body: Bytes::from(entry.body.clone()),
+                        similarity,
+                    }
+                })
+            })
+    }
+
+    /// Stores an answer; bodies that are not UTF-8 are not cached.
+    pub fn insert(
+        &self,
+        partition: u64,
+        embedding: Vec<f32>,
+        content_type: Option<String>,
+        body: &Bytes,
+        ttl: Duration,
+        max_entries: usize,
+    ) {
+        let Ok(body) = std::str::from_utf8(body) else {
+            return;
+        };
+        let entry = Entry {
+            partition,
+            embedding,
+            content_type,
+            body: body.to_string(),
+            expires_at: OffsetDateTime::now_utc().unix_timestamp()
+                + i64::try_from(ttl.as_secs()).unwrap_or(i64::MAX / 2),
+        };
+        let mut partitions = self.partitions.lock().unwrap_or_else(|e| e.into_inner());
+        let partition = partitions.entry(partition).or_default();
+        partition.evict_to(max_entries);
+        partition.insert(entry);
+        partition.compact();
+        self.dirty.store(true, Ordering::Relaxed);
+    }
+
+    /// Drops expired entries and returns how many.
+    pub fn purge_expired(&self) -> usize {
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        let mut partitions = self.partitions.lock().unwrap_or_else(|e| e.into_inner());
+        let mut purged = 0;
+        for partition in partitions.values_mut() {
+            let expired: Vec<usize> = partition
+                .entries
+                .iter()
+                .filter(|(_, entry)| entry.expires_at <= now)
+                .map(|(id, _)| *id)
+                .collect();
+            purged += expired.len();
+            for id in expired {
+                partition.remove(id);
+            }
+            partition
+                .order
+                .retain(|id| partition.entries.contains_key(id));
+            partition.compact();
+        }
+        partitions.retain(|_, partition| !partition.entries.is_empty());
+        if purged > 0 {
+            self.dirty.store(true, Ordering::Relaxed);
+        }
+        purged
+    }
+
+    pub fn len(&self) -> usize {
+        let partitions = self.partitions.lock().unwrap_or_else(|e| e.into_inner());
+        partitions.values().map(|p| p.entries.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Reads entries saved by [`SemanticCache::save`]; unreadable lines and expired
+    /// entries are skipped. A missing file is an empty cache.
+    pub fn load(&self, path: &Path) -> std::io::Result<usize> {
+        let file = match std::fs::File::open(path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(err) => return Err(err),
+        };
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        let mut partitions = self.partitions.lock().unwrap_or_else(|e| e.into_inner());
+        let mut loaded = 0;
+        for line in BufReader::new(file).lines() {
+            let Ok(entry) = serde_json::from_str::<Entry>(&line?) else {
+                continue;
+            };
+            if entry.expires_at <= now {
+                continue;
+            }
+            partitions.entry(entry.partition).or_default().insert(entry);
+            loaded += 1;
+        }
+        Ok(loaded)
+    }
+
+    /// Writes every entry to `path` (through a temporary file) when anything changed
+    /// since the last save. Returns the entries written, or `None` when nothing changed.
+    pub fn save(&self, path: &Path) -> std::io::Result<Option<usize>> {
+        if !self.dirty.swap(false, Ordering::Relaxed) {
+            return Ok(None);
+        }
+        let result = self.write_to(path);
+        if result.is_err() {
+            self.dirty.store(true, Ordering::Relaxed);
+        }
+        result.map(Some)
+    }
+
+    fn write_to(&self, path: &Path) -> std::io::Result<usize> {
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(parent)?;
+        }
+        let tmp = path.with_extension("jsonl.tmp");
+        let mut out = BufWriter::new(std::fs::File::create(&tmp)?);
+        let mut written = 0;
+        {
+            let partitions = self.partitions.lock().unwrap_or_else(|e| e.into_inner());
+            for partition in partitions.values() {
+                for id in &partition.order {
+                    if let Some(entry) = partition.entries.get(id) {
+                        serde_json::to_writer(&mut out, entry)?;
+                        out.write_all(b"\n")?;
+                        written += 1;
+                    }
+                }
+            }
+        }
+        out.flush()?;
+        drop(out);
+        std::fs::rename(&tmp, path)?;
+        Ok(written)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: Duration = Duration::from_secs(86_400);
+
+    #[test]
+    fn similar_prompts_hit_within_their_partition() {
+        let cache = SemanticCache::default();
+        let body = Bytes::from_static(br#"{"id":"a"}"#);
+        cache.insert(1, vec![1.0, 0.0, 0.0], None, &body, DAY, 10);
+        cache.insert(
+            1,
+            vec![0.0, 1.0, 0.0],
+            None,
+            &Bytes::from_static(b"b"),
+            DAY,
+            10,
+        );
+
+        let hit = cache.lookup(1, &[0.99, 0.05, 0.0], 0.95).unwrap();
+        assert_eq!(hit.body, body);
+        assert!(hit.similarity > 0.95);
+        assert!(cache.lookup(1, &[0.7, 0.7, 0.0], 0.95).is_none());
+        assert!(cache.lookup(2, &[1.0, 0.0, 0.0], 0.5).is_none());
+    }
+
+    #[test]
+    fn oldest_entries_are_evicted_and_the_index_compacted() {
+        let cache = SemanticCache::default();
+        for i in 0..20 {
+            let angle = i as f32 * 0.3;
+            let body = Bytes::from(i.to_string());
+            cache.insert(7, vec![angle.cos(), angle.sin()], None, &body, DAY, 3);
+        }
+        assert_eq!(cache.len(), 3);
+        assert!(cache.lookup(7, &[1.0, 0.0], 0.999).is_none());
+        let last = 19.0_f32 * 0.3;
+        let hit = cache.lookup(7, &[last.cos(), last.sin()], 0.999).unwrap();
+        assert_eq!(hit.body, Bytes::from("19"));
+        let partitions = cache.partitions.lock().unwrap();
+        assert!(partitions[&7].index.tombstones() <= partitions[&7].index.len());
+    }
+
+    #[test]
+    fn entries_survive_a_save_and_load() {
+        let dir = std::env::temp_dir().join(format!("gproxy-semantic-{}", uuid::Uuid::new_v4()));
+        let path = dir.join(SEMANTIC_CACHE_FILE);
+        let cache = SemanticCache::default();
+        let body = Bytes::from_static(b"{}");
+        cache.insert(
+            3,
+            vec![0.0, 1.0],
+            Some("application/json".into()),
+            &body,
+            DAY,
+            10,
+        );
+        cache.insert(3, vec![1.0, 0.0], None, &body, Duration::ZERO, 10);
+        assert_eq!(cache.save(&path).unwrap(), Some(2));
+        assert_eq!(cache.save(&path).unwrap(), None);
+
+        let restored = SemanticCache::default();
+        assert_eq!(restored.load(&path).unwrap(), 1);
+        let hit = restored.lookup(3, &[0.0, 1.0], 0.99).unwrap();
+        assert_eq!(hit.content_type.as_deref(), Some("application/json"));
+        assert_eq!(cache.purge_expired(), 1);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
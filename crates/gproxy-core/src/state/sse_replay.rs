@@ -0,0 +1,330 @@
+//! Recent downstream SSE events of each stream, so a client that lost its connection can
+//! reconnect with `Last-Event-ID` and continue instead of starting the generation over.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use bytes::Bytes;
+use tokio::sync::{Notify, broadcast, mpsc};
+
+/// Events kept per stream; a reconnect further behind than this starts over.
+pub const SSE_REPLAY_EVENTS: usize = 256;
+/// How long a finished stream can still be resumed.
+pub const SSE_REPLAY_RETENTION: Duration = Duration::from_secs(60);
+/// How long the upstream is still read after the client went away, waiting for it to
+/// reconnect; without a reconnect the upstream is dropped so the generation is cancelled.
+pub const SSE_RESUME_GRACE: Duration = Duration::from_secs(5);
+
+pub struct SseReplay {
+    streams: Mutex<HashMap<String, Arc<ReplayStream>>>,
+    grace: Duration,
+}
+
+impl Default for SseReplay {
+    fn default() -> Self {
+        Self::with_grace(SSE_RESUME_GRACE)
+    }
+}
+
+struct ReplayStream {
+    user_key_id: i64,
+    state: Mutex<ReplayState>,
+    /// New events as they are numbered; `None` once the stream has ended.
+    live: broadcast::Sender<Option<(u64, Bytes)>>,
+    /// Signalled whenever a client resumes this stream.
+    resumed: Notify,
+}
+
+#[derive(Default)]
+struct ReplayState {
+    events: VecDeque<(u64, Bytes)>,
+    next_seq: u64,
+    finished_at: Option<Instant>,
+}
+
+impl ReplayStream {
+    fn push(&self, event: &[u8], stream_id: &str) -> Bytes {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let seq = state.next_seq;
+        state.next_seq += 1;
+        // The last `id:` of an event wins, so ours goes after any upstream one.
+        let mut framed = Vec::with_capacity(event.len() + stream_id.len() + 32);
+        framed.extend_from_slice(event);
+        framed.extend_from_slice(format!("\nid: {stream_id}.{seq}\n\n").as_bytes());
+        let framed = Bytes::from(framed);
+        if state.events.len() >= SSE_REPLAY_EVENTS {
+            state.events.pop_front();
+        }
+        state.events.push_back((seq, framed.clone()));
+        let _ = self.live.send(Some((seq, framed.clone())));
+        framed
+    }
+
+    fn finish(&self) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.finished_at = Some(Instant::now());
+        let _ = self.live.send(None);
+    }
+}
+
+impl SseReplay {
+    pub fn with_grace(grace: Duration) -> Self {
+        Self {
+            streams: Mutex::new(HashMap::new()),
+            grace,
+        }
+    }
+
+    /// Gives every event of `rx` an `id: {stream}.{seq}` and remembers the recent ones.
+    /// Once the returned receiver is dropped the upstream is only read on while a resumed
+    /// client follows it; if none reconnects within the grace period `rx` is dropped.
+    pub fn track(&self, user_key_id: i64, mut rx: mpsc::Receiver<Bytes>) -> mpsc::Receiver<Bytes> {
+        let stream_id = uuid::Uuid::new_v4().simple().to_string();
+        let stream = Arc::new(ReplayStream {
+            user_key_id,
+            state: Mutex::new(ReplayState::default()),
+            live: broadcast::channel(SSE_REPLAY_EVENTS).0,
+            resumed: Notify::new(),
+        });
+        {
+            let mut streams = self.streams.lock().unwrap_or_else(|e| e.into_inner());
+            purge_finished(&mut streams);
+            streams.insert(stream_id.clone(), stream.clone());
+        }
+
+        let (tx, rx_out) = mpsc::channel::<Bytes>(32);
+        let grace = self.grace;
+        tokio::spawn(async move {
+            let mut downstream = Some(tx);
+            let mut pending: Vec<u8> = Vec::new();
+            loop {
+                let chunk = match &downstream {
+                    Some(tx) => tokio::select! {
+                        chunk = rx.recv() => chunk,
+                        _ = tx.closed() => {
+                            downstream = None;
+                            continue;
+                        }
+                    },
+                    None => {
+                        // Nobody is reading: keep the upstream alive only for a reconnect.
+                        if stream.live.receiver_count() == 0
+                            && tokio::time::timeout(grace, stream.resumed.notified())
+                                .await
+                                .is_err()
+                        {
+                            break;
+                        }
+                        rx.recv().await
+                    }
+                };
+                let Some(chunk) = chunk else {
+                    break;
+                };
+                pending.extend_from_slice(&chunk);
+                while let Some(event) = take_event(&mut pending) {
+                    let framed = if is_comment_only(&event) {
+                        let mut framed = event;
+                        framed.extend_from_slice(b"\n\n");
+                        Bytes::from(framed)
+                    } else {
+                        stream.push(&event, &stream_id)
+                    };
+                    if let Some(tx) = &downstream
+                        && tx.send(framed).await.is_err()
+                    {
+                        downstream = None;
+                    }
+                }
+            }
+            if let Some(tx) = &downstream
+                && !pending.is_empty()
+            {
+                let _ = tx.send(Bytes::from(pending)).await;
+            }
+            stream.finish();
+        });
+        rx_out
+    }
+
+    /// Events after `last_event_id`, then the rest of the stream as it arrives. `None` when
+    /// the stream is unknown, expired, owned by another key, or no longer buffered that far
+    /// back; the caller then serves the request from scratch.
+    pub fn resume(&self, last_event_id: &str, user_key_id: i64) -> Option<mpsc::Receiver<Bytes>> {
+        let (stream_id, seq) = last_event_id.trim().rsplit_once('.')?;
+        let last_seq: u64 = seq.parse().ok()?;
+        let stream = {
+            let mut streams = self.streams.lock().unwrap_or_else(|e| e.into_inner());
+            purge_finished(&mut streams);
+            streams.get(stream_id)?.clone()
+        };
+        if stream.user_key_id != user_key_id {
+            return None;
+        }
+
+        let (backlog, mut live, finished) = {
+            let state = stream.state.lock().unwrap_or_else(|e| e.into_inner());
+            if last_seq >= state.next_seq {
+                return None;
+            }
+            let oldest = state.events.front().map(|(seq, _)| *seq)?;
+            if last_seq + 1 < oldest {
+                return None;
+            }
+            let backlog: Vec<_> = state
+                .events
+                .iter()
+                .filter(|(seq, _)| *seq > last_seq)
+                .cloned()
+                .collect();
+            // Subscribed under the lock so no event falls between backlog and live.
+            (
+                backlog,
+                stream.live.subscribe(),
+                state.finished_at.is_some(),
+            )
+        };
+        stream.resumed.notify_one();
+
+        let (tx, rx) = mpsc::channel::<Bytes>(32);
+        tokio::spawn(async move {
+            let mut sent = last_seq;
+            for (seq, event) in backlog {
+                if tx.send(event).await.is_err() {
+                    return;
+                }
+                sent = seq;
+            }
+            if finished {
+                return;
+            }
+            while let Ok(Some((seq, event))) = live.recv().await {
+                if seq <= sent {
+                    continue;
+                }
+                if tx.send(event).await.is_err() {
+                    return;
+                }
+                sent = seq;
+            }
+        });
+        Some(rx)
+    }
+
+    pub fn len(&self) -> usize {
+        self.streams.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+fn purge_finished(streams: &mut HashMap<String, Arc<ReplayStream>>) {
+    streams.retain(|_, stream| {
+        stream
+            .state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .finished_at
+            .is_none_or(|at| at.elapsed() < SSE_REPLAY_RETENTION)
+    });
+}
+
+/// Removes the first complete event (up to its blank line) from `pending`, without the
+/// trailing blank line.
+fn take_event(pending: &mut Vec<u8>) -> Option<Vec<u8>> {
+    let find = |needle: &[u8]| pending.windows(needle.len()).position(|w| w == needle);
+    let (end, sep) = match (find(b"\n\n"), find(b"\r\n\r\n")) {
+        (Some(lf), Some(crlf)) if crlf < lf => (crlf, 4),
+        (Some(lf), _) => (lf, 2),
+        (None, Some(crlf)) => (crlf, 4),
+        (None, None) => return None,
+    };
+    let event = pending[..end].to_vec();
+    pending.drain(..end + sep);
+    Some(event)
+}
+
+fn is_comment_only(event: &[u8]) -> bool {
+    event
+        .split(|b| *b == b'\n')
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+        .all(|line| line.is_empty() || line.starts_with(b":"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn collect(mut rx: mpsc::Receiver<Bytes>) -> String {
+        let mut out = Vec::new();
+        while let Some(chunk) = rx.recv().await {
+            out.extend_from_slice(&chunk);
+        }
+        String::from_utf8(out).unwrap()
+    }
+
+    #[tokio::test]
+    async fn events_are_numbered_and_resumable_by_the_same_key() {
+        let replay = SseReplay::default();
+        let (tx, rx) = mpsc::channel(8);
+        let tracked = replay.track(7, rx);
+        tx.send(Bytes::from_static(b"data: a\n\ndata: "))
+            .await
+            .unwrap();
+        tx.send(Bytes::from_static(b"b\n\n: ping\n\ndata: c\n\n"))
+            .await
+            .unwrap();
+        drop(tx);
+        let out = collect(tracked).await;
+
+        let first_id = out
+            .lines()
+            .find_map(|line| line.strip_prefix("id: "))
+            .unwrap()
+            .to_string();
+        let (stream_id, seq) = first_id.rsplit_once('.').unwrap();
+        assert_eq!(seq, "0");
+        assert!(out.contains(": ping\n\n"));
+        assert_eq!(out.matches("id: ").count(), 3);
+
+        assert!(replay.resume(&first_id, 8).is_none());
+        let resumed = collect(replay.resume(&first_id, 7).unwrap()).await;
+        assert_eq!(
+            resumed,
+            format!("data: b\nid: {stream_id}.1\n\ndata: c\nid: {stream_id}.2\n\n")
+        );
+    }
+
+    #[tokio::test]
+    async fn dropped_client_closes_the_upstream_after_the_grace() {
+        let replay = SseReplay::with_grace(Duration::from_millis(20));
+        let (tx, rx) = mpsc::channel(8);
+        drop(replay.track(7, rx));
+        tokio::time::timeout(Duration::from_secs(2), tx.closed())
+            .await
+            .expect("upstream is still read with nobody to resume it");
+    }
+
+    #[tokio::test]
+    async fn resumed_client_keeps_the_upstream_alive() {
+        let replay = SseReplay::with_grace(Duration::from_secs(5));
+        let (tx, rx) = mpsc::channel(8);
+        let mut tracked = replay.track(7, rx);
+        tx.send(Bytes::from_static(b"data: a\n\n")).await.unwrap();
+        let first = String::from_utf8(tracked.recv().await.unwrap().to_vec()).unwrap();
+        let first_id = first
+            .lines()
+            .find_map(|line| line.strip_prefix("id: "))
+            .unwrap()
+            .to_string();
+        drop(tracked);
+
+        let resumed = replay.resume(&first_id, 7).unwrap();
+        tx.send(Bytes::from_static(b"data: b\n\n")).await.unwrap();
+        drop(tx);
+        assert!(collect(resumed).await.starts_with("data: b\n"));
+    }
+}
@@ -0,0 +1,593 @@
+//! Rolling traffic statistics behind the admin overview.
+//!
+//! Every request is counted per total, provider, model, user key and provider transform pair
+//! (the protocol the caller spoke and the one the provider was sent) in two rings: one-minute
+//! slots for the last hour and one-hour slots for the last two days. Each series keeps
+//! request/error/token counts and a latency histogram. The hourly ring is what gets
+//! persisted, so history survives restarts at hour granularity.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use time::OffsetDateTime;
+
+use gproxy_provider_core::{Event, EventSink};
+use gproxy_storage::StatsHourlyRow;
+
+const MINUTE_SLOTS: usize = 60;
+const HOUR_SLOTS: usize = 48;
+
+/// Upper bounds (ms) of the latency buckets; one more bucket holds everything slower.
+const LATENCY_BOUNDS_MS: [u64; 15] = [
+    10, 25, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 20_000, 30_000, 60_000, 120_000,
+    300_000,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StatsDimension {
+    Total,
+    Provider,
+    Model,
+    Key,
+    /// Keyed `provider|user_proto->provider_proto`; see [`transform_key`].
+    Transform,
+}
+
+impl StatsDimension {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            StatsDimension::Total => "total",
+            StatsDimension::Provider => "provider",
+            StatsDimension::Model => "model",
+            StatsDimension::Key => "key",
+            StatsDimension::Transform => "transform",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "total" => Some(StatsDimension::Total),
+            "provider" => Some(StatsDimension::Provider),
+            "model" => Some(StatsDimension::Model),
+            "key" => Some(StatsDimension::Key),
+            "transform" => Some(StatsDimension::Transform),
+            _ => None,
+        }
+    }
+}
+
+type SeriesKey = (StatsDimension, String);
+
+/// Series key of a provider's transform pair.
+pub fn transform_key(provider: &str, pair: &str) -> String {
+    format!("{provider}|{pair}")
+}
+
+/// Fixed-bucket latency histogram with exponentially widening buckets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LatencyHistogram {
+    counts: [u64; LATENCY_BOUNDS_MS.len() + 1],
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self {
+            counts: [0; LATENCY_BOUNDS_MS.len() + 1],
+        }
+    }
+}
+
+impl LatencyHistogram {
+    pub fn record(&mut self, latency_ms: u64) {
+        let idx = LATENCY_BOUNDS_MS
+            .iter()
+            .position(|bound| latency_ms <= *bound)
+            .unwrap_or(LATENCY_BOUNDS_MS.len());
+        self.counts[idx] += 1;
+    }
+
+    pub fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Upper bound of the bucket holding the `q` quantile (0.0..=1.0); `None` when empty.
+    pub fn quantile_ms(&self, q: f64) -> Option<u64> {
+        let total = self.count();
+        if total == 0 {
+            return None;
+        }
+        let rank = ((q.clamp(0.0, 1.0) * total as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (idx, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                let last = LATENCY_BOUNDS_MS[LATENCY_BOUNDS_MS.len() - 1];
+                return Some(LATENCY_BOUNDS_MS.get(idx).copied().unwrap_or(last));
+            }
+        }
+        None
+    }
+
+    pub fn buckets(&self) -> Vec<u64> {
+        self.counts.to_vec()
+    }
+
+    /// Rebuilds a histogram from persisted bucket counts; a different layout is dropped.
+    pub fn from_buckets(buckets: &[u64]) -> Self {
+        let mut out = Self::default();
+        if buckets.len() == out.counts.len() {
+            out.counts.copy_from_slice(buckets);
+        }
+        out
+    }
+
+    fn merge(&mut self, other: &LatencyHistogram) {
+        for (a, b) in self.counts.iter_mut().zip(other.counts.iter()) {
+            *a += b;
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SeriesStats {
+    pub requests: u64,
+    pub errors: u64,
+    pub tokens: u64,
+    pub latency: LatencyHistogram,
+}
+
+impl SeriesStats {
+    pub fn error_rate(&self) -> f64 {
+        if self.requests == 0 {
+            0.0
+        } else {
+            self.errors as f64 / self.requests as f64
+        }
+    }
+
+    fn merge(&mut self, other: &SeriesStats) {
+        self.requests += other.requests;
+        self.errors += other.errors;
+        self.tokens += other.tokens;
+        self.latency.merge(&other.latency);
+    }
+}
+
+/// One observation applied to every series it belongs to.
+#[derive(Debug, Default, Clone, Copy)]
+struct Sample {
+    requests: u64,
+    errors: u64,
+    tokens: u64,
+    latency_ms: Option<u64>,
+}
+
+#[derive(Debug, Default, Clone)]
+struct Slot {
+    period: u64,
+    series: HashMap<SeriesKey, SeriesStats>,
+}
+
+#[derive(Debug)]
+struct Ring {
+    span_secs: u64,
+    slots: Vec<Slot>,
+}
+
+impl Ring {
+    fn new(span_secs: u64, len: usize) -> Self {
+        Self {
+            span_secs,
+            slots: vec![Slot::default(); len],
+        }
+    }
+
+    fn period_of(&self, at: SystemTime) -> u64 {
+        unix_secs(at) / self.span_secs
+    }
+
+    fn slot(&mut self, period: u64) -> Option<&mut Slot> {
+        let len = self.slots.len() as u64;
+        let slot = &mut self.slots[(period % len) as usize];
+        if slot.period > period {
+            // Older than the period this slot now holds.
+            return None;
+        }
+        if slot.period != period {
+            *slot = Slot {
+                period,
+                series: HashMap::new(),
+            };
+        }
+        Some(slot)
+    }
+
+    fn add(&mut self, period: u64, keys: &[SeriesKey], sample: Sample) {
+        let Some(slot) = self.slot(period) else {
+            return;
+        };
+        for key in keys {
+            let series = slot.series.entry(key.clone()).or_default();
+            series.requests += sample.requests;
+            series.errors += sample.errors;
+            series.tokens += sample.tokens;
+            if let Some(latency_ms) = sample.latency_ms {
+                series.latency.record(latency_ms);
+            }
+        }
+    }
+
+    /// Sums the last `periods` slots up to and including `now`.
+    fn window(&self, now: u64, periods: u64) -> HashMap<SeriesKey, SeriesStats> {
+        let periods = periods.min(self.slots.len() as u64);
+        let mut out: HashMap<SeriesKey, SeriesStats> = HashMap::new();
+        for slot in &self.slots {
+            if slot.period > now || now - slot.period >= periods {
+                continue;
+            }
+            for (key, series) in &slot.series {
+                out.entry(key.clone()).or_default().merge(series);
+            }
+        }
+        out
+    }
+}
+
+/// Aggregated series over a time window.
+#[derive(Debug, Default, Clone)]
+pub struct StatsWindow {
+    series: HashMap<SeriesKey, SeriesStats>,
+}
+
+impl StatsWindow {
+    pub fn total(&self) -> SeriesStats {
+        self.get(StatsDimension::Total, "")
+    }
+
+    pub fn get(&self, dimension: StatsDimension, key: &str) -> SeriesStats {
+        self.series
+            .get(&(dimension, key.to_string()))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Busiest series of a dimension by request count.
+    pub fn top(&self, dimension: StatsDimension, limit: usize) -> Vec<(String, SeriesStats)> {
+        let mut out: Vec<_> = self
+            .series
+            .iter()
+            .filter(|((dim, _), _)| *dim == dimension)
+            .map(|((_, key), series)| (key.clone(), series.clone()))
+            .collect();
+        out.sort_by(|a, b| b.1.requests.cmp(&a.1.requests).then_with(|| a.0.cmp(&b.0)));
+        out.truncate(limit);
+        out
+    }
+
+    /// Transform pairs that served a provider's requests, busiest first.
+    pub fn transforms(&self, provider: &str) -> Vec<(String, SeriesStats)> {
+        let prefix = transform_key(provider, "");
+        let mut out: Vec<_> = self
+            .series
+            .iter()
+            .filter(|((dim, _), _)| *dim == StatsDimension::Transform)
+            .filter_map(|((_, key), series)| {
+                let pair = key.strip_prefix(&prefix)?;
+                Some((pair.to_string(), series.clone()))
+            })
+            .collect();
+        out.sort_by(|a, b| b.1.requests.cmp(&a.1.requests).then_with(|| a.0.cmp(&b.0)));
+        out
+    }
+}
+
+#[derive(Debug)]
+pub struct TrafficStats {
+    minutes: Mutex<Ring>,
+    hours: Mutex<Ring>,
+    active_streams: AtomicUsize,
+    encode_mismatches: AtomicU64,
+}
+
+impl Default for TrafficStats {
+    fn default() -> Self {
+        Self {
+            minutes: Mutex::new(Ring::new(60, MINUTE_SLOTS)),
+            hours: Mutex::new(Ring::new(3600, HOUR_SLOTS)),
+            active_streams: AtomicUsize::new(0),
+            encode_mismatches: AtomicU64::new(0),
+        }
+    }
+}
+
+/// Counts a stream as active until dropped.
+pub struct ActiveStreamGuard {
+    stats: Arc<TrafficStats>,
+}
+
+impl Drop for ActiveStreamGuard {
+    fn drop(&mut self) {
+        self.stats.active_streams.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl TrafficStats {
+    /// How far back the hourly ring reaches; older persisted rows are not restored.
+    pub const RETENTION: Duration = Duration::from_secs(3600 * HOUR_SLOTS as u64);
+
+    pub fn stream_started(self: &Arc<Self>) -> ActiveStreamGuard {
+        self.active_streams.fetch_add(1, Ordering::Relaxed);
+        ActiveStreamGuard {
+            stats: self.clone(),
+        }
+    }
+
+    pub fn active_streams(&self) -> usize {
+        self.active_streams.load(Ordering::Relaxed)
+    }
+
+    /// Counts a response that could not be encoded as the operation the client asked for.
+    pub fn record_encode_mismatch(&self) {
+        self.encode_mismatches.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Op-mismatched encodes since start; not persisted.
+    pub fn encode_mismatches(&self) -> u64 {
+        self.encode_mismatches.load(Ordering::Relaxed)
+    }
+
+    /// Totals over the last `minutes` minutes including the current one. Up to an hour
+    /// is answered per minute; longer windows round up to whole hours.
+    pub fn window(&self, minutes: u64) -> StatsWindow {
+        self.window_at(SystemTime::now(), minutes)
+    }
+
+    fn window_at(&self, now: SystemTime, minutes: u64) -> StatsWindow {
+        let series = if minutes <= MINUTE_SLOTS as u64 {
+            let ring = self.minutes.lock().unwrap_or_else(|e| e.into_inner());
+            ring.window(ring.period_of(now), minutes)
+        } else {
+            let ring = self.hours.lock().unwrap_or_else(|e| e.into_inner());
+            ring.window(ring.period_of(now), minutes.div_ceil(60))
+        };
+        StatsWindow { series }
+    }
+
+    fn record(&self, at: SystemTime, keys: &[SeriesKey], sample: Sample) {
+        {
+            let mut ring = self.minutes.lock().unwrap_or_else(|e| e.into_inner());
+            let period = ring.period_of(at);
+            ring.add(period, keys, sample);
+        }
+        let mut ring = self.hours.lock().unwrap_or_else(|e| e.into_inner());
+        let period = ring.period_of(at);
+        ring.add(period, keys, sample);
+    }
+
+    /// Hourly aggregates starting at or after `since`, for persistence.
+    pub fn snapshot_hours(&self, since: SystemTime) -> Vec<StatsHourlyRow> {
+        let ring = self.hours.lock().unwrap_or_else(|e| e.into_inner());
+        let since = ring.period_of(since);
+        let mut rows = Vec::new();
+        for slot in ring.slots.iter().filter(|slot| slot.period >= since) {
+            let Ok(hour) = OffsetDateTime::from_unix_timestamp((slot.period * 3600) as i64) else {
+                continue;
+            };
+            for ((dimension, key), series) in &slot.series {
+                rows.push(StatsHourlyRow {
+                    hour,
+                    dimension: dimension.as_str().to_string(),
+                    dimension_key: key.clone(),
+                    requests: series.requests as i64,
+                    errors: series.errors as i64,
+                    tokens: series.tokens as i64,
+                    latency_histogram: series.latency.buckets(),
+                });
+            }
+        }
+        rows
+    }
+
+    /// Restores persisted hourly aggregates into the hourly ring.
+    pub fn load_hours(&self, rows: &[StatsHourlyRow]) {
+        let mut ring = self.hours.lock().unwrap_or_else(|e| e.into_inner());
+        for row in rows {
+            let Some(dimension) = StatsDimension::parse(&row.dimension) else {
+                continue;
+            };
+            let Ok(period) = u64::try_from(row.hour.unix_timestamp() / 3600) else {
+                continue;
+            };
+            let Some(slot) = ring.slot(period) else {
+                continue;
+            };
+            slot.series
+                .entry((dimension, row.dimension_key.clone()))
+                .or_default()
+                .merge(&SeriesStats {
+                    requests: row.requests.max(0) as u64,
+                    errors: row.errors.max(0) as u64,
+                    tokens: row.tokens.max(0) as u64,
+                    latency: LatencyHistogram::from_buckets(&row.latency_histogram),
+                });
+        }
+    }
+}
+
+fn unix_secs(at: SystemTime) -> u64 {
+    at.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+impl EventSink for TrafficStats {
+    fn write<'a>(&'a self, event: &'a Event) -> Pin<Box<dyn Future<Output = ()> + Send + 'a>> {
+        Box::pin(async move {
+            match event {
+                Event::Downstream(ev) => {
+                    let failed = ev.response_status.is_none_or(|status| status >= 400);
+                    let mut keys = vec![(StatsDimension::Total, String::new())];
+                    if let Some(user_key_id) = ev.user_key_id {
+                        keys.push((StatsDimension::Key, user_key_id.to_string()));
+                    }
+                    let sample = Sample {
+                        requests: 1,
+                        errors: u64::from(failed),
+                        tokens: 0,
+                        latency_ms: ev.latency_ms,
+                    };
+                    self.record(ev.at, &keys, sample);
+                }
+                Event::Upstream(ev) if !ev.internal => {
+                    let failed = ev.error_kind.is_some()
+                        || ev.response_status.is_none_or(|status| status >= 400);
+                    let tokens = ev.usage.as_ref().map_or(0, |usage| {
+                        u64::from(usage.input_tokens.unwrap_or(0))
+                            + u64::from(usage.output_tokens.unwrap_or(0))
+                    });
+                    let mut keys = vec![(StatsDimension::Provider, ev.provider.clone())];
+                    if let Some(model) = &ev.model {
+                        keys.push((StatsDimension::Model, model.clone()));
+                    }
+                    if let Some(pair) = &ev.transform {
+                        keys.push((StatsDimension::Transform, transform_key(&ev.provider, pair)));
+                    }
+                    let sample = Sample {
+                        requests: 1,
+                        errors: u64::from(failed),
+                        tokens,
+                        latency_ms: ev.latency_ms,
+                    };
+                    self.record(ev.at, &keys, sample);
+
+                    // Tokens are only known upstream; credit them to the caller too.
+                    if tokens > 0 {
+                        let mut keys = vec![(StatsDimension::Total, String::new())];
+                        if let Some(user_key_id) = ev.user_key_id {
+                            keys.push((StatsDimension::Key, user_key_id.to_string()));
+                        }
+                        let sample = Sample {
+                            tokens,
+                            ..Sample::default()
+                        };
+                        self.record(ev.at, &keys, sample);
+                    }
+                }
+                _ => {}
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn total() -> Vec<SeriesKey> {
+        vec![(StatsDimension::Total, String::new())]
+    }
+
+    fn hit(latency_ms: u64, failed: bool) -> Sample {
+        Sample {
+            requests: 1,
+            errors: u64::from(failed),
+            tokens: 10,
+            latency_ms: Some(latency_ms),
+        }
+    }
+
+    #[test]
+    fn minute_windows_only_sum_recent_slots() {
+        let stats = TrafficStats::default();
+        let now = UNIX_EPOCH + Duration::from_secs(1_000 * 60);
+        let model = vec![(StatsDimension::Model, "m1".to_string())];
+        stats.record(now, &total(), hit(40, true));
+        stats.record(now, &model, hit(40, false));
+        stats.record(now - Duration::from_secs(600), &total(), hit(40, false));
+        // Same minute slot as `now` but an hour old, so it is dropped.
+        stats.record(now - Duration::from_secs(3600), &total(), hit(40, false));
+
+        let recent = stats.window_at(now, 5).total();
+        assert_eq!((recent.requests, recent.errors), (1, 1));
+        let hour = stats.window_at(now, 60);
+        assert_eq!(hour.total().requests, 2);
+        assert_eq!(hour.top(StatsDimension::Model, 5)[0].0, "m1");
+        // The hourly ring still has the older request.
+        assert_eq!(stats.window_at(now, 24 * 60).total().requests, 3);
+    }
+
+    #[test]
+    fn transforms_are_listed_per_provider() {
+        let stats = TrafficStats::default();
+        let now = SystemTime::now();
+        let key = |provider: &str, pair: &str| {
+            vec![(StatsDimension::Transform, transform_key(provider, pair))]
+        };
+        stats.record(now, &key("claude", "openai_chat->claude"), hit(40, false));
+        stats.record(now, &key("claude", "openai_chat->claude"), hit(40, true));
+        stats.record(now, &key("claude", "claude->claude"), hit(40, false));
+        stats.record(now, &key("claude-eu", "gemini->claude"), hit(40, false));
+
+        let pairs = stats.window_at(now, 60).transforms("claude");
+        let summary: Vec<_> = pairs
+            .iter()
+            .map(|(pair, series)| (pair.as_str(), series.requests, series.errors))
+            .collect();
+        assert_eq!(
+            summary,
+            [("openai_chat->claude", 2, 1), ("claude->claude", 1, 0)]
+        );
+    }
+
+    #[test]
+    fn histogram_quantiles_use_bucket_bounds() {
+        let mut hist = LatencyHistogram::default();
+        for ms in [5, 30, 40, 45, 900] {
+            hist.record(ms);
+        }
+        assert_eq!(hist.quantile_ms(0.5), Some(50));
+        assert_eq!(hist.quantile_ms(0.95), Some(1_000));
+        assert_eq!(LatencyHistogram::default().quantile_ms(0.5), None);
+        assert_eq!(LatencyHistogram::from_buckets(&hist.buckets()), hist);
+    }
+
+    #[test]
+    fn hourly_rows_round_trip() {
+        let stats = TrafficStats::default();
+        let now = SystemTime::now();
+        let key = vec![(StatsDimension::Key, "7".to_string())];
+        stats.record(now, &key, hit(120, false));
+        let rows = stats.snapshot_hours(now - Duration::from_secs(3600));
+        assert_eq!(rows.len(), 1);
+
+        let restored = TrafficStats::default();
+        restored.load_hours(&rows);
+        let series = restored
+            .window_at(now, 2 * 60)
+            .get(StatsDimension::Key, "7");
+        assert_eq!((series.requests, series.tokens), (1, 10));
+        assert_eq!(series.latency.quantile_ms(0.5), Some(250));
+    }
+
+    #[test]
+    fn stream_guard_tracks_active_streams() {
+        let stats = Arc::new(TrafficStats::default());
+        let guard = stats.stream_started();
+        assert_eq!(stats.active_streams(), 1);
+        drop(guard);
+        assert_eq!(stats.active_streams(), 0);
+    }
+
+    #[test]
+    fn encode_mismatches_are_counted() {
+        let stats = TrafficStats::default();
+        assert_eq!(stats.encode_mismatches(), 0);
+        stats.record_encode_mismatch();
+        stats.record_encode_mismatch();
+        assert_eq!(stats.encode_mismatches(), 2);
+    }
+}
@@ -0,0 +1,131 @@
+//! Incident switches flipped through the admin API: maintenance mode answers every proxy
+//! call with a 503 while the admin API stays up, and read-only mode refuses admin config
+//! edits. Both live in memory only and are off again after a restart.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use arc_swap::ArcSwapOption;
+use serde::Serialize;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+/// Message of the maintenance 503 when none is given.
+pub const DEFAULT_MAINTENANCE_MESSAGE: &str = "gproxy is down for maintenance";
+
+#[derive(Debug)]
+struct Maintenance {
+    message: String,
+    since: OffsetDateTime,
+}
+
+#[derive(Debug, Default)]
+pub struct SystemMode {
+    maintenance: ArcSwapOption<Maintenance>,
+    read_only: AtomicBool,
+    read_only_since: ArcSwapOption<OffsetDateTime>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SystemModeStatus {
+    pub maintenance: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub maintenance_message: Option<String>,
+    /// RFC 3339.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub maintenance_since: Option<String>,
+    pub read_only: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub read_only_since: Option<String>,
+}
+
+impl SystemMode {
+    /// The message proxy calls are refused with, while maintenance mode is on.
+    pub fn maintenance_message(&self) -> Option<String> {
+        self.maintenance
+            .load()
+            .as_ref()
+            .map(|maintenance| maintenance.message.clone())
+    }
+
+    /// Turns maintenance mode on (with `message`, or the default) or off. Turning it on
+    /// again only replaces the message.
+    pub fn set_maintenance(&self, enabled: bool, message: Option<String>) {
+        if !enabled {
+            self.maintenance.store(None);
+            return;
+        }
+        let message = message
+            .map(|message| message.trim().to_string())
+            .filter(|message| !message.is_empty())
+            .unwrap_or_else(|| DEFAULT_MAINTENANCE_MESSAGE.to_string());
+        let since = self
+            .maintenance
+            .load()
+            .as_ref()
+            .map_or_else(OffsetDateTime::now_utc, |current| current.since);
+        self.maintenance
+            .store(Some(Arc::new(Maintenance { message, since })));
+    }
+
+    pub fn read_only(&self) -> bool {
+        self.read_only.load(Ordering::Relaxed)
+    }
+
+    pub fn set_read_only(&self, enabled: bool) {
+        if self.read_only.swap(enabled, Ordering::Relaxed) == enabled {
+            return;
+        }
+        self.read_only_since
+            .store(enabled.then(|| Arc::new(OffsetDateTime::now_utc())));
+    }
+
+    pub fn status(&self) -> SystemModeStatus {
+        let maintenance = self.maintenance.load();
+        SystemModeStatus {
+            maintenance: maintenance.is_some(),
+            maintenance_message: maintenance.as_ref().map(|m| m.message.clone()),
+            maintenance_since: maintenance
+                .as_ref()
+                .and_then(|m| m.since.format(&Rfc3339).ok()),
+            read_only: self.read_only(),
+            read_only_since: self
+                .read_only_since
+                .load()
+                .as_deref()
+                .and_then(|since| since.format(&Rfc3339).ok()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maintenance_keeps_its_start_and_falls_back_to_the_default_message() {
+        let mode = SystemMode::default();
+        assert_eq!(mode.maintenance_message(), None);
+
+        mode.set_maintenance(true, Some("  ".to_string()));
+        assert_eq!(
+            mode.maintenance_message().as_deref(),
+            Some(DEFAULT_MAINTENANCE_MESSAGE)
+        );
+        let since = mode.status().maintenance_since;
+
+        mode.set_maintenance(true, Some("DB migration, back at 14:00 UTC".to_string()));
+        let status = mode.status();
+        assert_eq!(
+            status.maintenance_message.as_deref(),
+            Some("DB migration, back at 14:00 UTC")
+        );
+        assert_eq!(status.maintenance_since, since);
+
+        mode.set_maintenance(false, None);
+        mode.set_read_only(true);
+        let status = mode.status();
+        assert!(!status.maintenance && status.read_only);
+        assert!(status.read_only_since.is_some());
+    }
+}
@@ -0,0 +1,109 @@
+//! Client tool calls seen in generate responses, written to the `tool_calls` table.
+//!
+//! Calls with an id are remembered per key for a while so that the request carrying their
+//! result can stamp the call with how long the client took to run the tool.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use time::OffsetDateTime;
+
+use gproxy_storage::{Storage, ToolCallRecord};
+
+/// How long a call waits for its result before it is forgotten.
+const PENDING_TTL: Duration = Duration::from_secs(60 * 60);
+/// Most calls awaiting a result at once; older ones go first when full.
+const MAX_PENDING: usize = 10_000;
+
+struct Pending {
+    row_id: i64,
+    at: OffsetDateTime,
+    seen: Instant,
+}
+
+#[derive(Default)]
+pub struct ToolCalls {
+    storage: OnceLock<Arc<dyn Storage>>,
+    /// (user key, call id) -> the stored call awaiting its result.
+    pending: Arc<Mutex<HashMap<(i64, String), Pending>>>,
+}
+
+impl ToolCalls {
+    /// Sets where calls are written; until then they are dropped.
+    pub fn start(&self, storage: Arc<dyn Storage>) {
+        let _ = self.storage.set(storage);
+    }
+
+    pub fn record(&self, record: ToolCallRecord) {
+        let Some(storage) = self.storage.get().cloned() else {
+            return;
+        };
+        let pending = self.pending.clone();
+        tokio::spawn(async move {
+            let row_id = match storage.append_tool_call(&record).await {
+                Ok(row_id) => row_id,
+                Err(err) => {
+                    gproxy_common::log_error!("tool_calls", "store tool call: {err}");
+                    return;
+                }
+            };
+            let Some(call_id) = record.call_id else {
+                return;
+            };
+            let mut pending = pending.lock().unwrap_or_else(|e| e.into_inner());
+            if pending.len() >= MAX_PENDING {
+                pending.retain(|_, call| call.seen.elapsed() < PENDING_TTL);
+                if pending.len() >= MAX_PENDING
+                    && let Some(oldest) = pending
+                        .iter()
+                        .min_by_key(|(_, call)| call.seen)
+                        .map(|(key, _)| key.clone())
+                {
+                    pending.remove(&oldest);
+                }
+            }
+            pending.insert(
+                (record.user_key_id, call_id),
+                Pending {
+                    row_id,
+                    at: record.at,
+                    seen: Instant::now(),
+                },
+            );
+        });
+    }
+
+    /// Stamps the latency of the key's calls whose results a request sends back.
+    pub fn results(&self, user_key_id: i64, call_ids: Vec<String>) {
+        if call_ids.is_empty() {
+            return;
+        }
+        let Some(storage) = self.storage.get().cloned() else {
+            return;
+        };
+        let done: Vec<Pending> = {
+            let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+            call_ids
+                .into_iter()
+                .filter_map(|call_id| pending.remove(&(user_key_id, call_id)))
+                .filter(|call| call.seen.elapsed() < PENDING_TTL)
+                .collect()
+        };
+        if done.is_empty() {
+            return;
+        }
+        let now = OffsetDateTime::now_utc();
+        tokio::spawn(async move {
+            for call in done {
+                let latency_ms = (now - call.at).whole_milliseconds().max(0) as i64;
+                if let Err(err) = storage
+                    .complete_tool_call(call.row_id, now, latency_ms)
+                    .await
+                {
+                    gproxy_common::log_error!("tool_calls", "store tool result: {err}");
+                }
+            }
+        });
+    }
+}
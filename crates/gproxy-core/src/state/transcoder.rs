@@ -0,0 +1,160 @@
+//! Worker threads for stream transcoding. Decoding an upstream chunk, converting its events
+//! to the client's protocol and re-encoding them is CPU work; with many cross-protocol
+//! streams at once it would hold up the runtime threads that also accept connections and
+//! move bytes. Streams hand each chunk to this pool instead and await the result.
+//!
+//! The queue is bounded, so a saturated pool slows the streams feeding it rather than
+//! buffering without limit. Workers start with the first job.
+
+use std::any::Any;
+use std::panic::{AssertUnwindSafe, catch_unwind, resume_unwind};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use serde::Serialize;
+use tokio::sync::{mpsc, oneshot};
+
+/// Most worker threads, whatever the core count.
+const MAX_WORKERS: usize = 8;
+/// Jobs that may wait per worker before submitters wait for room.
+const QUEUE_PER_WORKER: usize = 32;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+#[derive(Debug, Default)]
+struct Counters {
+    queued: AtomicUsize,
+    busy: AtomicUsize,
+    completed: AtomicU64,
+}
+
+/// Pool load for the admin overview.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct TranscoderStatus {
+    pub workers: usize,
+    pub queue_capacity: usize,
+    /// Jobs waiting for a worker.
+    pub queued: usize,
+    /// Jobs being run.
+    pub busy: usize,
+    pub completed: u64,
+}
+
+pub struct TranscoderPool {
+    workers: usize,
+    queue: OnceLock<mpsc::Sender<Job>>,
+    counters: Arc<Counters>,
+}
+
+impl Default for TranscoderPool {
+    fn default() -> Self {
+        let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+        Self::new(cores.min(MAX_WORKERS))
+    }
+}
+
+impl TranscoderPool {
+    pub fn new(workers: usize) -> Self {
+        Self {
+            workers: workers.max(1),
+            queue: OnceLock::new(),
+            counters: Arc::default(),
+        }
+    }
+
+    /// Runs `job` on a worker and returns its result. Waits for room when the queue is
+    /// full; a panic in `job` resumes in the caller.
+    pub async fn run<T, F>(&self, job: F) -> T
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel::<Result<T, Box<dyn Any + Send>>>();
+        let counters = self.counters.clone();
+        let job: Job = Box::new(move || {
+            counters.queued.fetch_sub(1, Ordering::Relaxed);
+            counters.busy.fetch_add(1, Ordering::Relaxed);
+            let result = catch_unwind(AssertUnwindSafe(job));
+            counters.busy.fetch_sub(1, Ordering::Relaxed);
+            counters.completed.fetch_add(1, Ordering::Relaxed);
+            let _ = tx.send(result);
+        });
+        self.counters.queued.fetch_add(1, Ordering::Relaxed);
+        if let Err(mpsc::error::SendError(job)) = self.queue().send(job).await {
+            // Workers are gone; the job still has to run.
+            job();
+        }
+        match rx.await {
+            Ok(Ok(value)) => value,
+            Ok(Err(panic)) => resume_unwind(panic),
+            Err(_) => panic!("transcoder job dropped without running"),
+        }
+    }
+
+    pub fn status(&self) -> TranscoderStatus {
+        TranscoderStatus {
+            workers: self.workers,
+            queue_capacity: self.workers * QUEUE_PER_WORKER,
+            queued: self.counters.queued.load(Ordering::Relaxed),
+            busy: self.counters.busy.load(Ordering::Relaxed),
+            completed: self.counters.completed.load(Ordering::Relaxed),
+        }
+    }
+
+    fn queue(&self) -> &mpsc::Sender<Job> {
+        self.queue.get_or_init(|| {
+            let (tx, rx) = mpsc::channel::<Job>(self.workers * QUEUE_PER_WORKER);
+            let rx = Arc::new(Mutex::new(rx));
+            for index in 0..self.workers {
+                let rx = rx.clone();
+                let spawned = std::thread::Builder::new()
+                    .name(format!("gproxy-transcode-{index}"))
+                    .spawn(move || {
+                        loop {
+                            let job = rx.lock().unwrap_or_else(|e| e.into_inner()).blocking_recv();
+                            match job {
+                                Some(job) => job(),
+                                None => break,
+                            }
+                        }
+                    });
+                if let Err(err) = spawned {
+                    gproxy_common::log_warn!("transcoder", "spawn worker {index}: {err}");
+                }
+            }
+            tx
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn runs_jobs_on_workers() {
+        let pool = TranscoderPool::new(2);
+        let caller = std::thread::current().id();
+        let (value, worker) = pool
+            .run(move || (21 * 2, std::thread::current().id()))
+            .await;
+        assert_eq!(value, 42);
+        assert_ne!(worker, caller);
+
+        let status = pool.status();
+        assert_eq!((status.queued, status.busy, status.completed), (0, 0, 1));
+        assert_eq!(status.queue_capacity, 2 * QUEUE_PER_WORKER);
+    }
+
+    #[tokio::test]
+    async fn job_panics_reach_the_caller() {
+        let pool = Arc::new(TranscoderPool::new(1));
+        let task = tokio::spawn({
+            let pool = pool.clone();
+            async move { pool.run(|| -> u32 { panic!("bad chunk") }).await }
+        });
+        assert!(task.await.unwrap_err().is_panic());
+        // The worker survives the panic.
+        assert_eq!(pool.run(|| 7).await, 7);
+    }
+}
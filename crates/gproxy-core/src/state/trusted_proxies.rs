@@ -0,0 +1,98 @@
+//! Reverse proxies whose forwarding headers (`x-forwarded-for`, `x-real-ip`) and edge
+//! country headers are believed. Requests from any other peer are taken at their socket
+//! address, so a client cannot pick its own IP or country.
+
+use std::net::IpAddr;
+
+use gproxy_common::GlobalConfig;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrustedProxies {
+    nets: Vec<(IpAddr, u8)>,
+}
+
+impl TrustedProxies {
+    /// Parses `trusted_proxies`; entries that are not an address or CIDR are skipped.
+    pub fn from_global(global: &GlobalConfig) -> Self {
+        Self::parse(global.trusted_proxies.as_deref().unwrap_or_default())
+    }
+
+    /// Comma-separated addresses (`10.0.0.1`) and CIDRs (`10.0.0.0/8`, `fd00::/8`).
+    pub fn parse(list: &str) -> Self {
+        Self {
+            nets: list
+                .split(',')
+                .map(str::trim)
+                .filter(|entry| !entry.is_empty())
+                .filter_map(parse_net)
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nets.is_empty()
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let ip = canonical(ip);
+        self.nets
+            .iter()
+            .any(|(net, prefix)| in_net(ip, *net, *prefix))
+    }
+}
+
+fn parse_net(entry: &str) -> Option<(IpAddr, u8)> {
+    let (addr, prefix) = match entry.split_once('/') {
+        Some((addr, prefix)) => (addr, Some(prefix)),
+        None => (entry, None),
+    };
+    let addr = canonical(addr.parse::<IpAddr>().ok()?);
+    let max = if addr.is_ipv4() { 32 } else { 128 };
+    let prefix = match prefix {
+        Some(prefix) => prefix.parse::<u8>().ok().filter(|prefix| *prefix <= max)?,
+        None => max,
+    };
+    Some((addr, prefix))
+}
+
+/// IPv4-mapped IPv6 peers (`::ffff:10.0.0.1`, seen on dual-stack listeners) compare as IPv4.
+fn canonical(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(IpAddr::V6(v6), IpAddr::V4),
+        ip => ip,
+    }
+}
+
+fn in_net(ip: IpAddr, net: IpAddr, prefix: u8) -> bool {
+    match (ip, net) {
+        (IpAddr::V4(ip), IpAddr::V4(net)) => {
+            let mask = u32::MAX.checked_shl(32 - u32::from(prefix)).unwrap_or(0);
+            u32::from(ip) & mask == u32::from(net) & mask
+        }
+        (IpAddr::V6(ip), IpAddr::V6(net)) => {
+            let mask = u128::MAX.checked_shl(128 - u32::from(prefix)).unwrap_or(0);
+            u128::from(ip) & mask == u128::from(net) & mask
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn addresses_and_cidrs_match_their_ranges() {
+        let trusted =
+            TrustedProxies::parse(" 10.0.0.0/8, 192.168.1.7 ,fd00::/8, nonsense, 1.2.3.4/33");
+        assert_eq!(trusted.nets.len(), 3);
+        for ip in ["10.1.2.3", "192.168.1.7", "fd12::1", "::ffff:10.9.9.9"] {
+            assert!(trusted.contains(ip.parse().unwrap()), "{ip}");
+        }
+        for ip in ["11.0.0.1", "192.168.1.8", "fe80::1", "::1"] {
+            assert!(!trusted.contains(ip.parse().unwrap()), "{ip}");
+        }
+        assert!(TrustedProxies::parse("0.0.0.0/0").contains("8.8.8.8".parse().unwrap()));
+        assert!(TrustedProxies::parse("").is_empty());
+    }
+}
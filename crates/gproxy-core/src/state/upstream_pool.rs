@@ -0,0 +1,267 @@
+//! What the upstream HTTP client is holding: in-flight requests per host, the DNS cache,
+//! connection-level failures and the number of cached clients, plus a flush switch.
+//!
+//! wreq keeps its connection pool private, so "open connections" is approximated by the
+//! requests (and streamed bodies) currently in flight to a host.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use time::OffsetDateTime;
+
+/// How long a resolved address list is served from the cache.
+pub const DNS_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// Connection-level failure classes counted per host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectFailure {
+    Dns,
+    Connect,
+    Tls,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HostPoolStats {
+    pub in_flight: u64,
+    pub requests: u64,
+    pub dns_failures: u64,
+    pub connect_failures: u64,
+    pub tls_failures: u64,
+    pub last_failure_at: Option<OffsetDateTime>,
+    pub last_failure: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DnsCacheEntry {
+    pub host: String,
+    pub addrs: Vec<SocketAddr>,
+    pub age: Duration,
+    /// Lookups answered from this entry since it was resolved.
+    pub hits: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UpstreamPoolSnapshot {
+    pub cached_clients: usize,
+    pub flushes: u64,
+    pub hosts: Vec<(String, HostPoolStats)>,
+    pub dns: Vec<DnsCacheEntry>,
+}
+
+#[derive(Debug)]
+struct CachedAddrs {
+    addrs: Vec<SocketAddr>,
+    resolved_at: Instant,
+    hits: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct UpstreamPoolStats {
+    hosts: Mutex<HashMap<String, HostPoolStats>>,
+    /// Requests in flight per credential, for draining a credential before it is removed.
+    credentials: Mutex<HashMap<i64, u64>>,
+    dns: Mutex<HashMap<String, CachedAddrs>>,
+    cached_clients: AtomicUsize,
+    /// Bumped by `flush`; clients drop their cached connections when it moves.
+    generation: AtomicU64,
+}
+
+impl UpstreamPoolStats {
+    /// Counts a request to `host` (made with `credential_id`, if any) as in flight until
+    /// the guard is dropped.
+    pub fn begin(self: &Arc<Self>, host: &str, credential_id: Option<i64>) -> InFlightGuard {
+        {
+            let mut hosts = self.hosts.lock().unwrap_or_else(|e| e.into_inner());
+            let entry = hosts.entry(host.to_string()).or_default();
+            entry.in_flight += 1;
+            entry.requests += 1;
+        }
+        if let Some(id) = credential_id {
+            let mut credentials = self.credentials.lock().unwrap_or_else(|e| e.into_inner());
+            *credentials.entry(id).or_default() += 1;
+        }
+        InFlightGuard {
+            stats: self.clone(),
+            host: host.to_string(),
+            credential_id,
+        }
+    }
+
+    /// Requests (including streamed bodies) currently in flight with `credential_id`.
+    pub fn credential_in_flight(&self, credential_id: i64) -> u64 {
+        self.credentials
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&credential_id)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    pub fn record_failure(&self, host: &str, failure: ConnectFailure, message: &str) {
+        let mut hosts = self.hosts.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = hosts.entry(host.to_string()).or_default();
+        match failure {
+            ConnectFailure::Dns => entry.dns_failures += 1,
+            ConnectFailure::Connect => entry.connect_failures += 1,
+            ConnectFailure::Tls => entry.tls_failures += 1,
+        }
+        entry.last_failure_at = Some(OffsetDateTime::now_utc());
+        entry.last_failure = Some(message.to_string());
+    }
+
+    /// Fresh cached addresses for `host`, if any.
+    pub fn cached_addrs(&self, host: &str) -> Option<Vec<SocketAddr>> {
+        self.cached_addrs_at(host, Instant::now())
+    }
+
+    fn cached_addrs_at(&self, host: &str, now: Instant) -> Option<Vec<SocketAddr>> {
+        let mut dns = self.dns.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = dns.get_mut(host)?;
+        if now.duration_since(entry.resolved_at) >= DNS_CACHE_TTL {
+            dns.remove(host);
+            return None;
+        }
+        entry.hits += 1;
+        Some(entry.addrs.clone())
+    }
+
+    pub fn store_addrs(&self, host: &str, addrs: Vec<SocketAddr>) {
+        let mut dns = self.dns.lock().unwrap_or_else(|e| e.into_inner());
+        dns.insert(
+            host.to_string(),
+            CachedAddrs {
+                addrs,
+                resolved_at: Instant::now(),
+                hits: 0,
+            },
+        );
+    }
+
+    pub fn set_cached_clients(&self, count: usize) {
+        self.cached_clients.store(count, Ordering::Relaxed);
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// Empties the DNS cache and tells clients to drop their connection pools. Requests
+    /// already in flight finish on the connections they hold. Returns the DNS entries dropped.
+    pub fn flush(&self) -> usize {
+        let dropped = {
+            let mut dns = self.dns.lock().unwrap_or_else(|e| e.into_inner());
+            let dropped = dns.len();
+            dns.clear();
+            dropped
+        };
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        dropped
+    }
+
+    pub fn snapshot(&self) -> UpstreamPoolSnapshot {
+        let now = Instant::now();
+        let mut hosts: Vec<_> = self
+            .hosts
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .map(|(host, stats)| (host.clone(), stats.clone()))
+            .collect();
+        hosts.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut dns: Vec<_> = self
+            .dns
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .map(|(host, entry)| DnsCacheEntry {
+                host: host.clone(),
+                addrs: entry.addrs.clone(),
+                age: now.duration_since(entry.resolved_at),
+                hits: entry.hits,
+            })
+            .collect();
+        dns.sort_by(|a, b| a.host.cmp(&b.host));
+        UpstreamPoolSnapshot {
+            cached_clients: self.cached_clients.load(Ordering::Relaxed),
+            flushes: self.generation(),
+            hosts,
+            dns,
+        }
+    }
+}
+
+/// Keeps a request counted as in flight; streamed responses hold it until the body ends.
+#[derive(Debug)]
+pub struct InFlightGuard {
+    stats: Arc<UpstreamPoolStats>,
+    host: String,
+    credential_id: Option<i64>,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        {
+            let mut hosts = self.stats.hosts.lock().unwrap_or_else(|e| e.into_inner());
+            if let Some(entry) = hosts.get_mut(&self.host) {
+                entry.in_flight = entry.in_flight.saturating_sub(1);
+            }
+        }
+        if let Some(id) = self.credential_id {
+            let mut credentials = self
+                .stats
+                .credentials
+                .lock()
+                .unwrap_or_else(|e| e.into_inner());
+            if let Some(count) = credentials.get_mut(&id) {
+                *count = count.saturating_sub(1);
+                if *count == 0 {
+                    credentials.remove(&id);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn in_flight_follows_guards_and_flush_clears_dns() {
+        let stats = Arc::new(UpstreamPoolStats::default());
+        let first = stats.begin("api.example.com", None);
+        let second = stats.begin("api.example.com", Some(7));
+        drop(first);
+        assert_eq!(stats.credential_in_flight(7), 1);
+        stats.record_failure("api.example.com", ConnectFailure::Tls, "handshake");
+        stats.store_addrs("api.example.com", vec!["10.0.0.1:0".parse().unwrap()]);
+        assert!(stats.cached_addrs("api.example.com").is_some());
+
+        let snapshot = stats.snapshot();
+        let host = &snapshot.hosts[0].1;
+        assert_eq!(
+            (host.in_flight, host.requests, host.tls_failures),
+            (1, 2, 1)
+        );
+        assert_eq!(snapshot.dns[0].hits, 1);
+        drop(second);
+        assert_eq!(stats.credential_in_flight(7), 0);
+
+        assert_eq!(stats.flush(), 1);
+        assert_eq!(stats.generation(), 1);
+        assert!(stats.cached_addrs("api.example.com").is_none());
+        assert_eq!(stats.snapshot().hosts[0].1.in_flight, 0);
+    }
+
+    #[test]
+    fn dns_entries_expire_after_ttl() {
+        let stats = UpstreamPoolStats::default();
+        stats.store_addrs("api.example.com", vec!["10.0.0.1:0".parse().unwrap()]);
+        let later = Instant::now() + DNS_CACHE_TTL;
+        assert!(stats.cached_addrs_at("api.example.com", later).is_none());
+        assert!(stats.snapshot().dns.is_empty());
+    }
+}
@@ -0,0 +1,53 @@
+//! Temporary user keys: minted through the admin API with a TTL and an optional
+//! provider/model scope. Auth refuses a key past its `expires_at`; a periodic job then
+//! deletes it.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use time::OffsetDateTime;
+
+use gproxy_storage::Storage;
+
+use crate::state::AppState;
+
+/// Shortest TTL a temporary key may be minted with.
+pub const MIN_TTL_SECS: i64 = 60;
+/// Longest TTL a temporary key may be minted with.
+pub const MAX_TTL_SECS: i64 = 30 * 24 * 3600;
+
+const EXPIRY_INTERVAL: Duration = Duration::from_secs(60);
+const EXPIRY_JITTER: Duration = Duration::from_secs(5);
+
+/// Deletes expired keys once a minute.
+pub fn register_user_key_expiry(state: &Arc<AppState>, storage: Arc<dyn Storage>) {
+    let app = state.clone();
+    state.jobs.register(
+        "user_key_expiry",
+        EXPIRY_INTERVAL,
+        EXPIRY_JITTER,
+        move || {
+            let app = app.clone();
+            let storage = storage.clone();
+            async move {
+                let now = OffsetDateTime::now_utc();
+                let expired: Vec<i64> = app
+                    .snapshot
+                    .load()
+                    .user_keys
+                    .iter()
+                    .filter(|k| k.expires_at.is_some_and(|at| at <= now))
+                    .map(|k| k.id)
+                    .collect();
+                for &id in &expired {
+                    storage
+                        .delete_user_key(id)
+                        .await
+                        .map_err(|err| format!("delete user key {id}: {err}"))?;
+                    app.apply_user_key_delete(id);
+                }
+                Ok((!expired.is_empty()).then(|| format!("deleted {}", expired.len())))
+            }
+        },
+    );
+}
@@ -1,24 +1,74 @@
 use std::collections::HashMap;
 use std::future::Future;
+use std::io::{Read, Write};
+use std::net::{IpAddr, SocketAddr};
 use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use bytes::Bytes;
 use futures_util::StreamExt;
+use tokio::time::Instant;
+use wreq::dns::{Addrs, Name, Resolve, Resolving};
+use wreq::tls::CertStore;
 use wreq::{Client, Method, Proxy};
 
+use crate::state::{ConnectFailure, InFlightGuard, UpstreamPoolStats};
 use gproxy_common::GlobalConfig;
 use gproxy_provider_core::provider::{UpstreamFailure, UpstreamTransportErrorKind};
 use gproxy_provider_core::{
-    Headers, HttpMethod, UpstreamBody, UpstreamHttpRequest, UpstreamHttpResponse,
+    EgressPolicy, Headers, HttpMethod, IpFamily, TlsPolicy, UpstreamBody, UpstreamHttpRequest,
+    UpstreamHttpResponse, UpstreamTimeouts, header_get, header_remove,
 };
 
+/// The `accept-encoding` sent upstream: the encodings [`ContentEncoding`] decodes.
+const ACCEPT_ENCODING: &str = "gzip, deflate";
+
+type SendFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<UpstreamHttpResponse, UpstreamFailure>> + Send + 'a>>;
+
+/// Per-provider transport settings layered over the client defaults.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SendOptions {
+    pub timeouts: UpstreamTimeouts,
+    pub egress: EgressPolicy,
+    pub tls: TlsPolicy,
+    /// Proxy picked from the provider's pool; replaces the global proxy.
+    pub proxy: Option<String>,
+    /// Credential the request is made with; counted as in flight while it runs.
+    pub credential_id: Option<i64>,
+}
+
 pub trait UpstreamClient: Send + Sync {
-    fn send<'a>(
+    fn send<'a>(&'a self, req: UpstreamHttpRequest) -> SendFuture<'a>;
+
+    /// Like `send`, with per-provider timeouts and egress settings.
+    fn send_with_options<'a>(
         &'a self,
         req: UpstreamHttpRequest,
-    ) -> Pin<Box<dyn Future<Output = Result<UpstreamHttpResponse, UpstreamFailure>> + Send + 'a>>;
+        _options: SendOptions,
+    ) -> SendFuture<'a> {
+        self.send(req)
+    }
+}
+
+/// The global egress settings; unparsable values are ignored.
+pub fn global_egress(global: &GlobalConfig) -> EgressPolicy {
+    let trimmed = |value: &Option<String>| {
+        value
+            .as_deref()
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+    };
+    EgressPolicy {
+        ip_family: trimmed(&global.egress_ip_family).and_then(|value| IpFamily::parse(&value)),
+        local_address: trimmed(&global.egress_local_address)
+            .and_then(|value| value.parse::<IpAddr>().ok()),
+        interface: trimmed(&global.egress_interface),
+        ..EgressPolicy::default()
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -55,7 +105,24 @@ impl Default for UpstreamClientConfig {
 pub struct WreqUpstreamClient {
     config: UpstreamClientConfig,
     proxy_resolver: Arc<dyn Fn() -> Option<String> + Send + Sync>,
-    clients: Arc<Mutex<HashMap<Option<String>, Client>>>,
+    egress_resolver: Arc<dyn Fn() -> EgressPolicy + Send + Sync>,
+    /// Largest decompressed body accepted (0 is no limit).
+    body_limit_resolver: Arc<dyn Fn() -> u64 + Send + Sync>,
+    clients: Arc<Mutex<HashMap<ClientKey, Client>>>,
+    pool: Arc<UpstreamPoolStats>,
+    /// The pool flush generation the cached clients were built under.
+    pool_generation: Arc<AtomicU64>,
+}
+
+/// Clients are cached per proxy, connect/read timeout, egress and TLS settings, which
+/// wreq only sets when a client is built.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ClientKey {
+    proxy: Option<String>,
+    connect_timeout: Duration,
+    read_timeout: Duration,
+    egress: EgressPolicy,
+    tls: TlsPolicy,
 }
 
 impl WreqUpstreamClient {
@@ -72,22 +139,65 @@ impl WreqUpstreamClient {
         F: Fn() -> Option<String> + Send + Sync + 'static,
     {
         let resolver: Arc<dyn Fn() -> Option<String> + Send + Sync> = Arc::new(proxy_resolver);
-        let initial_proxy = normalize_proxy(resolver());
-        let initial_client = build_client(&config, initial_proxy.as_deref())?;
+        let initial_key = ClientKey {
+            proxy: normalize_proxy(resolver()),
+            connect_timeout: config.connect_timeout,
+            read_timeout: config.stream_idle_timeout,
+            egress: EgressPolicy::default(),
+            tls: TlsPolicy::default(),
+        };
+        let pool = Arc::new(UpstreamPoolStats::default());
+        let initial_client = build_client(&config, &initial_key, &pool, None)?;
         let mut clients = HashMap::new();
-        clients.insert(initial_proxy, initial_client);
+        clients.insert(initial_key, initial_client);
+        pool.set_cached_clients(clients.len());
         Ok(Self {
             config,
             proxy_resolver: resolver,
+            egress_resolver: Arc::new(EgressPolicy::default),
+            body_limit_resolver: Arc::new(|| 0),
             clients: Arc::new(Mutex::new(clients)),
+            pool_generation: Arc::new(AtomicU64::new(pool.generation())),
+            pool,
         })
     }
 
+    /// Reports connections and DNS lookups into `pool` (usually `AppState::upstream_pool`)
+    /// and follows its flushes.
+    pub fn with_pool_stats(mut self, pool: Arc<UpstreamPoolStats>) -> Self {
+        // Clients built so far resolve through the old cache; rebuild them lazily.
+        self.clients = Arc::new(Mutex::new(HashMap::new()));
+        self.pool_generation = Arc::new(AtomicU64::new(pool.generation()));
+        pool.set_cached_clients(0);
+        self.pool = pool;
+        self
+    }
+
+    /// Supplies the default egress settings (usually from the live global config);
+    /// provider settings passed to `send_with_options` win over them.
+    pub fn with_egress_resolver<F>(mut self, egress_resolver: F) -> Self
+    where
+        F: Fn() -> EgressPolicy + Send + Sync + 'static,
+    {
+        self.egress_resolver = Arc::new(egress_resolver);
+        self
+    }
+
+    /// Supplies the limit on decompressed bodies (usually the live
+    /// `max_response_body_bytes`), so a small compressed answer cannot inflate past it.
+    pub fn with_body_limit_resolver<F>(mut self, body_limit_resolver: F) -> Self
+    where
+        F: Fn() -> u64 + Send + Sync + 'static,
+    {
+        self.body_limit_resolver = Arc::new(body_limit_resolver);
+        self
+    }
+
     fn current_proxy(&self) -> Option<String> {
         normalize_proxy((self.proxy_resolver)())
     }
 
-    fn client_for_proxy(&self, proxy: Option<String>) -> Result<Client, UpstreamFailure> {
+    fn client_for(&self, key: ClientKey) -> Result<Client, UpstreamFailure> {
         let mut guard = self
             .clients
             .lock()
@@ -95,11 +205,19 @@ impl WreqUpstreamClient {
                 kind: UpstreamTransportErrorKind::Other,
                 message: "upstream client cache lock failed".to_string(),
             })?;
-        if let Some(client) = guard.get(&proxy) {
+        let generation = self.pool.generation();
+        if self.pool_generation.swap(generation, Ordering::AcqRel) != generation {
+            // Flushed: dropping the clients closes their idle pooled connections.
+            guard.clear();
+        }
+        if let Some(client) = guard.get(&key) {
             return Ok(client.clone());
         }
-        let client = build_client(&self.config, proxy.as_deref()).map_err(map_wreq_error)?;
-        guard.insert(proxy, client.clone());
+        let cert_store = trusted_roots(&key.tls)?;
+        let client =
+            build_client(&self.config, &key, &self.pool, cert_store).map_err(map_wreq_error)?;
+        guard.insert(key, client.clone());
+        self.pool.set_cached_clients(guard.len());
         Ok(client)
     }
 }
@@ -110,27 +228,134 @@ fn normalize_proxy(value: Option<String>) -> Option<String> {
         .filter(|item| !item.is_empty())
 }
 
-fn build_client(config: &UpstreamClientConfig, proxy: Option<&str>) -> Result<Client, wreq::Error> {
+/// The built-in roots plus the policy's extra CAs; `None` keeps wreq's default store.
+/// The bundle file is read when a client is built, so edits apply after a pool flush.
+fn trusted_roots(tls: &TlsPolicy) -> Result<Option<CertStore>, UpstreamFailure> {
+    if tls.insecure_skip_verify || !tls.has_extra_roots() {
+        return Ok(None);
+    }
+    let tls_failure = |message: String| UpstreamFailure::Transport {
+        kind: UpstreamTransportErrorKind::Tls,
+        message,
+    };
+    let mut builder = CertStore::builder().add_der_certs(webpki_root_certs::TLS_SERVER_ROOT_CERTS);
+    if let Some(path) = tls.ca_bundle.as_deref() {
+        let pem = std::fs::read(path)
+            .map_err(|err| tls_failure(format!("read ca_bundle {path}: {err}")))?;
+        builder = builder.add_stack_pem_certs(pem);
+    }
+    if let Some(pem) = tls.ca_pem.as_deref() {
+        builder = builder.add_stack_pem_certs(pem);
+    }
+    builder
+        .build()
+        .map(Some)
+        .map_err(|err| tls_failure(format!("load extra CA certificates: {err}")))
+}
+
+fn build_client(
+    config: &UpstreamClientConfig,
+    key: &ClientKey,
+    pool: &Arc<UpstreamPoolStats>,
+    cert_store: Option<CertStore>,
+) -> Result<Client, wreq::Error> {
     let mut builder = Client::builder()
-        .connect_timeout(config.connect_timeout)
+        .connect_timeout(key.connect_timeout)
         .timeout(config.request_timeout)
-        .read_timeout(config.stream_idle_timeout);
+        .read_timeout(key.read_timeout)
+        .dns_resolver(CachingResolver {
+            pool: pool.clone(),
+            family: key.egress.ip_family.unwrap_or_default(),
+        });
 
-    if let Some(proxy) = proxy {
+    if let Some(proxy) = key.proxy.as_deref() {
         builder = builder.proxy(Proxy::all(proxy)?);
     }
+    if key.tls.insecure_skip_verify {
+        builder = builder.cert_verification(false).verify_hostname(false);
+    } else if let Some(cert_store) = cert_store {
+        builder = builder.cert_store(cert_store);
+    }
+    if let Some(address) = key.egress.local_address {
+        builder = builder.local_address(address);
+    }
+    #[cfg(any(
+        target_os = "android",
+        target_os = "fuchsia",
+        target_os = "illumos",
+        target_os = "linux",
+        target_os = "macos",
+        target_os = "solaris",
+    ))]
+    if let Some(interface) = key.egress.interface.clone() {
+        builder = builder.interface(interface);
+    }
 
     builder.build()
 }
 
+/// System resolver with a short-lived cache kept in `UpstreamPoolStats`, so cached
+/// entries can be listed and flushed from the admin API. Answers are ordered (or
+/// filtered) by the egress address family.
+struct CachingResolver {
+    pool: Arc<UpstreamPoolStats>,
+    family: IpFamily,
+}
+
+impl Resolve for CachingResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let pool = self.pool.clone();
+        let family = self.family;
+        Box::pin(async move {
+            let host = name.as_str().to_string();
+            let addrs = match pool.cached_addrs(&host) {
+                Some(addrs) => addrs,
+                None => match tokio::net::lookup_host((host.as_str(), 0)).await {
+                    Ok(addrs) => {
+                        let addrs: Vec<SocketAddr> = addrs.collect();
+                        pool.store_addrs(&host, addrs.clone());
+                        addrs
+                    }
+                    Err(err) => {
+                        pool.record_failure(&host, ConnectFailure::Dns, &err.to_string());
+                        return Err(err.into());
+                    }
+                },
+            };
+            let addrs = family.arrange(addrs);
+            if addrs.is_empty() {
+                let message = format!("no {} address for {host}", family.as_str());
+                pool.record_failure(&host, ConnectFailure::Dns, &message);
+                return Err(message.into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
 impl UpstreamClient for WreqUpstreamClient {
-    fn send<'a>(
+    fn send<'a>(&'a self, req: UpstreamHttpRequest) -> SendFuture<'a> {
+        self.send_with_options(req, SendOptions::default())
+    }
+
+    fn send_with_options<'a>(
         &'a self,
         req: UpstreamHttpRequest,
-    ) -> Pin<Box<dyn Future<Output = Result<UpstreamHttpResponse, UpstreamFailure>> + Send + 'a>>
-    {
+        options: SendOptions,
+    ) -> SendFuture<'a> {
         Box::pin(async move {
-            let client = self.client_for_proxy(self.current_proxy())?;
+            let timeouts = options.timeouts;
+            let idle_timeout = timeouts.idle().unwrap_or(self.config.stream_idle_timeout);
+            let client = self.client_for(ClientKey {
+                proxy: normalize_proxy(options.proxy).or_else(|| self.current_proxy()),
+                connect_timeout: timeouts.connect().unwrap_or(self.config.connect_timeout),
+                read_timeout: idle_timeout,
+                egress: options
+                    .egress
+                    .or((self.egress_resolver)())
+                    .without_proxy_pool(),
+                tls: options.tls,
+            })?;
             if req.url.starts_with("local://") {
                 let body = req.body.unwrap_or_default();
                 return Ok(UpstreamHttpResponse {
@@ -139,23 +364,107 @@ impl UpstreamClient for WreqUpstreamClient {
                     body: UpstreamBody::Bytes(body),
                 });
             }
+            let host = url_host(&req.url);
+            let in_flight = self.pool.begin(&host, options.credential_id);
             let method = http_method_to_wreq(req.method);
             let mut builder = client.request(method, &req.url);
 
             for (k, v) in &req.headers {
-                builder = builder.header(k, v);
+                if !k.eq_ignore_ascii_case("accept-encoding") {
+                    builder = builder.header(k, v);
+                }
+            }
+            // Only encodings `ContentEncoding` decodes are offered, whatever the caller
+            // asked for. Streams stay uncompressed unless the caller asked, so chunks are
+            // never held back by the encoder.
+            if !req.is_stream || header_get(&req.headers, "accept-encoding").is_some() {
+                builder = builder.header("accept-encoding", ACCEPT_ENCODING);
             }
 
             if let Some(body) = req.body {
                 builder = builder.body(body);
             }
 
-            let resp = builder.send().await.map_err(map_wreq_error)?;
-            convert_response(resp, req.is_stream, self.config.stream_idle_timeout).await
+            let deadline = timeouts.total().map(|total| Instant::now() + total);
+            let body_limit = (self.body_limit_resolver)();
+            let pool = &self.pool;
+            let exchange = async {
+                let resp = match timeouts.first_byte() {
+                    Some(limit) => {
+                        tokio::time::timeout(limit, builder.send())
+                            .await
+                            .map_err(|_| {
+                                timeout_failure(
+                                    UpstreamTransportErrorKind::FirstByteTimeout,
+                                    "upstream_first_byte_timeout",
+                                    limit,
+                                )
+                            })?
+                    }
+                    None => builder.send().await,
+                }
+                .map_err(|err| {
+                    let failure = map_wreq_error(err);
+                    record_connect_failure(pool, &host, &failure);
+                    failure
+                })?;
+                convert_response(
+                    resp,
+                    req.is_stream,
+                    idle_timeout,
+                    deadline,
+                    body_limit,
+                    in_flight,
+                )
+                .await
+            };
+            match timeouts.total() {
+                Some(total) => tokio::time::timeout(total, exchange).await.map_err(|_| {
+                    timeout_failure(
+                        UpstreamTransportErrorKind::Timeout,
+                        "upstream_total_timeout",
+                        total,
+                    )
+                })?,
+                None => exchange.await,
+            }
         })
     }
 }
 
+fn url_host(url: &str) -> String {
+    url.parse::<http::Uri>()
+        .ok()
+        .and_then(|uri| uri.host().map(str::to_string))
+        .unwrap_or_default()
+}
+
+fn record_connect_failure(pool: &UpstreamPoolStats, host: &str, failure: &UpstreamFailure) {
+    let UpstreamFailure::Transport { kind, message } = failure else {
+        return;
+    };
+    let class = match kind {
+        UpstreamTransportErrorKind::Dns => ConnectFailure::Dns,
+        UpstreamTransportErrorKind::Tls => ConnectFailure::Tls,
+        UpstreamTransportErrorKind::Connect | UpstreamTransportErrorKind::ConnectTimeout => {
+            ConnectFailure::Connect
+        }
+        _ => return,
+    };
+    pool.record_failure(host, class, message);
+}
+
+fn timeout_failure(
+    kind: UpstreamTransportErrorKind,
+    what: &str,
+    limit: Duration,
+) -> UpstreamFailure {
+    UpstreamFailure::Transport {
+        kind,
+        message: format!("{what} after {}ms", limit.as_millis()),
+    }
+}
+
 fn http_method_to_wreq(method: HttpMethod) -> Method {
     match method {
         HttpMethod::Get => Method::GET,
@@ -166,17 +475,34 @@ fn http_method_to_wreq(method: HttpMethod) -> Method {
     }
 }
 
+/// `deadline` is the total timeout; for streams it also ends the body once reached.
+/// `body_limit` caps decompression (see [`ContentEncoding::decode`]).
+/// Streams keep `in_flight` until the body ends.
 async fn convert_response(
     resp: wreq::Response,
     want_stream: bool,
     stream_idle_timeout: Duration,
+    deadline: Option<Instant>,
+    body_limit: u64,
+    in_flight: InFlightGuard,
 ) -> Result<UpstreamHttpResponse, UpstreamFailure> {
     let status = resp.status().as_u16();
-    let headers = headers_from_wreq(resp.headers());
+    let mut headers = headers_from_wreq(resp.headers());
+    let encoding = ContentEncoding::take_from(&mut headers);
 
     let is_success = (200..300).contains(&status);
     if !is_success || !want_stream {
         let body = resp.bytes().await.map_err(map_wreq_error)?;
+        let body =
+            match encoding {
+                Some(encoding) => encoding.decode(&body, body_limit).map_err(|err| {
+                    UpstreamFailure::Transport {
+                        kind: UpstreamTransportErrorKind::Other,
+                        message: format!("decode upstream body: {err}"),
+                    }
+                })?,
+                None => body,
+            };
         return Ok(UpstreamHttpResponse {
             status,
             headers,
@@ -186,9 +512,17 @@ async fn convert_response(
 
     let (tx, rx) = tokio::sync::mpsc::channel::<Bytes>(16);
     tokio::spawn(async move {
+        let _in_flight = in_flight;
         let mut stream = resp.bytes_stream();
+        let mut decoder = encoding.map(|encoding| StreamingDecoder::new(encoding, body_limit));
         loop {
-            let next = tokio::time::timeout(stream_idle_timeout, stream.next()).await;
+            let idle_deadline = Instant::now() + stream_idle_timeout;
+            let wait_until = deadline.map_or(idle_deadline, |deadline| deadline.min(idle_deadline));
+            let next = tokio::select! {
+                // The reader went away (e.g. the stream watchdog fired): drop the upstream.
+                _ = tx.closed() => break,
+                next = tokio::time::timeout_at(wait_until, stream.next()) => next,
+            };
             let item = match next {
                 Ok(item) => item,
                 Err(_) => break,
@@ -200,6 +534,14 @@ async fn convert_response(
                 Ok(chunk) => chunk,
                 Err(_) => break,
             };
+            let chunk = match decoder.as_mut() {
+                Some(decoder) => match decoder.push(&chunk) {
+                    Ok(chunk) if chunk.is_empty() => continue,
+                    Ok(chunk) => chunk,
+                    Err(_) => break,
+                },
+                None => chunk,
+            };
             if tx.send(chunk).await.is_err() {
                 break;
             }
@@ -213,11 +555,125 @@ async fn convert_response(
     })
 }
 
+/// `content-encoding` values decoded before bodies reach the engine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ContentEncoding {
+    Gzip,
+    Deflate,
+}
+
+impl ContentEncoding {
+    /// Strip the encoding (and the now-wrong length) from `headers` when it is supported.
+    fn take_from(headers: &mut Headers) -> Option<Self> {
+        let encoding = match header_get(headers, "content-encoding")?
+            .trim()
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "gzip" | "x-gzip" => ContentEncoding::Gzip,
+            "deflate" => ContentEncoding::Deflate,
+            _ => return None,
+        };
+        header_remove(headers, "content-encoding");
+        header_remove(headers, "content-length");
+        Some(encoding)
+    }
+
+    /// Decoding stops one byte past `limit` (0 is no limit), so an oversize body is
+    /// still over the limit for the engine's size check without being inflated whole.
+    fn decode(self, body: &[u8], limit: u64) -> std::io::Result<Bytes> {
+        let cap = if limit == 0 {
+            u64::MAX
+        } else {
+            limit.saturating_add(1)
+        };
+        let mut out = Vec::new();
+        match self {
+            ContentEncoding::Gzip => {
+                flate2::read::MultiGzDecoder::new(body)
+                    .take(cap)
+                    .read_to_end(&mut out)?;
+            }
+            ContentEncoding::Deflate => {
+                // HTTP `deflate` is zlib-wrapped, but some servers send raw deflate.
+                if flate2::read::ZlibDecoder::new(body)
+                    .take(cap)
+                    .read_to_end(&mut out)
+                    .is_err()
+                {
+                    out.clear();
+                    flate2::read::DeflateDecoder::new(body)
+                        .take(cap)
+                        .read_to_end(&mut out)?;
+                }
+            }
+        }
+        Ok(Bytes::from(out))
+    }
+}
+
+/// Compressed bytes fed to a [`StreamingDecoder`] between checks of its output size.
+const STREAM_DECODE_STEP: usize = 1024;
+
+/// Decodes a compressed stream chunk by chunk. `limit` (0 is no limit) caps what one
+/// upstream chunk may decode to; past it `push` fails and the stream ends.
+struct StreamingDecoder {
+    inner: StreamingInner,
+    limit: u64,
+}
+
+enum StreamingInner {
+    Gzip(flate2::write::GzDecoder<Vec<u8>>),
+    Deflate(flate2::write::ZlibDecoder<Vec<u8>>),
+}
+
+impl StreamingDecoder {
+    fn new(encoding: ContentEncoding, limit: u64) -> Self {
+        let inner = match encoding {
+            ContentEncoding::Gzip => {
+                StreamingInner::Gzip(flate2::write::GzDecoder::new(Vec::new()))
+            }
+            ContentEncoding::Deflate => {
+                StreamingInner::Deflate(flate2::write::ZlibDecoder::new(Vec::new()))
+            }
+        };
+        Self { inner, limit }
+    }
+
+    fn push(&mut self, chunk: &[u8]) -> std::io::Result<Bytes> {
+        for step in chunk.chunks(STREAM_DECODE_STEP) {
+            let decoded = match &mut self.inner {
+                StreamingInner::Gzip(decoder) => {
+                    decoder.write_all(step)?;
+                    decoder.flush()?;
+                    decoder.get_ref().len()
+                }
+                StreamingInner::Deflate(decoder) => {
+                    decoder.write_all(step)?;
+                    decoder.flush()?;
+                    decoder.get_ref().len()
+                }
+            };
+            if self.limit > 0 && decoded as u64 > self.limit {
+                return Err(std::io::Error::other(format!(
+                    "decoded chunk over {} bytes",
+                    self.limit
+                )));
+            }
+        }
+        let out = match &mut self.inner {
+            StreamingInner::Gzip(decoder) => std::mem::take(decoder.get_mut()),
+            StreamingInner::Deflate(decoder) => std::mem::take(decoder.get_mut()),
+        };
+        Ok(Bytes::from(out))
+    }
+}
+
 fn headers_from_wreq(map: &wreq::header::HeaderMap) -> Headers {
-    let mut out = Vec::new();
+    let mut out = Headers::with_capacity(map.len());
     for (k, v) in map {
         if let Ok(s) = v.to_str() {
-            out.push((k.as_str().to_string(), s.to_string()));
+            out.append(k.as_str(), s);
         }
     }
     out
@@ -234,6 +690,9 @@ fn map_wreq_error(err: wreq::Error) -> UpstreamFailure {
 fn classify_wreq_error(err: &wreq::Error) -> UpstreamTransportErrorKind {
     let message = err.to_string().to_ascii_lowercase();
     if err.is_timeout() {
+        if err.is_connect() {
+            return UpstreamTransportErrorKind::ConnectTimeout;
+        }
         if message.contains("read") || message.contains("idle") {
             return UpstreamTransportErrorKind::ReadTimeout;
         }
@@ -256,3 +715,60 @@ fn classify_wreq_error(err: &wreq::Error) -> UpstreamTransportErrorKind {
     }
     UpstreamTransportErrorKind::Other
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn gzip_body_is_decoded_and_headers_stripped() {
+        let mut headers: Headers = vec![
+            ("content-encoding".to_string(), "gzip".to_string()),
+            ("content-length".to_string(), "42".to_string()),
+            ("content-type".to_string(), "application/json".to_string()),
+        ]
+        .into();
+        let encoding = ContentEncoding::take_from(&mut headers).unwrap();
+        assert_eq!(headers.len(), 1);
+        let body = encoding.decode(&gzip(br#"{"ok":true}"#), 0).unwrap();
+        assert_eq!(body.as_ref(), br#"{"ok":true}"#);
+    }
+
+    #[test]
+    fn gzip_stream_is_decoded_incrementally() {
+        let compressed = gzip(b"data: one\n\ndata: two\n\n");
+        let mut decoder = StreamingDecoder::new(ContentEncoding::Gzip, 0);
+        let mut out = Vec::new();
+        for chunk in compressed.chunks(7) {
+            out.extend_from_slice(&decoder.push(chunk).unwrap());
+        }
+        assert_eq!(out, b"data: one\n\ndata: two\n\n");
+    }
+
+    #[test]
+    fn decoding_stops_past_the_body_limit() {
+        let compressed = gzip(&vec![b'a'; 1 << 20]);
+        let body = ContentEncoding::Gzip.decode(&compressed, 1000).unwrap();
+        assert_eq!(body.len(), 1001);
+        let body = ContentEncoding::Gzip.decode(&compressed, 0).unwrap();
+        assert_eq!(body.len(), 1 << 20);
+
+        let mut decoder = StreamingDecoder::new(ContentEncoding::Gzip, 1000);
+        assert!(decoder.push(&compressed).is_err());
+        let mut decoder = StreamingDecoder::new(ContentEncoding::Gzip, 1000);
+        assert_eq!(decoder.push(&gzip(b"data: one\n\n")).unwrap().len(), 11);
+    }
+
+    #[test]
+    fn unknown_encoding_is_left_alone() {
+        let mut headers: Headers = vec![("content-encoding".to_string(), "br".to_string())].into();
+        assert!(ContentEncoding::take_from(&mut headers).is_none());
+        assert_eq!(headers.len(), 1);
+    }
+}
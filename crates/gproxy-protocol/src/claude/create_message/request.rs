@@ -59,6 +59,9 @@ pub struct CreateMessageRequestBody {
     /// Range 0.0-1.0. Avoid setting both top_p and temperature.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub top_p: Option<f64>,
+    /// Fields not modeled above; see [`crate::ExtraFields`].
+    #[serde(flatten)]
+    pub extra: crate::ExtraFields,
 }
 
 #[derive(Debug, Clone)]
@@ -0,0 +1,9 @@
+pub mod request;
+pub mod response;
+pub mod types;
+
+pub use request::{
+    BatchGenerateContentPath, BatchGenerateContentRequest, BatchGenerateContentRequestBody,
+};
+pub use response::BatchGenerateContentResponse;
+pub use types::*;
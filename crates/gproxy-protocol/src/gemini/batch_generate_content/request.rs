@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+
+use crate::gemini::batch_generate_content::types::GenerateContentBatch;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchGenerateContentPath {
+    /// Format: models/{model}. It takes the form models/{model}.
+    pub model: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchGenerateContentRequestBody {
+    pub batch: GenerateContentBatch,
+}
+
+#[derive(Debug, Clone)]
+pub struct BatchGenerateContentRequest {
+    pub path: BatchGenerateContentPath,
+    pub body: BatchGenerateContentRequestBody,
+}
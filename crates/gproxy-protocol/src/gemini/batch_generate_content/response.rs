@@ -0,0 +1,44 @@
+use crate::gemini::batch_generate_content::types::BatchOperation;
+
+pub type BatchGenerateContentResponse = BatchOperation;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exposes_inlined_responses_of_finished_batch() {
+        let json = r#"
+        {
+          "name": "batches/123",
+          "done": true,
+          "response": {
+            "@type": "type.googleapis.com/google.ai.generativelanguage.v1beta.GenerateContentBatchOutput",
+            "inlinedResponses": {
+              "inlinedResponses": [
+                {
+                  "response": {
+                    "candidates": [],
+                    "usageMetadata": { "promptTokenCount": 3, "candidatesTokenCount": 5, "totalTokenCount": 8 }
+                  },
+                  "metadata": { "key": "a" }
+                },
+                { "error": { "code": 3, "message": "bad request" } }
+              ]
+            }
+          }
+        }
+        "#;
+
+        let parsed: BatchGenerateContentResponse =
+            serde_json::from_str(json).expect("deserialize batch operation");
+        assert_eq!(parsed.name, "batches/123");
+        assert_eq!(parsed.inlined_responses().len(), 2);
+        assert!(parsed.inlined_responses()[0].response.is_some());
+        assert!(parsed.inlined_responses()[1].error.is_some());
+
+        let running: BatchGenerateContentResponse =
+            serde_json::from_str(r#"{ "name": "batches/124" }"#).expect("deserialize running");
+        assert!(running.inlined_responses().is_empty());
+    }
+}
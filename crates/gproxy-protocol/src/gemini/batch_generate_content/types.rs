@@ -0,0 +1,99 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::gemini::generate_content::request::GenerateContentRequestBody;
+use crate::gemini::generate_content::response::GenerateContentResponse;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GenerateContentBatch {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    pub input_config: InputConfig,
+    /// Output only on reads; the model comes from the request path on create.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<i64>,
+}
+
+/// Either an uploaded JSONL file (`files/...`) or inline requests.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InputConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub requests: Option<InlinedRequests>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InlinedRequests {
+    pub requests: Vec<InlinedRequest>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InlinedRequest {
+    pub request: GenerateContentRequestBody,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Value>,
+}
+
+/// Long-running operation returned by batch create and get.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchOperation {
+    /// Format: batches/{batch}.
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub done: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response: Option<GenerateContentBatchOutput>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GenerateContentBatchOutput {
+    #[serde(rename = "@type", skip_serializing_if = "Option::is_none")]
+    pub type_url: Option<String>,
+    /// Set when the batch was submitted with a file; results are in that file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub responses_file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inlined_responses: Option<InlinedResponses>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InlinedResponses {
+    #[serde(default)]
+    pub inlined_responses: Vec<InlinedResponse>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InlinedResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response: Option<GenerateContentResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Value>,
+}
+
+impl BatchOperation {
+    /// Inner responses of a finished inline batch; empty while running or for file batches.
+    pub fn inlined_responses(&self) -> &[InlinedResponse] {
+        self.response
+            .as_ref()
+            .and_then(|output| output.inlined_responses.as_ref())
+            .map(|inlined| inlined.inlined_responses.as_slice())
+            .unwrap_or_default()
+    }
+}
@@ -31,6 +31,9 @@ pub struct GenerateContentRequestBody {
     pub generation_config: Option<GenerationConfig>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cached_content: Option<String>,
+    /// Fields not modeled above; see [`crate::ExtraFields`].
+    #[serde(flatten)]
+    pub extra: crate::ExtraFields,
 }
 
 #[derive(Debug, Clone)]
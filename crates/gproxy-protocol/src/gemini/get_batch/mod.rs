@@ -0,0 +1,5 @@
+pub mod request;
+pub mod response;
+
+pub use request::{GetBatchPath, GetBatchRequest};
+pub use response::GetBatchResponse;
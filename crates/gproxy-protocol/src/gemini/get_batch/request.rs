@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetBatchPath {
+    /// Format: batches/{batch}.
+    pub name: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct GetBatchRequest {
+    pub path: GetBatchPath,
+}
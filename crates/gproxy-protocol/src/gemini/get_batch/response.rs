@@ -0,0 +1,3 @@
+use crate::gemini::batch_generate_content::types::BatchOperation;
+
+pub type GetBatchResponse = BatchOperation;
@@ -1,5 +1,7 @@
+pub mod batch_generate_content;
 pub mod count_tokens;
 pub mod generate_content;
+pub mod get_batch;
 pub mod get_model;
 pub mod list_models;
 pub mod stream_content;
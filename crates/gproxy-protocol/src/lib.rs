@@ -2,3 +2,7 @@ pub mod claude;
 pub mod gemini;
 pub mod openai;
 pub mod sse;
+
+/// Top-level body fields a request struct doesn't model, captured with `#[serde(flatten)]`
+/// so same-protocol calls can forward them and strict providers can reject them.
+pub type ExtraFields = serde_json::Map<String, serde_json::Value>;
@@ -0,0 +1,16 @@
+pub mod request;
+pub mod response;
+pub mod types;
+
+pub use request::{
+    ConversationItemPath, ConversationItemQuery, ConversationPath, CreateConversationBody,
+    CreateConversationItemsBody, CreateConversationItemsRequest, CreateConversationRequest,
+    DeleteConversationItemRequest, DeleteConversationRequest, GetConversationItemRequest,
+    GetConversationRequest, ListConversationItemsQuery, ListConversationItemsRequest,
+    UpdateConversationBody, UpdateConversationRequest,
+};
+pub use response::{
+    ConversationItemListResponse, ConversationItemResponse, ConversationResponse,
+    DeleteConversationResponse,
+};
+pub use types::{Conversation, ConversationDeletedObjectType, ConversationObjectType};
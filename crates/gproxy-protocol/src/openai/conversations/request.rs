@@ -0,0 +1,134 @@
+use serde::{Deserialize, Serialize};
+
+use crate::openai::create_response::types::{InputItem, Metadata, ResponseInclude};
+use crate::openai::list_input_items::request::ListOrder;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConversationPath {
+    /// The ID of the conversation.
+    pub conversation_id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConversationItemPath {
+    /// The ID of the conversation that contains the item.
+    pub conversation_id: String,
+    /// The ID of the item.
+    pub item_id: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct CreateConversationBody {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub items: Option<Vec<InputItem>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Metadata>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CreateConversationRequest {
+    pub body: CreateConversationBody,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GetConversationRequest {
+    pub path: ConversationPath,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct UpdateConversationBody {
+    pub metadata: Metadata,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpdateConversationRequest {
+    pub path: ConversationPath,
+    pub body: UpdateConversationBody,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeleteConversationRequest {
+    pub path: ConversationPath,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct ListConversationItemsQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order: Option<ListOrder>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include: Option<Vec<ResponseInclude>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ListConversationItemsRequest {
+    pub path: ConversationPath,
+    #[serde(default)]
+    pub query: ListConversationItemsQuery,
+}
+
+/// `include` for item create/get.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct ConversationItemQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include: Option<Vec<ResponseInclude>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct CreateConversationItemsBody {
+    pub items: Vec<InputItem>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CreateConversationItemsRequest {
+    pub path: ConversationPath,
+    #[serde(default)]
+    pub query: ConversationItemQuery,
+    pub body: CreateConversationItemsBody,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GetConversationItemRequest {
+    pub path: ConversationItemPath,
+    #[serde(default)]
+    pub query: ConversationItemQuery,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeleteConversationItemRequest {
+    pub path: ConversationItemPath,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deserializes_create_conversation_body() {
+        let body: CreateConversationBody = serde_json::from_value(serde_json::json!({
+            "items": [{ "type": "message", "role": "user", "content": "Hello!" }],
+            "metadata": { "topic": "demo" },
+        }))
+        .expect("deserialize create conversation body");
+        assert_eq!(body.items.as_ref().map(Vec::len), Some(1));
+        assert_eq!(
+            body.metadata
+                .as_ref()
+                .and_then(|m| m.get("topic"))
+                .map(String::as_str),
+            Some("demo")
+        );
+
+        let empty: CreateConversationBody =
+            serde_json::from_value(serde_json::json!({})).expect("deserialize empty body");
+        assert!(empty.items.is_none() && empty.metadata.is_none());
+    }
+}
@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+
+use crate::openai::conversations::types::{Conversation, ConversationDeletedObjectType};
+use crate::openai::list_response_items::types::{ItemResource, ResponseItemList};
+
+/// Returned by create, get and update.
+pub type ConversationResponse = Conversation;
+
+pub type ConversationItemListResponse = ResponseItemList;
+
+pub type ConversationItemResponse = ItemResource;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DeleteConversationResponse {
+    pub id: String,
+    pub object: ConversationDeletedObjectType,
+    pub deleted: bool,
+}
@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+
+use crate::openai::create_response::types::Metadata;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ConversationObjectType {
+    #[serde(rename = "conversation")]
+    Conversation,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct Conversation {
+    pub id: String,
+    pub object: ConversationObjectType,
+    pub created_at: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Metadata>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ConversationDeletedObjectType {
+    #[serde(rename = "conversation.deleted")]
+    ConversationDeleted,
+}
@@ -99,6 +99,9 @@ pub struct CreateChatCompletionRequestBody {
     pub service_tier: Option<ServiceTier>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub prompt_cache_retention: Option<PromptCacheRetention>,
+    /// Fields not modeled above; see [`crate::ExtraFields`].
+    #[serde(flatten)]
+    pub extra: crate::ExtraFields,
 }
 
 #[derive(Debug, Clone)]
@@ -113,3 +116,27 @@ pub enum StopConfiguration {
     Single(String),
     Many(Vec<String>),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_body_fields_round_trip_through_extra() {
+        let body: CreateChatCompletionRequestBody = serde_json::from_value(serde_json::json!({
+            "model": "gpt-5",
+            "messages": [],
+            "verbosity_hint": { "level": 2 },
+        }))
+        .expect("parse chat request body");
+        assert_eq!(body.model, "gpt-5");
+        assert_eq!(
+            body.extra.keys().map(String::as_str).collect::<Vec<_>>(),
+            ["verbosity_hint"]
+        );
+
+        let value = serde_json::to_value(&body).expect("serialize chat request body");
+        assert_eq!(value["verbosity_hint"]["level"], 2);
+        assert!(value.get("extra").is_none());
+    }
+}
@@ -797,6 +797,9 @@ pub struct ChatCompletionResponseMessage {
     pub role: ChatCompletionResponseRole,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub content: Option<String>,
+    /// Reasoning text some OpenAI-compatible upstreams (e.g. DeepSeek) send next to the answer.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning_content: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub refusal: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -75,6 +75,9 @@ pub struct CreateResponseRequestBody {
     pub service_tier: Option<ServiceTier>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub prompt_cache_retention: Option<PromptCacheRetention>,
+    /// Fields not modeled above; see [`crate::ExtraFields`].
+    #[serde(flatten)]
+    pub extra: crate::ExtraFields,
 }
 
 #[derive(Debug, Clone)]
@@ -116,6 +116,67 @@ pub enum ResponseStreamEvent {
     CustomToolCallInputDone(ResponseCustomToolCallInputDoneEvent),
 }
 
+impl ResponseStreamEvent {
+    /// Position of the event in its response's stream.
+    pub fn sequence_number(&self) -> i64 {
+        match self {
+            ResponseStreamEvent::AudioDelta(ev) => ev.sequence_number,
+            ResponseStreamEvent::AudioDone(ev) => ev.sequence_number,
+            ResponseStreamEvent::AudioTranscriptDelta(ev) => ev.sequence_number,
+            ResponseStreamEvent::AudioTranscriptDone(ev) => ev.sequence_number,
+            ResponseStreamEvent::CodeInterpreterCallCodeDelta(ev) => ev.sequence_number,
+            ResponseStreamEvent::CodeInterpreterCallCodeDone(ev) => ev.sequence_number,
+            ResponseStreamEvent::CodeInterpreterCallCompleted(ev) => ev.sequence_number,
+            ResponseStreamEvent::CodeInterpreterCallInProgress(ev) => ev.sequence_number,
+            ResponseStreamEvent::CodeInterpreterCallInterpreting(ev) => ev.sequence_number,
+            ResponseStreamEvent::Completed(ev) => ev.sequence_number,
+            ResponseStreamEvent::ContentPartAdded(ev) => ev.sequence_number,
+            ResponseStreamEvent::ContentPartDone(ev) => ev.sequence_number,
+            ResponseStreamEvent::Created(ev) => ev.sequence_number,
+            ResponseStreamEvent::Error(ev) => ev.sequence_number,
+            ResponseStreamEvent::FileSearchCallCompleted(ev) => ev.sequence_number,
+            ResponseStreamEvent::FileSearchCallInProgress(ev) => ev.sequence_number,
+            ResponseStreamEvent::FileSearchCallSearching(ev) => ev.sequence_number,
+            ResponseStreamEvent::FunctionCallArgumentsDelta(ev) => ev.sequence_number,
+            ResponseStreamEvent::FunctionCallArgumentsDone(ev) => ev.sequence_number,
+            ResponseStreamEvent::InProgress(ev) => ev.sequence_number,
+            ResponseStreamEvent::Failed(ev) => ev.sequence_number,
+            ResponseStreamEvent::Incomplete(ev) => ev.sequence_number,
+            ResponseStreamEvent::OutputItemAdded(ev) => ev.sequence_number,
+            ResponseStreamEvent::OutputItemDone(ev) => ev.sequence_number,
+            ResponseStreamEvent::ReasoningSummaryPartAdded(ev) => ev.sequence_number,
+            ResponseStreamEvent::ReasoningSummaryPartDone(ev) => ev.sequence_number,
+            ResponseStreamEvent::ReasoningSummaryTextDelta(ev) => ev.sequence_number,
+            ResponseStreamEvent::ReasoningSummaryTextDone(ev) => ev.sequence_number,
+            ResponseStreamEvent::ReasoningTextDelta(ev) => ev.sequence_number,
+            ResponseStreamEvent::ReasoningTextDone(ev) => ev.sequence_number,
+            ResponseStreamEvent::RefusalDelta(ev) => ev.sequence_number,
+            ResponseStreamEvent::RefusalDone(ev) => ev.sequence_number,
+            ResponseStreamEvent::OutputTextDelta(ev) => ev.sequence_number,
+            ResponseStreamEvent::OutputTextDone(ev) => ev.sequence_number,
+            ResponseStreamEvent::WebSearchCallCompleted(ev) => ev.sequence_number,
+            ResponseStreamEvent::WebSearchCallInProgress(ev) => ev.sequence_number,
+            ResponseStreamEvent::WebSearchCallSearching(ev) => ev.sequence_number,
+            ResponseStreamEvent::ImageGenCallCompleted(ev) => ev.sequence_number,
+            ResponseStreamEvent::ImageGenCallGenerating(ev) => ev.sequence_number,
+            ResponseStreamEvent::ImageGenCallInProgress(ev) => ev.sequence_number,
+            ResponseStreamEvent::ImageGenCallPartialImage(ev) => ev.sequence_number,
+            ResponseStreamEvent::MCPCallArgumentsDelta(ev) => ev.sequence_number,
+            ResponseStreamEvent::MCPCallArgumentsDone(ev) => ev.sequence_number,
+            ResponseStreamEvent::MCPCallCompleted(ev) => ev.sequence_number,
+            ResponseStreamEvent::MCPCallFailed(ev) => ev.sequence_number,
+            ResponseStreamEvent::MCPCallInProgress(ev) => ev.sequence_number,
+            ResponseStreamEvent::MCPListToolsCompleted(ev) => ev.sequence_number,
+            ResponseStreamEvent::MCPListToolsFailed(ev) => ev.sequence_number,
+            ResponseStreamEvent::MCPListToolsInProgress(ev) => ev.sequence_number,
+            ResponseStreamEvent::OutputTextAnnotationAdded(ev) => ev.sequence_number,
+            ResponseStreamEvent::Queued(ev) => ev.sequence_number,
+            ResponseStreamEvent::CustomToolCallInputDelta(ev) => ev.sequence_number,
+            ResponseStreamEvent::CustomToolCallInputDone(ev) => ev.sequence_number,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub struct ResponseAudioDeltaEvent {
@@ -404,15 +404,17 @@ pub struct OutputMessage {
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub struct EasyInputMessage {
-    #[serde(rename = "type")]
+    /// Clients commonly leave `type` out; it can only be `message`.
+    #[serde(rename = "type", default)]
     pub r#type: EasyInputMessageType,
     pub role: EasyInputMessageRole,
     /// For input messages, only `input_*` content parts are valid (not enforced here).
     pub content: EasyInputMessageContent,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum EasyInputMessageType {
+    #[default]
     #[serde(rename = "message")]
     Message,
 }
@@ -678,6 +680,7 @@ pub enum AllowedTool {
     Function {
         name: String,
     },
+    #[serde(rename = "mcp")]
     MCP {
         server_label: String,
         #[serde(skip_serializing_if = "Option::is_none")]
@@ -828,6 +831,7 @@ pub enum Tool {
     LocalShell(LocalShellTool),
     Shell(FunctionShellTool),
     Custom(CustomTool),
+    #[serde(rename = "mcp")]
     MCP(MCPTool),
     ApplyPatch(ApplyPatchTool),
 }
@@ -1362,7 +1366,7 @@ pub enum Item {
     CustomToolCallOutput(CustomToolCallOutput),
     CustomToolCall(CustomToolCall),
     /// Forward-compatible fallback for unknown item shapes.
-    Unknown(Value),    
+    Unknown(Value),
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -0,0 +1,8 @@
+pub mod request;
+pub mod response;
+
+pub use request::{DeleteFileRequest, FilePath, GetFileRequest, ListFilesQuery, ListFilesRequest};
+pub use response::{
+    DeleteFileResponse, FileListObjectType, FileObject, FileObjectType, GetFileResponse,
+    ListFilesResponse,
+};
@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum FileObjectType {
+    #[serde(rename = "file")]
+    File,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct FileObject {
+    pub id: String,
+    pub object: FileObjectType,
+    pub bytes: i64,
+    pub created_at: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<i64>,
+    pub filename: String,
+    /// Kept as a string so new purposes pass through unchanged.
+    pub purpose: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_details: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum FileListObjectType {
+    #[serde(rename = "list")]
+    List,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct ListFilesResponse {
+    pub object: FileListObjectType,
+    pub data: Vec<FileObject>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub has_more: Option<bool>,
+}
+
+pub type GetFileResponse = FileObject;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DeleteFileResponse {
+    pub id: String,
+    pub object: FileObjectType,
+    pub deleted: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deserializes_file_list_payload() {
+        let json = r#"
+        {
+          "object": "list",
+          "data": [
+            {
+              "id": "file-abc123",
+              "object": "file",
+              "bytes": 175,
+              "created_at": 1613677385,
+              "filename": "salesOverview.pdf",
+              "purpose": "user_data"
+            }
+          ],
+          "has_more": false
+        }
+        "#;
+
+        let parsed: ListFilesResponse =
+            serde_json::from_str(json).expect("deserialize file list payload");
+        assert_eq!(parsed.data.len(), 1);
+        assert_eq!(parsed.data[0].purpose, "user_data");
+        assert_eq!(parsed.has_more, Some(false));
+    }
+}
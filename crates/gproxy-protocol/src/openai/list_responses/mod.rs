@@ -0,0 +1,5 @@
+pub mod request;
+pub mod response;
+
+pub use request::{ListResponsesQuery, ListResponsesRequest};
+pub use response::{ListResponsesObjectType, ListResponsesResponse};
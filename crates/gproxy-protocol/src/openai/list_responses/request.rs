@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+
+use crate::openai::list_input_items::request::ListOrder;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct ListResponsesQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order: Option<ListOrder>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ListResponsesRequest {
+    #[serde(default)]
+    pub query: ListResponsesQuery,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_list_responses_query() {
+        let query: ListResponsesQuery =
+            serde_urlencoded::from_str("limit=10&order=asc&after=resp_1")
+                .expect("parse list responses query");
+        assert_eq!(query.limit, Some(10));
+        assert_eq!(query.order, Some(ListOrder::Asc));
+        assert_eq!(query.after.as_deref(), Some("resp_1"));
+        assert!(query.before.is_none());
+    }
+}
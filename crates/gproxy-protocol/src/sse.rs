@@ -1,16 +1,81 @@
 use bytes::Bytes;
 
+/// Longest line, and longest event, the parser buffers. Anything longer is dropped and
+/// counted in [`SseParser::dropped`] instead of growing without bound.
+pub const MAX_SSE_EVENT_BYTES: usize = 16 * 1024 * 1024;
+
 #[derive(Debug, Clone, Default)]
 pub struct SseEvent {
     pub event: Option<String>,
     pub data: String,
 }
 
+/// UTF-8 decoding across chunk boundaries: a character split between two chunks is held
+/// back until the rest of it arrives, and invalid bytes become U+FFFD rather than losing
+/// the whole chunk.
+#[derive(Debug, Default)]
+pub struct Utf8Decoder {
+    pending: Vec<u8>,
+}
+
+impl Utf8Decoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, chunk: &[u8]) -> String {
+        self.pending.extend_from_slice(chunk);
+        let mut out = String::new();
+        let mut rest: &[u8] = &self.pending;
+        loop {
+            match std::str::from_utf8(rest) {
+                Ok(text) => {
+                    out.push_str(text);
+                    rest = &[];
+                    break;
+                }
+                Err(err) => {
+                    let (valid, after) = rest.split_at(err.valid_up_to());
+                    out.push_str(&String::from_utf8_lossy(valid));
+                    match err.error_len() {
+                        Some(len) => {
+                            out.push(char::REPLACEMENT_CHARACTER);
+                            rest = &after[len..];
+                        }
+                        // An incomplete character at the end; wait for the next chunk.
+                        None => {
+                            rest = after;
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        let consumed = self.pending.len() - rest.len();
+        self.pending.drain(..consumed);
+        out
+    }
+
+    /// Whatever is left at the end of the stream, lossily.
+    pub fn finish(&mut self) -> String {
+        let out = String::from_utf8_lossy(&self.pending).into_owned();
+        self.pending.clear();
+        out
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct SseParser {
+    utf8: Utf8Decoder,
     buffer: String,
     event: Option<String>,
     data_lines: Vec<String>,
+    data_bytes: usize,
+    /// The rest of an oversized line is being discarded.
+    skip_line: bool,
+    /// The current event outgrew the limit and is discarded at its end.
+    skip_event: bool,
+    dropped: u64,
 }
 
 impl SseParser {
@@ -19,85 +84,103 @@ impl SseParser {
     }
 
     pub fn push_bytes(&mut self, chunk: &Bytes) -> Vec<SseEvent> {
-        match std::str::from_utf8(chunk) {
-            Ok(text) => self.push_str(text),
-            Err(_) => Vec::new(),
-        }
+        let text = self.utf8.push(chunk);
+        self.push_str(&text)
     }
 
     pub fn push_str(&mut self, chunk: &str) -> Vec<SseEvent> {
         self.buffer.push_str(chunk);
         let mut events = Vec::new();
 
-        while let Some(pos) = self.buffer.find('\n') {
-            let mut line = self.buffer[..pos].to_string();
-            self.buffer.drain(..=pos);
-
-            if line.ends_with('\r') {
-                line.pop();
-            }
-
-            if line.is_empty() {
-                self.finish_event(&mut events);
-                continue;
-            }
-
-            if line.starts_with(':') {
-                continue;
-            }
-
-            if let Some(value) = line.strip_prefix("event:") {
-                let value = value.trim_start();
-                self.event = if value.is_empty() {
-                    None
-                } else {
-                    Some(value.to_string())
-                };
-                continue;
-            }
-            if line == "event" {
-                self.event = None;
-                continue;
-            }
-
-            if let Some(value) = line.strip_prefix("data:") {
-                let value = value.trim_start();
-                self.data_lines.push(value.to_string());
-                continue;
-            }
-            if line == "data" {
-                self.data_lines.push(String::new());
+        let buffer = std::mem::take(&mut self.buffer);
+        let mut start = 0;
+        while let Some(offset) = buffer[start..].find('\n') {
+            let end = start + offset;
+            let line = &buffer[start..end];
+            start = end + 1;
+            if std::mem::take(&mut self.skip_line) {
                 continue;
             }
+            self.handle_line(line.strip_suffix('\r').unwrap_or(line), &mut events);
         }
+        self.buffer = buffer[start..].to_string();
 
+        if self.buffer.len() > MAX_SSE_EVENT_BYTES {
+            self.buffer.clear();
+            self.skip_line = true;
+            // The event the line belonged to is incomplete now.
+            self.data_lines.clear();
+            self.data_bytes = 0;
+            self.skip_event = true;
+            self.dropped += 1;
+        }
         events
     }
 
     pub fn finish(&mut self) -> Vec<SseEvent> {
-        let mut events = Vec::new();
-        if !self.buffer.is_empty() {
-            let mut line = std::mem::take(&mut self.buffer);
-            if line.ends_with('\r') {
-                line.pop();
-            }
-            if let Some(value) = line.strip_prefix("event:") {
-                let value = value.trim_start();
-                self.event = if value.is_empty() {
-                    None
-                } else {
-                    Some(value.to_string())
-                };
-            } else if let Some(value) = line.strip_prefix("data:") {
-                let value = value.trim_start();
-                self.data_lines.push(value.to_string());
-            }
+        let tail = self.utf8.finish();
+        let mut events = self.push_str(&tail);
+        let line = std::mem::take(&mut self.buffer);
+        if !std::mem::take(&mut self.skip_line) {
+            self.handle_line(line.strip_suffix('\r').unwrap_or(&line), &mut events);
         }
         self.finish_event(&mut events);
         events
     }
 
+    /// Lines and events dropped so far for exceeding [`MAX_SSE_EVENT_BYTES`].
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    fn handle_line(&mut self, line: &str, events: &mut Vec<SseEvent>) {
+        if line.is_empty() {
+            self.finish_event(events);
+            return;
+        }
+
+        if line.starts_with(':') {
+            return;
+        }
+
+        if let Some(value) = line.strip_prefix("event:") {
+            let value = value.trim_start();
+            self.event = if value.is_empty() {
+                None
+            } else {
+                Some(value.to_string())
+            };
+            return;
+        }
+        if line == "event" {
+            self.event = None;
+            return;
+        }
+
+        let value = match line.strip_prefix("data:") {
+            Some(value) => value.trim_start(),
+            None if line == "data" => "",
+            None => return,
+        };
+        if self.skip_event {
+            return;
+        }
+        if self.data_bytes + value.len() > MAX_SSE_EVENT_BYTES {
+            self.data_lines.clear();
+            self.data_bytes = 0;
+            self.skip_event = true;
+            self.dropped += 1;
+            return;
+        }
+        self.data_bytes += value.len() + 1;
+        self.data_lines.push(value.to_string());
+    }
+
     fn finish_event(&mut self, events: &mut Vec<SseEvent>) {
+        if std::mem::take(&mut self.skip_event) {
+            self.event = None;
+            return;
+        }
         if self.event.is_none() && self.data_lines.is_empty() {
             return;
         }
@@ -107,5 +190,51 @@ impl SseParser {
             data,
         });
         self.data_lines.clear();
+        self.data_bytes = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn characters_split_across_chunks_survive() {
+        let text = "data: {\"text\":\"héllo ✓\"}\n\n".as_bytes();
+        let split = text.iter().position(|b| *b == 0xE2).unwrap() + 1;
+        let mut parser = SseParser::new();
+        let mut events = parser.push_bytes(&Bytes::copy_from_slice(&text[..split]));
+        events.extend(parser.push_bytes(&Bytes::copy_from_slice(&text[split..])));
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].data, "{\"text\":\"héllo ✓\"}");
+    }
+
+    #[test]
+    fn invalid_bytes_do_not_lose_the_chunk() {
+        let mut parser = SseParser::new();
+        let events = parser.push_bytes(&Bytes::from_static(b"data: a\xFFb\n\ndata: c\n\n"));
+        let data: Vec<_> = events.iter().map(|ev| ev.data.as_str()).collect();
+        assert_eq!(data, ["a\u{FFFD}b", "c"]);
+    }
+
+    #[test]
+    fn oversized_lines_are_dropped_and_parsing_resumes() {
+        let mut parser = SseParser::new();
+        let long = "x".repeat(MAX_SSE_EVENT_BYTES + 1);
+        assert!(parser.push_str(&format!("data: {long}")).is_empty());
+        let events = parser.push_str("yyy\n\nevent: ping\ndata: ok\n\n");
+        assert_eq!(parser.dropped(), 1);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event.as_deref(), Some("ping"));
+        assert_eq!(events[0].data, "ok");
+    }
+
+    #[test]
+    fn finish_flushes_an_unterminated_event() {
+        let mut parser = SseParser::new();
+        assert!(parser.push_str("event: done\ndata: 1\ndata: 2").is_empty());
+        let events = parser.finish();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].data, "1\n2");
     }
 }
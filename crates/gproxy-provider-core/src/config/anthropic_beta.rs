@@ -0,0 +1,122 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{Headers, header_get, header_remove, header_set};
+
+use super::header_policy::matches_any;
+
+/// Default and unsupported `anthropic-beta` flags for Claude-protocol upstreams.
+pub const ANTHROPIC_BETA_KEY: &str = "anthropic_beta";
+
+pub const ANTHROPIC_BETA_HEADER: &str = "anthropic-beta";
+
+/// Per-provider `anthropic-beta` negotiation for Claude-protocol upstreams.
+///
+/// The upstream header is the union of what the provider set, what the client sent and
+/// `defaults`, minus anything matching `unsupported` (a trailing `*` matches any suffix).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnthropicBetaPolicy {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub defaults: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unsupported: Vec<String>,
+}
+
+impl AnthropicBetaPolicy {
+    /// The provider's `anthropic_beta` policy; without one the header carries what the
+    /// provider and the client set.
+    pub fn from_config_json(config: &serde_json::Value) -> Self {
+        config
+            .get(ANTHROPIC_BETA_KEY)
+            .and_then(|value| serde_json::from_value(value.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    /// Rejects empty flags and flags containing a comma or whitespace, which would split
+    /// into other flags in the header.
+    pub fn validate_config_json(config: &serde_json::Value) -> Result<(), String> {
+        let Some(policy) = super::section::<Self>(config, ANTHROPIC_BETA_KEY)? else {
+            return Ok(());
+        };
+        for beta in policy.defaults.iter().chain(&policy.unsupported) {
+            if beta.is_empty() || beta.contains(|c: char| c == ',' || c.is_whitespace()) {
+                return Err(format!("`{beta}` is not a beta flag"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Rewrites the upstream `anthropic-beta` header from the provider's, the client's and
+    /// the default values.
+    pub fn apply(&self, client: &Headers, upstream: &mut Headers) {
+        let mut betas = header_betas(upstream);
+        for beta in header_betas(client)
+            .into_iter()
+            .chain(self.defaults.clone())
+        {
+            if !betas.iter().any(|b| b.eq_ignore_ascii_case(&beta)) {
+                betas.push(beta);
+            }
+        }
+        betas.retain(|beta| !matches_any(&self.unsupported, beta));
+        if betas.is_empty() {
+            header_remove(upstream, ANTHROPIC_BETA_HEADER);
+        } else {
+            header_set(upstream, ANTHROPIC_BETA_HEADER, betas.join(","));
+        }
+    }
+}
+
+/// Beta flags carried by the `anthropic-beta` header, in order.
+pub fn header_betas(headers: &Headers) -> Vec<String> {
+    header_get(headers, ANTHROPIC_BETA_HEADER)
+        .map(|value| {
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|beta| !beta.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn beta_header(value: &str) -> Headers {
+        vec![(ANTHROPIC_BETA_HEADER.to_string(), value.to_string())].into()
+    }
+
+    #[test]
+    fn merges_client_and_default_betas_after_provider_ones() {
+        let policy = AnthropicBetaPolicy {
+            defaults: vec!["prompt-caching-2024-07-31".into()],
+            unsupported: Vec::new(),
+        };
+        let mut upstream = beta_header("oauth-2025-04-20");
+        policy.apply(
+            &beta_header("token-efficient-tools-2025-02-19, OAUTH-2025-04-20"),
+            &mut upstream,
+        );
+        assert_eq!(
+            header_betas(&upstream),
+            vec![
+                "oauth-2025-04-20",
+                "token-efficient-tools-2025-02-19",
+                "prompt-caching-2024-07-31"
+            ]
+        );
+    }
+
+    #[test]
+    fn strips_unsupported_betas() {
+        let policy = AnthropicBetaPolicy {
+            defaults: Vec::new(),
+            unsupported: vec!["context-1m-*".into()],
+        };
+        let mut upstream = Headers::new();
+        policy.apply(&beta_header("context-1m-2025-08-07"), &mut upstream);
+        assert!(header_get(&upstream, ANTHROPIC_BETA_HEADER).is_none());
+    }
+}
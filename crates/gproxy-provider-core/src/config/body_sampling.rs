@@ -0,0 +1,99 @@
+use serde::{Deserialize, Serialize};
+
+/// Share of successful requests whose bodies are logged.
+pub const BODY_SAMPLING_KEY: &str = "body_sampling";
+
+/// Share of successful requests whose request and response bodies are logged. Failed
+/// requests always keep their bodies, and usage and other metadata are never sampled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct BodySampling {
+    /// 0 to 100; larger values are read as 100.
+    pub success_percent: u8,
+}
+
+impl Default for BodySampling {
+    fn default() -> Self {
+        Self {
+            success_percent: 100,
+        }
+    }
+}
+
+impl BodySampling {
+    pub fn new(success_percent: u8) -> Self {
+        Self {
+            success_percent: success_percent.min(100),
+        }
+    }
+
+    /// The provider's `body_sampling`, read as at most 100 percent; `None` keeps every
+    /// body unless the key sets a share.
+    pub fn from_config_json(config: &serde_json::Value) -> Option<Self> {
+        let sampling: Self = serde_json::from_value(config.get(BODY_SAMPLING_KEY)?.clone()).ok()?;
+        Some(Self::new(sampling.success_percent))
+    }
+
+    /// Rejects a `success_percent` above 100 rather than reading it as 100.
+    pub fn validate_config_json(config: &serde_json::Value) -> Result<(), String> {
+        match super::section::<Self>(config, BODY_SAMPLING_KEY)? {
+            Some(sampling) if sampling.success_percent > 100 => {
+                Err("`success_percent` must be between 0 and 100".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether the successful request `trace_id` keeps its bodies. The choice depends on
+    /// the trace id alone, so the downstream event and every upstream attempt of a request
+    /// agree, on every instance.
+    pub fn keeps(&self, trace_id: &str) -> bool {
+        match self.success_percent {
+            100.. => true,
+            0 => false,
+            percent => trace_bucket(trace_id) < u64::from(percent),
+        }
+    }
+}
+
+/// FNV-1a of the trace id, folded into 0..100.
+fn trace_bucket(trace_id: &str) -> u64 {
+    let hash = trace_id
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+    hash % 100
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ProviderConfig;
+
+    #[test]
+    fn body_sampling_is_read_from_provider_config() {
+        let value = serde_json::json!({
+            "kind": "openai",
+            "channel_settings": {},
+            "body_sampling": { "success_percent": 250 },
+        });
+        assert!(serde_json::from_value::<ProviderConfig>(value.clone()).is_ok());
+        assert_eq!(
+            BodySampling::from_config_json(&value),
+            Some(BodySampling::new(100))
+        );
+        assert_eq!(BodySampling::from_config_json(&serde_json::json!({})), None);
+    }
+
+    #[test]
+    fn keeps_about_the_configured_share_of_traces() {
+        let sampling = BodySampling::new(10);
+        let kept = (0..10_000)
+            .filter(|i| sampling.keeps(&format!("0190a1b2-{i:04x}")))
+            .count();
+        assert!((800..1200).contains(&kept), "kept {kept}");
+        assert_eq!(sampling.keeps("trace-1"), sampling.keeps("trace-1"));
+        assert!(!BodySampling::new(0).keeps("trace-1"));
+        assert!(BodySampling::default().keeps("trace-1"));
+    }
+}
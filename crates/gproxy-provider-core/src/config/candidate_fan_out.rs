@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+
+/// Whether multi-candidate requests are split into one call per candidate.
+pub const CANDIDATE_FAN_OUT_KEY: &str = "candidate_fan_out";
+
+/// Most calls one request fans out to, whatever the settings say.
+const MAX_CANDIDATES_CAP: u32 = 8;
+
+/// Several candidates from an upstream protocol that answers with one (Claude, Responses):
+/// a chat `n` or Gemini `candidateCount` request is sent that many times and the answers
+/// are merged into one response. Off by default, since every call is billed.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CandidateFanOutPolicy {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Most candidates served per request (default 4, at most 8); larger asks are cut.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_candidates: Option<u32>,
+}
+
+impl CandidateFanOutPolicy {
+    /// The provider's `candidate_fan_out` settings; without them a request makes one call.
+    pub fn from_config_json(config: &serde_json::Value) -> Self {
+        config
+            .get(CANDIDATE_FAN_OUT_KEY)
+            .and_then(|value| serde_json::from_value(value.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    /// Rejects a `max_candidates` outside 1 to 8 rather than clamping it silently.
+    pub fn validate_config_json(config: &serde_json::Value) -> Result<(), String> {
+        let Some(policy) = super::section::<Self>(config, CANDIDATE_FAN_OUT_KEY)? else {
+            return Ok(());
+        };
+        if policy
+            .max_candidates
+            .is_some_and(|max| !(1..=MAX_CANDIDATES_CAP).contains(&max))
+        {
+            return Err(format!(
+                "`max_candidates` must be between 1 and {MAX_CANDIDATES_CAP}"
+            ));
+        }
+        Ok(())
+    }
+
+    /// Calls to make for `requested` candidates; `None` when off or one is enough.
+    pub fn calls(&self, requested: u32) -> Option<u32> {
+        let cap = self
+            .max_candidates
+            .unwrap_or(4)
+            .clamp(1, MAX_CANDIDATES_CAP);
+        let calls = requested.min(cap);
+        (self.enabled && calls > 1).then_some(calls)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ProviderConfig;
+
+    #[test]
+    fn candidate_fan_out_is_read_from_provider_config() {
+        let value = serde_json::json!({
+            "kind": "claude",
+            "channel_settings": {},
+            "candidate_fan_out": { "enabled": true, "max_candidates": 3 },
+        });
+        assert!(serde_json::from_value::<ProviderConfig>(value.clone()).is_ok());
+        let policy = CandidateFanOutPolicy::from_config_json(&value);
+        assert_eq!(policy.calls(2), Some(2));
+        assert_eq!(policy.calls(5), Some(3));
+        assert_eq!(policy.calls(1), None);
+
+        let wide =
+            serde_json::json!({ "candidate_fan_out": { "enabled": true, "max_candidates": 50 } });
+        assert_eq!(
+            CandidateFanOutPolicy::from_config_json(&wide).calls(20),
+            Some(8)
+        );
+        assert_eq!(
+            CandidateFanOutPolicy::from_config_json(&serde_json::json!({})).calls(4),
+            None
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use super::dispatch::model_matches;
+use crate::Op;
+
+/// Rules for requests the provider refuses outright.
+pub const DISALLOW_KEY: &str = "disallow";
+
+/// Requests the provider refuses outright, before a credential is picked. Callers get a
+/// 403 naming the rule.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DisallowRule {
+    pub id: String,
+    /// Model pattern (`gpt-4*`, `*-preview`); absent matches every request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Downstream operations (`generate_content`, `count_tokens`, ...); empty matches all.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ops: Vec<Op>,
+    /// The rule stops applying at this time.
+    #[serde(
+        default,
+        with = "time::serde::rfc3339::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub expires_at: Option<OffsetDateTime>,
+    /// Shown to callers when the rule matches.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl DisallowRule {
+    /// The provider's `disallow` rules in order; without them the provider refuses
+    /// nothing.
+    pub fn list_from_config_json(config: &serde_json::Value) -> Vec<Self> {
+        config
+            .get(DISALLOW_KEY)
+            .and_then(|value| serde_json::from_value(value.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    /// Rejects rules without an id, with an id used twice or with an empty model pattern.
+    pub fn validate_config_json(config: &serde_json::Value) -> Result<(), String> {
+        let Some(rules) = super::section::<Vec<Self>>(config, DISALLOW_KEY)? else {
+            return Ok(());
+        };
+        for (i, rule) in rules.iter().enumerate() {
+            if rule.id.trim().is_empty() {
+                return Err("a rule has an empty `id`".to_string());
+            }
+            if rules[..i].iter().any(|other| other.id == rule.id) {
+                return Err(format!("rule id `{}` is used twice", rule.id));
+            }
+            if rule.model.as_deref().is_some_and(|m| m.trim().is_empty()) {
+                return Err(format!("rule `{}` has an empty `model`", rule.id));
+            }
+        }
+        Ok(())
+    }
+
+    /// The first rule in `rules` refusing `op` on `model` at `now`.
+    pub fn find<'a>(
+        rules: &'a [Self],
+        model: Option<&str>,
+        op: Op,
+        now: OffsetDateTime,
+    ) -> Option<&'a Self> {
+        rules.iter().find(|rule| rule.matches(model, op, now))
+    }
+
+    pub fn is_expired(&self, now: OffsetDateTime) -> bool {
+        self.expires_at.is_some_and(|at| at <= now)
+    }
+
+    /// A model-scoped rule never matches requests that name no model.
+    pub fn matches(&self, model: Option<&str>, op: Op, now: OffsetDateTime) -> bool {
+        if self.is_expired(now) || !(self.ops.is_empty() || self.ops.contains(&op)) {
+            return false;
+        }
+        match (&self.model, model) {
+            (None, _) => true,
+            (Some(pattern), Some(model)) => {
+                model_matches(pattern, model.strip_prefix("models/").unwrap_or(model))
+            }
+            (Some(_), None) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ProviderConfig;
+
+    #[test]
+    fn rules_match_by_model_pattern_operation_and_expiry() {
+        let value = serde_json::json!({
+            "kind": "openai",
+            "channel_settings": {},
+            "disallow": [
+                { "id": "old", "model": "gpt-4*", "expires_at": "2026-01-01T00:00:00Z" },
+                { "id": "previews", "model": "*-preview", "ops": ["generate_content", "stream_generate_content"] },
+                { "id": "no-counting", "ops": ["count_tokens"], "reason": "use the estimate" },
+            ],
+        });
+        assert!(serde_json::from_value::<ProviderConfig>(value.clone()).is_ok());
+        let rules = DisallowRule::list_from_config_json(&value);
+        assert_eq!(rules.len(), 3);
+
+        let before = OffsetDateTime::from_unix_timestamp(1_760_000_000).unwrap();
+        let after = OffsetDateTime::from_unix_timestamp(1_800_000_000).unwrap();
+        let id = |model, op, now| DisallowRule::find(&rules, model, op, now).map(|r| r.id.as_str());
+
+        assert_eq!(id(Some("gpt-4o"), Op::GenerateContent, before), Some("old"));
+        assert_eq!(id(Some("gpt-4o"), Op::GenerateContent, after), None);
+        assert_eq!(
+            id(
+                Some("models/gemini-3-pro-preview"),
+                Op::StreamGenerateContent,
+                after
+            ),
+            Some("previews")
+        );
+        assert_eq!(id(Some("gemini-3-pro-preview"), Op::ModelGet, after), None);
+        assert_eq!(id(None, Op::CountTokens, after), Some("no-counting"));
+        assert_eq!(id(None, Op::ModelList, before), None);
+    }
+}
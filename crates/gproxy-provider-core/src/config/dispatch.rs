@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 use crate::{Op, Proto, TransformContext};
 
@@ -36,6 +37,100 @@ pub enum OperationKind {
 impl OperationKind {
     pub const COUNT: usize = 20;
 
+    pub const ALL: [OperationKind; OperationKind::COUNT] = [
+        OperationKind::ClaudeGenerate,
+        OperationKind::ClaudeGenerateStream,
+        OperationKind::ClaudeCountTokens,
+        OperationKind::ClaudeModelsList,
+        OperationKind::ClaudeModelsGet,
+        OperationKind::GeminiGenerate,
+        OperationKind::GeminiGenerateStream,
+        OperationKind::GeminiCountTokens,
+        OperationKind::GeminiModelsList,
+        OperationKind::GeminiModelsGet,
+        OperationKind::OpenAIChatGenerate,
+        OperationKind::OpenAIChatGenerateStream,
+        OperationKind::OpenAIResponseGenerate,
+        OperationKind::OpenAIResponseGenerateStream,
+        OperationKind::OpenAIInputTokens,
+        OperationKind::OpenAIModelsList,
+        OperationKind::OpenAIModelsGet,
+        OperationKind::OAuthStart,
+        OperationKind::OAuthCallback,
+        OperationKind::Usage,
+    ];
+
+    /// Config key of the operation (`openai_chat_generate`, `gemini_models_get`, ...).
+    pub fn key(self) -> &'static str {
+        match self {
+            OperationKind::ClaudeGenerate => "claude_generate",
+            OperationKind::ClaudeGenerateStream => "claude_generate_stream",
+            OperationKind::ClaudeCountTokens => "claude_count_tokens",
+            OperationKind::ClaudeModelsList => "claude_models_list",
+            OperationKind::ClaudeModelsGet => "claude_models_get",
+            OperationKind::GeminiGenerate => "gemini_generate",
+            OperationKind::GeminiGenerateStream => "gemini_generate_stream",
+            OperationKind::GeminiCountTokens => "gemini_count_tokens",
+            OperationKind::GeminiModelsList => "gemini_models_list",
+            OperationKind::GeminiModelsGet => "gemini_models_get",
+            OperationKind::OpenAIChatGenerate => "openai_chat_generate",
+            OperationKind::OpenAIChatGenerateStream => "openai_chat_generate_stream",
+            OperationKind::OpenAIResponseGenerate => "openai_response_generate",
+            OperationKind::OpenAIResponseGenerateStream => "openai_response_generate_stream",
+            OperationKind::OpenAIInputTokens => "openai_input_tokens",
+            OperationKind::OpenAIModelsList => "openai_models_list",
+            OperationKind::OpenAIModelsGet => "openai_models_get",
+            OperationKind::OAuthStart => "oauth_start",
+            OperationKind::OAuthCallback => "oauth_callback",
+            OperationKind::Usage => "usage",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.key() == key)
+    }
+
+    /// Protocol and operation the kind is called with; `None` for the internal ops.
+    fn proto_op(self) -> Option<(Proto, Op)> {
+        Some(match self {
+            OperationKind::ClaudeGenerate => (Proto::Claude, Op::GenerateContent),
+            OperationKind::ClaudeGenerateStream => (Proto::Claude, Op::StreamGenerateContent),
+            OperationKind::ClaudeCountTokens => (Proto::Claude, Op::CountTokens),
+            OperationKind::ClaudeModelsList => (Proto::Claude, Op::ModelList),
+            OperationKind::ClaudeModelsGet => (Proto::Claude, Op::ModelGet),
+            OperationKind::GeminiGenerate => (Proto::Gemini, Op::GenerateContent),
+            OperationKind::GeminiGenerateStream => (Proto::Gemini, Op::StreamGenerateContent),
+            OperationKind::GeminiCountTokens => (Proto::Gemini, Op::CountTokens),
+            OperationKind::GeminiModelsList => (Proto::Gemini, Op::ModelList),
+            OperationKind::GeminiModelsGet => (Proto::Gemini, Op::ModelGet),
+            OperationKind::OpenAIChatGenerate => (Proto::OpenAIChat, Op::GenerateContent),
+            OperationKind::OpenAIChatGenerateStream => {
+                (Proto::OpenAIChat, Op::StreamGenerateContent)
+            }
+            OperationKind::OpenAIResponseGenerate => (Proto::OpenAIResponse, Op::GenerateContent),
+            OperationKind::OpenAIResponseGenerateStream => {
+                (Proto::OpenAIResponse, Op::StreamGenerateContent)
+            }
+            OperationKind::OpenAIInputTokens => (Proto::OpenAI, Op::CountTokens),
+            OperationKind::OpenAIModelsList => (Proto::OpenAI, Op::ModelList),
+            OperationKind::OpenAIModelsGet => (Proto::OpenAI, Op::ModelGet),
+            OperationKind::OAuthStart | OperationKind::OAuthCallback | OperationKind::Usage => {
+                return None;
+            }
+        })
+    }
+
+    /// The same operation in `target`'s protocol, which a `Transform` rule calls.
+    pub fn in_proto(self, target: Proto) -> Option<Self> {
+        let (src, op) = self.proto_op()?;
+        Self::from_context(&TransformContext {
+            src: target,
+            dst: src,
+            src_op: op,
+            dst_op: op,
+        })
+    }
+
     pub fn from_context(ctx: &TransformContext) -> Option<Self> {
         match ctx.src_op {
             Op::GenerateContent => match ctx.src {
@@ -75,7 +170,21 @@ impl OperationKind {
             | Op::ResponseCancel
             | Op::ResponseListInputItems
             | Op::ResponseCompact
-            | Op::MemoryTraceSummarize => None,
+            | Op::MemoryTraceSummarize
+            | Op::ResponseList
+            | Op::ConversationCreate
+            | Op::ConversationGet
+            | Op::ConversationUpdate
+            | Op::ConversationDelete
+            | Op::ConversationItemList
+            | Op::ConversationItemCreate
+            | Op::ConversationItemGet
+            | Op::ConversationItemDelete
+            | Op::FileList
+            | Op::FileGet
+            | Op::FileDelete
+            | Op::BatchGenerateContent
+            | Op::BatchGet => None,
         }
     }
 }
@@ -91,31 +200,362 @@ pub enum DispatchRule {
     Unsupported,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+/// Dispatch rule overrides that apply only to matching models.
+pub const MODEL_DISPATCH_KEY: &str = "model_dispatch";
+
+/// Dispatch overrides for models matching `model`: an exact id or a prefix ending in `*`
+/// (e.g. `o3*`), compared without Gemini's `models/` prefix.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelDispatchRule {
+    pub model: String,
+    /// Rules by operation key (`openai_chat_generate`, ...); other operations keep the
+    /// provider's rule.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub ops: HashMap<String, DispatchRule>,
+    /// Never serve a stream request with a non-stream upstream call or the reverse.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub no_stream_fallback: bool,
+}
+
+impl ModelDispatchRule {
+    /// The provider's `model_dispatch` rules in order; the first rule matching a model
+    /// applies.
+    pub fn list_from_config_json(config: &serde_json::Value) -> Vec<Self> {
+        config
+            .get(MODEL_DISPATCH_KEY)
+            .and_then(|value| serde_json::from_value(value.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    /// Rejects rules with an empty model pattern or an unknown operation key.
+    pub fn validate_config_json(config: &serde_json::Value) -> Result<(), String> {
+        let Some(rules) = super::section::<Vec<Self>>(config, MODEL_DISPATCH_KEY)? else {
+            return Ok(());
+        };
+        for rule in &rules {
+            if rule.model.trim().is_empty() {
+                return Err("a rule has an empty `model`".to_string());
+            }
+            if let Some(key) = rule
+                .ops
+                .keys()
+                .find(|key| OperationKind::from_key(key).is_none())
+            {
+                return Err(format!("unknown operation `{key}`"));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn matches(&self, model: &str) -> bool {
+        model_matches(&self.model, model.strip_prefix("models/").unwrap_or(model))
+    }
+
+    fn rule(&self, kind: OperationKind) -> Option<DispatchRule> {
+        self.ops.get(kind.key()).copied()
+    }
+}
+
+/// Model id glob: `*` matches any run of characters and `?` exactly one; anything else
+/// must match literally.
+pub(crate) fn model_matches(pattern: &str, model: &str) -> bool {
+    let pattern: Vec<char> = pattern.trim().chars().collect();
+    let model: Vec<char> = model.chars().collect();
+    let (mut p, mut m) = (0, 0);
+    // Pattern and model positions just after the last `*`, to backtrack to.
+    let mut star: Option<(usize, usize)> = None;
+    while m < model.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p + 1, m));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == model[m] => {
+                p += 1;
+                m += 1;
+            }
+            _ => match star {
+                Some((star_p, star_m)) => {
+                    p = star_p;
+                    m = star_m + 1;
+                    star = Some((star_p, star_m + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Replacements for single rules of the built-in dispatch table.
+pub const DISPATCH_KEY: &str = "dispatch";
+
+/// Per-provider replacements for rules of the provider's built-in dispatch table, by
+/// operation key. Marking a generate operation `unsupported` makes requests of that mode
+/// fall back to the other one (stream-to-non-stream or the reverse).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DispatchOverrides {
+    #[serde(default)]
+    pub ops: HashMap<String, DispatchRule>,
+}
+
+impl DispatchOverrides {
+    /// The provider's `dispatch` overrides. [`DispatchTable::with_overrides`] ignores
+    /// them unless they pass [`DispatchOverrides::validate`].
+    pub fn from_config_json(config: &serde_json::Value) -> Self {
+        config
+            .get(DISPATCH_KEY)
+            .and_then(|value| serde_json::from_value(value.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    /// Rejects a `dispatch` section that does not parse. Its rules need the provider's
+    /// table and are checked by [`DispatchOverrides::validate`].
+    pub fn validate_config_json(config: &serde_json::Value) -> Result<(), String> {
+        super::section::<Self>(config, DISPATCH_KEY).map(|_| ())
+    }
+
+    /// Checks the overrides against the provider's built-in table: operations must be
+    /// known, `native` only where the provider is native already, and a `transform` must
+    /// target the same operation in a protocol the provider serves natively once the
+    /// overrides apply. `unsupported` is always allowed.
+    pub fn validate(&self, base: &DispatchTable) -> Result<(), String> {
+        let mut effective = base.ops;
+        for (key, rule) in &self.ops {
+            let kind =
+                OperationKind::from_key(key).ok_or_else(|| format!("unknown operation `{key}`"))?;
+            effective[kind as usize] = *rule;
+        }
+        for (kind, rule) in self.entries() {
+            match rule {
+                DispatchRule::Unsupported => {}
+                DispatchRule::Native => {
+                    if base.rule(kind) != DispatchRule::Native {
+                        return Err(format!("`{}` is not native for this provider", kind.key()));
+                    }
+                }
+                DispatchRule::Transform { target } => {
+                    let served = kind
+                        .in_proto(target)
+                        .filter(|target_kind| *target_kind != kind)
+                        .is_some_and(|target_kind| {
+                            effective[target_kind as usize] == DispatchRule::Native
+                        });
+                    if !served {
+                        return Err(format!(
+                            "`{}` cannot be transformed to a protocol this provider does not serve natively",
+                            kind.key()
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Overrides of known operations, in table order.
+    pub fn entries(&self) -> impl Iterator<Item = (OperationKind, DispatchRule)> + '_ {
+        OperationKind::ALL
+            .into_iter()
+            .filter_map(|kind| Some((kind, *self.ops.get(kind.key())?)))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DispatchTable {
     ops: [DispatchRule; OperationKind::COUNT],
+    /// Checked in order before `ops`; the first rule matching the model wins.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    models: Vec<ModelDispatchRule>,
 }
 
 impl DispatchTable {
     pub const fn new(ops: [DispatchRule; OperationKind::COUNT]) -> Self {
-        Self { ops }
+        Self {
+            ops,
+            models: Vec::new(),
+        }
+    }
+
+    /// Puts `rules` ahead of the table's own model rules.
+    pub fn with_model_rules(mut self, rules: Vec<ModelDispatchRule>) -> Self {
+        if !rules.is_empty() {
+            self.models.splice(0..0, rules);
+        }
+        self
     }
 
     pub fn rule(&self, kind: OperationKind) -> DispatchRule {
         self.ops[kind as usize]
     }
     pub fn rule_for_context(&self, ctx: &TransformContext) -> DispatchRule {
-        match OperationKind::from_context(ctx) {
-            Some(kind) => self.rule(kind),
-            None => DispatchRule::Unsupported,
+        self.rule_for_model(ctx, None)
+    }
+
+    /// First model rule matching `model`, if any.
+    pub fn model_rule(&self, model: Option<&str>) -> Option<&ModelDispatchRule> {
+        let model = model?;
+        self.models.iter().find(|rule| rule.matches(model))
+    }
+
+    /// Like [`Self::rule_for_context`], with `model`'s rule taking precedence.
+    pub fn rule_for_model(&self, ctx: &TransformContext, model: Option<&str>) -> DispatchRule {
+        let Some(kind) = OperationKind::from_context(ctx) else {
+            return DispatchRule::Unsupported;
+        };
+        self.model_rule(model)
+            .and_then(|rule| rule.rule(kind))
+            .unwrap_or_else(|| self.rule(kind))
+    }
+
+    /// Replaces the rules of the operations in `overrides`. Overrides that do not pass
+    /// [`DispatchOverrides::validate`] against this table are ignored as a whole.
+    pub fn with_overrides(mut self, overrides: &DispatchOverrides) -> Self {
+        if overrides.ops.is_empty() || overrides.validate(&self).is_err() {
+            return self;
+        }
+        for (kind, rule) in overrides.entries() {
+            self.ops[kind as usize] = rule;
         }
+        self
+    }
+
+    /// Rules an override of `kind` may use when no other operation is overridden.
+    pub fn override_choices(&self, kind: OperationKind) -> Vec<DispatchRule> {
+        let targets = [
+            Proto::Claude,
+            Proto::Gemini,
+            Proto::OpenAI,
+            Proto::OpenAIChat,
+            Proto::OpenAIResponse,
+        ];
+        [DispatchRule::Native, DispatchRule::Unsupported]
+            .into_iter()
+            .chain(
+                targets
+                    .into_iter()
+                    .map(|target| DispatchRule::Transform { target }),
+            )
+            .filter(|rule| {
+                DispatchOverrides {
+                    ops: HashMap::from([(kind.key().to_string(), *rule)]),
+                }
+                .validate(self)
+                .is_ok()
+            })
+            .collect()
+    }
+
+    /// Whether a stream request may be served by a non-stream upstream call and the reverse.
+    pub fn allows_stream_fallback(&self, model: Option<&str>) -> bool {
+        !self
+            .model_rule(model)
+            .is_some_and(|rule| rule.no_stream_fallback)
     }
 }
 
 impl Default for DispatchTable {
     fn default() -> Self {
-        Self {
-            ops: [DispatchRule::Unsupported; OperationKind::COUNT],
+        Self::new([DispatchRule::Unsupported; OperationKind::COUNT])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn model_rules_override_operations_and_stream_fallback() {
+        let table = DispatchTable::new([DispatchRule::Native; OperationKind::COUNT])
+            .with_model_rules(ModelDispatchRule::list_from_config_json(&serde_json::json!({
+                "model_dispatch": [{
+                    "model": "o3*",
+                    "ops": { "openai_chat_generate": { "transform": { "target": "openai_response" } } },
+                    "no_stream_fallback": true,
+                }],
+            })));
+        let ctx = TransformContext {
+            src: Proto::OpenAIChat,
+            dst: Proto::OpenAIChat,
+            src_op: Op::GenerateContent,
+            dst_op: Op::GenerateContent,
+        };
+        assert_eq!(
+            table.rule_for_model(&ctx, Some("o3-mini")),
+            DispatchRule::Transform {
+                target: Proto::OpenAIResponse
+            }
+        );
+        assert_eq!(
+            table.rule_for_model(&ctx, Some("gpt-4o")),
+            DispatchRule::Native
+        );
+        assert_eq!(table.rule_for_context(&ctx), DispatchRule::Native);
+        assert!(!table.allows_stream_fallback(Some("o3")));
+        assert!(table.allows_stream_fallback(Some("gpt-4o")));
+        assert_eq!(OperationKind::from_key("usage"), Some(OperationKind::Usage));
+    }
+
+    #[test]
+    fn overrides_are_checked_against_the_provider_table() {
+        let mut ops = [DispatchRule::Unsupported; OperationKind::COUNT];
+        for kind in [
+            OperationKind::ClaudeGenerate,
+            OperationKind::ClaudeGenerateStream,
+            OperationKind::ClaudeCountTokens,
+        ] {
+            ops[kind as usize] = DispatchRule::Native;
+        }
+        let base = DispatchTable::new(ops);
+        let overrides = |value: serde_json::Value| {
+            DispatchOverrides::from_config_json(&serde_json::json!({ "dispatch": value }))
+        };
+
+        let stream_only = overrides(serde_json::json!({
+            "ops": {
+                "claude_generate": "unsupported",
+                "openai_input_tokens": { "transform": { "target": "claude" } },
+            },
+        }));
+        assert_eq!(stream_only.validate(&base), Ok(()));
+        let table = base.clone().with_overrides(&stream_only);
+        assert_eq!(
+            table.rule(OperationKind::ClaudeGenerate),
+            DispatchRule::Unsupported
+        );
+        assert_eq!(
+            table.rule(OperationKind::OpenAIInputTokens),
+            DispatchRule::Transform {
+                target: Proto::Claude
+            }
+        );
+        assert_eq!(
+            base.override_choices(OperationKind::GeminiCountTokens),
+            [
+                DispatchRule::Unsupported,
+                DispatchRule::Transform {
+                    target: Proto::Claude
+                }
+            ]
+        );
+
+        for invalid in [
+            serde_json::json!({ "ops": { "claude_generate_fast": "native" } }),
+            serde_json::json!({ "ops": { "gemini_generate": "native" } }),
+            serde_json::json!({ "ops": { "gemini_generate": { "transform": { "target": "openai_chat" } } } }),
+            serde_json::json!({ "ops": {
+                "claude_count_tokens": "unsupported",
+                "gemini_count_tokens": { "transform": { "target": "claude" } },
+            } }),
+        ] {
+            let invalid = overrides(invalid);
+            assert!(invalid.validate(&base).is_err(), "{invalid:?}");
+            assert_eq!(
+                base.clone()
+                    .with_overrides(&invalid)
+                    .rule(OperationKind::GeminiGenerate),
+                DispatchRule::Unsupported
+            );
         }
     }
 }
@@ -0,0 +1,191 @@
+use std::net::{IpAddr, SocketAddr};
+
+use serde::{Deserialize, Serialize};
+
+/// Address family, bind address and outbound proxies of upstream connections.
+pub const EGRESS_KEY: &str = "egress";
+
+const PROXY_SCHEMES: &[&str] = &["http", "https", "socks4", "socks4a", "socks5", "socks5h"];
+
+/// Which address family upstream connections use.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IpFamily {
+    /// Resolver order, with happy-eyeballs fallback between families.
+    #[default]
+    Auto,
+    /// Try IPv4 addresses first; IPv6 stays as the fallback.
+    Ipv4,
+    /// Try IPv6 addresses first; IPv4 stays as the fallback.
+    Ipv6,
+    Ipv4Only,
+    Ipv6Only,
+}
+
+impl IpFamily {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            IpFamily::Auto => "auto",
+            IpFamily::Ipv4 => "ipv4",
+            IpFamily::Ipv6 => "ipv6",
+            IpFamily::Ipv4Only => "ipv4_only",
+            IpFamily::Ipv6Only => "ipv6_only",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "auto" => Some(IpFamily::Auto),
+            "ipv4" => Some(IpFamily::Ipv4),
+            "ipv6" => Some(IpFamily::Ipv6),
+            "ipv4_only" => Some(IpFamily::Ipv4Only),
+            "ipv6_only" => Some(IpFamily::Ipv6Only),
+            _ => None,
+        }
+    }
+
+    /// Reorders (or filters) resolved addresses; the connector races families in list order.
+    pub fn arrange(self, addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+        if self == IpFamily::Auto {
+            return addrs;
+        }
+        let (v4, v6): (Vec<_>, Vec<_>) = addrs.into_iter().partition(SocketAddr::is_ipv4);
+        match self {
+            IpFamily::Auto | IpFamily::Ipv4 => v4.into_iter().chain(v6).collect(),
+            IpFamily::Ipv6 => v6.into_iter().chain(v4).collect(),
+            IpFamily::Ipv4Only => v4,
+            IpFamily::Ipv6Only => v6,
+        }
+    }
+}
+
+/// How requests spread over a provider's proxy pool.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProxyRotation {
+    /// Each credential sticks to one proxy while that proxy is healthy.
+    #[default]
+    Credential,
+    /// Every request takes the next proxy.
+    Request,
+}
+
+/// Where upstream connections leave from. Provider settings win over the global ones
+/// field by field.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct EgressPolicy {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ip_family: Option<IpFamily>,
+    /// Source address to bind before connecting.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub local_address: Option<IpAddr>,
+    /// Network interface to bind (Linux, macOS, Android, illumos, Solaris).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interface: Option<String>,
+    /// Outbound proxies used instead of the global proxy; proxies that fail to connect are
+    /// skipped for a while.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub proxies: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy_rotation: Option<ProxyRotation>,
+}
+
+impl EgressPolicy {
+    /// The provider's own `egress` settings, before the global ones fill the gaps through
+    /// [`EgressPolicy::or`].
+    pub fn from_config_json(config: &serde_json::Value) -> Self {
+        config
+            .get(EGRESS_KEY)
+            .and_then(|value| serde_json::from_value(value.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    /// Rejects an empty `interface` and proxies that are not `scheme://host` URLs of a
+    /// supported proxy scheme.
+    pub fn validate_config_json(config: &serde_json::Value) -> Result<(), String> {
+        let Some(policy) = super::section::<Self>(config, EGRESS_KEY)? else {
+            return Ok(());
+        };
+        if policy
+            .interface
+            .as_deref()
+            .is_some_and(|name| name.trim().is_empty())
+        {
+            return Err("`interface` is empty".to_string());
+        }
+        for proxy in &policy.proxies {
+            let valid = proxy.split_once("://").is_some_and(|(scheme, rest)| {
+                PROXY_SCHEMES.contains(&scheme.to_ascii_lowercase().as_str())
+                    && !rest.trim_end_matches('/').is_empty()
+            });
+            if !valid {
+                return Err(format!("`{proxy}` is not a proxy URL"));
+            }
+        }
+        Ok(())
+    }
+
+    /// `self` wins wherever it sets a value.
+    pub fn or(self, fallback: EgressPolicy) -> Self {
+        Self {
+            ip_family: self.ip_family.or(fallback.ip_family),
+            local_address: self.local_address.or(fallback.local_address),
+            interface: self.interface.or(fallback.interface),
+            proxies: if self.proxies.is_empty() {
+                fallback.proxies
+            } else {
+                self.proxies
+            },
+            proxy_rotation: self.proxy_rotation.or(fallback.proxy_rotation),
+        }
+    }
+
+    /// The settings a connection is built with; the proxy pool is resolved per request.
+    pub fn without_proxy_pool(self) -> Self {
+        Self {
+            proxies: Vec::new(),
+            proxy_rotation: None,
+            ..self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ProviderConfig;
+
+    #[test]
+    fn provider_egress_layers_over_global_and_orders_addresses() {
+        let value = serde_json::json!({
+            "kind": "openai",
+            "channel_settings": {},
+            "egress": {
+                "ip_family": "ipv4_only",
+                "proxies": ["http://10.0.0.1:3128", "http://10.0.0.2:3128"],
+                "proxy_rotation": "request",
+            },
+        });
+        assert!(serde_json::from_value::<ProviderConfig>(value.clone()).is_ok());
+        let global = EgressPolicy {
+            ip_family: Some(IpFamily::Ipv6),
+            local_address: Some("192.0.2.10".parse().unwrap()),
+            interface: None,
+            ..EgressPolicy::default()
+        };
+        let egress = EgressPolicy::from_config_json(&value).or(global);
+        assert_eq!(egress.ip_family, Some(IpFamily::Ipv4Only));
+        assert_eq!(egress.local_address, Some("192.0.2.10".parse().unwrap()));
+        assert_eq!(egress.proxies.len(), 2);
+        assert_eq!(egress.proxy_rotation, Some(ProxyRotation::Request));
+        assert!(egress.without_proxy_pool().proxies.is_empty());
+
+        let addrs: Vec<SocketAddr> = vec![
+            "[2001:db8::1]:443".parse().unwrap(),
+            "192.0.2.1:443".parse().unwrap(),
+        ];
+        assert_eq!(IpFamily::Ipv4Only.arrange(addrs.clone()), vec![addrs[1]]);
+        assert_eq!(IpFamily::Ipv6.arrange(addrs.clone()), addrs);
+        assert_eq!(IpFamily::Ipv4.arrange(addrs.clone())[0], addrs[1]);
+    }
+}
@@ -0,0 +1,292 @@
+use std::time::Duration;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+/// Rules mapping upstream error responses to retry, cooldown or pass-through.
+pub const FAILURE_RULES_KEY: &str = "failure_rules";
+
+/// Where `custom` providers kept their rules before `failure_rules` existed
+/// (`channel_settings.error_rules`). Still read, after `failure_rules`.
+const CUSTOM_ERROR_RULES_KEY: &str = "error_rules";
+
+/// What the engine does with an upstream error response matched by a rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureAction {
+    /// Try again without cooling the credential down.
+    #[serde(alias = "ignore")]
+    Retry,
+    /// Cool the credential down for `cooldown_secs` and try another.
+    #[serde(alias = "upstream_error")]
+    Cooldown,
+    /// Cool the credential down as rate limited (for the model, on generate requests)
+    /// and try another.
+    RateLimit,
+    /// Cool the credential down for the requested model only and try another.
+    ModelUnavailable,
+    /// Mark the credential's auth invalid, as a 401 does, and try another.
+    #[serde(alias = "auth_invalid")]
+    DisableCredential,
+    /// Return the upstream error to the client: no retry, no cooldown, no auth recovery.
+    PassThrough,
+}
+
+/// One rule; every condition given must hold. Rules only see HTTP error responses,
+/// transport failures keep the built-in classification.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FailureRule {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    /// Regex searched in the response body.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_regex: Option<String>,
+    /// Substring of the response body, compared ignoring ASCII case.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_contains: Option<String>,
+    /// Error code of a JSON error body (`error.code`, `error.type` or `error.status`),
+    /// compared ignoring case.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<String>,
+    pub action: FailureAction,
+    /// Cooldown of `cooldown` and `model_unavailable` (default 10s, or `Retry-After` /
+    /// 30s on a 429), `rate_limit` (default `Retry-After` / 30s) or `disable_credential`
+    /// (default: until the credential is re-enabled).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cooldown_secs: Option<u64>,
+}
+
+/// The rule that matched a failure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FailureMatch {
+    pub action: FailureAction,
+    pub cooldown: Option<Duration>,
+}
+
+#[derive(Debug, Clone)]
+struct CompiledRule {
+    status: Option<u16>,
+    body: Option<Regex>,
+    /// `body_contains`, lowercased.
+    body_contains: Option<String>,
+    error_code: Option<String>,
+    matched: FailureMatch,
+}
+
+/// Compiled `failure_rules` of a provider: checked in order before the built-in failure
+/// classification and the provider's own cooldown decision; the first match wins. A
+/// `custom` provider's `channel_settings.error_rules` follow its `failure_rules`.
+#[derive(Debug, Clone, Default)]
+pub struct FailurePolicy {
+    rules: Vec<CompiledRule>,
+}
+
+impl FailurePolicy {
+    /// Reads the rules from a provider config JSON. A missing or malformed list adds no
+    /// rules, and rules whose regex does not compile are skipped.
+    pub fn from_config_json(config: &JsonValue) -> Self {
+        let rules: Vec<FailureRule> = rule_lists(config)
+            .filter_map(|value| serde_json::from_value::<Vec<FailureRule>>(value.clone()).ok())
+            .flatten()
+            .collect();
+        Self::compile(&rules).unwrap_or_else(|(policy, _)| policy)
+    }
+
+    /// Checks the `failure_rules` section of a provider config JSON, and a `custom`
+    /// provider's `error_rules`, if any.
+    pub fn validate_config_json(config: &JsonValue) -> Result<(), String> {
+        for value in rule_lists(config) {
+            let rules: Vec<FailureRule> =
+                serde_json::from_value(value.clone()).map_err(|err| err.to_string())?;
+            Self::compile(&rules).map_err(|(_, err)| err)?;
+        }
+        Ok(())
+    }
+
+    /// Compiles `rules`; on a bad regex, returns the policy without that rule and the error.
+    fn compile(rules: &[FailureRule]) -> Result<Self, (Self, String)> {
+        let mut error = None;
+        let mut compiled = Vec::with_capacity(rules.len());
+        for rule in rules {
+            let body = match rule.body_regex.as_deref().map(Regex::new).transpose() {
+                Ok(body) => body,
+                Err(err) => {
+                    error.get_or_insert_with(|| {
+                        format!(
+                            "body_regex `{}`: {err}",
+                            rule.body_regex.as_deref().unwrap_or("")
+                        )
+                    });
+                    continue;
+                }
+            };
+            compiled.push(CompiledRule {
+                status: rule.status,
+                body,
+                body_contains: rule.body_contains.as_deref().map(str::to_ascii_lowercase),
+                error_code: rule.error_code.clone(),
+                matched: FailureMatch {
+                    action: rule.action,
+                    cooldown: rule.cooldown_secs.map(Duration::from_secs),
+                },
+            });
+        }
+        let policy = Self { rules: compiled };
+        match error {
+            Some(err) => Err((policy, err)),
+            None => Ok(policy),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// The first rule matching an error response.
+    pub fn matching(&self, status: u16, body: &[u8]) -> Option<FailureMatch> {
+        if self.rules.is_empty() {
+            return None;
+        }
+        let text = String::from_utf8_lossy(body);
+        let mut lowered: Option<String> = None;
+        let mut codes: Option<Vec<String>> = None;
+        self.rules
+            .iter()
+            .find(|rule| {
+                if rule.status.is_some_and(|expected| expected != status) {
+                    return false;
+                }
+                if let Some(regex) = &rule.body
+                    && !regex.is_match(&text)
+                {
+                    return false;
+                }
+                if let Some(needle) = &rule.body_contains
+                    && !lowered
+                        .get_or_insert_with(|| text.to_ascii_lowercase())
+                        .contains(needle.as_str())
+                {
+                    return false;
+                }
+                match &rule.error_code {
+                    Some(expected) => codes
+                        .get_or_insert_with(|| error_codes(body))
+                        .iter()
+                        .any(|code| code.eq_ignore_ascii_case(expected)),
+                    None => true,
+                }
+            })
+            .map(|rule| rule.matched)
+    }
+}
+
+/// The rule lists of a provider config JSON, in the order they are tried.
+fn rule_lists(config: &JsonValue) -> impl Iterator<Item = &JsonValue> {
+    let custom = config.get("kind").and_then(JsonValue::as_str) == Some("custom");
+    let legacy = custom
+        .then(|| config.get("channel_settings")?.get(CUSTOM_ERROR_RULES_KEY))
+        .flatten();
+    config.get(FAILURE_RULES_KEY).into_iter().chain(legacy)
+}
+
+/// Error codes of a JSON error body as sent by OpenAI (`error.code`, `error.type`),
+/// Claude (`error.type`) and Gemini (`error.status`, `error.code`), plus top-level `code`
+/// and `type`.
+fn error_codes(body: &[u8]) -> Vec<String> {
+    let Ok(value) = serde_json::from_slice::<JsonValue>(body) else {
+        return Vec::new();
+    };
+    let error = value.get("error");
+    [
+        error.and_then(|e| e.get("code")),
+        error.and_then(|e| e.get("type")),
+        error.and_then(|e| e.get("status")),
+        value.get("code"),
+        value.get("type"),
+    ]
+    .into_iter()
+    .flatten()
+    .filter_map(|code| match code {
+        JsonValue::String(code) => Some(code.clone()),
+        JsonValue::Number(code) => Some(code.to_string()),
+        _ => None,
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rules_match_status_body_and_error_code_in_order() {
+        let config = serde_json::json!({
+            "failure_rules": [
+                { "status": 400, "error_code": "insufficient_quota", "action": "cooldown", "cooldown_secs": 600 },
+                { "body_regex": "(?i)key .* revoked", "action": "disable_credential" },
+                { "status": 529, "action": "retry" },
+                { "status": 503, "action": "pass_through" },
+            ],
+        });
+        assert!(FailurePolicy::validate_config_json(&config).is_ok());
+        let policy = FailurePolicy::from_config_json(&config);
+
+        let quota = br#"{"error":{"type":"invalid_request_error","code":"insufficient_quota"}}"#;
+        assert_eq!(
+            policy.matching(400, quota),
+            Some(FailureMatch {
+                action: FailureAction::Cooldown,
+                cooldown: Some(Duration::from_secs(600)),
+            })
+        );
+        assert_eq!(
+            policy
+                .matching(401, b"API Key abc was revoked")
+                .map(|m| m.action),
+            Some(FailureAction::DisableCredential)
+        );
+        assert_eq!(
+            policy.matching(529, b"").map(|m| m.action),
+            Some(FailureAction::Retry)
+        );
+        assert_eq!(policy.matching(400, b"{}"), None);
+        assert_eq!(policy.matching(500, b""), None);
+
+        let gemini = br#"{"error":{"code":429,"status":"RESOURCE_EXHAUSTED"}}"#;
+        let by_code = FailurePolicy::from_config_json(&serde_json::json!({
+            "failure_rules": [{ "error_code": "resource_exhausted", "action": "pass_through" }],
+        }));
+        assert_eq!(
+            by_code.matching(429, gemini).map(|m| m.action),
+            Some(FailureAction::PassThrough)
+        );
+
+        let custom = FailurePolicy::from_config_json(&serde_json::json!({
+            "kind": "custom",
+            "channel_settings": { "error_rules": [
+                { "status": 400, "body_contains": "QUOTA", "action": "rate_limit", "cooldown_secs": 600 },
+                { "status": 503, "action": "ignore" },
+            ] },
+            "failure_rules": [{ "status": 503, "action": "model_unavailable" }],
+        }));
+        assert_eq!(
+            custom.matching(400, b"daily quota hit"),
+            Some(FailureMatch {
+                action: FailureAction::RateLimit,
+                cooldown: Some(Duration::from_secs(600)),
+            })
+        );
+        assert_eq!(
+            custom.matching(503, b"").map(|m| m.action),
+            Some(FailureAction::ModelUnavailable)
+        );
+        assert_eq!(custom.matching(400, b"bad"), None);
+
+        let bad =
+            serde_json::json!({ "failure_rules": [{ "body_regex": "(", "action": "retry" }] });
+        assert!(FailurePolicy::validate_config_json(&bad).is_err());
+        assert!(FailurePolicy::from_config_json(&bad).is_empty());
+    }
+}
@@ -0,0 +1,191 @@
+use serde::{Deserialize, Serialize};
+
+use crate::Headers;
+
+/// Headers forwarded upstream, exposed to callers or stripped.
+pub const HEADER_POLICY_KEY: &str = "header_policy";
+
+/// Headers that describe the downstream connection or body and never make sense upstream.
+const NEVER_FORWARDED: &[&str] = &[
+    "host",
+    "connection",
+    "content-length",
+    "content-type",
+    "content-encoding",
+    "transfer-encoding",
+    "accept-encoding",
+    "authorization",
+    "x-api-key",
+    "x-goog-api-key",
+];
+
+/// Per-provider header pass-through rules.
+///
+/// Names match case-insensitively; a trailing `*` matches any suffix
+/// (e.g. `x-ratelimit-*`).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeaderPolicy {
+    /// Downstream request headers copied onto the upstream request when the provider
+    /// did not already set them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub forward_request: Vec<String>,
+    /// Upstream response headers returned to the caller. Empty keeps all of them;
+    /// `content-type` and `x-gproxy-*` are always kept.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub expose_response: Vec<String>,
+    /// Headers removed from both the upstream request and the downstream response.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub strip: Vec<String>,
+}
+
+impl HeaderPolicy {
+    /// The provider's `header_policy`; without one no client header is forwarded and
+    /// every response header is returned.
+    pub fn from_config_json(config: &serde_json::Value) -> Self {
+        config
+            .get(HEADER_POLICY_KEY)
+            .and_then(|value| serde_json::from_value(value.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    /// Rejects a `header_policy` with a pattern that is not a header name, optionally
+    /// ending in `*`.
+    pub fn validate_config_json(config: &serde_json::Value) -> Result<(), String> {
+        let Some(policy) = super::section::<Self>(config, HEADER_POLICY_KEY)? else {
+            return Ok(());
+        };
+        let patterns = policy
+            .forward_request
+            .iter()
+            .chain(&policy.expose_response)
+            .chain(&policy.strip);
+        for pattern in patterns {
+            let trimmed = pattern.trim();
+            let name = trimmed.strip_suffix('*').unwrap_or(trimmed);
+            if trimmed.is_empty() || !name.bytes().all(is_token_byte) {
+                return Err(format!("`{pattern}` is not a header name pattern"));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn apply_request(&self, downstream: &Headers, upstream: &mut Headers) {
+        for (name, value) in downstream {
+            if !matches_any(&self.forward_request, name)
+                || matches_any(&self.strip, name)
+                || NEVER_FORWARDED.iter().any(|n| n.eq_ignore_ascii_case(name))
+                || upstream.contains(name)
+            {
+                continue;
+            }
+            upstream.append(name.to_ascii_lowercase(), value.clone());
+        }
+        if !self.strip.is_empty() {
+            upstream.retain(|name, _| !matches_any(&self.strip, name));
+        }
+    }
+
+    pub fn apply_response(&self, headers: &mut Headers) {
+        headers.retain(|name, _| {
+            if matches_any(&self.strip, name) {
+                return false;
+            }
+            self.expose_response.is_empty()
+                || name.eq_ignore_ascii_case("content-type")
+                || name.to_ascii_lowercase().starts_with("x-gproxy-")
+                || matches_any(&self.expose_response, name)
+        });
+    }
+}
+
+/// `tchar` of RFC 9110, without `*`, which only ends a pattern.
+fn is_token_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || b"!#$%&'+-.^_`|~".contains(&byte)
+}
+
+pub(crate) fn matches_any(patterns: &[String], name: &str) -> bool {
+    patterns.iter().any(|pattern| {
+        let pattern = pattern.trim();
+        match pattern.strip_suffix('*') {
+            Some(prefix) => name
+                .get(..prefix.len())
+                .is_some_and(|head| head.eq_ignore_ascii_case(prefix)),
+            None => name.eq_ignore_ascii_case(pattern),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ProviderConfig;
+
+    fn policy() -> HeaderPolicy {
+        HeaderPolicy {
+            forward_request: vec!["anthropic-beta".into(), "openai-*".into(), "host".into()],
+            expose_response: vec!["x-ratelimit-*".into()],
+            strip: vec!["openai-project".into()],
+        }
+    }
+
+    #[test]
+    fn forwards_listed_request_headers_without_overriding_provider() {
+        let downstream = vec![
+            ("Anthropic-Beta".to_string(), "tools-2024".to_string()),
+            ("OpenAI-Organization".to_string(), "org-1".to_string()),
+            ("OpenAI-Project".to_string(), "p".to_string()),
+            ("Host".to_string(), "gproxy.local".to_string()),
+            ("x-other".to_string(), "1".to_string()),
+        ]
+        .into();
+        let mut upstream: Headers =
+            vec![("openai-organization".to_string(), "org-0".to_string())].into();
+        policy().apply_request(&downstream, &mut upstream);
+        assert_eq!(
+            upstream,
+            vec![
+                ("openai-organization".to_string(), "org-0".to_string()),
+                ("anthropic-beta".to_string(), "tools-2024".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn response_allowlist_keeps_essentials() {
+        let mut headers: Headers = vec![
+            ("content-type".to_string(), "application/json".to_string()),
+            (
+                "x-ratelimit-remaining-requests".to_string(),
+                "9".to_string(),
+            ),
+            ("openai-project".to_string(), "p".to_string()),
+            ("cf-ray".to_string(), "abc".to_string()),
+            ("x-gproxy-error-code".to_string(), "e".to_string()),
+        ]
+        .into();
+        policy().apply_response(&mut headers);
+        let names: Vec<_> = headers.iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "content-type",
+                "x-ratelimit-remaining-requests",
+                "x-gproxy-error-code"
+            ]
+        );
+    }
+
+    #[test]
+    fn policy_lives_beside_provider_config() {
+        let value = serde_json::json!({
+            "kind": "openai",
+            "channel_settings": {},
+            "header_policy": { "strip": ["cf-ray"] },
+        });
+        assert!(serde_json::from_value::<ProviderConfig>(value.clone()).is_ok());
+        assert_eq!(
+            HeaderPolicy::from_config_json(&value).strip,
+            vec!["cf-ray".to_string()]
+        );
+    }
+}
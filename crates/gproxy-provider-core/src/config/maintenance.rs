@@ -0,0 +1,118 @@
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+/// Periods during which the provider takes no traffic.
+pub const MAINTENANCE_KEY: &str = "maintenance";
+
+/// A period during which the provider takes no traffic. Requests go to
+/// `fallback_provider` when set, otherwise they are rejected with a 503.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaintenanceWindow {
+    #[serde(with = "time::serde::rfc3339")]
+    pub start: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub end: OffsetDateTime,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fallback_provider: Option<String>,
+    /// Shown to callers when the request is rejected.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+impl MaintenanceWindow {
+    pub fn contains(&self, at: OffsetDateTime) -> bool {
+        self.start <= at && at < self.end
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct MaintenanceSchedule {
+    pub windows: Vec<MaintenanceWindow>,
+}
+
+impl MaintenanceSchedule {
+    /// The provider's `maintenance` windows; without them the provider always takes
+    /// traffic.
+    pub fn from_config_json(config: &serde_json::Value) -> Self {
+        config
+            .get(MAINTENANCE_KEY)
+            .and_then(|value| serde_json::from_value(value.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    /// Rejects windows that end before they start or name an empty fallback.
+    pub fn validate_config_json(config: &serde_json::Value) -> Result<(), String> {
+        let Some(schedule) = super::section::<Self>(config, MAINTENANCE_KEY)? else {
+            return Ok(());
+        };
+        for window in &schedule.windows {
+            if window.end <= window.start {
+                return Err(format!(
+                    "window starting {} ends before it starts",
+                    window.start
+                ));
+            }
+            if window
+                .fallback_provider
+                .as_deref()
+                .is_some_and(|provider| provider.trim().is_empty())
+            {
+                return Err("`fallback_provider` is empty".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// The window covering `at`; overlapping windows resolve to the one ending last.
+    pub fn active_at(&self, at: OffsetDateTime) -> Option<&MaintenanceWindow> {
+        self.windows
+            .iter()
+            .filter(|window| window.contains(at))
+            .max_by_key(|window| window.end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ProviderConfig;
+
+    #[test]
+    fn active_window_is_read_from_provider_config() {
+        let value = serde_json::json!({
+            "kind": "openai",
+            "channel_settings": {},
+            "maintenance": [
+                { "start": "2026-01-01T00:00:00Z", "end": "2026-01-01T02:00:00Z" },
+                {
+                    "start": "2026-01-01T01:00:00Z",
+                    "end": "2026-01-01T03:00:00Z",
+                    "fallback_provider": "backup"
+                }
+            ],
+        });
+        assert!(serde_json::from_value::<ProviderConfig>(value.clone()).is_ok());
+        let schedule = MaintenanceSchedule::from_config_json(&value);
+        let at = |hour: u8| {
+            OffsetDateTime::from_unix_timestamp(1_767_225_600 + i64::from(hour) * 3600).unwrap()
+        };
+
+        assert!(
+            schedule
+                .active_at(at(0))
+                .unwrap()
+                .fallback_provider
+                .is_none()
+        );
+        assert_eq!(
+            schedule
+                .active_at(at(1))
+                .unwrap()
+                .fallback_provider
+                .as_deref(),
+            Some("backup")
+        );
+        assert!(schedule.active_at(at(3)).is_none());
+    }
+}
@@ -1,10 +1,74 @@
+//! Provider configuration: the typed [`ProviderConfig`] and the policy sections beside it.
+//!
+//! A provider's config JSON holds `kind` and `channel_settings` (read into
+//! [`ProviderConfig`]) plus optional policy sections at the top level, each under its own
+//! `*_KEY` constant, so a policy never collides with a channel setting. Each section's
+//! type reads it with `from_config_json` (`list_from_config_json` for rule lists), which
+//! falls back to the default when the section is absent or does not parse, and checks it
+//! with `validate_config_json` before a config is stored. [`ProviderPolicies`] reads them
+//! all at once.
+
+mod anthropic_beta;
+mod body_sampling;
+mod candidate_fan_out;
+mod disallow;
 mod dispatch;
+mod egress;
+mod failure_rules;
+mod header_policy;
+mod maintenance;
 mod model_table;
+mod parsing;
+mod policies;
+mod post_process;
 mod provider_config;
+mod raw_passthrough;
+mod semantic_cache;
+mod stream_failover;
+mod timeouts;
+mod tls;
 
-pub use dispatch::{DispatchRule, DispatchTable, OperationKind};
+pub use anthropic_beta::{
+    ANTHROPIC_BETA_HEADER, ANTHROPIC_BETA_KEY, AnthropicBetaPolicy, header_betas,
+};
+pub use body_sampling::{BODY_SAMPLING_KEY, BodySampling};
+pub use candidate_fan_out::{CANDIDATE_FAN_OUT_KEY, CandidateFanOutPolicy};
+pub use disallow::{DISALLOW_KEY, DisallowRule};
+pub use dispatch::{
+    DISPATCH_KEY, DispatchOverrides, DispatchRule, DispatchTable, MODEL_DISPATCH_KEY,
+    ModelDispatchRule, OperationKind,
+};
+pub use egress::{EGRESS_KEY, EgressPolicy, IpFamily, ProxyRotation};
+pub use failure_rules::{
+    FAILURE_RULES_KEY, FailureAction, FailureMatch, FailurePolicy, FailureRule,
+};
+pub use header_policy::{HEADER_POLICY_KEY, HeaderPolicy};
+pub use maintenance::{MAINTENANCE_KEY, MaintenanceSchedule, MaintenanceWindow};
 pub use model_table::{ModelRecord, ModelTable};
+pub use parsing::{PARSING_KEY, ParsingMode};
+pub use policies::ProviderPolicies;
+pub use post_process::{
+    POST_PROCESS_KEY, PostProcessConfig, PostProcessPolicy, PostProcessRule, TextWindow,
+};
 pub use provider_config::{
     AntigravityConfig, ClaudeCodeConfig, ClaudeCodePreludeText, CodexConfig, CountTokensMode,
-    CustomProviderConfig, ProviderConfig,
+    CustomAuth, CustomProviderConfig, PathOverrides, PluginProviderConfig, ProviderConfig,
+    RequestSigning,
 };
+pub use raw_passthrough::{RAW_PASSTHROUGH_KEY, RawPassthroughPolicy};
+pub use semantic_cache::{SEMANTIC_CACHE_KEY, SemanticCacheSettings};
+pub use stream_failover::{STREAM_FAILOVER_KEY, StreamFailover};
+pub use timeouts::{TIMEOUTS_KEY, TimeoutPolicy, UpstreamTimeouts};
+pub use tls::{TLS_KEY, TlsPolicy};
+
+/// The section of a provider config JSON under `key`, `None` when absent. Unlike the
+/// `from_config_json` readers, a section that does not deserialize is an error.
+fn section<T: serde::de::DeserializeOwned>(
+    config: &serde_json::Value,
+    key: &str,
+) -> Result<Option<T>, String> {
+    config
+        .get(key)
+        .map(|value| T::deserialize(value).map_err(|err| err.to_string()))
+        .transpose()
+}
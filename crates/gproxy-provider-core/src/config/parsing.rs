@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+
+/// Whether unknown request body fields are kept or rejected.
+pub const PARSING_KEY: &str = "parsing";
+
+/// What happens to top-level request body fields gproxy has no typed field for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParsingMode {
+    /// Unknown fields are kept and sent upstream when no protocol transform is needed;
+    /// a transform to another protocol drops them.
+    #[default]
+    Lenient,
+    /// Requests with unknown fields are rejected with `400 unknown_fields`.
+    Strict,
+}
+
+impl ParsingMode {
+    /// The provider's `parsing` mode; lenient unless the config says `strict`.
+    pub fn from_config_json(config: &serde_json::Value) -> Self {
+        config
+            .get(PARSING_KEY)
+            .and_then(|value| serde_json::from_value(value.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    /// Rejects any value other than `lenient` and `strict`.
+    pub fn validate_config_json(config: &serde_json::Value) -> Result<(), String> {
+        super::section::<Self>(config, PARSING_KEY).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ProviderConfig;
+
+    #[test]
+    fn parsing_mode_is_read_from_provider_config() {
+        let value = serde_json::json!({
+            "kind": "openai",
+            "channel_settings": {},
+            "parsing": "strict",
+        });
+        assert!(serde_json::from_value::<ProviderConfig>(value.clone()).is_ok());
+        assert_eq!(ParsingMode::from_config_json(&value), ParsingMode::Strict);
+        assert_eq!(
+            ParsingMode::from_config_json(&serde_json::json!({ "parsing": "loose" })),
+            ParsingMode::Lenient
+        );
+    }
+}
@@ -0,0 +1,199 @@
+use std::sync::Arc;
+
+use super::{
+    ANTHROPIC_BETA_KEY, AnthropicBetaPolicy, BODY_SAMPLING_KEY, BodySampling,
+    CANDIDATE_FAN_OUT_KEY, CandidateFanOutPolicy, DISALLOW_KEY, DISPATCH_KEY, DisallowRule,
+    DispatchOverrides, EGRESS_KEY, EgressPolicy, FAILURE_RULES_KEY, FailurePolicy,
+    HEADER_POLICY_KEY, HeaderPolicy, MAINTENANCE_KEY, MODEL_DISPATCH_KEY, MaintenanceSchedule,
+    ModelDispatchRule, PARSING_KEY, POST_PROCESS_KEY, ParsingMode, PostProcessPolicy,
+    RAW_PASSTHROUGH_KEY, RawPassthroughPolicy, SEMANTIC_CACHE_KEY, STREAM_FAILOVER_KEY,
+    SemanticCacheSettings, StreamFailover, TIMEOUTS_KEY, TLS_KEY, TimeoutPolicy, TlsPolicy,
+};
+
+/// The policies that sit beside [`super::ProviderConfig`] in a provider config JSON, read
+/// in one pass so a request does not parse them again.
+#[derive(Debug, Clone, Default)]
+pub struct ProviderPolicies {
+    pub header: HeaderPolicy,
+    pub anthropic_beta: AnthropicBetaPolicy,
+    pub timeouts: TimeoutPolicy,
+    pub egress: EgressPolicy,
+    pub tls: TlsPolicy,
+    pub parsing: ParsingMode,
+    pub disallow: Vec<DisallowRule>,
+    pub dispatch: DispatchOverrides,
+    pub model_dispatch: Vec<ModelDispatchRule>,
+    pub candidate_fan_out: CandidateFanOutPolicy,
+    pub raw_passthrough: RawPassthroughPolicy,
+    pub maintenance: MaintenanceSchedule,
+    pub stream_failover: StreamFailover,
+    pub semantic_cache: Option<SemanticCacheSettings>,
+    /// `None` when the provider has no post-process rules.
+    pub post_process: Option<Arc<PostProcessPolicy>>,
+    pub failure: FailurePolicy,
+}
+
+impl ProviderPolicies {
+    /// Reads every policy the way its own `from_config_json` does.
+    pub fn from_config_json(config: &serde_json::Value) -> Self {
+        let post_process = PostProcessPolicy::from_config_json(config);
+        Self {
+            header: HeaderPolicy::from_config_json(config),
+            anthropic_beta: AnthropicBetaPolicy::from_config_json(config),
+            timeouts: TimeoutPolicy::from_config_json(config),
+            egress: EgressPolicy::from_config_json(config),
+            tls: TlsPolicy::from_config_json(config),
+            parsing: ParsingMode::from_config_json(config),
+            disallow: DisallowRule::list_from_config_json(config),
+            dispatch: DispatchOverrides::from_config_json(config),
+            model_dispatch: ModelDispatchRule::list_from_config_json(config),
+            candidate_fan_out: CandidateFanOutPolicy::from_config_json(config),
+            raw_passthrough: RawPassthroughPolicy::from_config_json(config),
+            maintenance: MaintenanceSchedule::from_config_json(config),
+            stream_failover: StreamFailover::from_config_json(config),
+            semantic_cache: SemanticCacheSettings::from_config_json(config),
+            post_process: (!post_process.is_empty()).then(|| Arc::new(post_process)),
+            failure: FailurePolicy::from_config_json(config),
+        }
+    }
+
+    /// Checks every policy section a config carries, so a malformed one is refused when
+    /// it is written instead of being read as empty later. The error names the section.
+    pub fn validate_config_json(config: &serde_json::Value) -> Result<(), (&'static str, String)> {
+        type Check = fn(&serde_json::Value) -> Result<(), String>;
+        let checks: &[(&'static str, Check)] = &[
+            (HEADER_POLICY_KEY, HeaderPolicy::validate_config_json),
+            (
+                ANTHROPIC_BETA_KEY,
+                AnthropicBetaPolicy::validate_config_json,
+            ),
+            (TIMEOUTS_KEY, TimeoutPolicy::validate_config_json),
+            (EGRESS_KEY, EgressPolicy::validate_config_json),
+            (TLS_KEY, TlsPolicy::validate_config_json),
+            (PARSING_KEY, ParsingMode::validate_config_json),
+            (DISALLOW_KEY, DisallowRule::validate_config_json),
+            (DISPATCH_KEY, DispatchOverrides::validate_config_json),
+            (MODEL_DISPATCH_KEY, ModelDispatchRule::validate_config_json),
+            (
+                CANDIDATE_FAN_OUT_KEY,
+                CandidateFanOutPolicy::validate_config_json,
+            ),
+            (
+                RAW_PASSTHROUGH_KEY,
+                RawPassthroughPolicy::validate_config_json,
+            ),
+            (MAINTENANCE_KEY, MaintenanceSchedule::validate_config_json),
+            (STREAM_FAILOVER_KEY, StreamFailover::validate_config_json),
+            (
+                SEMANTIC_CACHE_KEY,
+                SemanticCacheSettings::validate_config_json,
+            ),
+            (POST_PROCESS_KEY, PostProcessPolicy::validate_config_json),
+            (FAILURE_RULES_KEY, FailurePolicy::validate_config_json),
+            (BODY_SAMPLING_KEY, BodySampling::validate_config_json),
+        ];
+        checks
+            .iter()
+            .try_for_each(|(key, check)| check(config).map_err(|err| (*key, err)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn policies_are_read_together_and_default_when_absent() {
+        let policies = ProviderPolicies::from_config_json(&serde_json::json!({
+            "kind": "openai",
+            "stream_failover": { "provider": "backup" },
+            "parsing": "strict",
+            "post_process": { "rules": [{ "type": "trim_trailing_whitespace" }] },
+        }));
+        assert_eq!(policies.stream_failover.provider.as_deref(), Some("backup"));
+        assert_eq!(policies.parsing, ParsingMode::Strict);
+        assert!(policies.post_process.is_some());
+        assert!(policies.semantic_cache.is_none());
+
+        let empty = ProviderPolicies::from_config_json(&serde_json::json!({ "kind": "openai" }));
+        assert!(empty.post_process.is_none());
+        assert_eq!(empty.parsing, ParsingMode::default());
+        assert!(empty.disallow.is_empty());
+    }
+
+    #[test]
+    fn malformed_sections_are_refused_by_name() {
+        let valid = serde_json::json!({
+            "kind": "openai",
+            "header_policy": { "forward_request": ["openai-*", "x-trace-id"], "strip": ["*"] },
+            "tls": { "ca_pem": "-----BEGIN CERTIFICATE-----\nMIIB\n-----END CERTIFICATE-----" },
+            "egress": { "proxies": ["socks5h://10.0.0.1:1080"] },
+            "timeouts": { "connect_ms": 1000, "total_ms": 60000 },
+            "anthropic_beta": { "defaults": ["prompt-caching-2024-07-31"] },
+            "raw_passthrough": { "enabled": true, "allow_prefixes": ["/v1/batches"] },
+            "maintenance": [{
+                "start": "2026-10-01T00:00:00Z",
+                "end": "2026-10-01T01:00:00Z",
+            }],
+            "body_sampling": { "success_percent": 10 },
+        });
+        assert_eq!(ProviderPolicies::validate_config_json(&valid), Ok(()));
+
+        for (key, value) in [
+            ("header_policy", serde_json::json!({ "strip": ["x-*-id"] })),
+            (
+                "header_policy",
+                serde_json::json!({ "forward_request": "openai-beta" }),
+            ),
+            ("tls", serde_json::json!({ "ca_pem": "not a certificate" })),
+            (
+                "egress",
+                serde_json::json!({ "proxies": ["10.0.0.1:3128"] }),
+            ),
+            (
+                "timeouts",
+                serde_json::json!({ "first_byte_ms": 5000, "total_ms": 1000 }),
+            ),
+            (
+                "timeouts",
+                serde_json::json!({ "operations": { "generate_content": { "total_ms": 1, "connect_ms": 2 } } }),
+            ),
+            ("anthropic_beta", serde_json::json!({ "defaults": ["a,b"] })),
+            ("parsing", serde_json::json!("loose")),
+            (
+                "disallow",
+                serde_json::json!([{ "id": "a" }, { "id": "a" }]),
+            ),
+            ("dispatch", serde_json::json!({ "ops": ["native"] })),
+            (
+                "model_dispatch",
+                serde_json::json!([{ "model": "o3*", "ops": { "nope": "native" } }]),
+            ),
+            (
+                "candidate_fan_out",
+                serde_json::json!({ "enabled": true, "max_candidates": 9 }),
+            ),
+            (
+                "raw_passthrough",
+                serde_json::json!({ "allow_prefixes": ["/v1/../admin"] }),
+            ),
+            (
+                "maintenance",
+                serde_json::json!([{ "start": "2026-10-01T01:00:00Z", "end": "2026-10-01T00:00:00Z" }]),
+            ),
+            ("stream_failover", serde_json::json!({ "provider": " " })),
+            (
+                "body_sampling",
+                serde_json::json!({ "success_percent": 101 }),
+            ),
+        ] {
+            let mut config = valid.clone();
+            config[key] = value;
+            assert_eq!(
+                ProviderPolicies::validate_config_json(&config).map_err(|(key, _)| key),
+                Err(key),
+                "{config}"
+            );
+        }
+    }
+}
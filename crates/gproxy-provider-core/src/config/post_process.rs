@@ -0,0 +1,333 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Rewrites of generated text in upstream responses.
+pub const POST_PROCESS_KEY: &str = "post_process";
+
+/// Characters of streamed text held back by default so rules can match across deltas.
+const DEFAULT_WINDOW_CHARS: usize = 64;
+/// Upper bound for a configured window.
+const MAX_WINDOW_CHARS: usize = 4096;
+
+/// One rewrite of generated text. Rules run in order.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PostProcessRule {
+    /// Regex find/replace; `replacement` may reference groups (`$1`, `${name}`).
+    Replace {
+        pattern: String,
+        #[serde(default)]
+        replacement: String,
+    },
+    /// Removes every occurrence of the strings.
+    Remove { strings: Vec<String> },
+    /// Trims whitespace at the end of the text.
+    TrimTrailingWhitespace,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PostProcessConfig {
+    #[serde(default)]
+    pub rules: Vec<PostProcessRule>,
+    /// Characters of streamed text held back for matching; a match longer than this
+    /// can be missed in streams.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub window: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+enum CompiledRule {
+    Replace(Regex, String),
+    Remove(Vec<String>),
+    TrimTrailingWhitespace,
+}
+
+/// Compiled post-processing rules of a provider, applied to the text of generate
+/// responses and streams.
+#[derive(Debug, Clone, Default)]
+pub struct PostProcessPolicy {
+    rules: Vec<CompiledRule>,
+    /// Characters of streamed text held back.
+    window_chars: usize,
+    /// Bytes of the longest removed string, which is always held back too.
+    longest_removed: usize,
+    trims: bool,
+}
+
+impl PostProcessPolicy {
+    /// Reads the rules from a provider config JSON. Missing or malformed config is empty,
+    /// and replace rules whose pattern does not compile are skipped.
+    pub fn from_config_json(config: &serde_json::Value) -> Self {
+        config
+            .get(POST_PROCESS_KEY)
+            .and_then(|value| serde_json::from_value::<PostProcessConfig>(value.clone()).ok())
+            .map(|config| Self::compile(&config).unwrap_or_else(|(policy, _)| policy))
+            .unwrap_or_default()
+    }
+
+    /// Checks the `post_process` section of a provider config JSON, if any.
+    pub fn validate_config_json(config: &serde_json::Value) -> Result<(), String> {
+        let Some(value) = config.get(POST_PROCESS_KEY) else {
+            return Ok(());
+        };
+        let config: PostProcessConfig =
+            serde_json::from_value(value.clone()).map_err(|err| err.to_string())?;
+        Self::compile(&config).map(|_| ()).map_err(|(_, err)| err)
+    }
+
+    /// Compiles `config`; on a bad pattern, returns the policy without it and the error.
+    fn compile(config: &PostProcessConfig) -> Result<Self, (Self, String)> {
+        let mut error = None;
+        let mut rules = Vec::with_capacity(config.rules.len());
+        for rule in &config.rules {
+            match rule {
+                PostProcessRule::Replace {
+                    pattern,
+                    replacement,
+                } => match Regex::new(pattern) {
+                    Ok(regex) => rules.push(CompiledRule::Replace(regex, replacement.clone())),
+                    Err(err) => {
+                        error.get_or_insert_with(|| format!("pattern `{pattern}`: {err}"));
+                    }
+                },
+                PostProcessRule::Remove { strings } => {
+                    let strings: Vec<String> =
+                        strings.iter().filter(|s| !s.is_empty()).cloned().collect();
+                    if !strings.is_empty() {
+                        rules.push(CompiledRule::Remove(strings));
+                    }
+                }
+                PostProcessRule::TrimTrailingWhitespace => {
+                    rules.push(CompiledRule::TrimTrailingWhitespace)
+                }
+            }
+        }
+        let window_chars = config
+            .window
+            .unwrap_or(DEFAULT_WINDOW_CHARS)
+            .min(MAX_WINDOW_CHARS);
+        let longest_removed = rules
+            .iter()
+            .filter_map(|rule| match rule {
+                CompiledRule::Remove(strings) => strings.iter().map(String::len).max(),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0);
+        let policy = Self {
+            trims: rules
+                .iter()
+                .any(|rule| matches!(rule, CompiledRule::TrimTrailingWhitespace)),
+            rules,
+            window_chars,
+            longest_removed,
+        };
+        match error {
+            Some(err) => Err((policy, err)),
+            None => Ok(policy),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Runs the rules over `text`. Trimming only applies when `text` is the end of the
+    /// output (`at_end`).
+    pub fn apply(&self, text: &str, at_end: bool) -> String {
+        let mut out = text.to_string();
+        for rule in &self.rules {
+            match rule {
+                CompiledRule::Replace(regex, replacement) => {
+                    if let std::borrow::Cow::Owned(replaced) =
+                        regex.replace_all(&out, replacement.as_str())
+                    {
+                        out = replaced;
+                    }
+                }
+                CompiledRule::Remove(strings) => {
+                    for s in strings {
+                        if out.contains(s.as_str()) {
+                            out = out.replace(s.as_str(), "");
+                        }
+                    }
+                }
+                CompiledRule::TrimTrailingWhitespace => {
+                    if at_end {
+                        out.truncate(out.trim_end().len());
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    /// Where `raw` may be split without cutting through a match or giving away trailing
+    /// whitespace that may still turn out to end the text.
+    fn safe_cut(&self, raw: &str) -> usize {
+        let mut cut = match self.window_chars {
+            0 => raw.len(),
+            n => raw.char_indices().rev().nth(n - 1).map_or(0, |(i, _)| i),
+        };
+        cut = cut.min(floor_char_boundary(
+            raw,
+            raw.len().saturating_sub(self.longest_removed),
+        ));
+        if self.trims {
+            cut = cut.min(raw.trim_end().len());
+        }
+        loop {
+            let before = cut;
+            for rule in &self.rules {
+                let straddles = |start: usize, end: usize| start < cut && end > cut;
+                let start = match rule {
+                    CompiledRule::Replace(regex, _) => regex
+                        .find_iter(raw)
+                        .find(|m| straddles(m.start(), m.end()))
+                        .map(|m| m.start()),
+                    CompiledRule::Remove(strings) => strings
+                        .iter()
+                        .flat_map(|s| raw.match_indices(s.as_str()).map(|(i, s)| (i, i + s.len())))
+                        .filter(|(start, end)| straddles(*start, *end))
+                        .map(|(start, _)| start)
+                        .min(),
+                    CompiledRule::TrimTrailingWhitespace => None,
+                };
+                if let Some(start) = start {
+                    cut = start;
+                }
+            }
+            if cut == before {
+                return cut;
+            }
+        }
+    }
+}
+
+/// Streaming application of a [`PostProcessPolicy`] to one text channel: the last
+/// window of text is held back so a match split across deltas is still caught.
+#[derive(Debug, Default)]
+pub struct TextWindow {
+    raw: String,
+}
+
+impl TextWindow {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a delta and returns the processed text that is safe to emit.
+    pub fn push(&mut self, policy: &PostProcessPolicy, delta: &str) -> String {
+        self.raw.push_str(delta);
+        let cut = policy.safe_cut(&self.raw);
+        if cut == 0 {
+            return String::new();
+        }
+        let head: String = self.raw.drain(..cut).collect();
+        policy.apply(&head, false)
+    }
+
+    /// The processed rest of the text once the channel ends.
+    pub fn finish(&mut self, policy: &PostProcessPolicy) -> String {
+        let rest = std::mem::take(&mut self.raw);
+        policy.apply(&rest, true)
+    }
+}
+
+fn floor_char_boundary(text: &str, mut index: usize) -> usize {
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ProviderConfig;
+
+    fn policy(rules: serde_json::Value) -> PostProcessPolicy {
+        PostProcessPolicy::from_config_json(&serde_json::json!({
+            "post_process": { "rules": rules, "window": 8 },
+        }))
+    }
+
+    fn streamed(policy: &PostProcessPolicy, deltas: &[&str]) -> String {
+        let mut window = TextWindow::new();
+        let mut out: String = deltas.iter().map(|d| window.push(policy, d)).collect();
+        out.push_str(&window.finish(policy));
+        out
+    }
+
+    #[test]
+    fn rules_are_read_from_provider_config_in_order() {
+        let value = serde_json::json!({
+            "kind": "openai",
+            "channel_settings": {},
+            "post_process": {
+                "rules": [
+                    { "type": "replace", "pattern": "(?i)as an ai,\\s*", "replacement": "" },
+                    { "type": "remove", "strings": ["<|end|>"] },
+                    { "type": "trim_trailing_whitespace" },
+                ],
+            },
+        });
+        assert!(serde_json::from_value::<ProviderConfig>(value.clone()).is_ok());
+        let policy = PostProcessPolicy::from_config_json(&value);
+        assert_eq!(
+            policy.apply("As an AI, hello<|end|> \n", true),
+            "hello".to_string()
+        );
+        assert_eq!(policy.apply("hi \n", false), "hi \n".to_string());
+        assert!(PostProcessPolicy::from_config_json(&serde_json::json!({})).is_empty());
+    }
+
+    #[test]
+    fn bad_patterns_are_reported_and_skipped() {
+        let value = serde_json::json!({
+            "post_process": { "rules": [
+                { "type": "replace", "pattern": "(", "replacement": "" },
+                { "type": "remove", "strings": ["x"] },
+            ] },
+        });
+        assert!(PostProcessPolicy::validate_config_json(&value).is_err());
+        assert_eq!(
+            PostProcessPolicy::from_config_json(&value).apply("axb", true),
+            "ab"
+        );
+        assert!(PostProcessPolicy::validate_config_json(&serde_json::json!({})).is_ok());
+    }
+
+    #[test]
+    fn matches_split_across_deltas_are_caught() {
+        let policy = policy(serde_json::json!([
+            { "type": "replace", "pattern": "secret-\\d+", "replacement": "[redacted]" },
+            { "type": "remove", "strings": ["<|end|>"] },
+            { "type": "trim_trailing_whitespace" },
+        ]));
+        let deltas = [
+            "The code is sec",
+            "ret-4",
+            "2 and é",
+            "tc<|e",
+            "nd|>",
+            " \n",
+            " ",
+        ];
+        assert_eq!(streamed(&policy, &deltas), "The code is [redacted] and étc");
+        assert_eq!(
+            streamed(&policy, &deltas),
+            policy.apply(&deltas.concat(), true)
+        );
+    }
+
+    #[test]
+    fn text_beyond_the_window_is_released() {
+        let policy = policy(serde_json::json!([{ "type": "remove", "strings": ["zz"] }]));
+        let mut window = TextWindow::new();
+        assert_eq!(window.push(&policy, "short"), "");
+        let released = window.push(&policy, &"a".repeat(100));
+        assert!(released.starts_with("short"));
+        assert_eq!(released.len() + policy.window_chars, 105);
+    }
+}
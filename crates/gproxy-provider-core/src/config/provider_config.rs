@@ -1,9 +1,13 @@
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 
 use crate::Proto;
 
+use super::dispatch::model_matches;
 use super::{DispatchTable, ModelTable};
 
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "kind", content = "channel_settings", rename_all = "lowercase")]
 pub enum ProviderConfig {
@@ -19,30 +23,80 @@ pub enum ProviderConfig {
     Nvidia(NvidiaConfig),
     DeepSeek(DeepSeekConfig),
     Custom(CustomProviderConfig),
+    /// Served by an out-of-tree `UpstreamProvider` registered under `implementation`.
+    Plugin(PluginProviderConfig),
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct OpenAIConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub base_url: Option<String>,
+    #[serde(flatten)]
+    pub path_overrides: PathOverrides,
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ClaudeConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub base_url: Option<String>,
+    #[serde(flatten)]
+    pub path_overrides: PathOverrides,
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct AIStudioConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub base_url: Option<String>,
+    #[serde(flatten)]
+    pub path_overrides: PathOverrides,
+}
+
+/// Upstream paths of a built-in provider pointed at a regional endpoint, an enterprise
+/// gateway or an API-compatible clone through its `base_url`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PathOverrides {
+    /// Replaces the version segment (`/v1`, `/v1beta`) every built-in path starts with,
+    /// e.g. `/api/v3`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path_prefix: Option<String>,
+    /// Upstream path per operation, keyed like the dispatch table (`openai_chat_generate`,
+    /// `gemini_generate_stream`, ...). `{model}` is replaced with the request's model.
+    /// Wins over `path_prefix`.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub paths: HashMap<String, String>,
+}
+
+impl PathOverrides {
+    /// The path of operation `op`, whose built-in path is `default`.
+    pub fn resolve(&self, op: &str, default: &str, model: &str) -> String {
+        if let Some(template) = self.paths.get(op) {
+            let model = model.trim_start_matches('/').trim_start_matches("models/");
+            return template.replace("{model}", model);
+        }
+        self.prefixed(default)
+    }
+
+    /// `default` with its version segment replaced by `path_prefix`, if one is set. For
+    /// calls without an operation key, such as stored-resource endpoints.
+    pub fn prefixed(&self, default: &str) -> String {
+        let Some(prefix) = self.path_prefix.as_deref() else {
+            return default.to_string();
+        };
+        let prefix = prefix.trim_end_matches('/');
+        let rest = default.trim_start_matches('/');
+        let rest = rest.find('/').map_or("", |at| &rest[at..]);
+        format!("{prefix}{rest}")
+    }
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct VertexExpressConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub base_url: Option<String>,
+    /// Regions (`us-central1`) or base URLs tried in order when the base URL answers a
+    /// request with a 429 or a capacity error.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallback_regions: Vec<String>,
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -73,6 +127,10 @@ pub struct ClaudeCodeConfig {
     pub platform_base_url: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none", alias = "prelude_txt")]
     pub prelude_text: Option<ClaudeCodePreludeText>,
+    /// Injected prelude with `{prelude}` (the `prelude_text` line), `{key_label}`,
+    /// `{org}`, `{date}` and `{allowed_tools}` filled in per request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prelude_template: Option<String>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Default)]
@@ -147,6 +205,15 @@ pub struct DeepSeekConfig {
     pub base_url: Option<String>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginProviderConfig {
+    /// Registry name of the provider implementation.
+    pub implementation: String,
+    /// Everything else in `channel_settings`, left for the plugin to interpret.
+    #[serde(flatten)]
+    pub settings: serde_json::Map<String, serde_json::Value>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CustomProviderConfig {
     pub id: String,
@@ -160,6 +227,59 @@ pub struct CustomProviderConfig {
     pub count_tokens: CountTokensMode,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub json_param_mask: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_signing: Option<RequestSigning>,
+    /// Replaces the protocol's default auth header (`x-api-key`, `x-goog-api-key`, bearer).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<CustomAuth>,
+    /// Upstream path per operation, keyed like the dispatch table (`openai_chat_generate`,
+    /// `gemini_generate_stream`, ...). `{model}` is replaced with the request's model.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub paths: HashMap<String, String>,
+    /// Models this upstream may be asked for; exact ids or prefixes ending in `*`.
+    /// Empty allows everything.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_models: Vec<String>,
+}
+
+impl CustomProviderConfig {
+    pub fn allows_model(&self, model: &str) -> bool {
+        self.allowed_models.is_empty()
+            || self
+                .allowed_models
+                .iter()
+                .any(|pattern| model_matches(pattern, model))
+    }
+}
+
+/// Auth header for custom upstreams, e.g. `{"header": "api-key"}` or
+/// `{"header": "authorization", "template": "Token {api_key}"}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomAuth {
+    pub header: String,
+    #[serde(default = "default_auth_template")]
+    pub template: String,
+}
+
+impl CustomAuth {
+    pub fn value(&self, api_key: &str) -> String {
+        self.template.replace("{api_key}", api_key)
+    }
+}
+
+fn default_auth_template() -> String {
+    "{api_key}".to_string()
+}
+
+/// HMAC-SHA256 request signature for custom upstreams. The hex digest of the final
+/// body (prefixed with `"{timestamp}."` when `timestamp_header` is set) goes into `header`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequestSigning {
+    pub header: String,
+    pub secret: String,
+    /// Also send the unix timestamp (seconds) in this header and sign it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp_header: Option<String>,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
@@ -174,7 +294,39 @@ pub enum CountTokensMode {
 
 #[cfg(test)]
 mod tests {
-    use super::ClaudeCodePreludeText;
+    use super::{ClaudeCodePreludeText, PathOverrides, ProviderConfig};
+
+    #[test]
+    fn path_overrides_replace_the_version_or_the_whole_path() {
+        let config: ProviderConfig = serde_json::from_value(serde_json::json!({
+            "kind": "openai",
+            "channel_settings": {
+                "base_url": "https://ark.example.com",
+                "path_prefix": "/api/v3/",
+                "paths": { "openai_chat_generate": "/deployments/{model}/chat" },
+            },
+        }))
+        .unwrap();
+        let ProviderConfig::OpenAI(cfg) = config else {
+            panic!("expected openai config");
+        };
+        let paths = &cfg.path_overrides;
+        assert_eq!(
+            paths.resolve("openai_chat_generate", "/v1/chat/completions", "gpt-4.1"),
+            "/deployments/gpt-4.1/chat"
+        );
+        assert_eq!(
+            paths.resolve("openai_response_generate", "/v1/responses", "gpt-4.1"),
+            "/api/v3/responses"
+        );
+        assert_eq!(paths.prefixed("/v1/files/f-1"), "/api/v3/files/f-1");
+
+        let none = PathOverrides::default();
+        assert_eq!(
+            none.resolve("gemini_generate", "/v1beta/models/g:generateContent", "g"),
+            "/v1beta/models/g:generateContent"
+        );
+    }
 
     #[test]
     fn claudecode_prelude_text_parses_canonical_values() {
@@ -0,0 +1,83 @@
+use serde::{Deserialize, Serialize};
+
+/// Whether untyped `/{provider}/...` paths are forwarded verbatim.
+pub const RAW_PASSTHROUGH_KEY: &str = "raw_passthrough";
+
+/// Forwarding of `/{provider}/...` paths gproxy has no typed route for. Such requests go
+/// upstream verbatim with the credential injected, so new vendor endpoints work before
+/// gproxy supports them.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct RawPassthroughPolicy {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Path prefixes (e.g. `/v1/batches`) that may be forwarded; empty allows any path.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allow_prefixes: Vec<String>,
+}
+
+impl RawPassthroughPolicy {
+    /// The provider's `raw_passthrough` settings; without them no untyped path is
+    /// forwarded.
+    pub fn from_config_json(config: &serde_json::Value) -> Self {
+        config
+            .get(RAW_PASSTHROUGH_KEY)
+            .and_then(|value| serde_json::from_value(value.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    /// Rejects prefixes that do not start with `/` or step out with `..`.
+    pub fn validate_config_json(config: &serde_json::Value) -> Result<(), String> {
+        let Some(policy) = super::section::<Self>(config, RAW_PASSTHROUGH_KEY)? else {
+            return Ok(());
+        };
+        for prefix in &policy.allow_prefixes {
+            if !prefix.starts_with('/') || prefix.split('/').any(|segment| segment == "..") {
+                return Err(format!("`{prefix}` is not an absolute path prefix"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether `path` (starting with `/`) may be forwarded.
+    pub fn allows(&self, path: &str) -> bool {
+        if !self.enabled || path.split('/').any(|segment| segment == "..") {
+            return false;
+        }
+        self.allow_prefixes.is_empty()
+            || self.allow_prefixes.iter().any(|prefix| {
+                let prefix = prefix.trim_end_matches('/');
+                path == prefix
+                    || path
+                        .strip_prefix(prefix)
+                        .is_some_and(|rest| rest.starts_with('/'))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ProviderConfig;
+
+    #[test]
+    fn raw_passthrough_policy_is_read_from_provider_config() {
+        let value = serde_json::json!({
+            "kind": "openai",
+            "channel_settings": {},
+            "raw_passthrough": { "enabled": true, "allow_prefixes": ["/v1/batches/"] },
+        });
+        assert!(serde_json::from_value::<ProviderConfig>(value.clone()).is_ok());
+        let policy = RawPassthroughPolicy::from_config_json(&value);
+        assert!(policy.allows("/v1/batches"));
+        assert!(policy.allows("/v1/batches/batch_1/cancel"));
+        assert!(!policy.allows("/v1/batchesx"));
+        assert!(!policy.allows("/v1/batches/../files"));
+        assert!(!policy.allows("/v1/files"));
+
+        let any = serde_json::json!({ "raw_passthrough": { "enabled": true } });
+        assert!(RawPassthroughPolicy::from_config_json(&any).allows("/v1/files"));
+        assert!(
+            !RawPassthroughPolicy::from_config_json(&serde_json::json!({})).allows("/v1/files")
+        );
+    }
+}
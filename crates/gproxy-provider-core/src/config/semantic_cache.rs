@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+
+/// Serving answers from earlier similar prompts.
+pub const SEMANTIC_CACHE_KEY: &str = "semantic_cache";
+
+const DEFAULT_THRESHOLD: f32 = 0.95;
+const DEFAULT_TTL_SECS: u64 = 24 * 60 * 60;
+const DEFAULT_MAX_ENTRIES: usize = 10_000;
+const DEFAULT_EMBEDDING_PATH: &str = "/v1/embeddings";
+
+/// Serving non-stream generate requests from earlier answers to similar prompts. The
+/// prompt is embedded through `embedding_provider`, an OpenAI-compatible embeddings
+/// endpoint, and a cached answer whose prompt is at least `threshold` similar (cosine)
+/// is returned instead of calling upstream.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SemanticCacheSettings {
+    /// Provider whose credentials serve the embedding calls.
+    pub embedding_provider: String,
+    pub embedding_model: String,
+    /// Path of the embeddings endpoint below the embedding provider's base URL.
+    #[serde(default = "default_embedding_path")]
+    pub embedding_path: String,
+    #[serde(default = "default_threshold")]
+    pub threshold: f32,
+    #[serde(default = "default_ttl_secs")]
+    pub ttl_secs: u64,
+    /// Entries kept per partition; the oldest go first.
+    #[serde(default = "default_max_entries")]
+    pub max_entries: usize,
+    /// Share answers between user keys. By default every key has its own cache.
+    #[serde(default)]
+    pub shared: bool,
+}
+
+fn default_embedding_path() -> String {
+    DEFAULT_EMBEDDING_PATH.to_string()
+}
+
+fn default_threshold() -> f32 {
+    DEFAULT_THRESHOLD
+}
+
+fn default_ttl_secs() -> u64 {
+    DEFAULT_TTL_SECS
+}
+
+fn default_max_entries() -> usize {
+    DEFAULT_MAX_ENTRIES
+}
+
+impl SemanticCacheSettings {
+    /// Reads the settings from a provider config JSON; missing or malformed settings, or
+    /// ones that could never hit, leave the cache off.
+    pub fn from_config_json(config: &serde_json::Value) -> Option<Self> {
+        let settings: Self =
+            serde_json::from_value(config.get(SEMANTIC_CACHE_KEY)?.clone()).ok()?;
+        settings.validate().ok()?;
+        Some(settings)
+    }
+
+    /// Checks the `semantic_cache` section of a provider config JSON, if any.
+    pub fn validate_config_json(config: &serde_json::Value) -> Result<(), String> {
+        let Some(value) = config.get(SEMANTIC_CACHE_KEY) else {
+            return Ok(());
+        };
+        let settings: Self =
+            serde_json::from_value(value.clone()).map_err(|err| err.to_string())?;
+        settings.validate()
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.embedding_provider.trim().is_empty() || self.embedding_model.trim().is_empty() {
+            return Err("embedding_provider and embedding_model are required".to_string());
+        }
+        if !self.embedding_path.starts_with('/') {
+            return Err("embedding_path must start with `/`".to_string());
+        }
+        if !(self.threshold > 0.0 && self.threshold <= 1.0) {
+            return Err("threshold must be in (0, 1]".to_string());
+        }
+        if self.ttl_secs == 0 || self.max_entries == 0 {
+            return Err("ttl_secs and max_entries must be positive".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ProviderConfig;
+
+    #[test]
+    fn semantic_cache_settings_are_read_from_provider_config() {
+        let value = serde_json::json!({
+            "kind": "openai",
+            "channel_settings": {},
+            "semantic_cache": {
+                "embedding_provider": "openai",
+                "embedding_model": "text-embedding-3-small",
+                "threshold": 0.9,
+            },
+        });
+        assert!(serde_json::from_value::<ProviderConfig>(value.clone()).is_ok());
+        let settings = SemanticCacheSettings::from_config_json(&value).unwrap();
+        assert_eq!(settings.threshold, 0.9);
+        assert_eq!(settings.embedding_path, "/v1/embeddings");
+        assert_eq!(settings.ttl_secs, DEFAULT_TTL_SECS);
+        assert!(!settings.shared);
+        assert!(SemanticCacheSettings::from_config_json(&serde_json::json!({})).is_none());
+
+        let bad = serde_json::json!({
+            "semantic_cache": {
+                "embedding_provider": "openai",
+                "embedding_model": "m",
+                "threshold": 1.5,
+            },
+        });
+        assert!(SemanticCacheSettings::validate_config_json(&bad).is_err());
+        assert!(SemanticCacheSettings::from_config_json(&bad).is_none());
+        assert!(SemanticCacheSettings::validate_config_json(&serde_json::json!({})).is_ok());
+    }
+}
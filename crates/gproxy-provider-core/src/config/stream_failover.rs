@@ -0,0 +1,64 @@
+use serde::{Deserialize, Serialize};
+
+/// Provider a stalled stream is sent to once no credential is left.
+pub const STREAM_FAILOVER_KEY: &str = "stream_failover";
+
+/// Where a generate stream goes when it stalls or closes before its first token and the
+/// provider has no other credential left to try.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StreamFailover {
+    /// Provider the request is sent to instead, one hop only: its own `stream_failover`
+    /// is not followed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+}
+
+impl StreamFailover {
+    /// The provider's `stream_failover` target; without one a stalled stream fails once
+    /// every credential has been tried.
+    pub fn from_config_json(config: &serde_json::Value) -> Self {
+        config
+            .get(STREAM_FAILOVER_KEY)
+            .and_then(|value| serde_json::from_value(value.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    /// Rejects an empty target name.
+    pub fn validate_config_json(config: &serde_json::Value) -> Result<(), String> {
+        let Some(failover) = super::section::<Self>(config, STREAM_FAILOVER_KEY)? else {
+            return Ok(());
+        };
+        if failover
+            .provider
+            .as_deref()
+            .is_some_and(|provider| provider.trim().is_empty())
+        {
+            return Err("`provider` is empty".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ProviderConfig;
+
+    #[test]
+    fn stream_failover_is_read_from_provider_config() {
+        let value = serde_json::json!({
+            "kind": "openai",
+            "channel_settings": {},
+            "stream_failover": { "provider": "openai-backup" },
+        });
+        assert!(serde_json::from_value::<ProviderConfig>(value.clone()).is_ok());
+        assert_eq!(
+            StreamFailover::from_config_json(&value).provider.as_deref(),
+            Some("openai-backup")
+        );
+        assert_eq!(
+            StreamFailover::from_config_json(&serde_json::json!({ "stream_failover": "x" })),
+            StreamFailover::default()
+        );
+    }
+}
@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Op;
+
+/// Upstream connect, first-byte, total and idle timeouts.
+pub const TIMEOUTS_KEY: &str = "timeouts";
+
+/// Upstream timeouts in milliseconds. Unset (or zero) values keep the client defaults.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpstreamTimeouts {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connect_ms: Option<u64>,
+    /// Until the upstream response headers arrive.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_byte_ms: Option<u64>,
+    /// The whole exchange, including reading or streaming the body.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_ms: Option<u64>,
+    /// Longest silence between two body chunks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_ms: Option<u64>,
+}
+
+impl UpstreamTimeouts {
+    pub fn connect(&self) -> Option<Duration> {
+        millis(self.connect_ms)
+    }
+
+    pub fn first_byte(&self) -> Option<Duration> {
+        millis(self.first_byte_ms)
+    }
+
+    pub fn total(&self) -> Option<Duration> {
+        millis(self.total_ms)
+    }
+
+    pub fn idle(&self) -> Option<Duration> {
+        millis(self.idle_ms)
+    }
+
+    /// `other` wins wherever it sets a value.
+    fn overlay(self, other: UpstreamTimeouts) -> Self {
+        Self {
+            connect_ms: other.connect_ms.or(self.connect_ms),
+            first_byte_ms: other.first_byte_ms.or(self.first_byte_ms),
+            total_ms: other.total_ms.or(self.total_ms),
+            idle_ms: other.idle_ms.or(self.idle_ms),
+        }
+    }
+}
+
+fn millis(value: Option<u64>) -> Option<Duration> {
+    value.filter(|ms| *ms > 0).map(Duration::from_millis)
+}
+
+/// Provider-wide timeouts with per-operation overrides, e.g.
+/// `{"first_byte_ms": 30000, "operations": {"generate_content": {"total_ms": 120000}}}`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeoutPolicy {
+    #[serde(flatten)]
+    pub defaults: UpstreamTimeouts,
+    /// Keyed by the upstream operation (`generate_content`, `stream_generate_content`, ...).
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub operations: HashMap<Op, UpstreamTimeouts>,
+}
+
+impl TimeoutPolicy {
+    /// The provider's `timeouts`; without them every operation keeps the client defaults.
+    pub fn from_config_json(config: &serde_json::Value) -> Self {
+        config
+            .get(TIMEOUTS_KEY)
+            .and_then(|value| serde_json::from_value(value.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    /// Rejects `timeouts` whose `total_ms` ends an operation before its `connect_ms` or
+    /// `first_byte_ms` could.
+    pub fn validate_config_json(config: &serde_json::Value) -> Result<(), String> {
+        let Some(policy) = super::section::<Self>(config, TIMEOUTS_KEY)? else {
+            return Ok(());
+        };
+        let ops = policy
+            .operations
+            .keys()
+            .map(|op| (Some(*op), policy.for_op(*op)));
+        for (op, timeouts) in std::iter::once((None, policy.defaults)).chain(ops) {
+            let Some(total) = timeouts.total() else {
+                continue;
+            };
+            if timeouts.connect().is_some_and(|t| t > total)
+                || timeouts.first_byte().is_some_and(|t| t > total)
+            {
+                let scope = op.map_or("defaults".to_string(), |op| format!("{op:?}"));
+                return Err(format!(
+                    "`total_ms` of {scope} is shorter than `connect_ms` or `first_byte_ms`"
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn for_op(&self, op: Op) -> UpstreamTimeouts {
+        match self.operations.get(&op) {
+            Some(overrides) => self.defaults.overlay(*overrides),
+            None => self.defaults,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ProviderConfig;
+
+    #[test]
+    fn operation_overrides_layer_over_provider_defaults() {
+        let value = serde_json::json!({
+            "kind": "openai",
+            "channel_settings": {},
+            "timeouts": {
+                "connect_ms": 3000,
+                "first_byte_ms": 30000,
+                "operations": {
+                    "generate_content": { "total_ms": 120000 },
+                    "stream_generate_content": { "first_byte_ms": 0, "idle_ms": 15000 }
+                }
+            },
+        });
+        assert!(serde_json::from_value::<ProviderConfig>(value.clone()).is_ok());
+        let policy = TimeoutPolicy::from_config_json(&value);
+
+        let generate = policy.for_op(Op::GenerateContent);
+        assert_eq!(generate.connect(), Some(Duration::from_secs(3)));
+        assert_eq!(generate.first_byte(), Some(Duration::from_secs(30)));
+        assert_eq!(generate.total(), Some(Duration::from_secs(120)));
+
+        // Zero switches an inherited timeout off.
+        let stream = policy.for_op(Op::StreamGenerateContent);
+        assert_eq!(stream.first_byte(), None);
+        assert_eq!(stream.idle(), Some(Duration::from_secs(15)));
+
+        assert_eq!(policy.for_op(Op::ModelList).total(), None);
+    }
+}
@@ -0,0 +1,84 @@
+use serde::{Deserialize, Serialize};
+
+/// Extra trusted CA certificates and certificate verification of upstreams.
+pub const TLS_KEY: &str = "tls";
+
+/// Trust settings for a provider's upstream connections.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TlsPolicy {
+    /// Path to a PEM file of extra CA certificates, trusted next to the built-in roots.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_bundle: Option<String>,
+    /// Inline PEM certificates, trusted like `ca_bundle`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_pem: Option<String>,
+    /// Accept any certificate and host name. Only for upstreams reached through a
+    /// trusted network; must be set explicitly.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub insecure_skip_verify: bool,
+}
+
+impl TlsPolicy {
+    /// The provider's `tls` settings; without them certificates are verified against the
+    /// built-in roots only.
+    pub fn from_config_json(config: &serde_json::Value) -> Self {
+        config
+            .get(TLS_KEY)
+            .and_then(|value| serde_json::from_value(value.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    /// Rejects an empty `ca_bundle` path and a `ca_pem` holding no PEM certificate. The
+    /// bundle file itself is read when a connection is built.
+    pub fn validate_config_json(config: &serde_json::Value) -> Result<(), String> {
+        let Some(policy) = super::section::<Self>(config, TLS_KEY)? else {
+            return Ok(());
+        };
+        if policy
+            .ca_bundle
+            .as_deref()
+            .is_some_and(|path| path.trim().is_empty())
+        {
+            return Err("`ca_bundle` is empty".to_string());
+        }
+        if policy
+            .ca_pem
+            .as_deref()
+            .is_some_and(|pem| !pem.contains("-----BEGIN CERTIFICATE-----"))
+        {
+            return Err("`ca_pem` holds no PEM certificate".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn has_extra_roots(&self) -> bool {
+        self.ca_bundle.is_some() || self.ca_pem.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ProviderConfig;
+
+    #[test]
+    fn tls_policy_is_read_from_provider_config() {
+        let value = serde_json::json!({
+            "kind": "openai",
+            "channel_settings": {},
+            "tls": { "ca_bundle": "/etc/gproxy/corp-ca.pem" },
+        });
+        assert!(serde_json::from_value::<ProviderConfig>(value.clone()).is_ok());
+        let policy = TlsPolicy::from_config_json(&value);
+        assert_eq!(policy.ca_bundle.as_deref(), Some("/etc/gproxy/corp-ca.pem"));
+        assert!(policy.has_extra_roots());
+        assert!(!policy.insecure_skip_verify);
+
+        let insecure = serde_json::json!({ "tls": { "insecure_skip_verify": true } });
+        assert!(TlsPolicy::from_config_json(&insecure).insecure_skip_verify);
+        assert_eq!(
+            TlsPolicy::from_config_json(&serde_json::json!({})),
+            TlsPolicy::default()
+        );
+    }
+}
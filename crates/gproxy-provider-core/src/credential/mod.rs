@@ -3,7 +3,7 @@ mod pool;
 mod state;
 mod unavailable_queue;
 
-pub use pool::{AcquireError, CredentialPool};
+pub use pool::{AcquireError, CredentialPool, PoolAvailability};
 pub use state::{CredentialId, CredentialState, UnavailableReason};
 
 use serde::{Deserialize, Serialize};
@@ -22,6 +22,8 @@ pub enum Credential {
     Nvidia(ApiKeyCredential),
     DeepSeek(ApiKeyCredential),
     Custom(ApiKeyCredential),
+    /// Opaque secret for plugin providers; its shape is up to the plugin.
+    Plugin(serde_json::Value),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 
@@ -20,8 +20,17 @@ pub enum AcquireError {
     NoActiveCredentials,
 }
 
+/// Enabled credential counts for one provider.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolAvailability {
+    pub credentials: usize,
+    pub unavailable: usize,
+    /// Active per-model cooldowns across the provider's credentials.
+    pub model_cooldowns: usize,
+}
+
 pub struct CredentialPool {
-    creds: RwLock<HashMap<CredentialId, Credential>>,
+    creds: RwLock<HashMap<CredentialId, Arc<Credential>>>,
     by_provider: RwLock<HashMap<String, Vec<CredentialId>>>,
     states: Arc<RwLock<HashMap<CredentialId, CredentialState>>>,
     model_states: Arc<RwLock<HashMap<ModelStateKey, ModelStateValue>>>,
@@ -59,7 +68,7 @@ impl CredentialPool {
 
     pub async fn insert(&self, provider: impl Into<String>, id: CredentialId, cred: Credential) {
         let provider = provider.into();
-        self.creds.write().await.insert(id, cred);
+        self.creds.write().await.insert(id, Arc::new(cred));
         // Avoid duplicated IDs in the provider index; insert() can be called on enable toggles.
         let mut by_provider = self.by_provider.write().await;
         let ids = by_provider.entry(provider).or_default();
@@ -74,7 +83,7 @@ impl CredentialPool {
     }
 
     pub async fn update_credential(&self, id: CredentialId, cred: Credential) {
-        self.creds.write().await.insert(id, cred);
+        self.creds.write().await.insert(id, Arc::new(cred));
     }
 
     pub async fn set_enabled(&self, provider: &str, id: CredentialId, enabled: bool) {
@@ -105,39 +114,40 @@ impl CredentialPool {
     pub async fn acquire(
         &self,
         provider: &str,
-    ) -> Result<(CredentialId, Credential), AcquireError> {
-        let ids = {
-            let guard = self.by_provider.read().await;
-            guard.get(provider).cloned()
-        };
-        let Some(ids) = ids else {
-            return Err(AcquireError::ProviderUnknown);
-        };
+    ) -> Result<(CredentialId, Arc<Credential>), AcquireError> {
+        self.acquire_scoped(provider, None, None).await
+    }
 
-        let states = self.states.read().await;
-        let chosen = ids
-            .into_iter()
-            .find(|id| matches!(states.get(id), Some(CredentialState::Active)));
-        drop(states);
+    pub async fn acquire_for_model(
+        &self,
+        provider: &str,
+        model: &str,
+    ) -> Result<(CredentialId, Arc<Credential>), AcquireError> {
+        self.acquire_scoped(provider, Some(model), None).await
+    }
 
-        let Some(id) = chosen else {
-            return Err(AcquireError::NoActiveCredentials);
-        };
-        let cred = self
-            .creds
-            .read()
+    /// Acquire an active credential, optionally honoring per-model cooldowns and
+    /// restricting the candidates to `allowed` (tenant isolation). The credential is
+    /// shared with the pool rather than copied per request.
+    pub async fn acquire_scoped(
+        &self,
+        provider: &str,
+        model: Option<&str>,
+        allowed: Option<&HashSet<CredentialId>>,
+    ) -> Result<(CredentialId, Arc<Credential>), AcquireError> {
+        self.acquire_excluding(provider, model, allowed, &HashSet::new())
             .await
-            .get(&id)
-            .cloned()
-            .ok_or(AcquireError::NoActiveCredentials)?;
-        Ok((id, cred))
     }
 
-    pub async fn acquire_for_model(
+    /// Like [`acquire_scoped`](Self::acquire_scoped), skipping the credentials in
+    /// `excluded`: ones that already failed the request without being put on cooldown.
+    pub async fn acquire_excluding(
         &self,
         provider: &str,
-        model: &str,
-    ) -> Result<(CredentialId, Credential), AcquireError> {
+        model: Option<&str>,
+        allowed: Option<&HashSet<CredentialId>>,
+        excluded: &HashSet<CredentialId>,
+    ) -> Result<(CredentialId, Arc<Credential>), AcquireError> {
         let ids = {
             let guard = self.by_provider.read().await;
             guard.get(provider).cloned()
@@ -149,9 +159,15 @@ impl CredentialPool {
         let states = self.states.read().await;
         let model_states = self.model_states.read().await;
         let chosen = ids.into_iter().find(|id| {
+            if allowed.is_some_and(|allowed| !allowed.contains(id)) || excluded.contains(id) {
+                return false;
+            }
             if !matches!(states.get(id), Some(CredentialState::Active)) {
                 return false;
             }
+            let Some(model) = model else {
+                return true;
+            };
             let key = (*id, model.to_string());
             match model_states.get(&key) {
                 Some((until, _reason)) => *until <= Instant::now(),
@@ -241,6 +257,27 @@ impl CredentialPool {
             .await;
     }
 
+    pub async fn availability(&self, provider: &str) -> PoolAvailability {
+        let ids = {
+            let guard = self.by_provider.read().await;
+            guard.get(provider).cloned().unwrap_or_default()
+        };
+        let now = Instant::now();
+        let states = self.states.read().await;
+        let model_states = self.model_states.read().await;
+        PoolAvailability {
+            credentials: ids.len(),
+            unavailable: ids
+                .iter()
+                .filter(|id| !matches!(states.get(id), Some(CredentialState::Active)))
+                .count(),
+            model_cooldowns: model_states
+                .iter()
+                .filter(|((id, _), (until, _))| *until > now && ids.contains(id))
+                .count(),
+        }
+    }
+
     pub async fn state(&self, credential_id: CredentialId) -> Option<CredentialState> {
         self.states.read().await.get(&credential_id).cloned()
     }
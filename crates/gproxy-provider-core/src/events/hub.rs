@@ -1,13 +1,37 @@
+use std::collections::VecDeque;
 use std::future::Future;
 use std::pin::Pin;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 
-use tokio::sync::{RwLock, broadcast};
+use tokio::sync::{Notify, RwLock, broadcast};
 
 use super::types::Event;
 
 pub trait EventSink: Send + Sync {
     fn write<'a>(&'a self, event: &'a Event) -> Pin<Box<dyn Future<Output = ()> + Send + 'a>>;
+
+    /// Label of the sink in queue statistics.
+    fn name(&self) -> &str {
+        let name = std::any::type_name::<Self>();
+        name.rsplit("::").next().unwrap_or(name)
+    }
+}
+
+/// Edits every event before subscribers and sinks see it, e.g. to drop content that must
+/// not leave the process.
+pub trait EventFilter: Send + Sync {
+    fn apply(&self, event: &mut Event);
+}
+
+/// Delivery state of one sink's queue.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SinkQueueStats {
+    pub sink: String,
+    /// Events waiting for the sink.
+    pub queued: usize,
+    /// Events dropped because the queue was full.
+    pub dropped: u64,
 }
 
 #[derive(Clone)]
@@ -17,16 +41,84 @@ pub struct EventHub {
 
 struct Inner {
     tx: broadcast::Sender<Event>,
-    sinks: RwLock<Vec<Arc<dyn EventSink>>>,
+    buffer: usize,
+    sinks: RwLock<Vec<Arc<SinkQueue>>>,
+    filters: RwLock<Vec<Arc<dyn EventFilter>>>,
+}
+
+/// Events waiting for one sink, written in order by the sink's own task. When the queue
+/// holds `capacity` events the oldest telemetry event makes room; usage events are billed
+/// from, so they are never dropped and may take the queue past its capacity.
+struct SinkQueue {
+    sink: Arc<dyn EventSink>,
+    capacity: usize,
+    events: Mutex<VecDeque<Event>>,
+    ready: Notify,
+    dropped: AtomicU64,
+}
+
+impl SinkQueue {
+    fn push(&self, event: Event) {
+        {
+            let mut events = self.events.lock().unwrap_or_else(|e| e.into_inner());
+            if events.len() >= self.capacity {
+                match events.iter().position(|queued| !is_billing(queued)) {
+                    Some(oldest) => {
+                        events.remove(oldest);
+                        self.dropped.fetch_add(1, Ordering::Relaxed);
+                    }
+                    None if !is_billing(&event) => {
+                        self.dropped.fetch_add(1, Ordering::Relaxed);
+                        return;
+                    }
+                    None => {}
+                }
+            }
+            events.push_back(event);
+        }
+        self.ready.notify_one();
+    }
+
+    fn pop(&self) -> Option<Event> {
+        self.events
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .pop_front()
+    }
+
+    async fn run(self: Arc<Self>) {
+        loop {
+            match self.pop() {
+                Some(event) => self.sink.write(&event).await,
+                None => self.ready.notified().await,
+            }
+        }
+    }
+
+    fn stats(&self) -> SinkQueueStats {
+        SinkQueueStats {
+            sink: self.sink.name().to_string(),
+            queued: self.events.lock().unwrap_or_else(|e| e.into_inner()).len(),
+            dropped: self.dropped.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Events that carry usage, which billing is computed from.
+fn is_billing(event: &Event) -> bool {
+    matches!(event, Event::Upstream(ev) if ev.usage.is_some())
 }
 
 impl EventHub {
+    /// `buffer` bounds both the subscriber channel and each sink's queue.
     pub fn new(buffer: usize) -> Self {
         let (tx, _) = broadcast::channel(buffer);
         Self {
             inner: Arc::new(Inner {
                 tx,
+                buffer,
                 sinks: RwLock::new(Vec::new()),
+                filters: RwLock::new(Vec::new()),
             }),
         }
     }
@@ -35,18 +127,220 @@ impl EventHub {
         self.inner.tx.subscribe()
     }
 
+    /// Adds a sink fed from its own bounded queue, so a slow sink never holds up `emit`.
     pub async fn add_sink(&self, sink: Arc<dyn EventSink>) {
-        self.inner.sinks.write().await.push(sink);
+        let queue = Arc::new(SinkQueue {
+            sink,
+            capacity: self.inner.buffer.max(1),
+            events: Mutex::new(VecDeque::new()),
+            ready: Notify::new(),
+            dropped: AtomicU64::new(0),
+        });
+        tokio::spawn(queue.clone().run());
+        self.inner.sinks.write().await.push(queue);
     }
 
-    pub async fn emit(&self, event: Event) {
+    pub async fn add_filter(&self, filter: Arc<dyn EventFilter>) {
+        self.inner.filters.write().await.push(filter);
+    }
+
+    pub async fn emit(&self, mut event: Event) {
+        for filter in self.inner.filters.read().await.iter() {
+            filter.apply(&mut event);
+        }
         let _ = self.inner.tx.send(event.clone());
-        let sinks = self.inner.sinks.read().await.clone();
-        for sink in sinks {
-            let event_ref = event.clone();
-            tokio::spawn(async move {
-                sink.write(&event_ref).await;
-            });
+        for queue in self.inner.sinks.read().await.iter() {
+            queue.push(event.clone());
         }
     }
+
+    /// Queue depth and dropped events per sink, in the order sinks were added.
+    pub async fn sink_queues(&self) -> Vec<SinkQueueStats> {
+        self.inner
+            .sinks
+            .read()
+            .await
+            .iter()
+            .map(|queue| queue.stats())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::SystemTime;
+
+    use tokio::sync::Semaphore;
+
+    use super::*;
+    use crate::UsageSummary;
+    use crate::events::{DownstreamEvent, UpstreamEvent};
+
+    struct DropBodies;
+
+    impl EventFilter for DropBodies {
+        fn apply(&self, event: &mut Event) {
+            if let Event::Downstream(ev) = event {
+                ev.request_body = None;
+                ev.response_body = None;
+            }
+        }
+    }
+
+    fn downstream(tag: &str) -> Event {
+        Event::Downstream(DownstreamEvent {
+            trace_id: None,
+            at: SystemTime::now(),
+            user_id: None,
+            user_key_id: None,
+            request_method: "POST".to_string(),
+            request_headers: crate::Headers::new(),
+            request_path: "/v1/messages".to_string(),
+            request_query: None,
+            request_body: None,
+            response_status: Some(200),
+            response_headers: crate::Headers::new(),
+            response_body: None,
+            tags: vec![tag.to_string()],
+            latency_ms: None,
+            client_ip: None,
+            country: None,
+            asn: None,
+        })
+    }
+
+    fn billed(tag: &str) -> Event {
+        Event::Upstream(UpstreamEvent {
+            trace_id: None,
+            at: SystemTime::now(),
+            user_id: None,
+            user_key_id: None,
+            provider: "claude".to_string(),
+            credential_id: None,
+            internal: false,
+            attempt_no: 1,
+            operation: "generate_content".to_string(),
+            request_method: "POST".to_string(),
+            request_headers: crate::Headers::new(),
+            request_host: None,
+            request_path: "/v1/messages".to_string(),
+            request_query: None,
+            request_body: None,
+            response_status: Some(200),
+            response_headers: crate::Headers::new(),
+            response_body: None,
+            usage: Some(UsageSummary::default()),
+            error_kind: None,
+            error_message: None,
+            transport_kind: None,
+            tags: vec![tag.to_string()],
+            anthropic_betas: Vec::new(),
+            model: None,
+            latency_ms: None,
+            experiment: None,
+            experiment_arm: None,
+            transform: None,
+        })
+    }
+
+    /// Records each event's tag, then waits for a permit before taking the next one.
+    struct GatedSink {
+        written: Mutex<Vec<String>>,
+        gate: Semaphore,
+    }
+
+    impl GatedSink {
+        fn written(&self) -> Vec<String> {
+            self.written.lock().unwrap().clone()
+        }
+    }
+
+    impl EventSink for GatedSink {
+        fn write<'a>(&'a self, event: &'a Event) -> Pin<Box<dyn Future<Output = ()> + Send + 'a>> {
+            Box::pin(async move {
+                let tag = match event {
+                    Event::Downstream(ev) => ev.tags[0].clone(),
+                    Event::Upstream(ev) => ev.tags[0].clone(),
+                    _ => String::new(),
+                };
+                self.written.lock().unwrap().push(tag);
+                self.gate.acquire().await.unwrap().forget();
+            })
+        }
+    }
+
+    async fn wait_for(sink: &GatedSink, count: usize) {
+        while sink.written().len() < count {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn full_queues_shed_telemetry_but_keep_usage() {
+        let hub = EventHub::new(2);
+        let sink = Arc::new(GatedSink {
+            written: Mutex::new(Vec::new()),
+            gate: Semaphore::new(0),
+        });
+        hub.add_sink(sink.clone()).await;
+
+        hub.emit(downstream("t1")).await;
+        wait_for(&sink, 1).await;
+        for event in [
+            downstream("t2"),
+            downstream("t3"),
+            downstream("t4"),
+            billed("u5"),
+            billed("u6"),
+            downstream("t7"),
+        ] {
+            hub.emit(event).await;
+        }
+        assert_eq!(
+            hub.sink_queues().await,
+            [SinkQueueStats {
+                sink: "GatedSink".to_string(),
+                queued: 2,
+                dropped: 4,
+            }]
+        );
+
+        sink.gate.add_permits(8);
+        wait_for(&sink, 3).await;
+        assert_eq!(sink.written(), ["t1", "u5", "u6"]);
+        let stats = &hub.sink_queues().await[0];
+        assert_eq!((stats.queued, stats.dropped), (0, 4));
+    }
+
+    #[tokio::test]
+    async fn filters_run_before_subscribers_see_the_event() {
+        let hub = EventHub::new(8);
+        hub.add_filter(Arc::new(DropBodies)).await;
+        let mut rx = hub.subscribe();
+        hub.emit(Event::Downstream(DownstreamEvent {
+            trace_id: None,
+            at: SystemTime::now(),
+            user_id: Some(1),
+            user_key_id: Some(2),
+            request_method: "POST".to_string(),
+            request_headers: crate::Headers::new(),
+            request_path: "/v1/messages".to_string(),
+            request_query: None,
+            request_body: Some(b"secret".to_vec()),
+            response_status: Some(200),
+            response_headers: crate::Headers::new(),
+            response_body: Some(b"answer".to_vec()),
+            tags: Vec::new(),
+            latency_ms: None,
+            client_ip: None,
+            country: None,
+            asn: None,
+        }))
+        .await;
+        let Ok(Event::Downstream(ev)) = rx.recv().await else {
+            panic!("expected a downstream event");
+        };
+        assert!(ev.request_body.is_none() && ev.response_body.is_none());
+        assert_eq!(ev.user_key_id, Some(2));
+    }
 }
@@ -2,9 +2,9 @@ mod hub;
 mod terminal_sink;
 mod types;
 
-pub use hub::{EventHub, EventSink};
+pub use hub::{EventFilter, EventHub, EventSink, SinkQueueStats};
 pub use terminal_sink::TerminalEventSink;
 pub use types::{
     DownstreamEvent, Event, ModelUnavailableEndEvent, ModelUnavailableStartEvent, OperationalEvent,
-    UnavailableEndEvent, UnavailableStartEvent, UpstreamEvent,
+    UnavailableEndEvent, UnavailableStartEvent, UpstreamEvent, UserKeyAutoDisabledEvent,
 };
@@ -6,7 +6,7 @@ use super::{Event, EventSink};
 /// Best-effort terminal sink for structured events.
 ///
 /// This is intentionally lightweight and does not depend on `tracing`.
-/// It prints one JSON line per event.
+/// It writes one JSON line per event through the process logger (stderr and/or the log file).
 pub struct TerminalEventSink;
 
 impl TerminalEventSink {
@@ -25,14 +25,9 @@ impl EventSink for TerminalEventSink {
     fn write<'a>(&'a self, event: &'a Event) -> Pin<Box<dyn Future<Output = ()> + Send + 'a>> {
         Box::pin(async move {
             // Avoid panicking in sinks.
-            match event.to_log_json() {
-                Ok(line) => {
-                    // Use stderr to keep stdout clean for potential streaming responses.
-                    eprintln!("{line}");
-                }
-                Err(err) => {
-                    eprintln!("{{\"event\":\"event_serialize_error\",\"error\":\"{err}\"}}");
-                }
+            match event.to_log_value() {
+                Ok(value) => gproxy_common::log::event(&value),
+                Err(err) => gproxy_common::log_error!("event", "serialize event: {err}"),
             }
         })
     }
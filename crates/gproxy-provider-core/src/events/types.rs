@@ -6,6 +6,7 @@ use serde_json::Value as JsonValue;
 use crate::provider::UpstreamTransportErrorKind;
 use crate::{CredentialId, Headers, UnavailableReason, UsageSummary};
 
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Event {
     Downstream(DownstreamEvent),
@@ -27,6 +28,20 @@ pub struct DownstreamEvent {
     pub response_status: Option<u16>,
     pub response_headers: Headers,
     pub response_body: Option<Vec<u8>>,
+    /// Caller-supplied request tags (`x-gproxy-tags` / body `metadata.tags`).
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Time from request arrival until the response finished (or failed).
+    #[serde(default)]
+    pub latency_ms: Option<u64>,
+    #[serde(default)]
+    pub client_ip: Option<String>,
+    /// ISO country code from the edge or the local GeoIP database.
+    #[serde(default)]
+    pub country: Option<String>,
+    /// Autonomous system number from the local GeoIP database.
+    #[serde(default)]
+    pub asn: Option<u32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -42,6 +57,9 @@ pub struct UpstreamEvent {
     pub operation: String,
     pub request_method: String,
     pub request_headers: Headers,
+    /// Host the request went to; telling apart the endpoints of providers that fail over.
+    #[serde(default)]
+    pub request_host: Option<String>,
     pub request_path: String,
     pub request_query: Option<String>,
     pub request_body: Option<Vec<u8>>,
@@ -52,6 +70,26 @@ pub struct UpstreamEvent {
     pub error_kind: Option<String>,
     pub error_message: Option<String>,
     pub transport_kind: Option<UpstreamTransportErrorKind>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// `anthropic-beta` flags sent on this upstream request.
+    #[serde(default)]
+    pub anthropic_betas: Vec<String>,
+    /// Model requested by the caller, when the operation names one.
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Time from downstream request arrival until this attempt finished.
+    #[serde(default)]
+    pub latency_ms: Option<u64>,
+    /// A/B experiment the request was routed through, with the assigned arm.
+    #[serde(default)]
+    pub experiment: Option<String>,
+    #[serde(default)]
+    pub experiment_arm: Option<String>,
+    /// `user_proto->provider_proto` pair the request was converted through, e.g.
+    /// `openai_chat->claude`; the same protocol twice when it went out as received.
+    #[serde(default)]
+    pub transform: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -60,6 +98,7 @@ pub enum OperationalEvent {
     UnavailableEnd(UnavailableEndEvent),
     ModelUnavailableStart(ModelUnavailableStartEvent),
     ModelUnavailableEnd(ModelUnavailableEndEvent),
+    UserKeyAutoDisabled(UserKeyAutoDisabledEvent),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -92,6 +131,20 @@ pub struct ModelUnavailableEndEvent {
     pub model: String,
 }
 
+/// A user key was disabled by an abuse rule; the fields are the evidence that tripped it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserKeyAutoDisabledEvent {
+    pub at: SystemTime,
+    pub user_id: i64,
+    pub user_key_id: i64,
+    /// `distinct_ips` or `blocked_country`.
+    pub rule: String,
+    /// Requests seen from the key in the current minute.
+    pub requests: u64,
+    pub client_ips: Vec<String>,
+    pub country: Option<String>,
+}
+
 impl Event {
     pub fn to_log_value(&self) -> Result<JsonValue, serde_json::Error> {
         let mut value = serde_json::to_value(self)?;
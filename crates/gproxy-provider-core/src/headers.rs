@@ -1,31 +1,327 @@
-pub type Headers = Vec<(String, String)>;
+//! HTTP headers as an ordered multimap. Names keep the case they were set with and are
+//! compared ASCII case-insensitively; repeated names are kept in order. Serialized as a
+//! list of `[name, value]` pairs, the form events have always been stored in.
+//!
+//! Lookups go through an index of entry positions by folded name. `Headers` dereferences
+//! to its pairs read-only; every change goes through the methods below so the index
+//! stays in step.
 
-pub fn header_set(headers: &mut Headers, name: impl Into<String>, value: impl Into<String>) {
-    let name = name.into();
-    let value = value.into();
-    let key = name.to_ascii_lowercase();
-    if let Some((_, v)) = headers
-        .iter_mut()
-        .find(|(k, _)| k.to_ascii_lowercase() == key)
-    {
-        *v = value;
-        return;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Deref;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+#[derive(Clone, Default)]
+pub struct Headers {
+    entries: Vec<(String, String)>,
+    /// Positions in `entries` by ASCII-lowercased name, in order.
+    index: HashMap<String, Vec<usize>>,
+}
+
+/// `name` ASCII-lowercased; borrowed when it already is, as most names passed in are.
+fn folded(name: &str) -> Cow<'_, str> {
+    if name.bytes().any(|b| b.is_ascii_uppercase()) {
+        Cow::Owned(name.to_ascii_lowercase())
+    } else {
+        Cow::Borrowed(name)
+    }
+}
+
+impl Headers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            entries: Vec::with_capacity(capacity),
+            index: HashMap::with_capacity(capacity),
+        }
+    }
+
+    fn positions(&self, name: &str) -> &[usize] {
+        self.index
+            .get(folded(name).as_ref())
+            .map_or(&[], Vec::as_slice)
+    }
+
+    fn reindex(&mut self) {
+        self.index.clear();
+        for (pos, (name, _)) in self.entries.iter().enumerate() {
+            self.index
+                .entry(folded(name).into_owned())
+                .or_default()
+                .push(pos);
+        }
+    }
+
+    /// First value of `name`.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        let pos = *self.positions(name).first()?;
+        Some(self.entries[pos].1.as_str())
+    }
+
+    /// Every value of `name`, in order.
+    pub fn get_all<'a>(&'a self, name: &str) -> impl Iterator<Item = &'a str> + 'a {
+        self.positions(name)
+            .iter()
+            .map(|&pos| self.entries[pos].1.as_str())
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        !self.positions(name).is_empty()
+    }
+
+    /// Sets the first `name` to `value`, keeping its position and any later repeats, or
+    /// appends it.
+    pub fn set(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        let name = name.into();
+        match self.positions(&name).first() {
+            Some(&pos) => self.entries[pos].1 = value.into(),
+            None => self.append(name, value),
+        }
+    }
+
+    /// Replaces every `name` with one `value` at the first one's position, or appends it.
+    /// Returns the first value replaced.
+    pub fn insert(&mut self, name: impl Into<String>, value: impl Into<String>) -> Option<String> {
+        let name = name.into();
+        let (first, repeated) = match self.positions(&name) {
+            [] => {
+                self.append(name, value);
+                return None;
+            }
+            positions => (positions[0], positions.len() > 1),
+        };
+        let previous = std::mem::replace(&mut self.entries[first].1, value.into());
+        if repeated {
+            let first_name = folded(&name).into_owned();
+            let mut seen = false;
+            self.entries.retain(|(k, _)| {
+                if folded(k) != first_name {
+                    return true;
+                }
+                !std::mem::replace(&mut seen, true)
+            });
+            self.reindex();
+        }
+        Some(previous)
+    }
+
+    /// Adds a value, keeping existing ones.
+    pub fn append(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        let name = name.into();
+        self.index
+            .entry(folded(&name).into_owned())
+            .or_default()
+            .push(self.entries.len());
+        self.entries.push((name, value.into()));
+    }
+
+    /// Removes the first `name` and returns its value.
+    pub fn remove(&mut self, name: &str) -> Option<String> {
+        let pos = *self.positions(name).first()?;
+        let (_, value) = self.entries.remove(pos);
+        self.reindex();
+        Some(value)
+    }
+
+    /// Removes every `name`.
+    pub fn remove_all(&mut self, name: &str) {
+        if self.contains(name) {
+            self.retain(|k, _| !k.eq_ignore_ascii_case(name));
+        }
+    }
+
+    /// Keeps the entries `keep` accepts, in order.
+    pub fn retain(&mut self, mut keep: impl FnMut(&str, &str) -> bool) {
+        self.entries.retain(|(k, v)| keep(k, v));
+        self.reindex();
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, (String, String)> {
+        self.entries.iter()
+    }
+
+    /// Every entry with its value editable; names stay fixed so lookups keep working.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&str, &mut String)> {
+        self.entries.iter_mut().map(|(k, v)| (k.as_str(), v))
+    }
+
+    pub fn into_vec(self) -> Vec<(String, String)> {
+        self.entries
+    }
+}
+
+impl Deref for Headers {
+    type Target = [(String, String)];
+
+    fn deref(&self) -> &Self::Target {
+        &self.entries
+    }
+}
+
+impl fmt::Debug for Headers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(&self.entries).finish()
+    }
+}
+
+impl PartialEq for Headers {
+    fn eq(&self, other: &Self) -> bool {
+        self.entries == other.entries
+    }
+}
+
+impl Eq for Headers {}
+
+impl Serialize for Headers {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.entries.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Headers {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::<(String, String)>::deserialize(deserializer).map(Self::from)
+    }
+}
+
+impl From<Vec<(String, String)>> for Headers {
+    fn from(entries: Vec<(String, String)>) -> Self {
+        let mut headers = Self {
+            entries,
+            index: HashMap::new(),
+        };
+        headers.reindex();
+        headers
+    }
+}
+
+impl From<Headers> for Vec<(String, String)> {
+    fn from(headers: Headers) -> Self {
+        headers.entries
+    }
+}
+
+impl PartialEq<Vec<(String, String)>> for Headers {
+    fn eq(&self, other: &Vec<(String, String)>) -> bool {
+        &self.entries == other
+    }
+}
+
+impl FromIterator<(String, String)> for Headers {
+    fn from_iter<I: IntoIterator<Item = (String, String)>>(iter: I) -> Self {
+        Self::from(iter.into_iter().collect::<Vec<_>>())
+    }
+}
+
+impl Extend<(String, String)> for Headers {
+    fn extend<I: IntoIterator<Item = (String, String)>>(&mut self, iter: I) {
+        for (name, value) in iter {
+            self.append(name, value);
+        }
     }
-    headers.push((name, value));
+}
+
+impl IntoIterator for Headers {
+    type Item = (String, String);
+    type IntoIter = std::vec::IntoIter<(String, String)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Headers {
+    type Item = &'a (String, String);
+    type IntoIter = std::slice::Iter<'a, (String, String)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter()
+    }
+}
+
+pub fn header_set(headers: &mut Headers, name: impl Into<String>, value: impl Into<String>) {
+    headers.set(name, value);
 }
 
 pub fn header_get<'a>(headers: &'a Headers, name: &str) -> Option<&'a str> {
-    let key = name.to_ascii_lowercase();
-    headers
-        .iter()
-        .find(|(k, _)| k.to_ascii_lowercase() == key)
-        .map(|(_, v)| v.as_str())
+    headers.get(name)
 }
 
 pub fn header_remove(headers: &mut Headers, name: &str) -> Option<String> {
-    let key = name.to_ascii_lowercase();
-    let idx = headers
-        .iter()
-        .position(|(k, _)| k.to_ascii_lowercase() == key)?;
-    Some(headers.remove(idx).1)
+    headers.remove(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookups_ignore_case_and_keep_repeats() {
+        let mut headers = Headers::new();
+        headers.append("Set-Cookie", "a=1");
+        headers.append("content-type", "text/plain");
+        headers.append("set-cookie", "b=2");
+
+        assert_eq!(headers.get("SET-COOKIE"), Some("a=1"));
+        assert_eq!(
+            headers.get_all("set-cookie").collect::<Vec<_>>(),
+            ["a=1", "b=2"]
+        );
+
+        headers.set("Content-Type", "application/json");
+        assert_eq!(
+            headers[1],
+            ("content-type".into(), "application/json".into())
+        );
+        assert_eq!(headers.remove("set-cookie").as_deref(), Some("a=1"));
+        assert_eq!(headers.get("Set-Cookie"), Some("b=2"));
+        headers.remove_all("SET-COOKIE");
+        assert!(!headers.contains("set-cookie"));
+        assert_eq!(headers.len(), 1);
+    }
+
+    #[test]
+    fn edits_keep_the_index_in_step() {
+        let mut headers: Headers = vec![
+            ("Accept".to_string(), "a".to_string()),
+            ("x-trace".to_string(), "1".to_string()),
+            ("accept".to_string(), "b".to_string()),
+            ("x-trace".to_string(), "2".to_string()),
+        ]
+        .into();
+
+        assert_eq!(headers.insert("ACCEPT", "c").as_deref(), Some("a"));
+        assert_eq!(headers.get_all("accept").collect::<Vec<_>>(), ["c"]);
+        assert_eq!(headers.get_all("x-trace").collect::<Vec<_>>(), ["1", "2"]);
+        assert_eq!(headers.insert("x-new", "n"), None);
+
+        headers.retain(|name, value| name != "x-trace" || value != "1");
+        assert_eq!(headers.get("x-trace"), Some("2"));
+        assert_eq!(headers.get("x-new"), Some("n"));
+
+        for (name, value) in headers.iter_mut() {
+            if name == "x-trace" {
+                value.push('!');
+            }
+        }
+        headers.extend([("X-Trace".to_string(), "3".to_string())]);
+        assert_eq!(headers.get_all("x-trace").collect::<Vec<_>>(), ["2!", "3"]);
+        assert_eq!(
+            headers.iter().map(|(k, _)| k.as_str()).collect::<Vec<_>>(),
+            ["Accept", "x-trace", "x-new", "X-Trace"]
+        );
+    }
+
+    #[test]
+    fn serializes_as_pairs() {
+        let headers: Headers = vec![("x-a".to_string(), "1".to_string())].into();
+        let json = serde_json::to_string(&headers).unwrap();
+        assert_eq!(json, r#"[["x-a","1"]]"#);
+        assert_eq!(serde_json::from_str::<Headers>(&json).unwrap(), headers);
+    }
 }
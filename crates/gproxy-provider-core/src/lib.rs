@@ -13,35 +13,52 @@ pub mod provider;
 pub mod registry;
 
 pub use config::{
-    ClaudeCodePreludeText, CountTokensMode, DispatchRule, DispatchTable, ModelTable, OperationKind,
-    ProviderConfig,
+    ANTHROPIC_BETA_HEADER, ANTHROPIC_BETA_KEY, AnthropicBetaPolicy, BODY_SAMPLING_KEY,
+    BodySampling, CANDIDATE_FAN_OUT_KEY, CandidateFanOutPolicy, ClaudeCodePreludeText,
+    CountTokensMode, DISALLOW_KEY, DISPATCH_KEY, DisallowRule, DispatchOverrides, DispatchRule,
+    DispatchTable, EGRESS_KEY, EgressPolicy, FAILURE_RULES_KEY, FailureAction, FailureMatch,
+    FailurePolicy, HEADER_POLICY_KEY, HeaderPolicy, IpFamily, MAINTENANCE_KEY, MODEL_DISPATCH_KEY,
+    MaintenanceSchedule, MaintenanceWindow, ModelDispatchRule, ModelTable, OperationKind,
+    PARSING_KEY, POST_PROCESS_KEY, ParsingMode, PostProcessPolicy, ProviderConfig,
+    ProviderPolicies, ProxyRotation, RAW_PASSTHROUGH_KEY, RawPassthroughPolicy, SEMANTIC_CACHE_KEY,
+    STREAM_FAILOVER_KEY, SemanticCacheSettings, StreamFailover, TIMEOUTS_KEY, TLS_KEY, TextWindow,
+    TimeoutPolicy, TlsPolicy, UpstreamTimeouts, header_betas,
 };
 pub use credential::{
-    AcquireError, Credential, CredentialId, CredentialPool, CredentialState, UnavailableReason,
+    AcquireError, Credential, CredentialId, CredentialPool, CredentialState, PoolAvailability,
+    UnavailableReason,
 };
 pub use errors::{ProviderError, ProviderResult};
 pub use events::{
-    DownstreamEvent, Event, EventHub, EventSink, ModelUnavailableEndEvent,
-    ModelUnavailableStartEvent, OperationalEvent, TerminalEventSink, UnavailableEndEvent,
-    UnavailableStartEvent, UpstreamEvent,
+    DownstreamEvent, Event, EventFilter, EventHub, EventSink, ModelUnavailableEndEvent,
+    ModelUnavailableStartEvent, OperationalEvent, SinkQueueStats, TerminalEventSink,
+    UnavailableEndEvent, UnavailableStartEvent, UpstreamEvent, UserKeyAutoDisabledEvent,
 };
 pub use headers::{Headers, header_get, header_remove, header_set};
 pub use provider::{
-    AuthRetryAction, HttpMethod, OAuthCallbackRequest, OAuthCallbackResult, OAuthCredential,
-    OAuthStartRequest, UpstreamBody, UpstreamCtx, UpstreamHttpRequest, UpstreamHttpResponse,
-    UpstreamProvider,
+    AuthRetryAction, CallerInfo, HttpMethod, OAuthCallbackRequest, OAuthCallbackResult,
+    OAuthCredential, OAuthStartRequest, RawPassthroughRequest, UpstreamBody, UpstreamCtx,
+    UpstreamHttpRequest, UpstreamHttpResponse, UpstreamProvider,
 };
 pub use registry::ProviderRegistry;
 
 // Re-export the protocol/transform typed enums from gproxy-transform.
 pub use gproxy_transform::middleware::{
-    CountTokensRequest, CountTokensResponse, GenerateContentRequest, GenerateContentResponse,
-    MemoryTraceSummarizeRequest, MemoryTraceSummarizeResponse, ModelGetRequest, ModelGetResponse,
-    ModelListRequest, ModelListResponse, Op, Proto, Request, Response, ResponseCancelRequest,
-    ResponseCancelResponse, ResponseCompactRequest, ResponseCompactResponse, ResponseDeleteRequest,
-    ResponseDeleteResponse, ResponseGetRequest, ResponseGetResponse, ResponseListInputItemsRequest,
-    ResponseListInputItemsResponse, StreamEvent, StreamFormat, TransformContext, TransformError,
-    stream_format,
+    BatchGenerateContentRequest, BatchGenerateContentResponse, BatchGetRequest, BatchGetResponse,
+    ConversationCreateRequest, ConversationCreateResponse, ConversationDeleteRequest,
+    ConversationDeleteResponse, ConversationGetRequest, ConversationGetResponse,
+    ConversationItemCreateRequest, ConversationItemCreateResponse, ConversationItemDeleteRequest,
+    ConversationItemDeleteResponse, ConversationItemGetRequest, ConversationItemGetResponse,
+    ConversationItemListRequest, ConversationItemListResponse, ConversationUpdateRequest,
+    ConversationUpdateResponse, CountTokensRequest, CountTokensResponse, FileDeleteRequest,
+    FileDeleteResponse, FileGetRequest, FileGetResponse, FileListRequest, FileListResponse,
+    GenerateContentRequest, GenerateContentResponse, MemoryTraceSummarizeRequest,
+    MemoryTraceSummarizeResponse, ModelGetRequest, ModelGetResponse, ModelListRequest,
+    ModelListResponse, Op, Proto, Request, Response, ResponseCancelRequest, ResponseCancelResponse,
+    ResponseCompactRequest, ResponseCompactResponse, ResponseDeleteRequest, ResponseDeleteResponse,
+    ResponseGetRequest, ResponseGetResponse, ResponseListInputItemsRequest,
+    ResponseListInputItemsResponse, ResponseListRequest, ResponseListResponse, StreamEvent,
+    StreamFormat, TransformContext, TransformError, stream_format,
 };
 
 // Re-export usage helpers used by the middleware/engine layer.
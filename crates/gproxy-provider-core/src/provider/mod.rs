@@ -2,10 +2,12 @@ use async_trait::async_trait;
 use bytes::Bytes;
 use std::future::Future;
 use std::pin::Pin;
+use std::sync::Arc;
 use std::time::Duration;
 
 use gproxy_protocol::{claude, gemini, openai};
 
+use crate::config::{FailureAction, FailureMatch};
 use crate::headers::{Headers, header_get};
 use crate::{
     Credential, DispatchTable, Op, Proto, ProviderConfig, ProviderError, ProviderResult, Request,
@@ -14,6 +16,9 @@ use crate::{
 
 pub type ByteStream = tokio::sync::mpsc::Receiver<Bytes>;
 
+/// Maps the `data` of one upstream SSE event to the SSE frames core decodes in its place.
+pub type InternalEventUnwrap = fn(&str) -> Vec<Bytes>;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum HttpMethod {
     Get,
@@ -91,6 +96,22 @@ pub struct OAuthCallbackRequest {
     pub headers: Headers,
 }
 
+/// Downstream request on a `/{provider}/...` path without a typed route, forwarded
+/// verbatim when the provider enables raw passthrough.
+///
+/// This is *not* part of protocol transform; it is a provider internal ability.
+#[derive(Debug, Clone)]
+pub struct RawPassthroughRequest {
+    pub method: HttpMethod,
+    /// Path below the provider prefix, starting with `/` (e.g. `/v1/batches`).
+    pub path: String,
+    /// Query string with downstream auth params already stripped.
+    pub query: Option<String>,
+    /// Downstream headers with auth, hop-by-hop and framing headers already stripped.
+    pub headers: Headers,
+    pub body: Option<Bytes>,
+}
+
 #[derive(Debug, Clone)]
 pub struct OAuthCredential {
     pub name: Option<String>,
@@ -104,6 +125,16 @@ pub struct OAuthCallbackResult {
     pub credential: Option<OAuthCredential>,
 }
 
+/// The user key a request is made for, as far as providers may render it into what they
+/// send upstream.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CallerInfo {
+    pub key_label: Option<String>,
+    pub org_name: Option<String>,
+    /// Claude Code prelude template set on the key; replaces the provider's.
+    pub prelude_template: Option<String>,
+}
+
 #[derive(Debug, Clone)]
 pub struct UpstreamCtx {
     pub trace_id: Option<String>,
@@ -116,12 +147,19 @@ pub struct UpstreamCtx {
     pub op: Op,
     pub internal: bool,
     pub attempt_no: u32,
+    pub caller: Arc<CallerInfo>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub enum UpstreamTransportErrorKind {
+    /// The whole exchange took longer than the total timeout.
     Timeout,
+    /// No body bytes arrived within the idle timeout.
     ReadTimeout,
+    /// The connection could not be established within the connect timeout.
+    ConnectTimeout,
+    /// The connection was up but response headers did not arrive in time.
+    FirstByteTimeout,
     Connect,
     Dns,
     Tls,
@@ -154,6 +192,11 @@ pub enum AuthRetryAction {
     None,
     RetrySame,
     UpdateCredential(Box<Credential>),
+    /// Retry the same credential against another upstream endpoint the provider picked.
+    ///
+    /// Unlike `RetrySame` this may be returned on every failure of one request, so the
+    /// provider must stop returning it once it runs out of endpoints.
+    Failover,
 }
 
 const RATE_LIMIT_FALLBACK_SECS: u64 = 30;
@@ -193,6 +236,8 @@ pub fn default_decide_unavailable(failure: &UpstreamFailure) -> Option<Unavailab
         UpstreamFailure::Transport { kind, .. } => match kind {
             UpstreamTransportErrorKind::Timeout
             | UpstreamTransportErrorKind::ReadTimeout
+            | UpstreamTransportErrorKind::ConnectTimeout
+            | UpstreamTransportErrorKind::FirstByteTimeout
             | UpstreamTransportErrorKind::Connect
             | UpstreamTransportErrorKind::Dns
             | UpstreamTransportErrorKind::Tls => Some(UnavailableDecision {
@@ -204,6 +249,51 @@ pub fn default_decide_unavailable(failure: &UpstreamFailure) -> Option<Unavailab
     }
 }
 
+/// The cooldown for an HTTP failure matched by one of the provider's `failure_rules`.
+/// `Retry` yields a zero-length decision: try again without cooling the credential down.
+pub fn decide_unavailable_with_failure_rule(
+    rule: FailureMatch,
+    failure: &UpstreamFailure,
+) -> Option<UnavailableDecision> {
+    let (status, headers) = match failure {
+        UpstreamFailure::Http {
+            status, headers, ..
+        } => (*status, Some(headers)),
+        UpstreamFailure::Transport { .. } => (0, None),
+    };
+    let (duration, reason) = match rule.action {
+        FailureAction::PassThrough => return None,
+        FailureAction::Retry => (Duration::ZERO, UnavailableReason::Unknown),
+        FailureAction::Cooldown if status == 429 => (
+            rule.cooldown
+                .or_else(|| headers.and_then(parse_retry_after))
+                .unwrap_or(Duration::from_secs(RATE_LIMIT_FALLBACK_SECS)),
+            UnavailableReason::RateLimit,
+        ),
+        FailureAction::Cooldown => (
+            rule.cooldown
+                .unwrap_or(Duration::from_secs(SHORT_COOLDOWN_SECS)),
+            UnavailableReason::Upstream5xx,
+        ),
+        FailureAction::RateLimit => (
+            rule.cooldown
+                .or_else(|| headers.and_then(parse_retry_after))
+                .unwrap_or(Duration::from_secs(RATE_LIMIT_FALLBACK_SECS)),
+            UnavailableReason::RateLimit,
+        ),
+        FailureAction::ModelUnavailable => (
+            rule.cooldown
+                .unwrap_or(Duration::from_secs(SHORT_COOLDOWN_SECS)),
+            UnavailableReason::ModelDisallow,
+        ),
+        FailureAction::DisableCredential => (
+            rule.cooldown.unwrap_or_else(auth_invalid_duration),
+            UnavailableReason::AuthInvalid,
+        ),
+    };
+    Some(UnavailableDecision { duration, reason })
+}
+
 fn parse_retry_after(headers: &Headers) -> Option<Duration> {
     let value = header_get(headers, "retry-after")?;
     let value = value.trim();
@@ -229,6 +319,9 @@ type GeminiStreamGenerateContentRequest =
 type GeminiCountTokensRequest = gemini::count_tokens::request::CountTokensRequest;
 type GeminiModelsListRequest = gemini::list_models::request::ListModelsRequest;
 type GeminiModelsGetRequest = gemini::get_model::request::GetModelRequest;
+type GeminiBatchGenerateContentRequest =
+    gemini::batch_generate_content::request::BatchGenerateContentRequest;
+type GeminiBatchGetRequest = gemini::get_batch::request::GetBatchRequest;
 
 type OpenAIChatCompletionRequest =
     openai::create_chat_completions::request::CreateChatCompletionRequest;
@@ -239,6 +332,21 @@ type OpenAIResponseCancelRequest = openai::cancel_response::request::CancelRespo
 type OpenAIResponseListInputItemsRequest = openai::list_input_items::request::ListInputItemsRequest;
 type OpenAIResponseCompactRequest = openai::compact_response::request::CompactResponseRequest;
 type OpenAIMemoryTraceSummarizeRequest = openai::trace_summarize::request::TraceSummarizeRequest;
+type OpenAIResponseListRequest = openai::list_responses::request::ListResponsesRequest;
+type OpenAIConversationCreateRequest = openai::conversations::request::CreateConversationRequest;
+type OpenAIConversationGetRequest = openai::conversations::request::GetConversationRequest;
+type OpenAIConversationUpdateRequest = openai::conversations::request::UpdateConversationRequest;
+type OpenAIConversationDeleteRequest = openai::conversations::request::DeleteConversationRequest;
+type OpenAIConversationItemListRequest =
+    openai::conversations::request::ListConversationItemsRequest;
+type OpenAIConversationItemCreateRequest =
+    openai::conversations::request::CreateConversationItemsRequest;
+type OpenAIConversationItemGetRequest = openai::conversations::request::GetConversationItemRequest;
+type OpenAIConversationItemDeleteRequest =
+    openai::conversations::request::DeleteConversationItemRequest;
+type OpenAIFileListRequest = openai::files::request::ListFilesRequest;
+type OpenAIFileGetRequest = openai::files::request::GetFileRequest;
+type OpenAIFileDeleteRequest = openai::files::request::DeleteFileRequest;
 type OpenAIInputTokensRequest = openai::count_tokens::request::InputTokenCountRequest;
 type OpenAIModelsListRequest = openai::list_models::request::ListModelsRequest;
 type OpenAIModelsGetRequest = openai::get_model::request::GetModelRequest;
@@ -347,6 +455,26 @@ pub trait UpstreamProvider: Send + Sync {
         Err(ProviderError::Unsupported("gemini.models_get"))
     }
 
+    async fn build_gemini_batch_generate(
+        &self,
+        _ctx: &UpstreamCtx,
+        _config: &ProviderConfig,
+        _credential: &Credential,
+        _req: &GeminiBatchGenerateContentRequest,
+    ) -> ProviderResult<UpstreamHttpRequest> {
+        Err(ProviderError::Unsupported("gemini.batch_generate_content"))
+    }
+
+    async fn build_gemini_batch_get(
+        &self,
+        _ctx: &UpstreamCtx,
+        _config: &ProviderConfig,
+        _credential: &Credential,
+        _req: &GeminiBatchGetRequest,
+    ) -> ProviderResult<UpstreamHttpRequest> {
+        Err(ProviderError::Unsupported("gemini.batches_get"))
+    }
+
     async fn build_openai_chat(
         &self,
         _ctx: &UpstreamCtx,
@@ -431,6 +559,130 @@ pub trait UpstreamProvider: Send + Sync {
         ))
     }
 
+    async fn build_openai_response_list(
+        &self,
+        _ctx: &UpstreamCtx,
+        _config: &ProviderConfig,
+        _credential: &Credential,
+        _req: &OpenAIResponseListRequest,
+    ) -> ProviderResult<UpstreamHttpRequest> {
+        Err(ProviderError::Unsupported("openai.responses_list"))
+    }
+
+    async fn build_openai_conversation_create(
+        &self,
+        _ctx: &UpstreamCtx,
+        _config: &ProviderConfig,
+        _credential: &Credential,
+        _req: &OpenAIConversationCreateRequest,
+    ) -> ProviderResult<UpstreamHttpRequest> {
+        Err(ProviderError::Unsupported("openai.conversations_create"))
+    }
+
+    async fn build_openai_conversation_get(
+        &self,
+        _ctx: &UpstreamCtx,
+        _config: &ProviderConfig,
+        _credential: &Credential,
+        _req: &OpenAIConversationGetRequest,
+    ) -> ProviderResult<UpstreamHttpRequest> {
+        Err(ProviderError::Unsupported("openai.conversations_get"))
+    }
+
+    async fn build_openai_conversation_update(
+        &self,
+        _ctx: &UpstreamCtx,
+        _config: &ProviderConfig,
+        _credential: &Credential,
+        _req: &OpenAIConversationUpdateRequest,
+    ) -> ProviderResult<UpstreamHttpRequest> {
+        Err(ProviderError::Unsupported("openai.conversations_update"))
+    }
+
+    async fn build_openai_conversation_delete(
+        &self,
+        _ctx: &UpstreamCtx,
+        _config: &ProviderConfig,
+        _credential: &Credential,
+        _req: &OpenAIConversationDeleteRequest,
+    ) -> ProviderResult<UpstreamHttpRequest> {
+        Err(ProviderError::Unsupported("openai.conversations_delete"))
+    }
+
+    async fn build_openai_conversation_item_list(
+        &self,
+        _ctx: &UpstreamCtx,
+        _config: &ProviderConfig,
+        _credential: &Credential,
+        _req: &OpenAIConversationItemListRequest,
+    ) -> ProviderResult<UpstreamHttpRequest> {
+        Err(ProviderError::Unsupported("openai.conversation_items_list"))
+    }
+
+    async fn build_openai_conversation_item_create(
+        &self,
+        _ctx: &UpstreamCtx,
+        _config: &ProviderConfig,
+        _credential: &Credential,
+        _req: &OpenAIConversationItemCreateRequest,
+    ) -> ProviderResult<UpstreamHttpRequest> {
+        Err(ProviderError::Unsupported(
+            "openai.conversation_items_create",
+        ))
+    }
+
+    async fn build_openai_conversation_item_get(
+        &self,
+        _ctx: &UpstreamCtx,
+        _config: &ProviderConfig,
+        _credential: &Credential,
+        _req: &OpenAIConversationItemGetRequest,
+    ) -> ProviderResult<UpstreamHttpRequest> {
+        Err(ProviderError::Unsupported("openai.conversation_items_get"))
+    }
+
+    async fn build_openai_conversation_item_delete(
+        &self,
+        _ctx: &UpstreamCtx,
+        _config: &ProviderConfig,
+        _credential: &Credential,
+        _req: &OpenAIConversationItemDeleteRequest,
+    ) -> ProviderResult<UpstreamHttpRequest> {
+        Err(ProviderError::Unsupported(
+            "openai.conversation_items_delete",
+        ))
+    }
+
+    async fn build_openai_file_list(
+        &self,
+        _ctx: &UpstreamCtx,
+        _config: &ProviderConfig,
+        _credential: &Credential,
+        _req: &OpenAIFileListRequest,
+    ) -> ProviderResult<UpstreamHttpRequest> {
+        Err(ProviderError::Unsupported("openai.files_list"))
+    }
+
+    async fn build_openai_file_get(
+        &self,
+        _ctx: &UpstreamCtx,
+        _config: &ProviderConfig,
+        _credential: &Credential,
+        _req: &OpenAIFileGetRequest,
+    ) -> ProviderResult<UpstreamHttpRequest> {
+        Err(ProviderError::Unsupported("openai.files_get"))
+    }
+
+    async fn build_openai_file_delete(
+        &self,
+        _ctx: &UpstreamCtx,
+        _config: &ProviderConfig,
+        _credential: &Credential,
+        _req: &OpenAIFileDeleteRequest,
+    ) -> ProviderResult<UpstreamHttpRequest> {
+        Err(ProviderError::Unsupported("openai.files_delete"))
+    }
+
     async fn build_openai_input_tokens(
         &self,
         _ctx: &UpstreamCtx,
@@ -461,6 +713,19 @@ pub trait UpstreamProvider: Send + Sync {
         Err(ProviderError::Unsupported("openai.models_get"))
     }
 
+    /// Last step before a built request is sent: runs after the `build_*` hook and the
+    /// provider header/beta policies, so it sees the final URL, headers and body. Use it
+    /// for computed headers, signatures or query params that must cover the whole request.
+    async fn finalize_request(
+        &self,
+        _ctx: &UpstreamCtx,
+        _config: &ProviderConfig,
+        _credential: &Credential,
+        _req: &mut UpstreamHttpRequest,
+    ) -> ProviderResult<()> {
+        Ok(())
+    }
+
     /// Provider-managed OAuth start (downstream endpoint).
     ///
     /// Providers that support OAuth (e.g. codex/claudecode/antigravity) should override this.
@@ -569,6 +834,29 @@ pub trait UpstreamProvider: Send + Sync {
         Ok(None)
     }
 
+    /// Optional internal envelope hook for non-stream responses.
+    ///
+    /// Providers whose upstream wraps protocol responses in an envelope of its own
+    /// (e.g. Code Assist's `{"response": {...}}`) return the bare protocol body here.
+    /// Runs before `normalize_nonstream_response`.
+    fn unwrap_internal_response(
+        &self,
+        _proto: Proto,
+        _op: Op,
+        body: Bytes,
+    ) -> ProviderResult<Bytes> {
+        Ok(body)
+    }
+
+    /// Optional internal envelope hook for streams.
+    ///
+    /// The streaming counterpart of `unwrap_internal_response`: when this returns
+    /// `Some`, core reads the upstream stream as SSE and replaces every event with the
+    /// frames the function returns.
+    fn unwrap_internal_stream(&self, _proto: Proto) -> Option<InternalEventUnwrap> {
+        None
+    }
+
     /// Optional non-stream response normalization hook.
     ///
     /// Providers can rewrite upstream JSON body shapes before core decodes
@@ -588,6 +876,32 @@ pub trait UpstreamProvider: Send + Sync {
         Ok(body)
     }
 
+    /// Optional stream response normalization hook.
+    ///
+    /// The streaming counterpart of `normalize_nonstream_response`: providers can
+    /// rewrite upstream stream events before core decodes them.
+    fn normalize_stream_response(
+        &self,
+        _config: &ProviderConfig,
+        _proto: Proto,
+        _op: Op,
+        rx: ByteStream,
+    ) -> ByteStream {
+        rx
+    }
+
+    /// Raw passthrough: `req` on the provider's base URL with the credential's auth
+    /// injected and the body left untouched.
+    async fn build_raw_passthrough(
+        &self,
+        _ctx: &UpstreamCtx,
+        _config: &ProviderConfig,
+        _credential: &Credential,
+        _req: &RawPassthroughRequest,
+    ) -> ProviderResult<UpstreamHttpRequest> {
+        Err(ProviderError::Unsupported("raw_passthrough"))
+    }
+
     async fn build_upstream_usage(
         &self,
         _ctx: &UpstreamCtx,
@@ -13,11 +13,65 @@ impl ProviderRegistry {
         Self::default()
     }
 
+    /// Adds `provider` under its `name()`, replacing any provider already registered there.
     pub fn register(&mut self, provider: Arc<dyn UpstreamProvider>) {
         self.providers.insert(provider.name().to_string(), provider);
     }
 
+    pub fn extend(&mut self, providers: impl IntoIterator<Item = Arc<dyn UpstreamProvider>>) {
+        for provider in providers {
+            self.register(provider);
+        }
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.providers.contains_key(name)
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<_> = self.providers.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+
     pub fn get(&self, name: &str) -> Option<Arc<dyn UpstreamProvider>> {
         self.providers.get(name).cloned()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DispatchTable, ProviderConfig};
+
+    struct EchoPlugin;
+
+    #[async_trait::async_trait]
+    impl UpstreamProvider for EchoPlugin {
+        fn name(&self) -> &'static str {
+            "echo"
+        }
+
+        fn dispatch_table(&self, _config: &ProviderConfig) -> DispatchTable {
+            DispatchTable::default()
+        }
+    }
+
+    #[test]
+    fn extra_providers_are_reachable_through_plugin_configs() {
+        let mut registry = ProviderRegistry::new();
+        registry.extend([Arc::new(EchoPlugin) as Arc<dyn UpstreamProvider>]);
+        assert_eq!(registry.names(), vec!["echo"]);
+
+        let config: ProviderConfig = serde_json::from_value(serde_json::json!({
+            "kind": "plugin",
+            "channel_settings": { "implementation": "echo", "region": "eu" },
+        }))
+        .unwrap();
+        let ProviderConfig::Plugin(plugin) = config else {
+            panic!("expected a plugin config");
+        };
+        assert!(registry.contains(&plugin.implementation));
+        assert_eq!(plugin.settings["region"], "eu");
+    }
+}
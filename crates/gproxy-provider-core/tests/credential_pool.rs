@@ -74,3 +74,32 @@ async fn stale_queue_entry_does_not_recover_early() {
     let state = pool.state(1).await.unwrap();
     assert!(matches!(state, CredentialState::Active));
 }
+
+#[tokio::test]
+async fn acquire_scoped_skips_credentials_outside_allowed_set() {
+    let pool = CredentialPool::new(EventHub::new(16));
+    for id in [1, 2] {
+        pool.insert(
+            "test",
+            id,
+            Credential::Custom(ApiKeyCredential {
+                api_key: format!("k{id}"),
+            }),
+        )
+        .await;
+    }
+
+    let allowed = std::collections::HashSet::from([2]);
+    let (id, _) = pool
+        .acquire_scoped("test", None, Some(&allowed))
+        .await
+        .unwrap();
+    assert_eq!(id, 2);
+
+    let none = std::collections::HashSet::from([3]);
+    assert!(
+        pool.acquire_scoped("test", None, Some(&none))
+            .await
+            .is_err()
+    );
+}
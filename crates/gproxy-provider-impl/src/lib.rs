@@ -5,6 +5,7 @@
 
 mod auth_extractor;
 mod builtin;
+mod passthrough;
 mod providers;
 mod registry;
 
@@ -0,0 +1,30 @@
+use gproxy_provider_core::{RawPassthroughRequest, UpstreamHttpRequest, header_get};
+
+/// Raw passthrough request on `url` (the provider's base URL joined with `req.path`):
+/// method, headers, query and body are kept as sent. The provider sets auth on top.
+pub fn forward(url: String, req: &RawPassthroughRequest) -> UpstreamHttpRequest {
+    let mut url = url;
+    if let Some(query) = req.query.as_deref().filter(|q| !q.is_empty()) {
+        url.push('?');
+        url.push_str(query);
+    }
+    UpstreamHttpRequest {
+        method: req.method,
+        url,
+        headers: req.headers.clone(),
+        body: req.body.clone(),
+        is_stream: wants_stream(req),
+    }
+}
+
+/// SSE is requested through `accept` or a JSON body with `"stream": true`.
+fn wants_stream(req: &RawPassthroughRequest) -> bool {
+    if header_get(&req.headers, "accept").is_some_and(|v| v.contains("text/event-stream")) {
+        return true;
+    }
+    req.body
+        .as_deref()
+        .and_then(|body| serde_json::from_slice::<serde_json::Value>(body).ok())
+        .and_then(|body| body.get("stream").and_then(serde_json::Value::as_bool))
+        .unwrap_or(false)
+}
@@ -1,12 +1,12 @@
 use bytes::Bytes;
 
 use gproxy_provider_core::{
-    Credential, DispatchRule, DispatchTable, HttpMethod, Proto, ProviderConfig, ProviderError,
-    ProviderResult, UpstreamCtx, UpstreamHttpRequest, UpstreamProvider,
-    credential::ApiKeyCredential,
+    Credential, DispatchRule, DispatchTable, Headers, HttpMethod, Proto, ProviderConfig,
+    ProviderError, ProviderResult, RawPassthroughRequest, UpstreamCtx, UpstreamHttpRequest,
+    UpstreamProvider, credential::ApiKeyCredential,
 };
 
-use crate::auth_extractor;
+use crate::{auth_extractor, passthrough};
 
 const PROVIDER_NAME: &str = "aistudio";
 const DEFAULT_BASE_URL: &str = "https://generativelanguage.googleapis.com";
@@ -90,9 +90,14 @@ impl UpstreamProvider for AIStudioProvider {
         build_gemini_request(
             config,
             credential,
-            &format!(
-                "/v1beta/{}:generateContent",
-                normalize_model_name(&req.path.model)
+            &upstream_path(
+                config,
+                "gemini_generate",
+                &format!(
+                    "/v1beta/{}:generateContent",
+                    normalize_model_name(&req.path.model)
+                ),
+                &req.path.model,
             ),
             &req.body,
             false,
@@ -106,9 +111,14 @@ impl UpstreamProvider for AIStudioProvider {
         credential: &Credential,
         req: &gproxy_protocol::gemini::stream_content::request::StreamGenerateContentRequest,
     ) -> ProviderResult<UpstreamHttpRequest> {
-        let mut path = format!(
-            "/v1beta/{}:streamGenerateContent",
-            normalize_model_name(&req.path.model)
+        let mut path = upstream_path(
+            config,
+            "gemini_generate_stream",
+            &format!(
+                "/v1beta/{}:streamGenerateContent",
+                normalize_model_name(&req.path.model)
+            ),
+            &req.path.model,
         );
         if let Some(query) = req
             .query
@@ -132,9 +142,14 @@ impl UpstreamProvider for AIStudioProvider {
         build_gemini_request(
             config,
             credential,
-            &format!(
-                "/v1beta/{}:countTokens",
-                normalize_model_name(&req.path.model)
+            &upstream_path(
+                config,
+                "gemini_count_tokens",
+                &format!(
+                    "/v1beta/{}:countTokens",
+                    normalize_model_name(&req.path.model)
+                ),
+                &req.path.model,
             ),
             &req.body,
             false,
@@ -150,11 +165,12 @@ impl UpstreamProvider for AIStudioProvider {
     ) -> ProviderResult<UpstreamHttpRequest> {
         let base_url = aistudio_base_url(config)?;
         let api_key = aistudio_api_key(credential)?;
-        let mut url = build_url(Some(base_url), DEFAULT_BASE_URL, "/v1beta/models");
+        let path = upstream_path(config, "gemini_models_list", "/v1beta/models", "");
+        let mut url = build_url(Some(base_url), DEFAULT_BASE_URL, &path);
         if let Some(q) = build_gemini_query(&req.query) {
             url = format!("{url}?{q}");
         }
-        let mut headers = Vec::new();
+        let mut headers = Headers::new();
         auth_extractor::set_header(&mut headers, "x-goog-api-key", api_key);
         auth_extractor::set_accept_json(&mut headers);
         Ok(UpstreamHttpRequest {
@@ -175,12 +191,65 @@ impl UpstreamProvider for AIStudioProvider {
     ) -> ProviderResult<UpstreamHttpRequest> {
         let base_url = aistudio_base_url(config)?;
         let api_key = aistudio_api_key(credential)?;
-        let url = build_url(
-            Some(base_url),
-            DEFAULT_BASE_URL,
+        let path = upstream_path(
+            config,
+            "gemini_models_get",
             &format!("/v1beta/{}", normalize_model_name(&req.path.name)),
+            &req.path.name,
         );
-        let mut headers = Vec::new();
+        let url = build_url(Some(base_url), DEFAULT_BASE_URL, &path);
+        let mut headers = Headers::new();
+        auth_extractor::set_header(&mut headers, "x-goog-api-key", api_key);
+        auth_extractor::set_accept_json(&mut headers);
+        Ok(UpstreamHttpRequest {
+            method: HttpMethod::Get,
+            url,
+            headers,
+            body: None,
+            is_stream: false,
+        })
+    }
+
+    async fn build_gemini_batch_generate(
+        &self,
+        _ctx: &UpstreamCtx,
+        config: &ProviderConfig,
+        credential: &Credential,
+        req: &gproxy_protocol::gemini::batch_generate_content::request::BatchGenerateContentRequest,
+    ) -> ProviderResult<UpstreamHttpRequest> {
+        build_gemini_request(
+            config,
+            credential,
+            &prefixed_path(
+                config,
+                &format!(
+                    "/v1beta/{}:batchGenerateContent",
+                    normalize_model_name(&req.path.model)
+                ),
+            ),
+            &req.body,
+            false,
+        )
+    }
+
+    async fn build_gemini_batch_get(
+        &self,
+        _ctx: &UpstreamCtx,
+        config: &ProviderConfig,
+        credential: &Credential,
+        req: &gproxy_protocol::gemini::get_batch::request::GetBatchRequest,
+    ) -> ProviderResult<UpstreamHttpRequest> {
+        let base_url = aistudio_base_url(config)?;
+        let api_key = aistudio_api_key(credential)?;
+        let name = req.path.name.trim_start_matches('/');
+        let name = if name.starts_with("batches/") {
+            name.to_string()
+        } else {
+            format!("batches/{name}")
+        };
+        let path = prefixed_path(config, &format!("/v1beta/{name}"));
+        let url = build_url(Some(base_url), DEFAULT_BASE_URL, &path);
+        let mut headers = Headers::new();
         auth_extractor::set_header(&mut headers, "x-goog-api-key", api_key);
         auth_extractor::set_accept_json(&mut headers);
         Ok(UpstreamHttpRequest {
@@ -201,14 +270,21 @@ impl UpstreamProvider for AIStudioProvider {
     ) -> ProviderResult<UpstreamHttpRequest> {
         let base_url = aistudio_base_url(config)?;
         let api_key = aistudio_api_key(credential)?;
-        let url = build_url(
-            Some(base_url),
-            DEFAULT_BASE_URL,
+        let op = if req.body.stream.unwrap_or(false) {
+            "openai_chat_generate_stream"
+        } else {
+            "openai_chat_generate"
+        };
+        let path = upstream_path(
+            config,
+            op,
             "/v1beta/openai/chat/completions",
+            &req.body.model,
         );
+        let url = build_url(Some(base_url), DEFAULT_BASE_URL, &path);
         let body =
             serde_json::to_vec(&req.body).map_err(|err| ProviderError::Other(err.to_string()))?;
-        let mut headers = Vec::new();
+        let mut headers = Headers::new();
         auth_extractor::set_bearer(&mut headers, api_key);
         auth_extractor::set_accept_json(&mut headers);
         auth_extractor::set_content_type_json(&mut headers);
@@ -220,6 +296,21 @@ impl UpstreamProvider for AIStudioProvider {
             is_stream: req.body.stream.unwrap_or(false),
         })
     }
+
+    async fn build_raw_passthrough(
+        &self,
+        _ctx: &UpstreamCtx,
+        config: &ProviderConfig,
+        credential: &Credential,
+        req: &RawPassthroughRequest,
+    ) -> ProviderResult<UpstreamHttpRequest> {
+        let base_url = aistudio_base_url(config)?;
+        let api_key = aistudio_api_key(credential)?;
+        let url = build_url(Some(base_url), DEFAULT_BASE_URL, &req.path);
+        let mut upstream = passthrough::forward(url, req);
+        auth_extractor::set_header(&mut upstream.headers, "x-goog-api-key", api_key);
+        Ok(upstream)
+    }
 }
 
 fn aistudio_base_url(config: &ProviderConfig) -> ProviderResult<&str> {
@@ -231,6 +322,22 @@ fn aistudio_base_url(config: &ProviderConfig) -> ProviderResult<&str> {
     }
 }
 
+/// Path of operation `op` after the config's path overrides.
+fn upstream_path(config: &ProviderConfig, op: &str, default: &str, model: &str) -> String {
+    match config {
+        ProviderConfig::AIStudio(cfg) => cfg.path_overrides.resolve(op, default, model),
+        _ => default.to_string(),
+    }
+}
+
+/// `path` under the config's `path_prefix`, for calls without an operation key.
+fn prefixed_path(config: &ProviderConfig, path: &str) -> String {
+    match config {
+        ProviderConfig::AIStudio(cfg) => cfg.path_overrides.prefixed(path),
+        _ => path.to_string(),
+    }
+}
+
 fn aistudio_api_key(credential: &Credential) -> ProviderResult<&str> {
     match credential {
         Credential::AIStudio(ApiKeyCredential { api_key }) => Ok(api_key.as_str()),
@@ -251,7 +358,7 @@ fn build_gemini_request<T: serde::Serialize>(
     let api_key = aistudio_api_key(credential)?;
     let url = build_url(Some(base_url), DEFAULT_BASE_URL, path);
     let body = serde_json::to_vec(body).map_err(|err| ProviderError::Other(err.to_string()))?;
-    let mut headers = Vec::new();
+    let mut headers = Headers::new();
     auth_extractor::set_header(&mut headers, "x-goog-api-key", api_key);
     auth_extractor::set_accept_json(&mut headers);
     auth_extractor::set_content_type_json(&mut headers);
@@ -6,16 +6,18 @@ use rand::RngCore;
 use serde::Deserialize;
 
 use gproxy_provider_core::credential::AntigravityCredential;
-use gproxy_provider_core::provider::UpstreamFailure;
+use gproxy_provider_core::provider::{InternalEventUnwrap, UpstreamFailure};
 use gproxy_provider_core::{
-    AuthRetryAction, CountTokensRequest, Credential, DispatchRule, DispatchTable, HttpMethod,
-    ModelGetRequest, ModelListRequest, OAuthCallbackRequest, OAuthCallbackResult, OAuthCredential,
-    OAuthStartRequest, Proto, ProviderConfig, ProviderError, ProviderResult, Request, UpstreamBody,
-    UpstreamCtx, UpstreamHttpRequest, UpstreamHttpResponse, UpstreamProvider, header_set,
+    AuthRetryAction, CountTokensRequest, Credential, DispatchRule, DispatchTable, Headers,
+    HttpMethod, ModelGetRequest, ModelListRequest, OAuthCallbackRequest, OAuthCallbackResult,
+    OAuthCredential, OAuthStartRequest, Op, Proto, ProviderConfig, ProviderError, ProviderResult,
+    Request, UpstreamBody, UpstreamCtx, UpstreamHttpRequest, UpstreamHttpResponse,
+    UpstreamProvider, header_set,
 };
 
 use crate::auth_extractor;
 use crate::providers::http_client::{SharedClientKind, client_for_ctx};
+use crate::providers::internal_envelope;
 mod oauth;
 mod usage;
 
@@ -204,7 +206,7 @@ impl UpstreamProvider for AntigravityProvider {
         if let Some(q) = build_gemini_query(&req.query) {
             url = format!("{url}?{q}");
         }
-        let mut headers = Vec::new();
+        let mut headers = Headers::new();
         auth_extractor::set_bearer(&mut headers, access_token);
         auth_extractor::set_accept_json(&mut headers);
         auth_extractor::set_content_type_json(&mut headers);
@@ -237,7 +239,7 @@ impl UpstreamProvider for AntigravityProvider {
         if !req.path.name.is_empty() {
             url = format!("{url}?name={}", urlencoding::encode(&req.path.name));
         }
-        let mut headers = Vec::new();
+        let mut headers = Headers::new();
         auth_extractor::set_bearer(&mut headers, access_token);
         auth_extractor::set_accept_json(&mut headers);
         auth_extractor::set_content_type_json(&mut headers);
@@ -402,6 +404,20 @@ impl UpstreamProvider for AntigravityProvider {
         }
     }
 
+    fn unwrap_internal_response(&self, proto: Proto, op: Op, body: Bytes) -> ProviderResult<Bytes> {
+        if proto == Proto::Gemini && op == Op::GenerateContent {
+            internal_envelope::unwrap_response(body, true)
+        } else {
+            Ok(body)
+        }
+    }
+
+    fn unwrap_internal_stream(&self, proto: Proto) -> Option<InternalEventUnwrap> {
+        (proto == Proto::Gemini).then_some(
+            (|data: &str| internal_envelope::unwrap_event(data, true)) as InternalEventUnwrap,
+        )
+    }
+
     async fn build_upstream_usage(
         &self,
         ctx: &UpstreamCtx,
@@ -442,7 +458,7 @@ fn build_gemini_request<T: serde::Serialize>(
     let access_token = antigravity_access_token(credential)?;
     let url = build_url(Some(base_url), DEFAULT_BASE_URL, path);
     let body = serde_json::to_vec(body).map_err(|err| ProviderError::Other(err.to_string()))?;
-    let mut headers = Vec::new();
+    let mut headers = Headers::new();
     auth_extractor::set_bearer(&mut headers, access_token);
     auth_extractor::set_accept_json(&mut headers);
     auth_extractor::set_content_type_json(&mut headers);
@@ -552,7 +568,7 @@ fn request_type_for_model(model: &str) -> &'static str {
 }
 
 fn json_response(body: serde_json::Value) -> UpstreamHttpResponse {
-    let mut headers = Vec::new();
+    let mut headers = Headers::new();
     header_set(&mut headers, "content-type", "application/json");
     let bytes = Bytes::from(serde_json::to_vec(&body).unwrap_or_default());
     UpstreamHttpResponse {
@@ -563,7 +579,7 @@ fn json_response(body: serde_json::Value) -> UpstreamHttpResponse {
 }
 
 fn json_error(status: u16, message: &str) -> UpstreamHttpResponse {
-    let mut headers = Vec::new();
+    let mut headers = Headers::new();
     header_set(&mut headers, "content-type", "application/json");
     let bytes = Bytes::from(
         serde_json::to_vec(&serde_json::json!({ "error": message })).unwrap_or_default(),
@@ -576,7 +592,7 @@ fn json_error(status: u16, message: &str) -> UpstreamHttpResponse {
 }
 
 fn local_json_response(status: u16, body: Vec<u8>) -> UpstreamHttpResponse {
-    let mut headers = Vec::new();
+    let mut headers = Headers::new();
     header_set(&mut headers, "content-type", "application/json");
     UpstreamHttpResponse {
         status,
@@ -25,7 +25,7 @@ pub(super) fn build_upstream_usage(
         }
     };
 
-    let mut headers = Vec::new();
+    let mut headers = Headers::new();
     auth_extractor::set_bearer(&mut headers, access_token);
     auth_extractor::set_accept_json(&mut headers);
     auth_extractor::set_content_type_json(&mut headers);
@@ -2,12 +2,12 @@ use bytes::Bytes;
 use serde::Serialize;
 
 use gproxy_provider_core::{
-    Credential, DispatchRule, DispatchTable, HttpMethod, Proto, ProviderConfig, ProviderError,
-    ProviderResult, UpstreamCtx, UpstreamHttpRequest, UpstreamProvider,
-    credential::ApiKeyCredential,
+    Credential, DispatchRule, DispatchTable, Headers, HttpMethod, Proto, ProviderConfig,
+    ProviderError, ProviderResult, RawPassthroughRequest, UpstreamCtx, UpstreamHttpRequest,
+    UpstreamProvider, credential::ApiKeyCredential,
 };
 
-use crate::auth_extractor;
+use crate::{auth_extractor, passthrough};
 
 const PROVIDER_NAME: &str = "claude";
 const DEFAULT_BASE_URL: &str = "https://api.anthropic.com";
@@ -106,11 +106,17 @@ impl UpstreamProvider for ClaudeProvider {
             }
         };
 
-        let url = build_url(Some(base_url), DEFAULT_BASE_URL, "/v1/messages");
         let is_stream = req.body.stream.unwrap_or(false);
+        let op = if is_stream {
+            "claude_generate_stream"
+        } else {
+            "claude_generate"
+        };
+        let path = upstream_path(config, op, "/v1/messages", &model_name(&req.body.model));
+        let url = build_url(Some(base_url), DEFAULT_BASE_URL, &path);
         let body =
             serde_json::to_vec(&req.body).map_err(|err| ProviderError::Other(err.to_string()))?;
-        let mut headers = Vec::new();
+        let mut headers = Headers::new();
         auth_extractor::set_header(&mut headers, "x-api-key", api_key);
         auth_extractor::set_accept_json(&mut headers);
         auth_extractor::set_content_type_json(&mut headers);
@@ -150,14 +156,16 @@ impl UpstreamProvider for ClaudeProvider {
             }
         };
 
-        let url = build_url(
-            Some(base_url),
-            DEFAULT_BASE_URL,
+        let path = upstream_path(
+            config,
+            "claude_count_tokens",
             "/v1/messages/count_tokens",
+            &model_name(&req.body.model),
         );
+        let url = build_url(Some(base_url), DEFAULT_BASE_URL, &path);
         let body =
             serde_json::to_vec(&req.body).map_err(|err| ProviderError::Other(err.to_string()))?;
-        let mut headers = Vec::new();
+        let mut headers = Headers::new();
         auth_extractor::set_header(&mut headers, "x-api-key", api_key);
         auth_extractor::set_accept_json(&mut headers);
         auth_extractor::set_content_type_json(&mut headers);
@@ -197,13 +205,14 @@ impl UpstreamProvider for ClaudeProvider {
             }
         };
 
-        let mut url = build_url(Some(base_url), DEFAULT_BASE_URL, "/v1/models");
+        let path = upstream_path(config, "claude_models_list", "/v1/models", "");
+        let mut url = build_url(Some(base_url), DEFAULT_BASE_URL, &path);
         let query = build_claude_models_list_query(&req.query);
         if !query.is_empty() {
             url.push('?');
             url.push_str(&query);
         }
-        let mut headers = Vec::new();
+        let mut headers = Headers::new();
         auth_extractor::set_header(&mut headers, "x-api-key", api_key);
         auth_extractor::set_accept_json(&mut headers);
         apply_anthropic_headers(&mut headers, &req.headers)?;
@@ -242,12 +251,14 @@ impl UpstreamProvider for ClaudeProvider {
             }
         };
 
-        let url = build_url(
-            Some(base_url),
-            DEFAULT_BASE_URL,
+        let path = upstream_path(
+            config,
+            "claude_models_get",
             &format!("/v1/models/{}", req.path.model_id),
+            &req.path.model_id,
         );
-        let mut headers = Vec::new();
+        let url = build_url(Some(base_url), DEFAULT_BASE_URL, &path);
+        let mut headers = Headers::new();
         auth_extractor::set_header(&mut headers, "x-api-key", api_key);
         auth_extractor::set_accept_json(&mut headers);
         apply_anthropic_headers(&mut headers, &req.headers)?;
@@ -287,11 +298,17 @@ impl UpstreamProvider for ClaudeProvider {
             }
         };
 
-        let url = build_url(Some(base_url), DEFAULT_BASE_URL, "/v1/chat/completions");
         let is_stream = req.body.stream.unwrap_or(false);
+        let op = if is_stream {
+            "openai_chat_generate_stream"
+        } else {
+            "openai_chat_generate"
+        };
+        let path = upstream_path(config, op, "/v1/chat/completions", &req.body.model);
+        let url = build_url(Some(base_url), DEFAULT_BASE_URL, &path);
         let body =
             serde_json::to_vec(&req.body).map_err(|err| ProviderError::Other(err.to_string()))?;
-        let mut headers = Vec::new();
+        let mut headers = Headers::new();
         auth_extractor::set_bearer(&mut headers, api_key);
         auth_extractor::set_accept_json(&mut headers);
         auth_extractor::set_content_type_json(&mut headers);
@@ -307,6 +324,36 @@ impl UpstreamProvider for ClaudeProvider {
     // NOTE: We intentionally do not support arbitrary passthrough requests here.
     // Upstream calls are modeled as typed ops (protocol requests) plus a few
     // internal abilities like oauth/usage, handled elsewhere.
+
+    async fn build_raw_passthrough(
+        &self,
+        _ctx: &UpstreamCtx,
+        config: &ProviderConfig,
+        credential: &Credential,
+        req: &RawPassthroughRequest,
+    ) -> ProviderResult<UpstreamHttpRequest> {
+        let base_url = match config {
+            ProviderConfig::Claude(cfg) => cfg.base_url.as_deref().unwrap_or(DEFAULT_BASE_URL),
+            _ => {
+                return Err(ProviderError::InvalidConfig(
+                    "expected ProviderConfig::Claude".to_string(),
+                ));
+            }
+        };
+        let api_key = match credential {
+            Credential::Claude(ApiKeyCredential { api_key }) => api_key.as_str(),
+            _ => {
+                return Err(ProviderError::InvalidConfig(
+                    "expected Credential::Claude".to_string(),
+                ));
+            }
+        };
+
+        let url = build_url(Some(base_url), DEFAULT_BASE_URL, &req.path);
+        let mut upstream = passthrough::forward(url, req);
+        auth_extractor::set_header(&mut upstream.headers, "x-api-key", api_key);
+        Ok(upstream)
+    }
 }
 
 fn build_url(base_url: Option<&str>, default_base: &str, path: &str) -> String {
@@ -318,6 +365,21 @@ fn build_url(base_url: Option<&str>, default_base: &str, path: &str) -> String {
     format!("{base}/{path}")
 }
 
+/// Path of operation `op` after the config's path overrides.
+fn upstream_path(config: &ProviderConfig, op: &str, default: &str, model: &str) -> String {
+    match config {
+        ProviderConfig::Claude(cfg) => cfg.path_overrides.resolve(op, default, model),
+        _ => default.to_string(),
+    }
+}
+
+fn model_name<T: serde::Serialize>(model: &T) -> String {
+    serde_json::to_value(model)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
 fn apply_anthropic_headers(
     headers: &mut gproxy_provider_core::Headers,
     anthropic_headers: &impl Serialize,
@@ -6,15 +6,16 @@ use sha2::{Digest, Sha256};
 
 use gproxy_provider_core::credential::ClaudeCodeCredential;
 use gproxy_provider_core::{
-    AuthRetryAction, ClaudeCodePreludeText, Credential, DispatchRule, DispatchTable, HttpMethod,
-    OAuthCallbackRequest, OAuthCallbackResult, OAuthCredential, OAuthStartRequest, Proto,
-    ProviderConfig, ProviderError, ProviderResult, Request, UpstreamCtx, UpstreamHttpRequest,
-    UpstreamHttpResponse, UpstreamProvider, header_get, header_set,
+    AuthRetryAction, ClaudeCodePreludeText, Credential, DispatchRule, DispatchTable, Headers,
+    HttpMethod, OAuthCallbackRequest, OAuthCallbackResult, OAuthCredential, OAuthStartRequest,
+    Proto, ProviderConfig, ProviderError, ProviderResult, Request, UpstreamCtx,
+    UpstreamHttpRequest, UpstreamHttpResponse, UpstreamProvider, header_get, header_set,
 };
 
 use crate::auth_extractor;
 mod cookie;
 mod oauth;
+mod prelude;
 mod usage;
 
 const PROVIDER_NAME: &str = "claudecode";
@@ -138,17 +139,23 @@ impl UpstreamProvider for ClaudeCodeProvider {
         let system_prelude = claudecode_system_prelude(config)?;
         let url = build_url(Some(base_url), DEFAULT_API_BASE_URL, "/v1/messages");
         let mut body_obj = req.body.clone();
+        let system_prelude = prelude::render_prelude(
+            ctx,
+            claudecode_prelude_template(config),
+            system_prelude,
+            body_obj.tools.as_ref(),
+        );
         apply_claude_code_system(
             &mut body_obj.system,
             ctx.user_agent.as_deref(),
-            system_prelude,
+            &system_prelude,
         );
         let model = model_to_string(&body_obj.model);
         normalize_claude_code_sampling(model.as_deref(), body_obj.temperature, &mut body_obj.top_p);
         let is_stream = body_obj.stream.unwrap_or(false);
         let body =
             serde_json::to_vec(&body_obj).map_err(|err| ProviderError::Other(err.to_string()))?;
-        let mut headers = Vec::new();
+        let mut headers = Headers::new();
         auth_extractor::set_bearer(&mut headers, &access_token);
         auth_extractor::set_accept_json(&mut headers);
         auth_extractor::set_content_type_json(&mut headers);
@@ -181,15 +188,21 @@ impl UpstreamProvider for ClaudeCodeProvider {
             "/v1/messages/count_tokens",
         );
         let mut body_obj = req.body.clone();
+        let system_prelude = prelude::render_prelude(
+            ctx,
+            claudecode_prelude_template(config),
+            system_prelude,
+            body_obj.tools.as_ref(),
+        );
         apply_claude_code_system(
             &mut body_obj.system,
             ctx.user_agent.as_deref(),
-            system_prelude,
+            &system_prelude,
         );
         let model = model_to_string(&body_obj.model);
         let body =
             serde_json::to_vec(&body_obj).map_err(|err| ProviderError::Other(err.to_string()))?;
-        let mut headers = Vec::new();
+        let mut headers = Headers::new();
         auth_extractor::set_bearer(&mut headers, &access_token);
         auth_extractor::set_accept_json(&mut headers);
         auth_extractor::set_content_type_json(&mut headers);
@@ -221,7 +234,7 @@ impl UpstreamProvider for ClaudeCodeProvider {
             url.push('?');
             url.push_str(&query);
         }
-        let mut headers = Vec::new();
+        let mut headers = Headers::new();
         auth_extractor::set_bearer(&mut headers, &access_token);
         auth_extractor::set_accept_json(&mut headers);
         auth_extractor::set_user_agent(&mut headers, CLAUDE_CODE_UA);
@@ -250,7 +263,7 @@ impl UpstreamProvider for ClaudeCodeProvider {
             DEFAULT_API_BASE_URL,
             &format!("/v1/models/{}", req.path.model_id),
         );
-        let mut headers = Vec::new();
+        let mut headers = Headers::new();
         auth_extractor::set_bearer(&mut headers, &access_token);
         auth_extractor::set_accept_json(&mut headers);
         auth_extractor::set_user_agent(&mut headers, CLAUDE_CODE_UA);
@@ -451,6 +464,13 @@ fn claudecode_system_prelude(config: &ProviderConfig) -> ProviderResult<&'static
     }
 }
 
+fn claudecode_prelude_template(config: &ProviderConfig) -> Option<&str> {
+    match config {
+        ProviderConfig::ClaudeCode(cfg) => cfg.prelude_template.as_deref(),
+        _ => None,
+    }
+}
+
 pub(super) fn claudecode_oauth_redirect_uri(config: &ProviderConfig) -> ProviderResult<String> {
     match config {
         ProviderConfig::ClaudeCode(_) => Ok(DEFAULT_OAUTH_REDIRECT_URI.to_string()),
@@ -575,7 +595,7 @@ fn json_response(body: serde_json::Value) -> UpstreamHttpResponse {
     let bytes = serde_json::to_vec(&body).unwrap_or_default();
     UpstreamHttpResponse {
         status: 200,
-        headers: vec![("content-type".to_string(), "application/json".to_string())],
+        headers: vec![("content-type".to_string(), "application/json".to_string())].into(),
         body: gproxy_provider_core::provider::UpstreamBody::Bytes(Bytes::from(bytes)),
     }
 }
@@ -585,7 +605,7 @@ fn json_error(status: u16, message: &str) -> UpstreamHttpResponse {
     let bytes = serde_json::to_vec(&body).unwrap_or_default();
     UpstreamHttpResponse {
         status,
-        headers: vec![("content-type".to_string(), "application/json".to_string())],
+        headers: vec![("content-type".to_string(), "application/json".to_string())].into(),
         body: gproxy_provider_core::provider::UpstreamBody::Bytes(Bytes::from(bytes)),
     }
 }
@@ -678,10 +698,11 @@ mod tests {
 
     #[test]
     fn ensure_oauth_beta_strips_context_1m_when_disabled() {
-        let mut headers = vec![(
+        let mut headers: Headers = vec![(
             HEADER_BETA.to_string(),
             "output-128k-2025-02-19,context-1m-2025-08-07".to_string(),
-        )];
+        )]
+        .into();
         ensure_oauth_beta(&mut headers, false);
         let beta = header_get(&headers, HEADER_BETA).unwrap_or_default();
         assert!(beta.contains("output-128k-2025-02-19"));
@@ -691,10 +712,11 @@ mod tests {
 
     #[test]
     fn ensure_oauth_beta_keeps_context_1m_when_enabled() {
-        let mut headers = vec![(
+        let mut headers: Headers = vec![(
             HEADER_BETA.to_string(),
             "output-128k-2025-02-19,context-1m-2025-08-07".to_string(),
-        )];
+        )]
+        .into();
         ensure_oauth_beta(&mut headers, true);
         let beta = header_get(&headers, HEADER_BETA).unwrap_or_default();
         assert!(beta.contains("output-128k-2025-02-19"));
@@ -706,7 +728,7 @@ mod tests {
     fn forbidden_response_detected() {
         let failure = UpstreamFailure::Http {
             status: 403,
-            headers: vec![],
+            headers: Headers::new(),
             body: Bytes::from_static(
                 b"feature context-1m-2025-08-07 is not available for this account",
             ),
@@ -718,7 +740,7 @@ mod tests {
     fn forbidden_response_detected_not_yet_available_long_context_beta() {
         let failure = UpstreamFailure::Http {
             status: 400,
-            headers: vec![],
+            headers: Headers::new(),
             body: Bytes::from_static(
                 b"The long context beta is not yet available for this subscription.",
             ),
@@ -730,7 +752,7 @@ mod tests {
     fn forbidden_response_detected_incompatible_long_context_beta_header() {
         let failure = UpstreamFailure::Http {
             status: 400,
-            headers: vec![],
+            headers: Headers::new(),
             body: Bytes::from_static(
                 b"This authentication style is incompatible with the long context beta header.",
             ),
@@ -0,0 +1,158 @@
+//! Prelude templates: `{prelude}`, `{key_label}`, `{org}`, `{date}` and `{allowed_tools}`
+//! are filled in per request; other `{...}` text is left as written.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+
+use gproxy_provider_core::UpstreamCtx;
+
+/// The prelude for one request: the key's template, else the provider's, else the fixed
+/// `prelude` line alone.
+pub(super) fn render_prelude<T: Serialize>(
+    ctx: &UpstreamCtx,
+    provider_template: Option<&str>,
+    prelude: &str,
+    tools: Option<&T>,
+) -> String {
+    let Some(template) = ctx
+        .caller
+        .prelude_template
+        .as_deref()
+        .or(provider_template)
+        .filter(|template| !template.trim().is_empty())
+    else {
+        return prelude.to_string();
+    };
+    let allowed_tools = tool_names(tools).join(", ");
+    let date = utc_date(SystemTime::now());
+    render(template, |name| match name {
+        "prelude" => Some(prelude),
+        "key_label" => Some(ctx.caller.key_label.as_deref().unwrap_or_default()),
+        "org" => Some(ctx.caller.org_name.as_deref().unwrap_or_default()),
+        "date" => Some(date.as_str()),
+        "allowed_tools" => Some(allowed_tools.as_str()),
+        _ => None,
+    })
+}
+
+fn render<'a>(template: &str, var: impl Fn(&str) -> Option<&'a str>) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        match after
+            .find('}')
+            .and_then(|end| Some((end, var(&after[..end])?)))
+        {
+            Some((end, value)) => {
+                out.push_str(value);
+                rest = &after[end + 1..];
+            }
+            None => {
+                out.push('{');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Names of the request's tools, in request order.
+fn tool_names<T: Serialize>(tools: Option<&T>) -> Vec<String> {
+    let Some(JsonValue::Array(tools)) = tools.and_then(|tools| serde_json::to_value(tools).ok())
+    else {
+        return Vec::new();
+    };
+    tools
+        .iter()
+        .filter_map(|tool| tool.get("name")?.as_str().map(str::to_string))
+        .collect()
+}
+
+/// `YYYY-MM-DD` of `now` in UTC.
+fn utc_date(now: SystemTime) -> String {
+    let days = now
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() / 86_400)
+        .unwrap_or_default() as i64;
+    // Days since 1970-01-01 to a civil date (proleptic Gregorian).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use gproxy_provider_core::{CallerInfo, Op};
+    use serde_json::json;
+
+    use super::*;
+
+    fn ctx(caller: CallerInfo) -> UpstreamCtx {
+        UpstreamCtx {
+            trace_id: None,
+            user_id: None,
+            user_key_id: None,
+            user_agent: None,
+            outbound_proxy: None,
+            provider: "claudecode".to_string(),
+            credential_id: None,
+            op: Op::GenerateContent,
+            internal: false,
+            attempt_no: 1,
+            caller: Arc::new(caller),
+        }
+    }
+
+    #[test]
+    fn key_template_wins_and_unknown_braces_stay() {
+        let caller = CallerInfo {
+            key_label: Some("ci".to_string()),
+            org_name: Some("Acme".to_string()),
+            prelude_template: Some(
+                "{prelude} Key {key_label} of {org}; tools: {allowed_tools}. Keep {json}"
+                    .to_string(),
+            ),
+        };
+        let tools = json!([{ "name": "bash" }, { "name": "edit" }]);
+        assert_eq!(
+            render_prelude(&ctx(caller), Some("{prelude} ignored"), "P.", Some(&tools)),
+            "P. Key ci of Acme; tools: bash, edit. Keep {json}"
+        );
+
+        let provider = render_prelude(
+            &ctx(CallerInfo::default()),
+            Some("{prelude} ({key_label})"),
+            "P.",
+            None::<&JsonValue>,
+        );
+        assert_eq!(provider, "P. ()");
+        assert_eq!(
+            render_prelude(&ctx(CallerInfo::default()), None, "P.", None::<&JsonValue>),
+            "P."
+        );
+    }
+
+    #[test]
+    fn dates_are_utc_calendar_days() {
+        assert_eq!(utc_date(UNIX_EPOCH), "1970-01-01");
+        let leap_day = UNIX_EPOCH + Duration::from_secs(951_782_400 + 86_399);
+        assert_eq!(utc_date(leap_day), "2000-02-29");
+        let date = UNIX_EPOCH + Duration::from_secs(1_792_195_200);
+        assert_eq!(utc_date(date), "2026-10-17");
+    }
+}
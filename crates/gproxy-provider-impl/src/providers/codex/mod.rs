@@ -8,10 +8,10 @@ use tiktoken_rs::{CoreBPE, get_bpe_from_model, o200k_base};
 
 use gproxy_provider_core::credential::CodexCredential;
 use gproxy_provider_core::{
-    AuthRetryAction, Credential, DispatchRule, DispatchTable, HttpMethod, OAuthCallbackRequest,
-    OAuthCallbackResult, OAuthCredential, OAuthStartRequest, Op, Proto, ProviderConfig,
-    ProviderError, ProviderResult, Request, UpstreamBody, UpstreamCtx, UpstreamHttpRequest,
-    UpstreamHttpResponse, UpstreamProvider, header_set,
+    AuthRetryAction, Credential, DispatchRule, DispatchTable, Headers, HttpMethod,
+    OAuthCallbackRequest, OAuthCallbackResult, OAuthCredential, OAuthStartRequest, Op, Proto,
+    ProviderConfig, ProviderError, ProviderResult, Request, UpstreamBody, UpstreamCtx,
+    UpstreamHttpRequest, UpstreamHttpResponse, UpstreamProvider, header_set,
 };
 
 use gproxy_protocol::openai;
@@ -207,7 +207,7 @@ impl UpstreamProvider for CodexProvider {
         let body =
             serde_json::to_vec(&body).map_err(|err| ProviderError::Other(err.to_string()))?;
 
-        let mut headers = Vec::new();
+        let mut headers = Headers::new();
         auth_extractor::set_bearer(&mut headers, access_token);
         auth_extractor::set_accept_json(&mut headers);
         auth_extractor::set_content_type_json(&mut headers);
@@ -243,7 +243,7 @@ impl UpstreamProvider for CodexProvider {
             url.push_str(&query);
         }
 
-        let mut headers = Vec::new();
+        let mut headers = Headers::new();
         auth_extractor::set_bearer(&mut headers, access_token);
         auth_extractor::set_accept_json(&mut headers);
         auth_extractor::set_header(&mut headers, "chatgpt-account-id", account_id);
@@ -271,7 +271,7 @@ impl UpstreamProvider for CodexProvider {
             req.path.response_id
         );
 
-        let mut headers = Vec::new();
+        let mut headers = Headers::new();
         auth_extractor::set_bearer(&mut headers, access_token);
         auth_extractor::set_accept_json(&mut headers);
         auth_extractor::set_header(&mut headers, "chatgpt-account-id", account_id);
@@ -299,7 +299,7 @@ impl UpstreamProvider for CodexProvider {
             req.path.response_id
         );
 
-        let mut headers = Vec::new();
+        let mut headers = Headers::new();
         auth_extractor::set_bearer(&mut headers, access_token);
         auth_extractor::set_accept_json(&mut headers);
         auth_extractor::set_header(&mut headers, "chatgpt-account-id", account_id);
@@ -333,7 +333,7 @@ impl UpstreamProvider for CodexProvider {
             url.push_str(&query);
         }
 
-        let mut headers = Vec::new();
+        let mut headers = Headers::new();
         auth_extractor::set_bearer(&mut headers, access_token);
         auth_extractor::set_accept_json(&mut headers);
         auth_extractor::set_header(&mut headers, "chatgpt-account-id", account_id);
@@ -363,7 +363,7 @@ impl UpstreamProvider for CodexProvider {
             serde_json::to_vec(&body).map_err(|err| ProviderError::Other(err.to_string()))?;
         let url = format!("{}/responses/compact", base_url.trim_end_matches('/'));
 
-        let mut headers = Vec::new();
+        let mut headers = Headers::new();
         auth_extractor::set_bearer(&mut headers, access_token);
         auth_extractor::set_accept_json(&mut headers);
         auth_extractor::set_content_type_json(&mut headers);
@@ -395,7 +395,7 @@ impl UpstreamProvider for CodexProvider {
             base_url.trim_end_matches('/')
         );
 
-        let mut headers = Vec::new();
+        let mut headers = Headers::new();
         auth_extractor::set_bearer(&mut headers, access_token);
         auth_extractor::set_accept_json(&mut headers);
         auth_extractor::set_content_type_json(&mut headers);
@@ -438,7 +438,7 @@ impl UpstreamProvider for CodexProvider {
         let (access_token, account_id) = codex_credential(credential)?;
         let url = codex_models_url(base_url);
 
-        let mut headers = Vec::new();
+        let mut headers = Headers::new();
         auth_extractor::set_bearer(&mut headers, access_token);
         auth_extractor::set_accept_json(&mut headers);
         auth_extractor::set_header(&mut headers, "chatgpt-account-id", account_id);
@@ -464,7 +464,7 @@ impl UpstreamProvider for CodexProvider {
         let _model = normalize_model_id(&req.path.model);
         let url = codex_models_url(base_url);
 
-        let mut headers = Vec::new();
+        let mut headers = Headers::new();
         auth_extractor::set_bearer(&mut headers, access_token);
         auth_extractor::set_accept_json(&mut headers);
         auth_extractor::set_header(&mut headers, "chatgpt-account-id", account_id);
@@ -568,7 +568,7 @@ fn codex_models_url(base_url: &str) -> String {
 }
 
 fn local_json_request(body: Vec<u8>) -> UpstreamHttpRequest {
-    let mut headers = Vec::new();
+    let mut headers = Headers::new();
     auth_extractor::set_accept_json(&mut headers);
     auth_extractor::set_content_type_json(&mut headers);
     UpstreamHttpRequest {
@@ -969,7 +969,7 @@ fn ensure_codex_instructions_field(
 }
 
 fn json_response(body: serde_json::Value) -> UpstreamHttpResponse {
-    let mut headers = Vec::new();
+    let mut headers = Headers::new();
     header_set(&mut headers, "content-type", "application/json");
     let bytes = Bytes::from(serde_json::to_vec(&body).unwrap_or_default());
     UpstreamHttpResponse {
@@ -980,7 +980,7 @@ fn json_response(body: serde_json::Value) -> UpstreamHttpResponse {
 }
 
 fn json_error(status: u16, message: &str) -> UpstreamHttpResponse {
-    let mut headers = Vec::new();
+    let mut headers = Headers::new();
     header_set(&mut headers, "content-type", "application/json");
     let bytes = Bytes::from(
         serde_json::to_vec(&serde_json::json!({ "error": message })).unwrap_or_default(),
@@ -1195,6 +1195,7 @@ mod tests {
             prompt_cache_key: None,
             service_tier: None,
             prompt_cache_retention: None,
+            extra: Default::default(),
         };
 
         ensure_codex_instructions_field(&mut body);
@@ -1244,6 +1245,7 @@ mod tests {
                         prompt_cache_key: None,
                         service_tier: None,
                         prompt_cache_retention: None,
+                        extra: Default::default(),
                     },
                 },
             ),
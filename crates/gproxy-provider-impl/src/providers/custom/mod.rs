@@ -1,18 +1,24 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
 use bytes::Bytes;
+use hmac::{Hmac, Mac};
 use serde::Serialize;
 use serde_json::json;
+use sha2::Sha256;
 use tiktoken_rs::{get_bpe_from_model, o200k_base};
 
-use gproxy_provider_core::config::{CustomProviderConfig, ModelRecord};
+use gproxy_provider_core::config::{CustomProviderConfig, ModelRecord, RequestSigning};
 use gproxy_provider_core::header_get;
 use gproxy_provider_core::{
-    CountTokensMode, Credential, DispatchTable, HttpMethod, ProviderConfig, ProviderError,
-    ProviderResult, UpstreamBody, UpstreamCtx, UpstreamHttpRequest, UpstreamHttpResponse,
-    UpstreamProvider, credential::ApiKeyCredential, header_set,
+    CountTokensMode, Credential, DispatchTable, Headers, HttpMethod, ProviderConfig, ProviderError,
+    ProviderResult, RawPassthroughRequest, UpstreamBody, UpstreamCtx, UpstreamHttpRequest,
+    UpstreamHttpResponse, UpstreamProvider, credential::ApiKeyCredential, header_set,
+};
+use gproxy_provider_core::{
+    CountTokensRequest, GenerateContentRequest, ModelGetRequest, ModelListRequest, Request,
 };
-use gproxy_provider_core::{CountTokensRequest, ModelGetRequest, ModelListRequest, Request};
 
-use crate::auth_extractor;
+use crate::{auth_extractor, passthrough};
 
 const PROVIDER_NAME: &str = "custom";
 const CLAUDE_CREATED_AT: &str = "2026-01-01T00:00:00Z";
@@ -41,11 +47,30 @@ impl UpstreamProvider for CustomProvider {
 
     fn dispatch_table(&self, config: &ProviderConfig) -> DispatchTable {
         match config {
-            ProviderConfig::Custom(cfg) => cfg.dispatch,
+            ProviderConfig::Custom(cfg) => cfg.dispatch.clone(),
             _ => DispatchTable::default(),
         }
     }
 
+    async fn finalize_request(
+        &self,
+        _ctx: &UpstreamCtx,
+        config: &ProviderConfig,
+        _credential: &Credential,
+        req: &mut UpstreamHttpRequest,
+    ) -> ProviderResult<()> {
+        let cfg = custom_config(config)?;
+        finalize_json_request(cfg, req)?;
+        if let Some(signing) = cfg.request_signing.as_ref() {
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default();
+            sign_request(signing, timestamp, req)?;
+        }
+        Ok(())
+    }
+
     async fn build_claude_messages(
         &self,
         _ctx: &UpstreamCtx,
@@ -55,23 +80,30 @@ impl UpstreamProvider for CustomProvider {
     ) -> ProviderResult<UpstreamHttpRequest> {
         let cfg = custom_config(config)?;
         let api_key = custom_api_key(credential)?;
-        let url = build_url(&cfg.base_url, "/v1/messages");
+        let op = if req.body.stream.unwrap_or(false) {
+            "claude_generate_stream"
+        } else {
+            "claude_generate"
+        };
+        let model = model_to_string(&req.body.model).unwrap_or_default();
+        let url = build_url(
+            &cfg.base_url,
+            &upstream_path(cfg, op, "/v1/messages", &model),
+        );
         let body =
             serde_json::to_vec(&req.body).map_err(|err| ProviderError::Other(err.to_string()))?;
-        let mut headers = Vec::new();
-        auth_extractor::set_header(&mut headers, "x-api-key", api_key);
+        let mut headers = Headers::new();
+        set_auth(&mut headers, cfg, api_key, CLAUDE_AUTH);
         auth_extractor::set_accept_json(&mut headers);
         auth_extractor::set_content_type_json(&mut headers);
         apply_anthropic_headers(&mut headers, &req.headers)?;
-        let mut upstream = UpstreamHttpRequest {
+        Ok(UpstreamHttpRequest {
             method: HttpMethod::Post,
             url,
             headers,
             body: Some(Bytes::from(body)),
             is_stream: req.body.stream.unwrap_or(false),
-        };
-        finalize_json_request(cfg, &mut upstream)?;
-        Ok(upstream)
+        })
     }
 
     async fn build_claude_count_tokens(
@@ -85,23 +117,28 @@ impl UpstreamProvider for CustomProvider {
         let api_key = custom_api_key(credential)?;
         match cfg.count_tokens {
             CountTokensMode::Upstream => {
-                let url = build_url(&cfg.base_url, "/v1/messages/count_tokens");
+                let model = model_to_string(&req.body.model).unwrap_or_default();
+                let path = upstream_path(
+                    cfg,
+                    "claude_count_tokens",
+                    "/v1/messages/count_tokens",
+                    &model,
+                );
+                let url = build_url(&cfg.base_url, &path);
                 let body = serde_json::to_vec(&req.body)
                     .map_err(|err| ProviderError::Other(err.to_string()))?;
-                let mut headers = Vec::new();
-                auth_extractor::set_header(&mut headers, "x-api-key", api_key);
+                let mut headers = Headers::new();
+                set_auth(&mut headers, cfg, api_key, CLAUDE_AUTH);
                 auth_extractor::set_accept_json(&mut headers);
                 auth_extractor::set_content_type_json(&mut headers);
                 apply_anthropic_headers(&mut headers, &req.headers)?;
-                let mut upstream = UpstreamHttpRequest {
+                Ok(UpstreamHttpRequest {
                     method: HttpMethod::Post,
                     url,
                     headers,
                     body: Some(Bytes::from(body)),
                     is_stream: false,
-                };
-                finalize_json_request(cfg, &mut upstream)?;
-                Ok(upstream)
+                })
             }
             CountTokensMode::Tokenizers | CountTokensMode::Tiktoken => {
                 let model =
@@ -125,14 +162,15 @@ impl UpstreamProvider for CustomProvider {
     ) -> ProviderResult<UpstreamHttpRequest> {
         let cfg = custom_config(config)?;
         let api_key = custom_api_key(credential)?;
-        let mut url = build_url(&cfg.base_url, "/v1/models");
+        let path = upstream_path(cfg, "claude_models_list", "/v1/models", "");
+        let mut url = build_url(&cfg.base_url, &path);
         let query = build_claude_models_list_query(&req.query);
         if !query.is_empty() {
             url.push('?');
             url.push_str(&query);
         }
-        let mut headers = Vec::new();
-        auth_extractor::set_header(&mut headers, "x-api-key", api_key);
+        let mut headers = Headers::new();
+        set_auth(&mut headers, cfg, api_key, CLAUDE_AUTH);
         auth_extractor::set_accept_json(&mut headers);
         apply_anthropic_headers(&mut headers, &req.headers)?;
         Ok(UpstreamHttpRequest {
@@ -153,9 +191,15 @@ impl UpstreamProvider for CustomProvider {
     ) -> ProviderResult<UpstreamHttpRequest> {
         let cfg = custom_config(config)?;
         let api_key = custom_api_key(credential)?;
-        let url = build_url(&cfg.base_url, &format!("/v1/models/{}", req.path.model_id));
-        let mut headers = Vec::new();
-        auth_extractor::set_header(&mut headers, "x-api-key", api_key);
+        let path = upstream_path(
+            cfg,
+            "claude_models_get",
+            &format!("/v1/models/{}", req.path.model_id),
+            &req.path.model_id,
+        );
+        let url = build_url(&cfg.base_url, &path);
+        let mut headers = Headers::new();
+        set_auth(&mut headers, cfg, api_key, CLAUDE_AUTH);
         auth_extractor::set_accept_json(&mut headers);
         apply_anthropic_headers(&mut headers, &req.headers)?;
         Ok(UpstreamHttpRequest {
@@ -174,13 +218,14 @@ impl UpstreamProvider for CustomProvider {
         credential: &Credential,
         req: &gproxy_protocol::gemini::generate_content::request::GenerateContentRequest,
     ) -> ProviderResult<UpstreamHttpRequest> {
-        build_gemini_request(
-            custom_config(config)?,
-            custom_api_key(credential)?,
+        let cfg = custom_config(config)?;
+        let path = upstream_path(
+            cfg,
+            "gemini_generate",
             &format!("/v1beta/{}:generateContent", req.path.model),
-            &req.body,
-            false,
-        )
+            &req.path.model,
+        );
+        build_gemini_request(cfg, custom_api_key(credential)?, &path, &req.body, false)
     }
 
     async fn build_gemini_generate_stream(
@@ -190,13 +235,14 @@ impl UpstreamProvider for CustomProvider {
         credential: &Credential,
         req: &gproxy_protocol::gemini::stream_content::request::StreamGenerateContentRequest,
     ) -> ProviderResult<UpstreamHttpRequest> {
-        build_gemini_request(
-            custom_config(config)?,
-            custom_api_key(credential)?,
+        let cfg = custom_config(config)?;
+        let path = upstream_path(
+            cfg,
+            "gemini_generate_stream",
             &format!("/v1beta/{}:streamGenerateContent", req.path.model),
-            &req.body,
-            true,
-        )
+            &req.path.model,
+        );
+        build_gemini_request(cfg, custom_api_key(credential)?, &path, &req.body, true)
     }
 
     async fn build_gemini_count_tokens(
@@ -209,13 +255,15 @@ impl UpstreamProvider for CustomProvider {
         let cfg = custom_config(config)?;
         let api_key = custom_api_key(credential)?;
         match cfg.count_tokens {
-            CountTokensMode::Upstream => build_gemini_request(
-                cfg,
-                api_key,
-                &format!("/v1beta/{}:countTokens", req.path.model),
-                &req.body,
-                false,
-            ),
+            CountTokensMode::Upstream => {
+                let path = upstream_path(
+                    cfg,
+                    "gemini_count_tokens",
+                    &format!("/v1beta/{}:countTokens", req.path.model),
+                    &req.path.model,
+                );
+                build_gemini_request(cfg, api_key, &path, &req.body, false)
+            }
             CountTokensMode::Tokenizers | CountTokensMode::Tiktoken => {
                 let model = normalize_model_id(&req.path.model);
                 let text = serde_json::to_string(&req.body)
@@ -237,12 +285,13 @@ impl UpstreamProvider for CustomProvider {
     ) -> ProviderResult<UpstreamHttpRequest> {
         let cfg = custom_config(config)?;
         let api_key = custom_api_key(credential)?;
-        let mut url = build_url(&cfg.base_url, "/v1beta/models");
+        let path = upstream_path(cfg, "gemini_models_list", "/v1beta/models", "");
+        let mut url = build_url(&cfg.base_url, &path);
         if let Some(q) = build_gemini_list_query(&req.query) {
             url = format!("{url}?{q}");
         }
-        let mut headers = Vec::new();
-        auth_extractor::set_header(&mut headers, "x-goog-api-key", api_key);
+        let mut headers = Headers::new();
+        set_auth(&mut headers, cfg, api_key, GEMINI_AUTH);
         auth_extractor::set_accept_json(&mut headers);
         Ok(UpstreamHttpRequest {
             method: HttpMethod::Get,
@@ -262,9 +311,15 @@ impl UpstreamProvider for CustomProvider {
     ) -> ProviderResult<UpstreamHttpRequest> {
         let cfg = custom_config(config)?;
         let api_key = custom_api_key(credential)?;
-        let url = build_url(&cfg.base_url, &format!("/v1beta/{}", req.path.name));
-        let mut headers = Vec::new();
-        auth_extractor::set_header(&mut headers, "x-goog-api-key", api_key);
+        let path = upstream_path(
+            cfg,
+            "gemini_models_get",
+            &format!("/v1beta/{}", req.path.name),
+            &req.path.name,
+        );
+        let url = build_url(&cfg.base_url, &path);
+        let mut headers = Headers::new();
+        set_auth(&mut headers, cfg, api_key, GEMINI_AUTH);
         auth_extractor::set_accept_json(&mut headers);
         Ok(UpstreamHttpRequest {
             method: HttpMethod::Get,
@@ -284,22 +339,26 @@ impl UpstreamProvider for CustomProvider {
     ) -> ProviderResult<UpstreamHttpRequest> {
         let cfg = custom_config(config)?;
         let api_key = custom_api_key(credential)?;
-        let url = build_url(&cfg.base_url, "/v1/chat/completions");
+        let op = if req.body.stream.unwrap_or(false) {
+            "openai_chat_generate_stream"
+        } else {
+            "openai_chat_generate"
+        };
+        let path = upstream_path(cfg, op, "/v1/chat/completions", &req.body.model);
+        let url = build_url(&cfg.base_url, &path);
         let body =
             serde_json::to_vec(&req.body).map_err(|err| ProviderError::Other(err.to_string()))?;
-        let mut headers = Vec::new();
-        auth_extractor::set_bearer(&mut headers, api_key);
+        let mut headers = Headers::new();
+        set_auth(&mut headers, cfg, api_key, OPENAI_AUTH);
         auth_extractor::set_accept_json(&mut headers);
         auth_extractor::set_content_type_json(&mut headers);
-        let mut upstream = UpstreamHttpRequest {
+        Ok(UpstreamHttpRequest {
             method: HttpMethod::Post,
             url,
             headers,
             body: Some(Bytes::from(body)),
             is_stream: req.body.stream.unwrap_or(false),
-        };
-        finalize_json_request(cfg, &mut upstream)?;
-        Ok(upstream)
+        })
     }
 
     async fn build_openai_responses(
@@ -311,22 +370,26 @@ impl UpstreamProvider for CustomProvider {
     ) -> ProviderResult<UpstreamHttpRequest> {
         let cfg = custom_config(config)?;
         let api_key = custom_api_key(credential)?;
-        let url = build_url(&cfg.base_url, "/v1/responses");
+        let op = if req.body.stream.unwrap_or(false) {
+            "openai_response_generate_stream"
+        } else {
+            "openai_response_generate"
+        };
+        let path = upstream_path(cfg, op, "/v1/responses", &req.body.model);
+        let url = build_url(&cfg.base_url, &path);
         let body =
             serde_json::to_vec(&req.body).map_err(|err| ProviderError::Other(err.to_string()))?;
-        let mut headers = Vec::new();
-        auth_extractor::set_bearer(&mut headers, api_key);
+        let mut headers = Headers::new();
+        set_auth(&mut headers, cfg, api_key, OPENAI_AUTH);
         auth_extractor::set_accept_json(&mut headers);
         auth_extractor::set_content_type_json(&mut headers);
-        let mut upstream = UpstreamHttpRequest {
+        Ok(UpstreamHttpRequest {
             method: HttpMethod::Post,
             url,
             headers,
             body: Some(Bytes::from(body)),
             is_stream: req.body.stream.unwrap_or(false),
-        };
-        finalize_json_request(cfg, &mut upstream)?;
-        Ok(upstream)
+        })
     }
 
     async fn build_openai_input_tokens(
@@ -340,22 +403,26 @@ impl UpstreamProvider for CustomProvider {
         let api_key = custom_api_key(credential)?;
         match cfg.count_tokens {
             CountTokensMode::Upstream => {
-                let url = build_url(&cfg.base_url, "/v1/responses/input_tokens");
+                let path = upstream_path(
+                    cfg,
+                    "openai_input_tokens",
+                    "/v1/responses/input_tokens",
+                    &req.body.model,
+                );
+                let url = build_url(&cfg.base_url, &path);
                 let body = serde_json::to_vec(&req.body)
                     .map_err(|err| ProviderError::Other(err.to_string()))?;
-                let mut headers = Vec::new();
-                auth_extractor::set_bearer(&mut headers, api_key);
+                let mut headers = Headers::new();
+                set_auth(&mut headers, cfg, api_key, OPENAI_AUTH);
                 auth_extractor::set_accept_json(&mut headers);
                 auth_extractor::set_content_type_json(&mut headers);
-                let mut upstream = UpstreamHttpRequest {
+                Ok(UpstreamHttpRequest {
                     method: HttpMethod::Post,
                     url,
                     headers,
                     body: Some(Bytes::from(body)),
                     is_stream: false,
-                };
-                finalize_json_request(cfg, &mut upstream)?;
-                Ok(upstream)
+                })
             }
             CountTokensMode::Tokenizers | CountTokensMode::Tiktoken => {
                 let text = serde_json::to_string(&req.body)
@@ -380,9 +447,10 @@ impl UpstreamProvider for CustomProvider {
     ) -> ProviderResult<UpstreamHttpRequest> {
         let cfg = custom_config(config)?;
         let api_key = custom_api_key(credential)?;
-        let url = build_url(&cfg.base_url, "/v1/models");
-        let mut headers = Vec::new();
-        auth_extractor::set_bearer(&mut headers, api_key);
+        let path = upstream_path(cfg, "openai_models_list", "/v1/models", "");
+        let url = build_url(&cfg.base_url, &path);
+        let mut headers = Headers::new();
+        set_auth(&mut headers, cfg, api_key, OPENAI_AUTH);
         auth_extractor::set_accept_json(&mut headers);
         Ok(UpstreamHttpRequest {
             method: HttpMethod::Get,
@@ -402,9 +470,15 @@ impl UpstreamProvider for CustomProvider {
     ) -> ProviderResult<UpstreamHttpRequest> {
         let cfg = custom_config(config)?;
         let api_key = custom_api_key(credential)?;
-        let url = build_url(&cfg.base_url, &format!("/v1/models/{}", req.path.model));
-        let mut headers = Vec::new();
-        auth_extractor::set_bearer(&mut headers, api_key);
+        let path = upstream_path(
+            cfg,
+            "openai_models_get",
+            &format!("/v1/models/{}", req.path.model),
+            &req.path.model,
+        );
+        let url = build_url(&cfg.base_url, &path);
+        let mut headers = Headers::new();
+        set_auth(&mut headers, cfg, api_key, OPENAI_AUTH);
         auth_extractor::set_accept_json(&mut headers);
         Ok(UpstreamHttpRequest {
             method: HttpMethod::Get,
@@ -423,6 +497,18 @@ impl UpstreamProvider for CustomProvider {
         req: &Request,
     ) -> ProviderResult<Option<UpstreamHttpResponse>> {
         let cfg = custom_config(config)?;
+        if let Some(model) = request_model(req)
+            && !cfg.allows_model(&model)
+        {
+            let body = serde_json::to_vec(&json!({
+                "error": {
+                    "type": "model_not_allowed",
+                    "message": format!("model `{model}` is not allowed for this provider"),
+                }
+            }))
+            .map_err(|err| ProviderError::Other(err.to_string()))?;
+            return Ok(Some(local_json_response(403, body)));
+        }
         let Some(table) = cfg.model_table.as_ref() else {
             return Ok(None);
         };
@@ -516,6 +602,21 @@ impl UpstreamProvider for CustomProvider {
             _ => Ok(None),
         }
     }
+
+    async fn build_raw_passthrough(
+        &self,
+        _ctx: &UpstreamCtx,
+        config: &ProviderConfig,
+        credential: &Credential,
+        req: &RawPassthroughRequest,
+    ) -> ProviderResult<UpstreamHttpRequest> {
+        let cfg = custom_config(config)?;
+        let api_key = custom_api_key(credential)?;
+        let url = build_url(&cfg.base_url, &req.path);
+        let mut upstream = passthrough::forward(url, req);
+        set_auth(&mut upstream.headers, cfg, api_key, OPENAI_AUTH);
+        Ok(upstream)
+    }
 }
 
 fn custom_config(config: &ProviderConfig) -> ProviderResult<&CustomProviderConfig> {
@@ -536,6 +637,53 @@ fn custom_api_key(credential: &Credential) -> ProviderResult<&str> {
     }
 }
 
+/// How a protocol authenticates when the config has no `auth` override.
+#[derive(Debug, Clone, Copy)]
+enum ProtoAuth {
+    Header(&'static str),
+    Bearer,
+}
+
+const CLAUDE_AUTH: ProtoAuth = ProtoAuth::Header("x-api-key");
+const GEMINI_AUTH: ProtoAuth = ProtoAuth::Header("x-goog-api-key");
+const OPENAI_AUTH: ProtoAuth = ProtoAuth::Bearer;
+
+fn set_auth(headers: &mut Headers, cfg: &CustomProviderConfig, api_key: &str, fallback: ProtoAuth) {
+    match (cfg.auth.as_ref(), fallback) {
+        (Some(auth), _) => auth_extractor::set_header(headers, &auth.header, &auth.value(api_key)),
+        (None, ProtoAuth::Header(name)) => auth_extractor::set_header(headers, name, api_key),
+        (None, ProtoAuth::Bearer) => auth_extractor::set_bearer(headers, api_key),
+    }
+}
+
+/// The configured path for `op` with `{model}` filled in, or the protocol default.
+fn upstream_path(cfg: &CustomProviderConfig, op: &str, default: &str, model: &str) -> String {
+    match cfg.paths.get(op) {
+        Some(template) => template.replace("{model}", &normalize_model_id(model)),
+        None => default.to_string(),
+    }
+}
+
+fn request_model(req: &Request) -> Option<String> {
+    let model = match req {
+        Request::GenerateContent(GenerateContentRequest::Claude(r)) => {
+            model_to_string(&r.body.model)?
+        }
+        Request::GenerateContent(GenerateContentRequest::OpenAIChat(r)) => r.body.model.clone(),
+        Request::GenerateContent(GenerateContentRequest::OpenAIResponse(r)) => r.body.model.clone(),
+        Request::GenerateContent(GenerateContentRequest::Gemini(r)) => r.path.model.clone(),
+        Request::GenerateContent(GenerateContentRequest::GeminiStream(r)) => r.path.model.clone(),
+        Request::CountTokens(CountTokensRequest::Claude(r)) => model_to_string(&r.body.model)?,
+        Request::CountTokens(CountTokensRequest::OpenAI(r)) => r.body.model.clone(),
+        Request::CountTokens(CountTokensRequest::Gemini(r)) => r.path.model.clone(),
+        Request::ModelGet(ModelGetRequest::Claude(r)) => r.path.model_id.clone(),
+        Request::ModelGet(ModelGetRequest::OpenAI(r)) => r.path.model.clone(),
+        Request::ModelGet(ModelGetRequest::Gemini(r)) => r.path.name.clone(),
+        _ => return None,
+    };
+    Some(normalize_model_id(&model))
+}
+
 fn count_text_tiktoken(model: &str, text: &str) -> ProviderResult<i64> {
     let bpe = get_bpe_from_model(model)
         .or_else(|_| o200k_base())
@@ -552,19 +700,17 @@ fn build_gemini_request<T: serde::Serialize>(
 ) -> ProviderResult<UpstreamHttpRequest> {
     let url = build_url(&cfg.base_url, path);
     let body = serde_json::to_vec(body).map_err(|err| ProviderError::Other(err.to_string()))?;
-    let mut headers = Vec::new();
-    auth_extractor::set_header(&mut headers, "x-goog-api-key", api_key);
+    let mut headers = Headers::new();
+    set_auth(&mut headers, cfg, api_key, GEMINI_AUTH);
     auth_extractor::set_accept_json(&mut headers);
     auth_extractor::set_content_type_json(&mut headers);
-    let mut upstream = UpstreamHttpRequest {
+    Ok(UpstreamHttpRequest {
         method: HttpMethod::Post,
         url,
         headers,
         body: Some(Bytes::from(body)),
         is_stream,
-    };
-    finalize_json_request(cfg, &mut upstream)?;
-    Ok(upstream)
+    })
 }
 
 fn finalize_json_request(
@@ -580,6 +726,30 @@ fn finalize_json_request(
     apply_json_param_mask(&cfg.json_param_mask, req)
 }
 
+fn sign_request(
+    signing: &RequestSigning,
+    timestamp: u64,
+    req: &mut UpstreamHttpRequest,
+) -> ProviderResult<()> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(signing.secret.as_bytes())
+        .map_err(|err| ProviderError::InvalidConfig(format!("request_signing: {err}")))?;
+    if let Some(header) = signing.timestamp_header.as_deref() {
+        let timestamp = timestamp.to_string();
+        mac.update(timestamp.as_bytes());
+        mac.update(b".");
+        header_set(&mut req.headers, header, &timestamp);
+    }
+    mac.update(req.body.as_deref().unwrap_or_default());
+    let signature: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    header_set(&mut req.headers, &signing.header, &signature);
+    Ok(())
+}
+
 fn is_json_content_type(req: &UpstreamHttpRequest) -> bool {
     header_get(&req.headers, "content-type")
         .map(|v| v.to_ascii_lowercase().contains("application/json"))
@@ -851,7 +1021,7 @@ fn build_gemini_list_query(
 }
 
 fn local_json_request(body: Vec<u8>) -> UpstreamHttpRequest {
-    let mut headers = Vec::new();
+    let mut headers = Headers::new();
     auth_extractor::set_accept_json(&mut headers);
     auth_extractor::set_content_type_json(&mut headers);
     UpstreamHttpRequest {
@@ -864,7 +1034,7 @@ fn local_json_request(body: Vec<u8>) -> UpstreamHttpRequest {
 }
 
 fn local_json_response(status: u16, body: Vec<u8>) -> UpstreamHttpResponse {
-    let mut headers = Vec::new();
+    let mut headers = Headers::new();
     header_set(&mut headers, "content-type", "application/json");
     UpstreamHttpResponse {
         status,
@@ -988,7 +1158,7 @@ mod tests {
         let mut req = UpstreamHttpRequest {
             method: HttpMethod::Post,
             url: "https://example.com/v1/chat/completions".to_string(),
-            headers: vec![("content-type".to_string(), "application/json".to_string())],
+            headers: vec![("content-type".to_string(), "application/json".to_string())].into(),
             body: Some(Bytes::from(
                 serde_json::to_vec(&json!({
                     "model": "gpt-4o-mini",
@@ -1014,6 +1184,70 @@ mod tests {
         assert_eq!(body["model"], json!("gpt-4o-mini"));
     }
 
+    #[test]
+    fn sign_request_sets_hmac_sha256_header() {
+        let mut req = UpstreamHttpRequest {
+            method: HttpMethod::Post,
+            url: "https://example.com/v1/chat/completions".to_string(),
+            headers: Headers::new(),
+            body: Some(Bytes::from_static(b"what do ya want for nothing?")),
+            is_stream: false,
+        };
+        let mut signing = RequestSigning {
+            header: "x-signature".to_string(),
+            secret: "Jefe".to_string(),
+            timestamp_header: None,
+        };
+        sign_request(&signing, 0, &mut req).unwrap();
+        // RFC 4231 test case 2.
+        assert_eq!(
+            header_get(&req.headers, "x-signature"),
+            Some("5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843")
+        );
+
+        signing.timestamp_header = Some("x-timestamp".to_string());
+        sign_request(&signing, 1_700_000_000, &mut req).unwrap();
+        assert_eq!(header_get(&req.headers, "x-timestamp"), Some("1700000000"));
+        assert_ne!(
+            header_get(&req.headers, "x-signature"),
+            Some("5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843")
+        );
+    }
+
+    #[test]
+    fn declarative_config_drives_auth_paths_and_models() {
+        let cfg: CustomProviderConfig = serde_json::from_value(json!({
+            "id": "compat",
+            "enabled": true,
+            "proto": "openai_chat",
+            "base_url": "https://llm.example.com",
+            "dispatch": DispatchTable::default(),
+            "auth": { "header": "authorization", "template": "Token {api_key}" },
+            "paths": { "openai_chat_generate": "/deployments/{model}/chat" },
+            "allowed_models": ["gpt-4o", "llama-*"],
+        }))
+        .unwrap();
+
+        let mut headers = Headers::new();
+        set_auth(&mut headers, &cfg, "sk-1", OPENAI_AUTH);
+        assert_eq!(header_get(&headers, "authorization"), Some("Token sk-1"));
+        assert_eq!(
+            upstream_path(
+                &cfg,
+                "openai_chat_generate",
+                "/v1/chat/completions",
+                "models/llama-3"
+            ),
+            "/deployments/llama-3/chat"
+        );
+        assert_eq!(
+            upstream_path(&cfg, "openai_models_list", "/v1/models", ""),
+            "/v1/models"
+        );
+        assert!(cfg.allows_model("gpt-4o") && cfg.allows_model("llama-3-70b"));
+        assert!(!cfg.allows_model("gpt-4o-mini"));
+    }
+
     #[test]
     fn parse_json_mask_path_supports_json_pointer() {
         let path = parse_json_mask_path("/messages/0/content").unwrap();
@@ -2,12 +2,12 @@ use bytes::Bytes;
 use serde::Serialize;
 
 use gproxy_provider_core::{
-    Credential, DispatchRule, DispatchTable, HttpMethod, Proto, ProviderConfig, ProviderError,
-    ProviderResult, UpstreamCtx, UpstreamHttpRequest, UpstreamProvider,
-    credential::ApiKeyCredential,
+    Credential, DispatchRule, DispatchTable, Headers, HttpMethod, Proto, ProviderConfig,
+    ProviderError, ProviderResult, RawPassthroughRequest, UpstreamCtx, UpstreamHttpRequest,
+    UpstreamProvider, credential::ApiKeyCredential,
 };
 
-use crate::auth_extractor;
+use crate::{auth_extractor, passthrough};
 
 const PROVIDER_NAME: &str = "deepseek";
 const DEFAULT_BASE_URL: &str = "https://api.deepseek.com";
@@ -97,7 +97,7 @@ impl UpstreamProvider for DeepSeekProvider {
         let is_stream = req.body.stream.unwrap_or(false);
         let body =
             serde_json::to_vec(&req.body).map_err(|err| ProviderError::Other(err.to_string()))?;
-        let mut headers = Vec::new();
+        let mut headers = Headers::new();
         auth_extractor::set_header(&mut headers, "x-api-key", api_key);
         auth_extractor::set_accept_json(&mut headers);
         auth_extractor::set_content_type_json(&mut headers);
@@ -124,7 +124,7 @@ impl UpstreamProvider for DeepSeekProvider {
         let is_stream = req.body.stream.unwrap_or(false);
         let body =
             serde_json::to_vec(&req.body).map_err(|err| ProviderError::Other(err.to_string()))?;
-        let mut headers = Vec::new();
+        let mut headers = Headers::new();
         auth_extractor::set_bearer(&mut headers, api_key);
         auth_extractor::set_accept_json(&mut headers);
         auth_extractor::set_content_type_json(&mut headers);
@@ -189,6 +189,21 @@ impl UpstreamProvider for DeepSeekProvider {
             serde_json::to_vec(&found).map_err(|err| ProviderError::Other(err.to_string()))?;
         Ok(local_json_request(body))
     }
+
+    async fn build_raw_passthrough(
+        &self,
+        _ctx: &UpstreamCtx,
+        config: &ProviderConfig,
+        credential: &Credential,
+        req: &RawPassthroughRequest,
+    ) -> ProviderResult<UpstreamHttpRequest> {
+        let base_url = deepseek_base_url(config)?;
+        let api_key = deepseek_api_key(credential)?;
+        let url = build_url(Some(base_url), DEFAULT_BASE_URL, &req.path);
+        let mut upstream = passthrough::forward(url, req);
+        auth_extractor::set_bearer(&mut upstream.headers, api_key);
+        Ok(upstream)
+    }
 }
 
 fn deepseek_base_url(config: &ProviderConfig) -> ProviderResult<&str> {
@@ -210,7 +225,7 @@ fn deepseek_api_key(credential: &Credential) -> ProviderResult<&str> {
 }
 
 fn local_json_request(body: Vec<u8>) -> UpstreamHttpRequest {
-    let mut headers = Vec::new();
+    let mut headers = Headers::new();
     auth_extractor::set_accept_json(&mut headers);
     auth_extractor::set_content_type_json(&mut headers);
     UpstreamHttpRequest {
@@ -6,17 +6,20 @@ use rand::RngCore;
 use serde::Deserialize;
 use serde_json::Value as JsonValue;
 
+use gproxy_provider_core::provider::InternalEventUnwrap;
 use gproxy_provider_core::{
-    AuthRetryAction, Credential, DispatchRule, DispatchTable, HttpMethod, ModelGetRequest,
+    AuthRetryAction, Credential, DispatchRule, DispatchTable, Headers, HttpMethod, ModelGetRequest,
     ModelListRequest, OAuthCallbackRequest, OAuthCallbackResult, OAuthCredential,
-    OAuthStartRequest, Proto, ProviderConfig, ProviderError, ProviderResult, Request, UpstreamBody,
-    UpstreamCtx, UpstreamHttpRequest, UpstreamHttpResponse, UpstreamProvider, header_set,
+    OAuthStartRequest, Op, Proto, ProviderConfig, ProviderError, ProviderResult, Request,
+    UpstreamBody, UpstreamCtx, UpstreamHttpRequest, UpstreamHttpResponse, UpstreamProvider,
+    header_set,
 };
 
 use gproxy_protocol::gemini;
 
 use crate::auth_extractor;
 use crate::providers::http_client::{SharedClientKind, client_for_ctx};
+use crate::providers::internal_envelope;
 mod oauth;
 mod usage;
 
@@ -225,6 +228,20 @@ impl UpstreamProvider for GeminiCliProvider {
         }
     }
 
+    fn unwrap_internal_response(&self, proto: Proto, op: Op, body: Bytes) -> ProviderResult<Bytes> {
+        if proto == Proto::Gemini && op == Op::GenerateContent {
+            internal_envelope::unwrap_response(body, false)
+        } else {
+            Ok(body)
+        }
+    }
+
+    fn unwrap_internal_stream(&self, proto: Proto) -> Option<InternalEventUnwrap> {
+        (proto == Proto::Gemini).then_some(
+            (|data: &str| internal_envelope::unwrap_event(data, false)) as InternalEventUnwrap,
+        )
+    }
+
     fn oauth_start(
         &self,
         ctx: &UpstreamCtx,
@@ -349,7 +366,7 @@ fn build_gemini_request<T: serde::Serialize>(
     let access_token = geminicli_access_token(credential)?;
     let url = build_url(Some(base_url), DEFAULT_BASE_URL, path);
     let body = serde_json::to_vec(body).map_err(|err| ProviderError::Other(err.to_string()))?;
-    let mut headers = Vec::new();
+    let mut headers = Headers::new();
     auth_extractor::set_bearer(&mut headers, access_token);
     auth_extractor::set_accept_json(&mut headers);
     auth_extractor::set_content_type_json(&mut headers);
@@ -413,7 +430,7 @@ fn find_model_value(list: &JsonValue, target: &str) -> Option<JsonValue> {
 }
 
 fn local_json_response(status: u16, body: Vec<u8>) -> UpstreamHttpResponse {
-    let mut headers = Vec::new();
+    let mut headers = Headers::new();
     header_set(&mut headers, "content-type", "application/json");
     UpstreamHttpResponse {
         status,
@@ -461,7 +478,7 @@ fn wrap_internal_request(
 }
 
 fn json_response(body: serde_json::Value) -> UpstreamHttpResponse {
-    let mut headers = Vec::new();
+    let mut headers = Headers::new();
     header_set(&mut headers, "content-type", "application/json");
     let bytes = Bytes::from(serde_json::to_vec(&body).unwrap_or_default());
     UpstreamHttpResponse {
@@ -472,7 +489,7 @@ fn json_response(body: serde_json::Value) -> UpstreamHttpResponse {
 }
 
 fn json_error(status: u16, message: &str) -> UpstreamHttpResponse {
-    let mut headers = Vec::new();
+    let mut headers = Headers::new();
     header_set(&mut headers, "content-type", "application/json");
     let bytes = Bytes::from(
         serde_json::to_vec(&serde_json::json!({ "error": message })).unwrap_or_default(),
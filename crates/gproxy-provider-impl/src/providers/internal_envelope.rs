@@ -0,0 +1,149 @@
+//! The envelope Code Assist (`v1internal`) endpoints wrap Gemini responses in:
+//! `{"response": {...}}`, sometimes nested twice, with stream events carrying either one
+//! wrapped chunk or an array of them. Used by the Gemini CLI and Antigravity providers.
+
+use bytes::Bytes;
+use serde_json::Value as JsonValue;
+
+use gproxy_provider_core::{ProviderError, ProviderResult};
+
+/// The bare Gemini body of a wrapped non-stream response. `fill_parts` adds the
+/// `parts` that some upstreams leave out of a candidate's `content`.
+pub fn unwrap_response(body: Bytes, fill_parts: bool) -> ProviderResult<Bytes> {
+    let value: JsonValue = serde_json::from_slice(&body)
+        .map_err(|err| ProviderError::Other(format!("json_decode_failed: {err}")))?;
+    let mut value = unwrap_value(value);
+    if fill_parts {
+        fill_missing_parts(&mut value);
+    }
+    serde_json::to_vec(&value)
+        .map(Bytes::from)
+        .map_err(|err| ProviderError::Other(format!("json_encode_failed: {err}")))
+}
+
+/// SSE frames of the bare Gemini chunks in one wrapped stream event; data that is not
+/// JSON passes through as is.
+pub fn unwrap_event(data: &str, fill_parts: bool) -> Vec<Bytes> {
+    if data == "[DONE]" {
+        return vec![Bytes::from_static(b"data: [DONE]\n\n")];
+    }
+    let value: JsonValue = match serde_json::from_str(data) {
+        Ok(value) => value,
+        Err(_) => return vec![sse_frame(data.as_bytes())],
+    };
+    let mut value = unwrap_value(value);
+    if fill_parts {
+        fill_missing_parts(&mut value);
+    }
+    let items = match value {
+        JsonValue::Array(items) => items
+            .into_iter()
+            .map(|item| {
+                let mut item = unwrap_value(item);
+                if fill_parts {
+                    fill_missing_parts(&mut item);
+                }
+                item
+            })
+            .collect(),
+        other => vec![other],
+    };
+    items
+        .iter()
+        .filter_map(|item| serde_json::to_vec(item).ok())
+        .map(|payload| sse_frame(&payload))
+        .collect()
+}
+
+fn unwrap_value(value: JsonValue) -> JsonValue {
+    match value {
+        JsonValue::Object(mut map) => match map.remove("response") {
+            Some(JsonValue::Object(mut inner)) => match inner.remove("response") {
+                Some(nested) => nested,
+                None => JsonValue::Object(inner),
+            },
+            Some(inner) => inner,
+            None => JsonValue::Object(map),
+        },
+        other => other,
+    }
+}
+
+fn fill_missing_parts(value: &mut JsonValue) {
+    match value {
+        JsonValue::Object(map) => {
+            if let Some(JsonValue::Array(candidates)) = map.get_mut("candidates") {
+                for candidate in candidates {
+                    if let JsonValue::Object(candidate) = candidate
+                        && let Some(JsonValue::Object(content)) = candidate.get_mut("content")
+                    {
+                        content
+                            .entry("parts")
+                            .or_insert_with(|| JsonValue::Array(Vec::new()));
+                    }
+                }
+            }
+        }
+        JsonValue::Array(items) => {
+            for item in items {
+                fill_missing_parts(item);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn sse_frame(payload: &[u8]) -> Bytes {
+    let mut frame = Vec::with_capacity(payload.len() + 8);
+    frame.extend_from_slice(b"data: ");
+    frame.extend_from_slice(payload);
+    frame.extend_from_slice(b"\n\n");
+    Bytes::from(frame)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn responses_lose_their_envelope() {
+        let body = json!({
+            "response": { "response": { "candidates": [{ "content": { "role": "model" } }] } },
+        });
+        let out = unwrap_response(Bytes::from(body.to_string()), true).unwrap();
+        let out: JsonValue = serde_json::from_slice(&out).unwrap();
+        assert_eq!(out["candidates"][0]["content"]["parts"], json!([]));
+
+        let out = unwrap_response(Bytes::from(body.to_string()), false).unwrap();
+        let out: JsonValue = serde_json::from_slice(&out).unwrap();
+        assert!(out["candidates"][0]["content"].get("parts").is_none());
+
+        assert!(unwrap_response(Bytes::from_static(b"not json"), false).is_err());
+    }
+
+    #[test]
+    fn stream_events_split_into_bare_chunks() {
+        let data = json!([
+            { "response": { "candidates": [{ "index": 0 }] } },
+            { "response": { "candidates": [{ "index": 1 }] } },
+        ]);
+        let frames = unwrap_event(&data.to_string(), false);
+        assert_eq!(
+            frames,
+            [
+                Bytes::from_static(b"data: {\"candidates\":[{\"index\":0}]}\n\n"),
+                Bytes::from_static(b"data: {\"candidates\":[{\"index\":1}]}\n\n"),
+            ]
+        );
+        assert_eq!(
+            unwrap_event("[DONE]", true),
+            [Bytes::from_static(b"data: [DONE]\n\n")]
+        );
+        assert_eq!(
+            unwrap_event("keep-alive", true),
+            [Bytes::from_static(b"data: keep-alive\n\n")]
+        );
+    }
+}
@@ -7,6 +7,7 @@ mod custom;
 mod deepseek;
 mod geminicli;
 mod http_client;
+mod internal_envelope;
 mod nvidia;
 mod oauth_common;
 mod openai;
@@ -0,0 +1,111 @@
+//! The NIM model catalog, fetched from `/v1/models` and kept for a while so that model
+//! lookups do not each go upstream. NIM ids contain a `/` (`meta/llama-3.1-8b-instruct`),
+//! which its per-model endpoint does not resolve, so model gets are answered from here.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use serde_json::{Value as JsonValue, json};
+
+use gproxy_provider_core::{ProviderError, UpstreamCtx};
+
+use crate::providers::http_client::{SharedClientKind, client_for_ctx};
+
+/// How long a fetched catalog is served before it is fetched again.
+const CATALOG_TTL: Duration = Duration::from_secs(10 * 60);
+
+struct CachedCatalog {
+    fetched: Instant,
+    models: Vec<JsonValue>,
+}
+
+/// Models listed at `base_url`, from the cache while it is fresh.
+pub fn models(
+    ctx: &UpstreamCtx,
+    base_url: &str,
+    api_key: &str,
+) -> Result<Vec<JsonValue>, ProviderError> {
+    let cache = catalog_cache();
+    {
+        let guard = cache
+            .lock()
+            .map_err(|_| ProviderError::Other("catalog lock failed".to_string()))?;
+        if let Some(cached) = guard.get(base_url)
+            && cached.fetched.elapsed() < CATALOG_TTL
+        {
+            return Ok(cached.models.clone());
+        }
+    }
+
+    let url = super::build_url(Some(base_url), super::DEFAULT_BASE_URL, "/v1/models");
+    let body = crate::providers::oauth_common::block_on(fetch_catalog(ctx, url, api_key))?;
+    let models = models_in_list(&body)
+        .ok_or_else(|| ProviderError::Other("model catalog is not a model list".to_string()))?;
+    let mut guard = cache
+        .lock()
+        .map_err(|_| ProviderError::Other("catalog lock failed".to_string()))?;
+    guard.insert(
+        base_url.to_string(),
+        CachedCatalog {
+            fetched: Instant::now(),
+            models: models.clone(),
+        },
+    );
+    Ok(models)
+}
+
+/// OpenAI model list body of `models`.
+pub fn list_json(models: Vec<JsonValue>) -> JsonValue {
+    json!({ "object": "list", "data": models })
+}
+
+/// The catalog entry of `model`, matched with or without a leading `models/`.
+pub fn find(models: &[JsonValue], model: &str) -> Option<JsonValue> {
+    let model = model.trim_start_matches('/').trim_start_matches("models/");
+    models
+        .iter()
+        .find(|entry| entry.get("id").and_then(JsonValue::as_str) == Some(model))
+        .cloned()
+}
+
+fn models_in_list(body: &JsonValue) -> Option<Vec<JsonValue>> {
+    body.get("data")?.as_array().map(|models| {
+        models
+            .iter()
+            .filter(|entry| entry.get("id").and_then(JsonValue::as_str).is_some())
+            .cloned()
+            .collect()
+    })
+}
+
+fn catalog_cache() -> &'static Mutex<HashMap<String, CachedCatalog>> {
+    static CACHE: OnceLock<Mutex<HashMap<String, CachedCatalog>>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+async fn fetch_catalog(
+    ctx: &UpstreamCtx,
+    url: String,
+    api_key: &str,
+) -> Result<JsonValue, ProviderError> {
+    let client = client_for_ctx(ctx, SharedClientKind::Global)?;
+    let resp = client
+        .get(url)
+        .header("Authorization", format!("Bearer {api_key}"))
+        .header("Accept", "application/json")
+        .send()
+        .await
+        .map_err(|err| ProviderError::Other(err.to_string()))?;
+    let status = resp.status();
+    let body = resp
+        .bytes()
+        .await
+        .map_err(|err| ProviderError::Other(err.to_string()))?;
+    if !status.is_success() {
+        return Err(ProviderError::Other(format!(
+            "model catalog fetch failed: {status}"
+        )));
+    }
+    serde_json::from_slice(&body).map_err(|err| ProviderError::Other(err.to_string()))
+}
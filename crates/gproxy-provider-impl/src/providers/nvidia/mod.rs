@@ -1,13 +1,20 @@
 use bytes::Bytes;
 
+use gproxy_provider_core::provider::{
+    ByteStream, UnavailableDecision, UpstreamFailure, default_decide_unavailable,
+};
 use gproxy_provider_core::{
-    Credential, DispatchRule, DispatchTable, HttpMethod, Proto, ProviderConfig, ProviderError,
-    ProviderResult, UpstreamCtx, UpstreamHttpRequest, UpstreamProvider,
-    credential::ApiKeyCredential,
+    Credential, DispatchRule, DispatchTable, Headers, HttpMethod, ModelGetRequest,
+    ModelListRequest, Op, Proto, ProviderConfig, ProviderError, ProviderResult,
+    RawPassthroughRequest, Request, UnavailableReason, UpstreamBody, UpstreamCtx,
+    UpstreamHttpRequest, UpstreamHttpResponse, UpstreamProvider, credential::ApiKeyCredential,
+    header_set,
 };
 
-use crate::auth_extractor;
+use crate::{auth_extractor, passthrough};
 
+mod catalog;
+mod quirks;
 mod tokenizer;
 
 const PROVIDER_NAME: &str = "nvidia";
@@ -86,6 +93,96 @@ impl UpstreamProvider for NvidiaProvider {
         DISPATCH_TABLE
     }
 
+    fn decide_unavailable(
+        &self,
+        _ctx: &UpstreamCtx,
+        _config: &ProviderConfig,
+        _credential: &Credential,
+        _req: &Request,
+        failure: &UpstreamFailure,
+    ) -> Option<UnavailableDecision> {
+        if let UpstreamFailure::Http {
+            status: 429, body, ..
+        } = failure
+            && let Some(duration) = quirks::retry_after(body)
+        {
+            return Some(UnavailableDecision {
+                duration,
+                reason: UnavailableReason::RateLimit,
+            });
+        }
+        default_decide_unavailable(failure)
+    }
+
+    fn local_response(
+        &self,
+        ctx: &UpstreamCtx,
+        config: &ProviderConfig,
+        credential: &Credential,
+        req: &Request,
+    ) -> ProviderResult<Option<UpstreamHttpResponse>> {
+        let model = match req {
+            Request::ModelList(ModelListRequest::OpenAI(_)) => None,
+            Request::ModelGet(ModelGetRequest::OpenAI(r)) => Some(r.path.model.as_str()),
+            _ => return Ok(None),
+        };
+        let base_url = nvidia_base_url(config)?;
+        let api_key = nvidia_api_key(credential)?;
+        // Without a catalog the request goes upstream and reports the failure itself.
+        let Ok(models) = catalog::models(ctx, base_url, api_key) else {
+            return Ok(None);
+        };
+        let body = match model {
+            None => catalog::list_json(models),
+            Some(model) => match catalog::find(&models, model) {
+                Some(entry) => entry,
+                None => {
+                    let body = serde_json::to_vec(&serde_json::json!({
+                        "error": {
+                            "type": "invalid_request_error",
+                            "code": "model_not_found",
+                            "message": format!("model `{model}` is not in the NIM catalog"),
+                        }
+                    }))
+                    .map_err(|err| ProviderError::Other(err.to_string()))?;
+                    return Ok(Some(local_json_response(404, body)));
+                }
+            },
+        };
+        let body =
+            serde_json::to_vec(&body).map_err(|err| ProviderError::Other(err.to_string()))?;
+        Ok(Some(local_json_response(200, body)))
+    }
+
+    fn normalize_nonstream_response(
+        &self,
+        _ctx: &UpstreamCtx,
+        _config: &ProviderConfig,
+        _credential: &Credential,
+        proto: Proto,
+        op: Op,
+        _req: &Request,
+        body: Bytes,
+    ) -> ProviderResult<Bytes> {
+        if proto == Proto::OpenAIChat && op == Op::GenerateContent {
+            return Ok(quirks::normalize_completion(body));
+        }
+        Ok(body)
+    }
+
+    fn normalize_stream_response(
+        &self,
+        _config: &ProviderConfig,
+        proto: Proto,
+        op: Op,
+        rx: ByteStream,
+    ) -> ByteStream {
+        if proto == Proto::OpenAIChat && op == Op::StreamGenerateContent {
+            return quirks::normalize_stream(rx);
+        }
+        rx
+    }
+
     async fn build_openai_chat(
         &self,
         _ctx: &UpstreamCtx,
@@ -99,7 +196,7 @@ impl UpstreamProvider for NvidiaProvider {
         let is_stream = req.body.stream.unwrap_or(false);
         let body =
             serde_json::to_vec(&req.body).map_err(|err| ProviderError::Other(err.to_string()))?;
-        let mut headers = Vec::new();
+        let mut headers = Headers::new();
         auth_extractor::set_bearer(&mut headers, api_key);
         auth_extractor::set_accept_json(&mut headers);
         auth_extractor::set_content_type_json(&mut headers);
@@ -151,7 +248,7 @@ impl UpstreamProvider for NvidiaProvider {
         let base_url = nvidia_base_url(config)?;
         let api_key = nvidia_api_key(credential)?;
         let url = build_url(Some(base_url), DEFAULT_BASE_URL, "/v1/models");
-        let mut headers = Vec::new();
+        let mut headers = Headers::new();
         auth_extractor::set_bearer(&mut headers, api_key);
         auth_extractor::set_accept_json(&mut headers);
         Ok(UpstreamHttpRequest {
@@ -177,7 +274,7 @@ impl UpstreamProvider for NvidiaProvider {
             DEFAULT_BASE_URL,
             &format!("/v1/models/{}", req.path.model),
         );
-        let mut headers = Vec::new();
+        let mut headers = Headers::new();
         auth_extractor::set_bearer(&mut headers, api_key);
         auth_extractor::set_accept_json(&mut headers);
         Ok(UpstreamHttpRequest {
@@ -188,6 +285,21 @@ impl UpstreamProvider for NvidiaProvider {
             is_stream: false,
         })
     }
+
+    async fn build_raw_passthrough(
+        &self,
+        _ctx: &UpstreamCtx,
+        config: &ProviderConfig,
+        credential: &Credential,
+        req: &RawPassthroughRequest,
+    ) -> ProviderResult<UpstreamHttpRequest> {
+        let base_url = nvidia_base_url(config)?;
+        let api_key = nvidia_api_key(credential)?;
+        let url = build_url(Some(base_url), DEFAULT_BASE_URL, &req.path);
+        let mut upstream = passthrough::forward(url, req);
+        auth_extractor::set_bearer(&mut upstream.headers, api_key);
+        Ok(upstream)
+    }
 }
 
 fn nvidia_base_url(config: &ProviderConfig) -> ProviderResult<&str> {
@@ -230,7 +342,7 @@ fn nvidia_data_dir(config: &ProviderConfig) -> Option<&str> {
 }
 
 fn local_json_request(body: Vec<u8>) -> UpstreamHttpRequest {
-    let mut headers = Vec::new();
+    let mut headers = Headers::new();
     auth_extractor::set_accept_json(&mut headers);
     auth_extractor::set_content_type_json(&mut headers);
     UpstreamHttpRequest {
@@ -242,6 +354,16 @@ fn local_json_request(body: Vec<u8>) -> UpstreamHttpRequest {
     }
 }
 
+fn local_json_response(status: u16, body: Vec<u8>) -> UpstreamHttpResponse {
+    let mut headers = Headers::new();
+    header_set(&mut headers, "content-type", "application/json");
+    UpstreamHttpResponse {
+        status,
+        headers,
+        body: UpstreamBody::Bytes(Bytes::from(body)),
+    }
+}
+
 fn build_url(base_url: Option<&str>, default_base: &str, path: &str) -> String {
     let base = base_url.unwrap_or(default_base).trim_end_matches('/');
     let mut path = path.trim_start_matches('/');
@@ -0,0 +1,274 @@
+//! NIM chat completions that stray from the OpenAI wire shape.
+//!
+//! Depending on the model, NIM ends tool-calling turns with `finish_reason: "stop"` or its
+//! own `tool_call`/`function_call`/`eos`/`max_tokens`, sends tool arguments as a JSON
+//! object instead of a string, leaves out a call's `type`, `id` or stream `index`, or
+//! answers with the legacy `function_call` message field. Those are rewritten into the
+//! standard shape before core decodes them. Its 429s carry the wait in the body as
+//! `retry_after_ms` rather than in a `Retry-After` header.
+
+use std::collections::HashSet;
+use std::time::Duration;
+
+use bytes::Bytes;
+use serde_json::{Value as JsonValue, json};
+
+use gproxy_protocol::sse::SseParser;
+use gproxy_provider_core::provider::ByteStream;
+
+/// Rewrites a non-stream chat completion body; bodies that are not JSON pass unchanged.
+pub fn normalize_completion(body: Bytes) -> Bytes {
+    let Ok(mut value) = serde_json::from_slice::<JsonValue>(&body) else {
+        return body;
+    };
+    let mut with_tools = HashSet::new();
+    normalize_chunk(&mut value, "message", &mut with_tools);
+    serde_json::to_vec(&value).map(Bytes::from).unwrap_or(body)
+}
+
+/// Rewrites every chunk of a chat completion SSE stream.
+pub fn normalize_stream(mut rx: ByteStream) -> ByteStream {
+    let (tx, rx_out) = tokio::sync::mpsc::channel::<Bytes>(32);
+    tokio::spawn(async move {
+        let mut parser = SseParser::new();
+        // Choices that have streamed a tool call, for their closing `finish_reason`.
+        let mut with_tools = HashSet::new();
+        while let Some(chunk) = rx.recv().await {
+            for ev in parser.push_bytes(&chunk) {
+                let frame = normalize_event(ev.event.as_deref(), &ev.data, &mut with_tools);
+                if tx.send(frame).await.is_err() {
+                    return;
+                }
+            }
+        }
+        for ev in parser.finish() {
+            let frame = normalize_event(ev.event.as_deref(), &ev.data, &mut with_tools);
+            if tx.send(frame).await.is_err() {
+                return;
+            }
+        }
+    });
+    rx_out
+}
+
+/// How long a 429 body asks to wait, from `retry_after_ms` at the top level or under
+/// `error`.
+pub fn retry_after(body: &[u8]) -> Option<Duration> {
+    let value: JsonValue = serde_json::from_slice(body).ok()?;
+    let field = value
+        .get("retry_after_ms")
+        .or_else(|| value.get("error")?.get("retry_after_ms"))?;
+    let ms = match field {
+        JsonValue::Number(ms) => ms.as_f64()?,
+        JsonValue::String(ms) => ms.trim().parse().ok()?,
+        _ => return None,
+    };
+    (ms.is_finite() && ms > 0.0).then(|| Duration::from_millis(ms.ceil() as u64))
+}
+
+fn normalize_event(event: Option<&str>, data: &str, with_tools: &mut HashSet<u64>) -> Bytes {
+    let data = match serde_json::from_str::<JsonValue>(data) {
+        Ok(mut value) => {
+            normalize_chunk(&mut value, "delta", with_tools);
+            value.to_string()
+        }
+        Err(_) => data.to_string(),
+    };
+    let frame = match event {
+        Some(event) => format!("event: {event}\ndata: {data}\n\n"),
+        None => format!("data: {data}\n\n"),
+    };
+    Bytes::from(frame)
+}
+
+/// Normalizes the `choices` of a completion (`key` = `message`) or a stream chunk
+/// (`key` = `delta`).
+fn normalize_chunk(value: &mut JsonValue, key: &str, with_tools: &mut HashSet<u64>) {
+    let stream = key == "delta";
+    let Some(choices) = value.get_mut("choices").and_then(JsonValue::as_array_mut) else {
+        return;
+    };
+    for (position, choice) in choices.iter_mut().enumerate() {
+        let index = choice
+            .get("index")
+            .and_then(JsonValue::as_u64)
+            .unwrap_or(position as u64);
+        if let Some(message) = choice.get_mut(key).filter(|m| m.is_object()) {
+            lift_function_call(message);
+            if let Some(calls) = message
+                .get_mut("tool_calls")
+                .and_then(JsonValue::as_array_mut)
+                .filter(|calls| !calls.is_empty())
+            {
+                for (position, call) in calls.iter_mut().enumerate() {
+                    normalize_tool_call(call, position, stream);
+                }
+                with_tools.insert(index);
+            }
+        }
+        let Some(reason) = choice.get("finish_reason").and_then(JsonValue::as_str) else {
+            continue;
+        };
+        let reason = match reason {
+            "tool_call" | "function_call" => "tool_calls",
+            "stop" | "eos" | "end_turn" | "stop_sequence" if with_tools.contains(&index) => {
+                "tool_calls"
+            }
+            "eos" | "end_turn" | "stop_sequence" => "stop",
+            "max_tokens" | "model_length" => "length",
+            _ => continue,
+        };
+        choice["finish_reason"] = json!(reason);
+    }
+}
+
+/// Turns a legacy `function_call` into a one-element `tool_calls`.
+fn lift_function_call(message: &mut JsonValue) {
+    let Some(map) = message.as_object_mut() else {
+        return;
+    };
+    if map.contains_key("tool_calls") {
+        return;
+    }
+    let Some(function) = map.remove("function_call").filter(JsonValue::is_object) else {
+        return;
+    };
+    map.insert(
+        "tool_calls".to_string(),
+        json!([{ "type": "function", "function": function }]),
+    );
+}
+
+fn normalize_tool_call(call: &mut JsonValue, position: usize, stream: bool) {
+    let Some(map) = call.as_object_mut() else {
+        return;
+    };
+    if stream && !map.contains_key("index") {
+        map.insert("index".to_string(), json!(position));
+    }
+    let index = map
+        .get("index")
+        .and_then(JsonValue::as_u64)
+        .unwrap_or(position as u64);
+    let starts_call = map
+        .get("function")
+        .and_then(|function| function.get("name"))
+        .is_some();
+    if !stream || starts_call {
+        map.entry("type").or_insert_with(|| json!("function"));
+        if !map.get("id").is_some_and(JsonValue::is_string) {
+            map.insert("id".to_string(), json!(format!("call_{index}")));
+        }
+    }
+    let Some(function) = map.get_mut("function").and_then(JsonValue::as_object_mut) else {
+        return;
+    };
+    match function.get("arguments") {
+        Some(JsonValue::String(_)) => {}
+        Some(JsonValue::Null) | None if !stream => {
+            function.insert("arguments".to_string(), json!("{}"));
+        }
+        Some(JsonValue::Null) | None => {}
+        Some(other) => {
+            let text = other.to_string();
+            function.insert("arguments".to_string(), json!(text));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalized(body: JsonValue) -> JsonValue {
+        let out = normalize_completion(Bytes::from(body.to_string()));
+        serde_json::from_slice(&out).unwrap()
+    }
+
+    #[test]
+    fn tool_calls_and_finish_reasons_get_the_openai_shape() {
+        let out = normalized(json!({
+            "choices": [{
+                "index": 0,
+                "finish_reason": "stop",
+                "message": {
+                    "role": "assistant",
+                    "tool_calls": [{ "function": { "name": "f", "arguments": { "q": 1 } } }],
+                },
+            }],
+        }));
+        let choice = &out["choices"][0];
+        assert_eq!(choice["finish_reason"], "tool_calls");
+        let call = &choice["message"]["tool_calls"][0];
+        assert_eq!(call["type"], "function");
+        assert_eq!(call["id"], "call_0");
+        assert_eq!(call["function"]["arguments"], r#"{"q":1}"#);
+
+        let out = normalized(json!({
+            "choices": [{
+                "finish_reason": "function_call",
+                "message": { "role": "assistant", "function_call": { "name": "g" } },
+            }],
+        }));
+        let choice = &out["choices"][0];
+        assert_eq!(choice["finish_reason"], "tool_calls");
+        assert_eq!(
+            choice["message"]["tool_calls"][0]["function"]["arguments"],
+            "{}"
+        );
+        assert!(choice["message"].get("function_call").is_none());
+
+        let out = normalized(json!({
+            "choices": [
+                { "index": 0, "finish_reason": "eos", "message": { "content": "hi" } },
+                { "index": 1, "finish_reason": "max_tokens", "message": { "content": "h" } },
+            ],
+        }));
+        assert_eq!(out["choices"][0]["finish_reason"], "stop");
+        assert_eq!(out["choices"][1]["finish_reason"], "length");
+    }
+
+    #[test]
+    fn stream_chunks_keep_tool_state_across_events() {
+        let mut with_tools = HashSet::new();
+        let first = json!({
+            "choices": [{
+                "index": 0,
+                "delta": { "tool_calls": [{ "function": { "name": "f", "arguments": "" } }] },
+            }],
+        });
+        let frame = normalize_event(None, &first.to_string(), &mut with_tools);
+        let frame = std::str::from_utf8(&frame).unwrap();
+        let value: JsonValue =
+            serde_json::from_str(frame.strip_prefix("data: ").unwrap().trim()).unwrap();
+        let call = &value["choices"][0]["delta"]["tool_calls"][0];
+        assert_eq!(call["index"], 0);
+        assert_eq!(call["id"], "call_0");
+
+        let last = json!({ "choices": [{ "index": 0, "delta": {}, "finish_reason": "stop" }] });
+        let frame = normalize_event(None, &last.to_string(), &mut with_tools);
+        assert!(
+            std::str::from_utf8(&frame)
+                .unwrap()
+                .contains(r#""finish_reason":"tool_calls""#)
+        );
+
+        let done = normalize_event(None, "[DONE]", &mut with_tools);
+        assert_eq!(done, Bytes::from_static(b"data: [DONE]\n\n"));
+    }
+
+    #[test]
+    fn retry_after_ms_is_read_from_the_body() {
+        let body = json!({ "error": { "message": "slow down", "retry_after_ms": 1500 } });
+        assert_eq!(
+            retry_after(body.to_string().as_bytes()),
+            Some(Duration::from_millis(1500))
+        );
+        assert_eq!(
+            retry_after(br#"{"retry_after_ms":"250.5"}"#),
+            Some(Duration::from_millis(251))
+        );
+        assert_eq!(retry_after(br#"{"retry_after_ms":0}"#), None);
+        assert_eq!(retry_after(b"too many requests"), None);
+    }
+}
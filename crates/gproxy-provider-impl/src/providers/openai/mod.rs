@@ -1,12 +1,12 @@
 use bytes::Bytes;
 
 use gproxy_provider_core::{
-    Credential, DispatchRule, DispatchTable, HttpMethod, Proto, ProviderConfig, ProviderError,
-    ProviderResult, UpstreamCtx, UpstreamHttpRequest, UpstreamProvider,
-    credential::ApiKeyCredential,
+    Credential, DispatchRule, DispatchTable, Headers, HttpMethod, Proto, ProviderConfig,
+    ProviderError, ProviderResult, RawPassthroughRequest, UpstreamCtx, UpstreamHttpRequest,
+    UpstreamProvider, credential::ApiKeyCredential,
 };
 
-use crate::auth_extractor;
+use crate::{auth_extractor, passthrough};
 
 const PROVIDER_NAME: &str = "openai";
 const DEFAULT_BASE_URL: &str = "https://api.openai.com";
@@ -105,8 +105,9 @@ impl UpstreamProvider for OpenAIProvider {
             }
         };
 
-        let url = build_url(Some(base_url), DEFAULT_BASE_URL, "/v1/models");
-        let mut headers = Vec::new();
+        let path = upstream_path(config, "openai_models_list", "/v1/models", "");
+        let url = build_url(Some(base_url), DEFAULT_BASE_URL, &path);
+        let mut headers = Headers::new();
         auth_extractor::set_bearer(&mut headers, api_key);
         auth_extractor::set_accept_json(&mut headers);
         Ok(UpstreamHttpRequest {
@@ -144,12 +145,14 @@ impl UpstreamProvider for OpenAIProvider {
             }
         };
 
-        let url = build_url(
-            Some(base_url),
-            DEFAULT_BASE_URL,
+        let path = upstream_path(
+            config,
+            "openai_models_get",
             &format!("/v1/models/{}", req.path.model),
+            &req.path.model,
         );
-        let mut headers = Vec::new();
+        let url = build_url(Some(base_url), DEFAULT_BASE_URL, &path);
+        let mut headers = Headers::new();
         auth_extractor::set_bearer(&mut headers, api_key);
         auth_extractor::set_accept_json(&mut headers);
         Ok(UpstreamHttpRequest {
@@ -187,14 +190,16 @@ impl UpstreamProvider for OpenAIProvider {
             }
         };
 
-        let url = build_url(
-            Some(base_url),
-            DEFAULT_BASE_URL,
+        let path = upstream_path(
+            config,
+            "openai_input_tokens",
             "/v1/responses/input_tokens",
+            &req.body.model,
         );
+        let url = build_url(Some(base_url), DEFAULT_BASE_URL, &path);
         let body =
             serde_json::to_vec(&req.body).map_err(|err| ProviderError::Other(err.to_string()))?;
-        let mut headers = Vec::new();
+        let mut headers = Headers::new();
         auth_extractor::set_bearer(&mut headers, api_key);
         auth_extractor::set_accept_json(&mut headers);
         auth_extractor::set_content_type_json(&mut headers);
@@ -233,11 +238,17 @@ impl UpstreamProvider for OpenAIProvider {
             }
         };
 
-        let url = build_url(Some(base_url), DEFAULT_BASE_URL, "/v1/chat/completions");
         let is_stream = req.body.stream.unwrap_or(false);
+        let op = if is_stream {
+            "openai_chat_generate_stream"
+        } else {
+            "openai_chat_generate"
+        };
+        let path = upstream_path(config, op, "/v1/chat/completions", &req.body.model);
+        let url = build_url(Some(base_url), DEFAULT_BASE_URL, &path);
         let body =
             serde_json::to_vec(&req.body).map_err(|err| ProviderError::Other(err.to_string()))?;
-        let mut headers = Vec::new();
+        let mut headers = Headers::new();
         auth_extractor::set_bearer(&mut headers, api_key);
         auth_extractor::set_accept_json(&mut headers);
         auth_extractor::set_content_type_json(&mut headers);
@@ -276,11 +287,17 @@ impl UpstreamProvider for OpenAIProvider {
             }
         };
 
-        let url = build_url(Some(base_url), DEFAULT_BASE_URL, "/v1/responses");
         let is_stream = req.body.stream.unwrap_or(false);
+        let op = if is_stream {
+            "openai_response_generate_stream"
+        } else {
+            "openai_response_generate"
+        };
+        let path = upstream_path(config, op, "/v1/responses", &req.body.model);
+        let url = build_url(Some(base_url), DEFAULT_BASE_URL, &path);
         let body =
             serde_json::to_vec(&req.body).map_err(|err| ProviderError::Other(err.to_string()))?;
-        let mut headers = Vec::new();
+        let mut headers = Headers::new();
         auth_extractor::set_bearer(&mut headers, api_key);
         auth_extractor::set_accept_json(&mut headers);
         auth_extractor::set_content_type_json(&mut headers);
@@ -317,7 +334,7 @@ impl UpstreamProvider for OpenAIProvider {
                 ));
             }
         };
-        let mut path = format!("/v1/responses/{}", req.path.response_id);
+        let mut path = prefixed_path(config, &format!("/v1/responses/{}", req.path.response_id));
         let query = serde_urlencoded::to_string(&req.query)
             .map_err(|err| ProviderError::Other(err.to_string()))?;
         if !query.is_empty() {
@@ -325,7 +342,7 @@ impl UpstreamProvider for OpenAIProvider {
             path.push_str(&query);
         }
         let url = build_url(Some(base_url), DEFAULT_BASE_URL, &path);
-        let mut headers = Vec::new();
+        let mut headers = Headers::new();
         auth_extractor::set_bearer(&mut headers, api_key);
         auth_extractor::set_accept_json(&mut headers);
         Ok(UpstreamHttpRequest {
@@ -364,9 +381,9 @@ impl UpstreamProvider for OpenAIProvider {
         let url = build_url(
             Some(base_url),
             DEFAULT_BASE_URL,
-            &format!("/v1/responses/{}", req.path.response_id),
+            &prefixed_path(config, &format!("/v1/responses/{}", req.path.response_id)),
         );
-        let mut headers = Vec::new();
+        let mut headers = Headers::new();
         auth_extractor::set_bearer(&mut headers, api_key);
         auth_extractor::set_accept_json(&mut headers);
         Ok(UpstreamHttpRequest {
@@ -405,9 +422,12 @@ impl UpstreamProvider for OpenAIProvider {
         let url = build_url(
             Some(base_url),
             DEFAULT_BASE_URL,
-            &format!("/v1/responses/{}/cancel", req.path.response_id),
+            &prefixed_path(
+                config,
+                &format!("/v1/responses/{}/cancel", req.path.response_id),
+            ),
         );
-        let mut headers = Vec::new();
+        let mut headers = Headers::new();
         auth_extractor::set_bearer(&mut headers, api_key);
         auth_extractor::set_accept_json(&mut headers);
         Ok(UpstreamHttpRequest {
@@ -443,7 +463,10 @@ impl UpstreamProvider for OpenAIProvider {
                 ));
             }
         };
-        let mut path = format!("/v1/responses/{}/input_items", req.path.response_id);
+        let mut path = prefixed_path(
+            config,
+            &format!("/v1/responses/{}/input_items", req.path.response_id),
+        );
         let query = serde_urlencoded::to_string(&req.query)
             .map_err(|err| ProviderError::Other(err.to_string()))?;
         if !query.is_empty() {
@@ -451,7 +474,7 @@ impl UpstreamProvider for OpenAIProvider {
             path.push_str(&query);
         }
         let url = build_url(Some(base_url), DEFAULT_BASE_URL, &path);
-        let mut headers = Vec::new();
+        let mut headers = Headers::new();
         auth_extractor::set_bearer(&mut headers, api_key);
         auth_extractor::set_accept_json(&mut headers);
         Ok(UpstreamHttpRequest {
@@ -487,10 +510,14 @@ impl UpstreamProvider for OpenAIProvider {
                 ));
             }
         };
-        let url = build_url(Some(base_url), DEFAULT_BASE_URL, "/v1/responses/compact");
+        let url = build_url(
+            Some(base_url),
+            DEFAULT_BASE_URL,
+            &prefixed_path(config, "/v1/responses/compact"),
+        );
         let body =
             serde_json::to_vec(&req.body).map_err(|err| ProviderError::Other(err.to_string()))?;
-        let mut headers = Vec::new();
+        let mut headers = Headers::new();
         auth_extractor::set_bearer(&mut headers, api_key);
         auth_extractor::set_accept_json(&mut headers);
         auth_extractor::set_content_type_json(&mut headers);
@@ -502,6 +529,191 @@ impl UpstreamProvider for OpenAIProvider {
             is_stream: false,
         })
     }
+
+    async fn build_openai_response_list(
+        &self,
+        _ctx: &UpstreamCtx,
+        config: &ProviderConfig,
+        credential: &Credential,
+        req: &gproxy_protocol::openai::list_responses::request::ListResponsesRequest,
+    ) -> ProviderResult<UpstreamHttpRequest> {
+        let path = with_query("/v1/responses".to_string(), &req.query)?;
+        resource_request(config, credential, HttpMethod::Get, path, None)
+    }
+
+    async fn build_openai_conversation_create(
+        &self,
+        _ctx: &UpstreamCtx,
+        config: &ProviderConfig,
+        credential: &Credential,
+        req: &gproxy_protocol::openai::conversations::request::CreateConversationRequest,
+    ) -> ProviderResult<UpstreamHttpRequest> {
+        let body = json_body(&req.body)?;
+        resource_request(
+            config,
+            credential,
+            HttpMethod::Post,
+            "/v1/conversations".to_string(),
+            Some(body),
+        )
+    }
+
+    async fn build_openai_conversation_get(
+        &self,
+        _ctx: &UpstreamCtx,
+        config: &ProviderConfig,
+        credential: &Credential,
+        req: &gproxy_protocol::openai::conversations::request::GetConversationRequest,
+    ) -> ProviderResult<UpstreamHttpRequest> {
+        let path = format!("/v1/conversations/{}", req.path.conversation_id);
+        resource_request(config, credential, HttpMethod::Get, path, None)
+    }
+
+    async fn build_openai_conversation_update(
+        &self,
+        _ctx: &UpstreamCtx,
+        config: &ProviderConfig,
+        credential: &Credential,
+        req: &gproxy_protocol::openai::conversations::request::UpdateConversationRequest,
+    ) -> ProviderResult<UpstreamHttpRequest> {
+        let path = format!("/v1/conversations/{}", req.path.conversation_id);
+        let body = json_body(&req.body)?;
+        resource_request(config, credential, HttpMethod::Post, path, Some(body))
+    }
+
+    async fn build_openai_conversation_delete(
+        &self,
+        _ctx: &UpstreamCtx,
+        config: &ProviderConfig,
+        credential: &Credential,
+        req: &gproxy_protocol::openai::conversations::request::DeleteConversationRequest,
+    ) -> ProviderResult<UpstreamHttpRequest> {
+        let path = format!("/v1/conversations/{}", req.path.conversation_id);
+        resource_request(config, credential, HttpMethod::Delete, path, None)
+    }
+
+    async fn build_openai_conversation_item_list(
+        &self,
+        _ctx: &UpstreamCtx,
+        config: &ProviderConfig,
+        credential: &Credential,
+        req: &gproxy_protocol::openai::conversations::request::ListConversationItemsRequest,
+    ) -> ProviderResult<UpstreamHttpRequest> {
+        let path = with_query(
+            format!("/v1/conversations/{}/items", req.path.conversation_id),
+            &req.query,
+        )?;
+        resource_request(config, credential, HttpMethod::Get, path, None)
+    }
+
+    async fn build_openai_conversation_item_create(
+        &self,
+        _ctx: &UpstreamCtx,
+        config: &ProviderConfig,
+        credential: &Credential,
+        req: &gproxy_protocol::openai::conversations::request::CreateConversationItemsRequest,
+    ) -> ProviderResult<UpstreamHttpRequest> {
+        let path = with_query(
+            format!("/v1/conversations/{}/items", req.path.conversation_id),
+            &req.query,
+        )?;
+        let body = json_body(&req.body)?;
+        resource_request(config, credential, HttpMethod::Post, path, Some(body))
+    }
+
+    async fn build_openai_conversation_item_get(
+        &self,
+        _ctx: &UpstreamCtx,
+        config: &ProviderConfig,
+        credential: &Credential,
+        req: &gproxy_protocol::openai::conversations::request::GetConversationItemRequest,
+    ) -> ProviderResult<UpstreamHttpRequest> {
+        let path = with_query(
+            format!(
+                "/v1/conversations/{}/items/{}",
+                req.path.conversation_id, req.path.item_id
+            ),
+            &req.query,
+        )?;
+        resource_request(config, credential, HttpMethod::Get, path, None)
+    }
+
+    async fn build_openai_conversation_item_delete(
+        &self,
+        _ctx: &UpstreamCtx,
+        config: &ProviderConfig,
+        credential: &Credential,
+        req: &gproxy_protocol::openai::conversations::request::DeleteConversationItemRequest,
+    ) -> ProviderResult<UpstreamHttpRequest> {
+        let path = format!(
+            "/v1/conversations/{}/items/{}",
+            req.path.conversation_id, req.path.item_id
+        );
+        resource_request(config, credential, HttpMethod::Delete, path, None)
+    }
+
+    async fn build_openai_file_list(
+        &self,
+        _ctx: &UpstreamCtx,
+        config: &ProviderConfig,
+        credential: &Credential,
+        req: &gproxy_protocol::openai::files::request::ListFilesRequest,
+    ) -> ProviderResult<UpstreamHttpRequest> {
+        let path = with_query("/v1/files".to_string(), &req.query)?;
+        resource_request(config, credential, HttpMethod::Get, path, None)
+    }
+
+    async fn build_openai_file_get(
+        &self,
+        _ctx: &UpstreamCtx,
+        config: &ProviderConfig,
+        credential: &Credential,
+        req: &gproxy_protocol::openai::files::request::GetFileRequest,
+    ) -> ProviderResult<UpstreamHttpRequest> {
+        let path = format!("/v1/files/{}", req.path.file_id);
+        resource_request(config, credential, HttpMethod::Get, path, None)
+    }
+
+    async fn build_openai_file_delete(
+        &self,
+        _ctx: &UpstreamCtx,
+        config: &ProviderConfig,
+        credential: &Credential,
+        req: &gproxy_protocol::openai::files::request::DeleteFileRequest,
+    ) -> ProviderResult<UpstreamHttpRequest> {
+        let path = format!("/v1/files/{}", req.path.file_id);
+        resource_request(config, credential, HttpMethod::Delete, path, None)
+    }
+
+    async fn build_raw_passthrough(
+        &self,
+        _ctx: &UpstreamCtx,
+        config: &ProviderConfig,
+        credential: &Credential,
+        req: &RawPassthroughRequest,
+    ) -> ProviderResult<UpstreamHttpRequest> {
+        let base_url = match config {
+            ProviderConfig::OpenAI(cfg) => cfg.base_url.as_deref().unwrap_or(DEFAULT_BASE_URL),
+            _ => {
+                return Err(ProviderError::InvalidConfig(
+                    "expected ProviderConfig::OpenAI".to_string(),
+                ));
+            }
+        };
+        let api_key = match credential {
+            Credential::OpenAI(ApiKeyCredential { api_key }) => api_key.as_str(),
+            _ => {
+                return Err(ProviderError::InvalidConfig(
+                    "expected Credential::OpenAI".to_string(),
+                ));
+            }
+        };
+
+        let url = build_url(Some(base_url), DEFAULT_BASE_URL, &req.path);
+        let mut upstream = passthrough::forward(url, req);
+        auth_extractor::set_bearer(&mut upstream.headers, api_key);
+        Ok(upstream)
+    }
 }
 
 fn build_url(base_url: Option<&str>, default_base: &str, path: &str) -> String {
@@ -512,3 +724,77 @@ fn build_url(base_url: Option<&str>, default_base: &str, path: &str) -> String {
     }
     format!("{base}/{path}")
 }
+
+/// Path of operation `op` after the config's path overrides.
+fn upstream_path(config: &ProviderConfig, op: &str, default: &str, model: &str) -> String {
+    match config {
+        ProviderConfig::OpenAI(cfg) => cfg.path_overrides.resolve(op, default, model),
+        _ => default.to_string(),
+    }
+}
+
+/// `path` under the config's `path_prefix`, for calls without an operation key.
+fn prefixed_path(config: &ProviderConfig, path: &str) -> String {
+    match config {
+        ProviderConfig::OpenAI(cfg) => cfg.path_overrides.prefixed(path),
+        _ => path.to_string(),
+    }
+}
+
+/// JSON call on one of the stored-resource endpoints (responses, conversations, files).
+fn resource_request(
+    config: &ProviderConfig,
+    credential: &Credential,
+    method: HttpMethod,
+    path: String,
+    body: Option<Vec<u8>>,
+) -> ProviderResult<UpstreamHttpRequest> {
+    let base_url = match config {
+        ProviderConfig::OpenAI(cfg) => cfg.base_url.as_deref().unwrap_or(DEFAULT_BASE_URL),
+        _ => {
+            return Err(ProviderError::InvalidConfig(
+                "expected ProviderConfig::OpenAI".to_string(),
+            ));
+        }
+    };
+    let api_key = match credential {
+        Credential::OpenAI(ApiKeyCredential { api_key }) => api_key.as_str(),
+        _ => {
+            return Err(ProviderError::InvalidConfig(
+                "expected Credential::OpenAI".to_string(),
+            ));
+        }
+    };
+    let url = build_url(
+        Some(base_url),
+        DEFAULT_BASE_URL,
+        &prefixed_path(config, &path),
+    );
+    let mut headers = Headers::new();
+    auth_extractor::set_bearer(&mut headers, api_key);
+    auth_extractor::set_accept_json(&mut headers);
+    if body.is_some() {
+        auth_extractor::set_content_type_json(&mut headers);
+    }
+    Ok(UpstreamHttpRequest {
+        method,
+        url,
+        headers,
+        body: body.map(Bytes::from),
+        is_stream: false,
+    })
+}
+
+fn with_query<Q: serde::Serialize>(mut path: String, query: &Q) -> ProviderResult<String> {
+    let query =
+        serde_urlencoded::to_string(query).map_err(|err| ProviderError::Other(err.to_string()))?;
+    if !query.is_empty() {
+        path.push('?');
+        path.push_str(&query);
+    }
+    Ok(path)
+}
+
+fn json_body<T: serde::Serialize>(body: &T) -> ProviderResult<Vec<u8>> {
+    serde_json::to_vec(body).map_err(|err| ProviderError::Other(err.to_string()))
+}
@@ -2,7 +2,7 @@ use bytes::Bytes;
 use serde_json::Value as JsonValue;
 
 use gproxy_provider_core::{
-    AuthRetryAction, Credential, DispatchRule, DispatchTable, HttpMethod, Op, Proto,
+    AuthRetryAction, Credential, DispatchRule, DispatchTable, Headers, HttpMethod, Op, Proto,
     ProviderConfig, ProviderError, ProviderResult, Request, UpstreamCtx, UpstreamHttpRequest,
     UpstreamProvider,
 };
@@ -116,6 +116,7 @@ impl UpstreamProvider for VertexProvider {
         let normalized = match op {
             Op::ModelList => vertex_model_list_payload(value),
             Op::ModelGet => vertex_model_get_payload(value),
+            Op::BatchGenerateContent | Op::BatchGet => vertex_batch_job_to_operation(value),
             _ => value,
         };
         serde_json::to_vec(&normalized)
@@ -188,7 +189,7 @@ impl UpstreamProvider for VertexProvider {
             url = format!("{url}?{query}");
         }
         let (access_token, _) = oauth::fetch_access_token(ctx, credential, &token_uri, false)?;
-        let mut headers = Vec::new();
+        let mut headers = Headers::new();
         auth_extractor::set_bearer(&mut headers, &access_token);
         auth_extractor::set_accept_json(&mut headers);
         Ok(UpstreamHttpRequest {
@@ -212,7 +213,51 @@ impl UpstreamProvider for VertexProvider {
         let path = format!("/v1beta1/publishers/google/models/{model_id}");
         let url = build_url(Some(vertex_base_url(config)?), DEFAULT_BASE_URL, &path);
         let (access_token, _) = oauth::fetch_access_token(ctx, credential, &token_uri, false)?;
-        let mut headers = Vec::new();
+        let mut headers = Headers::new();
+        auth_extractor::set_bearer(&mut headers, &access_token);
+        auth_extractor::set_accept_json(&mut headers);
+        Ok(UpstreamHttpRequest {
+            method: HttpMethod::Get,
+            url,
+            headers,
+            body: None,
+            is_stream: false,
+        })
+    }
+
+    async fn build_gemini_batch_generate(
+        &self,
+        ctx: &UpstreamCtx,
+        config: &ProviderConfig,
+        credential: &Credential,
+        req: &gproxy_protocol::gemini::batch_generate_content::request::BatchGenerateContentRequest,
+    ) -> ProviderResult<UpstreamHttpRequest> {
+        let (project_id, location, token_uri) = vertex_context(config, credential)?;
+        let model_id = normalize_model_name(&req.path.model);
+        let body = vertex_batch_job_payload(&model_id, &req.body.batch)?;
+        let path = format!("/v1/projects/{project_id}/locations/{location}/batchPredictionJobs");
+        build_vertex_request(ctx, config, credential, &path, &body, false, &token_uri)
+    }
+
+    async fn build_gemini_batch_get(
+        &self,
+        ctx: &UpstreamCtx,
+        config: &ProviderConfig,
+        credential: &Credential,
+        req: &gproxy_protocol::gemini::get_batch::request::GetBatchRequest,
+    ) -> ProviderResult<UpstreamHttpRequest> {
+        let (project_id, location, token_uri) = vertex_context(config, credential)?;
+        let job_id = req
+            .path
+            .name
+            .rsplit('/')
+            .next()
+            .unwrap_or(req.path.name.as_str());
+        let path =
+            format!("/v1/projects/{project_id}/locations/{location}/batchPredictionJobs/{job_id}");
+        let url = build_url(Some(vertex_base_url(config)?), DEFAULT_BASE_URL, &path);
+        let (access_token, _) = oauth::fetch_access_token(ctx, credential, &token_uri, false)?;
+        let mut headers = Headers::new();
         auth_extractor::set_bearer(&mut headers, &access_token);
         auth_extractor::set_accept_json(&mut headers);
         Ok(UpstreamHttpRequest {
@@ -240,7 +285,7 @@ impl UpstreamProvider for VertexProvider {
         let (access_token, _) = oauth::fetch_access_token(ctx, credential, &token_uri, false)?;
         let body_bytes =
             serde_json::to_vec(&body).map_err(|err| ProviderError::Other(err.to_string()))?;
-        let mut headers = Vec::new();
+        let mut headers = Headers::new();
         auth_extractor::set_bearer(&mut headers, &access_token);
         auth_extractor::set_accept_json(&mut headers);
         auth_extractor::set_content_type_json(&mut headers);
@@ -311,7 +356,7 @@ fn build_vertex_request<T: serde::Serialize>(
     let url = build_url(Some(vertex_base_url(config)?), DEFAULT_BASE_URL, path);
     let (access_token, _) = oauth::fetch_access_token(ctx, credential, token_uri, false)?;
     let body = serde_json::to_vec(body).map_err(|err| ProviderError::Other(err.to_string()))?;
-    let mut headers = Vec::new();
+    let mut headers = Headers::new();
     auth_extractor::set_bearer(&mut headers, &access_token);
     auth_extractor::set_accept_json(&mut headers);
     auth_extractor::set_content_type_json(&mut headers);
@@ -457,6 +502,87 @@ fn append_query(path: &str, query: Option<&str>) -> String {
     }
 }
 
+/// Vertex runs batches as batch prediction jobs over Cloud Storage or BigQuery, so only
+/// `inputConfig.fileName` with a `gs://` or `bq://` source is accepted. Results land next
+/// to the input: `{dir}/output` for Cloud Storage, the input table's dataset for BigQuery.
+fn vertex_batch_job_payload(
+    model_id: &str,
+    batch: &gproxy_protocol::gemini::batch_generate_content::types::GenerateContentBatch,
+) -> ProviderResult<JsonValue> {
+    let source = batch
+        .input_config
+        .file_name
+        .as_deref()
+        .map(str::trim)
+        .unwrap_or_default();
+    let (input_config, output_config) = if source.starts_with("gs://") {
+        let dir = source
+            .rsplit_once('/')
+            .map(|(dir, _)| dir)
+            .filter(|dir| *dir != "gs:/")
+            .unwrap_or(source);
+        (
+            serde_json::json!({ "instancesFormat": "jsonl", "gcsSource": { "uris": [source] } }),
+            serde_json::json!({
+                "predictionsFormat": "jsonl",
+                "gcsDestination": { "outputUriPrefix": format!("{dir}/output") },
+            }),
+        )
+    } else if let Some(table) = source.strip_prefix("bq://") {
+        let dataset = table.rsplit_once('.').map_or(table, |(dataset, _)| dataset);
+        (
+            serde_json::json!({ "instancesFormat": "bigquery", "bigquerySource": { "inputUri": source } }),
+            serde_json::json!({
+                "predictionsFormat": "bigquery",
+                "bigqueryDestination": { "outputUri": format!("bq://{dataset}") },
+            }),
+        )
+    } else {
+        return Err(ProviderError::Unsupported("vertex.batch_inline_requests"));
+    };
+    Ok(serde_json::json!({
+        "displayName": batch.display_name.as_deref().unwrap_or("gproxy-batch"),
+        "model": format!("publishers/google/models/{model_id}"),
+        "inputConfig": input_config,
+        "outputConfig": output_config,
+    }))
+}
+
+/// Batch prediction job as a Gemini batch operation named `batches/{job id}`; the job
+/// itself goes into `metadata` and its output location into `response.responsesFile`.
+fn vertex_batch_job_to_operation(job: JsonValue) -> JsonValue {
+    let job_id = job
+        .get("name")
+        .and_then(JsonValue::as_str)
+        .and_then(|name| name.rsplit('/').next())
+        .unwrap_or_default()
+        .to_string();
+    let done = matches!(
+        job.get("state").and_then(JsonValue::as_str),
+        Some(
+            "JOB_STATE_SUCCEEDED"
+                | "JOB_STATE_PARTIALLY_SUCCEEDED"
+                | "JOB_STATE_FAILED"
+                | "JOB_STATE_CANCELLED"
+                | "JOB_STATE_EXPIRED"
+        )
+    );
+    let mut operation = serde_json::json!({ "name": format!("batches/{job_id}"), "done": done });
+    let output = job
+        .pointer("/outputInfo/gcsOutputDirectory")
+        .or_else(|| job.pointer("/outputInfo/bigqueryOutputTable"))
+        .or_else(|| job.pointer("/outputInfo/bigqueryOutputDataset"))
+        .cloned();
+    if let Some(output) = output.filter(|_| done) {
+        operation["response"] = serde_json::json!({ "responsesFile": output });
+    }
+    if let Some(error) = job.get("error") {
+        operation["error"] = error.clone();
+    }
+    operation["metadata"] = job;
+    operation
+}
+
 fn vertex_model_list_payload(value: JsonValue) -> JsonValue {
     let JsonValue::Object(mut map) = value else {
         return value;
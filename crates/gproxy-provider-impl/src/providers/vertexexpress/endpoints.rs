@@ -0,0 +1,211 @@
+//! Regional failover: a request goes to the base URL (the global endpoint by default) and,
+//! when that answers with a 429 or a capacity error, to each configured fallback region in
+//! turn, all on the same credential. Capacity differs a lot by region, so a region that is
+//! out of it says little about the key.
+//!
+//! The endpoint in use is tracked per request (trace id) and credential between the
+//! engine's attempts.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use gproxy_provider_core::UpstreamCtx;
+use gproxy_provider_core::provider::UpstreamFailure;
+
+/// How long a request's endpoint is remembered between two of its attempts.
+const CURSOR_TTL: Duration = Duration::from_secs(10 * 60);
+/// Most requests tracked at once; stale ones are dropped first.
+const MAX_CURSORS: usize = 10_000;
+
+struct Cursor {
+    index: usize,
+    seen: Instant,
+}
+
+/// Base URLs tried in order: `base_url`, then one per fallback region. A region entry is
+/// either a region name, served at `https://{region}-aiplatform.googleapis.com`, or a
+/// full base URL.
+pub fn endpoints(base_url: &str, fallback_regions: &[String]) -> Vec<String> {
+    let mut endpoints = vec![base_url.trim_end_matches('/').to_string()];
+    for region in fallback_regions {
+        let region = region.trim();
+        if region.is_empty() {
+            continue;
+        }
+        let endpoint = if region.contains("://") {
+            region.trim_end_matches('/').to_string()
+        } else {
+            format!("https://{region}-aiplatform.googleapis.com")
+        };
+        if !endpoints.contains(&endpoint) {
+            endpoints.push(endpoint);
+        }
+    }
+    endpoints
+}
+
+/// Index of the endpoint the request's next attempt on this credential goes to.
+pub fn current(ctx: &UpstreamCtx) -> usize {
+    let Some(key) = cursor_key(ctx) else {
+        return 0;
+    };
+    let cursors = cursors().lock().unwrap_or_else(|e| e.into_inner());
+    cursors
+        .get(&key)
+        .filter(|cursor| cursor.seen.elapsed() < CURSOR_TTL)
+        .map(|cursor| cursor.index)
+        .unwrap_or(0)
+}
+
+/// Moves the request on to the next of `count` endpoints; `false` once all have been
+/// tried, which also forgets the request.
+pub fn advance(ctx: &UpstreamCtx, count: usize) -> bool {
+    let Some(key) = cursor_key(ctx) else {
+        return false;
+    };
+    let mut cursors = cursors().lock().unwrap_or_else(|e| e.into_inner());
+    let index = cursors
+        .get(&key)
+        .filter(|cursor| cursor.seen.elapsed() < CURSOR_TTL)
+        .map(|cursor| cursor.index)
+        .unwrap_or(0)
+        + 1;
+    if index >= count {
+        cursors.remove(&key);
+        return false;
+    }
+    if cursors.len() >= MAX_CURSORS {
+        cursors.retain(|_, cursor| cursor.seen.elapsed() < CURSOR_TTL);
+        if cursors.len() >= MAX_CURSORS
+            && let Some(oldest) = cursors
+                .iter()
+                .min_by_key(|(_, cursor)| cursor.seen)
+                .map(|(key, _)| key.clone())
+        {
+            cursors.remove(&oldest);
+        }
+    }
+    cursors.insert(
+        key,
+        Cursor {
+            index,
+            seen: Instant::now(),
+        },
+    );
+    true
+}
+
+/// Forgets the request, returning the index of the endpoint that served it.
+pub fn finish(ctx: &UpstreamCtx) -> Option<usize> {
+    let key = cursor_key(ctx)?;
+    let mut cursors = cursors().lock().unwrap_or_else(|e| e.into_inner());
+    cursors.remove(&key).map(|cursor| cursor.index)
+}
+
+/// Whether a failure says the endpoint is short of capacity rather than the request or
+/// the key being wrong.
+pub fn is_capacity_failure(failure: &UpstreamFailure) -> bool {
+    let UpstreamFailure::Http { status, body, .. } = failure else {
+        return false;
+    };
+    match status {
+        429 | 529 => true,
+        500 | 503 => {
+            let body = String::from_utf8_lossy(body).to_ascii_lowercase();
+            ["resource_exhausted", "overloaded", "capacity"]
+                .iter()
+                .any(|marker| body.contains(marker))
+        }
+        _ => false,
+    }
+}
+
+fn cursor_key(ctx: &UpstreamCtx) -> Option<(String, i64)> {
+    Some((ctx.trace_id.clone()?, ctx.credential_id?))
+}
+
+fn cursors() -> &'static Mutex<HashMap<(String, i64), Cursor>> {
+    static CURSORS: OnceLock<Mutex<HashMap<(String, i64), Cursor>>> = OnceLock::new();
+    CURSORS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use gproxy_provider_core::{Headers, Op};
+
+    use super::*;
+
+    fn ctx(trace_id: &str) -> UpstreamCtx {
+        UpstreamCtx {
+            trace_id: Some(trace_id.to_string()),
+            user_id: None,
+            user_key_id: None,
+            user_agent: None,
+            outbound_proxy: None,
+            provider: "vertexexpress".to_string(),
+            credential_id: Some(1),
+            op: Op::GenerateContent,
+            internal: false,
+            attempt_no: 1,
+            caller: Default::default(),
+        }
+    }
+
+    fn http(status: u16, body: &str) -> UpstreamFailure {
+        UpstreamFailure::Http {
+            status,
+            headers: Headers::new(),
+            body: Bytes::from(body.to_string()),
+        }
+    }
+
+    #[test]
+    fn regions_follow_the_base_url() {
+        let regions = vec![
+            "us-central1".to_string(),
+            " ".to_string(),
+            "https://europe-west4-aiplatform.googleapis.com/".to_string(),
+            "us-central1".to_string(),
+        ];
+        assert_eq!(
+            endpoints("https://aiplatform.googleapis.com/", &regions),
+            [
+                "https://aiplatform.googleapis.com",
+                "https://us-central1-aiplatform.googleapis.com",
+                "https://europe-west4-aiplatform.googleapis.com",
+            ]
+        );
+    }
+
+    #[test]
+    fn a_request_walks_the_endpoints_once() {
+        let ctx = ctx("failover-walk");
+        assert_eq!(current(&ctx), 0);
+        assert!(advance(&ctx, 3));
+        assert!(advance(&ctx, 3));
+        assert_eq!(current(&ctx), 2);
+        assert!(!advance(&ctx, 3));
+        assert_eq!(current(&ctx), 0);
+
+        assert!(advance(&ctx, 3));
+        assert_eq!(finish(&ctx), Some(1));
+        assert_eq!(finish(&ctx), None);
+
+        let mut untraced = ctx.clone();
+        untraced.trace_id = None;
+        assert!(!advance(&untraced, 3));
+    }
+
+    #[test]
+    fn only_capacity_failures_fail_over() {
+        assert!(is_capacity_failure(&http(429, "")));
+        assert!(is_capacity_failure(&http(
+            503,
+            r#"{"error":{"status":"RESOURCE_EXHAUSTED"}}"#
+        )));
+        assert!(!is_capacity_failure(&http(503, "upstream connect error")));
+        assert!(!is_capacity_failure(&http(400, "RESOURCE_EXHAUSTED")));
+    }
+}
@@ -4,14 +4,16 @@ use bytes::Bytes;
 use serde_json::Value as JsonValue;
 
 use gproxy_provider_core::{
-    Credential, DispatchRule, DispatchTable, HttpMethod, ModelGetRequest, ModelListRequest, Proto,
-    ProviderConfig, ProviderError, ProviderResult, Request, UpstreamBody, UpstreamCtx,
-    UpstreamHttpRequest, UpstreamHttpResponse, UpstreamProvider, credential::ApiKeyCredential,
-    header_set,
+    AuthRetryAction, Credential, DispatchRule, DispatchTable, Headers, HttpMethod, ModelGetRequest,
+    ModelListRequest, Proto, ProviderConfig, ProviderError, ProviderResult, Request, UpstreamBody,
+    UpstreamCtx, UpstreamHttpRequest, UpstreamHttpResponse, UpstreamProvider,
+    credential::ApiKeyCredential, header_set, provider::UpstreamFailure,
 };
 
 use crate::auth_extractor;
 
+mod endpoints;
+
 const PROVIDER_NAME: &str = "vertexexpress";
 const DEFAULT_BASE_URL: &str = "https://aiplatform.googleapis.com";
 const MODELS_JSON: &str = include_str!("models.json");
@@ -91,7 +93,7 @@ impl UpstreamProvider for VertexExpressProvider {
 
     async fn build_gemini_generate(
         &self,
-        _ctx: &UpstreamCtx,
+        ctx: &UpstreamCtx,
         config: &ProviderConfig,
         credential: &Credential,
         req: &gproxy_protocol::gemini::generate_content::request::GenerateContentRequest,
@@ -99,6 +101,7 @@ impl UpstreamProvider for VertexExpressProvider {
         let model = vertexexpress_model(&req.path.model);
         let body = vertex_generate_payload(model, &req.body)?;
         build_gemini_request(
+            ctx,
             config,
             credential,
             &format!("/v1beta1/publishers/google/models/{model}:generateContent"),
@@ -109,7 +112,7 @@ impl UpstreamProvider for VertexExpressProvider {
 
     async fn build_gemini_generate_stream(
         &self,
-        _ctx: &UpstreamCtx,
+        ctx: &UpstreamCtx,
         config: &ProviderConfig,
         credential: &Credential,
         req: &gproxy_protocol::gemini::stream_content::request::StreamGenerateContentRequest,
@@ -120,12 +123,12 @@ impl UpstreamProvider for VertexExpressProvider {
             &format!("/v1beta1/publishers/google/models/{model}:streamGenerateContent"),
             req.query.as_deref(),
         );
-        build_gemini_request(config, credential, &path, &body, true)
+        build_gemini_request(ctx, config, credential, &path, &body, true)
     }
 
     async fn build_gemini_count_tokens(
         &self,
-        _ctx: &UpstreamCtx,
+        ctx: &UpstreamCtx,
         config: &ProviderConfig,
         credential: &Credential,
         req: &gproxy_protocol::gemini::count_tokens::request::CountTokensRequest,
@@ -133,6 +136,7 @@ impl UpstreamProvider for VertexExpressProvider {
         let model = vertexexpress_model(&req.path.model);
         let body = vertex_count_tokens_payload(model, &req.body);
         build_gemini_request(
+            ctx,
             config,
             credential,
             &format!("/v1beta1/publishers/google/models/{model}:countTokens"),
@@ -161,7 +165,7 @@ impl UpstreamProvider for VertexExpressProvider {
             query.push_str(&extra);
         }
         url = format!("{url}?{query}");
-        let mut headers = Vec::new();
+        let mut headers = Headers::new();
         auth_extractor::set_accept_json(&mut headers);
         Ok(UpstreamHttpRequest {
             method: HttpMethod::Get,
@@ -188,7 +192,7 @@ impl UpstreamProvider for VertexExpressProvider {
             &format!("/v1beta1/publishers/google/models/{name}"),
         );
         let url = format!("{url}?key={}", urlencoding::encode(api_key));
-        let mut headers = Vec::new();
+        let mut headers = Headers::new();
         auth_extractor::set_accept_json(&mut headers);
         Ok(UpstreamHttpRequest {
             method: HttpMethod::Get,
@@ -199,6 +203,44 @@ impl UpstreamProvider for VertexExpressProvider {
         })
     }
 
+    fn on_upstream_failure<'a>(
+        &'a self,
+        ctx: &'a UpstreamCtx,
+        config: &'a ProviderConfig,
+        _credential: &'a Credential,
+        _req: &'a Request,
+        failure: &'a UpstreamFailure,
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = ProviderResult<AuthRetryAction>> + Send + 'a>,
+    > {
+        Box::pin(async move {
+            if !endpoints::is_capacity_failure(failure) {
+                endpoints::finish(ctx);
+                return Ok(AuthRetryAction::None);
+            }
+            let count = vertexexpress_endpoints(config)?.len();
+            if endpoints::advance(ctx, count) {
+                Ok(AuthRetryAction::Failover)
+            } else {
+                Ok(AuthRetryAction::None)
+            }
+        })
+    }
+
+    fn on_upstream_success<'a>(
+        &'a self,
+        ctx: &'a UpstreamCtx,
+        _config: &'a ProviderConfig,
+        _credential: &'a Credential,
+        _req: &'a Request,
+        _response: &'a UpstreamHttpResponse,
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = ProviderResult<Option<Credential>>> + Send + 'a>,
+    > {
+        endpoints::finish(ctx);
+        Box::pin(async { Ok(None) })
+    }
+
     fn local_response(
         &self,
         _ctx: &UpstreamCtx,
@@ -245,6 +287,26 @@ fn vertexexpress_base_url(config: &ProviderConfig) -> ProviderResult<&str> {
     }
 }
 
+/// Base URLs a request may go to, the configured one first.
+fn vertexexpress_endpoints(config: &ProviderConfig) -> ProviderResult<Vec<String>> {
+    match config {
+        ProviderConfig::VertexExpress(cfg) => Ok(endpoints::endpoints(
+            cfg.base_url.as_deref().unwrap_or(DEFAULT_BASE_URL),
+            &cfg.fallback_regions,
+        )),
+        _ => Err(ProviderError::InvalidConfig(
+            "expected ProviderConfig::VertexExpress".to_string(),
+        )),
+    }
+}
+
+/// Base URL of this attempt: the configured one, or the region the request failed over to.
+fn vertexexpress_endpoint(ctx: &UpstreamCtx, config: &ProviderConfig) -> ProviderResult<String> {
+    let mut endpoints = vertexexpress_endpoints(config)?;
+    let index = endpoints::current(ctx).min(endpoints.len() - 1);
+    Ok(endpoints.swap_remove(index))
+}
+
 fn vertexexpress_api_key(credential: &Credential) -> ProviderResult<&str> {
     match credential {
         Credential::VertexExpress(ApiKeyCredential { api_key }) => Ok(api_key.as_str()),
@@ -259,19 +321,20 @@ fn vertexexpress_model(model: &str) -> &str {
 }
 
 fn build_gemini_request<T: serde::Serialize>(
+    ctx: &UpstreamCtx,
     config: &ProviderConfig,
     credential: &Credential,
     path: &str,
     body: &T,
     is_stream: bool,
 ) -> ProviderResult<UpstreamHttpRequest> {
-    let base_url = vertexexpress_base_url(config)?;
+    let base_url = vertexexpress_endpoint(ctx, config)?;
     let api_key = vertexexpress_api_key(credential)?;
-    let url = build_url(Some(base_url), DEFAULT_BASE_URL, path);
+    let url = build_url(Some(&base_url), DEFAULT_BASE_URL, path);
     let sep = if url.contains('?') { '&' } else { '?' };
     let url = format!("{url}{sep}key={}", urlencoding::encode(api_key));
     let body = serde_json::to_vec(body).map_err(|err| ProviderError::Other(err.to_string()))?;
-    let mut headers = Vec::new();
+    let mut headers = Headers::new();
     auth_extractor::set_accept_json(&mut headers);
     auth_extractor::set_content_type_json(&mut headers);
     Ok(UpstreamHttpRequest {
@@ -440,7 +503,7 @@ fn normalize_vertex_model_id(model: &str) -> String {
 }
 
 fn local_json_response(status: u16, body: Vec<u8>) -> UpstreamHttpResponse {
-    let mut headers = Vec::new();
+    let mut headers = Headers::new();
     header_set(&mut headers, "content-type", "application/json");
     UpstreamHttpResponse {
         status,
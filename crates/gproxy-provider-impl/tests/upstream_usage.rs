@@ -36,6 +36,7 @@ async fn codex_upstream_usage_request_shape() {
         op: gproxy_provider_core::Op::GenerateContent,
         internal: true,
         attempt_no: 0,
+        caller: Default::default(),
     };
 
     let req = provider
@@ -60,6 +61,7 @@ async fn claudecode_upstream_usage_request_shape() {
         claude_ai_base_url: None,
         platform_base_url: Some("https://console.anthropic.com/".to_string()),
         prelude_text: None,
+        prelude_template: None,
     });
     let cred = Credential::ClaudeCode(ClaudeCodeCredential {
         access_token: "t".to_string(),
@@ -86,6 +88,7 @@ async fn claudecode_upstream_usage_request_shape() {
         op: gproxy_provider_core::Op::GenerateContent,
         internal: true,
         attempt_no: 0,
+        caller: Default::default(),
     };
 
     let req = provider
@@ -135,6 +138,7 @@ async fn antigravity_upstream_usage_request_shape() {
         op: gproxy_provider_core::Op::GenerateContent,
         internal: true,
         attempt_no: 0,
+        caller: Default::default(),
     };
 
     let req = provider
@@ -0,0 +1,165 @@
+//! One streamed request end to end: an HTTP/1.1 client on a loopback socket, the proxy
+//! router served by axum, the engine, and an in-process upstream that answers every call
+//! with the same OpenAI chat stream. Covers what `stream_pipeline` leaves out (routing,
+//! auth, dispatch, logging and the response body), but not the upstream HTTP client.
+//!
+//! `cargo bench -p gproxy-router --bench proxy_loopback`
+
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::runtime::Runtime;
+
+use gproxy_common::GlobalConfigPatch;
+use gproxy_common::log::LogFilter;
+use gproxy_core::upstream_client::UpstreamClient;
+use gproxy_provider_core::provider::UpstreamFailure;
+use gproxy_provider_core::{UpstreamBody, UpstreamHttpRequest, UpstreamHttpResponse};
+use gproxy_router::GproxyBuilder;
+use gproxy_storage::{MemorySeed, MemoryStorage};
+
+const API_KEY: &str = "sk-bench";
+const TEXT_DELTAS: usize = 400;
+/// A typical read from the upstream socket.
+const CHUNK_SIZE: usize = 1024;
+
+/// Streams `body` in `CHUNK_SIZE` pieces to every call, as an upstream would.
+struct CannedUpstream {
+    body: Bytes,
+}
+
+impl UpstreamClient for CannedUpstream {
+    fn send<'a>(
+        &'a self,
+        _req: UpstreamHttpRequest,
+    ) -> Pin<Box<dyn Future<Output = Result<UpstreamHttpResponse, UpstreamFailure>> + Send + 'a>>
+    {
+        let (tx, rx) = tokio::sync::mpsc::channel(16);
+        let body = self.body.clone();
+        tokio::spawn(async move {
+            for start in (0..body.len()).step_by(CHUNK_SIZE) {
+                let end = (start + CHUNK_SIZE).min(body.len());
+                if tx.send(body.slice(start..end)).await.is_err() {
+                    return;
+                }
+            }
+        });
+        Box::pin(std::future::ready(Ok(UpstreamHttpResponse {
+            status: 200,
+            headers: vec![("content-type".to_string(), "text/event-stream".to_string())].into(),
+            body: UpstreamBody::Stream(rx),
+        })))
+    }
+}
+
+/// An OpenAI chat answer of `TEXT_DELTAS` deltas, as the upstream sends it.
+fn chat_stream() -> Bytes {
+    let mut out = String::new();
+    let mut event = |delta: serde_json::Value, finish: Option<&str>| {
+        let chunk = serde_json::json!({
+            "id": "chatcmpl-bench",
+            "object": "chat.completion.chunk",
+            "created": 1,
+            "model": "gpt-4o",
+            "choices": [{ "index": 0, "delta": delta, "finish_reason": finish }],
+        });
+        out.push_str(&format!("data: {chunk}\n\n"));
+    };
+    event(
+        serde_json::json!({ "role": "assistant", "content": "" }),
+        None,
+    );
+    for i in 0..TEXT_DELTAS {
+        event(
+            serde_json::json!({ "content": format!("token {i} of the answer, ") }),
+            None,
+        );
+    }
+    event(serde_json::json!({}), Some("stop"));
+    out.push_str("data: [DONE]\n\n");
+    Bytes::from(out)
+}
+
+/// Serves the proxy router on a loopback port and returns its address.
+async fn serve(upstream: Bytes) -> SocketAddr {
+    let seed: MemorySeed = serde_json::from_value(serde_json::json!({
+        "providers": [{
+            "name": "openai",
+            "config_json": { "kind": "openai", "channel_settings": {} },
+        }],
+        "credentials": [{
+            "provider": "openai",
+            "secret_json": { "OpenAI": { "api_key": "sk-upstream" } },
+        }],
+        "users": [{ "id": 1, "name": "bench" }],
+        "user_keys": [{ "user_id": 1, "api_key": API_KEY }],
+    }))
+    .unwrap();
+    let gproxy = GproxyBuilder::new()
+        .global(GlobalConfigPatch {
+            admin_key: Some("admin".to_string()),
+            ..GlobalConfigPatch::default()
+        })
+        .storage(Arc::new(MemoryStorage::from_seed(seed).unwrap()))
+        .upstream_client(Arc::new(CannedUpstream { body: upstream }))
+        .build()
+        .await
+        .unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let app = gproxy
+        .proxy_router()
+        .into_make_service_with_connect_info::<SocketAddr>();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    addr
+}
+
+/// Posts `body` to `path` on a fresh connection and reads the whole response.
+async fn post(addr: SocketAddr, path: &str, body: &str) -> Vec<u8> {
+    let mut conn = TcpStream::connect(addr).await.unwrap();
+    let head = format!(
+        "POST {path} HTTP/1.1\r\nhost: {addr}\r\nauthorization: Bearer {API_KEY}\r\n\
+         content-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+        body.len()
+    );
+    conn.write_all(head.as_bytes()).await.unwrap();
+    conn.write_all(body.as_bytes()).await.unwrap();
+    let mut resp = Vec::new();
+    conn.read_to_end(&mut resp).await.unwrap();
+    assert!(
+        resp.starts_with(b"HTTP/1.1 200"),
+        "{}",
+        String::from_utf8_lossy(&resp)
+    );
+    resp
+}
+
+fn proxy_loopback(c: &mut Criterion) {
+    // Every request logs its events at `info`; writing them out would dominate the run.
+    gproxy_common::log::set_filter(LogFilter::parse("warn").unwrap());
+    let rt = Runtime::new().unwrap();
+    let upstream = chat_stream();
+    let addr = rt.block_on(serve(upstream.clone()));
+    let chat = serde_json::json!({
+        "model": "gpt-4o",
+        "messages": [{ "role": "user", "content": "hi" }],
+        "stream": true,
+    })
+    .to_string();
+
+    let mut group = c.benchmark_group("proxy_loopback");
+    group.throughput(Throughput::Bytes(upstream.len() as u64));
+    group.bench_function("openai_chat_stream", |b| {
+        b.iter(|| rt.block_on(post(addr, "/openai/v1/chat/completions", &chat)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, proxy_loopback);
+criterion_main!(benches);
@@ -1,3 +1,4 @@
+use std::convert::Infallible;
 use std::sync::Arc;
 #[cfg(windows)]
 use std::sync::{Mutex, OnceLock};
@@ -6,30 +7,88 @@ use std::{env, fs, io::Read, path::PathBuf, time::Duration};
 
 use axum::Json;
 use axum::Router;
-use axum::extract::{Path, Query, State};
-use axum::http::{HeaderMap, StatusCode, header};
+use axum::extract::{Extension, Path, Query, State};
+use axum::http::{HeaderMap, Method, StatusCode, header};
 use axum::middleware::{self, Next};
 use axum::response::{IntoResponse, Response};
-use axum::routing::{get, post, put};
-use serde::Deserialize;
+use axum::routing::{delete, get, post, put};
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 use time::{Duration as TimeDuration, OffsetDateTime, format_description::well_known::Rfc3339};
-
-use gproxy_core::state::{AppState, CredentialInsertInput, ProviderRuntime};
-use gproxy_provider_core::{Credential, CredentialState, ProviderConfig, UnavailableReason};
+use tokio::sync::broadcast::error::RecvError;
+use tokio_stream::wrappers::ReceiverStream;
+
+use gproxy_core::incidents::incident_json;
+use gproxy_core::jobs::TriggerError;
+use gproxy_core::log_views::{LogViewFilter, MAX_ALERT_THRESHOLD, MAX_WINDOW_SECS};
+use gproxy_core::proxy_engine::{
+    AllowedOrigins, KeyScope, McpPolicy, ModerationPolicy, ProxyEngine,
+};
+use gproxy_core::state::{
+    AppState, BodyRetention, CredentialInsertInput, DEFAULT_CAPTURE_RETENTION, DNS_CACHE_TTL,
+    DrainAction, ProviderRuntime, SeriesStats, StatsDimension,
+};
+use gproxy_core::temporary_keys::{MAX_TTL_SECS, MIN_TTL_SECS};
+use gproxy_provider_core::{
+    Credential, CredentialState, DISALLOW_KEY, DISPATCH_KEY, DisallowRule, DispatchOverrides,
+    DispatchTable, OperationKind, ProviderConfig, ProviderPolicies, UnavailableReason,
+};
 use gproxy_storage::Storage;
 
+use crate::listing::{self, ListQuery, TimeCursor};
+use crate::proxy::wrap_sse_stream_with_heartbeat;
+
 #[derive(Clone)]
 pub struct AdminState {
     pub app: Arc<AppState>,
     pub storage: Arc<dyn Storage>,
+    /// Proxy routes used to replay logged requests; replay is unavailable without it.
+    pub proxy: Option<Router>,
+    /// Engine whose provider implementations dispatch overrides are checked against;
+    /// `/providers/{name}/dispatch` is unavailable without it.
+    pub engine: Option<Arc<ProxyEngine>>,
+}
+
+/// Which part of the namespace an admin token may see.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdminScope {
+    Global,
+    Org(i64),
+}
+
+impl AdminScope {
+    fn org_id(self) -> Option<i64> {
+        match self {
+            AdminScope::Global => None,
+            AdminScope::Org(id) => Some(id),
+        }
+    }
 }
 
 pub fn admin_router(app: Arc<AppState>, storage: Arc<dyn Storage>) -> Router {
-    let state = AdminState { app, storage };
+    admin_router_with_proxy(app, storage, None)
+}
+
+/// Like [`admin_router`], with the proxy routes that `POST /logs/downstream/{id}/replay`
+/// sends replayed requests through.
+pub fn admin_router_with_proxy(
+    app: Arc<AppState>,
+    storage: Arc<dyn Storage>,
+    proxy: Option<Router>,
+) -> Router {
+    admin_router_with_state(AdminState {
+        app,
+        storage,
+        proxy,
+        engine: None,
+    })
+}
 
+pub(crate) fn admin_router_with_state(state: AdminState) -> Router {
     Router::new()
         .route("/health", get(health))
+        .route("/overview", get(get_overview))
         .route("/global_config", get(get_global).put(put_global))
         .route("/providers", get(list_providers))
         .route(
@@ -38,15 +97,36 @@ pub fn admin_router(app: Arc<AppState>, storage: Arc<dyn Storage>) -> Router {
                 .put(upsert_provider)
                 .delete(delete_provider),
         )
+        .route(
+            "/providers/{name}/canary",
+            get(get_provider_canary)
+                .put(start_provider_canary)
+                .delete(cancel_provider_canary),
+        )
+        .route(
+            "/providers/{name}/canary/promote",
+            post(promote_provider_canary),
+        )
         .route(
             "/providers/{name}/credentials",
             get(list_provider_credentials).post(insert_credential),
         )
+        .route("/providers/{name}/disallow", get(list_disallow_rules))
+        .route(
+            "/providers/{name}/dispatch",
+            get(get_provider_dispatch).put(put_provider_dispatch),
+        )
+        .route(
+            "/providers/{name}/disallow/{id}",
+            put(upsert_disallow_rule).delete(delete_disallow_rule),
+        )
         .route("/credentials/{id}/enabled", put(set_credential_enabled))
+        .route("/credentials/{id}/drain", get(get_credential_drain))
         .route(
             "/credentials/{id}",
             put(update_credential).delete(delete_credential),
         )
+        .route("/credentials/{id}/usage", get(get_credential_usage))
         .route("/credentials", get(list_credentials))
         .route(
             "/usage/providers/{provider}/tokens",
@@ -65,19 +145,111 @@ pub fn admin_router(app: Arc<AppState>, storage: Arc<dyn Storage>) -> Router {
             get(usage_tokens_by_credential_model),
         )
         .route("/logs", get(query_logs))
+        .route("/logs/search", get(search_logs))
+        .route(
+            "/logs/downstream/{id}/replay",
+            post(replay_downstream_request),
+        )
+        .route("/diff", post(diff_providers))
+        .route("/traces/{trace_id}", get(get_trace_timeline))
+        .route("/operational_events", get(query_operational_events))
+        .route("/orgs", get(list_orgs))
+        .route(
+            "/orgs/{id}",
+            get(get_org).put(upsert_org).delete(delete_org),
+        )
+        .route("/orgs/{id}/enabled", put(set_org_enabled))
+        .route("/orgs/{id}/omit_bodies", put(set_org_omit_bodies))
+        .route("/orgs/{id}/limits", put(set_org_limits))
+        .route("/orgs/{id}/defaults", put(set_org_defaults))
+        .route(
+            "/orgs/{id}/grants",
+            get(list_org_grants).post(insert_org_grant),
+        )
+        .route("/org_grants/{id}", delete(delete_org_grant))
         .route("/users", get(list_users))
         .route("/users/{id}", put(upsert_user).delete(delete_user))
         .route("/users/{id}/enabled", put(set_user_enabled))
+        .route("/users/{id}/org", put(set_user_org))
         .route(
             "/users/{id}/keys",
             post(insert_user_key).get(list_user_keys),
         )
+        .route(
+            "/users/{id}/temporary_keys",
+            post(insert_temporary_user_key),
+        )
         .route("/user_keys/{id}/enabled", put(set_user_key_enabled))
+        .route("/user_keys/{id}/omit_bodies", put(set_user_key_omit_bodies))
+        .route(
+            "/user_keys/{id}/body_sample_percent",
+            put(set_user_key_body_sample_percent),
+        )
+        .route("/user_keys/{id}/limits", put(set_user_key_limits))
+        .route("/user_keys/{id}/defaults", put(set_user_key_defaults))
+        .route("/user_keys/{id}/mcp_policy", put(set_user_key_mcp_policy))
+        .route(
+            "/user_keys/{id}/moderation_policy",
+            put(set_user_key_moderation_policy),
+        )
+        .route(
+            "/user_keys/{id}/prelude_template",
+            put(set_user_key_prelude_template),
+        )
+        .route(
+            "/user_keys/{id}/allowed_origins",
+            put(set_user_key_allowed_origins),
+        )
+        .route("/mcp_tool_calls", get(list_mcp_tool_calls))
+        .route("/tool_calls", get(list_tool_calls))
+        .route("/aborted_streams", get(list_aborted_streams))
+        .route("/incidents", get(list_incidents))
+        .route("/secrets", get(list_secrets))
+        .route("/secrets/{name}", put(upsert_secret).delete(delete_secret))
+        .route(
+            "/user_keys/{id}/debug_capture",
+            put(arm_debug_capture).delete(disarm_debug_capture),
+        )
+        .route("/debug_captures", get(list_debug_captures))
+        .route("/debug_captures/armed", get(list_armed_debug_captures))
         .route(
             "/user_keys/{id}",
             put(update_user_key).delete(delete_user_key),
         )
+        .route("/model_profiles", get(list_model_profiles))
+        .route(
+            "/model_profiles/{name}",
+            put(upsert_model_profile).delete(delete_model_profile),
+        )
+        .route("/experiments", get(list_experiments))
+        .route(
+            "/experiments/{name}",
+            put(upsert_experiment).delete(delete_experiment),
+        )
+        .route("/experiments/{name}/usage", get(experiment_usage))
+        .route("/log_views", get(list_log_views))
+        .route(
+            "/log_views/{name}",
+            put(upsert_log_view).delete(delete_log_view),
+        )
+        .route("/log_views/{name}/logs", get(log_view_logs))
+        .route("/jobs", get(list_jobs))
+        .route("/jobs/{name}/runs", get(list_job_runs))
+        .route("/jobs/{name}/run", post(run_job))
         .route("/system/self_update", post(system_self_update))
+        .route("/system/upstream_pool", get(get_upstream_pool))
+        .route("/system/upstream_pool/flush", post(flush_upstream_pool))
+        .route("/system/snapshot/drift", get(get_snapshot_drift))
+        .route("/system/snapshot/resync", post(resync_snapshot))
+        .route(
+            "/system/log_filter",
+            get(get_log_filter).put(set_log_filter),
+        )
+        .route("/system/mode", get(get_system_mode))
+        .route("/system/maintenance", put(set_maintenance_mode))
+        .route("/system/read_only", put(set_read_only_mode))
+        .route("/events/config", get(stream_config_events))
+        .route("/ws", get(crate::live::admin_ws))
         .layer(middleware::from_fn_with_state(state.clone(), admin_auth))
         .with_state(state)
 }
@@ -85,17 +257,78 @@ pub fn admin_router(app: Arc<AppState>, storage: Arc<dyn Storage>) -> Router {
 async fn admin_auth(
     State(state): State<AdminState>,
     headers: HeaderMap,
-    req: axum::http::Request<axum::body::Body>,
+    mut req: axum::http::Request<axum::body::Body>,
     next: Next,
 ) -> Result<Response, StatusCode> {
     let key = extract_admin_key(&headers, req.uri()).ok_or(StatusCode::UNAUTHORIZED)?;
     let expected_key = state.app.global.load().admin_key.clone();
-    if key != expected_key {
-        return Err(StatusCode::UNAUTHORIZED);
+    let scope = if key == expected_key {
+        AdminScope::Global
+    } else {
+        let snapshot = state.app.snapshot.load();
+        let org = snapshot
+            .organizations
+            .iter()
+            .find(|o| o.enabled && o.admin_key.as_deref() == Some(key.as_str()))
+            .ok_or(StatusCode::UNAUTHORIZED)?;
+        if !org_scope_allows(req.method(), req.uri().path()) {
+            return Err(StatusCode::FORBIDDEN);
+        }
+        AdminScope::Org(org.id)
+    };
+    if state.app.system_mode.read_only() && !read_only_allows(req.method(), req.uri().path()) {
+        return Ok((
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({
+                "error": "read_only",
+                "detail": "gproxy is in read-only mode; PUT /admin/system/read_only to leave it",
+            })),
+        )
+            .into_response());
     }
+    req.extensions_mut().insert(scope);
     Ok(next.run(req).await)
 }
 
+/// Org-scoped admin tokens are read-only and limited to their own users and logs.
+fn org_scope_allows(method: &Method, path: &str) -> bool {
+    if method != Method::GET {
+        return false;
+    }
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    matches!(
+        segments.as_slice(),
+        ["health"]
+            | ["logs"]
+            | ["logs", "search"]
+            | ["log_views"]
+            | ["log_views", _, "logs"]
+            | ["users"]
+            | ["users", _, "keys"]
+            | ["orgs", _]
+            | ["orgs", _, "grants"]
+    )
+}
+
+/// In read-only mode only reads and calls that leave stored config alone get through.
+fn read_only_allows(method: &Method, path: &str) -> bool {
+    if method == Method::GET {
+        return true;
+    }
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    matches!(
+        segments.as_slice(),
+        ["system", "read_only"]
+            | ["system", "maintenance"]
+            | ["system", "log_filter"]
+            | ["system", "upstream_pool", "flush"]
+            | ["system", "snapshot", "resync"]
+            | ["logs", "downstream", _, "replay"]
+            | ["diff"]
+            | ["jobs", _, "run"]
+    )
+}
+
 fn extract_admin_key(headers: &HeaderMap, uri: &axum::http::Uri) -> Option<String> {
     if let Some(value) = headers.get("x-admin-key")
         && let Ok(s) = value.to_str()
@@ -132,15 +365,148 @@ async fn health() -> impl IntoResponse {
     (StatusCode::OK, Json(serde_json::json!({ "ok": true })))
 }
 
+const OVERVIEW_WINDOWS: [(&str, u64); 3] = [("5m", 5), ("60m", 60), ("24h", 24 * 60)];
+const OVERVIEW_TOP: usize = 10;
+
+pub(crate) fn series_json(series: &SeriesStats, minutes: u64) -> serde_json::Value {
+    serde_json::json!({
+        "requests": series.requests,
+        "requests_per_minute": series.requests as f64 / minutes as f64,
+        "errors": series.errors,
+        "error_rate": series.error_rate(),
+        "tokens": series.tokens,
+        "latency_p50_ms": series.latency.quantile_ms(0.5),
+        "latency_p95_ms": series.latency.quantile_ms(0.95),
+        "latency_p99_ms": series.latency.quantile_ms(0.99),
+    })
+}
+
+async fn get_overview(State(state): State<AdminState>) -> impl IntoResponse {
+    let stats = &state.app.stats;
+    let windows: Vec<_> = OVERVIEW_WINDOWS
+        .iter()
+        .map(|(label, minutes)| (*label, *minutes, stats.window(*minutes)))
+        .collect();
+
+    let mut window_json = serde_json::Map::new();
+    for (label, minutes, window) in &windows {
+        let mut entry = series_json(&window.total(), *minutes);
+        let top = |dimension: StatsDimension, name: &str| {
+            window
+                .top(dimension, OVERVIEW_TOP)
+                .into_iter()
+                .map(|(key, series)| {
+                    let mut item = series_json(&series, *minutes);
+                    item[name] = serde_json::json!(key);
+                    item
+                })
+                .collect::<Vec<_>>()
+        };
+        entry["top_models"] = serde_json::json!(top(StatsDimension::Model, "model"));
+        entry["top_keys"] = serde_json::json!(top(StatsDimension::Key, "user_key_id"));
+        window_json.insert((*label).to_string(), entry);
+    }
+
+    let event_queues: Vec<_> = state
+        .app
+        .events
+        .sink_queues()
+        .await
+        .into_iter()
+        .map(|queue| {
+            serde_json::json!({
+                "sink": queue.sink,
+                "queued": queue.queued,
+                "dropped": queue.dropped,
+            })
+        })
+        .collect();
+
+    let snapshot = state.app.snapshot.load();
+    let runtime_map = state.app.providers.load();
+    let now = OffsetDateTime::now_utc();
+    let mut providers = Vec::new();
+    for provider in &snapshot.providers {
+        let (availability, maintenance) = match runtime_map.get(&provider.name) {
+            Some(runtime) => (
+                runtime.pool.availability(&provider.name).await,
+                runtime.policies().maintenance.active_at(now).cloned(),
+            ),
+            None => Default::default(),
+        };
+        let mut upstream = serde_json::Map::new();
+        for (label, minutes, window) in &windows {
+            let series = window.get(StatsDimension::Provider, &provider.name);
+            let mut entry = series_json(&series, *minutes);
+            entry["attempts"] = serde_json::json!(series.requests);
+            entry["failures"] = serde_json::json!(series.errors);
+            entry["transforms"] = window
+                .transforms(&provider.name)
+                .into_iter()
+                .map(|(pair, series)| {
+                    let mut item = series_json(&series, *minutes);
+                    item["transform"] = serde_json::json!(pair);
+                    item
+                })
+                .collect();
+            upstream.insert((*label).to_string(), entry);
+        }
+        providers.push(serde_json::json!({
+            "name": provider.name,
+            "enabled": provider.enabled,
+            "credentials": availability.credentials,
+            "credentials_available": availability.credentials - availability.unavailable,
+            "credentials_unavailable": availability.unavailable,
+            "model_cooldowns": availability.model_cooldowns,
+            "maintenance": maintenance,
+            "upstream": upstream,
+        }));
+    }
+
+    Json(serde_json::json!({
+        "generated_at": format_time_rfc3339(now),
+        "active_streams": stats.active_streams(),
+        "transcoder": state.app.transcoder.status(),
+        "encode_mismatches": stats.encode_mismatches(),
+        "event_queues": event_queues,
+        "windows": window_json,
+        "providers": providers,
+    }))
+}
+
 async fn get_global(State(state): State<AdminState>) -> impl IntoResponse {
     let global = state.app.global.load();
     Json(serde_json::json!({
         "host": global.host,
         "port": global.port,
+        "admin_host": global.admin_host,
+        "admin_port": global.admin_port,
         "admin_key": global.admin_key,
         "proxy": global.proxy,
         "dsn": global.dsn,
         "event_redact_sensitive": global.event_redact_sensitive,
+        "native_error_format": global.native_error_format,
+        "stream_retry_on_interrupt": global.stream_retry_on_interrupt,
+        "stream_first_token_timeout_ms": global.stream_first_token_timeout_ms,
+        "coalesce_inflight_requests": global.coalesce_inflight_requests,
+        "response_compress_min_bytes": global.response_compress_min_bytes,
+        "key_abuse_max_ips": global.key_abuse_max_ips,
+        "key_abuse_min_rpm": global.key_abuse_min_rpm,
+        "key_abuse_blocked_countries": global.key_abuse_blocked_countries,
+        "alert_webhook_url": global.alert_webhook_url,
+        "geoip_country_db": global.geoip_country_db,
+        "geoip_asn_db": global.geoip_asn_db,
+        "stream_idle_timeout_ms": global.stream_idle_timeout_ms,
+        "egress_ip_family": global.egress_ip_family,
+        "egress_local_address": global.egress_local_address,
+        "egress_interface": global.egress_interface,
+        "tool_call_audit": global.tool_call_audit,
+        "max_response_body_bytes": global.max_response_body_bytes,
+        "truncate_oversize_responses": global.truncate_oversize_responses,
+        "event_log_max_body_bytes": global.event_log_max_body_bytes,
+        "log_search": global.log_search,
+        "trusted_proxies": global.trusted_proxies,
+        "sse_resume": global.sse_resume,
     }))
 }
 
@@ -148,9 +514,33 @@ async fn get_global(State(state): State<AdminState>) -> impl IntoResponse {
 struct PutGlobalBody {
     pub host: Option<String>,
     pub port: Option<u16>,
+    pub admin_host: Option<String>,
+    pub admin_port: Option<u16>,
     pub admin_key: Option<String>,
     pub proxy: Option<String>,
     pub event_redact_sensitive: Option<bool>,
+    pub native_error_format: Option<bool>,
+    pub stream_retry_on_interrupt: Option<bool>,
+    pub stream_first_token_timeout_ms: Option<u64>,
+    pub coalesce_inflight_requests: Option<bool>,
+    pub response_compress_min_bytes: Option<u64>,
+    pub key_abuse_max_ips: Option<u64>,
+    pub key_abuse_min_rpm: Option<u64>,
+    pub key_abuse_blocked_countries: Option<String>,
+    pub alert_webhook_url: Option<String>,
+    pub geoip_country_db: Option<String>,
+    pub geoip_asn_db: Option<String>,
+    pub stream_idle_timeout_ms: Option<u64>,
+    pub egress_ip_family: Option<String>,
+    pub egress_local_address: Option<String>,
+    pub egress_interface: Option<String>,
+    pub tool_call_audit: Option<bool>,
+    pub max_response_body_bytes: Option<u64>,
+    pub truncate_oversize_responses: Option<bool>,
+    pub event_log_max_body_bytes: Option<u64>,
+    pub log_search: Option<bool>,
+    pub sse_resume: Option<bool>,
+    pub trusted_proxies: Option<String>,
 }
 
 async fn put_global(
@@ -160,6 +550,8 @@ async fn put_global(
     let patch = gproxy_common::GlobalConfigPatch {
         host: body.host,
         port: body.port,
+        admin_host: body.admin_host,
+        admin_port: body.admin_port,
         admin_key: body.admin_key.and_then(|key| {
             let trimmed = key.trim();
             if trimmed.is_empty() {
@@ -171,6 +563,28 @@ async fn put_global(
         proxy: body.proxy,
         dsn: None,
         event_redact_sensitive: body.event_redact_sensitive,
+        native_error_format: body.native_error_format,
+        stream_retry_on_interrupt: body.stream_retry_on_interrupt,
+        stream_first_token_timeout_ms: body.stream_first_token_timeout_ms,
+        coalesce_inflight_requests: body.coalesce_inflight_requests,
+        response_compress_min_bytes: body.response_compress_min_bytes,
+        key_abuse_max_ips: body.key_abuse_max_ips,
+        key_abuse_min_rpm: body.key_abuse_min_rpm,
+        key_abuse_blocked_countries: body.key_abuse_blocked_countries,
+        alert_webhook_url: body.alert_webhook_url,
+        geoip_country_db: body.geoip_country_db,
+        geoip_asn_db: body.geoip_asn_db,
+        stream_idle_timeout_ms: body.stream_idle_timeout_ms,
+        egress_ip_family: body.egress_ip_family,
+        egress_local_address: body.egress_local_address,
+        egress_interface: body.egress_interface,
+        tool_call_audit: body.tool_call_audit,
+        max_response_body_bytes: body.max_response_body_bytes,
+        truncate_oversize_responses: body.truncate_oversize_responses,
+        event_log_max_body_bytes: body.event_log_max_body_bytes,
+        log_search: body.log_search,
+        sse_resume: body.sse_resume,
+        trusted_proxies: body.trusted_proxies,
     };
 
     // DB commit -> in-memory apply (strong consistency).
@@ -191,12 +605,16 @@ async fn put_global(
     if let Err(err) = state.storage.upsert_global_config(&next).await {
         return storage_error(err).into_response();
     }
+    state.storage.set_log_search(next.log_search);
     state.app.apply_global_config(next);
 
     (StatusCode::OK, Json(serde_json::json!({ "ok": true }))).into_response()
 }
 
-async fn list_providers(State(state): State<AdminState>) -> impl IntoResponse {
+async fn list_providers(
+    State(state): State<AdminState>,
+    Query(query): Query<ListQuery>,
+) -> impl IntoResponse {
     let snapshot = state.app.snapshot.load();
     let providers: Vec<_> = snapshot
         .providers
@@ -210,7 +628,12 @@ async fn list_providers(State(state): State<AdminState>) -> impl IntoResponse {
             })
         })
         .collect();
-    Json(serde_json::json!({ "providers": providers }))
+    list_page(
+        providers,
+        &query,
+        &["name", "enabled", "updated_at"],
+        "providers",
+    )
 }
 
 async fn get_provider(
@@ -375,6 +798,14 @@ async fn upsert_provider(
     Path(name): Path<String>,
     Json(body): Json<UpsertProviderBody>,
 ) -> impl IntoResponse {
+    if let Err((key, err)) = ProviderPolicies::validate_config_json(&body.config_json) {
+        return bad_request(&format!("invalid_{key}"), err).into_response();
+    }
+    if let Some(base) = builtin_dispatch_table(&state, &body.config_json)
+        && let Err(err) = DispatchOverrides::from_config_json(&body.config_json).validate(&base)
+    {
+        return bad_request("invalid_dispatch_override", err).into_response();
+    }
     let id = match state
         .storage
         .upsert_provider(&name, &body.config_json, body.enabled)
@@ -435,173 +866,448 @@ async fn delete_provider(
 }
 
 #[derive(Debug, Deserialize)]
-struct InsertCredentialBody {
-    pub name: Option<String>,
-    #[serde(default = "default_object")]
-    pub settings_json: serde_json::Value,
-    pub secret_json: serde_json::Value,
-    #[serde(default = "default_true")]
-    pub enabled: bool,
+struct StartCanaryBody {
+    pub config_json: serde_json::Value,
+    #[serde(default = "default_canary_percent")]
+    pub percent: u8,
+    #[serde(default = "default_canary_max_error_rate")]
+    pub max_error_rate: f64,
+    #[serde(default = "default_canary_min_requests")]
+    pub min_requests: u64,
+    #[serde(default = "default_canary_window_secs")]
+    pub window_secs: u64,
 }
 
-fn default_true() -> bool {
-    true
+fn default_canary_percent() -> u8 {
+    5
 }
 
-fn default_object() -> serde_json::Value {
-    serde_json::json!({})
+fn default_canary_max_error_rate() -> f64 {
+    0.1
 }
 
-async fn insert_credential(
+fn default_canary_min_requests() -> u64 {
+    20
+}
+
+fn default_canary_window_secs() -> u64 {
+    600
+}
+
+fn canary_not_found() -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        Json(serde_json::json!({ "error": "canary_not_found" })),
+    )
+        .into_response()
+}
+
+async fn get_provider_canary(
     State(state): State<AdminState>,
-    Path(provider_name): Path<String>,
-    Json(body): Json<InsertCredentialBody>,
+    Path(name): Path<String>,
 ) -> impl IntoResponse {
-    // Validate provider exists in memory (snapshot + runtime map).
-    let snapshot = state.app.snapshot.load();
-    let provider = snapshot.providers.iter().find(|p| p.name == provider_name);
-    let Some(provider) = provider else {
-        return (
-            StatusCode::NOT_FOUND,
-            Json(serde_json::json!({ "error": "provider_not_found" })),
-        )
-            .into_response();
-    };
+    match state.app.provider_canary(&name) {
+        Some(canary) => (StatusCode::OK, Json(canary.status_json())).into_response(),
+        None => canary_not_found(),
+    }
+}
 
-    // Validate secret_json is a known Credential variant and matches provider config kind.
-    let cred: Credential = match serde_json::from_value(body.secret_json.clone()) {
-        Ok(c) => c,
-        Err(err) => {
+async fn start_provider_canary(
+    State(state): State<AdminState>,
+    Path(name): Path<String>,
+    Json(body): Json<StartCanaryBody>,
+) -> impl IntoResponse {
+    if body.percent == 0 || body.percent > 100 {
+        return bad_request("invalid_percent", "`percent` must be between 1 and 100")
+            .into_response();
+    }
+    if !(0.0..=1.0).contains(&body.max_error_rate) {
+        return bad_request(
+            "invalid_max_error_rate",
+            "`max_error_rate` must be between 0 and 1",
+        )
+        .into_response();
+    }
+    let current = {
+        let snapshot = state.app.snapshot.load();
+        let Some(provider) = snapshot.providers.iter().find(|p| p.name == name) else {
             return (
-                StatusCode::BAD_REQUEST,
-                Json(serde_json::json!({
-                    "error": "invalid_credential_json",
-                    "detail": err.to_string(),
-                })),
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({ "error": "provider_not_found" })),
             )
                 .into_response();
+        };
+        serde_json::from_value::<ProviderConfig>(provider.config_json.clone()).ok()
+    };
+    let next = match serde_json::from_value::<ProviderConfig>(body.config_json.clone()) {
+        Ok(v) => v,
+        Err(err) => {
+            return bad_request("provider_config_invalid", err.to_string()).into_response();
         }
     };
-
-    // Parse provider config to enforce credential kind (best-effort).
-    let runtime = state.app.providers.load().get(&provider_name).cloned();
-    if let Some(runtime) = runtime
-        && let Ok(cfg) =
-            serde_json::from_value::<ProviderConfig>(runtime.config_json.load().as_ref().clone())
-        && !credential_matches_provider(&cred, &cfg)
+    if let Err((key, err)) = ProviderPolicies::validate_config_json(&body.config_json) {
+        return bad_request(&format!("invalid_{key}"), err).into_response();
+    }
+    // Traffic split between two configs only makes sense for the same provider kind.
+    if current
+        .as_ref()
+        .is_some_and(|current| std::mem::discriminant(current) != std::mem::discriminant(&next))
+    {
+        return bad_request(
+            "provider_kind_mismatch",
+            "a canary config must keep the provider kind",
+        )
+        .into_response();
+    }
+    let settings = gproxy_core::state::CanarySettings {
+        percent: body.percent,
+        max_error_rate: body.max_error_rate,
+        min_requests: body.min_requests,
+        window: Duration::from_secs(body.window_secs),
+    };
+    if !state
+        .app
+        .start_provider_canary(&name, body.config_json, settings)
     {
         return (
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({ "error": "credential_kind_mismatch" })),
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "provider_not_found" })),
         )
             .into_response();
     }
+    match state.app.provider_canary(&name) {
+        Some(canary) => (StatusCode::OK, Json(canary.status_json())).into_response(),
+        None => canary_not_found(),
+    }
+}
 
+async fn promote_provider_canary(
+    State(state): State<AdminState>,
+    Path(name): Path<String>,
+) -> impl IntoResponse {
+    let Some(canary) = state.app.provider_canary(&name) else {
+        return canary_not_found();
+    };
+    if canary.is_rolled_back() {
+        return (
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({ "error": "canary_rolled_back" })),
+        )
+            .into_response();
+    }
+    let enabled = state
+        .app
+        .snapshot
+        .load()
+        .providers
+        .iter()
+        .find(|p| p.name == name)
+        .map(|p| p.enabled)
+        .unwrap_or(true);
+    let config_json = canary.config_json.as_ref().clone();
     let id = match state
         .storage
-        .insert_credential(
-            &provider_name,
-            body.name.as_deref(),
-            &body.settings_json,
-            &body.secret_json,
-            body.enabled,
-        )
+        .upsert_provider(&name, &config_json, enabled)
         .await
     {
         Ok(id) => id,
         Err(err) => return storage_error(err).into_response(),
     };
-
-    if let Err(err) = state
+    // Replaces the stable config and drops the canary.
+    state
         .app
-        .apply_credential_insert(CredentialInsertInput {
-            id,
-            provider_name,
-            provider_id: provider.id,
-            name: body.name,
-            settings_json: body.settings_json,
-            secret_json: body.secret_json,
-            enabled: body.enabled,
-        })
-        .await
-    {
-        return (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({ "error": "apply_memory_failed", "detail": err.to_string() })),
-        )
-            .into_response();
-    }
-
-    (StatusCode::OK, Json(serde_json::json!({ "id": id }))).into_response()
-}
-
-#[derive(Debug, Deserialize)]
-struct SetEnabledBody {
-    pub enabled: bool,
+        .apply_provider_upsert(id, name.clone(), config_json, enabled);
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({ "id": id, "name": name, "promoted": true })),
+    )
+        .into_response()
 }
 
-async fn set_credential_enabled(
+async fn cancel_provider_canary(
     State(state): State<AdminState>,
-    Path(id): Path<i64>,
-    Json(body): Json<SetEnabledBody>,
+    Path(name): Path<String>,
 ) -> impl IntoResponse {
-    if let Err(err) = state.storage.set_credential_enabled(id, body.enabled).await {
-        return storage_error(err).into_response();
+    match state.app.clear_provider_canary(&name) {
+        Some(_) => (StatusCode::OK, Json(serde_json::json!({ "ok": true }))).into_response(),
+        None => canary_not_found(),
     }
+}
 
-    if let Err(err) = state.app.apply_credential_enabled(id, body.enabled).await {
+async fn list_disallow_rules(
+    State(state): State<AdminState>,
+    Path(name): Path<String>,
+) -> impl IntoResponse {
+    let snapshot = state.app.snapshot.load();
+    let Some(provider) = snapshot.providers.iter().find(|p| p.name == name) else {
         return (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({ "error": "apply_memory_failed", "detail": err.to_string() })),
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "provider_not_found" })),
         )
             .into_response();
-    }
+    };
+    let now = OffsetDateTime::now_utc();
+    let rules: Vec<JsonValue> = DisallowRule::list_from_config_json(&provider.config_json)
+        .into_iter()
+        .map(|rule| {
+            let expired = rule.is_expired(now);
+            let mut value = serde_json::to_value(rule).unwrap_or_default();
+            value["expired"] = JsonValue::Bool(expired);
+            value
+        })
+        .collect();
+    Json(serde_json::json!({ "rules": rules })).into_response()
+}
 
-    (StatusCode::OK, Json(serde_json::json!({ "ok": true }))).into_response()
+async fn upsert_disallow_rule(
+    State(state): State<AdminState>,
+    Path((name, id)): Path<(String, String)>,
+    Json(mut body): Json<JsonValue>,
+) -> impl IntoResponse {
+    let Some(fields) = body.as_object_mut() else {
+        return bad_request("invalid_disallow_rule", "body must be a JSON object").into_response();
+    };
+    fields.insert("id".to_string(), JsonValue::String(id));
+    let rule = match serde_json::from_value::<DisallowRule>(body) {
+        Ok(rule) => rule,
+        Err(err) => return bad_request("invalid_disallow_rule", err.to_string()).into_response(),
+    };
+    edit_disallow_rules(&state, &name, |rules| {
+        match rules.iter_mut().find(|r| r.id == rule.id) {
+            Some(existing) => *existing = rule,
+            None => rules.push(rule),
+        }
+        true
+    })
+    .await
 }
 
-async fn delete_credential(
+async fn delete_disallow_rule(
     State(state): State<AdminState>,
-    Path(id): Path<i64>,
+    Path((name, id)): Path<(String, String)>,
 ) -> impl IntoResponse {
-    // Ensure it won't be acquired anymore after deletion.
-    {
-        let snapshot = state.app.snapshot.load();
-        if let Some(row) = snapshot.credentials.iter().find(|c| c.id == id)
-            && let Some(provider_name) = snapshot
-                .providers
-                .iter()
-                .find(|p| p.id == row.provider_id)
-                .map(|p| p.name.clone())
-            && let Some(runtime) = state.app.providers.load().get(&provider_name).cloned()
-        {
-            runtime.pool.set_enabled(&provider_name, id, false).await;
-        }
+    edit_disallow_rules(&state, &name, |rules| {
+        let before = rules.len();
+        rules.retain(|r| r.id != id);
+        rules.len() != before
+    })
+    .await
+}
+
+/// Rewrites the provider's `disallow` list through `edit`, which reports whether the rule
+/// it was asked about existed, and persists the config like a provider upsert.
+async fn edit_disallow_rules(
+    state: &AdminState,
+    name: &str,
+    edit: impl FnOnce(&mut Vec<DisallowRule>) -> bool,
+) -> Response {
+    let Some((mut config_json, enabled)) = state
+        .app
+        .snapshot
+        .load()
+        .providers
+        .iter()
+        .find(|p| p.name == name)
+        .map(|p| (p.config_json.clone(), p.enabled))
+    else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "provider_not_found" })),
+        )
+            .into_response();
+    };
+    let mut rules = DisallowRule::list_from_config_json(&config_json);
+    if !edit(&mut rules) {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "disallow_rule_not_found" })),
+        )
+            .into_response();
+    }
+    let Some(map) = config_json.as_object_mut() else {
+        return bad_request(
+            "provider_config_invalid",
+            "provider config is not an object",
+        )
+        .into_response();
+    };
+    if rules.is_empty() {
+        map.remove(DISALLOW_KEY);
+    } else {
+        map.insert(
+            DISALLOW_KEY.to_string(),
+            serde_json::to_value(&rules).unwrap_or_default(),
+        );
     }
+    let id = match state
+        .storage
+        .upsert_provider(name, &config_json, enabled)
+        .await
+    {
+        Ok(id) => id,
+        Err(err) => return storage_error(err).into_response(),
+    };
+    state
+        .app
+        .apply_provider_upsert(id, name.to_string(), config_json, enabled);
+    Json(serde_json::json!({ "rules": rules })).into_response()
+}
 
-    if let Err(err) = state.storage.delete_credential(id).await {
-        return storage_error(err).into_response();
+/// Built-in dispatch table of the implementation serving `config_json`.
+fn builtin_dispatch_table(state: &AdminState, config_json: &JsonValue) -> Option<DispatchTable> {
+    let config = serde_json::from_value::<ProviderConfig>(config_json.clone()).ok()?;
+    state.engine.as_ref()?.builtin_dispatch_table(&config)
+}
+
+/// The provider's config JSON and built-in dispatch table, or the error response.
+fn provider_dispatch(
+    state: &AdminState,
+    name: &str,
+) -> Result<(JsonValue, bool, DispatchTable), (StatusCode, Json<serde_json::Value>)> {
+    if state.engine.is_none() {
+        return Err((
+            StatusCode::NOT_IMPLEMENTED,
+            Json(serde_json::json!({
+                "error": "dispatch_unavailable",
+                "detail": "admin router was built without the proxy engine",
+            })),
+        ));
     }
+    let Some((config_json, enabled)) = state
+        .app
+        .snapshot
+        .load()
+        .providers
+        .iter()
+        .find(|p| p.name == name)
+        .map(|p| (p.config_json.clone(), p.enabled))
+    else {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "provider_not_found" })),
+        ));
+    };
+    let Some(base) = builtin_dispatch_table(state, &config_json) else {
+        return Err(bad_request(
+            "provider_config_invalid",
+            "provider config does not name a registered implementation",
+        ));
+    };
+    Ok((config_json, enabled, base))
+}
 
-    // Best-effort: remove from snapshot. Pool removal is handled by disabling before delete.
-    state.app.apply_credential_delete(id);
+/// Every operation with the provider's built-in rule, its override, the rule in effect
+/// and the rules an override may use.
+fn dispatch_view(base: &DispatchTable, overrides: &DispatchOverrides) -> JsonValue {
+    let effective = base.clone().with_overrides(overrides);
+    let ops: Vec<JsonValue> = OperationKind::ALL
+        .into_iter()
+        .map(|kind| {
+            serde_json::json!({
+                "op": kind.key(),
+                "default": base.rule(kind),
+                "override": overrides.ops.get(kind.key()),
+                "effective": effective.rule(kind),
+                "choices": base.override_choices(kind),
+            })
+        })
+        .collect();
+    serde_json::json!({ "ops": ops })
+}
 
-    (StatusCode::OK, Json(serde_json::json!({ "ok": true }))).into_response()
+async fn get_provider_dispatch(
+    State(state): State<AdminState>,
+    Path(name): Path<String>,
+) -> impl IntoResponse {
+    let (config_json, _, base) = match provider_dispatch(&state, &name) {
+        Ok(v) => v,
+        Err(err) => return err.into_response(),
+    };
+    Json(dispatch_view(
+        &base,
+        &DispatchOverrides::from_config_json(&config_json),
+    ))
+    .into_response()
+}
+
+/// Replaces the provider's dispatch overrides; an empty `ops` map removes them. Saved
+/// like a provider upsert, so the next request dispatches with them.
+async fn put_provider_dispatch(
+    State(state): State<AdminState>,
+    Path(name): Path<String>,
+    Json(overrides): Json<DispatchOverrides>,
+) -> impl IntoResponse {
+    let (mut config_json, enabled, base) = match provider_dispatch(&state, &name) {
+        Ok(v) => v,
+        Err(err) => return err.into_response(),
+    };
+    if let Err(err) = overrides.validate(&base) {
+        return bad_request("invalid_dispatch_override", err).into_response();
+    }
+    let Some(map) = config_json.as_object_mut() else {
+        return bad_request(
+            "provider_config_invalid",
+            "provider config is not an object",
+        )
+        .into_response();
+    };
+    if overrides.ops.is_empty() {
+        map.remove(DISPATCH_KEY);
+    } else {
+        map.insert(
+            DISPATCH_KEY.to_string(),
+            serde_json::to_value(&overrides).unwrap_or_default(),
+        );
+    }
+    let id = match state
+        .storage
+        .upsert_provider(&name, &config_json, enabled)
+        .await
+    {
+        Ok(id) => id,
+        Err(err) => return storage_error(err).into_response(),
+    };
+    state
+        .app
+        .apply_provider_upsert(id, name, config_json, enabled);
+    Json(dispatch_view(&base, &overrides)).into_response()
 }
 
 #[derive(Debug, Deserialize)]
-struct UpdateCredentialBody {
+struct InsertCredentialBody {
     pub name: Option<String>,
-    pub settings_json: Option<serde_json::Value>,
+    #[serde(default = "default_object")]
+    pub settings_json: serde_json::Value,
     pub secret_json: serde_json::Value,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
 }
 
-async fn update_credential(
+fn default_true() -> bool {
+    true
+}
+
+fn default_object() -> serde_json::Value {
+    serde_json::json!({})
+}
+
+async fn insert_credential(
     State(state): State<AdminState>,
-    Path(id): Path<i64>,
-    Json(body): Json<UpdateCredentialBody>,
+    Path(provider_name): Path<String>,
+    Json(body): Json<InsertCredentialBody>,
 ) -> impl IntoResponse {
-    // Validate secret_json is a known Credential variant.
+    // Validate provider exists in memory (snapshot + runtime map).
+    let snapshot = state.app.snapshot.load();
+    let provider = snapshot.providers.iter().find(|p| p.name == provider_name);
+    let Some(provider) = provider else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "provider_not_found" })),
+        )
+            .into_response();
+    };
+
+    // Validate secret_json is a known Credential variant and matches provider config kind.
     let cred: Credential = match serde_json::from_value(body.secret_json.clone()) {
         Ok(c) => c,
         Err(err) => {
@@ -616,32 +1322,10 @@ async fn update_credential(
         }
     };
 
-    // Validate provider kind matches existing provider config kind.
-    let snapshot = state.app.snapshot.load();
-    let existing = snapshot.credentials.iter().find(|c| c.id == id);
-    let Some(existing) = existing else {
-        return (
-            StatusCode::NOT_FOUND,
-            Json(serde_json::json!({ "error": "credential_not_found" })),
-        )
-            .into_response();
-    };
-    let provider_name = snapshot
-        .providers
-        .iter()
-        .find(|p| p.id == existing.provider_id)
-        .map(|p| p.name.clone());
-    let Some(provider_name) = provider_name else {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({ "error": "provider_not_found" })),
-        )
-            .into_response();
-    };
+    // Parse provider config to enforce credential kind (best-effort).
     let runtime = state.app.providers.load().get(&provider_name).cloned();
     if let Some(runtime) = runtime
-        && let Ok(cfg) =
-            serde_json::from_value::<ProviderConfig>(runtime.config_json.load().as_ref().clone())
+        && let Ok(cfg) = runtime.provider_config()
         && !credential_matches_provider(&cred, &cfg)
     {
         return (
@@ -651,29 +1335,32 @@ async fn update_credential(
             .into_response();
     }
 
-    if let Err(err) = state
+    let id = match state
         .storage
-        .update_credential(
-            id,
+        .insert_credential(
+            &provider_name,
             body.name.as_deref(),
-            body.settings_json
-                .as_ref()
-                .unwrap_or(&existing.settings_json),
+            &body.settings_json,
             &body.secret_json,
+            body.enabled,
         )
         .await
     {
-        return storage_error(err).into_response();
-    }
+        Ok(id) => id,
+        Err(err) => return storage_error(err).into_response(),
+    };
 
     if let Err(err) = state
         .app
-        .apply_credential_update(
+        .apply_credential_insert(CredentialInsertInput {
             id,
-            body.name,
-            body.settings_json.unwrap_or(existing.settings_json.clone()),
-            body.secret_json,
-        )
+            provider_name,
+            provider_id: provider.id,
+            name: body.name,
+            settings_json: body.settings_json,
+            secret_json: body.secret_json,
+            enabled: body.enabled,
+        })
         .await
     {
         return (
@@ -683,664 +1370,3918 @@ async fn update_credential(
             .into_response();
     }
 
-    (StatusCode::OK, Json(serde_json::json!({ "ok": true }))).into_response()
-}
-
-async fn list_credentials(State(state): State<AdminState>) -> impl IntoResponse {
-    let snapshot = state.app.snapshot.load();
-    let provider_map: std::collections::HashMap<i64, String> = snapshot
-        .providers
-        .iter()
-        .map(|p| (p.id, p.name.clone()))
-        .collect();
-    let runtime_map = state.app.providers.load();
-    let mut creds = Vec::new();
-    for c in &snapshot.credentials {
-        let runtime = provider_map
-            .get(&c.provider_id)
-            .and_then(|provider_name| runtime_map.get(provider_name).cloned());
-        let runtime_status = build_runtime_status(runtime.as_ref(), c.id, c.enabled).await;
-        creds.push(serde_json::json!({
-            "id": c.id,
-            "provider_id": c.provider_id,
-            "name": c.name,
-            "settings_json": c.settings_json,
-            "secret_json": c.secret_json,
-            "enabled": c.enabled,
-            "created_at": c.created_at,
-            "updated_at": c.updated_at,
-            "runtime_status": runtime_status,
-        }));
-    }
-    Json(serde_json::json!({ "credentials": creds }))
+    (StatusCode::OK, Json(serde_json::json!({ "id": id }))).into_response()
 }
 
 #[derive(Debug, Deserialize)]
-struct UsageRangeQuery {
-    from: String,
-    to: String,
-    #[serde(default)]
-    model_contains: Option<String>,
+struct SetEnabledBody {
+    pub enabled: bool,
 }
 
 #[derive(Debug, Deserialize)]
-struct LogsQuery {
-    #[serde(default)]
-    from: Option<String>,
-    #[serde(default)]
-    to: Option<String>,
-    #[serde(default)]
-    kind: Option<String>,
-    #[serde(default)]
-    provider: Option<String>,
-    #[serde(default)]
-    credential_id: Option<i64>,
-    #[serde(default)]
-    user_id: Option<i64>,
-    #[serde(default)]
-    user_key_id: Option<i64>,
-    #[serde(default)]
-    trace_id: Option<String>,
-    #[serde(default)]
-    operation: Option<String>,
-    #[serde(default)]
-    path_contains: Option<String>,
-    #[serde(default)]
-    status_min: Option<i32>,
-    #[serde(default)]
-    status_max: Option<i32>,
-    #[serde(default)]
-    limit: Option<usize>,
-    #[serde(default)]
-    offset: Option<usize>,
-    #[serde(default)]
-    cursor_at: Option<String>,
-    #[serde(default)]
-    cursor_id: Option<i64>,
-    #[serde(default)]
-    include_body: Option<bool>,
+struct DrainQuery {
+    /// Wait up to this long for in-flight requests before disabling or deleting.
+    drain_secs: Option<u64>,
 }
 
-async fn usage_tokens_by_provider(
+async fn set_credential_enabled(
     State(state): State<AdminState>,
-    Path(provider): Path<String>,
-    Query(query): Query<UsageRangeQuery>,
+    Path(id): Path<i64>,
+    Query(query): Query<DrainQuery>,
+    Json(body): Json<SetEnabledBody>,
 ) -> impl IntoResponse {
-    let (from, to) = match parse_usage_range(&query) {
-        Ok(v) => v,
-        Err(resp) => return resp.into_response(),
-    };
-
-    let aggregate = match state
-        .storage
-        .aggregate_usage_tokens(gproxy_storage::UsageAggregateFilter {
-            from,
-            to,
-            provider: Some(provider.clone()),
-            credential_id: None,
-            model: None,
-            model_contains: query.model_contains.clone(),
-        })
-        .await
+    if state.app.credential_drains.is_draining(id) {
+        return credential_draining();
+    }
+    if !body.enabled
+        && let Some(secs) = query.drain_secs
     {
-        Ok(v) => v,
-        Err(err) => return storage_error(err).into_response(),
-    };
-
-    (
-        StatusCode::OK,
-        Json(serde_json::json!({
-            "scope": "provider",
-            "provider": provider,
-            "from": query.from,
-            "to": query.to,
-            "matched_rows": aggregate.matched_rows,
-            "call_count": aggregate.matched_rows,
-            "input_tokens": aggregate.input_tokens,
-            "output_tokens": aggregate.output_tokens,
-            "cache_read_input_tokens": aggregate.cache_read_input_tokens,
-            "cache_creation_input_tokens": aggregate.cache_creation_input_tokens,
-            "total_tokens": aggregate.total_tokens,
-        })),
-    )
-        .into_response()
+        return start_credential_drain(state, id, DrainAction::Disable, secs).await;
+    }
+    match apply_credential_change(&state, id, Some(body.enabled)).await {
+        Ok(()) => (StatusCode::OK, Json(serde_json::json!({ "ok": true }))).into_response(),
+        Err(resp) => resp.into_response(),
+    }
 }
 
-async fn usage_tokens_by_provider_model(
+async fn delete_credential(
     State(state): State<AdminState>,
-    Path((provider, model)): Path<(String, String)>,
-    Query(query): Query<UsageRangeQuery>,
+    Path(id): Path<i64>,
+    Query(query): Query<DrainQuery>,
 ) -> impl IntoResponse {
-    let (from, to) = match parse_usage_range(&query) {
-        Ok(v) => v,
-        Err(resp) => return resp.into_response(),
-    };
+    if state.app.credential_drains.is_draining(id) {
+        return credential_draining();
+    }
+    if let Some(secs) = query.drain_secs {
+        return start_credential_drain(state, id, DrainAction::Delete, secs).await;
+    }
+    // Ensure it won't be acquired anymore after deletion.
+    state.app.detach_credential(id).await;
+    match apply_credential_change(&state, id, None).await {
+        Ok(()) => (StatusCode::OK, Json(serde_json::json!({ "ok": true }))).into_response(),
+        Err(resp) => resp.into_response(),
+    }
+}
 
-    let aggregate = match state
-        .storage
-        .aggregate_usage_tokens(gproxy_storage::UsageAggregateFilter {
-            from,
-            to,
-            provider: Some(provider.clone()),
-            credential_id: None,
-            model: Some(model.clone()),
-            model_contains: query.model_contains.clone(),
-        })
-        .await
-    {
-        Ok(v) => v,
-        Err(err) => return storage_error(err).into_response(),
-    };
+async fn get_credential_drain(
+    State(state): State<AdminState>,
+    Path(id): Path<i64>,
+) -> impl IntoResponse {
+    match state.app.credential_drains.status(id) {
+        Some(status) => Json(serde_json::json!(status)).into_response(),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "drain_not_found" })),
+        )
+            .into_response(),
+    }
+}
 
+fn credential_draining() -> Response {
     (
-        StatusCode::OK,
-        Json(serde_json::json!({
-            "scope": "provider_model",
-            "provider": provider,
-            "model": model,
-            "from": query.from,
-            "to": query.to,
-            "matched_rows": aggregate.matched_rows,
-            "call_count": aggregate.matched_rows,
-            "input_tokens": aggregate.input_tokens,
-            "output_tokens": aggregate.output_tokens,
-            "cache_read_input_tokens": aggregate.cache_read_input_tokens,
-            "cache_creation_input_tokens": aggregate.cache_creation_input_tokens,
-            "total_tokens": aggregate.total_tokens,
-        })),
+        StatusCode::CONFLICT,
+        Json(serde_json::json!({ "error": "credential_draining" })),
     )
         .into_response()
 }
 
-async fn usage_tokens_by_credential(
-    State(state): State<AdminState>,
-    Path(credential_id): Path<i64>,
-    Query(query): Query<UsageRangeQuery>,
-) -> impl IntoResponse {
-    let (from, to) = match parse_usage_range(&query) {
-        Ok(v) => v,
-        Err(resp) => return resp.into_response(),
-    };
-
-    let aggregate = match state
-        .storage
-        .aggregate_usage_tokens(gproxy_storage::UsageAggregateFilter {
-            from,
-            to,
-            provider: None,
-            credential_id: Some(credential_id),
-            model: None,
-            model_contains: query.model_contains.clone(),
-        })
-        .await
+/// Takes the credential out of rotation now and applies `action` once its in-flight
+/// requests finish or `secs` pass, in the background. Answers 202 with the drain status.
+async fn start_credential_drain(
+    state: AdminState,
+    id: i64,
+    action: DrainAction,
+    secs: u64,
+) -> Response {
+    if !state
+        .app
+        .snapshot
+        .load()
+        .credentials
+        .iter()
+        .any(|c| c.id == id)
     {
-        Ok(v) => v,
-        Err(err) => return storage_error(err).into_response(),
-    };
-
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "credential_not_found" })),
+        )
+            .into_response();
+    }
+    if !state
+        .app
+        .credential_drains
+        .begin(id, action, Duration::from_secs(secs))
+    {
+        return credential_draining();
+    }
+    state.app.detach_credential(id).await;
+    let status = state.app.credential_drains.status(id);
+    tokio::spawn(async move {
+        let pool = state.app.upstream_pool.clone();
+        state
+            .app
+            .credential_drains
+            .wait(id, || pool.credential_in_flight(id))
+            .await;
+        let enabled = match action {
+            DrainAction::Disable => Some(false),
+            DrainAction::Delete => None,
+        };
+        let result = apply_credential_change(&state, id, enabled)
+            .await
+            .map_err(|(_, Json(body))| body["detail"].as_str().unwrap_or_default().to_string());
+        state.app.credential_drains.finish(id, result);
+    });
     (
-        StatusCode::OK,
-        Json(serde_json::json!({
-            "scope": "credential",
-            "credential_id": credential_id,
-            "from": query.from,
-            "to": query.to,
-            "matched_rows": aggregate.matched_rows,
-            "call_count": aggregate.matched_rows,
-            "input_tokens": aggregate.input_tokens,
-            "output_tokens": aggregate.output_tokens,
-            "cache_read_input_tokens": aggregate.cache_read_input_tokens,
-            "cache_creation_input_tokens": aggregate.cache_creation_input_tokens,
-            "total_tokens": aggregate.total_tokens,
-        })),
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({ "ok": true, "drain": status })),
     )
         .into_response()
 }
 
-async fn usage_tokens_by_credential_model(
-    State(state): State<AdminState>,
-    Path((credential_id, model)): Path<(i64, String)>,
-    Query(query): Query<UsageRangeQuery>,
-) -> impl IntoResponse {
-    let (from, to) = match parse_usage_range(&query) {
-        Ok(v) => v,
-        Err(resp) => return resp.into_response(),
+/// Persists and applies `enabled` for the credential, or deletes it when `None`.
+async fn apply_credential_change(
+    state: &AdminState,
+    id: i64,
+    enabled: Option<bool>,
+) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    let Some(enabled) = enabled else {
+        state
+            .storage
+            .delete_credential(id)
+            .await
+            .map_err(storage_error)?;
+        // Best-effort: remove from snapshot. Pool removal is handled by disabling before delete.
+        state.app.apply_credential_delete(id);
+        return Ok(());
     };
-
-    let aggregate = match state
+    state
         .storage
-        .aggregate_usage_tokens(gproxy_storage::UsageAggregateFilter {
-            from,
-            to,
-            provider: None,
-            credential_id: Some(credential_id),
-            model: Some(model.clone()),
-            model_contains: query.model_contains.clone(),
-        })
+        .set_credential_enabled(id, enabled)
         .await
-    {
-        Ok(v) => v,
-        Err(err) => return storage_error(err).into_response(),
-    };
+        .map_err(storage_error)?;
+    state
+        .app
+        .apply_credential_enabled(id, enabled)
+        .await
+        .map_err(|err| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": "apply_memory_failed", "detail": err.to_string() })),
+            )
+        })
+}
 
-    (
-        StatusCode::OK,
-        Json(serde_json::json!({
-            "scope": "credential_model",
-            "credential_id": credential_id,
-            "model": model,
-            "from": query.from,
-            "to": query.to,
-            "matched_rows": aggregate.matched_rows,
-            "call_count": aggregate.matched_rows,
-            "input_tokens": aggregate.input_tokens,
-            "output_tokens": aggregate.output_tokens,
-            "cache_read_input_tokens": aggregate.cache_read_input_tokens,
-            "cache_creation_input_tokens": aggregate.cache_creation_input_tokens,
-            "total_tokens": aggregate.total_tokens,
-        })),
-    )
-        .into_response()
+#[derive(Debug, Deserialize)]
+struct UpdateCredentialBody {
+    pub name: Option<String>,
+    pub settings_json: Option<serde_json::Value>,
+    pub secret_json: serde_json::Value,
 }
 
-async fn query_logs(
+async fn update_credential(
     State(state): State<AdminState>,
-    Query(query): Query<LogsQuery>,
+    Path(id): Path<i64>,
+    Json(body): Json<UpdateCredentialBody>,
 ) -> impl IntoResponse {
-    let kind = match normalize_opt_str(query.kind).as_deref() {
-        None | Some("all") => None,
-        Some("upstream") => Some(gproxy_storage::LogRecordKind::Upstream),
-        Some("downstream") => Some(gproxy_storage::LogRecordKind::Downstream),
-        Some(other) => {
+    // Validate secret_json is a known Credential variant.
+    let cred: Credential = match serde_json::from_value(body.secret_json.clone()) {
+        Ok(c) => c,
+        Err(err) => {
             return (
                 StatusCode::BAD_REQUEST,
                 Json(serde_json::json!({
-                    "error": "invalid_kind",
-                    "detail": format!("unsupported kind: {other}; expected one of all/upstream/downstream"),
+                    "error": "invalid_credential_json",
+                    "detail": err.to_string(),
                 })),
             )
                 .into_response();
         }
     };
 
-    if let (Some(status_min), Some(status_max)) = (query.status_min, query.status_max)
-        && status_max < status_min
+    // Validate provider kind matches existing provider config kind.
+    let snapshot = state.app.snapshot.load();
+    let existing = snapshot.credentials.iter().find(|c| c.id == id);
+    let Some(existing) = existing else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "credential_not_found" })),
+        )
+            .into_response();
+    };
+    let provider_name = snapshot
+        .providers
+        .iter()
+        .find(|p| p.id == existing.provider_id)
+        .map(|p| p.name.clone());
+    let Some(provider_name) = provider_name else {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "provider_not_found" })),
+        )
+            .into_response();
+    };
+    let runtime = state.app.providers.load().get(&provider_name).cloned();
+    if let Some(runtime) = runtime
+        && let Ok(cfg) = runtime.provider_config()
+        && !credential_matches_provider(&cred, &cfg)
     {
         return (
             StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({
-                "error": "invalid_status_range",
-                "detail": "`status_max` must be >= `status_min`",
-            })),
+            Json(serde_json::json!({ "error": "credential_kind_mismatch" })),
         )
             .into_response();
     }
 
-    let now = OffsetDateTime::now_utc();
-    let default_from = now - TimeDuration::hours(24);
-    let from = match normalize_opt_str(query.from) {
-        Some(raw) => match OffsetDateTime::parse(&raw, &Rfc3339) {
-            Ok(v) => v,
-            Err(err) => {
-                return (
-                    StatusCode::BAD_REQUEST,
-                    Json(serde_json::json!({
-                        "error": "invalid_from",
-                        "detail": err.to_string(),
-                    })),
-                )
-                    .into_response();
-            }
-        },
-        None => default_from,
+    if let Err(err) = state
+        .storage
+        .update_credential(
+            id,
+            body.name.as_deref(),
+            body.settings_json
+                .as_ref()
+                .unwrap_or(&existing.settings_json),
+            &body.secret_json,
+        )
+        .await
+    {
+        return storage_error(err).into_response();
+    }
+
+    if let Err(err) = state
+        .app
+        .apply_credential_update(
+            id,
+            body.name,
+            body.settings_json.unwrap_or(existing.settings_json.clone()),
+            body.secret_json,
+        )
+        .await
+    {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": "apply_memory_failed", "detail": err.to_string() })),
+        )
+            .into_response();
+    }
+
+    (StatusCode::OK, Json(serde_json::json!({ "ok": true }))).into_response()
+}
+
+async fn list_credentials(
+    State(state): State<AdminState>,
+    Query(query): Query<ListQuery>,
+) -> impl IntoResponse {
+    let snapshot = state.app.snapshot.load();
+    let provider_map: std::collections::HashMap<i64, String> = snapshot
+        .providers
+        .iter()
+        .map(|p| (p.id, p.name.clone()))
+        .collect();
+    let creds: Vec<_> = snapshot
+        .credentials
+        .iter()
+        .map(|c| {
+            serde_json::json!({
+                "id": c.id,
+                "provider_id": c.provider_id,
+                "name": c.name,
+                "settings_json": c.settings_json,
+                "secret_json": c.secret_json,
+                "enabled": c.enabled,
+                "created_at": c.created_at,
+                "updated_at": c.updated_at,
+            })
+        })
+        .collect();
+    let mut page = match listing::paginate(
+        creds,
+        &query,
+        &["provider_id", "name", "enabled", "created_at", "updated_at"],
+    ) {
+        Ok(page) => page,
+        Err(err) => return bad_request(err.code, err.detail).into_response(),
     };
-    let to = match normalize_opt_str(query.to) {
-        Some(raw) => match OffsetDateTime::parse(&raw, &Rfc3339) {
+    // Runtime status is only worked out for the credentials on the page.
+    let runtime_map = state.app.providers.load();
+    for (item, c) in page.items.iter_mut().filter_map(|item| {
+        let id = item.get("id")?.as_i64()?;
+        let c = snapshot.credentials.iter().find(|c| c.id == id)?;
+        Some((item, c))
+    }) {
+        let runtime = provider_map
+            .get(&c.provider_id)
+            .and_then(|provider_name| runtime_map.get(provider_name).cloned());
+        item["runtime_status"] = build_runtime_status(runtime.as_ref(), c.id, c.enabled).await;
+    }
+    Json(page.into_json("credentials", &query)).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+struct CredentialUsageQuery {
+    #[serde(default)]
+    from: Option<String>,
+    #[serde(default)]
+    to: Option<String>,
+    /// `hour` (default) or `day`.
+    #[serde(default)]
+    bucket: Option<String>,
+}
+
+const CREDENTIAL_USAGE_DEFAULT_RANGE: TimeDuration = TimeDuration::hours(24);
+const CREDENTIAL_RECENT_ERRORS: usize = 20;
+
+#[derive(Default)]
+struct CredentialUsageBucket {
+    requests: i64,
+    errors: i64,
+    input_tokens: i64,
+    output_tokens: i64,
+    cache_read_input_tokens: i64,
+    cache_creation_input_tokens: i64,
+}
+
+impl CredentialUsageBucket {
+    fn add_usage(&mut self, usage: &gproxy_storage::UsageRecord) {
+        self.input_tokens += usage.input_tokens.unwrap_or(0);
+        self.output_tokens += usage.output_tokens.unwrap_or(0);
+        self.cache_read_input_tokens += usage.cache_read_input_tokens.unwrap_or(0);
+        self.cache_creation_input_tokens += usage.cache_creation_input_tokens.unwrap_or(0);
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "requests": self.requests,
+            "errors": self.errors,
+            "input_tokens": self.input_tokens,
+            "output_tokens": self.output_tokens,
+            "cache_read_input_tokens": self.cache_read_input_tokens,
+            "cache_creation_input_tokens": self.cache_creation_input_tokens,
+            "total_tokens": self.input_tokens + self.output_tokens,
+        })
+    }
+}
+
+fn is_failed_outcome(outcome: &gproxy_storage::UpstreamOutcome) -> bool {
+    outcome.error_kind.is_some() || outcome.response_status.is_none_or(|status| status >= 400)
+}
+
+async fn get_credential_usage(
+    State(state): State<AdminState>,
+    Path(credential_id): Path<i64>,
+    Query(query): Query<CredentialUsageQuery>,
+) -> impl IntoResponse {
+    let snapshot = state.app.snapshot.load();
+    let Some(credential) = snapshot.credentials.iter().find(|c| c.id == credential_id) else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "credential_not_found" })),
+        )
+            .into_response();
+    };
+    let provider_name = snapshot
+        .providers
+        .iter()
+        .find(|p| p.id == credential.provider_id)
+        .map(|p| p.name.clone());
+
+    let to = match query
+        .to
+        .as_deref()
+        .map(|raw| OffsetDateTime::parse(raw, &Rfc3339))
+    {
+        None => OffsetDateTime::now_utc(),
+        Some(Ok(v)) => v,
+        Some(Err(err)) => return bad_request("invalid_to", err.to_string()).into_response(),
+    };
+    let from = match query
+        .from
+        .as_deref()
+        .map(|raw| OffsetDateTime::parse(raw, &Rfc3339))
+    {
+        None => to - CREDENTIAL_USAGE_DEFAULT_RANGE,
+        Some(Ok(v)) => v,
+        Some(Err(err)) => return bad_request("invalid_from", err.to_string()).into_response(),
+    };
+    if to < from {
+        return bad_request("invalid_range", "`to` must be >= `from`").into_response();
+    }
+    let bucket = query.bucket.as_deref().unwrap_or("hour");
+    let bucket_secs: i64 = match bucket {
+        "hour" => 3600,
+        "day" => 86_400,
+        _ => return bad_request("invalid_bucket", "expected `hour` or `day`").into_response(),
+    };
+
+    let (usages, outcomes, cooldowns) = match tokio::try_join!(
+        state
+            .storage
+            .list_credential_usages(credential_id, from, to),
+        state
+            .storage
+            .list_credential_outcomes(credential_id, from, to),
+        state
+            .storage
+            .list_credential_cooldowns(credential_id, from, to),
+    ) {
+        Ok(v) => v,
+        Err(err) => return storage_error(err).into_response(),
+    };
+
+    let bucket_of = |at: OffsetDateTime| at.unix_timestamp().div_euclid(bucket_secs) * bucket_secs;
+    let mut totals = CredentialUsageBucket::default();
+    let mut buckets: std::collections::BTreeMap<i64, CredentialUsageBucket> = Default::default();
+    for outcome in &outcomes {
+        let failed = i64::from(is_failed_outcome(outcome));
+        let entry = buckets.entry(bucket_of(outcome.at)).or_default();
+        entry.requests += 1;
+        entry.errors += failed;
+        totals.requests += 1;
+        totals.errors += failed;
+    }
+    for usage in &usages {
+        buckets
+            .entry(bucket_of(usage.at))
+            .or_default()
+            .add_usage(usage);
+        totals.add_usage(usage);
+    }
+    let series: Vec<_> = buckets
+        .iter()
+        .filter_map(|(start, bucket)| {
+            let start = OffsetDateTime::from_unix_timestamp(*start).ok()?;
+            let mut item = bucket.to_json();
+            item["start"] = serde_json::json!(format_time_rfc3339(start));
+            Some(item)
+        })
+        .collect();
+
+    let recent_errors: Vec<_> = outcomes
+        .iter()
+        .rev()
+        .filter(|outcome| is_failed_outcome(outcome))
+        .take(CREDENTIAL_RECENT_ERRORS)
+        .map(|outcome| {
+            serde_json::json!({
+                "id": outcome.id,
+                "at": format_time_rfc3339(outcome.at),
+                "trace_id": outcome.trace_id,
+                "operation": outcome.operation,
+                "response_status": outcome.response_status,
+                "error_kind": outcome.error_kind,
+                "error_message": outcome.error_message,
+            })
+        })
+        .collect();
+
+    let cooldowns: Vec<_> = cooldowns
+        .iter()
+        .map(|record| {
+            serde_json::json!({
+                "at": format_time_rfc3339(record.at),
+                "event_type": record.event_type,
+                "model": record.model,
+                "reason": record.reason.map(unavailable_reason_code),
+                "until": record.until.map(format_time_rfc3339),
+            })
+        })
+        .collect();
+
+    let runtime = provider_name
+        .as_ref()
+        .and_then(|name| state.app.providers.load().get(name).cloned());
+    let runtime_status =
+        build_runtime_status(runtime.as_ref(), credential_id, credential.enabled).await;
+
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "credential_id": credential_id,
+            "provider": provider_name,
+            "name": credential.name,
+            "enabled": credential.enabled,
+            "from": format_time_rfc3339(from),
+            "to": format_time_rfc3339(to),
+            "bucket": bucket,
+            "totals": totals.to_json(),
+            "series": series,
+            "recent_errors": recent_errors,
+            "cooldowns": cooldowns,
+            "runtime_status": runtime_status,
+        })),
+    )
+        .into_response()
+}
+
+#[derive(Debug, Deserialize)]
+struct OperationalEventsQuery {
+    #[serde(default)]
+    from: Option<String>,
+    #[serde(default)]
+    to: Option<String>,
+    #[serde(default)]
+    event_type: Option<String>,
+    #[serde(default)]
+    provider: Option<String>,
+    #[serde(default)]
+    credential_id: Option<i64>,
+    #[serde(default)]
+    model: Option<String>,
+    #[serde(default)]
+    limit: Option<usize>,
+    #[serde(default)]
+    cursor_at: Option<String>,
+    #[serde(default)]
+    cursor_id: Option<i64>,
+    #[serde(default)]
+    cursor: Option<String>,
+    #[serde(default)]
+    fields: Option<String>,
+}
+
+const OPERATIONAL_EVENT_TYPES: [&str; 5] = [
+    "unavailable_start",
+    "unavailable_end",
+    "model_unavailable_start",
+    "model_unavailable_end",
+    "user_key_auto_disabled",
+];
+
+async fn query_operational_events(
+    State(state): State<AdminState>,
+    Query(query): Query<OperationalEventsQuery>,
+) -> impl IntoResponse {
+    let event_type = normalize_opt_str(query.event_type);
+    if let Some(event_type) = event_type.as_deref()
+        && !OPERATIONAL_EVENT_TYPES.contains(&event_type)
+    {
+        return bad_request(
+            "invalid_event_type",
+            format!(
+                "unsupported event_type: {event_type}; expected one of {}",
+                OPERATIONAL_EVENT_TYPES.join("/")
+            ),
+        )
+        .into_response();
+    }
+
+    let now = OffsetDateTime::now_utc();
+    let from = match normalize_opt_str(query.from).map(|raw| OffsetDateTime::parse(&raw, &Rfc3339))
+    {
+        None => now - TimeDuration::hours(24),
+        Some(Ok(v)) => v,
+        Some(Err(err)) => return bad_request("invalid_from", err.to_string()).into_response(),
+    };
+    let to = match normalize_opt_str(query.to).map(|raw| OffsetDateTime::parse(&raw, &Rfc3339)) {
+        None => now,
+        Some(Ok(v)) => v,
+        Some(Err(err)) => return bad_request("invalid_to", err.to_string()).into_response(),
+    };
+    if to < from {
+        return bad_request("invalid_range", "`to` must be >= `from`").into_response();
+    }
+    let (cursor_at, cursor_id) =
+        match listing::time_cursor(query.cursor.as_deref(), query.cursor_at, query.cursor_id) {
             Ok(v) => v,
+            Err(err) => return bad_request(err.code, err.detail).into_response(),
+        };
+    let cursor = match (normalize_opt_str(cursor_at), cursor_id) {
+        (None, None) => None,
+        (Some(cursor_at), Some(cursor_id)) => match OffsetDateTime::parse(&cursor_at, &Rfc3339) {
+            Ok(at) => Some(gproxy_storage::LogCursor { at, id: cursor_id }),
             Err(err) => {
-                return (
-                    StatusCode::BAD_REQUEST,
-                    Json(serde_json::json!({
-                        "error": "invalid_to",
-                        "detail": err.to_string(),
-                    })),
-                )
-                    .into_response();
+                return bad_request("invalid_cursor_at", err.to_string()).into_response();
             }
         },
-        None => now,
+        _ => {
+            return bad_request(
+                "invalid_cursor",
+                "cursor_at and cursor_id must be provided together",
+            )
+            .into_response();
+        }
     };
-    if to < from {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({
-                "error": "invalid_range",
-                "detail": "`to` must be >= `from`",
-            })),
+
+    let limit = query.limit.unwrap_or(100).clamp(1, 500);
+
+    let snapshot = state.app.snapshot.load();
+    let provider_by_id: std::collections::HashMap<i64, &str> = snapshot
+        .providers
+        .iter()
+        .map(|p| (p.id, p.name.as_str()))
+        .collect();
+    let provider_of_credential: std::collections::HashMap<i64, &str> = snapshot
+        .credentials
+        .iter()
+        .filter_map(|c| Some((c.id, *provider_by_id.get(&c.provider_id)?)))
+        .collect();
+    let credential_ids = normalize_opt_str(query.provider).map(|provider| {
+        provider_of_credential
+            .iter()
+            .filter(|(_, name)| **name == provider)
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>()
+    });
+
+    let filter = gproxy_storage::OperationalEventFilter {
+        from,
+        to,
+        event_type,
+        credential_id: query.credential_id,
+        credential_ids,
+        model: normalize_opt_str(query.model),
+        limit,
+        cursor,
+    };
+    let result = match state.storage.query_operational_events(filter).await {
+        Ok(v) => v,
+        Err(err) => return storage_error(err).into_response(),
+    };
+
+    let rows: Vec<_> = result
+        .rows
+        .iter()
+        .map(|row| {
+            serde_json::json!({
+                "id": row.id,
+                "at": format_time_rfc3339(row.at),
+                "event_type": row.event_type,
+                "provider": row
+                    .credential_id
+                    .and_then(|id| provider_of_credential.get(&id)),
+                "credential_id": row.credential_id,
+                "model": row.model,
+                "reason": row.reason.map(unavailable_reason_code),
+                "until": row.until.map(format_time_rfc3339),
+                // Evidence for key auto-disables; credential events carry nothing extra.
+                "detail": (row.event_type == "user_key_auto_disabled")
+                    .then(|| row.payload.as_object().and_then(|o| o.values().next()).cloned())
+                    .flatten(),
+            })
+        })
+        .collect();
+    let rows = project_rows(rows, query.fields.as_deref());
+    let (next_cursor_at, next_cursor_id) = match result.next_cursor {
+        Some(cursor) => (Some(format_time_rfc3339(cursor.at)), Some(cursor.id)),
+        None => (None, None),
+    };
+    let next_cursor = time_cursor_json(next_cursor_at.as_deref(), next_cursor_id);
+
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "from": format_time_rfc3339(from),
+            "to": format_time_rfc3339(to),
+            "limit": limit,
+            "has_more": result.has_more,
+            "next_cursor": next_cursor,
+            "next_cursor_at": next_cursor_at,
+            "next_cursor_id": next_cursor_id,
+            "rows": rows,
+        })),
+    )
+        .into_response()
+}
+
+#[derive(Debug, Deserialize)]
+struct UsageRangeQuery {
+    from: String,
+    to: String,
+    #[serde(default)]
+    model_contains: Option<String>,
+    #[serde(default)]
+    tag: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LogsQuery {
+    #[serde(default)]
+    from: Option<String>,
+    #[serde(default)]
+    to: Option<String>,
+    #[serde(default)]
+    kind: Option<String>,
+    #[serde(default)]
+    provider: Option<String>,
+    #[serde(default)]
+    credential_id: Option<i64>,
+    #[serde(default)]
+    user_id: Option<i64>,
+    #[serde(default)]
+    user_key_id: Option<i64>,
+    #[serde(default)]
+    org_id: Option<i64>,
+    #[serde(default)]
+    trace_id: Option<String>,
+    #[serde(default)]
+    operation: Option<String>,
+    #[serde(default)]
+    tag: Option<String>,
+    #[serde(default)]
+    path_contains: Option<String>,
+    #[serde(default)]
+    status_min: Option<i32>,
+    #[serde(default)]
+    status_max: Option<i32>,
+    #[serde(default)]
+    country: Option<String>,
+    #[serde(default)]
+    asn: Option<i64>,
+    #[serde(default)]
+    limit: Option<usize>,
+    #[serde(default)]
+    offset: Option<usize>,
+    #[serde(default)]
+    cursor_at: Option<String>,
+    #[serde(default)]
+    cursor_id: Option<i64>,
+    #[serde(default)]
+    cursor: Option<String>,
+    #[serde(default)]
+    fields: Option<String>,
+    #[serde(default)]
+    include_body: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LogSearchParams {
+    #[serde(default)]
+    q: Option<String>,
+    #[serde(default)]
+    from: Option<String>,
+    #[serde(default)]
+    to: Option<String>,
+    #[serde(default)]
+    kind: Option<String>,
+    #[serde(default)]
+    org_id: Option<i64>,
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+async fn usage_tokens_by_provider(
+    State(state): State<AdminState>,
+    Path(provider): Path<String>,
+    Query(query): Query<UsageRangeQuery>,
+) -> impl IntoResponse {
+    let (from, to) = match parse_usage_range(&query) {
+        Ok(v) => v,
+        Err(resp) => return resp.into_response(),
+    };
+
+    let aggregate = match state
+        .storage
+        .aggregate_usage_tokens(gproxy_storage::UsageAggregateFilter {
+            from,
+            to,
+            provider: Some(provider.clone()),
+            credential_id: None,
+            user_ids: None,
+            model: None,
+            model_contains: query.model_contains.clone(),
+            tag: normalize_opt_str(query.tag.clone()),
+            experiment: None,
+            experiment_arm: None,
+        })
+        .await
+    {
+        Ok(v) => v,
+        Err(err) => return storage_error(err).into_response(),
+    };
+
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "scope": "provider",
+            "provider": provider,
+            "from": query.from,
+            "to": query.to,
+            "matched_rows": aggregate.matched_rows,
+            "call_count": aggregate.matched_rows,
+            "input_tokens": aggregate.input_tokens,
+            "output_tokens": aggregate.output_tokens,
+            "cache_read_input_tokens": aggregate.cache_read_input_tokens,
+            "cache_creation_input_tokens": aggregate.cache_creation_input_tokens,
+            "total_tokens": aggregate.total_tokens,
+        })),
+    )
+        .into_response()
+}
+
+async fn usage_tokens_by_provider_model(
+    State(state): State<AdminState>,
+    Path((provider, model)): Path<(String, String)>,
+    Query(query): Query<UsageRangeQuery>,
+) -> impl IntoResponse {
+    let (from, to) = match parse_usage_range(&query) {
+        Ok(v) => v,
+        Err(resp) => return resp.into_response(),
+    };
+
+    let aggregate = match state
+        .storage
+        .aggregate_usage_tokens(gproxy_storage::UsageAggregateFilter {
+            from,
+            to,
+            provider: Some(provider.clone()),
+            credential_id: None,
+            user_ids: None,
+            model: Some(model.clone()),
+            model_contains: query.model_contains.clone(),
+            tag: normalize_opt_str(query.tag.clone()),
+            experiment: None,
+            experiment_arm: None,
+        })
+        .await
+    {
+        Ok(v) => v,
+        Err(err) => return storage_error(err).into_response(),
+    };
+
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "scope": "provider_model",
+            "provider": provider,
+            "model": model,
+            "from": query.from,
+            "to": query.to,
+            "matched_rows": aggregate.matched_rows,
+            "call_count": aggregate.matched_rows,
+            "input_tokens": aggregate.input_tokens,
+            "output_tokens": aggregate.output_tokens,
+            "cache_read_input_tokens": aggregate.cache_read_input_tokens,
+            "cache_creation_input_tokens": aggregate.cache_creation_input_tokens,
+            "total_tokens": aggregate.total_tokens,
+        })),
+    )
+        .into_response()
+}
+
+async fn usage_tokens_by_credential(
+    State(state): State<AdminState>,
+    Path(credential_id): Path<i64>,
+    Query(query): Query<UsageRangeQuery>,
+) -> impl IntoResponse {
+    let (from, to) = match parse_usage_range(&query) {
+        Ok(v) => v,
+        Err(resp) => return resp.into_response(),
+    };
+
+    let aggregate = match state
+        .storage
+        .aggregate_usage_tokens(gproxy_storage::UsageAggregateFilter {
+            from,
+            to,
+            provider: None,
+            credential_id: Some(credential_id),
+            user_ids: None,
+            model: None,
+            model_contains: query.model_contains.clone(),
+            tag: normalize_opt_str(query.tag.clone()),
+            experiment: None,
+            experiment_arm: None,
+        })
+        .await
+    {
+        Ok(v) => v,
+        Err(err) => return storage_error(err).into_response(),
+    };
+
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "scope": "credential",
+            "credential_id": credential_id,
+            "from": query.from,
+            "to": query.to,
+            "matched_rows": aggregate.matched_rows,
+            "call_count": aggregate.matched_rows,
+            "input_tokens": aggregate.input_tokens,
+            "output_tokens": aggregate.output_tokens,
+            "cache_read_input_tokens": aggregate.cache_read_input_tokens,
+            "cache_creation_input_tokens": aggregate.cache_creation_input_tokens,
+            "total_tokens": aggregate.total_tokens,
+        })),
+    )
+        .into_response()
+}
+
+async fn usage_tokens_by_credential_model(
+    State(state): State<AdminState>,
+    Path((credential_id, model)): Path<(i64, String)>,
+    Query(query): Query<UsageRangeQuery>,
+) -> impl IntoResponse {
+    let (from, to) = match parse_usage_range(&query) {
+        Ok(v) => v,
+        Err(resp) => return resp.into_response(),
+    };
+
+    let aggregate = match state
+        .storage
+        .aggregate_usage_tokens(gproxy_storage::UsageAggregateFilter {
+            from,
+            to,
+            provider: None,
+            credential_id: Some(credential_id),
+            user_ids: None,
+            model: Some(model.clone()),
+            model_contains: query.model_contains.clone(),
+            tag: normalize_opt_str(query.tag.clone()),
+            experiment: None,
+            experiment_arm: None,
+        })
+        .await
+    {
+        Ok(v) => v,
+        Err(err) => return storage_error(err).into_response(),
+    };
+
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "scope": "credential_model",
+            "credential_id": credential_id,
+            "model": model,
+            "from": query.from,
+            "to": query.to,
+            "matched_rows": aggregate.matched_rows,
+            "call_count": aggregate.matched_rows,
+            "input_tokens": aggregate.input_tokens,
+            "output_tokens": aggregate.output_tokens,
+            "cache_read_input_tokens": aggregate.cache_read_input_tokens,
+            "cache_creation_input_tokens": aggregate.cache_creation_input_tokens,
+            "total_tokens": aggregate.total_tokens,
+        })),
+    )
+        .into_response()
+}
+
+async fn query_logs(
+    State(state): State<AdminState>,
+    Extension(scope): Extension<AdminScope>,
+    Query(query): Query<LogsQuery>,
+) -> impl IntoResponse {
+    let kind = match normalize_opt_str(query.kind).as_deref() {
+        None | Some("all") => None,
+        Some("upstream") => Some(gproxy_storage::LogRecordKind::Upstream),
+        Some("downstream") => Some(gproxy_storage::LogRecordKind::Downstream),
+        Some(other) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({
+                    "error": "invalid_kind",
+                    "detail": format!("unsupported kind: {other}; expected one of all/upstream/downstream"),
+                })),
+            )
+                .into_response();
+        }
+    };
+
+    if let (Some(status_min), Some(status_max)) = (query.status_min, query.status_max)
+        && status_max < status_min
+    {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": "invalid_status_range",
+                "detail": "`status_max` must be >= `status_min`",
+            })),
+        )
+            .into_response();
+    }
+    let tag = match query_tag(query.tag) {
+        Ok(tag) => tag,
+        Err(resp) => return resp.into_response(),
+    };
+
+    let now = OffsetDateTime::now_utc();
+    let default_from = now - TimeDuration::hours(24);
+    let from = match normalize_opt_str(query.from) {
+        Some(raw) => match OffsetDateTime::parse(&raw, &Rfc3339) {
+            Ok(v) => v,
+            Err(err) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(serde_json::json!({
+                        "error": "invalid_from",
+                        "detail": err.to_string(),
+                    })),
+                )
+                    .into_response();
+            }
+        },
+        None => default_from,
+    };
+    let to = match normalize_opt_str(query.to) {
+        Some(raw) => match OffsetDateTime::parse(&raw, &Rfc3339) {
+            Ok(v) => v,
+            Err(err) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(serde_json::json!({
+                        "error": "invalid_to",
+                        "detail": err.to_string(),
+                    })),
+                )
+                    .into_response();
+            }
+        },
+        None => now,
+    };
+    if to < from {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": "invalid_range",
+                "detail": "`to` must be >= `from`",
+            })),
+        )
+            .into_response();
+    }
+
+    let offset = query.offset.unwrap_or(0);
+    if offset > 0 {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": "offset_not_supported",
+                "detail": "offset pagination is disabled for performance; use cursor_at + cursor_id",
+            })),
+        )
+            .into_response();
+    }
+    let limit = query.limit.unwrap_or(100).clamp(1, 500);
+    let include_body = query.include_body.unwrap_or(false);
+    let (cursor_at, cursor_id) =
+        match listing::time_cursor(query.cursor.as_deref(), query.cursor_at, query.cursor_id) {
+            Ok(v) => v,
+            Err(err) => return bad_request(err.code, err.detail).into_response(),
+        };
+    let cursor = match (normalize_opt_str(cursor_at), cursor_id) {
+        (None, None) => None,
+        (Some(_), None) | (None, Some(_)) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({
+                    "error": "invalid_cursor",
+                    "detail": "cursor_at and cursor_id must be provided together",
+                })),
+            )
+                .into_response();
+        }
+        (Some(cursor_at), Some(cursor_id)) => {
+            let cursor_at = match OffsetDateTime::parse(&cursor_at, &Rfc3339) {
+                Ok(v) => v,
+                Err(err) => {
+                    return (
+                        StatusCode::BAD_REQUEST,
+                        Json(serde_json::json!({
+                            "error": "invalid_cursor_at",
+                            "detail": err.to_string(),
+                        })),
+                    )
+                        .into_response();
+                }
+            };
+            Some(gproxy_storage::LogCursor {
+                at: cursor_at,
+                id: cursor_id,
+            })
+        }
+    };
+
+    let org_id = scope.org_id().or(query.org_id);
+    let user_ids = org_id.map(|org_id| {
+        state
+            .app
+            .snapshot
+            .load()
+            .users
+            .iter()
+            .filter(|u| u.org_id == Some(org_id))
+            .map(|u| u.id)
+            .collect::<Vec<_>>()
+    });
+
+    let filter = gproxy_storage::LogQueryFilter {
+        from,
+        to,
+        kind,
+        provider: normalize_opt_str(query.provider),
+        credential_id: query.credential_id,
+        user_id: query.user_id,
+        user_ids,
+        user_key_id: query.user_key_id,
+        trace_id: normalize_opt_str(query.trace_id),
+        operation: normalize_opt_str(query.operation),
+        tag,
+        request_path_contains: normalize_opt_str(query.path_contains),
+        status_min: query.status_min,
+        status_max: query.status_max,
+        country: normalize_opt_str(query.country).map(|code| code.to_ascii_uppercase()),
+        asn: query.asn,
+        limit,
+        cursor,
+        include_body,
+    };
+
+    let result = match state.storage.query_logs(filter).await {
+        Ok(v) => v,
+        Err(err) => return storage_error(err).into_response(),
+    };
+
+    let rows: Vec<_> = result
+        .rows
+        .into_iter()
+        .map(|row| {
+            let kind = match row.kind {
+                gproxy_storage::LogRecordKind::Upstream => "upstream",
+                gproxy_storage::LogRecordKind::Downstream => "downstream",
+            };
+            let show_error_body = matches!(row.kind, gproxy_storage::LogRecordKind::Downstream)
+                && row.response_status.unwrap_or_default() >= 400;
+            let show_body = include_body || show_error_body;
+            let request_body = if show_body {
+                bytes_body_to_json(&row.request_body)
+            } else {
+                JsonValue::Null
+            };
+            let response_body = if show_body {
+                bytes_body_to_json(&row.response_body)
+            } else {
+                JsonValue::Null
+            };
+            serde_json::json!({
+                "id": row.id,
+                "kind": kind,
+                "at": format_time_rfc3339(row.at),
+                "trace_id": row.trace_id,
+                "provider": row.provider,
+                "credential_id": row.credential_id,
+                "user_id": row.user_id,
+                "user_key_id": row.user_key_id,
+                "attempt_no": row.attempt_no,
+                "operation": row.operation,
+                "request_method": row.request_method,
+                "request_host": row.request_host,
+                "request_path": row.request_path,
+                "request_body": request_body,
+                "response_status": row.response_status,
+                "response_body": response_body,
+                "error_kind": row.error_kind,
+                "error_message": row.error_message,
+                "tags": row.tags,
+                "anthropic_betas": row.anthropic_betas,
+                "client_ip": row.client_ip,
+                "country": row.country,
+                "asn": row.asn,
+            })
+        })
+        .collect();
+    let rows = project_rows(rows, query.fields.as_deref());
+
+    let (next_cursor_at, next_cursor_id) = match result.next_cursor {
+        Some(cursor) => (Some(format_time_rfc3339(cursor.at)), Some(cursor.id)),
+        None => (None, None),
+    };
+    let next_cursor = time_cursor_json(next_cursor_at.as_deref(), next_cursor_id);
+
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "from": format_time_rfc3339(from),
+            "to": format_time_rfc3339(to),
+            "kind": match kind {
+                None => "all",
+                Some(gproxy_storage::LogRecordKind::Upstream) => "upstream",
+                Some(gproxy_storage::LogRecordKind::Downstream) => "downstream",
+            },
+            "limit": limit,
+            "include_body": include_body,
+            "has_more": result.has_more,
+            "next_cursor": next_cursor,
+            "next_cursor_at": next_cursor_at,
+            "next_cursor_id": next_cursor_id,
+            "rows": rows,
+        })),
+    )
+        .into_response()
+}
+
+async fn search_logs(
+    State(state): State<AdminState>,
+    Extension(scope): Extension<AdminScope>,
+    Query(query): Query<LogSearchParams>,
+) -> impl IntoResponse {
+    let Some(text) = normalize_opt_str(query.q) else {
+        return bad_request("invalid_query", "`q` is required").into_response();
+    };
+    let kind = match normalize_opt_str(query.kind).as_deref() {
+        None | Some("all") => None,
+        Some("upstream") => Some(gproxy_storage::LogRecordKind::Upstream),
+        Some("downstream") => Some(gproxy_storage::LogRecordKind::Downstream),
+        Some(other) => {
+            return bad_request(
+                "invalid_kind",
+                format!("unsupported kind: {other}; expected one of all/upstream/downstream"),
+            )
+            .into_response();
+        }
+    };
+    let now = OffsetDateTime::now_utc();
+    let from = match normalize_opt_str(query.from) {
+        Some(raw) => match OffsetDateTime::parse(&raw, &Rfc3339) {
+            Ok(v) => v,
+            Err(err) => return bad_request("invalid_from", err.to_string()).into_response(),
+        },
+        None => now - TimeDuration::hours(24),
+    };
+    let to = match normalize_opt_str(query.to) {
+        Some(raw) => match OffsetDateTime::parse(&raw, &Rfc3339) {
+            Ok(v) => v,
+            Err(err) => return bad_request("invalid_to", err.to_string()).into_response(),
+        },
+        None => now,
+    };
+    if to < from {
+        return bad_request("invalid_range", "`to` must be >= `from`").into_response();
+    }
+
+    let org_id = scope.org_id().or(query.org_id);
+    let user_ids = org_id.map(|org_id| {
+        state
+            .app
+            .snapshot
+            .load()
+            .users
+            .iter()
+            .filter(|u| u.org_id == Some(org_id))
+            .map(|u| u.id)
+            .collect::<Vec<_>>()
+    });
+
+    let hits = match state
+        .storage
+        .search_logs(gproxy_storage::LogSearchQuery {
+            text,
+            from,
+            to,
+            kind,
+            user_ids,
+            limit: query.limit.unwrap_or(50).clamp(1, 500),
+        })
+        .await
+    {
+        Ok(v) => v,
+        Err(err) => return storage_error(err).into_response(),
+    };
+    let hits: Vec<_> = hits
+        .into_iter()
+        .map(|hit| {
+            serde_json::json!({
+                "kind": match hit.kind {
+                    gproxy_storage::LogRecordKind::Upstream => "upstream",
+                    gproxy_storage::LogRecordKind::Downstream => "downstream",
+                },
+                "id": hit.id,
+                "at": format_time_rfc3339(hit.at),
+                "trace_id": hit.trace_id,
+                "user_id": hit.user_id,
+                "snippet": hit.snippet,
+            })
+        })
+        .collect();
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "indexing": state.app.global.load().log_search,
+            "hits": hits,
+        })),
+    )
+        .into_response()
+}
+
+#[derive(Debug, Deserialize)]
+struct TraceQuery {
+    #[serde(default)]
+    include_body: Option<bool>,
+}
+
+/// Tag added to replayed requests, next to `replay_of:<log id>`.
+const REPLAY_TAG: &str = "replay";
+/// Tag added to requests sent by `POST /admin/diff`.
+const DIFF_TAG: &str = "diff";
+/// Largest proxy response body `POST /admin/diff` reads back.
+const DIFF_MAX_RESPONSE_BYTES: usize = 16 * 1024 * 1024;
+
+fn proxy_unavailable() -> Response {
+    (
+        StatusCode::NOT_IMPLEMENTED,
+        Json(serde_json::json!({
+            "error": "proxy_unavailable",
+            "detail": "admin router was built without proxy routes",
+        })),
+    )
+        .into_response()
+}
+
+fn unprocessable(error: &str, detail: impl Into<String>) -> Response {
+    (
+        StatusCode::UNPROCESSABLE_ENTITY,
+        Json(serde_json::json!({ "error": error, "detail": detail.into() })),
+    )
+        .into_response()
+}
+
+/// Current secret of a user key, if the key still exists.
+fn user_key_secret(state: &AdminState, user_key_id: Option<i64>) -> Option<String> {
+    let key_id = user_key_id?;
+    state
+        .app
+        .snapshot
+        .load()
+        .user_keys
+        .iter()
+        .find(|key| key.id == key_id)
+        .map(|key| key.api_key.clone())
+}
+
+/// Builds a request for the proxy routes from logged parts. Logged auth material,
+/// hop-by-hop headers and tags are replaced by `api_key` and `tags`.
+fn proxy_request(
+    method: Method,
+    mut uri: axum::http::Uri,
+    logged_headers: &[(String, String)],
+    api_key: &str,
+    tags: Vec<String>,
+    body: Vec<u8>,
+) -> axum::http::Request<axum::body::Body> {
+    crate::proxy::strip_downstream_auth_query(&mut uri);
+
+    let mut headers = HeaderMap::new();
+    for (name, value) in logged_headers {
+        let (Ok(name), Ok(value)) = (
+            header::HeaderName::from_bytes(name.as_bytes()),
+            header::HeaderValue::from_str(value),
+        ) else {
+            continue;
+        };
+        headers.append(name, value);
+    }
+    crate::proxy::strip_downstream_auth_headers(&mut headers);
+    for name in [
+        header::HOST,
+        header::CONTENT_LENGTH,
+        header::TRANSFER_ENCODING,
+        header::CONNECTION,
+    ] {
+        headers.remove(name);
+    }
+    headers.remove(crate::proxy::GPROXY_TAGS_HEADER);
+    let tags = crate::proxy::parse_request_tags(&tags.join(","));
+    if let Ok(value) = header::HeaderValue::from_str(&tags.join(",")) {
+        headers.insert(crate::proxy::GPROXY_TAGS_HEADER, value);
+    }
+    if let Ok(value) = header::HeaderValue::from_str(&format!("Bearer {api_key}")) {
+        headers.insert(header::AUTHORIZATION, value);
+    }
+
+    let mut req = axum::http::Request::new(axum::body::Body::from(body));
+    *req.method_mut() = method;
+    *req.uri_mut() = uri;
+    *req.headers_mut() = headers;
+    req
+}
+
+async fn call_proxy(proxy: Router, req: axum::http::Request<axum::body::Body>) -> Response {
+    match tower::ServiceExt::oneshot(proxy, req).await {
+        Ok(resp) => resp,
+        Err(never) => match never {},
+    }
+}
+
+fn logged_uri(
+    path: &str,
+    query: Option<&str>,
+) -> Result<axum::http::Uri, (StatusCode, Json<serde_json::Value>)> {
+    let uri = match query {
+        Some(query) if !query.is_empty() => format!("{path}?{query}"),
+        _ => path.to_string(),
+    };
+    uri.parse().map_err(|err: axum::http::uri::InvalidUri| {
+        (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(serde_json::json!({ "error": "invalid_uri", "detail": err.to_string() })),
+        )
+    })
+}
+
+/// Sends a logged downstream request through the current routing configuration again.
+///
+/// The request keeps its method, path, query, headers and body; the caller's key is
+/// re-resolved from `user_key_id`, and the original tags gain `replay` and
+/// `replay_of:<id>` so the new trace can be told apart in logs and usage.
+async fn replay_downstream_request(
+    State(state): State<AdminState>,
+    Path(id): Path<i64>,
+) -> Response {
+    let Some(proxy) = state.proxy.clone() else {
+        return proxy_unavailable();
+    };
+    let record = match state.storage.get_downstream_request(id).await {
+        Ok(Some(record)) => record,
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({ "error": "not_found" })),
+            )
+                .into_response();
+        }
+        Err(err) => return storage_error(err).into_response(),
+    };
+
+    let method = match Method::from_bytes(record.request_method.as_bytes()) {
+        Ok(method) => method,
+        Err(err) => return unprocessable("invalid_method", err.to_string()),
+    };
+    if record.request_body.is_none() && method != Method::GET {
+        return unprocessable(
+            "request_body_not_recorded",
+            "the request body was not logged (event_redact_sensitive)",
+        );
+    }
+    let Some(api_key) = user_key_secret(&state, record.user_key_id) else {
+        return unprocessable(
+            "user_key_not_found",
+            "the request has no user key or the key was deleted",
+        );
+    };
+    let uri = match logged_uri(&record.request_path, record.request_query.as_deref()) {
+        Ok(uri) => uri,
+        Err(err) => return err.into_response(),
+    };
+
+    // Replay markers go first so the tag cap never drops them.
+    let mut tags = vec![REPLAY_TAG.to_string(), format!("replay_of:{id}")];
+    tags.extend(record.tags);
+    let req = proxy_request(
+        method,
+        uri,
+        &record.request_headers,
+        &api_key,
+        tags,
+        record.request_body.unwrap_or_default(),
+    );
+    call_proxy(proxy, req).await
+}
+
+#[derive(Debug, Deserialize)]
+struct DiffBody {
+    /// Downstream log row to take the path, headers, body and user key from.
+    log_id: Option<i64>,
+    /// Proxy path; defaults to the logged path, then `/v1/chat/completions`.
+    path: Option<String>,
+    /// Inline JSON body; overrides the logged body.
+    body: Option<JsonValue>,
+    /// Key to send inline requests as; defaults to the logged key.
+    user_key_id: Option<i64>,
+    targets: Vec<DiffTarget>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DiffTarget {
+    provider: String,
+    model: Option<String>,
+}
+
+/// Runs one request against two provider/model targets and returns both responses,
+/// latencies and token usage side by side.
+///
+/// Both calls go through the proxy routes with the `diff` tag, so they are logged and
+/// billed like normal traffic. Streaming requests are sent non-streaming.
+async fn diff_providers(
+    State(state): State<AdminState>,
+    Json(payload): Json<DiffBody>,
+) -> Response {
+    let Some(proxy) = state.proxy.clone() else {
+        return proxy_unavailable();
+    };
+    if payload.targets.len() != 2 {
+        return bad_request("invalid_targets", "exactly two targets are required").into_response();
+    }
+
+    let record = match payload.log_id {
+        Some(id) => match state.storage.get_downstream_request(id).await {
+            Ok(Some(record)) => Some(record),
+            Ok(None) => {
+                return (
+                    StatusCode::NOT_FOUND,
+                    Json(serde_json::json!({ "error": "not_found" })),
+                )
+                    .into_response();
+            }
+            Err(err) => return storage_error(err).into_response(),
+        },
+        None => None,
+    };
+    let body = match (payload.body, record.as_ref()) {
+        (Some(body), _) => body,
+        (None, Some(record)) => match record
+            .request_body
+            .as_deref()
+            .map(serde_json::from_slice::<JsonValue>)
+        {
+            Some(Ok(body)) => body,
+            Some(Err(err)) => return unprocessable("invalid_logged_body", err.to_string()),
+            None => {
+                return unprocessable(
+                    "request_body_not_recorded",
+                    "the request body was not logged (event_redact_sensitive)",
+                );
+            }
+        },
+        (None, None) => {
+            return bad_request("missing_body", "either `log_id` or `body` is required")
+                .into_response();
+        }
+    };
+    let path = normalize_opt_str(payload.path)
+        .or_else(|| record.as_ref().map(|record| record.request_path.clone()))
+        .unwrap_or_else(|| "/v1/chat/completions".to_string());
+    let path = unprefixed_proxy_path(&path);
+    let user_key_id = payload
+        .user_key_id
+        .or_else(|| record.as_ref().and_then(|record| record.user_key_id));
+    let Some(api_key) = user_key_secret(&state, user_key_id) else {
+        return unprocessable(
+            "user_key_not_found",
+            "set `user_key_id`, or use a log row whose key still exists",
+        );
+    };
+    let logged_headers = record
+        .as_ref()
+        .map(|record| record.request_headers.clone())
+        .unwrap_or_default();
+    let mut tags = vec![DIFF_TAG.to_string()];
+    if let Some(id) = payload.log_id {
+        tags.push(format!("diff_of:{id}"));
+    }
+
+    let mut requests = Vec::with_capacity(2);
+    for target in &payload.targets {
+        let provider = target.provider.trim();
+        if provider.is_empty() || provider.contains('/') {
+            return bad_request("invalid_provider", format!("invalid provider: {provider}"))
+                .into_response();
+        }
+        let model = target
+            .model
+            .as_deref()
+            .map(str::trim)
+            .filter(|m| !m.is_empty());
+        let (target_path, target_body) = diff_target_request(&path, &body, model);
+        let uri = match format!("/{provider}{target_path}").parse::<axum::http::Uri>() {
+            Ok(uri) => uri,
+            Err(err) => return bad_request("invalid_path", err.to_string()).into_response(),
+        };
+        let mut req = proxy_request(
+            Method::POST,
+            uri,
+            &logged_headers,
+            &api_key,
+            tags.clone(),
+            target_body,
+        );
+        req.headers_mut().remove(header::ACCEPT_ENCODING);
+        req.headers_mut().insert(
+            header::CONTENT_TYPE,
+            header::HeaderValue::from_static("application/json"),
+        );
+        requests.push((
+            provider.to_string(),
+            model.map(str::to_string),
+            target_path,
+            req,
+        ));
+    }
+
+    let mut runs = requests.into_iter().map(|(provider, model, path, req)| {
+        let proxy = proxy.clone();
+        async move {
+            let started = std::time::Instant::now();
+            let resp = call_proxy(proxy, req).await;
+            let status = resp.status().as_u16();
+            let body = axum::body::to_bytes(resp.into_body(), DIFF_MAX_RESPONSE_BYTES).await;
+            let latency_ms = u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX);
+            let response = match body {
+                Ok(bytes) => serde_json::from_slice::<JsonValue>(&bytes).unwrap_or_else(|_| {
+                    JsonValue::String(String::from_utf8_lossy(&bytes).to_string())
+                }),
+                Err(err) => serde_json::json!({ "error": "read_body", "detail": err.to_string() }),
+            };
+            serde_json::json!({
+                "provider": provider,
+                "model": model,
+                "path": path,
+                "status": status,
+                "latency_ms": latency_ms,
+                "usage": response_usage_json(&response),
+                "response": response,
+            })
+        }
+    });
+    let (Some(first), Some(second)) = (runs.next(), runs.next()) else {
+        return bad_request("invalid_targets", "exactly two targets are required").into_response();
+    };
+    let (first, second) = tokio::join!(first, second);
+
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "log_id": payload.log_id,
+            "path": path,
+            "results": [first, second],
+        })),
+    )
+        .into_response()
+}
+
+/// Drops a `/{provider}` prefix so the path can be re-targeted.
+fn unprefixed_proxy_path(path: &str) -> String {
+    let path = format!("/{}", path.trim_start_matches('/'));
+    if path.starts_with("/v1/") || path.starts_with("/v1beta/") {
+        return path;
+    }
+    match path[1..].find('/') {
+        Some(idx) => path[idx + 1..].to_string(),
+        None => path,
+    }
+}
+
+/// Path and body for one diff target: the model is swapped in (path segment for
+/// Gemini, `model` field otherwise) and streaming is turned off.
+fn diff_target_request(path: &str, body: &JsonValue, model: Option<&str>) -> (String, Vec<u8>) {
+    let mut body = body.clone();
+    let mut path = path.to_string();
+    if let Some((prefix, rest)) = path.split_once("/models/")
+        && let Some((name, action)) = rest.rsplit_once(':')
+    {
+        let action = if action == "streamGenerateContent" {
+            "generateContent"
+        } else {
+            action
+        };
+        path = format!("{prefix}/models/{}:{action}", model.unwrap_or(name));
+    } else if let Some(obj) = body.as_object_mut() {
+        if let Some(model) = model {
+            obj.insert("model".to_string(), JsonValue::String(model.to_string()));
+        }
+        if obj.contains_key("stream") {
+            obj.insert("stream".to_string(), JsonValue::Bool(false));
+        }
+        obj.remove("stream_options");
+    }
+    (path, serde_json::to_vec(&body).unwrap_or_default())
+}
+
+/// Token usage from an OpenAI, Claude or Gemini response body.
+fn response_usage_json(response: &JsonValue) -> JsonValue {
+    if let Some(usage) = response.get("usageMetadata") {
+        return serde_json::json!({
+            "input_tokens": usage.get("promptTokenCount"),
+            "output_tokens": usage.get("candidatesTokenCount"),
+            "cache_read_input_tokens": usage.get("cachedContentTokenCount"),
+        });
+    }
+    let Some(usage) = response.get("usage") else {
+        return JsonValue::Null;
+    };
+    if usage.get("prompt_tokens").is_some() {
+        return serde_json::json!({
+            "input_tokens": usage.get("prompt_tokens"),
+            "output_tokens": usage.get("completion_tokens"),
+            "cache_read_input_tokens": usage
+                .get("prompt_tokens_details")
+                .and_then(|details| details.get("cached_tokens")),
+        });
+    }
+    serde_json::json!({
+        "input_tokens": usage.get("input_tokens"),
+        "output_tokens": usage.get("output_tokens"),
+        "cache_read_input_tokens": usage
+            .get("cache_read_input_tokens")
+            .or_else(|| {
+                usage
+                    .get("input_tokens_details")
+                    .and_then(|details| details.get("cached_tokens"))
+            }),
+        "cache_creation_input_tokens": usage.get("cache_creation_input_tokens"),
+    })
+}
+
+const TRACE_TIMELINE_MAX_ROWS: usize = 1000;
+
+async fn get_trace_timeline(
+    State(state): State<AdminState>,
+    Path(trace_id): Path<String>,
+    Query(query): Query<TraceQuery>,
+) -> impl IntoResponse {
+    let include_body = query.include_body.unwrap_or(false);
+    let filter = gproxy_storage::LogQueryFilter {
+        from: OffsetDateTime::UNIX_EPOCH,
+        to: OffsetDateTime::now_utc() + TimeDuration::days(1),
+        kind: None,
+        provider: None,
+        credential_id: None,
+        user_id: None,
+        user_ids: None,
+        user_key_id: None,
+        trace_id: Some(trace_id.clone()),
+        operation: None,
+        tag: None,
+        request_path_contains: None,
+        status_min: None,
+        status_max: None,
+        country: None,
+        asn: None,
+        limit: TRACE_TIMELINE_MAX_ROWS,
+        cursor: None,
+        include_body,
+    };
+    let logs = match state.storage.query_logs(filter).await {
+        Ok(v) => v,
+        Err(err) => return storage_error(err).into_response(),
+    };
+    let usages = match state.storage.list_trace_usages(&trace_id).await {
+        Ok(v) => v,
+        Err(err) => return storage_error(err).into_response(),
+    };
+    if logs.rows.is_empty() && usages.is_empty() {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "trace_not_found" })),
+        )
+            .into_response();
+    }
+
+    // query_logs returns newest first; the timeline reads oldest first.
+    let mut rows = logs.rows;
+    rows.reverse();
+    let started_at = rows
+        .iter()
+        .map(|r| r.at)
+        .chain(usages.iter().map(|u| u.at))
+        .min()
+        .unwrap_or_else(OffsetDateTime::now_utc);
+    let finished_at = rows
+        .iter()
+        .map(|r| r.at)
+        .chain(usages.iter().map(|u| u.at))
+        .max()
+        .unwrap_or(started_at);
+    let offset_ms = |at: OffsetDateTime| (at - started_at).whole_milliseconds();
+
+    let downstream = rows
+        .iter()
+        .find(|r| r.kind == gproxy_storage::LogRecordKind::Downstream);
+    let mut upstream_operations: Vec<&str> = Vec::new();
+    let mut providers: Vec<&str> = Vec::new();
+    for row in rows
+        .iter()
+        .filter(|r| r.kind == gproxy_storage::LogRecordKind::Upstream)
+    {
+        if let Some(op) = row.operation.as_deref()
+            && !upstream_operations.contains(&op)
+        {
+            upstream_operations.push(op);
+        }
+        if let Some(provider) = row.provider.as_deref()
+            && !providers.contains(&provider)
+        {
+            providers.push(provider);
+        }
+    }
+
+    let mut timeline: Vec<(OffsetDateTime, u8, JsonValue)> = Vec::new();
+    for row in &rows {
+        let (request_body, response_body) = if include_body {
+            (
+                bytes_body_to_json(&row.request_body),
+                bytes_body_to_json(&row.response_body),
+            )
+        } else {
+            (JsonValue::Null, JsonValue::Null)
+        };
+        let entry = match row.kind {
+            gproxy_storage::LogRecordKind::Upstream => serde_json::json!({
+                "kind": "upstream_attempt",
+                "id": row.id,
+                "at": format_time_rfc3339(row.at),
+                "offset_ms": offset_ms(row.at),
+                "provider": row.provider,
+                "credential_id": row.credential_id,
+                "attempt_no": row.attempt_no,
+                "operation": row.operation,
+                "request_method": row.request_method,
+                "request_host": row.request_host,
+                "request_path": row.request_path,
+                "response_status": row.response_status,
+                "error_kind": row.error_kind,
+                "error_message": row.error_message,
+                "anthropic_betas": row.anthropic_betas,
+                "request_body": request_body,
+                "response_body": response_body,
+            }),
+            gproxy_storage::LogRecordKind::Downstream => serde_json::json!({
+                "kind": "downstream_response",
+                "id": row.id,
+                "at": format_time_rfc3339(row.at),
+                "offset_ms": offset_ms(row.at),
+                "user_id": row.user_id,
+                "user_key_id": row.user_key_id,
+                "operation": row.operation,
+                "request_method": row.request_method,
+                "request_path": row.request_path,
+                "response_status": row.response_status,
+                "request_body": request_body,
+                "response_body": response_body,
+            }),
+        };
+        // Downstream is logged when the response completes; keep it after
+        // upstream entries that share a timestamp.
+        let rank = match row.kind {
+            gproxy_storage::LogRecordKind::Upstream => 0,
+            gproxy_storage::LogRecordKind::Downstream => 2,
+        };
+        timeline.push((row.at, rank, entry));
+    }
+    for usage in &usages {
+        timeline.push((
+            usage.at,
+            1,
+            serde_json::json!({
+                "kind": "usage",
+                "upstream_request_id": usage.upstream_request_id,
+                "at": format_time_rfc3339(usage.at),
+                "offset_ms": offset_ms(usage.at),
+                "provider": usage.provider,
+                "credential_id": usage.credential_id,
+                "attempt_no": usage.attempt_no,
+                "model": usage.model,
+                "input_tokens": usage.input_tokens,
+                "output_tokens": usage.output_tokens,
+                "cache_read_input_tokens": usage.cache_read_input_tokens,
+                "cache_creation_input_tokens": usage.cache_creation_input_tokens,
+            }),
+        ));
+    }
+    timeline.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.cmp(&b.1)));
+    let timeline: Vec<JsonValue> = timeline.into_iter().map(|(_, _, v)| v).collect();
+
+    let sum = |f: fn(&gproxy_storage::UsageRecord) -> Option<i64>| -> i64 {
+        usages.iter().filter_map(f).sum()
+    };
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "trace_id": trace_id,
+            "started_at": format_time_rfc3339(started_at),
+            "finished_at": format_time_rfc3339(finished_at),
+            "duration_ms": offset_ms(finished_at),
+            "user_id": downstream.and_then(|r| r.user_id),
+            "user_key_id": downstream.and_then(|r| r.user_key_id),
+            "tags": downstream.map(|r| r.tags.clone()).unwrap_or_default(),
+            "transform": {
+                "downstream_operation": downstream.and_then(|r| r.operation.clone()),
+                "upstream_operations": upstream_operations,
+                "providers": providers,
+            },
+            "attempts": rows
+                .iter()
+                .filter(|r| r.kind == gproxy_storage::LogRecordKind::Upstream)
+                .count(),
+            "usage": {
+                "input_tokens": sum(|u| u.input_tokens),
+                "output_tokens": sum(|u| u.output_tokens),
+                "cache_read_input_tokens": sum(|u| u.cache_read_input_tokens),
+                "cache_creation_input_tokens": sum(|u| u.cache_creation_input_tokens),
+            },
+            "truncated": logs.has_more,
+            "timeline": timeline,
+        })),
+    )
+        .into_response()
+}
+
+async fn list_orgs(State(state): State<AdminState>) -> impl IntoResponse {
+    let snapshot = state.app.snapshot.load();
+    let orgs: Vec<_> = snapshot
+        .organizations
+        .iter()
+        .map(|o| org_json(o, &snapshot))
+        .collect();
+    Json(serde_json::json!({ "orgs": orgs }))
+}
+
+async fn get_org(
+    State(state): State<AdminState>,
+    Extension(scope): Extension<AdminScope>,
+    Path(id): Path<i64>,
+) -> impl IntoResponse {
+    if scope.org_id().is_some_and(|org_id| org_id != id) {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({ "error": "forbidden" })),
+        )
+            .into_response();
+    }
+    let snapshot = state.app.snapshot.load();
+    let Some(org) = snapshot.organizations.iter().find(|o| o.id == id) else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "org_not_found" })),
+        )
+            .into_response();
+    };
+    Json(org_json(org, &snapshot)).into_response()
+}
+
+fn org_json(
+    org: &gproxy_storage::OrganizationRow,
+    snapshot: &gproxy_storage::StorageSnapshot,
+) -> JsonValue {
+    let user_ids: Vec<i64> = snapshot
+        .users
+        .iter()
+        .filter(|u| u.org_id == Some(org.id))
+        .map(|u| u.id)
+        .collect();
+    serde_json::json!({
+        "id": org.id,
+        "name": org.name,
+        "enabled": org.enabled,
+        "has_admin_key": org.admin_key.is_some(),
+        "omit_bodies": org.omit_bodies,
+        "rpm_limit": org.rpm_limit,
+        "tpm_limit": org.tpm_limit,
+        "monthly_token_budget": org.monthly_token_budget,
+        "default_provider": org.default_provider,
+        "default_model": org.default_model,
+        "user_ids": user_ids,
+        "created_at": org.created_at,
+        "updated_at": org.updated_at,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct UpsertOrgBody {
+    pub name: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub admin_key: Option<String>,
+}
+
+async fn upsert_org(
+    State(state): State<AdminState>,
+    Path(id): Path<i64>,
+    Json(body): Json<UpsertOrgBody>,
+) -> impl IntoResponse {
+    let admin_key = normalize_opt_str(body.admin_key);
+    if admin_key.as_deref() == Some(state.app.global.load().admin_key.as_str()) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": "invalid_admin_key",
+                "detail": "org admin_key must differ from the global admin key",
+            })),
+        )
+            .into_response();
+    }
+    if let Err(err) = state
+        .storage
+        .upsert_org_by_id(id, &body.name, body.enabled, admin_key.as_deref())
+        .await
+    {
+        return storage_error(err).into_response();
+    }
+    state
+        .app
+        .apply_org_upsert(id, body.name.clone(), body.enabled, admin_key);
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({ "id": id, "name": body.name })),
+    )
+        .into_response()
+}
+
+async fn delete_org(State(state): State<AdminState>, Path(id): Path<i64>) -> impl IntoResponse {
+    if let Err(err) = state.storage.delete_org(id).await {
+        return storage_error(err).into_response();
+    }
+    state.app.apply_org_delete(id);
+    (StatusCode::OK, Json(serde_json::json!({ "ok": true }))).into_response()
+}
+
+async fn set_org_enabled(
+    State(state): State<AdminState>,
+    Path(id): Path<i64>,
+    Json(body): Json<SetEnabledBody>,
+) -> impl IntoResponse {
+    if let Err(err) = state.storage.set_org_enabled(id, body.enabled).await {
+        return storage_error(err).into_response();
+    }
+    state.app.apply_org_enabled(id, body.enabled);
+    (StatusCode::OK, Json(serde_json::json!({ "ok": true }))).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+struct SetOmitBodiesBody {
+    pub omit_bodies: bool,
+}
+
+async fn set_org_omit_bodies(
+    State(state): State<AdminState>,
+    Path(id): Path<i64>,
+    Json(body): Json<SetOmitBodiesBody>,
+) -> impl IntoResponse {
+    if let Err(err) = state
+        .storage
+        .set_org_omit_bodies(id, body.omit_bodies)
+        .await
+    {
+        return storage_error(err).into_response();
+    }
+    state.app.apply_org_omit_bodies(id, body.omit_bodies);
+    (StatusCode::OK, Json(serde_json::json!({ "ok": true }))).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+struct SetOrgLimitsBody {
+    #[serde(default)]
+    pub rpm_limit: Option<i64>,
+    #[serde(default)]
+    pub tpm_limit: Option<i64>,
+    #[serde(default)]
+    pub monthly_token_budget: Option<i64>,
+}
+
+async fn set_org_limits(
+    State(state): State<AdminState>,
+    Path(id): Path<i64>,
+    Json(body): Json<SetOrgLimitsBody>,
+) -> impl IntoResponse {
+    if [body.rpm_limit, body.tpm_limit, body.monthly_token_budget]
+        .iter()
+        .any(|v| v.is_some_and(|v| v <= 0))
+    {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "limits must be positive" })),
+        )
+            .into_response();
+    }
+    if let Err(err) = state
+        .storage
+        .update_org_limits(
+            id,
+            body.rpm_limit,
+            body.tpm_limit,
+            body.monthly_token_budget,
+        )
+        .await
+    {
+        return storage_error(err).into_response();
+    }
+    state.app.apply_org_limits(
+        id,
+        body.rpm_limit,
+        body.tpm_limit,
+        body.monthly_token_budget,
+    );
+    (StatusCode::OK, Json(serde_json::json!({ "ok": true }))).into_response()
+}
+
+async fn set_org_defaults(
+    State(state): State<AdminState>,
+    Path(id): Path<i64>,
+    Json(body): Json<SetDefaultsBody>,
+) -> impl IntoResponse {
+    let default_provider = normalize_opt_str(body.default_provider);
+    let default_model = normalize_opt_str(body.default_model);
+    if let Err(resp) = check_defaults(
+        &state,
+        default_provider.as_deref(),
+        default_model.as_deref(),
+    ) {
+        return resp.into_response();
+    }
+    if let Err(err) = state
+        .storage
+        .update_org_defaults(id, default_provider.as_deref(), default_model.as_deref())
+        .await
+    {
+        return storage_error(err).into_response();
+    }
+    state
+        .app
+        .apply_org_defaults(id, default_provider, default_model);
+    (StatusCode::OK, Json(serde_json::json!({ "ok": true }))).into_response()
+}
+
+async fn list_org_grants(
+    State(state): State<AdminState>,
+    Extension(scope): Extension<AdminScope>,
+    Path(org_id): Path<i64>,
+) -> impl IntoResponse {
+    if scope.org_id().is_some_and(|id| id != org_id) {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({ "error": "forbidden" })),
+        )
+            .into_response();
+    }
+    let snapshot = state.app.snapshot.load();
+    let grants: Vec<_> = snapshot
+        .org_grants
+        .iter()
+        .filter(|g| g.org_id == org_id)
+        .map(|g| {
+            let provider = snapshot
+                .providers
+                .iter()
+                .find(|p| p.id == g.provider_id)
+                .map(|p| p.name.clone());
+            serde_json::json!({
+                "id": g.id,
+                "org_id": g.org_id,
+                "provider": provider,
+                "credential_id": g.credential_id,
+                "created_at": g.created_at,
+            })
+        })
+        .collect();
+    Json(serde_json::json!({ "grants": grants })).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+struct InsertOrgGrantBody {
+    pub provider: String,
+    #[serde(default)]
+    pub credential_id: Option<i64>,
+}
+
+async fn insert_org_grant(
+    State(state): State<AdminState>,
+    Path(org_id): Path<i64>,
+    Json(body): Json<InsertOrgGrantBody>,
+) -> impl IntoResponse {
+    let snapshot = state.app.snapshot.load();
+    if !snapshot.organizations.iter().any(|o| o.id == org_id) {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "org_not_found" })),
+        )
+            .into_response();
+    }
+    let Some(provider_id) = snapshot
+        .providers
+        .iter()
+        .find(|p| p.name == body.provider)
+        .map(|p| p.id)
+    else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "provider_not_found" })),
+        )
+            .into_response();
+    };
+    if let Some(credential_id) = body.credential_id
+        && !snapshot
+            .credentials
+            .iter()
+            .any(|c| c.id == credential_id && c.provider_id == provider_id)
+    {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": "credential_provider_mismatch",
+                "detail": "credential does not belong to provider",
+            })),
+        )
+            .into_response();
+    }
+    drop(snapshot);
+
+    let id = match state
+        .storage
+        .insert_org_grant(org_id, &body.provider, body.credential_id)
+        .await
+    {
+        Ok(id) => id,
+        Err(err) => return storage_error(err).into_response(),
+    };
+    state
+        .app
+        .apply_org_grant_insert(id, org_id, provider_id, body.credential_id);
+    (StatusCode::OK, Json(serde_json::json!({ "id": id }))).into_response()
+}
+
+async fn delete_org_grant(
+    State(state): State<AdminState>,
+    Path(id): Path<i64>,
+) -> impl IntoResponse {
+    if let Err(err) = state.storage.delete_org_grant(id).await {
+        return storage_error(err).into_response();
+    }
+    state.app.apply_org_grant_delete(id);
+    (StatusCode::OK, Json(serde_json::json!({ "ok": true }))).into_response()
+}
+
+async fn list_users(
+    State(state): State<AdminState>,
+    Extension(scope): Extension<AdminScope>,
+    Query(query): Query<ListQuery>,
+) -> impl IntoResponse {
+    let snapshot = state.app.snapshot.load();
+    let users: Vec<_> = snapshot
+        .users
+        .iter()
+        .filter(|u| scope.org_id().is_none_or(|org_id| u.org_id == Some(org_id)))
+        .map(|u| {
+            serde_json::json!({
+                "id": u.id,
+                "name": u.name,
+                "enabled": u.enabled,
+                "org_id": u.org_id,
+                "created_at": u.created_at,
+                "updated_at": u.updated_at,
+            })
+        })
+        .collect();
+    list_page(
+        users,
+        &query,
+        &["name", "enabled", "org_id", "created_at", "updated_at"],
+        "users",
+    )
+}
+
+#[derive(Debug, Deserialize)]
+struct UpsertUserBody {
+    pub name: String,
+    pub enabled: bool,
+}
+
+async fn upsert_user(
+    State(state): State<AdminState>,
+    Path(id): Path<i64>,
+    Json(body): Json<UpsertUserBody>,
+) -> impl IntoResponse {
+    if let Err(err) = state
+        .storage
+        .upsert_user_by_id(id, &body.name, body.enabled)
+        .await
+    {
+        return storage_error(err).into_response();
+    }
+    state
+        .app
+        .apply_user_upsert(id, body.name.clone(), body.enabled);
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({ "id": id, "name": body.name })),
+    )
+        .into_response()
+}
+
+async fn delete_user(State(state): State<AdminState>, Path(id): Path<i64>) -> impl IntoResponse {
+    if let Err(err) = state.storage.delete_user(id).await {
+        return storage_error(err).into_response();
+    }
+    state.app.apply_user_delete(id);
+    (StatusCode::OK, Json(serde_json::json!({ "ok": true }))).into_response()
+}
+
+async fn set_user_enabled(
+    State(state): State<AdminState>,
+    Path(id): Path<i64>,
+    Json(body): Json<SetEnabledBody>,
+) -> impl IntoResponse {
+    if let Err(err) = state.storage.set_user_enabled(id, body.enabled).await {
+        return storage_error(err).into_response();
+    }
+    state.app.apply_user_enabled(id, body.enabled);
+    (StatusCode::OK, Json(serde_json::json!({ "ok": true }))).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+struct SetUserOrgBody {
+    #[serde(default)]
+    pub org_id: Option<i64>,
+}
+
+async fn set_user_org(
+    State(state): State<AdminState>,
+    Path(id): Path<i64>,
+    Json(body): Json<SetUserOrgBody>,
+) -> impl IntoResponse {
+    if let Some(org_id) = body.org_id
+        && !state
+            .app
+            .snapshot
+            .load()
+            .organizations
+            .iter()
+            .any(|o| o.id == org_id)
+    {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "org_not_found" })),
+        )
+            .into_response();
+    }
+    if let Err(err) = state.storage.set_user_org(id, body.org_id).await {
+        return storage_error(err).into_response();
+    }
+    state.app.apply_user_org(id, body.org_id);
+    (StatusCode::OK, Json(serde_json::json!({ "ok": true }))).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+struct InsertUserKeyBody {
+    #[serde(default)]
+    pub key: Option<String>,
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+async fn insert_user_key(
+    State(state): State<AdminState>,
+    Path(user_id): Path<i64>,
+    Json(body): Json<InsertUserKeyBody>,
+) -> impl IntoResponse {
+    let key_plain = body.key.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    let id = match state
+        .storage
+        .insert_user_key(user_id, &key_plain, body.label.as_deref(), body.enabled)
+        .await
+    {
+        Ok(id) => id,
+        Err(err) => return storage_error(err).into_response(),
+    };
+
+    state
+        .app
+        .apply_user_key_insert(id, user_id, key_plain.clone(), body.label, body.enabled);
+
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({ "id": id, "key": key_plain })),
+    )
+        .into_response()
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct InsertTemporaryUserKeyBody {
+    pub ttl_secs: i64,
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub providers: Vec<String>,
+    #[serde(default)]
+    pub models: Vec<String>,
+}
+
+/// Mints a key that expires after `ttl_secs`, optionally limited to some providers and
+/// models. The key is only ever returned here.
+async fn insert_temporary_user_key(
+    State(state): State<AdminState>,
+    Path(user_id): Path<i64>,
+    Json(body): Json<InsertTemporaryUserKeyBody>,
+) -> impl IntoResponse {
+    if !(MIN_TTL_SECS..=MAX_TTL_SECS).contains(&body.ttl_secs) {
+        return bad_request(
+            "invalid_ttl_secs",
+            format!("must be between {MIN_TTL_SECS} and {MAX_TTL_SECS}"),
+        )
+        .into_response();
+    }
+    let snapshot = state.app.snapshot.load();
+    if !snapshot.users.iter().any(|u| u.id == user_id) {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "user_not_found" })),
+        )
+            .into_response();
+    }
+    if let Some(unknown) = body
+        .providers
+        .iter()
+        .find(|name| !snapshot.providers.iter().any(|p| p.name == **name))
+    {
+        return bad_request("invalid_key_scope", format!("unknown provider `{unknown}`"))
+            .into_response();
+    }
+    let key_scope = (!body.providers.is_empty() || !body.models.is_empty())
+        .then(|| serde_json::json!({ "providers": body.providers, "models": body.models }));
+    if let Some(scope) = &key_scope
+        && let Err(err) = KeyScope::from_json(scope)
+    {
+        return bad_request("invalid_key_scope", err).into_response();
+    }
+    let expires_at = OffsetDateTime::now_utc() + TimeDuration::seconds(body.ttl_secs);
+
+    let key_plain = uuid::Uuid::new_v4().to_string();
+    let id = match state
+        .storage
+        .insert_user_key(user_id, &key_plain, body.label.as_deref(), true)
+        .await
+    {
+        Ok(id) => id,
+        Err(err) => return storage_error(err).into_response(),
+    };
+    state
+        .app
+        .apply_user_key_insert(id, user_id, key_plain.clone(), body.label, true);
+    if let Err(err) = state
+        .storage
+        .update_user_key_scope(id, key_scope.as_ref(), Some(expires_at))
+        .await
+    {
+        // Don't leave a key behind that never expires.
+        let _ = state.storage.delete_user_key(id).await;
+        state.app.apply_user_key_delete(id);
+        return storage_error(err).into_response();
+    }
+    state
+        .app
+        .apply_user_key_scope(id, key_scope.clone(), Some(expires_at));
+
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "id": id,
+            "key": key_plain,
+            "expires_at": format_time_rfc3339(expires_at),
+            "key_scope": key_scope,
+        })),
+    )
+        .into_response()
+}
+
+async fn list_user_keys(
+    State(state): State<AdminState>,
+    Extension(scope): Extension<AdminScope>,
+    Path(user_id): Path<i64>,
+    Query(query): Query<ListQuery>,
+) -> impl IntoResponse {
+    let snapshot = state.app.snapshot.load();
+    if let Some(org_id) = scope.org_id()
+        && !snapshot
+            .users
+            .iter()
+            .any(|u| u.id == user_id && u.org_id == Some(org_id))
+    {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({ "error": "forbidden" })),
+        )
+            .into_response();
+    }
+    let keys: Vec<_> = snapshot
+        .user_keys
+        .iter()
+        .filter(|k| k.user_id == user_id)
+        .map(|k| {
+            serde_json::json!({
+                "id": k.id,
+                "user_id": k.user_id,
+                "label": k.label,
+                "enabled": k.enabled,
+                "rpm_limit": k.rpm_limit,
+                "tpm_limit": k.tpm_limit,
+                "stream_tps_limit": k.stream_tps_limit,
+                "max_output_tokens": k.max_output_tokens,
+                "omit_bodies": k.omit_bodies,
+                "default_provider": k.default_provider,
+                "default_model": k.default_model,
+                "mcp_policy": k.mcp_policy,
+                "moderation_policy": k.moderation_policy,
+                "prelude_template": k.prelude_template,
+                "key_scope": k.key_scope,
+                "expires_at": k.expires_at,
+                "allowed_origins": k.allowed_origins,
+                "body_sample_percent": k.body_sample_percent,
+                "created_at": k.created_at,
+                "updated_at": k.updated_at,
+            })
+        })
+        .collect();
+    list_page(
+        keys,
+        &query,
+        &["label", "enabled", "created_at", "updated_at"],
+        "keys",
+    )
+}
+
+async fn set_user_key_enabled(
+    State(state): State<AdminState>,
+    Path(id): Path<i64>,
+    Json(body): Json<SetEnabledBody>,
+) -> impl IntoResponse {
+    if let Err(err) = state.storage.set_user_key_enabled(id, body.enabled).await {
+        return storage_error(err).into_response();
+    }
+    state.app.apply_user_key_enabled(id, body.enabled);
+    (StatusCode::OK, Json(serde_json::json!({ "ok": true }))).into_response()
+}
+
+async fn set_user_key_omit_bodies(
+    State(state): State<AdminState>,
+    Path(id): Path<i64>,
+    Json(body): Json<SetOmitBodiesBody>,
+) -> impl IntoResponse {
+    if let Err(err) = state
+        .storage
+        .set_user_key_omit_bodies(id, body.omit_bodies)
+        .await
+    {
+        return storage_error(err).into_response();
+    }
+    state.app.apply_user_key_omit_bodies(id, body.omit_bodies);
+    (StatusCode::OK, Json(serde_json::json!({ "ok": true }))).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+struct SetBodySamplePercentBody {
+    /// 0..=100; `null` follows the provider's `body_sampling`.
+    #[serde(default)]
+    pub body_sample_percent: Option<i64>,
+}
+
+async fn set_user_key_body_sample_percent(
+    State(state): State<AdminState>,
+    Path(id): Path<i64>,
+    Json(body): Json<SetBodySamplePercentBody>,
+) -> impl IntoResponse {
+    if body
+        .body_sample_percent
+        .is_some_and(|percent| !(0..=100).contains(&percent))
+    {
+        return bad_request(
+            "invalid_body_sample_percent",
+            "body_sample_percent must be between 0 and 100",
+        )
+        .into_response();
+    }
+    if let Err(err) = state
+        .storage
+        .set_user_key_body_sample_percent(id, body.body_sample_percent)
+        .await
+    {
+        return storage_error(err).into_response();
+    }
+    state
+        .app
+        .apply_user_key_body_sample_percent(id, body.body_sample_percent);
+    (StatusCode::OK, Json(serde_json::json!({ "ok": true }))).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+struct ArmDebugCaptureBody {
+    /// Requests to capture, 1..=100.
+    requests: u32,
+    /// How long captured rows are kept; default one day, at most seven.
+    #[serde(default)]
+    retention_secs: Option<u64>,
+}
+
+async fn arm_debug_capture(
+    State(state): State<AdminState>,
+    Path(id): Path<i64>,
+    Json(body): Json<ArmDebugCaptureBody>,
+) -> impl IntoResponse {
+    let snapshot = state.app.snapshot.load();
+    if !snapshot.user_keys.iter().any(|k| k.id == id) {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "user_key_not_found" })),
+        )
+            .into_response();
+    }
+    if BodyRetention::new(state.app.snapshot.clone()).omits(id) {
+        return (
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({
+                "error": "omit_bodies",
+                "detail": "bodies of this key are never kept; clear omit_bodies on the key or its organization first",
+            })),
+        )
+            .into_response();
+    }
+    if body.requests == 0 {
+        return bad_request("invalid_requests", "requests must be at least 1").into_response();
+    }
+    let retention = body
+        .retention_secs
+        .map_or(DEFAULT_CAPTURE_RETENTION, Duration::from_secs);
+    let arm = state.app.debug_captures.arm(id, body.requests, retention);
+    Json(serde_json::json!({ "ok": true, "capture": arm })).into_response()
+}
+
+async fn disarm_debug_capture(
+    State(state): State<AdminState>,
+    Path(id): Path<i64>,
+) -> impl IntoResponse {
+    let disarmed = state.app.debug_captures.disarm(id);
+    Json(serde_json::json!({ "ok": true, "disarmed": disarmed }))
+}
+
+async fn list_armed_debug_captures(State(state): State<AdminState>) -> impl IntoResponse {
+    Json(serde_json::json!({ "armed": state.app.debug_captures.arms() }))
+}
+
+#[derive(Debug, Deserialize)]
+struct DebugCapturesQuery {
+    #[serde(default)]
+    user_key_id: Option<i64>,
+    #[serde(default)]
+    trace_id: Option<String>,
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+async fn list_debug_captures(
+    State(state): State<AdminState>,
+    Query(query): Query<DebugCapturesQuery>,
+) -> impl IntoResponse {
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+    let rows = match state
+        .storage
+        .list_debug_captures(query.user_key_id, query.trace_id.as_deref(), limit)
+        .await
+    {
+        Ok(v) => v,
+        Err(err) => return storage_error(err).into_response(),
+    };
+    let captures: Vec<_> = rows
+        .into_iter()
+        .map(|row| {
+            serde_json::json!({
+                "id": row.id,
+                "trace_id": row.trace_id,
+                "user_key_id": row.user_key_id,
+                "kind": row.kind,
+                "at": row.at,
+                "expires_at": row.expires_at,
+                "event": row.event_json,
+            })
+        })
+        .collect();
+    Json(serde_json::json!({ "captures": captures })).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+struct UpdateUserKeyBody {
+    pub label: Option<String>,
+}
+
+async fn update_user_key(
+    State(state): State<AdminState>,
+    Path(id): Path<i64>,
+    Json(body): Json<UpdateUserKeyBody>,
+) -> impl IntoResponse {
+    if let Err(err) = state
+        .storage
+        .update_user_key_label(id, body.label.as_deref())
+        .await
+    {
+        return storage_error(err).into_response();
+    }
+    state.app.apply_user_key_label(id, body.label);
+    (StatusCode::OK, Json(serde_json::json!({ "ok": true }))).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+struct SetUserKeyLimitsBody {
+    #[serde(default)]
+    pub rpm_limit: Option<i64>,
+    #[serde(default)]
+    pub tpm_limit: Option<i64>,
+    #[serde(default)]
+    pub stream_tps_limit: Option<i64>,
+    #[serde(default)]
+    pub max_output_tokens: Option<i64>,
+}
+
+async fn set_user_key_limits(
+    State(state): State<AdminState>,
+    Path(id): Path<i64>,
+    Json(body): Json<SetUserKeyLimitsBody>,
+) -> impl IntoResponse {
+    if [
+        body.rpm_limit,
+        body.tpm_limit,
+        body.stream_tps_limit,
+        body.max_output_tokens,
+    ]
+    .iter()
+    .any(|v| v.is_some_and(|v| v <= 0))
+    {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "limits must be positive" })),
+        )
+            .into_response();
+    }
+    if let Err(err) = state
+        .storage
+        .update_user_key_limits(
+            id,
+            body.rpm_limit,
+            body.tpm_limit,
+            body.stream_tps_limit,
+            body.max_output_tokens,
+        )
+        .await
+    {
+        return storage_error(err).into_response();
+    }
+    state.app.apply_user_key_limits(
+        id,
+        body.rpm_limit,
+        body.tpm_limit,
+        body.stream_tps_limit,
+        body.max_output_tokens,
+    );
+    (StatusCode::OK, Json(serde_json::json!({ "ok": true }))).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+struct SetDefaultsBody {
+    #[serde(default)]
+    pub default_provider: Option<String>,
+    #[serde(default)]
+    pub default_model: Option<String>,
+}
+
+/// A default model needs a default provider, and the provider must exist.
+fn check_defaults(
+    state: &AdminState,
+    default_provider: Option<&str>,
+    default_model: Option<&str>,
+) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    match default_provider {
+        None if default_model.is_some() => Err(bad_request(
+            "invalid_defaults",
+            "`default_model` requires `default_provider`",
+        )),
+        Some(name)
+            if !state
+                .app
+                .snapshot
+                .load()
+                .providers
+                .iter()
+                .any(|p| p.name == name) =>
+        {
+            Err(bad_request(
+                "unknown_provider",
+                format!("unknown provider: {name}"),
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
+async fn set_user_key_defaults(
+    State(state): State<AdminState>,
+    Path(id): Path<i64>,
+    Json(body): Json<SetDefaultsBody>,
+) -> impl IntoResponse {
+    let default_provider = normalize_opt_str(body.default_provider);
+    let default_model = normalize_opt_str(body.default_model);
+    if let Err(resp) = check_defaults(
+        &state,
+        default_provider.as_deref(),
+        default_model.as_deref(),
+    ) {
+        return resp.into_response();
+    }
+    if let Err(err) = state
+        .storage
+        .update_user_key_defaults(id, default_provider.as_deref(), default_model.as_deref())
+        .await
+    {
+        return storage_error(err).into_response();
+    }
+    state
+        .app
+        .apply_user_key_defaults(id, default_provider, default_model);
+    (StatusCode::OK, Json(serde_json::json!({ "ok": true }))).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+struct SetUserKeyMcpPolicyBody {
+    /// `null` lets the key declare any MCP server.
+    #[serde(default)]
+    pub mcp_policy: Option<JsonValue>,
+}
+
+async fn set_user_key_mcp_policy(
+    State(state): State<AdminState>,
+    Path(id): Path<i64>,
+    Json(body): Json<SetUserKeyMcpPolicyBody>,
+) -> impl IntoResponse {
+    let mcp_policy = match body.mcp_policy.filter(|v| !v.is_null()) {
+        Some(value) => match McpPolicy::from_json(&value) {
+            Ok(policy) => Some(serde_json::to_value(policy).unwrap_or(value)),
+            Err(err) => return bad_request("invalid_mcp_policy", err).into_response(),
+        },
+        None => None,
+    };
+    if let Err(err) = state
+        .storage
+        .update_user_key_mcp_policy(id, mcp_policy.as_ref())
+        .await
+    {
+        return storage_error(err).into_response();
+    }
+    state.app.apply_user_key_mcp_policy(id, mcp_policy);
+    (StatusCode::OK, Json(serde_json::json!({ "ok": true }))).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+struct SetUserKeyModerationPolicyBody {
+    /// `null` turns moderation off for the key.
+    #[serde(default)]
+    pub moderation_policy: Option<JsonValue>,
+}
+
+async fn set_user_key_moderation_policy(
+    State(state): State<AdminState>,
+    Path(id): Path<i64>,
+    Json(body): Json<SetUserKeyModerationPolicyBody>,
+) -> impl IntoResponse {
+    let moderation_policy = match body.moderation_policy.filter(|v| !v.is_null()) {
+        Some(value) => match ModerationPolicy::from_json(&value) {
+            Ok(policy) => Some(serde_json::to_value(policy).unwrap_or(value)),
+            Err(err) => return bad_request("invalid_moderation_policy", err).into_response(),
+        },
+        None => None,
+    };
+    if let Err(err) = state
+        .storage
+        .update_user_key_moderation_policy(id, moderation_policy.as_ref())
+        .await
+    {
+        return storage_error(err).into_response();
+    }
+    state
+        .app
+        .apply_user_key_moderation_policy(id, moderation_policy);
+    (StatusCode::OK, Json(serde_json::json!({ "ok": true }))).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+struct SetUserKeyPreludeTemplateBody {
+    /// `null` or empty goes back to the provider's prelude.
+    #[serde(default)]
+    pub prelude_template: Option<String>,
+}
+
+async fn set_user_key_prelude_template(
+    State(state): State<AdminState>,
+    Path(id): Path<i64>,
+    Json(body): Json<SetUserKeyPreludeTemplateBody>,
+) -> impl IntoResponse {
+    let prelude_template = body.prelude_template.filter(|t| !t.trim().is_empty());
+    if let Err(err) = state
+        .storage
+        .update_user_key_prelude_template(id, prelude_template.as_deref())
+        .await
+    {
+        return storage_error(err).into_response();
+    }
+    state
+        .app
+        .apply_user_key_prelude_template(id, prelude_template);
+    (StatusCode::OK, Json(serde_json::json!({ "ok": true }))).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+struct SetUserKeyAllowedOriginsBody {
+    /// `null` or an empty list allows any origin.
+    #[serde(default)]
+    pub allowed_origins: Option<JsonValue>,
+}
+
+async fn set_user_key_allowed_origins(
+    State(state): State<AdminState>,
+    Path(id): Path<i64>,
+    Json(body): Json<SetUserKeyAllowedOriginsBody>,
+) -> impl IntoResponse {
+    let allowed_origins = match body.allowed_origins.filter(|v| !v.is_null()) {
+        Some(value) => match AllowedOrigins::from_json(&value) {
+            Ok(allowed) if allowed.origins().is_empty() => None,
+            Ok(allowed) => Some(serde_json::json!(allowed.origins())),
+            Err(err) => return bad_request("invalid_allowed_origins", err).into_response(),
+        },
+        None => None,
+    };
+    if let Err(err) = state
+        .storage
+        .update_user_key_allowed_origins(id, allowed_origins.as_ref())
+        .await
+    {
+        return storage_error(err).into_response();
+    }
+    state
+        .app
+        .apply_user_key_allowed_origins(id, allowed_origins);
+    (StatusCode::OK, Json(serde_json::json!({ "ok": true }))).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+struct McpToolCallsQuery {
+    #[serde(default)]
+    user_key_id: Option<i64>,
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+async fn list_mcp_tool_calls(
+    State(state): State<AdminState>,
+    Query(query): Query<McpToolCallsQuery>,
+) -> impl IntoResponse {
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+    let rows = match state
+        .storage
+        .list_mcp_tool_calls(query.user_key_id, limit)
+        .await
+    {
+        Ok(v) => v,
+        Err(err) => return storage_error(err).into_response(),
+    };
+    let calls: Vec<_> = rows
+        .into_iter()
+        .map(|row| {
+            serde_json::json!({
+                "id": row.id,
+                "trace_id": row.trace_id,
+                "user_key_id": row.user_key_id,
+                "provider": row.provider,
+                "server_label": row.server_label,
+                "tool_name": row.tool_name,
+                "arguments": row.arguments,
+                "output": row.output,
+                "error": row.error,
+                "at": row.at,
+            })
+        })
+        .collect();
+    Json(serde_json::json!({ "calls": calls })).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+struct ToolCallsQuery {
+    #[serde(default)]
+    user_key_id: Option<i64>,
+    #[serde(default)]
+    tool_name: Option<String>,
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+async fn list_tool_calls(
+    State(state): State<AdminState>,
+    Query(query): Query<ToolCallsQuery>,
+) -> impl IntoResponse {
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+    let rows = match state
+        .storage
+        .list_tool_calls(query.user_key_id, query.tool_name.as_deref(), limit)
+        .await
+    {
+        Ok(v) => v,
+        Err(err) => return storage_error(err).into_response(),
+    };
+    let calls: Vec<_> = rows
+        .into_iter()
+        .map(|row| {
+            serde_json::json!({
+                "id": row.id,
+                "trace_id": row.trace_id,
+                "user_key_id": row.user_key_id,
+                "provider": row.provider,
+                "model": row.model,
+                "proto": row.proto,
+                "tool_name": row.tool_name,
+                "call_id": row.call_id,
+                "arguments_bytes": row.arguments_bytes,
+                "at": row.at,
+                "result_at": row.result_at,
+                "latency_ms": row.latency_ms,
+            })
+        })
+        .collect();
+    Json(serde_json::json!({ "calls": calls })).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+struct AbortedStreamsQuery {
+    #[serde(default)]
+    user_key_id: Option<i64>,
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+async fn list_aborted_streams(
+    State(state): State<AdminState>,
+    Query(query): Query<AbortedStreamsQuery>,
+) -> impl IntoResponse {
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+    let rows = match state
+        .storage
+        .list_aborted_streams(query.user_key_id, limit)
+        .await
+    {
+        Ok(v) => v,
+        Err(err) => return storage_error(err).into_response(),
+    };
+    let streams: Vec<_> = rows
+        .into_iter()
+        .map(|row| {
+            serde_json::json!({
+                "id": row.id,
+                "trace_id": row.trace_id,
+                "user_key_id": row.user_key_id,
+                "provider": row.provider,
+                "credential_id": row.credential_id,
+                "model": row.model,
+                "reason": row.reason,
+                "output_chars": row.output_chars,
+                "input_tokens": row.input_tokens,
+                "output_tokens": row.output_tokens,
+                "duration_ms": row.duration_ms,
+                "at": row.at,
+            })
+        })
+        .collect();
+    Json(serde_json::json!({ "streams": streams })).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+struct IncidentsQuery {
+    #[serde(default)]
+    provider: Option<String>,
+    /// Only incidents that have not been resolved yet.
+    #[serde(default)]
+    open: bool,
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+/// Stored incidents newest first. Open ones are stored within seconds of opening and
+/// carry the counts of the last store.
+async fn list_incidents(
+    State(state): State<AdminState>,
+    Query(query): Query<IncidentsQuery>,
+) -> impl IntoResponse {
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+    let rows = match state
+        .storage
+        .list_incidents(query.provider.as_deref(), query.open, limit)
+        .await
+    {
+        Ok(v) => v,
+        Err(err) => return storage_error(err).into_response(),
+    };
+    let incidents: Vec<_> = rows.iter().map(incident_json).collect();
+    Json(serde_json::json!({ "incidents": incidents })).into_response()
+}
+
+/// Secret names only; values are write-only through the admin API.
+async fn list_secrets(State(state): State<AdminState>) -> impl IntoResponse {
+    let snapshot = state.app.snapshot.load();
+    let secrets: Vec<_> = snapshot
+        .secrets
+        .iter()
+        .map(|s| {
+            serde_json::json!({
+                "id": s.id,
+                "name": s.name,
+                "created_at": s.created_at,
+                "updated_at": s.updated_at,
+            })
+        })
+        .collect();
+    Json(serde_json::json!({ "secrets": secrets }))
+}
+
+#[derive(Debug, Deserialize)]
+struct UpsertSecretBody {
+    pub value: String,
+}
+
+async fn upsert_secret(
+    State(state): State<AdminState>,
+    Path(name): Path<String>,
+    Json(body): Json<UpsertSecretBody>,
+) -> impl IntoResponse {
+    let name = name.trim().to_string();
+    if name.is_empty() || name.contains('/') {
+        return bad_request("invalid_name", "secret name must be non-empty without `/`")
+            .into_response();
+    }
+    if body.value.is_empty() {
+        return bad_request("invalid_value", "secret value must be non-empty").into_response();
+    }
+    let id = match state.storage.upsert_secret(&name, &body.value).await {
+        Ok(id) => id,
+        Err(err) => return storage_error(err).into_response(),
+    };
+    state.app.apply_secret_upsert(id, name.clone(), body.value);
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({ "id": id, "name": name })),
+    )
+        .into_response()
+}
+
+async fn delete_secret(
+    State(state): State<AdminState>,
+    Path(name): Path<String>,
+) -> impl IntoResponse {
+    if let Err(err) = state.storage.delete_secret(&name).await {
+        return storage_error(err).into_response();
+    }
+    state.app.apply_secret_delete(&name);
+    (StatusCode::OK, Json(serde_json::json!({ "ok": true }))).into_response()
+}
+
+async fn delete_user_key(
+    State(state): State<AdminState>,
+    Path(id): Path<i64>,
+) -> impl IntoResponse {
+    if let Err(err) = state.storage.delete_user_key(id).await {
+        return storage_error(err).into_response();
+    }
+    state.app.apply_user_key_delete(id);
+    (StatusCode::OK, Json(serde_json::json!({ "ok": true }))).into_response()
+}
+
+async fn list_model_profiles(State(state): State<AdminState>) -> impl IntoResponse {
+    let snapshot = state.app.snapshot.load();
+    let profiles: Vec<_> = snapshot
+        .model_profiles
+        .iter()
+        .map(|p| {
+            serde_json::json!({
+                "id": p.id,
+                "name": p.name,
+                "provider": p.provider,
+                "model": p.model,
+                "settings_json": p.settings_json,
+                "enabled": p.enabled,
+                "created_at": p.created_at,
+                "updated_at": p.updated_at,
+            })
+        })
+        .collect();
+    Json(serde_json::json!({ "model_profiles": profiles }))
+}
+
+#[derive(Debug, Deserialize)]
+struct UpsertModelProfileBody {
+    pub provider: String,
+    pub model: String,
+    #[serde(default = "default_object")]
+    pub settings_json: serde_json::Value,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+async fn upsert_model_profile(
+    State(state): State<AdminState>,
+    Path(name): Path<String>,
+    Json(body): Json<UpsertModelProfileBody>,
+) -> impl IntoResponse {
+    let name = name.trim().to_string();
+    // Profile names are matched as bare models; a `/` would read as a provider prefix.
+    if name.is_empty() || name.contains('/') || name.contains(':') {
+        return bad_request(
+            "invalid_name",
+            "profile name must be non-empty without `/` or `:`",
         )
+        .into_response();
+    }
+    let model = body.model.trim().to_string();
+    if model.is_empty() {
+        return bad_request("invalid_model", "`model` must not be empty").into_response();
+    }
+    if !body.settings_json.is_object() {
+        return bad_request("invalid_settings", "`settings_json` must be an object")
             .into_response();
     }
+    if !state
+        .app
+        .snapshot
+        .load()
+        .providers
+        .iter()
+        .any(|p| p.name == body.provider)
+    {
+        return bad_request(
+            "unknown_provider",
+            format!("unknown provider: {}", body.provider),
+        )
+        .into_response();
+    }
+    let id = match state
+        .storage
+        .upsert_model_profile(
+            &name,
+            &body.provider,
+            &model,
+            &body.settings_json,
+            body.enabled,
+        )
+        .await
+    {
+        Ok(id) => id,
+        Err(err) => return storage_error(err).into_response(),
+    };
+    state.app.apply_model_profile_upsert(
+        id,
+        name.clone(),
+        body.provider,
+        model,
+        body.settings_json,
+        body.enabled,
+    );
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({ "id": id, "name": name })),
+    )
+        .into_response()
+}
 
-    let offset = query.offset.unwrap_or(0);
-    if offset > 0 {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({
-                "error": "offset_not_supported",
-                "detail": "offset pagination is disabled for performance; use cursor_at + cursor_id",
-            })),
+async fn delete_model_profile(
+    State(state): State<AdminState>,
+    Path(name): Path<String>,
+) -> impl IntoResponse {
+    if let Err(err) = state.storage.delete_model_profile(&name).await {
+        return storage_error(err).into_response();
+    }
+    state.app.apply_model_profile_delete(&name);
+    (StatusCode::OK, Json(serde_json::json!({ "ok": true }))).into_response()
+}
+
+async fn list_experiments(State(state): State<AdminState>) -> impl IntoResponse {
+    let snapshot = state.app.snapshot.load();
+    let experiments: Vec<_> = snapshot
+        .experiments
+        .iter()
+        .map(|e| {
+            serde_json::json!({
+                "id": e.id,
+                "name": e.name,
+                "arms": e.arms_json,
+                "split": e.split,
+                "enabled": e.enabled,
+                "created_at": e.created_at,
+                "updated_at": e.updated_at,
+            })
+        })
+        .collect();
+    Json(serde_json::json!({ "experiments": experiments }))
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct ExperimentArmBody {
+    pub name: String,
+    pub provider: String,
+    pub model: String,
+    #[serde(default = "default_arm_weight")]
+    pub weight: u32,
+}
+
+fn default_arm_weight() -> u32 {
+    1
+}
+
+#[derive(Debug, Deserialize)]
+struct UpsertExperimentBody {
+    pub arms: Vec<ExperimentArmBody>,
+    #[serde(default = "default_experiment_split")]
+    pub split: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+fn default_experiment_split() -> String {
+    "random".to_string()
+}
+
+async fn upsert_experiment(
+    State(state): State<AdminState>,
+    Path(name): Path<String>,
+    Json(body): Json<UpsertExperimentBody>,
+) -> impl IntoResponse {
+    let name = name.trim().to_string();
+    if name.is_empty() || name.contains('/') || name.contains(':') {
+        return bad_request(
+            "invalid_name",
+            "experiment name must be non-empty without `/` or `:`",
+        )
+        .into_response();
+    }
+    if !matches!(body.split.as_str(), "random" | "user" | "session") {
+        return bad_request(
+            "invalid_split",
+            "`split` must be `random`, `user` or `session`",
         )
+        .into_response();
+    }
+    if body
+        .arms
+        .iter()
+        .map(|arm| u64::from(arm.weight))
+        .sum::<u64>()
+        == 0
+    {
+        return bad_request("invalid_arms", "at least one arm needs a positive weight")
             .into_response();
     }
-    let limit = query.limit.unwrap_or(100).clamp(1, 500);
-    let include_body = query.include_body.unwrap_or(false);
-    let cursor = match (normalize_opt_str(query.cursor_at), query.cursor_id) {
-        (None, None) => None,
-        (Some(_), None) | (None, Some(_)) => {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(serde_json::json!({
-                    "error": "invalid_cursor",
-                    "detail": "cursor_at and cursor_id must be provided together",
-                })),
-            )
+    {
+        let snapshot = state.app.snapshot.load();
+        let mut seen = std::collections::HashSet::new();
+        for arm in &body.arms {
+            if arm.name.trim().is_empty() || !seen.insert(arm.name.as_str()) {
+                return bad_request("invalid_arms", "arm names must be non-empty and unique")
+                    .into_response();
+            }
+            if arm.model.trim().is_empty() {
+                return bad_request("invalid_arms", format!("arm {} has no model", arm.name))
+                    .into_response();
+            }
+            if !snapshot.providers.iter().any(|p| p.name == arm.provider) {
+                return bad_request(
+                    "unknown_provider",
+                    format!("unknown provider: {}", arm.provider),
+                )
                 .into_response();
+            }
         }
-        (Some(cursor_at), Some(cursor_id)) => {
-            let cursor_at = match OffsetDateTime::parse(&cursor_at, &Rfc3339) {
-                Ok(v) => v,
-                Err(err) => {
-                    return (
-                        StatusCode::BAD_REQUEST,
-                        Json(serde_json::json!({
-                            "error": "invalid_cursor_at",
-                            "detail": err.to_string(),
-                        })),
-                    )
-                        .into_response();
-                }
-            };
-            Some(gproxy_storage::LogCursor {
-                at: cursor_at,
-                id: cursor_id,
-            })
-        }
+    }
+    let arms_json = match serde_json::to_value(&body.arms) {
+        Ok(v) => v,
+        Err(err) => return bad_request("invalid_arms", err.to_string()).into_response(),
     };
-
-    let filter = gproxy_storage::LogQueryFilter {
-        from,
-        to,
-        kind,
-        provider: normalize_opt_str(query.provider),
-        credential_id: query.credential_id,
-        user_id: query.user_id,
-        user_key_id: query.user_key_id,
-        trace_id: normalize_opt_str(query.trace_id),
-        operation: normalize_opt_str(query.operation),
-        request_path_contains: normalize_opt_str(query.path_contains),
-        status_min: query.status_min,
-        status_max: query.status_max,
-        limit,
-        cursor,
-        include_body,
+    let id = match state
+        .storage
+        .upsert_experiment(&name, &arms_json, &body.split, body.enabled)
+        .await
+    {
+        Ok(id) => id,
+        Err(err) => return storage_error(err).into_response(),
     };
+    state
+        .app
+        .apply_experiment_upsert(id, name.clone(), arms_json, body.split, body.enabled);
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({ "id": id, "name": name })),
+    )
+        .into_response()
+}
 
-    let result = match state.storage.query_logs(filter).await {
+async fn delete_experiment(
+    State(state): State<AdminState>,
+    Path(name): Path<String>,
+) -> impl IntoResponse {
+    if let Err(err) = state.storage.delete_experiment(&name).await {
+        return storage_error(err).into_response();
+    }
+    state.app.apply_experiment_delete(&name);
+    (StatusCode::OK, Json(serde_json::json!({ "ok": true }))).into_response()
+}
+
+/// Token usage per arm of an experiment, for comparing cost across arms.
+async fn experiment_usage(
+    State(state): State<AdminState>,
+    Path(name): Path<String>,
+    Query(query): Query<UsageRangeQuery>,
+) -> impl IntoResponse {
+    let (from, to) = match parse_usage_range(&query) {
         Ok(v) => v,
-        Err(err) => return storage_error(err).into_response(),
+        Err(resp) => return resp.into_response(),
     };
-
-    let rows: Vec<_> = result
-        .rows
-        .into_iter()
-        .map(|row| {
-            let kind = match row.kind {
-                gproxy_storage::LogRecordKind::Upstream => "upstream",
-                gproxy_storage::LogRecordKind::Downstream => "downstream",
-            };
-            let show_error_body = matches!(row.kind, gproxy_storage::LogRecordKind::Downstream)
-                && row.response_status.unwrap_or_default() >= 400;
-            let show_body = include_body || show_error_body;
-            let request_body = if show_body {
-                bytes_body_to_json(&row.request_body)
-            } else {
-                JsonValue::Null
-            };
-            let response_body = if show_body {
-                bytes_body_to_json(&row.response_body)
-            } else {
-                JsonValue::Null
-            };
-            serde_json::json!({
-                "id": row.id,
-                "kind": kind,
-                "at": format_time_rfc3339(row.at),
-                "trace_id": row.trace_id,
-                "provider": row.provider,
-                "credential_id": row.credential_id,
-                "user_id": row.user_id,
-                "user_key_id": row.user_key_id,
-                "attempt_no": row.attempt_no,
-                "operation": row.operation,
-                "request_method": row.request_method,
-                "request_path": row.request_path,
-                "request_body": request_body,
-                "response_status": row.response_status,
-                "response_body": response_body,
-                "error_kind": row.error_kind,
-                "error_message": row.error_message,
+    let arm_names: Vec<String> = {
+        let snapshot = state.app.snapshot.load();
+        let Some(experiment) = snapshot.experiments.iter().find(|e| e.name == name) else {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({ "error": "experiment_not_found" })),
+            )
+                .into_response();
+        };
+        experiment
+            .arms_json
+            .as_array()
+            .map(|arms| {
+                arms.iter()
+                    .filter_map(|arm| arm.get("name").and_then(|n| n.as_str()))
+                    .map(str::to_string)
+                    .collect()
             })
-        })
-        .collect();
-
-    let (next_cursor_at, next_cursor_id) = match result.next_cursor {
-        Some(cursor) => (Some(format_time_rfc3339(cursor.at)), Some(cursor.id)),
-        None => (None, None),
+            .unwrap_or_default()
     };
 
+    let mut arms = Vec::with_capacity(arm_names.len());
+    for arm in arm_names {
+        let aggregate = match state
+            .storage
+            .aggregate_usage_tokens(gproxy_storage::UsageAggregateFilter {
+                from,
+                to,
+                provider: None,
+                credential_id: None,
+                user_ids: None,
+                model: None,
+                model_contains: query.model_contains.clone(),
+                tag: normalize_opt_str(query.tag.clone()),
+                experiment: Some(name.clone()),
+                experiment_arm: Some(arm.clone()),
+            })
+            .await
+        {
+            Ok(v) => v,
+            Err(err) => return storage_error(err).into_response(),
+        };
+        arms.push(serde_json::json!({
+            "arm": arm,
+            "call_count": aggregate.matched_rows,
+            "input_tokens": aggregate.input_tokens,
+            "output_tokens": aggregate.output_tokens,
+            "cache_read_input_tokens": aggregate.cache_read_input_tokens,
+            "cache_creation_input_tokens": aggregate.cache_creation_input_tokens,
+            "total_tokens": aggregate.total_tokens,
+        }));
+    }
+
     (
         StatusCode::OK,
         Json(serde_json::json!({
-            "from": format_time_rfc3339(from),
-            "to": format_time_rfc3339(to),
-            "kind": match kind {
-                None => "all",
-                Some(gproxy_storage::LogRecordKind::Upstream) => "upstream",
-                Some(gproxy_storage::LogRecordKind::Downstream) => "downstream",
-            },
-            "limit": limit,
-            "include_body": include_body,
-            "has_more": result.has_more,
-            "next_cursor_at": next_cursor_at,
-            "next_cursor_id": next_cursor_id,
-            "rows": rows,
+            "experiment": name,
+            "from": query.from,
+            "to": query.to,
+            "arms": arms,
         })),
     )
         .into_response()
 }
 
-async fn list_users(State(state): State<AdminState>) -> impl IntoResponse {
+async fn list_log_views(State(state): State<AdminState>) -> impl IntoResponse {
     let snapshot = state.app.snapshot.load();
-    let users: Vec<_> = snapshot
-        .users
+    let views: Vec<_> = snapshot
+        .log_views
         .iter()
-        .map(|u| {
+        .map(|v| {
             serde_json::json!({
-                "id": u.id,
-                "name": u.name,
-                "enabled": u.enabled,
-                "created_at": u.created_at,
-                "updated_at": u.updated_at,
+                "id": v.id,
+                "name": v.name,
+                "filter": v.filter_json,
+                "window_secs": v.window_secs,
+                "alert_threshold": v.alert_threshold,
+                "created_at": v.created_at,
+                "updated_at": v.updated_at,
             })
         })
         .collect();
-    Json(serde_json::json!({ "users": users }))
+    Json(serde_json::json!({ "views": views }))
 }
 
 #[derive(Debug, Deserialize)]
-struct UpsertUserBody {
-    pub name: String,
-    pub enabled: bool,
+struct UpsertLogViewBody {
+    #[serde(default)]
+    pub filter: JsonValue,
+    #[serde(default = "default_log_view_window_secs")]
+    pub window_secs: i64,
+    #[serde(default)]
+    pub alert_threshold: Option<i64>,
 }
 
-async fn upsert_user(
-    State(state): State<AdminState>,
-    Path(id): Path<i64>,
-    Json(body): Json<UpsertUserBody>,
+fn default_log_view_window_secs() -> i64 {
+    24 * 3600
+}
+
+async fn upsert_log_view(
+    State(state): State<AdminState>,
+    Path(name): Path<String>,
+    Json(body): Json<UpsertLogViewBody>,
 ) -> impl IntoResponse {
-    if let Err(err) = state
+    let name = name.trim().to_string();
+    if name.is_empty() || name.contains('/') {
+        return bad_request("invalid_name", "view name must be non-empty without `/`")
+            .into_response();
+    }
+    let filter = match &body.filter {
+        JsonValue::Null => LogViewFilter::default(),
+        raw => match LogViewFilter::parse(raw) {
+            Ok(v) => v,
+            Err(err) => return bad_request("invalid_filter", err).into_response(),
+        },
+    };
+    if !(60..=MAX_WINDOW_SECS).contains(&body.window_secs) {
+        return bad_request(
+            "invalid_window",
+            format!("`window_secs` must be within 60..={MAX_WINDOW_SECS}"),
+        )
+        .into_response();
+    }
+    if let Some(threshold) = body.alert_threshold
+        && !(1..=MAX_ALERT_THRESHOLD).contains(&threshold)
+    {
+        return bad_request(
+            "invalid_alert_threshold",
+            format!("`alert_threshold` must be within 1..={MAX_ALERT_THRESHOLD}"),
+        )
+        .into_response();
+    }
+    let filter_json = match serde_json::to_value(&filter) {
+        Ok(v) => v,
+        Err(err) => return bad_request("invalid_filter", err.to_string()).into_response(),
+    };
+    let id = match state
         .storage
-        .upsert_user_by_id(id, &body.name, body.enabled)
+        .upsert_log_view(&name, &filter_json, body.window_secs, body.alert_threshold)
         .await
     {
-        return storage_error(err).into_response();
-    }
-    state
-        .app
-        .apply_user_upsert(id, body.name.clone(), body.enabled);
+        Ok(id) => id,
+        Err(err) => return storage_error(err).into_response(),
+    };
+    state.app.apply_log_view_upsert(
+        id,
+        name.clone(),
+        filter_json,
+        body.window_secs,
+        body.alert_threshold,
+    );
     (
         StatusCode::OK,
-        Json(serde_json::json!({ "id": id, "name": body.name })),
+        Json(serde_json::json!({ "id": id, "name": name })),
     )
         .into_response()
 }
 
-async fn delete_user(State(state): State<AdminState>, Path(id): Path<i64>) -> impl IntoResponse {
-    if let Err(err) = state.storage.delete_user(id).await {
-        return storage_error(err).into_response();
-    }
-    state.app.apply_user_delete(id);
-    (StatusCode::OK, Json(serde_json::json!({ "ok": true }))).into_response()
-}
-
-async fn set_user_enabled(
+async fn delete_log_view(
     State(state): State<AdminState>,
-    Path(id): Path<i64>,
-    Json(body): Json<SetEnabledBody>,
+    Path(name): Path<String>,
 ) -> impl IntoResponse {
-    if let Err(err) = state.storage.set_user_enabled(id, body.enabled).await {
+    if let Err(err) = state.storage.delete_log_view(&name).await {
         return storage_error(err).into_response();
     }
-    state.app.apply_user_enabled(id, body.enabled);
+    state.app.apply_log_view_delete(&name);
     (StatusCode::OK, Json(serde_json::json!({ "ok": true }))).into_response()
 }
 
 #[derive(Debug, Deserialize)]
-struct InsertUserKeyBody {
+struct LogViewLogsQuery {
     #[serde(default)]
-    pub key: Option<String>,
+    limit: Option<usize>,
     #[serde(default)]
-    pub label: Option<String>,
-    #[serde(default = "default_true")]
-    pub enabled: bool,
+    cursor: Option<String>,
+    #[serde(default)]
+    fields: Option<String>,
+    #[serde(default)]
+    include_body: Option<bool>,
 }
 
-async fn insert_user_key(
+/// Rows of a saved view over its trailing window, paged and shaped like `GET /logs`.
+async fn log_view_logs(
     State(state): State<AdminState>,
-    Path(user_id): Path<i64>,
-    Json(body): Json<InsertUserKeyBody>,
-) -> impl IntoResponse {
-    let key_plain = body.key.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
-
-    let id = match state
-        .storage
-        .insert_user_key(user_id, &key_plain, body.label.as_deref(), body.enabled)
-        .await
-    {
-        Ok(id) => id,
-        Err(err) => return storage_error(err).into_response(),
+    Extension(scope): Extension<AdminScope>,
+    Path(name): Path<String>,
+    Query(query): Query<LogViewLogsQuery>,
+) -> Response {
+    let Some(view) = state
+        .app
+        .snapshot
+        .load()
+        .log_views
+        .iter()
+        .find(|v| v.name == name)
+        .cloned()
+    else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "log_view_not_found" })),
+        )
+            .into_response();
+    };
+    let mut params = match view.filter_json {
+        JsonValue::Object(map) => map,
+        _ => serde_json::Map::new(),
     };
+    let from = OffsetDateTime::now_utc() - TimeDuration::seconds(view.window_secs);
+    params.insert("from".to_string(), format_time_rfc3339(from).into());
+    params.insert("limit".to_string(), serde_json::json!(query.limit));
+    params.insert("cursor".to_string(), serde_json::json!(query.cursor));
+    params.insert("fields".to_string(), serde_json::json!(query.fields));
+    params.insert(
+        "include_body".to_string(),
+        serde_json::json!(query.include_body),
+    );
+    let logs_query: LogsQuery = match serde_json::from_value(JsonValue::Object(params)) {
+        Ok(v) => v,
+        Err(err) => return bad_request("invalid_filter", err.to_string()).into_response(),
+    };
+    query_logs(State(state), Extension(scope), Query(logs_query))
+        .await
+        .into_response()
+}
 
-    state
-        .app
-        .apply_user_key_insert(id, user_id, key_plain.clone(), body.label, body.enabled);
+async fn list_jobs(State(state): State<AdminState>) -> impl IntoResponse {
+    Json(serde_json::json!({ "jobs": state.app.jobs.status_json() }))
+}
 
+fn job_not_found() -> Response {
     (
-        StatusCode::OK,
-        Json(serde_json::json!({ "id": id, "key": key_plain })),
+        StatusCode::NOT_FOUND,
+        Json(serde_json::json!({ "error": "job_not_found" })),
     )
         .into_response()
 }
 
-async fn list_user_keys(
+#[derive(Debug, Deserialize)]
+struct JobRunsQuery {
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+async fn list_job_runs(
     State(state): State<AdminState>,
-    Path(user_id): Path<i64>,
+    Path(name): Path<String>,
+    Query(query): Query<JobRunsQuery>,
 ) -> impl IntoResponse {
-    let snapshot = state.app.snapshot.load();
-    let keys: Vec<_> = snapshot
-        .user_keys
-        .iter()
-        .filter(|k| k.user_id == user_id)
-        .map(|k| {
+    if !state.app.jobs.contains(&name) {
+        return job_not_found();
+    }
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+    let runs = match state.storage.list_job_runs(Some(&name), limit).await {
+        Ok(v) => v,
+        Err(err) => return storage_error(err).into_response(),
+    };
+    let runs: Vec<_> = runs
+        .into_iter()
+        .map(|run| {
             serde_json::json!({
-                "id": k.id,
-                "user_id": k.user_id,
-                "label": k.label,
-                "enabled": k.enabled,
-                "created_at": k.created_at,
-                "updated_at": k.updated_at,
+                "id": run.id,
+                "trigger": run.trigger,
+                "started_at": run.started_at,
+                "finished_at": run.finished_at,
+                "ok": run.ok,
+                "message": run.message,
             })
         })
         .collect();
-    Json(serde_json::json!({ "keys": keys }))
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({ "job": name, "runs": runs })),
+    )
+        .into_response()
 }
 
-async fn set_user_key_enabled(
-    State(state): State<AdminState>,
-    Path(id): Path<i64>,
-    Json(body): Json<SetEnabledBody>,
-) -> impl IntoResponse {
-    if let Err(err) = state.storage.set_user_key_enabled(id, body.enabled).await {
-        return storage_error(err).into_response();
+async fn run_job(State(state): State<AdminState>, Path(name): Path<String>) -> impl IntoResponse {
+    match state.app.jobs.trigger(&name) {
+        Ok(()) => (
+            StatusCode::ACCEPTED,
+            Json(serde_json::json!({ "job": name, "triggered": true })),
+        )
+            .into_response(),
+        Err(TriggerError::NotFound) => job_not_found(),
+        Err(TriggerError::AlreadyRunning) => (
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({ "error": "job_already_running" })),
+        )
+            .into_response(),
     }
-    state.app.apply_user_key_enabled(id, body.enabled);
-    (StatusCode::OK, Json(serde_json::json!({ "ok": true }))).into_response()
+}
+
+const GPROXY_REPO_API_LATEST: &str = "https://api.github.com/repos/LeenHawk/gproxy/releases/latest";
+
+#[derive(Debug, Deserialize, Clone)]
+struct GithubReleaseAsset {
+    name: String,
+    browser_download_url: String,
 }
 
 #[derive(Debug, Deserialize)]
-struct UpdateUserKeyBody {
-    pub label: Option<String>,
+struct GithubReleaseInfo {
+    tag_name: String,
+    assets: Vec<GithubReleaseAsset>,
 }
 
-async fn update_user_key(
-    State(state): State<AdminState>,
-    Path(id): Path<i64>,
-    Json(body): Json<UpdateUserKeyBody>,
-) -> impl IntoResponse {
-    if let Err(err) = state
-        .storage
-        .update_user_key_label(id, body.label.as_deref())
-        .await
-    {
-        return storage_error(err).into_response();
+async fn get_upstream_pool(State(state): State<AdminState>) -> impl IntoResponse {
+    let snapshot = state.app.upstream_pool.snapshot();
+    let hosts: Vec<_> = snapshot
+        .hosts
+        .iter()
+        .map(|(host, stats)| {
+            serde_json::json!({
+                "host": host,
+                "in_flight": stats.in_flight,
+                "requests": stats.requests,
+                "dns_failures": stats.dns_failures,
+                "connect_failures": stats.connect_failures,
+                "tls_failures": stats.tls_failures,
+                "last_failure_at": stats.last_failure_at.map(format_time_rfc3339),
+                "last_failure": stats.last_failure,
+            })
+        })
+        .collect();
+    let dns: Vec<_> = snapshot
+        .dns
+        .iter()
+        .map(|entry| {
+            serde_json::json!({
+                "host": entry.host,
+                "addrs": entry.addrs.iter().map(|addr| addr.ip().to_string()).collect::<Vec<_>>(),
+                "age_ms": entry.age.as_millis() as u64,
+                "ttl_ms": DNS_CACHE_TTL.saturating_sub(entry.age).as_millis() as u64,
+                "hits": entry.hits,
+            })
+        })
+        .collect();
+    let egress_proxies: Vec<_> = state
+        .app
+        .egress_proxies
+        .snapshot()
+        .into_iter()
+        .map(|status| {
+            serde_json::json!({
+                "proxy": status.proxy,
+                "failures": status.failures,
+                "dead_for_ms": status.dead_for.map(|d| d.as_millis() as u64),
+                "last_error": status.last_error,
+            })
+        })
+        .collect();
+    Json(serde_json::json!({
+        "cached_clients": snapshot.cached_clients,
+        "flushes": snapshot.flushes,
+        "hosts": hosts,
+        "dns_cache": dns,
+        "egress_proxies": egress_proxies,
+    }))
+}
+
+async fn flush_upstream_pool(State(state): State<AdminState>) -> impl IntoResponse {
+    let dns_entries = state.app.upstream_pool.flush();
+    Json(serde_json::json!({
+        "ok": true,
+        "dns_entries_dropped": dns_entries,
+    }))
+}
+
+async fn get_snapshot_drift(State(state): State<AdminState>) -> impl IntoResponse {
+    let db = match state.storage.load_snapshot().await {
+        Ok(db) => db,
+        Err(err) => return storage_error(err).into_response(),
+    };
+    let drift = state.app.drift(&db);
+    Json(serde_json::json!({
+        "in_sync": drift.is_empty(),
+        "drift": drift,
+    }))
+    .into_response()
+}
+
+async fn resync_snapshot(State(state): State<AdminState>) -> impl IntoResponse {
+    let db = match state.storage.load_snapshot().await {
+        Ok(db) => db,
+        Err(err) => return storage_error(err).into_response(),
+    };
+    let drift = state.app.drift(&db);
+    if let Err(err) = state.app.resync(db).await {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": "resync_failed", "detail": err.to_string() })),
+        )
+            .into_response();
     }
-    state.app.apply_user_key_label(id, body.label);
-    (StatusCode::OK, Json(serde_json::json!({ "ok": true }))).into_response()
+    Json(serde_json::json!({ "ok": true, "drift": drift })).into_response()
 }
 
-async fn delete_user_key(
+async fn get_log_filter() -> impl IntoResponse {
+    Json(serde_json::json!({ "filter": gproxy_common::log::filter().to_string() }))
+}
+
+#[derive(Debug, Deserialize)]
+struct LogFilterBody {
+    filter: String,
+}
+
+async fn set_log_filter(Json(body): Json<LogFilterBody>) -> impl IntoResponse {
+    let filter = match gproxy_common::log::LogFilter::parse(&body.filter) {
+        Ok(filter) => filter,
+        Err(err) => return bad_request("invalid_log_filter", err).into_response(),
+    };
+    let applied = filter.to_string();
+    gproxy_common::log::set_filter(filter);
+    gproxy_common::log_info!("admin", "log filter set to {applied}");
+    Json(serde_json::json!({ "ok": true, "filter": applied })).into_response()
+}
+
+async fn get_system_mode(State(state): State<AdminState>) -> impl IntoResponse {
+    Json(state.app.system_mode.status())
+}
+
+#[derive(Debug, Deserialize)]
+struct MaintenanceBody {
+    enabled: bool,
+    #[serde(default)]
+    message: Option<String>,
+}
+
+async fn set_maintenance_mode(
     State(state): State<AdminState>,
-    Path(id): Path<i64>,
+    Json(body): Json<MaintenanceBody>,
 ) -> impl IntoResponse {
-    if let Err(err) = state.storage.delete_user_key(id).await {
-        return storage_error(err).into_response();
+    state
+        .app
+        .system_mode
+        .set_maintenance(body.enabled, body.message);
+    let status = state.app.system_mode.status();
+    match &status.maintenance_message {
+        Some(message) => gproxy_common::log_warn!("admin", "maintenance mode on: {message}"),
+        None => gproxy_common::log_warn!("admin", "maintenance mode off"),
     }
-    state.app.apply_user_key_delete(id);
-    (StatusCode::OK, Json(serde_json::json!({ "ok": true }))).into_response()
+    Json(status)
 }
 
-const GPROXY_REPO_API_LATEST: &str = "https://api.github.com/repos/LeenHawk/gproxy/releases/latest";
+#[derive(Debug, Deserialize)]
+struct ReadOnlyBody {
+    enabled: bool,
+}
 
-#[derive(Debug, Deserialize, Clone)]
-struct GithubReleaseAsset {
-    name: String,
-    browser_download_url: String,
+async fn set_read_only_mode(
+    State(state): State<AdminState>,
+    Json(body): Json<ReadOnlyBody>,
+) -> impl IntoResponse {
+    state.app.system_mode.set_read_only(body.enabled);
+    gproxy_common::log_warn!(
+        "admin",
+        "read-only mode {}",
+        if body.enabled { "on" } else { "off" }
+    );
+    Json(state.app.system_mode.status())
 }
 
-#[derive(Debug, Deserialize)]
-struct GithubReleaseInfo {
-    tag_name: String,
-    assets: Vec<GithubReleaseAsset>,
+/// Config changes as SSE (`event: config`, `id` = event `seq`) until the client leaves.
+/// A subscriber that falls behind gets `event: lagged` with the number of events missed.
+async fn stream_config_events(State(state): State<AdminState>) -> impl IntoResponse {
+    let mut events = state.app.config_events.subscribe();
+    let (tx, rx) = tokio::sync::mpsc::channel::<bytes::Bytes>(32);
+    tokio::spawn(async move {
+        loop {
+            let received = tokio::select! {
+                _ = tx.closed() => break,
+                received = events.recv() => received,
+            };
+            let frame = match received {
+                Ok(event) => format!(
+                    "id: {}\nevent: config\ndata: {}\n\n",
+                    event.seq,
+                    serde_json::to_string(&event).unwrap_or_default()
+                ),
+                Err(RecvError::Lagged(skipped)) => {
+                    format!("event: lagged\ndata: {{\"skipped\":{skipped}}}\n\n")
+                }
+                Err(RecvError::Closed) => break,
+            };
+            if tx.send(bytes::Bytes::from(frame)).await.is_err() {
+                break;
+            }
+        }
+    });
+    let stream = ReceiverStream::new(wrap_sse_stream_with_heartbeat(rx)).map(Ok::<_, Infallible>);
+    (
+        [
+            (header::CONTENT_TYPE, "text/event-stream"),
+            (header::CACHE_CONTROL, "no-cache"),
+        ],
+        axum::body::Body::from_stream(stream),
+    )
 }
 
 async fn system_self_update(State(state): State<AdminState>) -> impl IntoResponse {
@@ -1696,7 +5637,7 @@ fn restart_current_process(exe: PathBuf, args: Vec<std::ffi::OsString>) {
     let mut cmd = std::process::Command::new(&exe);
     cmd.args(&args);
     let err = cmd.exec();
-    eprintln!("self_update exec failed for {}: {err}", exe.display());
+    gproxy_common::log_error!("self_update", "exec failed for {}: {err}", exe.display());
     std::process::exit(1);
 }
 
@@ -1720,8 +5661,9 @@ fn restart_current_process(
         {
             Ok(_) => std::process::exit(0),
             Err(err) => {
-                eprintln!(
-                    "self_update powershell spawn failed for {} with staged {}: {err}",
+                gproxy_common::log_error!(
+                    "self_update",
+                    "powershell spawn failed for {} with staged {}: {err}",
                     exe.display(),
                     staged.display()
                 );
@@ -1732,8 +5674,9 @@ fn restart_current_process(
     match std::process::Command::new(&exe).args(&args).spawn() {
         Ok(_) => std::process::exit(0),
         Err(err) => {
-            eprintln!(
-                "self_update spawn failed for {} with args {:?}: {err}",
+            gproxy_common::log_error!(
+                "self_update",
+                "spawn failed for {} with args {:?}: {err}",
                 exe.display(),
                 args
             );
@@ -1747,8 +5690,9 @@ fn restart_current_process(exe: PathBuf, args: Vec<std::ffi::OsString>) {
     match std::process::Command::new(&exe).args(&args).spawn() {
         Ok(_) => std::process::exit(0),
         Err(err) => {
-            eprintln!(
-                "self_update spawn failed for {} with args {:?}: {err}",
+            gproxy_common::log_error!(
+                "self_update",
+                "spawn failed for {} with args {:?}: {err}",
                 exe.display(),
                 args
             );
@@ -1851,9 +5795,27 @@ fn parse_usage_range(
             })),
         ));
     }
+    query_tag(query.tag.clone())?;
     Ok((from, to))
 }
 
+/// A tag filter names one tag as `x-gproxy-tags` would accept it, so it can be
+/// matched against the stored `,tag,` list.
+fn query_tag(
+    input: Option<String>,
+) -> Result<Option<String>, (StatusCode, Json<serde_json::Value>)> {
+    let Some(tag) = normalize_opt_str(input) else {
+        return Ok(None);
+    };
+    if crate::proxy::parse_request_tags(&tag) != [tag.as_str()] {
+        return Err(bad_request(
+            "invalid_tag",
+            "a tag is up to 64 characters of A-Z, a-z, 0-9, `_`, `.`, `:` and `-`",
+        ));
+    }
+    Ok(Some(tag))
+}
+
 fn normalize_opt_str(input: Option<String>) -> Option<String> {
     input.and_then(|value| {
         let trimmed = value.trim();
@@ -1865,7 +5827,7 @@ fn normalize_opt_str(input: Option<String>) -> Option<String> {
     })
 }
 
-fn format_time_rfc3339(value: OffsetDateTime) -> String {
+pub(crate) fn format_time_rfc3339(value: OffsetDateTime) -> String {
     value
         .format(&Rfc3339)
         .unwrap_or_else(|_| value.unix_timestamp().to_string())
@@ -1878,6 +5840,39 @@ fn bytes_body_to_json(body: &Option<Vec<u8>>) -> JsonValue {
     }
 }
 
+/// Rows of a storage listing cut to the `fields` query parameter.
+fn project_rows(rows: Vec<JsonValue>, fields: Option<&str>) -> Vec<JsonValue> {
+    match fields.map(listing::parse_fields) {
+        Some(fields) => rows
+            .into_iter()
+            .map(|row| listing::project(row, &fields))
+            .collect(),
+        None => rows,
+    }
+}
+
+fn time_cursor_json(at: Option<&str>, id: Option<i64>) -> Option<String> {
+    Some(listing::encode_cursor(&TimeCursor {
+        at: at?.to_string(),
+        id: id?,
+    }))
+}
+
+/// A snapshot-backed list paged per [`ListQuery`], with the items under `key`.
+fn list_page(items: Vec<JsonValue>, query: &ListQuery, sortable: &[&str], key: &str) -> Response {
+    match listing::paginate(items, query, sortable) {
+        Ok(page) => Json(page.into_json(key, query)).into_response(),
+        Err(err) => bad_request(err.code, err.detail).into_response(),
+    }
+}
+
+fn bad_request(error: &str, detail: impl Into<String>) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(serde_json::json!({ "error": error, "detail": detail.into() })),
+    )
+}
+
 fn storage_error(err: gproxy_storage::StorageError) -> (StatusCode, Json<serde_json::Value>) {
     // TODO: map common unique constraint errors to 409.
     (
@@ -1885,3 +5880,41 @@ fn storage_error(err: gproxy_storage::StorageError) -> (StatusCode, Json<serde_j
         Json(serde_json::json!({ "error": "storage_error", "detail": err.to_string() })),
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn org_scope_reads_only_its_own_views() {
+        for path in [
+            "/health",
+            "/logs",
+            "/logs/search",
+            "/log_views",
+            "/log_views/3/logs",
+            "/users",
+            "/users/7/keys",
+            "/orgs/2",
+            "/orgs/2/grants/",
+        ] {
+            assert!(org_scope_allows(&Method::GET, path), "{path}");
+        }
+        for path in [
+            "/providers",
+            "/credentials",
+            "/orgs",
+            "/orgs/2/limits",
+            "/users/7",
+            "/user_keys/7/limits",
+            "/logs/downstream/1/replay",
+            "/system/read_only",
+        ] {
+            assert!(!org_scope_allows(&Method::GET, path), "{path}");
+        }
+        for method in [Method::POST, Method::PUT, Method::DELETE] {
+            assert!(!org_scope_allows(&method, "/users/7/keys"));
+            assert!(!org_scope_allows(&method, "/orgs/2"));
+        }
+    }
+}
@@ -0,0 +1,234 @@
+//! Embedding gproxy into another axum application.
+//!
+//! ```no_run
+//! # async fn run() -> anyhow::Result<()> {
+//! let gproxy = gproxy_router::GproxyBuilder::from_env()?.build().await?;
+//! let app = axum::Router::new().nest("/llm", gproxy.router());
+//! # let _ = app;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Serve the final router with `into_make_service_with_connect_info::<SocketAddr>()` so
+//! client addresses reach events and the leaked-key rules.
+
+use std::sync::Arc;
+
+use axum::Router;
+
+use gproxy_common::GlobalConfigPatch;
+use gproxy_core::bootstrap::{self, Bootstrap, BootstrapExtras, CliArgs};
+use gproxy_core::incidents::register_provider_incidents;
+use gproxy_core::log_views::register_log_view_alerts;
+use gproxy_core::org_budgets::register_org_budgets;
+use gproxy_core::proxy_engine::{
+    AuthChain, AuthProvider, ForwardAuthProvider, ProxyEngine, SnapshotAuthProvider,
+};
+use gproxy_core::state::AppState;
+use gproxy_core::temporary_keys::register_user_key_expiry;
+use gproxy_core::upstream_client::{
+    UpstreamClient, UpstreamClientConfig, WreqUpstreamClient, global_egress,
+};
+use gproxy_provider_core::{EventSink, ProviderRegistry, UpstreamProvider};
+use gproxy_storage::{SplitStorage, Storage, TelemetryStorage};
+
+/// Assembles the core, the proxy engine and the routers. Everything not set here falls
+/// back to what the `gproxy` binary uses.
+#[derive(Default)]
+pub struct GproxyBuilder {
+    global: GlobalConfigPatch,
+    storage: Option<Arc<dyn Storage>>,
+    telemetry_dsn: Option<String>,
+    telemetry_storage: Option<Arc<dyn TelemetryStorage>>,
+    clickhouse_url: Option<String>,
+    extras: BootstrapExtras,
+    auth: Vec<Arc<dyn AuthProvider>>,
+    forward_auth_url: Option<String>,
+    upstream_client: Option<Arc<dyn UpstreamClient>>,
+}
+
+impl GproxyBuilder {
+    /// Starts with no settings; the stored global config (or its defaults) applies.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts from the `GPROXY_*` environment and command line, like the binary.
+    pub fn from_env() -> anyhow::Result<Self> {
+        Self::from_args(bootstrap::cli_args_from_env())
+    }
+
+    pub fn from_args(args: CliArgs) -> anyhow::Result<Self> {
+        let telemetry_dsn = bootstrap::telemetry_dsn_from_args(&args);
+        let clickhouse_url = bootstrap::clickhouse_url_from_args(&args);
+        let forward_auth_url = bootstrap::forward_auth_url_from_args(&args);
+        let mut builder = Self::new().global(bootstrap::global_patch_from_args(args)?);
+        builder.telemetry_dsn = telemetry_dsn;
+        builder.clickhouse_url = clickhouse_url;
+        builder.forward_auth_url = forward_auth_url;
+        Ok(builder)
+    }
+
+    /// Global settings that win over the stored config; later calls win over earlier ones.
+    pub fn global(mut self, patch: GlobalConfigPatch) -> Self {
+        self.global.overlay(patch);
+        self
+    }
+
+    /// Uses this storage instead of connecting to `dsn`. The schema sync runs on it.
+    pub fn storage(mut self, storage: Arc<dyn Storage>) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+
+    /// Sends logs, usage, events and hourly stats to a separate DSN.
+    pub fn telemetry_dsn(mut self, dsn: impl Into<String>) -> Self {
+        self.telemetry_dsn = Some(dsn.into());
+        self
+    }
+
+    /// Sends logs, usage, events and hourly stats to this storage; wins over `telemetry_dsn`.
+    pub fn telemetry_storage(mut self, storage: Arc<dyn TelemetryStorage>) -> Self {
+        self.telemetry_storage = Some(storage);
+        self
+    }
+
+    /// Also writes request and usage rows to this ClickHouse HTTP endpoint.
+    pub fn clickhouse_url(mut self, url: impl Into<String>) -> Self {
+        self.clickhouse_url = Some(url.into());
+        self
+    }
+
+    pub fn provider(mut self, provider: Arc<dyn UpstreamProvider>) -> Self {
+        self.extras.providers.push(provider);
+        self
+    }
+
+    pub fn event_sink(mut self, sink: Arc<dyn EventSink>) -> Self {
+        self.extras.event_sinks.push(sink);
+        self
+    }
+
+    /// Adds a provider to the auth chain; providers are asked in the order added and the
+    /// first to allow or deny a request decides. Without any, the stored user keys are used.
+    pub fn auth_provider(mut self, auth: Arc<dyn AuthProvider>) -> Self {
+        self.auth.push(auth);
+        self
+    }
+
+    /// Asks this external authorizer after the stored user keys (see
+    /// [`ForwardAuthProvider`]).
+    pub fn forward_auth_url(mut self, url: impl Into<String>) -> Self {
+        self.forward_auth_url = Some(url.into());
+        self
+    }
+
+    pub fn upstream_client(mut self, client: Arc<dyn UpstreamClient>) -> Self {
+        self.upstream_client = Some(client);
+        self
+    }
+
+    pub async fn build(mut self) -> anyhow::Result<Gproxy> {
+        if let Some(url) = self.clickhouse_url.take() {
+            self.extras
+                .event_sinks
+                .push(bootstrap::clickhouse_sink(url)?);
+        }
+        let mut global = self.global;
+        let storage: Arc<dyn Storage> = match self.storage {
+            Some(storage) => {
+                // The DSN is only informational with an embedder-supplied storage.
+                global.dsn.get_or_insert_with(|| "external".to_string());
+                storage
+            }
+            None => {
+                let dsn = global.dsn.get_or_insert_with(bootstrap::default_dsn);
+                bootstrap::connect_storage(dsn).await?
+            }
+        };
+        let storage: Arc<dyn Storage> = match self.telemetry_storage {
+            Some(telemetry) => Arc::new(SplitStorage::new(storage, telemetry)),
+            None => bootstrap::with_telemetry_dsn(storage, self.telemetry_dsn.as_deref()).await?,
+        };
+        let Bootstrap {
+            storage,
+            state,
+            registry,
+        } = bootstrap::bootstrap_storage(storage, global, self.extras).await?;
+
+        let client = match self.upstream_client {
+            Some(client) => client,
+            None => default_upstream_client(&state)?,
+        };
+        let mut auth = self.auth;
+        if let Some(url) = self.forward_auth_url {
+            if auth.is_empty() {
+                auth.push(Arc::new(SnapshotAuthProvider));
+            }
+            auth.push(Arc::new(ForwardAuthProvider::new(url, client.clone())));
+        }
+        register_log_view_alerts(&state, storage.clone(), client.clone());
+        register_user_key_expiry(&state, storage.clone());
+        register_org_budgets(&state, storage.clone());
+        register_provider_incidents(&state, storage.clone(), client.clone());
+        let mut engine = ProxyEngine::new(state.clone(), registry.clone(), client, storage.clone());
+        match auth.len() {
+            0 => {}
+            1 => engine = engine.with_auth_provider(auth.remove(0)),
+            _ => engine = engine.with_auth_provider(Arc::new(AuthChain::new(auth))),
+        }
+
+        Ok(Gproxy {
+            state,
+            storage,
+            registry,
+            engine: Arc::new(engine),
+        })
+    }
+}
+
+fn default_upstream_client(state: &Arc<AppState>) -> anyhow::Result<Arc<dyn UpstreamClient>> {
+    let config = UpstreamClientConfig::from_global(&state.global.load());
+    let state_for_proxy = state.clone();
+    let state_for_egress = state.clone();
+    let state_for_limit = state.clone();
+    let client = WreqUpstreamClient::new_with_proxy_resolver(config, move || {
+        state_for_proxy.global.load().proxy.clone()
+    })?
+    .with_pool_stats(state.upstream_pool.clone())
+    .with_egress_resolver(move || global_egress(&state_for_egress.global.load()))
+    .with_body_limit_resolver(move || state_for_limit.global.load().max_response_body_bytes);
+    Ok(Arc::new(client))
+}
+
+/// A running core: shared state plus the pieces the routers are built from.
+#[derive(Clone)]
+pub struct Gproxy {
+    pub state: Arc<AppState>,
+    pub storage: Arc<dyn Storage>,
+    pub registry: Arc<ProviderRegistry>,
+    pub engine: Arc<ProxyEngine>,
+}
+
+impl Gproxy {
+    /// Proxy routes at the root and the admin API under `/admin`.
+    pub fn router(&self) -> Router {
+        self.proxy_router().nest("/admin", self.admin_router())
+    }
+
+    /// `/v1/...`, `/v1beta/...` and `/{provider}/...`.
+    pub fn proxy_router(&self) -> Router {
+        crate::proxy_router(self.engine.clone())
+    }
+
+    /// Admin API; log replay goes through [`Gproxy::proxy_router`] and dispatch overrides
+    /// are checked against the engine's provider implementations.
+    pub fn admin_router(&self) -> Router {
+        crate::admin::admin_router_with_state(crate::admin::AdminState {
+            app: self.state.clone(),
+            storage: self.storage.clone(),
+            proxy: Some(self.proxy_router()),
+            engine: Some(self.engine.clone()),
+        })
+    }
+}
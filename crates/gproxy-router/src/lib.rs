@@ -1,5 +1,9 @@
 pub mod admin;
+pub mod builder;
+mod listing;
+mod live;
 pub mod proxy;
 
-pub use admin::admin_router;
+pub use admin::{admin_router, admin_router_with_proxy};
+pub use builder::{Gproxy, GproxyBuilder};
 pub use proxy::proxy_router;
@@ -0,0 +1,265 @@
+//! Paging shared by the admin list endpoints: `limit`, an opaque `cursor` naming the last
+//! item seen, `sort` (a field, `-` prefixed for descending) and `fields` (comma-separated
+//! keys kept in each item). Items are JSON objects with an integer `id`, which breaks
+//! sort ties so a cursor stays valid while items are added or removed.
+
+use std::cmp::Ordering;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+/// Page size when a cursor is given without a limit.
+const DEFAULT_LIMIT: usize = 100;
+const MAX_LIMIT: usize = 500;
+
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct ListQuery {
+    /// Without `limit` or `cursor` every item is returned.
+    #[serde(default)]
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub cursor: Option<String>,
+    #[serde(default)]
+    pub sort: Option<String>,
+    #[serde(default)]
+    pub fields: Option<String>,
+}
+
+/// A rejected query parameter: error code and detail for a 400.
+#[derive(Debug)]
+pub(crate) struct ListError {
+    pub code: &'static str,
+    pub detail: String,
+}
+
+impl ListError {
+    fn new(code: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            code,
+            detail: detail.into(),
+        }
+    }
+}
+
+pub(crate) struct Page {
+    pub items: Vec<JsonValue>,
+    pub limit: Option<usize>,
+    pub has_more: bool,
+    pub next_cursor: Option<String>,
+}
+
+impl Page {
+    /// The response body: items under `key`, each cut to the requested `fields`.
+    pub fn into_json(self, key: &str, query: &ListQuery) -> JsonValue {
+        let fields = query.fields.as_deref().map(parse_fields);
+        let items: Vec<_> = self
+            .items
+            .into_iter()
+            .map(|item| match &fields {
+                Some(fields) => project(item, fields),
+                None => item,
+            })
+            .collect();
+        serde_json::json!({
+            key: items,
+            "limit": self.limit,
+            "has_more": self.has_more,
+            "next_cursor": self.next_cursor,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Cursor {
+    sort: String,
+    value: JsonValue,
+    id: i64,
+}
+
+/// Sorts `items` by the query's `sort` (one of `sortable`, or `id`) and returns the page
+/// after its cursor.
+pub(crate) fn paginate(
+    mut items: Vec<JsonValue>,
+    query: &ListQuery,
+    sortable: &[&str],
+) -> Result<Page, ListError> {
+    let sort = query
+        .sort
+        .as_deref()
+        .map(str::trim)
+        .filter(|sort| !sort.is_empty())
+        .unwrap_or("id");
+    let (field, descending) = match sort.strip_prefix('-') {
+        Some(field) => (field, true),
+        None => (sort, false),
+    };
+    if field != "id" && !sortable.contains(&field) {
+        return Err(ListError::new(
+            "invalid_sort",
+            format!(
+                "unsupported sort field: {field}; expected one of id/{}",
+                sortable.join("/")
+            ),
+        ));
+    }
+    let order = |a: (&JsonValue, i64), b: (&JsonValue, i64)| {
+        let ordering = compare(a.0, b.0).then(a.1.cmp(&b.1));
+        if descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    };
+    items.sort_by(|a, b| order(sort_key(a, field), sort_key(b, field)));
+
+    let cursor = match query.cursor.as_deref().map(str::trim) {
+        None | Some("") => None,
+        Some(raw) => {
+            let cursor: Cursor = decode_cursor(raw)
+                .ok_or_else(|| ListError::new("invalid_cursor", "malformed cursor"))?;
+            if cursor.sort != sort {
+                return Err(ListError::new(
+                    "invalid_cursor",
+                    format!("cursor was issued for sort={}", cursor.sort),
+                ));
+            }
+            Some(cursor)
+        }
+    };
+    if let Some(cursor) = &cursor {
+        let after = items.partition_point(|item| {
+            order(sort_key(item, field), (&cursor.value, cursor.id)) != Ordering::Greater
+        });
+        items.drain(..after);
+    }
+
+    let limit = match (query.limit, &cursor) {
+        (None, None) => None,
+        (limit, _) => Some(limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)),
+    };
+    let has_more = limit.is_some_and(|limit| items.len() > limit);
+    let mut next_cursor = None;
+    if let Some(limit) = limit {
+        items.truncate(limit);
+        if has_more && let Some(last) = items.last() {
+            let (value, id) = sort_key(last, field);
+            next_cursor = Some(encode_cursor(&Cursor {
+                sort: sort.to_string(),
+                value: value.clone(),
+                id,
+            }));
+        }
+    }
+    Ok(Page {
+        items,
+        limit,
+        has_more,
+        next_cursor,
+    })
+}
+
+/// Comma-separated field names, trimmed; empty names are skipped.
+pub(crate) fn parse_fields(raw: &str) -> Vec<String> {
+    raw.split(',')
+        .map(str::trim)
+        .filter(|field| !field.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Keeps only `fields` of an object; other values pass through.
+pub(crate) fn project(item: JsonValue, fields: &[String]) -> JsonValue {
+    match item {
+        JsonValue::Object(mut map) => JsonValue::Object(
+            fields
+                .iter()
+                .filter_map(|field| Some((field.clone(), map.remove(field)?)))
+                .collect(),
+        ),
+        other => other,
+    }
+}
+
+/// An opaque cursor for a position in a list (hex-encoded JSON, safe in a query string).
+pub(crate) fn encode_cursor<T: Serialize>(position: &T) -> String {
+    serde_json::to_vec(position)
+        .unwrap_or_default()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+pub(crate) fn decode_cursor<T: for<'de> Deserialize<'de>>(raw: &str) -> Option<T> {
+    if !raw.len().is_multiple_of(2) {
+        return None;
+    }
+    let bytes = (0..raw.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(raw.get(i..i + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    serde_json::from_slice(&bytes).ok()
+}
+
+/// Position in a newest-first storage listing (logs, operational events); the opaque
+/// `cursor` of those endpoints, taken in place of `cursor_at` + `cursor_id`.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct TimeCursor {
+    pub at: String,
+    pub id: i64,
+}
+
+/// `cursor_at` and `cursor_id` from an opaque `cursor` when one is given, else as passed.
+pub(crate) fn time_cursor(
+    cursor: Option<&str>,
+    cursor_at: Option<String>,
+    cursor_id: Option<i64>,
+) -> Result<(Option<String>, Option<i64>), ListError> {
+    match cursor.map(str::trim) {
+        None | Some("") => Ok((cursor_at, cursor_id)),
+        Some(raw) => {
+            let cursor: TimeCursor = decode_cursor(raw)
+                .ok_or_else(|| ListError::new("invalid_cursor", "malformed cursor"))?;
+            Ok((Some(cursor.at), Some(cursor.id)))
+        }
+    }
+}
+
+fn sort_key<'a>(item: &'a JsonValue, field: &str) -> (&'a JsonValue, i64) {
+    let id = item
+        .get("id")
+        .and_then(JsonValue::as_i64)
+        .unwrap_or_default();
+    (item.get(field).unwrap_or(&JsonValue::Null), id)
+}
+
+/// Orders JSON values: null, then booleans, numbers, strings and arrays, each by value.
+fn compare(a: &JsonValue, b: &JsonValue) -> Ordering {
+    fn rank(value: &JsonValue) -> u8 {
+        match value {
+            JsonValue::Null => 0,
+            JsonValue::Bool(_) => 1,
+            JsonValue::Number(_) => 2,
+            JsonValue::String(_) => 3,
+            JsonValue::Array(_) => 4,
+            JsonValue::Object(_) => 5,
+        }
+    }
+    match (a, b) {
+        (JsonValue::Bool(a), JsonValue::Bool(b)) => a.cmp(b),
+        (JsonValue::Number(a), JsonValue::Number(b)) => match (a.as_i64(), b.as_i64()) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            _ => a
+                .as_f64()
+                .unwrap_or_default()
+                .total_cmp(&b.as_f64().unwrap_or_default()),
+        },
+        (JsonValue::String(a), JsonValue::String(b)) => a.cmp(b),
+        (JsonValue::Array(a), JsonValue::Array(b)) => a
+            .iter()
+            .zip(b)
+            .map(|(a, b)| compare(a, b))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or_else(|| a.len().cmp(&b.len())),
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
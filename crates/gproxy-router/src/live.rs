@@ -0,0 +1,169 @@
+//! `GET /admin/ws`: one WebSocket carrying what the admin UI would otherwise poll for.
+//! Config changes and credential/model availability transitions are pushed as they
+//! happen, coarse traffic and pool numbers every few seconds.
+
+use std::time::{Duration, SystemTime};
+
+use axum::extract::State;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::response::Response;
+use serde_json::Value as JsonValue;
+use time::OffsetDateTime;
+use tokio::sync::broadcast::error::RecvError;
+
+use gproxy_core::state::StatsDimension;
+use gproxy_provider_core::{Event, OperationalEvent};
+
+use crate::admin::{AdminState, format_time_rfc3339, series_json};
+
+/// How often a `metrics` message is sent; the first goes out on connect.
+const METRICS_INTERVAL: Duration = Duration::from_secs(5);
+/// Trailing window the `metrics` numbers cover.
+const METRICS_WINDOW_MINUTES: u64 = 5;
+
+pub(crate) async fn admin_ws(State(state): State<AdminState>, ws: WebSocketUpgrade) -> Response {
+    ws.on_upgrade(move |socket| push_updates(state, socket))
+}
+
+async fn push_updates(state: AdminState, mut socket: WebSocket) {
+    let mut config = state.app.config_events.subscribe();
+    let mut events = state.app.events.subscribe();
+    let mut ticks = tokio::time::interval(METRICS_INTERVAL);
+    ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        let message = tokio::select! {
+            incoming = socket.recv() => match incoming {
+                // Pings are answered by axum; the UI sends nothing else.
+                Some(Ok(Message::Close(_)) | Err(_)) | None => break,
+                Some(Ok(_)) => continue,
+            },
+            received = config.recv() => match received {
+                Ok(event) => serde_json::json!({ "type": "config", "event": event }),
+                Err(RecvError::Lagged(skipped)) => lagged("config", skipped),
+                Err(RecvError::Closed) => break,
+            },
+            received = events.recv() => match received {
+                Ok(Event::Operational(event)) => pool_message(&state, &event),
+                Ok(_) => continue,
+                Err(RecvError::Lagged(skipped)) => lagged("pool", skipped),
+                Err(RecvError::Closed) => break,
+            },
+            _ = ticks.tick() => metrics_message(&state).await,
+        };
+        if socket
+            .send(Message::Text(message.to_string().into()))
+            .await
+            .is_err()
+        {
+            break;
+        }
+    }
+}
+
+/// Messages of `stream` were missed; the UI should refetch what that stream covers.
+fn lagged(stream: &str, skipped: u64) -> JsonValue {
+    serde_json::json!({ "type": "lagged", "stream": stream, "skipped": skipped })
+}
+
+/// An availability transition (or key auto-disable) with the credential's provider.
+fn pool_message(state: &AdminState, event: &OperationalEvent) -> JsonValue {
+    let (event_type, at, credential_id, model, reason, until) = match event {
+        OperationalEvent::UnavailableStart(v) => (
+            "unavailable_start",
+            v.at,
+            Some(v.credential_id),
+            None,
+            Some(v.reason),
+            Some(v.until),
+        ),
+        OperationalEvent::UnavailableEnd(v) => (
+            "unavailable_end",
+            v.at,
+            Some(v.credential_id),
+            None,
+            None,
+            None,
+        ),
+        OperationalEvent::ModelUnavailableStart(v) => (
+            "model_unavailable_start",
+            v.at,
+            Some(v.credential_id),
+            Some(v.model.as_str()),
+            Some(v.reason),
+            Some(v.until),
+        ),
+        OperationalEvent::ModelUnavailableEnd(v) => (
+            "model_unavailable_end",
+            v.at,
+            Some(v.credential_id),
+            Some(v.model.as_str()),
+            None,
+            None,
+        ),
+        OperationalEvent::UserKeyAutoDisabled(v) => {
+            return serde_json::json!({
+                "type": "pool",
+                "event_type": "user_key_auto_disabled",
+                "at": format_system_time(v.at),
+                "user_id": v.user_id,
+                "user_key_id": v.user_key_id,
+                "rule": v.rule,
+            });
+        }
+    };
+    let snapshot = state.app.snapshot.load();
+    let provider = credential_id
+        .and_then(|id| snapshot.credentials.iter().find(|c| c.id == id))
+        .and_then(|c| snapshot.providers.iter().find(|p| p.id == c.provider_id))
+        .map(|p| p.name.clone());
+    serde_json::json!({
+        "type": "pool",
+        "event_type": event_type,
+        "at": format_system_time(at),
+        "provider": provider,
+        "credential_id": credential_id,
+        "model": model,
+        "reason": reason,
+        "until": until.map(format_system_time),
+    })
+}
+
+/// Totals and per-provider numbers of the trailing window, plus pool availability: the
+/// parts of `/admin/overview` the dashboard refreshes.
+async fn metrics_message(state: &AdminState) -> JsonValue {
+    let stats = &state.app.stats;
+    let window = stats.window(METRICS_WINDOW_MINUTES);
+    let snapshot = state.app.snapshot.load();
+    let runtimes = state.app.providers.load();
+    let mut providers = Vec::with_capacity(snapshot.providers.len());
+    for provider in &snapshot.providers {
+        let availability = match runtimes.get(&provider.name) {
+            Some(runtime) => runtime.pool.availability(&provider.name).await,
+            None => Default::default(),
+        };
+        let mut entry = series_json(
+            &window.get(StatsDimension::Provider, &provider.name),
+            METRICS_WINDOW_MINUTES,
+        );
+        entry["name"] = serde_json::json!(provider.name);
+        entry["enabled"] = serde_json::json!(provider.enabled);
+        entry["credentials"] = serde_json::json!(availability.credentials);
+        entry["credentials_unavailable"] = serde_json::json!(availability.unavailable);
+        entry["model_cooldowns"] = serde_json::json!(availability.model_cooldowns);
+        providers.push(entry);
+    }
+    serde_json::json!({
+        "type": "metrics",
+        "at": format_time_rfc3339(OffsetDateTime::now_utc()),
+        "window_minutes": METRICS_WINDOW_MINUTES,
+        "total": series_json(&window.total(), METRICS_WINDOW_MINUTES),
+        "active_streams": stats.active_streams(),
+        "transcoder": state.app.transcoder.status(),
+        "system_mode": state.app.system_mode.status(),
+        "providers": providers,
+    })
+}
+
+fn format_system_time(at: SystemTime) -> String {
+    format_time_rfc3339(OffsetDateTime::from(at))
+}
@@ -1,35 +1,48 @@
 use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, Instant, SystemTime};
 
 use axum::body::{Body, to_bytes};
-use axum::extract::{DefaultBodyLimit, Extension, Path, Query, RawQuery, State};
+use axum::extract::{ConnectInfo, DefaultBodyLimit, Extension, Path, Query, RawQuery, State};
 use axum::http::{HeaderMap, HeaderName, HeaderValue, StatusCode, header};
 use axum::middleware::{self, Next};
 use axum::response::IntoResponse;
 use axum::response::Response;
-use axum::routing::{get, post};
+use axum::routing::{any, get, post};
 use axum::{Json, Router};
 use bytes::Bytes;
 use futures_util::StreamExt;
 use serde::{Deserialize, Serialize};
 use tokio_stream::wrappers::ReceiverStream;
 
-use gproxy_core::proxy_engine::{ProxyAuth, ProxyCall, ProxyEngine};
+use gproxy_core::proxy_engine::{
+    AuthRequest, ERROR_CODE_HEADER, ProxyAuth, ProxyCall, ProxyEngine,
+};
 use gproxy_protocol::claude;
 use gproxy_protocol::gemini;
 use gproxy_protocol::openai;
 use gproxy_provider_core::{
+    BatchGenerateContentRequest as MwBatchGenerateContentRequest,
+    BatchGetRequest as MwBatchGetRequest, ConversationCreateRequest as MwConversationCreateRequest,
+    ConversationDeleteRequest as MwConversationDeleteRequest,
+    ConversationGetRequest as MwConversationGetRequest,
+    ConversationItemCreateRequest as MwConversationItemCreateRequest,
+    ConversationItemDeleteRequest as MwConversationItemDeleteRequest,
+    ConversationItemGetRequest as MwConversationItemGetRequest,
+    ConversationItemListRequest as MwConversationItemListRequest,
+    ConversationUpdateRequest as MwConversationUpdateRequest,
     CountTokensRequest as MwCountTokensRequest, DownstreamEvent, Event,
-    GenerateContentRequest as MwGenerateContentRequest, Headers,
-    MemoryTraceSummarizeRequest as MwMemoryTraceSummarizeRequest,
+    FileDeleteRequest as MwFileDeleteRequest, FileGetRequest as MwFileGetRequest,
+    FileListRequest as MwFileListRequest, GenerateContentRequest as MwGenerateContentRequest,
+    Headers, HttpMethod, MemoryTraceSummarizeRequest as MwMemoryTraceSummarizeRequest,
     ModelGetRequest as MwModelGetRequest, ModelListRequest as MwModelListRequest,
-    OAuthCallbackRequest, OAuthStartRequest, Op, Proto, Request,
+    OAuthCallbackRequest, OAuthStartRequest, Op, Proto, RawPassthroughRequest, Request,
     ResponseCancelRequest as MwResponseCancelRequest,
     ResponseCompactRequest as MwResponseCompactRequest,
     ResponseDeleteRequest as MwResponseDeleteRequest, ResponseGetRequest as MwResponseGetRequest,
-    ResponseListInputItemsRequest as MwResponseListInputItemsRequest, UpstreamBody,
-    UpstreamHttpResponse,
+    ResponseListInputItemsRequest as MwResponseListInputItemsRequest,
+    ResponseListRequest as MwResponseListRequest, UpstreamBody, UpstreamHttpResponse, header_get,
 };
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -56,7 +69,7 @@ struct ProviderRouteCtx {
 
 const SSE_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
 const SSE_HEARTBEAT_FRAME: &[u8] = b": keep-alive\n\n";
-const MAX_DOWNSTREAM_LOG_BODY_BYTES: usize = 50 * 1024 * 1024;
+const MAX_DOWNSTREAM_REQUEST_BODY_BYTES: usize = 50 * 1024 * 1024;
 
 pub fn proxy_router(engine: Arc<ProxyEngine>) -> Router {
     let state = ProxyState { engine };
@@ -102,7 +115,10 @@ pub fn proxy_router(engine: Arc<ProxyEngine>) -> Router {
             "/{provider}/v1/chat/completions",
             post(openai_chat_completions),
         )
-        .route("/{provider}/v1/responses", post(openai_responses))
+        .route(
+            "/{provider}/v1/responses",
+            post(openai_responses).get(openai_response_list),
+        )
         .route(
             "/{provider}/v1/responses/compact",
             post(openai_response_compact),
@@ -123,6 +139,29 @@ pub fn proxy_router(engine: Arc<ProxyEngine>) -> Router {
             "/{provider}/v1/responses/input_tokens",
             post(openai_input_tokens),
         )
+        .route(
+            "/{provider}/v1/conversations",
+            post(openai_conversation_create),
+        )
+        .route(
+            "/{provider}/v1/conversations/{conversation_id}",
+            get(openai_conversation_get)
+                .post(openai_conversation_update)
+                .delete(openai_conversation_delete),
+        )
+        .route(
+            "/{provider}/v1/conversations/{conversation_id}/items",
+            get(openai_conversation_item_list).post(openai_conversation_item_create),
+        )
+        .route(
+            "/{provider}/v1/conversations/{conversation_id}/items/{item_id}",
+            get(openai_conversation_item_get).delete(openai_conversation_item_delete),
+        )
+        .route("/{provider}/v1/files", get(openai_file_list))
+        .route(
+            "/{provider}/v1/files/{file_id}",
+            get(openai_file_get).delete(openai_file_delete),
+        )
         .route(
             "/{provider}/v1/memories/trace_summarize",
             post(openai_memories_trace_summarize),
@@ -130,25 +169,113 @@ pub fn proxy_router(engine: Arc<ProxyEngine>) -> Router {
         // Shared OpenAI/Claude models endpoints (disambiguate by `anthropic-version` header).
         .route("/{provider}/v1/models", get(models_list_v1))
         .route("/{provider}/v1/models/{*model}", get(models_get_v1))
-        // Gemini v1/v1beta POST endpoints (generateContent/streamGenerateContent/countTokens).
+        // Gemini v1/v1beta POST endpoints (generateContent/streamGenerateContent/countTokens/
+        // batchGenerateContent).
         .route("/{provider}/v1/models/{*model}", post(gemini_post))
         .route("/{provider}/v1beta/models", get(gemini_models_list))
         .route("/{provider}/v1beta/models/{*name}", get(gemini_models_get))
         .route("/{provider}/v1beta/models/{*name}", post(gemini_post))
+        .route("/{provider}/v1beta/batches/{batch}", get(gemini_batch_get))
         // Provider-internal downstream abilities
         .route("/{provider}/oauth", get(oauth_start))
         .route("/{provider}/oauth/callback", get(oauth_callback))
         .route("/{provider}/usage", get(upstream_usage))
-        .layer(DefaultBodyLimit::max(MAX_DOWNSTREAM_LOG_BODY_BYTES))
+        // Anything else under a provider, for providers with `raw_passthrough` enabled.
+        .route("/{provider}/{*path}", any(raw_passthrough))
+        .layer(DefaultBodyLimit::max(MAX_DOWNSTREAM_REQUEST_BODY_BYTES))
         .layer(middleware::from_fn_with_state(state.clone(), proxy_auth))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            maintenance_gate,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            compress_response,
+        ))
         .with_state(state)
 }
 
+/// Maintenance mode: refuse every proxy call before authentication; the admin API is
+/// routed outside this layer and stays up.
+async fn maintenance_gate(
+    State(state): State<ProxyState>,
+    req: axum::http::Request<Body>,
+    next: Next,
+) -> Response {
+    match state.engine.maintenance_response() {
+        Some(resp) => to_axum_response(resp),
+        None => next.run(req).await,
+    }
+}
+
+/// Gzip large buffered JSON responses for clients that accept it. Streams (no exact size)
+/// and already-encoded bodies pass through untouched.
+async fn compress_response(
+    State(state): State<ProxyState>,
+    req: axum::http::Request<Body>,
+    next: Next,
+) -> Response {
+    let min_bytes = state.engine.response_compress_min_bytes();
+    let accepts_gzip = req
+        .headers()
+        .get(header::ACCEPT_ENCODING)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(accepts_gzip_encoding);
+    let resp = next.run(req).await;
+    if min_bytes == 0 || !accepts_gzip || resp.headers().contains_key(header::CONTENT_ENCODING) {
+        return resp;
+    }
+    let is_json = resp
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.to_ascii_lowercase().contains("json"));
+    let size = axum::body::HttpBody::size_hint(resp.body()).exact();
+    if !is_json || size.is_none_or(|size| size < min_bytes) {
+        return resp;
+    }
+
+    let (mut parts, body) = resp.into_parts();
+    let Ok(bytes) = to_bytes(body, usize::MAX).await else {
+        return (StatusCode::BAD_GATEWAY, "failed to read response body").into_response();
+    };
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    let compressed =
+        match std::io::Write::write_all(&mut encoder, &bytes).and_then(|()| encoder.finish()) {
+            Ok(compressed) => compressed,
+            Err(_) => return Response::from_parts(parts, Body::from(bytes)),
+        };
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts
+        .headers
+        .insert(header::CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+    parts
+        .headers
+        .append(header::VARY, HeaderValue::from_static("accept-encoding"));
+    Response::from_parts(parts, Body::from(compressed))
+}
+
+fn accepts_gzip_encoding(value: &str) -> bool {
+    value.split(',').any(|item| {
+        let mut parts = item.split(';');
+        let coding = parts.next().unwrap_or("").trim();
+        let rejected = parts.any(|param| {
+            param
+                .trim()
+                .strip_prefix("q=")
+                .and_then(|q| q.trim().parse::<f32>().ok())
+                .is_some_and(|q| q == 0.0)
+        });
+        !rejected && (coding.eq_ignore_ascii_case("gzip") || coding == "*")
+    })
+}
+
 async fn proxy_auth(
     State(state): State<ProxyState>,
     mut req: axum::http::Request<Body>,
     next: Next,
 ) -> Result<Response, StatusCode> {
+    let received_at = Instant::now();
     let trace_id = uuid::Uuid::now_v7().to_string();
     let trace_id_opt = Some(trace_id.clone());
     let request_method = req.method().as_str().to_string();
@@ -157,8 +284,26 @@ async fn proxy_auth(
     let request_path = req.uri().path().to_string();
     let request_query = maybe_redact_query(req.uri().query(), redact_sensitive);
 
+    let (client_ip, edge_country) = client_origin(&req, |ip| state.engine.is_trusted_proxy(ip));
+    let geo = state.engine.client_geo(client_ip.as_deref(), edge_country);
+
     // Extract before stripping.
     let key = extract_user_key(req.headers(), req.uri().query());
+    let key_source = key
+        .as_ref()
+        .map(|(_, source)| *source)
+        .unwrap_or(DownstreamKeySource::AuthorizationBearer);
+    let auth_request = AuthRequest {
+        api_key: key.map(|(key, _)| key),
+        method: request_method.clone(),
+        path: request_path.clone(),
+        headers: headers_to_vec(req.headers()),
+        client_ip: client_ip.clone(),
+    };
+    let header_tags = req
+        .headers()
+        .get(GPROXY_TAGS_HEADER)
+        .map(|value| value.to_str().map(parse_request_tags).unwrap_or_default());
 
     // Defense-in-depth: don't forward downstream auth material to handlers/providers/logs.
     // Do this for both success/failure to avoid accidental propagation.
@@ -167,7 +312,14 @@ async fn proxy_auth(
     req.extensions_mut()
         .insert(RequestTraceId(trace_id.clone()));
 
-    let Some(key) = key else {
+    let user_agent = req
+        .headers()
+        .get(header::USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty());
+
+    let Some(mut auth) = state.engine.authenticate(&auth_request).await else {
         state
             .engine
             .events()
@@ -182,52 +334,71 @@ async fn proxy_auth(
                 request_query,
                 request_body: None,
                 response_status: Some(StatusCode::UNAUTHORIZED.as_u16()),
-                response_headers: Vec::new(),
+                response_headers: Headers::new(),
                 response_body: None,
+                tags: header_tags.clone().unwrap_or_default(),
+                latency_ms: Some(elapsed_ms(received_at)),
+                client_ip: client_ip.clone(),
+                country: geo.country.clone(),
+                asn: geo.asn,
             }))
             .await;
         return Err(StatusCode::UNAUTHORIZED);
     };
 
-    let user_agent = req
-        .headers()
-        .get(header::USER_AGENT)
-        .and_then(|value| value.to_str().ok())
-        .map(|value| value.trim().to_string())
-        .filter(|value| !value.is_empty());
-
-    let Some(mut auth) = state.engine.authenticate_user_key(&key.0) else {
+    if state
+        .engine
+        .screen_key_abuse(&auth, client_ip.as_deref(), geo.country.as_deref())
+        .await
+    {
         state
             .engine
             .events()
             .emit(Event::Downstream(DownstreamEvent {
                 trace_id: trace_id_opt.clone(),
                 at: SystemTime::now(),
-                user_id: None,
-                user_key_id: None,
+                user_id: Some(auth.user_id),
+                user_key_id: Some(auth.user_key_id),
                 request_method,
                 request_headers,
                 request_path,
                 request_query,
                 request_body: None,
-                response_status: Some(StatusCode::UNAUTHORIZED.as_u16()),
-                response_headers: Vec::new(),
+                response_status: Some(StatusCode::FORBIDDEN.as_u16()),
+                response_headers: Headers::new(),
                 response_body: None,
+                tags: header_tags.clone().unwrap_or_default(),
+                latency_ms: Some(elapsed_ms(received_at)),
+                client_ip: client_ip.clone(),
+                country: geo.country.clone(),
+                asn: geo.asn,
             }))
             .await;
-        return Err(StatusCode::UNAUTHORIZED);
-    };
+        return Err(StatusCode::FORBIDDEN);
+    }
 
     auth.user_agent = user_agent;
-    req.extensions_mut().insert(auth);
-    req.extensions_mut().insert(key.1);
-    let auth = req.extensions().get::<ProxyAuth>().cloned().unwrap();
+    auth.request_headers = headers_to_vec(req.headers());
+    auth.received_at = received_at;
+    // A debug capture keeps this request's bodies whatever the redaction setting says.
+    let captured = state
+        .engine
+        .claim_debug_capture(auth.user_key_id, &trace_id);
+    let keep_bodies = !redact_sensitive || captured;
     let mut request_body: Option<Vec<u8>> = None;
-    if !redact_sensitive {
+    // `metadata.tags` is only read when the client sent no `x-gproxy-tags` header.
+    let read_body_tags = header_tags.is_none() && body_may_carry_tags(req.headers());
+    // Buffer when we need the body for logging or to read `metadata.tags`.
+    if keep_bodies || read_body_tags {
         let (parts, body) = req.into_parts();
-        match to_bytes(body, MAX_DOWNSTREAM_LOG_BODY_BYTES).await {
+        match to_bytes(body, MAX_DOWNSTREAM_REQUEST_BODY_BYTES).await {
             Ok(bytes) => {
-                request_body = Some(bytes.to_vec());
+                if read_body_tags {
+                    auth.tags = body_metadata_tags(&bytes);
+                }
+                if keep_bodies {
+                    request_body = Some(bytes.to_vec());
+                }
                 req = axum::http::Request::from_parts(parts, Body::from(bytes));
             }
             Err(_) => {
@@ -235,12 +406,18 @@ async fn proxy_auth(
             }
         }
     }
+    if let Some(tags) = header_tags {
+        auth.tags = tags;
+    }
+    req.extensions_mut().insert(auth);
+    req.extensions_mut().insert(key_source);
+    let auth = req.extensions().get::<ProxyAuth>().cloned().unwrap();
 
     let resp = next.run(req).await;
     let status = resp.status().as_u16();
     let response_headers = maybe_redact_headers(headers_to_vec(resp.headers()), redact_sensitive);
 
-    if redact_sensitive {
+    if !keep_bodies {
         state
             .engine
             .events()
@@ -257,6 +434,11 @@ async fn proxy_auth(
                 response_status: Some(status),
                 response_headers,
                 response_body: None,
+                tags: auth.tags.clone(),
+                latency_ms: Some(elapsed_ms(received_at)),
+                client_ip: client_ip.clone(),
+                country: geo.country.clone(),
+                asn: geo.asn,
             }))
             .await;
         return Ok(resp);
@@ -265,6 +447,7 @@ async fn proxy_auth(
     let (parts, body) = resp.into_parts();
     let (tx_out, rx_out) = tokio::sync::mpsc::channel::<Bytes>(32);
     let events = state.engine.events();
+    let log_body_cap = state.engine.event_log_max_body_bytes();
 
     tokio::spawn(async move {
         let mut stream = body.into_data_stream();
@@ -274,11 +457,7 @@ async fn proxy_auth(
                 Ok(chunk) => chunk,
                 Err(_) => break,
             };
-            append_capped(
-                &mut response_body,
-                chunk.as_ref(),
-                MAX_DOWNSTREAM_LOG_BODY_BYTES,
-            );
+            append_capped(&mut response_body, chunk.as_ref(), log_body_cap);
             if tx_out.send(chunk).await.is_err() {
                 break;
             }
@@ -298,6 +477,11 @@ async fn proxy_auth(
                 response_status: Some(status),
                 response_headers,
                 response_body: Some(response_body),
+                tags: auth.tags.clone(),
+                latency_ms: Some(elapsed_ms(received_at)),
+                client_ip: client_ip.clone(),
+                country: geo.country.clone(),
+                asn: geo.asn,
             }))
             .await;
     });
@@ -307,6 +491,82 @@ async fn proxy_auth(
     Ok(resp)
 }
 
+pub(crate) const GPROXY_TAGS_HEADER: &str = "x-gproxy-tags";
+const MAX_REQUEST_TAGS: usize = 16;
+const MAX_REQUEST_TAG_LEN: usize = 64;
+
+/// Parse a comma-separated tag list. Tags are trimmed, deduplicated and limited to
+/// `[A-Za-z0-9_.:-]` so they can be stored in a delimited column.
+pub(crate) fn parse_request_tags(raw: &str) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    for tag in raw.split(',') {
+        let tag = tag.trim();
+        if tag.is_empty()
+            || tag.len() > MAX_REQUEST_TAG_LEN
+            || !tag
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | ':' | '-'))
+        {
+            continue;
+        }
+        if out.iter().any(|existing| existing == tag) {
+            continue;
+        }
+        out.push(tag.to_string());
+        if out.len() >= MAX_REQUEST_TAGS {
+            break;
+        }
+    }
+    out
+}
+
+/// Largest request body scanned for `metadata.tags`; bigger bodies are not parsed for tags.
+const MAX_METADATA_TAGS_BODY_BYTES: usize = 1024 * 1024;
+
+/// Whether the body is JSON with a declared length small enough to scan for tags.
+fn body_may_carry_tags(headers: &HeaderMap) -> bool {
+    let is_json = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.to_ascii_lowercase().contains("json"));
+    let length = headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<usize>().ok());
+    is_json && length.is_some_and(|length| length <= MAX_METADATA_TAGS_BODY_BYTES)
+}
+
+#[derive(Deserialize)]
+struct MetadataTagsProbe {
+    metadata: Option<MetadataTags>,
+}
+
+#[derive(Deserialize)]
+struct MetadataTags {
+    tags: Option<serde_json::Value>,
+}
+
+fn body_metadata_tags(body: &[u8]) -> Vec<String> {
+    if body.len() > MAX_METADATA_TAGS_BODY_BYTES {
+        return Vec::new();
+    }
+    let Ok(probe) = serde_json::from_slice::<MetadataTagsProbe>(body) else {
+        return Vec::new();
+    };
+    match probe.metadata.and_then(|metadata| metadata.tags) {
+        Some(serde_json::Value::String(raw)) => parse_request_tags(&raw),
+        Some(serde_json::Value::Array(items)) => {
+            let joined = items
+                .iter()
+                .filter_map(serde_json::Value::as_str)
+                .collect::<Vec<_>>()
+                .join(",");
+            parse_request_tags(&joined)
+        }
+        _ => Vec::new(),
+    }
+}
+
 fn append_capped(buf: &mut Vec<u8>, chunk: &[u8], cap: usize) -> bool {
     if buf.len() >= cap {
         return true;
@@ -317,13 +577,13 @@ fn append_capped(buf: &mut Vec<u8>, chunk: &[u8], cap: usize) -> bool {
     take < chunk.len()
 }
 
-fn strip_downstream_auth_headers(headers: &mut HeaderMap) {
+pub(crate) fn strip_downstream_auth_headers(headers: &mut HeaderMap) {
     headers.remove(header::AUTHORIZATION);
     headers.remove("x-api-key");
     headers.remove("x-goog-api-key");
 }
 
-fn strip_downstream_auth_query(uri: &mut axum::http::Uri) {
+pub(crate) fn strip_downstream_auth_query(uri: &mut axum::http::Uri) {
     let Some(q) = uri.query() else { return };
 
     let Ok(pairs) = serde_urlencoded::from_str::<Vec<(String, String)>>(q) else {
@@ -410,13 +670,13 @@ struct AggregateErrorItem {
 
 async fn claude_messages_aggregate(
     State(state): State<ProxyState>,
-    Extension(auth): Extension<ProxyAuth>,
+    Extension(mut auth): Extension<ProxyAuth>,
     Extension(trace_id): Extension<RequestTraceId>,
     headers: HeaderMap,
     Json(mut body): Json<claude::create_message::request::CreateMessageRequestBody>,
 ) -> Response {
     let model = claude_model_to_string_for_route(&body.model);
-    let Some((provider, model)) = split_provider_model(&model) else {
+    let Some((route, model)) = resolve_aggregate_model(&state.engine, &mut auth, &model) else {
         return (StatusCode::BAD_REQUEST, "missing_provider_prefix").into_response();
     };
     body.model = claude::count_tokens::types::Model::Custom(model);
@@ -435,8 +695,8 @@ async fn claude_messages_aggregate(
     let call = ProxyCall::Protocol {
         trace_id: Some(trace_id.0.clone()),
         auth,
-        provider: provider.clone(),
-        response_model_prefix_provider: Some(provider),
+        provider: route.provider,
+        response_model_prefix_provider: route.response_model_prefix_provider,
         user_proto: Proto::Claude,
         user_op: op,
         req: Box::new(Request::GenerateContent(MwGenerateContentRequest::Claude(
@@ -448,13 +708,13 @@ async fn claude_messages_aggregate(
 
 async fn claude_count_tokens_aggregate(
     State(state): State<ProxyState>,
-    Extension(auth): Extension<ProxyAuth>,
+    Extension(mut auth): Extension<ProxyAuth>,
     Extension(trace_id): Extension<RequestTraceId>,
     headers: HeaderMap,
     Json(mut body): Json<claude::count_tokens::request::CountTokensRequestBody>,
 ) -> Response {
     let model = claude_model_to_string_for_route(&body.model);
-    let Some((provider, model)) = split_provider_model(&model) else {
+    let Some((route, model)) = resolve_aggregate_model(&state.engine, &mut auth, &model) else {
         return (StatusCode::BAD_REQUEST, "missing_provider_prefix").into_response();
     };
     body.model = claude::count_tokens::types::Model::Custom(model);
@@ -467,8 +727,8 @@ async fn claude_count_tokens_aggregate(
     let call = ProxyCall::Protocol {
         trace_id: Some(trace_id.0.clone()),
         auth,
-        provider: provider.clone(),
-        response_model_prefix_provider: Some(provider),
+        provider: route.provider,
+        response_model_prefix_provider: route.response_model_prefix_provider,
         user_proto: Proto::Claude,
         user_op: Op::CountTokens,
         req: Box::new(Request::CountTokens(MwCountTokensRequest::Claude(req))),
@@ -478,11 +738,12 @@ async fn claude_count_tokens_aggregate(
 
 async fn openai_chat_completions_aggregate(
     State(state): State<ProxyState>,
-    Extension(auth): Extension<ProxyAuth>,
+    Extension(mut auth): Extension<ProxyAuth>,
     Extension(trace_id): Extension<RequestTraceId>,
     Json(mut body): Json<openai::create_chat_completions::request::CreateChatCompletionRequestBody>,
 ) -> Response {
-    let Some((provider, model)) = split_provider_model(&body.model) else {
+    let Some((route, model)) = resolve_aggregate_model(&state.engine, &mut auth, &body.model)
+    else {
         return (StatusCode::BAD_REQUEST, "missing_provider_prefix").into_response();
     };
     body.model = model;
@@ -497,8 +758,8 @@ async fn openai_chat_completions_aggregate(
     let call = ProxyCall::Protocol {
         trace_id: Some(trace_id.0.clone()),
         auth,
-        provider: provider.clone(),
-        response_model_prefix_provider: Some(provider),
+        provider: route.provider,
+        response_model_prefix_provider: route.response_model_prefix_provider,
         user_proto: Proto::OpenAIChat,
         user_op: op,
         req: Box::new(Request::GenerateContent(
@@ -510,11 +771,12 @@ async fn openai_chat_completions_aggregate(
 
 async fn openai_responses_aggregate(
     State(state): State<ProxyState>,
-    Extension(auth): Extension<ProxyAuth>,
+    Extension(mut auth): Extension<ProxyAuth>,
     Extension(trace_id): Extension<RequestTraceId>,
     Json(mut body): Json<openai::create_response::request::CreateResponseRequestBody>,
 ) -> Response {
-    let Some((provider, model)) = split_provider_model(&body.model) else {
+    let Some((route, model)) = resolve_aggregate_model(&state.engine, &mut auth, &body.model)
+    else {
         return (StatusCode::BAD_REQUEST, "missing_provider_prefix").into_response();
     };
     body.model = model;
@@ -527,8 +789,8 @@ async fn openai_responses_aggregate(
     let call = ProxyCall::Protocol {
         trace_id: Some(trace_id.0.clone()),
         auth,
-        provider: provider.clone(),
-        response_model_prefix_provider: Some(provider),
+        provider: route.provider,
+        response_model_prefix_provider: route.response_model_prefix_provider,
         user_proto: Proto::OpenAIResponse,
         user_op: op,
         req: Box::new(Request::GenerateContent(
@@ -540,11 +802,12 @@ async fn openai_responses_aggregate(
 
 async fn openai_responses_compact_aggregate(
     State(state): State<ProxyState>,
-    Extension(auth): Extension<ProxyAuth>,
+    Extension(mut auth): Extension<ProxyAuth>,
     Extension(trace_id): Extension<RequestTraceId>,
     Json(mut body): Json<openai::compact_response::request::CompactResponseRequestBody>,
 ) -> Response {
-    let Some((provider, model)) = split_provider_model(&body.model) else {
+    let Some((route, model)) = resolve_aggregate_model(&state.engine, &mut auth, &body.model)
+    else {
         return (StatusCode::BAD_REQUEST, "missing_provider_prefix").into_response();
     };
     body.model = model;
@@ -552,8 +815,8 @@ async fn openai_responses_compact_aggregate(
     let call = ProxyCall::Protocol {
         trace_id: Some(trace_id.0.clone()),
         auth,
-        provider: provider.clone(),
-        response_model_prefix_provider: Some(provider),
+        provider: route.provider,
+        response_model_prefix_provider: route.response_model_prefix_provider,
         user_proto: Proto::OpenAI,
         user_op: Op::ResponseCompact,
         req: Box::new(Request::ResponseCompact(MwResponseCompactRequest::OpenAI(
@@ -565,11 +828,12 @@ async fn openai_responses_compact_aggregate(
 
 async fn openai_memories_trace_summarize_aggregate(
     State(state): State<ProxyState>,
-    Extension(auth): Extension<ProxyAuth>,
+    Extension(mut auth): Extension<ProxyAuth>,
     Extension(trace_id): Extension<RequestTraceId>,
     Json(mut body): Json<openai::trace_summarize::request::TraceSummarizeRequestBody>,
 ) -> Response {
-    let Some((provider, model)) = split_provider_model(&body.model) else {
+    let Some((route, model)) = resolve_aggregate_model(&state.engine, &mut auth, &body.model)
+    else {
         return (StatusCode::BAD_REQUEST, "missing_provider_prefix").into_response();
     };
     body.model = model;
@@ -577,8 +841,8 @@ async fn openai_memories_trace_summarize_aggregate(
     let call = ProxyCall::Protocol {
         trace_id: Some(trace_id.0.clone()),
         auth,
-        provider: provider.clone(),
-        response_model_prefix_provider: Some(provider),
+        provider: route.provider,
+        response_model_prefix_provider: route.response_model_prefix_provider,
         user_proto: Proto::OpenAI,
         user_op: Op::MemoryTraceSummarize,
         req: Box::new(Request::MemoryTraceSummarize(
@@ -590,11 +854,12 @@ async fn openai_memories_trace_summarize_aggregate(
 
 async fn openai_input_tokens_aggregate(
     State(state): State<ProxyState>,
-    Extension(auth): Extension<ProxyAuth>,
+    Extension(mut auth): Extension<ProxyAuth>,
     Extension(trace_id): Extension<RequestTraceId>,
     Json(mut body): Json<openai::count_tokens::request::InputTokenCountRequestBody>,
 ) -> Response {
-    let Some((provider, model)) = split_provider_model(&body.model) else {
+    let Some((route, model)) = resolve_aggregate_model(&state.engine, &mut auth, &body.model)
+    else {
         return (StatusCode::BAD_REQUEST, "missing_provider_prefix").into_response();
     };
     body.model = model;
@@ -602,8 +867,8 @@ async fn openai_input_tokens_aggregate(
     let call = ProxyCall::Protocol {
         trace_id: Some(trace_id.0.clone()),
         auth,
-        provider: provider.clone(),
-        response_model_prefix_provider: Some(provider),
+        provider: route.provider,
+        response_model_prefix_provider: route.response_model_prefix_provider,
         user_proto: Proto::OpenAI,
         user_op: Op::CountTokens,
         req: Box::new(Request::CountTokens(MwCountTokensRequest::OpenAI(req))),
@@ -786,28 +1051,16 @@ async fn models_list_v1_aggregate(
 
 async fn models_get_v1_aggregate(
     State(state): State<ProxyState>,
-    Extension(auth): Extension<ProxyAuth>,
+    Extension(mut auth): Extension<ProxyAuth>,
     Extension(trace_id): Extension<RequestTraceId>,
     Extension(key_source): Extension<DownstreamKeySource>,
     Path(model): Path<String>,
     headers: HeaderMap,
 ) -> Response {
-    let Some((provider, model)) = split_provider_model(&model) else {
+    let Some((route, model)) = resolve_aggregate_model(&state.engine, &mut auth, &model) else {
         return (StatusCode::BAD_REQUEST, "missing_provider_prefix").into_response();
     };
-    models_get_v1_inner(
-        state,
-        auth,
-        key_source,
-        ProviderRouteCtx {
-            provider: provider.clone(),
-            response_model_prefix_provider: Some(provider),
-        },
-        model,
-        trace_id.0,
-        headers,
-    )
-    .await
+    models_get_v1_inner(state, auth, key_source, route, model, trace_id.0, headers).await
 }
 
 async fn gemini_models_list_aggregate(
@@ -887,11 +1140,11 @@ async fn gemini_models_list_aggregate(
 
 async fn gemini_models_get_aggregate(
     State(state): State<ProxyState>,
-    Extension(auth): Extension<ProxyAuth>,
+    Extension(mut auth): Extension<ProxyAuth>,
     Extension(trace_id): Extension<RequestTraceId>,
     Path(name): Path<String>,
 ) -> Response {
-    let Some((provider, name)) = split_provider_model(&name) else {
+    let Some((route, name)) = resolve_aggregate_model(&state.engine, &mut auth, &name) else {
         return (StatusCode::BAD_REQUEST, "missing_provider_prefix").into_response();
     };
     let req = gemini::get_model::request::GetModelRequest {
@@ -902,8 +1155,8 @@ async fn gemini_models_get_aggregate(
     let call = ProxyCall::Protocol {
         trace_id: Some(trace_id.0.clone()),
         auth,
-        provider: provider.clone(),
-        response_model_prefix_provider: Some(provider),
+        provider: route.provider,
+        response_model_prefix_provider: route.response_model_prefix_provider,
         user_proto: Proto::Gemini,
         user_op: Op::ModelGet,
         req: Box::new(Request::ModelGet(MwModelGetRequest::Gemini(req))),
@@ -913,22 +1166,21 @@ async fn gemini_models_get_aggregate(
 
 async fn gemini_post_aggregate(
     State(state): State<ProxyState>,
-    Extension(auth): Extension<ProxyAuth>,
+    Extension(mut auth): Extension<ProxyAuth>,
     Extension(trace_id): Extension<RequestTraceId>,
     Path(model_action): Path<String>,
     RawQuery(query): RawQuery,
     body: Bytes,
 ) -> Response {
-    let Some((provider, model, action)) = split_provider_model_action(&model_action) else {
+    let Some((route, model, action)) =
+        resolve_aggregate_model_action(&state.engine, &mut auth, &model_action)
+    else {
         return (StatusCode::BAD_REQUEST, "missing_provider_prefix").into_response();
     };
     gemini_post_impl(
         state,
         auth,
-        ProviderRouteCtx {
-            provider: provider.clone(),
-            response_model_prefix_provider: Some(provider),
-        },
+        route,
         format!("{model}:{action}"),
         trace_id.0,
         query,
@@ -937,27 +1189,77 @@ async fn gemini_post_aggregate(
     .await
 }
 
-fn split_provider_model(input: &str) -> Option<(String, String)> {
+/// Provider and model for an aggregate route. An explicit `provider/model` wins;
+/// otherwise the key's default provider applies (with its default model when the
+/// request names none) and responses keep the model name the client sent.
+fn resolve_aggregate_model(
+    engine: &ProxyEngine,
+    auth: &mut ProxyAuth,
+    input: &str,
+) -> Option<(ProviderRouteCtx, String)> {
+    if let Some((provider, model)) = split_provider_model(input) {
+        let route = ProviderRouteCtx {
+            provider: provider.clone(),
+            response_model_prefix_provider: Some(provider),
+        };
+        return Some((route, model));
+    }
     let raw = input.trim().trim_start_matches('/');
-    let raw = raw.strip_prefix("models/").unwrap_or(raw);
-    let (provider, model) = raw.split_once('/')?;
-    let provider = provider.trim();
-    let model = model.trim();
-    if provider.is_empty() || model.is_empty() {
-        return None;
+    let raw = raw.strip_prefix("models/").unwrap_or(raw).trim();
+    // Experiments pick an arm per request and record it on `auth` for usage rows.
+    if let Some((provider, model)) = engine.assign_experiment(auth, raw) {
+        let route = ProviderRouteCtx {
+            provider,
+            response_model_prefix_provider: None,
+        };
+        return Some((route, model));
     }
-    Some((provider.to_string(), model.to_string()))
+    // Model profiles answer under their own name; the engine swaps in the real model.
+    if let Some(provider) = engine.model_profile_provider(raw) {
+        let route = ProviderRouteCtx {
+            provider,
+            response_model_prefix_provider: None,
+        };
+        return Some((route, raw.to_string()));
+    }
+    let provider = auth.default_provider.clone()?;
+    let model = if raw.is_empty() {
+        auth.default_model.clone()?
+    } else {
+        raw.to_string()
+    };
+    let route = ProviderRouteCtx {
+        provider,
+        response_model_prefix_provider: None,
+    };
+    Some((route, model))
 }
 
-fn split_provider_model_action(input: &str) -> Option<(String, String, String)> {
+fn resolve_aggregate_model_action(
+    engine: &ProxyEngine,
+    auth: &mut ProxyAuth,
+    input: &str,
+) -> Option<(ProviderRouteCtx, String, String)> {
     let raw = input.trim().trim_start_matches('/');
     let (model, action) = raw.split_once(':')?;
-    let (provider, model) = split_provider_model(model)?;
+    let (route, model) = resolve_aggregate_model(engine, auth, model)?;
     let action = action.trim();
     if action.is_empty() {
         return None;
     }
-    Some((provider, model, action.to_string()))
+    Some((route, model, action.to_string()))
+}
+
+fn split_provider_model(input: &str) -> Option<(String, String)> {
+    let raw = input.trim().trim_start_matches('/');
+    let raw = raw.strip_prefix("models/").unwrap_or(raw);
+    let (provider, model) = raw.split_once('/')?;
+    let provider = provider.trim();
+    let model = model.trim();
+    if provider.is_empty() || model.is_empty() {
+        return None;
+    }
+    Some((provider.to_string(), model.to_string()))
 }
 
 fn claude_model_to_string_for_route(model: &claude::count_tokens::types::Model) -> String {
@@ -978,17 +1280,23 @@ fn response_body_bytes(body: &UpstreamBody) -> Option<Bytes> {
 }
 
 fn parse_upstream_error(resp: &UpstreamHttpResponse) -> (String, serde_json::Value) {
+    // Engine errors carry their stable code in a header, independent of body shape.
+    let header_code = header_get(&resp.headers, ERROR_CODE_HEADER).map(str::to_string);
     let Some(bytes) = response_body_bytes(&resp.body) else {
-        return ("upstream_error".to_string(), serde_json::Value::Null);
+        let error = header_code.unwrap_or_else(|| "upstream_error".to_string());
+        return (error, serde_json::Value::Null);
     };
     let Ok(value) = serde_json::from_slice::<serde_json::Value>(&bytes) else {
-        return ("upstream_error".to_string(), serde_json::Value::Null);
-    };
-    let error = value
-        .get("error")
-        .and_then(|v| v.as_str())
-        .unwrap_or("upstream_error")
-        .to_string();
+        let error = header_code.unwrap_or_else(|| "upstream_error".to_string());
+        return (error, serde_json::Value::Null);
+    };
+    let error = header_code.unwrap_or_else(|| {
+        value
+            .get("error")
+            .and_then(|v| v.as_str())
+            .unwrap_or("upstream_error")
+            .to_string()
+    });
     let detail = value
         .get("detail")
         .cloned()
@@ -1066,6 +1374,45 @@ struct UpstreamUsageQuery {
     credential_id: i64,
 }
 
+#[allow(clippy::too_many_arguments)]
+async fn raw_passthrough(
+    State(state): State<ProxyState>,
+    Extension(auth): Extension<ProxyAuth>,
+    Extension(trace_id): Extension<RequestTraceId>,
+    Path((provider, path)): Path<(String, String)>,
+    method: axum::http::Method,
+    RawQuery(query): RawQuery,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let Some(method) = HttpMethod::parse(method.as_str()) else {
+        return StatusCode::METHOD_NOT_ALLOWED.into_response();
+    };
+    // Auth was stripped by `proxy_auth`; the upstream connection sets its own framing
+    // and content coding.
+    let headers = headers_to_vec(&headers)
+        .into_iter()
+        .filter(|(name, _)| {
+            !is_hop_by_hop_or_framing_header(name)
+                && !name.eq_ignore_ascii_case("host")
+                && !name.eq_ignore_ascii_case("accept-encoding")
+        })
+        .collect();
+    let call = ProxyCall::RawPassthrough {
+        trace_id: Some(trace_id.0.clone()),
+        auth,
+        provider,
+        req: Box::new(RawPassthroughRequest {
+            method,
+            path: format!("/{path}"),
+            query,
+            headers,
+            body: (!body.is_empty()).then_some(body),
+        }),
+    };
+    to_axum_response(state.engine.handle(call).await)
+}
+
 // ---- Claude ----
 
 async fn claude_messages(
@@ -1475,6 +1822,267 @@ async fn openai_response_list_input_items(
     to_axum_response(state.engine.handle(call).await)
 }
 
+/// Calls on stored OpenAI resources (response list, conversations, files): no model,
+/// no transform, OpenAI shape in and out.
+fn openai_resource_call(
+    trace_id: RequestTraceId,
+    auth: ProxyAuth,
+    provider: String,
+    user_op: Op,
+    req: Request,
+) -> ProxyCall {
+    ProxyCall::Protocol {
+        trace_id: Some(trace_id.0),
+        auth,
+        provider,
+        response_model_prefix_provider: None,
+        user_proto: Proto::OpenAI,
+        user_op,
+        req: Box::new(req),
+    }
+}
+
+async fn openai_response_list(
+    State(state): State<ProxyState>,
+    Extension(auth): Extension<ProxyAuth>,
+    Extension(trace_id): Extension<RequestTraceId>,
+    Path(provider): Path<String>,
+    Query(query): Query<openai::list_responses::request::ListResponsesQuery>,
+) -> Response {
+    let req = openai::list_responses::request::ListResponsesRequest { query };
+    let call = openai_resource_call(
+        trace_id,
+        auth,
+        provider,
+        Op::ResponseList,
+        Request::ResponseList(MwResponseListRequest::OpenAI(req)),
+    );
+    to_axum_response(state.engine.handle(call).await)
+}
+
+async fn openai_conversation_create(
+    State(state): State<ProxyState>,
+    Extension(auth): Extension<ProxyAuth>,
+    Extension(trace_id): Extension<RequestTraceId>,
+    Path(provider): Path<String>,
+    Json(body): Json<openai::conversations::request::CreateConversationBody>,
+) -> Response {
+    let req = openai::conversations::request::CreateConversationRequest { body };
+    let call = openai_resource_call(
+        trace_id,
+        auth,
+        provider,
+        Op::ConversationCreate,
+        Request::ConversationCreate(MwConversationCreateRequest::OpenAI(req)),
+    );
+    to_axum_response(state.engine.handle(call).await)
+}
+
+async fn openai_conversation_get(
+    State(state): State<ProxyState>,
+    Extension(auth): Extension<ProxyAuth>,
+    Extension(trace_id): Extension<RequestTraceId>,
+    Path((provider, conversation_id)): Path<(String, String)>,
+) -> Response {
+    let req = openai::conversations::request::GetConversationRequest {
+        path: openai::conversations::request::ConversationPath { conversation_id },
+    };
+    let call = openai_resource_call(
+        trace_id,
+        auth,
+        provider,
+        Op::ConversationGet,
+        Request::ConversationGet(MwConversationGetRequest::OpenAI(req)),
+    );
+    to_axum_response(state.engine.handle(call).await)
+}
+
+async fn openai_conversation_update(
+    State(state): State<ProxyState>,
+    Extension(auth): Extension<ProxyAuth>,
+    Extension(trace_id): Extension<RequestTraceId>,
+    Path((provider, conversation_id)): Path<(String, String)>,
+    Json(body): Json<openai::conversations::request::UpdateConversationBody>,
+) -> Response {
+    let req = openai::conversations::request::UpdateConversationRequest {
+        path: openai::conversations::request::ConversationPath { conversation_id },
+        body,
+    };
+    let call = openai_resource_call(
+        trace_id,
+        auth,
+        provider,
+        Op::ConversationUpdate,
+        Request::ConversationUpdate(MwConversationUpdateRequest::OpenAI(req)),
+    );
+    to_axum_response(state.engine.handle(call).await)
+}
+
+async fn openai_conversation_delete(
+    State(state): State<ProxyState>,
+    Extension(auth): Extension<ProxyAuth>,
+    Extension(trace_id): Extension<RequestTraceId>,
+    Path((provider, conversation_id)): Path<(String, String)>,
+) -> Response {
+    let req = openai::conversations::request::DeleteConversationRequest {
+        path: openai::conversations::request::ConversationPath { conversation_id },
+    };
+    let call = openai_resource_call(
+        trace_id,
+        auth,
+        provider,
+        Op::ConversationDelete,
+        Request::ConversationDelete(MwConversationDeleteRequest::OpenAI(req)),
+    );
+    to_axum_response(state.engine.handle(call).await)
+}
+
+async fn openai_conversation_item_list(
+    State(state): State<ProxyState>,
+    Extension(auth): Extension<ProxyAuth>,
+    Extension(trace_id): Extension<RequestTraceId>,
+    Path((provider, conversation_id)): Path<(String, String)>,
+    Query(query): Query<openai::conversations::request::ListConversationItemsQuery>,
+) -> Response {
+    let req = openai::conversations::request::ListConversationItemsRequest {
+        path: openai::conversations::request::ConversationPath { conversation_id },
+        query,
+    };
+    let call = openai_resource_call(
+        trace_id,
+        auth,
+        provider,
+        Op::ConversationItemList,
+        Request::ConversationItemList(MwConversationItemListRequest::OpenAI(req)),
+    );
+    to_axum_response(state.engine.handle(call).await)
+}
+
+async fn openai_conversation_item_create(
+    State(state): State<ProxyState>,
+    Extension(auth): Extension<ProxyAuth>,
+    Extension(trace_id): Extension<RequestTraceId>,
+    Path((provider, conversation_id)): Path<(String, String)>,
+    Query(query): Query<openai::conversations::request::ConversationItemQuery>,
+    Json(body): Json<openai::conversations::request::CreateConversationItemsBody>,
+) -> Response {
+    let req = openai::conversations::request::CreateConversationItemsRequest {
+        path: openai::conversations::request::ConversationPath { conversation_id },
+        query,
+        body,
+    };
+    let call = openai_resource_call(
+        trace_id,
+        auth,
+        provider,
+        Op::ConversationItemCreate,
+        Request::ConversationItemCreate(MwConversationItemCreateRequest::OpenAI(req)),
+    );
+    to_axum_response(state.engine.handle(call).await)
+}
+
+async fn openai_conversation_item_get(
+    State(state): State<ProxyState>,
+    Extension(auth): Extension<ProxyAuth>,
+    Extension(trace_id): Extension<RequestTraceId>,
+    Path((provider, conversation_id, item_id)): Path<(String, String, String)>,
+    Query(query): Query<openai::conversations::request::ConversationItemQuery>,
+) -> Response {
+    let req = openai::conversations::request::GetConversationItemRequest {
+        path: openai::conversations::request::ConversationItemPath {
+            conversation_id,
+            item_id,
+        },
+        query,
+    };
+    let call = openai_resource_call(
+        trace_id,
+        auth,
+        provider,
+        Op::ConversationItemGet,
+        Request::ConversationItemGet(MwConversationItemGetRequest::OpenAI(req)),
+    );
+    to_axum_response(state.engine.handle(call).await)
+}
+
+async fn openai_conversation_item_delete(
+    State(state): State<ProxyState>,
+    Extension(auth): Extension<ProxyAuth>,
+    Extension(trace_id): Extension<RequestTraceId>,
+    Path((provider, conversation_id, item_id)): Path<(String, String, String)>,
+) -> Response {
+    let req = openai::conversations::request::DeleteConversationItemRequest {
+        path: openai::conversations::request::ConversationItemPath {
+            conversation_id,
+            item_id,
+        },
+    };
+    let call = openai_resource_call(
+        trace_id,
+        auth,
+        provider,
+        Op::ConversationItemDelete,
+        Request::ConversationItemDelete(MwConversationItemDeleteRequest::OpenAI(req)),
+    );
+    to_axum_response(state.engine.handle(call).await)
+}
+
+async fn openai_file_list(
+    State(state): State<ProxyState>,
+    Extension(auth): Extension<ProxyAuth>,
+    Extension(trace_id): Extension<RequestTraceId>,
+    Path(provider): Path<String>,
+    Query(query): Query<openai::files::request::ListFilesQuery>,
+) -> Response {
+    let req = openai::files::request::ListFilesRequest { query };
+    let call = openai_resource_call(
+        trace_id,
+        auth,
+        provider,
+        Op::FileList,
+        Request::FileList(MwFileListRequest::OpenAI(req)),
+    );
+    to_axum_response(state.engine.handle(call).await)
+}
+
+async fn openai_file_get(
+    State(state): State<ProxyState>,
+    Extension(auth): Extension<ProxyAuth>,
+    Extension(trace_id): Extension<RequestTraceId>,
+    Path((provider, file_id)): Path<(String, String)>,
+) -> Response {
+    let req = openai::files::request::GetFileRequest {
+        path: openai::files::request::FilePath { file_id },
+    };
+    let call = openai_resource_call(
+        trace_id,
+        auth,
+        provider,
+        Op::FileGet,
+        Request::FileGet(MwFileGetRequest::OpenAI(req)),
+    );
+    to_axum_response(state.engine.handle(call).await)
+}
+
+async fn openai_file_delete(
+    State(state): State<ProxyState>,
+    Extension(auth): Extension<ProxyAuth>,
+    Extension(trace_id): Extension<RequestTraceId>,
+    Path((provider, file_id)): Path<(String, String)>,
+) -> Response {
+    let req = openai::files::request::DeleteFileRequest {
+        path: openai::files::request::FilePath { file_id },
+    };
+    let call = openai_resource_call(
+        trace_id,
+        auth,
+        provider,
+        Op::FileDelete,
+        Request::FileDelete(MwFileDeleteRequest::OpenAI(req)),
+    );
+    to_axum_response(state.engine.handle(call).await)
+}
+
 async fn openai_memories_trace_summarize(
     State(state): State<ProxyState>,
     Extension(auth): Extension<ProxyAuth>,
@@ -1563,6 +2171,29 @@ async fn gemini_models_get(
     to_axum_response(state.engine.handle(call).await)
 }
 
+async fn gemini_batch_get(
+    State(state): State<ProxyState>,
+    Extension(auth): Extension<ProxyAuth>,
+    Extension(trace_id): Extension<RequestTraceId>,
+    Path((provider, batch)): Path<(String, String)>,
+) -> Response {
+    let req = gemini::get_batch::request::GetBatchRequest {
+        path: gemini::get_batch::request::GetBatchPath {
+            name: format!("batches/{batch}"),
+        },
+    };
+    let call = ProxyCall::Protocol {
+        trace_id: Some(trace_id.0.clone()),
+        auth,
+        provider,
+        response_model_prefix_provider: None,
+        user_proto: Proto::Gemini,
+        user_op: Op::BatchGet,
+        req: Box::new(Request::BatchGet(MwBatchGetRequest::Gemini(req))),
+    };
+    to_axum_response(state.engine.handle(call).await)
+}
+
 async fn gemini_post(
     State(state): State<ProxyState>,
     Extension(auth): Extension<ProxyAuth>,
@@ -1687,6 +2318,33 @@ async fn gemini_post_impl(
             };
             to_axum_response(state.engine.handle(call).await)
         }
+        "batchGenerateContent" => {
+            let body: gemini::batch_generate_content::request::BatchGenerateContentRequestBody =
+                match serde_json::from_slice(&body) {
+                    Ok(v) => v,
+                    Err(_) => {
+                        return (StatusCode::BAD_REQUEST, "bad_gemini_body").into_response();
+                    }
+                };
+            let req = gemini::batch_generate_content::request::BatchGenerateContentRequest {
+                path: gemini::batch_generate_content::request::BatchGenerateContentPath {
+                    model: format!("models/{model}"),
+                },
+                body,
+            };
+            let call = ProxyCall::Protocol {
+                trace_id: Some(trace_id),
+                auth,
+                provider,
+                response_model_prefix_provider,
+                user_proto: Proto::Gemini,
+                user_op: Op::BatchGenerateContent,
+                req: Box::new(Request::BatchGenerateContent(
+                    MwBatchGenerateContentRequest::Gemini(req),
+                )),
+            };
+            to_axum_response(state.engine.handle(call).await)
+        }
         _ => (StatusCode::NOT_FOUND, "unknown_gemini_action").into_response(),
     }
 }
@@ -1745,7 +2403,7 @@ fn has_sse_content_type(headers: &Headers) -> bool {
         .unwrap_or(false)
 }
 
-fn wrap_sse_stream_with_heartbeat(
+pub(crate) fn wrap_sse_stream_with_heartbeat(
     mut upstream_rx: tokio::sync::mpsc::Receiver<Bytes>,
 ) -> tokio::sync::mpsc::Receiver<Bytes> {
     let (tx, rx) = tokio::sync::mpsc::channel::<Bytes>(32);
@@ -1788,11 +2446,76 @@ fn is_hop_by_hop_or_framing_header(name: &str) -> bool {
         || name.eq_ignore_ascii_case("upgrade")
 }
 
+fn elapsed_ms(since: Instant) -> u64 {
+    u64::try_from(since.elapsed().as_millis()).unwrap_or(u64::MAX)
+}
+
+/// Edge/CDN headers carrying the client's ISO country code.
+const CLIENT_COUNTRY_HEADERS: [&str; 3] = [
+    "cf-ipcountry",
+    "cloudfront-viewer-country",
+    "x-vercel-ip-country",
+];
+
+/// Client address and the country an edge reported for it. Forwarding and edge headers
+/// only count when the socket peer is a trusted proxy; otherwise the peer is the client.
+fn client_origin(
+    req: &axum::http::Request<Body>,
+    is_trusted: impl Fn(IpAddr) -> bool,
+) -> (Option<String>, Option<String>) {
+    let peer = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|info| info.0.ip());
+    match peer {
+        Some(peer) if is_trusted(peer) => (
+            Some(forwarded_client_ip(req.headers(), is_trusted).unwrap_or(peer))
+                .map(|ip| ip.to_string()),
+            client_country(req.headers()),
+        ),
+        peer => (peer.map(|ip| ip.to_string()), None),
+    }
+}
+
+/// The rightmost `x-forwarded-for` hop that is not a trusted proxy itself (so hops a client
+/// prepends are ignored), then `x-real-ip`.
+fn forwarded_client_ip(headers: &HeaderMap, is_trusted: impl Fn(IpAddr) -> bool) -> Option<IpAddr> {
+    let hops: Vec<IpAddr> = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|hop| hop.trim().parse().ok())
+        .collect();
+    hops.iter()
+        .rev()
+        .find(|hop| !is_trusted(**hop))
+        .or(hops.first())
+        .copied()
+        .or_else(|| {
+            headers
+                .get("x-real-ip")
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse().ok())
+        })
+}
+
+fn client_country(headers: &HeaderMap) -> Option<String> {
+    CLIENT_COUNTRY_HEADERS.iter().find_map(|name| {
+        headers
+            .get(*name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+    })
+}
+
 fn headers_to_vec(headers: &HeaderMap) -> Headers {
-    let mut out: Headers = Vec::new();
+    let mut out = Headers::with_capacity(headers.len());
     for (name, value) in headers {
         if let Ok(v) = value.to_str() {
-            out.push((name.as_str().to_string(), v.to_string()));
+            out.append(name.as_str(), v);
         }
     }
     out
@@ -1802,7 +2525,7 @@ fn maybe_redact_headers(mut headers: Headers, redact: bool) -> Headers {
     if !redact {
         return headers;
     }
-    for (k, v) in &mut headers {
+    for (k, v) in headers.iter_mut() {
         let key = k.to_ascii_lowercase();
         if matches!(
             key.as_str(),
@@ -1878,3 +2601,130 @@ fn parse_anthropic_headers(headers: &HeaderMap) -> claude::types::AnthropicHeade
 
     serde_json::from_value(serde_json::Value::Object(map)).unwrap_or_default()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn request_tags_are_trimmed_validated_and_deduplicated() {
+        assert_eq!(
+            parse_request_tags(" team-a, b.c:d ,team-a,,e_f"),
+            vec!["team-a", "b.c:d", "e_f"]
+        );
+        assert!(parse_request_tags("a b,x%y,ü,'q'").is_empty());
+        assert!(parse_request_tags(&"a".repeat(MAX_REQUEST_TAG_LEN + 1)).is_empty());
+        assert_eq!(
+            parse_request_tags(&"a".repeat(MAX_REQUEST_TAG_LEN)),
+            vec!["a".repeat(MAX_REQUEST_TAG_LEN)]
+        );
+        let many = (0..40)
+            .map(|i| format!("t{i}"))
+            .collect::<Vec<_>>()
+            .join(",");
+        assert_eq!(parse_request_tags(&many).len(), MAX_REQUEST_TAGS);
+    }
+
+    #[test]
+    fn metadata_tags_accept_a_list_or_a_string() {
+        assert_eq!(
+            body_metadata_tags(br#"{"model":"m","metadata":{"tags":["a","b c",1,"d"]}}"#),
+            vec!["a", "d"]
+        );
+        assert_eq!(
+            body_metadata_tags(br#"{"metadata":{"tags":"a, b","user_id":"u"}}"#),
+            vec!["a", "b"]
+        );
+        for body in [
+            &br#"{"metadata":{"tags":{"a":1}}}"#[..],
+            br#"{"metadata":"tags"}"#,
+            br#"{"metadata":null}"#,
+            br#"{"tags":["a"]}"#,
+            b"not json",
+        ] {
+            assert!(body_metadata_tags(body).is_empty());
+        }
+
+        let padding = "x".repeat(MAX_METADATA_TAGS_BODY_BYTES);
+        let large = format!(r#"{{"metadata":{{"tags":["a"]}},"pad":"{padding}"}}"#);
+        assert!(body_metadata_tags(large.as_bytes()).is_empty());
+    }
+
+    #[test]
+    fn forwarding_headers_count_only_from_trusted_proxies() {
+        let request = |peer: &str, headers: &[(&'static str, &str)]| {
+            let mut req = axum::http::Request::new(Body::empty());
+            for (name, value) in headers {
+                req.headers_mut().append(*name, value.parse().unwrap());
+            }
+            let peer = SocketAddr::new(peer.parse().unwrap(), 40000);
+            req.extensions_mut().insert(ConnectInfo(peer));
+            req
+        };
+        let trusted = |ip: IpAddr| ip.to_string().starts_with("10.");
+        let origin = |req| {
+            let (ip, country) = client_origin(&req, trusted);
+            (ip.unwrap_or_default(), country)
+        };
+        let spoofed = [
+            ("x-forwarded-for", "6.6.6.6"),
+            ("x-real-ip", "7.7.7.7"),
+            ("cf-ipcountry", "KP"),
+        ];
+
+        // A client talking to us directly cannot pick its own address or country.
+        assert_eq!(
+            origin(request("5.5.5.5", &spoofed)),
+            ("5.5.5.5".into(), None)
+        );
+
+        // Behind a trusted proxy the rightmost hop that is not a proxy is the client, so
+        // a hop the client prepended does not win either.
+        assert_eq!(
+            origin(request(
+                "10.0.0.1",
+                &[
+                    ("x-forwarded-for", "6.6.6.6, 1.2.3.4"),
+                    ("x-forwarded-for", "10.0.0.2"),
+                    ("cf-ipcountry", "DE"),
+                ],
+            )),
+            ("1.2.3.4".into(), Some("DE".into()))
+        );
+        assert_eq!(
+            origin(request("10.0.0.1", &[("x-forwarded-for", "10.0.0.3")])),
+            ("10.0.0.3".into(), None)
+        );
+        assert_eq!(
+            origin(request("10.0.0.1", &spoofed[1..])),
+            ("7.7.7.7".into(), Some("KP".into()))
+        );
+        assert_eq!(origin(request("10.0.0.1", &[])), ("10.0.0.1".into(), None));
+    }
+
+    #[test]
+    fn only_small_json_bodies_are_scanned_for_tags() {
+        let headers = |content_type: &str, length: Option<usize>| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::CONTENT_TYPE, content_type.parse().unwrap());
+            if let Some(length) = length {
+                headers.insert(header::CONTENT_LENGTH, length.into());
+            }
+            headers
+        };
+        assert!(body_may_carry_tags(&headers("application/json", Some(64))));
+        assert!(body_may_carry_tags(&headers(
+            "application/json; charset=utf-8",
+            Some(MAX_METADATA_TAGS_BODY_BYTES)
+        )));
+        assert!(!body_may_carry_tags(&headers(
+            "application/json",
+            Some(MAX_METADATA_TAGS_BODY_BYTES + 1)
+        )));
+        assert!(!body_may_carry_tags(&headers("application/json", None)));
+        assert!(!body_may_carry_tags(&headers(
+            "multipart/form-data",
+            Some(64)
+        )));
+    }
+}
@@ -0,0 +1,28 @@
+use sea_orm::entity::prelude::*;
+use time::OffsetDateTime;
+
+/// Generate streams that ended early (upstream error, idle timeout, client gone), with
+/// what they produced until then.
+#[sea_orm::model]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "aborted_streams")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub trace_id: Option<String>,
+    pub user_key_id: i64,
+    pub provider: String,
+    pub credential_id: Option<i64>,
+    pub model: Option<String>,
+    /// `error_kind` of the stream's upstream event, e.g. `stream_forward_error`.
+    pub reason: String,
+    /// Characters of output text streamed before the abort.
+    pub output_chars: i64,
+    pub input_tokens: Option<i64>,
+    /// Reported by upstream when it got that far, else estimated from `output_chars`.
+    pub output_tokens: i64,
+    pub duration_ms: i64,
+    pub at: OffsetDateTime,
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,20 @@
+use sea_orm::entity::prelude::*;
+use time::OffsetDateTime;
+
+/// Unredacted events of requests captured for debugging; purged once `expires_at` passes.
+#[sea_orm::model]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "debug_captures")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub trace_id: String,
+    pub user_key_id: i64,
+    /// `downstream` or `upstream`.
+    pub kind: String,
+    pub event_json: Json,
+    pub at: OffsetDateTime,
+    pub expires_at: OffsetDateTime,
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -19,6 +19,12 @@ pub struct Model {
     pub response_status: Option<i32>,
     pub response_headers_json: Json,
     pub response_body: Option<Vec<u8>>,
+    /// Comma-delimited tag list (`,a,b,`) so single tags can be matched with LIKE.
+    pub tags: Option<String>,
+    pub client_ip: Option<String>,
+    /// ISO country code of the client, when known.
+    pub country: Option<String>,
+    pub asn: Option<i64>,
     pub created_at: OffsetDateTime,
 }
 
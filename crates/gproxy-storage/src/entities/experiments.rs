@@ -0,0 +1,22 @@
+use sea_orm::entity::prelude::*;
+use time::OffsetDateTime;
+
+#[sea_orm::model]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "experiments")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    /// Virtual model name clients send, e.g. `chat-ab`.
+    #[sea_orm(unique_key = "experiment_name")]
+    pub name: String,
+    /// Arms as `[{"name", "provider", "model", "weight"}]`.
+    pub arms_json: Json,
+    /// How requests are bucketed: `random`, `user` or `session`.
+    pub split: String,
+    pub enabled: bool,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -9,11 +9,35 @@ pub struct Model {
     pub id: i64,
     pub host: String,
     pub port: i32,
+    pub admin_host: Option<String>,
+    pub admin_port: Option<i32>,
     #[sea_orm(column_name = "admin_key_hash")]
     pub admin_key: String,
     pub proxy: Option<String>,
     pub dsn: String,
     pub event_redact_sensitive: Option<bool>,
+    pub native_error_format: Option<bool>,
+    pub stream_retry_on_interrupt: Option<bool>,
+    pub stream_first_token_timeout_ms: Option<i64>,
+    pub coalesce_inflight_requests: Option<bool>,
+    pub response_compress_min_bytes: Option<i64>,
+    pub key_abuse_max_ips: Option<i64>,
+    pub key_abuse_min_rpm: Option<i64>,
+    pub key_abuse_blocked_countries: Option<String>,
+    pub alert_webhook_url: Option<String>,
+    pub geoip_country_db: Option<String>,
+    pub geoip_asn_db: Option<String>,
+    pub stream_idle_timeout_ms: Option<i64>,
+    pub egress_ip_family: Option<String>,
+    pub egress_local_address: Option<String>,
+    pub egress_interface: Option<String>,
+    pub tool_call_audit: Option<bool>,
+    pub max_response_body_bytes: Option<i64>,
+    pub truncate_oversize_responses: Option<bool>,
+    pub event_log_max_body_bytes: Option<i64>,
+    pub log_search: Option<bool>,
+    pub trusted_proxies: Option<String>,
+    pub sse_resume: Option<bool>,
     pub updated_at: OffsetDateTime,
 }
 
@@ -0,0 +1,23 @@
+use sea_orm::entity::prelude::*;
+use time::OffsetDateTime;
+
+/// Stretches of time during which every credential of a provider was unavailable.
+#[sea_orm::model]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "incidents")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub provider: String,
+    pub started_at: OffsetDateTime,
+    /// `None` while the incident is open.
+    pub ended_at: Option<OffsetDateTime>,
+    /// Models requested while the provider was down (JSON array of strings).
+    pub models: Json,
+    /// The first upstream failures that cooled its credentials down (JSON array).
+    pub error_samples: Json,
+    /// Requests refused with `no_active_credentials` meanwhile.
+    pub refused_requests: i64,
+}
+
+impl ActiveModelBehavior for ActiveModel {}
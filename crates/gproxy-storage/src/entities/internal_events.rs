@@ -8,6 +8,9 @@ pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i64,
     pub event_type: String,
+    /// Copied out of the payload so events can be filtered without decoding it.
+    pub credential_id: Option<i64>,
+    pub model: Option<String>,
     pub payload_json: Json,
     pub at: OffsetDateTime,
     pub created_at: OffsetDateTime,
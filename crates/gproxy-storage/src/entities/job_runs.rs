@@ -0,0 +1,21 @@
+use sea_orm::entity::prelude::*;
+use time::OffsetDateTime;
+
+/// Run history of the background job scheduler.
+#[sea_orm::model]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "job_runs")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub job: String,
+    /// `schedule` or `manual`.
+    pub trigger: String,
+    pub started_at: OffsetDateTime,
+    pub finished_at: OffsetDateTime,
+    pub ok: bool,
+    /// Job summary on success, error message on failure.
+    pub message: Option<String>,
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,24 @@
+use sea_orm::entity::prelude::*;
+use time::OffsetDateTime;
+
+/// Saved `/admin/logs` filters, optionally alerting when the rows they match within
+/// their window reach a threshold.
+#[sea_orm::model]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "log_views")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    #[sea_orm(unique_key = "log_view_name")]
+    pub name: String,
+    /// Filter as `/admin/logs` query parameters, e.g. `{"provider": "claude", "status_min": 500}`.
+    pub filter_json: Json,
+    /// Trailing time range the view covers, in seconds.
+    pub window_secs: i64,
+    /// Notify once the rows matched within the window reach this count.
+    pub alert_threshold: Option<i64>,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,23 @@
+use sea_orm::entity::prelude::*;
+use time::OffsetDateTime;
+
+/// Tools of remote MCP servers that upstream called while answering a Responses request.
+#[sea_orm::model]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "mcp_tool_calls")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub trace_id: Option<String>,
+    pub user_key_id: i64,
+    pub provider: String,
+    pub server_label: String,
+    pub tool_name: String,
+    /// Arguments as sent to the tool (JSON text).
+    pub arguments: Option<String>,
+    pub output: Option<String>,
+    pub error: Option<String>,
+    pub at: OffsetDateTime,
+}
+
+impl ActiveModelBehavior for ActiveModel {}
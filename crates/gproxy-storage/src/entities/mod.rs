@@ -1,29 +1,68 @@
+pub mod aborted_streams;
 pub mod credentials;
+pub mod debug_captures;
 pub mod downstream_requests;
+pub mod experiments;
 pub mod global_config;
+pub mod incidents;
 pub mod internal_events;
+pub mod job_runs;
+pub mod log_views;
+pub mod mcp_tool_calls;
+pub mod model_profiles;
+pub mod org_provider_grants;
+pub mod organizations;
 pub mod providers;
+pub mod secrets;
+pub mod stats_hourly;
+pub mod tool_calls;
 pub mod upstream_requests;
 pub mod upstream_usages;
 pub mod user_keys;
 pub mod users;
 
+pub use aborted_streams::Entity as AbortedStreams;
 pub use credentials::Entity as Credentials;
+pub use debug_captures::Entity as DebugCaptures;
 pub use downstream_requests::Entity as DownstreamRequests;
+pub use experiments::Entity as Experiments;
 pub use global_config::Entity as GlobalConfig;
+pub use incidents::Entity as Incidents;
 pub use internal_events::Entity as InternalEvents;
+pub use job_runs::Entity as JobRuns;
+pub use log_views::Entity as LogViews;
+pub use mcp_tool_calls::Entity as McpToolCalls;
+pub use model_profiles::Entity as ModelProfiles;
+pub use org_provider_grants::Entity as OrgProviderGrants;
+pub use organizations::Entity as Organizations;
 pub use providers::Entity as Providers;
+pub use secrets::Entity as Secrets;
+pub use stats_hourly::Entity as StatsHourly;
+pub use tool_calls::Entity as ToolCalls;
 pub use upstream_requests::Entity as UpstreamRequests;
 pub use upstream_usages::Entity as UpstreamUsages;
 pub use user_keys::Entity as UserKeys;
 pub use users::Entity as Users;
 
 pub mod prelude {
+    pub use super::AbortedStreams;
     pub use super::Credentials;
+    pub use super::DebugCaptures;
     pub use super::DownstreamRequests;
+    pub use super::Experiments;
     pub use super::GlobalConfig;
+    pub use super::Incidents;
     pub use super::InternalEvents;
+    pub use super::JobRuns;
+    pub use super::LogViews;
+    pub use super::McpToolCalls;
+    pub use super::ModelProfiles;
+    pub use super::OrgProviderGrants;
+    pub use super::Organizations;
     pub use super::Providers;
+    pub use super::Secrets;
+    pub use super::StatsHourly;
+    pub use super::ToolCalls;
     pub use super::UpstreamRequests;
     pub use super::UpstreamUsages;
     pub use super::UserKeys;
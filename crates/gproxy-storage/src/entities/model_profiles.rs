@@ -0,0 +1,22 @@
+use sea_orm::entity::prelude::*;
+use time::OffsetDateTime;
+
+#[sea_orm::model]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "model_profiles")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    /// Virtual model name clients send, e.g. `team-fast`.
+    #[sea_orm(unique_key = "model_profile_name")]
+    pub name: String,
+    pub provider: String,
+    pub model: String,
+    /// Request adjustments (`temperature_max`, `system_prompt`).
+    pub settings_json: Json,
+    pub enabled: bool,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+}
+
+impl ActiveModelBehavior for ActiveModel {}
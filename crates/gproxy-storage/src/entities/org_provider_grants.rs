@@ -0,0 +1,23 @@
+use sea_orm::entity::prelude::*;
+use time::OffsetDateTime;
+
+/// Grants an organization access to a provider, optionally narrowed to one credential.
+#[sea_orm::model]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "org_provider_grants")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub org_id: i64,
+    pub provider_id: i64,
+    pub credential_id: Option<i64>,
+    pub created_at: OffsetDateTime,
+    #[sea_orm(belongs_to, from = "org_id", to = "id", on_delete = "Cascade")]
+    pub org: HasOne<super::organizations::Entity>,
+    #[sea_orm(belongs_to, from = "provider_id", to = "id", on_delete = "Cascade")]
+    pub provider: HasOne<super::providers::Entity>,
+    #[sea_orm(belongs_to, from = "credential_id", to = "id", on_delete = "Cascade")]
+    pub credential: HasOne<super::credentials::Entity>,
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,32 @@
+use sea_orm::entity::prelude::*;
+use time::OffsetDateTime;
+
+#[sea_orm::model]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "organizations")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    #[sea_orm(unique_key = "organization_name")]
+    pub name: String,
+    pub enabled: bool,
+    #[sea_orm(unique_key = "organization_admin_key")]
+    pub admin_key: Option<String>,
+    /// Keep request/response bodies of the organization's traffic out of logs.
+    pub omit_bodies: Option<bool>,
+    /// Per-minute limits shared by the organization's keys.
+    pub rpm_limit: Option<i64>,
+    pub tpm_limit: Option<i64>,
+    /// Tokens per calendar month (UTC) across the organization's keys.
+    pub monthly_token_budget: Option<i64>,
+    pub default_provider: Option<String>,
+    pub default_model: Option<String>,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+    #[sea_orm(has_many)]
+    pub users: HasMany<super::users::Entity>,
+    #[sea_orm(has_many)]
+    pub provider_grants: HasMany<super::org_provider_grants::Entity>,
+}
+
+impl ActiveModelBehavior for ActiveModel {}
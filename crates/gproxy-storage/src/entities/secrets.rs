@@ -0,0 +1,19 @@
+use sea_orm::entity::prelude::*;
+use time::OffsetDateTime;
+
+/// Named secret values that config refers to by name instead of holding them inline,
+/// e.g. auth headers injected into MCP server tools.
+#[sea_orm::model]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "secrets")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    #[sea_orm(unique_key = "secret_name")]
+    pub name: String,
+    pub value: String,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,23 @@
+use sea_orm::entity::prelude::*;
+use time::OffsetDateTime;
+
+/// Coarse per-hour traffic aggregates flushed from the in-memory stats engine.
+#[sea_orm::model]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "stats_hourly")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub hour: OffsetDateTime,
+    /// `total`, `provider`, `model` or `key`.
+    pub dimension: String,
+    pub dimension_key: String,
+    pub requests: i64,
+    pub errors: i64,
+    pub tokens: i64,
+    /// Latency histogram bucket counts (see the core stats module for the layout).
+    pub latency_histogram: Json,
+    pub updated_at: OffsetDateTime,
+}
+
+impl ActiveModelBehavior for ActiveModel {}
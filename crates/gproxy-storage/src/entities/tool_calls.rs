@@ -0,0 +1,27 @@
+use sea_orm::entity::prelude::*;
+use time::OffsetDateTime;
+
+/// Client-side tool/function calls found in generate responses.
+#[sea_orm::model]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "tool_calls")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub trace_id: Option<String>,
+    pub user_key_id: i64,
+    pub provider: String,
+    pub model: Option<String>,
+    /// Client protocol the call was returned in, e.g. `openai_chat`.
+    pub proto: String,
+    pub tool_name: String,
+    /// Id the client echoes back with the result, where the protocol has one.
+    pub call_id: Option<String>,
+    pub arguments_bytes: i64,
+    pub at: OffsetDateTime,
+    /// When the result came back through the proxy, if it did.
+    pub result_at: Option<OffsetDateTime>,
+    pub latency_ms: Option<i64>,
+}
+
+impl ActiveModelBehavior for ActiveModel {}
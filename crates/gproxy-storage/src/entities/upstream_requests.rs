@@ -18,6 +18,7 @@ pub struct Model {
     pub operation: String,
     pub request_method: String,
     pub request_headers_json: Json,
+    pub request_host: Option<String>,
     pub request_path: String,
     pub request_query: Option<String>,
     pub request_body: Option<Vec<u8>>,
@@ -27,6 +28,10 @@ pub struct Model {
     pub error_kind: Option<String>,
     pub error_message: Option<String>,
     pub transport_kind: Option<String>,
+    /// Comma-delimited tag list (`,a,b,`) so single tags can be matched with LIKE.
+    pub tags: Option<String>,
+    /// `anthropic-beta` flags sent upstream, in the same delimited form as `tags`.
+    pub anthropic_betas: Option<String>,
     pub created_at: OffsetDateTime,
 }
 
@@ -23,6 +23,11 @@ pub struct Model {
     pub output_tokens: Option<i64>,
     pub cache_read_input_tokens: Option<i64>,
     pub cache_creation_input_tokens: Option<i64>,
+    /// Comma-delimited tag list (`,a,b,`) so single tags can be matched with LIKE.
+    pub tags: Option<String>,
+    /// A/B experiment name and assigned arm, when routed through an experiment.
+    pub experiment: Option<String>,
+    pub experiment_arm: Option<String>,
     pub created_at: OffsetDateTime,
     #[sea_orm(
         belongs_to,
@@ -13,6 +13,40 @@ pub struct Model {
     pub api_key: String,
     pub label: Option<String>,
     pub enabled: bool,
+    /// Requests per minute allowed for this key; `None` means unlimited.
+    pub rpm_limit: Option<i64>,
+    /// Tokens per minute allowed for this key; `None` means unlimited.
+    pub tpm_limit: Option<i64>,
+    /// Output tokens per second streamed to this key; `None` means no pacing.
+    pub stream_tps_limit: Option<i64>,
+    /// Upper bound on output tokens per request; `None` leaves the request's own value.
+    pub max_output_tokens: Option<i64>,
+    /// Keep request/response bodies of this key's traffic out of logs; `None` keeps them.
+    pub omit_bodies: Option<bool>,
+    /// Provider used for aggregate routes when the model has no `provider/` prefix.
+    pub default_provider: Option<String>,
+    /// Model used with `default_provider` when the request names none.
+    pub default_model: Option<String>,
+    /// MCP servers this key's Responses requests may declare, with auth injected from
+    /// secrets; `None` leaves MCP tools untouched.
+    pub mcp_policy: Option<Json>,
+    /// Moderation endpoint and category thresholds applied to this key's generate
+    /// requests; `None` leaves them unmoderated.
+    pub moderation_policy: Option<Json>,
+    /// Claude Code prelude template for this key's requests, replacing the provider's;
+    /// `None` keeps the provider's.
+    pub prelude_template: Option<String>,
+    /// Providers and models the key may call; `None` allows all.
+    pub key_scope: Option<Json>,
+    /// The key stops authenticating at this time and is deleted by the `user_key_expiry`
+    /// job; `None` never expires.
+    pub expires_at: Option<OffsetDateTime>,
+    /// Browser origins (`Origin`, or the origin of `Referer`) the key may be used from;
+    /// `None` allows any.
+    pub allowed_origins: Option<Json>,
+    /// Percent of this key's successful requests whose bodies are logged, overriding the
+    /// provider's `body_sampling`; `None` follows the provider.
+    pub body_sample_percent: Option<i64>,
     pub created_at: OffsetDateTime,
     pub updated_at: OffsetDateTime,
     #[sea_orm(belongs_to, from = "user_id", to = "id", on_delete = "Cascade")]
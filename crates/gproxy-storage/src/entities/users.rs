@@ -10,8 +10,11 @@ pub struct Model {
     #[sea_orm(unique_key = "user_name")]
     pub name: String,
     pub enabled: bool,
+    pub org_id: Option<i64>,
     pub created_at: OffsetDateTime,
     pub updated_at: OffsetDateTime,
+    #[sea_orm(belongs_to, from = "org_id", to = "id", on_delete = "SetNull")]
+    pub org: HasOne<super::organizations::Entity>,
     #[sea_orm(has_many)]
     pub keys: HasMany<super::user_keys::Entity>,
 }
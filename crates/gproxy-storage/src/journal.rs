@@ -0,0 +1,315 @@
+//! Write-ahead journal of usage events: every upstream event carrying usage is appended
+//! to a JSON lines file before it goes to the database and acknowledged once it is
+//! stored. Entries left unacknowledged by a crash or a database outage are written again
+//! on startup and by a periodic replay, so billing data is delivered at least once.
+
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use gproxy_provider_core::Event;
+
+use crate::TelemetryStorage;
+
+/// File of the journal inside `GPROXY_DATA_DIR`.
+pub const USAGE_JOURNAL_FILE: &str = "usage_journal.jsonl";
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum Line {
+    Entry { seq: u64, event: Box<Event> },
+    Ack { ack: u64 },
+}
+
+struct Pending {
+    event: Event,
+    /// The database write failed (or was cut short by a restart) and is up for replay.
+    failed: bool,
+}
+
+struct State {
+    file: File,
+    next_seq: u64,
+    pending: BTreeMap<u64, Pending>,
+}
+
+pub struct UsageJournal {
+    path: PathBuf,
+    state: Mutex<State>,
+}
+
+/// Outcome of one replay of failed entries.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct JournalReplay {
+    pub written: usize,
+    pub failed: usize,
+}
+
+impl UsageJournal {
+    /// Opens the journal at `path`, keeping the unacknowledged entries of an earlier run
+    /// for replay and dropping the rest of the file.
+    pub fn open(path: &Path) -> std::io::Result<Self> {
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut pending = BTreeMap::new();
+        match File::open(path) {
+            Ok(file) => {
+                for line in BufReader::new(file).lines() {
+                    // A crash can leave the last line half written.
+                    match serde_json::from_str::<Line>(&line?) {
+                        Ok(Line::Entry { seq, event }) => {
+                            pending.insert(
+                                seq,
+                                Pending {
+                                    event: *event,
+                                    failed: true,
+                                },
+                            );
+                        }
+                        Ok(Line::Ack { ack }) => {
+                            pending.remove(&ack);
+                        }
+                        Err(_) => {}
+                    }
+                }
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err),
+        }
+
+        let tmp = path.with_extension("jsonl.tmp");
+        {
+            let mut out = File::create(&tmp)?;
+            for (seq, entry) in &pending {
+                write_line(
+                    &mut out,
+                    &Line::Entry {
+                        seq: *seq,
+                        event: Box::new(entry.event.clone()),
+                    },
+                )?;
+            }
+            out.sync_all()?;
+        }
+        std::fs::rename(&tmp, path)?;
+        let file = OpenOptions::new().append(true).open(path)?;
+        let next_seq = pending.keys().next_back().map_or(1, |seq| seq + 1);
+        Ok(Self {
+            path: path.to_path_buf(),
+            state: Mutex::new(State {
+                file,
+                next_seq,
+                pending,
+            }),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Entries not yet acknowledged.
+    pub fn pending(&self) -> usize {
+        self.lock().pending.len()
+    }
+
+    /// Appends a usage event and syncs it to disk; `None` for events without usage, which
+    /// are not journaled.
+    pub fn record(&self, event: &Event) -> std::io::Result<Option<u64>> {
+        match journal_entry(event) {
+            Some(entry) => self.append(entry).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn append(&self, event: Event) -> std::io::Result<u64> {
+        let mut state = self.lock();
+        let seq = state.next_seq;
+        write_line(
+            &mut state.file,
+            &Line::Entry {
+                seq,
+                event: Box::new(event.clone()),
+            },
+        )?;
+        state.file.sync_data()?;
+        state.next_seq += 1;
+        state.pending.insert(
+            seq,
+            Pending {
+                event,
+                failed: false,
+            },
+        );
+        Ok(seq)
+    }
+
+    /// Marks an entry as stored. The file is emptied once nothing is pending.
+    pub fn ack(&self, seq: u64) -> std::io::Result<()> {
+        let mut state = self.lock();
+        if state.pending.remove(&seq).is_none() {
+            return Ok(());
+        }
+        if state.pending.is_empty() {
+            state.file.set_len(0)?;
+            return Ok(());
+        }
+        write_line(&mut state.file, &Line::Ack { ack: seq })
+    }
+
+    /// Leaves an entry for the next replay after its database write failed.
+    pub fn fail(&self, seq: u64) {
+        if let Some(entry) = self.lock().pending.get_mut(&seq) {
+            entry.failed = true;
+        }
+    }
+
+    /// Writes the failed entries to `storage` again, oldest first.
+    pub async fn replay<S: TelemetryStorage + ?Sized>(
+        &self,
+        storage: &S,
+    ) -> std::io::Result<JournalReplay> {
+        let failed: Vec<(u64, Event)> = self
+            .lock()
+            .pending
+            .iter()
+            .filter(|(_, entry)| entry.failed)
+            .map(|(seq, entry)| (*seq, entry.event.clone()))
+            .collect();
+        let mut replay = JournalReplay::default();
+        for (seq, event) in failed {
+            if storage.append_event(&event).await.is_ok() {
+                self.ack(seq)?;
+                replay.written += 1;
+            } else {
+                replay.failed += 1;
+            }
+        }
+        Ok(replay)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// The journaled form of a usage event. Bodies are left out, only the usage has to survive.
+fn journal_entry(event: &Event) -> Option<Event> {
+    let Event::Upstream(ev) = event else {
+        return None;
+    };
+    ev.usage.as_ref()?;
+    let mut ev = ev.clone();
+    ev.request_body = None;
+    ev.response_body = None;
+    Some(Event::Upstream(ev))
+}
+
+fn write_line(out: &mut File, line: &Line) -> std::io::Result<()> {
+    let mut buf = serde_json::to_vec(line)?;
+    buf.push(b'\n');
+    out.write_all(&buf)
+}
+
+/// Journals a usage event on a blocking thread; `None` when it was not journaled.
+pub(crate) async fn record_blocking(journal: &Arc<UsageJournal>, event: &Event) -> Option<u64> {
+    let entry = journal_entry(event)?;
+    let journal = journal.clone();
+    match tokio::task::spawn_blocking(move || journal.append(entry)).await {
+        Ok(Ok(seq)) => Some(seq),
+        Ok(Err(err)) => {
+            gproxy_common::log_error!("storage", "usage journal append failed: {err}");
+            None
+        }
+        Err(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::SystemTime;
+
+    use gproxy_provider_core::{Headers, UpstreamEvent, UsageSummary};
+
+    use super::*;
+    use crate::MemoryStorage;
+
+    fn usage_event(trace_id: &str) -> Event {
+        Event::Upstream(UpstreamEvent {
+            trace_id: Some(trace_id.to_string()),
+            at: SystemTime::now(),
+            user_id: Some(1),
+            user_key_id: Some(2),
+            provider: "claude".to_string(),
+            credential_id: Some(3),
+            internal: false,
+            attempt_no: 1,
+            operation: "generate_content".to_string(),
+            request_method: "POST".to_string(),
+            request_headers: Headers::new(),
+            request_host: None,
+            request_path: "/v1/messages".to_string(),
+            request_query: None,
+            request_body: Some(b"prompt".to_vec()),
+            response_status: Some(200),
+            response_headers: Headers::new(),
+            response_body: Some(b"answer".to_vec()),
+            usage: Some(UsageSummary::default()),
+            error_kind: None,
+            error_message: None,
+            transport_kind: None,
+            tags: Vec::new(),
+            anthropic_betas: Vec::new(),
+            model: Some("claude-sonnet-4".to_string()),
+            latency_ms: None,
+            experiment: None,
+            experiment_arm: None,
+            transform: None,
+        })
+    }
+
+    #[tokio::test]
+    async fn unacknowledged_entries_survive_a_restart() {
+        let dir = std::env::temp_dir().join(format!(
+            "gproxy-journal-{}-{}",
+            std::process::id(),
+            SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        let path = dir.join(USAGE_JOURNAL_FILE);
+
+        let journal = UsageJournal::open(&path).unwrap();
+        let first = journal.record(&usage_event("a")).unwrap().unwrap();
+        let second = journal.record(&usage_event("b")).unwrap().unwrap();
+        journal.ack(first).unwrap();
+        journal.fail(second);
+        let third = journal.record(&usage_event("c")).unwrap().unwrap();
+        drop(journal);
+
+        let journal = UsageJournal::open(&path).unwrap();
+        assert_eq!(journal.pending(), 2);
+        assert!(journal.record(&usage_event("d")).unwrap().unwrap() > third);
+
+        let storage = MemoryStorage::new();
+        let replay = journal.replay(&storage).await.unwrap();
+        assert_eq!(
+            replay,
+            JournalReplay {
+                written: 2,
+                failed: 0
+            }
+        );
+        assert_eq!(journal.pending(), 1);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
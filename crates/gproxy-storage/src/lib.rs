@@ -1,15 +1,27 @@
 pub mod entities;
+pub mod journal;
+pub mod memory;
 pub mod seaorm;
+mod search;
 pub mod sinks;
 pub mod snapshot;
+pub mod split;
 pub mod storage;
 
+pub use journal::{JournalReplay, USAGE_JOURNAL_FILE, UsageJournal};
+pub use memory::{MemorySeed, MemoryStorage};
 pub use seaorm::SeaOrmStorage;
 pub use sinks::DbEventSink;
 pub use snapshot::{
-    CredentialRow, GlobalConfigRow, ProviderRow, StorageSnapshot, UserKeyRow, UserRow,
+    CredentialRow, ExperimentRow, GlobalConfigRow, LogViewRow, ModelProfileRow, OrgGrantRow,
+    OrganizationRow, ProviderRow, SecretRow, StorageSnapshot, UserKeyRow, UserRow,
 };
+pub use split::SplitStorage;
 pub use storage::{
-    LogCursor, LogQueryFilter, LogQueryResult, LogRecord, LogRecordKind, Storage, StorageError,
-    StorageResult, UsageAggregate, UsageAggregateFilter,
+    AbortedStreamRecord, ConfigStorage, DebugCaptureRecord, DownstreamRequestRecord,
+    IncidentRecord, JobRunRecord, LogCursor, LogQueryFilter, LogQueryResult, LogRecord,
+    LogRecordKind, LogSearchHit, LogSearchQuery, McpToolCallRecord, OperationalEventFilter,
+    OperationalEventQueryResult, OperationalEventRecord, StatsHourlyRow, Storage, StorageError,
+    StorageResult, TelemetryStorage, ToolCallRecord, UpstreamOutcome, UsageAggregate,
+    UsageAggregateFilter, UsageRecord,
 };
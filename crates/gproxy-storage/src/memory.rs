@@ -0,0 +1,2338 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use time::OffsetDateTime;
+
+use gproxy_common::GlobalConfig;
+use gproxy_provider_core::Event;
+
+use crate::seaorm::{
+    OperationalParts, derive_downstream_observability, extract_model_for_usage,
+    extract_operational_at, merge_sorted_logs, operational_event_type, system_time_to_offset,
+};
+use crate::search::{matches, search_text, snippet};
+use crate::snapshot::{
+    CredentialRow, ExperimentRow, GlobalConfigRow, LogViewRow, ModelProfileRow, OrgGrantRow,
+    OrganizationRow, ProviderRow, SecretRow, StorageSnapshot, UserKeyRow, UserRow,
+};
+use crate::storage::{
+    AbortedStreamRecord, ConfigStorage, DebugCaptureRecord, DownstreamRequestRecord,
+    IncidentRecord, JobRunRecord, LogCursor, LogQueryFilter, LogQueryResult, LogRecord,
+    LogRecordKind, LogSearchHit, LogSearchQuery, McpToolCallRecord, OperationalEventFilter,
+    OperationalEventQueryResult, OperationalEventRecord, StatsHourlyRow, StorageError,
+    StorageResult, TelemetryStorage, ToolCallRecord, UpstreamOutcome, UsageAggregate,
+    UsageAggregateFilter, UsageRecord,
+};
+
+/// DSN scheme selecting [`MemoryStorage`]; anything after it is a seed file path.
+pub const MEMORY_DSN_PREFIX: &str = "memory://";
+
+/// Telemetry rows kept per table; the oldest rows are dropped first.
+const MAX_EVENT_ROWS: usize = 10_000;
+
+/// Initial configuration for a [`MemoryStorage`], usually read from a JSON file.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct MemorySeed {
+    pub providers: Vec<SeedProvider>,
+    pub credentials: Vec<SeedCredential>,
+    pub users: Vec<SeedUser>,
+    pub user_keys: Vec<SeedUserKey>,
+    pub model_profiles: Vec<SeedModelProfile>,
+    pub experiments: Vec<SeedExperiment>,
+    pub secrets: Vec<SeedSecret>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SeedProvider {
+    pub name: String,
+    pub config_json: serde_json::Value,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SeedCredential {
+    /// Provider name.
+    pub provider: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default = "empty_object")]
+    pub settings_json: serde_json::Value,
+    pub secret_json: serde_json::Value,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SeedUser {
+    pub id: i64,
+    pub name: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SeedUserKey {
+    pub user_id: i64,
+    pub api_key: String,
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub rpm_limit: Option<i64>,
+    #[serde(default)]
+    pub tpm_limit: Option<i64>,
+    #[serde(default)]
+    pub stream_tps_limit: Option<i64>,
+    #[serde(default)]
+    pub max_output_tokens: Option<i64>,
+    #[serde(default)]
+    pub omit_bodies: bool,
+    #[serde(default)]
+    pub default_provider: Option<String>,
+    #[serde(default)]
+    pub default_model: Option<String>,
+    #[serde(default)]
+    pub mcp_policy: Option<serde_json::Value>,
+    #[serde(default)]
+    pub moderation_policy: Option<serde_json::Value>,
+    #[serde(default)]
+    pub prelude_template: Option<String>,
+    #[serde(default)]
+    pub body_sample_percent: Option<i64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SeedModelProfile {
+    pub name: String,
+    pub provider: String,
+    pub model: String,
+    #[serde(default = "empty_object")]
+    pub settings_json: serde_json::Value,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SeedExperiment {
+    pub name: String,
+    pub arms_json: serde_json::Value,
+    #[serde(default = "default_split")]
+    pub split: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SeedSecret {
+    pub name: String,
+    pub value: String,
+}
+
+fn default_split() -> String {
+    "random".to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn empty_object() -> serde_json::Value {
+    serde_json::json!({})
+}
+
+/// Usage row plus the columns only used for filtering.
+#[derive(Debug, Clone)]
+struct StoredUsage {
+    trace_id: Option<String>,
+    user_id: Option<i64>,
+    tags: Vec<String>,
+    record: UsageRecord,
+}
+
+/// Downstream log row plus the request parts only needed for replay.
+#[derive(Debug, Clone)]
+struct StoredDownstream {
+    request_query: Option<String>,
+    request_headers: Vec<(String, String)>,
+    record: LogRecord,
+}
+
+#[derive(Debug, Default)]
+struct MemoryState {
+    global_config: Option<GlobalConfigRow>,
+    providers: BTreeMap<i64, ProviderRow>,
+    credentials: BTreeMap<i64, CredentialRow>,
+    organizations: BTreeMap<i64, OrganizationRow>,
+    org_grants: BTreeMap<i64, OrgGrantRow>,
+    users: BTreeMap<i64, UserRow>,
+    user_keys: BTreeMap<i64, UserKeyRow>,
+    model_profiles: BTreeMap<i64, ModelProfileRow>,
+    experiments: BTreeMap<i64, ExperimentRow>,
+    secrets: BTreeMap<i64, SecretRow>,
+    log_views: BTreeMap<i64, LogViewRow>,
+    upstream: VecDeque<LogRecord>,
+    downstream: VecDeque<StoredDownstream>,
+    usages: VecDeque<StoredUsage>,
+    operational: VecDeque<OperationalEventRecord>,
+    stats_hourly: BTreeMap<(OffsetDateTime, String, String), StatsHourlyRow>,
+    job_runs: VecDeque<JobRunRecord>,
+    debug_captures: VecDeque<DebugCaptureRecord>,
+    mcp_tool_calls: VecDeque<McpToolCallRecord>,
+    tool_calls: VecDeque<ToolCallRecord>,
+    aborted_streams: VecDeque<AbortedStreamRecord>,
+    incidents: VecDeque<IncidentRecord>,
+    last_id: i64,
+}
+
+/// Process-local storage for stateless deployments, CI and embedding.
+///
+/// Nothing survives a restart. Telemetry tables are capped at the most recent
+/// rows so a long-running process does not grow without bound.
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    state: Mutex<MemoryState>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Opens a storage for a `memory://[seed.json]` DSN.
+    pub fn from_dsn(dsn: &str) -> StorageResult<Self> {
+        let path = dsn
+            .strip_prefix(MEMORY_DSN_PREFIX)
+            .unwrap_or_default()
+            .trim();
+        if path.is_empty() {
+            return Ok(Self::new());
+        }
+        Self::from_seed_file(path)
+    }
+
+    pub fn from_seed_file(path: impl AsRef<Path>) -> StorageResult<Self> {
+        let raw = std::fs::read(path)?;
+        let seed: MemorySeed = serde_json::from_slice(&raw)?;
+        Self::from_seed(seed)
+    }
+
+    pub fn from_seed(seed: MemorySeed) -> StorageResult<Self> {
+        let storage = Self::new();
+        {
+            let mut state = storage.lock();
+            for provider in seed.providers {
+                state.upsert_provider(&provider.name, &provider.config_json, provider.enabled);
+            }
+            for credential in seed.credentials {
+                state.insert_credential(
+                    &credential.provider,
+                    credential.name.as_deref(),
+                    &credential.settings_json,
+                    &credential.secret_json,
+                    credential.enabled,
+                )?;
+            }
+            for user in seed.users {
+                state.upsert_user(user.id, &user.name, user.enabled);
+            }
+            for key in seed.user_keys {
+                let id = state.insert_user_key(
+                    key.user_id,
+                    &key.api_key,
+                    key.label.as_deref(),
+                    key.enabled,
+                )?;
+                if let Some(row) = state.user_keys.get_mut(&id) {
+                    row.rpm_limit = key.rpm_limit;
+                    row.tpm_limit = key.tpm_limit;
+                    row.stream_tps_limit = key.stream_tps_limit;
+                    row.max_output_tokens = key.max_output_tokens;
+                    row.omit_bodies = key.omit_bodies;
+                    row.default_provider = key.default_provider;
+                    row.default_model = key.default_model;
+                    row.mcp_policy = key.mcp_policy;
+                    row.moderation_policy = key.moderation_policy;
+                    row.prelude_template = key.prelude_template;
+                    row.body_sample_percent = key.body_sample_percent;
+                }
+            }
+            for profile in seed.model_profiles {
+                state.upsert_model_profile(
+                    &profile.name,
+                    &profile.provider,
+                    &profile.model,
+                    &profile.settings_json,
+                    profile.enabled,
+                );
+            }
+            for experiment in seed.experiments {
+                state.upsert_experiment(
+                    &experiment.name,
+                    &experiment.arms_json,
+                    &experiment.split,
+                    experiment.enabled,
+                );
+            }
+            for secret in seed.secrets {
+                state.upsert_secret(&secret.name, &secret.value);
+            }
+        }
+        Ok(storage)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MemoryState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+pub fn is_memory_dsn(dsn: &str) -> bool {
+    dsn.starts_with(MEMORY_DSN_PREFIX)
+}
+
+impl MemoryState {
+    fn next_id(&mut self) -> i64 {
+        self.last_id += 1;
+        self.last_id
+    }
+
+    fn provider_id(&self, name: &str) -> StorageResult<i64> {
+        self.providers
+            .values()
+            .find(|row| row.name == name)
+            .map(|row| row.id)
+            .ok_or_else(|| {
+                StorageError::Db(sea_orm::DbErr::RecordNotFound(format!(
+                    "provider not found: {name}"
+                )))
+            })
+    }
+
+    fn upsert_provider(
+        &mut self,
+        name: &str,
+        config_json: &serde_json::Value,
+        enabled: bool,
+    ) -> i64 {
+        let now = OffsetDateTime::now_utc();
+        if let Ok(id) = self.provider_id(name)
+            && let Some(row) = self.providers.get_mut(&id)
+        {
+            row.config_json = config_json.clone();
+            row.enabled = enabled;
+            row.updated_at = now;
+            return id;
+        }
+        let id = self.next_id();
+        self.providers.insert(
+            id,
+            ProviderRow {
+                id,
+                name: name.to_string(),
+                config_json: config_json.clone(),
+                enabled,
+                updated_at: now,
+            },
+        );
+        id
+    }
+
+    fn upsert_model_profile(
+        &mut self,
+        name: &str,
+        provider: &str,
+        model: &str,
+        settings_json: &serde_json::Value,
+        enabled: bool,
+    ) -> i64 {
+        let now = OffsetDateTime::now_utc();
+        if let Some(row) = self
+            .model_profiles
+            .values_mut()
+            .find(|row| row.name == name)
+        {
+            row.provider = provider.to_string();
+            row.model = model.to_string();
+            row.settings_json = settings_json.clone();
+            row.enabled = enabled;
+            row.updated_at = now;
+            return row.id;
+        }
+        let id = self.next_id();
+        self.model_profiles.insert(
+            id,
+            ModelProfileRow {
+                id,
+                name: name.to_string(),
+                provider: provider.to_string(),
+                model: model.to_string(),
+                settings_json: settings_json.clone(),
+                enabled,
+                created_at: now,
+                updated_at: now,
+            },
+        );
+        id
+    }
+
+    fn upsert_experiment(
+        &mut self,
+        name: &str,
+        arms_json: &serde_json::Value,
+        split: &str,
+        enabled: bool,
+    ) -> i64 {
+        let now = OffsetDateTime::now_utc();
+        if let Some(row) = self.experiments.values_mut().find(|row| row.name == name) {
+            row.arms_json = arms_json.clone();
+            row.split = split.to_string();
+            row.enabled = enabled;
+            row.updated_at = now;
+            return row.id;
+        }
+        let id = self.next_id();
+        self.experiments.insert(
+            id,
+            ExperimentRow {
+                id,
+                name: name.to_string(),
+                arms_json: arms_json.clone(),
+                split: split.to_string(),
+                enabled,
+                created_at: now,
+                updated_at: now,
+            },
+        );
+        id
+    }
+
+    fn upsert_secret(&mut self, name: &str, value: &str) -> i64 {
+        let now = OffsetDateTime::now_utc();
+        if let Some(row) = self.secrets.values_mut().find(|row| row.name == name) {
+            row.value = value.to_string();
+            row.updated_at = now;
+            return row.id;
+        }
+        let id = self.next_id();
+        self.secrets.insert(
+            id,
+            SecretRow {
+                id,
+                name: name.to_string(),
+                value: value.to_string(),
+                created_at: now,
+                updated_at: now,
+            },
+        );
+        id
+    }
+
+    fn insert_credential(
+        &mut self,
+        provider_name: &str,
+        name: Option<&str>,
+        settings_json: &serde_json::Value,
+        secret_json: &serde_json::Value,
+        enabled: bool,
+    ) -> StorageResult<i64> {
+        let provider_id = self.provider_id(provider_name)?;
+        let now = OffsetDateTime::now_utc();
+        let id = self.next_id();
+        self.credentials.insert(
+            id,
+            CredentialRow {
+                id,
+                provider_id,
+                name: name.map(|s| s.to_string()),
+                settings_json: settings_json.clone(),
+                secret_json: secret_json.clone(),
+                enabled,
+                created_at: now,
+                updated_at: now,
+            },
+        );
+        Ok(id)
+    }
+
+    fn upsert_user(&mut self, user_id: i64, name: &str, enabled: bool) {
+        let now = OffsetDateTime::now_utc();
+        let row = self.users.entry(user_id).or_insert_with(|| UserRow {
+            id: user_id,
+            name: String::new(),
+            enabled,
+            org_id: None,
+            created_at: now,
+            updated_at: now,
+        });
+        row.name = name.to_string();
+        row.enabled = enabled;
+        row.updated_at = now;
+    }
+
+    fn insert_user_key(
+        &mut self,
+        user_id: i64,
+        api_key: &str,
+        label: Option<&str>,
+        enabled: bool,
+    ) -> StorageResult<i64> {
+        if !self.users.contains_key(&user_id) {
+            return Err(StorageError::Db(sea_orm::DbErr::RecordNotFound(format!(
+                "user not found: {user_id}"
+            ))));
+        }
+        if self.user_keys.values().any(|row| row.api_key == api_key) {
+            return Err(StorageError::Db(sea_orm::DbErr::RecordNotInserted));
+        }
+        let now = OffsetDateTime::now_utc();
+        let id = self.next_id();
+        self.user_keys.insert(
+            id,
+            UserKeyRow {
+                id,
+                user_id,
+                api_key: api_key.to_string(),
+                label: label.map(|s| s.to_string()),
+                enabled,
+                rpm_limit: None,
+                tpm_limit: None,
+                stream_tps_limit: None,
+                max_output_tokens: None,
+                omit_bodies: false,
+                default_provider: None,
+                default_model: None,
+                mcp_policy: None,
+                moderation_policy: None,
+                prelude_template: None,
+                key_scope: None,
+                expires_at: None,
+                allowed_origins: None,
+                body_sample_percent: None,
+                created_at: now,
+                updated_at: now,
+            },
+        );
+        Ok(id)
+    }
+
+    fn remove_credential(&mut self, credential_id: i64) {
+        self.credentials.remove(&credential_id);
+        self.org_grants
+            .retain(|_, grant| grant.credential_id != Some(credential_id));
+    }
+}
+
+fn push_capped<T>(rows: &mut VecDeque<T>, row: T) {
+    if rows.len() >= MAX_EVENT_ROWS {
+        rows.pop_front();
+    }
+    rows.push_back(row);
+}
+
+fn before_cursor(at: OffsetDateTime, id: i64, cursor: Option<LogCursor>) -> bool {
+    match cursor {
+        Some(cursor) => at < cursor.at || (at == cursor.at && id < cursor.id),
+        None => true,
+    }
+}
+
+fn newest_first<'a, T: 'a>(
+    rows: impl Iterator<Item = &'a T>,
+    key: impl Fn(&T) -> (OffsetDateTime, i64),
+) -> Vec<&'a T> {
+    let mut rows: Vec<&T> = rows.collect();
+    rows.sort_by_key(|row| std::cmp::Reverse(key(row)));
+    rows
+}
+
+fn contains_path(row: &LogRecord, filter: &LogQueryFilter) -> bool {
+    filter
+        .request_path_contains
+        .as_deref()
+        .is_none_or(|needle| row.request_path.contains(needle))
+}
+
+fn status_in_range(status: Option<i32>, min: Option<i32>, max: Option<i32>) -> bool {
+    if min.is_none() && max.is_none() {
+        return true;
+    }
+    let Some(status) = status else {
+        return false;
+    };
+    min.is_none_or(|min| status >= min) && max.is_none_or(|max| status <= max)
+}
+
+fn log_row_matches(row: &LogRecord, filter: &LogQueryFilter) -> bool {
+    row.at >= filter.from
+        && row.at <= filter.to
+        && filter.user_id.is_none_or(|id| row.user_id == Some(id))
+        && filter
+            .user_ids
+            .as_ref()
+            .is_none_or(|ids| row.user_id.is_some_and(|id| ids.contains(&id)))
+        && filter
+            .user_key_id
+            .is_none_or(|id| row.user_key_id == Some(id))
+        && filter
+            .trace_id
+            .as_deref()
+            .is_none_or(|trace| row.trace_id.as_deref() == Some(trace))
+        && filter
+            .tag
+            .as_deref()
+            .is_none_or(|tag| row.tags.iter().any(|t| t == tag))
+        && contains_path(row, filter)
+        && status_in_range(row.response_status, filter.status_min, filter.status_max)
+        && before_cursor(row.at, row.id, filter.cursor)
+}
+
+#[async_trait]
+impl ConfigStorage for MemoryStorage {
+    async fn sync(&self) -> StorageResult<()> {
+        Ok(())
+    }
+
+    async fn load_global_config(&self) -> StorageResult<Option<GlobalConfigRow>> {
+        Ok(self.lock().global_config.clone())
+    }
+
+    async fn upsert_global_config(&self, config: &GlobalConfig) -> StorageResult<()> {
+        self.lock().global_config = Some(GlobalConfigRow {
+            id: 1,
+            config: config.clone(),
+            updated_at: OffsetDateTime::now_utc(),
+        });
+        Ok(())
+    }
+
+    async fn load_snapshot(&self) -> StorageResult<StorageSnapshot> {
+        let state = self.lock();
+        Ok(StorageSnapshot {
+            global_config: state.global_config.clone(),
+            providers: state.providers.values().cloned().collect(),
+            credentials: state.credentials.values().cloned().collect(),
+            organizations: state.organizations.values().cloned().collect(),
+            org_grants: state.org_grants.values().cloned().collect(),
+            users: state.users.values().cloned().collect(),
+            user_keys: state.user_keys.values().cloned().collect(),
+            model_profiles: state.model_profiles.values().cloned().collect(),
+            experiments: state.experiments.values().cloned().collect(),
+            secrets: state.secrets.values().cloned().collect(),
+            log_views: state.log_views.values().cloned().collect(),
+        })
+    }
+
+    async fn provider_names(&self) -> StorageResult<Vec<String>> {
+        Ok(self
+            .lock()
+            .providers
+            .values()
+            .map(|row| row.name.clone())
+            .collect())
+    }
+
+    async fn upsert_provider(
+        &self,
+        name: &str,
+        config_json: &serde_json::Value,
+        enabled: bool,
+    ) -> StorageResult<i64> {
+        Ok(self.lock().upsert_provider(name, config_json, enabled))
+    }
+
+    async fn delete_provider(&self, name: &str) -> StorageResult<()> {
+        let mut state = self.lock();
+        let Ok(provider_id) = state.provider_id(name) else {
+            return Ok(());
+        };
+        state.providers.remove(&provider_id);
+        let credential_ids: Vec<i64> = state
+            .credentials
+            .values()
+            .filter(|row| row.provider_id == provider_id)
+            .map(|row| row.id)
+            .collect();
+        for id in credential_ids {
+            state.remove_credential(id);
+        }
+        state
+            .org_grants
+            .retain(|_, grant| grant.provider_id != provider_id);
+        Ok(())
+    }
+
+    async fn insert_credential(
+        &self,
+        provider_name: &str,
+        name: Option<&str>,
+        settings_json: &serde_json::Value,
+        secret_json: &serde_json::Value,
+        enabled: bool,
+    ) -> StorageResult<i64> {
+        self.lock()
+            .insert_credential(provider_name, name, settings_json, secret_json, enabled)
+    }
+
+    async fn update_credential(
+        &self,
+        credential_id: i64,
+        name: Option<&str>,
+        settings_json: &serde_json::Value,
+        secret_json: &serde_json::Value,
+    ) -> StorageResult<()> {
+        if let Some(row) = self.lock().credentials.get_mut(&credential_id) {
+            row.name = name.map(|s| s.to_string());
+            row.settings_json = settings_json.clone();
+            row.secret_json = secret_json.clone();
+            row.updated_at = OffsetDateTime::now_utc();
+        }
+        Ok(())
+    }
+
+    async fn set_credential_enabled(&self, credential_id: i64, enabled: bool) -> StorageResult<()> {
+        if let Some(row) = self.lock().credentials.get_mut(&credential_id) {
+            row.enabled = enabled;
+            row.updated_at = OffsetDateTime::now_utc();
+        }
+        Ok(())
+    }
+
+    async fn delete_credential(&self, credential_id: i64) -> StorageResult<()> {
+        self.lock().remove_credential(credential_id);
+        Ok(())
+    }
+
+    async fn upsert_org_by_id(
+        &self,
+        org_id: i64,
+        name: &str,
+        enabled: bool,
+        admin_key: Option<&str>,
+    ) -> StorageResult<()> {
+        let now = OffsetDateTime::now_utc();
+        let mut state = self.lock();
+        let row = state
+            .organizations
+            .entry(org_id)
+            .or_insert_with(|| OrganizationRow {
+                id: org_id,
+                name: String::new(),
+                enabled,
+                admin_key: None,
+                omit_bodies: false,
+                rpm_limit: None,
+                tpm_limit: None,
+                monthly_token_budget: None,
+                default_provider: None,
+                default_model: None,
+                created_at: now,
+                updated_at: now,
+            });
+        row.name = name.to_string();
+        row.enabled = enabled;
+        row.admin_key = admin_key.map(|s| s.to_string());
+        row.updated_at = now;
+        Ok(())
+    }
+
+    async fn set_org_enabled(&self, org_id: i64, enabled: bool) -> StorageResult<()> {
+        if let Some(row) = self.lock().organizations.get_mut(&org_id) {
+            row.enabled = enabled;
+            row.updated_at = OffsetDateTime::now_utc();
+        }
+        Ok(())
+    }
+
+    async fn set_org_omit_bodies(&self, org_id: i64, omit_bodies: bool) -> StorageResult<()> {
+        if let Some(row) = self.lock().organizations.get_mut(&org_id) {
+            row.omit_bodies = omit_bodies;
+            row.updated_at = OffsetDateTime::now_utc();
+        }
+        Ok(())
+    }
+
+    async fn update_org_limits(
+        &self,
+        org_id: i64,
+        rpm_limit: Option<i64>,
+        tpm_limit: Option<i64>,
+        monthly_token_budget: Option<i64>,
+    ) -> StorageResult<()> {
+        if let Some(row) = self.lock().organizations.get_mut(&org_id) {
+            row.rpm_limit = rpm_limit;
+            row.tpm_limit = tpm_limit;
+            row.monthly_token_budget = monthly_token_budget;
+            row.updated_at = OffsetDateTime::now_utc();
+        }
+        Ok(())
+    }
+
+    async fn update_org_defaults(
+        &self,
+        org_id: i64,
+        default_provider: Option<&str>,
+        default_model: Option<&str>,
+    ) -> StorageResult<()> {
+        if let Some(row) = self.lock().organizations.get_mut(&org_id) {
+            row.default_provider = default_provider.map(|s| s.to_string());
+            row.default_model = default_model.map(|s| s.to_string());
+            row.updated_at = OffsetDateTime::now_utc();
+        }
+        Ok(())
+    }
+
+    async fn delete_org(&self, org_id: i64) -> StorageResult<()> {
+        let now = OffsetDateTime::now_utc();
+        let mut state = self.lock();
+        for user in state.users.values_mut() {
+            if user.org_id == Some(org_id) {
+                user.org_id = None;
+                user.updated_at = now;
+            }
+        }
+        state.org_grants.retain(|_, grant| grant.org_id != org_id);
+        state.organizations.remove(&org_id);
+        Ok(())
+    }
+
+    async fn insert_org_grant(
+        &self,
+        org_id: i64,
+        provider_name: &str,
+        credential_id: Option<i64>,
+    ) -> StorageResult<i64> {
+        let mut state = self.lock();
+        let provider_id = state.provider_id(provider_name)?;
+        let id = state.next_id();
+        state.org_grants.insert(
+            id,
+            OrgGrantRow {
+                id,
+                org_id,
+                provider_id,
+                credential_id,
+                created_at: OffsetDateTime::now_utc(),
+            },
+        );
+        Ok(id)
+    }
+
+    async fn delete_org_grant(&self, grant_id: i64) -> StorageResult<()> {
+        self.lock().org_grants.remove(&grant_id);
+        Ok(())
+    }
+
+    async fn upsert_user_by_id(
+        &self,
+        user_id: i64,
+        name: &str,
+        enabled: bool,
+    ) -> StorageResult<()> {
+        self.lock().upsert_user(user_id, name, enabled);
+        Ok(())
+    }
+
+    async fn set_user_enabled(&self, user_id: i64, enabled: bool) -> StorageResult<()> {
+        if let Some(row) = self.lock().users.get_mut(&user_id) {
+            row.enabled = enabled;
+            row.updated_at = OffsetDateTime::now_utc();
+        }
+        Ok(())
+    }
+
+    async fn set_user_org(&self, user_id: i64, org_id: Option<i64>) -> StorageResult<()> {
+        if let Some(row) = self.lock().users.get_mut(&user_id) {
+            row.org_id = org_id;
+            row.updated_at = OffsetDateTime::now_utc();
+        }
+        Ok(())
+    }
+
+    async fn delete_user(&self, user_id: i64) -> StorageResult<()> {
+        let mut state = self.lock();
+        state.users.remove(&user_id);
+        state.user_keys.retain(|_, key| key.user_id != user_id);
+        Ok(())
+    }
+
+    async fn insert_user_key(
+        &self,
+        user_id: i64,
+        api_key: &str,
+        label: Option<&str>,
+        enabled: bool,
+    ) -> StorageResult<i64> {
+        self.lock()
+            .insert_user_key(user_id, api_key, label, enabled)
+    }
+
+    async fn set_user_key_enabled(&self, user_key_id: i64, enabled: bool) -> StorageResult<()> {
+        if let Some(row) = self.lock().user_keys.get_mut(&user_key_id) {
+            row.enabled = enabled;
+            row.updated_at = OffsetDateTime::now_utc();
+        }
+        Ok(())
+    }
+
+    async fn set_user_key_omit_bodies(
+        &self,
+        user_key_id: i64,
+        omit_bodies: bool,
+    ) -> StorageResult<()> {
+        if let Some(row) = self.lock().user_keys.get_mut(&user_key_id) {
+            row.omit_bodies = omit_bodies;
+            row.updated_at = OffsetDateTime::now_utc();
+        }
+        Ok(())
+    }
+
+    async fn set_user_key_body_sample_percent(
+        &self,
+        user_key_id: i64,
+        body_sample_percent: Option<i64>,
+    ) -> StorageResult<()> {
+        if let Some(row) = self.lock().user_keys.get_mut(&user_key_id) {
+            row.body_sample_percent = body_sample_percent;
+            row.updated_at = OffsetDateTime::now_utc();
+        }
+        Ok(())
+    }
+
+    async fn update_user_key_label(
+        &self,
+        user_key_id: i64,
+        label: Option<&str>,
+    ) -> StorageResult<()> {
+        if let Some(row) = self.lock().user_keys.get_mut(&user_key_id) {
+            row.label = label.map(|s| s.to_string());
+            row.updated_at = OffsetDateTime::now_utc();
+        }
+        Ok(())
+    }
+
+    async fn update_user_key_limits(
+        &self,
+        user_key_id: i64,
+        rpm_limit: Option<i64>,
+        tpm_limit: Option<i64>,
+        stream_tps_limit: Option<i64>,
+        max_output_tokens: Option<i64>,
+    ) -> StorageResult<()> {
+        if let Some(row) = self.lock().user_keys.get_mut(&user_key_id) {
+            row.rpm_limit = rpm_limit;
+            row.tpm_limit = tpm_limit;
+            row.stream_tps_limit = stream_tps_limit;
+            row.max_output_tokens = max_output_tokens;
+            row.updated_at = OffsetDateTime::now_utc();
+        }
+        Ok(())
+    }
+
+    async fn update_user_key_defaults(
+        &self,
+        user_key_id: i64,
+        default_provider: Option<&str>,
+        default_model: Option<&str>,
+    ) -> StorageResult<()> {
+        if let Some(row) = self.lock().user_keys.get_mut(&user_key_id) {
+            row.default_provider = default_provider.map(|s| s.to_string());
+            row.default_model = default_model.map(|s| s.to_string());
+            row.updated_at = OffsetDateTime::now_utc();
+        }
+        Ok(())
+    }
+
+    async fn update_user_key_mcp_policy(
+        &self,
+        user_key_id: i64,
+        mcp_policy: Option<&serde_json::Value>,
+    ) -> StorageResult<()> {
+        if let Some(row) = self.lock().user_keys.get_mut(&user_key_id) {
+            row.mcp_policy = mcp_policy.cloned();
+            row.updated_at = OffsetDateTime::now_utc();
+        }
+        Ok(())
+    }
+
+    async fn update_user_key_moderation_policy(
+        &self,
+        user_key_id: i64,
+        moderation_policy: Option<&serde_json::Value>,
+    ) -> StorageResult<()> {
+        if let Some(row) = self.lock().user_keys.get_mut(&user_key_id) {
+            row.moderation_policy = moderation_policy.cloned();
+            row.updated_at = OffsetDateTime::now_utc();
+        }
+        Ok(())
+    }
+
+    async fn update_user_key_prelude_template(
+        &self,
+        user_key_id: i64,
+        prelude_template: Option<&str>,
+    ) -> StorageResult<()> {
+        if let Some(row) = self.lock().user_keys.get_mut(&user_key_id) {
+            row.prelude_template = prelude_template.map(str::to_string);
+            row.updated_at = OffsetDateTime::now_utc();
+        }
+        Ok(())
+    }
+
+    async fn update_user_key_scope(
+        &self,
+        user_key_id: i64,
+        key_scope: Option<&serde_json::Value>,
+        expires_at: Option<OffsetDateTime>,
+    ) -> StorageResult<()> {
+        if let Some(row) = self.lock().user_keys.get_mut(&user_key_id) {
+            row.key_scope = key_scope.cloned();
+            row.expires_at = expires_at;
+            row.updated_at = OffsetDateTime::now_utc();
+        }
+        Ok(())
+    }
+
+    async fn update_user_key_allowed_origins(
+        &self,
+        user_key_id: i64,
+        allowed_origins: Option<&serde_json::Value>,
+    ) -> StorageResult<()> {
+        if let Some(row) = self.lock().user_keys.get_mut(&user_key_id) {
+            row.allowed_origins = allowed_origins.cloned();
+            row.updated_at = OffsetDateTime::now_utc();
+        }
+        Ok(())
+    }
+
+    async fn delete_user_key(&self, user_key_id: i64) -> StorageResult<()> {
+        self.lock().user_keys.remove(&user_key_id);
+        Ok(())
+    }
+
+    async fn upsert_model_profile(
+        &self,
+        name: &str,
+        provider: &str,
+        model: &str,
+        settings_json: &serde_json::Value,
+        enabled: bool,
+    ) -> StorageResult<i64> {
+        Ok(self
+            .lock()
+            .upsert_model_profile(name, provider, model, settings_json, enabled))
+    }
+
+    async fn delete_model_profile(&self, name: &str) -> StorageResult<()> {
+        self.lock().model_profiles.retain(|_, row| row.name != name);
+        Ok(())
+    }
+
+    async fn upsert_experiment(
+        &self,
+        name: &str,
+        arms_json: &serde_json::Value,
+        split: &str,
+        enabled: bool,
+    ) -> StorageResult<i64> {
+        Ok(self
+            .lock()
+            .upsert_experiment(name, arms_json, split, enabled))
+    }
+
+    async fn delete_experiment(&self, name: &str) -> StorageResult<()> {
+        self.lock().experiments.retain(|_, row| row.name != name);
+        Ok(())
+    }
+
+    async fn upsert_secret(&self, name: &str, value: &str) -> StorageResult<i64> {
+        Ok(self.lock().upsert_secret(name, value))
+    }
+
+    async fn delete_secret(&self, name: &str) -> StorageResult<()> {
+        self.lock().secrets.retain(|_, row| row.name != name);
+        Ok(())
+    }
+
+    async fn upsert_log_view(
+        &self,
+        name: &str,
+        filter_json: &serde_json::Value,
+        window_secs: i64,
+        alert_threshold: Option<i64>,
+    ) -> StorageResult<i64> {
+        let mut state = self.lock();
+        let now = OffsetDateTime::now_utc();
+        if let Some(row) = state.log_views.values_mut().find(|row| row.name == name) {
+            row.filter_json = filter_json.clone();
+            row.window_secs = window_secs;
+            row.alert_threshold = alert_threshold;
+            row.updated_at = now;
+            return Ok(row.id);
+        }
+        let id = state.next_id();
+        state.log_views.insert(
+            id,
+            LogViewRow {
+                id,
+                name: name.to_string(),
+                filter_json: filter_json.clone(),
+                window_secs,
+                alert_threshold,
+                created_at: now,
+                updated_at: now,
+            },
+        );
+        Ok(id)
+    }
+
+    async fn delete_log_view(&self, name: &str) -> StorageResult<()> {
+        self.lock().log_views.retain(|_, row| row.name != name);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TelemetryStorage for MemoryStorage {
+    async fn sync_telemetry(&self) -> StorageResult<()> {
+        Ok(())
+    }
+
+    async fn append_event(&self, event: &Event) -> StorageResult<()> {
+        let mut state = self.lock();
+        match event {
+            Event::Downstream(ev) => {
+                let (provider, operation, attempt_no) = derive_downstream_observability(
+                    &ev.request_method,
+                    &ev.request_path,
+                    ev.request_body.as_deref(),
+                );
+                let id = state.next_id();
+                push_capped(
+                    &mut state.downstream,
+                    StoredDownstream {
+                        request_query: ev.request_query.clone(),
+                        request_headers: ev.request_headers.to_vec(),
+                        record: LogRecord {
+                            id,
+                            kind: LogRecordKind::Downstream,
+                            at: system_time_to_offset(ev.at),
+                            trace_id: ev.trace_id.clone(),
+                            provider,
+                            credential_id: None,
+                            user_id: ev.user_id,
+                            user_key_id: ev.user_key_id,
+                            attempt_no,
+                            operation,
+                            request_method: ev.request_method.clone(),
+                            request_host: None,
+                            request_path: ev.request_path.clone(),
+                            request_body: ev.request_body.clone(),
+                            response_status: ev.response_status.map(i32::from),
+                            response_body: ev.response_body.clone(),
+                            error_kind: None,
+                            error_message: None,
+                            tags: ev.tags.clone(),
+                            anthropic_betas: Vec::new(),
+                            client_ip: ev.client_ip.clone(),
+                            country: ev.country.clone(),
+                            asn: ev.asn.map(i64::from),
+                        },
+                    },
+                );
+            }
+            Event::Upstream(ev) => {
+                let id = state.next_id();
+                let at = system_time_to_offset(ev.at);
+                let attempt_no = i32::try_from(ev.attempt_no).unwrap_or(i32::MAX);
+                push_capped(
+                    &mut state.upstream,
+                    LogRecord {
+                        id,
+                        kind: LogRecordKind::Upstream,
+                        at,
+                        trace_id: ev.trace_id.clone(),
+                        provider: Some(ev.provider.clone()),
+                        credential_id: ev.credential_id,
+                        user_id: ev.user_id,
+                        user_key_id: ev.user_key_id,
+                        attempt_no: Some(attempt_no),
+                        operation: Some(ev.operation.clone()),
+                        request_method: ev.request_method.clone(),
+                        request_host: ev.request_host.clone(),
+                        request_path: ev.request_path.clone(),
+                        request_body: ev.request_body.clone(),
+                        response_status: ev.response_status.map(i32::from),
+                        response_body: ev.response_body.clone(),
+                        error_kind: ev.error_kind.clone(),
+                        error_message: ev.error_message.clone(),
+                        tags: ev.tags.clone(),
+                        anthropic_betas: ev.anthropic_betas.clone(),
+                        client_ip: None,
+                        country: None,
+                        asn: None,
+                    },
+                );
+                if let Some(usage) = &ev.usage {
+                    let model = match ev.operation.as_str() {
+                        "GenerateContent" | "StreamGenerateContent" => {
+                            extract_model_for_usage(&ev.request_path, ev.request_body.as_deref())
+                        }
+                        _ => None,
+                    };
+                    push_capped(
+                        &mut state.usages,
+                        StoredUsage {
+                            trace_id: ev.trace_id.clone(),
+                            user_id: ev.user_id,
+                            tags: ev.tags.clone(),
+                            record: UsageRecord {
+                                upstream_request_id: id,
+                                at,
+                                provider: ev.provider.clone(),
+                                credential_id: ev.credential_id,
+                                attempt_no,
+                                operation: ev.operation.clone(),
+                                model,
+                                input_tokens: usage.input_tokens.map(i64::from),
+                                output_tokens: usage.output_tokens.map(i64::from),
+                                cache_read_input_tokens: usage
+                                    .cache_read_input_tokens
+                                    .map(i64::from),
+                                cache_creation_input_tokens: usage
+                                    .cache_creation_input_tokens
+                                    .map(i64::from),
+                                experiment: ev.experiment.clone(),
+                                experiment_arm: ev.experiment_arm.clone(),
+                            },
+                        },
+                    );
+                }
+            }
+            Event::Operational(ev) => {
+                let parts = OperationalParts::of(ev);
+                let id = state.next_id();
+                push_capped(
+                    &mut state.operational,
+                    OperationalEventRecord {
+                        id,
+                        at: extract_operational_at(ev),
+                        event_type: operational_event_type(ev).to_string(),
+                        payload: serde_json::to_value(ev)?,
+                        credential_id: parts.credential_id,
+                        model: parts.model,
+                        reason: parts.reason,
+                        until: parts.until.map(system_time_to_offset),
+                    },
+                );
+            }
+        }
+        Ok(())
+    }
+
+    async fn aggregate_usage_tokens(
+        &self,
+        filter: UsageAggregateFilter,
+    ) -> StorageResult<UsageAggregate> {
+        let state = self.lock();
+        let mut out = UsageAggregate::default();
+        for usage in state.usages.iter().filter(|usage| {
+            let row = &usage.record;
+            row.at >= filter.from
+                && row.at <= filter.to
+                && filter.provider.as_deref().is_none_or(|p| row.provider == p)
+                && filter
+                    .credential_id
+                    .is_none_or(|id| row.credential_id == Some(id))
+                && filter
+                    .user_ids
+                    .as_ref()
+                    .is_none_or(|ids| usage.user_id.is_some_and(|id| ids.contains(&id)))
+                && filter
+                    .model
+                    .as_deref()
+                    .is_none_or(|m| row.model.as_deref() == Some(m))
+                && filter
+                    .model_contains
+                    .as_deref()
+                    .is_none_or(|m| row.model.as_deref().is_some_and(|model| model.contains(m)))
+                && filter
+                    .tag
+                    .as_deref()
+                    .is_none_or(|tag| usage.tags.iter().any(|t| t == tag))
+                && filter
+                    .experiment
+                    .as_deref()
+                    .is_none_or(|e| row.experiment.as_deref() == Some(e))
+                && filter
+                    .experiment_arm
+                    .as_deref()
+                    .is_none_or(|a| row.experiment_arm.as_deref() == Some(a))
+        }) {
+            let row = &usage.record;
+            out.matched_rows += 1;
+            out.input_tokens += row.input_tokens.unwrap_or(0);
+            out.output_tokens += row.output_tokens.unwrap_or(0);
+            out.cache_read_input_tokens += row.cache_read_input_tokens.unwrap_or(0);
+            out.cache_creation_input_tokens += row.cache_creation_input_tokens.unwrap_or(0);
+        }
+        out.total_tokens = out.input_tokens
+            + out.output_tokens
+            + out.cache_read_input_tokens
+            + out.cache_creation_input_tokens;
+        Ok(out)
+    }
+
+    /// Kept rows hold their bodies, so they are always searchable.
+    fn set_log_search(&self, _enabled: bool) {}
+
+    async fn search_logs(&self, query: LogSearchQuery) -> StorageResult<Vec<LogSearchHit>> {
+        let state = self.lock();
+        let rows = state
+            .upstream
+            .iter()
+            .chain(state.downstream.iter().map(|row| &row.record))
+            .filter(|row| query.kind.is_none_or(|kind| row.kind == kind))
+            .filter(|row| row.at >= query.from && row.at <= query.to)
+            .filter(|row| {
+                query
+                    .user_ids
+                    .as_ref()
+                    .is_none_or(|ids| row.user_id.is_some_and(|user_id| ids.contains(&user_id)))
+            });
+        let mut hits: Vec<_> = rows
+            .filter_map(|row| {
+                let text = search_text(
+                    row.request_body.as_deref(),
+                    row.response_body.as_deref(),
+                    row.error_message.as_deref(),
+                )?;
+                matches(&text, &query.text).then(|| LogSearchHit {
+                    kind: row.kind,
+                    id: row.id,
+                    at: row.at,
+                    trace_id: row.trace_id.clone(),
+                    user_id: row.user_id,
+                    snippet: snippet(&text, &query.text),
+                })
+            })
+            .collect();
+        hits.sort_by_key(|hit| std::cmp::Reverse((hit.at, hit.id)));
+        hits.truncate(query.limit);
+        Ok(hits)
+    }
+
+    async fn query_logs(&self, filter: LogQueryFilter) -> StorageResult<LogQueryResult> {
+        if filter.limit == 0 {
+            return Ok(LogQueryResult {
+                rows: Vec::new(),
+                has_more: false,
+                next_cursor: None,
+            });
+        }
+        let take = filter.limit.saturating_add(1);
+
+        let query_upstream = match filter.kind {
+            Some(LogRecordKind::Upstream) => true,
+            Some(LogRecordKind::Downstream) => false,
+            None => filter.country.is_none() && filter.asn.is_none(),
+        };
+        let query_downstream = match filter.kind {
+            Some(LogRecordKind::Upstream) => false,
+            Some(LogRecordKind::Downstream) => true,
+            None => {
+                filter.provider.is_none()
+                    && filter.credential_id.is_none()
+                    && filter.operation.is_none()
+            }
+        };
+
+        let state = self.lock();
+        let key = |row: &LogRecord| (row.at, row.id);
+
+        let mut upstream_rows = Vec::new();
+        if query_upstream {
+            let matching = state.upstream.iter().filter(|row| {
+                log_row_matches(row, &filter)
+                    && filter
+                        .provider
+                        .as_deref()
+                        .is_none_or(|p| row.provider.as_deref() == Some(p))
+                    && filter
+                        .credential_id
+                        .is_none_or(|id| row.credential_id == Some(id))
+                    && filter
+                        .operation
+                        .as_deref()
+                        .is_none_or(|op| row.operation.as_deref() == Some(op))
+            });
+            upstream_rows = newest_first(matching, key)
+                .into_iter()
+                .take(take)
+                .map(|row| {
+                    let mut row = row.clone();
+                    if !filter.include_body {
+                        row.request_body = None;
+                        row.response_body = None;
+                    }
+                    row
+                })
+                .collect();
+        }
+
+        let mut downstream_rows = Vec::new();
+        if query_downstream {
+            let matching = state
+                .downstream
+                .iter()
+                .map(|row| &row.record)
+                .filter(|row| {
+                    log_row_matches(row, &filter)
+                        && filter
+                            .country
+                            .as_deref()
+                            .is_none_or(|c| row.country.as_deref() == Some(c))
+                        && filter.asn.is_none_or(|asn| row.asn == Some(asn))
+                });
+            downstream_rows = newest_first(matching, key)
+                .into_iter()
+                .take(take)
+                .map(|row| {
+                    let mut row = row.clone();
+                    let include_error_body = row.response_status.unwrap_or_default() >= 400;
+                    if !filter.include_body && !include_error_body {
+                        row.request_body = None;
+                        row.response_body = None;
+                    }
+                    row
+                })
+                .collect();
+        }
+        drop(state);
+
+        let mut rows = merge_sorted_logs(upstream_rows, downstream_rows, take);
+        let has_more = rows.len() > filter.limit;
+        if has_more {
+            rows.truncate(filter.limit);
+        }
+        let next_cursor = if has_more {
+            rows.last().map(|row| LogCursor {
+                at: row.at,
+                id: row.id,
+            })
+        } else {
+            None
+        };
+
+        Ok(LogQueryResult {
+            rows,
+            has_more,
+            next_cursor,
+        })
+    }
+
+    async fn get_downstream_request(
+        &self,
+        id: i64,
+    ) -> StorageResult<Option<DownstreamRequestRecord>> {
+        let state = self.lock();
+        Ok(state
+            .downstream
+            .iter()
+            .find(|row| row.record.id == id)
+            .map(|row| DownstreamRequestRecord {
+                id,
+                at: row.record.at,
+                trace_id: row.record.trace_id.clone(),
+                user_id: row.record.user_id,
+                user_key_id: row.record.user_key_id,
+                request_method: row.record.request_method.clone(),
+                request_path: row.record.request_path.clone(),
+                request_query: row.request_query.clone(),
+                request_headers: row.request_headers.clone(),
+                request_body: row.record.request_body.clone(),
+                tags: row.record.tags.clone(),
+            }))
+    }
+
+    async fn list_trace_usages(&self, trace_id: &str) -> StorageResult<Vec<UsageRecord>> {
+        let state = self.lock();
+        let mut rows: Vec<UsageRecord> = state
+            .usages
+            .iter()
+            .filter(|usage| usage.trace_id.as_deref() == Some(trace_id))
+            .map(|usage| usage.record.clone())
+            .collect();
+        rows.sort_by_key(|row| (row.at, row.upstream_request_id));
+        Ok(rows)
+    }
+
+    async fn list_credential_usages(
+        &self,
+        credential_id: i64,
+        from: OffsetDateTime,
+        to: OffsetDateTime,
+    ) -> StorageResult<Vec<UsageRecord>> {
+        let state = self.lock();
+        let mut rows: Vec<UsageRecord> = state
+            .usages
+            .iter()
+            .map(|usage| &usage.record)
+            .filter(|row| {
+                row.credential_id == Some(credential_id) && row.at >= from && row.at <= to
+            })
+            .cloned()
+            .collect();
+        rows.sort_by_key(|row| (row.at, row.upstream_request_id));
+        Ok(rows)
+    }
+
+    async fn list_credential_outcomes(
+        &self,
+        credential_id: i64,
+        from: OffsetDateTime,
+        to: OffsetDateTime,
+    ) -> StorageResult<Vec<UpstreamOutcome>> {
+        let state = self.lock();
+        let mut rows: Vec<UpstreamOutcome> = state
+            .upstream
+            .iter()
+            .filter(|row| {
+                row.credential_id == Some(credential_id) && row.at >= from && row.at <= to
+            })
+            .map(|row| UpstreamOutcome {
+                id: row.id,
+                at: row.at,
+                trace_id: row.trace_id.clone(),
+                operation: row.operation.clone().unwrap_or_default(),
+                response_status: row.response_status,
+                error_kind: row.error_kind.clone(),
+                error_message: row.error_message.clone(),
+            })
+            .collect();
+        rows.sort_by_key(|row| (row.at, row.id));
+        Ok(rows)
+    }
+
+    async fn list_credential_cooldowns(
+        &self,
+        credential_id: i64,
+        from: OffsetDateTime,
+        to: OffsetDateTime,
+    ) -> StorageResult<Vec<OperationalEventRecord>> {
+        let state = self.lock();
+        let mut rows: Vec<OperationalEventRecord> = state
+            .operational
+            .iter()
+            .filter(|row| {
+                row.credential_id == Some(credential_id) && row.at >= from && row.at <= to
+            })
+            .cloned()
+            .collect();
+        rows.sort_by_key(|row| (row.at, row.id));
+        Ok(rows)
+    }
+
+    async fn query_operational_events(
+        &self,
+        filter: OperationalEventFilter,
+    ) -> StorageResult<OperationalEventQueryResult> {
+        if filter.limit == 0 {
+            return Ok(OperationalEventQueryResult {
+                rows: Vec::new(),
+                has_more: false,
+                next_cursor: None,
+            });
+        }
+        let state = self.lock();
+        let matching = state.operational.iter().filter(|row| {
+            row.at >= filter.from
+                && row.at <= filter.to
+                && filter
+                    .event_type
+                    .as_deref()
+                    .is_none_or(|t| row.event_type == t)
+                && filter
+                    .credential_id
+                    .is_none_or(|id| row.credential_id == Some(id))
+                && filter
+                    .credential_ids
+                    .as_ref()
+                    .is_none_or(|ids| row.credential_id.is_some_and(|id| ids.contains(&id)))
+                && filter
+                    .model
+                    .as_deref()
+                    .is_none_or(|m| row.model.as_deref() == Some(m))
+                && before_cursor(row.at, row.id, filter.cursor)
+        });
+        let mut rows: Vec<OperationalEventRecord> = newest_first(matching, |row| (row.at, row.id))
+            .into_iter()
+            .take(filter.limit.saturating_add(1))
+            .cloned()
+            .collect();
+
+        let has_more = rows.len() > filter.limit;
+        rows.truncate(filter.limit);
+        let next_cursor = if has_more {
+            rows.last().map(|row| LogCursor {
+                at: row.at,
+                id: row.id,
+            })
+        } else {
+            None
+        };
+        Ok(OperationalEventQueryResult {
+            rows,
+            has_more,
+            next_cursor,
+        })
+    }
+
+    async fn upsert_stats_hourly(&self, rows: &[StatsHourlyRow]) -> StorageResult<()> {
+        let mut state = self.lock();
+        for row in rows {
+            state.stats_hourly.insert(
+                (row.hour, row.dimension.clone(), row.dimension_key.clone()),
+                row.clone(),
+            );
+        }
+        Ok(())
+    }
+
+    async fn load_stats_hourly(&self, since: OffsetDateTime) -> StorageResult<Vec<StatsHourlyRow>> {
+        Ok(self
+            .lock()
+            .stats_hourly
+            .values()
+            .filter(|row| row.hour >= since)
+            .cloned()
+            .collect())
+    }
+
+    async fn append_job_run(&self, record: &JobRunRecord) -> StorageResult<i64> {
+        let mut state = self.lock();
+        let id = state.next_id();
+        push_capped(
+            &mut state.job_runs,
+            JobRunRecord {
+                id,
+                ..record.clone()
+            },
+        );
+        Ok(id)
+    }
+
+    async fn list_job_runs(
+        &self,
+        job: Option<&str>,
+        limit: usize,
+    ) -> StorageResult<Vec<JobRunRecord>> {
+        Ok(self
+            .lock()
+            .job_runs
+            .iter()
+            .rev()
+            .filter(|run| job.is_none_or(|job| run.job == job))
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+
+    async fn append_debug_capture(&self, record: &DebugCaptureRecord) -> StorageResult<i64> {
+        let mut state = self.lock();
+        let id = state.next_id();
+        push_capped(
+            &mut state.debug_captures,
+            DebugCaptureRecord {
+                id,
+                ..record.clone()
+            },
+        );
+        Ok(id)
+    }
+
+    async fn list_debug_captures(
+        &self,
+        user_key_id: Option<i64>,
+        trace_id: Option<&str>,
+        limit: usize,
+    ) -> StorageResult<Vec<DebugCaptureRecord>> {
+        Ok(self
+            .lock()
+            .debug_captures
+            .iter()
+            .rev()
+            .filter(|row| user_key_id.is_none_or(|id| row.user_key_id == id))
+            .filter(|row| trace_id.is_none_or(|trace_id| row.trace_id == trace_id))
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+
+    async fn purge_debug_captures(&self, now: OffsetDateTime) -> StorageResult<u64> {
+        let mut state = self.lock();
+        let before = state.debug_captures.len();
+        state.debug_captures.retain(|row| row.expires_at > now);
+        Ok((before - state.debug_captures.len()) as u64)
+    }
+
+    async fn append_mcp_tool_call(&self, record: &McpToolCallRecord) -> StorageResult<i64> {
+        let mut state = self.lock();
+        let id = state.next_id();
+        push_capped(
+            &mut state.mcp_tool_calls,
+            McpToolCallRecord {
+                id,
+                ..record.clone()
+            },
+        );
+        Ok(id)
+    }
+
+    async fn list_mcp_tool_calls(
+        &self,
+        user_key_id: Option<i64>,
+        limit: usize,
+    ) -> StorageResult<Vec<McpToolCallRecord>> {
+        Ok(self
+            .lock()
+            .mcp_tool_calls
+            .iter()
+            .rev()
+            .filter(|row| user_key_id.is_none_or(|id| row.user_key_id == id))
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+
+    async fn append_aborted_stream(&self, record: &AbortedStreamRecord) -> StorageResult<i64> {
+        let mut state = self.lock();
+        let id = state.next_id();
+        push_capped(
+            &mut state.aborted_streams,
+            AbortedStreamRecord {
+                id,
+                ..record.clone()
+            },
+        );
+        Ok(id)
+    }
+
+    async fn list_aborted_streams(
+        &self,
+        user_key_id: Option<i64>,
+        limit: usize,
+    ) -> StorageResult<Vec<AbortedStreamRecord>> {
+        Ok(self
+            .lock()
+            .aborted_streams
+            .iter()
+            .rev()
+            .filter(|row| user_key_id.is_none_or(|id| row.user_key_id == id))
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+
+    async fn append_tool_call(&self, record: &ToolCallRecord) -> StorageResult<i64> {
+        let mut state = self.lock();
+        let id = state.next_id();
+        push_capped(
+            &mut state.tool_calls,
+            ToolCallRecord {
+                id,
+                ..record.clone()
+            },
+        );
+        Ok(id)
+    }
+
+    async fn complete_tool_call(
+        &self,
+        id: i64,
+        result_at: OffsetDateTime,
+        latency_ms: i64,
+    ) -> StorageResult<()> {
+        if let Some(row) = self.lock().tool_calls.iter_mut().find(|row| row.id == id) {
+            row.result_at = Some(result_at);
+            row.latency_ms = Some(latency_ms);
+        }
+        Ok(())
+    }
+
+    async fn list_tool_calls(
+        &self,
+        user_key_id: Option<i64>,
+        tool_name: Option<&str>,
+        limit: usize,
+    ) -> StorageResult<Vec<ToolCallRecord>> {
+        Ok(self
+            .lock()
+            .tool_calls
+            .iter()
+            .rev()
+            .filter(|row| user_key_id.is_none_or(|id| row.user_key_id == id))
+            .filter(|row| tool_name.is_none_or(|name| row.tool_name == name))
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+
+    async fn append_incident(&self, record: &IncidentRecord) -> StorageResult<i64> {
+        let mut state = self.lock();
+        let id = state.next_id();
+        push_capped(
+            &mut state.incidents,
+            IncidentRecord {
+                id,
+                ..record.clone()
+            },
+        );
+        Ok(id)
+    }
+
+    async fn update_incident(&self, record: &IncidentRecord) -> StorageResult<()> {
+        if let Some(row) = self
+            .lock()
+            .incidents
+            .iter_mut()
+            .find(|row| row.id == record.id)
+        {
+            *row = record.clone();
+        }
+        Ok(())
+    }
+
+    async fn list_incidents(
+        &self,
+        provider: Option<&str>,
+        open_only: bool,
+        limit: usize,
+    ) -> StorageResult<Vec<IncidentRecord>> {
+        Ok(self
+            .lock()
+            .incidents
+            .iter()
+            .rev()
+            .filter(|row| provider.is_none_or(|provider| row.provider == provider))
+            .filter(|row| !open_only || row.ended_at.is_none())
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seed() -> MemorySeed {
+        serde_json::from_value(serde_json::json!({
+            "providers": [{"name": "openai", "config_json": {"kind": "openai"}}],
+            "credentials": [{"provider": "openai", "secret_json": {"api_key": "sk-1"}}],
+            "users": [{"id": 7, "name": "alice"}],
+            "user_keys": [{"user_id": 7, "api_key": "k-7", "rpm_limit": 10}]
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn seed_populates_snapshot() {
+        let storage = MemoryStorage::from_seed(seed()).unwrap();
+        let snapshot = storage.load_snapshot().await.unwrap();
+        assert_eq!(snapshot.providers.len(), 1);
+        assert_eq!(snapshot.credentials.len(), 1);
+        assert_eq!(
+            snapshot.credentials[0].provider_id,
+            snapshot.providers[0].id
+        );
+        assert_eq!(snapshot.user_keys[0].rpm_limit, Some(10));
+    }
+
+    #[tokio::test]
+    async fn delete_provider_cascades_credentials_and_grants() {
+        let storage = MemoryStorage::from_seed(seed()).unwrap();
+        storage
+            .upsert_org_by_id(1, "org", true, None)
+            .await
+            .unwrap();
+        storage.insert_org_grant(1, "openai", None).await.unwrap();
+        storage.delete_provider("openai").await.unwrap();
+        let snapshot = storage.load_snapshot().await.unwrap();
+        assert!(snapshot.credentials.is_empty());
+        assert!(snapshot.org_grants.is_empty());
+    }
+
+    #[tokio::test]
+    async fn model_profile_upsert_replaces_by_name() {
+        let storage = MemoryStorage::from_seed(seed()).unwrap();
+        let settings = serde_json::json!({ "temperature_max": 0.5 });
+        let id = storage
+            .upsert_model_profile("fast", "openai", "gpt-4o-mini", &settings, true)
+            .await
+            .unwrap();
+        let again = storage
+            .upsert_model_profile("fast", "openai", "gpt-4.1-mini", &settings, false)
+            .await
+            .unwrap();
+        assert_eq!(id, again);
+        let snapshot = storage.load_snapshot().await.unwrap();
+        assert_eq!(snapshot.model_profiles.len(), 1);
+        assert_eq!(snapshot.model_profiles[0].model, "gpt-4.1-mini");
+        assert!(!snapshot.model_profiles[0].enabled);
+
+        storage.delete_model_profile("fast").await.unwrap();
+        let snapshot = storage.load_snapshot().await.unwrap();
+        assert!(snapshot.model_profiles.is_empty());
+    }
+
+    fn usage_event(user_id: i64, arm: &str, tokens: u32) -> Event {
+        Event::Upstream(gproxy_provider_core::UpstreamEvent {
+            trace_id: None,
+            at: std::time::SystemTime::now(),
+            user_id: Some(user_id),
+            user_key_id: Some(3),
+            provider: "openai".to_string(),
+            credential_id: Some(1),
+            internal: false,
+            attempt_no: 1,
+            operation: "GenerateContent".to_string(),
+            request_method: "POST".to_string(),
+            request_headers: gproxy_provider_core::Headers::new(),
+            request_host: None,
+            request_path: "/v1/chat/completions".to_string(),
+            request_query: None,
+            request_body: None,
+            response_status: Some(200),
+            response_headers: gproxy_provider_core::Headers::new(),
+            response_body: None,
+            usage: Some(gproxy_provider_core::UsageSummary {
+                input_tokens: Some(tokens),
+                ..Default::default()
+            }),
+            error_kind: None,
+            error_message: None,
+            transport_kind: None,
+            tags: Vec::new(),
+            anthropic_betas: Vec::new(),
+            model: None,
+            latency_ms: None,
+            experiment: Some("chat-ab".to_string()),
+            experiment_arm: Some(arm.to_string()),
+            transform: None,
+        })
+    }
+
+    #[tokio::test]
+    async fn usage_aggregate_filters_by_experiment_arm() {
+        let storage = MemoryStorage::new();
+        for (arm, tokens) in [("a", 10), ("b", 20), ("b", 5)] {
+            storage
+                .append_event(&usage_event(7, arm, tokens))
+                .await
+                .unwrap();
+        }
+
+        let now = OffsetDateTime::now_utc();
+        let aggregate = storage
+            .aggregate_usage_tokens(UsageAggregateFilter {
+                from: now - time::Duration::minutes(1),
+                to: now + time::Duration::minutes(1),
+                provider: None,
+                credential_id: None,
+                user_ids: None,
+                model: None,
+                model_contains: None,
+                tag: None,
+                experiment: Some("chat-ab".to_string()),
+                experiment_arm: Some("b".to_string()),
+            })
+            .await
+            .unwrap();
+        assert_eq!(aggregate.matched_rows, 2);
+        assert_eq!(aggregate.input_tokens, 25);
+    }
+
+    #[tokio::test]
+    async fn org_scoped_queries_only_see_member_users() {
+        let storage = MemoryStorage::new();
+        for (user_id, tokens) in [(7, 10), (8, 20), (9, 40)] {
+            storage
+                .append_event(&usage_event(user_id, "a", tokens))
+                .await
+                .unwrap();
+        }
+        let now = OffsetDateTime::now_utc();
+        let logs = |user_ids: Option<Vec<i64>>| LogQueryFilter {
+            from: now - time::Duration::minutes(1),
+            to: now + time::Duration::minutes(1),
+            kind: None,
+            provider: None,
+            credential_id: None,
+            user_id: None,
+            user_ids,
+            user_key_id: None,
+            trace_id: None,
+            operation: None,
+            tag: None,
+            request_path_contains: None,
+            status_min: None,
+            status_max: None,
+            country: None,
+            asn: None,
+            limit: 100,
+            cursor: None,
+            include_body: false,
+        };
+        let users = |rows: Vec<LogRecord>| {
+            let mut users: Vec<_> = rows.into_iter().filter_map(|row| row.user_id).collect();
+            users.sort_unstable();
+            users
+        };
+
+        let rows = storage
+            .query_logs(logs(Some(vec![7, 9])))
+            .await
+            .unwrap()
+            .rows;
+        assert_eq!(users(rows), vec![7, 9]);
+        let rows = storage
+            .query_logs(logs(Some(Vec::new())))
+            .await
+            .unwrap()
+            .rows;
+        assert!(rows.is_empty());
+        let rows = storage.query_logs(logs(None)).await.unwrap().rows;
+        assert_eq!(users(rows), vec![7, 8, 9]);
+
+        let aggregate = storage
+            .aggregate_usage_tokens(UsageAggregateFilter {
+                from: now - time::Duration::minutes(1),
+                to: now + time::Duration::minutes(1),
+                provider: None,
+                credential_id: None,
+                user_ids: Some(vec![8, 9]),
+                model: None,
+                model_contains: None,
+                tag: None,
+                experiment: None,
+                experiment_arm: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(aggregate.input_tokens, 60);
+    }
+
+    #[tokio::test]
+    async fn duplicate_user_key_is_rejected() {
+        let storage = MemoryStorage::from_seed(seed()).unwrap();
+        assert!(storage.insert_user_key(7, "k-7", None, true).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn job_runs_list_newest_first_per_job() {
+        let storage = MemoryStorage::new();
+        let now = OffsetDateTime::now_utc();
+        for (job, ok) in [
+            ("stats_flush", true),
+            ("other", true),
+            ("stats_flush", false),
+        ] {
+            storage
+                .append_job_run(&JobRunRecord {
+                    id: 0,
+                    job: job.to_string(),
+                    trigger: "schedule".to_string(),
+                    started_at: now,
+                    finished_at: now,
+                    ok,
+                    message: None,
+                })
+                .await
+                .unwrap();
+        }
+        let runs = storage
+            .list_job_runs(Some("stats_flush"), 10)
+            .await
+            .unwrap();
+        assert_eq!(runs.len(), 2);
+        assert!(!runs[0].ok);
+        assert!(runs[0].id > runs[1].id);
+        assert_eq!(storage.list_job_runs(None, 1).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn debug_captures_filter_by_trace_and_expire() {
+        let storage = MemoryStorage::new();
+        let now = OffsetDateTime::now_utc();
+        for (trace_id, ttl) in [("t-1", 60), ("t-1", 60), ("t-2", -1)] {
+            storage
+                .append_debug_capture(&DebugCaptureRecord {
+                    id: 0,
+                    trace_id: trace_id.to_string(),
+                    user_key_id: 7,
+                    kind: "upstream".to_string(),
+                    event_json: serde_json::json!({ "trace_id": trace_id }),
+                    at: now,
+                    expires_at: now + time::Duration::seconds(ttl),
+                })
+                .await
+                .unwrap();
+        }
+        let t1 = storage
+            .list_debug_captures(Some(7), Some("t-1"), 10)
+            .await
+            .unwrap();
+        assert_eq!(t1.len(), 2);
+        assert_eq!(storage.purge_debug_captures(now).await.unwrap(), 1);
+        assert_eq!(
+            storage
+                .list_debug_captures(None, None, 10)
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn mcp_tool_calls_list_newest_first_per_key() {
+        let storage = MemoryStorage::new();
+        for (user_key_id, tool_name) in [(1, "search"), (2, "fetch"), (1, "read")] {
+            storage
+                .append_mcp_tool_call(&McpToolCallRecord {
+                    id: 0,
+                    trace_id: None,
+                    user_key_id,
+                    provider: "openai".to_string(),
+                    server_label: "docs".to_string(),
+                    tool_name: tool_name.to_string(),
+                    arguments: Some("{}".to_string()),
+                    output: None,
+                    error: None,
+                    at: OffsetDateTime::now_utc(),
+                })
+                .await
+                .unwrap();
+        }
+        let calls = storage.list_mcp_tool_calls(Some(1), 10).await.unwrap();
+        let tools: Vec<_> = calls.iter().map(|c| c.tool_name.as_str()).collect();
+        assert_eq!(tools, ["read", "search"]);
+        assert_eq!(storage.list_mcp_tool_calls(None, 2).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn aborted_streams_list_newest_first_per_key() {
+        let storage = MemoryStorage::new();
+        for (user_key_id, reason) in [
+            (1, "stream_forward_error"),
+            (2, "stream_interrupted"),
+            (1, "upstream_stream_idle_timeout"),
+        ] {
+            storage
+                .append_aborted_stream(&AbortedStreamRecord {
+                    id: 0,
+                    trace_id: None,
+                    user_key_id,
+                    provider: "claude".to_string(),
+                    credential_id: Some(3),
+                    model: Some("claude-sonnet-4".to_string()),
+                    reason: reason.to_string(),
+                    output_chars: 40,
+                    input_tokens: Some(12),
+                    output_tokens: 10,
+                    duration_ms: 900,
+                    at: OffsetDateTime::now_utc(),
+                })
+                .await
+                .unwrap();
+        }
+        let aborts = storage.list_aborted_streams(Some(1), 10).await.unwrap();
+        let reasons: Vec<_> = aborts.iter().map(|a| a.reason.as_str()).collect();
+        assert_eq!(
+            reasons,
+            ["upstream_stream_idle_timeout", "stream_forward_error"]
+        );
+        assert_ne!(aborts[0].id, aborts[1].id);
+        assert_eq!(
+            storage.list_aborted_streams(None, 2).await.unwrap().len(),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn tool_calls_filter_by_name_and_record_latency() {
+        let storage = MemoryStorage::new();
+        let at = OffsetDateTime::now_utc();
+        let mut ids = Vec::new();
+        for tool_name in ["get_weather", "search", "get_weather"] {
+            let id = storage
+                .append_tool_call(&ToolCallRecord {
+                    id: 0,
+                    trace_id: None,
+                    user_key_id: 1,
+                    provider: "openai".to_string(),
+                    model: Some("gpt-4.1".to_string()),
+                    proto: "openai_chat".to_string(),
+                    tool_name: tool_name.to_string(),
+                    call_id: Some(format!("call_{tool_name}")),
+                    arguments_bytes: 12,
+                    at,
+                    result_at: None,
+                    latency_ms: None,
+                })
+                .await
+                .unwrap();
+            ids.push(id);
+        }
+        storage
+            .complete_tool_call(ids[0], at + time::Duration::seconds(2), 2000)
+            .await
+            .unwrap();
+        let calls = storage
+            .list_tool_calls(None, Some("get_weather"), 10)
+            .await
+            .unwrap();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].latency_ms, None);
+        assert_eq!(calls[1].latency_ms, Some(2000));
+        assert!(
+            storage
+                .list_tool_calls(Some(2), None, 10)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn downstream_request_keeps_query_and_headers() {
+        let storage = MemoryStorage::new();
+        let event = Event::Downstream(gproxy_provider_core::DownstreamEvent {
+            trace_id: Some("t-1".to_string()),
+            at: std::time::SystemTime::now(),
+            user_id: Some(7),
+            user_key_id: Some(3),
+            request_method: "POST".to_string(),
+            request_headers: vec![("content-type".to_string(), "application/json".to_string())]
+                .into(),
+            request_path: "/v1/chat/completions".to_string(),
+            request_query: Some("stream=true".to_string()),
+            request_body: Some(b"{}".to_vec()),
+            response_status: Some(502),
+            response_headers: gproxy_provider_core::Headers::new(),
+            response_body: None,
+            tags: vec!["team-a".to_string()],
+            latency_ms: None,
+            client_ip: None,
+            country: None,
+            asn: None,
+        });
+        storage.append_event(&event).await.unwrap();
+
+        let record = storage.get_downstream_request(1).await.unwrap().unwrap();
+        assert_eq!(record.request_query.as_deref(), Some("stream=true"));
+        assert_eq!(record.request_headers.len(), 1);
+        assert_eq!(record.request_body.as_deref(), Some(&b"{}"[..]));
+        assert_eq!(record.tags, vec!["team-a".to_string()]);
+        assert!(storage.get_downstream_request(2).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn search_finds_phrases_in_bodies() {
+        let storage = MemoryStorage::new();
+        for (user_id, body) in [
+            (7, "Tell me about the Blue  Whale"),
+            (8, "blue whale facts"),
+        ] {
+            let event = Event::Downstream(gproxy_provider_core::DownstreamEvent {
+                trace_id: None,
+                at: std::time::SystemTime::now(),
+                user_id: Some(user_id),
+                user_key_id: None,
+                request_method: "POST".to_string(),
+                request_headers: gproxy_provider_core::Headers::new(),
+                request_path: "/v1/messages".to_string(),
+                request_query: None,
+                request_body: Some(body.as_bytes().to_vec()),
+                response_status: Some(200),
+                response_headers: gproxy_provider_core::Headers::new(),
+                response_body: None,
+                tags: Vec::new(),
+                latency_ms: None,
+                client_ip: None,
+                country: None,
+                asn: None,
+            });
+            storage.append_event(&event).await.unwrap();
+        }
+        let now = OffsetDateTime::now_utc();
+        let query = |text: &str, user_ids: Option<Vec<i64>>| LogSearchQuery {
+            text: text.to_string(),
+            from: now - time::Duration::hours(1),
+            to: now + time::Duration::hours(1),
+            kind: None,
+            user_ids,
+            limit: 10,
+        };
+        let hits = storage
+            .search_logs(query("blue whale", None))
+            .await
+            .unwrap();
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].user_id, Some(8));
+        let hits = storage
+            .search_logs(query("blue whale", Some(vec![7])))
+            .await
+            .unwrap();
+        assert_eq!(hits.len(), 1);
+        assert!(hits[0].snippet.contains("Blue  Whale"));
+        assert!(
+            storage
+                .search_logs(query("whale blue", None))
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn incidents_update_in_place_and_list_open() {
+        let storage = MemoryStorage::new();
+        let mut record = IncidentRecord {
+            id: 0,
+            provider: "claude".to_string(),
+            started_at: OffsetDateTime::now_utc(),
+            ended_at: None,
+            models: vec!["claude-sonnet-4".to_string()],
+            error_samples: vec!["http_status_429".to_string()],
+            refused_requests: 0,
+        };
+        record.id = storage.append_incident(&record).await.unwrap();
+        storage
+            .append_incident(&IncidentRecord {
+                provider: "openai".to_string(),
+                ..record.clone()
+            })
+            .await
+            .unwrap();
+        record.refused_requests = 12;
+        record.ended_at = Some(OffsetDateTime::now_utc());
+        storage.update_incident(&record).await.unwrap();
+
+        let open = storage.list_incidents(None, true, 10).await.unwrap();
+        assert_eq!(open.len(), 1);
+        assert_eq!(open[0].provider, "openai");
+        let claude = storage
+            .list_incidents(Some("claude"), false, 10)
+            .await
+            .unwrap();
+        assert_eq!(claude, vec![record]);
+    }
+}
@@ -1,6 +1,8 @@
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
-use sea_orm::sea_query::Index;
+use sea_orm::sea_query::{Index, LikeExpr};
 use sea_orm::{
     ActiveModelTrait, ActiveValue, ConnectionTrait, Database, DatabaseBackend, DatabaseConnection,
     EntityTrait, FromQueryResult, QueryOrder, QuerySelect, Schema,
@@ -13,12 +15,18 @@ use gproxy_provider_core::Event;
 
 use crate::entities;
 use crate::snapshot::{
-    CredentialRow, GlobalConfigRow, ProviderRow, StorageSnapshot, UserKeyRow, UserRow,
+    CredentialRow, ExperimentRow, GlobalConfigRow, LogViewRow, ModelProfileRow, OrgGrantRow,
+    OrganizationRow, ProviderRow, SecretRow, StorageSnapshot, UserKeyRow, UserRow,
 };
 use crate::storage::{
-    LogCursor, LogQueryFilter, LogQueryResult, LogRecord, LogRecordKind, Storage, StorageError,
-    StorageResult, UsageAggregate, UsageAggregateFilter,
+    AbortedStreamRecord, ConfigStorage, DebugCaptureRecord, DownstreamRequestRecord,
+    IncidentRecord, JobRunRecord, LogCursor, LogQueryFilter, LogQueryResult, LogRecord,
+    LogRecordKind, LogSearchHit, LogSearchQuery, McpToolCallRecord, OperationalEventFilter,
+    OperationalEventQueryResult, OperationalEventRecord, StatsHourlyRow, StorageError,
+    StorageResult, TelemetryStorage, ToolCallRecord, UpstreamOutcome, UsageAggregate,
+    UsageAggregateFilter, UsageRecord,
 };
+use search::LogText;
 
 #[derive(Debug, FromQueryResult)]
 struct UsageAggregateRow {
@@ -41,10 +49,24 @@ struct UpstreamLogLiteRow {
     attempt_no: i32,
     operation: String,
     request_method: String,
+    request_host: Option<String>,
     request_path: String,
     response_status: Option<i32>,
     error_kind: Option<String>,
     error_message: Option<String>,
+    tags: Option<String>,
+    anthropic_betas: Option<String>,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct UpstreamOutcomeRow {
+    id: i64,
+    at: OffsetDateTime,
+    trace_id: Option<String>,
+    operation: String,
+    response_status: Option<i32>,
+    error_kind: Option<String>,
+    error_message: Option<String>,
 }
 
 #[derive(Debug, FromQueryResult)]
@@ -59,11 +81,19 @@ struct DownstreamLogLiteRow {
     request_body: Option<Vec<u8>>,
     response_status: Option<i32>,
     response_body: Option<Vec<u8>>,
+    tags: Option<String>,
+    client_ip: Option<String>,
+    country: Option<String>,
+    asn: Option<i64>,
 }
 
+mod search;
+
 #[derive(Clone)]
 pub struct SeaOrmStorage {
     db: DatabaseConnection,
+    /// Whether new log rows go into the `log_search` index.
+    log_search: Arc<AtomicBool>,
 }
 
 impl SeaOrmStorage {
@@ -73,16 +103,39 @@ impl SeaOrmStorage {
         if db.get_database_backend() == DatabaseBackend::Sqlite {
             db.execute_unprepared("PRAGMA foreign_keys = ON").await?;
         }
-        Ok(Self { db })
+        Ok(Self {
+            db,
+            log_search: Arc::new(AtomicBool::new(false)),
+        })
     }
 
     pub fn connection(&self) -> &DatabaseConnection {
         &self.db
     }
 
-    pub async fn provider_names(&self) -> StorageResult<Vec<String>> {
-        let rows = entities::Providers::find().all(&self.db).await?;
-        Ok(rows.into_iter().map(|m| m.name).collect())
+    /// Fills `credential_id`/`model` on events written before those columns existed.
+    async fn backfill_internal_event_columns(&self) -> StorageResult<()> {
+        use entities::internal_events::ActiveModel as InternalActive;
+        use entities::internal_events::Column as InternalColumn;
+
+        let rows = entities::InternalEvents::find()
+            .filter(InternalColumn::CredentialId.is_null())
+            .filter(InternalColumn::EventType.ne("user_key_auto_disabled"))
+            .all(&self.db)
+            .await?;
+        for row in rows {
+            let Ok(ev) = serde_json::from_value::<gproxy_provider_core::OperationalEvent>(
+                row.payload_json.clone(),
+            ) else {
+                continue;
+            };
+            let parts = OperationalParts::of(&ev);
+            let mut active: InternalActive = row.into();
+            active.credential_id = ActiveValue::Set(parts.credential_id);
+            active.model = ActiveValue::Set(parts.model);
+            active.update(&self.db).await?;
+        }
+        Ok(())
     }
 
     async fn backfill_usage_models(&self) -> StorageResult<()> {
@@ -136,6 +189,8 @@ impl SeaOrmStorage {
 
     async fn ensure_performance_indexes(&self) -> StorageResult<()> {
         use entities::downstream_requests::Column as DownstreamColumn;
+        use entities::internal_events::Column as InternalEventColumn;
+        use entities::stats_hourly::Column as StatsHourlyColumn;
         use entities::upstream_requests::Column as UpstreamColumn;
         use entities::upstream_usages::Column as UpstreamUsageColumn;
 
@@ -242,6 +297,14 @@ impl SeaOrmStorage {
                 .col(DownstreamColumn::Id)
                 .if_not_exists()
                 .to_owned(),
+            Index::create()
+                .name("idx_downstream_requests_country_at_id")
+                .table(entities::downstream_requests::Entity)
+                .col(DownstreamColumn::Country)
+                .col(DownstreamColumn::At)
+                .col(DownstreamColumn::Id)
+                .if_not_exists()
+                .to_owned(),
             Index::create()
                 .name("idx_upstream_usages_at")
                 .table(entities::upstream_usages::Entity)
@@ -269,6 +332,29 @@ impl SeaOrmStorage {
                 .col(UpstreamUsageColumn::At)
                 .if_not_exists()
                 .to_owned(),
+            Index::create()
+                .name("idx_internal_events_at_id")
+                .table(entities::internal_events::Entity)
+                .col(InternalEventColumn::At)
+                .col(InternalEventColumn::Id)
+                .if_not_exists()
+                .to_owned(),
+            Index::create()
+                .name("idx_internal_events_credential_at_id")
+                .table(entities::internal_events::Entity)
+                .col(InternalEventColumn::CredentialId)
+                .col(InternalEventColumn::At)
+                .col(InternalEventColumn::Id)
+                .if_not_exists()
+                .to_owned(),
+            Index::create()
+                .name("idx_stats_hourly_hour_dimension_key")
+                .table(entities::stats_hourly::Entity)
+                .col(StatsHourlyColumn::Hour)
+                .col(StatsHourlyColumn::Dimension)
+                .col(StatsHourlyColumn::DimensionKey)
+                .if_not_exists()
+                .to_owned(),
         ];
 
         for statement in statements {
@@ -280,23 +366,23 @@ impl SeaOrmStorage {
 }
 
 #[async_trait::async_trait]
-impl Storage for SeaOrmStorage {
+impl ConfigStorage for SeaOrmStorage {
     async fn sync(&self) -> StorageResult<()> {
         Schema::new(self.db.get_database_backend())
             .builder()
             .register(entities::GlobalConfig)
             .register(entities::Providers)
             .register(entities::Credentials)
+            .register(entities::Organizations)
             .register(entities::Users)
+            .register(entities::OrgProviderGrants)
             .register(entities::UserKeys)
-            .register(entities::DownstreamRequests)
-            .register(entities::UpstreamRequests)
-            .register(entities::UpstreamUsages)
-            .register(entities::InternalEvents)
+            .register(entities::ModelProfiles)
+            .register(entities::Experiments)
+            .register(entities::Secrets)
+            .register(entities::LogViews)
             .sync(&self.db)
             .await?;
-        self.ensure_performance_indexes().await?;
-        self.backfill_usage_models().await?;
         Ok(())
     }
 
@@ -311,10 +397,55 @@ impl Storage for SeaOrmStorage {
             config: GlobalConfig {
                 host: m.host,
                 port: u16::try_from(m.port).unwrap_or(8787),
+                admin_host: m.admin_host,
+                admin_port: m.admin_port.and_then(|v| u16::try_from(v).ok()),
                 admin_key: m.admin_key,
                 proxy: m.proxy,
                 dsn: m.dsn,
                 event_redact_sensitive: m.event_redact_sensitive.unwrap_or(true),
+                egress_interface: m.egress_interface,
+                tool_call_audit: m.tool_call_audit.unwrap_or(false),
+                max_response_body_bytes: m
+                    .max_response_body_bytes
+                    .and_then(|v| u64::try_from(v).ok())
+                    .unwrap_or(0),
+                truncate_oversize_responses: m.truncate_oversize_responses.unwrap_or(false),
+                event_log_max_body_bytes: m
+                    .event_log_max_body_bytes
+                    .and_then(|v| u64::try_from(v).ok())
+                    .unwrap_or(gproxy_common::DEFAULT_EVENT_LOG_MAX_BODY_BYTES),
+                log_search: m.log_search.unwrap_or(false),
+                trusted_proxies: m.trusted_proxies,
+                sse_resume: m.sse_resume.unwrap_or(false),
+                egress_local_address: m.egress_local_address,
+                egress_ip_family: m.egress_ip_family,
+                stream_idle_timeout_ms: m
+                    .stream_idle_timeout_ms
+                    .and_then(|v| u64::try_from(v).ok())
+                    .unwrap_or(30000),
+                geoip_asn_db: m.geoip_asn_db,
+                geoip_country_db: m.geoip_country_db,
+                alert_webhook_url: m.alert_webhook_url,
+                key_abuse_blocked_countries: m.key_abuse_blocked_countries,
+                key_abuse_min_rpm: m
+                    .key_abuse_min_rpm
+                    .and_then(|v| u64::try_from(v).ok())
+                    .unwrap_or(0),
+                key_abuse_max_ips: m
+                    .key_abuse_max_ips
+                    .and_then(|v| u64::try_from(v).ok())
+                    .unwrap_or(0),
+                response_compress_min_bytes: m
+                    .response_compress_min_bytes
+                    .and_then(|v| u64::try_from(v).ok())
+                    .unwrap_or(0),
+                coalesce_inflight_requests: m.coalesce_inflight_requests.unwrap_or(false),
+                stream_first_token_timeout_ms: m
+                    .stream_first_token_timeout_ms
+                    .and_then(|v| u64::try_from(v).ok())
+                    .unwrap_or(0),
+                stream_retry_on_interrupt: m.stream_retry_on_interrupt.unwrap_or(false),
+                native_error_format: m.native_error_format.unwrap_or(false),
             },
             updated_at: m.updated_at,
         }))
@@ -334,11 +465,46 @@ impl Storage for SeaOrmStorage {
                 let mut active: GlobalActive = model.into();
                 active.host = ActiveValue::Set(config.host.clone());
                 active.port = ActiveValue::Set(i32::from(config.port));
+                active.admin_host = ActiveValue::Set(config.admin_host.clone());
+                active.admin_port = ActiveValue::Set(config.admin_port.map(i32::from));
                 active.admin_key = ActiveValue::Set(config.admin_key.clone());
                 active.proxy = ActiveValue::Set(config.proxy.clone());
                 active.dsn = ActiveValue::Set(config.dsn.clone());
                 active.event_redact_sensitive =
                     ActiveValue::Set(Some(config.event_redact_sensitive));
+                active.native_error_format = ActiveValue::Set(Some(config.native_error_format));
+                active.stream_retry_on_interrupt =
+                    ActiveValue::Set(Some(config.stream_retry_on_interrupt));
+                active.stream_first_token_timeout_ms =
+                    ActiveValue::Set(i64::try_from(config.stream_first_token_timeout_ms).ok());
+                active.coalesce_inflight_requests =
+                    ActiveValue::Set(Some(config.coalesce_inflight_requests));
+                active.response_compress_min_bytes =
+                    ActiveValue::Set(i64::try_from(config.response_compress_min_bytes).ok());
+                active.key_abuse_max_ips =
+                    ActiveValue::Set(i64::try_from(config.key_abuse_max_ips).ok());
+                active.key_abuse_min_rpm =
+                    ActiveValue::Set(i64::try_from(config.key_abuse_min_rpm).ok());
+                active.key_abuse_blocked_countries =
+                    ActiveValue::Set(config.key_abuse_blocked_countries.clone());
+                active.alert_webhook_url = ActiveValue::Set(config.alert_webhook_url.clone());
+                active.geoip_country_db = ActiveValue::Set(config.geoip_country_db.clone());
+                active.geoip_asn_db = ActiveValue::Set(config.geoip_asn_db.clone());
+                active.stream_idle_timeout_ms =
+                    ActiveValue::Set(i64::try_from(config.stream_idle_timeout_ms).ok());
+                active.egress_ip_family = ActiveValue::Set(config.egress_ip_family.clone());
+                active.egress_local_address = ActiveValue::Set(config.egress_local_address.clone());
+                active.egress_interface = ActiveValue::Set(config.egress_interface.clone());
+                active.tool_call_audit = ActiveValue::Set(Some(config.tool_call_audit));
+                active.max_response_body_bytes =
+                    ActiveValue::Set(i64::try_from(config.max_response_body_bytes).ok());
+                active.truncate_oversize_responses =
+                    ActiveValue::Set(Some(config.truncate_oversize_responses));
+                active.event_log_max_body_bytes =
+                    ActiveValue::Set(i64::try_from(config.event_log_max_body_bytes).ok());
+                active.log_search = ActiveValue::Set(Some(config.log_search));
+                active.trusted_proxies = ActiveValue::Set(config.trusted_proxies.clone());
+                active.sse_resume = ActiveValue::Set(Some(config.sse_resume));
                 active.updated_at = ActiveValue::Set(now);
                 active.update(&self.db).await?;
             }
@@ -347,10 +513,56 @@ impl Storage for SeaOrmStorage {
                     id: ActiveValue::Set(id),
                     host: ActiveValue::Set(config.host.clone()),
                     port: ActiveValue::Set(i32::from(config.port)),
+                    admin_host: ActiveValue::Set(config.admin_host.clone()),
+                    admin_port: ActiveValue::Set(config.admin_port.map(i32::from)),
                     admin_key: ActiveValue::Set(config.admin_key.clone()),
                     proxy: ActiveValue::Set(config.proxy.clone()),
                     dsn: ActiveValue::Set(config.dsn.clone()),
                     event_redact_sensitive: ActiveValue::Set(Some(config.event_redact_sensitive)),
+                    native_error_format: ActiveValue::Set(Some(config.native_error_format)),
+                    stream_retry_on_interrupt: ActiveValue::Set(Some(
+                        config.stream_retry_on_interrupt,
+                    )),
+                    stream_first_token_timeout_ms: ActiveValue::Set(
+                        i64::try_from(config.stream_first_token_timeout_ms).ok(),
+                    ),
+                    coalesce_inflight_requests: ActiveValue::Set(Some(
+                        config.coalesce_inflight_requests,
+                    )),
+                    response_compress_min_bytes: ActiveValue::Set(
+                        i64::try_from(config.response_compress_min_bytes).ok(),
+                    ),
+                    key_abuse_max_ips: ActiveValue::Set(
+                        i64::try_from(config.key_abuse_max_ips).ok(),
+                    ),
+                    key_abuse_min_rpm: ActiveValue::Set(
+                        i64::try_from(config.key_abuse_min_rpm).ok(),
+                    ),
+                    key_abuse_blocked_countries: ActiveValue::Set(
+                        config.key_abuse_blocked_countries.clone(),
+                    ),
+                    alert_webhook_url: ActiveValue::Set(config.alert_webhook_url.clone()),
+                    geoip_country_db: ActiveValue::Set(config.geoip_country_db.clone()),
+                    geoip_asn_db: ActiveValue::Set(config.geoip_asn_db.clone()),
+                    stream_idle_timeout_ms: ActiveValue::Set(
+                        i64::try_from(config.stream_idle_timeout_ms).ok(),
+                    ),
+                    egress_ip_family: ActiveValue::Set(config.egress_ip_family.clone()),
+                    egress_local_address: ActiveValue::Set(config.egress_local_address.clone()),
+                    egress_interface: ActiveValue::Set(config.egress_interface.clone()),
+                    tool_call_audit: ActiveValue::Set(Some(config.tool_call_audit)),
+                    max_response_body_bytes: ActiveValue::Set(
+                        i64::try_from(config.max_response_body_bytes).ok(),
+                    ),
+                    truncate_oversize_responses: ActiveValue::Set(Some(
+                        config.truncate_oversize_responses,
+                    )),
+                    event_log_max_body_bytes: ActiveValue::Set(
+                        i64::try_from(config.event_log_max_body_bytes).ok(),
+                    ),
+                    log_search: ActiveValue::Set(Some(config.log_search)),
+                    trusted_proxies: ActiveValue::Set(config.trusted_proxies.clone()),
+                    sse_resume: ActiveValue::Set(Some(config.sse_resume)),
                     updated_at: ActiveValue::Set(now),
                 };
                 entities::GlobalConfig::insert(active)
@@ -392,6 +604,37 @@ impl Storage for SeaOrmStorage {
             })
             .collect();
 
+        let organizations = entities::Organizations::find().all(&self.db).await?;
+        let organizations = organizations
+            .into_iter()
+            .map(|m| OrganizationRow {
+                id: m.id,
+                name: m.name,
+                enabled: m.enabled,
+                admin_key: m.admin_key,
+                omit_bodies: m.omit_bodies.unwrap_or(false),
+                rpm_limit: m.rpm_limit,
+                tpm_limit: m.tpm_limit,
+                monthly_token_budget: m.monthly_token_budget,
+                default_provider: m.default_provider,
+                default_model: m.default_model,
+                created_at: m.created_at,
+                updated_at: m.updated_at,
+            })
+            .collect();
+
+        let org_grants = entities::OrgProviderGrants::find().all(&self.db).await?;
+        let org_grants = org_grants
+            .into_iter()
+            .map(|m| OrgGrantRow {
+                id: m.id,
+                org_id: m.org_id,
+                provider_id: m.provider_id,
+                credential_id: m.credential_id,
+                created_at: m.created_at,
+            })
+            .collect();
+
         let users = entities::Users::find().all(&self.db).await?;
         let users = users
             .into_iter()
@@ -399,6 +642,7 @@ impl Storage for SeaOrmStorage {
                 id: m.id,
                 name: m.name,
                 enabled: m.enabled,
+                org_id: m.org_id,
                 created_at: m.created_at,
                 updated_at: m.updated_at,
             })
@@ -413,6 +657,75 @@ impl Storage for SeaOrmStorage {
                 api_key: m.api_key,
                 label: m.label,
                 enabled: m.enabled,
+                rpm_limit: m.rpm_limit,
+                tpm_limit: m.tpm_limit,
+                stream_tps_limit: m.stream_tps_limit,
+                max_output_tokens: m.max_output_tokens,
+                omit_bodies: m.omit_bodies.unwrap_or(false),
+                default_provider: m.default_provider,
+                default_model: m.default_model,
+                mcp_policy: m.mcp_policy,
+                moderation_policy: m.moderation_policy,
+                prelude_template: m.prelude_template,
+                key_scope: m.key_scope,
+                expires_at: m.expires_at,
+                allowed_origins: m.allowed_origins,
+                body_sample_percent: m.body_sample_percent,
+                created_at: m.created_at,
+                updated_at: m.updated_at,
+            })
+            .collect();
+
+        let model_profiles = entities::ModelProfiles::find().all(&self.db).await?;
+        let model_profiles = model_profiles
+            .into_iter()
+            .map(|m| ModelProfileRow {
+                id: m.id,
+                name: m.name,
+                provider: m.provider,
+                model: m.model,
+                settings_json: m.settings_json,
+                enabled: m.enabled,
+                created_at: m.created_at,
+                updated_at: m.updated_at,
+            })
+            .collect();
+
+        let experiments = entities::Experiments::find().all(&self.db).await?;
+        let experiments = experiments
+            .into_iter()
+            .map(|m| ExperimentRow {
+                id: m.id,
+                name: m.name,
+                arms_json: m.arms_json,
+                split: m.split,
+                enabled: m.enabled,
+                created_at: m.created_at,
+                updated_at: m.updated_at,
+            })
+            .collect();
+
+        let secrets = entities::Secrets::find().all(&self.db).await?;
+        let secrets = secrets
+            .into_iter()
+            .map(|m| SecretRow {
+                id: m.id,
+                name: m.name,
+                value: m.value,
+                created_at: m.created_at,
+                updated_at: m.updated_at,
+            })
+            .collect();
+
+        let log_views = entities::LogViews::find().all(&self.db).await?;
+        let log_views = log_views
+            .into_iter()
+            .map(|m| LogViewRow {
+                id: m.id,
+                name: m.name,
+                filter_json: m.filter_json,
+                window_secs: m.window_secs,
+                alert_threshold: m.alert_threshold,
                 created_at: m.created_at,
                 updated_at: m.updated_at,
             })
@@ -422,11 +735,22 @@ impl Storage for SeaOrmStorage {
             global_config,
             providers,
             credentials,
+            organizations,
+            org_grants,
             users,
             user_keys,
+            model_profiles,
+            experiments,
+            secrets,
+            log_views,
         })
     }
 
+    async fn provider_names(&self) -> StorageResult<Vec<String>> {
+        let rows = entities::Providers::find().all(&self.db).await?;
+        Ok(rows.into_iter().map(|m| m.name).collect())
+    }
+
     async fn upsert_provider(
         &self,
         name: &str,
@@ -569,6 +893,195 @@ impl Storage for SeaOrmStorage {
         Ok(())
     }
 
+    async fn upsert_org_by_id(
+        &self,
+        org_id: i64,
+        name: &str,
+        enabled: bool,
+        admin_key: Option<&str>,
+    ) -> StorageResult<()> {
+        use entities::organizations::ActiveModel as OrgActive;
+
+        let now = OffsetDateTime::now_utc();
+        let existing = entities::Organizations::find_by_id(org_id)
+            .one(&self.db)
+            .await?;
+
+        match existing {
+            Some(model) => {
+                let mut active: OrgActive = model.into();
+                active.name = ActiveValue::Set(name.to_string());
+                active.enabled = ActiveValue::Set(enabled);
+                active.admin_key = ActiveValue::Set(admin_key.map(|s| s.to_string()));
+                active.updated_at = ActiveValue::Set(now);
+                active.update(&self.db).await?;
+            }
+            None => {
+                let active = OrgActive {
+                    id: ActiveValue::Set(org_id),
+                    name: ActiveValue::Set(name.to_string()),
+                    enabled: ActiveValue::Set(enabled),
+                    admin_key: ActiveValue::Set(admin_key.map(|s| s.to_string())),
+                    omit_bodies: ActiveValue::Set(None),
+                    rpm_limit: ActiveValue::Set(None),
+                    tpm_limit: ActiveValue::Set(None),
+                    monthly_token_budget: ActiveValue::Set(None),
+                    default_provider: ActiveValue::Set(None),
+                    default_model: ActiveValue::Set(None),
+                    created_at: ActiveValue::Set(now),
+                    updated_at: ActiveValue::Set(now),
+                };
+                entities::Organizations::insert(active)
+                    .exec(&self.db)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn set_org_enabled(&self, org_id: i64, enabled: bool) -> StorageResult<()> {
+        use entities::organizations::ActiveModel as OrgActive;
+
+        let now = OffsetDateTime::now_utc();
+        let existing = entities::Organizations::find_by_id(org_id)
+            .one(&self.db)
+            .await?;
+        let Some(model) = existing else {
+            return Ok(());
+        };
+        let mut active: OrgActive = model.into();
+        active.enabled = ActiveValue::Set(enabled);
+        active.updated_at = ActiveValue::Set(now);
+        active.update(&self.db).await?;
+        Ok(())
+    }
+
+    async fn set_org_omit_bodies(&self, org_id: i64, omit_bodies: bool) -> StorageResult<()> {
+        use entities::organizations::ActiveModel as OrgActive;
+
+        let now = OffsetDateTime::now_utc();
+        let existing = entities::Organizations::find_by_id(org_id)
+            .one(&self.db)
+            .await?;
+        let Some(model) = existing else {
+            return Ok(());
+        };
+        let mut active: OrgActive = model.into();
+        active.omit_bodies = ActiveValue::Set(Some(omit_bodies));
+        active.updated_at = ActiveValue::Set(now);
+        active.update(&self.db).await?;
+        Ok(())
+    }
+
+    async fn update_org_limits(
+        &self,
+        org_id: i64,
+        rpm_limit: Option<i64>,
+        tpm_limit: Option<i64>,
+        monthly_token_budget: Option<i64>,
+    ) -> StorageResult<()> {
+        use entities::organizations::ActiveModel as OrgActive;
+
+        let existing = entities::Organizations::find_by_id(org_id)
+            .one(&self.db)
+            .await?;
+        let Some(model) = existing else {
+            return Ok(());
+        };
+        let now = OffsetDateTime::now_utc();
+        let mut active: OrgActive = model.into();
+        active.rpm_limit = ActiveValue::Set(rpm_limit);
+        active.tpm_limit = ActiveValue::Set(tpm_limit);
+        active.monthly_token_budget = ActiveValue::Set(monthly_token_budget);
+        active.updated_at = ActiveValue::Set(now);
+        active.update(&self.db).await?;
+        Ok(())
+    }
+
+    async fn update_org_defaults(
+        &self,
+        org_id: i64,
+        default_provider: Option<&str>,
+        default_model: Option<&str>,
+    ) -> StorageResult<()> {
+        use entities::organizations::ActiveModel as OrgActive;
+
+        let existing = entities::Organizations::find_by_id(org_id)
+            .one(&self.db)
+            .await?;
+        let Some(model) = existing else {
+            return Ok(());
+        };
+        let now = OffsetDateTime::now_utc();
+        let mut active: OrgActive = model.into();
+        active.default_provider = ActiveValue::Set(default_provider.map(|s| s.to_string()));
+        active.default_model = ActiveValue::Set(default_model.map(|s| s.to_string()));
+        active.updated_at = ActiveValue::Set(now);
+        active.update(&self.db).await?;
+        Ok(())
+    }
+
+    async fn delete_org(&self, org_id: i64) -> StorageResult<()> {
+        use entities::users::{ActiveModel as UserActive, Column as UserColumn};
+
+        // Detach members explicitly; ON DELETE SET NULL is not present on
+        // `users.org_id` columns that were added to an existing table.
+        let members = entities::Users::find()
+            .filter(UserColumn::OrgId.eq(org_id))
+            .all(&self.db)
+            .await?;
+        let now = OffsetDateTime::now_utc();
+        for model in members {
+            let mut active: UserActive = model.into();
+            active.org_id = ActiveValue::Set(None);
+            active.updated_at = ActiveValue::Set(now);
+            active.update(&self.db).await?;
+        }
+        entities::Organizations::delete_by_id(org_id)
+            .exec(&self.db)
+            .await?;
+        Ok(())
+    }
+
+    async fn insert_org_grant(
+        &self,
+        org_id: i64,
+        provider_name: &str,
+        credential_id: Option<i64>,
+    ) -> StorageResult<i64> {
+        use entities::org_provider_grants::ActiveModel as GrantActive;
+        use entities::providers::Column as ProviderColumn;
+
+        let provider = entities::Providers::find()
+            .filter(ProviderColumn::Name.eq(provider_name))
+            .one(&self.db)
+            .await?;
+        let Some(provider) = provider else {
+            return Err(StorageError::Db(sea_orm::DbErr::RecordNotFound(format!(
+                "provider not found: {provider_name}"
+            ))));
+        };
+
+        let active = GrantActive {
+            id: ActiveValue::NotSet,
+            org_id: ActiveValue::Set(org_id),
+            provider_id: ActiveValue::Set(provider.id),
+            credential_id: ActiveValue::Set(credential_id),
+            created_at: ActiveValue::Set(OffsetDateTime::now_utc()),
+        };
+        let inserted = entities::OrgProviderGrants::insert(active)
+            .exec(&self.db)
+            .await?;
+        Ok(inserted.last_insert_id)
+    }
+
+    async fn delete_org_grant(&self, grant_id: i64) -> StorageResult<()> {
+        entities::OrgProviderGrants::delete_by_id(grant_id)
+            .exec(&self.db)
+            .await?;
+        Ok(())
+    }
+
     async fn upsert_user_by_id(
         &self,
         user_id: i64,
@@ -593,6 +1106,7 @@ impl Storage for SeaOrmStorage {
                     id: ActiveValue::Set(user_id),
                     name: ActiveValue::Set(name.to_string()),
                     enabled: ActiveValue::Set(enabled),
+                    org_id: ActiveValue::Set(None),
                     created_at: ActiveValue::Set(now),
                     updated_at: ActiveValue::Set(now),
                 };
@@ -617,6 +1131,21 @@ impl Storage for SeaOrmStorage {
         Ok(())
     }
 
+    async fn set_user_org(&self, user_id: i64, org_id: Option<i64>) -> StorageResult<()> {
+        use entities::users::ActiveModel as UserActive;
+
+        let now = OffsetDateTime::now_utc();
+        let existing = entities::Users::find_by_id(user_id).one(&self.db).await?;
+        let Some(model) = existing else {
+            return Ok(());
+        };
+        let mut active: UserActive = model.into();
+        active.org_id = ActiveValue::Set(org_id);
+        active.updated_at = ActiveValue::Set(now);
+        active.update(&self.db).await?;
+        Ok(())
+    }
+
     async fn delete_user(&self, user_id: i64) -> StorageResult<()> {
         // Rely on DB-level ON DELETE CASCADE for user_keys.
         entities::Users::delete_by_id(user_id)
@@ -641,6 +1170,20 @@ impl Storage for SeaOrmStorage {
             api_key: ActiveValue::Set(api_key.to_string()),
             label: ActiveValue::Set(label.map(|s| s.to_string())),
             enabled: ActiveValue::Set(enabled),
+            rpm_limit: ActiveValue::Set(None),
+            tpm_limit: ActiveValue::Set(None),
+            stream_tps_limit: ActiveValue::Set(None),
+            max_output_tokens: ActiveValue::Set(None),
+            omit_bodies: ActiveValue::Set(None),
+            default_provider: ActiveValue::Set(None),
+            default_model: ActiveValue::Set(None),
+            mcp_policy: ActiveValue::Set(None),
+            moderation_policy: ActiveValue::Set(None),
+            prelude_template: ActiveValue::Set(None),
+            key_scope: ActiveValue::Set(None),
+            expires_at: ActiveValue::Set(None),
+            allowed_origins: ActiveValue::Set(None),
+            body_sample_percent: ActiveValue::Set(None),
             created_at: ActiveValue::Set(now),
             updated_at: ActiveValue::Set(now),
         };
@@ -665,35 +1208,463 @@ impl Storage for SeaOrmStorage {
         Ok(())
     }
 
-    async fn update_user_key_label(
+    async fn set_user_key_omit_bodies(
         &self,
         user_key_id: i64,
-        label: Option<&str>,
+        omit_bodies: bool,
     ) -> StorageResult<()> {
         use entities::user_keys::ActiveModel as UserKeyActive;
 
+        let now = OffsetDateTime::now_utc();
         let existing = entities::UserKeys::find_by_id(user_key_id)
             .one(&self.db)
             .await?;
         let Some(model) = existing else {
             return Ok(());
         };
-        let now = OffsetDateTime::now_utc();
         let mut active: UserKeyActive = model.into();
-        active.label = ActiveValue::Set(label.map(|s| s.to_string()));
+        active.omit_bodies = ActiveValue::Set(Some(omit_bodies));
         active.updated_at = ActiveValue::Set(now);
         active.update(&self.db).await?;
         Ok(())
     }
 
-    async fn delete_user_key(&self, user_key_id: i64) -> StorageResult<()> {
-        entities::UserKeys::delete_by_id(user_key_id)
-            .exec(&self.db)
-            .await?;
-        Ok(())
-    }
+    async fn set_user_key_body_sample_percent(
+        &self,
+        user_key_id: i64,
+        body_sample_percent: Option<i64>,
+    ) -> StorageResult<()> {
+        use entities::user_keys::ActiveModel as UserKeyActive;
 
-    async fn append_event(&self, event: &Event) -> StorageResult<()> {
+        let now = OffsetDateTime::now_utc();
+        let existing = entities::UserKeys::find_by_id(user_key_id)
+            .one(&self.db)
+            .await?;
+        let Some(model) = existing else {
+            return Ok(());
+        };
+        let mut active: UserKeyActive = model.into();
+        active.body_sample_percent = ActiveValue::Set(body_sample_percent);
+        active.updated_at = ActiveValue::Set(now);
+        active.update(&self.db).await?;
+        Ok(())
+    }
+
+    async fn update_user_key_label(
+        &self,
+        user_key_id: i64,
+        label: Option<&str>,
+    ) -> StorageResult<()> {
+        use entities::user_keys::ActiveModel as UserKeyActive;
+
+        let existing = entities::UserKeys::find_by_id(user_key_id)
+            .one(&self.db)
+            .await?;
+        let Some(model) = existing else {
+            return Ok(());
+        };
+        let now = OffsetDateTime::now_utc();
+        let mut active: UserKeyActive = model.into();
+        active.label = ActiveValue::Set(label.map(|s| s.to_string()));
+        active.updated_at = ActiveValue::Set(now);
+        active.update(&self.db).await?;
+        Ok(())
+    }
+
+    async fn update_user_key_limits(
+        &self,
+        user_key_id: i64,
+        rpm_limit: Option<i64>,
+        tpm_limit: Option<i64>,
+        stream_tps_limit: Option<i64>,
+        max_output_tokens: Option<i64>,
+    ) -> StorageResult<()> {
+        use entities::user_keys::ActiveModel as UserKeyActive;
+
+        let existing = entities::UserKeys::find_by_id(user_key_id)
+            .one(&self.db)
+            .await?;
+        let Some(model) = existing else {
+            return Ok(());
+        };
+        let now = OffsetDateTime::now_utc();
+        let mut active: UserKeyActive = model.into();
+        active.rpm_limit = ActiveValue::Set(rpm_limit);
+        active.tpm_limit = ActiveValue::Set(tpm_limit);
+        active.stream_tps_limit = ActiveValue::Set(stream_tps_limit);
+        active.max_output_tokens = ActiveValue::Set(max_output_tokens);
+        active.updated_at = ActiveValue::Set(now);
+        active.update(&self.db).await?;
+        Ok(())
+    }
+
+    async fn update_user_key_defaults(
+        &self,
+        user_key_id: i64,
+        default_provider: Option<&str>,
+        default_model: Option<&str>,
+    ) -> StorageResult<()> {
+        use entities::user_keys::ActiveModel as UserKeyActive;
+
+        let existing = entities::UserKeys::find_by_id(user_key_id)
+            .one(&self.db)
+            .await?;
+        let Some(model) = existing else {
+            return Ok(());
+        };
+        let now = OffsetDateTime::now_utc();
+        let mut active: UserKeyActive = model.into();
+        active.default_provider = ActiveValue::Set(default_provider.map(|s| s.to_string()));
+        active.default_model = ActiveValue::Set(default_model.map(|s| s.to_string()));
+        active.updated_at = ActiveValue::Set(now);
+        active.update(&self.db).await?;
+        Ok(())
+    }
+
+    async fn update_user_key_mcp_policy(
+        &self,
+        user_key_id: i64,
+        mcp_policy: Option<&serde_json::Value>,
+    ) -> StorageResult<()> {
+        use entities::user_keys::ActiveModel as UserKeyActive;
+
+        let existing = entities::UserKeys::find_by_id(user_key_id)
+            .one(&self.db)
+            .await?;
+        let Some(model) = existing else {
+            return Ok(());
+        };
+        let now = OffsetDateTime::now_utc();
+        let mut active: UserKeyActive = model.into();
+        active.mcp_policy = ActiveValue::Set(mcp_policy.cloned());
+        active.updated_at = ActiveValue::Set(now);
+        active.update(&self.db).await?;
+        Ok(())
+    }
+
+    async fn update_user_key_moderation_policy(
+        &self,
+        user_key_id: i64,
+        moderation_policy: Option<&serde_json::Value>,
+    ) -> StorageResult<()> {
+        use entities::user_keys::ActiveModel as UserKeyActive;
+
+        let existing = entities::UserKeys::find_by_id(user_key_id)
+            .one(&self.db)
+            .await?;
+        let Some(model) = existing else {
+            return Ok(());
+        };
+        let now = OffsetDateTime::now_utc();
+        let mut active: UserKeyActive = model.into();
+        active.moderation_policy = ActiveValue::Set(moderation_policy.cloned());
+        active.updated_at = ActiveValue::Set(now);
+        active.update(&self.db).await?;
+        Ok(())
+    }
+
+    async fn update_user_key_prelude_template(
+        &self,
+        user_key_id: i64,
+        prelude_template: Option<&str>,
+    ) -> StorageResult<()> {
+        use entities::user_keys::ActiveModel as UserKeyActive;
+
+        let existing = entities::UserKeys::find_by_id(user_key_id)
+            .one(&self.db)
+            .await?;
+        let Some(model) = existing else {
+            return Ok(());
+        };
+        let now = OffsetDateTime::now_utc();
+        let mut active: UserKeyActive = model.into();
+        active.prelude_template = ActiveValue::Set(prelude_template.map(str::to_string));
+        active.updated_at = ActiveValue::Set(now);
+        active.update(&self.db).await?;
+        Ok(())
+    }
+
+    async fn update_user_key_scope(
+        &self,
+        user_key_id: i64,
+        key_scope: Option<&serde_json::Value>,
+        expires_at: Option<OffsetDateTime>,
+    ) -> StorageResult<()> {
+        use entities::user_keys::ActiveModel as UserKeyActive;
+
+        let existing = entities::UserKeys::find_by_id(user_key_id)
+            .one(&self.db)
+            .await?;
+        let Some(model) = existing else {
+            return Ok(());
+        };
+        let now = OffsetDateTime::now_utc();
+        let mut active: UserKeyActive = model.into();
+        active.key_scope = ActiveValue::Set(key_scope.cloned());
+        active.expires_at = ActiveValue::Set(expires_at);
+        active.updated_at = ActiveValue::Set(now);
+        active.update(&self.db).await?;
+        Ok(())
+    }
+
+    async fn update_user_key_allowed_origins(
+        &self,
+        user_key_id: i64,
+        allowed_origins: Option<&serde_json::Value>,
+    ) -> StorageResult<()> {
+        use entities::user_keys::ActiveModel as UserKeyActive;
+
+        let existing = entities::UserKeys::find_by_id(user_key_id)
+            .one(&self.db)
+            .await?;
+        let Some(model) = existing else {
+            return Ok(());
+        };
+        let now = OffsetDateTime::now_utc();
+        let mut active: UserKeyActive = model.into();
+        active.allowed_origins = ActiveValue::Set(allowed_origins.cloned());
+        active.updated_at = ActiveValue::Set(now);
+        active.update(&self.db).await?;
+        Ok(())
+    }
+
+    async fn delete_user_key(&self, user_key_id: i64) -> StorageResult<()> {
+        entities::UserKeys::delete_by_id(user_key_id)
+            .exec(&self.db)
+            .await?;
+        Ok(())
+    }
+
+    async fn upsert_model_profile(
+        &self,
+        name: &str,
+        provider: &str,
+        model: &str,
+        settings_json: &serde_json::Value,
+        enabled: bool,
+    ) -> StorageResult<i64> {
+        use entities::model_profiles::{ActiveModel as ModelProfileActive, Column};
+
+        let now = OffsetDateTime::now_utc();
+        let existing = entities::ModelProfiles::find()
+            .filter(Column::Name.eq(name))
+            .one(&self.db)
+            .await?;
+
+        let id = match existing {
+            Some(row) => {
+                let mut active: ModelProfileActive = row.into();
+                active.provider = ActiveValue::Set(provider.to_string());
+                active.model = ActiveValue::Set(model.to_string());
+                active.settings_json = ActiveValue::Set(settings_json.clone());
+                active.enabled = ActiveValue::Set(enabled);
+                active.updated_at = ActiveValue::Set(now);
+                let updated = active.update(&self.db).await?;
+                updated.id
+            }
+            None => {
+                let active = ModelProfileActive {
+                    id: ActiveValue::NotSet,
+                    name: ActiveValue::Set(name.to_string()),
+                    provider: ActiveValue::Set(provider.to_string()),
+                    model: ActiveValue::Set(model.to_string()),
+                    settings_json: ActiveValue::Set(settings_json.clone()),
+                    enabled: ActiveValue::Set(enabled),
+                    created_at: ActiveValue::Set(now),
+                    updated_at: ActiveValue::Set(now),
+                };
+                let inserted = entities::ModelProfiles::insert(active)
+                    .exec(&self.db)
+                    .await?;
+                inserted.last_insert_id
+            }
+        };
+        Ok(id)
+    }
+
+    async fn delete_model_profile(&self, name: &str) -> StorageResult<()> {
+        use entities::model_profiles::Column;
+
+        entities::ModelProfiles::delete_many()
+            .filter(Column::Name.eq(name))
+            .exec(&self.db)
+            .await?;
+        Ok(())
+    }
+
+    async fn upsert_experiment(
+        &self,
+        name: &str,
+        arms_json: &serde_json::Value,
+        split: &str,
+        enabled: bool,
+    ) -> StorageResult<i64> {
+        use entities::experiments::{ActiveModel as ExperimentActive, Column};
+
+        let now = OffsetDateTime::now_utc();
+        let existing = entities::Experiments::find()
+            .filter(Column::Name.eq(name))
+            .one(&self.db)
+            .await?;
+
+        let id = match existing {
+            Some(row) => {
+                let mut active: ExperimentActive = row.into();
+                active.arms_json = ActiveValue::Set(arms_json.clone());
+                active.split = ActiveValue::Set(split.to_string());
+                active.enabled = ActiveValue::Set(enabled);
+                active.updated_at = ActiveValue::Set(now);
+                let updated = active.update(&self.db).await?;
+                updated.id
+            }
+            None => {
+                let active = ExperimentActive {
+                    id: ActiveValue::NotSet,
+                    name: ActiveValue::Set(name.to_string()),
+                    arms_json: ActiveValue::Set(arms_json.clone()),
+                    split: ActiveValue::Set(split.to_string()),
+                    enabled: ActiveValue::Set(enabled),
+                    created_at: ActiveValue::Set(now),
+                    updated_at: ActiveValue::Set(now),
+                };
+                let inserted = entities::Experiments::insert(active).exec(&self.db).await?;
+                inserted.last_insert_id
+            }
+        };
+        Ok(id)
+    }
+
+    async fn delete_experiment(&self, name: &str) -> StorageResult<()> {
+        use entities::experiments::Column;
+
+        entities::Experiments::delete_many()
+            .filter(Column::Name.eq(name))
+            .exec(&self.db)
+            .await?;
+        Ok(())
+    }
+
+    async fn upsert_secret(&self, name: &str, value: &str) -> StorageResult<i64> {
+        use entities::secrets::{ActiveModel as SecretActive, Column};
+
+        let now = OffsetDateTime::now_utc();
+        let existing = entities::Secrets::find()
+            .filter(Column::Name.eq(name))
+            .one(&self.db)
+            .await?;
+
+        let id = match existing {
+            Some(row) => {
+                let mut active: SecretActive = row.into();
+                active.value = ActiveValue::Set(value.to_string());
+                active.updated_at = ActiveValue::Set(now);
+                let updated = active.update(&self.db).await?;
+                updated.id
+            }
+            None => {
+                let active = SecretActive {
+                    id: ActiveValue::NotSet,
+                    name: ActiveValue::Set(name.to_string()),
+                    value: ActiveValue::Set(value.to_string()),
+                    created_at: ActiveValue::Set(now),
+                    updated_at: ActiveValue::Set(now),
+                };
+                let inserted = entities::Secrets::insert(active).exec(&self.db).await?;
+                inserted.last_insert_id
+            }
+        };
+        Ok(id)
+    }
+
+    async fn delete_secret(&self, name: &str) -> StorageResult<()> {
+        use entities::secrets::Column;
+
+        entities::Secrets::delete_many()
+            .filter(Column::Name.eq(name))
+            .exec(&self.db)
+            .await?;
+        Ok(())
+    }
+
+    async fn upsert_log_view(
+        &self,
+        name: &str,
+        filter_json: &serde_json::Value,
+        window_secs: i64,
+        alert_threshold: Option<i64>,
+    ) -> StorageResult<i64> {
+        use entities::log_views::{ActiveModel as LogViewActive, Column};
+
+        let now = OffsetDateTime::now_utc();
+        let existing = entities::LogViews::find()
+            .filter(Column::Name.eq(name))
+            .one(&self.db)
+            .await?;
+
+        let id = match existing {
+            Some(row) => {
+                let mut active: LogViewActive = row.into();
+                active.filter_json = ActiveValue::Set(filter_json.clone());
+                active.window_secs = ActiveValue::Set(window_secs);
+                active.alert_threshold = ActiveValue::Set(alert_threshold);
+                active.updated_at = ActiveValue::Set(now);
+                let updated = active.update(&self.db).await?;
+                updated.id
+            }
+            None => {
+                let active = LogViewActive {
+                    id: ActiveValue::NotSet,
+                    name: ActiveValue::Set(name.to_string()),
+                    filter_json: ActiveValue::Set(filter_json.clone()),
+                    window_secs: ActiveValue::Set(window_secs),
+                    alert_threshold: ActiveValue::Set(alert_threshold),
+                    created_at: ActiveValue::Set(now),
+                    updated_at: ActiveValue::Set(now),
+                };
+                let inserted = entities::LogViews::insert(active).exec(&self.db).await?;
+                inserted.last_insert_id
+            }
+        };
+        Ok(id)
+    }
+
+    async fn delete_log_view(&self, name: &str) -> StorageResult<()> {
+        use entities::log_views::Column;
+
+        entities::LogViews::delete_many()
+            .filter(Column::Name.eq(name))
+            .exec(&self.db)
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl TelemetryStorage for SeaOrmStorage {
+    async fn sync_telemetry(&self) -> StorageResult<()> {
+        Schema::new(self.db.get_database_backend())
+            .builder()
+            .register(entities::DownstreamRequests)
+            .register(entities::UpstreamRequests)
+            .register(entities::UpstreamUsages)
+            .register(entities::InternalEvents)
+            .register(entities::StatsHourly)
+            .register(entities::JobRuns)
+            .register(entities::DebugCaptures)
+            .register(entities::McpToolCalls)
+            .register(entities::ToolCalls)
+            .register(entities::AbortedStreams)
+            .register(entities::Incidents)
+            .sync(&self.db)
+            .await?;
+        self.ensure_performance_indexes().await?;
+        self.ensure_log_search_table().await?;
+        self.backfill_usage_models().await?;
+        self.backfill_internal_event_columns().await?;
+        Ok(())
+    }
+
+    async fn append_event(&self, event: &Event) -> StorageResult<()> {
         let now = OffsetDateTime::now_utc();
         match event {
             Event::Downstream(ev) => {
@@ -716,11 +1687,26 @@ impl Storage for SeaOrmStorage {
                         &ev.response_headers,
                     )?),
                     response_body: ActiveValue::Set(ev.response_body.clone()),
+                    tags: ActiveValue::Set(encode_tags(&ev.tags)),
+                    client_ip: ActiveValue::Set(ev.client_ip.clone()),
+                    country: ActiveValue::Set(ev.country.clone()),
+                    asn: ActiveValue::Set(ev.asn.map(i64::from)),
                     created_at: ActiveValue::Set(now),
                 };
-                entities::DownstreamRequests::insert(active)
+                let inserted = entities::DownstreamRequests::insert(active)
                     .exec(&self.db)
                     .await?;
+                self.index_log_text(LogText {
+                    kind: LogRecordKind::Downstream,
+                    log_id: inserted.last_insert_id,
+                    trace_id: ev.trace_id.as_deref(),
+                    user_id: ev.user_id,
+                    at: system_time_to_offset(ev.at),
+                    request_body: ev.request_body.as_deref(),
+                    response_body: ev.response_body.as_deref(),
+                    error_message: None,
+                })
+                .await;
             }
             Event::Upstream(ev) => {
                 use entities::upstream_requests::ActiveModel as UpstreamActive;
@@ -740,6 +1726,7 @@ impl Storage for SeaOrmStorage {
                     request_headers_json: ActiveValue::Set(serde_json::to_value(
                         &ev.request_headers,
                     )?),
+                    request_host: ActiveValue::Set(ev.request_host.clone()),
                     request_path: ActiveValue::Set(ev.request_path.clone()),
                     request_query: ActiveValue::Set(ev.request_query.clone()),
                     request_body: ActiveValue::Set(ev.request_body.clone()),
@@ -751,11 +1738,24 @@ impl Storage for SeaOrmStorage {
                     error_kind: ActiveValue::Set(ev.error_kind.clone()),
                     error_message: ActiveValue::Set(ev.error_message.clone()),
                     transport_kind: ActiveValue::Set(ev.transport_kind.map(|k| format!("{k:?}"))),
+                    tags: ActiveValue::Set(encode_tags(&ev.tags)),
+                    anthropic_betas: ActiveValue::Set(encode_tags(&ev.anthropic_betas)),
                     created_at: ActiveValue::Set(now),
                 };
                 let inserted = entities::UpstreamRequests::insert(active)
                     .exec(&self.db)
                     .await?;
+                self.index_log_text(LogText {
+                    kind: LogRecordKind::Upstream,
+                    log_id: inserted.last_insert_id,
+                    trace_id: ev.trace_id.as_deref(),
+                    user_id: ev.user_id,
+                    at: system_time_to_offset(ev.at),
+                    request_body: ev.request_body.as_deref(),
+                    response_body: ev.response_body.as_deref(),
+                    error_message: ev.error_message.as_deref(),
+                })
+                .await;
                 if let Some(usage) = &ev.usage {
                     let model = match ev.operation.as_str() {
                         "GenerateContent" | "StreamGenerateContent" => {
@@ -786,6 +1786,9 @@ impl Storage for SeaOrmStorage {
                         cache_creation_input_tokens: ActiveValue::Set(
                             usage.cache_creation_input_tokens.map(i64::from),
                         ),
+                        tags: ActiveValue::Set(encode_tags(&ev.tags)),
+                        experiment: ActiveValue::Set(ev.experiment.clone()),
+                        experiment_arm: ActiveValue::Set(ev.experiment_arm.clone()),
                         created_at: ActiveValue::Set(now),
                     };
                     entities::UpstreamUsages::insert(usage_active)
@@ -795,22 +1798,12 @@ impl Storage for SeaOrmStorage {
             }
             Event::Operational(ev) => {
                 use entities::internal_events::ActiveModel as InternalActive;
+                let parts = OperationalParts::of(ev);
                 let active = InternalActive {
                     id: ActiveValue::NotSet,
-                    event_type: ActiveValue::Set(match ev {
-                        gproxy_provider_core::OperationalEvent::UnavailableStart(_) => {
-                            "unavailable_start".to_string()
-                        }
-                        gproxy_provider_core::OperationalEvent::UnavailableEnd(_) => {
-                            "unavailable_end".to_string()
-                        }
-                        gproxy_provider_core::OperationalEvent::ModelUnavailableStart(_) => {
-                            "model_unavailable_start".to_string()
-                        }
-                        gproxy_provider_core::OperationalEvent::ModelUnavailableEnd(_) => {
-                            "model_unavailable_end".to_string()
-                        }
-                    }),
+                    event_type: ActiveValue::Set(operational_event_type(ev).to_string()),
+                    credential_id: ActiveValue::Set(parts.credential_id),
+                    model: ActiveValue::Set(parts.model),
                     payload_json: ActiveValue::Set(serde_json::to_value(ev)?),
                     at: ActiveValue::Set(extract_operational_at(ev)),
                     created_at: ActiveValue::Set(now),
@@ -851,12 +1844,25 @@ impl Storage for SeaOrmStorage {
         if let Some(credential_id) = filter.credential_id {
             usage_query = usage_query.filter(UpstreamUsageColumn::CredentialId.eq(credential_id));
         }
+        if let Some(user_ids) = filter.user_ids.as_ref() {
+            usage_query =
+                usage_query.filter(UpstreamUsageColumn::UserId.is_in(user_ids.iter().copied()));
+        }
         if let Some(model) = filter.model.as_deref() {
             usage_query = usage_query.filter(UpstreamUsageColumn::Model.eq(model));
         }
         if let Some(model_contains) = filter.model_contains.as_deref() {
             usage_query = usage_query.filter(UpstreamUsageColumn::Model.contains(model_contains));
         }
+        if let Some(tag) = filter.tag.as_deref() {
+            usage_query = usage_query.filter(UpstreamUsageColumn::Tags.like(tag_pattern(tag)));
+        }
+        if let Some(experiment) = filter.experiment.as_deref() {
+            usage_query = usage_query.filter(UpstreamUsageColumn::Experiment.eq(experiment));
+        }
+        if let Some(arm) = filter.experiment_arm.as_deref() {
+            usage_query = usage_query.filter(UpstreamUsageColumn::ExperimentArm.eq(arm));
+        }
 
         let Some(row) = usage_query
             .into_model::<UsageAggregateRow>()
@@ -896,7 +1902,11 @@ impl Storage for SeaOrmStorage {
 
         let fetch_limit = u64::try_from(filter.limit.saturating_add(1)).unwrap_or(u64::MAX);
 
-        let query_upstream = filter.kind != Some(LogRecordKind::Downstream);
+        let query_upstream = match filter.kind {
+            Some(LogRecordKind::Upstream) => true,
+            Some(LogRecordKind::Downstream) => false,
+            None => filter.country.is_none() && filter.asn.is_none(),
+        };
         let query_downstream = match filter.kind {
             Some(LogRecordKind::Upstream) => false,
             Some(LogRecordKind::Downstream) => true,
@@ -924,6 +1934,9 @@ impl Storage for SeaOrmStorage {
             if let Some(user_id) = filter.user_id {
                 q = q.filter(UpstreamColumn::UserId.eq(user_id));
             }
+            if let Some(user_ids) = filter.user_ids.as_ref() {
+                q = q.filter(UpstreamColumn::UserId.is_in(user_ids.iter().copied()));
+            }
             if let Some(user_key_id) = filter.user_key_id {
                 q = q.filter(UpstreamColumn::UserKeyId.eq(user_key_id));
             }
@@ -933,6 +1946,9 @@ impl Storage for SeaOrmStorage {
             if let Some(operation) = filter.operation.as_deref() {
                 q = q.filter(UpstreamColumn::Operation.eq(operation));
             }
+            if let Some(tag) = filter.tag.as_deref() {
+                q = q.filter(UpstreamColumn::Tags.like(tag_pattern(tag)));
+            }
             if let Some(path_contains) = filter.request_path_contains.as_deref() {
                 q = q.filter(UpstreamColumn::RequestPath.contains(path_contains));
             }
@@ -971,12 +1987,18 @@ impl Storage for SeaOrmStorage {
                     attempt_no: Some(row.attempt_no),
                     operation: Some(row.operation),
                     request_method: row.request_method,
+                    request_host: row.request_host,
                     request_path: row.request_path,
                     request_body: row.request_body,
                     response_status: row.response_status,
                     response_body: row.response_body,
                     error_kind: row.error_kind,
                     error_message: row.error_message,
+                    tags: decode_tags(row.tags.as_deref()),
+                    anthropic_betas: decode_tags(row.anthropic_betas.as_deref()),
+                    client_ip: None,
+                    country: None,
+                    asn: None,
                 }));
             } else {
                 let rows = q
@@ -991,10 +2013,13 @@ impl Storage for SeaOrmStorage {
                     .column(UpstreamColumn::AttemptNo)
                     .column(UpstreamColumn::Operation)
                     .column(UpstreamColumn::RequestMethod)
+                    .column(UpstreamColumn::RequestHost)
                     .column(UpstreamColumn::RequestPath)
                     .column(UpstreamColumn::ResponseStatus)
                     .column(UpstreamColumn::ErrorKind)
                     .column(UpstreamColumn::ErrorMessage)
+                    .column(UpstreamColumn::Tags)
+                    .column(UpstreamColumn::AnthropicBetas)
                     .order_by_desc(UpstreamColumn::At)
                     .order_by_desc(UpstreamColumn::Id)
                     .limit(fetch_limit)
@@ -1013,12 +2038,18 @@ impl Storage for SeaOrmStorage {
                     attempt_no: Some(row.attempt_no),
                     operation: Some(row.operation),
                     request_method: row.request_method,
+                    request_host: row.request_host,
                     request_path: row.request_path,
                     request_body: None,
                     response_status: row.response_status,
                     response_body: None,
                     error_kind: row.error_kind,
                     error_message: row.error_message,
+                    tags: decode_tags(row.tags.as_deref()),
+                    anthropic_betas: decode_tags(row.anthropic_betas.as_deref()),
+                    client_ip: None,
+                    country: None,
+                    asn: None,
                 }));
             }
         }
@@ -1031,12 +2062,18 @@ impl Storage for SeaOrmStorage {
             if let Some(user_id) = filter.user_id {
                 q = q.filter(DownstreamColumn::UserId.eq(user_id));
             }
+            if let Some(user_ids) = filter.user_ids.as_ref() {
+                q = q.filter(DownstreamColumn::UserId.is_in(user_ids.iter().copied()));
+            }
             if let Some(user_key_id) = filter.user_key_id {
                 q = q.filter(DownstreamColumn::UserKeyId.eq(user_key_id));
             }
             if let Some(trace_id) = filter.trace_id.as_deref() {
                 q = q.filter(DownstreamColumn::TraceId.eq(trace_id));
             }
+            if let Some(tag) = filter.tag.as_deref() {
+                q = q.filter(DownstreamColumn::Tags.like(tag_pattern(tag)));
+            }
             if let Some(path_contains) = filter.request_path_contains.as_deref() {
                 q = q.filter(DownstreamColumn::RequestPath.contains(path_contains));
             }
@@ -1046,6 +2083,12 @@ impl Storage for SeaOrmStorage {
             if let Some(status_max) = filter.status_max {
                 q = q.filter(DownstreamColumn::ResponseStatus.lte(status_max));
             }
+            if let Some(country) = filter.country.as_deref() {
+                q = q.filter(DownstreamColumn::Country.eq(country));
+            }
+            if let Some(asn) = filter.asn {
+                q = q.filter(DownstreamColumn::Asn.eq(asn));
+            }
             if let Some(cursor) = filter.cursor {
                 q = q.filter(
                     Condition::any()
@@ -1083,12 +2126,18 @@ impl Storage for SeaOrmStorage {
                         attempt_no,
                         operation,
                         request_method: row.request_method,
+                        request_host: None,
                         request_path: row.request_path,
                         request_body: row.request_body,
                         response_status: row.response_status,
                         response_body: row.response_body,
                         error_kind: None,
                         error_message: None,
+                        tags: decode_tags(row.tags.as_deref()),
+                        anthropic_betas: Vec::new(),
+                        client_ip: row.client_ip,
+                        country: row.country,
+                        asn: row.asn,
                     }
                 }));
             } else {
@@ -1104,6 +2153,10 @@ impl Storage for SeaOrmStorage {
                     .column(DownstreamColumn::RequestBody)
                     .column(DownstreamColumn::ResponseStatus)
                     .column(DownstreamColumn::ResponseBody)
+                    .column(DownstreamColumn::Tags)
+                    .column(DownstreamColumn::ClientIp)
+                    .column(DownstreamColumn::Country)
+                    .column(DownstreamColumn::Asn)
                     .order_by_desc(DownstreamColumn::At)
                     .order_by_desc(DownstreamColumn::Id)
                     .limit(fetch_limit)
@@ -1129,6 +2182,7 @@ impl Storage for SeaOrmStorage {
                         attempt_no,
                         operation,
                         request_method: row.request_method,
+                        request_host: None,
                         request_path: row.request_path,
                         request_body: if include_error_body {
                             row.request_body
@@ -1143,38 +2197,683 @@ impl Storage for SeaOrmStorage {
                         },
                         error_kind: None,
                         error_message: None,
+                        tags: decode_tags(row.tags.as_deref()),
+                        anthropic_betas: Vec::new(),
+                        client_ip: row.client_ip,
+                        country: row.country,
+                        asn: row.asn,
                     }
                 }));
             }
         }
 
-        let mut rows = merge_sorted_logs(
-            upstream_rows,
-            downstream_rows,
-            filter.limit.saturating_add(1),
-        );
-        let has_more = rows.len() > filter.limit;
-        if has_more {
-            rows.truncate(filter.limit);
+        let mut rows = merge_sorted_logs(
+            upstream_rows,
+            downstream_rows,
+            filter.limit.saturating_add(1),
+        );
+        let has_more = rows.len() > filter.limit;
+        if has_more {
+            rows.truncate(filter.limit);
+        }
+        let next_cursor = if has_more {
+            rows.last().map(|row| LogCursor {
+                at: row.at,
+                id: row.id,
+            })
+        } else {
+            None
+        };
+
+        Ok(LogQueryResult {
+            rows,
+            has_more,
+            next_cursor,
+        })
+    }
+
+    fn set_log_search(&self, enabled: bool) {
+        self.log_search.store(enabled, Ordering::Relaxed);
+    }
+
+    async fn search_logs(&self, query: LogSearchQuery) -> StorageResult<Vec<LogSearchHit>> {
+        self.search_log_text(query).await
+    }
+
+    async fn get_downstream_request(
+        &self,
+        id: i64,
+    ) -> StorageResult<Option<DownstreamRequestRecord>> {
+        let Some(row) = entities::DownstreamRequests::find_by_id(id)
+            .one(&self.db)
+            .await?
+        else {
+            return Ok(None);
+        };
+        Ok(Some(DownstreamRequestRecord {
+            id: row.id,
+            at: row.at,
+            trace_id: row.trace_id,
+            user_id: row.user_id,
+            user_key_id: row.user_key_id,
+            request_method: row.request_method,
+            request_path: row.request_path,
+            request_query: row.request_query,
+            request_headers: serde_json::from_value(row.request_headers_json).unwrap_or_default(),
+            request_body: row.request_body,
+            tags: decode_tags(row.tags.as_deref()),
+        }))
+    }
+
+    async fn list_trace_usages(&self, trace_id: &str) -> StorageResult<Vec<UsageRecord>> {
+        use entities::upstream_usages::Column as UpstreamUsageColumn;
+
+        let rows = entities::UpstreamUsages::find()
+            .filter(UpstreamUsageColumn::TraceId.eq(trace_id))
+            .order_by_asc(UpstreamUsageColumn::At)
+            .order_by_asc(UpstreamUsageColumn::Id)
+            .all(&self.db)
+            .await?;
+        Ok(rows.into_iter().map(usage_record_from_model).collect())
+    }
+
+    async fn list_credential_usages(
+        &self,
+        credential_id: i64,
+        from: OffsetDateTime,
+        to: OffsetDateTime,
+    ) -> StorageResult<Vec<UsageRecord>> {
+        use entities::upstream_usages::Column as UpstreamUsageColumn;
+
+        let rows = entities::UpstreamUsages::find()
+            .filter(UpstreamUsageColumn::CredentialId.eq(credential_id))
+            .filter(UpstreamUsageColumn::At.gte(from))
+            .filter(UpstreamUsageColumn::At.lte(to))
+            .order_by_asc(UpstreamUsageColumn::At)
+            .order_by_asc(UpstreamUsageColumn::Id)
+            .all(&self.db)
+            .await?;
+        Ok(rows.into_iter().map(usage_record_from_model).collect())
+    }
+
+    async fn list_credential_outcomes(
+        &self,
+        credential_id: i64,
+        from: OffsetDateTime,
+        to: OffsetDateTime,
+    ) -> StorageResult<Vec<UpstreamOutcome>> {
+        use entities::upstream_requests::Column as UpstreamColumn;
+
+        let rows = entities::UpstreamRequests::find()
+            .filter(UpstreamColumn::CredentialId.eq(credential_id))
+            .filter(UpstreamColumn::At.gte(from))
+            .filter(UpstreamColumn::At.lte(to))
+            .select_only()
+            .column(UpstreamColumn::Id)
+            .column(UpstreamColumn::At)
+            .column(UpstreamColumn::TraceId)
+            .column(UpstreamColumn::Operation)
+            .column(UpstreamColumn::ResponseStatus)
+            .column(UpstreamColumn::ErrorKind)
+            .column(UpstreamColumn::ErrorMessage)
+            .order_by_asc(UpstreamColumn::At)
+            .order_by_asc(UpstreamColumn::Id)
+            .into_model::<UpstreamOutcomeRow>()
+            .all(&self.db)
+            .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| UpstreamOutcome {
+                id: row.id,
+                at: row.at,
+                trace_id: row.trace_id,
+                operation: row.operation,
+                response_status: row.response_status,
+                error_kind: row.error_kind,
+                error_message: row.error_message,
+            })
+            .collect())
+    }
+
+    async fn list_credential_cooldowns(
+        &self,
+        credential_id: i64,
+        from: OffsetDateTime,
+        to: OffsetDateTime,
+    ) -> StorageResult<Vec<OperationalEventRecord>> {
+        use entities::internal_events::Column as InternalColumn;
+
+        let rows = entities::InternalEvents::find()
+            .filter(InternalColumn::CredentialId.eq(credential_id))
+            .filter(InternalColumn::At.gte(from))
+            .filter(InternalColumn::At.lte(to))
+            .order_by_asc(InternalColumn::At)
+            .order_by_asc(InternalColumn::Id)
+            .all(&self.db)
+            .await?;
+        Ok(rows
+            .into_iter()
+            .filter_map(operational_record_from_model)
+            .collect())
+    }
+
+    async fn query_operational_events(
+        &self,
+        filter: OperationalEventFilter,
+    ) -> StorageResult<OperationalEventQueryResult> {
+        use entities::internal_events::Column as InternalColumn;
+
+        if filter.limit == 0 {
+            return Ok(OperationalEventQueryResult {
+                rows: Vec::new(),
+                has_more: false,
+                next_cursor: None,
+            });
+        }
+        let fetch_limit = u64::try_from(filter.limit.saturating_add(1)).unwrap_or(u64::MAX);
+
+        let mut q = entities::InternalEvents::find()
+            .filter(InternalColumn::At.gte(filter.from))
+            .filter(InternalColumn::At.lte(filter.to));
+        if let Some(event_type) = filter.event_type.as_deref() {
+            q = q.filter(InternalColumn::EventType.eq(event_type));
+        }
+        if let Some(credential_id) = filter.credential_id {
+            q = q.filter(InternalColumn::CredentialId.eq(credential_id));
+        }
+        if let Some(credential_ids) = filter.credential_ids.as_ref() {
+            q = q.filter(InternalColumn::CredentialId.is_in(credential_ids.iter().copied()));
+        }
+        if let Some(model) = filter.model.as_deref() {
+            q = q.filter(InternalColumn::Model.eq(model));
+        }
+        if let Some(cursor) = filter.cursor {
+            q = q.filter(
+                Condition::any().add(InternalColumn::At.lt(cursor.at)).add(
+                    Condition::all()
+                        .add(InternalColumn::At.eq(cursor.at))
+                        .add(InternalColumn::Id.lt(cursor.id)),
+                ),
+            );
         }
+        let rows = q
+            .order_by_desc(InternalColumn::At)
+            .order_by_desc(InternalColumn::Id)
+            .limit(fetch_limit)
+            .all(&self.db)
+            .await?;
+
+        let has_more = rows.len() > filter.limit;
         let next_cursor = if has_more {
-            rows.last().map(|row| LogCursor {
+            rows.get(filter.limit - 1).map(|row| LogCursor {
                 at: row.at,
                 id: row.id,
             })
         } else {
             None
         };
-
-        Ok(LogQueryResult {
+        let rows = rows
+            .into_iter()
+            .take(filter.limit)
+            .filter_map(operational_record_from_model)
+            .collect();
+        Ok(OperationalEventQueryResult {
             rows,
             has_more,
             next_cursor,
         })
     }
+
+    async fn upsert_stats_hourly(&self, rows: &[StatsHourlyRow]) -> StorageResult<()> {
+        use entities::stats_hourly::ActiveModel as StatsActive;
+        use entities::stats_hourly::Column as StatsColumn;
+
+        let now = OffsetDateTime::now_utc();
+        for row in rows {
+            let existing = entities::StatsHourly::find()
+                .filter(StatsColumn::Hour.eq(row.hour))
+                .filter(StatsColumn::Dimension.eq(row.dimension.as_str()))
+                .filter(StatsColumn::DimensionKey.eq(row.dimension_key.as_str()))
+                .one(&self.db)
+                .await?;
+            let histogram = serde_json::to_value(&row.latency_histogram)?;
+            match existing {
+                Some(model) => {
+                    let mut active: StatsActive = model.into();
+                    active.requests = ActiveValue::Set(row.requests);
+                    active.errors = ActiveValue::Set(row.errors);
+                    active.tokens = ActiveValue::Set(row.tokens);
+                    active.latency_histogram = ActiveValue::Set(histogram);
+                    active.updated_at = ActiveValue::Set(now);
+                    active.update(&self.db).await?;
+                }
+                None => {
+                    let active = StatsActive {
+                        id: ActiveValue::NotSet,
+                        hour: ActiveValue::Set(row.hour),
+                        dimension: ActiveValue::Set(row.dimension.clone()),
+                        dimension_key: ActiveValue::Set(row.dimension_key.clone()),
+                        requests: ActiveValue::Set(row.requests),
+                        errors: ActiveValue::Set(row.errors),
+                        tokens: ActiveValue::Set(row.tokens),
+                        latency_histogram: ActiveValue::Set(histogram),
+                        updated_at: ActiveValue::Set(now),
+                    };
+                    entities::StatsHourly::insert(active).exec(&self.db).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn load_stats_hourly(&self, since: OffsetDateTime) -> StorageResult<Vec<StatsHourlyRow>> {
+        use entities::stats_hourly::Column as StatsColumn;
+
+        let rows = entities::StatsHourly::find()
+            .filter(StatsColumn::Hour.gte(since))
+            .order_by_asc(StatsColumn::Hour)
+            .all(&self.db)
+            .await?;
+        Ok(rows
+            .into_iter()
+            .map(|m| StatsHourlyRow {
+                hour: m.hour,
+                dimension: m.dimension,
+                dimension_key: m.dimension_key,
+                requests: m.requests,
+                errors: m.errors,
+                tokens: m.tokens,
+                latency_histogram: serde_json::from_value(m.latency_histogram).unwrap_or_default(),
+            })
+            .collect())
+    }
+
+    async fn append_job_run(&self, record: &JobRunRecord) -> StorageResult<i64> {
+        use entities::job_runs::ActiveModel as JobRunActive;
+
+        let active = JobRunActive {
+            id: ActiveValue::NotSet,
+            job: ActiveValue::Set(record.job.clone()),
+            trigger: ActiveValue::Set(record.trigger.clone()),
+            started_at: ActiveValue::Set(record.started_at),
+            finished_at: ActiveValue::Set(record.finished_at),
+            ok: ActiveValue::Set(record.ok),
+            message: ActiveValue::Set(record.message.clone()),
+        };
+        let res = entities::JobRuns::insert(active).exec(&self.db).await?;
+        Ok(res.last_insert_id)
+    }
+
+    async fn list_job_runs(
+        &self,
+        job: Option<&str>,
+        limit: usize,
+    ) -> StorageResult<Vec<JobRunRecord>> {
+        use entities::job_runs::Column as JobRunColumn;
+
+        let mut query = entities::JobRuns::find();
+        if let Some(job) = job {
+            query = query.filter(JobRunColumn::Job.eq(job));
+        }
+        let rows = query
+            .order_by_desc(JobRunColumn::Id)
+            .limit(limit as u64)
+            .all(&self.db)
+            .await?;
+        Ok(rows
+            .into_iter()
+            .map(|m| JobRunRecord {
+                id: m.id,
+                job: m.job,
+                trigger: m.trigger,
+                started_at: m.started_at,
+                finished_at: m.finished_at,
+                ok: m.ok,
+                message: m.message,
+            })
+            .collect())
+    }
+
+    async fn append_debug_capture(&self, record: &DebugCaptureRecord) -> StorageResult<i64> {
+        use entities::debug_captures::ActiveModel as DebugCaptureActive;
+
+        let active = DebugCaptureActive {
+            id: ActiveValue::NotSet,
+            trace_id: ActiveValue::Set(record.trace_id.clone()),
+            user_key_id: ActiveValue::Set(record.user_key_id),
+            kind: ActiveValue::Set(record.kind.clone()),
+            event_json: ActiveValue::Set(record.event_json.clone()),
+            at: ActiveValue::Set(record.at),
+            expires_at: ActiveValue::Set(record.expires_at),
+        };
+        let res = entities::DebugCaptures::insert(active)
+            .exec(&self.db)
+            .await?;
+        Ok(res.last_insert_id)
+    }
+
+    async fn list_debug_captures(
+        &self,
+        user_key_id: Option<i64>,
+        trace_id: Option<&str>,
+        limit: usize,
+    ) -> StorageResult<Vec<DebugCaptureRecord>> {
+        use entities::debug_captures::Column as DebugCaptureColumn;
+
+        let mut query = entities::DebugCaptures::find();
+        if let Some(user_key_id) = user_key_id {
+            query = query.filter(DebugCaptureColumn::UserKeyId.eq(user_key_id));
+        }
+        if let Some(trace_id) = trace_id {
+            query = query.filter(DebugCaptureColumn::TraceId.eq(trace_id));
+        }
+        let rows = query
+            .order_by_desc(DebugCaptureColumn::Id)
+            .limit(limit as u64)
+            .all(&self.db)
+            .await?;
+        Ok(rows
+            .into_iter()
+            .map(|m| DebugCaptureRecord {
+                id: m.id,
+                trace_id: m.trace_id,
+                user_key_id: m.user_key_id,
+                kind: m.kind,
+                event_json: m.event_json,
+                at: m.at,
+                expires_at: m.expires_at,
+            })
+            .collect())
+    }
+
+    async fn purge_debug_captures(&self, now: OffsetDateTime) -> StorageResult<u64> {
+        use entities::debug_captures::Column as DebugCaptureColumn;
+
+        let res = entities::DebugCaptures::delete_many()
+            .filter(DebugCaptureColumn::ExpiresAt.lte(now))
+            .exec(&self.db)
+            .await?;
+        Ok(res.rows_affected)
+    }
+
+    async fn append_mcp_tool_call(&self, record: &McpToolCallRecord) -> StorageResult<i64> {
+        use entities::mcp_tool_calls::ActiveModel as McpToolCallActive;
+
+        let active = McpToolCallActive {
+            id: ActiveValue::NotSet,
+            trace_id: ActiveValue::Set(record.trace_id.clone()),
+            user_key_id: ActiveValue::Set(record.user_key_id),
+            provider: ActiveValue::Set(record.provider.clone()),
+            server_label: ActiveValue::Set(record.server_label.clone()),
+            tool_name: ActiveValue::Set(record.tool_name.clone()),
+            arguments: ActiveValue::Set(record.arguments.clone()),
+            output: ActiveValue::Set(record.output.clone()),
+            error: ActiveValue::Set(record.error.clone()),
+            at: ActiveValue::Set(record.at),
+        };
+        let res = entities::McpToolCalls::insert(active)
+            .exec(&self.db)
+            .await?;
+        Ok(res.last_insert_id)
+    }
+
+    async fn list_mcp_tool_calls(
+        &self,
+        user_key_id: Option<i64>,
+        limit: usize,
+    ) -> StorageResult<Vec<McpToolCallRecord>> {
+        use entities::mcp_tool_calls::Column as McpToolCallColumn;
+
+        let mut query = entities::McpToolCalls::find();
+        if let Some(user_key_id) = user_key_id {
+            query = query.filter(McpToolCallColumn::UserKeyId.eq(user_key_id));
+        }
+        let rows = query
+            .order_by_desc(McpToolCallColumn::Id)
+            .limit(limit as u64)
+            .all(&self.db)
+            .await?;
+        Ok(rows
+            .into_iter()
+            .map(|m| McpToolCallRecord {
+                id: m.id,
+                trace_id: m.trace_id,
+                user_key_id: m.user_key_id,
+                provider: m.provider,
+                server_label: m.server_label,
+                tool_name: m.tool_name,
+                arguments: m.arguments,
+                output: m.output,
+                error: m.error,
+                at: m.at,
+            })
+            .collect())
+    }
+
+    async fn append_aborted_stream(&self, record: &AbortedStreamRecord) -> StorageResult<i64> {
+        use entities::aborted_streams::ActiveModel as AbortedStreamActive;
+
+        let active = AbortedStreamActive {
+            id: ActiveValue::NotSet,
+            trace_id: ActiveValue::Set(record.trace_id.clone()),
+            user_key_id: ActiveValue::Set(record.user_key_id),
+            provider: ActiveValue::Set(record.provider.clone()),
+            credential_id: ActiveValue::Set(record.credential_id),
+            model: ActiveValue::Set(record.model.clone()),
+            reason: ActiveValue::Set(record.reason.clone()),
+            output_chars: ActiveValue::Set(record.output_chars),
+            input_tokens: ActiveValue::Set(record.input_tokens),
+            output_tokens: ActiveValue::Set(record.output_tokens),
+            duration_ms: ActiveValue::Set(record.duration_ms),
+            at: ActiveValue::Set(record.at),
+        };
+        let res = entities::AbortedStreams::insert(active)
+            .exec(&self.db)
+            .await?;
+        Ok(res.last_insert_id)
+    }
+
+    async fn list_aborted_streams(
+        &self,
+        user_key_id: Option<i64>,
+        limit: usize,
+    ) -> StorageResult<Vec<AbortedStreamRecord>> {
+        use entities::aborted_streams::Column as AbortedStreamColumn;
+
+        let mut query = entities::AbortedStreams::find();
+        if let Some(user_key_id) = user_key_id {
+            query = query.filter(AbortedStreamColumn::UserKeyId.eq(user_key_id));
+        }
+        let rows = query
+            .order_by_desc(AbortedStreamColumn::Id)
+            .limit(limit as u64)
+            .all(&self.db)
+            .await?;
+        Ok(rows
+            .into_iter()
+            .map(|m| AbortedStreamRecord {
+                id: m.id,
+                trace_id: m.trace_id,
+                user_key_id: m.user_key_id,
+                provider: m.provider,
+                credential_id: m.credential_id,
+                model: m.model,
+                reason: m.reason,
+                output_chars: m.output_chars,
+                input_tokens: m.input_tokens,
+                output_tokens: m.output_tokens,
+                duration_ms: m.duration_ms,
+                at: m.at,
+            })
+            .collect())
+    }
+
+    async fn append_tool_call(&self, record: &ToolCallRecord) -> StorageResult<i64> {
+        use entities::tool_calls::ActiveModel as ToolCallActive;
+
+        let active = ToolCallActive {
+            id: ActiveValue::NotSet,
+            trace_id: ActiveValue::Set(record.trace_id.clone()),
+            user_key_id: ActiveValue::Set(record.user_key_id),
+            provider: ActiveValue::Set(record.provider.clone()),
+            model: ActiveValue::Set(record.model.clone()),
+            proto: ActiveValue::Set(record.proto.clone()),
+            tool_name: ActiveValue::Set(record.tool_name.clone()),
+            call_id: ActiveValue::Set(record.call_id.clone()),
+            arguments_bytes: ActiveValue::Set(record.arguments_bytes),
+            at: ActiveValue::Set(record.at),
+            result_at: ActiveValue::Set(record.result_at),
+            latency_ms: ActiveValue::Set(record.latency_ms),
+        };
+        let res = entities::ToolCalls::insert(active).exec(&self.db).await?;
+        Ok(res.last_insert_id)
+    }
+
+    async fn complete_tool_call(
+        &self,
+        id: i64,
+        result_at: OffsetDateTime,
+        latency_ms: i64,
+    ) -> StorageResult<()> {
+        use entities::tool_calls::ActiveModel as ToolCallActive;
+
+        let Some(model) = entities::ToolCalls::find_by_id(id).one(&self.db).await? else {
+            return Ok(());
+        };
+        let mut active: ToolCallActive = model.into();
+        active.result_at = ActiveValue::Set(Some(result_at));
+        active.latency_ms = ActiveValue::Set(Some(latency_ms));
+        active.update(&self.db).await?;
+        Ok(())
+    }
+
+    async fn list_tool_calls(
+        &self,
+        user_key_id: Option<i64>,
+        tool_name: Option<&str>,
+        limit: usize,
+    ) -> StorageResult<Vec<ToolCallRecord>> {
+        use entities::tool_calls::Column as ToolCallColumn;
+
+        let mut query = entities::ToolCalls::find();
+        if let Some(user_key_id) = user_key_id {
+            query = query.filter(ToolCallColumn::UserKeyId.eq(user_key_id));
+        }
+        if let Some(tool_name) = tool_name {
+            query = query.filter(ToolCallColumn::ToolName.eq(tool_name));
+        }
+        let rows = query
+            .order_by_desc(ToolCallColumn::Id)
+            .limit(limit as u64)
+            .all(&self.db)
+            .await?;
+        Ok(rows
+            .into_iter()
+            .map(|m| ToolCallRecord {
+                id: m.id,
+                trace_id: m.trace_id,
+                user_key_id: m.user_key_id,
+                provider: m.provider,
+                model: m.model,
+                proto: m.proto,
+                tool_name: m.tool_name,
+                call_id: m.call_id,
+                arguments_bytes: m.arguments_bytes,
+                at: m.at,
+                result_at: m.result_at,
+                latency_ms: m.latency_ms,
+            })
+            .collect())
+    }
+
+    async fn append_incident(&self, record: &IncidentRecord) -> StorageResult<i64> {
+        use entities::incidents::ActiveModel as IncidentActive;
+
+        let active = IncidentActive {
+            id: ActiveValue::NotSet,
+            provider: ActiveValue::Set(record.provider.clone()),
+            started_at: ActiveValue::Set(record.started_at),
+            ended_at: ActiveValue::Set(record.ended_at),
+            models: ActiveValue::Set(serde_json::json!(record.models)),
+            error_samples: ActiveValue::Set(serde_json::json!(record.error_samples)),
+            refused_requests: ActiveValue::Set(record.refused_requests),
+        };
+        let res = entities::Incidents::insert(active).exec(&self.db).await?;
+        Ok(res.last_insert_id)
+    }
+
+    async fn update_incident(&self, record: &IncidentRecord) -> StorageResult<()> {
+        use entities::incidents::ActiveModel as IncidentActive;
+
+        let Some(model) = entities::Incidents::find_by_id(record.id)
+            .one(&self.db)
+            .await?
+        else {
+            return Ok(());
+        };
+        let mut active: IncidentActive = model.into();
+        active.ended_at = ActiveValue::Set(record.ended_at);
+        active.models = ActiveValue::Set(serde_json::json!(record.models));
+        active.error_samples = ActiveValue::Set(serde_json::json!(record.error_samples));
+        active.refused_requests = ActiveValue::Set(record.refused_requests);
+        active.update(&self.db).await?;
+        Ok(())
+    }
+
+    async fn list_incidents(
+        &self,
+        provider: Option<&str>,
+        open_only: bool,
+        limit: usize,
+    ) -> StorageResult<Vec<IncidentRecord>> {
+        use entities::incidents::Column as IncidentColumn;
+
+        let mut query = entities::Incidents::find();
+        if let Some(provider) = provider {
+            query = query.filter(IncidentColumn::Provider.eq(provider));
+        }
+        if open_only {
+            query = query.filter(IncidentColumn::EndedAt.is_null());
+        }
+        let rows = query
+            .order_by_desc(IncidentColumn::Id)
+            .limit(limit as u64)
+            .all(&self.db)
+            .await?;
+        Ok(rows
+            .into_iter()
+            .map(|m| IncidentRecord {
+                id: m.id,
+                provider: m.provider,
+                started_at: m.started_at,
+                ended_at: m.ended_at,
+                models: serde_json::from_value(m.models).unwrap_or_default(),
+                error_samples: serde_json::from_value(m.error_samples).unwrap_or_default(),
+                refused_requests: m.refused_requests,
+            })
+            .collect())
+    }
+}
+
+fn usage_record_from_model(m: entities::upstream_usages::Model) -> UsageRecord {
+    UsageRecord {
+        upstream_request_id: m.upstream_request_id,
+        at: m.at,
+        provider: m.provider,
+        credential_id: m.credential_id,
+        attempt_no: m.attempt_no,
+        operation: m.operation,
+        model: m.model,
+        input_tokens: m.input_tokens,
+        output_tokens: m.output_tokens,
+        cache_read_input_tokens: m.cache_read_input_tokens,
+        cache_creation_input_tokens: m.cache_creation_input_tokens,
+        experiment: m.experiment,
+        experiment_arm: m.experiment_arm,
+    }
 }
 
-fn merge_sorted_logs(
+pub(crate) fn merge_sorted_logs(
     upstream_rows: Vec<LogRecord>,
     downstream_rows: Vec<LogRecord>,
     take: usize,
@@ -1201,7 +2900,36 @@ fn merge_sorted_logs(
     merged
 }
 
-fn system_time_to_offset(at: std::time::SystemTime) -> OffsetDateTime {
+fn encode_tags(tags: &[String]) -> Option<String> {
+    if tags.is_empty() {
+        return None;
+    }
+    Some(format!(",{},", tags.join(",")))
+}
+
+fn decode_tags(raw: Option<&str>) -> Vec<String> {
+    raw.map(|raw| {
+        raw.split(',')
+            .filter(|tag| !tag.is_empty())
+            .map(str::to_string)
+            .collect()
+    })
+    .unwrap_or_default()
+}
+
+/// Matches one tag in an `encode_tags` list; LIKE wildcards in the tag match literally.
+fn tag_pattern(tag: &str) -> LikeExpr {
+    let mut escaped = String::with_capacity(tag.len());
+    for c in tag.chars() {
+        if matches!(c, '\\' | '%' | '_') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    LikeExpr::new(format!("%,{escaped},%")).escape('\\')
+}
+
+pub(crate) fn system_time_to_offset(at: std::time::SystemTime) -> OffsetDateTime {
     match at.duration_since(std::time::UNIX_EPOCH) {
         Ok(dur) => OffsetDateTime::from_unix_timestamp_nanos(dur.as_nanos() as i128)
             .unwrap_or_else(|_| OffsetDateTime::now_utc()),
@@ -1209,7 +2937,9 @@ fn system_time_to_offset(at: std::time::SystemTime) -> OffsetDateTime {
     }
 }
 
-fn extract_operational_at(ev: &gproxy_provider_core::OperationalEvent) -> OffsetDateTime {
+pub(crate) fn extract_operational_at(
+    ev: &gproxy_provider_core::OperationalEvent,
+) -> OffsetDateTime {
     match ev {
         gproxy_provider_core::OperationalEvent::UnavailableStart(v) => system_time_to_offset(v.at),
         gproxy_provider_core::OperationalEvent::UnavailableEnd(v) => system_time_to_offset(v.at),
@@ -1219,10 +2949,91 @@ fn extract_operational_at(ev: &gproxy_provider_core::OperationalEvent) -> Offset
         gproxy_provider_core::OperationalEvent::ModelUnavailableEnd(v) => {
             system_time_to_offset(v.at)
         }
+        gproxy_provider_core::OperationalEvent::UserKeyAutoDisabled(v) => {
+            system_time_to_offset(v.at)
+        }
+    }
+}
+
+pub(crate) fn operational_event_type(ev: &gproxy_provider_core::OperationalEvent) -> &'static str {
+    use gproxy_provider_core::OperationalEvent;
+    match ev {
+        OperationalEvent::UnavailableStart(_) => "unavailable_start",
+        OperationalEvent::UnavailableEnd(_) => "unavailable_end",
+        OperationalEvent::ModelUnavailableStart(_) => "model_unavailable_start",
+        OperationalEvent::ModelUnavailableEnd(_) => "model_unavailable_end",
+        OperationalEvent::UserKeyAutoDisabled(_) => "user_key_auto_disabled",
+    }
+}
+
+/// Fields shared by every operational event variant.
+pub(crate) struct OperationalParts {
+    pub(crate) credential_id: Option<i64>,
+    pub(crate) model: Option<String>,
+    pub(crate) reason: Option<gproxy_provider_core::UnavailableReason>,
+    pub(crate) until: Option<std::time::SystemTime>,
+}
+
+impl OperationalParts {
+    pub(crate) fn of(ev: &gproxy_provider_core::OperationalEvent) -> Self {
+        use gproxy_provider_core::OperationalEvent;
+        match ev {
+            OperationalEvent::UnavailableStart(v) => Self {
+                credential_id: Some(v.credential_id),
+                model: None,
+                reason: Some(v.reason),
+                until: Some(v.until),
+            },
+            OperationalEvent::UnavailableEnd(v) => Self {
+                credential_id: Some(v.credential_id),
+                model: None,
+                reason: None,
+                until: None,
+            },
+            OperationalEvent::ModelUnavailableStart(v) => Self {
+                credential_id: Some(v.credential_id),
+                model: Some(v.model.clone()),
+                reason: Some(v.reason),
+                until: Some(v.until),
+            },
+            OperationalEvent::ModelUnavailableEnd(v) => Self {
+                credential_id: Some(v.credential_id),
+                model: Some(v.model.clone()),
+                reason: None,
+                until: None,
+            },
+            OperationalEvent::UserKeyAutoDisabled(_) => Self {
+                credential_id: None,
+                model: None,
+                reason: None,
+                until: None,
+            },
+        }
     }
 }
 
-fn extract_model_for_usage(request_path: &str, request_body: Option<&[u8]>) -> Option<String> {
+fn operational_record_from_model(
+    m: entities::internal_events::Model,
+) -> Option<OperationalEventRecord> {
+    let ev: gproxy_provider_core::OperationalEvent =
+        serde_json::from_value(m.payload_json.clone()).ok()?;
+    let parts = OperationalParts::of(&ev);
+    Some(OperationalEventRecord {
+        id: m.id,
+        at: m.at,
+        event_type: m.event_type,
+        payload: m.payload_json,
+        credential_id: parts.credential_id,
+        model: parts.model,
+        reason: parts.reason,
+        until: parts.until.map(system_time_to_offset),
+    })
+}
+
+pub(crate) fn extract_model_for_usage(
+    request_path: &str,
+    request_body: Option<&[u8]>,
+) -> Option<String> {
     if let Some(body) = request_body
         && let Ok(json) = serde_json::from_slice::<serde_json::Value>(body)
         && let Some(model) = json.get("model").and_then(|v| v.as_str())
@@ -1289,7 +3100,7 @@ fn normalize_model_candidate(raw: &str, allow_no_action_suffix: bool) -> Option<
     Some(s.to_string())
 }
 
-fn derive_downstream_observability(
+pub(crate) fn derive_downstream_observability(
     request_method: &str,
     request_path: &str,
     request_body: Option<&[u8]>,
@@ -1378,6 +3189,12 @@ fn derive_downstream_operation(
         if route_path.contains(":countTokens") {
             return Some("CountTokens".to_string());
         }
+        if route_path.contains(":batchGenerateContent") {
+            return Some("BatchGenerateContent".to_string());
+        }
+    }
+    if is_get && route_path.starts_with("/v1beta/batches/") {
+        return Some("BatchGet".to_string());
     }
     if route_path.starts_with("/v1/responses/") {
         if is_post && route_path.ends_with("/cancel") {
@@ -1393,6 +3210,36 @@ fn derive_downstream_operation(
             return Some("ResponseDelete".to_string());
         }
     }
+    if is_get && route_path == "/v1/responses" {
+        return Some("ResponseList".to_string());
+    }
+    if route_path == "/v1/conversations" && is_post {
+        return Some("ConversationCreate".to_string());
+    }
+    if let Some(rest) = route_path.strip_prefix("/v1/conversations/") {
+        let op = match (rest.split('/').count(), rest.contains("/items")) {
+            (1, _) if is_get => "ConversationGet",
+            (1, _) if is_post => "ConversationUpdate",
+            (1, _) if is_delete => "ConversationDelete",
+            (2, true) if is_get => "ConversationItemList",
+            (2, true) if is_post => "ConversationItemCreate",
+            (3, true) if is_get => "ConversationItemGet",
+            (3, true) if is_delete => "ConversationItemDelete",
+            _ => return None,
+        };
+        return Some(op.to_string());
+    }
+    if is_get && route_path == "/v1/files" {
+        return Some("FileList".to_string());
+    }
+    if route_path.starts_with("/v1/files/") && !route_path.ends_with("/content") {
+        if is_get {
+            return Some("FileGet".to_string());
+        }
+        if is_delete {
+            return Some("FileDelete".to_string());
+        }
+    }
 
     None
 }
@@ -1403,3 +3250,21 @@ fn extract_stream_flag(request_body: Option<&[u8]>) -> bool {
         .and_then(|json| json.get("stream").and_then(|value| value.as_bool()))
         .unwrap_or(false)
 }
+
+#[cfg(test)]
+mod tests {
+    use sea_orm::QueryTrait;
+
+    use super::*;
+
+    #[test]
+    fn tag_filter_matches_the_tag_literally() {
+        use entities::downstream_requests::Column as DownstreamColumn;
+
+        let sql = entities::downstream_requests::Entity::find()
+            .filter(DownstreamColumn::Tags.like(tag_pattern("team_a%")))
+            .build(DatabaseBackend::Sqlite)
+            .to_string();
+        assert!(sql.contains(r"LIKE '%,team\_a\%,%' ESCAPE '\'"), "{sql}");
+    }
+}
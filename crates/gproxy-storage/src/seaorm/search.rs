@@ -0,0 +1,177 @@
+//! Full-text index of logged bodies and error messages (`log_search`): an FTS5 table on
+//! SQLite, a `tsvector` column with a GIN index on Postgres, and a plain table scanned
+//! with `LOCATE` on MySQL. Rows are added by `append_event` while search is enabled.
+
+use std::sync::atomic::Ordering;
+
+use sea_orm::{ConnectionTrait, DatabaseBackend, Statement, Value};
+use time::OffsetDateTime;
+
+use super::SeaOrmStorage;
+use crate::search::{search_text, snippet};
+use crate::storage::{LogRecordKind, LogSearchHit, LogSearchQuery, StorageResult};
+
+/// One log row to index.
+pub(super) struct LogText<'a> {
+    pub kind: LogRecordKind,
+    pub log_id: i64,
+    pub trace_id: Option<&'a str>,
+    pub user_id: Option<i64>,
+    pub at: OffsetDateTime,
+    pub request_body: Option<&'a [u8]>,
+    pub response_body: Option<&'a [u8]>,
+    pub error_message: Option<&'a str>,
+}
+
+impl SeaOrmStorage {
+    pub(super) async fn ensure_log_search_table(&self) -> StorageResult<()> {
+        let statements: &[&str] = match self.db.get_database_backend() {
+            DatabaseBackend::Sqlite => &[
+                "CREATE VIRTUAL TABLE IF NOT EXISTS log_search USING fts5(body, \
+                 kind UNINDEXED, log_id UNINDEXED, trace_id UNINDEXED, user_id UNINDEXED, \
+                 at_ms UNINDEXED)",
+            ],
+            DatabaseBackend::Postgres => &[
+                "CREATE TABLE IF NOT EXISTS log_search (id BIGSERIAL PRIMARY KEY, \
+                 kind TEXT NOT NULL, log_id BIGINT NOT NULL, trace_id TEXT, user_id BIGINT, \
+                 at_ms BIGINT NOT NULL, body TEXT NOT NULL, \
+                 body_tsv TSVECTOR GENERATED ALWAYS AS (to_tsvector('simple', body)) STORED)",
+                "CREATE INDEX IF NOT EXISTS idx_log_search_body_tsv ON log_search \
+                 USING GIN (body_tsv)",
+                "CREATE INDEX IF NOT EXISTS idx_log_search_at_ms ON log_search (at_ms)",
+            ],
+            _ => &[
+                "CREATE TABLE IF NOT EXISTS log_search (id BIGINT AUTO_INCREMENT PRIMARY KEY, \
+                 kind VARCHAR(16) NOT NULL, log_id BIGINT NOT NULL, trace_id VARCHAR(255), \
+                 user_id BIGINT, at_ms BIGINT NOT NULL, body MEDIUMTEXT NOT NULL, \
+                 KEY idx_log_search_at_ms (at_ms))",
+            ],
+        };
+        for sql in statements {
+            self.db.execute_unprepared(sql).await?;
+        }
+        Ok(())
+    }
+
+    /// Adds a log row to the index when search is enabled. Best-effort: the log row is
+    /// already stored, so a failure here only makes it unsearchable.
+    pub(super) async fn index_log_text(&self, row: LogText<'_>) {
+        if !self.log_search.load(Ordering::Relaxed) {
+            return;
+        }
+        let Some(body) = search_text(row.request_body, row.response_body, row.error_message) else {
+            return;
+        };
+        let values: Vec<Value> = vec![
+            body.into(),
+            kind_name(row.kind).into(),
+            row.log_id.into(),
+            row.trace_id.map(str::to_string).into(),
+            row.user_id.into(),
+            unix_ms(row.at).into(),
+        ];
+        let stmt = self.statement(
+            "INSERT INTO log_search (body, kind, log_id, trace_id, user_id, at_ms) \
+             VALUES (?, ?, ?, ?, ?, ?)",
+            values,
+        );
+        if let Err(err) = self.db.execute_raw(stmt).await {
+            gproxy_common::log_warn!("storage", "log search index insert failed: {err}");
+        }
+    }
+
+    pub(super) async fn search_log_text(
+        &self,
+        query: LogSearchQuery,
+    ) -> StorageResult<Vec<LogSearchHit>> {
+        let words: Vec<&str> = query.text.split_whitespace().collect();
+        if words.is_empty() || query.limit == 0 {
+            return Ok(Vec::new());
+        }
+        let backend = self.db.get_database_backend();
+        let (condition, needle) = match backend {
+            DatabaseBackend::Sqlite => (
+                "log_search MATCH ?",
+                format!("\"{}\"", words.join(" ").replace('"', "\"\"")),
+            ),
+            DatabaseBackend::Postgres => {
+                ("body_tsv @@ phraseto_tsquery('simple', ?)", words.join(" "))
+            }
+            _ => ("LOCATE(?, body) > 0", words.join(" ")),
+        };
+        let mut sql = format!(
+            "SELECT kind, log_id, trace_id, user_id, at_ms, body FROM log_search \
+             WHERE {condition} AND at_ms >= ? AND at_ms <= ?"
+        );
+        let mut values: Vec<Value> = vec![
+            needle.into(),
+            unix_ms(query.from).into(),
+            unix_ms(query.to).into(),
+        ];
+        if let Some(kind) = query.kind {
+            sql.push_str(" AND kind = ?");
+            values.push(kind_name(kind).into());
+        }
+        if let Some(user_ids) = &query.user_ids {
+            if user_ids.is_empty() {
+                return Ok(Vec::new());
+            }
+            sql.push_str(" AND user_id IN (");
+            sql.push_str(&vec!["?"; user_ids.len()].join(", "));
+            sql.push(')');
+            values.extend(user_ids.iter().map(|id| Value::from(*id)));
+        }
+        sql.push_str(" ORDER BY at_ms DESC, log_id DESC LIMIT ?");
+        values.push(i64::try_from(query.limit).unwrap_or(i64::MAX).into());
+
+        let rows = self.db.query_all_raw(self.statement(&sql, values)).await?;
+        let mut hits = Vec::with_capacity(rows.len());
+        for row in rows {
+            let kind = match row.try_get::<String>("", "kind")?.as_str() {
+                "downstream" => LogRecordKind::Downstream,
+                _ => LogRecordKind::Upstream,
+            };
+            let at_ms: i64 = row.try_get("", "at_ms")?;
+            let body: String = row.try_get("", "body")?;
+            hits.push(LogSearchHit {
+                kind,
+                id: row.try_get("", "log_id")?,
+                at: OffsetDateTime::from_unix_timestamp_nanos(i128::from(at_ms) * 1_000_000)
+                    .unwrap_or(OffsetDateTime::UNIX_EPOCH),
+                trace_id: row.try_get("", "trace_id")?,
+                user_id: row.try_get("", "user_id")?,
+                snippet: snippet(&body, &query.text),
+            });
+        }
+        Ok(hits)
+    }
+
+    /// A statement written with `?` placeholders, numbered for Postgres.
+    fn statement(&self, sql: &str, values: Vec<Value>) -> Statement {
+        let backend = self.db.get_database_backend();
+        let sql = if backend == DatabaseBackend::Postgres {
+            let mut numbered = String::with_capacity(sql.len() + 16);
+            for (i, part) in sql.split('?').enumerate() {
+                if i > 0 {
+                    numbered.push_str(&format!("${i}"));
+                }
+                numbered.push_str(part);
+            }
+            numbered
+        } else {
+            sql.to_string()
+        };
+        Statement::from_sql_and_values(backend, sql, values)
+    }
+}
+
+fn kind_name(kind: LogRecordKind) -> &'static str {
+    match kind {
+        LogRecordKind::Upstream => "upstream",
+        LogRecordKind::Downstream => "downstream",
+    }
+}
+
+fn unix_ms(at: OffsetDateTime) -> i64 {
+    i64::try_from(at.unix_timestamp_nanos() / 1_000_000).unwrap_or_default()
+}
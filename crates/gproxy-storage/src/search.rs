@@ -0,0 +1,132 @@
+//! Text of a log row as indexed for full-text search, and the snippet shown for a hit.
+
+/// Most text indexed per log row; bodies beyond it are not searchable.
+pub(crate) const MAX_SEARCH_TEXT_BYTES: usize = 256 * 1024;
+/// Characters kept on each side of the match in a snippet.
+const SNIPPET_CONTEXT: usize = 80;
+
+/// Request body, response body and error message as one text, or `None` when all are
+/// empty. Bytes that are not UTF-8 are replaced and NUL characters dropped.
+pub(crate) fn search_text(
+    request_body: Option<&[u8]>,
+    response_body: Option<&[u8]>,
+    error_message: Option<&str>,
+) -> Option<String> {
+    let mut text = String::new();
+    let parts = [
+        request_body,
+        response_body,
+        error_message.map(str::as_bytes),
+    ];
+    for part in parts.into_iter().flatten().filter(|part| !part.is_empty()) {
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(&String::from_utf8_lossy(part));
+        if text.len() >= MAX_SEARCH_TEXT_BYTES {
+            break;
+        }
+    }
+    text.retain(|c| c != '\0');
+    truncate_at_char(&mut text, MAX_SEARCH_TEXT_BYTES);
+    (!text.is_empty()).then_some(text)
+}
+
+/// Whether `text` contains the words of `needle` in order, ignoring case and spacing.
+pub(crate) fn matches(text: &str, needle: &str) -> bool {
+    let words = words(needle);
+    !words.is_empty() && find(&text.to_ascii_lowercase(), &words).is_some()
+}
+
+/// Up to [`SNIPPET_CONTEXT`] characters on each side of the first match of `needle`, or
+/// the start of `text` when the match can't be placed (e.g. the index stemmed a word).
+pub(crate) fn snippet(text: &str, needle: &str) -> String {
+    let words = words(needle);
+    let (start, end) = find(&text.to_ascii_lowercase(), &words).unwrap_or((0, 0));
+    let from = text[..start]
+        .char_indices()
+        .rev()
+        .nth(SNIPPET_CONTEXT - 1)
+        .map_or(0, |(i, _)| i);
+    let to = text[end..]
+        .char_indices()
+        .nth(SNIPPET_CONTEXT)
+        .map_or(text.len(), |(i, _)| end + i);
+    let mut out = String::new();
+    if from > 0 {
+        out.push('…');
+    }
+    out.push_str(&text[from..to]);
+    if to < text.len() {
+        out.push('…');
+    }
+    out
+}
+
+fn words(needle: &str) -> Vec<String> {
+    needle
+        .split_whitespace()
+        .map(str::to_ascii_lowercase)
+        .collect()
+}
+
+/// Byte range in `lower` of the words, one after another with only whitespace between.
+fn find(lower: &str, words: &[String]) -> Option<(usize, usize)> {
+    let first = words.first()?;
+    let mut from = 0;
+    while let Some(pos) = lower[from..].find(first.as_str()) {
+        let start = from + pos;
+        let mut end = start + first.len();
+        let matched = words[1..].iter().all(|word| {
+            let rest = &lower[end..];
+            let gap = rest.len() - rest.trim_start().len();
+            if gap > 0 && rest[gap..].starts_with(word.as_str()) {
+                end += gap + word.len();
+                true
+            } else {
+                false
+            }
+        });
+        if matched {
+            return Some((start, end));
+        }
+        from = start + lower[start..].chars().next().map_or(1, char::len_utf8);
+    }
+    None
+}
+
+fn truncate_at_char(text: &mut String, max: usize) {
+    if text.len() > max {
+        let mut cut = max;
+        while !text.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        text.truncate(cut);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn phrases_match_across_case_and_spacing() {
+        let text = search_text(
+            Some(b"{\"content\":\"Please pay Invoice\n  4711 today\"}"),
+            None,
+            Some("upstream said no"),
+        )
+        .unwrap();
+        assert!(text.ends_with("\nupstream said no"));
+        assert!(matches(&text, "invoice 4711"));
+        assert!(matches(&text, "SAID"));
+        assert!(!matches(&text, "invoice 4712"));
+        assert!(!matches(&text, "  "));
+        assert_eq!(search_text(Some(b""), None, None), None);
+
+        let long = format!("{}invoice 4711{}", "a".repeat(200), "b".repeat(200));
+        let snippet = snippet(&long, "Invoice 4711");
+        assert!(snippet.starts_with('…') && snippet.ends_with('…'));
+        assert_eq!(snippet.chars().count(), 80 + "invoice 4711".len() + 80 + 2);
+    }
+}
@@ -4,24 +4,49 @@ use std::sync::Arc;
 
 use gproxy_provider_core::{Event, EventSink};
 
-use crate::Storage;
+use crate::TelemetryStorage;
+use crate::journal::{UsageJournal, record_blocking};
 
-/// Persist events into DB via `Storage::append_event`.
-pub struct DbEventSink<S: Storage> {
+/// Persist events into DB via `TelemetryStorage::append_event`.
+pub struct DbEventSink<S: TelemetryStorage + ?Sized> {
     storage: Arc<S>,
+    journal: Option<Arc<UsageJournal>>,
 }
 
-impl<S: Storage> DbEventSink<S> {
+impl<S: TelemetryStorage + ?Sized> DbEventSink<S> {
     pub fn new(storage: Arc<S>) -> Self {
-        Self { storage }
+        Self {
+            storage,
+            journal: None,
+        }
+    }
+
+    /// Journals usage events before writing them, see [`UsageJournal`].
+    pub fn with_journal(mut self, journal: Arc<UsageJournal>) -> Self {
+        self.journal = Some(journal);
+        self
     }
 }
 
-impl<S: Storage> EventSink for DbEventSink<S> {
+impl<S: TelemetryStorage + ?Sized> EventSink for DbEventSink<S> {
     fn write<'a>(&'a self, event: &'a Event) -> Pin<Box<dyn Future<Output = ()> + Send + 'a>> {
         Box::pin(async move {
-            // Event persistence must not block the request path; best-effort is fine.
-            let _ = self.storage.append_event(event).await;
+            let seq = match &self.journal {
+                Some(journal) => record_blocking(journal, event).await,
+                None => None,
+            };
+            // Event persistence must not block the request path; best-effort is fine,
+            // except for journaled usage events, which are replayed until stored.
+            let stored = self.storage.append_event(event).await.is_ok();
+            if let (Some(journal), Some(seq)) = (&self.journal, seq) {
+                if stored {
+                    if let Err(err) = journal.ack(seq) {
+                        gproxy_common::log_error!("storage", "usage journal ack failed: {err}");
+                    }
+                } else {
+                    journal.fail(seq);
+                }
+            }
         })
     }
 }
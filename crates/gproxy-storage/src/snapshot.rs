@@ -30,11 +30,45 @@ pub struct CredentialRow {
     pub updated_at: OffsetDateTime,
 }
 
+#[derive(Debug, Clone)]
+pub struct OrganizationRow {
+    pub id: i64,
+    pub name: String,
+    pub enabled: bool,
+    /// Optional admin token scoped to this organization.
+    pub admin_key: Option<String>,
+    /// Request/response bodies of the organization's traffic are never logged.
+    pub omit_bodies: bool,
+    /// Per-minute limits shared by every key of the organization.
+    pub rpm_limit: Option<i64>,
+    pub tpm_limit: Option<i64>,
+    /// Tokens the organization's keys may use per calendar month (UTC).
+    pub monthly_token_budget: Option<i64>,
+    /// Provider/model that aggregate routes fall back to for member keys without
+    /// defaults of their own.
+    pub default_provider: Option<String>,
+    pub default_model: Option<String>,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+}
+
+/// Provider/credential grant for an organization. `credential_id = None` grants
+/// every credential of the provider.
+#[derive(Debug, Clone)]
+pub struct OrgGrantRow {
+    pub id: i64,
+    pub org_id: i64,
+    pub provider_id: i64,
+    pub credential_id: Option<i64>,
+    pub created_at: OffsetDateTime,
+}
+
 #[derive(Debug, Clone)]
 pub struct UserRow {
     pub id: i64,
     pub name: String,
     pub enabled: bool,
+    pub org_id: Option<i64>,
     pub created_at: OffsetDateTime,
     pub updated_at: OffsetDateTime,
 }
@@ -46,6 +80,66 @@ pub struct UserKeyRow {
     pub api_key: String,
     pub label: Option<String>,
     pub enabled: bool,
+    pub rpm_limit: Option<i64>,
+    pub tpm_limit: Option<i64>,
+    pub stream_tps_limit: Option<i64>,
+    pub max_output_tokens: Option<i64>,
+    /// Request/response bodies of this key's traffic are never logged.
+    pub omit_bodies: bool,
+    pub default_provider: Option<String>,
+    pub default_model: Option<String>,
+    pub mcp_policy: Option<JsonValue>,
+    pub moderation_policy: Option<JsonValue>,
+    pub prelude_template: Option<String>,
+    pub key_scope: Option<JsonValue>,
+    pub expires_at: Option<OffsetDateTime>,
+    pub allowed_origins: Option<JsonValue>,
+    pub body_sample_percent: Option<i64>,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+}
+
+#[derive(Debug, Clone)]
+pub struct ModelProfileRow {
+    pub id: i64,
+    pub name: String,
+    pub provider: String,
+    pub model: String,
+    pub settings_json: JsonValue,
+    pub enabled: bool,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExperimentRow {
+    pub id: i64,
+    pub name: String,
+    pub arms_json: JsonValue,
+    pub split: String,
+    pub enabled: bool,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+}
+
+/// A saved `/admin/logs` filter over a trailing window.
+#[derive(Debug, Clone)]
+pub struct LogViewRow {
+    pub id: i64,
+    pub name: String,
+    pub filter_json: JsonValue,
+    pub window_secs: i64,
+    pub alert_threshold: Option<i64>,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+}
+
+/// A named secret; config refers to it by name.
+#[derive(Debug, Clone)]
+pub struct SecretRow {
+    pub id: i64,
+    pub name: String,
+    pub value: String,
     pub created_at: OffsetDateTime,
     pub updated_at: OffsetDateTime,
 }
@@ -55,6 +149,12 @@ pub struct StorageSnapshot {
     pub global_config: Option<GlobalConfigRow>,
     pub providers: Vec<ProviderRow>,
     pub credentials: Vec<CredentialRow>,
+    pub organizations: Vec<OrganizationRow>,
+    pub org_grants: Vec<OrgGrantRow>,
     pub users: Vec<UserRow>,
     pub user_keys: Vec<UserKeyRow>,
+    pub model_profiles: Vec<ModelProfileRow>,
+    pub experiments: Vec<ExperimentRow>,
+    pub secrets: Vec<SecretRow>,
+    pub log_views: Vec<LogViewRow>,
 }
@@ -0,0 +1,606 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use time::OffsetDateTime;
+
+use gproxy_common::GlobalConfig;
+use gproxy_provider_core::Event;
+
+use crate::snapshot::{GlobalConfigRow, StorageSnapshot};
+use crate::storage::{
+    AbortedStreamRecord, ConfigStorage, DebugCaptureRecord, DownstreamRequestRecord,
+    IncidentRecord, JobRunRecord, LogQueryFilter, LogQueryResult, LogSearchHit, LogSearchQuery,
+    McpToolCallRecord, OperationalEventFilter, OperationalEventQueryResult, OperationalEventRecord,
+    StatsHourlyRow, StorageResult, TelemetryStorage, ToolCallRecord, UpstreamOutcome,
+    UsageAggregate, UsageAggregateFilter, UsageRecord,
+};
+
+/// Configuration on one backend, logs/usage/events/stats on another (e.g. config in
+/// Postgres, telemetry in a second SQLite file).
+pub struct SplitStorage {
+    config: Arc<dyn ConfigStorage>,
+    telemetry: Arc<dyn TelemetryStorage>,
+}
+
+impl SplitStorage {
+    pub fn new(config: Arc<dyn ConfigStorage>, telemetry: Arc<dyn TelemetryStorage>) -> Self {
+        Self { config, telemetry }
+    }
+
+    pub fn config(&self) -> &Arc<dyn ConfigStorage> {
+        &self.config
+    }
+
+    pub fn telemetry(&self) -> &Arc<dyn TelemetryStorage> {
+        &self.telemetry
+    }
+}
+
+#[async_trait]
+impl ConfigStorage for SplitStorage {
+    async fn sync(&self) -> StorageResult<()> {
+        self.config.sync().await
+    }
+
+    async fn load_global_config(&self) -> StorageResult<Option<GlobalConfigRow>> {
+        self.config.load_global_config().await
+    }
+
+    async fn upsert_global_config(&self, config: &GlobalConfig) -> StorageResult<()> {
+        self.config.upsert_global_config(config).await
+    }
+
+    async fn load_snapshot(&self) -> StorageResult<StorageSnapshot> {
+        self.config.load_snapshot().await
+    }
+
+    async fn provider_names(&self) -> StorageResult<Vec<String>> {
+        self.config.provider_names().await
+    }
+
+    async fn upsert_provider(
+        &self,
+        name: &str,
+        config_json: &serde_json::Value,
+        enabled: bool,
+    ) -> StorageResult<i64> {
+        self.config
+            .upsert_provider(name, config_json, enabled)
+            .await
+    }
+
+    async fn delete_provider(&self, name: &str) -> StorageResult<()> {
+        self.config.delete_provider(name).await
+    }
+
+    async fn insert_credential(
+        &self,
+        provider_name: &str,
+        name: Option<&str>,
+        settings_json: &serde_json::Value,
+        secret_json: &serde_json::Value,
+        enabled: bool,
+    ) -> StorageResult<i64> {
+        self.config
+            .insert_credential(provider_name, name, settings_json, secret_json, enabled)
+            .await
+    }
+
+    async fn update_credential(
+        &self,
+        credential_id: i64,
+        name: Option<&str>,
+        settings_json: &serde_json::Value,
+        secret_json: &serde_json::Value,
+    ) -> StorageResult<()> {
+        self.config
+            .update_credential(credential_id, name, settings_json, secret_json)
+            .await
+    }
+
+    async fn set_credential_enabled(&self, credential_id: i64, enabled: bool) -> StorageResult<()> {
+        self.config
+            .set_credential_enabled(credential_id, enabled)
+            .await
+    }
+
+    async fn delete_credential(&self, credential_id: i64) -> StorageResult<()> {
+        self.config.delete_credential(credential_id).await
+    }
+
+    async fn upsert_org_by_id(
+        &self,
+        org_id: i64,
+        name: &str,
+        enabled: bool,
+        admin_key: Option<&str>,
+    ) -> StorageResult<()> {
+        self.config
+            .upsert_org_by_id(org_id, name, enabled, admin_key)
+            .await
+    }
+
+    async fn set_org_enabled(&self, org_id: i64, enabled: bool) -> StorageResult<()> {
+        self.config.set_org_enabled(org_id, enabled).await
+    }
+
+    async fn set_org_omit_bodies(&self, org_id: i64, omit_bodies: bool) -> StorageResult<()> {
+        self.config.set_org_omit_bodies(org_id, omit_bodies).await
+    }
+
+    async fn update_org_limits(
+        &self,
+        org_id: i64,
+        rpm_limit: Option<i64>,
+        tpm_limit: Option<i64>,
+        monthly_token_budget: Option<i64>,
+    ) -> StorageResult<()> {
+        self.config
+            .update_org_limits(org_id, rpm_limit, tpm_limit, monthly_token_budget)
+            .await
+    }
+
+    async fn update_org_defaults(
+        &self,
+        org_id: i64,
+        default_provider: Option<&str>,
+        default_model: Option<&str>,
+    ) -> StorageResult<()> {
+        self.config
+            .update_org_defaults(org_id, default_provider, default_model)
+            .await
+    }
+
+    async fn delete_org(&self, org_id: i64) -> StorageResult<()> {
+        self.config.delete_org(org_id).await
+    }
+
+    async fn insert_org_grant(
+        &self,
+        org_id: i64,
+        provider_name: &str,
+        credential_id: Option<i64>,
+    ) -> StorageResult<i64> {
+        self.config
+            .insert_org_grant(org_id, provider_name, credential_id)
+            .await
+    }
+
+    async fn delete_org_grant(&self, grant_id: i64) -> StorageResult<()> {
+        self.config.delete_org_grant(grant_id).await
+    }
+
+    async fn upsert_user_by_id(
+        &self,
+        user_id: i64,
+        name: &str,
+        enabled: bool,
+    ) -> StorageResult<()> {
+        self.config.upsert_user_by_id(user_id, name, enabled).await
+    }
+
+    async fn set_user_enabled(&self, user_id: i64, enabled: bool) -> StorageResult<()> {
+        self.config.set_user_enabled(user_id, enabled).await
+    }
+
+    async fn set_user_org(&self, user_id: i64, org_id: Option<i64>) -> StorageResult<()> {
+        self.config.set_user_org(user_id, org_id).await
+    }
+
+    async fn delete_user(&self, user_id: i64) -> StorageResult<()> {
+        self.config.delete_user(user_id).await
+    }
+
+    async fn insert_user_key(
+        &self,
+        user_id: i64,
+        api_key: &str,
+        label: Option<&str>,
+        enabled: bool,
+    ) -> StorageResult<i64> {
+        self.config
+            .insert_user_key(user_id, api_key, label, enabled)
+            .await
+    }
+
+    async fn set_user_key_enabled(&self, user_key_id: i64, enabled: bool) -> StorageResult<()> {
+        self.config.set_user_key_enabled(user_key_id, enabled).await
+    }
+
+    async fn set_user_key_omit_bodies(
+        &self,
+        user_key_id: i64,
+        omit_bodies: bool,
+    ) -> StorageResult<()> {
+        self.config
+            .set_user_key_omit_bodies(user_key_id, omit_bodies)
+            .await
+    }
+
+    async fn set_user_key_body_sample_percent(
+        &self,
+        user_key_id: i64,
+        body_sample_percent: Option<i64>,
+    ) -> StorageResult<()> {
+        self.config
+            .set_user_key_body_sample_percent(user_key_id, body_sample_percent)
+            .await
+    }
+
+    async fn update_user_key_label(
+        &self,
+        user_key_id: i64,
+        label: Option<&str>,
+    ) -> StorageResult<()> {
+        self.config.update_user_key_label(user_key_id, label).await
+    }
+
+    async fn update_user_key_limits(
+        &self,
+        user_key_id: i64,
+        rpm_limit: Option<i64>,
+        tpm_limit: Option<i64>,
+        stream_tps_limit: Option<i64>,
+        max_output_tokens: Option<i64>,
+    ) -> StorageResult<()> {
+        self.config
+            .update_user_key_limits(
+                user_key_id,
+                rpm_limit,
+                tpm_limit,
+                stream_tps_limit,
+                max_output_tokens,
+            )
+            .await
+    }
+
+    async fn update_user_key_defaults(
+        &self,
+        user_key_id: i64,
+        default_provider: Option<&str>,
+        default_model: Option<&str>,
+    ) -> StorageResult<()> {
+        self.config
+            .update_user_key_defaults(user_key_id, default_provider, default_model)
+            .await
+    }
+
+    async fn update_user_key_mcp_policy(
+        &self,
+        user_key_id: i64,
+        mcp_policy: Option<&serde_json::Value>,
+    ) -> StorageResult<()> {
+        self.config
+            .update_user_key_mcp_policy(user_key_id, mcp_policy)
+            .await
+    }
+
+    async fn update_user_key_moderation_policy(
+        &self,
+        user_key_id: i64,
+        moderation_policy: Option<&serde_json::Value>,
+    ) -> StorageResult<()> {
+        self.config
+            .update_user_key_moderation_policy(user_key_id, moderation_policy)
+            .await
+    }
+
+    async fn update_user_key_prelude_template(
+        &self,
+        user_key_id: i64,
+        prelude_template: Option<&str>,
+    ) -> StorageResult<()> {
+        self.config
+            .update_user_key_prelude_template(user_key_id, prelude_template)
+            .await
+    }
+
+    async fn update_user_key_scope(
+        &self,
+        user_key_id: i64,
+        key_scope: Option<&serde_json::Value>,
+        expires_at: Option<OffsetDateTime>,
+    ) -> StorageResult<()> {
+        self.config
+            .update_user_key_scope(user_key_id, key_scope, expires_at)
+            .await
+    }
+
+    async fn update_user_key_allowed_origins(
+        &self,
+        user_key_id: i64,
+        allowed_origins: Option<&serde_json::Value>,
+    ) -> StorageResult<()> {
+        self.config
+            .update_user_key_allowed_origins(user_key_id, allowed_origins)
+            .await
+    }
+
+    async fn delete_user_key(&self, user_key_id: i64) -> StorageResult<()> {
+        self.config.delete_user_key(user_key_id).await
+    }
+
+    async fn upsert_model_profile(
+        &self,
+        name: &str,
+        provider: &str,
+        model: &str,
+        settings_json: &serde_json::Value,
+        enabled: bool,
+    ) -> StorageResult<i64> {
+        self.config
+            .upsert_model_profile(name, provider, model, settings_json, enabled)
+            .await
+    }
+
+    async fn delete_model_profile(&self, name: &str) -> StorageResult<()> {
+        self.config.delete_model_profile(name).await
+    }
+
+    async fn upsert_experiment(
+        &self,
+        name: &str,
+        arms_json: &serde_json::Value,
+        split: &str,
+        enabled: bool,
+    ) -> StorageResult<i64> {
+        self.config
+            .upsert_experiment(name, arms_json, split, enabled)
+            .await
+    }
+
+    async fn delete_experiment(&self, name: &str) -> StorageResult<()> {
+        self.config.delete_experiment(name).await
+    }
+
+    async fn upsert_secret(&self, name: &str, value: &str) -> StorageResult<i64> {
+        self.config.upsert_secret(name, value).await
+    }
+
+    async fn delete_secret(&self, name: &str) -> StorageResult<()> {
+        self.config.delete_secret(name).await
+    }
+
+    async fn upsert_log_view(
+        &self,
+        name: &str,
+        filter_json: &serde_json::Value,
+        window_secs: i64,
+        alert_threshold: Option<i64>,
+    ) -> StorageResult<i64> {
+        self.config
+            .upsert_log_view(name, filter_json, window_secs, alert_threshold)
+            .await
+    }
+
+    async fn delete_log_view(&self, name: &str) -> StorageResult<()> {
+        self.config.delete_log_view(name).await
+    }
+}
+
+#[async_trait]
+impl TelemetryStorage for SplitStorage {
+    async fn sync_telemetry(&self) -> StorageResult<()> {
+        self.telemetry.sync_telemetry().await
+    }
+
+    async fn append_event(&self, event: &Event) -> StorageResult<()> {
+        self.telemetry.append_event(event).await
+    }
+
+    async fn aggregate_usage_tokens(
+        &self,
+        filter: UsageAggregateFilter,
+    ) -> StorageResult<UsageAggregate> {
+        self.telemetry.aggregate_usage_tokens(filter).await
+    }
+
+    async fn query_logs(&self, filter: LogQueryFilter) -> StorageResult<LogQueryResult> {
+        self.telemetry.query_logs(filter).await
+    }
+
+    async fn get_downstream_request(
+        &self,
+        id: i64,
+    ) -> StorageResult<Option<DownstreamRequestRecord>> {
+        self.telemetry.get_downstream_request(id).await
+    }
+
+    async fn list_trace_usages(&self, trace_id: &str) -> StorageResult<Vec<UsageRecord>> {
+        self.telemetry.list_trace_usages(trace_id).await
+    }
+
+    async fn list_credential_usages(
+        &self,
+        credential_id: i64,
+        from: OffsetDateTime,
+        to: OffsetDateTime,
+    ) -> StorageResult<Vec<UsageRecord>> {
+        self.telemetry
+            .list_credential_usages(credential_id, from, to)
+            .await
+    }
+
+    async fn list_credential_outcomes(
+        &self,
+        credential_id: i64,
+        from: OffsetDateTime,
+        to: OffsetDateTime,
+    ) -> StorageResult<Vec<UpstreamOutcome>> {
+        self.telemetry
+            .list_credential_outcomes(credential_id, from, to)
+            .await
+    }
+
+    async fn list_credential_cooldowns(
+        &self,
+        credential_id: i64,
+        from: OffsetDateTime,
+        to: OffsetDateTime,
+    ) -> StorageResult<Vec<OperationalEventRecord>> {
+        self.telemetry
+            .list_credential_cooldowns(credential_id, from, to)
+            .await
+    }
+
+    async fn query_operational_events(
+        &self,
+        filter: OperationalEventFilter,
+    ) -> StorageResult<OperationalEventQueryResult> {
+        self.telemetry.query_operational_events(filter).await
+    }
+
+    async fn upsert_stats_hourly(&self, rows: &[StatsHourlyRow]) -> StorageResult<()> {
+        self.telemetry.upsert_stats_hourly(rows).await
+    }
+
+    async fn load_stats_hourly(&self, since: OffsetDateTime) -> StorageResult<Vec<StatsHourlyRow>> {
+        self.telemetry.load_stats_hourly(since).await
+    }
+
+    async fn append_job_run(&self, record: &JobRunRecord) -> StorageResult<i64> {
+        self.telemetry.append_job_run(record).await
+    }
+
+    async fn list_job_runs(
+        &self,
+        job: Option<&str>,
+        limit: usize,
+    ) -> StorageResult<Vec<JobRunRecord>> {
+        self.telemetry.list_job_runs(job, limit).await
+    }
+
+    async fn append_debug_capture(&self, record: &DebugCaptureRecord) -> StorageResult<i64> {
+        self.telemetry.append_debug_capture(record).await
+    }
+
+    async fn list_debug_captures(
+        &self,
+        user_key_id: Option<i64>,
+        trace_id: Option<&str>,
+        limit: usize,
+    ) -> StorageResult<Vec<DebugCaptureRecord>> {
+        self.telemetry
+            .list_debug_captures(user_key_id, trace_id, limit)
+            .await
+    }
+
+    async fn purge_debug_captures(&self, now: OffsetDateTime) -> StorageResult<u64> {
+        self.telemetry.purge_debug_captures(now).await
+    }
+
+    async fn append_mcp_tool_call(&self, record: &McpToolCallRecord) -> StorageResult<i64> {
+        self.telemetry.append_mcp_tool_call(record).await
+    }
+
+    async fn list_mcp_tool_calls(
+        &self,
+        user_key_id: Option<i64>,
+        limit: usize,
+    ) -> StorageResult<Vec<McpToolCallRecord>> {
+        self.telemetry.list_mcp_tool_calls(user_key_id, limit).await
+    }
+
+    async fn append_aborted_stream(&self, record: &AbortedStreamRecord) -> StorageResult<i64> {
+        self.telemetry.append_aborted_stream(record).await
+    }
+
+    fn set_log_search(&self, enabled: bool) {
+        self.telemetry.set_log_search(enabled);
+    }
+
+    async fn search_logs(&self, query: LogSearchQuery) -> StorageResult<Vec<LogSearchHit>> {
+        self.telemetry.search_logs(query).await
+    }
+
+    async fn list_aborted_streams(
+        &self,
+        user_key_id: Option<i64>,
+        limit: usize,
+    ) -> StorageResult<Vec<AbortedStreamRecord>> {
+        self.telemetry
+            .list_aborted_streams(user_key_id, limit)
+            .await
+    }
+
+    async fn append_incident(&self, record: &IncidentRecord) -> StorageResult<i64> {
+        self.telemetry.append_incident(record).await
+    }
+
+    async fn update_incident(&self, record: &IncidentRecord) -> StorageResult<()> {
+        self.telemetry.update_incident(record).await
+    }
+
+    async fn list_incidents(
+        &self,
+        provider: Option<&str>,
+        open_only: bool,
+        limit: usize,
+    ) -> StorageResult<Vec<IncidentRecord>> {
+        self.telemetry
+            .list_incidents(provider, open_only, limit)
+            .await
+    }
+
+    async fn append_tool_call(&self, record: &ToolCallRecord) -> StorageResult<i64> {
+        self.telemetry.append_tool_call(record).await
+    }
+
+    async fn complete_tool_call(
+        &self,
+        id: i64,
+        result_at: OffsetDateTime,
+        latency_ms: i64,
+    ) -> StorageResult<()> {
+        self.telemetry
+            .complete_tool_call(id, result_at, latency_ms)
+            .await
+    }
+
+    async fn list_tool_calls(
+        &self,
+        user_key_id: Option<i64>,
+        tool_name: Option<&str>,
+        limit: usize,
+    ) -> StorageResult<Vec<ToolCallRecord>> {
+        self.telemetry
+            .list_tool_calls(user_key_id, tool_name, limit)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryStorage;
+
+    #[tokio::test]
+    async fn routes_each_half_to_its_backend() {
+        let config = Arc::new(MemoryStorage::new());
+        let telemetry = Arc::new(MemoryStorage::new());
+        let split = SplitStorage::new(config.clone(), telemetry.clone());
+
+        split
+            .upsert_provider("openai", &serde_json::json!({}), true)
+            .await
+            .unwrap();
+        let hour = OffsetDateTime::UNIX_EPOCH;
+        split
+            .upsert_stats_hourly(&[StatsHourlyRow {
+                hour,
+                dimension: "provider".to_string(),
+                dimension_key: "openai".to_string(),
+                requests: 1,
+                errors: 0,
+                tokens: 0,
+                latency_histogram: Vec::new(),
+            }])
+            .await
+            .unwrap();
+
+        assert_eq!(config.provider_names().await.unwrap(), vec!["openai"]);
+        assert!(telemetry.provider_names().await.unwrap().is_empty());
+        assert_eq!(telemetry.load_stats_hourly(hour).await.unwrap().len(), 1);
+        assert!(config.load_stats_hourly(hour).await.unwrap().is_empty());
+    }
+}
@@ -2,7 +2,7 @@ use async_trait::async_trait;
 use time::OffsetDateTime;
 
 use gproxy_common::GlobalConfig;
-use gproxy_provider_core::Event;
+use gproxy_provider_core::{Event, UnavailableReason};
 
 use crate::snapshot::{GlobalConfigRow, StorageSnapshot};
 
@@ -14,6 +14,8 @@ pub enum StorageError {
     Db(#[from] sea_orm::DbErr),
     #[error("serde json error: {0}")]
     Serde(#[from] serde_json::Error),
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
 }
 
 #[derive(Debug, Clone)]
@@ -22,8 +24,13 @@ pub struct UsageAggregateFilter {
     pub to: OffsetDateTime,
     pub provider: Option<String>,
     pub credential_id: Option<i64>,
+    /// Restrict the sum to these users (used for organization budgets).
+    pub user_ids: Option<Vec<i64>>,
     pub model: Option<String>,
     pub model_contains: Option<String>,
+    pub tag: Option<String>,
+    pub experiment: Option<String>,
+    pub experiment_arm: Option<String>,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -36,6 +43,23 @@ pub struct UsageAggregate {
     pub total_tokens: i64,
 }
 
+#[derive(Debug, Clone)]
+pub struct UsageRecord {
+    pub upstream_request_id: i64,
+    pub at: OffsetDateTime,
+    pub provider: String,
+    pub credential_id: Option<i64>,
+    pub attempt_no: i32,
+    pub operation: String,
+    pub model: Option<String>,
+    pub input_tokens: Option<i64>,
+    pub output_tokens: Option<i64>,
+    pub cache_read_input_tokens: Option<i64>,
+    pub cache_creation_input_tokens: Option<i64>,
+    pub experiment: Option<String>,
+    pub experiment_arm: Option<String>,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum LogRecordKind {
     Upstream,
@@ -56,17 +80,181 @@ pub struct LogQueryFilter {
     pub provider: Option<String>,
     pub credential_id: Option<i64>,
     pub user_id: Option<i64>,
+    /// Restrict results to this set of users (used for organization scoping).
+    pub user_ids: Option<Vec<i64>>,
     pub user_key_id: Option<i64>,
     pub trace_id: Option<String>,
     pub operation: Option<String>,
+    pub tag: Option<String>,
     pub request_path_contains: Option<String>,
     pub status_min: Option<i32>,
     pub status_max: Option<i32>,
+    /// Client country/ASN; downstream rows only.
+    pub country: Option<String>,
+    pub asn: Option<i64>,
     pub limit: usize,
     pub cursor: Option<LogCursor>,
     pub include_body: bool,
 }
 
+/// Outcome of one upstream attempt, without headers or bodies.
+#[derive(Debug, Clone)]
+pub struct UpstreamOutcome {
+    pub id: i64,
+    pub at: OffsetDateTime,
+    pub trace_id: Option<String>,
+    pub operation: String,
+    pub response_status: Option<i32>,
+    pub error_kind: Option<String>,
+    pub error_message: Option<String>,
+}
+
+/// One `internal_events` row: a credential (or credential+model) availability transition
+/// or a user key auto-disable.
+#[derive(Debug, Clone)]
+pub struct OperationalEventRecord {
+    pub id: i64,
+    pub at: OffsetDateTime,
+    pub event_type: String,
+    /// The stored event as JSON.
+    pub payload: serde_json::Value,
+    pub credential_id: Option<i64>,
+    pub model: Option<String>,
+    /// Set on `*_start` events.
+    pub reason: Option<UnavailableReason>,
+    pub until: Option<OffsetDateTime>,
+}
+
+#[derive(Debug, Clone)]
+pub struct OperationalEventFilter {
+    pub from: OffsetDateTime,
+    pub to: OffsetDateTime,
+    pub event_type: Option<String>,
+    pub credential_id: Option<i64>,
+    /// Restrict results to this set of credentials (used for provider filtering).
+    pub credential_ids: Option<Vec<i64>>,
+    pub model: Option<String>,
+    pub limit: usize,
+    pub cursor: Option<LogCursor>,
+}
+
+#[derive(Debug, Clone)]
+pub struct OperationalEventQueryResult {
+    pub rows: Vec<OperationalEventRecord>,
+    pub has_more: bool,
+    pub next_cursor: Option<LogCursor>,
+}
+
+/// One persisted hourly aggregate for a stats dimension.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatsHourlyRow {
+    pub hour: OffsetDateTime,
+    pub dimension: String,
+    pub dimension_key: String,
+    pub requests: i64,
+    pub errors: i64,
+    pub tokens: i64,
+    pub latency_histogram: Vec<u64>,
+}
+
+/// One finished run of a scheduled background job.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JobRunRecord {
+    pub id: i64,
+    pub job: String,
+    /// `schedule` or `manual`.
+    pub trigger: String,
+    pub started_at: OffsetDateTime,
+    pub finished_at: OffsetDateTime,
+    pub ok: bool,
+    pub message: Option<String>,
+}
+
+/// One event of a request captured for debugging, stored with its bodies whatever the
+/// redaction settings say.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DebugCaptureRecord {
+    pub id: i64,
+    pub trace_id: String,
+    pub user_key_id: i64,
+    /// `downstream` or `upstream`.
+    pub kind: String,
+    /// The event as written to the terminal log (bodies as text).
+    pub event_json: serde_json::Value,
+    pub at: OffsetDateTime,
+    pub expires_at: OffsetDateTime,
+}
+
+/// A tool of a remote MCP server that upstream called for a Responses request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct McpToolCallRecord {
+    pub id: i64,
+    pub trace_id: Option<String>,
+    pub user_key_id: i64,
+    pub provider: String,
+    pub server_label: String,
+    pub tool_name: String,
+    /// Arguments as sent to the tool (JSON text).
+    pub arguments: Option<String>,
+    pub output: Option<String>,
+    pub error: Option<String>,
+    pub at: OffsetDateTime,
+}
+
+/// A generate stream that ended early, with what it produced until then.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AbortedStreamRecord {
+    pub id: i64,
+    pub trace_id: Option<String>,
+    pub user_key_id: i64,
+    pub provider: String,
+    pub credential_id: Option<i64>,
+    pub model: Option<String>,
+    /// `error_kind` of the stream's upstream event.
+    pub reason: String,
+    pub output_chars: i64,
+    pub input_tokens: Option<i64>,
+    /// Reported by upstream, else estimated from `output_chars`.
+    pub output_tokens: i64,
+    pub duration_ms: i64,
+    pub at: OffsetDateTime,
+}
+
+/// A stretch of time during which every credential of a provider was unavailable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IncidentRecord {
+    pub id: i64,
+    pub provider: String,
+    pub started_at: OffsetDateTime,
+    /// Set once a credential is available again.
+    pub ended_at: Option<OffsetDateTime>,
+    /// Models requested from the provider while it was down.
+    pub models: Vec<String>,
+    /// The first upstream failures that cooled its credentials down.
+    pub error_samples: Vec<String>,
+    /// Requests refused with `no_active_credentials` meanwhile.
+    pub refused_requests: i64,
+}
+
+/// A client-side tool call found in a generate response.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolCallRecord {
+    pub id: i64,
+    pub trace_id: Option<String>,
+    pub user_key_id: i64,
+    pub provider: String,
+    pub model: Option<String>,
+    /// Client protocol, e.g. `openai_chat`.
+    pub proto: String,
+    pub tool_name: String,
+    pub call_id: Option<String>,
+    pub arguments_bytes: i64,
+    pub at: OffsetDateTime,
+    /// Set once the client sends the call's result back through the proxy.
+    pub result_at: Option<OffsetDateTime>,
+    pub latency_ms: Option<i64>,
+}
+
 #[derive(Debug, Clone)]
 pub struct LogRecord {
     pub id: i64,
@@ -80,12 +268,34 @@ pub struct LogRecord {
     pub attempt_no: Option<i32>,
     pub operation: Option<String>,
     pub request_method: String,
+    pub request_host: Option<String>,
     pub request_path: String,
     pub request_body: Option<Vec<u8>>,
     pub response_status: Option<i32>,
     pub response_body: Option<Vec<u8>>,
     pub error_kind: Option<String>,
     pub error_message: Option<String>,
+    pub tags: Vec<String>,
+    pub anthropic_betas: Vec<String>,
+    pub client_ip: Option<String>,
+    pub country: Option<String>,
+    pub asn: Option<i64>,
+}
+
+/// A stored downstream request with everything needed to send it again.
+#[derive(Debug, Clone)]
+pub struct DownstreamRequestRecord {
+    pub id: i64,
+    pub at: OffsetDateTime,
+    pub trace_id: Option<String>,
+    pub user_id: Option<i64>,
+    pub user_key_id: Option<i64>,
+    pub request_method: String,
+    pub request_path: String,
+    pub request_query: Option<String>,
+    pub request_headers: Vec<(String, String)>,
+    pub request_body: Option<Vec<u8>>,
+    pub tags: Vec<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -95,15 +305,40 @@ pub struct LogQueryResult {
     pub next_cursor: Option<LogCursor>,
 }
 
-/// Storage is used for:
+/// Full-text search over logged request/response bodies and error messages.
+#[derive(Debug, Clone)]
+pub struct LogSearchQuery {
+    /// Words matched as a phrase, ignoring case.
+    pub text: String,
+    pub from: OffsetDateTime,
+    pub to: OffsetDateTime,
+    pub kind: Option<LogRecordKind>,
+    /// Restrict results to this set of users (used for organization scoping).
+    pub user_ids: Option<Vec<i64>>,
+    pub limit: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct LogSearchHit {
+    pub kind: LogRecordKind,
+    /// Id of the matching upstream or downstream log row.
+    pub id: i64,
+    pub at: OffsetDateTime,
+    pub trace_id: Option<String>,
+    pub user_id: Option<i64>,
+    /// Text around the match.
+    pub snippet: String,
+}
+
+/// Configuration half of the storage: providers, credentials, organizations, users
+/// and keys. Used for:
 /// - bootstrap (load_snapshot)
 /// - admin mutations (writes only)
-/// - event persistence (append_event)
 ///
 /// Runtime reads must NOT hit DB; they read from in-memory snapshots.
 #[async_trait]
-pub trait Storage: Send + Sync {
-    /// Entity-first schema sync (SeaORM 2.0). Enabled by default at bootstrap.
+pub trait ConfigStorage: Send + Sync {
+    /// Entity-first schema sync (SeaORM 2.0) of the config tables, run at bootstrap.
     async fn sync(&self) -> StorageResult<()>;
 
     async fn load_global_config(&self) -> StorageResult<Option<GlobalConfigRow>>;
@@ -112,6 +347,7 @@ pub trait Storage: Send + Sync {
     async fn load_snapshot(&self) -> StorageResult<StorageSnapshot>;
 
     // Providers
+    async fn provider_names(&self) -> StorageResult<Vec<String>>;
     async fn upsert_provider(
         &self,
         name: &str,
@@ -139,10 +375,46 @@ pub trait Storage: Send + Sync {
     async fn set_credential_enabled(&self, credential_id: i64, enabled: bool) -> StorageResult<()>;
     async fn delete_credential(&self, credential_id: i64) -> StorageResult<()>;
 
+    // Organizations
+    async fn upsert_org_by_id(
+        &self,
+        org_id: i64,
+        name: &str,
+        enabled: bool,
+        admin_key: Option<&str>,
+    ) -> StorageResult<()>;
+    async fn set_org_enabled(&self, org_id: i64, enabled: bool) -> StorageResult<()>;
+    /// Keep request/response bodies of the organization's traffic out of logs.
+    async fn set_org_omit_bodies(&self, org_id: i64, omit_bodies: bool) -> StorageResult<()>;
+    /// Per-minute limits and monthly token budget shared by the organization's keys.
+    async fn update_org_limits(
+        &self,
+        org_id: i64,
+        rpm_limit: Option<i64>,
+        tpm_limit: Option<i64>,
+        monthly_token_budget: Option<i64>,
+    ) -> StorageResult<()>;
+    /// Provider/model that aggregate routes fall back to for the organization's keys.
+    async fn update_org_defaults(
+        &self,
+        org_id: i64,
+        default_provider: Option<&str>,
+        default_model: Option<&str>,
+    ) -> StorageResult<()>;
+    async fn delete_org(&self, org_id: i64) -> StorageResult<()>;
+    async fn insert_org_grant(
+        &self,
+        org_id: i64,
+        provider_name: &str,
+        credential_id: Option<i64>,
+    ) -> StorageResult<i64>;
+    async fn delete_org_grant(&self, grant_id: i64) -> StorageResult<()>;
+
     // Users / keys (auth)
     async fn upsert_user_by_id(&self, user_id: i64, name: &str, enabled: bool)
     -> StorageResult<()>;
     async fn set_user_enabled(&self, user_id: i64, enabled: bool) -> StorageResult<()>;
+    async fn set_user_org(&self, user_id: i64, org_id: Option<i64>) -> StorageResult<()>;
     async fn delete_user(&self, user_id: i64) -> StorageResult<()>;
     async fn insert_user_key(
         &self,
@@ -152,13 +424,115 @@ pub trait Storage: Send + Sync {
         enabled: bool,
     ) -> StorageResult<i64>;
     async fn set_user_key_enabled(&self, user_key_id: i64, enabled: bool) -> StorageResult<()>;
+    /// Keep request/response bodies of this key's traffic out of logs.
+    async fn set_user_key_omit_bodies(
+        &self,
+        user_key_id: i64,
+        omit_bodies: bool,
+    ) -> StorageResult<()>;
+    /// Percent of the key's successful requests whose bodies are logged; `None` follows
+    /// the provider.
+    async fn set_user_key_body_sample_percent(
+        &self,
+        user_key_id: i64,
+        body_sample_percent: Option<i64>,
+    ) -> StorageResult<()>;
     async fn update_user_key_label(
         &self,
         user_key_id: i64,
         label: Option<&str>,
     ) -> StorageResult<()>;
+    async fn update_user_key_limits(
+        &self,
+        user_key_id: i64,
+        rpm_limit: Option<i64>,
+        tpm_limit: Option<i64>,
+        stream_tps_limit: Option<i64>,
+        max_output_tokens: Option<i64>,
+    ) -> StorageResult<()>;
+    /// Provider/model that aggregate routes fall back to for this key.
+    async fn update_user_key_defaults(
+        &self,
+        user_key_id: i64,
+        default_provider: Option<&str>,
+        default_model: Option<&str>,
+    ) -> StorageResult<()>;
+    /// MCP servers the key may declare; `None` lifts the restriction.
+    async fn update_user_key_mcp_policy(
+        &self,
+        user_key_id: i64,
+        mcp_policy: Option<&serde_json::Value>,
+    ) -> StorageResult<()>;
+    /// Moderation applied to the key's generate requests; `None` turns it off.
+    async fn update_user_key_moderation_policy(
+        &self,
+        user_key_id: i64,
+        moderation_policy: Option<&serde_json::Value>,
+    ) -> StorageResult<()>;
+    /// Claude Code prelude template for the key's requests; `None` keeps the provider's.
+    async fn update_user_key_prelude_template(
+        &self,
+        user_key_id: i64,
+        prelude_template: Option<&str>,
+    ) -> StorageResult<()>;
+    /// Providers/models the key may call and when it expires; `None` lifts either.
+    async fn update_user_key_scope(
+        &self,
+        user_key_id: i64,
+        key_scope: Option<&serde_json::Value>,
+        expires_at: Option<OffsetDateTime>,
+    ) -> StorageResult<()>;
+    /// Origins the key may be used from; `None` allows any.
+    async fn update_user_key_allowed_origins(
+        &self,
+        user_key_id: i64,
+        allowed_origins: Option<&serde_json::Value>,
+    ) -> StorageResult<()>;
     async fn delete_user_key(&self, user_key_id: i64) -> StorageResult<()>;
 
+    // Model profiles (virtual models)
+    async fn upsert_model_profile(
+        &self,
+        name: &str,
+        provider: &str,
+        model: &str,
+        settings_json: &serde_json::Value,
+        enabled: bool,
+    ) -> StorageResult<i64>;
+    async fn delete_model_profile(&self, name: &str) -> StorageResult<()>;
+
+    // A/B experiments (virtual models split across arms)
+    async fn upsert_experiment(
+        &self,
+        name: &str,
+        arms_json: &serde_json::Value,
+        split: &str,
+        enabled: bool,
+    ) -> StorageResult<i64>;
+    async fn delete_experiment(&self, name: &str) -> StorageResult<()>;
+
+    // Secrets (referenced by name from other config)
+    async fn upsert_secret(&self, name: &str, value: &str) -> StorageResult<i64>;
+    async fn delete_secret(&self, name: &str) -> StorageResult<()>;
+
+    // Saved log views
+    async fn upsert_log_view(
+        &self,
+        name: &str,
+        filter_json: &serde_json::Value,
+        window_secs: i64,
+        alert_threshold: Option<i64>,
+    ) -> StorageResult<i64>;
+    async fn delete_log_view(&self, name: &str) -> StorageResult<()>;
+}
+
+/// Telemetry half of the storage: request logs, usage, operational events and hourly
+/// stats. Written by the event bus, read by the admin endpoints.
+#[async_trait]
+pub trait TelemetryStorage: Send + Sync {
+    /// Schema sync for the telemetry tables; runs at bootstrap after [`ConfigStorage::sync`].
+    async fn sync_telemetry(&self) -> StorageResult<()>;
+
     async fn append_event(&self, event: &Event) -> StorageResult<()>;
 
     async fn aggregate_usage_tokens(
@@ -167,4 +541,124 @@ pub trait Storage: Send + Sync {
     ) -> StorageResult<UsageAggregate>;
 
     async fn query_logs(&self, filter: LogQueryFilter) -> StorageResult<LogQueryResult>;
+
+    /// Turns indexing of logged bodies and error messages for [`Self::search_logs`] on or
+    /// off; rows written while it is off cannot be found.
+    fn set_log_search(&self, enabled: bool);
+    /// Log rows whose bodies or error message contain `query.text`, newest first.
+    async fn search_logs(&self, query: LogSearchQuery) -> StorageResult<Vec<LogSearchHit>>;
+
+    /// One downstream request row including its headers and query, for replay.
+    async fn get_downstream_request(
+        &self,
+        id: i64,
+    ) -> StorageResult<Option<DownstreamRequestRecord>>;
+
+    /// Usage rows recorded for a single trace, ordered by time.
+    async fn list_trace_usages(&self, trace_id: &str) -> StorageResult<Vec<UsageRecord>>;
+
+    /// Usage rows, upstream attempt outcomes and cooldown transitions of one credential
+    /// in `[from, to]`, ordered by time.
+    async fn list_credential_usages(
+        &self,
+        credential_id: i64,
+        from: OffsetDateTime,
+        to: OffsetDateTime,
+    ) -> StorageResult<Vec<UsageRecord>>;
+    async fn list_credential_outcomes(
+        &self,
+        credential_id: i64,
+        from: OffsetDateTime,
+        to: OffsetDateTime,
+    ) -> StorageResult<Vec<UpstreamOutcome>>;
+    async fn list_credential_cooldowns(
+        &self,
+        credential_id: i64,
+        from: OffsetDateTime,
+        to: OffsetDateTime,
+    ) -> StorageResult<Vec<OperationalEventRecord>>;
+
+    /// Operational events newest first, with the same cursor scheme as `query_logs`.
+    async fn query_operational_events(
+        &self,
+        filter: OperationalEventFilter,
+    ) -> StorageResult<OperationalEventQueryResult>;
+
+    /// Replace the stored aggregates for each row's (hour, dimension, key).
+    async fn upsert_stats_hourly(&self, rows: &[StatsHourlyRow]) -> StorageResult<()>;
+    async fn load_stats_hourly(&self, since: OffsetDateTime) -> StorageResult<Vec<StatsHourlyRow>>;
+
+    /// Records a finished job run; `record.id` is ignored and the stored id returned.
+    async fn append_job_run(&self, record: &JobRunRecord) -> StorageResult<i64>;
+    /// Most recent runs first, optionally for a single job.
+    async fn list_job_runs(
+        &self,
+        job: Option<&str>,
+        limit: usize,
+    ) -> StorageResult<Vec<JobRunRecord>>;
+
+    /// Stores a captured event; `record.id` is ignored and the stored id returned.
+    async fn append_debug_capture(&self, record: &DebugCaptureRecord) -> StorageResult<i64>;
+    /// Most recent captures first, optionally for one key or one trace.
+    async fn list_debug_captures(
+        &self,
+        user_key_id: Option<i64>,
+        trace_id: Option<&str>,
+        limit: usize,
+    ) -> StorageResult<Vec<DebugCaptureRecord>>;
+    /// Deletes captures whose `expires_at` is not after `now`; returns how many went.
+    async fn purge_debug_captures(&self, now: OffsetDateTime) -> StorageResult<u64>;
+
+    /// Records an MCP tool call; `record.id` is ignored and the stored id returned.
+    async fn append_mcp_tool_call(&self, record: &McpToolCallRecord) -> StorageResult<i64>;
+    /// Most recent calls first, optionally for one key.
+    async fn list_mcp_tool_calls(
+        &self,
+        user_key_id: Option<i64>,
+        limit: usize,
+    ) -> StorageResult<Vec<McpToolCallRecord>>;
+
+    /// Records an aborted stream; `record.id` is ignored and the stored id returned.
+    async fn append_aborted_stream(&self, record: &AbortedStreamRecord) -> StorageResult<i64>;
+    /// Most recent aborts first, optionally for one key.
+    async fn list_aborted_streams(
+        &self,
+        user_key_id: Option<i64>,
+        limit: usize,
+    ) -> StorageResult<Vec<AbortedStreamRecord>>;
+
+    /// Records a tool call; `record.id` is ignored and the stored id returned.
+    async fn append_tool_call(&self, record: &ToolCallRecord) -> StorageResult<i64>;
+    /// Stamps when the call's result came back and how long after the call that was.
+    async fn complete_tool_call(
+        &self,
+        id: i64,
+        result_at: OffsetDateTime,
+        latency_ms: i64,
+    ) -> StorageResult<()>;
+    /// Most recent calls first, optionally for one key and/or one tool name.
+    async fn list_tool_calls(
+        &self,
+        user_key_id: Option<i64>,
+        tool_name: Option<&str>,
+        limit: usize,
+    ) -> StorageResult<Vec<ToolCallRecord>>;
+
+    /// Records an incident; `record.id` is ignored and the stored id returned.
+    async fn append_incident(&self, record: &IncidentRecord) -> StorageResult<i64>;
+    /// Replaces the stored incident `record.id` with `record`.
+    async fn update_incident(&self, record: &IncidentRecord) -> StorageResult<()>;
+    /// Most recent incidents first, optionally for one provider and/or only open ones.
+    async fn list_incidents(
+        &self,
+        provider: Option<&str>,
+        open_only: bool,
+        limit: usize,
+    ) -> StorageResult<Vec<IncidentRecord>>;
 }
+
+/// Both halves on one backend. Implemented for anything that implements both traits, so
+/// a single backend and a [`crate::SplitStorage`] are interchangeable.
+pub trait Storage: ConfigStorage + TelemetryStorage {}
+
+impl<T: ConfigStorage + TelemetryStorage + ?Sized> Storage for T {}
@@ -0,0 +1,597 @@
+//! Golden-fixture harness for the generate-content transforms. Recorded vendor payloads
+//! are replayed through every proto pair and checked for what must hold whatever the
+//! pair: the transform succeeds, its output serializes, and a round trip back to the
+//! source proto keeps the model and every piece of text.
+//!
+//! Corpus layout, relative to the root given to [`load_corpus`]:
+//!
+//! ```text
+//! <proto>/request/<name>.json   non-stream request body
+//! <proto>/response/<name>.json  non-stream response body
+//! <proto>/stream/<name>.*       stream transcript: SSE `data:` lines, NDJSON or a JSON array
+//! ```
+//!
+//! `<proto>` is `claude`, `openai_chat`, `openai_response` or `gemini`. Gemini requests
+//! take their model from the body's `model` field, since the path is not recorded.
+//! Streams are checked by collecting them into a response, directly and after
+//! converting them to the target's stream.
+//!
+//! The crate's own corpus lives in `fixtures/` and runs with `cargo test -p
+//! gproxy-transform`. To add a case, drop a recorded payload (credentials and ids you
+//! care about scrubbed) into the matching directory; a fixture that exposes a lossy
+//! transform is fixed together with that transform.
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use gproxy_protocol::claude::create_message::request::CreateMessageRequest as ClaudeCreateMessageRequest;
+use gproxy_protocol::gemini::generate_content::request::{
+    GenerateContentPath, GenerateContentRequest as GeminiGenerateContentRequest,
+    GenerateContentRequestBody as GeminiGenerateContentRequestBody,
+};
+use gproxy_protocol::openai::create_chat_completions::request::CreateChatCompletionRequest as OpenAIChatCompletionRequest;
+use gproxy_protocol::openai::create_response::request::CreateResponseRequest as OpenAIResponseRequest;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::middleware::{
+    GenerateContentRequest, GenerateContentResponse, Op, Proto, Request, Response, StreamEvent,
+    StreamToNostream, StreamTransformer, TransformContext, transform_request, transform_response,
+};
+
+/// Protos with generate-content transforms, in corpus directory order.
+pub const GENERATE_PROTOS: [Proto; 4] = [
+    Proto::Claude,
+    Proto::OpenAIChat,
+    Proto::OpenAIResponse,
+    Proto::Gemini,
+];
+
+/// Keys whose string values are compared across a round trip.
+const TEXT_KEYS: [&str; 6] = [
+    "text",
+    "content",
+    "system",
+    "instructions",
+    "input",
+    "refusal",
+];
+
+const DEFAULT_GEMINI_MODEL: &str = "models/gemini-fixture";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixtureKind {
+    Request,
+    Response,
+    Stream,
+}
+
+impl FixtureKind {
+    pub const ALL: [FixtureKind; 3] = [
+        FixtureKind::Request,
+        FixtureKind::Response,
+        FixtureKind::Stream,
+    ];
+
+    pub fn dir_name(self) -> &'static str {
+        match self {
+            FixtureKind::Request => "request",
+            FixtureKind::Response => "response",
+            FixtureKind::Stream => "stream",
+        }
+    }
+}
+
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone)]
+pub enum FixturePayload {
+    Request(GenerateContentRequest),
+    Response(GenerateContentResponse),
+    Stream(Vec<StreamEvent>),
+}
+
+#[derive(Debug, Clone)]
+pub struct Fixture {
+    /// `<proto>/<kind>/<file>`, for reports.
+    pub name: String,
+    pub proto: Proto,
+    pub payload: FixturePayload,
+}
+
+#[derive(Debug, Clone)]
+pub struct FixtureError {
+    pub path: PathBuf,
+    pub message: String,
+}
+
+impl fmt::Display for FixtureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.message)
+    }
+}
+
+impl std::error::Error for FixtureError {}
+
+/// One broken invariant of one fixture against one target proto.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub fixture: String,
+    pub target: Proto,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} -> {:?}: {}", self.fixture, self.target, self.message)
+    }
+}
+
+pub fn proto_dir_name(proto: Proto) -> &'static str {
+    match proto {
+        Proto::Claude => "claude",
+        Proto::OpenAI => "openai",
+        Proto::OpenAIChat => "openai_chat",
+        Proto::OpenAIResponse => "openai_response",
+        Proto::Gemini => "gemini",
+    }
+}
+
+/// Loads every fixture under `root`, sorted by name. Missing directories are skipped.
+pub fn load_corpus(root: &Path) -> Result<Vec<Fixture>, FixtureError> {
+    let mut fixtures = Vec::new();
+    for proto in GENERATE_PROTOS {
+        for kind in FixtureKind::ALL {
+            let dir = root.join(proto_dir_name(proto)).join(kind.dir_name());
+            if !dir.is_dir() {
+                continue;
+            }
+            let entries = fs::read_dir(&dir).map_err(|err| FixtureError {
+                path: dir.clone(),
+                message: err.to_string(),
+            })?;
+            let mut paths = Vec::new();
+            for entry in entries {
+                let entry = entry.map_err(|err| FixtureError {
+                    path: dir.clone(),
+                    message: err.to_string(),
+                })?;
+                if entry.path().is_file() {
+                    paths.push(entry.path());
+                }
+            }
+            paths.sort();
+            for path in paths {
+                fixtures.push(load_fixture(&path, proto, kind)?);
+            }
+        }
+    }
+    Ok(fixtures)
+}
+
+pub fn load_fixture(path: &Path, proto: Proto, kind: FixtureKind) -> Result<Fixture, FixtureError> {
+    let fail = |message: String| FixtureError {
+        path: path.to_path_buf(),
+        message,
+    };
+    let text = fs::read_to_string(path).map_err(|err| fail(err.to_string()))?;
+    let payload = parse_fixture(proto, kind, &text).map_err(fail)?;
+    let file = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    Ok(Fixture {
+        name: format!("{}/{}/{file}", proto_dir_name(proto), kind.dir_name()),
+        proto,
+        payload,
+    })
+}
+
+pub fn parse_fixture(
+    proto: Proto,
+    kind: FixtureKind,
+    text: &str,
+) -> Result<FixturePayload, String> {
+    match kind {
+        FixtureKind::Request => parse_request(proto, text).map(FixturePayload::Request),
+        FixtureKind::Response => parse_response(proto, text).map(FixturePayload::Response),
+        FixtureKind::Stream => parse_transcript(text)?
+            .into_iter()
+            .map(|value| parse_stream_event(proto, value))
+            .collect::<Result<Vec<_>, _>>()
+            .map(FixturePayload::Stream),
+    }
+}
+
+fn parse_request(proto: Proto, text: &str) -> Result<GenerateContentRequest, String> {
+    Ok(match proto {
+        Proto::Claude => GenerateContentRequest::Claude(ClaudeCreateMessageRequest {
+            headers: Default::default(),
+            body: from_str(text)?,
+        }),
+        Proto::OpenAIChat => GenerateContentRequest::OpenAIChat(OpenAIChatCompletionRequest {
+            body: from_str(text)?,
+        }),
+        Proto::OpenAIResponse => GenerateContentRequest::OpenAIResponse(OpenAIResponseRequest {
+            body: from_str(text)?,
+        }),
+        Proto::Gemini => {
+            let body: GeminiGenerateContentRequestBody = from_str(text)?;
+            let model = match body.model.as_deref() {
+                Some(model) if model.starts_with("models/") => model.to_string(),
+                Some(model) => format!("models/{model}"),
+                None => DEFAULT_GEMINI_MODEL.to_string(),
+            };
+            GenerateContentRequest::Gemini(GeminiGenerateContentRequest {
+                path: GenerateContentPath { model },
+                body,
+            })
+        }
+        Proto::OpenAI => return Err("openai has no generate-content fixtures".to_string()),
+    })
+}
+
+fn parse_response(proto: Proto, text: &str) -> Result<GenerateContentResponse, String> {
+    Ok(match proto {
+        Proto::Claude => GenerateContentResponse::Claude(from_str(text)?),
+        Proto::OpenAIChat => GenerateContentResponse::OpenAIChat(from_str(text)?),
+        Proto::OpenAIResponse => GenerateContentResponse::OpenAIResponse(from_str(text)?),
+        Proto::Gemini => GenerateContentResponse::Gemini(from_str(text)?),
+        Proto::OpenAI => return Err("openai has no generate-content fixtures".to_string()),
+    })
+}
+
+fn parse_stream_event(proto: Proto, value: Value) -> Result<StreamEvent, String> {
+    let parsed = match proto {
+        Proto::Claude => serde_json::from_value(value).map(StreamEvent::Claude),
+        Proto::OpenAIChat => serde_json::from_value(value).map(StreamEvent::OpenAIChat),
+        Proto::OpenAIResponse => serde_json::from_value(value).map(StreamEvent::OpenAIResponse),
+        Proto::Gemini => serde_json::from_value(value).map(StreamEvent::Gemini),
+        Proto::OpenAI => return Err("openai has no generate-content fixtures".to_string()),
+    };
+    parsed.map_err(|err| err.to_string())
+}
+
+/// Splits a stream transcript into event payloads. Accepts a JSON array, SSE (only
+/// `data:` lines are read, `[DONE]` is dropped) or one JSON document per line.
+pub fn parse_transcript(text: &str) -> Result<Vec<Value>, String> {
+    if text.trim_start().starts_with('[') {
+        return from_str(text);
+    }
+    let mut events = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        let payload = match line.strip_prefix("data:") {
+            Some(data) => data.trim(),
+            None if line.is_empty()
+                || line.starts_with(':')
+                || line.starts_with("event:")
+                || line.starts_with("id:")
+                || line.starts_with("retry:") =>
+            {
+                continue;
+            }
+            None => line,
+        };
+        if payload.is_empty() || payload == "[DONE]" {
+            continue;
+        }
+        events.push(from_str(payload)?);
+    }
+    Ok(events)
+}
+
+fn from_str<T: DeserializeOwned>(text: &str) -> Result<T, String> {
+    serde_json::from_str(text).map_err(|err| err.to_string())
+}
+
+/// Runs `fixture` against every generate-content proto; empty when all invariants hold.
+pub fn check_fixture(fixture: &Fixture) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    for target in GENERATE_PROTOS {
+        let result = match &fixture.payload {
+            FixturePayload::Request(req) => check_request(fixture.proto, target, req),
+            FixturePayload::Response(resp) => check_response(fixture.proto, target, resp),
+            FixturePayload::Stream(events) => check_stream(fixture.proto, target, events),
+        };
+        if let Err(messages) = result {
+            violations.extend(messages.into_iter().map(|message| Violation {
+                fixture: fixture.name.clone(),
+                target,
+                message,
+            }));
+        }
+    }
+    violations
+}
+
+/// Loads and checks the whole corpus under `root`.
+pub fn check_corpus(root: &Path) -> Result<Vec<Violation>, FixtureError> {
+    Ok(load_corpus(root)?.iter().flat_map(check_fixture).collect())
+}
+
+fn generate_ctx(src: Proto, dst: Proto, src_op: Op, dst_op: Op) -> TransformContext {
+    TransformContext {
+        src,
+        dst,
+        src_op,
+        dst_op,
+    }
+}
+
+fn check_request(src: Proto, dst: Proto, req: &GenerateContentRequest) -> Result<(), Vec<String>> {
+    let original = request_json(req).map_err(|err| vec![format!("fixture: {err}")])?;
+    let forward = convert_request(src, dst, req.clone())
+        .map_err(|err| vec![format!("request transform: {err}")])?;
+    let forward_json =
+        request_json(&forward).map_err(|err| vec![format!("request output: {err}")])?;
+    let back = convert_request(dst, src, forward)
+        .map_err(|err| vec![format!("request round trip: {err}")])?;
+    let back_json = request_json(&back).map_err(|err| vec![format!("round trip output: {err}")])?;
+
+    let mut problems = Vec::new();
+    let (model, back_model) = (request_model(req), request_model(&back));
+    if model != back_model {
+        problems.push(format!("model {model:?} came back as {back_model:?}"));
+    }
+    problems.extend(lost_texts(&original, &forward_json, "request transform"));
+    problems.extend(lost_texts(&original, &back_json, "request round trip"));
+    problems_result(problems)
+}
+
+fn check_response(
+    src: Proto,
+    dst: Proto,
+    resp: &GenerateContentResponse,
+) -> Result<(), Vec<String>> {
+    let original = response_json(resp).map_err(|err| vec![format!("fixture: {err}")])?;
+    let forward = convert_response(src, dst, resp.clone())
+        .map_err(|err| vec![format!("response transform: {err}")])?;
+    let forward_json =
+        response_json(&forward).map_err(|err| vec![format!("response output: {err}")])?;
+    let back = convert_response(dst, src, forward)
+        .map_err(|err| vec![format!("response round trip: {err}")])?;
+    let back_json =
+        response_json(&back).map_err(|err| vec![format!("round trip output: {err}")])?;
+
+    let mut problems = Vec::new();
+    problems.extend(lost_texts(&original, &forward_json, "response transform"));
+    problems.extend(lost_texts(&original, &back_json, "response round trip"));
+    problems_result(problems)
+}
+
+/// The stream is collected into a `src` response as the reference. Converting it to a
+/// `dst` stream, and collecting it into a `dst` response, must both keep its text.
+fn check_stream(src: Proto, dst: Proto, events: &[StreamEvent]) -> Result<(), Vec<String>> {
+    let reference = collect_stream(src, src, events)
+        .map_err(|err| vec![format!("fixture: {err}")])
+        .and_then(|resp| response_json(&resp).map_err(|err| vec![format!("fixture: {err}")]))?;
+
+    let ctx = generate_ctx(
+        src,
+        dst,
+        Op::StreamGenerateContent,
+        Op::StreamGenerateContent,
+    );
+    let mut transformer =
+        StreamTransformer::new(&ctx).map_err(|err| vec![format!("stream transformer: {err:?}")])?;
+    let mut converted = Vec::new();
+    for event in events {
+        let out = transformer
+            .push(event.clone())
+            .map_err(|err| vec![format!("stream transform: {err:?}")])?;
+        for event in &out {
+            stream_event_json(event).map_err(|err| vec![format!("stream output: {err}")])?;
+        }
+        converted.extend(out);
+    }
+    let streamed = collect_stream(dst, dst, &converted)
+        .and_then(|resp| response_json(&resp))
+        .map_err(|err| vec![format!("converted stream: {err}")])?;
+    let collected = collect_stream(src, dst, events)
+        .and_then(|resp| response_json(&resp))
+        .map_err(|err| vec![format!("stream collect: {err}")])?;
+
+    let mut problems = lost_texts(&reference, &streamed, "stream transform");
+    problems.extend(lost_texts(&reference, &collected, "stream collect"));
+    problems_result(problems)
+}
+
+/// Collects a `src` stream into a `dst` response, as a non-stream client would get it.
+fn collect_stream(
+    src: Proto,
+    dst: Proto,
+    events: &[StreamEvent],
+) -> Result<GenerateContentResponse, String> {
+    let ctx = generate_ctx(src, dst, Op::StreamGenerateContent, Op::GenerateContent);
+    let mut collector = StreamToNostream::new(&ctx).map_err(|err| format!("{err:?}"))?;
+    let mut collected = None;
+    for event in events {
+        if let Some(resp) = collector
+            .push(event.clone())
+            .map_err(|err| format!("{err:?}"))?
+        {
+            collected = Some(resp);
+        }
+    }
+    if collected.is_none() {
+        collected = collector
+            .finalize_on_eof()
+            .map_err(|err| format!("{err:?}"))?;
+    }
+    match collected {
+        Some(Response::GenerateContent(resp)) => Ok(resp),
+        Some(_) => Err("not a generate-content response".to_string()),
+        None => Err("collected no response".to_string()),
+    }
+}
+
+fn problems_result(problems: Vec<String>) -> Result<(), Vec<String>> {
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(problems)
+    }
+}
+
+fn convert_request(
+    src: Proto,
+    dst: Proto,
+    req: GenerateContentRequest,
+) -> Result<GenerateContentRequest, String> {
+    let ctx = generate_ctx(src, dst, Op::GenerateContent, Op::GenerateContent);
+    match transform_request(&ctx, Request::GenerateContent(req)) {
+        Ok(Request::GenerateContent(req)) => Ok(req),
+        Ok(_) => Err("not a generate-content request".to_string()),
+        Err(err) => Err(format!("{err:?}")),
+    }
+}
+
+fn convert_response(
+    src: Proto,
+    dst: Proto,
+    resp: GenerateContentResponse,
+) -> Result<GenerateContentResponse, String> {
+    let ctx = generate_ctx(src, dst, Op::GenerateContent, Op::GenerateContent);
+    match transform_response(&ctx, Response::GenerateContent(resp)) {
+        Ok(Response::GenerateContent(resp)) => Ok(resp),
+        Ok(_) => Err("not a generate-content response".to_string()),
+        Err(err) => Err(format!("{err:?}")),
+    }
+}
+
+fn to_json(value: &impl Serialize) -> Result<Value, String> {
+    serde_json::to_value(value).map_err(|err| err.to_string())
+}
+
+fn request_json(req: &GenerateContentRequest) -> Result<Value, String> {
+    match req {
+        GenerateContentRequest::Claude(req) => to_json(&req.body),
+        GenerateContentRequest::OpenAIChat(req) => to_json(&req.body),
+        GenerateContentRequest::OpenAIResponse(req) => to_json(&req.body),
+        GenerateContentRequest::Gemini(req) => to_json(&req.body),
+        GenerateContentRequest::GeminiStream(req) => to_json(&req.body),
+    }
+}
+
+fn response_json(resp: &GenerateContentResponse) -> Result<Value, String> {
+    match resp {
+        GenerateContentResponse::Claude(resp) => to_json(resp),
+        GenerateContentResponse::OpenAIChat(resp) => to_json(resp),
+        GenerateContentResponse::OpenAIResponse(resp) => to_json(resp),
+        GenerateContentResponse::Gemini(resp) => to_json(resp),
+    }
+}
+
+fn stream_event_json(event: &StreamEvent) -> Result<Value, String> {
+    match event {
+        StreamEvent::Claude(event) => to_json(event),
+        StreamEvent::OpenAIChat(event) => to_json(event),
+        StreamEvent::OpenAIResponse(event) => to_json(event),
+        StreamEvent::Gemini(event) => to_json(event),
+    }
+}
+
+/// The requested model without Gemini's `models/` prefix.
+fn request_model(req: &GenerateContentRequest) -> Option<String> {
+    let model = match req {
+        GenerateContentRequest::Claude(req) => to_json(&req.body.model)
+            .ok()?
+            .as_str()
+            .map(str::to_string)?,
+        GenerateContentRequest::OpenAIChat(req) => req.body.model.clone(),
+        GenerateContentRequest::OpenAIResponse(req) => req.body.model.clone(),
+        GenerateContentRequest::Gemini(req) => req.path.model.clone(),
+        GenerateContentRequest::GeminiStream(req) => req.path.model.clone(),
+    };
+    Some(model.strip_prefix("models/").unwrap_or(&model).to_string())
+}
+
+/// Texts of `original` that appear in no string of `output`.
+fn lost_texts(original: &Value, output: &Value, stage: &str) -> Vec<String> {
+    let mut texts = Vec::new();
+    collect_texts(original, &mut texts);
+    let mut strings = Vec::new();
+    collect_strings(output, &mut strings);
+    texts
+        .into_iter()
+        .filter(|text| !strings.iter().any(|s| s.contains(text.as_str())))
+        .map(|text| format!("{stage} lost text {text:?}"))
+        .collect()
+}
+
+fn collect_texts(value: &Value, out: &mut Vec<String>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                match value {
+                    Value::String(text) if TEXT_KEYS.contains(&key.as_str()) => {
+                        if !text.trim().is_empty() {
+                            out.push(text.clone());
+                        }
+                    }
+                    _ => collect_texts(value, out),
+                }
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|item| collect_texts(item, out)),
+        _ => {}
+    }
+}
+
+fn collect_strings(value: &Value, out: &mut Vec<String>) {
+    match value {
+        Value::String(text) => out.push(text.clone()),
+        Value::Object(map) => map.values().for_each(|value| collect_strings(value, out)),
+        Value::Array(items) => items.iter().for_each(|item| collect_strings(item, out)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn corpus_root() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures")
+    }
+
+    #[test]
+    fn corpus_holds_its_invariants() {
+        let fixtures = load_corpus(&corpus_root()).unwrap();
+        for proto in GENERATE_PROTOS {
+            for kind in FixtureKind::ALL {
+                let prefix = format!("{}/{}/", proto_dir_name(proto), kind.dir_name());
+                assert!(
+                    fixtures.iter().any(|f| f.name.starts_with(&prefix)),
+                    "no fixture under {prefix}"
+                );
+            }
+        }
+        let violations: Vec<String> = fixtures
+            .iter()
+            .flat_map(check_fixture)
+            .map(|v| v.to_string())
+            .collect();
+        assert!(violations.is_empty(), "{}", violations.join("\n"));
+    }
+
+    #[test]
+    fn transcripts_accept_sse_ndjson_and_arrays() {
+        let sse = "event: message_start\ndata: {\"a\":1}\n\n: ping\ndata: [DONE]\n";
+        assert_eq!(parse_transcript(sse).unwrap().len(), 1);
+        assert_eq!(parse_transcript("{\"a\":1}\n{\"a\":2}\n").unwrap().len(), 2);
+        assert_eq!(parse_transcript("[{\"a\":1},{\"a\":2}]").unwrap().len(), 2);
+        assert!(parse_transcript("data: {oops").is_err());
+    }
+
+    #[test]
+    fn lost_text_is_reported() {
+        let original = serde_json::json!({ "messages": [{ "content": "hello there" }] });
+        let kept = serde_json::json!({ "contents": [{ "parts": [{ "text": "hello there" }] }] });
+        let dropped = serde_json::json!({ "contents": [] });
+        assert!(lost_texts(&original, &kept, "t").is_empty());
+        assert_eq!(lost_texts(&original, &dropped, "t").len(), 1);
+    }
+}
@@ -63,6 +63,7 @@ pub fn transform_request(request: OpenAIInputTokenCountRequest) -> GeminiCountTo
         system_instruction,
         generation_config,
         cached_content: None,
+        extra: Default::default(),
     };
 
     let generate_content_request = serde_json::to_value(generate_content_request).ok();
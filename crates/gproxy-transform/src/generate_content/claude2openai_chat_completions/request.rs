@@ -100,6 +100,7 @@ pub fn transform_request(request: ClaudeCreateMessageRequest) -> OpenAIChatCompl
             prompt_cache_key: None,
             service_tier: None,
             prompt_cache_retention: None,
+            extra: Default::default(),
         },
     }
 }
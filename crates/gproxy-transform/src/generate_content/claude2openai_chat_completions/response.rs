@@ -5,7 +5,7 @@ use gproxy_protocol::claude::create_message::response::CreateMessageResponse as
 use gproxy_protocol::claude::create_message::types::{
     BetaCacheCreation, BetaContentBlock, BetaMessage, BetaMessageRole, BetaMessageType,
     BetaServerToolUsage, BetaServiceTierUsed, BetaStopReason, BetaTextBlock, BetaTextBlockType,
-    BetaToolUseBlock, BetaToolUseBlockType, BetaUsage,
+    BetaThinkingBlock, BetaThinkingBlockType, BetaToolUseBlock, BetaToolUseBlockType, BetaUsage,
 };
 use gproxy_protocol::openai::create_chat_completions::response::CreateChatCompletionResponse;
 use gproxy_protocol::openai::create_chat_completions::types::{
@@ -46,6 +46,16 @@ pub fn transform_response(response: CreateChatCompletionResponse) -> ClaudeCreat
 fn map_response_message(message: &ChatCompletionResponseMessage) -> Vec<BetaContentBlock> {
     let mut blocks = Vec::new();
 
+    if let Some(reasoning) = &message.reasoning_content
+        && !reasoning.is_empty()
+    {
+        blocks.push(BetaContentBlock::Thinking(BetaThinkingBlock {
+            signature: String::new(),
+            thinking: reasoning.clone(),
+            r#type: BetaThinkingBlockType::Thinking,
+        }));
+    }
+
     if let Some(content) = &message.content
         && !content.is_empty()
     {
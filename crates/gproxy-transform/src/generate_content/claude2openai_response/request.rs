@@ -84,6 +84,7 @@ pub fn transform_request(request: ClaudeCreateMessageRequest) -> OpenAIResponseR
             prompt_cache_key: None,
             service_tier: None,
             prompt_cache_retention: None,
+            extra: Default::default(),
         },
     }
 }
@@ -89,6 +89,7 @@ pub fn transform_request(request: GeminiGenerateContentRequest) -> ClaudeCreateM
             tools,
             top_k,
             top_p,
+            extra: Default::default(),
         },
     }
 }
@@ -79,7 +79,10 @@ pub fn transform_request(request: GeminiGenerateContentRequest) -> CreateChatCom
             logit_bias: None,
             logprobs: None,
             max_tokens: None,
-            n: None,
+            n: generation_config
+                .and_then(|config| config.candidate_count)
+                .filter(|count| *count > 1)
+                .map(i64::from),
             prediction: None,
             seed: None,
             stream_options: None,
@@ -97,6 +100,7 @@ pub fn transform_request(request: GeminiGenerateContentRequest) -> CreateChatCom
             prompt_cache_key: None,
             service_tier: None,
             prompt_cache_retention: None,
+            extra: Default::default(),
         },
     }
 }
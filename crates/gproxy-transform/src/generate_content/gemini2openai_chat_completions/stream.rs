@@ -2,15 +2,17 @@ use std::collections::BTreeMap;
 
 use gproxy_protocol::gemini::count_tokens::types::Part as GeminiPart;
 use gproxy_protocol::gemini::generate_content::response::GenerateContentResponse;
-use gproxy_protocol::gemini::generate_content::types::{FinishReason, UsageMetadata};
+use gproxy_protocol::gemini::generate_content::types::{
+    FinishReason, LogprobsCandidate, LogprobsResult, UsageMetadata,
+};
 use gproxy_protocol::openai::create_chat_completions::stream::{
     ChatCompletionChunkObjectType, ChatCompletionStreamChoice, CreateChatCompletionStreamResponse,
 };
 use gproxy_protocol::openai::create_chat_completions::types::{
-    ChatCompletionFinishReason, ChatCompletionMessageToolCallChunk,
+    ChatCompletionChoiceLogprobs, ChatCompletionFinishReason, ChatCompletionMessageToolCallChunk,
     ChatCompletionMessageToolCallChunkFunction, ChatCompletionRole,
-    ChatCompletionStreamResponseDelta, ChatCompletionToolCallChunkType, CompletionTokensDetails,
-    CompletionUsage, PromptTokensDetails,
+    ChatCompletionStreamResponseDelta, ChatCompletionTokenLogprob, ChatCompletionToolCallChunkType,
+    ChatCompletionTopLogprob, CompletionTokensDetails, CompletionUsage, PromptTokensDetails,
 };
 
 #[derive(Debug, Clone)]
@@ -29,7 +31,6 @@ pub struct GeminiToOpenAIChatCompletionStreamState {
     model: String,
     created: i64,
     role_sent: BTreeMap<i64, bool>,
-    text_buffers: BTreeMap<i64, String>,
     tool_calls: BTreeMap<(i64, String), ToolCallState>,
     tool_counters: BTreeMap<i64, i64>,
     usage: Option<CompletionUsage>,
@@ -42,7 +43,6 @@ impl GeminiToOpenAIChatCompletionStreamState {
             model: "unknown".to_string(),
             created: 0,
             role_sent: BTreeMap::new(),
-            text_buffers: BTreeMap::new(),
             tool_calls: BTreeMap::new(),
             tool_counters: BTreeMap::new(),
             usage: None,
@@ -66,7 +66,25 @@ impl GeminiToOpenAIChatCompletionStreamState {
                 .index
                 .map(|value| value as i64)
                 .unwrap_or(idx as i64);
+            let start = events.len();
             events.extend(self.handle_parts(choice_index, &candidate.content.parts));
+            // A chunk's logprobs cover the tokens of that chunk; they ride on its first delta.
+            if let Some(result) = &candidate.logprobs_result {
+                let logprobs = map_logprobs(result);
+                match events
+                    .get_mut(start)
+                    .and_then(|chunk| chunk.choices.first_mut())
+                {
+                    Some(choice) => choice.logprobs = Some(logprobs),
+                    None => {
+                        let mut chunk = self.make_chunk(choice_index, empty_delta(), None);
+                        if let Some(choice) = chunk.choices.first_mut() {
+                            choice.logprobs = Some(logprobs);
+                        }
+                        events.push(chunk);
+                    }
+                }
+            }
             if let Some(reason) = candidate.finish_reason {
                 finish_reasons.push((choice_index, reason));
             }
@@ -160,23 +178,11 @@ impl GeminiToOpenAIChatCompletionStreamState {
         choice_index: i64,
         text: String,
     ) -> CreateChatCompletionStreamResponse {
-        let delta_text = {
-            let buffer = self.text_buffers.entry(choice_index).or_default();
-            if buffer.is_empty() {
-                buffer.push_str(&text);
-                text
-            } else {
-                buffer.push('\n');
-                buffer.push_str(&text);
-                format!("\n{}", text)
-            }
-        };
-
         let role = self.take_role(choice_index);
         self.make_chunk(
             choice_index,
             ChatCompletionStreamResponseDelta {
-                content: Some(delta_text),
+                content: Some(text),
                 reasoning_content: None,
                 function_call: None,
                 tool_calls: None,
@@ -352,6 +358,52 @@ impl GeminiToOpenAIChatCompletionStreamState {
     }
 }
 
+fn empty_delta() -> ChatCompletionStreamResponseDelta {
+    ChatCompletionStreamResponseDelta {
+        content: None,
+        reasoning_content: None,
+        function_call: None,
+        tool_calls: None,
+        role: None,
+        refusal: None,
+        obfuscation: None,
+    }
+}
+
+fn map_logprobs(result: &LogprobsResult) -> ChatCompletionChoiceLogprobs {
+    let content = result
+        .chosen_candidates
+        .iter()
+        .enumerate()
+        .map(|(step, chosen)| ChatCompletionTokenLogprob {
+            token: chosen.token.clone(),
+            logprob: chosen.log_probability,
+            bytes: Some(token_bytes(&chosen.token)),
+            top_logprobs: result
+                .top_candidates
+                .get(step)
+                .map(|top| top.candidates.iter().map(map_top_logprob).collect())
+                .unwrap_or_default(),
+        })
+        .collect();
+    ChatCompletionChoiceLogprobs {
+        content: Some(content),
+        refusal: None,
+    }
+}
+
+fn map_top_logprob(candidate: &LogprobsCandidate) -> ChatCompletionTopLogprob {
+    ChatCompletionTopLogprob {
+        token: candidate.token.clone(),
+        logprob: candidate.log_probability,
+        bytes: Some(token_bytes(&candidate.token)),
+    }
+}
+
+fn token_bytes(token: &str) -> Vec<i64> {
+    token.bytes().map(i64::from).collect()
+}
+
 impl Default for GeminiToOpenAIChatCompletionStreamState {
     fn default() -> Self {
         Self::new()
@@ -94,6 +94,7 @@ pub fn transform_request(request: GeminiGenerateContentRequest) -> OpenAIRespons
             prompt_cache_key: None,
             service_tier: None,
             prompt_cache_retention: None,
+            extra: Default::default(),
         },
     }
 }
@@ -7,14 +7,17 @@ use gproxy_protocol::openai::create_response::response::{Response, ResponseObjec
 use gproxy_protocol::openai::create_response::stream::{
     ResponseCompletedEvent, ResponseCreatedEvent, ResponseFunctionCallArgumentsDeltaEvent,
     ResponseFunctionCallArgumentsDoneEvent, ResponseOutputItemAddedEvent,
-    ResponseOutputItemDoneEvent, ResponseStreamEvent, ResponseTextDeltaEvent,
+    ResponseOutputItemDoneEvent, ResponseReasoningSummaryPartAddedEvent,
+    ResponseReasoningSummaryPartDoneEvent, ResponseReasoningSummaryTextDeltaEvent,
+    ResponseReasoningSummaryTextDoneEvent, ResponseStreamEvent, ResponseTextDeltaEvent,
     ResponseTextDoneEvent,
 };
 use gproxy_protocol::openai::create_response::types::{
     FunctionCallItemStatus, FunctionToolCall, FunctionToolCallType, MessageStatus, OutputItem,
     OutputMessage, OutputMessageContent, OutputMessageRole, OutputMessageType, OutputTextContent,
-    ResponseIncompleteDetails, ResponseIncompleteReason, ResponseStatus, ResponseUsage,
-    ResponseUsageInputTokensDetails, ResponseUsageOutputTokensDetails,
+    ReasoningItem, ReasoningItemStatus, ReasoningItemType, ResponseIncompleteDetails,
+    ResponseIncompleteReason, ResponseStatus, ResponseUsage, ResponseUsageInputTokensDetails,
+    ResponseUsageOutputTokensDetails, SummaryPart, SummaryTextContent,
 };
 
 #[derive(Debug, Clone)]
@@ -24,6 +27,14 @@ struct MessageState {
     text: String,
 }
 
+/// A candidate's thought parts, streamed as the summary of one reasoning item.
+#[derive(Debug, Clone)]
+struct ReasoningState {
+    output_index: i64,
+    item_id: String,
+    text: String,
+}
+
 #[derive(Debug, Clone)]
 struct ToolState {
     output_index: i64,
@@ -41,6 +52,7 @@ pub struct GeminiToOpenAIResponseStreamState {
     created_sent: bool,
     next_output_index: i64,
     message_states: BTreeMap<i64, MessageState>,
+    reasoning_states: BTreeMap<i64, ReasoningState>,
     tool_states: BTreeMap<String, ToolState>,
     output_items: BTreeMap<i64, OutputItem>,
     tool_counter: i64,
@@ -58,6 +70,7 @@ impl GeminiToOpenAIResponseStreamState {
             created_sent: false,
             next_output_index: 0,
             message_states: BTreeMap::new(),
+            reasoning_states: BTreeMap::new(),
             tool_states: BTreeMap::new(),
             output_items: BTreeMap::new(),
             tool_counter: 0,
@@ -123,6 +136,13 @@ impl GeminiToOpenAIResponseStreamState {
     fn handle_part(&mut self, candidate_index: i64, part: &GeminiPart) -> Vec<ResponseStreamEvent> {
         let mut events = Vec::new();
 
+        if part.thought == Some(true) {
+            if let Some(text) = part.text.clone() {
+                events.extend(self.emit_reasoning(candidate_index, text));
+            }
+            return events;
+        }
+
         if let Some(text) = part.text.clone()
             && !text.is_empty()
         {
@@ -224,6 +244,69 @@ impl GeminiToOpenAIResponseStreamState {
         events
     }
 
+    fn emit_reasoning(&mut self, candidate_index: i64, text: String) -> Vec<ResponseStreamEvent> {
+        if text.is_empty() {
+            return Vec::new();
+        }
+
+        let mut events = Vec::new();
+        if !self.reasoning_states.contains_key(&candidate_index) {
+            let output_index = self.next_output_index;
+            self.next_output_index += 1;
+            let item_id = format!("reasoning_{}", candidate_index);
+            let item = OutputItem::Reasoning(ReasoningItem {
+                r#type: ReasoningItemType::Reasoning,
+                id: item_id.clone(),
+                encrypted_content: None,
+                summary: Vec::new(),
+                content: Vec::new(),
+                status: Some(ReasoningItemStatus::InProgress),
+            });
+            events.push(ResponseStreamEvent::OutputItemAdded(
+                ResponseOutputItemAddedEvent {
+                    output_index,
+                    item: item.clone(),
+                    sequence_number: self.next_sequence(),
+                },
+            ));
+            events.push(ResponseStreamEvent::ReasoningSummaryPartAdded(
+                ResponseReasoningSummaryPartAddedEvent {
+                    item_id: item_id.clone(),
+                    output_index,
+                    summary_index: 0,
+                    part: SummaryPart::SummaryText(SummaryTextContent {
+                        text: String::new(),
+                    }),
+                    sequence_number: self.next_sequence(),
+                },
+            ));
+            self.output_items.insert(output_index, item);
+            self.reasoning_states.insert(
+                candidate_index,
+                ReasoningState {
+                    output_index,
+                    item_id,
+                    text: String::new(),
+                },
+            );
+        }
+
+        let sequence_number = self.next_sequence();
+        if let Some(state) = self.reasoning_states.get_mut(&candidate_index) {
+            state.text.push_str(&text);
+            events.push(ResponseStreamEvent::ReasoningSummaryTextDelta(
+                ResponseReasoningSummaryTextDeltaEvent {
+                    item_id: state.item_id.clone(),
+                    output_index: state.output_index,
+                    summary_index: 0,
+                    delta: text,
+                    sequence_number,
+                },
+            ));
+        }
+        events
+    }
+
     fn emit_function_call(
         &mut self,
         candidate_index: i64,
@@ -300,6 +383,53 @@ impl GeminiToOpenAIResponseStreamState {
         let mut events = Vec::new();
         let (status, incomplete_details) = map_finish_reason(finish_reason);
 
+        let reasoning_states = self
+            .reasoning_states
+            .values()
+            .cloned()
+            .collect::<Vec<ReasoningState>>();
+        for state in reasoning_states {
+            let part = SummaryPart::SummaryText(SummaryTextContent {
+                text: state.text.clone(),
+            });
+            events.push(ResponseStreamEvent::ReasoningSummaryTextDone(
+                ResponseReasoningSummaryTextDoneEvent {
+                    item_id: state.item_id.clone(),
+                    output_index: state.output_index,
+                    summary_index: 0,
+                    text: state.text.clone(),
+                    sequence_number: self.next_sequence(),
+                },
+            ));
+            events.push(ResponseStreamEvent::ReasoningSummaryPartDone(
+                ResponseReasoningSummaryPartDoneEvent {
+                    item_id: state.item_id.clone(),
+                    output_index: state.output_index,
+                    summary_index: 0,
+                    part: part.clone(),
+                    sequence_number: self.next_sequence(),
+                },
+            ));
+
+            let item = OutputItem::Reasoning(ReasoningItem {
+                r#type: ReasoningItemType::Reasoning,
+                id: state.item_id.clone(),
+                encrypted_content: None,
+                summary: vec![part],
+                content: Vec::new(),
+                status: Some(ReasoningItemStatus::Completed),
+            });
+
+            events.push(ResponseStreamEvent::OutputItemDone(
+                ResponseOutputItemDoneEvent {
+                    output_index: state.output_index,
+                    item: item.clone(),
+                    sequence_number: self.next_sequence(),
+                },
+            ));
+            self.output_items.insert(state.output_index, item);
+        }
+
         let message_states = self
             .message_states
             .values()
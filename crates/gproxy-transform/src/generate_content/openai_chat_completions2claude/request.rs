@@ -140,6 +140,7 @@ pub fn transform_request(request: OpenAIChatCompletionRequest) -> ClaudeCreateMe
             tools,
             top_k: None,
             top_p: request.body.top_p,
+            extra: Default::default(),
         },
     }
 }
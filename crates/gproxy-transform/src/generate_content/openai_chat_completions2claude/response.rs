@@ -19,6 +19,7 @@ pub fn transform_response(response: ClaudeCreateMessageResponse) -> CreateChatCo
     let message = ChatCompletionResponseMessage {
         role: ChatCompletionResponseRole::Assistant,
         content,
+        reasoning_content: None,
         refusal,
         tool_calls,
         annotations: None,
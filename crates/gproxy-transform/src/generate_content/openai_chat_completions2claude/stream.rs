@@ -3,11 +3,11 @@ use std::collections::BTreeMap;
 use gproxy_protocol::claude::count_tokens::types::Model as ClaudeModel;
 use gproxy_protocol::claude::create_message::stream::{
     BetaStreamContentBlock, BetaStreamContentBlockDelta, BetaStreamEvent, BetaStreamEventKnown,
-    BetaStreamMessage, BetaStreamMessageDelta, BetaStreamUsage,
+    BetaStreamMessage, BetaStreamMessageDelta, BetaStreamUsage, BetaThinkingBlockStream,
 };
 use gproxy_protocol::claude::create_message::types::{
     BetaMessageRole, BetaMessageType, BetaStopReason, BetaTextBlock, BetaTextBlockType,
-    BetaToolUseBlock, BetaToolUseBlockType, JsonObject,
+    BetaThinkingBlockType, BetaToolUseBlock, BetaToolUseBlockType, JsonObject,
 };
 use gproxy_protocol::openai::create_chat_completions::stream::CreateChatCompletionStreamResponse;
 use gproxy_protocol::openai::create_chat_completions::types::{
@@ -28,6 +28,7 @@ pub struct OpenAIToClaudeChatCompletionStreamState {
     finish_emitted: bool,
     pending_finish: Option<BetaStopReason>,
     next_block_index: u32,
+    thinking_block_index: Option<u32>,
     text_block_index: Option<u32>,
     tool_blocks: BTreeMap<i64, ToolBlockInfo>,
 }
@@ -41,6 +42,7 @@ impl OpenAIToClaudeChatCompletionStreamState {
             finish_emitted: false,
             pending_finish: None,
             next_block_index: 0,
+            thinking_block_index: None,
             text_block_index: None,
             tool_blocks: BTreeMap::new(),
         }
@@ -82,10 +84,12 @@ impl OpenAIToClaudeChatCompletionStreamState {
         let choice = chunk.choices.first();
 
         if let Some(choice) = choice {
+            if let Some(reasoning) = &choice.delta.reasoning_content {
+                events.extend(self.emit_thinking(reasoning));
+            }
+
             if let Some(content) = &choice.delta.content {
                 events.extend(self.emit_text(content));
-            } else if let Some(reasoning) = &choice.delta.reasoning_content {
-                events.extend(self.emit_text(reasoning));
             }
 
             if let Some(refusal) = &choice.delta.refusal {
@@ -140,12 +144,51 @@ impl OpenAIToClaudeChatCompletionStreamState {
         events
     }
 
+    fn emit_thinking(&mut self, thinking: &str) -> Vec<BetaStreamEvent> {
+        if thinking.is_empty() {
+            return Vec::new();
+        }
+
+        let mut events = Vec::new();
+        let block_index = match self.thinking_block_index {
+            Some(index) => index,
+            None => {
+                let index = self.next_block_index;
+                self.next_block_index += 1;
+                self.thinking_block_index = Some(index);
+                events.push(BetaStreamEvent::Known(
+                    BetaStreamEventKnown::ContentBlockStart {
+                        index,
+                        content_block: BetaStreamContentBlock::Thinking(BetaThinkingBlockStream {
+                            signature: None,
+                            thinking: String::new(),
+                            r#type: BetaThinkingBlockType::Thinking,
+                        }),
+                    },
+                ));
+                index
+            }
+        };
+
+        events.push(BetaStreamEvent::Known(
+            BetaStreamEventKnown::ContentBlockDelta {
+                index: block_index,
+                delta: BetaStreamContentBlockDelta::ThinkingDelta {
+                    thinking: thinking.to_string(),
+                },
+            },
+        ));
+
+        events
+    }
+
     fn emit_text(&mut self, text: &str) -> Vec<BetaStreamEvent> {
         if text.is_empty() {
             return Vec::new();
         }
 
-        let mut events = Vec::new();
+        // Reasoning comes first; the answer starts once it is over.
+        let mut events = self.close_thinking_block();
         let block_index = match self.text_block_index {
             Some(index) => index,
             None => {
@@ -182,7 +225,7 @@ impl OpenAIToClaudeChatCompletionStreamState {
         &mut self,
         call: &ChatCompletionMessageToolCallChunk,
     ) -> Vec<BetaStreamEvent> {
-        let mut events = Vec::new();
+        let mut events = self.close_thinking_block();
         let index = call.index;
 
         let info = self.tool_blocks.entry(index).or_insert_with(|| {
@@ -234,7 +277,7 @@ impl OpenAIToClaudeChatCompletionStreamState {
         &mut self,
         call: &ChatCompletionFunctionCallDelta,
     ) -> Vec<BetaStreamEvent> {
-        let mut events = Vec::new();
+        let mut events = self.close_thinking_block();
         let key = -1;
         let info = self.tool_blocks.entry(key).or_insert_with(|| {
             let block_index = self.next_block_index;
@@ -275,8 +318,17 @@ impl OpenAIToClaudeChatCompletionStreamState {
         events
     }
 
+    fn close_thinking_block(&mut self) -> Vec<BetaStreamEvent> {
+        match self.thinking_block_index.take() {
+            Some(index) => vec![BetaStreamEvent::Known(
+                BetaStreamEventKnown::ContentBlockStop { index },
+            )],
+            None => Vec::new(),
+        }
+    }
+
     fn close_open_blocks(&mut self) -> Vec<BetaStreamEvent> {
-        let mut events = Vec::new();
+        let mut events = self.close_thinking_block();
 
         if let Some(index) = self.text_block_index.take() {
             events.push(BetaStreamEvent::Known(
@@ -119,6 +119,9 @@ pub fn transform_request(request: CreateChatCompletionRequest) -> GeminiGenerate
         request.body.modalities,
         request.body.reasoning_effort,
         extra_thinking_config,
+        request.body.logprobs,
+        request.body.top_logprobs,
+        request.body.n,
         model_id,
     );
 
@@ -133,6 +136,7 @@ pub fn transform_request(request: CreateChatCompletionRequest) -> GeminiGenerate
             system_instruction,
             generation_config,
             cached_content,
+            extra: Default::default(),
         },
     }
 }
@@ -673,6 +677,9 @@ fn map_generation_config(
     modalities: Option<Vec<ResponseModality>>,
     reasoning_effort: Option<ReasoningEffort>,
     extra_thinking_config: Option<ThinkingConfig>,
+    logprobs: Option<bool>,
+    top_logprobs: Option<i64>,
+    n: Option<i64>,
     model_id: &str,
 ) -> Option<GenerationConfig> {
     let max_output_tokens = max_completion_tokens
@@ -712,6 +719,12 @@ fn map_generation_config(
     let thinking_config =
         extra_thinking_config.or_else(|| map_thinking_config(reasoning_effort, model_id));
 
+    let response_logprobs = logprobs.filter(|enabled| *enabled);
+    let logprobs = top_logprobs
+        .filter(|_| response_logprobs.is_some())
+        .map(|count| count.clamp(0, 20) as u32);
+    let candidate_count = n.filter(|count| *count > 1).map(|count| count as u32);
+
     if max_output_tokens.is_none()
         && temperature.is_none()
         && top_p.is_none()
@@ -720,6 +733,8 @@ fn map_generation_config(
         && response_mime_type.is_none()
         && response_modalities.as_ref().is_none_or(|m| m.is_empty())
         && thinking_config.is_none()
+        && response_logprobs.is_none()
+        && candidate_count.is_none()
     {
         return None;
     }
@@ -731,7 +746,7 @@ fn map_generation_config(
         response_json_schema_internal: None,
         response_json_schema,
         response_modalities,
-        candidate_count: None,
+        candidate_count,
         max_output_tokens,
         temperature,
         top_p,
@@ -739,8 +754,8 @@ fn map_generation_config(
         seed: None,
         presence_penalty: None,
         frequency_penalty: None,
-        response_logprobs: None,
-        logprobs: None,
+        response_logprobs,
+        logprobs,
         enable_enhanced_civic_answers: None,
         speech_config: None,
         thinking_config,
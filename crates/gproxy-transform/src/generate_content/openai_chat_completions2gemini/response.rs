@@ -1,12 +1,15 @@
 use gproxy_protocol::gemini::count_tokens::types::Content as GeminiContent;
 use gproxy_protocol::gemini::generate_content::response::GenerateContentResponse as GeminiGenerateContentResponse;
-use gproxy_protocol::gemini::generate_content::types::{Candidate, FinishReason, UsageMetadata};
+use gproxy_protocol::gemini::generate_content::types::{
+    Candidate, FinishReason, LogprobsCandidate, LogprobsResult, UsageMetadata,
+};
 use gproxy_protocol::openai::create_chat_completions::response::{
     ChatCompletionChoice, ChatCompletionObjectType, CreateChatCompletionResponse,
 };
 use gproxy_protocol::openai::create_chat_completions::types::{
-    ChatCompletionFinishReason, ChatCompletionMessageToolCall, ChatCompletionResponseMessage,
-    ChatCompletionResponseRole, CompletionTokensDetails, CompletionUsage, PromptTokensDetails,
+    ChatCompletionChoiceLogprobs, ChatCompletionFinishReason, ChatCompletionMessageToolCall,
+    ChatCompletionResponseMessage, ChatCompletionResponseRole, ChatCompletionTokenLogprob,
+    ChatCompletionTopLogprob, CompletionTokensDetails, CompletionUsage, PromptTokensDetails,
 };
 
 /// Convert a Gemini generate-content response into an OpenAI chat-completions response.
@@ -30,6 +33,7 @@ pub fn transform_response(response: GeminiGenerateContentResponse) -> CreateChat
             message: ChatCompletionResponseMessage {
                 role: ChatCompletionResponseRole::Assistant,
                 content: None,
+                reasoning_content: None,
                 refusal: None,
                 tool_calls: None,
                 annotations: None,
@@ -67,6 +71,7 @@ fn map_candidate_to_choice(candidate: &Candidate, fallback_index: usize) -> Chat
     let message = ChatCompletionResponseMessage {
         role: ChatCompletionResponseRole::Assistant,
         content,
+        reasoning_content: None,
         refusal: None,
         tool_calls: if tool_calls.is_empty() {
             None
@@ -85,10 +90,45 @@ fn map_candidate_to_choice(candidate: &Candidate, fallback_index: usize) -> Chat
             .finish_reason
             .map(map_finish_reason)
             .unwrap_or(ChatCompletionFinishReason::Stop),
-        logprobs: None,
+        logprobs: candidate.logprobs_result.as_ref().map(map_logprobs),
+    }
+}
+
+/// Gemini's chosen token per step, with that step's top candidates as alternatives.
+fn map_logprobs(result: &LogprobsResult) -> ChatCompletionChoiceLogprobs {
+    let content = result
+        .chosen_candidates
+        .iter()
+        .enumerate()
+        .map(|(step, chosen)| ChatCompletionTokenLogprob {
+            token: chosen.token.clone(),
+            logprob: chosen.log_probability,
+            bytes: Some(token_bytes(&chosen.token)),
+            top_logprobs: result
+                .top_candidates
+                .get(step)
+                .map(|top| top.candidates.iter().map(map_top_logprob).collect())
+                .unwrap_or_default(),
+        })
+        .collect();
+    ChatCompletionChoiceLogprobs {
+        content: Some(content),
+        refusal: None,
     }
 }
 
+fn map_top_logprob(candidate: &LogprobsCandidate) -> ChatCompletionTopLogprob {
+    ChatCompletionTopLogprob {
+        token: candidate.token.clone(),
+        logprob: candidate.log_probability,
+        bytes: Some(token_bytes(&candidate.token)),
+    }
+}
+
+fn token_bytes(token: &str) -> Vec<i64> {
+    token.bytes().map(i64::from).collect()
+}
+
 fn map_content_to_message_parts(
     content: &GeminiContent,
     index: usize,
@@ -90,7 +90,11 @@ pub fn transform_request(request: CreateChatCompletionRequest) -> CreateResponse
         body: gproxy_protocol::openai::create_response::request::CreateResponseRequestBody {
             model: request.body.model,
             input,
-            include: None,
+            include: request.body.logprobs.filter(|enabled| *enabled).map(|_| {
+                vec![
+                    gproxy_protocol::openai::create_response::types::ResponseInclude::MessageOutputTextLogprobs,
+                ]
+            }),
             parallel_tool_calls: request.body.parallel_tool_calls,
             store: request.body.store,
             instructions,
@@ -126,6 +130,7 @@ pub fn transform_request(request: CreateChatCompletionRequest) -> CreateResponse
             prompt_cache_key: request.body.prompt_cache_key,
             service_tier: request.body.service_tier,
             prompt_cache_retention: request.body.prompt_cache_retention,
+            extra: Default::default(),
         },
     }
 }
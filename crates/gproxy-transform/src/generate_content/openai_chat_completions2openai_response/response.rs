@@ -2,13 +2,15 @@ use gproxy_protocol::openai::create_chat_completions::response::{
     ChatCompletionChoice, ChatCompletionObjectType, CreateChatCompletionResponse,
 };
 use gproxy_protocol::openai::create_chat_completions::types::{
-    ChatCompletionFinishReason, ChatCompletionMessageToolCall,
+    ChatCompletionChoiceLogprobs, ChatCompletionFinishReason, ChatCompletionMessageToolCall,
     ChatCompletionMessageToolCallFunction, ChatCompletionResponseMessage,
-    ChatCompletionResponseRole, CompletionTokensDetails, CompletionUsage, PromptTokensDetails,
+    ChatCompletionResponseRole, ChatCompletionTokenLogprob, ChatCompletionTopLogprob,
+    CompletionTokensDetails, CompletionUsage, PromptTokensDetails,
 };
 use gproxy_protocol::openai::create_response::response::Response;
 use gproxy_protocol::openai::create_response::types::{
-    CustomToolCall, FunctionToolCall, OutputItem, OutputMessageContent, ResponseIncompleteReason,
+    CustomToolCall, FunctionToolCall, LogProb, OutputItem, OutputMessageContent,
+    ResponseIncompleteReason, TopLogProb,
 };
 
 /// Convert an OpenAI responses response into an OpenAI chat-completions response.
@@ -35,6 +37,7 @@ pub fn transform_response(response: Response) -> CreateChatCompletionResponse {
     let message = ChatCompletionResponseMessage {
         role: ChatCompletionResponseRole::Assistant,
         content,
+        reasoning_content: None,
         refusal,
         tool_calls: if tool_calls.is_empty() {
             None
@@ -47,6 +50,7 @@ pub fn transform_response(response: Response) -> CreateChatCompletionResponse {
     };
 
     let finish_reason = map_finish_reason(&response, &message);
+    let logprobs = map_logprobs(&response.output);
 
     CreateChatCompletionResponse {
         id: response.id,
@@ -57,7 +61,7 @@ pub fn transform_response(response: Response) -> CreateChatCompletionResponse {
             index: 0,
             message,
             finish_reason,
-            logprobs: None,
+            logprobs,
         }],
         usage: response.usage.as_ref().map(map_usage),
         service_tier: response.service_tier,
@@ -105,6 +109,46 @@ fn extract_message_parts(
     (texts, refusals, tool_calls)
 }
 
+/// Logprobs of the output text, present only when the upstream returned some.
+fn map_logprobs(output: &[OutputItem]) -> Option<ChatCompletionChoiceLogprobs> {
+    let mut content = Vec::new();
+    for item in output {
+        if let OutputItem::Message(message) = item {
+            for part in &message.content {
+                if let OutputMessageContent::OutputText(text) = part
+                    && let Some(logprobs) = &text.logprobs
+                {
+                    content.extend(logprobs.iter().map(map_token_logprob));
+                }
+            }
+        }
+    }
+    if content.is_empty() {
+        return None;
+    }
+    Some(ChatCompletionChoiceLogprobs {
+        content: Some(content),
+        refusal: None,
+    })
+}
+
+fn map_token_logprob(logprob: &LogProb) -> ChatCompletionTokenLogprob {
+    ChatCompletionTokenLogprob {
+        token: logprob.token.clone(),
+        logprob: logprob.logprob,
+        bytes: Some(logprob.bytes.clone()),
+        top_logprobs: logprob.top_logprobs.iter().map(map_top_logprob).collect(),
+    }
+}
+
+fn map_top_logprob(logprob: &TopLogProb) -> ChatCompletionTopLogprob {
+    ChatCompletionTopLogprob {
+        token: logprob.token.clone(),
+        logprob: logprob.logprob,
+        bytes: Some(logprob.bytes.clone()),
+    }
+}
+
 fn map_function_call(call: &FunctionToolCall) -> Option<ChatCompletionMessageToolCall> {
     let id = call.id.clone().or_else(|| Some(call.call_id.clone()))?;
     Some(ChatCompletionMessageToolCall::Function {
@@ -9,12 +9,14 @@ use gproxy_protocol::openai::create_response::response::{Response, ResponseObjec
 use gproxy_protocol::openai::create_response::stream::{
     ResponseCompletedEvent, ResponseCreatedEvent, ResponseFunctionCallArgumentsDeltaEvent,
     ResponseFunctionCallArgumentsDoneEvent, ResponseOutputItemAddedEvent,
-    ResponseOutputItemDoneEvent, ResponseRefusalDeltaEvent, ResponseRefusalDoneEvent,
-    ResponseStreamEvent, ResponseTextDeltaEvent, ResponseTextDoneEvent,
+    ResponseOutputItemDoneEvent, ResponseReasoningTextDeltaEvent, ResponseReasoningTextDoneEvent,
+    ResponseRefusalDeltaEvent, ResponseRefusalDoneEvent, ResponseStreamEvent,
+    ResponseTextDeltaEvent, ResponseTextDoneEvent,
 };
 use gproxy_protocol::openai::create_response::types::{
     FunctionCallItemStatus, FunctionToolCall, FunctionToolCallType, MessageStatus, OutputItem,
-    OutputMessage, OutputMessageContent, OutputMessageRole, OutputMessageType, RefusalContent,
+    OutputMessage, OutputMessageContent, OutputMessageRole, OutputMessageType, ReasoningContent,
+    ReasoningItem, ReasoningItemStatus, ReasoningItemType, ReasoningTextContent, RefusalContent,
     ResponseIncompleteDetails, ResponseIncompleteReason, ResponseStatus, ResponseUsage,
     ResponseUsageInputTokensDetails, ResponseUsageOutputTokensDetails,
 };
@@ -27,6 +29,13 @@ struct ChoiceState {
     refusal: String,
 }
 
+#[derive(Debug, Clone)]
+struct ReasoningState {
+    output_index: i64,
+    item_id: String,
+    text: String,
+}
+
 #[derive(Debug, Clone)]
 struct ToolCallState {
     output_index: i64,
@@ -44,6 +53,7 @@ pub struct OpenAIChatCompletionToResponseStreamState {
     created_sent: bool,
     next_output_index: i64,
     choices: BTreeMap<i64, ChoiceState>,
+    reasoning: BTreeMap<i64, ReasoningState>,
     tool_calls: BTreeMap<(i64, i64), ToolCallState>,
     output_items: BTreeMap<i64, OutputItem>,
     usage: Option<ResponseUsage>,
@@ -61,6 +71,7 @@ impl OpenAIChatCompletionToResponseStreamState {
             created_sent: false,
             next_output_index: 0,
             choices: BTreeMap::new(),
+            reasoning: BTreeMap::new(),
             tool_calls: BTreeMap::new(),
             output_items: BTreeMap::new(),
             usage: None,
@@ -103,10 +114,12 @@ impl OpenAIChatCompletionToResponseStreamState {
                 events.extend(self.ensure_message(choice_index));
             }
 
+            if let Some(reasoning) = delta.reasoning_content {
+                events.extend(self.emit_reasoning(choice_index, reasoning));
+            }
+
             if let Some(content) = delta.content {
                 events.extend(self.emit_text(choice_index, content));
-            } else if let Some(reasoning) = delta.reasoning_content {
-                events.extend(self.emit_text(choice_index, reasoning));
             }
 
             if let Some(refusal) = delta.refusal {
@@ -201,6 +214,58 @@ impl OpenAIChatCompletionToResponseStreamState {
         events
     }
 
+    fn emit_reasoning(&mut self, choice_index: i64, text: String) -> Vec<ResponseStreamEvent> {
+        if text.is_empty() {
+            return Vec::new();
+        }
+
+        let mut events = Vec::new();
+        if !self.reasoning.contains_key(&choice_index) {
+            let output_index = self.next_output_index;
+            self.next_output_index += 1;
+            let item_id = format!("reasoning_{}", choice_index);
+            let item = OutputItem::Reasoning(ReasoningItem {
+                r#type: ReasoningItemType::Reasoning,
+                id: item_id.clone(),
+                encrypted_content: None,
+                summary: Vec::new(),
+                content: Vec::new(),
+                status: Some(ReasoningItemStatus::InProgress),
+            });
+            events.push(ResponseStreamEvent::OutputItemAdded(
+                ResponseOutputItemAddedEvent {
+                    output_index,
+                    item: item.clone(),
+                    sequence_number: self.next_sequence(),
+                },
+            ));
+            self.output_items.insert(output_index, item);
+            self.reasoning.insert(
+                choice_index,
+                ReasoningState {
+                    output_index,
+                    item_id,
+                    text: String::new(),
+                },
+            );
+        }
+
+        let sequence_number = self.next_sequence();
+        if let Some(state) = self.reasoning.get_mut(&choice_index) {
+            state.text.push_str(&text);
+            events.push(ResponseStreamEvent::ReasoningTextDelta(
+                ResponseReasoningTextDeltaEvent {
+                    item_id: state.item_id.clone(),
+                    output_index: state.output_index,
+                    content_index: 0,
+                    delta: text,
+                    sequence_number,
+                },
+            ));
+        }
+        events
+    }
+
     fn emit_refusal(&mut self, choice_index: i64, refusal: String) -> Vec<ResponseStreamEvent> {
         if refusal.is_empty() {
             return Vec::new();
@@ -328,6 +393,43 @@ impl OpenAIChatCompletionToResponseStreamState {
         let mut events = Vec::new();
         let (status, incomplete_details) = map_finish_reason(finish_reason);
 
+        let reasoning_states = self
+            .reasoning
+            .values()
+            .cloned()
+            .collect::<Vec<ReasoningState>>();
+        for state in reasoning_states {
+            events.push(ResponseStreamEvent::ReasoningTextDone(
+                ResponseReasoningTextDoneEvent {
+                    item_id: state.item_id.clone(),
+                    output_index: state.output_index,
+                    content_index: 0,
+                    text: state.text.clone(),
+                    sequence_number: self.next_sequence(),
+                },
+            ));
+
+            let item = OutputItem::Reasoning(ReasoningItem {
+                r#type: ReasoningItemType::Reasoning,
+                id: state.item_id.clone(),
+                encrypted_content: None,
+                summary: Vec::new(),
+                content: vec![ReasoningContent::ReasoningText(ReasoningTextContent {
+                    text: state.text,
+                })],
+                status: Some(ReasoningItemStatus::Completed),
+            });
+
+            events.push(ResponseStreamEvent::OutputItemDone(
+                ResponseOutputItemDoneEvent {
+                    output_index: state.output_index,
+                    item: item.clone(),
+                    sequence_number: self.next_sequence(),
+                },
+            ));
+            self.output_items.insert(state.output_index, item);
+        }
+
         let choice_states = self.choices.values().cloned().collect::<Vec<ChoiceState>>();
         for state in choice_states {
             if !state.refusal.is_empty() {
@@ -107,6 +107,7 @@ pub fn transform_request(request: OpenAIResponseRequest) -> ClaudeCreateMessageR
             tools,
             top_k: None,
             top_p: request.body.top_p,
+            extra: Default::default(),
         },
     }
 }
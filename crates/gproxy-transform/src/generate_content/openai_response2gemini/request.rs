@@ -70,6 +70,7 @@ pub fn transform_request(request: OpenAIResponseRequest) -> GeminiGenerateConten
             system_instruction,
             generation_config,
             cached_content: None,
+            extra: Default::default(),
         },
     }
 }
@@ -11,8 +11,9 @@ use gproxy_protocol::openai::create_response::stream::{
     ResponseCompletedEvent, ResponseFunctionCallArgumentsDeltaEvent,
     ResponseFunctionCallArgumentsDoneEvent, ResponseMCPCallArgumentsDeltaEvent,
     ResponseMCPCallArgumentsDoneEvent, ResponseOutputItemAddedEvent, ResponseOutputItemDoneEvent,
-    ResponseRefusalDeltaEvent, ResponseRefusalDoneEvent, ResponseStreamEvent,
-    ResponseTextDeltaEvent, ResponseTextDoneEvent,
+    ResponseReasoningSummaryTextDeltaEvent, ResponseReasoningSummaryTextDoneEvent,
+    ResponseReasoningTextDeltaEvent, ResponseReasoningTextDoneEvent, ResponseRefusalDeltaEvent,
+    ResponseRefusalDoneEvent, ResponseStreamEvent, ResponseTextDeltaEvent, ResponseTextDoneEvent,
 };
 use gproxy_protocol::openai::create_response::types::{
     CustomToolCall, FunctionToolCall, MCPToolCall, OutputItem, ResponseIncompleteDetails,
@@ -42,6 +43,10 @@ pub struct OpenAIResponseToGeminiStreamState {
     model_version: String,
     text_buffers: BTreeMap<(i64, i64), String>,
     refusal_buffers: BTreeMap<(i64, i64), String>,
+    /// Reasoning summaries by (output index, summary index).
+    summary_buffers: BTreeMap<(i64, i64), String>,
+    /// Full reasoning text by (output index, content index).
+    reasoning_buffers: BTreeMap<(i64, i64), String>,
     tool_states: BTreeMap<i64, ToolState>,
     usage: Option<ResponseUsage>,
     saw_refusal: bool,
@@ -55,6 +60,8 @@ impl OpenAIResponseToGeminiStreamState {
             model_version: "models/unknown".to_string(),
             text_buffers: BTreeMap::new(),
             refusal_buffers: BTreeMap::new(),
+            summary_buffers: BTreeMap::new(),
+            reasoning_buffers: BTreeMap::new(),
             tool_states: BTreeMap::new(),
             usage: None,
             saw_refusal: false,
@@ -95,6 +102,14 @@ impl OpenAIResponseToGeminiStreamState {
             ResponseStreamEvent::OutputTextDone(event) => self.handle_text_done(event),
             ResponseStreamEvent::RefusalDelta(event) => self.handle_refusal_delta(event),
             ResponseStreamEvent::RefusalDone(event) => self.handle_refusal_done(event),
+            ResponseStreamEvent::ReasoningSummaryTextDelta(event) => {
+                self.handle_reasoning_summary_delta(event)
+            }
+            ResponseStreamEvent::ReasoningSummaryTextDone(event) => {
+                self.handle_reasoning_summary_done(event)
+            }
+            ResponseStreamEvent::ReasoningTextDelta(event) => self.handle_reasoning_delta(event),
+            ResponseStreamEvent::ReasoningTextDone(event) => self.handle_reasoning_done(event),
             ResponseStreamEvent::FunctionCallArgumentsDelta(event) => {
                 self.handle_function_call_delta(event)
             }
@@ -182,6 +197,62 @@ impl OpenAIResponseToGeminiStreamState {
         }
     }
 
+    fn handle_reasoning_summary_delta(
+        &mut self,
+        event: ResponseReasoningSummaryTextDeltaEvent,
+    ) -> Vec<GenerateContentResponse> {
+        if event.delta.is_empty() {
+            return Vec::new();
+        }
+        self.summary_buffers
+            .entry((event.output_index, event.summary_index))
+            .and_modify(|value| value.push_str(&event.delta))
+            .or_insert_with(|| event.delta.clone());
+        self.emit_parts(vec![thought_part(event.delta)])
+    }
+
+    fn handle_reasoning_summary_done(
+        &mut self,
+        event: ResponseReasoningSummaryTextDoneEvent,
+    ) -> Vec<GenerateContentResponse> {
+        let key = (event.output_index, event.summary_index);
+        let delta = compute_delta(self.summary_buffers.get(&key), &event.text);
+        self.summary_buffers.insert(key, event.text);
+        if delta.is_empty() {
+            Vec::new()
+        } else {
+            self.emit_parts(vec![thought_part(delta)])
+        }
+    }
+
+    fn handle_reasoning_delta(
+        &mut self,
+        event: ResponseReasoningTextDeltaEvent,
+    ) -> Vec<GenerateContentResponse> {
+        if event.delta.is_empty() {
+            return Vec::new();
+        }
+        self.reasoning_buffers
+            .entry((event.output_index, event.content_index))
+            .and_modify(|value| value.push_str(&event.delta))
+            .or_insert_with(|| event.delta.clone());
+        self.emit_parts(vec![thought_part(event.delta)])
+    }
+
+    fn handle_reasoning_done(
+        &mut self,
+        event: ResponseReasoningTextDoneEvent,
+    ) -> Vec<GenerateContentResponse> {
+        let key = (event.output_index, event.content_index);
+        let delta = compute_delta(self.reasoning_buffers.get(&key), &event.text);
+        self.reasoning_buffers.insert(key, event.text);
+        if delta.is_empty() {
+            Vec::new()
+        } else {
+            self.emit_parts(vec![thought_part(delta)])
+        }
+    }
+
     fn handle_function_call_delta(
         &mut self,
         event: ResponseFunctionCallArgumentsDeltaEvent,
@@ -472,6 +543,13 @@ fn text_part(text: String) -> GeminiPart {
     }
 }
 
+fn thought_part(text: String) -> GeminiPart {
+    GeminiPart {
+        thought: Some(true),
+        ..text_part(text)
+    }
+}
+
 fn compute_delta(previous: Option<&String>, full: &str) -> String {
     match previous {
         Some(prev) if full.starts_with(prev) => full[prev.len()..].to_string(),
@@ -104,6 +104,7 @@ pub fn transform_request(request: CreateResponseRequest) -> CreateChatCompletion
             prompt_cache_key: request.body.prompt_cache_key,
             service_tier: request.body.service_tier,
             prompt_cache_retention: request.body.prompt_cache_retention,
+            extra: Default::default(),
         },
     }
 }
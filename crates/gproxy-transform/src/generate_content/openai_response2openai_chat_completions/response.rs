@@ -7,7 +7,8 @@ use gproxy_protocol::openai::create_response::response::{Response, ResponseObjec
 use gproxy_protocol::openai::create_response::types::{
     CustomToolCall, CustomToolCallType, FunctionCallItemStatus, FunctionToolCall,
     FunctionToolCallType, MessageStatus, OutputItem, OutputMessage, OutputMessageContent,
-    OutputMessageRole, OutputMessageType, ResponseStatus, ResponseUsage,
+    OutputMessageRole, OutputMessageType, ReasoningContent, ReasoningItem, ReasoningItemStatus,
+    ReasoningItemType, ReasoningTextContent, ResponseStatus, ResponseUsage,
     ResponseUsageInputTokensDetails, ResponseUsageOutputTokensDetails,
 };
 
@@ -63,6 +64,21 @@ pub fn transform_response(response: CreateChatCompletionResponse) -> Response {
 fn append_choice_output(choice: &ChatCompletionChoice, output: &mut Vec<OutputItem>) {
     let message = &choice.message;
 
+    if let Some(reasoning) = &message.reasoning_content
+        && !reasoning.is_empty()
+    {
+        output.push(OutputItem::Reasoning(ReasoningItem {
+            r#type: ReasoningItemType::Reasoning,
+            id: format!("reasoning_{}", choice.index),
+            encrypted_content: None,
+            summary: Vec::new(),
+            content: vec![ReasoningContent::ReasoningText(ReasoningTextContent {
+                text: reasoning.clone(),
+            })],
+            status: Some(ReasoningItemStatus::Completed),
+        }));
+    }
+
     if let Some(item) = map_message_to_output(message, choice.index) {
         output.push(OutputItem::Message(item));
     }
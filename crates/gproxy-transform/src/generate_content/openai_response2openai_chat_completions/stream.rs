@@ -4,10 +4,10 @@ use gproxy_protocol::openai::create_chat_completions::stream::{
     ChatCompletionChunkObjectType, ChatCompletionStreamChoice, CreateChatCompletionStreamResponse,
 };
 use gproxy_protocol::openai::create_chat_completions::types::{
-    ChatCompletionFinishReason, ChatCompletionMessageToolCallChunk,
+    ChatCompletionChoiceLogprobs, ChatCompletionFinishReason, ChatCompletionMessageToolCallChunk,
     ChatCompletionMessageToolCallChunkFunction, ChatCompletionRole,
-    ChatCompletionStreamResponseDelta, ChatCompletionToolCallChunkType, CompletionTokensDetails,
-    CompletionUsage, PromptTokensDetails,
+    ChatCompletionStreamResponseDelta, ChatCompletionTokenLogprob, ChatCompletionToolCallChunkType,
+    ChatCompletionTopLogprob, CompletionTokensDetails, CompletionUsage, PromptTokensDetails,
 };
 use gproxy_protocol::openai::create_response::response::Response;
 use gproxy_protocol::openai::create_response::stream::{
@@ -19,7 +19,7 @@ use gproxy_protocol::openai::create_response::stream::{
 };
 use gproxy_protocol::openai::create_response::types::{
     CustomToolCall, FunctionToolCall, MCPToolCall, OutputItem, ResponseIncompleteDetails,
-    ResponseIncompleteReason, ResponseStatus, ResponseUsage,
+    ResponseIncompleteReason, ResponseLogProb, ResponseStatus, ResponseUsage,
 };
 
 #[derive(Debug, Clone)]
@@ -158,7 +158,7 @@ impl OpenAIResponseToChatCompletionStreamState {
             .or_insert_with(|| event.delta.clone());
 
         let role = self.take_role();
-        self.emit_delta(ChatCompletionStreamResponseDelta {
+        let mut chunks = self.emit_delta(ChatCompletionStreamResponseDelta {
             content: Some(event.delta),
             reasoning_content: None,
             function_call: None,
@@ -166,7 +166,15 @@ impl OpenAIResponseToChatCompletionStreamState {
             role,
             refusal: None,
             obfuscation: None,
-        })
+        });
+        if !event.logprobs.is_empty()
+            && let Some(choice) = chunks
+                .first_mut()
+                .and_then(|chunk| chunk.choices.first_mut())
+        {
+            choice.logprobs = Some(map_logprobs(&event.logprobs));
+        }
+        chunks
     }
 
     fn handle_text_done(
@@ -624,6 +632,30 @@ impl Default for OpenAIResponseToChatCompletionStreamState {
     }
 }
 
+fn map_logprobs(logprobs: &[ResponseLogProb]) -> ChatCompletionChoiceLogprobs {
+    let content = logprobs
+        .iter()
+        .map(|logprob| ChatCompletionTokenLogprob {
+            token: logprob.token.clone(),
+            logprob: logprob.logprob,
+            bytes: None,
+            top_logprobs: logprob
+                .top_logprobs
+                .iter()
+                .map(|top| ChatCompletionTopLogprob {
+                    token: top.token.clone(),
+                    logprob: top.logprob,
+                    bytes: None,
+                })
+                .collect(),
+        })
+        .collect();
+    ChatCompletionChoiceLogprobs {
+        content: Some(content),
+        refusal: None,
+    }
+}
+
 fn compute_delta(previous: Option<&String>, full: &str) -> String {
     match previous {
         Some(prev) if full.starts_with(prev) => full[prev.len()..].to_string(),
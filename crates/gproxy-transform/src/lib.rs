@@ -1,3 +1,4 @@
+pub mod conformance;
 pub mod count_tokens;
 pub mod generate_content;
 pub mod get_model;
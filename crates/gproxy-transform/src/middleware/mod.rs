@@ -9,16 +9,24 @@ mod usage;
 mod tests;
 
 pub use types::{
-    CountTokensRequest, CountTokensResponse, GenerateContentRequest, GenerateContentResponse,
-    MemoryTraceSummarizeRequest, MemoryTraceSummarizeResponse, ModelGetRequest, ModelGetResponse,
-    ModelListRequest, ModelListResponse, Op, Proto, Request, Response, ResponseCancelRequest,
-    ResponseCancelResponse, ResponseCompactRequest, ResponseCompactResponse, ResponseDeleteRequest,
-    ResponseDeleteResponse, ResponseGetRequest, ResponseGetResponse, ResponseListInputItemsRequest,
-    ResponseListInputItemsResponse, StreamEvent, StreamFormat, TransformContext, TransformError,
-    stream_format,
+    BatchGenerateContentRequest, BatchGenerateContentResponse, BatchGetRequest, BatchGetResponse,
+    ConversationCreateRequest, ConversationCreateResponse, ConversationDeleteRequest,
+    ConversationDeleteResponse, ConversationGetRequest, ConversationGetResponse,
+    ConversationItemCreateRequest, ConversationItemCreateResponse, ConversationItemDeleteRequest,
+    ConversationItemDeleteResponse, ConversationItemGetRequest, ConversationItemGetResponse,
+    ConversationItemListRequest, ConversationItemListResponse, ConversationUpdateRequest,
+    ConversationUpdateResponse, CountTokensRequest, CountTokensResponse, FileDeleteRequest,
+    FileDeleteResponse, FileGetRequest, FileGetResponse, FileListRequest, FileListResponse,
+    GenerateContentRequest, GenerateContentResponse, MemoryTraceSummarizeRequest,
+    MemoryTraceSummarizeResponse, ModelGetRequest, ModelGetResponse, ModelListRequest,
+    ModelListResponse, Op, Proto, Request, Response, ResponseCancelRequest, ResponseCancelResponse,
+    ResponseCompactRequest, ResponseCompactResponse, ResponseDeleteRequest, ResponseDeleteResponse,
+    ResponseGetRequest, ResponseGetResponse, ResponseListInputItemsRequest,
+    ResponseListInputItemsResponse, ResponseListRequest, ResponseListResponse, StreamEvent,
+    StreamFormat, TransformContext, TransformError, stream_format,
 };
 
-pub use ops::{transform_request, transform_response};
+pub use ops::{transform_request, transform_response, transform_warnings};
 pub use stream::{NostreamToStream, StreamToNostream, StreamTransformer};
 pub use usage::{
     CountTokensFn, OutputAccumulator, UsageAccumulator, UsageError, UsageSummary,
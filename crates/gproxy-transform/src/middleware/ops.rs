@@ -1,8 +1,12 @@
+use gproxy_protocol::gemini::generate_content::request::GenerateContentRequest as GeminiGenerateContentRequest;
+use gproxy_protocol::gemini::stream_content::request::StreamGenerateContentRequest as GeminiStreamGenerateContentRequest;
+
 use super::generate::{transform_generate_request, transform_generate_response};
 use super::helpers::{ensure_basic_proto, ensure_non_generate};
 use super::types::{
-    CountTokensRequest, CountTokensResponse, ModelGetRequest, ModelGetResponse, ModelListRequest,
-    ModelListResponse, Op, Request, Response, TransformContext, TransformError,
+    CountTokensRequest, CountTokensResponse, GenerateContentRequest, ModelGetRequest,
+    ModelGetResponse, ModelListRequest, ModelListResponse, Op, Proto, Request, Response,
+    TransformContext, TransformError,
 };
 
 use crate::count_tokens;
@@ -38,6 +42,46 @@ pub fn transform_response(
     }
 }
 
+/// Warning codes for request features that `ctx` can't carry to the upstream and drops;
+/// empty when nothing is lost.
+pub fn transform_warnings(ctx: &TransformContext, req: &Request) -> Vec<&'static str> {
+    let Request::GenerateContent(req) = req else {
+        return Vec::new();
+    };
+    // Only chat and Gemini have several candidates per request.
+    let multi_candidate = matches!(ctx.dst, Proto::OpenAIChat | Proto::Gemini);
+    let mut warnings = Vec::new();
+    match req {
+        GenerateContentRequest::OpenAIChat(req) => {
+            if req.body.logprobs == Some(true)
+                && !matches!(
+                    ctx.dst,
+                    Proto::OpenAIChat | Proto::OpenAIResponse | Proto::Gemini
+                )
+            {
+                warnings.push("logprobs_unsupported");
+            }
+            if req.body.n.is_some_and(|n| n > 1) && !multi_candidate {
+                warnings.push("n_unsupported");
+            }
+        }
+        GenerateContentRequest::Gemini(GeminiGenerateContentRequest { body, .. })
+        | GenerateContentRequest::GeminiStream(GeminiStreamGenerateContentRequest {
+            body, ..
+        }) => {
+            let candidates = body
+                .generation_config
+                .as_ref()
+                .and_then(|config| config.candidate_count);
+            if candidates.is_some_and(|count| count > 1) && !multi_candidate {
+                warnings.push("candidate_count_unsupported");
+            }
+        }
+        _ => {}
+    }
+    warnings
+}
+
 fn transform_model_list_request(
     ctx: &TransformContext,
     req: ModelListRequest,
@@ -710,7 +710,7 @@ fn stream_delta_from_message(
     ChatCompletionStreamResponseDelta {
         role: Some(map_chat_role(message.role)),
         content: message.content.clone(),
-        reasoning_content: None,
+        reasoning_content: message.reasoning_content.clone(),
         function_call: message
             .function_call
             .as_ref()
@@ -106,6 +106,7 @@ fn make_openai_chat_request(stream: Option<bool>) -> CreateChatCompletionRequest
             prompt_cache_key: None,
             service_tier: None,
             prompt_cache_retention: None,
+            extra: Default::default(),
         },
     }
 }
@@ -114,6 +115,7 @@ fn make_openai_chat_response_with_usage(usage: CompletionUsage) -> CreateChatCom
     let message = ChatCompletionResponseMessage {
         role: ChatCompletionResponseRole::Assistant,
         content: Some("ok".to_string()),
+        reasoning_content: None,
         refusal: None,
         tool_calls: None,
         annotations: None,
@@ -340,7 +342,7 @@ fn fallback_usage_does_not_set_cache_fields() {
     impl CountTokensFn for FixedCounter {
         type Error = ();
 
-        fn count_tokens(
+        async fn count_tokens(
             &self,
             proto: Proto,
             _req: CountTokensRequest,
@@ -367,15 +369,244 @@ fn fallback_usage_does_not_set_cache_fields() {
     }
 
     let req = GenerateContentRequest::OpenAIChat(make_openai_chat_request(Some(false)));
-    let summary = fallback_usage_with_count_tokens(
+    let counting = std::pin::pin!(fallback_usage_with_count_tokens(
         Proto::OpenAIChat,
         &req,
         "hello",
         &FixedCounter { value: 42 },
-    )
-    .unwrap();
+    ));
+    // The counter never waits, so one poll finishes the count.
+    let std::task::Poll::Ready(summary) =
+        counting.poll(&mut std::task::Context::from_waker(std::task::Waker::noop()))
+    else {
+        panic!("fixed counter should not wait");
+    };
+    let summary = summary.unwrap();
     assert_eq!(summary.input_tokens, Some(42));
     assert_eq!(summary.output_tokens, Some(42));
     assert_eq!(summary.cache_read_input_tokens, None);
     assert_eq!(summary.cache_creation_input_tokens, None);
 }
+
+#[test]
+fn reasoning_content_maps_to_thinking_and_reasoning_items() {
+    let mut resp = make_openai_chat_response_with_usage(CompletionUsage {
+        prompt_tokens: 1,
+        completion_tokens: 1,
+        total_tokens: 2,
+        completion_tokens_details: None,
+        prompt_tokens_details: None,
+    });
+    resp.choices[0].message.reasoning_content = Some("think".to_string());
+
+    let claude =
+        crate::generate_content::claude2openai_chat_completions::response::transform_response(
+            resp.clone(),
+        );
+    let claude = serde_json::to_value(&claude).unwrap();
+    assert_eq!(claude["content"][0]["type"], "thinking");
+    assert_eq!(claude["content"][0]["thinking"], "think");
+    assert_eq!(claude["content"][1]["text"], "ok");
+
+    let response =
+        crate::generate_content::openai_response2openai_chat_completions::response::transform_response(
+            resp,
+        );
+    let response = serde_json::to_value(&response).unwrap();
+    assert_eq!(response["output"][0]["type"], "reasoning");
+    assert_eq!(response["output"][0]["content"][0]["text"], "think");
+    assert_eq!(response["output"][1]["type"], "message");
+}
+
+#[test]
+fn gemini_thoughts_stream_as_responses_reasoning_and_back() {
+    let chunk: GeminiGenerateContentResponse = serde_json::from_value(serde_json::json!({
+        "candidates": [{
+            "index": 0,
+            "content": {
+                "role": "model",
+                "parts": [{ "text": "plan", "thought": true }, { "text": "answer" }],
+            },
+        }],
+    }))
+    .unwrap();
+    let last: GeminiGenerateContentResponse = serde_json::from_value(serde_json::json!({
+        "candidates": [{ "index": 0, "content": { "parts": [] }, "finishReason": "STOP" }],
+    }))
+    .unwrap();
+
+    let mut to_responses =
+        crate::generate_content::gemini2openai_response::stream::GeminiToOpenAIResponseStreamState::new();
+    let mut events = to_responses.transform_response(chunk);
+    events.extend(to_responses.transform_response(last));
+    let events = serde_json::to_value(&events).unwrap();
+    let types = events
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|event| event["type"].as_str().unwrap_or_default())
+        .collect::<Vec<_>>();
+    assert!(types.contains(&"response.reasoning_summary_text.delta"));
+    let completed = events.as_array().unwrap().last().unwrap();
+    assert_eq!(completed["response"]["output"][0]["type"], "reasoning");
+    assert_eq!(
+        completed["response"]["output"][0]["summary"][0]["text"],
+        "plan"
+    );
+    assert_eq!(
+        completed["response"]["output"][1]["content"][0]["text"],
+        "answer"
+    );
+
+    let mut to_gemini =
+        crate::generate_content::openai_response2gemini::stream::OpenAIResponseToGeminiStreamState::new();
+    let events: Vec<gproxy_protocol::openai::create_response::stream::ResponseStreamEvent> =
+        serde_json::from_value(events).unwrap();
+    let chunks = events
+        .into_iter()
+        .flat_map(|event| to_gemini.transform_event(event))
+        .collect::<Vec<_>>();
+    let chunks = serde_json::to_value(&chunks).unwrap();
+    let parts = chunks
+        .as_array()
+        .unwrap()
+        .iter()
+        .flat_map(|chunk| {
+            chunk["candidates"][0]["content"]["parts"]
+                .as_array()
+                .cloned()
+        })
+        .flatten()
+        .collect::<Vec<_>>();
+    assert_eq!(parts[0]["text"], "plan");
+    assert_eq!(parts[0]["thought"], true);
+    assert_eq!(parts[1]["text"], "answer");
+    assert!(parts[1].get("thought").is_none());
+}
+
+#[test]
+fn logprobs_map_through_gemini_or_warn() {
+    let mut req = make_openai_chat_request(None);
+    req.body.logprobs = Some(true);
+    req.body.top_logprobs = Some(2);
+    let req = Request::GenerateContent(GenerateContentRequest::OpenAIChat(req));
+
+    let mut ctx = TransformContext {
+        src: Proto::OpenAIChat,
+        dst: Proto::Gemini,
+        src_op: Op::GenerateContent,
+        dst_op: Op::GenerateContent,
+    };
+    assert!(transform_warnings(&ctx, &req).is_empty());
+    let out = match transform_request(&ctx, req.clone()).unwrap() {
+        Request::GenerateContent(GenerateContentRequest::Gemini(req)) => req,
+        _ => panic!("unexpected output"),
+    };
+    let config = out.body.generation_config.unwrap();
+    assert_eq!(config.response_logprobs, Some(true));
+    assert_eq!(config.logprobs, Some(2));
+
+    ctx.dst = Proto::Claude;
+    assert_eq!(transform_warnings(&ctx, &req), ["logprobs_unsupported"]);
+
+    let resp: GeminiGenerateContentResponse = serde_json::from_value(serde_json::json!({
+        "candidates": [{
+            "index": 0,
+            "content": { "role": "model", "parts": [{ "text": "Hi" }] },
+            "finishReason": "STOP",
+            "logprobsResult": {
+                "topCandidates": [{ "candidates": [
+                    { "token": "Hi", "tokenId": 1, "logProbability": -0.1 },
+                    { "token": "Hey", "tokenId": 2, "logProbability": -2.5 },
+                ] }],
+                "chosenCandidates": [{ "token": "Hi", "tokenId": 1, "logProbability": -0.1 }],
+                "logProbabilitySum": -0.1,
+            },
+        }],
+    }))
+    .unwrap();
+    let chat =
+        crate::generate_content::openai_chat_completions2gemini::response::transform_response(resp);
+    let logprobs = serde_json::to_value(&chat.choices[0].logprobs).unwrap();
+    assert_eq!(logprobs["content"][0]["token"], "Hi");
+    assert_eq!(
+        logprobs["content"][0]["bytes"],
+        serde_json::json!([72, 105])
+    );
+    assert_eq!(logprobs["content"][0]["top_logprobs"][1]["token"], "Hey");
+}
+
+#[test]
+fn candidates_map_between_chat_and_gemini() {
+    let mut req = make_openai_chat_request(None);
+    req.body.n = Some(2);
+    let req = Request::GenerateContent(GenerateContentRequest::OpenAIChat(req));
+    let mut ctx = TransformContext {
+        src: Proto::OpenAIChat,
+        dst: Proto::Gemini,
+        src_op: Op::GenerateContent,
+        dst_op: Op::GenerateContent,
+    };
+    assert!(transform_warnings(&ctx, &req).is_empty());
+    let gemini = match transform_request(&ctx, req.clone()).unwrap() {
+        Request::GenerateContent(GenerateContentRequest::Gemini(req)) => req,
+        _ => panic!("unexpected output"),
+    };
+    assert_eq!(
+        gemini
+            .body
+            .generation_config
+            .as_ref()
+            .and_then(|config| config.candidate_count),
+        Some(2)
+    );
+    ctx.dst = Proto::Claude;
+    assert_eq!(transform_warnings(&ctx, &req), ["n_unsupported"]);
+
+    let back = TransformContext {
+        src: Proto::Gemini,
+        dst: Proto::OpenAIChat,
+        src_op: Op::GenerateContent,
+        dst_op: Op::GenerateContent,
+    };
+    let chat = match transform_request(
+        &back,
+        Request::GenerateContent(GenerateContentRequest::Gemini(gemini)),
+    )
+    .unwrap()
+    {
+        Request::GenerateContent(GenerateContentRequest::OpenAIChat(req)) => req,
+        _ => panic!("unexpected output"),
+    };
+    assert_eq!(chat.body.n, Some(2));
+
+    // Interleaved candidates of a Gemini stream come out as one choice each.
+    let chunk: GeminiGenerateContentResponse = serde_json::from_value(serde_json::json!({
+        "candidates": [
+            { "index": 0, "content": { "role": "model", "parts": [{ "text": "a" }] } },
+            { "index": 1, "content": { "role": "model", "parts": [{ "text": "b" }] } },
+        ],
+    }))
+    .unwrap();
+    let last: GeminiGenerateContentResponse = serde_json::from_value(serde_json::json!({
+        "candidates": [
+            { "index": 1, "content": { "parts": [{ "text": "2" }] }, "finishReason": "STOP" },
+            { "index": 0, "content": { "parts": [{ "text": "1" }] }, "finishReason": "STOP" },
+        ],
+    }))
+    .unwrap();
+    let mut to_chat =
+        crate::generate_content::gemini2openai_chat_completions::stream::GeminiToOpenAIChatCompletionStreamState::new();
+    let mut assembled =
+        crate::stream2nostream::openai_chat_completions::OpenAIChatCompletionStreamToResponseState::new();
+    let mut done = None;
+    for chunk in [chunk, last] {
+        for event in to_chat.transform_response(chunk) {
+            done = assembled.push_chunk(event).or(done);
+        }
+    }
+    let done = done.unwrap();
+    assert_eq!(done.choices.len(), 2);
+    assert_eq!(done.choices[0].message.content.as_deref(), Some("a1"));
+    assert_eq!(done.choices[1].message.content.as_deref(), Some("b2"));
+}
@@ -9,10 +9,14 @@ use gproxy_protocol::claude::get_model::request::GetModelRequest as ClaudeGetMod
 use gproxy_protocol::claude::get_model::response::GetModelResponse as ClaudeGetModelResponse;
 use gproxy_protocol::claude::list_models::request::ListModelsRequest as ClaudeListModelsRequest;
 use gproxy_protocol::claude::list_models::response::ListModelsResponse as ClaudeListModelsResponse;
+use gproxy_protocol::gemini::batch_generate_content::request::BatchGenerateContentRequest as GeminiBatchGenerateContentRequest;
+use gproxy_protocol::gemini::batch_generate_content::response::BatchGenerateContentResponse as GeminiBatchGenerateContentResponse;
 use gproxy_protocol::gemini::count_tokens::request::CountTokensRequest as GeminiCountTokensRequest;
 use gproxy_protocol::gemini::count_tokens::response::CountTokensResponse as GeminiCountTokensResponse;
 use gproxy_protocol::gemini::generate_content::request::GenerateContentRequest as GeminiGenerateContentRequest;
 use gproxy_protocol::gemini::generate_content::response::GenerateContentResponse as GeminiGenerateContentResponse;
+use gproxy_protocol::gemini::get_batch::request::GetBatchRequest as GeminiBatchGetRequest;
+use gproxy_protocol::gemini::get_batch::response::GetBatchResponse as GeminiBatchGetResponse;
 use gproxy_protocol::gemini::get_model::request::GetModelRequest as GeminiGetModelRequest;
 use gproxy_protocol::gemini::get_model::response::GetModelResponse as GeminiGetModelResponse;
 use gproxy_protocol::gemini::list_models::request::ListModelsRequest as GeminiListModelsRequest;
@@ -23,6 +27,22 @@ use gproxy_protocol::openai::cancel_response::request::CancelResponseRequest as
 use gproxy_protocol::openai::cancel_response::response::CancelResponseResponse as OpenAICancelResponseResponse;
 use gproxy_protocol::openai::compact_response::request::CompactResponseRequest as OpenAICompactResponseRequest;
 use gproxy_protocol::openai::compact_response::response::CompactResponseResponse as OpenAICompactResponseResponse;
+use gproxy_protocol::openai::conversations::request::CreateConversationItemsRequest as OpenAIConversationItemCreateRequest;
+use gproxy_protocol::openai::conversations::request::CreateConversationRequest as OpenAIConversationCreateRequest;
+use gproxy_protocol::openai::conversations::request::DeleteConversationItemRequest as OpenAIConversationItemDeleteRequest;
+use gproxy_protocol::openai::conversations::request::DeleteConversationRequest as OpenAIConversationDeleteRequest;
+use gproxy_protocol::openai::conversations::request::GetConversationItemRequest as OpenAIConversationItemGetRequest;
+use gproxy_protocol::openai::conversations::request::GetConversationRequest as OpenAIConversationGetRequest;
+use gproxy_protocol::openai::conversations::request::ListConversationItemsRequest as OpenAIConversationItemListRequest;
+use gproxy_protocol::openai::conversations::request::UpdateConversationRequest as OpenAIConversationUpdateRequest;
+use gproxy_protocol::openai::conversations::response::ConversationItemListResponse as OpenAIConversationItemListResponse;
+use gproxy_protocol::openai::conversations::response::ConversationItemListResponse as OpenAIConversationItemCreateResponse;
+use gproxy_protocol::openai::conversations::response::ConversationItemResponse as OpenAIConversationItemGetResponse;
+use gproxy_protocol::openai::conversations::response::ConversationResponse as OpenAIConversationCreateResponse;
+use gproxy_protocol::openai::conversations::response::ConversationResponse as OpenAIConversationGetResponse;
+use gproxy_protocol::openai::conversations::response::ConversationResponse as OpenAIConversationUpdateResponse;
+use gproxy_protocol::openai::conversations::response::ConversationResponse as OpenAIConversationItemDeleteResponse;
+use gproxy_protocol::openai::conversations::response::DeleteConversationResponse as OpenAIConversationDeleteResponse;
 use gproxy_protocol::openai::count_tokens::request::InputTokenCountRequest as OpenAICountTokensRequest;
 use gproxy_protocol::openai::count_tokens::response::InputTokenCountResponse as OpenAICountTokensResponse;
 use gproxy_protocol::openai::create_chat_completions::request::CreateChatCompletionRequest as OpenAIChatCompletionRequest;
@@ -33,6 +53,12 @@ use gproxy_protocol::openai::create_response::response::Response as OpenAIRespon
 use gproxy_protocol::openai::create_response::stream::ResponseStreamEvent;
 use gproxy_protocol::openai::delete_response::request::DeleteResponseRequest as OpenAIDeleteResponseRequest;
 use gproxy_protocol::openai::delete_response::response::DeleteResponseResponse as OpenAIDeleteResponseResponse;
+use gproxy_protocol::openai::files::request::DeleteFileRequest as OpenAIFileDeleteRequest;
+use gproxy_protocol::openai::files::request::GetFileRequest as OpenAIFileGetRequest;
+use gproxy_protocol::openai::files::request::ListFilesRequest as OpenAIFileListRequest;
+use gproxy_protocol::openai::files::response::DeleteFileResponse as OpenAIFileDeleteResponse;
+use gproxy_protocol::openai::files::response::GetFileResponse as OpenAIFileGetResponse;
+use gproxy_protocol::openai::files::response::ListFilesResponse as OpenAIFileListResponse;
 use gproxy_protocol::openai::get_model::request::GetModelRequest as OpenAIGetModelRequest;
 use gproxy_protocol::openai::get_model::response::GetModelResponse as OpenAIGetModelResponse;
 use gproxy_protocol::openai::get_response::request::GetResponseRequest as OpenAIGetResponseRequest;
@@ -41,6 +67,8 @@ use gproxy_protocol::openai::list_input_items::request::ListInputItemsRequest as
 use gproxy_protocol::openai::list_input_items::response::ListInputItemsResponse as OpenAIListInputItemsResponse;
 use gproxy_protocol::openai::list_models::request::ListModelsRequest as OpenAIListModelsRequest;
 use gproxy_protocol::openai::list_models::response::ListModelsResponse as OpenAIListModelsResponse;
+use gproxy_protocol::openai::list_responses::request::ListResponsesRequest as OpenAIResponseListRequest;
+use gproxy_protocol::openai::list_responses::response::ListResponsesResponse as OpenAIResponseListResponse;
 use gproxy_protocol::openai::trace_summarize::request::TraceSummarizeRequest as OpenAITraceSummarizeRequest;
 use gproxy_protocol::openai::trace_summarize::response::TraceSummarizeResponse as OpenAITraceSummarizeResponse;
 
@@ -58,6 +86,19 @@ pub enum Proto {
     Gemini,
 }
 
+impl Proto {
+    /// The protocol's serialized name.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Proto::Claude => "claude",
+            Proto::OpenAI => "openai",
+            Proto::OpenAIChat => "openai_chat",
+            Proto::OpenAIResponse => "openai_response",
+            Proto::Gemini => "gemini",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum Op {
@@ -72,6 +113,20 @@ pub enum Op {
     ResponseListInputItems,
     ResponseCompact,
     MemoryTraceSummarize,
+    ResponseList,
+    ConversationCreate,
+    ConversationGet,
+    ConversationUpdate,
+    ConversationDelete,
+    ConversationItemList,
+    ConversationItemCreate,
+    ConversationItemGet,
+    ConversationItemDelete,
+    FileList,
+    FileGet,
+    FileDelete,
+    BatchGenerateContent,
+    BatchGet,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -113,6 +168,20 @@ pub enum Request {
     ResponseListInputItems(ResponseListInputItemsRequest),
     ResponseCompact(ResponseCompactRequest),
     MemoryTraceSummarize(MemoryTraceSummarizeRequest),
+    ResponseList(ResponseListRequest),
+    ConversationCreate(ConversationCreateRequest),
+    ConversationGet(ConversationGetRequest),
+    ConversationUpdate(ConversationUpdateRequest),
+    ConversationDelete(ConversationDeleteRequest),
+    ConversationItemList(ConversationItemListRequest),
+    ConversationItemCreate(ConversationItemCreateRequest),
+    ConversationItemGet(ConversationItemGetRequest),
+    ConversationItemDelete(ConversationItemDeleteRequest),
+    FileList(FileListRequest),
+    FileGet(FileGetRequest),
+    FileDelete(FileDeleteRequest),
+    BatchGenerateContent(BatchGenerateContentRequest),
+    BatchGet(BatchGetRequest),
 }
 
 #[allow(clippy::large_enum_variant)]
@@ -128,6 +197,20 @@ pub enum Response {
     ResponseListInputItems(ResponseListInputItemsResponse),
     ResponseCompact(ResponseCompactResponse),
     MemoryTraceSummarize(MemoryTraceSummarizeResponse),
+    ResponseList(ResponseListResponse),
+    ConversationCreate(ConversationCreateResponse),
+    ConversationGet(ConversationGetResponse),
+    ConversationUpdate(ConversationUpdateResponse),
+    ConversationDelete(ConversationDeleteResponse),
+    ConversationItemList(ConversationItemListResponse),
+    ConversationItemCreate(ConversationItemCreateResponse),
+    ConversationItemGet(ConversationItemGetResponse),
+    ConversationItemDelete(ConversationItemDeleteResponse),
+    FileList(FileListResponse),
+    FileGet(FileGetResponse),
+    FileDelete(FileDeleteResponse),
+    BatchGenerateContent(BatchGenerateContentResponse),
+    BatchGet(BatchGetResponse),
 }
 
 #[derive(Debug, Clone)]
@@ -250,6 +333,146 @@ pub enum MemoryTraceSummarizeResponse {
     OpenAI(OpenAITraceSummarizeResponse),
 }
 
+#[derive(Debug, Clone)]
+pub enum ResponseListRequest {
+    OpenAI(OpenAIResponseListRequest),
+}
+
+#[derive(Debug, Clone)]
+pub enum ResponseListResponse {
+    OpenAI(OpenAIResponseListResponse),
+}
+
+#[derive(Debug, Clone)]
+pub enum ConversationCreateRequest {
+    OpenAI(OpenAIConversationCreateRequest),
+}
+
+#[derive(Debug, Clone)]
+pub enum ConversationCreateResponse {
+    OpenAI(OpenAIConversationCreateResponse),
+}
+
+#[derive(Debug, Clone)]
+pub enum ConversationGetRequest {
+    OpenAI(OpenAIConversationGetRequest),
+}
+
+#[derive(Debug, Clone)]
+pub enum ConversationGetResponse {
+    OpenAI(OpenAIConversationGetResponse),
+}
+
+#[derive(Debug, Clone)]
+pub enum ConversationUpdateRequest {
+    OpenAI(OpenAIConversationUpdateRequest),
+}
+
+#[derive(Debug, Clone)]
+pub enum ConversationUpdateResponse {
+    OpenAI(OpenAIConversationUpdateResponse),
+}
+
+#[derive(Debug, Clone)]
+pub enum ConversationDeleteRequest {
+    OpenAI(OpenAIConversationDeleteRequest),
+}
+
+#[derive(Debug, Clone)]
+pub enum ConversationDeleteResponse {
+    OpenAI(OpenAIConversationDeleteResponse),
+}
+
+#[derive(Debug, Clone)]
+pub enum ConversationItemListRequest {
+    OpenAI(OpenAIConversationItemListRequest),
+}
+
+#[derive(Debug, Clone)]
+pub enum ConversationItemListResponse {
+    OpenAI(OpenAIConversationItemListResponse),
+}
+
+#[derive(Debug, Clone)]
+pub enum ConversationItemCreateRequest {
+    OpenAI(OpenAIConversationItemCreateRequest),
+}
+
+#[derive(Debug, Clone)]
+pub enum ConversationItemCreateResponse {
+    OpenAI(OpenAIConversationItemCreateResponse),
+}
+
+#[derive(Debug, Clone)]
+pub enum ConversationItemGetRequest {
+    OpenAI(OpenAIConversationItemGetRequest),
+}
+
+#[derive(Debug, Clone)]
+pub enum ConversationItemGetResponse {
+    OpenAI(OpenAIConversationItemGetResponse),
+}
+
+#[derive(Debug, Clone)]
+pub enum ConversationItemDeleteRequest {
+    OpenAI(OpenAIConversationItemDeleteRequest),
+}
+
+#[derive(Debug, Clone)]
+pub enum ConversationItemDeleteResponse {
+    OpenAI(OpenAIConversationItemDeleteResponse),
+}
+
+#[derive(Debug, Clone)]
+pub enum FileListRequest {
+    OpenAI(OpenAIFileListRequest),
+}
+
+#[derive(Debug, Clone)]
+pub enum FileListResponse {
+    OpenAI(OpenAIFileListResponse),
+}
+
+#[derive(Debug, Clone)]
+pub enum FileGetRequest {
+    OpenAI(OpenAIFileGetRequest),
+}
+
+#[derive(Debug, Clone)]
+pub enum FileGetResponse {
+    OpenAI(OpenAIFileGetResponse),
+}
+
+#[derive(Debug, Clone)]
+pub enum FileDeleteRequest {
+    OpenAI(OpenAIFileDeleteRequest),
+}
+
+#[derive(Debug, Clone)]
+pub enum FileDeleteResponse {
+    OpenAI(OpenAIFileDeleteResponse),
+}
+
+#[derive(Debug, Clone)]
+pub enum BatchGenerateContentRequest {
+    Gemini(GeminiBatchGenerateContentRequest),
+}
+
+#[derive(Debug, Clone)]
+pub enum BatchGenerateContentResponse {
+    Gemini(GeminiBatchGenerateContentResponse),
+}
+
+#[derive(Debug, Clone)]
+pub enum BatchGetRequest {
+    Gemini(GeminiBatchGetRequest),
+}
+
+#[derive(Debug, Clone)]
+pub enum BatchGetResponse {
+    Gemini(GeminiBatchGetResponse),
+}
+
 #[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone)]
 pub enum StreamEvent {
@@ -200,6 +200,8 @@ impl OutputAccumulator {
     }
 }
 
+/// Counts tokens upstream. Async so that fallback counting runs on the caller's runtime
+/// instead of holding a blocking thread while the call is in flight.
 pub trait CountTokensFn {
     type Error;
 
@@ -207,7 +209,7 @@ pub trait CountTokensFn {
         &self,
         proto: Proto,
         req: CountTokensRequest,
-    ) -> Result<CountTokensResponse, Self::Error>;
+    ) -> impl Future<Output = Result<CountTokensResponse, Self::Error>> + Send;
 }
 
 #[derive(Debug, Clone)]
@@ -248,7 +250,7 @@ pub fn output_for_counting(proto: Proto, resp: &GenerateContentResponse) -> Stri
     }
 }
 
-pub fn fallback_usage_with_count_tokens<E>(
+pub async fn fallback_usage_with_count_tokens<E>(
     proto: Proto,
     input_req: &GenerateContentRequest,
     output_text: &str,
@@ -258,6 +260,7 @@ pub fn fallback_usage_with_count_tokens<E>(
     let input_model = input_req_model(proto, &input_req);
     let input_resp = count_fn
         .count_tokens(proto, input_req)
+        .await
         .map_err(UsageError::CountTokens)?;
     let input_tokens = count_tokens_value(&input_resp);
 
@@ -268,6 +271,7 @@ pub fn fallback_usage_with_count_tokens<E>(
             .ok_or(UsageError::BuildRequest)?;
         let output_resp = count_fn
             .count_tokens(proto, output_req)
+            .await
             .map_err(UsageError::CountTokens)?;
         count_tokens_value(&output_resp)
     };
@@ -77,6 +77,8 @@ impl GeminiStreamToResponseState {
         let entry = self.candidates.entry(index).or_insert_with(|| {
             let mut candidate = incoming.clone();
             candidate.index = Some(index);
+            // The parts are merged in below.
+            candidate.content.parts.clear();
             candidate
         });
 
@@ -102,8 +104,16 @@ impl GeminiStreamToResponseState {
         if incoming.avg_logprobs.is_some() {
             entry.avg_logprobs = incoming.avg_logprobs;
         }
-        if incoming.logprobs_result.is_some() {
-            entry.logprobs_result = incoming.logprobs_result;
+        if let Some(incoming) = incoming.logprobs_result {
+            // Each chunk carries the logprobs of its own tokens.
+            match entry.logprobs_result.as_mut() {
+                Some(result) => {
+                    result.top_candidates.extend(incoming.top_candidates);
+                    result.chosen_candidates.extend(incoming.chosen_candidates);
+                    result.log_probability_sum += incoming.log_probability_sum;
+                }
+                None => entry.logprobs_result = Some(incoming),
+            }
         }
         if incoming.url_context_metadata.is_some() {
             entry.url_context_metadata = incoming.url_context_metadata;
@@ -23,6 +23,7 @@ struct ToolCallState {
 struct ChoiceState {
     role: ChatCompletionResponseRole,
     content: String,
+    reasoning_content: String,
     refusal: String,
     tool_calls: BTreeMap<i64, ToolCallState>,
     function_call: Option<ChatCompletionFunctionCall>,
@@ -74,6 +75,10 @@ impl OpenAIChatCompletionStreamToResponseState {
                 state.content.push_str(&content);
             }
 
+            if let Some(reasoning) = delta.reasoning_content {
+                state.reasoning_content.push_str(&reasoning);
+            }
+
             if let Some(refusal) = delta.refusal {
                 state.refusal.push_str(&refusal);
             }
@@ -131,6 +136,7 @@ impl OpenAIChatCompletionStreamToResponseState {
         self.choices.entry(index).or_insert_with(|| ChoiceState {
             role: ChatCompletionResponseRole::Assistant,
             content: String::new(),
+            reasoning_content: String::new(),
             refusal: String::new(),
             tool_calls: BTreeMap::new(),
             function_call: None,
@@ -218,6 +224,11 @@ fn build_message(index: i64, state: &ChoiceState) -> ChatCompletionResponseMessa
     } else {
         Some(state.content.clone())
     };
+    let reasoning_content = if state.reasoning_content.is_empty() {
+        None
+    } else {
+        Some(state.reasoning_content.clone())
+    };
     let refusal = if state.refusal.is_empty() {
         None
     } else {
@@ -247,6 +258,7 @@ fn build_message(index: i64, state: &ChoiceState) -> ChatCompletionResponseMessa
     ChatCompletionResponseMessage {
         role: state.role,
         content,
+        reasoning_content,
         refusal,
         tool_calls,
         annotations: None,
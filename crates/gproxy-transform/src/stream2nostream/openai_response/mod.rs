@@ -18,10 +18,11 @@ use gproxy_protocol::openai::create_response::types::{
     Annotation, CodeInterpreterToolCall, CodeInterpreterToolCallStatus,
     CodeInterpreterToolCallType, CustomToolCall, CustomToolCallType, FileSearchToolCallStatus,
     FunctionCallItemStatus, FunctionToolCall, FunctionToolCallType, ImageGenToolCallStatus,
-    MCPToolCall, MCPToolCallStatus, MCPToolCallType, MessageStatus, OutputContent, OutputItem,
-    OutputMessage, OutputMessageContent, OutputMessageRole, OutputMessageType, OutputTextContent,
-    ReasoningContent, ReasoningItem, ReasoningItemStatus, ReasoningItemType, ReasoningTextContent,
-    RefusalContent, ResponseStatus, SummaryPart, SummaryTextContent, WebSearchToolCallStatus,
+    LogProb, MCPToolCall, MCPToolCallStatus, MCPToolCallType, MessageStatus, OutputContent,
+    OutputItem, OutputMessage, OutputMessageContent, OutputMessageRole, OutputMessageType,
+    OutputTextContent, ReasoningContent, ReasoningItem, ReasoningItemStatus, ReasoningItemType,
+    ReasoningTextContent, RefusalContent, ResponseLogProb, ResponseStatus, SummaryPart,
+    SummaryTextContent, TopLogProb, WebSearchToolCallStatus,
 };
 
 #[derive(Debug, Clone)]
@@ -341,7 +342,11 @@ impl OpenAIResponseStreamToResponseState {
 
         if let MessagePartState::Text(text) = entry {
             text.text.push_str(&event.delta);
-            // `ResponseLogProb` doesn't map cleanly to `LogProb` (bytes missing).
+            if !event.logprobs.is_empty() {
+                text.logprobs
+                    .get_or_insert_with(Vec::new)
+                    .extend(event.logprobs.iter().map(map_logprob));
+            }
         }
         self.sync_message_content(event.output_index);
     }
@@ -359,7 +364,9 @@ impl OpenAIResponseStreamToResponseState {
 
         if let MessagePartState::Text(text) = entry {
             text.text = event.text;
-            // `ResponseLogProb` doesn't map cleanly to `LogProb` (bytes missing).
+            if !event.logprobs.is_empty() {
+                text.logprobs = Some(event.logprobs.iter().map(map_logprob).collect());
+            }
         }
         self.sync_message_content(event.output_index);
     }
@@ -898,16 +905,12 @@ impl OpenAIResponseStreamToResponseState {
             let mut output = Vec::with_capacity(ordered.len());
             for (index, mut item) in ordered {
                 match &mut item {
-                    OutputItem::Message(message) => {
-                        if self.has_message_parts(index) {
-                            message.content = self.build_message_content(index);
-                        }
+                    OutputItem::Message(message) if self.has_message_parts(index) => {
+                        message.content = self.build_message_content(index);
                     }
-                    OutputItem::Reasoning(reasoning) => {
-                        if self.has_reasoning_parts(index) {
-                            reasoning.content = self.build_reasoning_content(index);
-                            reasoning.summary = self.build_reasoning_summary(index);
-                        }
+                    OutputItem::Reasoning(reasoning) if self.has_reasoning_parts(index) => {
+                        reasoning.content = self.build_reasoning_content(index);
+                        reasoning.summary = self.build_reasoning_summary(index);
                     }
                     _ => {}
                 }
@@ -1267,3 +1270,21 @@ fn mcp_status_rank(status: MCPToolCallStatus) -> i32 {
         MCPToolCallStatus::Failed => 4,
     }
 }
+
+/// Stream logprobs leave out `bytes`; they are the token's UTF-8 bytes.
+fn map_logprob(logprob: &ResponseLogProb) -> LogProb {
+    LogProb {
+        token: logprob.token.clone(),
+        logprob: logprob.logprob,
+        bytes: logprob.token.bytes().map(i64::from).collect(),
+        top_logprobs: logprob
+            .top_logprobs
+            .iter()
+            .map(|top| TopLogProb {
+                token: top.token.clone(),
+                logprob: top.logprob,
+                bytes: top.token.bytes().map(i64::from).collect(),
+            })
+            .collect(),
+    }
+}
@@ -0,0 +1,26 @@
+#![no_main]
+
+use bytes::Bytes;
+use gproxy_protocol::sse::{SseEvent, SseParser};
+use libfuzzer_sys::fuzz_target;
+
+fn parse<'a>(chunks: impl Iterator<Item = &'a [u8]>) -> Vec<(Option<String>, String)> {
+    let mut parser = SseParser::new();
+    let mut events: Vec<SseEvent> = Vec::new();
+    for chunk in chunks {
+        events.extend(parser.push_bytes(&Bytes::copy_from_slice(chunk)));
+    }
+    events.extend(parser.finish());
+    events.into_iter().map(|ev| (ev.event, ev.data)).collect()
+}
+
+// The first byte picks the chunk size, so splits land inside lines and characters;
+// however the stream is cut, it must parse to the same events.
+fuzz_target!(|data: &[u8]| {
+    let Some((&seed, body)) = data.split_first() else {
+        return;
+    };
+    let whole = parse(std::iter::once(body));
+    let split = parse(body.chunks(usize::from(seed % 64) + 1));
+    assert_eq!(whole, split);
+});
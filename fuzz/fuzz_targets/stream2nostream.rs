@@ -0,0 +1,45 @@
+#![no_main]
+
+use gproxy_transform::conformance::{FixtureKind, FixturePayload, GENERATE_PROTOS, parse_fixture};
+use gproxy_transform::middleware::{Op, StreamToNostream, StreamTransformer, TransformContext};
+use libfuzzer_sys::fuzz_target;
+
+// First byte: source and target proto. The rest is a stream transcript (SSE, NDJSON or
+// a JSON array); any event sequence that decodes, in any order, must transform and
+// collect without panicking.
+fuzz_target!(|data: &[u8]| {
+    let Some((&seed, body)) = data.split_first() else {
+        return;
+    };
+    let Ok(text) = std::str::from_utf8(body) else {
+        return;
+    };
+    let src = GENERATE_PROTOS[usize::from(seed) % GENERATE_PROTOS.len()];
+    let dst = GENERATE_PROTOS[usize::from(seed >> 2) % GENERATE_PROTOS.len()];
+    let Ok(FixturePayload::Stream(events)) = parse_fixture(src, FixtureKind::Stream, text) else {
+        return;
+    };
+
+    let ctx = TransformContext {
+        src,
+        dst,
+        src_op: Op::StreamGenerateContent,
+        dst_op: Op::StreamGenerateContent,
+    };
+    if let Ok(mut transformer) = StreamTransformer::new(&ctx) {
+        for event in &events {
+            let _ = transformer.push(event.clone());
+        }
+    }
+
+    let ctx = TransformContext {
+        dst_op: Op::GenerateContent,
+        ..ctx
+    };
+    if let Ok(mut collector) = StreamToNostream::new(&ctx) {
+        for event in events {
+            let _ = collector.push(event);
+        }
+        let _ = collector.finalize_on_eof();
+    }
+});
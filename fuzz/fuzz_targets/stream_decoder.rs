@@ -0,0 +1,40 @@
+#![no_main]
+
+use bytes::Bytes;
+use gproxy_core::proxy_engine::StreamDecoder;
+use gproxy_provider_core::{Proto, StreamFormat};
+use libfuzzer_sys::fuzz_target;
+
+const PROTOS: [Proto; 4] = [
+    Proto::Claude,
+    Proto::OpenAIChat,
+    Proto::OpenAIResponse,
+    Proto::Gemini,
+];
+const FORMATS: [StreamFormat; 3] = [
+    StreamFormat::SseNamedEvent,
+    StreamFormat::SseDataOnly,
+    StreamFormat::JsonStream,
+];
+
+fn decode<'a>(proto: Proto, format: StreamFormat, chunks: impl Iterator<Item = &'a [u8]>) -> usize {
+    let mut decoder = StreamDecoder::new(proto, format);
+    let mut count = 0;
+    for chunk in chunks {
+        count += decoder.push_bytes(&Bytes::copy_from_slice(chunk)).len();
+    }
+    count + decoder.finish().len()
+}
+
+// First byte: proto, format and chunk size. Malformed upstream bytes must never panic,
+// and chunk boundaries must not change how many events come out.
+fuzz_target!(|data: &[u8]| {
+    let Some((&seed, body)) = data.split_first() else {
+        return;
+    };
+    let proto = PROTOS[usize::from(seed) % PROTOS.len()];
+    let format = FORMATS[usize::from(seed >> 2) % FORMATS.len()];
+    let whole = decode(proto, format, std::iter::once(body));
+    let split = decode(proto, format, body.chunks(usize::from(seed >> 4) + 1));
+    assert_eq!(whole, split);
+});